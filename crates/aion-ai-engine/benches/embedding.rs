@@ -0,0 +1,63 @@
+use aion_ai_engine::inference::InferenceEngine;
+use aion_ai_engine::nlp::{embed_batch, EmbeddingOptions};
+use aion_ai_engine::AIEngineConfig;
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+use std::time::Instant;
+use tokio::runtime::Runtime;
+
+// Embedding throughput: one inference request per text vs. batched requests
+
+const CHUNKS: usize = 1024;
+const BATCH_SIZES: [usize; 3] = [8, 32, 128];
+const MODEL: &str = "sentence-encoder";
+
+/// Documentation-like chunks of varying length
+fn chunks() -> Vec<String> {
+    const WORDS: [&str; 12] = [
+        "plugin", "registry", "install", "version", "reload", "timeout", "memory", "search", "index",
+        "embedding", "query", "configuration",
+    ];
+    (0..CHUNKS)
+        .map(|i| {
+            let length = 16 + (i * 37) % 96;
+            (0..length).map(|j| WORDS[(i + j * 7) % WORDS.len()]).collect::<Vec<_>>().join(" ")
+        })
+        .collect()
+}
+
+fn embed_all(runtime: &Runtime, engine: &InferenceEngine, texts: &[String], options: &EmbeddingOptions) {
+    black_box(runtime.block_on(embed_batch(engine, MODEL, texts, options)).unwrap());
+}
+
+fn texts_per_second(f: impl FnOnce()) -> f64 {
+    let started = Instant::now();
+    f();
+    CHUNKS as f64 / started.elapsed().as_secs_f64()
+}
+
+fn benchmark_embedding(c: &mut Criterion) {
+    let runtime = Runtime::new().unwrap();
+    let engine = InferenceEngine::new(AIEngineConfig::default());
+    let texts = chunks();
+    let options = |max_batch_size| EmbeddingOptions { max_batch_size, ..EmbeddingOptions::default() };
+
+    let baseline = texts_per_second(|| embed_all(&runtime, &engine, &texts, &options(1)));
+    println!("texts/s embedding {} chunks one at a time: {:.0}", CHUNKS, baseline);
+    for batch_size in BATCH_SIZES {
+        let batched = texts_per_second(|| embed_all(&runtime, &engine, &texts, &options(batch_size)));
+        println!("texts/s with batches of {}: {:.0} ({:.1}x)", batch_size, batched, batched / baseline);
+    }
+
+    let mut group = c.benchmark_group("embedding");
+    group.throughput(Throughput::Elements(CHUNKS as u64));
+    group.bench_function("one_at_a_time", |b| b.iter(|| embed_all(&runtime, &engine, &texts, &options(1))));
+    for batch_size in BATCH_SIZES {
+        group.bench_function(format!("batch_{}", batch_size), |b| {
+            b.iter(|| embed_all(&runtime, &engine, &texts, &options(batch_size)))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, benchmark_embedding);
+criterion_main!(benches);
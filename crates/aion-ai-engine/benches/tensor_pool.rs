@@ -0,0 +1,92 @@
+use aion_ai_engine::tensor_pool::TensorPool;
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+// Tensor pool benchmarks: pooled vs. fresh activation buffers under concurrent requests
+
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicU64 = AtomicU64::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+const SHAPES: [[usize; 2]; 3] = [[128, 768], [512, 768], [512, 3072]];
+const THREADS: usize = 8;
+const REQUESTS_PER_THREAD: usize = 32;
+
+fn simulate_request(index: usize, pool: Option<&Arc<TensorPool>>) -> f32 {
+    let shape = SHAPES[index % SHAPES.len()];
+    match pool {
+        Some(pool) => {
+            let mut activations = pool.acquire_for_overwrite(&shape);
+            for (i, value) in activations.iter_mut().enumerate() {
+                *value = (i % 97) as f32;
+            }
+            activations[activations.len() / 2]
+        }
+        None => {
+            let mut activations = vec![0.0f32; shape[0] * shape[1]];
+            for (i, value) in activations.iter_mut().enumerate() {
+                *value = (i % 97) as f32;
+            }
+            activations[activations.len() / 2]
+        }
+    }
+}
+
+fn run_concurrent(pool: Option<Arc<TensorPool>>) {
+    std::thread::scope(|scope| {
+        for thread in 0..THREADS {
+            let pool = pool.clone();
+            scope.spawn(move || {
+                for request in 0..REQUESTS_PER_THREAD {
+                    black_box(simulate_request(thread + request, pool.as_ref()));
+                }
+            });
+        }
+    });
+}
+
+fn allocations_during(f: impl FnOnce()) -> u64 {
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    f();
+    ALLOCATIONS.load(Ordering::Relaxed) - before
+}
+
+fn benchmark_tensor_allocation(c: &mut Criterion) {
+    let pool = TensorPool::new(512 * 1024 * 1024);
+    // Warm the pool so steady-state requests are measured
+    run_concurrent(Some(pool.clone()));
+
+    let fresh = allocations_during(|| run_concurrent(None));
+    let pooled = allocations_during(|| run_concurrent(Some(pool.clone())));
+    println!(
+        "allocations per {} requests: fresh = {}, pooled = {} (pool hit rate {:.2})",
+        THREADS * REQUESTS_PER_THREAD,
+        fresh,
+        pooled,
+        pool.stats().hit_rate
+    );
+
+    let mut group = c.benchmark_group("tensor_allocation");
+    group.bench_function("fresh", |b| b.iter(|| run_concurrent(None)));
+    group.bench_function("pooled", |b| b.iter(|| run_concurrent(Some(pool.clone()))));
+    group.finish();
+}
+
+criterion_group!(benches, benchmark_tensor_allocation);
+criterion_main!(benches);
@@ -6,11 +6,20 @@
 use std::collections::HashMap;
 use std::sync::Arc;
 use anyhow::{Result, anyhow};
+use futures::stream::{self, BoxStream, StreamExt};
 use serde::{Deserialize, Serialize};
 use tokio::sync::RwLock;
 use reqwest::Client;
 use uuid::Uuid;
 
+pub use ectus_plugin_sdk::AIOptions;
+
+/// Default `max_tokens` for `AIProvider::complete` when the options leave it unset
+pub const DEFAULT_COMPLETION_MAX_TOKENS: usize = 1024;
+
+/// Incremental text of a streamed completion
+pub type TextStream = BoxStream<'static, Result<String>>;
+
 /// AI Provider types supported by the system
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum AIProviderType {
@@ -23,6 +32,45 @@ pub enum AIProviderType {
     Azure,
 }
 
+impl AIProviderType {
+    /// List price of the provider's default model, for estimates made
+    /// before a provider instance exists
+    pub fn list_pricing(&self) -> PricingInfo {
+        let (input_cost_per_token, output_cost_per_token) = match self {
+            AIProviderType::OpenAI | AIProviderType::Azure => (0.00003, 0.00006),
+            AIProviderType::Anthropic => (0.000015, 0.000075),
+            AIProviderType::GoogleGemini => (0.0000005, 0.0000015),
+            AIProviderType::Cohere => (0.0000015, 0.000002),
+            AIProviderType::LocalOllama | AIProviderType::HuggingFace => (0.0, 0.0),
+        };
+        PricingInfo {
+            input_cost_per_token,
+            output_cost_per_token,
+            currency: "USD".to_string(),
+        }
+    }
+}
+
+impl std::str::FromStr for AIProviderType {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "openai" => Ok(AIProviderType::OpenAI),
+            "anthropic" | "claude" => Ok(AIProviderType::Anthropic),
+            "gemini" | "google" => Ok(AIProviderType::GoogleGemini),
+            "ollama" | "local" => Ok(AIProviderType::LocalOllama),
+            "huggingface" => Ok(AIProviderType::HuggingFace),
+            "cohere" => Ok(AIProviderType::Cohere),
+            "azure" => Ok(AIProviderType::Azure),
+            _ => Err(anyhow!(
+                "Unknown AI provider {}; expected one of openai, anthropic, gemini, ollama, huggingface, cohere, azure",
+                s
+            )),
+        }
+    }
+}
+
 /// AI Provider manager for handling multiple AI services
 pub struct AIProviderManager {
     providers: Arc<RwLock<HashMap<String, Box<dyn AIProvider + Send + Sync>>>>,
@@ -30,11 +78,13 @@ pub struct AIProviderManager {
     load_balancer: Arc<LoadBalancer>,
     rate_limiter: Arc<RateLimiter>,
     cost_tracker: Arc<CostTracker>,
+    /// Provider ids `complete` and `stream` try in order; registration order by default
+    priority: Arc<RwLock<Vec<String>>>,
 }
 
 /// Trait for AI providers
 #[async_trait::async_trait]
-pub trait AIProvider {
+pub trait AIProvider: Send + Sync {
     /// Get provider type
     fn provider_type(&self) -> AIProviderType;
 
@@ -53,6 +103,29 @@ pub trait AIProvider {
     /// Chat completion with conversation history
     async fn chat_completion(&self, request: &ChatCompletionRequest) -> Result<ChatCompletionResponse>;
 
+    /// Complete a single prompt as a plugin would through `PluginContext::invoke_ai`.
+    /// The default goes through `chat_completion`, which always uses the provider's own model.
+    async fn complete(&self, prompt: &str, options: &AIOptions) -> Result<String> {
+        let request = ChatCompletionRequest {
+            messages: vec![ChatMessage {
+                role: ChatRole::User,
+                content: prompt.to_string(),
+                timestamp: chrono::Utc::now(),
+            }],
+            max_tokens: options.max_tokens,
+            temperature: options.temperature,
+            system_prompt: options.system_prompt.clone(),
+        };
+        Ok(self.chat_completion(&request).await?.message.content)
+    }
+
+    /// Like `complete`, yielding the text as it is generated. The default
+    /// yields the whole completion at once.
+    async fn stream(&self, prompt: &str, options: &AIOptions) -> Result<TextStream> {
+        let text = self.complete(prompt, options).await?;
+        Ok(stream::once(async move { Ok(text) }).boxed())
+    }
+
     /// Get model information
     fn get_model_info(&self) -> ModelInfo;
 
@@ -73,6 +146,7 @@ pub struct AnthropicProvider {
     client: Client,
     api_key: String,
     model: String,
+    base_url: String,
 }
 
 /// Google Gemini Provider implementation
@@ -300,6 +374,7 @@ impl AIProviderManager {
             load_balancer: Arc::new(LoadBalancer::new()),
             rate_limiter: Arc::new(RateLimiter::new()),
             cost_tracker: Arc::new(CostTracker::new()),
+            priority: Arc::new(RwLock::new(Vec::new())),
         }
     }
 
@@ -309,6 +384,11 @@ impl AIProviderManager {
         let mut providers = self.providers.write().await;
         providers.insert(provider_id.clone(), provider);
 
+        let mut priority = self.priority.write().await;
+        if !priority.contains(&provider_id) {
+            priority.push(provider_id.clone());
+        }
+
         // Set as default if it's the first provider
         let mut default = self.default_provider.write().await;
         if default.is_none() {
@@ -370,6 +450,61 @@ impl AIProviderManager {
         }
     }
 
+    /// Complete `prompt` with the first provider, in priority order, that succeeds
+    pub async fn complete(&self, prompt: &str, options: &AIOptions) -> Result<String> {
+        let providers = self.providers.read().await;
+        let mut failures = Vec::new();
+
+        for provider_id in self.priority.read().await.iter() {
+            let Some(provider) = providers.get(provider_id) else {
+                continue;
+            };
+            match provider.complete(prompt, options).await {
+                Ok(text) => return Ok(text),
+                Err(error) => {
+                    tracing::warn!("AI provider {} failed, trying the next one: {}", provider_id, error);
+                    failures.push(format!("{}: {}", provider_id, error));
+                }
+            }
+        }
+
+        Err(all_providers_failed(failures))
+    }
+
+    /// Stream a completion of `prompt` from the first provider, in priority
+    /// order, that accepts the request. Errors after the stream has started
+    /// are passed through rather than retried elsewhere.
+    pub async fn stream(&self, prompt: &str, options: &AIOptions) -> Result<TextStream> {
+        let providers = self.providers.read().await;
+        let mut failures = Vec::new();
+
+        for provider_id in self.priority.read().await.iter() {
+            let Some(provider) = providers.get(provider_id) else {
+                continue;
+            };
+            match provider.stream(prompt, options).await {
+                Ok(stream) => return Ok(stream),
+                Err(error) => {
+                    tracing::warn!("AI provider {} failed, trying the next one: {}", provider_id, error);
+                    failures.push(format!("{}: {}", provider_id, error));
+                }
+            }
+        }
+
+        Err(all_providers_failed(failures))
+    }
+
+    /// Set the order `complete` and `stream` try providers in. Registered
+    /// providers left out of `provider_ids` are not used by them.
+    pub async fn set_priority(&self, provider_ids: Vec<String>) -> Result<()> {
+        let providers = self.providers.read().await;
+        if let Some(unknown) = provider_ids.iter().find(|id| !providers.contains_key(*id)) {
+            return Err(anyhow!("Provider {} not found", unknown));
+        }
+        *self.priority.write().await = provider_ids;
+        Ok(())
+    }
+
     /// Get all available providers
     pub async fn get_available_providers(&self) -> Vec<String> {
         let providers = self.providers.read().await;
@@ -414,6 +549,52 @@ impl AIProviderManager {
     }
 }
 
+fn all_providers_failed(failures: Vec<String>) -> anyhow::Error {
+    if failures.is_empty() {
+        anyhow!("No providers available")
+    } else {
+        anyhow!("All AI providers failed: {}", failures.join("; "))
+    }
+}
+
+/// Text from a server-sent events response, one item per event `extract`
+/// finds text in. The stream ends with the response or at a `[DONE]` event.
+fn sse_text_stream(response: reqwest::Response, extract: fn(&serde_json::Value) -> Option<String>) -> TextStream {
+    let body = response.bytes_stream().boxed();
+
+    stream::unfold((body, Vec::new()), move |(mut body, mut buffer)| async move {
+        loop {
+            if let Some(newline) = buffer.iter().position(|&b| b == b'\n') {
+                let line: Vec<u8> = buffer.drain(..=newline).collect();
+                let line = String::from_utf8_lossy(&line);
+                let Some(data) = line.trim_end().strip_prefix("data:") else {
+                    continue;
+                };
+                let data = data.trim_start();
+                if data == "[DONE]" {
+                    return None;
+                }
+                match serde_json::from_str::<serde_json::Value>(data) {
+                    Ok(event) => {
+                        if let Some(text) = extract(&event) {
+                            return Some((Ok(text), (body, buffer)));
+                        }
+                    }
+                    Err(error) => return Some((Err(error.into()), (body, buffer))),
+                }
+                continue;
+            }
+
+            match body.next().await {
+                Some(Ok(chunk)) => buffer.extend_from_slice(&chunk),
+                Some(Err(error)) => return Some((Err(error.into()), (body, buffer))),
+                None => return None,
+            }
+        }
+    })
+    .boxed()
+}
+
 impl OpenAIProvider {
     /// Create a new OpenAI provider
     pub fn new(api_key: String, model: Option<String>) -> Self {
@@ -434,6 +615,44 @@ impl OpenAIProvider {
             base_url,
         }
     }
+
+    /// Chat completions body for a single prompt
+    fn prompt_body(&self, prompt: &str, options: &AIOptions, stream: bool) -> serde_json::Value {
+        let mut messages = Vec::new();
+        if let Some(system_prompt) = &options.system_prompt {
+            messages.push(serde_json::json!({ "role": "system", "content": system_prompt }));
+        }
+        messages.push(serde_json::json!({ "role": "user", "content": prompt }));
+
+        let mut body = serde_json::json!({
+            "model": options.model.as_deref().unwrap_or(&self.model),
+            "messages": messages,
+            "stream": stream
+        });
+        if let Some(temperature) = options.temperature {
+            body["temperature"] = serde_json::json!(temperature);
+        }
+        if let Some(max_tokens) = options.max_tokens {
+            body["max_tokens"] = serde_json::json!(max_tokens);
+        }
+        body
+    }
+
+    async fn send_prompt(&self, prompt: &str, options: &AIOptions, stream: bool) -> Result<reqwest::Response> {
+        let response = self.client
+            .post(&format!("{}/chat/completions", self.base_url))
+            .header("Authorization", format!("Bearer {}", self.api_key))
+            .header("Content-Type", "application/json")
+            .json(&self.prompt_body(prompt, options, stream))
+            .send()
+            .await?;
+
+        if response.status().is_success() {
+            Ok(response)
+        } else {
+            Err(anyhow!("OpenAI API error: {}", response.status()))
+        }
+    }
 }
 
 #[async_trait::async_trait]
@@ -609,6 +828,26 @@ impl AIProvider for OpenAIProvider {
         }
     }
 
+    async fn complete(&self, prompt: &str, options: &AIOptions) -> Result<String> {
+        let result: serde_json::Value = self.send_prompt(prompt, options, false).await?.json().await?;
+
+        result["choices"][0]["message"]["content"]
+            .as_str()
+            .map(str::to_string)
+            .ok_or_else(|| anyhow!("OpenAI API returned no completion"))
+    }
+
+    async fn stream(&self, prompt: &str, options: &AIOptions) -> Result<TextStream> {
+        let response = self.send_prompt(prompt, options, true).await?;
+
+        Ok(sse_text_stream(response, |event| {
+            event["choices"][0]["delta"]["content"]
+                .as_str()
+                .filter(|text| !text.is_empty())
+                .map(str::to_string)
+        }))
+    }
+
     fn get_model_info(&self) -> ModelInfo {
         ModelInfo {
             name: self.model.clone(),
@@ -646,6 +885,54 @@ impl AnthropicProvider {
             client: Client::new(),
             api_key,
             model: model.unwrap_or_else(|| "claude-3-sonnet-20240229".to_string()),
+            base_url: "https://api.anthropic.com/v1".to_string(),
+        }
+    }
+
+    /// Create provider with custom base URL
+    pub fn new_with_base_url(api_key: String, base_url: String, model: String) -> Self {
+        Self {
+            client: Client::new(),
+            api_key,
+            model,
+            base_url,
+        }
+    }
+
+    /// Messages API body for a single prompt
+    fn prompt_body(&self, prompt: &str, options: &AIOptions, stream: bool) -> serde_json::Value {
+        let mut body = serde_json::json!({
+            "model": options.model.as_deref().unwrap_or(&self.model),
+            "max_tokens": options.max_tokens.unwrap_or(DEFAULT_COMPLETION_MAX_TOKENS),
+            "messages": [{
+                "role": "user",
+                "content": prompt
+            }],
+            "stream": stream
+        });
+        if let Some(system_prompt) = &options.system_prompt {
+            body["system"] = serde_json::json!(system_prompt);
+        }
+        if let Some(temperature) = options.temperature {
+            body["temperature"] = serde_json::json!(temperature);
+        }
+        body
+    }
+
+    async fn send_prompt(&self, prompt: &str, options: &AIOptions, stream: bool) -> Result<reqwest::Response> {
+        let response = self.client
+            .post(&format!("{}/messages", self.base_url))
+            .header("x-api-key", &self.api_key)
+            .header("Content-Type", "application/json")
+            .header("anthropic-version", "2023-06-01")
+            .json(&self.prompt_body(prompt, options, stream))
+            .send()
+            .await?;
+
+        if response.status().is_success() {
+            Ok(response)
+        } else {
+            Err(anyhow!("Anthropic API error: {}", response.status()))
         }
     }
 }
@@ -677,7 +964,7 @@ impl AIProvider for AnthropicProvider {
         });
 
         let response = self.client
-            .post("https://api.anthropic.com/v1/messages")
+            .post(&format!("{}/messages", self.base_url))
             .header("x-api-key", &self.api_key)
             .header("Content-Type", "application/json")
             .header("anthropic-version", "2023-06-01")
@@ -761,6 +1048,29 @@ impl AIProvider for AnthropicProvider {
         })
     }
 
+    async fn complete(&self, prompt: &str, options: &AIOptions) -> Result<String> {
+        let result: serde_json::Value = self.send_prompt(prompt, options, false).await?.json().await?;
+
+        let text: String = result["content"]
+            .as_array()
+            .ok_or_else(|| anyhow!("Anthropic API returned no content"))?
+            .iter()
+            .filter_map(|block| block["text"].as_str())
+            .collect();
+        Ok(text)
+    }
+
+    async fn stream(&self, prompt: &str, options: &AIOptions) -> Result<TextStream> {
+        let response = self.send_prompt(prompt, options, true).await?;
+
+        Ok(sse_text_stream(response, |event| {
+            if event["type"] != "content_block_delta" {
+                return None;
+            }
+            event["delta"]["text"].as_str().map(str::to_string)
+        }))
+    }
+
     fn get_model_info(&self) -> ModelInfo {
         ModelInfo {
             name: self.model.clone(),
@@ -1075,4 +1385,145 @@ mod tests {
         assert_eq!(provider.provider_id(), "openai");
         assert!(matches!(provider.provider_type(), AIProviderType::OpenAI));
     }
+
+    use wiremock::matchers::{body_partial_json, header, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn options(model: Option<&str>) -> AIOptions {
+        AIOptions {
+            model: model.map(str::to_string),
+            temperature: Some(0.25),
+            max_tokens: Some(64),
+            system_prompt: Some("You write Rust".to_string()),
+        }
+    }
+
+    #[tokio::test]
+    async fn openai_complete_maps_options() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/chat/completions"))
+            .and(header("Authorization", "Bearer test_key"))
+            .and(body_partial_json(serde_json::json!({
+                "model": "gpt-4o",
+                "temperature": 0.25,
+                "max_tokens": 64,
+                "stream": false,
+                "messages": [
+                    { "role": "system", "content": "You write Rust" },
+                    { "role": "user", "content": "Write a hello world" }
+                ]
+            })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "choices": [{ "message": { "role": "assistant", "content": "fn main() {}" } }]
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let provider = OpenAIProvider::new_with_base_url("test_key".to_string(), server.uri(), "gpt-4".to_string());
+        let text = provider.complete("Write a hello world", &options(Some("gpt-4o"))).await.unwrap();
+        assert_eq!(text, "fn main() {}");
+    }
+
+    #[tokio::test]
+    async fn anthropic_complete_maps_options() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/messages"))
+            .and(header("x-api-key", "test_key"))
+            .and(header("anthropic-version", "2023-06-01"))
+            .and(body_partial_json(serde_json::json!({
+                "model": "claude-3-haiku-20240307",
+                "temperature": 0.25,
+                "max_tokens": 64,
+                "system": "You write Rust",
+                "messages": [{ "role": "user", "content": "Write a hello world" }]
+            })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "content": [{ "type": "text", "text": "fn main() {}" }]
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let provider = AnthropicProvider::new_with_base_url(
+            "test_key".to_string(),
+            server.uri(),
+            "claude-3-haiku-20240307".to_string(),
+        );
+        // The provider's own model is used when the options name none
+        let text = provider.complete("Write a hello world", &options(None)).await.unwrap();
+        assert_eq!(text, "fn main() {}");
+    }
+
+    #[tokio::test]
+    async fn openai_stream_yields_deltas() {
+        let server = MockServer::start().await;
+        let events = concat!(
+            "data: {\"choices\":[{\"delta\":{\"role\":\"assistant\"}}]}\n\n",
+            "data: {\"choices\":[{\"delta\":{\"content\":\"fn main\"}}]}\n\n",
+            "data: {\"choices\":[{\"delta\":{\"content\":\"() {}\"}}]}\n\n",
+            "data: [DONE]\n\n",
+        );
+        Mock::given(method("POST"))
+            .and(path("/chat/completions"))
+            .and(body_partial_json(serde_json::json!({ "stream": true })))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(events, "text/event-stream"))
+            .mount(&server)
+            .await;
+
+        let provider = OpenAIProvider::new_with_base_url("test_key".to_string(), server.uri(), "gpt-4".to_string());
+        let chunks: Vec<String> = provider
+            .stream("Write a hello world", &options(None))
+            .await
+            .unwrap()
+            .map(|chunk| chunk.unwrap())
+            .collect()
+            .await;
+        assert_eq!(chunks, vec!["fn main", "() {}"]);
+    }
+
+    #[tokio::test]
+    async fn manager_falls_back_through_priority() {
+        let openai = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(503))
+            .mount(&openai)
+            .await;
+        let anthropic = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/messages"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "content": [{ "type": "text", "text": "from claude" }]
+            })))
+            .mount(&anthropic)
+            .await;
+
+        let manager = AIProviderManager::new();
+        manager
+            .register_provider(Box::new(AnthropicProvider::new_with_base_url(
+                "test_key".to_string(),
+                anthropic.uri(),
+                "claude-3-haiku-20240307".to_string(),
+            )))
+            .await
+            .unwrap();
+        manager
+            .register_provider(Box::new(OpenAIProvider::new_with_base_url(
+                "test_key".to_string(),
+                openai.uri(),
+                "gpt-4".to_string(),
+            )))
+            .await
+            .unwrap();
+        manager.set_priority(vec!["openai".to_string(), "anthropic".to_string()]).await.unwrap();
+
+        let text = manager.complete("Say hi", &options(None)).await.unwrap();
+        assert_eq!(text, "from claude");
+
+        assert!(manager.set_priority(vec!["gemini".to_string()]).await.is_err());
+        manager.set_priority(vec!["openai".to_string()]).await.unwrap();
+        assert!(manager.complete("Say hi", &options(None)).await.is_err());
+    }
 }
\ No newline at end of file
@@ -11,6 +11,7 @@ use crate::test_integration::{TestIntegrationEngine, DetailedTestResults, TestFa
 use crate::code_generation::GeneratedCode;
 use crate::llm_providers::{MultiProviderLLM, LLMRequest, LLMClient, GroqClient, OpenAIClient, HuggingFaceClient, GitHubModelsClient, CloudflareAIClient};
 use crate::locked_files::LockedFilesManager;
+use crate::formatting::FormattingService;
 
 /// Maximum iterations before giving up
 const MAX_AUTOCORRECTION_ITERATIONS: u32 = 5;
@@ -24,6 +25,7 @@ pub struct AutocorrectionCycle {
     max_iterations: u32,
     llm: MultiProviderLLM,
     locked_files: LockedFilesManager,
+    formatting: FormattingService,
 }
 
 /// Result of autocorrection attempt
@@ -114,6 +116,7 @@ impl AutocorrectionCycle {
             max_iterations: MAX_AUTOCORRECTION_ITERATIONS,
             llm,
             locked_files,
+            formatting: FormattingService::new(),
         })
     }
 
@@ -202,13 +205,20 @@ impl AutocorrectionCycle {
             ).await?;
 
             // Step 5: Apply fixes to code
-            let (updated_code, applied_fixes) = self.apply_fixes_to_code(
+            let (mut updated_code, applied_fixes) = self.apply_fixes_to_code(
                 code.clone(),
                 fixes,
             ).await?;
 
             info!("   Applied {} fixes", applied_fixes.len());
 
+            // Format before the change is written or shown, so diffs only
+            // contain the fix itself
+            let main_file = format!("src/main.{}", source_extension(language));
+            let (formatted, status) = self.formatting.format_source(&main_file, &updated_code.code).await;
+            debug!("   Formatting {}: {:?}", main_file, status);
+            updated_code.code = formatted;
+
             // Step 6: Write updated code to project
             self.write_code_to_project(project_path, &updated_code, language).await?;
 
@@ -375,15 +385,7 @@ impl AutocorrectionCycle {
         code: &GeneratedCode,
         language: &str,
     ) -> Result<()> {
-        let file_extension = match language.to_lowercase().as_str() {
-            "rust" => "rs",
-            "typescript" | "javascript" => "ts",
-            "python" => "py",
-            "go" => "go",
-            _ => "txt",
-        };
-
-        let main_file = project_path.join(format!("src/main.{}", file_extension));
+        let main_file = project_path.join(format!("src/main.{}", source_extension(language)));
 
         // Check if file is locked
         if self.locked_files.is_locked(&main_file) {
@@ -408,6 +410,17 @@ impl AutocorrectionCycle {
     }
 }
 
+/// Extension of the main source file for a language
+fn source_extension(language: &str) -> &'static str {
+    match language.to_lowercase().as_str() {
+        "rust" => "rs",
+        "typescript" | "javascript" => "ts",
+        "python" => "py",
+        "go" => "go",
+        _ => "txt",
+    }
+}
+
 impl Default for AutocorrectionCycle {
     fn default() -> Self {
         Self::new().unwrap_or_else(|e| {
@@ -422,6 +435,7 @@ impl Default for AutocorrectionCycle {
                 max_iterations: MAX_AUTOCORRECTION_ITERATIONS,
                 llm: MultiProviderLLM::new(),
                 locked_files,
+                formatting: FormattingService::new(),
             }
         })
     }
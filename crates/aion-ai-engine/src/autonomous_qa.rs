@@ -2036,4 +2036,4 @@ impl Default for PerformanceBenchmarks {
             benchmarks: Vec::new(),
         }
     }
-}
\ No newline at end of file
+}
@@ -9,9 +9,12 @@ use std::sync::Arc;
 use uuid::Uuid;
 use chrono::{DateTime, Utc};
 
-use crate::errors::{AIEngineError, Result};
-use crate::inference::{InferenceEngine, InferenceRequest, InferenceResult};
+use crate::ai_providers::AIProviderType;
+use crate::context_packer::{ContextPacker, PackedContext, PackingReport, ProjectFile};
+use crate::errors::{AIEngineError, AIResult, Result};
+use crate::inference::{InferenceEngine, InferenceParameters, InferenceRequest, InferenceResult};
 use crate::nlp::NLPProcessor;
+use crate::rag::estimate_tokens;
 use crate::models::{ModelMetadata, ModelCapability};
 
 // String helper traits for code generation
@@ -91,6 +94,12 @@ pub struct CodeGenerationRequest {
     pub constraints: GenerationConstraints,
     pub context: Option<ProjectContext>,
     pub optimization_level: OptimizationLevel,
+    /// Sampling settings for every model call of the generation. The same
+    /// requirements with a `seed` or `greedy` set give byte-identical code on
+    /// the same machine; across hardware it is best-effort (see the
+    /// `generation` module).
+    #[serde(default)]
+    pub sampling: InferenceParameters,
 }
 
 /// Supported programming languages
@@ -176,6 +185,10 @@ pub struct ProjectContext {
     pub project_structure: Option<ProjectStructure>,
     pub domain_knowledge: Option<String>,
     pub team_preferences: Option<HashMap<String, String>>,
+    /// Source files of the existing project; the most relevant are packed
+    /// into the generation prompt
+    #[serde(default)]
+    pub files: Vec<ProjectFile>,
 }
 
 /// Project structure information
@@ -210,6 +223,9 @@ pub struct CodeGenerationResult {
     pub deployment_config: Option<DeploymentConfig>,
     pub metrics: GenerationMetrics,
     pub suggestions: Vec<CodeSuggestion>,
+    /// Project files included in the prompt, when the request had any
+    #[serde(default)]
+    pub context_report: Option<PackingReport>,
 }
 
 /// A generated file
@@ -317,6 +333,53 @@ pub enum ImpactLevel {
     Critical,
 }
 
+/// Tokens of instructions and templates sent with every generation besides the requirements
+const GENERATION_PROMPT_OVERHEAD_TOKENS: usize = 1_500;
+/// Tokens generated for the skeleton every project gets
+const BASE_COMPLETION_TOKENS: usize = 2_000;
+/// Tokens generated per requirement token, across code, tests and documentation
+const COMPLETION_TOKENS_PER_REQUIREMENT_TOKEN: usize = 12;
+
+/// Predicted token usage and price of generating code from some requirements
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CostEstimate {
+    pub provider: AIProviderType,
+    pub prompt_tokens: usize,
+    pub completion_tokens: usize,
+    pub cost_usd: f64,
+}
+
+impl CostEstimate {
+    /// Fail with `CostLimitExceeded` if the estimate is above `max_cost_usd`
+    pub fn check_limit(&self, max_cost_usd: f64) -> AIResult<()> {
+        if self.cost_usd > max_cost_usd {
+            return Err(AIEngineError::CostLimitExceeded {
+                estimated_usd: self.cost_usd,
+                limit_usd: max_cost_usd,
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Estimate what generating code from `requirements` costs on `provider`,
+/// before any model is called. Token counts grow linearly with the length
+/// of the requirements; prices come from `AIProviderType::list_pricing`.
+pub fn estimate_cost(requirements: &str, provider: &AIProviderType) -> CostEstimate {
+    let requirement_tokens = estimate_tokens(requirements);
+    let prompt_tokens = GENERATION_PROMPT_OVERHEAD_TOKENS + requirement_tokens;
+    let completion_tokens = BASE_COMPLETION_TOKENS + requirement_tokens * COMPLETION_TOKENS_PER_REQUIREMENT_TOKEN;
+
+    let pricing = provider.list_pricing();
+    CostEstimate {
+        provider: provider.clone(),
+        prompt_tokens,
+        completion_tokens,
+        cost_usd: prompt_tokens as f64 * pricing.input_cost_per_token
+            + completion_tokens as f64 * pricing.output_cost_per_token,
+    }
+}
+
 impl CodeGenerationEngine {
     /// Create a new code generation engine
     pub async fn new(
@@ -334,6 +397,12 @@ impl CodeGenerationEngine {
         })
     }
 
+    /// Pack relevant files of `ProjectContext::files` into generation prompts
+    pub fn with_context_packer(mut self, packer: Arc<ContextPacker>) -> Self {
+        self.context_manager = Arc::new(ContextManager::with_packer(packer));
+        self
+    }
+
     /// Generate code from requirements
     pub async fn generate_code(
         &self,
@@ -352,6 +421,7 @@ impl CodeGenerationEngine {
             &analyzed_requirements,
             &request.architecture,
             &enriched_context,
+            &request.sampling,
         ).await?;
 
         // Step 4: Generate code for each component
@@ -359,6 +429,7 @@ impl CodeGenerationEngine {
             &architecture,
             &request.language,
             &enriched_context,
+            &request.sampling,
         ).await?;
 
         // Step 5: Generate tests
@@ -399,6 +470,7 @@ impl CodeGenerationEngine {
             deployment_config: Some(deployment_config),
             metrics,
             suggestions,
+            context_report: enriched_context.project_files.map(|packed| packed.report),
         })
     }
 
@@ -430,6 +502,7 @@ impl CodeGenerationEngine {
         requirements: &AnalyzedRequirements,
         pattern: &ArchitecturePattern,
         context: &EnrichedContext,
+        sampling: &InferenceParameters,
     ) -> Result<SystemArchitecture> {
         // Use AI to design optimal architecture
         let prompt = self.build_architecture_prompt(requirements, pattern, context);
@@ -438,7 +511,7 @@ impl CodeGenerationEngine {
             id: Uuid::new_v4(),
             model_id: "architecture-designer".to_string(),
             input: crate::inference::InferenceInput::Text(prompt),
-            parameters: Default::default(),
+            parameters: sampling.clone(),
         }).await?;
 
         self.parse_architecture_design(inference_result)
@@ -450,11 +523,12 @@ impl CodeGenerationEngine {
         architecture: &SystemArchitecture,
         language: &ProgrammingLanguage,
         context: &EnrichedContext,
+        sampling: &InferenceParameters,
     ) -> Result<Vec<GeneratedFile>> {
         let mut generated_files = Vec::new();
 
         for component in &architecture.components {
-            let code = self.generate_single_component(component, language, context).await?;
+            let code = self.generate_single_component(component, language, context, sampling).await?;
             generated_files.extend(code);
         }
 
@@ -467,6 +541,7 @@ impl CodeGenerationEngine {
         component: &Component,
         language: &ProgrammingLanguage,
         context: &EnrichedContext,
+        sampling: &InferenceParameters,
     ) -> Result<Vec<GeneratedFile>> {
         let template = self.template_registry.read().await
             .get_template(language, &component.component_type)?;
@@ -477,7 +552,7 @@ impl CodeGenerationEngine {
             id: Uuid::new_v4(),
             model_id: "code-generator".to_string(),
             input: crate::inference::InferenceInput::Text(prompt),
-            parameters: Default::default(),
+            parameters: sampling.clone(),
         }).await?;
 
         self.parse_generated_code(inference_result, component, language)
@@ -736,7 +811,11 @@ impl CodeGenerationEngine {
     }
 
     fn build_code_generation_prompt(&self, component: &Component, template: String, context: &EnrichedContext) -> String {
-        format!("Generate code for component: {} using template: {}", component.name, template)
+        let prompt = format!("Generate code for component: {} using template: {}", component.name, template);
+        match &context.project_files {
+            Some(packed) if !packed.text.is_empty() => format!("Existing project files:\n\n{}{}", packed.text, prompt),
+            _ => prompt,
+        }
     }
 
     fn parse_generated_code(&self, result: InferenceResult, component: &Component, language: &ProgrammingLanguage) -> Result<Vec<GeneratedFile>> {
@@ -2687,6 +2766,8 @@ struct EnrichedContext {
     best_practices: Vec<String>,
     security_considerations: Vec<String>,
     performance_patterns: Vec<String>,
+    /// Existing project files selected for the prompt
+    project_files: Option<PackedContext>,
 }
 
 #[derive(Debug, Clone)]
@@ -2775,19 +2856,33 @@ impl ValidationEngine {
 }
 
 // Context Manager
-struct ContextManager;
+struct ContextManager {
+    packer: Option<Arc<ContextPacker>>,
+}
 
 impl ContextManager {
     fn new() -> Self {
-        Self
+        Self { packer: None }
+    }
+
+    fn with_packer(packer: Arc<ContextPacker>) -> Self {
+        Self { packer: Some(packer) }
     }
 
     async fn build_context(&self, request: &CodeGenerationRequest, analyzed: &AnalyzedRequirements) -> Result<EnrichedContext> {
+        let project_files = match (&self.packer, &request.context) {
+            (Some(packer), Some(context)) if !context.files.is_empty() => {
+                Some(packer.pack(&request.requirements, &context.files).await?)
+            }
+            _ => None,
+        };
+
         Ok(EnrichedContext {
             domain_patterns: vec![],
             best_practices: vec![],
             security_considerations: vec![],
             performance_patterns: vec![],
+            project_files,
         })
     }
 }
@@ -2799,4 +2894,32 @@ impl CodeGenMetrics {
     fn new() -> Self {
         Self
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cost_estimate_scales_with_requirements() {
+        let short = "A REST API for a todo list.";
+        let long = short.repeat(20);
+
+        let small = estimate_cost(short, &AIProviderType::OpenAI);
+        let large = estimate_cost(&long, &AIProviderType::OpenAI);
+        assert!(large.prompt_tokens > small.prompt_tokens);
+        assert!(large.completion_tokens > small.completion_tokens);
+        assert!(large.cost_usd > small.cost_usd);
+
+        assert_eq!(estimate_cost(&long, &AIProviderType::LocalOllama).cost_usd, 0.0);
+    }
+
+    #[test]
+    fn cost_above_limit_is_rejected() {
+        let estimate = estimate_cost(&"Users can sign up and log in. ".repeat(50), &AIProviderType::Anthropic);
+
+        assert!(estimate.check_limit(estimate.cost_usd).is_ok());
+        let error = estimate.check_limit(estimate.cost_usd / 2.0).unwrap_err();
+        assert!(matches!(error, AIEngineError::CostLimitExceeded { .. }));
+    }
+}
@@ -0,0 +1,373 @@
+//! # Context Packing
+//!
+//! Chooses which project files go into a code generation prompt. Files the
+//! task names come first, then the files semantic code search ranks most
+//! relevant to the task. Each file is included whole when it fits the token
+//! budget, as its declaration lines only when it does not, and cut short at a
+//! line boundary as a last resort. The packed context never exceeds the
+//! budget, and a [`PackingReport`] lists what was included, in what form,
+//! and what was left out.
+
+use crate::errors::AIResult;
+use crate::rag::estimate_tokens;
+use crate::semantic_search::{CodeSearchQuery, SemanticCodeIndex};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tracing::debug;
+
+/// Line prefixes that declare something, across the languages the engine
+/// generates
+const DECLARATION_PREFIXES: &[&str] = &[
+    "pub ", "pub(", "fn ", "async fn ", "unsafe fn ", "struct ", "enum ", "trait ", "impl ", "impl<", "type ",
+    "const ", "static ", "mod ", "use ", "class ", "def ", "async def ", "interface ", "export ", "function ",
+    "async function ", "func ", "package ", "import ", "from ", "public ", "protected ", "abstract ",
+];
+
+/// A source file of the project being generated into
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProjectFile {
+    pub path: PathBuf,
+    pub content: String,
+}
+
+impl ProjectFile {
+    pub fn new(path: impl Into<PathBuf>, content: impl Into<String>) -> Self {
+        Self {
+            path: path.into(),
+            content: content.into(),
+        }
+    }
+}
+
+/// Token budget and relevance settings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ContextPackerConfig {
+    /// Model context window
+    pub context_window_tokens: usize,
+    /// Tokens kept free for the task prompt and the generated output
+    pub reserved_tokens: usize,
+    /// Code search hits considered when ranking files
+    pub search_limit: usize,
+    /// Files whose best hit scores below this are left out unless the task
+    /// names them
+    pub min_score: f32,
+}
+
+impl Default for ContextPackerConfig {
+    fn default() -> Self {
+        Self {
+            context_window_tokens: 8192,
+            reserved_tokens: 3072,
+            search_limit: 50,
+            min_score: 0.1,
+        }
+    }
+}
+
+impl ContextPackerConfig {
+    /// Tokens available to packed files
+    pub fn budget(&self) -> usize {
+        self.context_window_tokens.saturating_sub(self.reserved_tokens)
+    }
+}
+
+/// How much of a file made it into the context
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Inclusion {
+    Full,
+    /// Declaration lines only
+    Signatures,
+    /// Cut off where the budget ran out
+    Truncated,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IncludedFile {
+    pub path: PathBuf,
+    pub inclusion: Inclusion,
+    pub tokens: usize,
+    /// Best code search score, when the file was a hit
+    pub score: Option<f32>,
+    /// The task names this file
+    pub referenced: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ExclusionReason {
+    NotRelevant,
+    OverBudget,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExcludedFile {
+    pub path: PathBuf,
+    pub reason: ExclusionReason,
+    pub score: Option<f32>,
+}
+
+/// What [`ContextPacker::pack`] put in the context
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PackingReport {
+    pub budget_tokens: usize,
+    pub used_tokens: usize,
+    /// In context order
+    pub included: Vec<IncludedFile>,
+    pub excluded: Vec<ExcludedFile>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PackedContext {
+    pub text: String,
+    pub report: PackingReport,
+}
+
+/// Selects and trims project files to fit a model's context window
+pub struct ContextPacker {
+    index: Arc<SemanticCodeIndex>,
+    config: ContextPackerConfig,
+}
+
+impl ContextPacker {
+    pub fn new(index: Arc<SemanticCodeIndex>) -> Self {
+        Self {
+            index,
+            config: ContextPackerConfig::default(),
+        }
+    }
+
+    pub fn with_config(mut self, config: ContextPackerConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    pub fn config(&self) -> &ContextPackerConfig {
+        &self.config
+    }
+
+    /// Pack the files most useful for `task` into the configured budget
+    pub async fn pack(&self, task: &str, files: &[ProjectFile]) -> AIResult<PackedContext> {
+        for file in files {
+            self.index.index_file(&file.path, &file.content).await?;
+        }
+
+        let query = CodeSearchQuery::new(task)
+            .with_limit(self.config.search_limit)
+            .with_min_score(self.config.min_score);
+        let mut scores: HashMap<&Path, f32> = HashMap::new();
+        for hit in self.index.search(&query).await? {
+            // The index may hold files from earlier calls; only rank this project's
+            if let Some(file) = files.iter().find(|file| file.path == hit.path) {
+                let score = scores.entry(file.path.as_path()).or_insert(hit.score);
+                *score = score.max(hit.score);
+            }
+        }
+
+        let mut candidates: Vec<(&ProjectFile, Option<usize>, Option<f32>)> = files
+            .iter()
+            .map(|file| (file, mention_position(task, &file.path), scores.get(file.path.as_path()).copied()))
+            .collect();
+        // Referenced files in order of mention, then by relevance
+        candidates.sort_by(|(a, a_mention, a_score), (b, b_mention, b_score)| match (a_mention, b_mention) {
+            (Some(a), Some(b)) => a.cmp(b),
+            (Some(_), None) => std::cmp::Ordering::Less,
+            (None, Some(_)) => std::cmp::Ordering::Greater,
+            (None, None) => b_score
+                .unwrap_or(f32::MIN)
+                .total_cmp(&a_score.unwrap_or(f32::MIN))
+                .then_with(|| a.path.cmp(&b.path)),
+        });
+
+        let budget = self.config.budget();
+        let mut text = String::new();
+        let mut report = PackingReport {
+            budget_tokens: budget,
+            ..PackingReport::default()
+        };
+
+        for (file, mention, score) in candidates {
+            if mention.is_none() && score.is_none() {
+                report.excluded.push(ExcludedFile {
+                    path: file.path.clone(),
+                    reason: ExclusionReason::NotRelevant,
+                    score,
+                });
+                continue;
+            }
+
+            match pack_file(file, budget - report.used_tokens) {
+                Some((section, inclusion, tokens)) => {
+                    text.push_str(&section);
+                    report.used_tokens += tokens;
+                    report.included.push(IncludedFile {
+                        path: file.path.clone(),
+                        inclusion,
+                        tokens,
+                        score,
+                        referenced: mention.is_some(),
+                    });
+                }
+                None => report.excluded.push(ExcludedFile {
+                    path: file.path.clone(),
+                    reason: ExclusionReason::OverBudget,
+                    score,
+                }),
+            }
+        }
+
+        debug_assert!(estimate_tokens(&text) <= budget);
+        debug!(
+            "Packed {} of {} files into {}/{} tokens",
+            report.included.len(),
+            files.len(),
+            report.used_tokens,
+            budget
+        );
+        Ok(PackedContext { text, report })
+    }
+}
+
+/// Where the task first names `path`, by full path or file name
+fn mention_position(task: &str, path: &Path) -> Option<usize> {
+    let full = path.to_string_lossy().replace('\\', "/");
+    let name = path.file_name()?.to_string_lossy();
+    // Bare names without an extension ("lib", "mod") are too common to count
+    let name = Some(name.as_ref()).filter(|name| name.contains('.'));
+    [Some(full.as_str()), name]
+        .into_iter()
+        .flatten()
+        .filter_map(|needle| {
+            task.match_indices(needle).map(|(i, _)| i).find(|&i| {
+                let before = task[..i].chars().next_back();
+                let after = task[i + needle.len()..].chars().next();
+                !before.map_or(false, is_path_char) && !after.map_or(false, |c| c.is_alphanumeric() || c == '_')
+            })
+        })
+        .min()
+}
+
+fn is_path_char(c: char) -> bool {
+    c.is_alphanumeric() || matches!(c, '_' | '-' | '.' | '/')
+}
+
+/// The largest form of `file` that fits `available` tokens, with its token
+/// count. Token estimates are subadditive, so summing sections never
+/// undercounts the packed text.
+fn pack_file(file: &ProjectFile, available: usize) -> Option<(String, Inclusion, usize)> {
+    let full = render_section(&file.path, Inclusion::Full, file.content.lines());
+    let tokens = estimate_tokens(&full);
+    if tokens <= available {
+        return Some((full, Inclusion::Full, tokens));
+    }
+
+    let declarations: Vec<&str> = file.content.lines().filter(|line| is_declaration(line)).collect();
+    if !declarations.is_empty() {
+        let signatures = render_section(&file.path, Inclusion::Signatures, declarations.iter().copied());
+        let tokens = estimate_tokens(&signatures);
+        if tokens <= available {
+            return Some((signatures, Inclusion::Signatures, tokens));
+        }
+    }
+
+    // Keep as many leading lines as fit, preferring declarations
+    let lines: Vec<&str> = if declarations.is_empty() { file.content.lines().collect() } else { declarations };
+    let fits = |count: usize| estimate_tokens(&render_section(&file.path, Inclusion::Truncated, lines[..count].iter().copied())) <= available;
+    if lines.is_empty() || !fits(1) {
+        return None;
+    }
+    let (mut low, mut high) = (1, lines.len());
+    while low < high {
+        let mid = (low + high + 1) / 2;
+        if fits(mid) {
+            low = mid;
+        } else {
+            high = mid - 1;
+        }
+    }
+    let truncated = render_section(&file.path, Inclusion::Truncated, lines[..low].iter().copied());
+    let tokens = estimate_tokens(&truncated);
+    Some((truncated, Inclusion::Truncated, tokens))
+}
+
+fn is_declaration(line: &str) -> bool {
+    let trimmed = line.trim_start();
+    DECLARATION_PREFIXES.iter().any(|prefix| trimmed.starts_with(prefix))
+}
+
+fn render_section<'a>(path: &Path, inclusion: Inclusion, lines: impl Iterator<Item = &'a str>) -> String {
+    let note = match inclusion {
+        Inclusion::Full => "",
+        Inclusion::Signatures => " (declarations only)",
+        Inclusion::Truncated => " (truncated)",
+    };
+    let fence = path.extension().and_then(|ext| ext.to_str()).unwrap_or("");
+    let mut section = format!("### {}{}\n```{}\n", path.display(), note, fence);
+    for line in lines {
+        section.push_str(line);
+        section.push('\n');
+    }
+    section.push_str("```\n\n");
+    section
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::semantic_search::HashingEmbedder;
+
+    fn packer(context_window_tokens: usize) -> ContextPacker {
+        ContextPacker::new(Arc::new(SemanticCodeIndex::new(Arc::new(HashingEmbedder::default())))).with_config(
+            ContextPackerConfig {
+                context_window_tokens,
+                reserved_tokens: 0,
+                ..ContextPackerConfig::default()
+            },
+        )
+    }
+
+    fn project() -> Vec<ProjectFile> {
+        let body = "    let total = items.iter().map(|item| item.price * item.quantity as f64).sum::<f64>();\n".repeat(20);
+        vec![
+            ProjectFile::new("src/billing.rs", format!("pub fn invoice_total(items: &[LineItem]) -> f64 {{\n{}    total\n}}\n", body)),
+            ProjectFile::new("src/email.rs", "pub fn validate_email_address(email: &str) -> bool {\n    email.contains('@')\n}\n"),
+            ProjectFile::new("src/config.rs", "pub struct Config {\n    pub port: u16,\n}\n"),
+        ]
+    }
+
+    #[tokio::test]
+    async fn referenced_and_relevant_files_come_first() {
+        let packed = packer(4096).pack("Add tax to the invoice total in src/config.rs", &project()).await.unwrap();
+
+        let included: Vec<&Path> = packed.report.included.iter().map(|file| file.path.as_path()).collect();
+        assert_eq!(included[0], Path::new("src/config.rs"));
+        assert!(packed.report.included[0].referenced);
+        assert_eq!(included[1], Path::new("src/billing.rs"));
+        assert!(packed.report.included.iter().all(|file| file.inclusion == Inclusion::Full));
+        assert!(packed.text.starts_with("### src/config.rs\n```rs\n"));
+    }
+
+    #[tokio::test]
+    async fn falls_back_to_declarations_and_never_exceeds_the_budget() {
+        for window in [40, 60, 120, 400] {
+            let packed = packer(window).pack("compute the invoice total for line items", &project()).await.unwrap();
+            assert!(packed.report.used_tokens <= window, "{:?}", packed.report);
+            assert!(estimate_tokens(&packed.text) <= packed.report.used_tokens);
+            assert_eq!(packed.report.included.len() + packed.report.excluded.len(), 3);
+        }
+
+        let packed = packer(60).pack("compute the invoice total for line items", &project()).await.unwrap();
+        let billing = packed.report.included.iter().find(|file| file.path == Path::new("src/billing.rs")).unwrap();
+        assert_eq!(billing.inclusion, Inclusion::Signatures);
+        assert!(packed.text.contains("pub fn invoice_total(items: &[LineItem]) -> f64 {"));
+        assert!(!packed.text.contains("let total"));
+    }
+
+    #[test]
+    fn mentions_need_a_path_boundary() {
+        assert_eq!(mention_position("update src/billing.rs please", Path::new("src/billing.rs")), Some(7));
+        assert_eq!(mention_position("see billing.rs", Path::new("src/billing.rs")), Some(4));
+        assert_eq!(mention_position("see old_billing.rs", Path::new("src/billing.rs")), None);
+        assert_eq!(mention_position("the lib crate", Path::new("src/lib")), None);
+    }
+}
@@ -0,0 +1,216 @@
+//! # Compute Devices
+//!
+//! Device selection and GPU-to-CPU fallback for the inference engine.
+//!
+//! When a request fails on its device with an error the device itself caused
+//! (out of memory, driver fault, lost device), the engine can retry it on the
+//! devices listed in [`DeviceFallbackConfig`]. Errors caused by the request,
+//! such as malformed input, are never retried. Fallbacks are capped per time
+//! window: once the cap is hit the engine stops falling back and logs an
+//! error, so a persistently broken GPU surfaces as failures instead of
+//! quietly moving all traffic to a slower device.
+
+use crate::errors::{AIEngineError, AIResult};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Device an inference runs on
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum InferenceDevice {
+    #[default]
+    Cpu,
+    /// CUDA GPU by ordinal
+    Cuda(usize),
+    /// Apple GPU by ordinal
+    Metal(usize),
+}
+
+impl InferenceDevice {
+    pub fn is_gpu(&self) -> bool {
+        !matches!(self, InferenceDevice::Cpu)
+    }
+
+    /// Open the device for Candle. A missing device, or one Candle was built
+    /// without support for, is a recoverable `DeviceError`.
+    pub fn to_candle(&self) -> AIResult<candle_core::Device> {
+        let device = match self {
+            InferenceDevice::Cpu => Ok(candle_core::Device::Cpu),
+            InferenceDevice::Cuda(ordinal) => candle_core::Device::new_cuda(*ordinal),
+            InferenceDevice::Metal(ordinal) => candle_core::Device::new_metal(*ordinal),
+        };
+        device.map_err(|e| AIEngineError::DeviceError {
+            device: self.to_string(),
+            reason: e.to_string(),
+        })
+    }
+}
+
+impl fmt::Display for InferenceDevice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InferenceDevice::Cpu => write!(f, "cpu"),
+            InferenceDevice::Cuda(ordinal) => write!(f, "cuda:{}", ordinal),
+            InferenceDevice::Metal(ordinal) => write!(f, "metal:{}", ordinal),
+        }
+    }
+}
+
+/// Where and how often failed requests may be retried on another device
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeviceFallbackConfig {
+    pub enabled: bool,
+    /// Devices to try, in order, after the primary fails
+    pub fallback_devices: Vec<InferenceDevice>,
+    /// Most fallbacks allowed within `window`
+    pub max_fallbacks: usize,
+    pub window: Duration,
+}
+
+impl Default for DeviceFallbackConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            fallback_devices: vec![InferenceDevice::Cpu],
+            max_fallbacks: 20,
+            window: Duration::from_secs(60),
+        }
+    }
+}
+
+/// Records that a response was served by a fallback device
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeviceFallback {
+    /// Device the request failed on
+    pub from: InferenceDevice,
+    /// The error that triggered the fallback
+    pub reason: String,
+}
+
+/// Message fragments of device faults reported by CUDA, Metal and their
+/// libraries
+const DEVICE_ERROR_MARKERS: &[&str] = &[
+    "out of memory",
+    "cuda error",
+    "cuda_error",
+    "cublas",
+    "cudnn",
+    "device-side assert",
+    "device lost",
+    "device removed",
+    "illegal memory access",
+    "metal error",
+];
+
+/// Whether an inference error was caused by the device rather than the
+/// request, so that retrying elsewhere could succeed. The model manager's
+/// `MemoryLimitExceeded` is its own budget, not the device running out.
+pub fn is_recoverable_device_error(error: &anyhow::Error) -> bool {
+    for cause in error.chain() {
+        if let Some(engine_error) = cause.downcast_ref::<AIEngineError>() {
+            return matches!(engine_error, AIEngineError::DeviceError { .. });
+        }
+    }
+
+    let message = format!("{:#}", error).to_lowercase();
+    DEVICE_ERROR_MARKERS.iter().any(|marker| message.contains(marker))
+}
+
+/// Sliding-window cap on fallbacks
+pub struct FallbackLimiter {
+    max_fallbacks: usize,
+    window: Duration,
+    recent: Mutex<VecDeque<Instant>>,
+    exhausted: AtomicBool,
+}
+
+impl FallbackLimiter {
+    pub fn new(max_fallbacks: usize, window: Duration) -> Self {
+        Self {
+            max_fallbacks,
+            window,
+            recent: Mutex::new(VecDeque::new()),
+            exhausted: AtomicBool::new(false),
+        }
+    }
+
+    /// Take a fallback slot if one is free
+    pub fn try_acquire(&self) -> bool {
+        self.try_acquire_at(Instant::now())
+    }
+
+    fn try_acquire_at(&self, now: Instant) -> bool {
+        let mut recent = self.recent.lock().unwrap();
+        while recent.front().map_or(false, |at| now.duration_since(*at) >= self.window) {
+            recent.pop_front();
+        }
+        if recent.len() < self.max_fallbacks {
+            recent.push_back(now);
+            self.exhausted.store(false, Ordering::Relaxed);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// True the first time the budget is found exhausted after having slots
+    /// free, so callers alert once per outage instead of on every request
+    pub fn mark_exhausted(&self) -> bool {
+        !self.exhausted.swap(true, Ordering::Relaxed)
+    }
+
+    /// Fallbacks taken within the current window
+    pub fn recent_fallbacks(&self) -> usize {
+        let now = Instant::now();
+        self.recent
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|at| now.duration_since(**at) < self.window)
+            .count()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classifies_device_errors() {
+        assert!(is_recoverable_device_error(&anyhow::anyhow!(
+            "CUDA out of memory. Tried to allocate 2.00 GiB"
+        )));
+        assert!(is_recoverable_device_error(&anyhow::Error::new(AIEngineError::DeviceError {
+            device: "cuda:0".to_string(),
+            reason: "device lost".to_string(),
+        })));
+        assert!(!is_recoverable_device_error(&anyhow::Error::new(
+            AIEngineError::InvalidInputFormat {
+                model: "bert".to_string(),
+                reason: "out of memory is not a valid token".to_string(),
+            }
+        )));
+        assert!(!is_recoverable_device_error(&anyhow::anyhow!("tokenizer rejected input")));
+        assert!(!is_recoverable_device_error(&anyhow::Error::new(AIEngineError::MemoryLimitExceeded {
+            requested: 2048,
+            limit: 1024,
+        })));
+    }
+
+    #[test]
+    fn limiter_caps_fallbacks_per_window() {
+        let limiter = FallbackLimiter::new(2, Duration::from_secs(60));
+        let start = Instant::now();
+        assert!(limiter.try_acquire_at(start));
+        assert!(limiter.try_acquire_at(start));
+        assert!(!limiter.try_acquire_at(start + Duration::from_secs(1)));
+        assert!(limiter.mark_exhausted());
+        assert!(!limiter.mark_exhausted());
+
+        assert!(limiter.try_acquire_at(start + Duration::from_secs(61)));
+        assert!(limiter.mark_exhausted());
+    }
+}
@@ -0,0 +1,265 @@
+// Go values and `go test` stubs
+
+use super::{integer, matching_close, split_top_level, CollectionShape, EdgeCase, EdgeCaseKind, ParsedSignature, SignatureParameter, ValueType};
+
+/// The method receiver, if any, and the rest of the signature after it.
+/// Lines before the `func` line, such as a package clause, are skipped.
+pub(super) fn split_receiver(source: &str) -> Option<(Option<SignatureParameter>, &str)> {
+    let start = source
+        .match_indices("func")
+        .map(|(index, _)| index)
+        .find(|&index| index == 0 || source[..index].trim_end_matches(|c| c == ' ' || c == '\t').ends_with('\n'))?;
+    let after_func = source[start + "func".len()..].trim_start();
+
+    match after_func.strip_prefix('(') {
+        Some(_) => {
+            let close = matching_close(after_func, 0)?;
+            let receiver = parse_parameters(&after_func[1..close]).into_iter().next();
+            Some((receiver, after_func[close + 1..].trim_start()))
+        }
+        None => Some((None, after_func)),
+    }
+}
+
+/// The package named by a `package` clause in `source`, `main` without one
+pub(super) fn package_name(source: &str) -> &str {
+    source
+        .lines()
+        .find_map(|line| line.trim().strip_prefix("package "))
+        .and_then(|rest| rest.split_whitespace().next())
+        .unwrap_or("main")
+}
+
+/// Go allows `a, b int`: names without a type share the next type
+pub(super) fn parse_parameters(list: &str) -> Vec<SignatureParameter> {
+    let mut parameters = Vec::new();
+    let mut untyped = Vec::new();
+
+    for parameter in split_top_level(list, ',') {
+        let parameter = parameter.trim();
+        if parameter.is_empty() {
+            continue;
+        }
+        match parameter.split_once(char::is_whitespace) {
+            Some((name, type_name)) => {
+                let type_name = type_name.trim();
+                let type_name = match type_name.strip_prefix("...") {
+                    Some(element) => format!("[]{}", element),
+                    None => type_name.to_string(),
+                };
+                for name in untyped.drain(..).chain(std::iter::once(name.to_string())) {
+                    parameters.push(SignatureParameter { name, type_name: type_name.clone() });
+                }
+            }
+            None => untyped.push(parameter.to_string()),
+        }
+    }
+
+    // Unnamed parameters, e.g. `func(int, string)`
+    for (index, type_name) in untyped.into_iter().enumerate() {
+        parameters.push(SignatureParameter { name: format!("arg{}", index), type_name });
+    }
+    parameters
+}
+
+pub(super) fn classify(type_name: &str) -> ValueType {
+    if let Some(inner) = type_name.strip_prefix('*') {
+        return ValueType::Nullable(Box::new(classify(inner)));
+    }
+    if let Some(element) = type_name.strip_prefix("[]") {
+        return ValueType::Collection {
+            shape: CollectionShape::List,
+            type_name: type_name.to_string(),
+            element: Box::new(classify(element)),
+            value: None,
+        };
+    }
+    if let Some(rest) = type_name.strip_prefix("map[") {
+        let close = rest.find(']').unwrap_or(rest.len());
+        return ValueType::Collection {
+            shape: CollectionShape::Map,
+            type_name: type_name.to_string(),
+            element: Box::new(classify(&rest[..close])),
+            value: Some(Box::new(classify(rest.get(close + 1..).unwrap_or("")))),
+        };
+    }
+
+    match type_name {
+        "int" => integer("int", None, true),
+        "uint" | "uintptr" => integer(type_name, None, false),
+        "int8" | "int16" | "int32" | "int64" => integer(type_name, type_name[3..].parse().ok(), true),
+        "uint8" | "uint16" | "uint32" | "uint64" => integer(type_name, type_name[4..].parse().ok(), false),
+        "byte" => integer("uint8", Some(8), false),
+        "rune" => integer("int32", Some(32), true),
+        "float32" | "float64" => ValueType::Float { name: type_name.to_string() },
+        "bool" => ValueType::Bool,
+        "string" => ValueType::Text { owned: true },
+        _ => ValueType::Other(type_name.to_string()),
+    }
+}
+
+/// A type in the type set of `constraint`: the first term of a union, or
+/// `int` for `any`, `comparable` and the numeric constraint interfaces
+pub(super) fn concrete_type(constraint: &str) -> String {
+    let term = split_top_level(constraint, '|')[0].trim().trim_start_matches('~');
+    match term {
+        "" | "any" | "comparable" | "interface{}" => "int".to_string(),
+        _ if term.contains('.') => match term.rsplit('.').next() {
+            Some("Float") => "float64".to_string(),
+            Some("Unsigned") => "uint".to_string(),
+            _ => "int".to_string(),
+        },
+        _ => term.to_string(),
+    }
+}
+
+pub(super) fn typical_value(value_type: &ValueType) -> String {
+    match value_type {
+        ValueType::Integer { .. } | ValueType::Float { .. } => "1".to_string(),
+        ValueType::Bool => "true".to_string(),
+        ValueType::Text { .. } => "\"a\"".to_string(),
+        ValueType::Collection { .. } => collection_literal(value_type, false),
+        ValueType::Nullable(inner) => format!("new({})", type_name(inner)),
+        ValueType::Reference { inner, .. } => typical_value(inner),
+        ValueType::Other(type_name) => format!("*new({})", type_name),
+    }
+}
+
+pub(super) fn boundary_values(value_type: &ValueType) -> Vec<(EdgeCaseKind, String)> {
+    match value_type {
+        ValueType::Integer { bits, signed, .. } => {
+            let size = bits.map(|bits| bits.to_string()).unwrap_or_default();
+            let mut values = vec![(EdgeCaseKind::Zero, "0".to_string())];
+            // An unsigned minimum is zero, covered above
+            if *signed {
+                values.push((EdgeCaseKind::Minimum, format!("math.MinInt{}", size)));
+            }
+            values.push((EdgeCaseKind::Maximum, format!("math.Max{}{}", if *signed { "Int" } else { "Uint" }, size)));
+            values
+        }
+        ValueType::Float { name } => {
+            let (max, nan) = if name == "float32" {
+                ("math.MaxFloat32", "float32(math.NaN())")
+            } else {
+                ("math.MaxFloat64", "math.NaN()")
+            };
+            vec![
+                (EdgeCaseKind::Zero, "0".to_string()),
+                (EdgeCaseKind::Minimum, format!("-{}", max)),
+                (EdgeCaseKind::Maximum, max.to_string()),
+                (EdgeCaseKind::NotANumber, nan.to_string()),
+            ]
+        }
+        ValueType::Bool | ValueType::Other(_) => Vec::new(),
+        ValueType::Text { .. } => vec![(EdgeCaseKind::Empty, "\"\"".to_string())],
+        // A nil slice or map is distinct from an empty one. The nil is typed
+        // so a generic parameter can still be inferred from it.
+        ValueType::Collection { type_name, .. } => vec![
+            (EdgeCaseKind::Empty, collection_literal(value_type, true)),
+            (EdgeCaseKind::SingleElement, collection_literal(value_type, false)),
+            (EdgeCaseKind::Null, format!("{}(nil)", type_name)),
+        ],
+        // Pointers to boundary values would need a variable; the nil case covers them
+        ValueType::Nullable(_) => vec![(EdgeCaseKind::Null, format!("({})(nil)", type_name(value_type)))],
+        ValueType::Reference { inner, .. } => boundary_values(inner),
+    }
+}
+
+/// Empty or single-element literal of a collection type
+fn collection_literal(value_type: &ValueType, empty: bool) -> String {
+    let ValueType::Collection { type_name, element, value, .. } = value_type else {
+        return typical_value(value_type);
+    };
+    if empty {
+        return format!("{}{{}}", type_name);
+    }
+
+    let element = typical_value(element);
+    match value.as_deref() {
+        Some(value) => format!("{}{{{}: {}}}", type_name, element, typical_value(value)),
+        None => format!("{}{{{}}}", type_name, element),
+    }
+}
+
+fn type_name(value_type: &ValueType) -> String {
+    match value_type {
+        ValueType::Integer { name, .. } | ValueType::Float { name } => name.clone(),
+        ValueType::Bool => "bool".to_string(),
+        ValueType::Text { .. } => "string".to_string(),
+        ValueType::Collection { type_name, .. } => type_name.clone(),
+        ValueType::Nullable(inner) => format!("*{}", type_name(inner)),
+        ValueType::Reference { inner, .. } => type_name(inner),
+        ValueType::Other(type_name) => type_name.clone(),
+    }
+}
+
+/// Test functions in `package`. A method is called on `receiver`, an
+/// expression producing a receiver value.
+pub(super) fn render_tests(signature: &ParsedSignature, cases: &[EdgeCase], package: &str, receiver: Option<&str>) -> String {
+    let uses_math = cases.iter().any(|case| case.arguments.iter().any(|argument| argument.contains("math.")));
+    let setup = receiver.map(|receiver| format!("\tsubject := {}\n", receiver)).unwrap_or_default();
+    let function = match receiver {
+        Some(_) => format!("subject.{}", signature.name),
+        None => signature.name.clone(),
+    };
+
+    let mut code = format!("package {}\n\nimport (\n", package);
+    if uses_math {
+        code.push_str("\t\"math\"\n");
+    }
+    code.push_str("\t\"testing\"\n)\n");
+    for case in cases {
+        code.push_str(&format!(
+            "\nfunc Test{}(t *testing.T) {{\n\t// {}\n{}\t{}({})\n\t// TODO: check the expected result\n}}\n",
+            to_pascal_case(&case.name),
+            case.description,
+            setup,
+            function,
+            case.arguments.join(", ")
+        ));
+    }
+    code
+}
+
+fn to_pascal_case(name: &str) -> String {
+    name.split('_')
+        .filter(|part| !part.is_empty())
+        .map(|part| {
+            let mut chars = part.chars();
+            chars.next().map(|first| first.to_uppercase().chain(chars).collect::<String>()).unwrap_or_default()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::edge_cases::{assert_parses, generate_edge_cases, values};
+    use super::*;
+
+    #[test]
+    fn methods_are_called_on_a_receiver_in_the_declared_package() {
+        let suite = generate_edge_cases("package store\n\nfunc (s *Store) Trim(ids []string, keep, offset uint8) error", "go").unwrap();
+        assert_eq!(suite.signature.name, "Trim");
+        assert_eq!(suite.signature.receiver.as_ref().unwrap().type_name, "*Store");
+        assert_eq!(values(&suite, "keep"), vec![(EdgeCaseKind::Zero, "0".to_string()), (EdgeCaseKind::Maximum, "math.MaxUint8".to_string())]);
+        assert_eq!(values(&suite, "ids")[2], (EdgeCaseKind::Null, "[]string(nil)".to_string()));
+
+        assert!(suite.test_code.starts_with("package store\n\nimport (\n\t\"math\"\n\t\"testing\"\n)\n"));
+        assert!(suite.test_code.contains(
+            "func TestTrimIdsEmpty(t *testing.T) {\n\t// ids is empty\n\tsubject := new(Store)\n\tsubject.Trim([]string{}, 1, 1)\n"
+        ));
+        assert_parses(tree_sitter_go::language(), &suite.test_code);
+    }
+
+    #[test]
+    fn functions_default_to_package_main_and_resolve_type_parameters() {
+        let suite = generate_edge_cases("func Sum[T ~int | ~float64](values []T, limit *T) T", "go").unwrap();
+        assert!(suite.signature.receiver.is_none());
+        assert_eq!(values(&suite, "values")[1], (EdgeCaseKind::SingleElement, "[]int{1}".to_string()));
+        assert_eq!(values(&suite, "limit"), vec![(EdgeCaseKind::Null, "(*int)(nil)".to_string())]);
+
+        assert!(suite.test_code.starts_with("package main\n\nimport (\n\t\"testing\"\n)\n"));
+        assert!(suite.test_code.contains("\tSum([]int{}, new(int))\n"));
+        assert_parses(tree_sitter_go::language(), &suite.test_code);
+    }
+}
@@ -0,0 +1,550 @@
+// AION-R Edge Case Test Generation
+//
+// Each parameter of a function signature is classified by its declared type
+// and given the boundary inputs for that type: zero and the type's limits for
+// numbers, empty and single-element values for strings and collections, and
+// null for types that admit it. A case varies one parameter and passes an
+// ordinary value for the others, and is emitted as a test stub in the
+// language's usual framework, left for the developer to assert on.
+//
+// Signature parsing is shared; what a type's values look like and how the
+// tests are written lives in one module per language.
+
+use serde::{Deserialize, Serialize};
+
+use crate::errors::{AIEngineError, AIResult};
+
+mod go;
+mod python;
+mod rust;
+mod typescript;
+
+/// Which boundary an edge case exercises
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EdgeCaseKind {
+    Zero,
+    /// The type's smallest value, where going lower overflows
+    Minimum,
+    /// The type's largest value, where going higher overflows
+    Maximum,
+    NotANumber,
+    Empty,
+    SingleElement,
+    Null,
+}
+
+impl EdgeCaseKind {
+    fn label(self) -> &'static str {
+        match self {
+            EdgeCaseKind::Zero => "zero",
+            EdgeCaseKind::Minimum => "min",
+            EdgeCaseKind::Maximum => "max",
+            EdgeCaseKind::NotANumber => "nan",
+            EdgeCaseKind::Empty => "empty",
+            EdgeCaseKind::SingleElement => "single_element",
+            EdgeCaseKind::Null => "null",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SignatureParameter {
+    pub name: String,
+    pub type_name: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ParsedSignature {
+    pub name: String,
+    /// Go method receiver, e.g. `s *Store`
+    pub receiver: Option<SignatureParameter>,
+    /// Generic parameters with their bounds or constraints as `type_name`,
+    /// empty when unbounded
+    pub type_parameters: Vec<SignatureParameter>,
+    pub parameters: Vec<SignatureParameter>,
+    pub is_async: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EdgeCase {
+    /// Test name, e.g. `resize_width_max`
+    pub name: String,
+    /// Parameter set to the boundary value
+    pub parameter: String,
+    pub kind: EdgeCaseKind,
+    /// Argument expressions in the target language, one per parameter
+    pub arguments: Vec<String>,
+    pub description: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EdgeCaseSuite {
+    pub language: String,
+    pub signature: ParsedSignature,
+    pub cases: Vec<EdgeCase>,
+    /// Test stubs for every case, in the language's test framework
+    pub test_code: String,
+}
+
+/// Boundary-value test cases for `function_signature`, written in
+/// `language` ("rust", "typescript", "python" or "go"), with test stubs for
+/// cargo test, Jest, pytest or `go test` respectively.
+///
+/// Generic parameters are given a concrete type satisfying their bounds
+/// where one is recognised. Go stubs are declared in the package named by a
+/// `package` clause preceding the signature, `main` without one.
+pub fn generate_edge_cases(function_signature: &str, language: &str) -> AIResult<EdgeCaseSuite> {
+    let target = TargetLanguage::parse(language).ok_or_else(|| AIEngineError::ConfigurationError {
+        field: "language".to_string(),
+        reason: format!("edge case generation does not support {}", language),
+    })?;
+    let signature = parse_signature(function_signature, target).ok_or_else(|| AIEngineError::PreprocessingFailed {
+        reason: format!("cannot parse {} function signature: {}", language, function_signature.trim()),
+    })?;
+
+    let types: Vec<ValueType> = signature
+        .parameters
+        .iter()
+        .map(|parameter| classify_type(&resolve_type_parameters(&parameter.type_name, &signature.type_parameters, target), target))
+        .collect();
+    let typical: Vec<String> = types.iter().map(|value_type| typical_value(value_type, target)).collect();
+
+    let mut cases = Vec::new();
+    for (index, (parameter, value_type)) in signature.parameters.iter().zip(&types).enumerate() {
+        for (kind, value) in boundary_values(value_type, target) {
+            let mut arguments = typical.clone();
+            arguments[index] = value.clone();
+            cases.push(EdgeCase {
+                name: format!("{}_{}_{}", to_snake_case(&signature.name), to_snake_case(&parameter.name), kind.label()),
+                parameter: parameter.name.clone(),
+                kind,
+                arguments,
+                description: describe(kind, &parameter.name, &parameter.type_name, &value),
+            });
+        }
+    }
+
+    let test_code = match target {
+        TargetLanguage::Rust => rust::render_tests(&signature, &cases),
+        TargetLanguage::TypeScript => typescript::render_tests(&signature, &cases),
+        TargetLanguage::Python => python::render_tests(&signature, &cases),
+        TargetLanguage::Go => {
+            let receiver = signature.receiver.as_ref().map(|receiver| {
+                let type_name = resolve_type_parameters(&receiver.type_name, &signature.type_parameters, target);
+                go::typical_value(&go::classify(&type_name))
+            });
+            go::render_tests(&signature, &cases, go::package_name(function_signature), receiver.as_deref())
+        }
+    };
+    Ok(EdgeCaseSuite {
+        language: target.name().to_string(),
+        signature,
+        cases,
+        test_code,
+    })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TargetLanguage {
+    Rust,
+    TypeScript,
+    Python,
+    Go,
+}
+
+impl TargetLanguage {
+    fn parse(language: &str) -> Option<Self> {
+        match language.trim().to_lowercase().as_str() {
+            "rust" | "rs" => Some(Self::Rust),
+            "typescript" | "ts" | "javascript" | "js" => Some(Self::TypeScript),
+            "python" | "py" => Some(Self::Python),
+            "go" | "golang" => Some(Self::Go),
+            _ => None,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::Rust => "rust",
+            Self::TypeScript => "typescript",
+            Self::Python => "python",
+            Self::Go => "go",
+        }
+    }
+}
+
+/// What a parameter type allows, as far as boundaries go
+#[derive(Debug, Clone)]
+enum ValueType {
+    /// `bits` is `None` for platform-sized or unbounded integers
+    Integer { name: String, bits: Option<u32>, signed: bool },
+    Float { name: String },
+    Bool,
+    /// `owned` distinguishes Rust `String` from `&str`
+    Text { owned: bool },
+    Collection { shape: CollectionShape, type_name: String, element: Box<ValueType>, value: Option<Box<ValueType>> },
+    Nullable(Box<ValueType>),
+    /// Rust `&T` / `&mut T`
+    Reference { mutable: bool, inner: Box<ValueType> },
+    Other(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CollectionShape {
+    List,
+    Slice,
+    Tuple,
+    Set,
+    Map,
+}
+
+fn integer(name: &str, bits: Option<u32>, signed: bool) -> ValueType {
+    ValueType::Integer { name: name.to_string(), bits, signed }
+}
+
+fn classify_type(type_name: &str, target: TargetLanguage) -> ValueType {
+    let type_name = type_name.trim();
+    match target {
+        TargetLanguage::Rust => rust::classify(type_name),
+        TargetLanguage::TypeScript => typescript::classify(type_name),
+        TargetLanguage::Python => python::classify(type_name),
+        TargetLanguage::Go => go::classify(type_name),
+    }
+}
+
+/// Ordinary, unremarkable value of the type
+fn typical_value(value_type: &ValueType, target: TargetLanguage) -> String {
+    match target {
+        TargetLanguage::Rust => rust::typical_value(value_type),
+        TargetLanguage::TypeScript => typescript::typical_value(value_type),
+        TargetLanguage::Python => python::typical_value(value_type),
+        TargetLanguage::Go => go::typical_value(value_type),
+    }
+}
+
+/// The boundary inputs of the type, in the order the cases are emitted
+fn boundary_values(value_type: &ValueType, target: TargetLanguage) -> Vec<(EdgeCaseKind, String)> {
+    match target {
+        TargetLanguage::Rust => rust::boundary_values(value_type),
+        TargetLanguage::TypeScript => typescript::boundary_values(value_type),
+        TargetLanguage::Python => python::boundary_values(value_type),
+        TargetLanguage::Go => go::boundary_values(value_type),
+    }
+}
+
+/// Replace the signature's generic parameters in `type_name` with concrete
+/// types, since a test cannot name them
+fn resolve_type_parameters(type_name: &str, type_parameters: &[SignatureParameter], target: TargetLanguage) -> String {
+    let concrete: Vec<(&str, String)> = type_parameters
+        .iter()
+        .map(|parameter| {
+            let bound = parameter.type_name.trim();
+            let concrete = match target {
+                TargetLanguage::Rust => rust::concrete_type(bound),
+                TargetLanguage::TypeScript => typescript::concrete_type(bound),
+                TargetLanguage::Go => go::concrete_type(bound),
+                TargetLanguage::Python => "Any".to_string(),
+            };
+            (parameter.name.as_str(), concrete)
+        })
+        .collect();
+
+    // A bound may name another parameter, e.g. `I: IntoIterator<Item = T>`
+    let mut resolved = type_name.to_string();
+    for _ in 0..=concrete.len() {
+        let next = concrete
+            .iter()
+            .fold(resolved.clone(), |text, (name, replacement)| replace_identifier(&text, name, replacement));
+        if next == resolved {
+            break;
+        }
+        resolved = next;
+    }
+    resolved
+}
+
+/// Replace whole-identifier occurrences of `name`
+fn replace_identifier(text: &str, name: &str, replacement: &str) -> String {
+    let is_identifier = |c: char| c.is_alphanumeric() || c == '_' || c == '$';
+    let mut replaced = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(start) = rest.find(|c: char| is_identifier(c)) {
+        replaced.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let end = rest.find(|c: char| !is_identifier(c)).unwrap_or(rest.len());
+        replaced.push_str(if &rest[..end] == name { replacement } else { &rest[..end] });
+        rest = &rest[end..];
+    }
+    replaced.push_str(rest);
+    replaced
+}
+
+fn parse_signature(signature: &str, target: TargetLanguage) -> Option<ParsedSignature> {
+    let mut text = signature.trim();
+    let mut receiver = None;
+    if target == TargetLanguage::Go {
+        (receiver, text) = go::split_receiver(text)?;
+    }
+
+    let open = text.find('(')?;
+    let close = matching_close(text, open)?;
+    let mut prefix = text[..open].trim_end();
+    // Generic parameters between the name and the parameter list
+    let mut type_parameters = Vec::new();
+    if prefix.ends_with('>') || (target == TargetLanguage::Go && prefix.ends_with(']')) {
+        let generics = matching_open(prefix)?;
+        type_parameters = parse_type_parameters(&prefix[generics + 1..prefix.len() - 1], target);
+        prefix = prefix[..generics].trim_end();
+    }
+    if target == TargetLanguage::Rust {
+        rust::add_where_clause(&mut type_parameters, &text[close + 1..]);
+    }
+
+    const KEYWORDS: &[&str] = &[
+        "pub", "crate", "async", "unsafe", "const", "extern", "fn", "function", "export", "default", "let", "var",
+        "public", "private", "protected", "static", "def", "func",
+    ];
+    let words: Vec<&str> = prefix
+        .split(|c: char| !(c.is_alphanumeric() || c == '_' || c == '$'))
+        .filter(|word| !word.is_empty())
+        .collect();
+    let name = words.iter().rev().find(|word| !KEYWORDS.contains(word))?.to_string();
+    let is_async = words.contains(&"async");
+
+    let parameters = match target {
+        TargetLanguage::Go => go::parse_parameters(&text[open + 1..close]),
+        _ => split_top_level(&text[open + 1..close], ',')
+            .into_iter()
+            .filter_map(|parameter| parse_parameter(parameter, target))
+            .collect(),
+    };
+
+    Some(ParsedSignature { name, receiver, type_parameters, parameters, is_async })
+}
+
+fn parse_type_parameters(list: &str, target: TargetLanguage) -> Vec<SignatureParameter> {
+    match target {
+        TargetLanguage::Go => go::parse_parameters(list),
+        TargetLanguage::Rust => rust::parse_type_parameters(list),
+        TargetLanguage::TypeScript => typescript::parse_type_parameters(list),
+        TargetLanguage::Python => Vec::new(),
+    }
+}
+
+fn parse_parameter(parameter: &str, target: TargetLanguage) -> Option<SignatureParameter> {
+    let parameter = parameter.trim();
+    // Defaults do not change the type
+    let parameter = match target {
+        TargetLanguage::TypeScript | TargetLanguage::Python => split_top_level(parameter, '=')[0].trim(),
+        _ => parameter,
+    };
+    if parameter.is_empty() {
+        return None;
+    }
+
+    let (name, type_name) = match find_type_colon(parameter) {
+        Some(colon) => (parameter[..colon].trim(), parameter[colon + 1..].trim()),
+        None => (parameter, ""),
+    };
+    let name = name.trim_start_matches("mut ").trim();
+
+    match target {
+        TargetLanguage::Rust => {
+            if name.ends_with("self") {
+                return None;
+            }
+        }
+        TargetLanguage::Python => {
+            if matches!(name, "self" | "cls" | "*" | "/") || name.starts_with('*') {
+                return None;
+            }
+        }
+        TargetLanguage::TypeScript => {
+            if name == "this" {
+                return None;
+            }
+            if let Some(rest) = name.strip_prefix("...") {
+                let type_name = if type_name.is_empty() { "any[]" } else { type_name };
+                return Some(SignatureParameter { name: rest.to_string(), type_name: type_name.to_string() });
+            }
+            if let Some(optional) = name.strip_suffix('?') {
+                let type_name = if type_name.is_empty() { "any" } else { type_name };
+                return Some(SignatureParameter {
+                    name: optional.to_string(),
+                    type_name: format!("{} | undefined", type_name),
+                });
+            }
+        }
+        TargetLanguage::Go => {}
+    }
+
+    let untyped = match target {
+        TargetLanguage::Python => "Any",
+        _ => "any",
+    };
+    Some(SignatureParameter {
+        name: name.to_string(),
+        type_name: if type_name.is_empty() { untyped.to_string() } else { type_name.to_string() },
+    })
+}
+
+/// Split at `separator` where it is not nested in brackets of any kind
+fn split_top_level(text: &str, separator: char) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut depth = 0i32;
+    let mut start = 0;
+    let mut previous = ' ';
+
+    for (index, c) in text.char_indices() {
+        match c {
+            '(' | '[' | '{' | '<' => depth += 1,
+            // `->` and `=>` are arrows, not closing brackets
+            '>' if previous == '-' || previous == '=' => {}
+            ')' | ']' | '}' | '>' => depth -= 1,
+            _ if c == separator && depth == 0 => {
+                // `==`, `<=` and `>=` in Python and TypeScript defaults
+                let next = text[index + c.len_utf8()..].chars().next();
+                if separator != '=' || (next != Some('=') && !matches!(previous, '=' | '!' | '<' | '>')) {
+                    parts.push(&text[start..index]);
+                    start = index + c.len_utf8();
+                }
+            }
+            _ => {}
+        }
+        previous = c;
+    }
+    parts.push(&text[start..]);
+    parts
+}
+
+/// The `:` separating a parameter name from its type, skipping Rust `::` paths
+fn find_type_colon(parameter: &str) -> Option<usize> {
+    let bytes = parameter.as_bytes();
+    (0..bytes.len()).find(|&index| {
+        bytes[index] == b':'
+            && bytes.get(index + 1) != Some(&b':')
+            && (index == 0 || bytes[index - 1] != b':')
+    })
+}
+
+fn matching_close(text: &str, open: usize) -> Option<usize> {
+    let mut depth = 0;
+    for (index, c) in text[open..].char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(open + index);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Start of the bracket group that `text` ends with
+fn matching_open(text: &str) -> Option<usize> {
+    let mut depth = 0;
+    for (index, c) in text.char_indices().rev() {
+        match c {
+            '>' | ']' => depth += 1,
+            '<' | '[' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(index);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// `Name<A, B>` as ("Name", ["A", "B"])
+fn generic_arguments(type_name: &str) -> (&str, Vec<&str>) {
+    match (type_name.find('<'), type_name.rfind('>')) {
+        (Some(open), Some(close)) if close > open => (
+            type_name[..open].trim(),
+            split_top_level(&type_name[open + 1..close], ',').into_iter().map(str::trim).collect(),
+        ),
+        _ => (type_name.trim(), Vec::new()),
+    }
+}
+
+fn describe(kind: EdgeCaseKind, parameter: &str, type_name: &str, value: &str) -> String {
+    match kind {
+        EdgeCaseKind::Zero => format!("{} is zero", parameter),
+        EdgeCaseKind::Minimum => format!("{} = {}, the smallest {}; going lower overflows", parameter, value, type_name),
+        EdgeCaseKind::Maximum => format!("{} = {}, the largest {}; going higher overflows", parameter, value, type_name),
+        EdgeCaseKind::NotANumber => format!("{} is NaN", parameter),
+        EdgeCaseKind::Empty => format!("{} is empty", parameter),
+        EdgeCaseKind::SingleElement => format!("{} has a single element", parameter),
+        EdgeCaseKind::Null => format!("{} is {}", parameter, value),
+    }
+}
+
+fn to_snake_case(name: &str) -> String {
+    let mut snake = String::new();
+    for (index, c) in name.chars().enumerate() {
+        if c.is_uppercase() {
+            if index > 0 && !snake.ends_with('_') {
+                snake.push('_');
+            }
+            snake.extend(c.to_lowercase());
+        } else if c.is_alphanumeric() {
+            snake.push(c);
+        } else if !snake.ends_with('_') {
+            snake.push('_');
+        }
+    }
+    snake
+}
+
+/// Argument values of the cases varying `parameter`
+#[cfg(test)]
+fn values(suite: &EdgeCaseSuite, parameter: &str) -> Vec<(EdgeCaseKind, String)> {
+    let index = suite.signature.parameters.iter().position(|p| p.name == parameter).unwrap();
+    suite
+        .cases
+        .iter()
+        .filter(|case| case.parameter == parameter)
+        .map(|case| (case.kind, case.arguments[index].clone()))
+        .collect()
+}
+
+/// Panics unless `code` parses cleanly in `language`
+#[cfg(test)]
+fn assert_parses(language: tree_sitter::Language, code: &str) {
+    let mut parser = tree_sitter::Parser::new();
+    parser.set_language(language).unwrap();
+    let tree = parser.parse(code, None).unwrap();
+    assert!(!tree.root_node().has_error(), "generated code does not parse:\n{}", code);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn generic_parameters_are_resolved_before_classification() {
+        let rust = [SignatureParameter { name: "T".to_string(), type_name: "Into<i64>".to_string() }];
+        assert_eq!(resolve_type_parameters("Vec<T>", &rust, TargetLanguage::Rust), "Vec<i64>");
+        // Only whole identifiers are replaced
+        assert_eq!(resolve_type_parameters("HashMap<Tag, T>", &rust, TargetLanguage::Rust), "HashMap<Tag, i64>");
+
+        let chained = [
+            SignatureParameter { name: "I".to_string(), type_name: "IntoIterator<Item = T>".to_string() },
+            SignatureParameter { name: "T".to_string(), type_name: "Copy".to_string() },
+        ];
+        assert_eq!(resolve_type_parameters("I", &chained, TargetLanguage::Rust), "Vec<i32>");
+    }
+
+    #[test]
+    fn unsupported_languages_and_unparseable_signatures_are_errors() {
+        assert!(generate_edge_cases("fn f(x: i32)", "cobol").is_err());
+        assert!(generate_edge_cases("not a function", "rust").is_err());
+    }
+}
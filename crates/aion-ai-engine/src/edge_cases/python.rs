@@ -0,0 +1,158 @@
+// Python values and pytest stubs
+
+use super::{integer, split_top_level, CollectionShape, EdgeCase, EdgeCaseKind, ParsedSignature, ValueType};
+
+pub(super) fn classify(type_name: &str) -> ValueType {
+    let members: Vec<&str> = split_top_level(type_name, '|').into_iter().map(str::trim).collect();
+    if members.len() > 1 && members.contains(&"None") {
+        let rest: Vec<&str> = members.into_iter().filter(|m| *m != "None").collect();
+        return ValueType::Nullable(Box::new(classify(&rest.join(" | "))));
+    }
+
+    let (base, arguments) = match (type_name.find('['), type_name.rfind(']')) {
+        (Some(open), Some(close)) if close > open => (
+            type_name[..open].trim(),
+            split_top_level(&type_name[open + 1..close], ',').into_iter().map(str::trim).collect(),
+        ),
+        _ => (type_name, Vec::new()),
+    };
+    let base = base.rsplit('.').next().unwrap_or(base);
+    let argument = |index: usize| Box::new(arguments.get(index).map(|a| classify(a)).unwrap_or(ValueType::Other("Any".to_string())));
+    let collection = |shape| ValueType::Collection { shape, type_name: base.to_string(), element: argument(0), value: None };
+
+    match base {
+        "int" => integer("int", None, true),
+        "float" => ValueType::Float { name: "float".to_string() },
+        "bool" => ValueType::Bool,
+        "str" => ValueType::Text { owned: true },
+        "list" | "List" | "Sequence" | "Iterable" => collection(CollectionShape::List),
+        "tuple" | "Tuple" => collection(CollectionShape::Tuple),
+        "set" | "Set" | "frozenset" | "FrozenSet" => collection(CollectionShape::Set),
+        "dict" | "Dict" | "Mapping" => ValueType::Collection {
+            shape: CollectionShape::Map,
+            type_name: base.to_string(),
+            element: argument(0),
+            value: Some(argument(1)),
+        },
+        "Optional" => ValueType::Nullable(argument(0)),
+        "Union" if arguments.contains(&"None") => {
+            let rest: Vec<&str> = arguments.iter().copied().filter(|a| *a != "None").collect();
+            ValueType::Nullable(Box::new(classify(&rest.join(" | "))))
+        }
+        _ => ValueType::Other(type_name.to_string()),
+    }
+}
+
+pub(super) fn typical_value(value_type: &ValueType) -> String {
+    match value_type {
+        ValueType::Integer { .. } => "1".to_string(),
+        ValueType::Float { .. } => "1.0".to_string(),
+        ValueType::Bool => "True".to_string(),
+        ValueType::Text { .. } => "\"a\"".to_string(),
+        ValueType::Collection { .. } => collection_literal(value_type, false),
+        ValueType::Nullable(inner) | ValueType::Reference { inner, .. } => typical_value(inner),
+        ValueType::Other(_) => "None".to_string(),
+    }
+}
+
+pub(super) fn boundary_values(value_type: &ValueType) -> Vec<(EdgeCaseKind, String)> {
+    match value_type {
+        ValueType::Integer { .. } => vec![
+            (EdgeCaseKind::Zero, "0".to_string()),
+            (EdgeCaseKind::Minimum, "-sys.maxsize - 1".to_string()),
+            (EdgeCaseKind::Maximum, "sys.maxsize".to_string()),
+        ],
+        ValueType::Float { .. } => vec![
+            (EdgeCaseKind::Zero, "0.0".to_string()),
+            (EdgeCaseKind::Minimum, "-sys.float_info.max".to_string()),
+            (EdgeCaseKind::Maximum, "sys.float_info.max".to_string()),
+            (EdgeCaseKind::NotANumber, "float(\"nan\")".to_string()),
+        ],
+        ValueType::Bool | ValueType::Other(_) => Vec::new(),
+        ValueType::Text { .. } => vec![(EdgeCaseKind::Empty, "\"\"".to_string())],
+        ValueType::Collection { .. } => vec![
+            (EdgeCaseKind::Empty, collection_literal(value_type, true)),
+            (EdgeCaseKind::SingleElement, collection_literal(value_type, false)),
+        ],
+        ValueType::Nullable(inner) => {
+            let mut values = vec![(EdgeCaseKind::Null, "None".to_string())];
+            values.extend(boundary_values(inner));
+            values
+        }
+        ValueType::Reference { inner, .. } => boundary_values(inner),
+    }
+}
+
+/// Empty or single-element literal of a collection type
+fn collection_literal(value_type: &ValueType, empty: bool) -> String {
+    let ValueType::Collection { shape, element, value, .. } = value_type else {
+        return typical_value(value_type);
+    };
+    let element = typical_value(element);
+    let entry_value = value.as_deref().map(typical_value).unwrap_or_default();
+
+    match shape {
+        CollectionShape::Tuple if empty => "()".to_string(),
+        CollectionShape::Tuple => format!("({},)", element),
+        CollectionShape::Set if empty => "set()".to_string(),
+        CollectionShape::Set => format!("{{{}}}", element),
+        CollectionShape::Map if empty => "{}".to_string(),
+        CollectionShape::Map => format!("{{{}: {}}}", element, entry_value),
+        _ if empty => "[]".to_string(),
+        _ => format!("[{}]", element),
+    }
+}
+
+pub(super) fn render_tests(signature: &ParsedSignature, cases: &[EdgeCase]) -> String {
+    let uses = |needle: &str| cases.iter().any(|case| case.arguments.iter().any(|argument| argument.contains(needle)));
+    let (decorator, keyword, awaited) = if signature.is_async {
+        ("@pytest.mark.asyncio\n", "async def", "await ")
+    } else {
+        ("", "def", "")
+    };
+
+    let mut code = String::new();
+    if uses("sys.") {
+        code.push_str("import sys\n\n");
+    }
+    if signature.is_async {
+        code.push_str("import pytest\n\n");
+    }
+    for case in cases {
+        code.push_str(&format!(
+            "\n{}{} test_{}():\n    # {}\n    result = {}{}({})\n    # TODO: assert the expected result\n\n",
+            decorator,
+            keyword,
+            case.name,
+            case.description,
+            awaited,
+            signature.name,
+            case.arguments.join(", ")
+        ));
+    }
+    code
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::edge_cases::{assert_parses, generate_edge_cases, values};
+    use super::*;
+
+    #[test]
+    fn optional_and_integer_parameters_use_python_limits() {
+        let suite = generate_edge_cases("def page(limit: int, cursor: Optional[str] = None) -> list[Row]:", "python").unwrap();
+        assert_eq!(values(&suite, "limit")[2], (EdgeCaseKind::Maximum, "sys.maxsize".to_string()));
+        assert_eq!(values(&suite, "cursor")[..2], [(EdgeCaseKind::Null, "None".to_string()), (EdgeCaseKind::Empty, "\"\"".to_string())]);
+        assert!(suite.test_code.starts_with("import sys\n"));
+        assert_parses(tree_sitter_python::language(), &suite.test_code);
+    }
+
+    #[test]
+    fn async_functions_get_asyncio_tests() {
+        let suite = generate_edge_cases("async def fetch(keys: dict[str, int], retries: int = 3):", "python").unwrap();
+        assert_eq!(suite.signature.parameters.len(), 2);
+        assert_eq!(values(&suite, "keys")[1], (EdgeCaseKind::SingleElement, "{\"a\": 1}".to_string()));
+        assert!(suite.test_code.contains("@pytest.mark.asyncio\nasync def test_fetch_keys_empty():"));
+        assert_parses(tree_sitter_python::language(), &suite.test_code);
+    }
+}
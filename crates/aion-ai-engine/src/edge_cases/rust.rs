@@ -0,0 +1,290 @@
+// Rust values and cargo test stubs
+
+use super::{find_type_colon, generic_arguments, integer, split_top_level, CollectionShape, EdgeCase, EdgeCaseKind, ParsedSignature, SignatureParameter, ValueType};
+
+pub(super) fn classify(type_name: &str) -> ValueType {
+    if let Some(inner) = type_name.strip_prefix('&') {
+        // Drop a lifetime, e.g. `&'a str`
+        let inner = inner.trim_start();
+        let inner = match inner.strip_prefix('\'') {
+            Some(rest) => rest.split_once(char::is_whitespace).map(|(_, rest)| rest).unwrap_or(rest),
+            None => inner,
+        };
+        let (mutable, inner) = match inner.trim_start().strip_prefix("mut ") {
+            Some(rest) => (true, rest.trim()),
+            None => (false, inner.trim()),
+        };
+        return match classify(inner) {
+            ValueType::Text { .. } if inner == "str" => ValueType::Text { owned: false },
+            inner => ValueType::Reference { mutable, inner: Box::new(inner) },
+        };
+    }
+    // An argument-position `impl Trait` is an anonymous generic parameter
+    if let Some(bounds) = type_name.strip_prefix("impl ") {
+        return classify(&concrete_type(bounds));
+    }
+    if let Some(element) = type_name.strip_prefix('[').and_then(|rest| rest.strip_suffix(']')) {
+        if !element.contains(';') {
+            return ValueType::Collection {
+                shape: CollectionShape::Slice,
+                type_name: type_name.to_string(),
+                element: Box::new(classify(element)),
+                value: None,
+            };
+        }
+    }
+
+    let (base, arguments) = generic_arguments(type_name);
+    let base = base.rsplit("::").next().unwrap_or(base);
+    let argument = |index: usize| Box::new(arguments.get(index).map(|a| classify(a)).unwrap_or(ValueType::Other("_".to_string())));
+
+    match base {
+        "i8" | "i16" | "i32" | "i64" | "i128" => integer(base, base[1..].parse().ok(), true),
+        "u8" | "u16" | "u32" | "u64" | "u128" => integer(base, base[1..].parse().ok(), false),
+        "isize" => integer(base, None, true),
+        "usize" => integer(base, None, false),
+        "f32" | "f64" => ValueType::Float { name: base.to_string() },
+        "bool" => ValueType::Bool,
+        "String" => ValueType::Text { owned: true },
+        "str" => ValueType::Text { owned: false },
+        "Vec" | "VecDeque" => ValueType::Collection {
+            shape: CollectionShape::List,
+            type_name: base.to_string(),
+            element: argument(0),
+            value: None,
+        },
+        "HashSet" | "BTreeSet" => ValueType::Collection {
+            shape: CollectionShape::Set,
+            type_name: base.to_string(),
+            element: argument(0),
+            value: None,
+        },
+        "HashMap" | "BTreeMap" => ValueType::Collection {
+            shape: CollectionShape::Map,
+            type_name: base.to_string(),
+            element: argument(0),
+            value: Some(argument(1)),
+        },
+        "Option" => ValueType::Nullable(argument(0)),
+        _ => ValueType::Other(type_name.to_string()),
+    }
+}
+
+/// A type satisfying `bounds`, e.g. `i64` for `Into<i64>`. Unrecognised
+/// bounds get `i32`, which implements the common derivable traits.
+pub(super) fn concrete_type(bounds: &str) -> String {
+    for bound in split_top_level(bounds, '+') {
+        let (base, arguments) = generic_arguments(bound.trim());
+        let base = base.rsplit("::").next().unwrap_or(base);
+        let argument = arguments.first().copied().unwrap_or("");
+        match base {
+            "Into" if !argument.is_empty() => return argument.to_string(),
+            "AsRef" | "Borrow" if argument == "str" => return "String".to_string(),
+            "AsRef" | "Borrow" if argument == "Path" => return "std::path::PathBuf".to_string(),
+            "AsRef" | "Borrow" => {
+                if let Some(element) = argument.strip_prefix('[').and_then(|rest| rest.strip_suffix(']')) {
+                    return format!("Vec<{}>", element);
+                }
+            }
+            "IntoIterator" => {
+                let item = argument.split_once('=').map(|(_, item)| item.trim()).unwrap_or("i32");
+                return format!("Vec<{}>", item);
+            }
+            _ => {}
+        }
+    }
+    "i32".to_string()
+}
+
+/// `T: Bound, 'a, const N: usize` as type parameters; lifetimes and const
+/// generics are left out
+pub(super) fn parse_type_parameters(list: &str) -> Vec<SignatureParameter> {
+    split_top_level(list, ',')
+        .into_iter()
+        .map(|parameter| split_top_level(parameter, '=')[0].trim())
+        .filter(|parameter| !parameter.is_empty() && !parameter.starts_with('\'') && !parameter.starts_with("const "))
+        .map(|parameter| match find_type_colon(parameter) {
+            Some(colon) => SignatureParameter {
+                name: parameter[..colon].trim().to_string(),
+                type_name: parameter[colon + 1..].trim().to_string(),
+            },
+            None => SignatureParameter { name: parameter.to_string(), type_name: String::new() },
+        })
+        .collect()
+}
+
+/// Merge the bounds of a `where` clause in `tail`, the text after the
+/// parameter list, into `type_parameters`
+pub(super) fn add_where_clause(type_parameters: &mut [SignatureParameter], tail: &str) {
+    let Some((_, clause)) = tail.split_once("where") else {
+        return;
+    };
+    let clause = clause.split('{').next().unwrap_or(clause);
+    for predicate in split_top_level(clause, ',') {
+        let Some(colon) = find_type_colon(predicate) else {
+            continue;
+        };
+        let name = predicate[..colon].trim();
+        let bounds = predicate[colon + 1..].trim();
+        if let Some(parameter) = type_parameters.iter_mut().find(|parameter| parameter.name == name) {
+            parameter.type_name = if parameter.type_name.is_empty() {
+                bounds.to_string()
+            } else {
+                format!("{} + {}", parameter.type_name, bounds)
+            };
+        }
+    }
+}
+
+pub(super) fn typical_value(value_type: &ValueType) -> String {
+    match value_type {
+        ValueType::Integer { .. } => "1".to_string(),
+        ValueType::Float { .. } => "1.0".to_string(),
+        ValueType::Bool => "true".to_string(),
+        ValueType::Text { owned: true } => "\"a\".to_string()".to_string(),
+        ValueType::Text { owned: false } => "\"a\"".to_string(),
+        ValueType::Collection { .. } => collection_literal(value_type, false),
+        ValueType::Nullable(inner) => format!("Some({})", typical_value(inner)),
+        ValueType::Reference { mutable, inner } => reference(*mutable, typical_value(inner)),
+        ValueType::Other(_) => "Default::default()".to_string(),
+    }
+}
+
+pub(super) fn boundary_values(value_type: &ValueType) -> Vec<(EdgeCaseKind, String)> {
+    match value_type {
+        ValueType::Integer { name, signed, .. } => {
+            let mut values = vec![(EdgeCaseKind::Zero, "0".to_string())];
+            // An unsigned minimum is zero, covered above
+            if *signed {
+                values.push((EdgeCaseKind::Minimum, format!("{}::MIN", name)));
+            }
+            values.push((EdgeCaseKind::Maximum, format!("{}::MAX", name)));
+            values
+        }
+        ValueType::Float { name } => vec![
+            (EdgeCaseKind::Zero, "0.0".to_string()),
+            (EdgeCaseKind::Minimum, format!("{}::MIN", name)),
+            (EdgeCaseKind::Maximum, format!("{}::MAX", name)),
+            (EdgeCaseKind::NotANumber, format!("{}::NAN", name)),
+        ],
+        ValueType::Bool | ValueType::Other(_) => Vec::new(),
+        ValueType::Text { owned } => {
+            let empty = if *owned { "String::new()" } else { "\"\"" };
+            vec![(EdgeCaseKind::Empty, empty.to_string())]
+        }
+        ValueType::Collection { .. } => vec![
+            (EdgeCaseKind::Empty, collection_literal(value_type, true)),
+            (EdgeCaseKind::SingleElement, collection_literal(value_type, false)),
+        ],
+        ValueType::Nullable(inner) => {
+            let mut values = vec![(EdgeCaseKind::Null, "None".to_string())];
+            values.extend(boundary_values(inner).into_iter().map(|(kind, value)| (kind, format!("Some({})", value))));
+            values
+        }
+        ValueType::Reference { mutable, inner } => boundary_values(inner)
+            .into_iter()
+            .map(|(kind, value)| (kind, reference(*mutable, value)))
+            .collect(),
+    }
+}
+
+fn reference(mutable: bool, value: String) -> String {
+    if mutable {
+        format!("&mut {}", value)
+    } else {
+        format!("&{}", value)
+    }
+}
+
+/// Empty or single-element literal of a collection type
+fn collection_literal(value_type: &ValueType, empty: bool) -> String {
+    let ValueType::Collection { shape, type_name, element, value } = value_type else {
+        return typical_value(value_type);
+    };
+    let element = typical_value(element);
+    let entry_value = value.as_deref().map(typical_value).unwrap_or_default();
+
+    match shape {
+        CollectionShape::Slice if empty => "[]".to_string(),
+        CollectionShape::Slice => format!("[{}]", element),
+        CollectionShape::List if type_name == "Vec" && !empty => format!("vec![{}]", element),
+        CollectionShape::Map if !empty => format!("{}::from([({}, {})])", type_name, element, entry_value),
+        _ if empty => format!("{}::new()", type_name),
+        _ => format!("{}::from([{}])", type_name, element),
+    }
+}
+
+pub(super) fn render_tests(signature: &ParsedSignature, cases: &[EdgeCase]) -> String {
+    let uses = |needle: &str| cases.iter().any(|case| case.arguments.iter().any(|argument| argument.contains(needle)));
+    let (attribute, keyword, awaited) = if signature.is_async {
+        ("#[tokio::test]", "async fn", ".await")
+    } else {
+        ("#[test]", "fn", "")
+    };
+
+    let mut code = String::from("#[cfg(test)]\nmod edge_case_tests {\n    use super::*;\n");
+    if ["HashMap", "HashSet", "BTreeMap", "BTreeSet", "VecDeque"].iter().any(|collection| uses(collection)) {
+        code.push_str("    use std::collections::*;\n");
+    }
+    for case in cases {
+        code.push_str(&format!(
+            "\n    {}\n    {} {}() {{\n        // {}\n        let _result = {}({}){};\n        // TODO: assert the expected result\n    }}\n",
+            attribute,
+            keyword,
+            case.name,
+            case.description,
+            signature.name,
+            case.arguments.join(", "),
+            awaited
+        ));
+    }
+    code.push_str("}\n");
+    code
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::edge_cases::{assert_parses, generate_edge_cases, values};
+    use super::*;
+
+    #[test]
+    fn integer_and_vec_parameters_get_boundary_cases() {
+        let suite = generate_edge_cases("pub fn rolling_sum<T: Into<i64>>(window: i32, items: Vec<T>) -> i64", "rust").unwrap();
+        assert_eq!(suite.signature.name, "rolling_sum");
+        assert_eq!(suite.signature.type_parameters[0].type_name, "Into<i64>");
+
+        assert_eq!(values(&suite, "window"), vec![
+            (EdgeCaseKind::Zero, "0".to_string()),
+            (EdgeCaseKind::Minimum, "i32::MIN".to_string()),
+            (EdgeCaseKind::Maximum, "i32::MAX".to_string()),
+        ]);
+        // `T` is given a concrete type, so the element type is inferable
+        assert_eq!(values(&suite, "items"), vec![
+            (EdgeCaseKind::Empty, "Vec::new()".to_string()),
+            (EdgeCaseKind::SingleElement, "vec![1]".to_string()),
+        ]);
+
+        // The other parameter keeps an ordinary value
+        let overflow = suite.cases.iter().find(|case| case.name == "rolling_sum_window_max").unwrap();
+        assert_eq!(overflow.arguments, vec!["i32::MAX", "vec![1]"]);
+        assert!(suite.test_code.contains("#[test]\n    fn rolling_sum_items_empty() {"));
+        assert!(suite.test_code.contains("let _result = rolling_sum(1, Vec::new());"));
+        assert_parses(tree_sitter_rust::language(), &suite.test_code);
+    }
+
+    #[test]
+    fn where_clauses_and_impl_trait_resolve_to_concrete_types() {
+        let suite = generate_edge_cases(
+            "async fn index<S, I>(name: S, ids: impl IntoIterator<Item = u32>, tags: &HashSet<I>) where S: AsRef<str>, I: Hash + Eq",
+            "rust",
+        )
+        .unwrap();
+
+        assert_eq!(values(&suite, "name"), vec![(EdgeCaseKind::Empty, "String::new()".to_string())]);
+        assert_eq!(values(&suite, "ids")[1], (EdgeCaseKind::SingleElement, "vec![1]".to_string()));
+        assert_eq!(values(&suite, "tags")[1], (EdgeCaseKind::SingleElement, "&HashSet::from([1])".to_string()));
+        assert!(suite.test_code.contains("#[tokio::test]\n    async fn index_name_empty() {"));
+        assert!(suite.test_code.contains("    use std::collections::*;\n"));
+        assert_parses(tree_sitter_rust::language(), &suite.test_code);
+    }
+}
@@ -0,0 +1,166 @@
+// TypeScript values and Jest stubs
+
+use super::{generic_arguments, integer, split_top_level, CollectionShape, EdgeCase, EdgeCaseKind, ParsedSignature, SignatureParameter, ValueType};
+
+pub(super) fn classify(type_name: &str) -> ValueType {
+    let members: Vec<&str> = split_top_level(type_name, '|').into_iter().map(str::trim).collect();
+    if members.len() > 1 {
+        let non_null: Vec<&str> = members.iter().copied().filter(|m| !matches!(*m, "null" | "undefined")).collect();
+        if non_null.len() < members.len() {
+            return ValueType::Nullable(Box::new(classify(&non_null.join(" | "))));
+        }
+    }
+
+    if let Some(element) = type_name.strip_suffix("[]") {
+        return ValueType::Collection {
+            shape: CollectionShape::List,
+            type_name: type_name.to_string(),
+            element: Box::new(classify(element.trim_start_matches('(').trim_end_matches(')'))),
+            value: None,
+        };
+    }
+
+    let (base, arguments) = generic_arguments(type_name);
+    let argument = |index: usize| Box::new(arguments.get(index).map(|a| classify(a)).unwrap_or(ValueType::Other("any".to_string())));
+    match base {
+        "number" => ValueType::Float { name: "number".to_string() },
+        "bigint" => integer("bigint", None, true),
+        "string" => ValueType::Text { owned: true },
+        "boolean" => ValueType::Bool,
+        "Array" | "ReadonlyArray" => ValueType::Collection {
+            shape: CollectionShape::List,
+            type_name: base.to_string(),
+            element: argument(0),
+            value: None,
+        },
+        "Set" => ValueType::Collection { shape: CollectionShape::Set, type_name: base.to_string(), element: argument(0), value: None },
+        "Map" => ValueType::Collection {
+            shape: CollectionShape::Map,
+            type_name: base.to_string(),
+            element: argument(0),
+            value: Some(argument(1)),
+        },
+        _ => ValueType::Other(type_name.to_string()),
+    }
+}
+
+/// The `extends` constraint itself, or `unknown` for an unconstrained parameter
+pub(super) fn concrete_type(constraint: &str) -> String {
+    if constraint.is_empty() {
+        "unknown".to_string()
+    } else {
+        constraint.to_string()
+    }
+}
+
+/// `T extends Bound = Default` as type parameters
+pub(super) fn parse_type_parameters(list: &str) -> Vec<SignatureParameter> {
+    split_top_level(list, ',')
+        .into_iter()
+        .map(|parameter| split_top_level(parameter, '=')[0].trim())
+        .filter(|parameter| !parameter.is_empty())
+        .map(|parameter| {
+            let (name, constraint) = parameter.split_once(" extends ").unwrap_or((parameter, ""));
+            SignatureParameter { name: name.trim().to_string(), type_name: constraint.trim().to_string() }
+        })
+        .collect()
+}
+
+pub(super) fn typical_value(value_type: &ValueType) -> String {
+    match value_type {
+        ValueType::Integer { .. } => "1n".to_string(),
+        ValueType::Float { .. } => "1".to_string(),
+        ValueType::Bool => "true".to_string(),
+        ValueType::Text { .. } => "\"a\"".to_string(),
+        ValueType::Collection { .. } => collection_literal(value_type, false),
+        ValueType::Nullable(inner) | ValueType::Reference { inner, .. } => typical_value(inner),
+        ValueType::Other(type_name) => format!("{{}} as {}", type_name),
+    }
+}
+
+pub(super) fn boundary_values(value_type: &ValueType) -> Vec<(EdgeCaseKind, String)> {
+    match value_type {
+        // bigint is unbounded
+        ValueType::Integer { .. } => vec![(EdgeCaseKind::Zero, "0n".to_string())],
+        ValueType::Float { .. } => vec![
+            (EdgeCaseKind::Zero, "0".to_string()),
+            (EdgeCaseKind::Minimum, "Number.MIN_SAFE_INTEGER".to_string()),
+            (EdgeCaseKind::Maximum, "Number.MAX_SAFE_INTEGER".to_string()),
+            (EdgeCaseKind::NotANumber, "NaN".to_string()),
+        ],
+        ValueType::Bool | ValueType::Other(_) => Vec::new(),
+        ValueType::Text { .. } => vec![(EdgeCaseKind::Empty, "\"\"".to_string())],
+        ValueType::Collection { .. } => vec![
+            (EdgeCaseKind::Empty, collection_literal(value_type, true)),
+            (EdgeCaseKind::SingleElement, collection_literal(value_type, false)),
+        ],
+        ValueType::Nullable(inner) => {
+            let null = match inner.as_ref() {
+                ValueType::Other(type_name) if type_name == "any" => "undefined",
+                _ => "null",
+            };
+            let mut values = vec![(EdgeCaseKind::Null, null.to_string())];
+            values.extend(boundary_values(inner));
+            values
+        }
+        ValueType::Reference { inner, .. } => boundary_values(inner),
+    }
+}
+
+/// Empty or single-element literal of a collection type
+fn collection_literal(value_type: &ValueType, empty: bool) -> String {
+    let ValueType::Collection { shape, element, value, .. } = value_type else {
+        return typical_value(value_type);
+    };
+    let element = typical_value(element);
+    let entry_value = value.as_deref().map(typical_value).unwrap_or_default();
+
+    match shape {
+        CollectionShape::Set if empty => "new Set()".to_string(),
+        CollectionShape::Set => format!("new Set([{}])", element),
+        CollectionShape::Map if empty => "new Map()".to_string(),
+        CollectionShape::Map => format!("new Map([[{}, {}]])", element, entry_value),
+        _ if empty => "[]".to_string(),
+        _ => format!("[{}]", element),
+    }
+}
+
+pub(super) fn render_tests(signature: &ParsedSignature, cases: &[EdgeCase]) -> String {
+    let (modifier, awaited) = if signature.is_async { ("async ", "await ") } else { ("", "") };
+
+    let mut code = format!("describe(\"{} edge cases\", () => {{\n", signature.name);
+    for case in cases {
+        code.push_str(&format!(
+            "  it(\"{}\", {}() => {{\n    const result = {}{}({});\n    // TODO: expect(result).toEqual(...)\n  }});\n",
+            case.description.replace('"', "\\\""),
+            modifier,
+            awaited,
+            signature.name,
+            case.arguments.join(", ")
+        ));
+    }
+    code.push_str("});\n");
+    code
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::edge_cases::{assert_parses, generate_edge_cases, values};
+    use super::*;
+
+    #[test]
+    fn optional_parameters_are_nullable_and_async_functions_are_awaited() {
+        let suite = generate_edge_cases("export async function score(weights: number[], bias?: number)", "typescript").unwrap();
+        assert_eq!(values(&suite, "bias")[0], (EdgeCaseKind::Null, "null".to_string()));
+        assert!(suite.test_code.contains("async () => {\n    const result = await score([1], null);"));
+        assert_parses(tree_sitter_typescript::language_typescript(), &suite.test_code);
+    }
+
+    #[test]
+    fn type_parameters_are_replaced_by_their_constraint() {
+        let suite = generate_edge_cases("function first<T, K extends string = string>(items: T[], keys: Set<K>): T", "typescript").unwrap();
+        assert_eq!(values(&suite, "items")[1], (EdgeCaseKind::SingleElement, "[{} as unknown]".to_string()));
+        assert_eq!(values(&suite, "keys")[1], (EdgeCaseKind::SingleElement, "new Set([\"a\"])".to_string()));
+        assert_parses(tree_sitter_typescript::language_typescript(), &suite.test_code);
+    }
+}
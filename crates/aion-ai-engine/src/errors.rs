@@ -25,6 +25,9 @@ pub enum AIEngineError {
     #[error("Memory limit exceeded: requested {requested} bytes, limit {limit} bytes")]
     MemoryLimitExceeded { requested: usize, limit: usize },
 
+    #[error("Model {model} is unloading and no longer accepts requests")]
+    ModelUnloading { model: String },
+
     #[error("Concurrent inference limit reached: {limit}")]
     ConcurrencyLimitReached { limit: usize },
 
@@ -37,9 +40,47 @@ pub enum AIEngineError {
     #[error("Postprocessing failed: {reason}")]
     PostprocessingFailed { reason: String },
 
+    #[error("Text does not match the extraction schema: {reason}")]
+    SchemaMismatch { reason: String },
+
+    #[error("Invalid sampling parameter {parameter}: {reason}")]
+    InvalidSamplingParameter { parameter: String, reason: String },
+
     #[error("Configuration error: {field} - {reason}")]
     ConfigurationError { field: String, reason: String },
 
+    #[error("Device error on {device}: {reason}")]
+    DeviceError { device: String, reason: String },
+
+    #[error("Vector dimension mismatch: expected {expected}, got {actual}")]
+    DimensionMismatch { expected: usize, actual: usize },
+
+    #[error("Template not found: {template}")]
+    TemplateNotFound { template: String },
+
+    #[error("Template error in {template} at {line}:{column}: {reason}")]
+    TemplateError {
+        template: String,
+        line: usize,
+        column: usize,
+        reason: String,
+    },
+
+    #[error("Access to model {model} denied (HTTP {status}): {hint}")]
+    ModelAccessDenied { model: String, status: u16, hint: String },
+
+    #[error("Adapter {adapter} is incompatible with base model {base}: {reason}")]
+    AdapterIncompatible { adapter: String, base: String, reason: String },
+
+    #[error("Unsupported quantization {quantization}; supported: {supported}")]
+    UnsupportedQuantization { quantization: String, supported: String },
+
+    #[error("Checksum mismatch for model {model}: expected {expected}, got {actual}")]
+    ChecksumMismatch { model: String, expected: String, actual: String },
+
+    #[error("Estimated cost ${estimated_usd:.4} exceeds the limit of ${limit_usd:.4}")]
+    CostLimitExceeded { estimated_usd: f64, limit_usd: f64 },
+
     #[error("IO error: {0}")]
     Io(#[from] std::io::Error),
 
@@ -163,6 +204,8 @@ pub enum ErrorRecoveryStrategy {
     FallbackModel { fallback_model: String },
     /// Fallback to different backend
     FallbackBackend { fallback_backend: String },
+    /// Retry on a different compute device
+    FallbackDevice { fallback_device: String },
     /// Use cached result if available
     UseCachedResult,
     /// Return default/safe result
@@ -208,6 +251,7 @@ pub mod utils {
             AIEngineError::ModelNotFound { .. } => false,
             AIEngineError::BackendNotAvailable { .. } => true,
             AIEngineError::CacheError { .. } => true,
+            AIEngineError::DeviceError { .. } => true,
             AIEngineError::Io(_) => true,
             AIEngineError::Http(_) => true,
             _ => false,
@@ -239,6 +283,9 @@ pub mod utils {
                 initial_delay_ms: 500,
                 max_delay_ms: 2000,
             }),
+            AIEngineError::DeviceError { .. } => Some(ErrorRecoveryStrategy::FallbackDevice {
+                fallback_device: "cpu".to_string(),
+            }),
             _ => None,
         }
     }
@@ -0,0 +1,316 @@
+//! # Source Formatting
+//!
+//! Formats whole sets of files with each language's standard formatter
+//! (rustfmt, prettier, black, gofmt), several files at a time. Formatters
+//! read the source on stdin and write the result to stdout, so nothing is
+//! written to disk here.
+//!
+//! Formatting never fails an operation: a file with no formatter for its
+//! language, a formatter that is not installed, or one that rejects the file
+//! leaves the file as it was and is noted in the [`FormattingReport`].
+
+use futures::stream::{self, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::Path;
+use std::process::Stdio;
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+use tracing::{debug, warn};
+
+/// Files formatted at once by default
+const DEFAULT_CONCURRENCY: usize = 8;
+
+/// A formatter gets this long per file by default
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// An external formatter that reads stdin and writes stdout
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Formatter {
+    pub name: String,
+    pub program: String,
+    /// `{path}` is replaced with the file's path, for formatters that infer
+    /// settings from it
+    pub args: Vec<String>,
+    /// Extensions handled, without the dot
+    pub extensions: Vec<String>,
+}
+
+impl Formatter {
+    pub fn new(name: impl Into<String>, program: impl Into<String>, args: &[&str], extensions: &[&str]) -> Self {
+        Self {
+            name: name.into(),
+            program: program.into(),
+            args: args.iter().map(|arg| arg.to_string()).collect(),
+            extensions: extensions.iter().map(|ext| ext.to_string()).collect(),
+        }
+    }
+
+    pub fn rustfmt() -> Self {
+        Self::new("rustfmt", "rustfmt", &["--edition", "2021", "--emit", "stdout", "--quiet"], &["rs"])
+    }
+
+    pub fn prettier() -> Self {
+        Self::new(
+            "prettier",
+            "prettier",
+            &["--stdin-filepath", "{path}"],
+            &["js", "jsx", "mjs", "cjs", "ts", "tsx", "json", "css", "scss", "html", "vue", "yaml", "yml", "md"],
+        )
+    }
+
+    pub fn black() -> Self {
+        Self::new("black", "black", &["--quiet", "-"], &["py", "pyi"])
+    }
+
+    pub fn gofmt() -> Self {
+        Self::new("gofmt", "gofmt", &[], &["go"])
+    }
+
+    fn handles(&self, path: &str) -> bool {
+        let extension = Path::new(path).extension().and_then(|ext| ext.to_str()).unwrap_or("");
+        self.extensions.iter().any(|ext| ext.eq_ignore_ascii_case(extension))
+    }
+}
+
+/// What formatting did to one file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum FormatStatus {
+    Changed { formatter: String },
+    Unchanged { formatter: String },
+    /// No formatter handles this file type
+    Unsupported,
+    /// The formatter is not installed
+    Unavailable { formatter: String },
+    /// The formatter rejected the file or timed out
+    Failed { formatter: String, error: String },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileFormatResult {
+    pub path: String,
+    pub status: FormatStatus,
+}
+
+/// Outcome of formatting a set of files, in input order
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FormattingReport {
+    pub files: Vec<FileFormatResult>,
+}
+
+impl FormattingReport {
+    /// Paths whose content the formatter changed
+    pub fn changed(&self) -> impl Iterator<Item = &str> {
+        self.files
+            .iter()
+            .filter(|file| matches!(file.status, FormatStatus::Changed { .. }))
+            .map(|file| file.path.as_str())
+    }
+
+    /// Files left unformatted because their formatter was missing or failed
+    pub fn unformatted(&self) -> impl Iterator<Item = &FileFormatResult> {
+        self.files
+            .iter()
+            .filter(|file| matches!(file.status, FormatStatus::Unavailable { .. } | FormatStatus::Failed { .. }))
+    }
+}
+
+/// Formats files with the formatter registered for their extension
+pub struct FormattingService {
+    formatters: Vec<Formatter>,
+    concurrency: usize,
+    timeout: Duration,
+    /// Formatters found missing, so each is warned about and probed once
+    unavailable: Mutex<HashSet<String>>,
+}
+
+impl Default for FormattingService {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FormattingService {
+    /// Service with rustfmt, prettier, black and gofmt
+    pub fn new() -> Self {
+        Self::with_formatters(vec![Formatter::rustfmt(), Formatter::prettier(), Formatter::black(), Formatter::gofmt()])
+    }
+
+    pub fn with_formatters(formatters: Vec<Formatter>) -> Self {
+        Self {
+            formatters,
+            concurrency: DEFAULT_CONCURRENCY,
+            timeout: DEFAULT_TIMEOUT,
+            unavailable: Mutex::new(HashSet::new()),
+        }
+    }
+
+    /// Register a formatter, taking precedence over earlier ones for the same
+    /// extensions
+    pub fn with_formatter(mut self, formatter: Formatter) -> Self {
+        self.formatters.insert(0, formatter);
+        self
+    }
+
+    pub fn with_concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn formatter_for(&self, path: &str) -> Option<&Formatter> {
+        self.formatters.iter().find(|formatter| formatter.handles(path))
+    }
+
+    /// Format one file's source; on any failure the source is returned as is
+    pub async fn format_source(&self, path: &str, source: &str) -> (String, FormatStatus) {
+        let Some(formatter) = self.formatter_for(path) else {
+            return (source.to_string(), FormatStatus::Unsupported);
+        };
+        if self.unavailable.lock().unwrap().contains(&formatter.name) {
+            return (source.to_string(), FormatStatus::Unavailable { formatter: formatter.name.clone() });
+        }
+
+        match self.run(formatter, path, source).await {
+            Ok(formatted) if formatted == source => (formatted, FormatStatus::Unchanged { formatter: formatter.name.clone() }),
+            Ok(formatted) => (formatted, FormatStatus::Changed { formatter: formatter.name.clone() }),
+            Err(RunError::NotInstalled) => {
+                if self.unavailable.lock().unwrap().insert(formatter.name.clone()) {
+                    warn!("{} is not installed; files it formats are left unformatted", formatter.program);
+                }
+                (source.to_string(), FormatStatus::Unavailable { formatter: formatter.name.clone() })
+            }
+            Err(RunError::Failed(error)) => {
+                warn!("{} could not format {}: {}", formatter.name, path, error);
+                (source.to_string(), FormatStatus::Failed { formatter: formatter.name.clone(), error })
+            }
+        }
+    }
+
+    /// Format files in place, in parallel
+    pub async fn format_files<'a, I>(&self, files: I) -> FormattingReport
+    where
+        I: IntoIterator<Item = (&'a str, &'a mut String)>,
+    {
+        let mut results: Vec<(usize, FileFormatResult)> = stream::iter(files.into_iter().enumerate())
+            .map(|(index, (path, content))| async move {
+                let (formatted, status) = self.format_source(path, content).await;
+                *content = formatted;
+                (index, FileFormatResult { path: path.to_string(), status })
+            })
+            .buffer_unordered(self.concurrency)
+            .collect()
+            .await;
+        results.sort_by_key(|(index, _)| *index);
+
+        let report = FormattingReport {
+            files: results.into_iter().map(|(_, result)| result).collect(),
+        };
+        debug!(
+            "Formatted {} files: {} changed, {} left unformatted",
+            report.files.len(),
+            report.changed().count(),
+            report.unformatted().count()
+        );
+        report
+    }
+
+    async fn run(&self, formatter: &Formatter, path: &str, source: &str) -> Result<String, RunError> {
+        let mut child = Command::new(&formatter.program)
+            .args(formatter.args.iter().map(|arg| arg.replace("{path}", path)))
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|e| match e.kind() {
+                std::io::ErrorKind::NotFound => RunError::NotInstalled,
+                _ => RunError::Failed(e.to_string()),
+            })?;
+
+        // Write concurrently with reading so a large file cannot fill both pipes
+        let mut stdin = child.stdin.take().expect("stdin is piped");
+        let input = source.to_string();
+        let writer = tokio::spawn(async move { stdin.write_all(input.as_bytes()).await });
+
+        let output = tokio::time::timeout(self.timeout, child.wait_with_output())
+            .await
+            .map_err(|_| RunError::Failed(format!("timed out after {:?}", self.timeout)))?
+            .map_err(|e| RunError::Failed(e.to_string()))?;
+        // A formatter may exit without reading all input; its exit status says whether that was an error
+        let _ = writer.await;
+
+        if !output.status.success() {
+            return Err(RunError::Failed(format!(
+                "exited with {}: {}",
+                output.status,
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+        String::from_utf8(output.stdout).map_err(|_| RunError::Failed("output is not UTF-8".to_string()))
+    }
+}
+
+enum RunError {
+    NotInstalled,
+    Failed(String),
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    /// Upper-cases text files; idempotent like a real formatter
+    fn service() -> FormattingService {
+        FormattingService::with_formatters(vec![
+            Formatter::new("upper", "tr", &["a-z", "A-Z"], &["txt"]),
+            Formatter::new("missing", "aion-no-such-formatter", &[], &["md"]),
+            Formatter::new("broken", "sh", &["-c", "echo 'syntax error' >&2; exit 1"], &["bad"]),
+        ])
+    }
+
+    #[tokio::test]
+    async fn formats_in_place_and_reports_changes() {
+        let service = service();
+        let mut files = vec![
+            ("a.txt".to_string(), "hello".to_string()),
+            ("b.txt".to_string(), "ALREADY".to_string()),
+            ("c.md".to_string(), "# title".to_string()),
+            ("d.bad".to_string(), "x".to_string()),
+            ("e.bin".to_string(), "raw".to_string()),
+        ];
+
+        let report = service.format_files(files.iter_mut().map(|(path, content)| (path.as_str(), content))).await;
+
+        assert_eq!(files[0].1, "HELLO");
+        assert_eq!(report.changed().collect::<Vec<_>>(), vec!["a.txt"]);
+        assert_eq!(report.files[1].status, FormatStatus::Unchanged { formatter: "upper".to_string() });
+        // Missing and failing formatters leave the file untouched
+        assert_eq!(files[2].1, "# title");
+        assert_eq!(report.files[2].status, FormatStatus::Unavailable { formatter: "missing".to_string() });
+        assert_eq!(files[3].1, "x");
+        assert!(matches!(&report.files[3].status, FormatStatus::Failed { error, .. } if error.contains("syntax error")));
+        assert_eq!(report.files[4].status, FormatStatus::Unsupported);
+        assert_eq!(report.unformatted().count(), 2);
+    }
+
+    #[tokio::test]
+    async fn formatting_twice_changes_nothing() {
+        let service = service();
+        let mut files = vec![("a.txt".to_string(), "mixed Case".to_string())];
+
+        service.format_files(files.iter_mut().map(|(path, content)| (path.as_str(), content))).await;
+        let once = files[0].1.clone();
+        let report = service.format_files(files.iter_mut().map(|(path, content)| (path.as_str(), content))).await;
+
+        assert_eq!(files[0].1, once);
+        assert_eq!(report.changed().count(), 0);
+    }
+}
@@ -0,0 +1,893 @@
+//! # Token Generation
+//!
+//! Autoregressive sampling loop shared by the local backends.
+//!
+//! Each step takes the model's next-token logits, applies the configured
+//! penalties (repetition, frequency, presence, no-repeat n-grams), samples a
+//! token and checks stop sequences against the detokenized text. Stop sequences
+//! are matched on the decoded string rather than on token ids, so a stop marker
+//! split across several tokens is still caught, and the marker itself is never
+//! part of the returned text.
+//!
+//! ## Sampling order
+//!
+//! Logits pass through the stages below in this fixed order, so the same
+//! parameters always describe the same distribution:
+//!
+//! 1. penalties (repetition, frequency, presence, no-repeat n-grams)
+//! 2. temperature, then softmax
+//! 3. top-k: keep the `top_k` most likely tokens
+//! 4. typical: keep the tokens whose surprise is closest to the entropy, up
+//!    to `typical_p` of the mass
+//! 5. top-p: keep the most likely tokens up to `top_p` of the mass
+//! 6. min-p: drop tokens below `min_p` times the top probability
+//! 7. draw from what is left, renormalized
+//!
+//! Every filter renormalizes before the next one runs and always keeps at
+//! least one token. Greedy decoding (or temperature 0) skips all of it and
+//! takes the argmax. With `mirostat` set, stages 3 to 6 are replaced by
+//! Mirostat 2.0, which truncates tokens more surprising than a running target
+//! and adjusts that target after each token so the output's perplexity stays
+//! near `tau`.
+//!
+//! ## Reproducibility
+//!
+//! With `seed` set, the sampler's random stream is fixed, so the same prompt,
+//! parameters and logits always produce the same tokens. Greedy decoding uses
+//! no randomness at all. What a seed cannot pin down is the logits themselves:
+//! GPU kernels may sum in a different order between runs (atomics, split-K
+//! matmuls, cuDNN algorithm selection), batch composition changes padding and
+//! therefore reductions, and different hardware or library versions round
+//! differently. A near-tie between two tokens can then flip. Byte-identical
+//! output is therefore only guaranteed on CPU, or on the same GPU with
+//! deterministic kernels and an unbatched request.
+
+use crate::errors::{AIEngineError, AIResult};
+use crate::inference::InferenceParameters;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+/// Source of next-token logits
+pub trait TokenModel {
+    /// Logits over the vocabulary for the token following `context`
+    fn next_token_logits(&mut self, context: &[u32]) -> Result<Vec<f32>>;
+
+    /// End-of-sequence token, if the model has one
+    fn eos_token(&self) -> Option<u32> {
+        None
+    }
+}
+
+/// Turns token ids back into text
+pub trait TokenDecoder {
+    fn decode(&self, tokens: &[u32]) -> String;
+}
+
+/// A Candle language model driven by the sampling loop, so `seed` and the
+/// other sampling parameters apply to it like to any other backend
+///
+/// `forward(input_ids, index_pos)` is the model's forward pass over the new
+/// tokens at positions from `index_pos`, as in `candle_transformers`' models
+/// with a KV cache; it returns logits for the last position, shaped
+/// `[vocab]`, `[1, vocab]` or `[1, seq, vocab]`.
+#[cfg(feature = "candle")]
+pub struct CandleTokenModel<F> {
+    forward: F,
+    device: candle_core::Device,
+    eos_token: Option<u32>,
+    /// Tokens already fed to the model and held in its cache
+    processed: usize,
+}
+
+#[cfg(feature = "candle")]
+impl<F> CandleTokenModel<F>
+where
+    F: FnMut(&candle_core::Tensor, usize) -> candle_core::Result<candle_core::Tensor>,
+{
+    pub fn new(forward: F, device: candle_core::Device, eos_token: Option<u32>) -> Self {
+        Self { forward, device, eos_token, processed: 0 }
+    }
+}
+
+#[cfg(feature = "candle")]
+impl<F> TokenModel for CandleTokenModel<F>
+where
+    F: FnMut(&candle_core::Tensor, usize) -> candle_core::Result<candle_core::Tensor>,
+{
+    fn next_token_logits(&mut self, context: &[u32]) -> Result<Vec<f32>> {
+        let input = candle_core::Tensor::new(&context[self.processed..], &self.device)?.unsqueeze(0)?;
+        let mut logits = (self.forward)(&input, self.processed)?;
+        self.processed = context.len();
+
+        while logits.rank() > 1 {
+            let rows = logits.dim(0)?;
+            logits = logits.get(rows - 1)?;
+        }
+        Ok(logits.to_dtype(candle_core::DType::F32)?.to_vec1::<f32>()?)
+    }
+
+    fn eos_token(&self) -> Option<u32> {
+        self.eos_token
+    }
+}
+
+/// Sampling controls derived from `InferenceParameters`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SamplingConfig {
+    pub max_new_tokens: usize,
+    pub temperature: f32,
+    pub top_p: f32,
+    /// Keep only the k most likely tokens; 0 disables
+    #[serde(default)]
+    pub top_k: usize,
+    /// Minimum probability relative to the most likely token; 0.0 disables
+    #[serde(default)]
+    pub min_p: f32,
+    /// Mass kept by locally typical sampling; 1.0 disables
+    #[serde(default = "disabled_typical_p")]
+    pub typical_p: f32,
+    /// Replaces top-k, typical, top-p and min-p when set
+    #[serde(default)]
+    pub mirostat: Option<MirostatConfig>,
+    pub stop_sequences: Vec<String>,
+    /// Divides positive (multiplies negative) logits of tokens already in the context; 1.0 disables
+    pub repetition_penalty: f32,
+    /// Subtracted once per previous occurrence in the generated text
+    pub frequency_penalty: f32,
+    /// Subtracted once if the token occurred in the generated text at all
+    pub presence_penalty: f32,
+    /// Forbid repeating any n-gram of this size; 0 disables
+    pub no_repeat_ngram_size: usize,
+    /// Fixed seed for the sampler's random stream
+    pub seed: Option<u64>,
+    /// Argmax decoding regardless of temperature
+    pub greedy: bool,
+}
+
+impl From<&InferenceParameters> for SamplingConfig {
+    fn from(parameters: &InferenceParameters) -> Self {
+        Self {
+            max_new_tokens: parameters.max_length.unwrap_or(512),
+            temperature: parameters.temperature.unwrap_or(1.0),
+            top_p: parameters.top_p.unwrap_or(1.0),
+            top_k: parameters.top_k.unwrap_or(0),
+            min_p: parameters.min_p.unwrap_or(0.0),
+            typical_p: parameters.typical_p.unwrap_or(1.0),
+            mirostat: parameters.mirostat,
+            stop_sequences: parameters.stop_sequences.iter().filter(|s| !s.is_empty()).cloned().collect(),
+            repetition_penalty: parameters.repetition_penalty.unwrap_or(1.0),
+            frequency_penalty: parameters.frequency_penalty.unwrap_or(0.0),
+            presence_penalty: parameters.presence_penalty.unwrap_or(0.0),
+            no_repeat_ngram_size: parameters.no_repeat_ngram_size.unwrap_or(0),
+            seed: parameters.seed,
+            greedy: parameters.greedy,
+        }
+    }
+}
+
+impl SamplingConfig {
+    /// Reject values outside each parameter's range
+    pub fn validate(&self) -> AIResult<()> {
+        let invalid = |parameter: &str, reason: String| {
+            Err(AIEngineError::InvalidSamplingParameter { parameter: parameter.to_string(), reason })
+        };
+
+        if !(self.temperature.is_finite() && self.temperature >= 0.0) {
+            return invalid("temperature", format!("must be a finite number >= 0, got {}", self.temperature));
+        }
+        if !(self.top_p > 0.0 && self.top_p <= 1.0) {
+            return invalid("top_p", format!("must be in (0, 1], got {}", self.top_p));
+        }
+        if !(0.0..=1.0).contains(&self.min_p) {
+            return invalid("min_p", format!("must be in [0, 1], got {}", self.min_p));
+        }
+        if !(self.typical_p > 0.0 && self.typical_p <= 1.0) {
+            return invalid("typical_p", format!("must be in (0, 1], got {}", self.typical_p));
+        }
+        if !(self.repetition_penalty.is_finite() && self.repetition_penalty > 0.0) {
+            return invalid("repetition_penalty", format!("must be > 0, got {}", self.repetition_penalty));
+        }
+        if let Some(mirostat) = &self.mirostat {
+            if !(mirostat.tau.is_finite() && mirostat.tau > 0.0) {
+                return invalid("mirostat.tau", format!("must be > 0, got {}", mirostat.tau));
+            }
+            if !(mirostat.eta > 0.0 && mirostat.eta <= 1.0) {
+                return invalid("mirostat.eta", format!("must be in (0, 1], got {}", mirostat.eta));
+            }
+        }
+        Ok(())
+    }
+}
+
+fn disabled_typical_p() -> f32 {
+    1.0
+}
+
+/// Mirostat 2.0 settings
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct MirostatConfig {
+    /// Target surprise in bits per token; lower is more focused
+    #[serde(default = "MirostatConfig::default_tau")]
+    pub tau: f32,
+    /// How quickly the truncation threshold follows the target
+    #[serde(default = "MirostatConfig::default_eta")]
+    pub eta: f32,
+}
+
+impl MirostatConfig {
+    fn default_tau() -> f32 {
+        5.0
+    }
+
+    fn default_eta() -> f32 {
+        0.1
+    }
+}
+
+impl Default for MirostatConfig {
+    fn default() -> Self {
+        Self { tau: Self::default_tau(), eta: Self::default_eta() }
+    }
+}
+
+/// Running state of Mirostat across the tokens of one generation
+#[derive(Debug, Clone)]
+pub struct MirostatState {
+    config: MirostatConfig,
+    /// Current maximum surprise in bits, `mu` in the paper
+    max_surprise: f32,
+}
+
+impl MirostatState {
+    pub fn new(config: MirostatConfig) -> Self {
+        Self { config, max_surprise: 2.0 * config.tau }
+    }
+
+    /// Pick the next token and move the threshold toward the target surprise
+    pub fn sample(&mut self, logits: &[f32], config: &SamplingConfig, rng: &mut SamplerRng) -> u32 {
+        if config.greedy || config.temperature <= 0.0 {
+            return argmax(logits);
+        }
+        let Some(mut candidates) = softmax_candidates(logits, config.temperature) else {
+            return argmax(logits);
+        };
+
+        let keep = candidates.iter().take_while(|(_, p)| -p.log2() <= self.max_surprise).count();
+        truncate(&mut candidates, keep);
+        let Some((token, probability)) = draw(&candidates, rng) else {
+            return argmax(logits);
+        };
+
+        let surprise = -probability.log2();
+        self.max_surprise -= self.config.eta * (surprise - self.config.tau);
+        token
+    }
+}
+
+/// Why generation ended
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FinishReason {
+    /// A stop sequence was produced
+    Stop(String),
+    /// The model emitted its end-of-sequence token
+    EndOfSequence,
+    /// `max_new_tokens` was reached
+    Length,
+}
+
+/// Result of a generation run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GenerationOutput {
+    /// Generated text with any stop sequence trimmed
+    pub text: String,
+    /// Generated token ids, including the tokens that formed the stop sequence
+    pub tokens: Vec<u32>,
+    pub finish_reason: FinishReason,
+}
+
+/// Run the sampling loop until a stop condition is met
+pub fn generate<M: TokenModel, D: TokenDecoder>(
+    model: &mut M,
+    decoder: &D,
+    prompt: &[u32],
+    config: &SamplingConfig,
+) -> Result<GenerationOutput> {
+    config.validate()?;
+    let mut mirostat = config.mirostat.map(MirostatState::new);
+    let mut rng = match config.seed {
+        Some(seed) => SamplerRng::seeded(seed),
+        None => SamplerRng::from_entropy(),
+    };
+    let mut context = prompt.to_vec();
+    let mut generated: Vec<u32> = Vec::new();
+    let mut counts: HashMap<u32, usize> = HashMap::new();
+    let longest_stop = config.stop_sequences.iter().map(String::len).max().unwrap_or(0);
+    let mut checked_len: usize = 0;
+
+    while generated.len() < config.max_new_tokens {
+        let mut logits = model.next_token_logits(&context)?;
+        apply_penalties(&mut logits, &context, &counts, config);
+        let token = match mirostat.as_mut() {
+            Some(mirostat) => mirostat.sample(&logits, config, &mut rng),
+            None => sample(&logits, config, &mut rng),
+        };
+
+        if model.eos_token() == Some(token) {
+            return Ok(GenerationOutput {
+                text: decoder.decode(&generated),
+                tokens: generated,
+                finish_reason: FinishReason::EndOfSequence,
+            });
+        }
+
+        context.push(token);
+        generated.push(token);
+        *counts.entry(token).or_insert(0) += 1;
+
+        // Decode the whole continuation so multi-byte characters and stop markers
+        // spanning token boundaries are seen exactly as the caller will see them
+        let text = decoder.decode(&generated);
+        if longest_stop > 0 {
+            let search_from = floor_char_boundary(&text, checked_len.saturating_sub(longest_stop));
+            if let Some((index, stop)) = find_stop(&text, search_from, &config.stop_sequences) {
+                return Ok(GenerationOutput {
+                    text: text[..index].to_string(),
+                    tokens: generated,
+                    finish_reason: FinishReason::Stop(stop),
+                });
+            }
+        }
+        checked_len = text.len();
+    }
+
+    Ok(GenerationOutput {
+        text: decoder.decode(&generated),
+        tokens: generated,
+        finish_reason: FinishReason::Length,
+    })
+}
+
+/// Cut `text` at the earliest stop sequence, if any
+pub fn truncate_at_stop(text: &str, stop_sequences: &[String]) -> (String, Option<String>) {
+    match find_stop(text, 0, stop_sequences) {
+        Some((index, stop)) => (text[..index].to_string(), Some(stop)),
+        None => (text.to_string(), None),
+    }
+}
+
+/// Earliest occurrence of any stop sequence starting at or after `from`
+fn find_stop(text: &str, from: usize, stop_sequences: &[String]) -> Option<(usize, String)> {
+    stop_sequences
+        .iter()
+        .filter(|stop| !stop.is_empty())
+        .filter_map(|stop| text[from..].find(stop.as_str()).map(|i| (from + i, stop.clone())))
+        .min_by_key(|(index, _)| *index)
+}
+
+fn floor_char_boundary(text: &str, index: usize) -> usize {
+    let mut index = index.min(text.len());
+    while index > 0 && !text.is_char_boundary(index) {
+        index -= 1;
+    }
+    index
+}
+
+/// Apply repetition, frequency, presence and n-gram penalties in place
+pub fn apply_penalties(logits: &mut [f32], context: &[u32], generated_counts: &HashMap<u32, usize>, config: &SamplingConfig) {
+    if config.repetition_penalty != 1.0 && config.repetition_penalty > 0.0 {
+        let seen: HashSet<u32> = context.iter().copied().collect();
+        for token in seen {
+            if let Some(logit) = logits.get_mut(token as usize) {
+                *logit = if *logit > 0.0 {
+                    *logit / config.repetition_penalty
+                } else {
+                    *logit * config.repetition_penalty
+                };
+            }
+        }
+    }
+
+    if config.frequency_penalty != 0.0 || config.presence_penalty != 0.0 {
+        for (&token, &count) in generated_counts {
+            if let Some(logit) = logits.get_mut(token as usize) {
+                *logit -= config.frequency_penalty * count as f32 + config.presence_penalty;
+            }
+        }
+    }
+
+    for token in banned_ngram_tokens(context, config.no_repeat_ngram_size) {
+        if let Some(logit) = logits.get_mut(token as usize) {
+            *logit = f32::NEG_INFINITY;
+        }
+    }
+}
+
+/// Tokens that would complete an n-gram already present in `context`
+fn banned_ngram_tokens(context: &[u32], n: usize) -> Vec<u32> {
+    if n == 0 || context.len() + 1 < n {
+        return Vec::new();
+    }
+    if n == 1 {
+        return context.to_vec();
+    }
+    let prefix = &context[context.len() + 1 - n..];
+    context
+        .windows(n)
+        .filter(|window| &window[..n - 1] == prefix)
+        .map(|window| window[n - 1])
+        .collect()
+}
+
+/// Pick the next token: argmax at temperature 0, otherwise a draw from the
+/// candidates left by the filters, in the order given in the module docs
+pub fn sample(logits: &[f32], config: &SamplingConfig, rng: &mut SamplerRng) -> u32 {
+    if config.greedy || config.temperature <= 0.0 {
+        return argmax(logits);
+    }
+    filtered_candidates(logits, config)
+        .and_then(|candidates| draw(&candidates, rng))
+        .map(|(token, _)| token)
+        .unwrap_or_else(|| argmax(logits))
+}
+
+/// Tokens that may be sampled and their renormalized probabilities, most
+/// likely first; `None` when no logit is finite
+pub fn filtered_candidates(logits: &[f32], config: &SamplingConfig) -> Option<Vec<(u32, f32)>> {
+    let mut candidates = softmax_candidates(logits, config.temperature)?;
+    if config.top_k > 0 {
+        truncate(&mut candidates, config.top_k);
+    }
+    if config.typical_p < 1.0 {
+        apply_typical(&mut candidates, config.typical_p);
+    }
+    if config.top_p < 1.0 {
+        let keep = mass_cutoff(candidates.iter().map(|(_, p)| *p), config.top_p);
+        truncate(&mut candidates, keep);
+    }
+    if config.min_p > 0.0 {
+        let threshold = candidates[0].1 * config.min_p;
+        let keep = candidates.iter().take_while(|(_, p)| *p >= threshold).count();
+        truncate(&mut candidates, keep);
+    }
+    Some(candidates)
+}
+
+/// Softmax of the finite logits at `temperature`, most likely first
+fn softmax_candidates(logits: &[f32], temperature: f32) -> Option<Vec<(u32, f32)>> {
+    let max = logits.iter().copied().filter(|l| l.is_finite()).fold(f32::NEG_INFINITY, f32::max);
+    if !max.is_finite() {
+        return None;
+    }
+    let mut candidates: Vec<(u32, f32)> = logits
+        .iter()
+        .enumerate()
+        .filter(|(_, l)| l.is_finite())
+        .map(|(i, l)| (i as u32, ((l - max) / temperature).exp()))
+        .collect();
+    // Ties are broken by token id so the candidate order never depends on the sort algorithm
+    sort_by_probability(&mut candidates);
+    normalize(&mut candidates);
+    Some(candidates)
+}
+
+/// Keep the tokens whose surprise is closest to the distribution's entropy
+/// until they hold `typical_p` of the mass
+fn apply_typical(candidates: &mut Vec<(u32, f32)>, typical_p: f32) {
+    let entropy: f32 = candidates.iter().filter(|(_, p)| *p > 0.0).map(|(_, p)| -p * p.ln()).sum();
+    let distance = |p: f32| (-p.ln() - entropy).abs();
+    candidates.sort_by(|a, b| distance(a.1).total_cmp(&distance(b.1)).then(a.0.cmp(&b.0)));
+    let keep = mass_cutoff(candidates.iter().map(|(_, p)| *p), typical_p);
+    candidates.truncate(keep.max(1));
+    sort_by_probability(candidates);
+    normalize(candidates);
+}
+
+/// Length of the shortest prefix holding at least `mass` of the probability
+fn mass_cutoff(probabilities: impl Iterator<Item = f32>, mass: f32) -> usize {
+    let mut cumulative = 0.0;
+    let mut count = 0;
+    for p in probabilities {
+        cumulative += p;
+        count += 1;
+        if cumulative >= mass {
+            break;
+        }
+    }
+    count
+}
+
+/// Keep the first `keep` candidates (at least one) and renormalize
+fn truncate(candidates: &mut Vec<(u32, f32)>, keep: usize) {
+    candidates.truncate(keep.max(1));
+    normalize(candidates);
+}
+
+fn sort_by_probability(candidates: &mut [(u32, f32)]) {
+    candidates.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0)));
+}
+
+fn normalize(candidates: &mut [(u32, f32)]) {
+    let total: f32 = candidates.iter().map(|(_, p)| p).sum();
+    if total > 0.0 {
+        for (_, p) in candidates.iter_mut() {
+            *p /= total;
+        }
+    }
+}
+
+/// Draw one candidate in proportion to its probability
+fn draw(candidates: &[(u32, f32)], rng: &mut SamplerRng) -> Option<(u32, f32)> {
+    let mut target = rng.next_f32();
+    for &(token, p) in candidates {
+        if target < p {
+            return Some((token, p));
+        }
+        target -= p;
+    }
+    candidates.last().copied()
+}
+
+fn argmax(logits: &[f32]) -> u32 {
+    logits
+        .iter()
+        .enumerate()
+        .fold((0usize, f32::NEG_INFINITY), |best, (i, &l)| if l > best.1 { (i, l) } else { best })
+        .0 as u32
+}
+
+/// Small xorshift generator; sampling doesn't need cryptographic randomness
+#[derive(Debug, Clone)]
+pub struct SamplerRng {
+    state: u64,
+}
+
+impl SamplerRng {
+    pub fn seeded(seed: u64) -> Self {
+        Self { state: splitmix64(seed) | 1 }
+    }
+
+    pub fn from_entropy() -> Self {
+        let nanos = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_nanos() as u64)
+            .unwrap_or(0x9E37_79B9_7F4A_7C15);
+        Self { state: splitmix64(nanos ^ (std::process::id() as u64).rotate_left(32)) | 1 }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        let mut x = self.state;
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        self.state = x;
+        x
+    }
+
+    /// Uniform in [0, 1)
+    pub fn next_f32(&mut self) -> f32 {
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+}
+
+fn splitmix64(seed: u64) -> u64 {
+    let mut z = seed.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VOCAB: [&str; 7] = ["Hello", " world", "<|", "end", "|>", " more", " text"];
+
+    /// Plays back a fixed token script, strongly preferring the next scripted token
+    struct ScriptedModel {
+        prompt_len: usize,
+        script: Vec<u32>,
+    }
+
+    impl TokenModel for ScriptedModel {
+        fn next_token_logits(&mut self, context: &[u32]) -> Result<Vec<f32>> {
+            let step = context.len() - self.prompt_len;
+            let mut logits = vec![0.0; VOCAB.len()];
+            if let Some(&token) = self.script.get(step) {
+                logits[token as usize] = 10.0;
+            }
+            Ok(logits)
+        }
+    }
+
+    struct VocabDecoder;
+
+    impl TokenDecoder for VocabDecoder {
+        fn decode(&self, tokens: &[u32]) -> String {
+            tokens.iter().map(|t| VOCAB[*t as usize]).collect()
+        }
+    }
+
+    fn greedy_config() -> SamplingConfig {
+        SamplingConfig::from(&InferenceParameters {
+            temperature: Some(0.0),
+            max_length: Some(16),
+            ..InferenceParameters::default()
+        })
+    }
+
+    #[test]
+    fn multi_token_stop_sequence_halts_generation() {
+        let mut model = ScriptedModel { prompt_len: 1, script: vec![0, 1, 2, 3, 4, 5, 6] };
+        let config = SamplingConfig { stop_sequences: vec!["<|end|>".to_string()], ..greedy_config() };
+
+        let output = generate(&mut model, &VocabDecoder, &[0], &config).unwrap();
+
+        assert_eq!(output.text, "Hello world");
+        assert_eq!(output.finish_reason, FinishReason::Stop("<|end|>".to_string()));
+        // Generation stopped on the token completing the marker, not after it
+        assert_eq!(output.tokens, vec![0, 1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn stop_sequence_inside_a_single_token_is_trimmed() {
+        let mut model = ScriptedModel { prompt_len: 1, script: vec![0, 1, 5] };
+        let config = SamplingConfig { stop_sequences: vec!["orld".to_string()], ..greedy_config() };
+
+        let output = generate(&mut model, &VocabDecoder, &[0], &config).unwrap();
+
+        assert_eq!(output.text, "Hello w");
+        assert_eq!(output.tokens, vec![0, 1]);
+    }
+
+    /// Uniform-ish logits so every step is a real random draw
+    struct FlatModel;
+
+    impl TokenModel for FlatModel {
+        fn next_token_logits(&mut self, context: &[u32]) -> Result<Vec<f32>> {
+            Ok((0..VOCAB.len()).map(|i| ((i + context.len()) % 3) as f32 * 0.1).collect())
+        }
+    }
+
+    #[test]
+    fn same_seed_gives_identical_output() {
+        let parameters = InferenceParameters {
+            temperature: Some(1.0),
+            top_p: Some(1.0),
+            max_length: Some(64),
+            seed: Some(42),
+            ..InferenceParameters::default()
+        };
+        let config = SamplingConfig::from(&parameters);
+
+        let first = generate(&mut FlatModel, &VocabDecoder, &[0], &config).unwrap();
+        let second = generate(&mut FlatModel, &VocabDecoder, &[0], &config).unwrap();
+
+        assert_eq!(first.tokens, second.tokens);
+        assert_eq!(first.text.as_bytes(), second.text.as_bytes());
+    }
+
+    #[test]
+    fn greedy_ignores_temperature() {
+        let config = SamplingConfig { greedy: true, temperature: 2.0, ..greedy_config() };
+        let logits = [0.1, 0.9, 0.5];
+        for _ in 0..16 {
+            assert_eq!(sample(&logits, &config, &mut SamplerRng::from_entropy()), 1);
+        }
+    }
+
+    #[test]
+    fn no_repeat_ngram_blocks_repeated_bigram() {
+        let config = SamplingConfig { no_repeat_ngram_size: 2, ..greedy_config() };
+        // Context ends in token 1, and the bigram (1, 2) already occurred
+        let context = [1, 2, 3, 1];
+        let mut logits = vec![0.0, 0.0, 5.0, 1.0];
+        apply_penalties(&mut logits, &context, &HashMap::new(), &config);
+
+        assert_eq!(logits[2], f32::NEG_INFINITY);
+        assert_eq!(sample(&logits, &config, &mut SamplerRng::from_entropy()), 3);
+    }
+
+    #[test]
+    fn penalties_lower_repeated_tokens() {
+        let config = SamplingConfig {
+            repetition_penalty: 2.0,
+            frequency_penalty: 0.5,
+            presence_penalty: 0.25,
+            ..greedy_config()
+        };
+        let mut counts = HashMap::new();
+        counts.insert(0, 2);
+        let mut logits = vec![4.0, -1.0, 3.0];
+        apply_penalties(&mut logits, &[0, 0, 1], &counts, &config);
+
+        assert_eq!(logits[0], 4.0 / 2.0 - (0.5 * 2.0 + 0.25));
+        assert_eq!(logits[1], -2.0);
+        assert_eq!(logits[2], 3.0);
+    }
+
+    fn sampled_config(configure: impl FnOnce(&mut SamplingConfig)) -> SamplingConfig {
+        let mut config = SamplingConfig::from(&InferenceParameters {
+            temperature: Some(1.0),
+            top_p: Some(1.0),
+            ..InferenceParameters::default()
+        });
+        configure(&mut config);
+        config
+    }
+
+    /// Probabilities 0.4, 0.3, 0.15, 0.1, 0.05 at temperature 1
+    fn ranked_logits() -> Vec<f32> {
+        [0.4f32, 0.3, 0.15, 0.1, 0.05].iter().map(|p| p.ln()).collect()
+    }
+
+    fn tokens(candidates: &[(u32, f32)]) -> Vec<u32> {
+        candidates.iter().map(|(token, _)| *token).collect()
+    }
+
+    /// Relative frequency of each token over many seeded draws
+    fn frequencies(logits: &[f32], config: &SamplingConfig) -> Vec<f32> {
+        let mut rng = SamplerRng::seeded(7);
+        let mut counts = vec![0usize; logits.len()];
+        let draws = 20_000;
+        for _ in 0..draws {
+            counts[sample(logits, config, &mut rng) as usize] += 1;
+        }
+        counts.iter().map(|&c| c as f32 / draws as f32).collect()
+    }
+
+    #[test]
+    fn each_filter_keeps_the_specified_tokens() {
+        let logits = ranked_logits();
+
+        let top_k = sampled_config(|c| c.top_k = 2);
+        assert_eq!(tokens(&filtered_candidates(&logits, &top_k).unwrap()), vec![0, 1]);
+
+        let top_p = sampled_config(|c| c.top_p = 0.8);
+        assert_eq!(tokens(&filtered_candidates(&logits, &top_p).unwrap()), vec![0, 1, 2]);
+
+        let min_p = sampled_config(|c| c.min_p = 0.3);
+        // 0.3 * 0.4 = 0.12 keeps the first three
+        assert_eq!(tokens(&filtered_candidates(&logits, &min_p).unwrap()), vec![0, 1, 2]);
+
+        // Entropy is ~1.39 nats; token 1 (surprise 1.20) is the most typical,
+        // then token 0 (0.92) and token 2 (1.90)
+        let typical = sampled_config(|c| c.typical_p = 0.5);
+        assert_eq!(tokens(&filtered_candidates(&logits, &typical).unwrap()), vec![0, 1]);
+    }
+
+    #[test]
+    fn filters_apply_in_documented_order() {
+        let logits = ranked_logits();
+        // top-k runs first, so top-p measures mass within the two survivors:
+        // 0.4 / 0.7 = 0.57 < 0.6, so both stay
+        let config = sampled_config(|c| {
+            c.top_k = 2;
+            c.top_p = 0.6;
+        });
+        let candidates = filtered_candidates(&logits, &config).unwrap();
+        assert_eq!(tokens(&candidates), vec![0, 1]);
+        assert!((candidates[0].1 - 0.4 / 0.7).abs() < 1e-5);
+
+        // top-p runs before min-p: it keeps 0.4 + 0.3 + 0.15 >= 0.8, and min-p
+        // keeps all three. Run the other way round, min-p would first leave
+        // 0.47, 0.35, 0.18 and top-p would then drop token 2.
+        let config = sampled_config(|c| {
+            c.top_p = 0.8;
+            c.min_p = 0.3;
+        });
+        assert_eq!(tokens(&filtered_candidates(&logits, &config).unwrap()), vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn sampling_frequencies_follow_filtered_distribution() {
+        let logits = ranked_logits();
+
+        let unfiltered = frequencies(&logits, &sampled_config(|_| {}));
+        for (frequency, expected) in unfiltered.iter().zip([0.4, 0.3, 0.15, 0.1, 0.05]) {
+            assert!((frequency - expected).abs() < 0.02, "{} vs {}", frequency, expected);
+        }
+
+        let top_k = frequencies(&logits, &sampled_config(|c| c.top_k = 2));
+        assert!((top_k[0] - 0.4 / 0.7).abs() < 0.02);
+        assert!(top_k[2..].iter().all(|&f| f == 0.0));
+
+        let min_p = frequencies(&logits, &sampled_config(|c| c.min_p = 0.5));
+        assert!(min_p[2..].iter().all(|&f| f == 0.0));
+    }
+
+    #[test]
+    fn mirostat_target_controls_surprise() {
+        // Zipf-like distribution over a larger vocabulary
+        let logits: Vec<f32> = (1..=200).map(|rank| -(rank as f32).ln()).collect();
+        let probabilities = softmax_candidates(&logits, 1.0).unwrap();
+        let surprise = |token: u32| -probabilities.iter().find(|(t, _)| *t == token).unwrap().1.log2();
+        let steps = 2_000;
+
+        let average_surprise = |mirostat: Option<MirostatConfig>| {
+            let config = sampled_config(|c| c.mirostat = mirostat);
+            let mut state = mirostat.map(MirostatState::new);
+            let mut rng = SamplerRng::seeded(11);
+            let total: f32 = (0..steps)
+                .map(|_| {
+                    let token = match state.as_mut() {
+                        Some(state) => state.sample(&logits, &config, &mut rng),
+                        None => sample(&logits, &config, &mut rng),
+                    };
+                    surprise(token)
+                })
+                .sum();
+            total / steps as f32
+        };
+
+        let focused = average_surprise(Some(MirostatConfig { tau: 2.0, eta: 0.1 }));
+        let loose = average_surprise(Some(MirostatConfig { tau: 5.0, eta: 0.1 }));
+        let unconstrained = average_surprise(None);
+        assert!(focused + 1.0 < loose, "{} vs {}", focused, loose);
+        assert!(loose < unconstrained, "{} vs {}", loose, unconstrained);
+    }
+
+    #[test]
+    fn out_of_range_values_are_rejected() {
+        let invalid = [
+            sampled_config(|c| c.top_p = 0.0),
+            sampled_config(|c| c.top_p = 1.5),
+            sampled_config(|c| c.min_p = -0.1),
+            sampled_config(|c| c.typical_p = 0.0),
+            sampled_config(|c| c.temperature = f32::NAN),
+            sampled_config(|c| c.mirostat = Some(MirostatConfig { tau: 0.0, eta: 0.1 })),
+        ];
+        for config in &invalid {
+            assert!(matches!(config.validate(), Err(AIEngineError::InvalidSamplingParameter { .. })));
+        }
+        assert!(sampled_config(|c| c.top_p = 1.0).validate().is_ok());
+
+        let error = SamplingConfig::from(&InferenceParameters { top_p: Some(2.0), ..Default::default() })
+            .validate()
+            .unwrap_err();
+        assert_eq!(error.to_string(), "Invalid sampling parameter top_p: must be in (0, 1], got 2");
+    }
+
+    /// Candle model whose logits depend on the last token through a fixed matrix
+    #[cfg(feature = "candle")]
+    fn candle_model() -> CandleTokenModel<impl FnMut(&candle_core::Tensor, usize) -> candle_core::Result<candle_core::Tensor>> {
+        let device = candle_core::Device::Cpu;
+        let vocab = VOCAB.len();
+        let weights: Vec<f32> = (0..vocab * vocab).map(|i| ((i * 7) % 5) as f32 * 0.2).collect();
+        let weights = candle_core::Tensor::from_vec(weights, (vocab, vocab), &device).unwrap();
+        CandleTokenModel::new(
+            move |input: &candle_core::Tensor, _index_pos| {
+                let last = input.squeeze(0)?.to_vec1::<u32>()?.last().copied().unwrap_or(0);
+                weights.get(last as usize)?.unsqueeze(0)
+            },
+            device,
+            None,
+        )
+    }
+
+    #[cfg(feature = "candle")]
+    #[test]
+    fn candle_model_sampling_is_reproducible_with_a_seed() {
+        let config = |seed| {
+            SamplingConfig::from(&InferenceParameters {
+                temperature: Some(1.0),
+                top_p: Some(1.0),
+                max_length: Some(48),
+                seed: Some(seed),
+                ..InferenceParameters::default()
+            })
+        };
+
+        let first = generate(&mut candle_model(), &VocabDecoder, &[0], &config(42)).unwrap();
+        let second = generate(&mut candle_model(), &VocabDecoder, &[0], &config(42)).unwrap();
+        assert_eq!(first.tokens.len(), 48);
+        assert_eq!(first.tokens, second.tokens);
+        assert_eq!(first.text.as_bytes(), second.text.as_bytes());
+
+        // The seed is what makes it repeatable: other seeds sample other tokens
+        let others: HashSet<Vec<u32>> = (0..8)
+            .map(|seed| generate(&mut candle_model(), &VocabDecoder, &[0], &config(seed)).unwrap().tokens)
+            .collect();
+        assert!(others.len() > 1);
+    }
+}
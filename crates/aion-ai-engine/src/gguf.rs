@@ -0,0 +1,552 @@
+//! # GGUF Models
+//!
+//! Reads and validates the header of GGUF files, the single-file format of
+//! llama.cpp: metadata key/values, then one descriptor per tensor (name,
+//! shape, quantization and offset), then the aligned tensor data. Only the
+//! header is parsed here; with the `candle` feature the tensors are mapped
+//! into Candle's quantized tensors, which keep the GGML block layout as is.
+
+use crate::errors::{AIEngineError, AIResult};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::{BufReader, Read};
+use std::path::{Path, PathBuf};
+
+/// `GGUF` in little-endian byte order
+const GGUF_MAGIC: u32 = 0x4655_4747;
+
+/// Data alignment when `general.alignment` is absent
+const DEFAULT_ALIGNMENT: u64 = 32;
+
+/// GGML supports at most four dimensions
+const MAX_DIMS: u32 = 4;
+
+/// Arrays longer than this (vocabularies, merges) are summarized rather than
+/// copied into catalog metadata
+const MAX_METADATA_ARRAY: usize = 64;
+
+/// Tensor encodings a GGUF file can use; each quantized type stores blocks of
+/// `block_size` weights in `type_size` bytes
+#[allow(non_camel_case_types)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum GgufQuantization {
+    F32,
+    F16,
+    BF16,
+    Q4_0,
+    Q4_1,
+    Q5_0,
+    Q5_1,
+    Q8_0,
+    Q8_1,
+    Q2_K,
+    Q3_K,
+    Q4_K,
+    Q5_K,
+    Q6_K,
+    Q8_K,
+    /// Importance-matrix, ternary and integer types Candle cannot load
+    Other(u32),
+}
+
+/// Quantizations `ModelManager::load_gguf` can map into Candle tensors
+pub fn supported_quantizations() -> &'static [GgufQuantization] {
+    use GgufQuantization::*;
+    &[F32, F16, BF16, Q4_0, Q4_1, Q5_0, Q5_1, Q8_0, Q8_1, Q2_K, Q3_K, Q4_K, Q5_K, Q6_K, Q8_K]
+}
+
+impl GgufQuantization {
+    fn from_ggml_type(id: u32) -> Self {
+        use GgufQuantization::*;
+        match id {
+            0 => F32,
+            1 => F16,
+            2 => Q4_0,
+            3 => Q4_1,
+            6 => Q5_0,
+            7 => Q5_1,
+            8 => Q8_0,
+            9 => Q8_1,
+            10 => Q2_K,
+            11 => Q3_K,
+            12 => Q4_K,
+            13 => Q5_K,
+            14 => Q6_K,
+            15 => Q8_K,
+            30 => BF16,
+            other => Other(other),
+        }
+    }
+
+    pub fn is_supported(&self) -> bool {
+        supported_quantizations().contains(self)
+    }
+
+    /// Weights per block and bytes per block, for supported types
+    fn block_layout(&self) -> Option<(u64, u64)> {
+        use GgufQuantization::*;
+        Some(match self {
+            F32 => (1, 4),
+            F16 | BF16 => (1, 2),
+            Q4_0 => (32, 18),
+            Q4_1 => (32, 20),
+            Q5_0 => (32, 22),
+            Q5_1 => (32, 24),
+            Q8_0 => (32, 34),
+            Q8_1 => (32, 36),
+            Q2_K => (256, 84),
+            Q3_K => (256, 110),
+            Q4_K => (256, 144),
+            Q5_K => (256, 176),
+            Q6_K => (256, 210),
+            Q8_K => (256, 292),
+            Other(_) => return None,
+        })
+    }
+}
+
+impl std::fmt::Display for GgufQuantization {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GgufQuantization::Other(id) => write!(f, "GGML type {}", id),
+            known => write!(f, "{:?}", known),
+        }
+    }
+}
+
+impl std::str::FromStr for GgufQuantization {
+    type Err = AIEngineError;
+
+    fn from_str(s: &str) -> AIResult<Self> {
+        supported_quantizations()
+            .iter()
+            .copied()
+            .find(|q| q.to_string().eq_ignore_ascii_case(s))
+            .ok_or_else(|| AIEngineError::UnsupportedQuantization {
+                quantization: s.to_string(),
+                supported: supported_list(),
+            })
+    }
+}
+
+/// Supported quantizations as a comma-separated list, for error messages
+pub(crate) fn supported_list() -> String {
+    supported_quantizations().iter().map(|q| q.to_string()).collect::<Vec<_>>().join(", ")
+}
+
+/// Descriptor of one tensor in a GGUF file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GgufTensorInfo {
+    pub name: String,
+    /// Innermost dimension first, as GGML orders them
+    pub dims: Vec<u64>,
+    pub quantization: GgufQuantization,
+    /// Offset from the start of the data section
+    pub offset: u64,
+}
+
+impl GgufTensorInfo {
+    pub fn element_count(&self) -> u64 {
+        self.dims.iter().product()
+    }
+
+    /// Bytes of tensor data, for supported quantizations with whole blocks
+    pub fn size_bytes(&self) -> Option<u64> {
+        let (block_size, type_size) = self.quantization.block_layout()?;
+        let elements = self.element_count();
+        // Blocks run along the innermost dimension
+        (self.dims.first().copied().unwrap_or(1) % block_size == 0)
+            .then(|| elements / block_size * type_size)
+    }
+}
+
+/// Parsed GGUF header
+#[derive(Debug, Clone)]
+pub struct GgufFile {
+    pub path: PathBuf,
+    pub version: u32,
+    pub metadata: BTreeMap<String, serde_json::Value>,
+    pub tensors: Vec<GgufTensorInfo>,
+    /// Absolute offset of the tensor data section
+    pub data_offset: u64,
+    pub file_len: u64,
+}
+
+impl GgufFile {
+    /// Parse the header of a GGUF file; tensor data is not read
+    pub fn open(path: &Path) -> AIResult<Self> {
+        let file = std::fs::File::open(path)?;
+        let file_len = file.metadata()?.len();
+        let mut reader = HeaderReader {
+            inner: BufReader::new(file),
+            position: 0,
+            file_len,
+            path,
+        };
+
+        if reader.u32()? != GGUF_MAGIC {
+            return Err(reader.invalid("not a GGUF file (bad magic number)"));
+        }
+        let version = reader.u32()?;
+        if !(2..=3).contains(&version) {
+            return Err(reader.invalid(&format!("GGUF version {} is not supported (expected 2 or 3)", version)));
+        }
+        let tensor_count = reader.count(24)?;
+        let metadata_count = reader.count(12)?;
+
+        let mut metadata = BTreeMap::new();
+        for _ in 0..metadata_count {
+            let key = reader.string()?;
+            let value_type = reader.u32()?;
+            let value = reader.value(value_type)?;
+            metadata.insert(key, value);
+        }
+
+        let mut tensors = Vec::with_capacity(tensor_count as usize);
+        for _ in 0..tensor_count {
+            let name = reader.string()?;
+            let n_dims = reader.u32()?;
+            if n_dims == 0 || n_dims > MAX_DIMS {
+                return Err(reader.invalid(&format!("tensor {} has {} dimensions", name, n_dims)));
+            }
+            let dims = (0..n_dims).map(|_| reader.u64()).collect::<AIResult<Vec<u64>>>()?;
+            let quantization = GgufQuantization::from_ggml_type(reader.u32()?);
+            let offset = reader.u64()?;
+            tensors.push(GgufTensorInfo { name, dims, quantization, offset });
+        }
+
+        let alignment = match metadata.get("general.alignment") {
+            None => DEFAULT_ALIGNMENT,
+            Some(value) => value
+                .as_u64()
+                .filter(|a| a.is_power_of_two())
+                .ok_or_else(|| reader.invalid("general.alignment must be a power of two"))?,
+        };
+        let data_offset = reader.position.div_ceil(alignment) * alignment;
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            version,
+            metadata,
+            tensors,
+            data_offset,
+            file_len,
+        })
+    }
+
+    /// Check that every tensor uses a supported quantization and lies
+    /// within the file, aligned and with its full size
+    pub fn validate(&self) -> AIResult<()> {
+        if self.architecture().is_none() {
+            return Err(self.invalid("missing general.architecture"));
+        }
+        if self.tensors.is_empty() {
+            return Err(self.invalid("contains no tensors"));
+        }
+        let alignment = self.metadata.get("general.alignment").and_then(|a| a.as_u64()).unwrap_or(DEFAULT_ALIGNMENT);
+
+        for tensor in &self.tensors {
+            if !tensor.quantization.is_supported() {
+                return Err(AIEngineError::UnsupportedQuantization {
+                    quantization: format!("{} (tensor {})", tensor.quantization, tensor.name),
+                    supported: supported_list(),
+                });
+            }
+            let size = tensor.size_bytes().ok_or_else(|| {
+                self.invalid(&format!(
+                    "tensor {} has shape {:?}, which is not a whole number of {} blocks",
+                    tensor.name, tensor.dims, tensor.quantization
+                ))
+            })?;
+            if tensor.offset % alignment != 0 {
+                return Err(self.invalid(&format!("tensor {} is not aligned to {} bytes", tensor.name, alignment)));
+            }
+            let end = self
+                .data_offset
+                .checked_add(tensor.offset)
+                .and_then(|start| start.checked_add(size));
+            if !matches!(end, Some(end) if end <= self.file_len) {
+                return Err(self.invalid(&format!("tensor {} extends past the end of the file; is it truncated?", tensor.name)));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn architecture(&self) -> Option<&str> {
+        self.metadata.get("general.architecture").and_then(|a| a.as_str())
+    }
+
+    pub fn name(&self) -> Option<&str> {
+        self.metadata.get("general.name").and_then(|n| n.as_str())
+    }
+
+    pub fn context_length(&self) -> Option<u64> {
+        let architecture = self.architecture()?;
+        self.metadata.get(&format!("{}.context_length", architecture)).and_then(|c| c.as_u64())
+    }
+
+    /// Quantization of the bulk of the weights; mixed files such as Q4_K_M
+    /// keep norms in F32 and some layers in Q6_K
+    pub fn quantization(&self) -> Option<GgufQuantization> {
+        let mut bytes: Vec<(GgufQuantization, u64)> = Vec::new();
+        for tensor in self.tensors.iter().filter(|t| t.dims.len() >= 2) {
+            let size = tensor.size_bytes().unwrap_or(0);
+            match bytes.iter_mut().find(|(q, _)| *q == tensor.quantization) {
+                Some((_, total)) => *total += size,
+                None => bytes.push((tensor.quantization, size)),
+            }
+        }
+        bytes.into_iter().max_by_key(|(_, total)| *total).map(|(q, _)| q)
+    }
+
+    /// Bytes of tensor data, i.e. the memory the weights need once loaded
+    pub fn data_size(&self) -> u64 {
+        self.tensors.iter().filter_map(|t| t.size_bytes()).sum()
+    }
+
+    /// Metadata worth keeping in the model catalog: everything except the
+    /// tokenizer tables, with long arrays reduced to their length
+    pub fn catalog_metadata(&self) -> impl Iterator<Item = (String, serde_json::Value)> + '_ {
+        self.metadata
+            .iter()
+            .filter(|(key, _)| !key.starts_with("tokenizer.ggml."))
+            .map(|(key, value)| match value {
+                serde_json::Value::Array(items) if items.len() > MAX_METADATA_ARRAY => {
+                    (format!("{}.length", key), serde_json::json!(items.len()))
+                }
+                _ => (key.clone(), value.clone()),
+            })
+    }
+
+    fn invalid(&self, reason: &str) -> AIEngineError {
+        invalid(&self.path, reason)
+    }
+}
+
+fn invalid(path: &Path, reason: &str) -> AIEngineError {
+    AIEngineError::ModelLoadingFailed {
+        model: path.display().to_string(),
+        reason: format!("invalid GGUF file: {}", reason),
+    }
+}
+
+/// Little-endian reader that refuses lengths the file cannot hold, so a
+/// corrupt header fails cleanly instead of allocating without bound
+struct HeaderReader<'a, R: Read> {
+    inner: R,
+    position: u64,
+    file_len: u64,
+    path: &'a Path,
+}
+
+impl<R: Read> HeaderReader<'_, R> {
+    fn invalid(&self, reason: &str) -> AIEngineError {
+        invalid(self.path, reason)
+    }
+
+    fn bytes<const N: usize>(&mut self) -> AIResult<[u8; N]> {
+        let mut buf = [0u8; N];
+        self.inner.read_exact(&mut buf).map_err(|e| match e.kind() {
+            std::io::ErrorKind::UnexpectedEof => self.invalid("header is truncated"),
+            _ => AIEngineError::Io(e),
+        })?;
+        self.position += N as u64;
+        Ok(buf)
+    }
+
+    fn u32(&mut self) -> AIResult<u32> {
+        Ok(u32::from_le_bytes(self.bytes()?))
+    }
+
+    fn u64(&mut self) -> AIResult<u64> {
+        Ok(u64::from_le_bytes(self.bytes()?))
+    }
+
+    /// A count of items taking at least `min_item_bytes` each
+    fn count(&mut self, min_item_bytes: u64) -> AIResult<u64> {
+        let count = self.u64()?;
+        if count.saturating_mul(min_item_bytes) > self.file_len.saturating_sub(self.position) {
+            return Err(self.invalid(&format!("count {} does not fit in the file", count)));
+        }
+        Ok(count)
+    }
+
+    fn string(&mut self) -> AIResult<String> {
+        let len = self.count(1)?;
+        let mut buf = vec![0u8; len as usize];
+        self.inner.read_exact(&mut buf).map_err(|_| self.invalid("header is truncated"))?;
+        self.position += len;
+        String::from_utf8(buf).map_err(|_| self.invalid("string is not UTF-8"))
+    }
+
+    fn value(&mut self, value_type: u32) -> AIResult<serde_json::Value> {
+        use serde_json::json;
+        Ok(match value_type {
+            0 => json!(self.bytes::<1>()?[0]),
+            1 => json!(self.bytes::<1>()?[0] as i8),
+            2 => json!(u16::from_le_bytes(self.bytes()?)),
+            3 => json!(i16::from_le_bytes(self.bytes()?)),
+            4 => json!(self.u32()?),
+            5 => json!(i32::from_le_bytes(self.bytes()?)),
+            6 => json!(f32::from_le_bytes(self.bytes()?)),
+            7 => json!(self.bytes::<1>()?[0] != 0),
+            8 => json!(self.string()?),
+            9 => {
+                let item_type = self.u32()?;
+                if item_type == 9 {
+                    return Err(self.invalid("nested arrays are not supported"));
+                }
+                let len = self.count(1)?;
+                json!((0..len).map(|_| self.value(item_type)).collect::<AIResult<Vec<_>>>()?)
+            }
+            10 => json!(self.u64()?),
+            11 => json!(i64::from_le_bytes(self.bytes()?)),
+            12 => json!(f64::from_le_bytes(self.bytes()?)),
+            other => return Err(self.invalid(&format!("unknown metadata value type {}", other))),
+        })
+    }
+}
+
+/// Weights of a GGUF model as Candle quantized tensors, by tensor name
+#[cfg(feature = "candle")]
+pub struct GgufWeights {
+    pub tensors: std::collections::HashMap<String, candle_core::quantized::QTensor>,
+}
+
+#[cfg(feature = "candle")]
+impl GgufWeights {
+    /// Map every tensor of a validated file onto `device`
+    pub fn load(file: &GgufFile, device: &candle_core::Device) -> AIResult<Self> {
+        use candle_core::quantized::gguf_file;
+
+        let failed = |e: candle_core::Error| AIEngineError::ModelLoadingFailed {
+            model: file.path.display().to_string(),
+            reason: e.to_string(),
+        };
+        let mut reader = std::fs::File::open(&file.path)?;
+        let content = gguf_file::Content::read(&mut reader).map_err(failed)?;
+        let tensors = file
+            .tensors
+            .iter()
+            .map(|info| Ok((info.name.clone(), content.tensor(&mut reader, &info.name, device).map_err(failed)?)))
+            .collect::<AIResult<_>>()?;
+        Ok(Self { tensors })
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// A GGUF v3 file with `tensors` as (name, dims, GGML type id), each
+    /// filled with zeroed data of the right size
+    pub(crate) fn gguf(metadata: &[(&str, serde_json::Value)], tensors: &[(&str, &[u64], u32)]) -> Vec<u8> {
+        fn string(out: &mut Vec<u8>, s: &str) {
+            out.extend_from_slice(&(s.len() as u64).to_le_bytes());
+            out.extend_from_slice(s.as_bytes());
+        }
+
+        let mut out = Vec::new();
+        out.extend_from_slice(&GGUF_MAGIC.to_le_bytes());
+        out.extend_from_slice(&3u32.to_le_bytes());
+        out.extend_from_slice(&(tensors.len() as u64).to_le_bytes());
+        out.extend_from_slice(&(metadata.len() as u64).to_le_bytes());
+        for (key, value) in metadata {
+            string(&mut out, key);
+            match value {
+                serde_json::Value::String(s) => {
+                    out.extend_from_slice(&8u32.to_le_bytes());
+                    string(&mut out, s);
+                }
+                serde_json::Value::Number(n) => {
+                    out.extend_from_slice(&4u32.to_le_bytes());
+                    out.extend_from_slice(&(n.as_u64().unwrap() as u32).to_le_bytes());
+                }
+                other => panic!("unsupported test metadata {:?}", other),
+            }
+        }
+
+        let mut offset = 0u64;
+        let mut sizes = Vec::new();
+        for (name, dims, ggml_type) in tensors {
+            string(&mut out, name);
+            out.extend_from_slice(&(dims.len() as u32).to_le_bytes());
+            for dim in *dims {
+                out.extend_from_slice(&dim.to_le_bytes());
+            }
+            out.extend_from_slice(&ggml_type.to_le_bytes());
+            out.extend_from_slice(&offset.to_le_bytes());
+            let info = GgufTensorInfo {
+                name: name.to_string(),
+                dims: dims.to_vec(),
+                quantization: GgufQuantization::from_ggml_type(*ggml_type),
+                offset,
+            };
+            // Unsupported types get a token size; validation rejects them first
+            let size = info.size_bytes().unwrap_or(32);
+            sizes.push(size);
+            offset += size.div_ceil(DEFAULT_ALIGNMENT) * DEFAULT_ALIGNMENT;
+        }
+        out.resize(out.len().div_ceil(DEFAULT_ALIGNMENT as usize) * DEFAULT_ALIGNMENT as usize, 0);
+        out.resize(out.len() + offset as usize, 0);
+        out
+    }
+
+    pub(crate) fn llama_metadata() -> Vec<(&'static str, serde_json::Value)> {
+        vec![
+            ("general.architecture", serde_json::json!("llama")),
+            ("general.name", serde_json::json!("Tiny Llama")),
+            ("llama.context_length", serde_json::json!(2048)),
+        ]
+    }
+
+    fn write(bytes: &[u8]) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("aion-gguf-{}.gguf", uuid::Uuid::new_v4()));
+        std::fs::write(&path, bytes).unwrap();
+        path
+    }
+
+    #[test]
+    fn parses_header_and_finds_dominant_quantization() {
+        let path = write(&gguf(
+            &llama_metadata(),
+            &[
+                ("token_embd.weight", &[256, 64], 14),
+                ("blk.0.attn_q.weight", &[256, 256], 12),
+                ("blk.0.ffn_up.weight", &[256, 512], 12),
+                ("blk.0.attn_norm.weight", &[256], 0),
+            ],
+        ));
+        let file = GgufFile::open(&path).unwrap();
+        file.validate().unwrap();
+
+        assert_eq!(file.version, 3);
+        assert_eq!(file.architecture(), Some("llama"));
+        assert_eq!(file.context_length(), Some(2048));
+        assert_eq!(file.quantization(), Some(GgufQuantization::Q4_K));
+        assert_eq!(file.tensors[1].size_bytes(), Some(256 * 256 / 256 * 144));
+        std::fs::remove_file(path).ok();
+    }
+
+    #[test]
+    fn rejects_unsupported_and_corrupt_files() {
+        // IQ2_XXS cannot be loaded by Candle
+        let path = write(&gguf(&llama_metadata(), &[("blk.0.attn_q.weight", &[256, 256], 16)]));
+        let err = GgufFile::open(&path).unwrap().validate().unwrap_err();
+        assert!(matches!(err, AIEngineError::UnsupportedQuantization { ref quantization, .. } if quantization.contains("attn_q")));
+        std::fs::remove_file(path).ok();
+
+        let mut truncated = gguf(&llama_metadata(), &[("blk.0.attn_q.weight", &[256, 256], 8)]);
+        truncated.truncate(truncated.len() - 100);
+        let path = write(&truncated);
+        assert!(matches!(GgufFile::open(&path).unwrap().validate(), Err(AIEngineError::ModelLoadingFailed { .. })));
+        std::fs::remove_file(path).ok();
+
+        let path = write(b"GGML not really");
+        assert!(matches!(GgufFile::open(&path), Err(AIEngineError::ModelLoadingFailed { .. })));
+        std::fs::remove_file(path).ok();
+
+        assert!("q4_k".parse::<GgufQuantization>().is_ok());
+        assert!(matches!("IQ2_XXS".parse::<GgufQuantization>(), Err(AIEngineError::UnsupportedQuantization { .. })));
+    }
+}
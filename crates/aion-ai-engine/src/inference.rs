@@ -2,6 +2,10 @@
 //!
 //! High-performance inference engine with support for multiple backends and models.
 
+use crate::device::{is_recoverable_device_error, DeviceFallback, DeviceFallbackConfig, FallbackLimiter, InferenceDevice};
+use crate::errors::{AIEngineError, AIResult};
+use crate::generation::SamplingConfig;
+use crate::tensor_pool::{TensorPool, TensorPoolStats};
 use crate::{AIEngineConfig, InferenceBackend};
 use anyhow::{Context, Result};
 use dashmap::DashMap;
@@ -31,6 +35,8 @@ pub struct InferenceRequest {
 pub enum InferenceInput {
     /// Text input for NLP tasks
     Text(String),
+    /// Several texts embedded in one forward pass
+    TextBatch(Vec<String>),
     /// Image data as bytes
     Image(Vec<u8>),
     /// Audio data as bytes
@@ -54,8 +60,41 @@ pub struct InferenceParameters {
     pub temperature: Option<f32>,
     /// Top-p sampling for text generation
     pub top_p: Option<f32>,
+    /// Keep only the k most likely tokens; 0 disables
+    #[serde(default)]
+    pub top_k: Option<usize>,
+    /// Drop tokens less likely than this fraction of the most likely one
+    #[serde(default)]
+    pub min_p: Option<f32>,
+    /// Locally typical sampling mass; 1.0 disables
+    #[serde(default)]
+    pub typical_p: Option<f32>,
+    /// Perplexity-targeted sampling; replaces top-k, typical, top-p and min-p when set
+    #[serde(default)]
+    pub mirostat: Option<crate::generation::MirostatConfig>,
     /// Number of beams for beam search
     pub num_beams: Option<usize>,
+    /// Generation halts when any of these is produced; the stop text is not returned
+    #[serde(default)]
+    pub stop_sequences: Vec<String>,
+    /// Penalty applied to tokens already in the context (1.0 disables)
+    #[serde(default)]
+    pub repetition_penalty: Option<f32>,
+    /// Penalty per previous occurrence of a token in the generated text
+    #[serde(default)]
+    pub frequency_penalty: Option<f32>,
+    /// Penalty for any token that already appeared in the generated text
+    #[serde(default)]
+    pub presence_penalty: Option<f32>,
+    /// Forbid repeating n-grams of this size
+    #[serde(default)]
+    pub no_repeat_ngram_size: Option<usize>,
+    /// Seed for sampling; identical seed and input give identical output on the same backend
+    #[serde(default)]
+    pub seed: Option<u64>,
+    /// Always pick the most likely token (temperature 0); fully deterministic
+    #[serde(default)]
+    pub greedy: bool,
     /// Custom parameters for specific models
     pub custom: std::collections::HashMap<String, serde_json::Value>,
 }
@@ -66,12 +105,30 @@ impl Default for InferenceParameters {
             max_length: Some(512),
             temperature: Some(0.7),
             top_p: Some(0.9),
+            top_k: None,
+            min_p: None,
+            typical_p: None,
+            mirostat: None,
             num_beams: Some(1),
+            stop_sequences: Vec::new(),
+            repetition_penalty: None,
+            frequency_penalty: None,
+            presence_penalty: None,
+            no_repeat_ngram_size: None,
+            seed: None,
+            greedy: false,
             custom: std::collections::HashMap::new(),
         }
     }
 }
 
+impl InferenceParameters {
+    /// Check the sampling parameters; see [`SamplingConfig::validate`]
+    pub fn validate(&self) -> AIResult<()> {
+        SamplingConfig::from(self).validate()
+    }
+}
+
 /// Inference response containing results and metadata
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct InferenceResponse {
@@ -106,6 +163,8 @@ pub enum InferenceOutput {
     },
     /// Embedding vector
     Embedding(Vec<f32>),
+    /// One embedding vector per text of a [`InferenceInput::TextBatch`], in order
+    Embeddings(Vec<Vec<f32>>),
     /// Structured prediction
     Structured(serde_json::Value),
 }
@@ -138,6 +197,12 @@ pub struct TranscriptionSegment {
 pub struct InferenceMetadata {
     /// Backend used for inference
     pub backend: InferenceBackend,
+    /// Device that produced the output
+    #[serde(default)]
+    pub device: InferenceDevice,
+    /// Set when the primary device failed and `device` is a fallback
+    #[serde(default)]
+    pub fallback: Option<DeviceFallback>,
     /// Model used
     pub model: String,
     /// Inference duration in milliseconds
@@ -150,6 +215,20 @@ pub struct InferenceMetadata {
     pub timestamp: chrono::DateTime<chrono::Utc>,
 }
 
+/// Share of `max_memory` the tensor pool may retain (1 / divisor)
+const TENSOR_POOL_MEMORY_DIVISOR: usize = 4;
+
+/// Hidden size of the activation buffers used on the Candle text path
+const CANDLE_HIDDEN_SIZE: usize = 768;
+
+/// Stable per-token value in `[0, 1)` seeding a token's activations
+fn token_seed(token: &str) -> f32 {
+    use std::hash::{Hash, Hasher};
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    token.to_lowercase().hash(&mut hasher);
+    (hasher.finish() >> 40) as f32 / (1u64 << 24) as f32
+}
+
 /// High-performance inference engine
 pub struct InferenceEngine {
     config: AIEngineConfig,
@@ -159,6 +238,12 @@ pub struct InferenceEngine {
     active_sessions: Arc<DashMap<Uuid, InferenceSession>>,
     /// Performance metrics
     metrics: Arc<crate::performance::PerformanceMetrics>,
+    /// Recycled activation buffers shared across requests
+    tensor_pool: Arc<TensorPool>,
+    /// Primary compute device
+    device: InferenceDevice,
+    fallback_config: DeviceFallbackConfig,
+    fallback_limiter: Arc<FallbackLimiter>,
 }
 
 #[derive(Debug)]
@@ -174,18 +259,60 @@ impl InferenceEngine {
         let inference_semaphore = Arc::new(Semaphore::new(config.max_concurrent_inferences));
         let active_sessions = Arc::new(DashMap::new());
         let metrics = Arc::new(crate::performance::PerformanceMetrics::new());
+        // Model weights take the bulk of max_memory; pooled activations may retain a quarter of it
+        let tensor_pool = TensorPool::new(config.max_memory / TENSOR_POOL_MEMORY_DIVISOR);
+        let fallback_config = DeviceFallbackConfig::default();
+        let fallback_limiter = Arc::new(FallbackLimiter::new(fallback_config.max_fallbacks, fallback_config.window));
 
         Self {
             config,
             inference_semaphore,
             active_sessions,
             metrics,
+            tensor_pool,
+            device: InferenceDevice::default(),
+            fallback_config,
+            fallback_limiter,
         }
     }
 
+    /// Run inferences on `device` instead of the CPU
+    pub fn with_device(mut self, device: InferenceDevice) -> Self {
+        self.device = device;
+        self
+    }
+
+    /// Replace the default device fallback policy
+    pub fn with_device_fallback(mut self, config: DeviceFallbackConfig) -> Self {
+        self.fallback_limiter = Arc::new(FallbackLimiter::new(config.max_fallbacks, config.window));
+        self.fallback_config = config;
+        self
+    }
+
+    /// Export inference latency and token counts to a Prometheus registry
+    pub fn with_metrics_registry(mut self, registry: Arc<aion_monitoring::MetricsRegistry>) -> Self {
+        self.metrics = Arc::new(crate::performance::PerformanceMetrics::new().with_metrics_registry(registry));
+        self
+    }
+
+    pub fn device(&self) -> InferenceDevice {
+        self.device
+    }
+
+    pub fn config(&self) -> &AIEngineConfig {
+        &self.config
+    }
+
+    /// Fallbacks taken within the current rate-limit window
+    pub fn recent_device_fallbacks(&self) -> usize {
+        self.fallback_limiter.recent_fallbacks()
+    }
+
     /// Perform inference on the given request
     #[instrument(skip(self, request), fields(request_id = %request.id, model = %request.model))]
     pub async fn infer(&self, request: InferenceRequest) -> Result<InferenceResponse> {
+        request.parameters.validate()?;
+
         // Acquire semaphore permit to limit concurrent inferences
         let _permit = self
             .inference_semaphore
@@ -209,17 +336,37 @@ impl InferenceEngine {
             request.id, request.model, backend
         );
 
-        // Perform inference based on backend and input type
-        let result = self.perform_inference(&request, &backend).await;
+        // Perform inference based on backend and input type, retrying on a
+        // fallback device if the primary one faults
+        let result = match self.perform_inference(&request, &backend, self.device).await {
+            Ok(output) => Ok((output, self.device, None)),
+            Err(e) => match self.retry_on_fallback(&request, &backend, &e).await {
+                Some((device, Ok(output))) => {
+                    let fallback = DeviceFallback {
+                        from: self.device,
+                        reason: format!("{:#}", e),
+                    };
+                    Ok((output, device, Some(fallback)))
+                }
+                Some((device, Err(retry_error))) => {
+                    warn!("Fallback to {} also failed for request {}: {:#}", device, request.id, retry_error);
+                    Err(e)
+                }
+                None => Err(e),
+            },
+        };
 
         // Remove from active sessions
         self.active_sessions.remove(&request.id);
+        self.tensor_pool.publish_metrics();
 
         match result {
-            Ok(output) => {
+            Ok((output, device, fallback)) => {
                 let duration = start_time.elapsed();
                 let metadata = InferenceMetadata {
                     backend,
+                    device,
+                    fallback,
                     model: request.model.clone(),
                     duration_ms: duration.as_millis() as u64,
                     memory_usage: self.estimate_memory_usage(&output),
@@ -232,6 +379,7 @@ impl InferenceEngine {
                     &request.model,
                     duration,
                     metadata.memory_usage,
+                    metadata.tokens_processed.unwrap_or(0),
                     true,
                 );
 
@@ -249,7 +397,7 @@ impl InferenceEngine {
             }
             Err(e) => {
                 let duration = start_time.elapsed();
-                self.metrics.record_inference(&request.model, duration, 0, false);
+                self.metrics.record_inference(&request.model, duration, 0, 0, false);
 
                 error!(
                     "Inference failed for request {} after {}ms: {}",
@@ -263,30 +411,107 @@ impl InferenceEngine {
         }
     }
 
+    /// Retry a request that failed on the primary device on each configured
+    /// fallback device in turn. Returns `None` when the error is not a device
+    /// fault or fallback is disabled or rate-limited.
+    async fn retry_on_fallback(
+        &self,
+        request: &InferenceRequest,
+        backend: &InferenceBackend,
+        error: &anyhow::Error,
+    ) -> Option<(InferenceDevice, Result<InferenceOutput>)> {
+        if !self.fallback_config.enabled || !is_recoverable_device_error(error) {
+            return None;
+        }
+
+        let mut last_attempt = None;
+        for &device in self.fallback_config.fallback_devices.iter().filter(|d| **d != self.device) {
+            if !self.fallback_limiter.try_acquire() {
+                if self.fallback_limiter.mark_exhausted() {
+                    error!(
+                        "Device fallback limit reached ({} within {:?}); failures on {} are no longer retried. Check device health.",
+                        self.fallback_config.max_fallbacks, self.fallback_config.window, self.device
+                    );
+                }
+                break;
+            }
+
+            warn!(
+                "Request {} failed on {} ({:#}); retrying on {}",
+                request.id, self.device, error, device
+            );
+            let result = self.perform_inference(request, backend, device).await;
+            match &result {
+                Err(e) if is_recoverable_device_error(e) => last_attempt = Some((device, result)),
+                _ => return Some((device, result)),
+            }
+        }
+        last_attempt
+    }
+
     /// Perform the actual inference based on backend and input type
     async fn perform_inference(
         &self,
         request: &InferenceRequest,
         backend: &InferenceBackend,
+        device: InferenceDevice,
     ) -> Result<InferenceOutput> {
+        debug!("Running request {} on {}", request.id, device);
+        if let InferenceBackend::Candle = backend {
+            // A device that cannot be opened fails the request here, so
+            // `infer` can retry it on a fallback device
+            let device = device.to_candle()?;
+            return self.candle_inference(request, &device).await;
+        }
+
         match (&request.input, backend) {
-            (InferenceInput::Text(text), InferenceBackend::Candle) => {
+            #[cfg(feature = "torch")]
+            (input, InferenceBackend::PyTorch) => {
+                self.pytorch_inference(input, &request.model, &request.parameters)
+                    .await
+            }
+            #[cfg(feature = "tensorflow")]
+            (input, InferenceBackend::TensorFlow) => {
+                self.tensorflow_inference(input, &request.model, &request.parameters)
+                    .await
+            }
+            #[cfg(feature = "onnx")]
+            (input, InferenceBackend::ONNX) => {
+                self.onnx_inference(input, &request.model, &request.parameters)
+                    .await
+            }
+            _ => {
+                warn!("Unsupported combination: {:?} with {:?}, falling back to mock",
+                      std::mem::discriminant(&request.input), backend);
+                self.mock_inference(&request.input, &request.model).await
+            }
+        }
+    }
+
+    /// Candle backend inference; tensor work runs on `device`
+    async fn candle_inference(&self, request: &InferenceRequest, device: &candle_core::Device) -> Result<InferenceOutput> {
+        match &request.input {
+            InferenceInput::Text(text) => {
                 self.candle_text_inference(text, &request.model, &request.parameters)
                     .await
             }
-            (InferenceInput::Image(image_data), InferenceBackend::Candle) => {
+            InferenceInput::TextBatch(texts) => {
+                self.candle_embedding_inference(texts, &request.model, &request.parameters, device)
+                    .await
+            }
+            InferenceInput::Image(image_data) => {
                 self.candle_image_inference(image_data, &request.model, &request.parameters)
                     .await
             }
-            (InferenceInput::Audio(audio_data), InferenceBackend::Candle) => {
+            InferenceInput::Audio(audio_data) => {
                 self.candle_audio_inference(audio_data, &request.model, &request.parameters)
                     .await
             }
-            (InferenceInput::Structured(data), InferenceBackend::Candle) => {
+            InferenceInput::Structured(data) => {
                 self.candle_structured_inference(data, &request.model, &request.parameters)
                     .await
             }
-            (InferenceInput::MultiModal { text, image, audio }, InferenceBackend::Candle) => {
+            InferenceInput::MultiModal { text, image, audio } => {
                 self.candle_multimodal_inference(
                     text.as_deref(),
                     image.as_deref(),
@@ -296,26 +521,6 @@ impl InferenceEngine {
                 )
                 .await
             }
-            #[cfg(feature = "torch")]
-            (input, InferenceBackend::PyTorch) => {
-                self.pytorch_inference(input, &request.model, &request.parameters)
-                    .await
-            }
-            #[cfg(feature = "tensorflow")]
-            (input, InferenceBackend::TensorFlow) => {
-                self.tensorflow_inference(input, &request.model, &request.parameters)
-                    .await
-            }
-            #[cfg(feature = "onnx")]
-            (input, InferenceBackend::ONNX) => {
-                self.onnx_inference(input, &request.model, &request.parameters)
-                    .await
-            }
-            _ => {
-                warn!("Unsupported combination: {:?} with {:?}, falling back to mock",
-                      std::mem::discriminant(&request.input), backend);
-                self.mock_inference(&request.input, &request.model).await
-            }
         }
     }
 
@@ -340,10 +545,66 @@ impl InferenceEngine {
         } else {
             format!("Generated response using {}: {}", model, text)
         };
+        let (generated_text, _) = crate::generation::truncate_at_stop(&generated_text, &parameters.stop_sequences);
 
         Ok(InferenceOutput::Text(generated_text))
     }
 
+    /// Candle backend embedding of a batch of texts in one forward pass
+    async fn candle_embedding_inference(
+        &self,
+        texts: &[String],
+        model: &str,
+        parameters: &InferenceParameters,
+        device: &candle_core::Device,
+    ) -> Result<InferenceOutput> {
+        debug!("Performing Candle embedding inference for {} texts with model: {}", texts.len(), model);
+
+        // Every text is padded to the longest one so the batch is one
+        // [texts * length, hidden] activation matrix; padding rows stay zero
+        // and are left out of the pooling
+        let max_tokens = parameters.max_length.unwrap_or(512).max(1);
+        let sequences: Vec<Vec<&str>> = texts
+            .iter()
+            .map(|text| text.split_whitespace().take(max_tokens).collect())
+            .collect();
+        let length = sequences.iter().map(Vec::len).max().unwrap_or(0).max(1);
+        let mut activations = self.tensor_pool.acquire(&[texts.len().max(1) * length, CANDLE_HIDDEN_SIZE]);
+        for (index, tokens) in sequences.iter().enumerate() {
+            for (position, token) in tokens.iter().enumerate() {
+                let row = (index * length + position) * CANDLE_HIDDEN_SIZE;
+                let seed = token_seed(token);
+                for (col, value) in activations[row..row + CANDLE_HIDDEN_SIZE].iter_mut().enumerate() {
+                    *value = (seed * (col + 1) as f32).sin() + 0.1 * (position as f32 / (col + 1) as f32).cos();
+                }
+            }
+        }
+
+        // Pool on the device: sum each text's rows, to which padding adds
+        // nothing, then scale the sums to unit length
+        let device_error = |e: candle_core::Error| AIEngineError::DeviceError {
+            device: format!("{:?}", device.location()),
+            reason: e.to_string(),
+        };
+        let batch = candle_core::Tensor::from_slice(&activations[..], (texts.len().max(1), length, CANDLE_HIDDEN_SIZE), device)
+            .map_err(device_error)?;
+        drop(activations);
+        let sums = batch.sum(1).map_err(device_error)?;
+        let norms = sums
+            .sqr()
+            .and_then(|squares| squares.sum_keepdim(1))
+            .and_then(|norms| norms.sqrt())
+            .and_then(|norms| norms.maximum(f32::MIN_POSITIVE))
+            .map_err(device_error)?;
+        let mut embeddings = sums
+            .broadcast_div(&norms)
+            .and_then(|pooled| pooled.to_vec2::<f32>())
+            .map_err(device_error)?;
+        embeddings.truncate(texts.len());
+
+        Ok(InferenceOutput::Embeddings(embeddings))
+    }
+
     /// Candle backend image inference
     async fn candle_image_inference(
         &self,
@@ -494,6 +755,7 @@ impl InferenceEngine {
                 "Mock response for: {}",
                 text
             ))),
+            InferenceInput::TextBatch(texts) => Ok(InferenceOutput::Embeddings(vec![Vec::new(); texts.len()])),
             InferenceInput::Image(_) => Ok(InferenceOutput::Classification {
                 class: "unknown".to_string(),
                 confidence: 0.5,
@@ -523,6 +785,7 @@ impl InferenceEngine {
             InferenceOutput::ObjectDetection { objects } => objects.len() * 128,
             InferenceOutput::Transcription { segments, .. } => segments.len() * 256,
             InferenceOutput::Embedding(vec) => vec.len() * 4,
+            InferenceOutput::Embeddings(vectors) => vectors.iter().map(|vec| vec.len() * 4).sum(),
             InferenceOutput::Structured(_) => 1024, // Rough estimate
         }
     }
@@ -546,10 +809,54 @@ impl InferenceEngine {
     pub fn get_metrics(&self) -> Arc<crate::performance::PerformanceMetrics> {
         self.metrics.clone()
     }
+
+    /// Get tensor pool hit rate and retained bytes
+    pub fn get_tensor_pool_stats(&self) -> TensorPoolStats {
+        self.tensor_pool.stats()
+    }
 }
 
 impl Default for InferenceEngine {
     fn default() -> Self {
         Self::new(AIEngineConfig::default())
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn embedding_request() -> InferenceRequest {
+        InferenceRequest {
+            id: Uuid::new_v4(),
+            model: "sentence-encoder".to_string(),
+            input: InferenceInput::TextBatch(vec!["validate the email address".to_string()]),
+            parameters: InferenceParameters::default(),
+            backend: Some(InferenceBackend::Candle),
+        }
+    }
+
+    #[tokio::test]
+    async fn failing_primary_device_is_retried_on_the_fallback_and_tagged() {
+        // No CUDA device with this ordinal exists, so opening it fails
+        let primary = InferenceDevice::Cuda(99);
+        let engine = InferenceEngine::new(AIEngineConfig::default()).with_device(primary);
+
+        let response = engine.infer(embedding_request()).await.unwrap();
+        assert_eq!(response.metadata.device, InferenceDevice::Cpu);
+        let fallback = response.metadata.fallback.expect("a fallback record");
+        assert_eq!(fallback.from, primary);
+        assert!(fallback.reason.contains("cuda:99"), "{}", fallback.reason);
+        assert!(matches!(response.output, InferenceOutput::Embeddings(ref vectors) if vectors.len() == 1));
+        assert_eq!(engine.recent_device_fallbacks(), 1);
+
+        let cpu = InferenceEngine::new(AIEngineConfig::default());
+        let response = cpu.infer(embedding_request()).await.unwrap();
+        assert_eq!(response.metadata.device, InferenceDevice::Cpu);
+        assert!(response.metadata.fallback.is_none());
+
+        let disabled = InferenceEngine::new(AIEngineConfig::default())
+            .with_device(primary)
+            .with_device_fallback(DeviceFallbackConfig { enabled: false, ..DeviceFallbackConfig::default() });
+        assert!(disabled.infer(embedding_request()).await.is_err());
+    }
+}
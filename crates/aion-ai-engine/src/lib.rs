@@ -20,12 +20,16 @@ pub mod vision;
 pub mod audio;
 pub mod traditional_ml;
 pub mod model_manager;
+pub mod lora;
+pub mod gguf;
 pub mod performance;
 pub mod errors;
 pub mod code_generation;
 pub mod requirements_analyzer;
 pub mod autonomous_qa;
+pub mod edge_cases;
 pub mod template_engine;
+pub mod template_layout;
 pub mod project_scaffolding;
 pub mod progress_tracking;
 pub mod bug_prediction;
@@ -39,6 +43,17 @@ pub mod ast_parser;
 pub mod refactoring_operations;
 pub mod llm_providers;
 pub mod locked_files;
+pub mod tensor_pool;
+pub mod generation;
+pub mod semantic_search;
+pub mod vector_store;
+pub mod reranking;
+pub mod device;
+pub mod prompt_guard;
+pub mod rag;
+pub mod output_filter;
+pub mod context_packer;
+pub mod formatting;
 
 pub use inference::*;
 pub use models::*;
@@ -52,8 +67,15 @@ pub use errors::*;
 pub use code_generation::*;
 pub use requirements_analyzer::*;
 pub use autonomous_qa::*;
+pub use edge_cases::*;
 pub use template_engine::*;
+pub use template_layout::*;
 pub use project_scaffolding::*;
+pub use tensor_pool::*;
+pub use device::*;
+pub use generation::*;
+pub use lora::{AdapterStack, BaseModelShape, LoraAdapter, LoraConfig};
+pub use gguf::{supported_quantizations, GgufQuantization};
 
 /// AI Engine configuration
 #[derive(Debug, Clone, serde::Deserialize, serde::Serialize)]
@@ -18,6 +18,7 @@ use serde::{Deserialize, Serialize};
 use std::time::Duration;
 use reqwest::Client;
 use async_trait::async_trait;
+use crate::prompt_guard::{guard_prompt, FlaggedInput, GuardMode, UntrustedInput};
 
 /// LLM Provider types
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
@@ -49,6 +50,13 @@ pub struct LLMResponse {
     pub finish_reason: Option<String>,
 }
 
+/// Response to a guarded request, with the untrusted inputs that were flagged
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GuardedResponse {
+    pub response: LLMResponse,
+    pub flagged: Vec<FlaggedInput>,
+}
+
 /// Trait for LLM providers
 #[async_trait]
 pub trait LLMClient: Send + Sync {
@@ -681,6 +689,33 @@ impl MultiProviderLLM {
         Err(last_error.unwrap_or_else(|| anyhow!("No LLM providers available")))
     }
 
+    /// Generate with untrusted inputs (retrieved documents, repository code)
+    /// appended to the prompt after passing through the injection guard.
+    /// The response reports every input the guard flagged.
+    pub async fn generate_guarded(
+        &self,
+        request: &LLMRequest,
+        untrusted: &[UntrustedInput],
+        mode: GuardMode,
+    ) -> Result<GuardedResponse> {
+        let (prompt, flagged) = guard_prompt(&request.prompt, untrusted, mode);
+        for input in &flagged {
+            tracing::warn!(
+                "Possible prompt injection in {} (risk {:.2}, {} finding(s))",
+                input.source,
+                input.assessment.risk_score,
+                input.assessment.findings.len()
+            );
+        }
+
+        let guarded_request = LLMRequest {
+            prompt,
+            ..request.clone()
+        };
+        let response = self.generate_with_fallback(&guarded_request).await?;
+        Ok(GuardedResponse { response, flagged })
+    }
+
     pub fn available_providers(&self) -> Vec<LLMProvider> {
         self.providers.iter().map(|p| p.provider_type()).collect()
     }
@@ -0,0 +1,475 @@
+//! # LoRA Adapters
+//!
+//! Low-rank adapters in the PEFT layout: a directory holding
+//! `adapter_config.json` and `adapter_model.safetensors`. An adapter adds
+//! `scale * B·A` to the weight of each module it targets, where `A` is
+//! `rank × in`, `B` is `out × rank` and `scale = lora_alpha / r`. Applying it
+//! to an activation costs two thin matrix products and never touches the base
+//! weights, so one base model in memory can serve any number of adapters.
+
+use crate::errors::{AIEngineError, AIResult};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+use std::sync::Arc;
+
+/// Adapter configuration file inside an adapter directory
+pub const ADAPTER_CONFIG_FILE: &str = "adapter_config.json";
+
+/// Adapter weights file inside an adapter directory
+pub const ADAPTER_WEIGHTS_FILE: &str = "adapter_model.safetensors";
+
+/// Base model metadata key describing its linear modules as `{"q_proj": [out, in], ...}`
+pub const LINEAR_MODULES_KEY: &str = "linear_modules";
+
+/// Base model metadata key holding its number of transformer layers
+pub const NUM_LAYERS_KEY: &str = "num_hidden_layers";
+
+/// `adapter_config.json`, as written by PEFT
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LoraConfig {
+    /// Default rank; modules may use another one (PEFT's `rank_pattern`)
+    pub r: usize,
+    pub lora_alpha: f32,
+    /// Module names the adapter modifies, e.g. `q_proj`
+    pub target_modules: Vec<String>,
+    #[serde(default)]
+    pub base_model_name_or_path: Option<String>,
+}
+
+/// The low-rank update of one module
+#[derive(Debug, Clone)]
+pub struct LoraModule {
+    pub in_features: usize,
+    pub out_features: usize,
+    pub rank: usize,
+    /// `rank × in_features`, row-major
+    a: Vec<f32>,
+    /// `out_features × rank`, row-major
+    b: Vec<f32>,
+}
+
+impl LoraModule {
+    pub fn new(in_features: usize, out_features: usize, rank: usize, a: Vec<f32>, b: Vec<f32>) -> Option<Self> {
+        (a.len() == rank * in_features && b.len() == out_features * rank).then_some(Self {
+            in_features,
+            out_features,
+            rank,
+            a,
+            b,
+        })
+    }
+
+    /// Add `scale * B·A·input` to `output`
+    pub fn apply(&self, scale: f32, input: &[f32], output: &mut [f32]) {
+        debug_assert_eq!(input.len(), self.in_features);
+        debug_assert_eq!(output.len(), self.out_features);
+        let projected: Vec<f32> = self
+            .a
+            .chunks_exact(self.in_features)
+            .map(|row| row.iter().zip(input).map(|(w, x)| w * x).sum::<f32>() * scale)
+            .collect();
+        for (out, row) in output.iter_mut().zip(self.b.chunks_exact(self.rank)) {
+            *out += row.iter().zip(&projected).map(|(w, p)| w * p).sum::<f32>();
+        }
+    }
+
+    fn size_bytes(&self) -> u64 {
+        ((self.a.len() + self.b.len()) * std::mem::size_of::<f32>()) as u64
+    }
+}
+
+/// Shapes of a base model's linear modules, taken from its catalog metadata
+#[derive(Debug, Clone)]
+pub struct BaseModelShape {
+    /// Module name to `(out_features, in_features)`
+    pub modules: HashMap<String, (usize, usize)>,
+    pub num_layers: Option<usize>,
+}
+
+impl BaseModelShape {
+    pub fn from_metadata(metadata: &HashMap<String, serde_json::Value>) -> Option<Self> {
+        let modules = metadata
+            .get(LINEAR_MODULES_KEY)?
+            .as_object()?
+            .iter()
+            .map(|(name, dims)| {
+                let dims: [usize; 2] = serde_json::from_value(dims.clone()).ok()?;
+                Some((name.clone(), (dims[0], dims[1])))
+            })
+            .collect::<Option<HashMap<_, _>>>()?;
+        let num_layers = metadata.get(NUM_LAYERS_KEY).and_then(|v| v.as_u64()).map(|v| v as usize);
+        Some(Self { modules, num_layers })
+    }
+}
+
+/// A LoRA adapter with its weights in memory
+#[derive(Debug, Clone)]
+pub struct LoraAdapter {
+    pub name: String,
+    pub config: LoraConfig,
+    /// Full module path (e.g. `model.layers.0.self_attn.q_proj`) to its update
+    modules: BTreeMap<String, LoraModule>,
+}
+
+impl LoraAdapter {
+    /// Load an adapter directory, or an adapter's `.safetensors` file with
+    /// `adapter_config.json` next to it
+    pub fn load(path: &Path) -> AIResult<Self> {
+        let (dir, weights) = if path.is_dir() {
+            (path.to_path_buf(), path.join(ADAPTER_WEIGHTS_FILE))
+        } else {
+            (path.parent().map(Path::to_path_buf).unwrap_or_default(), path.to_path_buf())
+        };
+        let name = dir
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "adapter".to_string());
+
+        let config: LoraConfig = serde_json::from_slice(&std::fs::read(dir.join(ADAPTER_CONFIG_FILE))?)
+            .map_err(|e| load_error(&name, format!("invalid {}: {}", ADAPTER_CONFIG_FILE, e)))?;
+        let tensors = read_safetensors(&std::fs::read(&weights)?).map_err(|reason| load_error(&name, reason))?;
+        Self::from_tensors(name, config, tensors)
+    }
+
+    /// Build an adapter from PEFT-named tensors (`….lora_A.weight`, `….lora_B.weight`)
+    pub fn from_tensors(name: String, config: LoraConfig, tensors: HashMap<String, Tensor>) -> AIResult<Self> {
+        if config.r == 0 || config.target_modules.is_empty() {
+            return Err(load_error(&name, "rank and target_modules must be non-empty".to_string()));
+        }
+
+        let mut pairs: BTreeMap<String, (Option<Tensor>, Option<Tensor>)> = BTreeMap::new();
+        for (tensor_name, tensor) in tensors {
+            let (module, is_a) = if let Some(module) = tensor_name.strip_suffix(".lora_A.weight") {
+                (module, true)
+            } else if let Some(module) = tensor_name.strip_suffix(".lora_B.weight") {
+                (module, false)
+            } else {
+                continue;
+            };
+            let module = module.strip_prefix("base_model.model.").unwrap_or(module).to_string();
+            let entry = pairs.entry(module).or_default();
+            if is_a {
+                entry.0 = Some(tensor);
+            } else {
+                entry.1 = Some(tensor);
+            }
+        }
+
+        let mut modules = BTreeMap::new();
+        for (module, pair) in pairs {
+            let (Some(a), Some(b)) = pair else {
+                return Err(load_error(&name, format!("{} needs both lora_A and lora_B weights", module)));
+            };
+            let (&[rank, in_features], &[out_features, b_rank]) = (a.shape.as_slice(), b.shape.as_slice()) else {
+                return Err(load_error(&name, format!("{} weights must be matrices", module)));
+            };
+            if rank == 0 || rank != b_rank {
+                return Err(load_error(
+                    &name,
+                    format!("{} has lora_A rank {} but lora_B rank {}", module, rank, b_rank),
+                ));
+            }
+            let lora = LoraModule::new(in_features, out_features, rank, a.data, b.data)
+                .ok_or_else(|| load_error(&name, format!("{} weights do not match their shapes", module)))?;
+            modules.insert(module, lora);
+        }
+        if modules.is_empty() {
+            return Err(load_error(&name, "no LoRA weights found".to_string()));
+        }
+        Ok(Self { name, config, modules })
+    }
+
+    /// Multiplier of every update, `lora_alpha / r`
+    pub fn scale(&self) -> f32 {
+        self.config.lora_alpha / self.config.r as f32
+    }
+
+    pub fn modules(&self) -> impl Iterator<Item = (&str, &LoraModule)> {
+        self.modules.iter().map(|(name, module)| (name.as_str(), module))
+    }
+
+    pub fn module(&self, path: &str) -> Option<&LoraModule> {
+        self.modules.get(path)
+    }
+
+    pub fn max_rank(&self) -> usize {
+        self.modules.values().map(|module| module.rank).max().unwrap_or(0)
+    }
+
+    /// Memory the adapter's weights take
+    pub fn size_bytes(&self) -> u64 {
+        self.modules.values().map(LoraModule::size_bytes).sum()
+    }
+
+    /// Check that every module the adapter touches exists in the base model
+    /// with matching dimensions, and that no rank exceeds them
+    pub fn check_compatible(&self, base_model_id: &str, base: &BaseModelShape) -> AIResult<()> {
+        let incompatible = |reason: String| AIEngineError::AdapterIncompatible {
+            adapter: self.name.clone(),
+            base: base_model_id.to_string(),
+            reason,
+        };
+
+        for target in &self.config.target_modules {
+            if !base.modules.contains_key(target) {
+                return Err(incompatible(format!(
+                    "target module {} does not exist in the base model (it has {})",
+                    target,
+                    sorted_names(&base.modules)
+                )));
+            }
+        }
+
+        for (path, module) in &self.modules {
+            let kind = path.rsplit('.').next().unwrap_or(path);
+            let Some(&(out_features, in_features)) = base.modules.get(kind) else {
+                return Err(incompatible(format!("module {} does not exist in the base model", path)));
+            };
+            if (module.out_features, module.in_features) != (out_features, in_features) {
+                return Err(incompatible(format!(
+                    "{} is {}×{} in the adapter but {}×{} in the base model",
+                    path, module.out_features, module.in_features, out_features, in_features
+                )));
+            }
+            if module.rank > out_features.min(in_features) {
+                return Err(incompatible(format!(
+                    "{} has rank {}, more than its {}×{} weight allows",
+                    path, module.rank, out_features, in_features
+                )));
+            }
+            if let (Some(layer), Some(num_layers)) = (layer_index(path), base.num_layers) {
+                if layer >= num_layers {
+                    return Err(incompatible(format!(
+                        "{} targets layer {} but the base model has {} layers",
+                        path, layer, num_layers
+                    )));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Adapters applied together, each with its own weight
+#[derive(Debug, Clone, Default)]
+pub struct AdapterStack {
+    layers: Vec<(Arc<LoraAdapter>, f32)>,
+}
+
+impl AdapterStack {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, adapter: Arc<LoraAdapter>, weight: f32) {
+        self.layers.push((adapter, weight));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.layers.is_empty()
+    }
+
+    pub fn adapters(&self) -> impl Iterator<Item = (&LoraAdapter, f32)> {
+        self.layers.iter().map(|(adapter, weight)| (adapter.as_ref(), *weight))
+    }
+
+    /// Add every adapter's update for `module` to the base output
+    /// `output = W·input`
+    pub fn apply(&self, module: &str, input: &[f32], output: &mut [f32]) {
+        for (adapter, weight) in &self.layers {
+            if let Some(lora) = adapter.module(module) {
+                lora.apply(weight * adapter.scale(), input, output);
+            }
+        }
+    }
+}
+
+/// A tensor read from a safetensors file, converted to `f32`
+#[derive(Debug, Clone)]
+pub struct Tensor {
+    pub shape: Vec<usize>,
+    pub data: Vec<f32>,
+}
+
+#[derive(Deserialize)]
+struct TensorEntry {
+    dtype: String,
+    shape: Vec<usize>,
+    data_offsets: [usize; 2],
+}
+
+/// Parse a safetensors file: an 8-byte little-endian header length, a JSON
+/// header and the raw tensor data
+fn read_safetensors(bytes: &[u8]) -> Result<HashMap<String, Tensor>, String> {
+    let header_len = bytes
+        .get(..8)
+        .map(|len| u64::from_le_bytes(len.try_into().unwrap()) as usize)
+        .ok_or("weights file is too short")?;
+    let header = bytes
+        .get(8..8usize.saturating_add(header_len))
+        .ok_or("weights header is truncated")?;
+    let data = &bytes[8 + header_len..];
+    let mut entries: HashMap<String, serde_json::Value> =
+        serde_json::from_slice(header).map_err(|e| format!("invalid weights header: {}", e))?;
+    entries.remove("__metadata__");
+
+    let mut tensors = HashMap::new();
+    for (name, entry) in entries {
+        let entry: TensorEntry = serde_json::from_value(entry).map_err(|e| format!("invalid entry for {}: {}", name, e))?;
+        let [start, end] = entry.data_offsets;
+        let raw = data
+            .get(start..end)
+            .filter(|_| start <= end)
+            .ok_or_else(|| format!("{} points outside the weights data", name))?;
+        let values: Vec<f32> = match entry.dtype.as_str() {
+            "F32" => raw.chunks_exact(4).map(|b| f32::from_le_bytes(b.try_into().unwrap())).collect(),
+            "F16" => raw.chunks_exact(2).map(|b| f16_to_f32(u16::from_le_bytes([b[0], b[1]]))).collect(),
+            "BF16" => raw
+                .chunks_exact(2)
+                .map(|b| f32::from_bits((u16::from_le_bytes([b[0], b[1]]) as u32) << 16))
+                .collect(),
+            other => return Err(format!("{} has unsupported dtype {}", name, other)),
+        };
+        if values.len() != entry.shape.iter().product::<usize>() {
+            return Err(format!("{} holds {} values for shape {:?}", name, values.len(), entry.shape));
+        }
+        tensors.insert(name, Tensor { shape: entry.shape, data: values });
+    }
+    Ok(tensors)
+}
+
+fn f16_to_f32(bits: u16) -> f32 {
+    let sign = if bits & 0x8000 != 0 { -1.0 } else { 1.0 };
+    let exponent = ((bits >> 10) & 0x1f) as i32;
+    let mantissa = (bits & 0x3ff) as f32;
+    sign * match exponent {
+        0 => mantissa * 2f32.powi(-24),
+        0x1f if mantissa == 0.0 => f32::INFINITY,
+        0x1f => f32::NAN,
+        _ => (1.0 + mantissa / 1024.0) * 2f32.powi(exponent - 15),
+    }
+}
+
+/// Layer number in a path like `model.layers.12.self_attn.q_proj`
+fn layer_index(path: &str) -> Option<usize> {
+    let mut segments = path.split('.');
+    segments.find(|segment| *segment == "layers" || *segment == "h" || *segment == "layer")?;
+    segments.next()?.parse().ok()
+}
+
+fn sorted_names(modules: &HashMap<String, (usize, usize)>) -> String {
+    let mut names: Vec<&str> = modules.keys().map(String::as_str).collect();
+    names.sort_unstable();
+    names.join(", ")
+}
+
+fn load_error(adapter: &str, reason: String) -> AIEngineError {
+    AIEngineError::ModelLoadingFailed {
+        model: adapter.to_string(),
+        reason,
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// Serialize F32 tensors as a safetensors file
+    pub(crate) fn safetensors(tensors: &[(&str, &[usize], &[f32])]) -> Vec<u8> {
+        let mut header = serde_json::Map::new();
+        let mut data = Vec::new();
+        for (name, shape, values) in tensors {
+            let start = data.len();
+            for value in *values {
+                data.extend_from_slice(&value.to_le_bytes());
+            }
+            header.insert(
+                name.to_string(),
+                serde_json::json!({ "dtype": "F32", "shape": shape, "data_offsets": [start, data.len()] }),
+            );
+        }
+        let header = serde_json::to_vec(&header).unwrap();
+        let mut bytes = (header.len() as u64).to_le_bytes().to_vec();
+        bytes.extend(header);
+        bytes.extend(data);
+        bytes
+    }
+
+    pub(crate) fn config(r: usize, alpha: f32, targets: &[&str]) -> LoraConfig {
+        LoraConfig {
+            r,
+            lora_alpha: alpha,
+            target_modules: targets.iter().map(|t| t.to_string()).collect(),
+            base_model_name_or_path: None,
+        }
+    }
+
+    fn adapter(name: &str, a: &[f32], b: &[f32]) -> LoraAdapter {
+        let bytes = safetensors(&[
+            ("base_model.model.model.layers.0.q_proj.lora_A.weight", &[1, 2], a),
+            ("base_model.model.model.layers.0.q_proj.lora_B.weight", &[2, 1], b),
+        ]);
+        LoraAdapter::from_tensors(name.to_string(), config(1, 2.0, &["q_proj"]), read_safetensors(&bytes).unwrap()).unwrap()
+    }
+
+    fn base(out_features: usize, in_features: usize) -> BaseModelShape {
+        BaseModelShape {
+            modules: HashMap::from([("q_proj".to_string(), (out_features, in_features))]),
+            num_layers: Some(1),
+        }
+    }
+
+    #[test]
+    fn stacked_adapters_add_weighted_updates() {
+        // A = [1, 1], B = [1, 2]ᵀ, scale 2: update = 2 * B·A·x
+        let first = Arc::new(adapter("first", &[1.0, 1.0], &[1.0, 2.0]));
+        let second = Arc::new(adapter("second", &[1.0, 0.0], &[0.0, 1.0]));
+        let input = [1.0, 2.0];
+
+        let mut stack = AdapterStack::new();
+        stack.push(first, 1.0);
+        let mut output = [10.0, 10.0];
+        stack.apply("model.layers.0.q_proj", &input, &mut output);
+        assert_eq!(output, [16.0, 22.0]);
+
+        stack.push(second, 0.5);
+        let mut output = [10.0, 10.0];
+        stack.apply("model.layers.0.q_proj", &input, &mut output);
+        assert_eq!(output, [16.0, 23.0]);
+
+        // Modules an adapter does not touch keep the base output
+        let mut output = [10.0, 10.0];
+        stack.apply("model.layers.0.v_proj", &input, &mut output);
+        assert_eq!(output, [10.0, 10.0]);
+    }
+
+    #[test]
+    fn incompatible_adapters_are_rejected() {
+        let adapter = adapter("tiny", &[1.0, 1.0], &[1.0, 2.0]);
+        assert!(adapter.check_compatible("base", &base(2, 2)).is_ok());
+
+        let err = adapter.check_compatible("base", &base(4, 2)).unwrap_err();
+        assert!(err.to_string().contains("2×2 in the adapter but 4×2"), "{}", err);
+
+        let mut missing = base(2, 2);
+        missing.modules = HashMap::from([("k_proj".to_string(), (2, 2))]);
+        let err = adapter.check_compatible("base", &missing).unwrap_err();
+        assert!(matches!(err, AIEngineError::AdapterIncompatible { .. }));
+        assert!(err.to_string().contains("target module q_proj does not exist"), "{}", err);
+
+        let mut shallow = base(2, 2);
+        shallow.num_layers = Some(0);
+        assert!(adapter.check_compatible("base", &shallow).is_err());
+    }
+
+    #[test]
+    fn mismatched_ranks_fail_to_load() {
+        let bytes = safetensors(&[
+            ("m.q_proj.lora_A.weight", &[2, 2], &[0.0; 4]),
+            ("m.q_proj.lora_B.weight", &[2, 1], &[0.0; 2]),
+        ]);
+        let result = LoraAdapter::from_tensors("bad".to_string(), config(2, 4.0, &["q_proj"]), read_safetensors(&bytes).unwrap());
+        assert!(matches!(result, Err(AIEngineError::ModelLoadingFailed { reason, .. }) if reason.contains("rank")));
+    }
+}
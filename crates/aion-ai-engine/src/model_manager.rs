@@ -3,12 +3,19 @@
 //! Manages AI model loading, caching, and lifecycle.
 
 use crate::errors::{AIEngineError, AIResult};
+use crate::gguf::{GgufFile, GgufQuantization};
+use crate::lora::{AdapterStack, BaseModelShape, LoraAdapter, LINEAR_MODULES_KEY};
 use anyhow::{Context, Result};
 use dashmap::DashMap;
+use futures::StreamExt;
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
 use std::sync::Arc;
-use tokio::sync::RwLock;
+use std::time::Duration;
+use sha2::{Digest, Sha256};
+use tokio::io::AsyncWriteExt;
+use tokio::sync::{Notify, RwLock};
 use tracing::{debug, error, info, warn};
 
 /// Model information and metadata
@@ -68,6 +75,8 @@ pub enum ModelFormat {
     HuggingFace,
     /// Candle format
     Candle,
+    /// GGUF single-file format (llama.cpp)
+    GGUF,
     /// Custom format
     Custom(String),
 }
@@ -104,6 +113,224 @@ pub struct LoadedModel {
     pub model_data: Option<Arc<dyn Send + Sync>>,
 }
 
+/// How `unload_model` treats requests that are still using the model
+#[derive(Debug, Clone)]
+pub struct DrainOptions {
+    /// How long to wait for in-flight requests before detaching the model anyway
+    pub timeout: Duration,
+    /// Model that new requests are migrated to while this one drains
+    pub replacement_model: Option<String>,
+}
+
+impl Default for DrainOptions {
+    fn default() -> Self {
+        Self {
+            timeout: Duration::from_secs(30),
+            replacement_model: None,
+        }
+    }
+}
+
+/// Result of draining and unloading a model
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DrainReport {
+    /// Model that was unloaded
+    pub model_id: String,
+    /// Whether every in-flight request finished before the timeout
+    pub drained: bool,
+    /// Requests still running when the timeout hit; resources are freed once they finish
+    pub outstanding_requests: usize,
+    /// Time spent waiting for in-flight requests
+    pub waited_ms: u64,
+}
+
+/// Progress of a model download
+#[derive(Debug, Clone, Copy)]
+pub struct DownloadProgress {
+    pub downloaded_bytes: u64,
+    /// Total size, when the server reports it
+    pub total_bytes: Option<u64>,
+    pub phase: DownloadPhase,
+}
+
+/// What a pull is doing when it reports progress
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DownloadPhase {
+    /// Downloading, continuing after `resumed_from` bytes kept from an earlier attempt
+    Downloading { resumed_from: u64 },
+    /// Following another pull of the same model instead of downloading it twice
+    Attached,
+    /// Checking the finished download against its checksum
+    Verifying,
+}
+
+/// How `pull_model_with` fetches a model
+#[derive(Debug, Clone)]
+pub struct PullOptions {
+    /// Download again even if the model is already cached
+    pub force: bool,
+    /// Hugging Face access token, needed for gated and private models
+    pub auth_token: Option<String>,
+    /// Consecutive failed attempts before giving up; every retry resumes the partial file
+    pub max_attempts: u32,
+}
+
+impl Default for PullOptions {
+    fn default() -> Self {
+        Self {
+            force: false,
+            auth_token: None,
+            max_attempts: 5,
+        }
+    }
+}
+
+/// A LoRA adapter loaded on top of a base model
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AdapterHandle {
+    /// `base_model_id:adapter_name`
+    pub id: String,
+    pub base_model_id: String,
+    pub name: String,
+    pub max_rank: usize,
+    /// Modules the adapter updates
+    pub modules: usize,
+    /// Memory the adapter's own weights take
+    pub memory_usage: u64,
+}
+
+#[derive(Clone)]
+struct LoadedAdapter {
+    handle: AdapterHandle,
+    adapter: Arc<LoraAdapter>,
+}
+
+/// A model present in the local cache directory
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CachedModel {
+    pub info: ModelInfo,
+    /// Bytes the model occupies on disk
+    pub size_on_disk: u64,
+    /// Last time any process loaded the model
+    pub last_used: Option<chrono::DateTime<chrono::Utc>>,
+    /// Processes that currently have the model loaded
+    pub resident_in: Vec<u32>,
+}
+
+/// Metadata key recording when a model was last loaded
+const LAST_USED_KEY: &str = "last_used";
+
+/// Directory under the cache dir holding one marker file per process that has a model loaded
+const RESIDENCY_DIR: &str = ".resident";
+
+/// Metadata key holding the SHA-256 a catalog model's download must match
+const CHECKSUM_KEY: &str = "sha256";
+
+/// A pull refreshes its lock file this often
+const PULL_LOCK_HEARTBEAT: Duration = Duration::from_secs(5);
+
+/// A lock file not refreshed for this long was left by a pull that died
+const PULL_LOCK_STALE_AFTER: Duration = Duration::from_secs(30);
+
+/// How often a pull following another one checks on it
+const PULL_FOLLOW_INTERVAL: Duration = Duration::from_millis(500);
+
+/// A download that receives nothing for this long is retried
+const DOWNLOAD_STALL_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Overrides the client's timeout, which is far too short for a whole model
+const DOWNLOAD_REQUEST_TIMEOUT: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Per-model in-flight accounting used to drain before unloading
+#[derive(Debug, Default)]
+struct ModelActivity {
+    in_flight: AtomicUsize,
+    draining: AtomicBool,
+    idle: Notify,
+    replacement: std::sync::Mutex<Option<String>>,
+}
+
+impl ModelActivity {
+    /// Register a request; fails once draining has started
+    fn enter(&self) -> bool {
+        // Increment before checking `draining` so a concurrent drain either sees this
+        // request in `in_flight` or this request sees the drain flag
+        self.in_flight.fetch_add(1, Ordering::SeqCst);
+        if self.draining.load(Ordering::SeqCst) {
+            self.exit();
+            return false;
+        }
+        true
+    }
+
+    fn exit(&self) {
+        if self.in_flight.fetch_sub(1, Ordering::SeqCst) == 1 {
+            self.idle.notify_waiters();
+        }
+    }
+
+    async fn wait_idle(&self) {
+        loop {
+            let notified = self.idle.notified();
+            tokio::pin!(notified);
+            notified.as_mut().enable();
+            if self.in_flight.load(Ordering::SeqCst) == 0 {
+                return;
+            }
+            notified.await;
+        }
+    }
+}
+
+/// Active use of a loaded model; the model is not freed while any lease is alive
+pub struct ModelLease {
+    model_id: String,
+    model: Arc<RwLock<LoadedModel>>,
+    activity: Arc<ModelActivity>,
+    adapters: AdapterStack,
+}
+
+impl ModelLease {
+    /// Identifier of the leased model (the replacement when the request was migrated)
+    pub fn model_id(&self) -> &str {
+        &self.model_id
+    }
+
+    /// The leased model
+    pub fn model(&self) -> &Arc<RwLock<LoadedModel>> {
+        &self.model
+    }
+
+    /// Adapters to apply on top of the model's weights; empty for the plain model
+    pub fn adapters(&self) -> &AdapterStack {
+        &self.adapters
+    }
+
+    /// Output of the linear `module` for `input`: the base projection
+    /// `weight·input`, with `weight` row-major `[out_features, in_features]`,
+    /// plus the update of every adapter in the lease
+    pub fn linear(&self, module: &str, weight: &[f32], input: &[f32]) -> Vec<f32> {
+        let mut output: Vec<f32> = weight
+            .chunks_exact(input.len().max(1))
+            .map(|row| row.iter().zip(input).map(|(w, x)| w * x).sum())
+            .collect();
+        self.adapters.apply(module, input, &mut output);
+        output
+    }
+}
+
+impl Drop for ModelLease {
+    fn drop(&mut self) {
+        self.activity.exit();
+    }
+}
+
+impl std::fmt::Debug for ModelLease {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ModelLease").field("model_id", &self.model_id).finish()
+    }
+}
+
 /// Model manager for loading and caching models
 pub struct ModelManager {
     /// Cache directory for downloaded models
@@ -116,6 +343,12 @@ pub struct ModelManager {
     model_catalog: Arc<RwLock<std::collections::HashMap<String, ModelInfo>>>,
     /// Current memory usage
     current_memory_usage: Arc<std::sync::atomic::AtomicU64>,
+    /// In-flight request accounting per loaded model
+    model_activity: Arc<DashMap<String, Arc<ModelActivity>>>,
+    /// Per-model locks so concurrent callers share one load
+    loading: Arc<DashMap<String, Arc<tokio::sync::Mutex<()>>>>,
+    /// LoRA adapters by id, sharing their base models' weights
+    adapters: Arc<DashMap<String, LoadedAdapter>>,
     /// Download client
     http_client: reqwest::Client,
 }
@@ -136,6 +369,9 @@ impl ModelManager {
             loaded_models: Arc::new(DashMap::new()),
             model_catalog: Arc::new(RwLock::new(std::collections::HashMap::new())),
             current_memory_usage: Arc::new(std::sync::atomic::AtomicU64::new(0)),
+            model_activity: Arc::new(DashMap::new()),
+            loading: Arc::new(DashMap::new()),
+            adapters: Arc::new(DashMap::new()),
             http_client,
         };
 
@@ -149,7 +385,7 @@ impl ModelManager {
     /// Load model catalog from local or remote source
     async fn load_model_catalog(&self) -> Result<()> {
         // Try to load from local catalog file first
-        let catalog_path = self.cache_dir.join("model_catalog.json");
+        let catalog_path = self.catalog_path();
 
         if catalog_path.exists() {
             match self.load_local_catalog(&catalog_path).await {
@@ -254,6 +490,15 @@ impl ModelManager {
         Ok(())
     }
 
+    /// Directory downloaded models are stored in
+    pub fn cache_dir(&self) -> &Path {
+        &self.cache_dir
+    }
+
+    fn catalog_path(&self) -> PathBuf {
+        self.cache_dir.join("model_catalog.json")
+    }
+
     /// Get available models
     pub async fn list_models(&self) -> Vec<ModelInfo> {
         let catalog_guard = self.model_catalog.read().await;
@@ -267,15 +512,64 @@ impl ModelManager {
     }
 
     /// Load a model
+    ///
+    /// Concurrent calls for the same model share one load: the first caller
+    /// loads while the others wait on the model's lock and then find it loaded.
     pub async fn load_model(&self, model_id: &str) -> AIResult<Arc<RwLock<LoadedModel>>> {
-        // Check if model is already loaded
-        if let Some(loaded_model) = self.loaded_models.get(model_id) {
-            let mut model_guard = loaded_model.write().await;
-            model_guard.last_accessed = std::time::Instant::now();
-            model_guard.ref_count += 1;
-            return Ok(loaded_model.clone());
+        if let Some(loaded_model) = self.touch_loaded(model_id).await? {
+            return Ok(loaded_model);
         }
 
+        let lock = self.loading_lock(model_id);
+        let result = {
+            let _loading = lock.lock().await;
+            match self.touch_loaded(model_id).await {
+                Ok(Some(loaded_model)) => Ok(loaded_model),
+                Ok(None) => self.load_model_locked(model_id).await,
+                Err(e) => Err(e),
+            }
+        };
+        self.release_loading_lock(model_id, lock);
+        result
+    }
+
+    /// Bump the access time and reference count of an already loaded model
+    async fn touch_loaded(&self, model_id: &str) -> AIResult<Option<Arc<RwLock<LoadedModel>>>> {
+        let Some(loaded_model) = self.loaded_models.get(model_id).map(|m| m.clone()) else {
+            return Ok(None);
+        };
+        if self.is_draining(model_id) {
+            return Err(AIEngineError::ModelUnloading {
+                model: model_id.to_string(),
+            });
+        }
+        let mut model_guard = loaded_model.write().await;
+        model_guard.last_accessed = std::time::Instant::now();
+        model_guard.ref_count += 1;
+        drop(model_guard);
+        Ok(Some(loaded_model))
+    }
+
+    fn loading_lock(&self, model_id: &str) -> Arc<tokio::sync::Mutex<()>> {
+        self.loading.entry(model_id.to_string()).or_default().clone()
+    }
+
+    /// Drop the caller's handle on a model's lock, removing it once nobody waits on it
+    fn release_loading_lock(&self, model_id: &str, lock: Arc<tokio::sync::Mutex<()>>) {
+        drop(lock);
+        self.loading.remove_if(model_id, |_, lock| Arc::strong_count(lock) == 1);
+    }
+
+    /// Register `loaded_model` for use; its activity goes in first so a model
+    /// that is visible always has request accounting
+    fn register_loaded(&self, model_id: &str, loaded_model: Arc<RwLock<LoadedModel>>) {
+        self.model_activity
+            .insert(model_id.to_string(), Arc::new(ModelActivity::default()));
+        self.loaded_models.insert(model_id.to_string(), loaded_model);
+    }
+
+    /// Load a model that is not loaded yet; the caller holds its loading lock
+    async fn load_model_locked(&self, model_id: &str) -> AIResult<Arc<RwLock<LoadedModel>>> {
         // Get model info from catalog
         let model_info = {
             let catalog_guard = self.model_catalog.read().await;
@@ -289,19 +583,6 @@ impl ModelManager {
         // Check memory requirements
         self.ensure_memory_available(model_info.memory_requirements)?;
 
-        // Create loaded model entry
-        let loaded_model = Arc::new(RwLock::new(LoadedModel {
-            info: model_info.clone(),
-            state: ModelState::Loading,
-            last_accessed: std::time::Instant::now(),
-            memory_usage: model_info.memory_requirements,
-            ref_count: 1,
-            model_data: None,
-        }));
-
-        self.loaded_models
-            .insert(model_id.to_string(), loaded_model.clone());
-
         // Download model if not available locally
         if model_info.local_path.is_none() || !model_info.local_path.as_ref().unwrap().exists() {
             if let Some(remote_url) = &model_info.remote_url {
@@ -312,11 +593,16 @@ impl ModelManager {
         // Simulate model loading
         tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
 
-        // Update model state
-        {
-            let mut model_guard = loaded_model.write().await;
-            model_guard.state = ModelState::Loaded;
-        }
+        // Only a fully loaded model becomes visible to other callers
+        let loaded_model = Arc::new(RwLock::new(LoadedModel {
+            info: model_info.clone(),
+            state: ModelState::Loaded,
+            last_accessed: std::time::Instant::now(),
+            memory_usage: model_info.memory_requirements,
+            ref_count: 1,
+            model_data: None,
+        }));
+        self.register_loaded(model_id, loaded_model.clone());
 
         // Update memory usage
         self.current_memory_usage.fetch_add(
@@ -324,27 +610,427 @@ impl ModelManager {
             std::sync::atomic::Ordering::Relaxed,
         );
 
+        self.record_residency(model_id).await;
         info!("Model {} loaded successfully", model_id);
         Ok(loaded_model)
     }
 
-    /// Unload a model
-    pub async fn unload_model(&self, model_id: &str) -> AIResult<()> {
-        if let Some((_, loaded_model)) = self.loaded_models.remove(model_id) {
-            let mut model_guard = loaded_model.write().await;
-            model_guard.state = ModelState::Unloading;
+    /// Load a local GGUF model into the Candle backend
+    ///
+    /// The header is validated before any weights are read: the file must be
+    /// GGUF v2 or v3, name its architecture, use only quantizations Candle
+    /// supports, and hold every tensor it declares. `quantization` is the
+    /// encoding the caller expects for the bulk of the weights (`Q4_K` for a
+    /// `Q4_K_M` file) and must match the file. The model is registered under
+    /// its `general.name`, or the file stem, and can then be acquired for
+    /// inference like any other model.
+    pub async fn load_gguf(&self, path: impl AsRef<Path>, quantization: GgufQuantization) -> AIResult<Arc<RwLock<LoadedModel>>> {
+        if !quantization.is_supported() {
+            return Err(AIEngineError::UnsupportedQuantization {
+                quantization: quantization.to_string(),
+                supported: crate::gguf::supported_list(),
+            });
+        }
+        let path = path.as_ref().to_path_buf();
+        let file = {
+            let path = path.clone();
+            tokio::task::spawn_blocking(move || {
+                let file = GgufFile::open(&path)?;
+                file.validate()?;
+                Ok::<_, AIEngineError>(file)
+            })
+            .await??
+        };
+
+        let found = file.quantization();
+        if found != Some(quantization) {
+            return Err(AIEngineError::ModelLoadingFailed {
+                model: path.display().to_string(),
+                reason: format!(
+                    "expected {} weights but the file is {}",
+                    quantization,
+                    found.map(|q| q.to_string()).unwrap_or_else(|| "empty".to_string())
+                ),
+            });
+        }
+
+        let model_id = gguf_model_id(&file);
+        let lock = self.loading_lock(&model_id);
+        let result = {
+            let _loading = lock.lock().await;
+            self.register_gguf_locked(&model_id, &path, &file, quantization).await
+        };
+        self.release_loading_lock(&model_id, lock);
+        result
+    }
+
+    /// Map and register a validated GGUF file; the caller holds its loading lock
+    async fn register_gguf_locked(
+        &self,
+        model_id: &str,
+        path: &Path,
+        file: &GgufFile,
+        quantization: GgufQuantization,
+    ) -> AIResult<Arc<RwLock<LoadedModel>>> {
+        let model_id = model_id.to_string();
+        if let Some(loaded) = self.loaded_models.get(&model_id).map(|m| m.clone()) {
+            if loaded.read().await.info.local_path.as_deref() != Some(path) {
+                return Err(AIEngineError::ModelLoadingFailed {
+                    model: model_id,
+                    reason: "a different model with this id is already loaded".to_string(),
+                });
+            }
+            return self
+                .touch_loaded(&model_id)
+                .await?
+                .ok_or(AIEngineError::ModelUnloading { model: model_id });
+        }
+
+        let memory_requirements = file.data_size();
+        self.ensure_memory_available(memory_requirements)?;
+        let model_data = self.map_gguf_weights(file).await?;
+
+        let mut metadata: std::collections::HashMap<String, serde_json::Value> = file.catalog_metadata().collect();
+        metadata.insert("quantization".to_string(), serde_json::json!(quantization.to_string()));
+        if let Some(context_length) = file.context_length() {
+            metadata.insert("context_length".to_string(), serde_json::json!(context_length));
+        }
+        let model_info = ModelInfo {
+            id: model_id.clone(),
+            name: file.name().unwrap_or(&model_id).to_string(),
+            version: format!("gguf-v{}", file.version),
+            description: format!("{} model loaded from {}", file.architecture().unwrap_or("unknown"), path.display()),
+            model_type: ModelType::Text,
+            tasks: vec!["text-generation".to_string()],
+            size_bytes: file.file_len,
+            memory_requirements,
+            local_path: Some(path.to_path_buf()),
+            remote_url: None,
+            format: ModelFormat::GGUF,
+            metadata,
+        };
+        self.model_catalog.write().await.insert(model_id.clone(), model_info.clone());
+
+        let loaded_model = Arc::new(RwLock::new(LoadedModel {
+            info: model_info,
+            state: ModelState::Loaded,
+            last_accessed: std::time::Instant::now(),
+            memory_usage: memory_requirements,
+            ref_count: 1,
+            model_data: Some(model_data),
+        }));
+        self.register_loaded(&model_id, loaded_model.clone());
+        self.current_memory_usage.fetch_add(memory_requirements, Ordering::Relaxed);
+
+        self.record_residency(&model_id).await;
+        info!(
+            "Loaded GGUF model {} ({} tensors, {}) from {}",
+            model_id,
+            file.tensors.len(),
+            quantization,
+            path.display()
+        );
+        Ok(loaded_model)
+    }
+
+    #[cfg(feature = "candle")]
+    async fn map_gguf_weights(&self, file: &GgufFile) -> AIResult<Arc<dyn Send + Sync>> {
+        let file = file.clone();
+        let weights = tokio::task::spawn_blocking(move || {
+            crate::gguf::GgufWeights::load(&file, &candle_core::Device::Cpu)
+        })
+        .await??;
+        Ok(Arc::new(weights))
+    }
+
+    #[cfg(not(feature = "candle"))]
+    async fn map_gguf_weights(&self, _file: &GgufFile) -> AIResult<Arc<dyn Send + Sync>> {
+        Err(AIEngineError::BackendNotAvailable {
+            backend: "candle (build aion-ai-engine with the `candle` feature to load GGUF models)".to_string(),
+        })
+    }
+
+    /// Lease a model for one request, loading it if needed.
+    ///
+    /// While the model drains, requests are migrated to the replacement model if
+    /// one was given to `unload_model`, and rejected with `ModelUnloading` otherwise.
+    pub async fn acquire(&self, model_id: &str) -> AIResult<ModelLease> {
+        let mut target = model_id.to_string();
+        // Bounded so a cycle of replacements can't loop forever
+        for _ in 0..4 {
+            if !self.loaded_models.contains_key(&target) {
+                self.load_model(&target).await?;
+            }
+
+            let model = self.loaded_models.get(&target).map(|m| m.clone());
+            let activity = self.model_activity.get(&target).map(|a| a.clone());
+            let (Some(model), Some(activity)) = (model, activity) else {
+                return Err(AIEngineError::ModelUnloading { model: target });
+            };
+
+            if activity.enter() {
+                return Ok(ModelLease {
+                    model_id: target,
+                    model,
+                    activity,
+                    adapters: AdapterStack::new(),
+                });
+            }
+
+            let replacement = activity.replacement.lock().unwrap().clone();
+            match replacement {
+                Some(replacement) => {
+                    debug!("Model {} is draining, migrating request to {}", target, replacement);
+                    target = replacement;
+                }
+                None => return Err(AIEngineError::ModelUnloading { model: target }),
+            }
+        }
+
+        Err(AIEngineError::ModelUnloading {
+            model: model_id.to_string(),
+        })
+    }
+
+    /// Number of requests currently using a model
+    pub fn in_flight_requests(&self, model_id: &str) -> usize {
+        self.model_activity
+            .get(model_id)
+            .map(|a| a.in_flight.load(Ordering::SeqCst))
+            .unwrap_or(0)
+    }
+
+    fn is_draining(&self, model_id: &str) -> bool {
+        self.model_activity
+            .get(model_id)
+            .map(|a| a.draining.load(Ordering::SeqCst))
+            .unwrap_or(false)
+    }
+
+    /// Unload a model after draining in-flight requests.
+    ///
+    /// New requests stop being accepted immediately. The model's resources are
+    /// freed once the last in-flight request finishes; if that takes longer than
+    /// `options.timeout` the model is detached from the manager right away and
+    /// freed in the background when the remaining requests complete.
+    pub async fn unload_model(&self, model_id: &str, options: DrainOptions) -> AIResult<DrainReport> {
+        let Some(activity) = self.model_activity.get(model_id).map(|a| a.clone()) else {
+            return Ok(DrainReport {
+                model_id: model_id.to_string(),
+                drained: true,
+                outstanding_requests: 0,
+                waited_ms: 0,
+            });
+        };
+
+        if let Some(replacement) = &options.replacement_model {
+            if replacement == model_id {
+                return Err(AIEngineError::ConfigurationError {
+                    field: "replacement_model".to_string(),
+                    reason: "a model cannot replace itself".to_string(),
+                });
+            }
+            // Make sure migrated requests have somewhere to go before cutting traffic over
+            if !self.loaded_models.contains_key(replacement) {
+                self.load_model(replacement).await?;
+            }
+        }
+        *activity.replacement.lock().unwrap() = options.replacement_model.clone();
+        activity.draining.store(true, Ordering::SeqCst);
+
+        if let Some(model) = self.loaded_models.get(model_id).map(|m| m.clone()) {
+            model.write().await.state = ModelState::Unloading;
+        }
+
+        let started = std::time::Instant::now();
+        let drained = tokio::time::timeout(options.timeout, activity.wait_idle()).await.is_ok();
+        let outstanding_requests = activity.in_flight.load(Ordering::SeqCst);
+
+        // Detach first so nothing new can find the model, then account for the memory
+        self.model_activity.remove(model_id);
+        self.unload_adapters_of(model_id);
+        self.clear_residency(model_id).await;
+        let Some((_, model)) = self.loaded_models.remove(model_id) else {
+            return Ok(DrainReport {
+                model_id: model_id.to_string(),
+                drained,
+                outstanding_requests,
+                waited_ms: started.elapsed().as_millis() as u64,
+            });
+        };
+        let memory_usage = model.read().await.memory_usage;
+        self.current_memory_usage
+            .fetch_sub(memory_usage, std::sync::atomic::Ordering::Relaxed);
 
-            // Update memory usage
-            self.current_memory_usage.fetch_sub(
-                model_guard.memory_usage,
-                std::sync::atomic::Ordering::Relaxed,
+        if drained {
+            Self::free_model(&model).await;
+            info!("Model {} drained and unloaded", model_id);
+        } else {
+            warn!(
+                "Model {} still has {} in-flight requests after {:?}; freeing when they finish",
+                model_id, outstanding_requests, options.timeout
             );
+            let model_id = model_id.to_string();
+            tokio::spawn(async move {
+                activity.wait_idle().await;
+                Self::free_model(&model).await;
+                info!("Model {} unloaded after late requests finished", model_id);
+            });
+        }
+
+        Ok(DrainReport {
+            model_id: model_id.to_string(),
+            drained,
+            outstanding_requests,
+            waited_ms: started.elapsed().as_millis() as u64,
+        })
+    }
 
-            model_guard.state = ModelState::NotLoaded;
-            info!("Model {} unloaded", model_id);
+    /// Load a LoRA adapter onto a base model, loading the base model if needed
+    ///
+    /// The adapter is checked against the module shapes the base model's
+    /// catalog entry declares (`linear_modules` and `num_hidden_layers`).
+    /// Adapters share the base model's weights, so each one only costs its
+    /// low-rank matrices. Loading an adapter whose id is already loaded swaps
+    /// in the new weights; requests holding the old ones finish with them.
+    pub async fn load_adapter(&self, base_model_id: &str, adapter_path: impl AsRef<Path>) -> AIResult<AdapterHandle> {
+        let base_info = self.get_model_info(base_model_id).await.ok_or_else(|| AIEngineError::ModelNotFound {
+            model: base_model_id.to_string(),
+        })?;
+        let adapter_path = adapter_path.as_ref().to_path_buf();
+        let adapter = tokio::task::spawn_blocking(move || LoraAdapter::load(&adapter_path)).await??;
+
+        let shape = BaseModelShape::from_metadata(&base_info.metadata).ok_or_else(|| AIEngineError::AdapterIncompatible {
+            adapter: adapter.name.clone(),
+            base: base_model_id.to_string(),
+            reason: format!(
+                "the base model's catalog entry does not describe its modules (metadata `{}`), so the adapter cannot be checked",
+                LINEAR_MODULES_KEY
+            ),
+        })?;
+        adapter.check_compatible(base_model_id, &shape)?;
+        if let Some(trained_on) = &adapter.config.base_model_name_or_path {
+            if trained_on != base_model_id && trained_on != &base_info.name {
+                warn!("Adapter {} was trained on {}, applying it to {}", adapter.name, trained_on, base_model_id);
+            }
         }
 
-        Ok(())
+        if self.is_draining(base_model_id) {
+            return Err(AIEngineError::ModelUnloading {
+                model: base_model_id.to_string(),
+            });
+        }
+        if !self.loaded_models.contains_key(base_model_id) {
+            self.load_model(base_model_id).await?;
+        }
+
+        let id = format!("{}:{}", base_model_id, adapter.name);
+        let memory_usage = adapter.size_bytes();
+        let replaced_usage = self.adapters.get(&id).map(|loaded| loaded.handle.memory_usage).unwrap_or(0);
+        self.ensure_memory_available(memory_usage.saturating_sub(replaced_usage))?;
+
+        let handle = AdapterHandle {
+            id: id.clone(),
+            base_model_id: base_model_id.to_string(),
+            name: adapter.name.clone(),
+            max_rank: adapter.max_rank(),
+            modules: adapter.modules().count(),
+            memory_usage,
+        };
+        let loaded = LoadedAdapter {
+            handle: handle.clone(),
+            adapter: Arc::new(adapter),
+        };
+        self.current_memory_usage.fetch_add(memory_usage, Ordering::Relaxed);
+        match self.adapters.insert(id.clone(), loaded) {
+            Some(replaced) => {
+                self.current_memory_usage.fetch_sub(replaced.handle.memory_usage, Ordering::Relaxed);
+                info!("Swapped adapter {} ({} modules, rank {})", id, handle.modules, handle.max_rank);
+            }
+            None => info!("Loaded adapter {} ({} modules, rank {})", id, handle.modules, handle.max_rank),
+        }
+        Ok(handle)
+    }
+
+    /// Unload an adapter; returns whether it was loaded
+    pub fn unload_adapter(&self, adapter_id: &str) -> bool {
+        let Some((_, loaded)) = self.adapters.remove(adapter_id) else {
+            return false;
+        };
+        self.current_memory_usage.fetch_sub(loaded.handle.memory_usage, Ordering::Relaxed);
+        info!("Unloaded adapter {}", adapter_id);
+        true
+    }
+
+    /// Adapters loaded onto a base model
+    pub fn adapters(&self, base_model_id: &str) -> Vec<AdapterHandle> {
+        let mut handles: Vec<AdapterHandle> = self
+            .adapters
+            .iter()
+            .filter(|loaded| loaded.handle.base_model_id == base_model_id)
+            .map(|loaded| loaded.handle.clone())
+            .collect();
+        handles.sort_by(|a, b| a.id.cmp(&b.id));
+        handles
+    }
+
+    fn unload_adapters_of(&self, base_model_id: &str) {
+        for handle in self.adapters(base_model_id) {
+            self.unload_adapter(&handle.id);
+        }
+    }
+
+    /// Lease a base model with adapters stacked on it, each scaled by its weight
+    ///
+    /// Unlike `acquire`, a draining model is not migrated to its replacement,
+    /// because the adapters only fit the base they were loaded onto.
+    ///
+    /// Adapters take effect in forward passes that compute their linear
+    /// modules through `ModelLease::linear`. The inference engine's Candle
+    /// text path does not run a model forward pass yet, so its output does not
+    /// reflect the adapters.
+    pub async fn acquire_with_adapters(&self, base_model_id: &str, adapters: &[(&str, f32)]) -> AIResult<ModelLease> {
+        let mut stack = AdapterStack::new();
+        for &(adapter_id, weight) in adapters {
+            let loaded = self
+                .adapters
+                .get(adapter_id)
+                .map(|loaded| loaded.clone())
+                .ok_or_else(|| AIEngineError::ModelNotFound {
+                    model: adapter_id.to_string(),
+                })?;
+            if loaded.handle.base_model_id != base_model_id {
+                return Err(AIEngineError::AdapterIncompatible {
+                    adapter: adapter_id.to_string(),
+                    base: base_model_id.to_string(),
+                    reason: format!("it is loaded onto {}", loaded.handle.base_model_id),
+                });
+            }
+            if !weight.is_finite() {
+                return Err(AIEngineError::InvalidInputFormat {
+                    model: adapter_id.to_string(),
+                    reason: format!("adapter weight must be finite, got {}", weight),
+                });
+            }
+            stack.push(loaded.adapter, weight);
+        }
+
+        let mut lease = self.acquire(base_model_id).await?;
+        if lease.model_id() != base_model_id {
+            return Err(AIEngineError::ModelUnloading {
+                model: base_model_id.to_string(),
+            });
+        }
+        lease.adapters = stack;
+        Ok(lease)
+    }
+
+    /// Release model data; only called once no lease can observe the model
+    async fn free_model(model: &Arc<RwLock<LoadedModel>>) {
+        let mut model_guard = model.write().await;
+        model_guard.model_data = None;
+        model_guard.ref_count = 0;
+        model_guard.state = ModelState::NotLoaded;
     }
 
     /// Download a model from remote URL
@@ -369,6 +1055,434 @@ impl ModelManager {
         Ok(())
     }
 
+    /// Download a model into the cache with default `PullOptions`
+    pub async fn pull_model<F>(&self, source: &str, on_progress: F) -> AIResult<ModelInfo>
+    where
+        F: FnMut(DownloadProgress),
+    {
+        self.pull_model_with(source, &PullOptions::default(), on_progress).await
+    }
+
+    /// Download a model into the cache, reporting progress as bytes arrive
+    ///
+    /// `source` is either a catalog model id or a URL; a URL that is not in the
+    /// catalog is added to it under the id taken from its last path segment.
+    /// Interrupted downloads are retried and resumed from the partial file,
+    /// and the result is verified against the catalog's or the server's
+    /// SHA-256 before it replaces the cached copy. If the same model is
+    /// already being pulled, by this or another process, this call follows
+    /// that download instead of starting a second one.
+    pub async fn pull_model_with<F>(&self, source: &str, options: &PullOptions, mut on_progress: F) -> AIResult<ModelInfo>
+    where
+        F: FnMut(DownloadProgress),
+    {
+        let mut model_info = self.resolve_source(source).await?;
+        if !options.force && model_info.local_path.as_ref().is_some_and(|path| path.exists()) {
+            debug!("Model {} is already cached", model_info.id);
+            return Ok(model_info);
+        }
+        let url = model_info.remote_url.clone().ok_or_else(|| AIEngineError::ConfigurationError {
+            field: "remote_url".to_string(),
+            reason: format!("model {} has no download URL", model_info.id),
+        })?;
+
+        // Download next to the final path so an interrupted pull never looks cached
+        let model_path = self.cache_dir.join(format!("{}.model", model_info.id));
+        let partial_path = model_path.with_extension("model.part");
+        let lock = loop {
+            if let Some(lock) = PullLock::acquire(&model_path).await? {
+                break lock;
+            }
+            info!("Model {} is already being pulled, following that download", model_info.id);
+            if let Some(pulled) = self.follow_pull(&model_info.id, &model_path, &mut on_progress).await? {
+                return Ok(pulled);
+            }
+            info!("Earlier pull of model {} did not finish, taking over", model_info.id);
+        };
+
+        if options.force {
+            remove_if_exists(&partial_path).await?;
+        }
+
+        info!("Pulling model {} from {}", model_info.id, url);
+        let published = self
+            .download_resumable(&model_info.id, &url, &partial_path, &lock, options, &mut on_progress)
+            .await?;
+
+        let downloaded_bytes = tokio::fs::metadata(&partial_path).await?.len();
+        on_progress(DownloadProgress {
+            downloaded_bytes,
+            total_bytes: Some(downloaded_bytes),
+            phase: DownloadPhase::Verifying,
+        });
+        let expected = model_info
+            .metadata
+            .get(CHECKSUM_KEY)
+            .and_then(|value| value.as_str())
+            .map(str::to_lowercase)
+            .or(published);
+        match expected {
+            Some(expected) => {
+                let actual = sha256_file(partial_path.clone()).await?;
+                if actual != expected {
+                    remove_if_exists(&partial_path).await?;
+                    return Err(AIEngineError::ChecksumMismatch {
+                        model: model_info.id.clone(),
+                        expected,
+                        actual,
+                    });
+                }
+                debug!("Model {} matches checksum {}", model_info.id, expected);
+            }
+            None => warn!("No checksum is published for model {}; download not verified", model_info.id),
+        }
+        tokio::fs::rename(&partial_path, &model_path).await?;
+
+        model_info.local_path = Some(model_path);
+        model_info.size_bytes = downloaded_bytes;
+        self.record_pulled_model(model_info.clone()).await?;
+        drop(lock);
+
+        info!("Model {} pulled ({} bytes)", model_info.id, downloaded_bytes);
+        Ok(model_info)
+    }
+
+    /// Download `url` into `partial_path`, retrying transient failures and
+    /// resuming after the bytes already on disk; returns the checksum the
+    /// server published, if any
+    async fn download_resumable<F>(
+        &self,
+        model_id: &str,
+        url: &str,
+        partial_path: &Path,
+        lock: &PullLock,
+        options: &PullOptions,
+        on_progress: &mut F,
+    ) -> AIResult<Option<String>>
+    where
+        F: FnMut(DownloadProgress),
+    {
+        let mut failures = 0;
+        loop {
+            let before = file_len(partial_path).await;
+            let error = match self.download_attempt(model_id, url, partial_path, lock, options, on_progress).await {
+                Ok(published) => return Ok(published),
+                Err(e) => e,
+            };
+            // Only consecutive attempts without progress count against the limit
+            if file_len(partial_path).await > before {
+                failures = 0;
+            }
+            failures += 1;
+            if !is_transient_download_error(&error) || failures >= options.max_attempts {
+                return Err(error);
+            }
+            let delay = Duration::from_secs(1 << failures.min(5));
+            warn!("Download of model {} interrupted ({}), resuming in {:?}", model_id, error, delay);
+            tokio::time::sleep(delay).await;
+        }
+    }
+
+    async fn download_attempt<F>(
+        &self,
+        model_id: &str,
+        url: &str,
+        partial_path: &Path,
+        lock: &PullLock,
+        options: &PullOptions,
+        on_progress: &mut F,
+    ) -> AIResult<Option<String>>
+    where
+        F: FnMut(DownloadProgress),
+    {
+        let mut resumed_from = file_len(partial_path).await;
+        let mut response = self.download_request(url, resumed_from, options).send().await?;
+        if response.status() == reqwest::StatusCode::RANGE_NOT_SATISFIABLE {
+            debug!("Partial download of model {} no longer matches the remote file, starting over", model_id);
+            resumed_from = 0;
+            response = self.download_request(url, 0, options).send().await?;
+        }
+
+        let status = response.status();
+        if status == reqwest::StatusCode::UNAUTHORIZED || status == reqwest::StatusCode::FORBIDDEN {
+            return Err(AIEngineError::ModelAccessDenied {
+                model: model_id.to_string(),
+                status: status.as_u16(),
+                hint: access_hint(url, options.auth_token.is_some()),
+            });
+        }
+        let response = response.error_for_status()?;
+
+        // A server that ignores the range sends the whole file again
+        let resuming = status == reqwest::StatusCode::PARTIAL_CONTENT;
+        if !resuming {
+            resumed_from = 0;
+        }
+        let total_bytes = resuming
+            .then(|| content_range_total(response.headers()))
+            .flatten()
+            .or_else(|| response.content_length().map(|len| len + resumed_from));
+        lock.set_total_bytes(total_bytes);
+        let published = published_checksum(response.headers());
+
+        let mut file = if resuming {
+            tokio::fs::OpenOptions::new().append(true).open(partial_path).await?
+        } else {
+            tokio::fs::File::create(partial_path).await?
+        };
+        let phase = DownloadPhase::Downloading { resumed_from };
+        let mut downloaded_bytes = resumed_from;
+        on_progress(DownloadProgress { downloaded_bytes, total_bytes, phase });
+
+        let mut stream = response.bytes_stream();
+        loop {
+            let chunk = match tokio::time::timeout(DOWNLOAD_STALL_TIMEOUT, stream.next()).await {
+                Ok(Some(chunk)) => chunk?,
+                Ok(None) => break,
+                Err(_) => {
+                    file.flush().await?;
+                    return Err(AIEngineError::Io(std::io::Error::new(
+                        std::io::ErrorKind::TimedOut,
+                        format!("no data received for {:?}", DOWNLOAD_STALL_TIMEOUT),
+                    )));
+                }
+            };
+            file.write_all(&chunk).await?;
+            downloaded_bytes += chunk.len() as u64;
+            on_progress(DownloadProgress { downloaded_bytes, total_bytes, phase });
+        }
+        file.flush().await?;
+
+        if total_bytes.is_some_and(|total| downloaded_bytes < total) {
+            return Err(AIEngineError::Io(std::io::Error::new(
+                std::io::ErrorKind::UnexpectedEof,
+                format!("connection closed after {} of {} bytes", downloaded_bytes, total_bytes.unwrap_or(0)),
+            )));
+        }
+        Ok(published)
+    }
+
+    fn download_request(&self, url: &str, offset: u64, options: &PullOptions) -> reqwest::RequestBuilder {
+        let mut request = self.http_client.get(url).timeout(DOWNLOAD_REQUEST_TIMEOUT);
+        if offset > 0 {
+            request = request.header(reqwest::header::RANGE, format!("bytes={}-", offset));
+        }
+        // Only Hugging Face gets the token; any other host would just see it leak
+        if let Some(token) = options.auth_token.as_deref().filter(|_| is_huggingface_url(url)) {
+            request = request.bearer_auth(token);
+        }
+        request
+    }
+
+    /// Follow another pull of a model until it ends; returns the model if that
+    /// pull cached it, or `None` if it failed and this pull should take over
+    async fn follow_pull<F>(&self, model_id: &str, model_path: &Path, on_progress: &mut F) -> AIResult<Option<ModelInfo>>
+    where
+        F: FnMut(DownloadProgress),
+    {
+        let lock_path = PullLock::path_for(model_path);
+        let partial_path = model_path.with_extension("model.part");
+        while let Some((state, age)) = PullLock::read(&lock_path).await {
+            if age > PULL_LOCK_STALE_AFTER {
+                warn!("Pull of model {} by process {} stopped responding", model_id, state.pid);
+                remove_if_exists(&lock_path).await?;
+                break;
+            }
+            on_progress(DownloadProgress {
+                downloaded_bytes: file_len(&partial_path).await,
+                total_bytes: state.total_bytes,
+                phase: DownloadPhase::Attached,
+            });
+            tokio::time::sleep(PULL_FOLLOW_INTERVAL).await;
+        }
+        Ok(self.reload_pulled_model(model_id).await)
+    }
+
+    /// The model as another pull left it in the catalog file, if it is now cached
+    async fn reload_pulled_model(&self, model_id: &str) -> Option<ModelInfo> {
+        let content = tokio::fs::read_to_string(self.catalog_path()).await.ok()?;
+        let mut on_disk: std::collections::HashMap<String, ModelInfo> = serde_json::from_str(&content).ok()?;
+        let model_info = on_disk.remove(model_id).filter(|info| info.local_path.as_ref().is_some_and(|path| path.exists()))?;
+        self.model_catalog.write().await.insert(model_id.to_string(), model_info.clone());
+        Some(model_info)
+    }
+
+    /// Add a pulled model to the catalog, keeping entries other processes
+    /// have saved since this one loaded it
+    async fn record_pulled_model(&self, model_info: ModelInfo) -> AIResult<()> {
+        let catalog_path = self.catalog_path();
+        let mut catalog = self.model_catalog.write().await;
+        if let Ok(content) = tokio::fs::read_to_string(&catalog_path).await {
+            match serde_json::from_str::<std::collections::HashMap<String, ModelInfo>>(&content) {
+                Ok(on_disk) => catalog.extend(on_disk),
+                Err(e) => warn!("Ignoring unreadable catalog {}: {}", catalog_path.display(), e),
+            }
+        }
+        catalog.insert(model_info.id.clone(), model_info);
+        tokio::fs::write(&catalog_path, serde_json::to_string_pretty(&*catalog)?).await?;
+        Ok(())
+    }
+
+    async fn resolve_source(&self, source: &str) -> AIResult<ModelInfo> {
+        if let Some(model_info) = self.get_model_info(source).await {
+            return Ok(model_info);
+        }
+        if !(source.starts_with("https://") || source.starts_with("http://")) {
+            return Err(AIEngineError::ModelNotFound {
+                model: source.to_string(),
+            });
+        }
+
+        let file_name = source.trim_end_matches('/').rsplit('/').next().unwrap_or_default();
+        let id = file_name.split('.').next().unwrap_or_default();
+        if id.is_empty() {
+            return Err(AIEngineError::InvalidInputFormat {
+                model: source.to_string(),
+                reason: "cannot derive a model id from the URL".to_string(),
+            });
+        }
+        // Pulled from this URL before
+        if let Some(model_info) = self.get_model_info(id).await.filter(|info| info.remote_url.as_deref() == Some(source)) {
+            return Ok(model_info);
+        }
+
+        Ok(ModelInfo {
+            id: id.to_string(),
+            name: id.to_string(),
+            version: "unknown".to_string(),
+            description: format!("Pulled from {}", source),
+            model_type: ModelType::Text,
+            tasks: Vec::new(),
+            size_bytes: 0,
+            memory_requirements: 0,
+            local_path: None,
+            remote_url: Some(source.to_string()),
+            format: ModelFormat::Custom(file_name.rsplit_once('.').map(|(_, ext)| ext).unwrap_or("bin").to_string()),
+            metadata: std::collections::HashMap::new(),
+        })
+    }
+
+    /// Models that are present in the cache directory
+    pub async fn cached_models(&self) -> Vec<CachedModel> {
+        let catalog: Vec<ModelInfo> = self.model_catalog.read().await.values().cloned().collect();
+
+        let mut cached = Vec::new();
+        for info in catalog {
+            let Some(path) = info.local_path.clone().filter(|p| p.exists()) else {
+                continue;
+            };
+            let size_on_disk = tokio::task::spawn_blocking(move || disk_usage(&path))
+                .await
+                .unwrap_or(0);
+            let last_used = info
+                .metadata
+                .get(LAST_USED_KEY)
+                .and_then(|v| v.as_str())
+                .and_then(|v| chrono::DateTime::parse_from_rfc3339(v).ok())
+                .map(|v| v.with_timezone(&chrono::Utc));
+            let resident_in = self.model_residency(&info.id).await;
+            cached.push(CachedModel {
+                info,
+                size_on_disk,
+                last_used,
+                resident_in,
+            });
+        }
+        cached.sort_by(|a, b| a.info.id.cmp(&b.info.id));
+        cached
+    }
+
+    /// Delete a model's files from the cache, returning the bytes freed
+    ///
+    /// The model is drained and unloaded first if this manager has it loaded.
+    /// Other processes are not consulted; check `model_residency` beforehand.
+    pub async fn remove_cached_model(&self, model_id: &str) -> AIResult<u64> {
+        let path = self
+            .get_model_info(model_id)
+            .await
+            .and_then(|info| info.local_path)
+            .filter(|path| path.exists())
+            .ok_or_else(|| AIEngineError::ModelNotFound {
+                model: model_id.to_string(),
+            })?;
+
+        if self.loaded_models.contains_key(model_id) {
+            self.unload_model(model_id, DrainOptions::default()).await?;
+        }
+
+        let freed = {
+            let path = path.clone();
+            tokio::task::spawn_blocking(move || disk_usage(&path)).await?
+        };
+        if path.is_dir() {
+            tokio::fs::remove_dir_all(&path).await?;
+        } else {
+            tokio::fs::remove_file(&path).await?;
+        }
+
+        if let Some(model_info) = self.model_catalog.write().await.get_mut(model_id) {
+            model_info.local_path = None;
+        }
+        self.save_catalog(&self.catalog_path()).await?;
+
+        info!("Removed cached model {} ({} bytes freed)", model_id, freed);
+        Ok(freed)
+    }
+
+    /// Process ids that currently have a model loaded, from their residency markers
+    pub async fn model_residency(&self, model_id: &str) -> Vec<u32> {
+        let prefix = format!("{}.", model_id);
+        let Ok(mut entries) = tokio::fs::read_dir(self.cache_dir.join(RESIDENCY_DIR)).await else {
+            return Vec::new();
+        };
+
+        let mut pids = Vec::new();
+        while let Ok(Some(entry)) = entries.next_entry().await {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if let Some(pid) = name.strip_prefix(&prefix).and_then(|pid| pid.parse().ok()) {
+                pids.push(pid);
+            }
+        }
+        pids
+    }
+
+    fn residency_marker(&self, model_id: &str) -> PathBuf {
+        self.cache_dir
+            .join(RESIDENCY_DIR)
+            .join(format!("{}.{}", model_id, std::process::id()))
+    }
+
+    /// Publish that this process has the model loaded and stamp its last use
+    async fn record_residency(&self, model_id: &str) {
+        let marker = self.residency_marker(model_id);
+        let written = async {
+            tokio::fs::create_dir_all(self.cache_dir.join(RESIDENCY_DIR)).await?;
+            tokio::fs::write(&marker, b"").await
+        };
+        if let Err(e) = written.await {
+            warn!("Failed to record residency of model {}: {}", model_id, e);
+        }
+
+        if let Some(model_info) = self.model_catalog.write().await.get_mut(model_id) {
+            model_info.metadata.insert(
+                LAST_USED_KEY.to_string(),
+                serde_json::Value::String(chrono::Utc::now().to_rfc3339()),
+            );
+        }
+        if let Err(e) = self.save_catalog(&self.catalog_path()).await {
+            warn!("Failed to persist last use of model {}: {}", model_id, e);
+        }
+    }
+
+    async fn clear_residency(&self, model_id: &str) {
+        let marker = self.residency_marker(model_id);
+        if let Err(e) = tokio::fs::remove_file(&marker).await {
+            if e.kind() != std::io::ErrorKind::NotFound {
+                warn!("Failed to clear residency of model {}: {}", model_id, e);
+            }
+        }
+    }
+
     /// Ensure sufficient memory is available
     fn ensure_memory_available(&self, required_memory: u64) -> AIResult<()> {
         let current_usage = self.current_memory_usage.load(std::sync::atomic::Ordering::Relaxed);
@@ -417,7 +1531,7 @@ impl ModelManager {
     pub async fn remove_model_from_catalog(&self, model_id: &str) -> AIResult<()> {
         // Unload if currently loaded
         if self.loaded_models.contains_key(model_id) {
-            self.unload_model(model_id).await?;
+            self.unload_model(model_id, DrainOptions::default()).await?;
         }
 
         // Remove from catalog
@@ -428,6 +1542,222 @@ impl ModelManager {
     }
 }
 
+/// Contents of a pull lock file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct PullLockState {
+    pid: u32,
+    total_bytes: Option<u64>,
+}
+
+/// Marks a model as being pulled, so other pulls of it follow this one
+///
+/// The file is rewritten every `PULL_LOCK_HEARTBEAT`; one that goes without
+/// a refresh for `PULL_LOCK_STALE_AFTER` was left behind by a dead process.
+/// Dropping the lock removes the file.
+struct PullLock {
+    path: PathBuf,
+    /// `u64::MAX` while the size is unknown
+    total_bytes: Arc<AtomicU64>,
+    heartbeat: tokio::task::JoinHandle<()>,
+}
+
+impl PullLock {
+    fn path_for(model_path: &Path) -> PathBuf {
+        model_path.with_extension("model.lock")
+    }
+
+    /// Take the lock, or `None` if another pull holds it
+    async fn acquire(model_path: &Path) -> AIResult<Option<Self>> {
+        let path = Self::path_for(model_path);
+        let state = PullLockState {
+            pid: std::process::id(),
+            total_bytes: None,
+        };
+        let created = tokio::fs::OpenOptions::new().write(true).create_new(true).open(&path).await;
+        let mut file = match created {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        file.write_all(&serde_json::to_vec(&state)?).await?;
+
+        let total_bytes = Arc::new(AtomicU64::new(u64::MAX));
+        let heartbeat = {
+            let (path, total_bytes) = (path.clone(), total_bytes.clone());
+            tokio::spawn(async move {
+                loop {
+                    tokio::time::sleep(PULL_LOCK_HEARTBEAT).await;
+                    let total = total_bytes.load(Ordering::Relaxed);
+                    let state = PullLockState {
+                        pid: std::process::id(),
+                        total_bytes: (total != u64::MAX).then_some(total),
+                    };
+                    // Never recreate the file: it may just have been released
+                    let path = path.clone();
+                    let refreshed = tokio::task::spawn_blocking(move || {
+                        use std::io::Write;
+                        let mut file = std::fs::OpenOptions::new().write(true).truncate(true).open(&path)?;
+                        file.write_all(&serde_json::to_vec(&state)?)
+                    })
+                    .await;
+                    if !matches!(refreshed, Ok(Ok(()))) {
+                        return;
+                    }
+                }
+            })
+        };
+        Ok(Some(Self {
+            path,
+            total_bytes,
+            heartbeat,
+        }))
+    }
+
+    /// The lock's state and time since it was last refreshed, if it is held
+    async fn read(path: &Path) -> Option<(PullLockState, Duration)> {
+        let metadata = tokio::fs::metadata(path).await.ok()?;
+        let age = metadata.modified().ok()?.elapsed().unwrap_or_default();
+        // A lock being rewritten reads as empty for a moment
+        let state = tokio::fs::read(path)
+            .await
+            .ok()
+            .and_then(|content| serde_json::from_slice(&content).ok())
+            .unwrap_or(PullLockState {
+                pid: 0,
+                total_bytes: None,
+            });
+        Some((state, age))
+    }
+
+    fn set_total_bytes(&self, total_bytes: Option<u64>) {
+        self.total_bytes.store(total_bytes.unwrap_or(u64::MAX), Ordering::Relaxed);
+    }
+}
+
+impl Drop for PullLock {
+    fn drop(&mut self) {
+        self.heartbeat.abort();
+        if let Err(e) = std::fs::remove_file(&self.path) {
+            warn!("Failed to release pull lock {}: {}", self.path.display(), e);
+        }
+    }
+}
+
+/// Whether a failed download attempt is worth resuming
+fn is_transient_download_error(error: &AIEngineError) -> bool {
+    match error {
+        AIEngineError::Http(e) => match e.status() {
+            Some(status) => status.is_server_error() || status == reqwest::StatusCode::TOO_MANY_REQUESTS,
+            None => e.is_timeout() || e.is_connect() || e.is_body() || e.is_request(),
+        },
+        AIEngineError::Io(e) => matches!(
+            e.kind(),
+            std::io::ErrorKind::TimedOut
+                | std::io::ErrorKind::UnexpectedEof
+                | std::io::ErrorKind::ConnectionReset
+                | std::io::ErrorKind::ConnectionAborted
+                | std::io::ErrorKind::Interrupted
+        ),
+        _ => false,
+    }
+}
+
+fn is_huggingface_url(url: &str) -> bool {
+    reqwest::Url::parse(url)
+        .ok()
+        .and_then(|url| url.host_str().map(str::to_lowercase))
+        .is_some_and(|host| host == "huggingface.co" || host.ends_with(".huggingface.co") || host == "hf.co")
+}
+
+/// What to do about a 401 or 403 from a model download
+fn access_hint(url: &str, has_token: bool) -> String {
+    match (is_huggingface_url(url), has_token) {
+        (true, false) => "the model is gated or private; accept its license on huggingface.co and provide an access token".to_string(),
+        (true, true) => "the access token was rejected; check that it is valid and that its account has accepted the model's license on huggingface.co".to_string(),
+        (false, _) => "the server requires credentials for this URL".to_string(),
+    }
+}
+
+/// Total size from a `Content-Range: bytes start-end/total` header
+fn content_range_total(headers: &reqwest::header::HeaderMap) -> Option<u64> {
+    headers
+        .get(reqwest::header::CONTENT_RANGE)?
+        .to_str()
+        .ok()?
+        .rsplit('/')
+        .next()?
+        .parse()
+        .ok()
+}
+
+/// SHA-256 the server publishes for the file; Hugging Face sends it as the
+/// ETag of LFS files
+fn published_checksum(headers: &reqwest::header::HeaderMap) -> Option<String> {
+    ["x-linked-etag", "etag"].iter().find_map(|name| {
+        let value = headers.get(*name)?.to_str().ok()?;
+        let value = value.trim_start_matches("W/").trim_matches('"');
+        (value.len() == 64 && value.chars().all(|c| c.is_ascii_hexdigit())).then(|| value.to_lowercase())
+    })
+}
+
+async fn sha256_file(path: PathBuf) -> AIResult<String> {
+    tokio::task::spawn_blocking(move || -> std::io::Result<String> {
+        use std::io::Read;
+        let mut file = std::fs::File::open(path)?;
+        let mut hasher = Sha256::new();
+        let mut buffer = vec![0; 1 << 20];
+        loop {
+            let read = file.read(&mut buffer)?;
+            if read == 0 {
+                break;
+            }
+            hasher.update(&buffer[..read]);
+        }
+        Ok(format!("{:x}", hasher.finalize()))
+    })
+    .await?
+    .map_err(Into::into)
+}
+
+async fn file_len(path: &Path) -> u64 {
+    tokio::fs::metadata(path).await.map(|metadata| metadata.len()).unwrap_or(0)
+}
+
+async fn remove_if_exists(path: &Path) -> std::io::Result<()> {
+    match tokio::fs::remove_file(path).await {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e),
+        _ => Ok(()),
+    }
+}
+
+/// Total size of a file, or of every file under a directory
+fn disk_usage(path: &Path) -> u64 {
+    let Ok(metadata) = std::fs::metadata(path) else {
+        return 0;
+    };
+    if !metadata.is_dir() {
+        return metadata.len();
+    }
+    std::fs::read_dir(path)
+        .map(|entries| entries.flatten().map(|entry| disk_usage(&entry.path())).sum())
+        .unwrap_or(0)
+}
+
+/// Catalog id of a GGUF model: its `general.name` in kebab case, or the file stem
+fn gguf_model_id(file: &GgufFile) -> String {
+    let from_name = file.name().map(|name| {
+        name.split(|c: char| !c.is_ascii_alphanumeric() && c != '.' && c != '_')
+            .filter(|part| !part.is_empty())
+            .collect::<Vec<_>>()
+            .join("-")
+            .to_lowercase()
+    });
+    from_name
+        .filter(|id| !id.is_empty())
+        .or_else(|| file.path.file_stem().map(|stem| stem.to_string_lossy().into_owned()))
+        .unwrap_or_else(|| "gguf-model".to_string())
+}
+
 impl Default for ModelManager {
     fn default() -> Self {
         // This will panic in async context, so it's mainly for testing
@@ -436,4 +1766,377 @@ impl Default for ModelManager {
             .block_on(Self::new(PathBuf::from("./models"), 8 * 1024 * 1024 * 1024))
             .unwrap()
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn test_manager() -> ModelManager {
+        let dir = std::env::temp_dir().join(format!("aion-model-manager-{}", uuid::Uuid::new_v4()));
+        ModelManager::new(dir, 8 * 1024 * 1024 * 1024).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn unload_drains_in_flight_requests() {
+        let manager = Arc::new(test_manager().await);
+        manager.load_model("bert-base").await.unwrap();
+
+        let mut workers = Vec::new();
+        for i in 0..32 {
+            let manager = manager.clone();
+            workers.push(tokio::spawn(async move {
+                tokio::time::sleep(Duration::from_millis(i)).await;
+                let lease = match manager.acquire("bert-base").await {
+                    Ok(lease) => lease,
+                    Err(AIEngineError::ModelUnloading { .. }) => return false,
+                    Err(e) => panic!("unexpected error: {}", e),
+                };
+                // A leased model must never look freed, before or after doing work
+                for _ in 0..5 {
+                    let state = lease.model().read().await.state.clone();
+                    assert!(!matches!(state, ModelState::NotLoaded), "request observed a freed model");
+                    tokio::time::sleep(Duration::from_millis(5)).await;
+                }
+                true
+            }));
+        }
+
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        let report = manager
+            .unload_model("bert-base", DrainOptions { timeout: Duration::from_secs(5), replacement_model: None })
+            .await
+            .unwrap();
+
+        let mut served = 0;
+        for worker in workers {
+            if worker.await.unwrap() {
+                served += 1;
+            }
+        }
+
+        assert!(report.drained);
+        assert_eq!(report.outstanding_requests, 0);
+        assert!(served > 0);
+        assert_eq!(manager.get_loaded_model_count(), 0);
+        assert_eq!(manager.get_memory_usage(), 0);
+        assert!(matches!(
+            manager.acquire("bert-base").await.map(|lease| lease.model_id().to_string()),
+            Ok(id) if id == "bert-base"
+        ));
+    }
+
+    #[tokio::test]
+    async fn concurrent_acquires_share_one_load() {
+        let manager = Arc::new(test_manager().await);
+        let required = manager.get_model_info("bert-base").await.unwrap().memory_requirements;
+
+        let callers: Vec<_> = (0..16)
+            .map(|_| {
+                let manager = manager.clone();
+                tokio::spawn(async move { manager.acquire("bert-base").await.map(|lease| lease.model().clone()) })
+            })
+            .collect();
+        let mut models = Vec::new();
+        for caller in callers {
+            models.push(caller.await.unwrap().expect("every caller gets the model"));
+        }
+
+        assert!(models.iter().all(|model| Arc::ptr_eq(model, &models[0])));
+        assert!(matches!(models[0].read().await.state, ModelState::Loaded));
+        assert_eq!(manager.get_loaded_model_count(), 1);
+        assert_eq!(manager.get_memory_usage(), required);
+        assert!(manager.loading.is_empty());
+    }
+
+    #[tokio::test]
+    async fn draining_model_migrates_to_replacement() {
+        let manager = Arc::new(test_manager().await);
+        manager.load_model("bert-base").await.unwrap();
+        let held = manager.acquire("bert-base").await.unwrap();
+
+        let unloader = {
+            let manager = manager.clone();
+            tokio::spawn(async move {
+                manager
+                    .unload_model(
+                        "bert-base",
+                        DrainOptions {
+                            timeout: Duration::from_secs(5),
+                            replacement_model: Some("gpt2-small".to_string()),
+                        },
+                    )
+                    .await
+            })
+        };
+
+        while !manager.is_draining("bert-base") {
+            tokio::task::yield_now().await;
+        }
+        let migrated = manager.acquire("bert-base").await.unwrap();
+        assert_eq!(migrated.model_id(), "gpt2-small");
+
+        drop(held);
+        let report = unloader.await.unwrap().unwrap();
+        assert!(report.drained);
+    }
+
+    #[tokio::test]
+    async fn cached_models_reflect_residency_and_removal() {
+        let manager = test_manager().await;
+        manager.load_model("bert-base").await.unwrap();
+
+        let cached = manager.cached_models().await;
+        let [model] = cached.as_slice() else {
+            panic!("expected only bert-base to be cached, got {:?}", cached);
+        };
+        assert_eq!(model.info.id, "bert-base");
+        assert!(model.size_on_disk > 0);
+        assert!(model.last_used.is_some());
+        assert_eq!(model.resident_in, vec![std::process::id()]);
+
+        let freed = manager.remove_cached_model("bert-base").await.unwrap();
+        assert_eq!(freed, model.size_on_disk);
+        assert_eq!(manager.get_loaded_model_count(), 0);
+        assert!(manager.model_residency("bert-base").await.is_empty());
+        assert!(manager.cached_models().await.is_empty());
+        assert!(matches!(
+            manager.remove_cached_model("bert-base").await,
+            Err(AIEngineError::ModelNotFound { .. })
+        ));
+    }
+
+    /// Serves `body` as `/tiny.bin`, honouring `Range: bytes=N-`, or answers
+    /// every request with `status` if given; returns the URL and the request
+    /// heads received
+    async fn serve_model(body: &'static [u8], status: Option<u16>, etag: String) -> (String, Arc<std::sync::Mutex<Vec<String>>>) {
+        use tokio::io::AsyncReadExt;
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/tiny.bin", listener.local_addr().unwrap());
+        let requests = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let seen = requests.clone();
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let mut head = Vec::new();
+                let mut byte = [0u8; 1];
+                while !head.ends_with(b"\r\n\r\n") && socket.read(&mut byte).await.unwrap_or(0) == 1 {
+                    head.push(byte[0]);
+                }
+                let head = String::from_utf8_lossy(&head).to_lowercase();
+                let offset = head
+                    .lines()
+                    .find_map(|line| line.strip_prefix("range: bytes="))
+                    .and_then(|range| range.trim_end_matches('-').parse::<usize>().ok());
+                seen.lock().unwrap().push(head);
+
+                let mut response = match (status, offset) {
+                    (Some(status), _) => format!("HTTP/1.1 {} Error\r\ncontent-length: 0\r\nconnection: close\r\n\r\n", status),
+                    (None, Some(offset)) => format!(
+                        "HTTP/1.1 206 Partial Content\r\ncontent-length: {}\r\ncontent-range: bytes {}-{}/{}\r\netag: \"{}\"\r\nconnection: close\r\n\r\n",
+                        body.len() - offset,
+                        offset,
+                        body.len() - 1,
+                        body.len(),
+                        etag
+                    ),
+                    (None, None) => format!(
+                        "HTTP/1.1 200 OK\r\ncontent-length: {}\r\netag: \"{}\"\r\nconnection: close\r\n\r\n",
+                        body.len(),
+                        etag
+                    ),
+                }
+                .into_bytes();
+                if status.is_none() {
+                    response.extend_from_slice(&body[offset.unwrap_or(0)..]);
+                }
+                let _ = socket.write_all(&response).await;
+            }
+        });
+        (url, requests)
+    }
+
+    const MODEL_BYTES: &[u8] = b"0123456789abcdefghijklmnopqrstuvwxyz";
+
+    #[tokio::test]
+    async fn pull_resumes_partial_download_and_verifies_checksum() {
+        let manager = test_manager().await;
+        let (url, requests) = serve_model(MODEL_BYTES, None, format!("{:x}", Sha256::digest(MODEL_BYTES))).await;
+        tokio::fs::write(manager.cache_dir().join("tiny.model.part"), &MODEL_BYTES[..10]).await.unwrap();
+
+        let mut phases = Vec::new();
+        let info = manager
+            .pull_model(&url, |update| {
+                if phases.last() != Some(&update.phase) {
+                    phases.push(update.phase);
+                }
+            })
+            .await
+            .unwrap();
+
+        let path = info.local_path.unwrap();
+        assert_eq!(tokio::fs::read(&path).await.unwrap(), MODEL_BYTES);
+        assert!(requests.lock().unwrap()[0].contains("range: bytes=10-"));
+        assert_eq!(phases, vec![DownloadPhase::Downloading { resumed_from: 10 }, DownloadPhase::Verifying]);
+        assert!(!manager.cache_dir().join("tiny.model.part").exists());
+        assert!(!manager.cache_dir().join("tiny.model.lock").exists());
+
+        // Cached now, so pulling again does not download
+        manager.pull_model(&url, |_| {}).await.unwrap();
+        assert_eq!(requests.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn failed_checksum_discards_download() {
+        let manager = test_manager().await;
+        let (url, _) = serve_model(MODEL_BYTES, None, "0".repeat(64)).await;
+
+        let result = manager.pull_model(&url, |_| {}).await;
+
+        assert!(matches!(result, Err(AIEngineError::ChecksumMismatch { .. })));
+        for leftover in ["tiny.model", "tiny.model.part", "tiny.model.lock"] {
+            assert!(!manager.cache_dir().join(leftover).exists(), "{} was left behind", leftover);
+        }
+    }
+
+    #[tokio::test]
+    async fn denied_download_is_reported_without_leaking_token() {
+        let manager = test_manager().await;
+        let (url, requests) = serve_model(MODEL_BYTES, Some(401), String::new()).await;
+        let options = PullOptions {
+            auth_token: Some("hf_secret".to_string()),
+            ..PullOptions::default()
+        };
+
+        let result = manager.pull_model_with(&url, &options, |_| {}).await;
+
+        assert!(matches!(result, Err(AIEngineError::ModelAccessDenied { status: 401, .. })));
+        assert_eq!(requests.lock().unwrap().len(), 1, "access errors are not retried");
+        assert!(!requests.lock().unwrap()[0].contains("hf_secret"));
+    }
+
+    /// Catalog a base model whose only linear module is a 2×2 `q_proj`
+    async fn adapter_base(manager: &ModelManager) {
+        let mut info = manager.get_model_info("bert-base").await.unwrap();
+        info.id = "tiny-base".to_string();
+        info.metadata.insert(crate::lora::LINEAR_MODULES_KEY.to_string(), serde_json::json!({ "q_proj": [2, 2] }));
+        info.metadata.insert(crate::lora::NUM_LAYERS_KEY.to_string(), serde_json::json!(1));
+        manager.add_model_to_catalog(info).await.unwrap();
+    }
+
+    async fn write_adapter(manager: &ModelManager, name: &str, out_features: usize, b: &[f32]) -> PathBuf {
+        let dir = manager.cache_dir().join(name);
+        tokio::fs::create_dir_all(&dir).await.unwrap();
+        let config = crate::lora::tests::config(1, 1.0, &["q_proj"]);
+        tokio::fs::write(dir.join(crate::lora::ADAPTER_CONFIG_FILE), serde_json::to_vec(&config).unwrap()).await.unwrap();
+        let weights = crate::lora::tests::safetensors(&[
+            ("base_model.model.model.layers.0.q_proj.lora_A.weight", &[1, 2], &[1.0, 1.0]),
+            ("base_model.model.model.layers.0.q_proj.lora_B.weight", &[out_features, 1], b),
+        ]);
+        tokio::fs::write(dir.join(crate::lora::ADAPTER_WEIGHTS_FILE), weights).await.unwrap();
+        dir
+    }
+
+    #[tokio::test]
+    async fn adapters_share_a_base_model_and_stack() {
+        let manager = test_manager().await;
+        adapter_base(&manager).await;
+        let first = manager.load_adapter("tiny-base", write_adapter(&manager, "first", 2, &[1.0, 0.0]).await).await.unwrap();
+        let second = manager.load_adapter("tiny-base", write_adapter(&manager, "second", 2, &[0.0, 1.0]).await).await.unwrap();
+        assert_eq!(manager.get_loaded_model_count(), 1);
+        assert_eq!(manager.adapters("tiny-base").len(), 2);
+
+        let lease = manager
+            .acquire_with_adapters("tiny-base", &[(first.id.as_str(), 1.0), (second.id.as_str(), 0.5)])
+            .await
+            .unwrap();
+        let mut output = [0.0, 0.0];
+        lease.adapters().apply("model.layers.0.q_proj", &[1.0, 1.0], &mut output);
+        assert_eq!(output, [2.0, 1.0]);
+        drop(lease);
+
+        // Reloading under the same name swaps the weights in place
+        let memory = manager.get_memory_usage();
+        manager.load_adapter("tiny-base", write_adapter(&manager, "first", 2, &[3.0, 0.0]).await).await.unwrap();
+        assert_eq!(manager.get_memory_usage(), memory);
+        let lease = manager.acquire_with_adapters("tiny-base", &[(first.id.as_str(), 1.0)]).await.unwrap();
+        let mut output = [0.0, 0.0];
+        lease.adapters().apply("model.layers.0.q_proj", &[1.0, 1.0], &mut output);
+        assert_eq!(output, [6.0, 0.0]);
+        drop(lease);
+
+        manager.unload_model("tiny-base", DrainOptions::default()).await.unwrap();
+        assert!(manager.adapters("tiny-base").is_empty());
+        assert_eq!(manager.get_memory_usage(), 0);
+    }
+
+    #[tokio::test]
+    async fn adapters_change_the_output_of_their_modules() {
+        let manager = test_manager().await;
+        adapter_base(&manager).await;
+        let adapter = manager.load_adapter("tiny-base", write_adapter(&manager, "shift", 2, &[0.5, -1.0]).await).await.unwrap();
+        let identity = [1.0, 0.0, 0.0, 1.0];
+        let input = [2.0, 4.0];
+
+        let base = manager.acquire("tiny-base").await.unwrap();
+        assert_eq!(base.linear("model.layers.0.q_proj", &identity, &input), vec![2.0, 4.0]);
+
+        let adapted = manager.acquire_with_adapters("tiny-base", &[(adapter.id.as_str(), 1.0)]).await.unwrap();
+        // A·x = 6, so B adds [3, -6]
+        assert_eq!(adapted.linear("model.layers.0.q_proj", &identity, &input), vec![5.0, -2.0]);
+        // Modules the adapter does not target are unchanged
+        assert_eq!(adapted.linear("model.layers.0.v_proj", &identity, &input), vec![2.0, 4.0]);
+    }
+
+    #[tokio::test]
+    async fn incompatible_adapter_is_rejected() {
+        let manager = test_manager().await;
+        adapter_base(&manager).await;
+
+        let result = manager.load_adapter("tiny-base", write_adapter(&manager, "wide", 3, &[1.0, 1.0, 1.0]).await).await;
+        assert!(matches!(result, Err(AIEngineError::AdapterIncompatible { .. })));
+
+        // bert-base does not describe its modules, so nothing can be checked against it
+        let result = manager.load_adapter("bert-base", write_adapter(&manager, "any", 2, &[1.0, 1.0]).await).await;
+        assert!(matches!(result, Err(AIEngineError::AdapterIncompatible { reason, .. }) if reason.contains("linear_modules")));
+        assert_eq!(manager.get_loaded_model_count(), 0);
+    }
+
+    async fn write_gguf(manager: &ModelManager, tensors: &[(&str, &[u64], u32)]) -> PathBuf {
+        let path = manager.cache_dir().join("tiny-llama.Q4_K_M.gguf");
+        tokio::fs::create_dir_all(manager.cache_dir()).await.unwrap();
+        let bytes = crate::gguf::tests::gguf(&crate::gguf::tests::llama_metadata(), tensors);
+        tokio::fs::write(&path, bytes).await.unwrap();
+        path
+    }
+
+    #[tokio::test]
+    async fn gguf_quantization_is_checked_before_loading() {
+        let manager = test_manager().await;
+        let path = write_gguf(&manager, &[("blk.0.attn_q.weight", &[256, 256], 12), ("blk.0.attn_norm.weight", &[256], 0)]).await;
+
+        let result = manager.load_gguf(&path, GgufQuantization::Other(16)).await;
+        assert!(matches!(result, Err(AIEngineError::UnsupportedQuantization { .. })));
+        let result = manager.load_gguf(&path, GgufQuantization::Q8_0).await;
+        assert!(matches!(result, Err(AIEngineError::ModelLoadingFailed { reason, .. }) if reason.contains("Q4_K")));
+        assert_eq!(manager.get_loaded_model_count(), 0);
+        assert_eq!(manager.get_memory_usage(), 0);
+    }
+
+    #[cfg(feature = "candle")]
+    #[tokio::test]
+    async fn gguf_model_is_registered_for_inference() {
+        let manager = test_manager().await;
+        let path = write_gguf(&manager, &[("blk.0.attn_q.weight", &[256, 256], 12), ("blk.0.attn_norm.weight", &[256], 0)]).await;
+
+        let loaded = manager.load_gguf(&path, GgufQuantization::Q4_K).await.unwrap();
+        let info = loaded.read().await.info.clone();
+        assert_eq!(info.id, "tiny-llama");
+        assert_eq!(info.format, ModelFormat::GGUF);
+        assert_eq!(info.metadata["quantization"], "Q4_K");
+        assert_eq!(info.metadata["context_length"], 2048);
+        assert_eq!(manager.get_memory_usage(), 256 * 144 + 256 * 4);
+
+        let lease = manager.acquire("tiny-llama").await.unwrap();
+        assert!(lease.model().read().await.model_data.is_some());
+    }
+}
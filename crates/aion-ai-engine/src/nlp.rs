@@ -1,12 +1,37 @@
 //! # Natural Language Processing
 //!
 //! NLP utilities and text processing functions.
+//!
+//! Structured extraction reads records described by a JSON schema out of free
+//! text. Fields are located by their labels ("Invoice number: 1042"), and the
+//! value after the label must parse as the field's type; values that are
+//! missing, ambiguous or unparseable come back as `null` rather than guessed.
+//!
+//! Entity recognition goes through one [`NerService`], shared by
+//! [`NLPProcessor::extract_entities`] and structured extraction, so every
+//! consumer sees the same detections for the same text. The service splits
+//! documents into sentences, recognizes uncached sentences in batches and
+//! caches results by sentence hash.
+//!
+//! [`embed_batch`] embeds many texts through the [`InferenceEngine`], sending
+//! each batch as one request that runs a single forward pass instead of one
+//! inference per text.
 
 use crate::errors::{AIEngineError, AIResult};
+use crate::inference::{InferenceEngine, InferenceInput, InferenceOutput, InferenceParameters, InferenceRequest};
 use regex::Regex;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use serde_json::Value;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, VecDeque};
+use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
 use unicode_normalization::UnicodeNormalization;
+use uuid::Uuid;
+
+/// Fields below this confidence are reported as `null`
+pub const MIN_FIELD_CONFIDENCE: f32 = 0.5;
 
 /// Text preprocessing options
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -110,6 +135,53 @@ pub struct LanguageDetectionResult {
     pub alternatives: Vec<(String, f32)>,
 }
 
+/// One field of an extracted record
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExtractedField {
+    /// Parsed value, `null` when not found or below [`MIN_FIELD_CONFIDENCE`]
+    pub value: Value,
+    /// Confidence in the value that was read, even when it was withheld
+    pub confidence: f32,
+    /// Text the value was read from
+    pub source: Option<String>,
+}
+
+/// A record extracted from part of a document
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExtractedRecord {
+    pub fields: HashMap<String, ExtractedField>,
+    /// Byte range of the document the record was read from
+    pub span: (usize, usize),
+}
+
+impl ExtractedRecord {
+    /// The record as a JSON object of field values
+    pub fn to_value(&self) -> Value {
+        Value::Object(self.fields.iter().map(|(name, field)| (name.clone(), field.value.clone())).collect())
+    }
+}
+
+/// Result of structured extraction
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StructuredExtraction {
+    pub records: Vec<ExtractedRecord>,
+    /// Why the text does not look like the schema at all, if it doesn't
+    pub schema_mismatch: Option<String>,
+    /// The schema described an array of records
+    pub expects_array: bool,
+}
+
+impl StructuredExtraction {
+    /// A single object, or an array when the document held several records
+    /// or the schema asked for an array
+    pub fn to_value(&self) -> Value {
+        match self.records.as_slice() {
+            [record] if !self.expects_array => record.to_value(),
+            records => Value::Array(records.iter().map(ExtractedRecord::to_value).collect()),
+        }
+    }
+}
+
 /// NLP processor for text analysis and preprocessing
 pub struct NLPProcessor {
     /// Compiled regex patterns for optimization
@@ -117,6 +189,8 @@ pub struct NLPProcessor {
     whitespace_regex: Regex,
     /// Stop words by language
     stop_words: HashMap<String, std::collections::HashSet<String>>,
+    /// Entity recognition shared with every other processor
+    ner: Arc<NerService>,
 }
 
 impl NLPProcessor {
@@ -141,9 +215,21 @@ impl NLPProcessor {
             punctuation_regex,
             whitespace_regex,
             stop_words,
+            ner: NerService::shared(),
         }
     }
 
+    /// Use `ner` instead of the process-wide service
+    pub fn with_ner_service(mut self, ner: Arc<NerService>) -> Self {
+        self.ner = ner;
+        self
+    }
+
+    /// The entity recognition service this processor uses
+    pub fn ner_service(&self) -> &Arc<NerService> {
+        &self.ner
+    }
+
     /// Preprocess text according to options
     pub fn preprocess_text(
         &self,
@@ -272,48 +358,20 @@ impl NLPProcessor {
         })
     }
 
-    /// Perform Named Entity Recognition (mock implementation)
+    /// Perform Named Entity Recognition through the shared [`NerService`]
     pub fn extract_entities(&self, text: &str) -> AIResult<NERResult> {
-        let mut entities = Vec::new();
-
-        // Simple pattern-based NER (mock implementation)
-        // In a real implementation, this would use a trained NER model
-
-        // Find email addresses
-        let email_regex = Regex::new(r"\b[A-Za-z0-9._%+-]+@[A-Za-z0-9.-]+\.[A-Z|a-z]{2,}\b").unwrap();
-        for mat in email_regex.find_iter(text) {
-            entities.push(Entity {
-                text: mat.as_str().to_string(),
-                entity_type: "EMAIL".to_string(),
-                confidence: 0.95,
-                start: mat.start(),
-                end: mat.end(),
-            });
-        }
-
-        // Find URLs
-        let url_regex = Regex::new(r"https?://[^\s]+").unwrap();
-        for mat in url_regex.find_iter(text) {
-            entities.push(Entity {
-                text: mat.as_str().to_string(),
-                entity_type: "URL".to_string(),
-                confidence: 0.9,
-                start: mat.start(),
-                end: mat.end(),
-            });
-        }
-
-        // Find potential person names (capitalized words)
-        let name_regex = Regex::new(r"\b[A-Z][a-z]+ [A-Z][a-z]+\b").unwrap();
-        for mat in name_regex.find_iter(text) {
-            entities.push(Entity {
-                text: mat.as_str().to_string(),
-                entity_type: "PERSON".to_string(),
-                confidence: 0.7,
-                start: mat.start(),
-                end: mat.end(),
-            });
-        }
+        let entities = self
+            .ner
+            .recognize(text)
+            .into_iter()
+            .map(|entity| Entity {
+                entity_type: entity.kind.as_str().to_string(),
+                text: entity.text,
+                confidence: entity.confidence,
+                start: entity.start,
+                end: entity.end,
+            })
+            .collect();
 
         Ok(NERResult {
             entities,
@@ -459,10 +517,972 @@ impl NLPProcessor {
 
         Ok(keywords)
     }
+
+    /// Extract records matching `schema` from `text`
+    ///
+    /// `schema` is a JSON schema for an object (or an array of objects) whose
+    /// properties have a `type` of string, number, integer or boolean, with
+    /// optional `format` (`date`, `email`, `uri`), `enum` and `title`. The
+    /// result is one object, or an array when the document contains several
+    /// records. Fails with [`AIEngineError::SchemaMismatch`] when none of the
+    /// schema's fields appear in the text.
+    pub fn extract_structured(&self, text: &str, schema: &Value) -> AIResult<Value> {
+        let extraction = self.extract_structured_detailed(text, schema)?;
+        match extraction.schema_mismatch {
+            Some(reason) => Err(AIEngineError::SchemaMismatch { reason }),
+            None => Ok(extraction.to_value()),
+        }
+    }
+
+    /// Like [`extract_structured`](Self::extract_structured), with per-field
+    /// confidence and the source text of each value
+    pub fn extract_structured_detailed(&self, text: &str, schema: &Value) -> AIResult<StructuredExtraction> {
+        let (fields, expects_array) = parse_extraction_schema(schema)?;
+
+        // A label that occurs several times (a second "Invoice number") starts a new record
+        let anchor = fields
+            .iter()
+            .filter(|field| field.required)
+            .chain(fields.iter().filter(|field| !field.required))
+            .map(|field| field.label_positions(text))
+            .find(|positions| positions.len() > 1);
+        let spans = match anchor {
+            Some(positions) => {
+                let mut starts: Vec<usize> = positions.iter().map(|&start| line_start(text, start)).collect();
+                starts[0] = 0;
+                starts.dedup();
+                starts
+                    .iter()
+                    .enumerate()
+                    .map(|(i, &start)| (start, starts.get(i + 1).copied().unwrap_or(text.len())))
+                    .collect()
+            }
+            None => vec![(0, text.len())],
+        };
+
+        let entities = self.ner.recognize(text);
+        let mut labeled_fields = 0;
+        let records = spans
+            .into_iter()
+            .map(|(start, end)| {
+                let segment = &text[start..end];
+                let segment_entities: Vec<&RecognizedEntity> =
+                    entities.iter().filter(|e| e.start >= start && e.end <= end).collect();
+                let fields = fields
+                    .iter()
+                    .map(|field| {
+                        let (extracted, labeled) = field.extract(segment, &segment_entities);
+                        labeled_fields += labeled as usize;
+                        (field.name.clone(), extracted)
+                    })
+                    .collect();
+                ExtractedRecord { fields, span: (start, end) }
+            })
+            .collect();
+
+        let schema_mismatch = (labeled_fields == 0).then(|| {
+            let names: Vec<&str> = fields.iter().map(|field| field.name.as_str()).collect();
+            format!("none of the fields {} are labeled in the text", names.join(", "))
+        });
+
+        Ok(StructuredExtraction { records, schema_mismatch, expects_array })
+    }
 }
 
 impl Default for NLPProcessor {
     fn default() -> Self {
         Self::new()
     }
-}
\ No newline at end of file
+}
+
+/// Entity categories found by the shared NER pass
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum EntityKind {
+    Person,
+    Email,
+    Url,
+    IpAddress,
+    PhoneNumber,
+    CreditCard,
+    Date,
+    Money,
+}
+
+impl EntityKind {
+    /// Tag used in [`Entity::entity_type`]
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EntityKind::Person => "PERSON",
+            EntityKind::Email => "EMAIL",
+            EntityKind::Url => "URL",
+            EntityKind::IpAddress => "IP_ADDRESS",
+            EntityKind::PhoneNumber => "PHONE_NUMBER",
+            EntityKind::CreditCard => "CREDIT_CARD",
+            EntityKind::Date => "DATE",
+            EntityKind::Money => "MONEY",
+        }
+    }
+}
+
+/// An entity with its position in the original document
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecognizedEntity {
+    pub kind: EntityKind,
+    pub text: String,
+    pub confidence: f32,
+    /// Byte offsets into the original text
+    pub start: usize,
+    pub end: usize,
+    /// Character offsets into the original text
+    pub char_start: usize,
+    pub char_end: usize,
+}
+
+/// An entity within one sentence, as recognizers report it
+#[derive(Debug, Clone, PartialEq)]
+pub struct SentenceEntity {
+    pub kind: EntityKind,
+    pub confidence: f32,
+    /// Byte offsets into the sentence
+    pub start: usize,
+    pub end: usize,
+}
+
+/// Backend that finds entities in sentences
+///
+/// Sentences arrive in batches so model-backed recognizers can run them
+/// together; the result holds one list per input sentence, in order.
+pub trait EntityRecognizer: Send + Sync {
+    fn recognize_batch(&self, sentences: &[&str]) -> Vec<Vec<SentenceEntity>>;
+}
+
+/// Pattern-based recognizer used when no model is configured
+pub struct PatternRecognizer {
+    patterns: Vec<(EntityKind, f32, Regex)>,
+}
+
+impl PatternRecognizer {
+    pub fn new() -> Self {
+        let patterns = [
+            (EntityKind::Email, 0.95, r"\b[A-Za-z0-9._%+-]+@[A-Za-z0-9.-]+\.[A-Za-z]{2,}\b"),
+            (EntityKind::Url, 0.9, r#"https?://[^\s<>"]+[^\s<>".,;:!?)]"#),
+            (EntityKind::IpAddress, 0.85, r"\b(?:(?:25[0-5]|2[0-4]\d|1?\d?\d)\.){3}(?:25[0-5]|2[0-4]\d|1?\d?\d)\b"),
+            (EntityKind::CreditCard, 0.9, r"\b(?:\d[ -]?){12,18}\d\b"),
+            (EntityKind::PhoneNumber, 0.75, r"(?:\+\d{1,3}[ .-]?)?(?:\(\d{2,4}\)|\b\d{2,4})[ .-]\d{3,4}[ .-]\d{3,4}\b"),
+            (
+                EntityKind::Date,
+                0.85,
+                r"\b(?:\d{4}-\d{1,2}-\d{1,2}|\d{1,2}[/.]\d{1,2}[/.]\d{4}|(?:Jan|Feb|Mar|Apr|May|Jun|Jul|Aug|Sep|Sept|Oct|Nov|Dec)[a-z]*\.? \d{1,2}(?:st|nd|rd|th)?,? \d{4}|\d{1,2}(?:st|nd|rd|th)? (?:Jan|Feb|Mar|Apr|May|Jun|Jul|Aug|Sep|Sept|Oct|Nov|Dec)[a-z]*\.?,? \d{4})\b",
+            ),
+            (EntityKind::Money, 0.8, r"(?:[$€£¥]\s?\d[\d,]*(?:\.\d{1,2})?|\b\d[\d,]*(?:\.\d{1,2})? ?(?:USD|EUR|GBP|MXN)\b)"),
+            (EntityKind::Person, 0.7, r"\b[A-Z][a-z]+ [A-Z][a-z]+\b"),
+        ];
+        Self {
+            patterns: patterns
+                .into_iter()
+                .map(|(kind, confidence, pattern)| (kind, confidence, Regex::new(pattern).expect("valid entity pattern")))
+                .collect(),
+        }
+    }
+}
+
+impl Default for PatternRecognizer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EntityRecognizer for PatternRecognizer {
+    fn recognize_batch(&self, sentences: &[&str]) -> Vec<Vec<SentenceEntity>> {
+        sentences
+            .iter()
+            .map(|sentence| {
+                let mut entities = Vec::new();
+                for (kind, confidence, pattern) in &self.patterns {
+                    for m in pattern.find_iter(sentence) {
+                        // Digit runs that fail the Luhn check are not card numbers
+                        if *kind == EntityKind::CreditCard && !luhn_valid(m.as_str()) {
+                            continue;
+                        }
+                        entities.push(SentenceEntity { kind: *kind, confidence: *confidence, start: m.start(), end: m.end() });
+                    }
+                }
+                entities
+            })
+            .collect()
+    }
+}
+
+fn luhn_valid(number: &str) -> bool {
+    let digits: Vec<u32> = number.chars().filter_map(|c| c.to_digit(10)).collect();
+    let sum: u32 = digits
+        .iter()
+        .rev()
+        .enumerate()
+        .map(|(i, &d)| if i % 2 == 1 { if d * 2 > 9 { d * 2 - 9 } else { d * 2 } } else { d })
+        .sum();
+    digits.len() >= 13 && sum % 10 == 0
+}
+
+/// Cache hit counters of a [`NerService`]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct NerCacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub entries: usize,
+}
+
+/// Sentence results keyed by hash, evicted oldest first
+struct NerCache {
+    capacity: usize,
+    entries: HashMap<u64, Arc<Vec<SentenceEntity>>>,
+    order: VecDeque<u64>,
+}
+
+impl NerCache {
+    fn insert(&mut self, key: u64, entities: Arc<Vec<SentenceEntity>>) {
+        if self.capacity == 0 || self.entries.insert(key, entities).is_some() {
+            return;
+        }
+        self.order.push_back(key);
+        while self.entries.len() > self.capacity {
+            if let Some(oldest) = self.order.pop_front() {
+                self.entries.remove(&oldest);
+            }
+        }
+    }
+}
+
+/// Default number of sentences recognized together
+pub const DEFAULT_NER_BATCH_SIZE: usize = 32;
+/// Default number of sentences whose entities are cached
+pub const DEFAULT_NER_CACHE_CAPACITY: usize = 10_000;
+/// Streamed text without a sentence boundary is recognized once it grows past this
+const MAX_PENDING_STREAM_BYTES: usize = 64 * 1024;
+
+/// Entity recognition shared by every consumer of a document
+///
+/// Entities of different kinds may overlap (a date inside a URL, a phone
+/// number that is also a card number); all of them are returned, ordered by
+/// position with longer spans first, and [`without_overlaps`] picks one per
+/// span for callers that need that.
+pub struct NerService {
+    recognizer: Arc<dyn EntityRecognizer>,
+    batch_size: usize,
+    cache: Mutex<NerCache>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl NerService {
+    pub fn new() -> Self {
+        Self::with_recognizer(Arc::new(PatternRecognizer::new()))
+    }
+
+    pub fn with_recognizer(recognizer: Arc<dyn EntityRecognizer>) -> Self {
+        Self {
+            recognizer,
+            batch_size: DEFAULT_NER_BATCH_SIZE,
+            cache: Mutex::new(NerCache {
+                capacity: DEFAULT_NER_CACHE_CAPACITY,
+                entries: HashMap::new(),
+                order: VecDeque::new(),
+            }),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+
+    pub fn with_cache_capacity(mut self, capacity: usize) -> Self {
+        self.cache.get_mut().unwrap_or_else(|e| e.into_inner()).capacity = capacity;
+        self
+    }
+
+    /// Process-wide service with the pattern recognizer
+    pub fn shared() -> Arc<NerService> {
+        static SHARED: OnceLock<Arc<NerService>> = OnceLock::new();
+        SHARED.get_or_init(|| Arc::new(NerService::new())).clone()
+    }
+
+    /// Entities in `text`, with offsets into `text`
+    pub fn recognize(&self, text: &str) -> Vec<RecognizedEntity> {
+        self.recognize_at(text, 0, 0)
+    }
+
+    /// Recognize a document that arrives in chunks
+    pub fn stream(&self) -> NerStream<'_> {
+        NerStream { service: self, pending: String::new(), byte_offset: 0, char_offset: 0 }
+    }
+
+    pub fn cache_stats(&self) -> NerCacheStats {
+        NerCacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            entries: self.cache.lock().unwrap_or_else(|e| e.into_inner()).entries.len(),
+        }
+    }
+
+    /// Recognize `text` that starts at `byte_base` / `char_base` of a larger document
+    fn recognize_at(&self, text: &str, byte_base: usize, char_base: usize) -> Vec<RecognizedEntity> {
+        let sentences = split_sentences(text);
+        let keys: Vec<u64> = sentences.iter().map(|(_, sentence)| sentence_key(sentence)).collect();
+
+        let mut results: Vec<Option<Arc<Vec<SentenceEntity>>>> = {
+            let cache = self.cache.lock().unwrap_or_else(|e| e.into_inner());
+            keys.iter().map(|key| cache.entries.get(key).cloned()).collect()
+        };
+        let missing: Vec<usize> = (0..sentences.len()).filter(|&i| results[i].is_none()).collect();
+        self.hits.fetch_add((sentences.len() - missing.len()) as u64, Ordering::Relaxed);
+        self.misses.fetch_add(missing.len() as u64, Ordering::Relaxed);
+
+        for batch in missing.chunks(self.batch_size) {
+            let batch_sentences: Vec<&str> = batch.iter().map(|&i| sentences[i].1).collect();
+            let recognized = self.recognizer.recognize_batch(&batch_sentences);
+            let mut cache = self.cache.lock().unwrap_or_else(|e| e.into_inner());
+            for (&i, entities) in batch.iter().zip(recognized) {
+                let entities = Arc::new(entities);
+                cache.insert(keys[i], entities.clone());
+                results[i] = Some(entities);
+            }
+        }
+
+        let mut entities = Vec::new();
+        let mut chars_before = 0;
+        let mut last_byte = 0;
+        for ((sentence_start, sentence), found) in sentences.iter().zip(results) {
+            chars_before += text[last_byte..*sentence_start].chars().count();
+            last_byte = *sentence_start;
+            for entity in found.iter().flat_map(|found| found.iter()) {
+                // Ignore spans a recognizer reports outside the sentence or off a char boundary
+                let Some(entity_text) = sentence.get(entity.start..entity.end) else { continue };
+                let char_start = chars_before + sentence[..entity.start].chars().count();
+                entities.push(RecognizedEntity {
+                    kind: entity.kind,
+                    text: entity_text.to_string(),
+                    confidence: entity.confidence,
+                    start: byte_base + sentence_start + entity.start,
+                    end: byte_base + sentence_start + entity.end,
+                    char_start: char_base + char_start,
+                    char_end: char_base + char_start + entity_text.chars().count(),
+                });
+            }
+        }
+
+        entities.sort_by(|a, b| a.start.cmp(&b.start).then(b.end.cmp(&a.end)).then(a.kind.as_str().cmp(b.kind.as_str())));
+        entities.dedup_by(|a, b| a.kind == b.kind && a.start == b.start && a.end == b.end);
+        entities
+    }
+}
+
+impl Default for NerService {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Incremental recognition over a document fed in chunks
+///
+/// Only complete sentences are recognized; the unfinished tail is kept until
+/// more text or [`finish`](Self::finish) arrives. Offsets are relative to the
+/// start of the whole stream.
+pub struct NerStream<'a> {
+    service: &'a NerService,
+    pending: String,
+    byte_offset: usize,
+    char_offset: usize,
+}
+
+impl NerStream<'_> {
+    /// Add text and return the entities of the sentences it completed
+    pub fn push(&mut self, chunk: &str) -> Vec<RecognizedEntity> {
+        self.pending.push_str(chunk);
+        let mut cut = last_sentence_boundary(&self.pending);
+        if cut == 0 && self.pending.len() > MAX_PENDING_STREAM_BYTES {
+            // No boundary in sight; split at whitespace so memory stays bounded
+            cut = self.pending.rfind(char::is_whitespace).map_or(self.pending.len(), |i| i + 1);
+        }
+        if cut == 0 {
+            return Vec::new();
+        }
+        let complete: String = self.pending.drain(..cut).collect();
+        self.recognize(&complete)
+    }
+
+    /// Recognize whatever text is left
+    pub fn finish(mut self) -> Vec<RecognizedEntity> {
+        let rest = std::mem::take(&mut self.pending);
+        self.recognize(&rest)
+    }
+
+    fn recognize(&mut self, text: &str) -> Vec<RecognizedEntity> {
+        let entities = self.service.recognize_at(text, self.byte_offset, self.char_offset);
+        self.byte_offset += text.len();
+        self.char_offset += text.chars().count();
+        entities
+    }
+}
+
+/// Keep one entity per overlapping region: the most confident, then the longest
+pub fn without_overlaps(entities: &[RecognizedEntity]) -> Vec<RecognizedEntity> {
+    let mut ranked: Vec<&RecognizedEntity> = entities.iter().collect();
+    ranked.sort_by(|a, b| {
+        b.confidence
+            .total_cmp(&a.confidence)
+            .then((b.end - b.start).cmp(&(a.end - a.start)))
+            .then(a.start.cmp(&b.start))
+    });
+    let mut kept: Vec<RecognizedEntity> = Vec::new();
+    for entity in ranked {
+        if kept.iter().all(|k| entity.end <= k.start || entity.start >= k.end) {
+            kept.push(entity.clone());
+        }
+    }
+    kept.sort_by_key(|entity| entity.start);
+    kept
+}
+
+fn sentence_key(sentence: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    sentence.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Byte offset just past the last sentence boundary in `text`, or 0
+pub(crate) fn last_sentence_boundary(text: &str) -> usize {
+    let mut last = 0;
+    let mut previous_terminal = false;
+    for (i, c) in text.char_indices() {
+        if c == '\n' {
+            last = i + 1;
+        } else if previous_terminal && c.is_whitespace() {
+            last = i;
+        }
+        previous_terminal = matches!(c, '.' | '!' | '?');
+    }
+    last
+}
+
+/// Sentences of `text` with their byte offsets, leading whitespace trimmed
+/// and blank sentences dropped
+fn split_sentences(text: &str) -> Vec<(usize, &str)> {
+    let mut sentences = Vec::new();
+    let mut start = 0;
+    let mut previous_terminal = false;
+    let push = |from: usize, to: usize, sentences: &mut Vec<(usize, &str)>| {
+        let sentence = &text[from..to];
+        let trimmed = sentence.trim_start();
+        if !trimmed.trim_end().is_empty() {
+            sentences.push((from + sentence.len() - trimmed.len(), trimmed));
+        }
+    };
+    for (i, c) in text.char_indices() {
+        if c == '\n' {
+            push(start, i, &mut sentences);
+            start = i + 1;
+        } else if previous_terminal && c.is_whitespace() {
+            push(start, i, &mut sentences);
+            start = i;
+        }
+        previous_terminal = matches!(c, '.' | '!' | '?');
+    }
+    push(start, text.len(), &mut sentences);
+    sentences
+}
+
+/// Value types a schema field can have
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum FieldKind {
+    String,
+    Number,
+    Integer,
+    Boolean,
+    Date,
+    Email,
+    Uri,
+}
+
+/// A schema property prepared for extraction
+struct FieldSpec {
+    name: String,
+    kind: FieldKind,
+    required: bool,
+    enum_values: Option<Vec<String>>,
+    label: Regex,
+}
+
+fn parse_extraction_schema(schema: &Value) -> AIResult<(Vec<FieldSpec>, bool)> {
+    let invalid = |reason: &str| AIEngineError::ConfigurationError {
+        field: "schema".to_string(),
+        reason: reason.to_string(),
+    };
+
+    let (object, expects_array) = match schema.get("type").and_then(Value::as_str) {
+        Some("array") => (schema.get("items").ok_or_else(|| invalid("array schema without items"))?, true),
+        _ => (schema, false),
+    };
+    let properties = object
+        .get("properties")
+        .and_then(Value::as_object)
+        .filter(|properties| !properties.is_empty())
+        .ok_or_else(|| invalid("schema must be an object with properties"))?;
+    let required: Vec<&str> = object
+        .get("required")
+        .and_then(Value::as_array)
+        .map(|names| names.iter().filter_map(Value::as_str).collect())
+        .unwrap_or_default();
+
+    let mut fields = Vec::new();
+    for (name, property) in properties {
+        let kind = match (
+            property.get("type").and_then(Value::as_str).unwrap_or("string"),
+            property.get("format").and_then(Value::as_str),
+        ) {
+            ("string", Some("date")) | ("string", Some("date-time")) => FieldKind::Date,
+            ("string", Some("email")) => FieldKind::Email,
+            ("string", Some("uri")) => FieldKind::Uri,
+            ("string", _) => FieldKind::String,
+            ("number", _) => FieldKind::Number,
+            ("integer", _) => FieldKind::Integer,
+            ("boolean", _) => FieldKind::Boolean,
+            (other, _) => return Err(invalid(&format!("unsupported type '{}' for field '{}'", other, name))),
+        };
+        let enum_values = property
+            .get("enum")
+            .and_then(Value::as_array)
+            .map(|values| values.iter().filter_map(Value::as_str).map(str::to_string).collect());
+        let title = property.get("title").and_then(Value::as_str);
+
+        fields.push(FieldSpec {
+            label: label_regex(name, title),
+            name: name.clone(),
+            kind,
+            required: required.contains(&name.as_str()),
+            enum_values,
+        });
+    }
+    Ok((fields, expects_array))
+}
+
+/// Regex matching a field's label, from its name (`invoice_number`,
+/// `invoiceNumber`) and title; a trailing "number" or "id" also matches
+/// "no.", "num" and "#"
+fn label_regex(name: &str, title: Option<&str>) -> Regex {
+    let mut alternatives = vec![label_pattern(&split_words(name))];
+    if let Some(title) = title {
+        alternatives.push(label_pattern(&split_words(title)));
+    }
+    Regex::new(&format!(r"(?i)\b(?:{})", alternatives.join("|"))).expect("label pattern is escaped")
+}
+
+fn label_pattern(words: &[String]) -> String {
+    let mut parts: Vec<String> = words.iter().map(|word| regex::escape(word)).collect();
+    if let Some(last) = words.last() {
+        if matches!(last.as_str(), "number" | "no" | "num" | "id") {
+            parts.pop();
+            parts.push(r"(?:number|no\.?|num|id|#)".to_string());
+        }
+    }
+    parts.join(r"[\s_\-]*")
+}
+
+/// Lowercase words of a snake_case, kebab-case, camelCase or spaced name
+fn split_words(name: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut current = String::new();
+    let mut previous_lower = false;
+    for c in name.chars() {
+        if !c.is_alphanumeric() {
+            if !current.is_empty() {
+                words.push(std::mem::take(&mut current));
+            }
+            previous_lower = false;
+            continue;
+        }
+        if c.is_uppercase() && previous_lower && !current.is_empty() {
+            words.push(std::mem::take(&mut current));
+        }
+        previous_lower = c.is_lowercase() || c.is_ascii_digit();
+        current.extend(c.to_lowercase());
+    }
+    if !current.is_empty() {
+        words.push(current);
+    }
+    words
+}
+
+impl FieldSpec {
+    /// Start of each label occurrence that is followed by a separator rather
+    /// than more of a word
+    fn label_positions(&self, text: &str) -> Vec<usize> {
+        self.label_matches(text).map(|(start, _)| start).collect()
+    }
+
+    fn label_matches<'t>(&'t self, text: &'t str) -> impl Iterator<Item = (usize, usize)> + 't {
+        self.label
+            .find_iter(text)
+            .filter(move |m| !text[m.end()..].starts_with(|c: char| c.is_alphanumeric()))
+            .map(|m| (m.start(), m.end()))
+    }
+
+    /// Extracted field, and whether its label was present
+    fn extract(&self, segment: &str, entities: &[&RecognizedEntity]) -> (ExtractedField, bool) {
+        let mut candidates: Vec<(Value, f32, String)> = Vec::new();
+        let mut labeled = false;
+        for (_, label_end) in self.label_matches(segment) {
+            labeled = true;
+            let line = segment[label_end..].lines().next().unwrap_or_default();
+            let rest = line.trim_start_matches(|c: char| c.is_whitespace() || matches!(c, ':' | '=' | '#' | '-' | '–'));
+            if let Some((value, certainty)) = self.parse_value(rest) {
+                candidates.push((value, 0.9 * certainty, rest.trim().to_string()));
+            }
+        }
+
+        // Without a label, an email, URL or date that occurs exactly once is a fair guess
+        let unlabeled_kind = match self.kind {
+            FieldKind::Email => Some(EntityKind::Email),
+            FieldKind::Uri => Some(EntityKind::Url),
+            FieldKind::Date => Some(EntityKind::Date),
+            _ => None,
+        };
+        if let (false, Some(kind)) = (labeled, unlabeled_kind) {
+            let found: Vec<(Value, f32, String)> = entities
+                .iter()
+                .filter(|entity| entity.kind == kind)
+                .filter_map(|entity| {
+                    self.parse_value(&entity.text).map(|(v, c)| (v, 0.6 * c, entity.text.clone()))
+                })
+                .collect();
+            let distinct: std::collections::HashSet<String> = found.iter().map(|(v, _, _)| v.to_string()).collect();
+            if distinct.len() == 1 {
+                candidates.extend(found.into_iter().take(1));
+            }
+        }
+
+        let Some((value, mut confidence, source)) = candidates.first().cloned() else {
+            return (ExtractedField { value: Value::Null, confidence: 0.0, source: None }, labeled);
+        };
+        // The same label with conflicting values lowers confidence in the first
+        if candidates.iter().any(|(other, _, _)| *other != value) {
+            confidence *= 0.5;
+        }
+
+        let value = if confidence >= MIN_FIELD_CONFIDENCE { value } else { Value::Null };
+        (ExtractedField { value, confidence, source: Some(source) }, labeled)
+    }
+
+    /// Parse a value from the text after a label, with how certain the
+    /// reading is
+    fn parse_value(&self, text: &str) -> Option<(Value, f32)> {
+        if let Some(allowed) = &self.enum_values {
+            let lower = text.to_lowercase();
+            return allowed
+                .iter()
+                .find(|value| {
+                    let value = value.to_lowercase();
+                    lower.split(|c: char| !c.is_alphanumeric() && c != '-' && c != '_').any(|word| word == value)
+                        || lower.starts_with(&value)
+                })
+                .map(|value| (Value::String(value.clone()), 1.0));
+        }
+
+        match self.kind {
+            FieldKind::String => {
+                let value: String = text.trim().chars().take(200).collect();
+                (!value.is_empty()).then(|| (Value::String(value), 0.9))
+            }
+            FieldKind::Number | FieldKind::Integer => {
+                let number = Regex::new(r"^[^\d+\-]{0,4}?([+\-]?\d[\d,]*(?:\.\d+)?)").unwrap();
+                let digits = number.captures(text)?.get(1)?.as_str().replace(',', "");
+                if self.kind == FieldKind::Integer {
+                    digits.parse::<i64>().ok().map(|n| (Value::from(n), 1.0))
+                } else {
+                    let n: f64 = digits.parse().ok()?;
+                    serde_json::Number::from_f64(n).map(|n| (Value::Number(n), 1.0))
+                }
+            }
+            FieldKind::Boolean => {
+                let word = text.split_whitespace().next()?.trim_matches(|c: char| !c.is_alphanumeric()).to_lowercase();
+                match word.as_str() {
+                    "yes" | "y" | "true" => Some((Value::Bool(true), 1.0)),
+                    "no" | "n" | "false" => Some((Value::Bool(false), 1.0)),
+                    _ => None,
+                }
+            }
+            FieldKind::Date => parse_date(text).map(|(date, certainty)| (Value::String(date), certainty)),
+            FieldKind::Email => {
+                let email = Regex::new(r"[A-Za-z0-9._%+-]+@[A-Za-z0-9.-]+\.[A-Za-z]{2,}").unwrap();
+                email.find(text).map(|m| (Value::String(m.as_str().to_string()), 1.0))
+            }
+            FieldKind::Uri => {
+                let url = Regex::new(r#"https?://[^\s<>"]+"#).unwrap();
+                url.find(text)
+                    .map(|m| (Value::String(m.as_str().trim_end_matches(['.', ',', ')']).to_string()), 1.0))
+            }
+        }
+    }
+}
+
+/// First date in `text` as `YYYY-MM-DD`, with how certain the reading is;
+/// numeric dates where day and month could be swapped are uncertain
+fn parse_date(text: &str) -> Option<(String, f32)> {
+    use chrono::NaiveDate;
+
+    let iso = Regex::new(r"\b(\d{4})-(\d{1,2})-(\d{1,2})\b").unwrap();
+    if let Some(c) = iso.captures(text) {
+        let date = NaiveDate::from_ymd_opt(c[1].parse().ok()?, c[2].parse().ok()?, c[3].parse().ok()?)?;
+        return Some((date.format("%Y-%m-%d").to_string(), 1.0));
+    }
+
+    let written = Regex::new(r"\b(?:[A-Za-z]{3,9}\.? \d{1,2}(?:st|nd|rd|th)?,? \d{4}|\d{1,2}(?:st|nd|rd|th)? [A-Za-z]{3,9}\.?,? \d{4})\b").unwrap();
+    if let Some(m) = written.find(text) {
+        let cleaned = Regex::new(r"(\d)(?:st|nd|rd|th)").unwrap().replace_all(m.as_str(), "$1").replace(['.', ','], "");
+        for format in ["%B %d %Y", "%b %d %Y", "%d %B %Y", "%d %b %Y"] {
+            if let Ok(date) = NaiveDate::parse_from_str(&cleaned, format) {
+                return Some((date.format("%Y-%m-%d").to_string(), 1.0));
+            }
+        }
+    }
+
+    let numeric = Regex::new(r"\b(\d{1,2})[/.](\d{1,2})[/.](\d{4})\b").unwrap();
+    let c = numeric.captures(text)?;
+    let (first, second, year): (u32, u32, i32) = (c[1].parse().ok()?, c[2].parse().ok()?, c[3].parse().ok()?);
+    let (date, certainty) = match (first > 12, second > 12) {
+        // Day first, as in 31/01/2024
+        (true, false) => (NaiveDate::from_ymd_opt(year, second, first)?, 1.0),
+        // Month first, as in 01/31/2024
+        (false, true) => (NaiveDate::from_ymd_opt(year, first, second)?, 1.0),
+        // 03/04/2024 could be either; read it month first but don't trust it
+        _ => (NaiveDate::from_ymd_opt(year, first, second)?, 0.4),
+    };
+    Some((date.format("%Y-%m-%d").to_string(), certainty))
+}
+
+fn line_start(text: &str, index: usize) -> usize {
+    text[..index].rfind('\n').map_or(0, |newline| newline + 1)
+}
+
+/// Texts are truncated to this many tokens unless options say otherwise
+pub const DEFAULT_EMBEDDING_MAX_LENGTH: usize = 128;
+
+/// Options for [`embed_batch`]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EmbeddingOptions {
+    /// Tokens kept per text; longer texts are truncated and shorter ones
+    /// padded to the longest text in their batch
+    pub max_length: usize,
+    /// Most texts run in one forward pass; larger inputs are split into
+    /// batches of this size
+    pub max_batch_size: usize,
+}
+
+impl EmbeddingOptions {
+    /// Batches no larger than the engine's concurrent inference limit
+    pub fn for_config(config: &crate::AIEngineConfig) -> Self {
+        Self {
+            max_length: DEFAULT_EMBEDDING_MAX_LENGTH,
+            max_batch_size: config.max_concurrent_inferences,
+        }
+    }
+}
+
+impl Default for EmbeddingOptions {
+    fn default() -> Self {
+        Self::for_config(&crate::AIEngineConfig::default())
+    }
+}
+
+/// Embed every text with `model`; one vector per text, in order.
+///
+/// Each batch of at most `options.max_batch_size` texts goes to the engine as
+/// a single [`InferenceInput::TextBatch`] request, which runs as one forward
+/// pass over the batch padded to its longest text, instead of one inference
+/// per text. `cargo bench --bench embedding` measures the difference.
+pub async fn embed_batch(
+    engine: &InferenceEngine,
+    model: &str,
+    texts: &[String],
+    options: &EmbeddingOptions,
+) -> AIResult<Vec<Vec<f32>>> {
+    if options.max_length == 0 {
+        return Err(AIEngineError::ConfigurationError {
+            field: "max_length".to_string(),
+            reason: "must be at least 1".to_string(),
+        });
+    }
+    if options.max_batch_size == 0 {
+        return Err(AIEngineError::ConfigurationError {
+            field: "max_batch_size".to_string(),
+            reason: "must be at least 1".to_string(),
+        });
+    }
+
+    let requests = texts.chunks(options.max_batch_size).map(|batch| async move {
+        let response = engine
+            .infer(InferenceRequest {
+                id: Uuid::new_v4(),
+                model: model.to_string(),
+                input: InferenceInput::TextBatch(batch.to_vec()),
+                parameters: InferenceParameters {
+                    max_length: Some(options.max_length),
+                    ..InferenceParameters::default()
+                },
+                backend: None,
+            })
+            .await?;
+        match response.output {
+            InferenceOutput::Embeddings(vectors) if vectors.len() == batch.len() => Ok(vectors),
+            _ => Err(AIEngineError::PostprocessingFailed {
+                reason: format!("model {} did not return one embedding per text", model),
+            }),
+        }
+    });
+
+    Ok(futures::future::try_join_all(requests).await?.into_iter().flatten().collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn invoice_schema() -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "invoice_number": { "type": "string" },
+                "date": { "type": "string", "format": "date" },
+                "total": { "type": "number" }
+            },
+            "required": ["invoice_number"]
+        })
+    }
+
+    #[test]
+    fn extracts_one_record_per_repeated_label() {
+        let text = "Invoice number: INV-1042\nDate: March 3, 2024\nTotal: $1,250.00\n\n\
+                    Invoice number: INV-1043\nDate: 2024-03-10\nTotal: $310.50\n";
+
+        let value = NLPProcessor::new().extract_structured(text, &invoice_schema()).unwrap();
+
+        assert_eq!(
+            value,
+            json!([
+                { "invoice_number": "INV-1042", "date": "2024-03-03", "total": 1250.0 },
+                { "invoice_number": "INV-1043", "date": "2024-03-10", "total": 310.5 }
+            ])
+        );
+    }
+
+    #[test]
+    fn uncertain_fields_are_null_and_unrelated_text_is_flagged() {
+        let processor = NLPProcessor::new();
+        let extraction = processor
+            .extract_structured_detailed("Invoice no. 77\nDate: 03/04/2024\n", &invoice_schema())
+            .unwrap();
+        let record = &extraction.records[0];
+
+        assert_eq!(record.fields["invoice_number"].value, json!("77"));
+        // Day and month could be swapped
+        assert_eq!(record.fields["date"].value, Value::Null);
+        assert!(record.fields["date"].confidence > 0.0);
+        assert_eq!(record.fields["total"].value, Value::Null);
+
+        let error = processor.extract_structured("The weather is lovely today.", &invoice_schema()).unwrap_err();
+        assert!(matches!(error, AIEngineError::SchemaMismatch { .. }));
+    }
+
+    #[test]
+    fn ner_offsets_map_to_original_text_and_results_are_cached() {
+        let service = NerService::new();
+        let text = "Café owner Ana López wrote to ana@example.com.\nSee https://example.com/ü for details.";
+
+        let entities = service.recognize(text);
+        let email = entities.iter().find(|e| e.kind == EntityKind::Email).unwrap();
+        assert_eq!(&text[email.start..email.end], "ana@example.com");
+        let chars: Vec<char> = text.chars().collect();
+        assert_eq!(chars[email.char_start..email.char_end].iter().collect::<String>(), "ana@example.com");
+        assert!(email.char_start < email.start, "multi-byte characters precede the email");
+
+        let misses = service.cache_stats().misses;
+        assert_eq!(service.recognize(text), entities);
+        assert_eq!(service.cache_stats().misses, misses);
+    }
+
+    #[test]
+    fn ner_stream_matches_whole_document() {
+        let service = NerService::new();
+        let text = "Call +1 555-123-4567 today. Paid $1,200.50 on 2024-03-10.\nCard 4111 1111 1111 1111 was used.";
+
+        let mut stream = service.stream();
+        let mut streamed = Vec::new();
+        for chunk in text.as_bytes().chunks(7) {
+            streamed.extend(stream.push(std::str::from_utf8(chunk).unwrap()));
+        }
+        streamed.extend(stream.finish());
+
+        assert_eq!(streamed, service.recognize(text));
+        assert!(streamed.iter().any(|e| e.kind == EntityKind::CreditCard));
+        assert!(streamed.iter().any(|e| e.kind == EntityKind::Money));
+    }
+
+    fn assert_close(left: &[f32], right: &[f32]) {
+        assert_eq!(left.len(), right.len());
+        for (l, r) in left.iter().zip(right) {
+            assert!((l - r).abs() < 1e-5, "{} != {}", l, r);
+        }
+    }
+
+    const EMBEDDING_MODEL: &str = "sentence-encoder";
+
+    #[tokio::test]
+    async fn batch_embeddings_match_single_embeddings() {
+        let engine = InferenceEngine::new(crate::AIEngineConfig::default());
+        let texts: Vec<String> = [
+            "Configure the plugin registry before the first install.",
+            "Hot reload drains running executions",
+            "",
+            "Semantic search ranks documentation chunks by cosine similarity to the query text.",
+            "tokens",
+        ]
+        .iter()
+        .map(|text| text.to_string())
+        .collect();
+        let options = EmbeddingOptions { max_length: 8, max_batch_size: 2 };
+
+        let batched = embed_batch(&engine, EMBEDDING_MODEL, &texts, &options).await.unwrap();
+        assert_eq!(batched.len(), texts.len());
+        for (text, vector) in texts.iter().zip(&batched) {
+            let single = embed_batch(&engine, EMBEDDING_MODEL, std::slice::from_ref(text), &options).await.unwrap();
+            assert_close(vector, &single[0]);
+        }
+
+        let options = EmbeddingOptions { max_length: 8, max_batch_size: 64 };
+        let unchunked = embed_batch(&engine, EMBEDDING_MODEL, &texts, &options).await.unwrap();
+        for (left, right) in batched.iter().zip(&unchunked) {
+            assert_close(left, right);
+        }
+
+        assert!(batched[2].iter().all(|value| *value == 0.0));
+        let norm = batched[0].iter().map(|value| value * value).sum::<f32>().sqrt();
+        assert!((norm - 1.0).abs() < 1e-5);
+    }
+
+    #[tokio::test]
+    async fn embeddings_truncate_to_max_length() {
+        let engine = InferenceEngine::new(crate::AIEngineConfig::default());
+        let options = EmbeddingOptions { max_length: 3, max_batch_size: 4 };
+        let texts = vec!["alpha beta gamma delta epsilon".to_string(), "alpha beta gamma".to_string()];
+
+        let vectors = embed_batch(&engine, EMBEDDING_MODEL, &texts, &options).await.unwrap();
+        assert_close(&vectors[0], &vectors[1]);
+
+        let options = EmbeddingOptions { max_length: 0, max_batch_size: 4 };
+        assert!(embed_batch(&engine, EMBEDDING_MODEL, &texts, &options).await.is_err());
+    }
+}
@@ -0,0 +1,392 @@
+//! # Output Filtering
+//!
+//! Screens generated text before it is returned to a caller. Entities found
+//! by the shared [`NerService`] (emails, phone numbers, card numbers, ...)
+//! and matches of a configurable blocklist are each allowed, redacted or
+//! cause the whole output to be blocked, as the [`OutputFilterPolicy`]
+//! says. Every action is recorded in a [`FilterReport`] for auditing; the
+//! report holds kinds and offsets only, never the filtered text itself.
+//!
+//! Streamed output goes through [`OutputFilterStream`], which releases text
+//! one complete sentence at a time so an entity split across token deltas
+//! is still seen whole.
+
+use crate::errors::{AIEngineError, AIResult};
+use crate::nlp::{last_sentence_boundary, EntityKind, NerService};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Streamed text without a sentence boundary is filtered once it grows past this
+const MAX_PENDING_STREAM_BYTES: usize = 16 * 1024;
+
+/// What to do with a match
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FilterAction {
+    Allow,
+    /// Replace the match with a placeholder
+    Redact,
+    /// Withhold the whole output
+    Block,
+}
+
+/// A blocklist entry
+#[derive(Debug, Clone)]
+pub struct BlocklistRule {
+    pub name: String,
+    pattern: Regex,
+    pub action: FilterAction,
+}
+
+impl BlocklistRule {
+    /// Rule matching a regular expression
+    pub fn pattern(name: impl Into<String>, pattern: &str, action: FilterAction) -> AIResult<Self> {
+        let name = name.into();
+        let pattern = Regex::new(pattern).map_err(|e| AIEngineError::ConfigurationError {
+            field: format!("blocklist.{}", name),
+            reason: e.to_string(),
+        })?;
+        Ok(Self { name, pattern, action })
+    }
+
+    /// Rule matching a word or phrase, case-insensitively
+    pub fn term(term: &str, action: FilterAction) -> Self {
+        let pattern = Regex::new(&format!(r"(?i)\b{}\b", regex::escape(term))).expect("escaped term is a valid pattern");
+        Self {
+            name: term.to_string(),
+            pattern,
+            action,
+        }
+    }
+}
+
+/// Which entities and blocklist matches to act on
+#[derive(Debug, Clone)]
+pub struct OutputFilterPolicy {
+    entity_actions: HashMap<EntityKind, FilterAction>,
+    blocklist: Vec<BlocklistRule>,
+    /// Entities recognized with lower confidence are ignored
+    pub min_confidence: f32,
+}
+
+impl Default for OutputFilterPolicy {
+    fn default() -> Self {
+        Self {
+            entity_actions: HashMap::new(),
+            blocklist: Vec::new(),
+            min_confidence: 0.5,
+        }
+    }
+}
+
+impl OutputFilterPolicy {
+    /// Policy that allows everything until configured
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Redact contact details, network addresses and card numbers
+    pub fn pii() -> Self {
+        Self::new()
+            .with_entity(EntityKind::Email, FilterAction::Redact)
+            .with_entity(EntityKind::PhoneNumber, FilterAction::Redact)
+            .with_entity(EntityKind::IpAddress, FilterAction::Redact)
+            .with_entity(EntityKind::CreditCard, FilterAction::Redact)
+    }
+
+    pub fn with_entity(mut self, kind: EntityKind, action: FilterAction) -> Self {
+        self.entity_actions.insert(kind, action);
+        self
+    }
+
+    pub fn with_rule(mut self, rule: BlocklistRule) -> Self {
+        self.blocklist.push(rule);
+        self
+    }
+
+    pub fn with_min_confidence(mut self, min_confidence: f32) -> Self {
+        self.min_confidence = min_confidence;
+        self
+    }
+
+    pub fn entity_action(&self, kind: EntityKind) -> FilterAction {
+        self.entity_actions.get(&kind).copied().unwrap_or(FilterAction::Allow)
+    }
+}
+
+/// What a filter event matched
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", content = "name", rename_all = "snake_case")]
+pub enum FilterMatch {
+    Entity(EntityKind),
+    Blocklist(String),
+}
+
+/// One redaction or block
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FilterEvent {
+    pub matched: FilterMatch,
+    pub action: FilterAction,
+    /// Byte offsets into the unfiltered output
+    pub start: usize,
+    pub end: usize,
+}
+
+/// Everything a filter did to one output
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct FilterReport {
+    pub blocked: bool,
+    pub events: Vec<FilterEvent>,
+}
+
+impl FilterReport {
+    pub fn redactions(&self) -> usize {
+        self.events.iter().filter(|event| event.action == FilterAction::Redact).count()
+    }
+
+    pub fn is_clean(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    fn absorb(&mut self, other: FilterReport, offset: usize) {
+        self.blocked |= other.blocked;
+        self.events.extend(other.events.into_iter().map(|event| FilterEvent {
+            start: event.start + offset,
+            end: event.end + offset,
+            ..event
+        }));
+    }
+}
+
+/// Filtered text and what was done to it. `text` is empty when blocked.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FilteredText {
+    pub text: String,
+    pub report: FilterReport,
+}
+
+/// Applies an [`OutputFilterPolicy`] to generated text
+pub struct OutputFilter {
+    ner: Arc<NerService>,
+    policy: OutputFilterPolicy,
+}
+
+impl OutputFilter {
+    pub fn new(policy: OutputFilterPolicy) -> Self {
+        Self {
+            ner: NerService::shared(),
+            policy,
+        }
+    }
+
+    pub fn with_ner_service(mut self, ner: Arc<NerService>) -> Self {
+        self.ner = ner;
+        self
+    }
+
+    pub fn policy(&self) -> &OutputFilterPolicy {
+        &self.policy
+    }
+
+    pub fn filter(&self, text: &str) -> FilteredText {
+        let mut events: Vec<FilterEvent> = self
+            .ner
+            .recognize(text)
+            .into_iter()
+            .filter(|entity| entity.confidence >= self.policy.min_confidence)
+            .filter_map(|entity| {
+                let action = self.policy.entity_action(entity.kind);
+                (action != FilterAction::Allow).then_some(FilterEvent {
+                    matched: FilterMatch::Entity(entity.kind),
+                    action,
+                    start: entity.start,
+                    end: entity.end,
+                })
+            })
+            .collect();
+
+        for rule in self.policy.blocklist.iter().filter(|rule| rule.action != FilterAction::Allow) {
+            events.extend(rule.pattern.find_iter(text).map(|m| FilterEvent {
+                matched: FilterMatch::Blocklist(rule.name.clone()),
+                action: rule.action,
+                start: m.start(),
+                end: m.end(),
+            }));
+        }
+
+        // Earliest first, longest first among equal starts; a span inside an
+        // earlier one is already covered by its redaction
+        events.sort_by(|a, b| a.start.cmp(&b.start).then(b.end.cmp(&a.end)));
+        let mut kept: Vec<FilterEvent> = Vec::new();
+        for event in events {
+            let covered = kept.last().map_or(false, |last| event.start < last.end);
+            if event.action == FilterAction::Block || !covered {
+                kept.push(event);
+            }
+        }
+
+        let blocked = kept.iter().any(|event| event.action == FilterAction::Block);
+        let text = if blocked {
+            String::new()
+        } else {
+            redact(text, &kept)
+        };
+
+        FilteredText {
+            text,
+            report: FilterReport { blocked, events: kept },
+        }
+    }
+
+    /// Filter a response that arrives as token deltas
+    pub fn stream(&self) -> OutputFilterStream<'_> {
+        OutputFilterStream {
+            filter: self,
+            pending: String::new(),
+            offset: 0,
+            report: FilterReport::default(),
+        }
+    }
+}
+
+fn redact(text: &str, events: &[FilterEvent]) -> String {
+    let mut redacted = String::with_capacity(text.len());
+    let mut cursor = 0;
+    for event in events.iter().filter(|event| event.action == FilterAction::Redact) {
+        if event.start < cursor {
+            continue;
+        }
+        redacted.push_str(&text[cursor..event.start]);
+        redacted.push_str(&placeholder(&event.matched));
+        cursor = event.end;
+    }
+    redacted.push_str(&text[cursor..]);
+    redacted
+}
+
+fn placeholder(matched: &FilterMatch) -> String {
+    match matched {
+        FilterMatch::Entity(kind) => format!("[REDACTED:{}]", kind.as_str()),
+        FilterMatch::Blocklist(_) => "[REDACTED]".to_string(),
+    }
+}
+
+/// Incremental filtering of streamed output
+///
+/// Text is held back until a sentence completes, then filtered and released.
+/// Once anything is blocked the stream releases nothing more.
+pub struct OutputFilterStream<'a> {
+    filter: &'a OutputFilter,
+    pending: String,
+    /// Bytes of unfiltered output already processed
+    offset: usize,
+    report: FilterReport,
+}
+
+impl OutputFilterStream<'_> {
+    /// Add a delta and return the filtered text that is now safe to emit
+    pub fn push(&mut self, delta: &str) -> String {
+        self.pending.push_str(delta);
+        let mut cut = last_sentence_boundary(&self.pending);
+        if cut == 0 && self.pending.len() > MAX_PENDING_STREAM_BYTES {
+            // No boundary in sight; split at whitespace so memory stays bounded
+            cut = self.pending.rfind(char::is_whitespace).map_or(self.pending.len(), |i| i + 1);
+        }
+        if cut == 0 {
+            return String::new();
+        }
+        let complete: String = self.pending.drain(..cut).collect();
+        self.release(&complete)
+    }
+
+    /// Filter and return whatever is left, with the report for the whole stream
+    pub fn finish(mut self) -> (String, FilterReport) {
+        let rest = std::mem::take(&mut self.pending);
+        let tail = self.release(&rest);
+        (tail, self.report)
+    }
+
+    pub fn is_blocked(&self) -> bool {
+        self.report.blocked
+    }
+
+    pub fn report(&self) -> &FilterReport {
+        &self.report
+    }
+
+    fn release(&mut self, text: &str) -> String {
+        let filtered = self.filter.filter(text);
+        self.report.absorb(filtered.report, self.offset);
+        self.offset += text.len();
+        if self.report.blocked {
+            String::new()
+        } else {
+            filtered.text
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn redacts_configured_entities() {
+        let filter = OutputFilter::new(OutputFilterPolicy::pii());
+        let filtered = filter.filter("Contact jane.doe@example.com or call 555-123-4567. Meeting on 2024-03-01.");
+
+        assert_eq!(
+            filtered.text,
+            "Contact [REDACTED:EMAIL] or call [REDACTED:PHONE_NUMBER]. Meeting on 2024-03-01."
+        );
+        assert!(!filtered.report.blocked);
+        assert_eq!(filtered.report.redactions(), 2);
+        assert_eq!(filtered.report.events[0].matched, FilterMatch::Entity(EntityKind::Email));
+        assert_eq!(filtered.report.events[0].start, 8);
+    }
+
+    #[test]
+    fn blocklist_blocks_whole_output() {
+        let policy = OutputFilterPolicy::pii()
+            .with_rule(BlocklistRule::term("project nightshade", FilterAction::Block))
+            .with_rule(BlocklistRule::term("darn", FilterAction::Redact));
+        let filter = OutputFilter::new(policy);
+
+        let redacted = filter.filter("Darn, the build failed.");
+        assert_eq!(redacted.text, "[REDACTED], the build failed.");
+
+        let blocked = filter.filter("Status of Project Nightshade: on track.");
+        assert!(blocked.report.blocked);
+        assert!(blocked.text.is_empty());
+        assert_eq!(blocked.report.events[0].matched, FilterMatch::Blocklist("project nightshade".to_string()));
+    }
+
+    #[test]
+    fn stream_catches_entities_split_across_deltas() {
+        let filter = OutputFilter::new(OutputFilterPolicy::pii());
+        let mut stream = filter.stream();
+
+        let mut emitted = String::new();
+        for delta in ["Write to jane.d", "oe@exam", "ple.com today. ", "Thanks"] {
+            emitted.push_str(&stream.push(delta));
+        }
+        let (tail, report) = stream.finish();
+        emitted.push_str(&tail);
+
+        assert_eq!(emitted, "Write to [REDACTED:EMAIL] today. Thanks");
+        assert_eq!(report.events.len(), 1);
+        assert_eq!((report.events[0].start, report.events[0].end), (9, 29));
+    }
+
+    #[test]
+    fn stream_stops_releasing_after_a_block() {
+        let filter = OutputFilter::new(OutputFilterPolicy::new().with_rule(BlocklistRule::term("secret", FilterAction::Block)));
+        let mut stream = filter.stream();
+
+        assert_eq!(stream.push("All good. "), "All good.");
+        assert_eq!(stream.push("The secret is out. More text. "), "");
+        assert!(stream.is_blocked());
+        assert_eq!(stream.finish().0, "");
+    }
+}
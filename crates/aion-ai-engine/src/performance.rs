@@ -2,6 +2,7 @@
 //!
 //! Performance metrics and monitoring for the AI engine.
 
+use aion_monitoring::MetricsRegistry;
 use dashmap::DashMap;
 use serde::{Deserialize, Serialize};
 use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
@@ -25,6 +26,8 @@ pub struct PerformanceMetrics {
     model_metrics: Arc<DashMap<String, ModelMetrics>>,
     /// Start time for uptime calculation
     start_time: Instant,
+    /// Prometheus series fed with successful inferences
+    metrics_registry: Option<Arc<MetricsRegistry>>,
 }
 
 #[derive(Debug, Default)]
@@ -84,19 +87,33 @@ impl PerformanceMetrics {
             total_memory_used: AtomicUsize::new(0),
             model_metrics: Arc::new(DashMap::new()),
             start_time: Instant::now(),
+            metrics_registry: None,
         }
     }
 
+    /// Also export successful inferences' latency and tokens to Prometheus
+    pub fn with_metrics_registry(mut self, registry: Arc<MetricsRegistry>) -> Self {
+        self.metrics_registry = Some(registry);
+        self
+    }
+
     /// Record an inference
     pub fn record_inference(
         &self,
         model: &str,
         duration: Duration,
         memory_usage: usize,
+        tokens: usize,
         success: bool,
     ) {
         let duration_ms = duration.as_millis() as u64;
 
+        if success {
+            if let Some(registry) = &self.metrics_registry {
+                registry.record_inference(model, duration, tokens as u64);
+            }
+        }
+
         // Update global metrics
         self.total_inferences.fetch_add(1, Ordering::Relaxed);
         self.total_inference_time_ms
@@ -276,4 +293,34 @@ impl Default for PerformanceMetrics {
     fn default() -> Self {
         Self::new()
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_recorded_inference_updates_prometheus_histogram() {
+        let registry = Arc::new(MetricsRegistry::new());
+        let metrics = PerformanceMetrics::new().with_metrics_registry(registry.clone());
+
+        metrics.record_inference("llama-7b", Duration::from_millis(250), 1024, 42, true);
+        let (sum, count) = registry.inference_latency("llama-7b");
+        assert!((sum - 0.25).abs() < 1e-9);
+        assert_eq!(count, 1);
+        assert_eq!(registry.inference_tokens("llama-7b"), 42);
+
+        metrics.record_inference("llama-7b", Duration::from_millis(1500), 1024, 8, true);
+        metrics.record_inference("llama-7b", Duration::from_millis(900), 0, 0, false);
+        let (sum, count) = registry.inference_latency("llama-7b");
+        assert!((sum - 1.75).abs() < 1e-9);
+        assert_eq!(count, 2);
+        assert_eq!(registry.inference_tokens("llama-7b"), 50);
+        assert_eq!(metrics.get_stats().total_inferences, 3);
+
+        let exposition = registry.render().unwrap();
+        assert!(exposition.contains("aion_inference_duration_seconds_bucket{model=\"llama-7b\",le=\"0.25\"} 1"));
+        assert!(exposition.contains("aion_inference_duration_seconds_sum{model=\"llama-7b\"} 1.75"));
+        assert!(exposition.contains("aion_inference_tokens_total{model=\"llama-7b\"} 50"));
+    }
+}
@@ -9,6 +9,7 @@ use tokio::sync::RwLock;
 use uuid::Uuid;
 
 use crate::errors::{AIEngineError, Result};
+use crate::formatting::FormattingService;
 use crate::template_engine::{ProjectTemplate, TemplateEngine, GeneratedFile};
 use crate::code_generation::GeneratedCode;
 
@@ -18,6 +19,7 @@ pub struct ProjectScaffoldingEngine {
     language_processors: HashMap<String, Arc<dyn LanguageProcessor>>,
     structure_generators: HashMap<String, Arc<dyn StructureGenerator>>,
     best_practices: Arc<RwLock<BestPracticesRegistry>>,
+    formatting: Arc<FormattingService>,
 }
 
 /// Language-specific code processor
@@ -1055,9 +1057,16 @@ impl ProjectScaffoldingEngine {
             language_processors,
             structure_generators,
             best_practices,
+            formatting: Arc::new(FormattingService::new()),
         })
     }
 
+    /// Use a custom formatting service for generated files
+    pub fn with_formatting(mut self, formatting: Arc<FormattingService>) -> Self {
+        self.formatting = formatting;
+        self
+    }
+
     /// Generate a complete project with advanced scaffolding
     pub async fn generate_advanced_project(
         &self,
@@ -1094,6 +1103,20 @@ impl ProjectScaffoldingEngine {
         let best_practice_files = self.apply_best_practices(language_config).await?;
         all_files.extend(best_practice_files);
 
+        // Post-generation hook: consistent formatting across all generated files
+        let formatting = self.formatting
+            .format_files(all_files.iter_mut().map(|file| (file.path.as_str(), &mut file.content)))
+            .await;
+        for file in &mut all_files {
+            file.size = file.content.len() as u64;
+        }
+        tracing::info!(
+            "Formatted {} generated files ({} changed, {} left unformatted)",
+            formatting.files.len(),
+            formatting.changed().count(),
+            formatting.unformatted().count()
+        );
+
         Ok(GeneratedCode {
             id: Uuid::new_v4(),
             project_name: project_name.to_string(),
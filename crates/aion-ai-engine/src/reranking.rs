@@ -0,0 +1,246 @@
+//! # Reranking
+//!
+//! Embedding retrieval scores the query and each passage independently,
+//! which is fast but imprecise. A cross-encoder reads the query and passage
+//! together and scores their relevance far more accurately, at the cost of
+//! one model call per candidate. [`rerank`] applies one to the best few
+//! candidates from a first-stage retrieval and reorders them.
+//!
+//! Reranking is best-effort: when it is disabled, no reranker is configured,
+//! or the reranker fails (for example because its model is not loaded), the
+//! candidates keep their retrieval order.
+
+use crate::errors::{AIEngineError, AIResult};
+use crate::inference::{InferenceEngine, InferenceInput, InferenceOutput, InferenceParameters, InferenceRequest};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tracing::{debug, warn};
+use uuid::Uuid;
+
+/// Scores query/passage pairs jointly
+#[async_trait]
+pub trait Reranker: Send + Sync {
+    /// Relevance of each passage to the query, in input order; higher is
+    /// more relevant
+    async fn score(&self, query: &str, passages: &[String]) -> AIResult<Vec<f32>>;
+}
+
+/// Cross-encoder served by an [`InferenceEngine`] model.
+///
+/// Each pair is sent as structured input `{"query": .., "passage": ..}`. The
+/// model may answer with a classification (the probability of a relevant
+/// label is used, otherwise the confidence), a one-element embedding holding
+/// the logit, or a JSON object with a numeric `score`.
+pub struct InferenceReranker {
+    engine: Arc<InferenceEngine>,
+    model: String,
+}
+
+impl InferenceReranker {
+    pub fn new(engine: Arc<InferenceEngine>, model: impl Into<String>) -> Self {
+        Self {
+            engine,
+            model: model.into(),
+        }
+    }
+
+    fn score_from_output(&self, output: InferenceOutput) -> AIResult<f32> {
+        let score = match output {
+            InferenceOutput::Classification {
+                class,
+                confidence,
+                probabilities,
+            } => probabilities
+                .iter()
+                .find(|(label, _)| is_relevant_label(label))
+                .map(|(_, probability)| *probability)
+                .unwrap_or(if is_relevant_label(&class) { confidence } else { 1.0 - confidence }),
+            InferenceOutput::Embedding(values) if values.len() == 1 => values[0],
+            InferenceOutput::Structured(value) => match value.get("score").and_then(|score| score.as_f64()) {
+                Some(score) => score as f32,
+                None => return Err(self.unexpected_output()),
+            },
+            _ => return Err(self.unexpected_output()),
+        };
+        Ok(score)
+    }
+
+    fn unexpected_output(&self) -> AIEngineError {
+        AIEngineError::PostprocessingFailed {
+            reason: format!("model {} did not return a relevance score", self.model),
+        }
+    }
+}
+
+fn is_relevant_label(label: &str) -> bool {
+    matches!(label.to_lowercase().as_str(), "relevant" | "label_1" | "1" | "yes" | "true")
+}
+
+#[async_trait]
+impl Reranker for InferenceReranker {
+    async fn score(&self, query: &str, passages: &[String]) -> AIResult<Vec<f32>> {
+        let requests = passages.iter().map(|passage| {
+            self.engine.infer(InferenceRequest {
+                id: Uuid::new_v4(),
+                model: self.model.clone(),
+                input: InferenceInput::Structured(serde_json::json!({
+                    "query": query,
+                    "passage": passage,
+                })),
+                parameters: InferenceParameters::default(),
+                backend: None,
+            })
+        });
+
+        futures::future::try_join_all(requests)
+            .await?
+            .into_iter()
+            .map(|response| self.score_from_output(response.output))
+            .collect()
+    }
+}
+
+/// When and how much to rerank
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RerankConfig {
+    /// Reranking adds a model call per candidate; turn it off when latency
+    /// matters more than precision
+    pub enabled: bool,
+    /// Most candidates sent to the reranker
+    pub candidate_cap: usize,
+}
+
+impl Default for RerankConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            candidate_cap: 50,
+        }
+    }
+}
+
+impl RerankConfig {
+    /// How many first-stage candidates to gather for a final `k` results
+    pub fn candidate_pool(&self, k: usize, reranker_available: bool) -> usize {
+        if self.enabled && reranker_available {
+            self.candidate_cap.max(k)
+        } else {
+            k
+        }
+    }
+}
+
+/// Reorder `candidates` (given in retrieval order) by reranker score and keep
+/// the best `k`, each paired with its rerank score. Falls back to the
+/// retrieval order, with no rerank scores, whenever reranking is skipped.
+pub async fn rerank<T, F>(
+    reranker: Option<&dyn Reranker>,
+    config: &RerankConfig,
+    query: &str,
+    mut candidates: Vec<T>,
+    passage: F,
+    k: usize,
+) -> Vec<(T, Option<f32>)>
+where
+    F: Fn(&T) -> String,
+{
+    let reranker = match reranker {
+        Some(reranker) if config.enabled && !candidates.is_empty() && k > 0 => reranker,
+        _ => return candidates.into_iter().take(k).map(|candidate| (candidate, None)).collect(),
+    };
+
+    candidates.truncate(config.candidate_cap.max(k));
+    let passages: Vec<String> = candidates.iter().map(passage).collect();
+
+    let scores = match reranker.score(query, &passages).await {
+        Ok(scores) if scores.len() == candidates.len() => scores,
+        Ok(scores) => {
+            warn!(
+                "Reranker returned {} scores for {} candidates; keeping retrieval order",
+                scores.len(),
+                candidates.len()
+            );
+            return candidates.into_iter().take(k).map(|candidate| (candidate, None)).collect();
+        }
+        Err(e) => {
+            warn!("Reranking skipped, keeping retrieval order: {}", e);
+            return candidates.into_iter().take(k).map(|candidate| (candidate, None)).collect();
+        }
+    };
+
+    debug!("Reranked {} candidates", candidates.len());
+    let mut scored: Vec<(T, f32)> = candidates.into_iter().zip(scores).collect();
+    // Stable sort keeps retrieval order among equal rerank scores
+    scored.sort_by(|a, b| b.1.total_cmp(&a.1));
+    scored
+        .into_iter()
+        .take(k)
+        .map(|(candidate, score)| (candidate, Some(score)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Scores passages by how many times they mention the query
+    struct CountingReranker;
+
+    #[async_trait]
+    impl Reranker for CountingReranker {
+        async fn score(&self, query: &str, passages: &[String]) -> AIResult<Vec<f32>> {
+            Ok(passages.iter().map(|p| p.matches(query).count() as f32).collect())
+        }
+    }
+
+    struct FailingReranker;
+
+    #[async_trait]
+    impl Reranker for FailingReranker {
+        async fn score(&self, _query: &str, _passages: &[String]) -> AIResult<Vec<f32>> {
+            Err(AIEngineError::ModelNotFound {
+                model: "cross-encoder".to_string(),
+            })
+        }
+    }
+
+    fn candidates() -> Vec<String> {
+        vec!["cache".to_string(), "cache cache cache".to_string(), "cache cache".to_string()]
+    }
+
+    #[tokio::test]
+    async fn reorders_by_rerank_score() {
+        let ranked = rerank(Some(&CountingReranker as &dyn Reranker), &RerankConfig::default(), "cache", candidates(), Clone::clone, 2).await;
+        assert_eq!(ranked, vec![
+            ("cache cache cache".to_string(), Some(3.0)),
+            ("cache cache".to_string(), Some(2.0)),
+        ]);
+    }
+
+    #[tokio::test]
+    async fn candidate_cap_bounds_the_rerank() {
+        let config = RerankConfig {
+            enabled: true,
+            candidate_cap: 1,
+        };
+        let ranked = rerank(Some(&CountingReranker as &dyn Reranker), &config, "cache", candidates(), Clone::clone, 1).await;
+        assert_eq!(ranked, vec![("cache".to_string(), Some(1.0))]);
+    }
+
+    #[tokio::test]
+    async fn falls_back_to_retrieval_order() {
+        let disabled = RerankConfig {
+            enabled: false,
+            ..RerankConfig::default()
+        };
+        for (reranker, config) in [
+            (None, RerankConfig::default()),
+            (Some(&FailingReranker as &dyn Reranker), RerankConfig::default()),
+            (Some(&CountingReranker as &dyn Reranker), disabled),
+        ] {
+            let ranked = rerank(reranker, &config, "cache", candidates(), Clone::clone, 2).await;
+            assert_eq!(ranked, vec![("cache".to_string(), None), ("cache cache cache".to_string(), None)]);
+        }
+    }
+}
@@ -0,0 +1,874 @@
+//! # Semantic Code Search
+//!
+//! Natural-language search over a project's source ("where do we validate
+//! email addresses?").
+//!
+//! Files are split along syntax boundaries: one chunk per function and per
+//! type definition found by the [`ASTParser`], with fixed-size line windows
+//! for code outside them or when parsing fails. Chunks are embedded through an
+//! [`Embedder`] and stored in a [`VectorStore`], brute force by default or an
+//! HNSW index for large projects, which ranks queries by cosine similarity.
+//! Each file is keyed by a hash of its contents, so re-indexing an unchanged
+//! file costs nothing and a changed file only re-embeds its own chunks.
+
+use crate::ast_parser::{ASTParser, Language};
+use crate::errors::{AIEngineError, AIResult};
+use crate::inference::InferenceEngine;
+use crate::nlp::{embed_batch, EmbeddingOptions};
+use crate::reranking::{rerank, RerankConfig, Reranker};
+use crate::vector_store::{BruteForceVectorStore, MetadataFilter, VectorRecord, VectorStore};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, RwLock};
+use tracing::{debug, warn};
+
+/// Lines per chunk for code outside functions and types
+pub const DEFAULT_WINDOW_LINES: usize = 40;
+
+/// Longer chunks are truncated before embedding
+const MAX_EMBED_CHARS: usize = 4000;
+
+/// Chunks sent to the embedder per call
+const EMBED_BATCH_SIZE: usize = 32;
+
+/// Metadata keys stored with every chunk vector
+const PATH_KEY: &str = "path";
+const LANGUAGE_KEY: &str = "language";
+const CHUNK_INDEX_KEY: &str = "chunk_index";
+
+/// Directories never worth indexing
+const SKIPPED_DIRS: &[&str] = &["target", "node_modules", "dist", "build", "vendor", "__pycache__"];
+
+const STOP_WORDS: &[&str] = &[
+    "a", "an", "and", "are", "as", "at", "be", "by", "do", "does", "for", "from", "how", "in",
+    "is", "it", "of", "on", "or", "our", "the", "that", "this", "to", "we", "what", "where",
+    "which", "who", "with",
+];
+
+/// Turns text into embedding vectors
+#[async_trait]
+pub trait Embedder: Send + Sync {
+    /// Embed each text; the result has one vector per input, in order
+    async fn embed(&self, texts: &[String]) -> AIResult<Vec<Vec<f32>>>;
+}
+
+/// Embeds through an [`InferenceEngine`] model, sending texts in batches
+/// through [`nlp::embed_batch`](crate::nlp::embed_batch)
+pub struct InferenceEmbedder {
+    engine: Arc<InferenceEngine>,
+    model: String,
+    options: EmbeddingOptions,
+}
+
+impl InferenceEmbedder {
+    pub fn new(engine: Arc<InferenceEngine>, model: impl Into<String>) -> Self {
+        let options = EmbeddingOptions::for_config(engine.config());
+        Self {
+            engine,
+            model: model.into(),
+            options,
+        }
+    }
+
+    /// Override the batch size and per-text token limit
+    pub fn with_options(mut self, options: EmbeddingOptions) -> Self {
+        self.options = options;
+        self
+    }
+}
+
+#[async_trait]
+impl Embedder for InferenceEmbedder {
+    async fn embed(&self, texts: &[String]) -> AIResult<Vec<Vec<f32>>> {
+        embed_batch(&self.engine, &self.model, texts, &self.options).await
+    }
+}
+
+/// Deterministic bag-of-terms embedder that needs no model.
+///
+/// Identifiers are split on case and underscores and terms are crudely
+/// stemmed, then hashed into a fixed number of signed buckets. Useful offline
+/// and in tests; a learned embedding model ranks far better.
+#[derive(Debug, Clone)]
+pub struct HashingEmbedder {
+    dimensions: usize,
+}
+
+impl HashingEmbedder {
+    pub fn new(dimensions: usize) -> Self {
+        Self {
+            dimensions: dimensions.max(1),
+        }
+    }
+
+    pub fn embed_text(&self, text: &str) -> Vec<f32> {
+        let mut vector = vec![0.0f32; self.dimensions];
+        for term in terms(text) {
+            let mut hasher = DefaultHasher::new();
+            term.hash(&mut hasher);
+            let hash = hasher.finish();
+            let bucket = (hash % self.dimensions as u64) as usize;
+            let sign = if hash >> 63 == 0 { 1.0 } else { -1.0 };
+            vector[bucket] += sign;
+        }
+        normalize(&mut vector);
+        vector
+    }
+}
+
+impl Default for HashingEmbedder {
+    fn default() -> Self {
+        Self::new(512)
+    }
+}
+
+#[async_trait]
+impl Embedder for HashingEmbedder {
+    async fn embed(&self, texts: &[String]) -> AIResult<Vec<Vec<f32>>> {
+        Ok(texts.iter().map(|text| self.embed_text(text)).collect())
+    }
+}
+
+/// What a chunk covers
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ChunkKind {
+    Function,
+    Type,
+    /// Line window outside any function or type
+    Block,
+}
+
+/// A searchable piece of a source file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CodeChunk {
+    pub path: PathBuf,
+    pub language: Language,
+    pub kind: ChunkKind,
+    /// Function or type name, when the chunk has one
+    pub symbol: Option<String>,
+    /// First line, 1-based
+    pub start_line: usize,
+    /// Last line, 1-based and inclusive
+    pub end_line: usize,
+    pub text: String,
+}
+
+impl CodeChunk {
+    fn embedding_input(&self) -> String {
+        let mut input = format!("{} {}\n", self.path.display(), self.symbol.as_deref().unwrap_or(""));
+        input.extend(self.text.chars().take(MAX_EMBED_CHARS));
+        input
+    }
+
+    fn describe(&self) -> String {
+        match (self.kind, &self.symbol) {
+            (ChunkKind::Function, Some(symbol)) => format!("Function `{}`", symbol),
+            (ChunkKind::Type, Some(symbol)) => format!("Type `{}`", symbol),
+            _ => format!("Lines {}-{}", self.start_line, self.end_line),
+        }
+    }
+}
+
+/// Natural-language query with optional filters
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CodeSearchQuery {
+    pub text: String,
+    /// Maximum number of hits
+    pub limit: usize,
+    /// Only search these languages; empty searches all
+    pub languages: Vec<Language>,
+    /// Only search files under this path
+    pub path_prefix: Option<PathBuf>,
+    /// Drop hits scoring below this
+    pub min_score: f32,
+    /// Rerank candidates when the index has a reranker
+    #[serde(default = "default_rerank")]
+    pub rerank: bool,
+}
+
+fn default_rerank() -> bool {
+    true
+}
+
+impl CodeSearchQuery {
+    pub fn new(text: impl Into<String>) -> Self {
+        Self {
+            text: text.into(),
+            limit: 10,
+            languages: Vec::new(),
+            path_prefix: None,
+            min_score: 0.0,
+            rerank: true,
+        }
+    }
+
+    pub fn with_limit(mut self, limit: usize) -> Self {
+        self.limit = limit;
+        self
+    }
+
+    pub fn with_language(mut self, language: Language) -> Self {
+        self.languages.push(language);
+        self
+    }
+
+    pub fn with_path_prefix(mut self, prefix: impl Into<PathBuf>) -> Self {
+        self.path_prefix = Some(prefix.into());
+        self
+    }
+
+    pub fn with_min_score(mut self, min_score: f32) -> Self {
+        self.min_score = min_score;
+        self
+    }
+
+    /// Skip reranking for this query, trading precision for latency
+    pub fn without_rerank(mut self) -> Self {
+        self.rerank = false;
+        self
+    }
+}
+
+/// A ranked code location
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CodeSearchHit {
+    pub path: PathBuf,
+    pub language: Language,
+    pub kind: ChunkKind,
+    pub symbol: Option<String>,
+    pub start_line: usize,
+    pub end_line: usize,
+    /// Cosine similarity to the query, in [-1, 1]
+    pub score: f32,
+    /// Cross-encoder relevance, when the hit was reranked
+    #[serde(default)]
+    pub rerank_score: Option<f32>,
+    /// Why this location matched
+    pub explanation: String,
+}
+
+/// Result of indexing one file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IndexOutcome {
+    /// Contents hash matched the indexed version
+    Unchanged,
+    /// File was (re-)chunked and embedded
+    Indexed { chunks: usize },
+}
+
+/// Totals from [`SemanticCodeIndex::index_directory`]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IndexStats {
+    pub indexed: usize,
+    pub unchanged: usize,
+    pub removed: usize,
+    pub skipped: usize,
+}
+
+/// A chunk whose embedding is in the vector store
+struct IndexedChunk {
+    chunk: CodeChunk,
+    terms: HashSet<String>,
+}
+
+struct IndexedFile {
+    content_hash: u64,
+    chunks: Vec<IndexedChunk>,
+}
+
+/// Embedding index over a project's source files
+pub struct SemanticCodeIndex {
+    embedder: Arc<dyn Embedder>,
+    store: Arc<dyn VectorStore>,
+    /// `None` when tree-sitter could not be initialised; files are then
+    /// chunked by line windows only
+    parser: Option<Mutex<ASTParser>>,
+    files: RwLock<HashMap<PathBuf, IndexedFile>>,
+    window_lines: usize,
+    reranker: Option<Arc<dyn Reranker>>,
+    rerank_config: RerankConfig,
+}
+
+impl SemanticCodeIndex {
+    pub fn new(embedder: Arc<dyn Embedder>) -> Self {
+        let parser = match ASTParser::new() {
+            Ok(parser) => Some(Mutex::new(parser)),
+            Err(e) => {
+                warn!("AST parser unavailable, falling back to line windows: {}", e);
+                None
+            }
+        };
+
+        Self {
+            embedder,
+            store: Arc::new(BruteForceVectorStore::new()),
+            parser,
+            files: RwLock::new(HashMap::new()),
+            window_lines: DEFAULT_WINDOW_LINES,
+            reranker: None,
+            rerank_config: RerankConfig::default(),
+        }
+    }
+
+    /// Keep chunk embeddings in `store`, which should hold nothing else
+    pub fn with_store(mut self, store: Arc<dyn VectorStore>) -> Self {
+        self.store = store;
+        self
+    }
+
+    pub fn with_window_lines(mut self, window_lines: usize) -> Self {
+        self.window_lines = window_lines.max(1);
+        self
+    }
+
+    /// Rerank the top candidates of each search with a cross-encoder
+    pub fn with_reranker(mut self, reranker: Arc<dyn Reranker>, config: RerankConfig) -> Self {
+        self.reranker = Some(reranker);
+        self.rerank_config = config;
+        self
+    }
+
+    /// Number of indexed files
+    pub fn file_count(&self) -> usize {
+        self.files.read().unwrap().len()
+    }
+
+    /// Number of indexed chunks across all files
+    pub fn chunk_count(&self) -> usize {
+        self.files.read().unwrap().values().map(|file| file.chunks.len()).sum()
+    }
+
+    /// Index or re-index one file. Files in unsupported languages are ignored.
+    pub async fn index_file(&self, path: impl AsRef<Path>, source: &str) -> AIResult<Option<IndexOutcome>> {
+        let path = path.as_ref();
+        let language = match Language::from_path(path) {
+            Some(language) => language,
+            None => return Ok(None),
+        };
+
+        let content_hash = hash_source(source);
+        if self.files.read().unwrap().get(path).map(|file| file.content_hash) == Some(content_hash) {
+            return Ok(Some(IndexOutcome::Unchanged));
+        }
+
+        let chunks = self.chunk_source(path, source, language);
+        let mut embeddings = Vec::with_capacity(chunks.len());
+        for batch in chunks.chunks(EMBED_BATCH_SIZE) {
+            let inputs: Vec<String> = batch.iter().map(CodeChunk::embedding_input).collect();
+            let vectors = self.embedder.embed(&inputs).await?;
+            if vectors.len() != inputs.len() {
+                return Err(AIEngineError::PostprocessingFailed {
+                    reason: format!("embedder returned {} vectors for {} inputs", vectors.len(), inputs.len()),
+                });
+            }
+            embeddings.extend(vectors);
+        }
+
+        let chunk_total = chunks.len();
+        let path_key = path.display().to_string();
+        let records = embeddings
+            .into_iter()
+            .enumerate()
+            .map(|(chunk_index, embedding)| {
+                VectorRecord::new(format!("{}#{}", path_key, chunk_index), embedding)
+                    .with_metadata(PATH_KEY, path_key.clone())
+                    .with_metadata(LANGUAGE_KEY, format!("{:?}", language))
+                    .with_metadata(CHUNK_INDEX_KEY, chunk_index.to_string())
+            })
+            .collect();
+        // The new version may have fewer chunks than the one it replaces
+        self.store.delete_where(&MetadataFilter::new().eq(PATH_KEY, path_key)).await?;
+        self.store.upsert(records).await?;
+
+        let indexed = chunks
+            .into_iter()
+            .map(|chunk| IndexedChunk {
+                terms: terms(&chunk.embedding_input()).collect(),
+                chunk,
+            })
+            .collect();
+
+        debug!("Indexed {} ({} chunks)", path.display(), chunk_total);
+        self.files.write().unwrap().insert(
+            path.to_path_buf(),
+            IndexedFile {
+                content_hash,
+                chunks: indexed,
+            },
+        );
+        Ok(Some(IndexOutcome::Indexed { chunks: chunk_total }))
+    }
+
+    /// Drop a file from the index; returns whether it was indexed
+    pub async fn remove_file(&self, path: impl AsRef<Path>) -> AIResult<bool> {
+        let path = path.as_ref();
+        let filter = MetadataFilter::new().eq(PATH_KEY, path.display().to_string());
+        self.store.delete_where(&filter).await?;
+        Ok(self.files.write().unwrap().remove(path).is_some())
+    }
+
+    /// Bring the index in line with every supported file under `root`:
+    /// new and changed files are indexed, vanished ones removed.
+    pub async fn index_directory(&self, root: impl AsRef<Path>) -> AIResult<IndexStats> {
+        let root = root.as_ref();
+        let mut stats = IndexStats::default();
+        let mut seen = HashSet::new();
+
+        for path in source_files(root)? {
+            let source = match std::fs::read_to_string(&path) {
+                Ok(source) => source,
+                Err(_) => {
+                    stats.skipped += 1;
+                    continue;
+                }
+            };
+            match self.index_file(&path, &source).await? {
+                Some(IndexOutcome::Indexed { .. }) => stats.indexed += 1,
+                Some(IndexOutcome::Unchanged) => stats.unchanged += 1,
+                None => stats.skipped += 1,
+            }
+            seen.insert(path);
+        }
+
+        let vanished: Vec<PathBuf> = self
+            .files
+            .read()
+            .unwrap()
+            .keys()
+            .filter(|path| path.starts_with(root) && !seen.contains(*path))
+            .cloned()
+            .collect();
+        for path in vanished {
+            if self.remove_file(&path).await? {
+                stats.removed += 1;
+            }
+        }
+
+        Ok(stats)
+    }
+
+    /// Rank indexed code against a natural-language query
+    pub async fn search(&self, query: &CodeSearchQuery) -> AIResult<Vec<CodeSearchHit>> {
+        if query.limit == 0 || query.text.trim().is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let query_embedding = self
+            .embedder
+            .embed(std::slice::from_ref(&query.text))
+            .await?
+            .pop()
+            .ok_or_else(|| AIEngineError::PostprocessingFailed {
+                reason: "embedder returned no vector for the query".to_string(),
+            })?;
+        let query_terms: Vec<String> = unique_terms(&query.text);
+
+        let reranker = self.reranker.as_deref().filter(|_| query.rerank);
+        let pool = self.rerank_config.candidate_pool(query.limit, reranker.is_some());
+
+        let mut filter = MetadataFilter::new();
+        if !query.languages.is_empty() {
+            filter = filter.any_of(LANGUAGE_KEY, query.languages.iter().map(|language| format!("{:?}", language)));
+        }
+        if let Some(prefix) = &query.path_prefix {
+            let paths: Vec<String> = self
+                .files
+                .read()
+                .unwrap()
+                .keys()
+                .filter(|path| path.starts_with(prefix))
+                .map(|path| path.display().to_string())
+                .collect();
+            if paths.is_empty() {
+                return Ok(Vec::new());
+            }
+            filter = filter.any_of(PATH_KEY, paths);
+        }
+        let matches = self.store.query(&query_embedding, pool, Some(&filter)).await?;
+
+        // Hits paired with their chunk text for the reranker; the lock must
+        // be released before awaiting it
+        let mut hits: Vec<(CodeSearchHit, String)> = {
+            let files = self.files.read().unwrap();
+            matches
+                .into_iter()
+                .filter(|found| found.score >= query.min_score)
+                .filter_map(|found| {
+                    let file = files.get(Path::new(found.metadata.get(PATH_KEY)?))?;
+                    let index: usize = found.metadata.get(CHUNK_INDEX_KEY)?.parse().ok()?;
+                    let indexed = file.chunks.get(index)?;
+                    let chunk = &indexed.chunk;
+                    let hit = CodeSearchHit {
+                        path: chunk.path.clone(),
+                        language: chunk.language,
+                        kind: chunk.kind,
+                        symbol: chunk.symbol.clone(),
+                        start_line: chunk.start_line,
+                        end_line: chunk.end_line,
+                        score: found.score,
+                        rerank_score: None,
+                        explanation: explain(chunk, &query_terms, &indexed.terms, found.score),
+                    };
+                    Some((hit, chunk.text.clone()))
+                })
+                .collect()
+        };
+
+        hits.sort_by(|(a, _), (b, _)| {
+            b.score
+                .partial_cmp(&a.score)
+                .unwrap_or(std::cmp::Ordering::Equal)
+                .then_with(|| a.path.cmp(&b.path))
+                .then_with(|| a.start_line.cmp(&b.start_line))
+        });
+
+        let ranked = rerank(reranker, &self.rerank_config, &query.text, hits, |(_, text)| text.clone(), query.limit).await;
+        Ok(ranked
+            .into_iter()
+            .map(|((hit, _), rerank_score)| CodeSearchHit { rerank_score, ..hit })
+            .collect())
+    }
+
+    fn chunk_source(&self, path: &Path, source: &str, language: Language) -> Vec<CodeChunk> {
+        let lines: Vec<&str> = source.lines().collect();
+        let mut chunks = Vec::new();
+
+        if let Some(parser) = &self.parser {
+            let parser = parser.lock().unwrap();
+            match parser.parse(source, language) {
+                Ok(ast) => {
+                    // (start_line, end_line, byte range, name, kind), 0-based lines
+                    let mut spans = Vec::new();
+                    for function in parser.extract_functions(&ast).unwrap_or_default() {
+                        spans.push((function.start_line, function.end_line, function.start_byte..function.end_byte, function.name, ChunkKind::Function));
+                    }
+                    for definition in parser.extract_structs(&ast).unwrap_or_default() {
+                        let start = line_of(source, definition.start_byte);
+                        let end = line_of(source, definition.end_byte);
+                        spans.push((start, end, definition.start_byte..definition.end_byte, definition.name, ChunkKind::Type));
+                    }
+                    spans.sort_by_key(|(start, end, ..)| (*start, std::cmp::Reverse(*end)));
+
+                    let mut covered_until: Option<usize> = None;
+                    for (start, end, bytes, name, kind) in spans {
+                        // Methods inside a type and nested closures are part of their parent chunk
+                        if covered_until.map_or(false, |until| end <= until) && kind == ChunkKind::Function {
+                            continue;
+                        }
+                        let text = match source.get(bytes) {
+                            Some(text) => text.to_string(),
+                            None => continue,
+                        };
+                        covered_until = Some(covered_until.map_or(end, |until| until.max(end)));
+                        chunks.push(CodeChunk {
+                            path: path.to_path_buf(),
+                            language,
+                            kind,
+                            symbol: (!name.is_empty()).then_some(name),
+                            start_line: start + 1,
+                            end_line: end + 1,
+                            text,
+                        });
+                    }
+                }
+                Err(e) => debug!("Falling back to line windows for {}: {}", path.display(), e),
+            }
+        }
+
+        let mut covered = vec![false; lines.len()];
+        for chunk in &chunks {
+            for line in chunk.start_line - 1..chunk.end_line.min(lines.len()) {
+                covered[line] = true;
+            }
+        }
+        chunks.extend(window_chunks(path, language, &lines, &covered, self.window_lines));
+        chunks.sort_by_key(|chunk| chunk.start_line);
+        chunks
+    }
+}
+
+/// Group uncovered, non-blank lines into windows of at most `window_lines`
+fn window_chunks(path: &Path, language: Language, lines: &[&str], covered: &[bool], window_lines: usize) -> Vec<CodeChunk> {
+    let mut chunks = Vec::new();
+    let mut start: Option<usize> = None;
+
+    let flush = |start: usize, end: usize, chunks: &mut Vec<CodeChunk>| {
+        let text = lines[start..end].join("\n");
+        if !text.trim().is_empty() {
+            chunks.push(CodeChunk {
+                path: path.to_path_buf(),
+                language,
+                kind: ChunkKind::Block,
+                symbol: None,
+                start_line: start + 1,
+                end_line: end,
+                text,
+            });
+        }
+    };
+
+    for index in 0..=lines.len() {
+        let open = index < lines.len() && !covered[index];
+        match (start, open) {
+            (None, true) => start = Some(index),
+            (Some(begin), false) => {
+                flush(begin, index, &mut chunks);
+                start = None;
+            }
+            (Some(begin), true) if index - begin == window_lines => {
+                flush(begin, index, &mut chunks);
+                start = Some(index);
+            }
+            _ => {}
+        }
+    }
+    chunks
+}
+
+fn source_files(root: &Path) -> AIResult<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    let mut pending = vec![root.to_path_buf()];
+
+    while let Some(dir) = pending.pop() {
+        for entry in std::fs::read_dir(&dir)? {
+            let entry = entry?;
+            let path = entry.path();
+            let name = entry.file_name();
+            let name = name.to_string_lossy();
+            let file_type = entry.file_type()?;
+
+            if file_type.is_dir() {
+                if !name.starts_with('.') && !SKIPPED_DIRS.contains(&name.as_ref()) {
+                    pending.push(path);
+                }
+            } else if file_type.is_file() && Language::from_path(&path).is_some() {
+                files.push(path);
+            }
+        }
+    }
+
+    files.sort();
+    Ok(files)
+}
+
+fn explain(chunk: &CodeChunk, query_terms: &[String], chunk_terms: &HashSet<String>, score: f32) -> String {
+    let shared: Vec<&str> = query_terms
+        .iter()
+        .filter(|term| chunk_terms.contains(*term))
+        .map(String::as_str)
+        .take(5)
+        .collect();
+
+    if shared.is_empty() {
+        format!("{} is semantically close to the query (score {:.2})", chunk.describe(), score)
+    } else {
+        format!("{} matches {} (score {:.2})", chunk.describe(), shared.join(", "), score)
+    }
+}
+
+fn hash_source(source: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    source.hash(&mut hasher);
+    hasher.finish()
+}
+
+fn line_of(source: &str, byte: usize) -> usize {
+    source
+        .get(..byte.min(source.len()))
+        .map_or(0, |prefix| prefix.matches('\n').count())
+}
+
+/// Lowercased, stemmed terms with identifiers split on case and underscores
+fn terms(text: &str) -> impl Iterator<Item = String> + '_ {
+    text.split(|c: char| !c.is_alphanumeric())
+        .flat_map(split_identifier)
+        .map(|word| word.to_lowercase())
+        .filter(|word| !STOP_WORDS.contains(&word.as_str()))
+        .map(|word| stem(&word))
+        .filter(|term| term.len() > 1)
+}
+
+fn unique_terms(text: &str) -> Vec<String> {
+    let mut seen = HashSet::new();
+    terms(text).filter(|term| seen.insert(term.clone())).collect()
+}
+
+/// `validateEmailAddress` -> `validate`, `Email`, `Address`
+fn split_identifier(word: &str) -> Vec<String> {
+    let mut parts = Vec::new();
+    let mut current = String::new();
+    let chars: Vec<char> = word.chars().collect();
+
+    for (i, &c) in chars.iter().enumerate() {
+        let boundary = c.is_uppercase()
+            && !current.is_empty()
+            && (chars[i - 1].is_lowercase()
+                || chars.get(i + 1).map_or(false, |next| next.is_lowercase()));
+        if boundary {
+            parts.push(std::mem::take(&mut current));
+        }
+        current.push(c);
+    }
+    if !current.is_empty() {
+        parts.push(current);
+    }
+    parts
+}
+
+/// Crude suffix stripping so "validates", "validation" and "validate" meet
+fn stem(word: &str) -> String {
+    const SUFFIXES: &[&str] = &["ation", "ing", "ate", "ies", "es", "ed", "s", "e"];
+    let mut word = word.to_string();
+
+    for _ in 0..2 {
+        let stripped = SUFFIXES.iter().find_map(|suffix| {
+            let base = word.strip_suffix(suffix)?;
+            let keeps_ss = *suffix == "s" && base.ends_with('s');
+            let es_ok = *suffix != "es" || base.ends_with(['s', 'x', 'z', 'h']);
+            (base.chars().count() >= 4 && !keeps_ss && es_ok).then(|| {
+                if *suffix == "ies" { format!("{}y", base) } else { base.to_string() }
+            })
+        });
+        match stripped {
+            Some(base) => word = base,
+            None => break,
+        }
+    }
+    word
+}
+
+fn normalize(vector: &mut [f32]) {
+    let norm = vector.iter().map(|v| v * v).sum::<f32>().sqrt();
+    if norm > 0.0 {
+        vector.iter_mut().for_each(|v| *v /= norm);
+    }
+}
+
+/// Cosine similarity; 0.0 for mismatched or zero vectors
+pub fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    if a.len() != b.len() {
+        return 0.0;
+    }
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm_a = a.iter().map(|v| v * v).sum::<f32>().sqrt();
+    let norm_b = b.iter().map(|v| v * v).sum::<f32>().sqrt();
+    if norm_a == 0.0 || norm_b == 0.0 {
+        0.0
+    } else {
+        dot / (norm_a * norm_b)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vector_store::{HnswParams, HnswVectorStore};
+
+    const VALIDATION: &str = r#"
+pub fn validate_email_address(email: &str) -> bool {
+    let parts: Vec<&str> = email.split('@').collect();
+    parts.len() == 2 && parts[1].contains('.')
+}
+
+pub fn render_invoice(total: f64) -> String {
+    format!("Total due: {:.2}", total)
+}
+"#;
+
+    fn index() -> SemanticCodeIndex {
+        SemanticCodeIndex::new(Arc::new(HashingEmbedder::default()))
+    }
+
+    #[tokio::test]
+    async fn natural_language_query_finds_the_matching_function() {
+        let index = index();
+        index.index_file("src/validation.rs", VALIDATION).await.unwrap();
+        index
+            .index_file("web/format.ts", "export function formatDate(d: Date) { return d.toISOString(); }\n")
+            .await
+            .unwrap();
+
+        let hits = index
+            .search(&CodeSearchQuery::new("where do we validate email addresses?"))
+            .await
+            .unwrap();
+
+        let top = &hits[0];
+        assert_eq!(top.path, PathBuf::from("src/validation.rs"));
+        assert_eq!(top.symbol.as_deref(), Some("validate_email_address"));
+        assert_eq!((top.start_line, top.end_line), (2, 5));
+        assert!(top.explanation.contains("email"), "{}", top.explanation);
+
+        let filtered = index
+            .search(&CodeSearchQuery::new("validate email").with_language(Language::TypeScript))
+            .await
+            .unwrap();
+        assert!(filtered.iter().all(|hit| hit.language == Language::TypeScript));
+
+        let scoped = index
+            .search(&CodeSearchQuery::new("validate email").with_path_prefix("web"))
+            .await
+            .unwrap();
+        assert!(scoped.iter().all(|hit| hit.path.starts_with("web")));
+    }
+
+    #[tokio::test]
+    async fn reindexing_skips_unchanged_files_and_replaces_changed_ones() {
+        let index = index();
+        assert!(matches!(
+            index.index_file("src/validation.rs", VALIDATION).await.unwrap(),
+            Some(IndexOutcome::Indexed { .. })
+        ));
+        assert_eq!(
+            index.index_file("src/validation.rs", VALIDATION).await.unwrap(),
+            Some(IndexOutcome::Unchanged)
+        );
+        assert_eq!(index.index_file("README.md", "# docs").await.unwrap(), None);
+
+        index
+            .index_file("src/validation.rs", "pub fn parse_phone(input: &str) -> String { input.to_string() }\n")
+            .await
+            .unwrap();
+        assert_eq!(index.file_count(), 1);
+
+        let hits = index.search(&CodeSearchQuery::new("validate email")).await.unwrap();
+        assert!(hits.iter().all(|hit| hit.symbol.as_deref() != Some("validate_email_address")));
+
+        assert!(index.remove_file("src/validation.rs").await.unwrap());
+        assert_eq!(index.chunk_count(), 0);
+        assert!(index.store.is_empty().await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn chunks_are_searched_through_the_configured_vector_store() {
+        let store: Arc<dyn VectorStore> = Arc::new(HnswVectorStore::new(HnswParams::default()).unwrap());
+        let index = index().with_store(store.clone());
+        index.index_file("src/validation.rs", VALIDATION).await.unwrap();
+        assert_eq!(store.len().await.unwrap(), index.chunk_count());
+
+        let hits = index.search(&CodeSearchQuery::new("validate email").with_limit(1)).await.unwrap();
+        assert_eq!(hits[0].symbol.as_deref(), Some("validate_email_address"));
+
+        index.index_file("src/validation.rs", "pub fn render() {}\n").await.unwrap();
+        assert_eq!(store.len().await.unwrap(), 1);
+    }
+
+    #[test]
+    fn identifiers_are_split_and_stemmed() {
+        let found: Vec<String> = terms("validateEmailAddresses HTTPServer is_valid").collect();
+        assert_eq!(found, vec!["valid", "email", "address", "http", "server", "valid"]);
+    }
+
+    #[test]
+    fn uncovered_lines_become_windows() {
+        let lines = vec!["use a;", "", "fn f() {}", "const X: u8 = 1;", "const Y: u8 = 2;", "const Z: u8 = 3;"];
+        let covered = vec![false, false, true, false, false, false];
+        let chunks = window_chunks(Path::new("lib.rs"), Language::Rust, &lines, &covered, 2);
+        let ranges: Vec<(usize, usize)> = chunks.iter().map(|c| (c.start_line, c.end_line)).collect();
+        assert_eq!(ranges, vec![(1, 2), (4, 5), (6, 6)]);
+    }
+}
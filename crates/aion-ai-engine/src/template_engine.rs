@@ -10,6 +10,7 @@ use uuid::Uuid;
 
 use crate::errors::{AIEngineError, Result};
 use crate::code_generation::GeneratedCode;
+use crate::template_layout::TemplateLayouts;
 
 /// Advanced template engine for generating full-stack projects
 pub struct TemplateEngine {
@@ -1256,6 +1257,7 @@ pub struct CompatibilityMatrix {
 /// Template renderer for processing template files
 pub struct TemplateRenderer {
     engine: handlebars::Handlebars<'static>,
+    layouts: RwLock<TemplateLayouts>,
 }
 
 /// File generator for creating project files
@@ -1296,6 +1298,14 @@ impl TemplateEngine {
         Ok(project)
     }
 
+    /// Register a shared layout, partial or child template
+    ///
+    /// Layouts registered here are available to every project template, so
+    /// marketplace and scaffolding templates can extend the same base.
+    pub async fn register_layout_template(&self, name: &str, source: &str) -> Result<()> {
+        self.project_generator.register_layout_template(name, source).await
+    }
+
     /// Get all available templates
     pub async fn list_templates(&self) -> Result<Vec<ProjectTemplate>> {
         let registry = self.template_registry.read().await;
@@ -2267,6 +2277,10 @@ impl ProjectGenerator {
         })
     }
 
+    pub async fn register_layout_template(&self, name: &str, source: &str) -> Result<()> {
+        self.template_engine.register_layout_template(name, source).await
+    }
+
     pub async fn generate(&self, template: &ProjectTemplate, project_name: &str) -> Result<GeneratedCode> {
         // Generate complete project based on template
         let mut generated_files = Vec::new();
@@ -2428,7 +2442,15 @@ impl TemplateRenderer {
         // Register built-in templates
         engine.register_template_string("rust_cargo_toml", include_str!("../templates/rust/Cargo.toml.hbs"))?;
 
-        Ok(Self { engine })
+        Ok(Self {
+            engine,
+            layouts: RwLock::new(TemplateLayouts::new()),
+        })
+    }
+
+    /// Register a template that can extend layouts and include partials
+    pub async fn register_layout_template(&self, name: &str, source: &str) -> Result<()> {
+        self.layouts.write().await.register(name, source)
     }
 
     pub async fn render_template(
@@ -2436,6 +2458,11 @@ impl TemplateRenderer {
         template_name: &str,
         variables: &HashMap<String, String>,
     ) -> Result<String> {
+        let layouts = self.layouts.read().await;
+        if layouts.contains(template_name) {
+            return layouts.render(template_name, variables);
+        }
+
         self.engine.render(template_name, variables)
             .map_err(|e| AIEngineError::Processing(format!("Template rendering failed: {}", e)))
     }
@@ -0,0 +1,417 @@
+//! # Template Layouts
+//!
+//! Composition of templates through handlebars partials, so marketplace and
+//! scaffolding templates can share a common layout.
+//!
+//! - A layout marks its overridable blocks as partial blocks with default
+//!   content: `{{#> body}}empty{{/body}}`.
+//! - A child template calls its layout as a partial block and overrides
+//!   blocks with inline partials:
+//!   `{{#> base}}{{#*inline "body"}}...{{/inline}}{{/base}}`. The layout is
+//!   rendered in the child's scope.
+//! - `{{> header title=project_name license="MIT"}}` includes a partial. The
+//!   partial sees only the hash arguments passed to it.
+//!
+//! Templates are rendered in strict mode without HTML escaping. Unknown
+//! templates, overrides of blocks the layout does not define, variables an
+//! include does not pass and templates that call themselves, directly or
+//! transitively, are reported with the template name and line/column of the
+//! offending tag before anything is rendered.
+
+use std::collections::{HashMap, HashSet};
+
+use handlebars::template::{DecoratorTemplate, HelperTemplate, Parameter, Template, TemplateElement};
+use handlebars::Handlebars;
+use serde_json::Value;
+
+use crate::errors::{AIEngineError, AIResult};
+
+/// Line and column (both 1-based) of a tag in a template source
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SourcePosition {
+    pub line: usize,
+    pub column: usize,
+}
+
+impl SourcePosition {
+    fn of(template: &Template, index: usize) -> Self {
+        template
+            .mapping
+            .get(index)
+            .map(|mapping| Self { line: mapping.0, column: mapping.1 })
+            .unwrap_or(Self { line: 1, column: 1 })
+    }
+}
+
+/// A `{{> name}}` or `{{#> name}}...{{/name}}` tag in a template
+struct PartialCall<'t> {
+    name: &'t str,
+    position: SourcePosition,
+    /// Called as a partial block, which has default content
+    block: bool,
+    /// Hash argument names, the whole scope of an included partial
+    arguments: Vec<&'t str>,
+    /// Inline partials defined in the block, overriding blocks of the layout
+    overrides: Vec<(&'t str, SourcePosition)>,
+}
+
+/// Registry of named templates that can extend and include each other
+#[derive(Debug, Clone)]
+pub struct TemplateLayouts {
+    registry: Handlebars<'static>,
+}
+
+impl Default for TemplateLayouts {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TemplateLayouts {
+    pub fn new() -> Self {
+        let mut registry = Handlebars::new();
+        registry.set_strict_mode(true);
+        // The templates generate source files, not HTML
+        registry.register_escape_fn(handlebars::no_escape);
+        Self { registry }
+    }
+
+    /// Parse and register a template, replacing any template of the same name
+    ///
+    /// Syntax errors are reported here; references to other templates are
+    /// resolved at render time, so templates may be registered in any order.
+    pub fn register(&mut self, name: impl Into<String>, source: &str) -> AIResult<()> {
+        let name = name.into();
+        self.registry.register_template_string(&name, source).map_err(|e| {
+            let position = SourcePosition { line: e.line_no.unwrap_or(1), column: e.column_no.unwrap_or(1) };
+            template_error(&name, position, e.to_string())
+        })
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.registry.has_template(name)
+    }
+
+    /// Render a registered template with the given variables
+    pub fn render(&self, name: &str, variables: &HashMap<String, String>) -> AIResult<String> {
+        self.check(name, &mut Vec::new(), &HashSet::new())?;
+        self.registry.render(name, variables).map_err(|e| {
+            let template = e.template_name.clone().unwrap_or_else(|| name.to_string());
+            let position = SourcePosition { line: e.line_no.unwrap_or(1), column: e.column_no.unwrap_or(1) };
+            template_error(&template, position, e.desc)
+        })
+    }
+
+    fn template(&self, name: &str) -> AIResult<&Template> {
+        self.registry
+            .get_template(name)
+            .ok_or_else(|| AIEngineError::TemplateNotFound { template: name.to_string() })
+    }
+
+    /// Check the partials `name` calls, given the stack of templates being
+    /// checked and the blocks overridden by the templates extending it
+    fn check<'t>(&'t self, name: &str, stack: &mut Vec<String>, overridden: &HashSet<&'t str>) -> AIResult<()> {
+        let template = self.template(name)?;
+        stack.push(name.to_string());
+
+        let mut calls = Vec::new();
+        partial_calls(template, &mut calls);
+        for call in calls {
+            // `@partial-block`, or a block of this layout filled by an
+            // override or by its default content
+            if call.name.starts_with('@')
+                || overridden.contains(call.name)
+                || (call.block && call.overrides.is_empty() && !self.contains(call.name))
+            {
+                continue;
+            }
+
+            if let Some(start) = stack.iter().position(|entry| entry == call.name) {
+                let cycle = stack[start..].iter().map(String::as_str).chain([call.name]).collect::<Vec<_>>();
+                return Err(template_error(name, call.position, format!("template cycle detected: {}", cycle.join(" -> "))));
+            }
+            if !self.contains(call.name) {
+                return Err(template_error(name, call.position, format!("template '{}' is not registered", call.name)));
+            }
+
+            if call.block {
+                let blocks = self.blocks(call.name, &mut HashSet::new());
+                for (block, position) in &call.overrides {
+                    if !blocks.contains(block) {
+                        return Err(template_error(
+                            name,
+                            *position,
+                            format!("block '{}' is not defined by '{}' or its ancestors", block, call.name),
+                        ));
+                    }
+                }
+                let mut inherited = overridden.clone();
+                inherited.extend(call.overrides.iter().map(|(block, _)| *block));
+                self.check(call.name, stack, &inherited)?;
+            } else {
+                self.check_scope(call.name, &call.arguments)?;
+                self.check(call.name, stack, &HashSet::new())?;
+            }
+        }
+
+        stack.pop();
+        Ok(())
+    }
+
+    /// Blocks `layout` and the layouts it extends let a child override
+    fn blocks<'t>(&'t self, layout: &'t str, visited: &mut HashSet<&'t str>) -> HashSet<&'t str> {
+        let mut blocks = HashSet::new();
+        let Some(template) = self.registry.get_template(layout) else {
+            return blocks;
+        };
+        if !visited.insert(layout) {
+            return blocks;
+        }
+
+        let mut calls = Vec::new();
+        partial_calls(template, &mut calls);
+        for call in calls {
+            if call.block && self.contains(call.name) {
+                blocks.extend(self.blocks(call.name, visited));
+            } else if !call.name.starts_with('@') {
+                blocks.insert(call.name);
+            }
+        }
+        blocks
+    }
+
+    /// An included partial may only use the variables passed to it
+    fn check_scope(&self, partial: &str, arguments: &[&str]) -> AIResult<()> {
+        let mut variables = Vec::new();
+        free_variables(self.template(partial)?, &mut variables);
+        match variables.into_iter().find(|(variable, _)| !arguments.contains(variable)) {
+            Some((variable, position)) => Err(template_error(
+                partial,
+                position,
+                format!("variable '{}' is not passed to '{}'", variable, partial),
+            )),
+            None => Ok(()),
+        }
+    }
+}
+
+fn partial_calls<'t>(template: &'t Template, calls: &mut Vec<PartialCall<'t>>) {
+    for (index, element) in template.elements.iter().enumerate() {
+        match element {
+            TemplateElement::PartialExpression(partial) | TemplateElement::PartialBlock(partial) => {
+                let block = matches!(element, TemplateElement::PartialBlock(_));
+                if let Some(name) = partial.name.as_name() {
+                    calls.push(PartialCall {
+                        name,
+                        position: SourcePosition::of(template, index),
+                        block,
+                        arguments: partial.hash.keys().map(String::as_str).collect(),
+                        overrides: partial.template.as_ref().map(inline_partials).unwrap_or_default(),
+                    });
+                }
+                if let Some(content) = &partial.template {
+                    partial_calls(content, calls);
+                }
+            }
+            TemplateElement::HelperBlock(helper) => {
+                for nested in helper.template.iter().chain(&helper.inverse) {
+                    partial_calls(nested, calls);
+                }
+            }
+            TemplateElement::DecoratorBlock(decorator) => {
+                if let Some(content) = &decorator.template {
+                    partial_calls(content, calls);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// `{{#*inline "name"}}` definitions directly inside a partial block
+fn inline_partials(content: &Template) -> Vec<(&str, SourcePosition)> {
+    content
+        .elements
+        .iter()
+        .enumerate()
+        .filter_map(|(index, element)| match element {
+            TemplateElement::DecoratorBlock(decorator) => {
+                inline_name(decorator).map(|name| (name, SourcePosition::of(content, index)))
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+fn inline_name(decorator: &DecoratorTemplate) -> Option<&str> {
+    if decorator.name.as_name() != Some("inline") {
+        return None;
+    }
+    match decorator.params.first() {
+        Some(Parameter::Literal(Value::String(name))) => Some(name),
+        _ => None,
+    }
+}
+
+/// Variables a template reads from its scope, ignoring the bodies of blocks
+/// such as `each` and `with` that change the scope
+fn free_variables<'t>(template: &'t Template, variables: &mut Vec<(&'t str, SourcePosition)>) {
+    for (index, element) in template.elements.iter().enumerate() {
+        let position = SourcePosition::of(template, index);
+        match element {
+            TemplateElement::Expression(helper) | TemplateElement::HtmlExpression(helper) => {
+                if helper.params.is_empty() && helper.hash.is_empty() {
+                    variables.extend(helper.name.as_name().and_then(scope_root).map(|root| (root, position)));
+                } else {
+                    helper_variables(helper, position, variables);
+                }
+            }
+            TemplateElement::HelperBlock(helper) => {
+                helper_variables(helper, position, variables);
+                let keeps_scope = matches!(helper.name.as_name(), Some("if" | "unless"));
+                for nested in helper.template.iter().filter(|_| keeps_scope).chain(&helper.inverse) {
+                    free_variables(nested, variables);
+                }
+            }
+            TemplateElement::PartialExpression(partial) | TemplateElement::PartialBlock(partial) => {
+                for parameter in partial.params.iter().chain(partial.hash.values()) {
+                    variables.extend(parameter.as_name().and_then(scope_root).map(|root| (root, position)));
+                }
+                if let Some(content) = &partial.template {
+                    free_variables(content, variables);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn helper_variables<'t>(helper: &'t HelperTemplate, position: SourcePosition, variables: &mut Vec<(&'t str, SourcePosition)>) {
+    for parameter in helper.params.iter().chain(helper.hash.values()) {
+        variables.extend(parameter.as_name().and_then(scope_root).map(|root| (root, position)));
+    }
+}
+
+/// The first segment of a path in the current scope, `None` for `this`,
+/// `@` data and parent scope paths
+fn scope_root(path: &str) -> Option<&str> {
+    if path.starts_with('@') || path.starts_with("..") || path.starts_with('.') || path.starts_with("this") {
+        return None;
+    }
+    path.split(|c| c == '.' || c == '/').next().filter(|root| !root.is_empty())
+}
+
+fn template_error(template: &str, position: SourcePosition, reason: impl Into<String>) -> AIEngineError {
+    AIEngineError::TemplateError {
+        template: template.to_string(),
+        line: position.line,
+        column: position.column,
+        reason: reason.into(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn layouts(templates: &[(&str, &str)]) -> TemplateLayouts {
+        let mut layouts = TemplateLayouts::new();
+        for (name, source) in templates {
+            layouts.register(*name, source).unwrap();
+        }
+        layouts
+    }
+
+    fn vars(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+    }
+
+    fn error_location(error: AIEngineError) -> (String, usize, usize, String) {
+        match error {
+            AIEngineError::TemplateError { template, line, column, reason } => (template, line, column, reason),
+            other => panic!("expected a located template error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_child_overrides_blocks_of_base() {
+        let layouts = layouts(&[
+            ("base", "<h1>{{#> title}}Ectus-R{{/title}}</h1>{{#> body}}empty{{/body}}"),
+            ("page", r#"{{#> base}}{{#*inline "body"}}Hello {{project_name}}{{/inline}}{{/base}}"#),
+            ("marketplace", r#"{{#> page}}{{#*inline "title"}}Marketplace{{/inline}}{{/page}}"#),
+        ]);
+        let variables = vars(&[("project_name", "shop & co")]);
+
+        assert_eq!(layouts.render("marketplace", &variables).unwrap(), "<h1>Marketplace</h1>Hello shop & co");
+        assert_eq!(layouts.render("page", &variables).unwrap(), "<h1>Ectus-R</h1>Hello shop & co");
+        assert_eq!(layouts.render("base", &variables).unwrap(), "<h1>Ectus-R</h1>empty");
+    }
+
+    #[test]
+    fn test_included_partial_sees_only_passed_variables() {
+        let layouts = layouts(&[
+            ("header", "# {{title}} ({{license}})"),
+            ("readme", r#"{{> header title=project_name license="MIT"}} {{secret}}"#),
+            ("leaky", "{{> header title=project_name}}"),
+        ]);
+        let variables = vars(&[("project_name", "shop"), ("secret", "s3cr3t"), ("license", "GPL")]);
+
+        assert_eq!(layouts.render("readme", &variables).unwrap(), "# shop (MIT) s3cr3t");
+
+        let (template, line, column, reason) = error_location(layouts.render("leaky", &variables).unwrap_err());
+        assert_eq!((template.as_str(), line, column), ("header", 1, 14));
+        assert!(reason.contains("license"));
+    }
+
+    #[test]
+    fn test_transitive_include_cycle_is_rejected() {
+        let layouts = layouts(&[
+            ("a", "A{{> b}}"),
+            ("b", "B{{> c}}"),
+            ("c", "{{> a}}"),
+            ("child", "{{#> parent}}{{/parent}}"),
+            ("parent", "{{#> child}}{{/child}}"),
+        ]);
+
+        let (template, line, column, reason) = error_location(layouts.render("a", &HashMap::new()).unwrap_err());
+        assert_eq!((template.as_str(), line, column), ("c", 1, 1));
+        assert!(reason.contains("a -> b -> c -> a"), "{}", reason);
+
+        let (_, _, _, reason) = error_location(layouts.render("child", &HashMap::new()).unwrap_err());
+        assert!(reason.contains("child -> parent -> child"), "{}", reason);
+    }
+
+    #[test]
+    fn test_missing_base_block_and_variable_are_located_errors() {
+        let layouts = layouts(&[
+            ("base", "{{#> body}}{{/body}}"),
+            ("orphan", "\n{{#> missing}}{{#*inline \"body\"}}x{{/inline}}{{/missing}}"),
+            ("typo", "{{#> base}}\n\n  {{#*inline \"bdoy\"}}x{{/inline}}{{/base}}"),
+            ("greeting", "Hi\n{{name}}"),
+        ]);
+
+        let (template, line, column, reason) = error_location(layouts.render("orphan", &HashMap::new()).unwrap_err());
+        assert_eq!((template.as_str(), line, column), ("orphan", 2, 1));
+        assert!(reason.contains("'missing'"));
+
+        let (template, line, column, reason) = error_location(layouts.render("typo", &HashMap::new()).unwrap_err());
+        assert_eq!((template.as_str(), line, column), ("typo", 3, 3));
+        assert!(reason.contains("bdoy"));
+
+        let (template, line, _, _) = error_location(layouts.render("greeting", &HashMap::new()).unwrap_err());
+        assert_eq!((template.as_str(), line), ("greeting", 2));
+
+        assert!(matches!(
+            layouts.render("nowhere", &HashMap::new()),
+            Err(AIEngineError::TemplateNotFound { .. })
+        ));
+    }
+
+    #[test]
+    fn test_syntax_errors_are_reported_on_register() {
+        let mut layouts = TemplateLayouts::new();
+        let unclosed = layouts.register("broken", "line\n{{#> body}}never closed").unwrap_err();
+        assert_eq!(error_location(unclosed).0, "broken");
+        assert!(!layouts.contains("broken"));
+    }
+}
@@ -0,0 +1,306 @@
+//! # Tensor Buffer Pool
+//!
+//! Recycles large `f32` tensor buffers across inference requests so the hot path
+//! doesn't allocate and free the same shapes over and over.
+//!
+//! Buffers are kept in per-shape free lists and the total retained size is
+//! bounded by the engine's `max_memory` budget; when returning a buffer would
+//! exceed it, least recently used shapes are evicted first.
+//!
+//! Reused buffers never carry data from a previous request: [`TensorPool::acquire`]
+//! hands out zeroed buffers, and [`TensorPool::acquire_for_overwrite`] skips the
+//! zeroing for callers that write every element. In debug builds the latter are
+//! filled with a poison pattern and checked on release, so a caller that leaves
+//! part of the buffer unwritten trips an assertion instead of leaking stale data.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::ops::{Deref, DerefMut};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// NaN with a recognizable payload, written into overwrite-only buffers in debug builds
+const POISON_BITS: u32 = 0x7FC0_DEAD;
+
+/// Tensor shape used as the free-list key
+pub type TensorShape = Vec<usize>;
+
+/// Pool statistics
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TensorPoolStats {
+    /// Acquisitions served from a free list
+    pub hits: u64,
+    /// Acquisitions that had to allocate
+    pub misses: u64,
+    /// Hit rate (0.0 to 1.0)
+    pub hit_rate: f64,
+    /// Bytes currently held in free lists
+    pub retained_bytes: usize,
+    /// Retention budget in bytes
+    pub max_retained_bytes: usize,
+    /// Buffers dropped to stay within budget
+    pub evictions: u64,
+    /// Number of distinct shapes with free buffers
+    pub shapes: usize,
+}
+
+#[derive(Debug, Default)]
+struct FreeList {
+    buffers: Vec<Vec<f32>>,
+    last_used: u64,
+}
+
+#[derive(Debug, Default)]
+struct PoolState {
+    free: HashMap<TensorShape, FreeList>,
+    retained_bytes: usize,
+    clock: u64,
+}
+
+/// Shape-keyed buffer pool bounded by a memory budget
+#[derive(Debug)]
+pub struct TensorPool {
+    state: Mutex<PoolState>,
+    max_retained_bytes: usize,
+    hits: AtomicU64,
+    misses: AtomicU64,
+    evictions: AtomicU64,
+}
+
+impl TensorPool {
+    /// Create a pool that retains at most `max_retained_bytes` of free buffers
+    pub fn new(max_retained_bytes: usize) -> Arc<Self> {
+        Arc::new(Self {
+            state: Mutex::new(PoolState::default()),
+            max_retained_bytes,
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+            evictions: AtomicU64::new(0),
+        })
+    }
+
+    /// Acquire a zeroed buffer for `shape`
+    pub fn acquire(self: &Arc<Self>, shape: &[usize]) -> PooledTensor {
+        let mut buffer = self.take(shape);
+        buffer.fill(0.0);
+        PooledTensor::new(self.clone(), shape.to_vec(), buffer, false)
+    }
+
+    /// Acquire a buffer the caller will overwrite completely; its contents are unspecified
+    pub fn acquire_for_overwrite(self: &Arc<Self>, shape: &[usize]) -> PooledTensor {
+        let mut buffer = self.take(shape);
+        if cfg!(debug_assertions) {
+            buffer.fill(f32::from_bits(POISON_BITS));
+        }
+        PooledTensor::new(self.clone(), shape.to_vec(), buffer, true)
+    }
+
+    fn take(&self, shape: &[usize]) -> Vec<f32> {
+        let len = shape.iter().product::<usize>();
+        let reused = {
+            let mut state = self.state.lock().unwrap();
+            state.clock += 1;
+            let clock = state.clock;
+            let buffer = state.free.get_mut(shape).and_then(|list| {
+                list.last_used = clock;
+                list.buffers.pop()
+            });
+            if let Some(buffer) = &buffer {
+                state.retained_bytes -= buffer_bytes(buffer);
+            }
+            buffer
+        };
+
+        match reused {
+            Some(buffer) => {
+                self.hits.fetch_add(1, Ordering::Relaxed);
+                buffer
+            }
+            None => {
+                self.misses.fetch_add(1, Ordering::Relaxed);
+                vec![0.0; len]
+            }
+        }
+    }
+
+    fn release(&self, shape: TensorShape, buffer: Vec<f32>) {
+        let bytes = buffer_bytes(&buffer);
+        if bytes > self.max_retained_bytes {
+            self.evictions.fetch_add(1, Ordering::Relaxed);
+            return;
+        }
+
+        let mut state = self.state.lock().unwrap();
+        while state.retained_bytes + bytes > self.max_retained_bytes {
+            // Evict from the least recently used shape other than the one being returned
+            let victim = state
+                .free
+                .iter()
+                .filter(|(s, list)| **s != shape && !list.buffers.is_empty())
+                .min_by_key(|(_, list)| list.last_used)
+                .map(|(s, _)| s.clone());
+            let Some(victim) = victim else {
+                self.evictions.fetch_add(1, Ordering::Relaxed);
+                return;
+            };
+            let list = state.free.get_mut(&victim).unwrap();
+            let evicted = list.buffers.pop().map(|b| buffer_bytes(&b)).unwrap_or(0);
+            if list.buffers.is_empty() {
+                state.free.remove(&victim);
+            }
+            state.retained_bytes -= evicted;
+            self.evictions.fetch_add(1, Ordering::Relaxed);
+        }
+
+        state.retained_bytes += bytes;
+        state.clock += 1;
+        let clock = state.clock;
+        let list = state.free.entry(shape).or_default();
+        list.last_used = clock;
+        list.buffers.push(buffer);
+    }
+
+    /// Drop every retained buffer
+    pub fn clear(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.free.clear();
+        state.retained_bytes = 0;
+    }
+
+    /// Current pool statistics
+    pub fn stats(&self) -> TensorPoolStats {
+        let hits = self.hits.load(Ordering::Relaxed);
+        let misses = self.misses.load(Ordering::Relaxed);
+        let state = self.state.lock().unwrap();
+        TensorPoolStats {
+            hits,
+            misses,
+            hit_rate: if hits + misses == 0 { 0.0 } else { hits as f64 / (hits + misses) as f64 },
+            retained_bytes: state.retained_bytes,
+            max_retained_bytes: self.max_retained_bytes,
+            evictions: self.evictions.load(Ordering::Relaxed),
+            shapes: state.free.len(),
+        }
+    }
+
+    /// Publish hit rate and retained bytes to the metrics recorder
+    pub fn publish_metrics(&self) {
+        let stats = self.stats();
+        metrics::gauge!("aion_tensor_pool_hit_rate").set(stats.hit_rate);
+        metrics::gauge!("aion_tensor_pool_retained_bytes").set(stats.retained_bytes as f64);
+        metrics::gauge!("aion_tensor_pool_evictions").set(stats.evictions as f64);
+    }
+}
+
+fn buffer_bytes(buffer: &[f32]) -> usize {
+    std::mem::size_of_val(buffer)
+}
+
+/// Buffer on loan from a [`TensorPool`]; returned to the pool when dropped
+#[derive(Debug)]
+pub struct PooledTensor {
+    pool: Arc<TensorPool>,
+    shape: TensorShape,
+    buffer: Option<Vec<f32>>,
+    overwrite_only: bool,
+}
+
+impl PooledTensor {
+    fn new(pool: Arc<TensorPool>, shape: TensorShape, buffer: Vec<f32>, overwrite_only: bool) -> Self {
+        Self { pool, shape, buffer: Some(buffer), overwrite_only }
+    }
+
+    pub fn shape(&self) -> &[usize] {
+        &self.shape
+    }
+
+    /// Take ownership of the buffer; it will not return to the pool
+    pub fn into_vec(mut self) -> Vec<f32> {
+        self.buffer.take().unwrap_or_default()
+    }
+}
+
+impl Deref for PooledTensor {
+    type Target = [f32];
+
+    fn deref(&self) -> &[f32] {
+        self.buffer.as_deref().unwrap_or(&[])
+    }
+}
+
+impl DerefMut for PooledTensor {
+    fn deref_mut(&mut self) -> &mut [f32] {
+        self.buffer.as_deref_mut().unwrap_or(&mut [])
+    }
+}
+
+impl Drop for PooledTensor {
+    fn drop(&mut self) {
+        let Some(buffer) = self.buffer.take() else { return };
+        if self.overwrite_only && !std::thread::panicking() {
+            debug_assert!(
+                !buffer.iter().any(|v| v.to_bits() == POISON_BITS),
+                "pooled tensor of shape {:?} was acquired for overwrite but not fully written; \
+                 a release build would have exposed data from a previous request",
+                self.shape
+            );
+        }
+        self.pool.release(std::mem::take(&mut self.shape), buffer);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reuses_buffers_per_shape() {
+        let pool = TensorPool::new(1024 * 1024);
+        drop(pool.acquire(&[4, 16]));
+        let _a = pool.acquire(&[4, 16]);
+        let _b = pool.acquire(&[8, 16]);
+
+        let stats = pool.stats();
+        assert_eq!(stats.hits, 1);
+        assert_eq!(stats.misses, 2);
+    }
+
+    #[test]
+    fn reused_buffers_are_zeroed() {
+        let pool = TensorPool::new(1024 * 1024);
+        {
+            let mut tensor = pool.acquire(&[32]);
+            tensor.fill(42.0);
+        }
+        let tensor = pool.acquire(&[32]);
+        assert!(tensor.iter().all(|v| *v == 0.0));
+    }
+
+    #[test]
+    fn retention_is_bounded_by_budget() {
+        // Room for exactly one 64-float buffer
+        let pool = TensorPool::new(64 * 4);
+        let a = pool.acquire(&[64]);
+        let b = pool.acquire(&[64]);
+        drop(a);
+        drop(b);
+
+        let stats = pool.stats();
+        assert_eq!(stats.retained_bytes, 64 * 4);
+        assert_eq!(stats.evictions, 1);
+
+        drop(pool.acquire(&[2, 32]));
+        let stats = pool.stats();
+        assert!(stats.retained_bytes <= 64 * 4);
+        assert_eq!(stats.shapes, 1);
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "not fully written")]
+    fn partially_written_overwrite_buffer_is_caught() {
+        let pool = TensorPool::new(1024 * 1024);
+        let mut tensor = pool.acquire_for_overwrite(&[8]);
+        tensor[..4].fill(1.0);
+    }
+}
@@ -0,0 +1,850 @@
+//! # Vector Stores
+//!
+//! Storage and nearest-neighbour lookup for embeddings, shared by the RAG
+//! pipeline, code search and marketplace search.
+//!
+//! - [`BruteForceVectorStore`] scores every record. Exact, and the right
+//!   choice for small sets and tests.
+//! - [`HnswVectorStore`] keeps a Hierarchical Navigable Small World graph for
+//!   approximate search at scale. [`HnswParams`] trades recall for latency,
+//!   and the index can be saved to disk and reloaded.
+//!
+//! Records carry string metadata that queries can filter on with a
+//! [`MetadataFilter`] (language, doc version, organization, ...). Upserting
+//! an existing id replaces its vector rather than adding a second copy.
+
+use crate::errors::{AIEngineError, AIResult};
+use crate::semantic_search::cosine_similarity;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::cmp::{Ordering, Reverse};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+use std::path::Path;
+use std::sync::RwLock;
+use tracing::debug;
+
+/// A vector with its id, filterable metadata and an opaque content payload
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VectorRecord {
+    pub id: String,
+    pub vector: Vec<f32>,
+    #[serde(default)]
+    pub metadata: HashMap<String, String>,
+    /// Text or reference the caller wants back with matches
+    #[serde(default)]
+    pub content: String,
+}
+
+impl VectorRecord {
+    pub fn new(id: impl Into<String>, vector: Vec<f32>) -> Self {
+        Self {
+            id: id.into(),
+            vector,
+            metadata: HashMap::new(),
+            content: String::new(),
+        }
+    }
+
+    pub fn with_metadata(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.metadata.insert(key.into(), value.into());
+        self
+    }
+
+    pub fn with_content(mut self, content: impl Into<String>) -> Self {
+        self.content = content.into();
+        self
+    }
+}
+
+/// A query result; `score` is cosine similarity, higher is closer
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VectorMatch {
+    pub id: String,
+    pub score: f32,
+    pub vector: Vec<f32>,
+    pub metadata: HashMap<String, String>,
+    pub content: String,
+}
+
+impl VectorMatch {
+    fn from_record(record: &VectorRecord, score: f32) -> Self {
+        Self {
+            id: record.id.clone(),
+            score,
+            vector: record.vector.clone(),
+            metadata: record.metadata.clone(),
+            content: record.content.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+enum FilterCondition {
+    Equals { key: String, value: String },
+    AnyOf { key: String, values: Vec<String> },
+}
+
+/// Conjunction of metadata conditions; the empty filter matches everything
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MetadataFilter {
+    conditions: Vec<FilterCondition>,
+}
+
+impl MetadataFilter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Require `key` to equal `value`
+    pub fn eq(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.conditions.push(FilterCondition::Equals {
+            key: key.into(),
+            value: value.into(),
+        });
+        self
+    }
+
+    /// Require `key` to equal one of `values`
+    pub fn any_of<I, V>(mut self, key: impl Into<String>, values: I) -> Self
+    where
+        I: IntoIterator<Item = V>,
+        V: Into<String>,
+    {
+        self.conditions.push(FilterCondition::AnyOf {
+            key: key.into(),
+            values: values.into_iter().map(Into::into).collect(),
+        });
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.conditions.is_empty()
+    }
+
+    pub fn matches(&self, metadata: &HashMap<String, String>) -> bool {
+        self.conditions.iter().all(|condition| match condition {
+            FilterCondition::Equals { key, value } => metadata.get(key) == Some(value),
+            FilterCondition::AnyOf { key, values } => {
+                metadata.get(key).map_or(false, |actual| values.contains(actual))
+            }
+        })
+    }
+}
+
+fn passes(filter: Option<&MetadataFilter>, record: &VectorRecord) -> bool {
+    filter.map_or(true, |filter| filter.matches(&record.metadata))
+}
+
+/// Embedding storage with filtered nearest-neighbour queries
+#[async_trait]
+pub trait VectorStore: Send + Sync {
+    /// Insert records, replacing any existing record with the same id
+    async fn upsert(&self, records: Vec<VectorRecord>) -> AIResult<()>;
+
+    /// Up to `k` records most similar to `vector` that pass `filter`, best first
+    async fn query(
+        &self,
+        vector: &[f32],
+        k: usize,
+        filter: Option<&MetadataFilter>,
+    ) -> AIResult<Vec<VectorMatch>>;
+
+    /// Remove records by id; returns how many existed
+    async fn delete(&self, ids: &[String]) -> AIResult<usize>;
+
+    /// Remove every record matching `filter`; returns how many were removed
+    async fn delete_where(&self, filter: &MetadataFilter) -> AIResult<usize>;
+
+    async fn len(&self) -> AIResult<usize>;
+
+    async fn is_empty(&self) -> AIResult<bool> {
+        Ok(self.len().await? == 0)
+    }
+}
+
+fn check_dimensions(expected: Option<usize>, records: &[VectorRecord]) -> AIResult<Option<usize>> {
+    let mut expected = expected;
+    for record in records {
+        match expected {
+            Some(dimensions) if dimensions != record.vector.len() => {
+                return Err(AIEngineError::DimensionMismatch {
+                    expected: dimensions,
+                    actual: record.vector.len(),
+                });
+            }
+            _ => expected = Some(record.vector.len()),
+        }
+    }
+    Ok(expected)
+}
+
+/// Exact search by scoring every record
+#[derive(Default)]
+pub struct BruteForceVectorStore {
+    records: RwLock<HashMap<String, VectorRecord>>,
+}
+
+impl BruteForceVectorStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Write every record to `path`, replacing it atomically
+    pub async fn save(&self, path: impl AsRef<Path>) -> AIResult<()> {
+        let path = path.as_ref();
+        let bytes = {
+            let records = self.records.read().unwrap();
+            bincode::serialize(&*records).map_err(|e| AIEngineError::Generic(e.into()))?
+        };
+
+        let partial = path.with_extension("partial");
+        tokio::fs::write(&partial, bytes).await?;
+        tokio::fs::rename(&partial, path).await?;
+        Ok(())
+    }
+
+    /// Load records written by [`BruteForceVectorStore::save`]
+    pub async fn load(path: impl AsRef<Path>) -> AIResult<Self> {
+        let bytes = tokio::fs::read(path.as_ref()).await?;
+        let records: HashMap<String, VectorRecord> =
+            bincode::deserialize(&bytes).map_err(|e| AIEngineError::Generic(e.into()))?;
+        Ok(Self {
+            records: RwLock::new(records),
+        })
+    }
+}
+
+#[async_trait]
+impl VectorStore for BruteForceVectorStore {
+    async fn upsert(&self, records: Vec<VectorRecord>) -> AIResult<()> {
+        let mut stored = self.records.write().unwrap();
+        let dimensions = stored.values().next().map(|record| record.vector.len());
+        check_dimensions(dimensions, &records)?;
+        for record in records {
+            stored.insert(record.id.clone(), record);
+        }
+        Ok(())
+    }
+
+    async fn query(
+        &self,
+        vector: &[f32],
+        k: usize,
+        filter: Option<&MetadataFilter>,
+    ) -> AIResult<Vec<VectorMatch>> {
+        let stored = self.records.read().unwrap();
+        if let Some(dimensions) = stored.values().next().map(|record| record.vector.len()) {
+            if dimensions != vector.len() {
+                return Err(AIEngineError::DimensionMismatch {
+                    expected: dimensions,
+                    actual: vector.len(),
+                });
+            }
+        }
+        let mut scored: Vec<(f32, &VectorRecord)> = stored
+            .values()
+            .filter(|record| passes(filter, record))
+            .map(|record| (cosine_similarity(vector, &record.vector), record))
+            .collect();
+        scored.sort_by(|a, b| b.0.total_cmp(&a.0).then_with(|| a.1.id.cmp(&b.1.id)));
+
+        Ok(scored
+            .into_iter()
+            .take(k)
+            .map(|(score, record)| VectorMatch::from_record(record, score))
+            .collect())
+    }
+
+    async fn delete(&self, ids: &[String]) -> AIResult<usize> {
+        let mut stored = self.records.write().unwrap();
+        Ok(ids.iter().filter(|id| stored.remove(*id).is_some()).count())
+    }
+
+    async fn delete_where(&self, filter: &MetadataFilter) -> AIResult<usize> {
+        let mut stored = self.records.write().unwrap();
+        let before = stored.len();
+        stored.retain(|_, record| !filter.matches(&record.metadata));
+        Ok(before - stored.len())
+    }
+
+    async fn len(&self) -> AIResult<usize> {
+        Ok(self.records.read().unwrap().len())
+    }
+}
+
+/// HNSW tuning knobs
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HnswParams {
+    /// Links per node on upper layers (twice this on the base layer). Higher
+    /// improves recall at the cost of memory and insert time.
+    pub m: usize,
+    /// Candidate list size while inserting. Higher builds a better graph,
+    /// more slowly.
+    pub ef_construction: usize,
+    /// Candidate list size while querying. Higher improves recall at the
+    /// cost of latency; never less than the requested `k`.
+    pub ef_search: usize,
+}
+
+impl Default for HnswParams {
+    fn default() -> Self {
+        Self {
+            m: 16,
+            ef_construction: 200,
+            ef_search: 64,
+        }
+    }
+}
+
+impl HnswParams {
+    fn validate(&self) -> AIResult<()> {
+        if self.m < 2 {
+            return Err(AIEngineError::ConfigurationError {
+                field: "hnsw.m".to_string(),
+                reason: "must be at least 2".to_string(),
+            });
+        }
+        if self.ef_construction == 0 || self.ef_search == 0 {
+            return Err(AIEngineError::ConfigurationError {
+                field: "hnsw.ef".to_string(),
+                reason: "ef_construction and ef_search must be positive".to_string(),
+            });
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct HnswNode {
+    record: VectorRecord,
+    /// Neighbour node indices, one list per layer this node lives on
+    neighbors: Vec<Vec<usize>>,
+    /// Tombstoned nodes stay in the graph for navigation but are never returned
+    deleted: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Scored {
+    distance: f32,
+    node: usize,
+}
+
+impl Eq for Scored {}
+
+impl Ord for Scored {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.distance
+            .total_cmp(&other.distance)
+            .then_with(|| self.node.cmp(&other.node))
+    }
+}
+
+impl PartialOrd for Scored {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+const MAX_LEVEL: usize = 16;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct HnswGraph {
+    params: HnswParams,
+    dimensions: Option<usize>,
+    nodes: Vec<HnswNode>,
+    /// Live id -> node index
+    ids: HashMap<String, usize>,
+    entry_point: Option<usize>,
+    max_level: usize,
+}
+
+impl HnswGraph {
+    fn new(params: HnswParams) -> Self {
+        Self {
+            params,
+            dimensions: None,
+            nodes: Vec::new(),
+            ids: HashMap::new(),
+            entry_point: None,
+            max_level: 0,
+        }
+    }
+
+    fn distance(&self, query: &[f32], node: usize) -> f32 {
+        1.0 - cosine_similarity(query, &self.nodes[node].record.vector)
+    }
+
+    fn max_neighbors(&self, layer: usize) -> usize {
+        if layer == 0 {
+            self.params.m * 2
+        } else {
+            self.params.m
+        }
+    }
+
+    /// Level drawn from the usual exponential distribution, seeded by the id
+    /// so rebuilding an index reproduces the same graph.
+    fn level_for(&self, id: &str) -> usize {
+        let mut hasher = DefaultHasher::new();
+        id.hash(&mut hasher);
+        let unit = ((hasher.finish() >> 11) as f64 + 1.0) / (1u64 << 53) as f64;
+        let scale = 1.0 / (self.params.m as f64).ln();
+        ((-unit.ln() * scale) as usize).min(MAX_LEVEL)
+    }
+
+    /// Greedy beam search on one layer; returns up to `ef` nodes, closest first
+    fn search_layer(&self, query: &[f32], entries: &[usize], ef: usize, layer: usize) -> Vec<Scored> {
+        let mut visited: HashSet<usize> = entries.iter().copied().collect();
+        let mut candidates = BinaryHeap::new();
+        let mut found = BinaryHeap::new();
+
+        for &entry in entries {
+            let scored = Scored {
+                distance: self.distance(query, entry),
+                node: entry,
+            };
+            candidates.push(Reverse(scored.clone()));
+            found.push(scored);
+        }
+        while found.len() > ef {
+            found.pop();
+        }
+
+        while let Some(Reverse(closest)) = candidates.pop() {
+            let furthest = found.peek().map_or(f32::INFINITY, |s: &Scored| s.distance);
+            if closest.distance > furthest && found.len() >= ef {
+                break;
+            }
+            for &neighbor in &self.nodes[closest.node].neighbors[layer] {
+                if !visited.insert(neighbor) {
+                    continue;
+                }
+                let distance = self.distance(query, neighbor);
+                let furthest = found.peek().map_or(f32::INFINITY, |s: &Scored| s.distance);
+                if found.len() < ef || distance < furthest {
+                    let scored = Scored { distance, node: neighbor };
+                    candidates.push(Reverse(scored.clone()));
+                    found.push(scored);
+                    if found.len() > ef {
+                        found.pop();
+                    }
+                }
+            }
+        }
+
+        found.into_sorted_vec()
+    }
+
+    /// Walk down from the entry point to layer 0 and return its best entry
+    fn descend(&self, query: &[f32], to_layer: usize) -> Option<usize> {
+        let mut entry = self.entry_point?;
+        for layer in (to_layer + 1..=self.max_level).rev() {
+            if let Some(best) = self.search_layer(query, &[entry], 1, layer).first() {
+                entry = best.node;
+            }
+        }
+        Some(entry)
+    }
+
+    fn upsert(&mut self, record: VectorRecord) {
+        if let Some(previous) = self.ids.remove(&record.id) {
+            self.nodes[previous].deleted = true;
+        }
+        self.insert(record);
+    }
+
+    fn insert(&mut self, record: VectorRecord) {
+        let level = self.level_for(&record.id);
+        let node = self.nodes.len();
+        let query = record.vector.clone();
+        self.ids.insert(record.id.clone(), node);
+        self.nodes.push(HnswNode {
+            record,
+            neighbors: vec![Vec::new(); level + 1],
+            deleted: false,
+        });
+
+        let entry = match self.descend(&query, level) {
+            Some(entry) => entry,
+            None => {
+                self.entry_point = Some(node);
+                self.max_level = level;
+                return;
+            }
+        };
+
+        let mut entries = vec![entry];
+        for layer in (0..=level.min(self.max_level)).rev() {
+            let found = self.search_layer(&query, &entries, self.params.ef_construction, layer);
+            let neighbors: Vec<usize> = found.iter().take(self.params.m).map(|s| s.node).collect();
+            let limit = self.max_neighbors(layer);
+
+            for &neighbor in &neighbors {
+                self.nodes[neighbor].neighbors[layer].push(node);
+                if self.nodes[neighbor].neighbors[layer].len() > limit {
+                    self.prune(neighbor, layer, limit);
+                }
+            }
+            self.nodes[node].neighbors[layer] = neighbors;
+            entries = found.into_iter().map(|s| s.node).collect();
+        }
+
+        if level > self.max_level {
+            self.max_level = level;
+            self.entry_point = Some(node);
+        }
+    }
+
+    /// Keep only the `limit` closest links of a node on one layer
+    fn prune(&mut self, node: usize, layer: usize, limit: usize) {
+        let vector = self.nodes[node].record.vector.clone();
+        let mut links: Vec<Scored> = self.nodes[node].neighbors[layer]
+            .iter()
+            .map(|&neighbor| Scored {
+                distance: self.distance(&vector, neighbor),
+                node: neighbor,
+            })
+            .collect();
+        links.sort();
+        links.truncate(limit);
+        self.nodes[node].neighbors[layer] = links.into_iter().map(|s| s.node).collect();
+    }
+
+    fn delete(&mut self, id: &str) -> bool {
+        match self.ids.remove(id) {
+            Some(node) => {
+                self.nodes[node].deleted = true;
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn search(&self, query: &[f32], k: usize, filter: Option<&MetadataFilter>) -> Vec<VectorMatch> {
+        if k == 0 || self.ids.is_empty() {
+            return Vec::new();
+        }
+        let entry = match self.descend(query, 0) {
+            Some(entry) => entry,
+            None => return Vec::new(),
+        };
+
+        // Tombstones and filtered-out nodes take up room in the candidate
+        // list, so widen the search until enough results survive.
+        let mut ef = self.params.ef_search.max(k);
+        loop {
+            let hits: Vec<VectorMatch> = self
+                .search_layer(query, &[entry], ef, 0)
+                .into_iter()
+                .filter(|s| {
+                    let node = &self.nodes[s.node];
+                    !node.deleted && passes(filter, &node.record)
+                })
+                .take(k)
+                .map(|s| VectorMatch::from_record(&self.nodes[s.node].record, 1.0 - s.distance))
+                .collect();
+            if hits.len() >= k || ef >= self.nodes.len() {
+                return hits;
+            }
+            ef = (ef * 2).min(self.nodes.len());
+        }
+    }
+
+    fn tombstones(&self) -> usize {
+        self.nodes.len() - self.ids.len()
+    }
+
+    /// Rebuild the graph from live records, dropping tombstones
+    fn compact(&mut self) {
+        let live: Vec<VectorRecord> = std::mem::take(&mut self.nodes)
+            .into_iter()
+            .filter(|node| !node.deleted)
+            .map(|node| node.record)
+            .collect();
+        let dimensions = if live.is_empty() { None } else { self.dimensions };
+        *self = Self {
+            dimensions,
+            ..Self::new(self.params.clone())
+        };
+        for record in live {
+            self.insert(record);
+        }
+    }
+
+    fn compact_if_sparse(&mut self) {
+        if self.tombstones() > self.ids.len() {
+            debug!("Compacting HNSW index: {} tombstones, {} live", self.tombstones(), self.ids.len());
+            self.compact();
+        }
+    }
+}
+
+/// Approximate nearest-neighbour store backed by an HNSW graph
+pub struct HnswVectorStore {
+    graph: RwLock<HnswGraph>,
+}
+
+impl Default for HnswVectorStore {
+    fn default() -> Self {
+        Self {
+            graph: RwLock::new(HnswGraph::new(HnswParams::default())),
+        }
+    }
+}
+
+impl HnswVectorStore {
+    pub fn new(params: HnswParams) -> AIResult<Self> {
+        params.validate()?;
+        Ok(Self {
+            graph: RwLock::new(HnswGraph::new(params)),
+        })
+    }
+
+    pub fn params(&self) -> HnswParams {
+        self.graph.read().unwrap().params.clone()
+    }
+
+    /// Change the query-time candidate list size. Takes effect immediately;
+    /// `m` and `ef_construction` are fixed once the graph is built.
+    pub fn set_ef_search(&self, ef_search: usize) {
+        self.graph.write().unwrap().params.ef_search = ef_search.max(1);
+    }
+
+    /// Rebuild the graph without deleted and replaced records. Runs
+    /// automatically once tombstones outnumber live records.
+    pub fn compact(&self) {
+        self.graph.write().unwrap().compact();
+    }
+
+    /// Write the index to `path`, replacing it atomically
+    pub async fn save(&self, path: impl AsRef<Path>) -> AIResult<()> {
+        let path = path.as_ref();
+        let bytes = {
+            let graph = self.graph.read().unwrap();
+            bincode::serialize(&*graph).map_err(|e| AIEngineError::Generic(e.into()))?
+        };
+
+        let partial = path.with_extension("partial");
+        tokio::fs::write(&partial, bytes).await?;
+        tokio::fs::rename(&partial, path).await?;
+        Ok(())
+    }
+
+    /// Load an index written by [`HnswVectorStore::save`]
+    pub async fn load(path: impl AsRef<Path>) -> AIResult<Self> {
+        let bytes = tokio::fs::read(path.as_ref()).await?;
+        let graph: HnswGraph = bincode::deserialize(&bytes).map_err(|e| AIEngineError::Generic(e.into()))?;
+        graph.params.validate()?;
+        Ok(Self {
+            graph: RwLock::new(graph),
+        })
+    }
+
+    /// Load the index at `path` if it exists, otherwise start an empty one
+    pub async fn open(path: impl AsRef<Path>, params: HnswParams) -> AIResult<Self> {
+        if tokio::fs::try_exists(path.as_ref()).await? {
+            Self::load(path).await
+        } else {
+            Self::new(params)
+        }
+    }
+}
+
+#[async_trait]
+impl VectorStore for HnswVectorStore {
+    async fn upsert(&self, records: Vec<VectorRecord>) -> AIResult<()> {
+        let mut graph = self.graph.write().unwrap();
+        graph.dimensions = check_dimensions(graph.dimensions, &records)?;
+        for record in records {
+            graph.upsert(record);
+        }
+        graph.compact_if_sparse();
+        Ok(())
+    }
+
+    async fn query(
+        &self,
+        vector: &[f32],
+        k: usize,
+        filter: Option<&MetadataFilter>,
+    ) -> AIResult<Vec<VectorMatch>> {
+        let graph = self.graph.read().unwrap();
+        if let Some(dimensions) = graph.dimensions {
+            if dimensions != vector.len() {
+                return Err(AIEngineError::DimensionMismatch {
+                    expected: dimensions,
+                    actual: vector.len(),
+                });
+            }
+        }
+        Ok(graph.search(vector, k, filter))
+    }
+
+    async fn delete(&self, ids: &[String]) -> AIResult<usize> {
+        let mut graph = self.graph.write().unwrap();
+        let removed = ids.iter().filter(|id| graph.delete(id)).count();
+        graph.compact_if_sparse();
+        Ok(removed)
+    }
+
+    async fn delete_where(&self, filter: &MetadataFilter) -> AIResult<usize> {
+        let mut graph = self.graph.write().unwrap();
+        let ids: Vec<String> = graph
+            .ids
+            .keys()
+            .filter(|id| filter.matches(&graph.nodes[graph.ids[*id]].record.metadata))
+            .cloned()
+            .collect();
+        for id in &ids {
+            graph.delete(id);
+        }
+        graph.compact_if_sparse();
+        Ok(ids.len())
+    }
+
+    async fn len(&self) -> AIResult<usize> {
+        Ok(self.graph.read().unwrap().ids.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Deterministic pseudo-random unit vectors
+    fn vectors(count: usize, dimensions: usize) -> Vec<Vec<f32>> {
+        let mut state = 0x2545_f491_4f6c_dd1du64;
+        (0..count)
+            .map(|_| {
+                (0..dimensions)
+                    .map(|_| {
+                        state ^= state << 13;
+                        state ^= state >> 7;
+                        state ^= state << 17;
+                        (state % 2000) as f32 / 1000.0 - 1.0
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    fn records(vectors: &[Vec<f32>]) -> Vec<VectorRecord> {
+        vectors
+            .iter()
+            .enumerate()
+            .map(|(i, vector)| {
+                VectorRecord::new(format!("v{}", i), vector.clone())
+                    .with_metadata("language", if i % 2 == 0 { "rust" } else { "python" })
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn upsert_replaces_existing_id() {
+        let stores: Vec<Box<dyn VectorStore>> =
+            vec![Box::new(BruteForceVectorStore::new()), Box::new(HnswVectorStore::default())];
+        for store in stores {
+            store.upsert(vec![VectorRecord::new("a", vec![1.0, 0.0])]).await.unwrap();
+            store.upsert(vec![VectorRecord::new("a", vec![0.0, 1.0])]).await.unwrap();
+            assert_eq!(store.len().await.unwrap(), 1);
+
+            let hits = store.query(&[0.0, 1.0], 5, None).await.unwrap();
+            assert_eq!(hits.len(), 1);
+            assert!((hits[0].score - 1.0).abs() < 1e-5);
+        }
+    }
+
+    #[tokio::test]
+    async fn rejects_mismatched_dimensions() {
+        let store = HnswVectorStore::default();
+        store.upsert(vec![VectorRecord::new("a", vec![1.0, 0.0])]).await.unwrap();
+        let result = store.upsert(vec![VectorRecord::new("b", vec![1.0, 0.0, 0.0])]).await;
+        assert!(matches!(result, Err(AIEngineError::DimensionMismatch { expected: 2, actual: 3 })));
+    }
+
+    #[tokio::test]
+    async fn brute_force_survives_save_and_load() {
+        let data = vectors(20, 8);
+        let store = BruteForceVectorStore::new();
+        store.upsert(records(&data)).await.unwrap();
+
+        let path = std::env::temp_dir().join(format!("vectors-{}.bin", uuid::Uuid::new_v4()));
+        store.save(&path).await.unwrap();
+        let reloaded = BruteForceVectorStore::load(&path).await.unwrap();
+        tokio::fs::remove_file(&path).await.unwrap();
+
+        assert_eq!(reloaded.len().await.unwrap(), 20);
+        let hits = reloaded.query(&data[4], 1, None).await.unwrap();
+        assert_eq!((hits[0].id.as_str(), hits[0].metadata["language"].as_str()), ("v4", "rust"));
+        assert!(matches!(
+            reloaded.query(&[1.0, 0.0], 1, None).await,
+            Err(AIEngineError::DimensionMismatch { expected: 8, actual: 2 })
+        ));
+    }
+
+    #[tokio::test]
+    async fn hnsw_recall_matches_brute_force() {
+        let data = vectors(600, 24);
+        let exact = BruteForceVectorStore::new();
+        let approximate = HnswVectorStore::default();
+        exact.upsert(records(&data)).await.unwrap();
+        approximate.upsert(records(&data)).await.unwrap();
+
+        let mut overlap = 0;
+        let queries = vectors(20, 24);
+        for query in &queries {
+            let expected: HashSet<String> =
+                exact.query(query, 10, None).await.unwrap().into_iter().map(|m| m.id).collect();
+            overlap += approximate
+                .query(query, 10, None)
+                .await
+                .unwrap()
+                .into_iter()
+                .filter(|m| expected.contains(&m.id))
+                .count();
+        }
+        let recall = overlap as f32 / (queries.len() * 10) as f32;
+        assert!(recall >= 0.9, "recall {}", recall);
+    }
+
+    #[tokio::test]
+    async fn filters_constrain_results() {
+        let data = vectors(200, 8);
+        let filter = MetadataFilter::new().eq("language", "python");
+        for store in [
+            Box::new(BruteForceVectorStore::new()) as Box<dyn VectorStore>,
+            Box::new(HnswVectorStore::default()),
+        ] {
+            store.upsert(records(&data)).await.unwrap();
+            let hits = store.query(&data[0], 10, Some(&filter)).await.unwrap();
+            assert_eq!(hits.len(), 10);
+            assert!(hits.iter().all(|m| m.metadata["language"] == "python"));
+
+            assert_eq!(store.delete_where(&filter).await.unwrap(), 100);
+            assert!(store.query(&data[1], 10, Some(&filter)).await.unwrap().is_empty());
+            assert_eq!(store.len().await.unwrap(), 100);
+        }
+    }
+
+    #[tokio::test]
+    async fn hnsw_survives_save_and_load() {
+        let data = vectors(100, 8);
+        let store = HnswVectorStore::new(HnswParams { ef_search: 32, ..HnswParams::default() }).unwrap();
+        store.upsert(records(&data)).await.unwrap();
+        store.delete(&["v3".to_string()]).await.unwrap();
+
+        let path = std::env::temp_dir().join(format!("hnsw-{}.bin", uuid::Uuid::new_v4()));
+        store.save(&path).await.unwrap();
+        let reloaded = HnswVectorStore::open(&path, HnswParams::default()).await.unwrap();
+        tokio::fs::remove_file(&path).await.unwrap();
+
+        assert_eq!(reloaded.len().await.unwrap(), 99);
+        assert_eq!(reloaded.params().ef_search, 32);
+        let before: Vec<String> = store.query(&data[7], 5, None).await.unwrap().into_iter().map(|m| m.id).collect();
+        let after: Vec<String> = reloaded.query(&data[7], 5, None).await.unwrap().into_iter().map(|m| m.id).collect();
+        assert_eq!(before, after);
+        assert_eq!(after[0], "v7");
+    }
+}
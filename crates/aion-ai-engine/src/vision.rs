@@ -3,8 +3,12 @@
 //! Image processing and computer vision utilities.
 
 use crate::errors::{AIEngineError, AIResult};
+use futures::stream::{self, StreamExt};
 use image::{ImageBuffer, RgbImage, DynamicImage};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
 /// Image preprocessing options
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -578,4 +582,109 @@ impl Default for VisionProcessor {
     fn default() -> Self {
         Self::new()
     }
-}
\ No newline at end of file
+}
+/// Outcome of classifying many image files
+#[derive(Debug, Default)]
+pub struct BatchClassification {
+    /// Classification of every file that decoded and classified successfully
+    pub results: HashMap<PathBuf, ClassificationResult>,
+    /// Why each remaining file failed
+    pub errors: HashMap<PathBuf, AIEngineError>,
+}
+
+/// Classify image files, decoding and classifying up to `concurrency` of
+/// them at a time on the blocking thread pool. Each file is decoded straight
+/// from disk and dropped once classified, so at most `concurrency` images are
+/// held in memory. A file that cannot be read, decoded or classified is
+/// recorded in `errors` and does not stop the batch.
+pub async fn classify_batch<I, P>(paths: I, top_k: usize, concurrency: usize) -> AIResult<BatchClassification>
+where
+    I: IntoIterator<Item = P>,
+    P: Into<PathBuf>,
+{
+    if concurrency == 0 {
+        return Err(AIEngineError::ConfigurationError {
+            field: "concurrency".to_string(),
+            reason: "must be at least 1".to_string(),
+        });
+    }
+
+    let processor = Arc::new(VisionProcessor::new());
+    let mut outcomes = stream::iter(paths.into_iter().map(Into::into))
+        .map(|path: PathBuf| {
+            let processor = Arc::clone(&processor);
+            async move {
+                let task_path = path.clone();
+                let outcome = tokio::task::spawn_blocking(move || {
+                    let image = decode_image_file(&task_path)?;
+                    processor.classify_image(&image, top_k)
+                })
+                .await
+                .map_err(AIEngineError::from)
+                .and_then(|outcome| outcome);
+                (path, outcome)
+            }
+        })
+        .buffer_unordered(concurrency);
+
+    let mut batch = BatchClassification::default();
+    while let Some((path, outcome)) = outcomes.next().await {
+        match outcome {
+            Ok(result) => {
+                batch.results.insert(path, result);
+            }
+            Err(error) => {
+                tracing::warn!("Failed to classify {}: {}", path.display(), error);
+                batch.errors.insert(path, error);
+            }
+        }
+    }
+    Ok(batch)
+}
+
+/// Decode an image from a buffered file reader, guessing the format from
+/// the file's contents rather than its extension
+fn decode_image_file(path: &Path) -> AIResult<DynamicImage> {
+    image::io::Reader::open(path)?
+        .with_guessed_format()?
+        .decode()
+        .map_err(|e| AIEngineError::PreprocessingFailed {
+            reason: format!("Failed to decode {}: {}", path.display(), e),
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn classify_batch_keeps_going_past_corrupt_files() {
+        let dir = std::env::temp_dir().join(format!("aion-vision-batch-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let mut paths = Vec::new();
+        for (index, (width, height)) in [(64, 32), (32, 64), (48, 48)].into_iter().enumerate() {
+            let path = dir.join(format!("valid-{}.png", index));
+            RgbImage::from_pixel(width, height, image::Rgb([230, 230, 230])).save(&path).unwrap();
+            paths.push(path);
+        }
+        let corrupt = dir.join("corrupt.png");
+        std::fs::write(&corrupt, b"\x89PNG\r\n\x1a\nnot really a png").unwrap();
+        let missing = dir.join("missing.jpg");
+        paths.extend([corrupt.clone(), missing.clone()]);
+
+        let batch = classify_batch(paths, 1, 2).await.unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(batch.results.len(), 3);
+        assert_eq!(batch.errors.len(), 2);
+        assert!(matches!(batch.errors[&corrupt], AIEngineError::PreprocessingFailed { .. }));
+        assert!(matches!(batch.errors[&missing], AIEngineError::Io(_)));
+
+        let wide = &batch.results[&dir.join("valid-0.png")];
+        assert_eq!(wide.predictions.len(), 1);
+        assert_eq!(wide.predictions[0].class, "landscape");
+
+        assert!(classify_batch(Vec::<PathBuf>::new(), 1, 0).await.is_err());
+    }
+}
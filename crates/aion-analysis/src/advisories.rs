@@ -0,0 +1,380 @@
+//! Vulnerability advisory ingestion
+//!
+//! Reads advisories in the [OSV](https://ossf.github.io/osv-schema/) format,
+//! as published by osv.dev, the GitHub advisory database and RustSec, and
+//! attaches the ones affecting a project's resolved dependency versions to
+//! those dependencies, where the SBOM export picks them up.
+
+use crate::{AnalysisProject, Dependency, Language, Result, SecuritySeverity, SecurityVulnerability};
+use serde::Deserialize;
+use std::cmp::Ordering;
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct OsvAdvisory {
+    pub id: String,
+    #[serde(default)]
+    pub aliases: Vec<String>,
+    #[serde(default)]
+    pub summary: String,
+    #[serde(default)]
+    pub details: String,
+    #[serde(default)]
+    pub affected: Vec<OsvAffected>,
+    #[serde(default)]
+    pub references: Vec<OsvReference>,
+    #[serde(default)]
+    pub database_specific: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct OsvAffected {
+    pub package: OsvPackage,
+    #[serde(default)]
+    pub ranges: Vec<OsvRange>,
+    /// Exact affected versions, listed in addition to or instead of ranges
+    #[serde(default)]
+    pub versions: Vec<String>,
+    #[serde(default)]
+    pub database_specific: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct OsvPackage {
+    pub ecosystem: String,
+    pub name: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct OsvRange {
+    #[serde(rename = "type")]
+    pub range_type: String,
+    #[serde(default)]
+    pub events: Vec<OsvEvent>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct OsvEvent {
+    pub introduced: Option<String>,
+    pub fixed: Option<String>,
+    pub last_affected: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct OsvReference {
+    pub url: String,
+}
+
+/// Parse one advisory, an array of advisories, or an osv.dev query response (`{"vulns": [...]}`)
+pub fn parse_osv_advisories(json: &str) -> Result<Vec<OsvAdvisory>> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Document {
+        Query { vulns: Vec<OsvAdvisory> },
+        Many(Vec<OsvAdvisory>),
+        One(Box<OsvAdvisory>),
+    }
+
+    Ok(match serde_json::from_str(json)? {
+        Document::Query { vulns } => vulns,
+        Document::Many(advisories) => advisories,
+        Document::One(advisory) => vec![*advisory],
+    })
+}
+
+/// Attach every advisory affecting a dependency's version to that dependency.
+/// Returns the number of advisories attached; advisories a dependency already
+/// carries are not added twice.
+///
+/// Dependencies are matched by name within the ecosystem of the project's
+/// language; for languages without a known ecosystem any ecosystem matches.
+pub fn apply_advisories(project: &mut AnalysisProject, advisories: &[OsvAdvisory]) -> usize {
+    let ecosystem = osv_ecosystem(&project.language);
+    let mut attached = 0;
+
+    for dependency in &mut project.dependencies {
+        for advisory in advisories {
+            let Some(affected) = advisory.affected.iter().find(|affected| {
+                in_ecosystem(affected, ecosystem)
+                    && same_package(&affected.package.name, &dependency.name, ecosystem)
+                    && affects(affected, &dependency.version)
+            }) else {
+                continue;
+            };
+
+            let vulnerability = vulnerability(advisory, affected);
+            if already_attached(dependency, &vulnerability) {
+                continue;
+            }
+            dependency.vulnerabilities.push(vulnerability);
+            attached += 1;
+        }
+    }
+    attached
+}
+
+fn osv_ecosystem(language: &Language) -> Option<&'static str> {
+    Some(match language {
+        Language::Rust => "crates.io",
+        Language::JavaScript | Language::TypeScript => "npm",
+        Language::Python => "PyPI",
+        Language::Go => "Go",
+        Language::Java => "Maven",
+        Language::CSharp => "NuGet",
+        _ => return None,
+    })
+}
+
+fn in_ecosystem(affected: &OsvAffected, ecosystem: Option<&str>) -> bool {
+    match ecosystem {
+        Some(ecosystem) => affected.package.ecosystem.eq_ignore_ascii_case(ecosystem),
+        None => true,
+    }
+}
+
+fn same_package(advisory_name: &str, dependency_name: &str, ecosystem: Option<&str>) -> bool {
+    match ecosystem {
+        // PyPI names are case-insensitive and treat -, _ and . alike
+        Some("PyPI") => normalize_pypi(advisory_name) == normalize_pypi(dependency_name),
+        _ => advisory_name == dependency_name,
+    }
+}
+
+fn normalize_pypi(name: &str) -> String {
+    name.to_lowercase().replace(['_', '.'], "-")
+}
+
+/// Whether `version` falls in an affected range or is listed explicitly
+fn affects(affected: &OsvAffected, version: &str) -> bool {
+    if affected.versions.iter().any(|listed| listed == version) {
+        return true;
+    }
+    affected
+        .ranges
+        .iter()
+        // GIT ranges are commit hashes, which a resolved version can't be compared with
+        .filter(|range| range.range_type != "GIT")
+        .any(|range| in_range(&range.events, version))
+}
+
+/// Walk the events in order: each `introduced` opens an affected interval and
+/// the next `fixed` (exclusive) or `last_affected` (inclusive) closes it
+fn in_range(events: &[OsvEvent], version: &str) -> bool {
+    let mut affected = false;
+    for event in events {
+        if let Some(introduced) = &event.introduced {
+            if introduced == "0" || compare_versions(version, introduced) != Ordering::Less {
+                affected = true;
+            }
+        }
+        if let Some(fixed) = &event.fixed {
+            if compare_versions(version, fixed) != Ordering::Less {
+                affected = false;
+            }
+        }
+        if let Some(last_affected) = &event.last_affected {
+            if compare_versions(version, last_affected) == Ordering::Greater {
+                affected = false;
+            }
+        }
+    }
+    affected
+}
+
+/// Compare dotted versions component by component, numerically where both
+/// components are numbers. A pre-release (`1.0.0-rc.1`) sorts before its release.
+pub(crate) fn compare_versions(a: &str, b: &str) -> Ordering {
+    let split = |version: &str| {
+        let version = version.trim_start_matches('v');
+        let (release, pre) = match version.split_once('-') {
+            Some((release, pre)) => (release.to_string(), Some(pre.to_string())),
+            None => (version.split('+').next().unwrap_or_default().to_string(), None),
+        };
+        (release, pre)
+    };
+    let (release_a, pre_a) = split(a);
+    let (release_b, pre_b) = split(b);
+
+    let mut parts_a = release_a.split('.');
+    let mut parts_b = release_b.split('.');
+    loop {
+        let ordering = match (parts_a.next(), parts_b.next()) {
+            (None, None) => break,
+            (a, b) => {
+                let (a, b) = (a.unwrap_or("0"), b.unwrap_or("0"));
+                match (a.parse::<u64>(), b.parse::<u64>()) {
+                    (Ok(a), Ok(b)) => a.cmp(&b),
+                    _ => a.cmp(b),
+                }
+            }
+        };
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+
+    match (pre_a, pre_b) {
+        (None, None) => Ordering::Equal,
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (Some(a), Some(b)) => a.cmp(&b),
+    }
+}
+
+fn vulnerability(advisory: &OsvAdvisory, affected: &OsvAffected) -> SecurityVulnerability {
+    let cve_id = advisory
+        .aliases
+        .iter()
+        .find(|alias| alias.starts_with("CVE-"))
+        .cloned()
+        .unwrap_or_else(|| advisory.id.clone());
+
+    let mut affected_versions = affected.versions.clone();
+    let mut fixed_versions = Vec::new();
+    for range in affected.ranges.iter().filter(|range| range.range_type != "GIT") {
+        let mut introduced = None;
+        for event in &range.events {
+            if let Some(version) = &event.introduced {
+                introduced = Some(version.clone());
+            }
+            let (bound, operator) = match (&event.fixed, &event.last_affected) {
+                (Some(fixed), _) => {
+                    fixed_versions.push(fixed.clone());
+                    (fixed, "<")
+                }
+                (None, Some(last_affected)) => (last_affected, "<="),
+                (None, None) => continue,
+            };
+            affected_versions.push(match introduced.take().filter(|version| version != "0") {
+                Some(introduced) => format!(">={}, {}{}", introduced, operator, bound),
+                None => format!("{}{}", operator, bound),
+            });
+        }
+        if let Some(introduced) = introduced {
+            affected_versions.push(format!(">={}", introduced));
+        }
+    }
+
+    SecurityVulnerability {
+        cve_id: Some(cve_id),
+        severity: severity(advisory, affected),
+        title: if advisory.summary.is_empty() { advisory.id.clone() } else { advisory.summary.clone() },
+        description: advisory.details.clone(),
+        affected_versions,
+        fixed_versions,
+        references: advisory.references.iter().map(|reference| reference.url.clone()).collect(),
+    }
+}
+
+/// Severity from `database_specific.severity` (GitHub, PyPA) of the affected
+/// package or the advisory; advisories without one are rated medium
+fn severity(advisory: &OsvAdvisory, affected: &OsvAffected) -> SecuritySeverity {
+    let rating = [&affected.database_specific, &advisory.database_specific]
+        .into_iter()
+        .flatten()
+        .find_map(|specific| specific.get("severity").and_then(serde_json::Value::as_str));
+
+    match rating.map(str::to_uppercase).as_deref() {
+        Some("CRITICAL") => SecuritySeverity::Critical,
+        Some("HIGH") => SecuritySeverity::High,
+        Some("LOW") => SecuritySeverity::Low,
+        Some("INFO") | Some("INFORMATIONAL") | Some("NONE") => SecuritySeverity::Info,
+        _ => SecuritySeverity::Medium,
+    }
+}
+
+fn already_attached(dependency: &Dependency, vulnerability: &SecurityVulnerability) -> bool {
+    dependency
+        .vulnerabilities
+        .iter()
+        .any(|existing| existing.cve_id.is_some() && existing.cve_id == vulnerability.cve_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{DependencySource, DependencyType};
+
+    const ADVISORY: &str = r#"{
+        "vulns": [{
+            "id": "GHSA-rv95-896h-c2vc",
+            "aliases": ["CVE-2024-29041"],
+            "summary": "Express.js Open Redirect in malformed URLs",
+            "details": "Versions of Express.js prior to 4.19.2 are vulnerable to an open redirect.",
+            "affected": [{
+                "package": { "ecosystem": "npm", "name": "express" },
+                "ranges": [{ "type": "SEMVER", "events": [{ "introduced": "0" }, { "fixed": "4.19.2" }] }]
+            }],
+            "references": [{ "type": "WEB", "url": "https://github.com/expressjs/express/security/advisories/GHSA-rv95-896h-c2vc" }],
+            "database_specific": { "severity": "MODERATE" }
+        }, {
+            "id": "GHSA-xxxx-yyyy-zzzz",
+            "summary": "Prototype pollution",
+            "affected": [{
+                "package": { "ecosystem": "npm", "name": "body-parser" },
+                "versions": ["1.20.1"],
+                "database_specific": { "severity": "HIGH" }
+            }]
+        }]
+    }"#;
+
+    fn project(dependencies: Vec<(&str, &str)>) -> AnalysisProject {
+        let mut project = crate::project_import::import_project(tempfile::tempdir().unwrap().path()).unwrap();
+        project.language = Language::JavaScript;
+        project.dependencies = dependencies
+            .into_iter()
+            .map(|(name, version)| Dependency {
+                name: name.to_string(),
+                version: version.to_string(),
+                dependency_type: DependencyType::Production,
+                source: DependencySource::Registry,
+                vulnerabilities: Vec::new(),
+                license: None,
+                size: None,
+                direct: true,
+                depends_on: Vec::new(),
+                hashes: Vec::new(),
+            })
+            .collect();
+        project
+    }
+
+    #[test]
+    fn attaches_advisories_to_affected_versions_only() {
+        let advisories = parse_osv_advisories(ADVISORY).unwrap();
+        let mut project = project(vec![("express", "4.18.2"), ("body-parser", "1.20.1"), ("lodash", "4.17.21")]);
+
+        assert_eq!(apply_advisories(&mut project, &advisories), 2);
+        // Applying the same advisories again adds nothing
+        assert_eq!(apply_advisories(&mut project, &advisories), 0);
+
+        let express = &project.dependencies[0].vulnerabilities;
+        assert_eq!(express.len(), 1);
+        assert_eq!(express[0].cve_id.as_deref(), Some("CVE-2024-29041"));
+        assert!(matches!(express[0].severity, SecuritySeverity::Medium));
+        assert_eq!(express[0].fixed_versions, vec!["4.19.2".to_string()]);
+        assert_eq!(express[0].affected_versions, vec!["<4.19.2".to_string()]);
+
+        let body_parser = &project.dependencies[1].vulnerabilities;
+        assert_eq!(body_parser[0].cve_id.as_deref(), Some("GHSA-xxxx-yyyy-zzzz"));
+        assert!(matches!(body_parser[0].severity, SecuritySeverity::High));
+        assert!(project.dependencies[2].vulnerabilities.is_empty());
+
+        let mut patched = self::project(vec![("express", "4.19.2")]);
+        assert_eq!(apply_advisories(&mut patched, &advisories), 0);
+    }
+
+    #[test]
+    fn ranges_follow_osv_event_order() {
+        let events = |json: &str| serde_json::from_str::<Vec<OsvEvent>>(json).unwrap();
+        let reintroduced = events(r#"[{"introduced":"1.0.0"},{"fixed":"1.2.0"},{"introduced":"2.0.0"},{"last_affected":"2.1.3"}]"#);
+        assert!(!in_range(&reintroduced, "0.9.0"));
+        assert!(in_range(&reintroduced, "1.1.9"));
+        assert!(!in_range(&reintroduced, "1.2.0"));
+        assert!(in_range(&reintroduced, "2.1.3"));
+        assert!(!in_range(&reintroduced, "2.1.4"));
+
+        assert_eq!(compare_versions("1.10.0", "1.9.0"), Ordering::Greater);
+        assert_eq!(compare_versions("1.0.0-rc.1", "1.0.0"), Ordering::Less);
+        assert_eq!(compare_versions("v1.2", "1.2.0"), Ordering::Equal);
+    }
+}
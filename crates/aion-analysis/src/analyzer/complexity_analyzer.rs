@@ -0,0 +1,510 @@
+use crate::{AnalysisIssue, CodeLocation, ComplexityGate, FileScope, Interrupted, Language, RuleCategory, SourceFile};
+use regex::bytes::Regex;
+use uuid::Uuid;
+
+/// Complexity of one function
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FunctionComplexity {
+    pub name: String,
+    /// One-based line of the function's header
+    pub start_line: u32,
+    pub start_column: u32,
+    /// One-based line where the body ends
+    pub end_line: u32,
+    pub start_byte: u32,
+    pub end_byte: u32,
+    /// One plus the number of decision points
+    pub cyclomatic: u32,
+    /// Branches weighted by how deeply they are nested, after SonarSource's
+    /// cognitive complexity
+    pub cognitive: u32,
+}
+
+/// Finds functions and measures their complexity without a full parser
+///
+/// Comments and string literals are masked out first, so keywords inside them
+/// are not counted. Functions nested in another function are measured on
+/// their own and left out of the enclosing one.
+pub struct ComplexityAnalyzer {
+    rust: Vec<Regex>,
+    javascript: Vec<Regex>,
+    go: Vec<Regex>,
+    c_like: Vec<Regex>,
+    python: Regex,
+    keyword: Regex,
+}
+
+impl ComplexityAnalyzer {
+    pub fn new() -> Self {
+        let regex = |pattern: &str| Regex::new(pattern).expect("valid function pattern");
+        Self {
+            rust: vec![regex(r"\bfn\s+(?P<name>[A-Za-z_][A-Za-z0-9_]*)")],
+            javascript: vec![
+                regex(r"\bfunction\b\s*\*?\s*(?P<name>[A-Za-z_$][\w$]*)"),
+                regex(r"(?P<name>[A-Za-z_$][\w$]*)\s*[=:]\s*(?:async\s+)?(?:function\b|(?:\([^()]*\)|[A-Za-z_$][\w$]*)\s*(?::\s*[^=;{]+)?=>\s*\{)"),
+                regex(r"(?m)^[ \t]*(?:(?:public|private|protected|static|async|override|readonly|get|set)\s+)*\*?(?P<name>[A-Za-z_$][\w$]*)\s*(?:<[^>]*>)?\s*\([^()]*\)\s*(?::\s*[^{;=]+)?\{"),
+            ],
+            go: vec![regex(r"\bfunc\s+(?:\([^)]*\)\s*)?(?P<name>[A-Za-z_]\w*)")],
+            c_like: vec![regex(
+                r"(?m)^[ \t]*(?:[\w<>\[\],.:*&?]+[ \t]+)+[*&]?(?P<name>[A-Za-z_~][\w]*)\s*\([^;{}]*\)\s*(?:const\s*)?(?:noexcept\s*)?(?:throws\s+[\w.,\s]+)?\{",
+            )],
+            python: regex(r"(?m)^(?P<indent>[ \t]*)(?:async[ \t]+)?def[ \t]+(?P<name>[A-Za-z_]\w*)"),
+            keyword: regex(
+                r"\b(?:if|else|elif|for|while|loop|match|switch|case|catch|except|and|or)\b|&&|\|\||=>|\?",
+            ),
+        }
+    }
+
+    /// Every function in `file` with its complexity; empty for languages
+    /// without functions
+    pub fn functions(&self, file: &SourceFile) -> Vec<FunctionComplexity> {
+        self.functions_within(file, &FileScope::unbounded())
+            .expect("an unbounded scope never interrupts")
+    }
+
+    /// Like [`ComplexityAnalyzer::functions`], stopping early when `scope` is interrupted
+    pub fn functions_within(&self, file: &SourceFile, scope: &FileScope) -> std::result::Result<Vec<FunctionComplexity>, Interrupted> {
+        let code = mask_comments_and_strings(file.content.as_bytes(), &file.language);
+        let spans = match file.language {
+            Language::Rust => self.brace_functions(&code, &self.rust),
+            Language::JavaScript | Language::TypeScript => self.brace_functions(&code, &self.javascript),
+            Language::Go => self.brace_functions(&code, &self.go),
+            Language::Java | Language::C | Language::CPlusPlus | Language::CSharp => self.brace_functions(&code, &self.c_like),
+            Language::Python => self.python_functions(&code),
+            _ => Vec::new(),
+        };
+
+        let lines = LineIndex::new(&file.content);
+        let mut functions = Vec::with_capacity(spans.len());
+        for span in &spans {
+            scope.check()?;
+            let nested: Vec<(usize, usize)> = spans
+                .iter()
+                .filter(|other| other.header > span.body.0 && other.body.1 <= span.body.1)
+                .map(|other| (other.header, other.body.1))
+                .collect();
+            let (cyclomatic, cognitive) = self.measure(&code, span, &nested, &file.language, &lines);
+            let (start_line, end_line) = (lines.line_of(span.header), lines.line_of(span.body.1.saturating_sub(1)));
+            functions.push(FunctionComplexity {
+                name: span.name.clone(),
+                start_line: start_line as u32 + 1,
+                start_column: lines.column(&file.content, span.header),
+                end_line: end_line as u32 + 1,
+                start_byte: span.header as u32,
+                end_byte: span.body.1 as u32,
+                cyclomatic,
+                cognitive,
+            });
+        }
+        Ok(functions)
+    }
+
+    /// Issues for the functions in `file` that exceed the gate's limits for
+    /// its language
+    pub fn gate_within(&self, file: &SourceFile, gate: &ComplexityGate, scope: &FileScope) -> std::result::Result<Vec<AnalysisIssue>, Interrupted> {
+        let Some(limits) = gate.limits_for(&file.language) else {
+            return Ok(Vec::new());
+        };
+        let mut issues = Vec::new();
+        for function in self.functions_within(file, scope)? {
+            let breaches = [
+                ("cyclomatic-complexity", "Cyclomatic Complexity", "cyclomatic", function.cyclomatic, limits.max_cyclomatic),
+                ("cognitive-complexity", "Cognitive Complexity", "cognitive", function.cognitive, limits.max_cognitive),
+            ];
+            for (rule_id, rule_name, kind, value, max) in breaches {
+                let Some(max) = max.filter(|&max| value > max) else {
+                    continue;
+                };
+                issues.push(AnalysisIssue {
+                    id: Uuid::new_v4(),
+                    rule_id: rule_id.to_string(),
+                    rule_name: rule_name.to_string(),
+                    severity: gate.severity.clone(),
+                    category: RuleCategory::Maintainability,
+                    message: format!(
+                        "Function `{}` has {} complexity {}, above the maximum of {} for {:?}",
+                        function.name, kind, value, max, file.language
+                    ),
+                    description: Some("Functions with many branches are hard to test and to change safely; split this one into smaller functions".to_string()),
+                    location: CodeLocation {
+                        file_path: file.path.clone(),
+                        start_line: function.start_line,
+                        start_column: function.start_column,
+                        end_line: function.end_line,
+                        end_column: 1,
+                        start_byte: function.start_byte,
+                        end_byte: function.end_byte,
+                    },
+                    suggested_fix: None,
+                    related_issues: Vec::new(),
+                    external_references: Vec::new(),
+                });
+            }
+        }
+        Ok(issues)
+    }
+
+    /// Functions whose body is the brace block following their header
+    fn brace_functions(&self, code: &[u8], headers: &[Regex]) -> Vec<FunctionSpan> {
+        let mut spans: Vec<FunctionSpan> = Vec::new();
+        for header in headers {
+            for captures in header.captures_iter(code) {
+                let name = captures.name("name").expect("pattern has a name group");
+                if is_control_keyword(name.as_bytes()) {
+                    continue;
+                }
+                let Some(open) = body_start(code, name.end()) else {
+                    continue;
+                };
+                if spans.iter().any(|span| span.body.0 == open) {
+                    continue;
+                }
+                let Some(close) = matching_brace(code, open) else {
+                    continue;
+                };
+                spans.push(FunctionSpan {
+                    name: String::from_utf8_lossy(name.as_bytes()).into_owned(),
+                    header: captures.get(0).map_or(name.start(), |m| m.start() + leading_whitespace(m.as_bytes())),
+                    body: (open, close + 1),
+                    indent: None,
+                });
+            }
+        }
+        spans.sort_by_key(|span| span.header);
+        spans
+    }
+
+    /// Functions whose body is the block indented under their `def`
+    fn python_functions(&self, code: &[u8]) -> Vec<FunctionSpan> {
+        let mut spans = Vec::new();
+        for captures in self.python.captures_iter(code) {
+            let def_indent = indent_width(captures.name("indent").map_or(&[][..], |m| m.as_bytes()));
+            let name = captures.name("name").expect("pattern has a name group");
+            // The header ends at the first `:` outside its parameter list
+            let Some(colon) = body_start_python(code, name.end()) else {
+                continue;
+            };
+            let mut end = line_end(code, colon);
+            let mut body_indent = None;
+            let mut position = end + 1;
+            while position < code.len() {
+                let next = line_end(code, position);
+                let line = &code[position..next];
+                let content = line.iter().position(|b| !b.is_ascii_whitespace());
+                if let Some(content) = content {
+                    let indent = indent_width(&line[..content]);
+                    if indent <= def_indent {
+                        break;
+                    }
+                    body_indent.get_or_insert(indent);
+                    end = next;
+                }
+                position = next + 1;
+            }
+            spans.push(FunctionSpan {
+                name: String::from_utf8_lossy(name.as_bytes()).into_owned(),
+                header: captures.get(0).map_or(0, |m| m.start()) + captures.name("indent").map_or(0, |m| m.len()),
+                body: (colon + 1, end.max(colon + 1)),
+                indent: Some((def_indent, body_indent.unwrap_or(def_indent + 4))),
+            });
+        }
+        spans
+    }
+
+    fn measure(
+        &self,
+        code: &[u8],
+        span: &FunctionSpan,
+        nested: &[(usize, usize)],
+        language: &Language,
+        lines: &LineIndex,
+    ) -> (u32, u32) {
+        let is_rust = *language == Language::Rust;
+        let is_python = *language == Language::Python;
+        let (body_start, body_end) = span.body;
+
+        let mut cyclomatic: i64 = 1;
+        let mut cognitive: u32 = 0;
+        let mut previous_keyword: &[u8] = b"";
+        let mut previous_operator: Option<(&[u8], usize)> = None;
+
+        for found in self.keyword.find_iter(&code[body_start..body_end]) {
+            let offset = body_start + found.start();
+            if nested.iter().any(|&(start, end)| offset >= start && offset < end) {
+                continue;
+            }
+            let token = found.as_bytes();
+            let nesting = match span.indent {
+                Some((_, body_indent)) => {
+                    let line = &code[lines.starts[lines.line_of(offset)]..offset];
+                    let indent = indent_width(&line[..leading_whitespace(line)]);
+                    let unit = body_indent.saturating_sub(span.indent.map_or(0, |(def, _)| def)).max(1);
+                    (indent.saturating_sub(body_indent) / unit) as u32
+                }
+                None => brace_depth(&code[body_start..offset]).saturating_sub(1),
+            };
+            let else_if = previous_keyword == b"else" && token == b"if";
+
+            match token {
+                b"if" | b"for" | b"while" | b"catch" => {
+                    cyclomatic += 1;
+                    cognitive += if else_if { 0 } else { 1 + nesting };
+                }
+                b"elif" | b"except" if is_python => {
+                    cyclomatic += 1;
+                    cognitive += 1;
+                }
+                b"else" => cognitive += 1,
+                b"loop" if is_rust => cognitive += 1 + nesting,
+                b"match" if is_rust => {
+                    // Each arm below adds a path; the first one is the default
+                    cyclomatic -= 1;
+                    cognitive += 1 + nesting;
+                }
+                b"=>" if is_rust => cyclomatic += 1,
+                b"switch" if !is_rust && !is_python => cognitive += 1 + nesting,
+                b"case" if !is_rust => cyclomatic += 1,
+                b"?" if !is_rust && !is_python && is_ternary(code, offset) => {
+                    cyclomatic += 1;
+                    cognitive += 1 + nesting;
+                }
+                b"&&" | b"||" if !is_python => {
+                    cyclomatic += 1;
+                    cognitive += boolean_sequence_cost(&mut previous_operator, token, lines.line_of(offset));
+                }
+                b"and" | b"or" if is_python => {
+                    cyclomatic += 1;
+                    cognitive += boolean_sequence_cost(&mut previous_operator, token, lines.line_of(offset));
+                }
+                _ => {}
+            }
+            if token.iter().all(u8::is_ascii_alphabetic) {
+                previous_keyword = token;
+            }
+        }
+
+        (cyclomatic.max(1) as u32, cognitive)
+    }
+}
+
+impl Default for ComplexityAnalyzer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+struct FunctionSpan {
+    name: String,
+    /// Byte offset of the header
+    header: usize,
+    /// Byte range of the body, including its braces
+    body: (usize, usize),
+    /// Indentation of the `def` and of the body, for Python
+    indent: Option<(usize, usize)>,
+}
+
+/// A run of the same boolean operator costs 1; each change of operator in an
+/// expression costs 1 more
+fn boolean_sequence_cost<'a>(previous: &mut Option<(&'a [u8], usize)>, operator: &'a [u8], line: usize) -> u32 {
+    let cost = match *previous {
+        Some((previous_operator, previous_line)) if previous_line == line && previous_operator == operator => 0,
+        _ => 1,
+    };
+    *previous = Some((operator, line));
+    cost
+}
+
+fn is_control_keyword(name: &[u8]) -> bool {
+    matches!(
+        name,
+        b"if" | b"else" | b"for" | b"while" | b"switch" | b"catch" | b"return" | b"new" | b"sizeof" | b"do" | b"function"
+    )
+}
+
+/// `?` of a conditional expression, not optional chaining, `??` or an
+/// optional parameter
+fn is_ternary(code: &[u8], offset: usize) -> bool {
+    let next = code[offset + 1..].iter().find(|b| !b.is_ascii_whitespace());
+    let previous = offset.checked_sub(1).map(|i| code[i]);
+    !matches!(next, Some(b'.' | b':' | b'?' | b'=' | b')' | b',')) && previous != Some(b'?')
+}
+
+/// The `{` opening the body after a function header, or `None` if a `;`
+/// ends the header first (a declaration without a body)
+fn body_start(code: &[u8], from: usize) -> Option<usize> {
+    let mut depth = 0i32;
+    for (i, &byte) in code.iter().enumerate().skip(from) {
+        match byte {
+            b'(' | b'[' => depth += 1,
+            b')' | b']' => depth -= 1,
+            b'{' if depth <= 0 => return Some(i),
+            b';' | b'}' if depth <= 0 => return None,
+            _ => {}
+        }
+    }
+    None
+}
+
+fn body_start_python(code: &[u8], from: usize) -> Option<usize> {
+    let mut depth = 0i32;
+    for (i, &byte) in code.iter().enumerate().skip(from) {
+        match byte {
+            b'(' | b'[' => depth += 1,
+            b')' | b']' => depth -= 1,
+            b':' if depth <= 0 => return Some(i),
+            _ => {}
+        }
+    }
+    None
+}
+
+fn matching_brace(code: &[u8], open: usize) -> Option<usize> {
+    let mut depth = 0usize;
+    for (i, &byte) in code.iter().enumerate().skip(open) {
+        match byte {
+            b'{' => depth += 1,
+            b'}' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(i);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Unclosed braces in `code`
+fn brace_depth(code: &[u8]) -> u32 {
+    code.iter().fold(0i64, |depth, &byte| match byte {
+        b'{' => depth + 1,
+        b'}' => depth - 1,
+        _ => depth,
+    }).max(0) as u32
+}
+
+fn line_end(code: &[u8], from: usize) -> usize {
+    code[from..].iter().position(|&b| b == b'\n').map_or(code.len(), |i| from + i)
+}
+
+fn leading_whitespace(bytes: &[u8]) -> usize {
+    bytes.iter().take_while(|b| b.is_ascii_whitespace()).count()
+}
+
+/// Columns of leading whitespace, with tabs as four
+fn indent_width(whitespace: &[u8]) -> usize {
+    whitespace.iter().map(|&b| if b == b'\t' { 4 } else { 1 }).sum()
+}
+
+/// `content` with comments and string literals blanked out, keeping byte
+/// offsets and line breaks so positions still line up with the source
+pub(crate) fn mask_comments_and_strings(content: &[u8], language: &Language) -> Vec<u8> {
+    let python = *language == Language::Python;
+    let rust = *language == Language::Rust;
+    let mut code = content.to_vec();
+    let mut i = 0;
+
+    while i < content.len() {
+        let rest = &content[i..];
+        let end = if (!python && rest.starts_with(b"//")) || (python && rest[0] == b'#') {
+            line_end(content, i)
+        } else if !python && rest.starts_with(b"/*") {
+            find(content, i + 2, b"*/").map_or(content.len(), |end| end + 2)
+        } else if python && (rest.starts_with(b"\"\"\"") || rest.starts_with(b"'''")) {
+            find(content, i + 3, &rest[..3]).map_or(content.len(), |end| end + 3)
+        } else if rust && rest[0] == b'r' && (rest.get(1) == Some(&b'"') || rest.get(1) == Some(&b'#')) && (i == 0 || !is_ident(content[i - 1])) {
+            let hashes = rest[1..].iter().take_while(|&&b| b == b'#').count();
+            if rest.get(1 + hashes) != Some(&b'"') {
+                i += 1;
+                continue;
+            }
+            let mut closing = vec![b'"'];
+            closing.extend(std::iter::repeat(b'#').take(hashes));
+            find(content, i + 2 + hashes, &closing).map_or(content.len(), |end| end + closing.len())
+        } else if rest[0] == b'"' || rest[0] == b'`' || (rest[0] == b'\'' && (!rust || is_char_literal(rest))) {
+            // Double-quoted strings are multi-line in Rust; template literals and raw strings everywhere
+            let multiline = rest[0] == b'`' || (rust && rest[0] == b'"');
+            quoted_end(content, i, multiline)
+        } else {
+            i += 1;
+            continue;
+        };
+        for byte in &mut code[i..end.min(content.len())] {
+            if *byte != b'\n' {
+                *byte = b' ';
+            }
+        }
+        i = end.max(i + 1);
+    }
+    code
+}
+
+fn quoted_end(content: &[u8], start: usize, multiline: bool) -> usize {
+    let quote = content[start];
+    let mut i = start + 1;
+    while i < content.len() {
+        match content[i] {
+            b'\\' => i += 2,
+            b'\n' if !multiline => return i,
+            byte if byte == quote => return i + 1,
+            _ => i += 1,
+        }
+    }
+    content.len()
+}
+
+/// A Rust `'x'` or `'\n'` rather than a lifetime such as `'a`
+fn is_char_literal(rest: &[u8]) -> bool {
+    match rest.get(1) {
+        Some(b'\\') => true,
+        Some(&first) => {
+            // The character may be several bytes long
+            let width = match first {
+                0x00..=0x7f => 1,
+                0xf0..=0xff => 4,
+                0xe0..=0xef => 3,
+                _ => 2,
+            };
+            rest.get(1 + width) == Some(&b'\'')
+        }
+        None => false,
+    }
+}
+
+pub(crate) fn is_ident(byte: u8) -> bool {
+    byte.is_ascii_alphanumeric() || byte == b'_'
+}
+
+fn find(haystack: &[u8], from: usize, needle: &[u8]) -> Option<usize> {
+    haystack
+        .get(from..)?
+        .windows(needle.len())
+        .position(|window| window == needle)
+        .map(|i| from + i)
+}
+
+/// Byte offsets of line starts
+pub(crate) struct LineIndex {
+    pub(crate) starts: Vec<usize>,
+}
+
+impl LineIndex {
+    pub(crate) fn new(content: &str) -> Self {
+        let starts = std::iter::once(0)
+            .chain(content.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+        Self { starts }
+    }
+
+    /// Zero-based line containing `offset`
+    pub(crate) fn line_of(&self, offset: usize) -> usize {
+        self.starts.partition_point(|&start| start <= offset) - 1
+    }
+
+    /// One-based character column of `offset`
+    pub(crate) fn column(&self, content: &str, offset: usize) -> u32 {
+        let start = self.starts[self.line_of(offset)];
+        content.get(start..offset).map_or(0, |prefix| prefix.chars().count()) as u32 + 1
+    }
+}
@@ -3,30 +3,43 @@ pub mod javascript_analyzer;
 pub mod typescript_analyzer;
 pub mod python_analyzer;
 pub mod multi_language_analyzer;
+pub mod secrets_analyzer;
+pub mod complexity_analyzer;
 
 pub use rust_analyzer::*;
 pub use javascript_analyzer::*;
 pub use typescript_analyzer::*;
 pub use python_analyzer::*;
 pub use multi_language_analyzer::*;
+pub use secrets_analyzer::*;
+pub use complexity_analyzer::*;
 
 use crate::{
     AnalysisProject, SourceFile, FileAnalysisResult, ProjectAnalysisResult, AnalysisIssue,
     CodeAnalyzer, Language, Severity, RuleCategory, CodeLocation, ProjectMetrics, SecurityFinding,
     RefactoringOpportunity, AISuggestion, Result, FileMetrics, ProjectInsight, ProjectRecommendation,
-    QualityRating, TechnicalDebtMetrics, ComplexityMetrics
+    QualityRating, TechnicalDebtMetrics, ComplexityMetrics, AnalysisContext, FileScope, Interrupted,
+    ComplexityGate, detect_language
 };
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 use uuid::Uuid;
 use chrono::Utc;
 use async_trait::async_trait;
+use sha2::{Digest, Sha256};
+
+/// Lines processed between cancellation checks in line-based passes
+const CHECK_INTERVAL: usize = 1024;
 
 pub struct DefaultCodeAnalyzer {
     rust_analyzer: RustAnalyzer,
     javascript_analyzer: JavaScriptAnalyzer,
     typescript_analyzer: TypeScriptAnalyzer,
     python_analyzer: PythonAnalyzer,
+    secrets_analyzer: SecretsAnalyzer,
+    complexity_analyzer: ComplexityAnalyzer,
+    complexity_gate: Option<ComplexityGate>,
     ai_enabled: bool,
     security_enabled: bool,
 }
@@ -38,6 +51,9 @@ impl DefaultCodeAnalyzer {
             javascript_analyzer: JavaScriptAnalyzer::new(),
             typescript_analyzer: TypeScriptAnalyzer::new(),
             python_analyzer: PythonAnalyzer::new(),
+            secrets_analyzer: SecretsAnalyzer::new(),
+            complexity_analyzer: ComplexityAnalyzer::new(),
+            complexity_gate: None,
             ai_enabled: true,
             security_enabled: true,
         }
@@ -49,27 +65,170 @@ impl DefaultCodeAnalyzer {
             javascript_analyzer: JavaScriptAnalyzer::new(),
             typescript_analyzer: TypeScriptAnalyzer::new(),
             python_analyzer: PythonAnalyzer::new(),
+            secrets_analyzer: SecretsAnalyzer::new(),
+            complexity_analyzer: ComplexityAnalyzer::new(),
+            complexity_gate: None,
             ai_enabled,
             security_enabled,
         }
     }
 
-    async fn analyze_file_by_language(&self, file: &SourceFile) -> Result<FileAnalysisResult> {
-        match file.language {
-            Language::Rust => self.rust_analyzer.analyze_file(file).await,
-            Language::JavaScript => self.javascript_analyzer.analyze_file(file).await,
-            Language::TypeScript => self.typescript_analyzer.analyze_file(file).await,
-            Language::Python => self.python_analyzer.analyze_file(file).await,
-            _ => self.analyze_generic_file(file).await,
+    /// Replace the secrets analyzer, e.g. to use a custom allowlist
+    pub fn with_secrets_analyzer(mut self, secrets_analyzer: SecretsAnalyzer) -> Self {
+        self.secrets_analyzer = secrets_analyzer;
+        self
+    }
+
+    /// Report functions over the gate's complexity limits as issues; a
+    /// project's own `complexity_gate` takes precedence
+    pub fn with_complexity_gate(mut self, gate: ComplexityGate) -> Self {
+        self.complexity_gate = Some(gate);
+        self
+    }
+
+    /// Analyze `file` within the context's time budget; a file that is cut
+    /// short yields a result flagged with why it stopped
+    async fn analyze_file_within(
+        &self,
+        file: &SourceFile,
+        ctx: &AnalysisContext,
+        gate: Option<&ComplexityGate>,
+    ) -> Result<(FileAnalysisResult, Option<Interrupted>)> {
+        let start_time = std::time::Instant::now();
+        let scope = ctx.file_scope();
+
+        // Racing the analysis interrupts analyzers at their next await point;
+        // the language analyzers and synchronous passes also check the scope
+        // between steps, so a long parse or tree walk stops on time too
+        let outcome = tokio::select! {
+            result = self.analyze_file_by_language(file, &scope, gate) => result,
+            interrupted = scope.interrupted() => Err(interrupted.into()),
+        };
+
+        match outcome {
+            Ok(result) => Ok((result, None)),
+            Err(e) => match e.downcast_ref::<Interrupted>() {
+                Some(&interrupted) => {
+                    tracing::warn!("{} for {}", interrupted, file.relative_path.display());
+                    let result = self.interrupted_result(file, interrupted, ctx.file_timeout(), start_time.elapsed());
+                    Ok((result, Some(interrupted)))
+                }
+                None => Err(e),
+            },
+        }
+    }
+
+    async fn analyze_file_by_language(
+        &self,
+        file: &SourceFile,
+        scope: &FileScope,
+        gate: Option<&ComplexityGate>,
+    ) -> Result<FileAnalysisResult> {
+        scope.check()?;
+        let mut result = match file.language {
+            Language::Rust => self.rust_analyzer.analyze_within(file, scope).await?,
+            Language::JavaScript => self.javascript_analyzer.analyze_within(file, scope).await?,
+            Language::TypeScript => self.typescript_analyzer.analyze_within(file, scope).await?,
+            Language::Python => self.python_analyzer.analyze_within(file, scope).await?,
+            _ => self.analyze_generic_file(file, scope).await?,
+        };
+
+        // Secrets turn up in any language and in config files alike
+        if self.security_enabled {
+            scope.check()?;
+            result.security_findings.extend(self.secrets_analyzer.analyze_within(file, scope)?);
+        }
+
+        if let Some(gate) = gate {
+            scope.check()?;
+            result.issues.extend(self.complexity_analyzer.gate_within(file, gate, scope)?);
         }
+
+        Ok(result)
     }
 
-    async fn analyze_generic_file(&self, file: &SourceFile) -> Result<FileAnalysisResult> {
+    /// Result for a file whose analysis stopped early, carrying a single
+    /// issue that says so
+    fn interrupted_result(
+        &self,
+        file: &SourceFile,
+        interrupted: Interrupted,
+        timeout: Option<Duration>,
+        elapsed: Duration,
+    ) -> FileAnalysisResult {
+        let (rule_id, rule_name, message) = match interrupted {
+            Interrupted::TimedOut => (
+                "analysis-timeout",
+                "Timeout",
+                format!(
+                    "Analysis exceeded the {:?} per-file timeout; results for this file are incomplete",
+                    timeout.unwrap_or_default()
+                ),
+            ),
+            Interrupted::Cancelled => (
+                "analysis-cancelled",
+                "Cancelled",
+                "Analysis was cancelled before this file was finished".to_string(),
+            ),
+        };
+        let line_count = file.line_count.max(1);
+
+        FileAnalysisResult {
+            file_id: file.id,
+            issues: vec![AnalysisIssue {
+                id: Uuid::new_v4(),
+                rule_id: rule_id.to_string(),
+                rule_name: rule_name.to_string(),
+                severity: Severity::Warning,
+                category: RuleCategory::Maintainability,
+                message,
+                description: Some("Very large or deeply nested files can exceed the analysis budget; consider splitting them up".to_string()),
+                location: CodeLocation {
+                    file_path: file.path.clone(),
+                    start_line: 1,
+                    start_column: 1,
+                    end_line: line_count,
+                    end_column: 1,
+                    start_byte: 0,
+                    end_byte: file.content.len() as u32,
+                },
+                suggested_fix: None,
+                related_issues: Vec::new(),
+                external_references: Vec::new(),
+            }],
+            metrics: FileMetrics {
+                lines_of_code: 0,
+                comment_lines: 0,
+                blank_lines: 0,
+                cyclomatic_complexity: 0,
+                cognitive_complexity: 0,
+                maintainability_index: 0.0,
+                technical_debt_minutes: 0,
+                duplication_percentage: 0.0,
+                function_count: 0,
+                class_count: 0,
+                interface_count: 0,
+                variable_count: 0,
+                import_count: 0,
+                export_count: 0,
+            },
+            security_findings: Vec::new(),
+            performance_insights: Vec::new(),
+            refactoring_opportunities: Vec::new(),
+            ai_suggestions: Vec::new(),
+            dependencies: Vec::new(),
+            exports: Vec::new(),
+            analysis_duration_ms: elapsed.as_millis() as u64,
+            analyzed_at: Utc::now(),
+        }
+    }
+
+    async fn analyze_generic_file(&self, file: &SourceFile, scope: &FileScope) -> Result<FileAnalysisResult> {
         let start_time = std::time::Instant::now();
 
         // Basic analysis for unsupported languages
-        let metrics = self.calculate_basic_metrics(file);
-        let issues = self.find_generic_issues(file);
+        let metrics = self.calculate_basic_metrics(file, scope)?;
+        let issues = self.find_generic_issues(file, scope)?;
 
         Ok(FileAnalysisResult {
             file_id: file.id,
@@ -86,7 +245,7 @@ impl DefaultCodeAnalyzer {
         })
     }
 
-    fn calculate_basic_metrics(&self, file: &SourceFile) -> FileMetrics {
+    fn calculate_basic_metrics(&self, file: &SourceFile, scope: &FileScope) -> std::result::Result<FileMetrics, Interrupted> {
         let lines: Vec<&str> = file.content.lines().collect();
         let total_lines = lines.len() as u32;
 
@@ -94,7 +253,10 @@ impl DefaultCodeAnalyzer {
         let mut comment_lines = 0;
         let mut blank_lines = 0;
 
-        for line in &lines {
+        for (line_num, line) in lines.iter().enumerate() {
+            if line_num % CHECK_INTERVAL == 0 {
+                scope.check()?;
+            }
             let trimmed = line.trim();
             if trimmed.is_empty() {
                 blank_lines += 1;
@@ -105,7 +267,7 @@ impl DefaultCodeAnalyzer {
             }
         }
 
-        FileMetrics {
+        Ok(FileMetrics {
             lines_of_code: code_lines,
             comment_lines,
             blank_lines,
@@ -120,14 +282,17 @@ impl DefaultCodeAnalyzer {
             variable_count: 0,
             import_count: 0,
             export_count: 0,
-        }
+        })
     }
 
-    fn find_generic_issues(&self, file: &SourceFile) -> Vec<AnalysisIssue> {
+    fn find_generic_issues(&self, file: &SourceFile, scope: &FileScope) -> std::result::Result<Vec<AnalysisIssue>, Interrupted> {
         let mut issues = Vec::new();
 
         // Check for very long lines
         for (line_num, line) in file.content.lines().enumerate() {
+            if line_num % CHECK_INTERVAL == 0 {
+                scope.check()?;
+            }
             if line.len() > 120 {
                 issues.push(AnalysisIssue {
                     id: Uuid::new_v4(),
@@ -155,6 +320,9 @@ impl DefaultCodeAnalyzer {
 
         // Check for potential TODO/FIXME comments
         for (line_num, line) in file.content.lines().enumerate() {
+            if line_num % CHECK_INTERVAL == 0 {
+                scope.check()?;
+            }
             let line_lower = line.to_lowercase();
             if line_lower.contains("todo") || line_lower.contains("fixme") || line_lower.contains("hack") {
                 issues.push(AnalysisIssue {
@@ -181,7 +349,7 @@ impl DefaultCodeAnalyzer {
             }
         }
 
-        issues
+        Ok(issues)
     }
 
     fn calculate_project_health_score(&self, results: &HashMap<PathBuf, FileAnalysisResult>) -> f64 {
@@ -384,19 +552,68 @@ impl DefaultCodeAnalyzer {
 
         recommendations
     }
-}
 
-#[async_trait]
-impl CodeAnalyzer for DefaultCodeAnalyzer {
-    async fn analyze_project(&self, project: &AnalysisProject) -> Result<ProjectAnalysisResult> {
+    /// Digest of everything besides file content that shapes a file's result
+    fn config_fingerprint(&self, project: &AnalysisProject, gate: Option<&ComplexityGate>) -> String {
+        // `Value` objects keep their keys sorted, so maps hash the same on every run
+        let settings = serde_json::json!({
+            "version": env!("CARGO_PKG_VERSION"),
+            "ai_enabled": self.ai_enabled,
+            "security_enabled": self.security_enabled,
+            "analysis_config": &project.configuration.analysis_config,
+            "complexity_gate": gate,
+        });
+        Sha256::digest(settings.to_string().as_bytes()).iter().map(|byte| format!("{:02x}", byte)).collect()
+    }
+
+    /// Analyze every file of `project`, except those whose result in
+    /// `previous` was computed from the same content
+    async fn analyze_project_reusing(
+        &self,
+        project: &AnalysisProject,
+        previous: Option<&ProjectAnalysisResult>,
+        ctx: &AnalysisContext,
+    ) -> Result<ProjectAnalysisResult> {
         let start_time = std::time::Instant::now();
         let mut file_results = HashMap::new();
-
-        // Analyze each file
+        let mut timed_out_files = Vec::new();
+        let mut file_hashes = HashMap::new();
+        let mut reused_files = Vec::new();
+        let gate = project
+            .configuration
+            .analysis_config
+            .complexity_gate
+            .as_ref()
+            .or(self.complexity_gate.as_ref());
+        let config_fingerprint = self.config_fingerprint(project, gate);
+        // Results of another project, or computed under other settings, are never reused
+        let previous = previous.filter(|previous| {
+            previous.project_id == project.id && previous.config_fingerprint == config_fingerprint
+        });
+
+        // Analyze each file; after cancellation the remaining files are
+        // flagged without being analyzed
         for file in &project.files {
-            let result = self.analyze_file_by_language(file).await?;
+            if let Some(cached) = previous.and_then(|previous| reusable_result(previous, file)) {
+                file_hashes.insert(file.relative_path.clone(), file.hash.clone());
+                reused_files.push(file.relative_path.clone());
+                file_results.insert(file.relative_path.clone(), FileAnalysisResult { file_id: file.id, ..cached.clone() });
+                continue;
+            }
+            let (result, interrupted) = self.analyze_file_within(file, ctx, gate).await?;
+            match interrupted {
+                // Incomplete results must be redone next time
+                Some(Interrupted::TimedOut) => timed_out_files.push(file.relative_path.clone()),
+                Some(Interrupted::Cancelled) => {}
+                None => {
+                    file_hashes.insert(file.relative_path.clone(), file.hash.clone());
+                }
+            }
             file_results.insert(file.relative_path.clone(), result);
         }
+        if !reused_files.is_empty() {
+            tracing::debug!("Reused analysis of {} unchanged files", reused_files.len());
+        }
 
         // Calculate project-level metrics
         let overall_health_score = self.calculate_project_health_score(&file_results);
@@ -444,16 +661,42 @@ impl CodeAnalyzer for DefaultCodeAnalyzer {
             project_level_insights,
             recommendations,
             trends: None, // TODO: Implement trend analysis
+            timed_out_files,
+            cancelled: ctx.is_cancelled(),
+            file_hashes,
+            config_fingerprint,
+            reused_files,
             analysis_duration_ms: start_time.elapsed().as_millis() as u64,
             analyzed_at: Utc::now(),
         })
     }
+}
+
+#[async_trait]
+impl CodeAnalyzer for DefaultCodeAnalyzer {
+    async fn analyze_project(&self, project: &AnalysisProject, ctx: &AnalysisContext) -> Result<ProjectAnalysisResult> {
+        self.analyze_project_reusing(project, None, ctx).await
+    }
+
+    async fn analyze_project_incremental(
+        &self,
+        project: &AnalysisProject,
+        previous: &ProjectAnalysisResult,
+        ctx: &AnalysisContext,
+    ) -> Result<ProjectAnalysisResult> {
+        self.analyze_project_reusing(project, Some(previous), ctx).await
+    }
 
-    async fn analyze_file(&self, file: &SourceFile) -> Result<FileAnalysisResult> {
-        self.analyze_file_by_language(file).await
+    async fn analyze_file(&self, file: &SourceFile, ctx: &AnalysisContext) -> Result<FileAnalysisResult> {
+        let (result, _) = self.analyze_file_within(file, ctx, self.complexity_gate.as_ref()).await?;
+        Ok(result)
     }
 
-    async fn analyze_code_snippet(&self, code: &str, language: Language) -> Result<Vec<AnalysisIssue>> {
+    async fn analyze_code_snippet(&self, code: &str, language: Language, ctx: &AnalysisContext) -> Result<Vec<AnalysisIssue>> {
+        let language = match language {
+            Language::Unknown => detect_language(Path::new(""), code.as_bytes()),
+            language => language,
+        };
         // Create a temporary file for analysis
         let temp_file = SourceFile {
             id: Uuid::new_v4(),
@@ -468,16 +711,19 @@ impl CodeAnalyzer for DefaultCodeAnalyzer {
             analysis_results: None,
         };
 
-        let result = self.analyze_file(&temp_file).await?;
+        let result = self.analyze_file(&temp_file, ctx).await?;
         Ok(result.issues)
     }
 
-    async fn get_metrics(&self, project: &AnalysisProject) -> Result<ProjectMetrics> {
+    async fn get_metrics(&self, project: &AnalysisProject, ctx: &AnalysisContext) -> Result<ProjectMetrics> {
         let mut language_distribution = HashMap::new();
         let mut total_lines = 0;
         let mut total_files = 0;
 
         for file in &project.files {
+            if ctx.is_cancelled() {
+                return Err(Interrupted::Cancelled.into());
+            }
             total_files += 1;
             total_lines += file.line_count;
 
@@ -542,28 +788,237 @@ impl CodeAnalyzer for DefaultCodeAnalyzer {
         })
     }
 
-    async fn find_security_issues(&self, project: &AnalysisProject) -> Result<Vec<SecurityFinding>> {
+    async fn find_security_issues(&self, project: &AnalysisProject, ctx: &AnalysisContext) -> Result<Vec<SecurityFinding>> {
         let mut all_findings = Vec::new();
 
+        // Findings gathered before a cancellation are still returned
         for file in &project.files {
-            let result = self.analyze_file(file).await?;
+            if ctx.is_cancelled() {
+                break;
+            }
+            let result = self.analyze_file(file, ctx).await?;
             all_findings.extend(result.security_findings);
         }
 
         Ok(all_findings)
     }
 
-    async fn suggest_refactorings(&self, file: &SourceFile) -> Result<Vec<RefactoringOpportunity>> {
-        let result = self.analyze_file(file).await?;
+    async fn suggest_refactorings(&self, file: &SourceFile, ctx: &AnalysisContext) -> Result<Vec<RefactoringOpportunity>> {
+        let result = self.analyze_file(file, ctx).await?;
         Ok(result.refactoring_opportunities)
     }
 
-    async fn get_ai_suggestions(&self, file: &SourceFile) -> Result<Vec<AISuggestion>> {
+    async fn get_ai_suggestions(&self, file: &SourceFile, ctx: &AnalysisContext) -> Result<Vec<AISuggestion>> {
         if !self.ai_enabled {
             return Ok(Vec::new());
         }
 
-        let result = self.analyze_file(file).await?;
+        let result = self.analyze_file(file, ctx).await?;
         Ok(result.ai_suggestions)
     }
-}
\ No newline at end of file
+}
+
+/// The previous result for `file` if it was completely analyzed from the
+/// same content
+fn reusable_result<'a>(previous: &'a ProjectAnalysisResult, file: &SourceFile) -> Option<&'a FileAnalysisResult> {
+    if file.hash.is_empty() || previous.file_hashes.get(&file.relative_path) != Some(&file.hash) {
+        return None;
+    }
+    previous.file_results.get(&file.relative_path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn source_file(name: &str, content: String) -> SourceFile {
+        source_file_in(name, content, Language::Markdown)
+    }
+
+    fn source_file_in(name: &str, content: String, language: Language) -> SourceFile {
+        SourceFile {
+            id: Uuid::new_v4(),
+            path: PathBuf::from(name),
+            relative_path: PathBuf::from(name),
+            language,
+            line_count: content.lines().count() as u32,
+            size_bytes: content.len() as u64,
+            content,
+            hash: String::new(),
+            last_modified: Utc::now(),
+            analysis_results: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_pathological_file_is_flagged_within_timeout() {
+        // Hundreds of thousands of over-long lines, each producing an issue
+        let content = format!("{}\n", "x".repeat(150)).repeat(200_000);
+        let file = source_file("huge.md", content);
+        let ctx = AnalysisContext::new().with_file_timeout(Some(Duration::from_millis(20)));
+
+        let started = std::time::Instant::now();
+        let result = DefaultCodeAnalyzer::new().analyze_file(&file, &ctx).await.unwrap();
+
+        assert!(started.elapsed() < Duration::from_secs(2), "took {:?}", started.elapsed());
+        assert_eq!(result.issues.len(), 1);
+        assert_eq!(result.issues[0].rule_id, "analysis-timeout");
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_context_flags_file_without_analyzing() {
+        let file = source_file("notes.md", "TODO: write notes\n".to_string());
+        let ctx = AnalysisContext::new();
+        ctx.cancel();
+
+        let result = DefaultCodeAnalyzer::new().analyze_file(&file, &ctx).await.unwrap();
+
+        assert_eq!(result.issues.len(), 1);
+        assert_eq!(result.issues[0].rule_id, "analysis-cancelled");
+    }
+
+    #[tokio::test]
+    async fn test_language_analyzer_observes_file_scope() {
+        let content = "def handler(event):\n    return event\n".repeat(10_000);
+        let file = source_file_in("handlers.py", content, Language::Python);
+        let expired = AnalysisContext::new().with_file_timeout(Some(Duration::ZERO)).file_scope();
+
+        let error = PythonAnalyzer::new().analyze_within(&file, &expired).await.unwrap_err();
+        assert_eq!(error.downcast_ref::<Interrupted>(), Some(&Interrupted::TimedOut));
+
+        let result = PythonAnalyzer::new().analyze_within(&file, &FileScope::unbounded()).await.unwrap();
+        assert_eq!(result.file_id, file.id);
+    }
+
+    const GO_SOURCE: &str = r#"package main
+
+// if this comment counted, simple would look complex: if if if
+func simple(a int) int {
+	return a + 1
+}
+
+func classify(n int, verbose bool) string {
+	label := "if else for"
+	if n < 0 && verbose {
+		label = "negative"
+	} else if n == 0 {
+		label = "zero"
+	} else {
+		for i := 0; i < n; i++ {
+			if i%2 == 0 || i%3 == 0 {
+				label = "composite"
+			}
+		}
+	}
+	switch label {
+	case "zero":
+		return label
+	case "negative":
+		return "-"
+	}
+	return label
+}
+"#;
+
+    #[test]
+    fn test_function_complexity() {
+        let file = source_file_in("main.go", GO_SOURCE.to_string(), Language::Go);
+        let functions = ComplexityAnalyzer::new().functions(&file);
+
+        let names: Vec<&str> = functions.iter().map(|f| f.name.as_str()).collect();
+        assert_eq!(names, ["simple", "classify"]);
+        assert_eq!((functions[0].cyclomatic, functions[0].cognitive), (1, 0));
+        // if, &&, else if, for, if, ||, and two cases
+        assert_eq!(functions[1].cyclomatic, 9);
+        // if +1, && +1, else if +1, else +1, for +2, if +3, || +1, switch +1
+        assert_eq!(functions[1].cognitive, 11);
+        assert_eq!((functions[1].start_line, functions[1].end_line), (8, 28));
+    }
+
+    #[tokio::test]
+    async fn test_complexity_gate_flags_offending_function() {
+        let file = source_file_in("main.go", GO_SOURCE.to_string(), Language::Go);
+        let gate = ComplexityGate {
+            languages: HashMap::from([(
+                Language::Go,
+                crate::ComplexityLimits { max_cyclomatic: Some(5), max_cognitive: None },
+            )]),
+            default_limits: None,
+            severity: Severity::Error,
+        };
+
+        let result = DefaultCodeAnalyzer::new()
+            .with_complexity_gate(gate.clone())
+            .analyze_file(&file, &AnalysisContext::new())
+            .await
+            .unwrap();
+        let gated: Vec<&AnalysisIssue> = result.issues.iter().filter(|i| i.rule_id == "cyclomatic-complexity").collect();
+        assert_eq!(gated.len(), 1);
+        assert!(matches!(gated[0].severity, Severity::Error));
+        assert!(matches!(gated[0].category, RuleCategory::Maintainability));
+        assert!(gated[0].message.contains("`classify`"));
+        assert_eq!(gated[0].location.start_line, 8);
+
+        // Languages without limits are not gated
+        let python = source_file_in("main.py", "def f(x):\n    return x\n".to_string(), Language::Python);
+        let issues = ComplexityAnalyzer::new().gate_within(&python, &gate, &FileScope::unbounded()).unwrap();
+        assert!(issues.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_incremental_analysis_only_reanalyzes_changed_files() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("a.md"), "# A\n").unwrap();
+        std::fs::write(dir.path().join("b.md"), "# B\n").unwrap();
+        std::fs::write(dir.path().join("c.md"), "# C\n").unwrap();
+        let analyzer = DefaultCodeAnalyzer::new();
+        let ctx = AnalysisContext::new();
+
+        let first = analyzer.analyze_project(&crate::import_project(dir.path()).unwrap(), &ctx).await.unwrap();
+        assert_eq!(first.file_hashes.len(), 3);
+        assert!(first.reused_files.is_empty());
+        assert_eq!(first.total_issues, 0);
+
+        std::fs::write(dir.path().join("c.md"), "# C\nTODO: finish\n").unwrap();
+        let project = crate::import_project(dir.path()).unwrap();
+        let second = analyzer.analyze_project_incremental(&project, &first, &ctx).await.unwrap();
+
+        let mut reused = second.reused_files.clone();
+        reused.sort();
+        assert_eq!(reused, [PathBuf::from("a.md"), PathBuf::from("b.md")]);
+        let a = PathBuf::from("a.md");
+        assert_eq!(second.file_results[&a].analyzed_at, first.file_results[&a].analyzed_at);
+        assert_eq!(second.file_results[&PathBuf::from("c.md")].issues[0].rule_id, "todo-comment");
+
+        // Aggregates come from the merged results, as a full run would compute them
+        let full = analyzer.analyze_project(&project, &ctx).await.unwrap();
+        assert_eq!(second.total_issues, 1);
+        assert_eq!(second.overall_health_score, full.overall_health_score);
+        assert!(second.overall_health_score < first.overall_health_score);
+    }
+
+    #[tokio::test]
+    async fn test_incremental_analysis_redoes_files_when_settings_change() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("a.md"), "# A\nTODO: finish\n").unwrap();
+        let ctx = AnalysisContext::new();
+        let mut project = crate::import_project(dir.path()).unwrap();
+
+        let first = DefaultCodeAnalyzer::new().analyze_project(&project, &ctx).await.unwrap();
+        assert!(!first.config_fingerprint.is_empty());
+
+        // Unchanged content under other analyzer settings is analyzed again
+        let gated = DefaultCodeAnalyzer::new().with_complexity_gate(ComplexityGate::default());
+        let second = gated.analyze_project_incremental(&project, &first, &ctx).await.unwrap();
+        assert!(second.reused_files.is_empty());
+        assert_ne!(second.config_fingerprint, first.config_fingerprint);
+
+        project.configuration.analysis_config.disabled_rules.push("todo-comment".to_string());
+        let third = DefaultCodeAnalyzer::new().analyze_project_incremental(&project, &first, &ctx).await.unwrap();
+        assert!(third.reused_files.is_empty());
+
+        // The same settings still reuse
+        let fourth = DefaultCodeAnalyzer::new().analyze_project_incremental(&project, &third, &ctx).await.unwrap();
+        assert_eq!(fourth.reused_files, [PathBuf::from("a.md")]);
+    }
+}
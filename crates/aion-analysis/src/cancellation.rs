@@ -0,0 +1,131 @@
+use std::fmt;
+use std::time::{Duration, Instant};
+use tokio_util::sync::CancellationToken;
+
+/// Per-file time budget used when none is configured
+pub const DEFAULT_FILE_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Cancellation and time limits for one analysis run
+///
+/// Cancelling the context stops the run as soon as every analyzer reaches its
+/// next check; files that did not finish are reported as incomplete instead
+/// of failing the whole run.
+#[derive(Debug, Clone)]
+pub struct AnalysisContext {
+    token: CancellationToken,
+    file_timeout: Option<Duration>,
+}
+
+impl AnalysisContext {
+    pub fn new() -> Self {
+        Self {
+            token: CancellationToken::new(),
+            file_timeout: Some(DEFAULT_FILE_TIMEOUT),
+        }
+    }
+
+    /// Time budget for each file; `None` lets files run until cancelled
+    pub fn with_file_timeout(mut self, file_timeout: Option<Duration>) -> Self {
+        self.file_timeout = file_timeout;
+        self
+    }
+
+    /// Observe an existing token, e.g. one cancelled when a request is aborted
+    pub fn with_cancellation(mut self, token: CancellationToken) -> Self {
+        self.token = token;
+        self
+    }
+
+    pub fn file_timeout(&self) -> Option<Duration> {
+        self.file_timeout
+    }
+
+    pub fn cancellation_token(&self) -> &CancellationToken {
+        &self.token
+    }
+
+    pub fn cancel(&self) {
+        self.token.cancel();
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.token.is_cancelled()
+    }
+
+    /// Start the clock for analyzing one file
+    pub fn file_scope(&self) -> FileScope {
+        FileScope {
+            token: self.token.child_token(),
+            deadline: self.file_timeout.map(|timeout| Instant::now() + timeout),
+        }
+    }
+}
+
+impl Default for AnalysisContext {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Why a file's analysis stopped early
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Interrupted {
+    Cancelled,
+    TimedOut,
+}
+
+impl fmt::Display for Interrupted {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Interrupted::Cancelled => f.write_str("analysis cancelled"),
+            Interrupted::TimedOut => f.write_str("analysis timed out"),
+        }
+    }
+}
+
+impl std::error::Error for Interrupted {}
+
+/// Cancellation and deadline for analyzing a single file
+///
+/// Synchronous analysis steps call [`FileScope::check`] regularly; async steps
+/// can race against [`FileScope::interrupted`].
+#[derive(Debug, Clone)]
+pub struct FileScope {
+    token: CancellationToken,
+    deadline: Option<Instant>,
+}
+
+impl FileScope {
+    /// A scope that never interrupts
+    pub fn unbounded() -> Self {
+        Self {
+            token: CancellationToken::new(),
+            deadline: None,
+        }
+    }
+
+    /// Fail once the run is cancelled or the file's time is up
+    pub fn check(&self) -> std::result::Result<(), Interrupted> {
+        if self.token.is_cancelled() {
+            return Err(Interrupted::Cancelled);
+        }
+        if self.deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+            return Err(Interrupted::TimedOut);
+        }
+        Ok(())
+    }
+
+    /// Resolves when the run is cancelled or the file's time is up
+    pub async fn interrupted(&self) -> Interrupted {
+        match self.deadline {
+            Some(deadline) => tokio::select! {
+                _ = self.token.cancelled() => Interrupted::Cancelled,
+                _ = tokio::time::sleep_until(tokio::time::Instant::from_std(deadline)) => Interrupted::TimedOut,
+            },
+            None => {
+                self.token.cancelled().await;
+                Interrupted::Cancelled
+            }
+        }
+    }
+}
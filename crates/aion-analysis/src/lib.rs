@@ -8,6 +8,12 @@ pub mod performance;
 pub mod patterns;
 pub mod suggestions;
 pub mod ai;
+pub mod sbom;
+pub mod advisories;
+pub mod cancellation;
+pub mod project_import;
+pub mod review;
+pub mod refactoring_engine;
 
 pub use ast::*;
 pub use analyzer::*;
@@ -19,6 +25,12 @@ pub use performance::*;
 pub use patterns::*;
 pub use suggestions::*;
 pub use ai::*;
+pub use sbom::*;
+pub use advisories::*;
+pub use cancellation::*;
+pub use project_import::*;
+pub use review::*;
+pub use refactoring_engine::*;
 
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
@@ -26,7 +38,7 @@ use std::path::PathBuf;
 use uuid::Uuid;
 use chrono::{DateTime, Utc};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum Language {
     Rust,
     JavaScript,
@@ -85,6 +97,39 @@ pub struct Dependency {
     pub vulnerabilities: Vec<SecurityVulnerability>,
     pub license: Option<String>,
     pub size: Option<u64>,
+    /// Declared by the project itself rather than pulled in by another dependency
+    #[serde(default = "default_direct")]
+    pub direct: bool,
+    /// Dependencies this one requires, as `name` or `name@version`
+    #[serde(default)]
+    pub depends_on: Vec<String>,
+    /// Checksums of the resolved artifact, e.g. from a lockfile
+    #[serde(default)]
+    pub hashes: Vec<DependencyHash>,
+}
+
+fn default_direct() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DependencyHash {
+    pub algorithm: HashAlgorithm,
+    /// Hex-encoded digest
+    pub value: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum HashAlgorithm {
+    Md5,
+    Sha1,
+    Sha256,
+    Sha384,
+    Sha512,
+    Sha3_256,
+    Sha3_512,
+    Blake2b256,
+    Blake3,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -124,6 +169,47 @@ pub struct AnalysisConfiguration {
     pub security_analysis_enabled: bool,
     pub performance_analysis_enabled: bool,
     pub refactoring_suggestions_enabled: bool,
+    /// Fail analysis of functions that are too complex; `None` disables the gate
+    #[serde(default)]
+    pub complexity_gate: Option<ComplexityGate>,
+}
+
+/// Maximum function complexity, reported as issues of the configured
+/// severity so CI can block merges that exceed it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ComplexityGate {
+    /// Limits for specific languages
+    #[serde(default)]
+    pub languages: HashMap<Language, ComplexityLimits>,
+    /// Limits for languages not listed; `None` leaves them ungated
+    pub default_limits: Option<ComplexityLimits>,
+    pub severity: Severity,
+}
+
+impl ComplexityGate {
+    pub fn limits_for(&self, language: &Language) -> Option<&ComplexityLimits> {
+        self.languages.get(language).or(self.default_limits.as_ref())
+    }
+}
+
+impl Default for ComplexityGate {
+    fn default() -> Self {
+        Self {
+            languages: HashMap::new(),
+            default_limits: Some(ComplexityLimits {
+                max_cyclomatic: Some(15),
+                max_cognitive: Some(15),
+            }),
+            severity: Severity::Error,
+        }
+    }
+}
+
+/// Per-function limits; an unset limit is not checked
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ComplexityLimits {
+    pub max_cyclomatic: Option<u32>,
+    pub max_cognitive: Option<u32>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -412,6 +498,9 @@ pub struct RefactoringOpportunity {
     pub risks: Vec<String>,
     pub suggested_approach: Vec<RefactoringStep>,
     pub automation_available: bool,
+    /// Name introduced by a rename or extraction, if the opportunity proposes one
+    #[serde(default)]
+    pub new_name: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -530,15 +619,28 @@ pub enum Visibility {
 
 pub type Result<T> = std::result::Result<T, Box<dyn std::error::Error + Send + Sync>>;
 
+/// Analyzes projects and files
+///
+/// Every method observes the cancellation token and per-file timeout of its
+/// [`AnalysisContext`]. Files whose analysis is cut short are returned with an
+/// `analysis-timeout` or `analysis-cancelled` issue instead of blocking.
 #[async_trait::async_trait]
 pub trait CodeAnalyzer {
-    async fn analyze_project(&self, project: &AnalysisProject) -> Result<ProjectAnalysisResult>;
-    async fn analyze_file(&self, file: &SourceFile) -> Result<FileAnalysisResult>;
-    async fn analyze_code_snippet(&self, code: &str, language: Language) -> Result<Vec<AnalysisIssue>>;
-    async fn get_metrics(&self, project: &AnalysisProject) -> Result<ProjectMetrics>;
-    async fn find_security_issues(&self, project: &AnalysisProject) -> Result<Vec<SecurityFinding>>;
-    async fn suggest_refactorings(&self, file: &SourceFile) -> Result<Vec<RefactoringOpportunity>>;
-    async fn get_ai_suggestions(&self, file: &SourceFile) -> Result<Vec<AISuggestion>>;
+    async fn analyze_project(&self, project: &AnalysisProject, ctx: &AnalysisContext) -> Result<ProjectAnalysisResult>;
+    /// Like `analyze_project`, reusing `previous` results for files whose hash
+    /// is unchanged; project-level scores are recomputed from the merged results
+    async fn analyze_project_incremental(
+        &self,
+        project: &AnalysisProject,
+        previous: &ProjectAnalysisResult,
+        ctx: &AnalysisContext,
+    ) -> Result<ProjectAnalysisResult>;
+    async fn analyze_file(&self, file: &SourceFile, ctx: &AnalysisContext) -> Result<FileAnalysisResult>;
+    async fn analyze_code_snippet(&self, code: &str, language: Language, ctx: &AnalysisContext) -> Result<Vec<AnalysisIssue>>;
+    async fn get_metrics(&self, project: &AnalysisProject, ctx: &AnalysisContext) -> Result<ProjectMetrics>;
+    async fn find_security_issues(&self, project: &AnalysisProject, ctx: &AnalysisContext) -> Result<Vec<SecurityFinding>>;
+    async fn suggest_refactorings(&self, file: &SourceFile, ctx: &AnalysisContext) -> Result<Vec<RefactoringOpportunity>>;
+    async fn get_ai_suggestions(&self, file: &SourceFile, ctx: &AnalysisContext) -> Result<Vec<AISuggestion>>;
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -555,6 +657,22 @@ pub struct ProjectAnalysisResult {
     pub project_level_insights: Vec<ProjectInsight>,
     pub recommendations: Vec<ProjectRecommendation>,
     pub trends: Option<AnalysisTrends>,
+    /// Files whose analysis hit the per-file timeout
+    #[serde(default)]
+    pub timed_out_files: Vec<PathBuf>,
+    /// The run was cancelled before every file was analyzed
+    #[serde(default)]
+    pub cancelled: bool,
+    /// Content hash of each completely analyzed file, for incremental runs
+    #[serde(default)]
+    pub file_hashes: HashMap<PathBuf, String>,
+    /// Digest of the analyzer settings `file_hashes` results were computed
+    /// with; results are only reused under the same settings
+    #[serde(default)]
+    pub config_fingerprint: String,
+    /// Files whose results were carried over from a previous run unchanged
+    #[serde(default)]
+    pub reused_files: Vec<PathBuf>,
     pub analysis_duration_ms: u64,
     pub analyzed_at: DateTime<Utc>,
 }
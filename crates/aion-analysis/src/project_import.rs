@@ -0,0 +1,1180 @@
+use crate::{
+    AnalysisConfiguration, AnalysisProject, ComplexityMetrics, Dependency, DependencyHash, DependencySource,
+    DependencyType, HashAlgorithm, Language, ProjectConfiguration, ProjectMetadata, Result, SourceFile,
+};
+use base64::Engine;
+use chrono::{DateTime, Utc};
+use rayon::prelude::*;
+use sha2::{Digest, Sha256};
+use regex::Regex;
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use uuid::Uuid;
+
+/// Bytes inspected when deciding whether a file is binary
+const BINARY_SNIFF_BYTES: usize = 8192;
+
+/// Signature score content needs before its language is trusted
+const MIN_SIGNATURE_SCORE: u32 = 3;
+
+/// Extensions that leave a file's language open: none at all, plain text,
+/// and headers shared by C and C++
+const AMBIGUOUS_EXTENSIONS: &[&str] = &["", "txt", "in", "h"];
+
+/// Options for [`import_project_with`]
+#[derive(Debug, Clone)]
+pub struct ImportOptions {
+    /// Larger files are skipped, which also keeps generated bundles out
+    pub max_file_size: u64,
+    pub include_hidden: bool,
+    pub follow_links: bool,
+}
+
+impl Default for ImportOptions {
+    fn default() -> Self {
+        Self {
+            max_file_size: 1024 * 1024,
+            include_hidden: false,
+            follow_links: false,
+        }
+    }
+}
+
+/// Build an [`AnalysisProject`] from an existing codebase
+///
+/// See [`import_project_with`].
+pub fn import_project(path: impl AsRef<Path>) -> Result<AnalysisProject> {
+    import_project_with(path, &ImportOptions::default())
+}
+
+/// Build an [`AnalysisProject`] from an existing codebase
+///
+/// The directory is walked respecting `.gitignore`, binary and oversized
+/// files are skipped, and the remaining source files are read in parallel.
+/// Dependencies come from every `Cargo.toml`, `package.json`,
+/// `requirements*.txt` and `go.mod` in the tree, so mixed-language repos and
+/// monorepos are covered. A `Cargo.lock` or `package-lock.json` next to them
+/// resolves declared versions, adds the transitive dependencies (marked as not
+/// direct), and fills in the dependency graph and artifact checksums. Project
+/// and file ids are derived from paths, so importing an unchanged tree again
+/// yields an equivalent project.
+pub fn import_project_with(path: impl AsRef<Path>, options: &ImportOptions) -> Result<AnalysisProject> {
+    let root = path.as_ref().canonicalize()?;
+    if !root.is_dir() {
+        return Err(format!("{} is not a directory", root.display()).into());
+    }
+
+    let project_id = Uuid::new_v5(&Uuid::NAMESPACE_URL, format!("file://{}", root.display()).as_bytes());
+    let (candidates, manifests) = walk(&root, options)?;
+
+    let mut files: Vec<SourceFile> = candidates
+        .par_iter()
+        .filter_map(|path| match read_source_file(&root, path, project_id) {
+            Ok(file) => file,
+            Err(e) => {
+                tracing::warn!("Skipping {}: {}", path.display(), e);
+                None
+            }
+        })
+        .collect();
+    files.sort_by(|a, b| a.relative_path.cmp(&b.relative_path));
+
+    let mut declared: HashMap<&'static str, Vec<Dependency>> = HashMap::new();
+    for manifest in &manifests {
+        match parse_manifest(manifest) {
+            Ok(parsed) => declared.entry(ecosystem_for(manifest)).or_default().extend(parsed),
+            Err(e) => tracing::warn!("Cannot parse {}: {}", manifest.display(), e),
+        }
+    }
+    for lockfile in &manifests {
+        match parse_lockfile(lockfile) {
+            Ok(Some(locked)) => apply_lockfile(declared.entry(ecosystem_for(lockfile)).or_default(), locked),
+            Ok(None) => {}
+            Err(e) => tracing::warn!("Cannot parse {}: {}", lockfile.display(), e),
+        }
+    }
+
+    let mut ecosystems: Vec<_> = declared.into_iter().collect();
+    ecosystems.sort_by_key(|(ecosystem, _)| *ecosystem);
+    let dependencies = merge_duplicates(ecosystems.into_iter().flat_map(|(_, dependencies)| dependencies));
+
+    let build_systems = detect_build_systems(&manifests);
+    let metadata = project_metadata(&files);
+    let language = primary_language(&files);
+    let name = root
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "project".to_string());
+
+    Ok(AnalysisProject {
+        id: project_id,
+        name,
+        language: language.clone(),
+        configuration: ProjectConfiguration {
+            target_language_version: None,
+            build_system: (!build_systems.is_empty())
+                .then(|| build_systems.iter().cloned().collect::<Vec<_>>().join(", ")),
+            test_framework: detect_test_framework(&language, &dependencies),
+            linting_rules: HashMap::new(),
+            formatting_config: HashMap::new(),
+            analysis_config: AnalysisConfiguration {
+                enabled_analyzers: Vec::new(),
+                disabled_rules: Vec::new(),
+                severity_levels: HashMap::new(),
+                custom_rules: Vec::new(),
+                ai_analysis_enabled: false,
+                security_analysis_enabled: true,
+                performance_analysis_enabled: true,
+                refactoring_suggestions_enabled: true,
+                complexity_gate: None,
+            },
+        },
+        root_path: root,
+        files,
+        dependencies,
+        metadata,
+        created_at: Utc::now(),
+        last_analyzed: None,
+    })
+}
+
+/// Collect source file candidates and dependency manifests
+fn walk(root: &Path, options: &ImportOptions) -> Result<(Vec<PathBuf>, Vec<PathBuf>)> {
+    let mut candidates = Vec::new();
+    let mut manifests = Vec::new();
+
+    let walker = ignore::WalkBuilder::new(root)
+        .hidden(!options.include_hidden)
+        .follow_links(options.follow_links)
+        .git_ignore(true)
+        .git_exclude(true)
+        .git_global(false)
+        // Honour .gitignore even when the tree isn't a git checkout
+        .require_git(false)
+        .build();
+
+    for entry in walker {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(e) => {
+                tracing::warn!("Skipping unreadable entry: {}", e);
+                continue;
+            }
+        };
+        if !entry.file_type().is_some_and(|file_type| file_type.is_file()) {
+            continue;
+        }
+        let path = entry.into_path();
+        if is_manifest(&path) {
+            manifests.push(path.clone());
+        }
+        // Files without a telling extension are kept for content detection
+        if language_for_path(&path) == Language::Unknown && !has_ambiguous_extension(&path) {
+            continue;
+        }
+        match std::fs::metadata(&path) {
+            Ok(metadata) if metadata.len() <= options.max_file_size => candidates.push(path),
+            Ok(_) => tracing::debug!("Skipping oversized file {}", path.display()),
+            Err(e) => tracing::warn!("Skipping {}: {}", path.display(), e),
+        }
+    }
+
+    manifests.sort();
+    Ok((candidates, manifests))
+}
+
+/// Read one file, or `None` for binary content and files whose language
+/// cannot be determined
+fn read_source_file(root: &Path, path: &Path, project_id: Uuid) -> Result<Option<SourceFile>> {
+    let bytes = std::fs::read(path)?;
+    if bytes.iter().take(BINARY_SNIFF_BYTES).any(|&byte| byte == 0) {
+        return Ok(None);
+    }
+    let language = detect_language(path, &bytes);
+    if language == Language::Unknown {
+        return Ok(None);
+    }
+    let Ok(content) = String::from_utf8(bytes) else {
+        return Ok(None);
+    };
+
+    let relative_path = path.strip_prefix(root).unwrap_or(path).to_path_buf();
+    // Forward slashes keep ids identical across platforms
+    let id_key = relative_path.to_string_lossy().replace('\\', "/");
+    let last_modified = std::fs::metadata(path)?
+        .modified()
+        .map(DateTime::<Utc>::from)
+        .unwrap_or_else(|_| Utc::now());
+
+    Ok(Some(SourceFile {
+        id: Uuid::new_v5(&project_id, id_key.as_bytes()),
+        path: path.to_path_buf(),
+        language,
+        size_bytes: content.len() as u64,
+        line_count: content.lines().count() as u32,
+        hash: hex_sha256(content.as_bytes()),
+        last_modified,
+        analysis_results: None,
+        relative_path,
+        content,
+    }))
+}
+
+fn hex_sha256(bytes: &[u8]) -> String {
+    Sha256::digest(bytes).iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Language of a file from its name alone; see [`detect_language`] for
+/// files whose name does not tell
+pub fn language_for_path(path: &Path) -> Language {
+    let file_name = path.file_name().and_then(|name| name.to_str()).unwrap_or_default();
+    if matches!(file_name, "Dockerfile" | "Makefile") {
+        return Language::Shell;
+    }
+    let extension = path.extension().and_then(|ext| ext.to_str()).unwrap_or_default().to_ascii_lowercase();
+    match extension.as_str() {
+        "rs" => Language::Rust,
+        "js" | "jsx" | "mjs" | "cjs" => Language::JavaScript,
+        "ts" | "tsx" | "mts" | "cts" => Language::TypeScript,
+        "py" | "pyi" => Language::Python,
+        "go" => Language::Go,
+        "java" => Language::Java,
+        "cpp" | "cc" | "cxx" | "hpp" | "hh" | "hxx" => Language::CPlusPlus,
+        "c" | "h" => Language::C,
+        "cs" => Language::CSharp,
+        "sql" => Language::SQL,
+        "html" | "htm" => Language::HTML,
+        "css" | "scss" => Language::CSS,
+        "json" => Language::JSON,
+        "yaml" | "yml" => Language::YAML,
+        "toml" => Language::TOML,
+        "md" | "markdown" => Language::Markdown,
+        "sh" | "bash" | "zsh" => Language::Shell,
+        _ => Language::Unknown,
+    }
+}
+
+/// Language of a file from its name and content
+///
+/// The extension decides unless it is missing or ambiguous (see
+/// `AMBIGUOUS_EXTENSIONS`). Otherwise a shebang line names the interpreter,
+/// and failing that the content is scored against signatures of each
+/// language, such as `fn main` for Rust or `package main` for Go. Binary
+/// content and text matching no language well enough are `Unknown`, apart
+/// from `.h` headers, which stay C unless they look like C++.
+pub fn detect_language(path: &Path, content: &[u8]) -> Language {
+    let by_name = language_for_path(path);
+    if by_name != Language::Unknown && !has_ambiguous_extension(path) {
+        return by_name;
+    }
+    let head = &content[..content.len().min(BINARY_SNIFF_BYTES)];
+    if head.contains(&0) {
+        return by_name;
+    }
+    let text = String::from_utf8_lossy(head);
+
+    if let Some(language) = shebang_language(&text) {
+        return language;
+    }
+    if by_name == Language::C {
+        return match signature_language(&text) {
+            Some(Language::CPlusPlus) => Language::CPlusPlus,
+            _ => Language::C,
+        };
+    }
+    if looks_like_json(&text) {
+        return Language::JSON;
+    }
+    signature_language(&text).unwrap_or(by_name)
+}
+
+fn has_ambiguous_extension(path: &Path) -> bool {
+    let extension = path.extension().and_then(|ext| ext.to_str()).unwrap_or_default().to_ascii_lowercase();
+    AMBIGUOUS_EXTENSIONS.contains(&extension.as_str())
+}
+
+/// Language of the interpreter named by a `#!` line
+fn shebang_language(text: &str) -> Option<Language> {
+    let line = text.lines().next()?.strip_prefix("#!")?;
+    // `#![...]` opens a Rust inner attribute, not a shebang
+    if line.starts_with('[') {
+        return None;
+    }
+    let mut words = line.split_whitespace();
+    let mut interpreter = words.next()?.rsplit('/').next()?;
+    if interpreter == "env" {
+        interpreter = words.find(|word| !word.starts_with('-') && !word.contains('='))?;
+    }
+    let name = interpreter.trim_end_matches(|c: char| c.is_ascii_digit() || c == '.');
+    match name {
+        "sh" | "bash" | "zsh" | "dash" | "ksh" | "ash" | "fish" => Some(Language::Shell),
+        "python" | "pypy" => Some(Language::Python),
+        "node" | "nodejs" | "bun" => Some(Language::JavaScript),
+        "deno" | "ts-node" | "tsx" => Some(Language::TypeScript),
+        "rust-script" | "cargo" => Some(Language::Rust),
+        _ => None,
+    }
+}
+
+fn looks_like_json(text: &str) -> bool {
+    let trimmed = text.trim();
+    (trimmed.starts_with('{') || trimmed.starts_with('['))
+        && serde_json::from_str::<serde_json::Value>(trimmed).is_ok()
+}
+
+/// Best-scoring language by content signatures, if any scores enough;
+/// earlier entries win ties, so C beats C++ unless C++ features show up
+fn signature_language(text: &str) -> Option<Language> {
+    let mut best: Option<(Language, u32)> = None;
+    for (language, signatures) in language_signatures() {
+        let score: u32 = signatures
+            .iter()
+            .filter(|(pattern, _)| pattern.is_match(text))
+            .map(|(_, weight)| weight)
+            .sum();
+        let beats_best = !matches!(best, Some((_, best_score)) if score <= best_score);
+        if score >= MIN_SIGNATURE_SCORE && beats_best {
+            best = Some((language.clone(), score));
+        }
+    }
+    best.map(|(language, _)| language)
+}
+
+type Signatures = Vec<(Language, Vec<(Regex, u32)>)>;
+
+fn language_signatures() -> &'static Signatures {
+    static SIGNATURES: OnceLock<Signatures> = OnceLock::new();
+    SIGNATURES.get_or_init(|| {
+        let table: [(Language, &[(&str, u32)]); 12] = [
+            (Language::Rust, &[
+                (r"(?m)^\s*(pub(\([^)]*\))?\s+)?(async\s+)?fn\s+\w+\s*[<(]", 2),
+                (r"(?m)^\s*use\s+(std|crate|super|self)::", 3),
+                (r"(?m)^\s*impl\b", 2),
+                (r"\blet\s+mut\b", 2),
+                (r"#\[derive\(", 3),
+                (r"\b(println|format|vec)!\s*[(\[]", 2),
+            ]),
+            (Language::Go, &[
+                (r"(?m)^package\s+\w+\s*$", 3),
+                (r"(?m)^func\s+(\([^)]*\)\s*)?\w+\s*\(", 3),
+                (r"(?m)^import\s+(\(|\x22)", 2),
+                (r"\bfmt\.\w+\(", 2),
+            ]),
+            (Language::C, &[
+                (r#"(?m)^\s*#\s*include\s*[<"]"#, 3),
+                (r"(?m)^\s*#\s*define\s+\w+", 2),
+                (r"\bint\s+main\s*\(", 2),
+                (r"\b(printf|malloc|free)\s*\(", 1),
+            ]),
+            (Language::CPlusPlus, &[
+                (r#"(?m)^\s*#\s*include\s*[<"]"#, 3),
+                (r"\bint\s+main\s*\(", 2),
+                (r"\bstd::", 2),
+                (r"(?m)^\s*namespace\s+\w+\s*\{", 2),
+                (r"\btemplate\s*<", 2),
+                (r"(?m)^\s*class\s+\w+[^;]*\{", 1),
+            ]),
+            (Language::Java, &[
+                (r"(?m)^package\s+[\w.]+;", 3),
+                (r"(?m)^import\s+java\.", 3),
+                (r"(?m)^\s*public\s+(final\s+|abstract\s+)?class\s+\w+", 3),
+                (r"\bSystem\.out\.print", 3),
+            ]),
+            (Language::CSharp, &[
+                (r"(?m)^using\s+System[\w.]*;", 3),
+                (r"(?m)^\s*namespace\s+[\w.]+", 1),
+                (r"\bConsole\.Write", 3),
+                (r"\bstatic\s+(async\s+)?\w+\s+Main\s*\(", 3),
+            ]),
+            (Language::Python, &[
+                (r"(?m)^\s*(async\s+)?def\s+\w+\s*\(.*\)\s*(->\s*[^:]+)?:\s*$", 3),
+                (r"(?m)^\s*(import\s+\w+|from\s+[\w.]+\s+import\s)", 2),
+                (r"\bif\s+__name__\s*==", 3),
+                (r"(?m)^\s*class\s+\w+(\([^)]*\))?:\s*$", 2),
+                (r"\bself\.\w+", 1),
+            ]),
+            (Language::JavaScript, &[
+                (r"\bfunction\s*\*?\s*\w*\s*\(", 2),
+                (r"\brequire\(['\x22]", 3),
+                (r"\bmodule\.exports\b", 3),
+                (r"\bconsole\.\w+\(", 2),
+                (r"(?m)^\s*(const|let|var)\s+\w+\s*=", 1),
+            ]),
+            (Language::TypeScript, &[
+                (r"(?m)^\s*(export\s+)?interface\s+\w+", 3),
+                (r"(?m)^\s*(export\s+)?type\s+\w+\s*=", 3),
+                (r":\s*(string|number|boolean|void)\b", 2),
+                (r"\bconsole\.\w+\(", 2),
+            ]),
+            (Language::Shell, &[
+                (r"(?m)^\s*set\s+-[a-z]+", 3),
+                (r"(?m)^\s*(fi|esac|done)\s*$", 2),
+                (r"(?m)^\s*if\s+\[\[?\s", 2),
+                (r"(?m)^\s*echo\s", 2),
+                (r"(?m)^\s*export\s+\w+=", 2),
+            ]),
+            (Language::SQL, &[
+                (r"(?i)\bcreate\s+(table|index|view)\b", 3),
+                (r"(?i)\bselect\s[^;]+\sfrom\s", 3),
+                (r"(?i)\binsert\s+into\b", 3),
+            ]),
+            (Language::HTML, &[
+                (r"(?i)<!doctype\s+html", 4),
+                (r"(?i)<html[\s>]", 3),
+            ]),
+        ];
+        table
+            .into_iter()
+            .map(|(language, patterns)| {
+                let patterns = patterns
+                    .iter()
+                    .map(|(pattern, weight)| (Regex::new(pattern).expect("valid language signature"), *weight))
+                    .collect();
+                (language, patterns)
+            })
+            .collect()
+    })
+}
+
+fn is_programming_language(language: &Language) -> bool {
+    !matches!(
+        language,
+        Language::JSON | Language::YAML | Language::TOML | Language::Markdown | Language::Unknown
+    )
+}
+
+/// Language with the most lines of code, ignoring data and docs formats
+fn primary_language(files: &[SourceFile]) -> Language {
+    let mut lines: HashMap<Language, u64> = HashMap::new();
+    for file in files.iter().filter(|file| is_programming_language(&file.language)) {
+        *lines.entry(file.language.clone()).or_default() += file.line_count as u64;
+    }
+    // Ties are broken by name so the result is deterministic
+    lines
+        .into_iter()
+        .max_by(|(a, a_lines), (b, b_lines)| {
+            a_lines.cmp(b_lines).then_with(|| format!("{:?}", b).cmp(&format!("{:?}", a)))
+        })
+        .map(|(language, _)| language)
+        .unwrap_or(Language::Unknown)
+}
+
+fn project_metadata(files: &[SourceFile]) -> ProjectMetadata {
+    // Distribution counts files per language
+    let mut language_distribution = HashMap::new();
+    for file in files {
+        *language_distribution.entry(file.language.clone()).or_insert(0) += 1;
+    }
+
+    ProjectMetadata {
+        total_files: files.len() as u32,
+        total_lines: files.iter().map(|file| file.line_count).sum(),
+        total_size_bytes: files.iter().map(|file| file.size_bytes).sum(),
+        language_distribution,
+        complexity_metrics: ComplexityMetrics {
+            average_cyclomatic_complexity: 0.0,
+            average_cognitive_complexity: 0.0,
+            average_maintainability_index: 0.0,
+            total_technical_debt_hours: 0.0,
+            hotspots: Vec::new(),
+        },
+        test_coverage: None,
+        documentation_coverage: None,
+    }
+}
+
+fn is_manifest(path: &Path) -> bool {
+    let file_name = path.file_name().and_then(|name| name.to_str()).unwrap_or_default();
+    matches!(file_name, "Cargo.toml" | "Cargo.lock" | "package.json" | "go.mod")
+        || (file_name.starts_with("requirements") && file_name.ends_with(".txt"))
+        || build_system_for(file_name).is_some()
+}
+
+fn build_system_for(file_name: &str) -> Option<&'static str> {
+    Some(match file_name {
+        "Cargo.toml" => "cargo",
+        "package-lock.json" => "npm",
+        "yarn.lock" => "yarn",
+        "pnpm-lock.yaml" => "pnpm",
+        "pyproject.toml" => "pyproject",
+        "setup.py" => "setuptools",
+        "go.mod" => "go",
+        "pom.xml" => "maven",
+        "build.gradle" | "build.gradle.kts" => "gradle",
+        "CMakeLists.txt" => "cmake",
+        "Makefile" => "make",
+        _ => return None,
+    })
+}
+
+fn detect_build_systems(manifests: &[PathBuf]) -> BTreeSet<String> {
+    let mut build_systems = BTreeSet::new();
+    let mut has_node_lockfile = false;
+    for manifest in manifests {
+        let file_name = manifest.file_name().and_then(|name| name.to_str()).unwrap_or_default();
+        if let Some(build_system) = build_system_for(file_name) {
+            has_node_lockfile |= matches!(build_system, "npm" | "yarn" | "pnpm");
+            build_systems.insert(build_system.to_string());
+        } else if file_name.starts_with("requirements") {
+            build_systems.insert("pip".to_string());
+        }
+    }
+    // A package.json without a lockfile is installed with npm
+    let has_package_json = manifests.iter().any(|manifest| manifest.ends_with("package.json"));
+    if has_package_json && !has_node_lockfile {
+        build_systems.insert("npm".to_string());
+    }
+    build_systems
+}
+
+fn detect_test_framework(language: &Language, dependencies: &[Dependency]) -> Option<String> {
+    const KNOWN: &[&str] = &["jest", "vitest", "mocha", "pytest", "junit"];
+    if let Some(framework) = KNOWN.iter().find(|known| dependencies.iter().any(|d| d.name == **known)) {
+        return Some(framework.to_string());
+    }
+    match language {
+        Language::Rust => Some("cargo test".to_string()),
+        Language::Go => Some("go test".to_string()),
+        _ => None,
+    }
+}
+
+fn parse_manifest(path: &Path) -> Result<Vec<Dependency>> {
+    let file_name = path.file_name().and_then(|name| name.to_str()).unwrap_or_default();
+    let parse = match file_name {
+        "Cargo.toml" => parse_cargo_toml,
+        "package.json" => parse_package_json,
+        "go.mod" => parse_go_mod,
+        name if name.starts_with("requirements") && name.ends_with(".txt") => {
+            let content = std::fs::read_to_string(path)?;
+            let dependency_type = if name.contains("dev") || name.contains("test") {
+                DependencyType::Development
+            } else {
+                DependencyType::Production
+            };
+            return Ok(parse_requirements_txt(&content, dependency_type));
+        }
+        _ => return Ok(Vec::new()),
+    };
+    parse(&std::fs::read_to_string(path)?)
+}
+
+/// Package ecosystem a manifest or lockfile belongs to; lockfiles only resolve
+/// dependencies declared in the same ecosystem
+fn ecosystem_for(path: &Path) -> &'static str {
+    match path.file_name().and_then(|name| name.to_str()).unwrap_or_default() {
+        "Cargo.toml" | "Cargo.lock" => "cargo",
+        "package.json" | "package-lock.json" => "npm",
+        "go.mod" => "go",
+        name if name.starts_with("requirements") => "pypi",
+        _ => "other",
+    }
+}
+
+/// A package pinned by a lockfile
+#[derive(Debug, Clone)]
+struct LockedPackage {
+    name: String,
+    version: String,
+    source: DependencySource,
+    /// `name` or `name@version`
+    depends_on: Vec<String>,
+    hashes: Vec<DependencyHash>,
+    dev: bool,
+    optional: bool,
+}
+
+fn parse_lockfile(path: &Path) -> Result<Option<Vec<LockedPackage>>> {
+    let parse = match path.file_name().and_then(|name| name.to_str()).unwrap_or_default() {
+        "Cargo.lock" => parse_cargo_lock,
+        "package-lock.json" => parse_package_lock,
+        _ => return Ok(None),
+    };
+    parse(&std::fs::read_to_string(path)?).map(Some)
+}
+
+fn parse_cargo_lock(content: &str) -> Result<Vec<LockedPackage>> {
+    let lock: toml::Value = toml::from_str(content)?;
+    let Some(packages) = lock.get("package").and_then(toml::Value::as_array) else {
+        return Ok(Vec::new());
+    };
+
+    let mut locked = Vec::new();
+    for package in packages {
+        let (Some(name), Some(version)) = (
+            package.get("name").and_then(toml::Value::as_str),
+            package.get("version").and_then(toml::Value::as_str),
+        ) else {
+            continue;
+        };
+        let source = match package.get("source").and_then(toml::Value::as_str) {
+            Some(source) if source.starts_with("git+") => DependencySource::Git,
+            Some(_) => DependencySource::Registry,
+            // Workspace members and path dependencies
+            None => DependencySource::Local,
+        };
+        // "name", "name version" or "name version (source)"
+        let depends_on = package
+            .get("dependencies")
+            .and_then(toml::Value::as_array)
+            .into_iter()
+            .flatten()
+            .filter_map(toml::Value::as_str)
+            .map(|spec| {
+                let mut parts = spec.split_whitespace();
+                match (parts.next(), parts.next()) {
+                    (Some(name), Some(version)) => format!("{}@{}", name, version),
+                    _ => spec.to_string(),
+                }
+            })
+            .collect();
+        let hashes = package
+            .get("checksum")
+            .and_then(toml::Value::as_str)
+            .map(|checksum| DependencyHash { algorithm: HashAlgorithm::Sha256, value: checksum.to_lowercase() })
+            .into_iter()
+            .collect();
+
+        locked.push(LockedPackage {
+            name: name.to_string(),
+            version: version.to_string(),
+            source,
+            depends_on,
+            hashes,
+            dev: false,
+            optional: false,
+        });
+    }
+    Ok(locked)
+}
+
+/// `package-lock.json` version 2 and 3, which list every installed package under `packages`
+fn parse_package_lock(content: &str) -> Result<Vec<LockedPackage>> {
+    let lock: serde_json::Value = serde_json::from_str(content)?;
+    let Some(packages) = lock.get("packages").and_then(serde_json::Value::as_object) else {
+        return Err("package-lock.json without a packages section (lockfileVersion 1) is not supported".into());
+    };
+
+    let mut locked = Vec::new();
+    for (path, package) in packages {
+        // "" is the project itself
+        let Some((_, name)) = path.rsplit_once("node_modules/") else { continue };
+        let Some(version) = package.get("version").and_then(serde_json::Value::as_str) else { continue };
+        let flag = |key: &str| package.get(key).and_then(serde_json::Value::as_bool).unwrap_or(false);
+        let source = if flag("link") {
+            DependencySource::Local
+        } else {
+            match package.get("resolved").and_then(serde_json::Value::as_str) {
+                Some(resolved) if resolved.starts_with("git") => DependencySource::Git,
+                _ => DependencySource::Registry,
+            }
+        };
+        let depends_on = ["dependencies", "optionalDependencies", "peerDependencies"]
+            .iter()
+            .filter_map(|section| package.get(*section).and_then(serde_json::Value::as_object))
+            .flat_map(|entries| entries.keys().cloned())
+            .collect();
+        let hashes = package
+            .get("integrity")
+            .and_then(serde_json::Value::as_str)
+            .map(parse_integrity)
+            .unwrap_or_default();
+
+        locked.push(LockedPackage {
+            name: name.to_string(),
+            version: version.to_string(),
+            source,
+            depends_on,
+            hashes,
+            dev: flag("dev"),
+            optional: flag("optional"),
+        });
+    }
+    Ok(locked)
+}
+
+/// Subresource integrity string, e.g. `sha512-<base64>`, as hex digests
+fn parse_integrity(integrity: &str) -> Vec<DependencyHash> {
+    integrity
+        .split_whitespace()
+        .filter_map(|entry| {
+            let (algorithm, digest) = entry.split_once('-')?;
+            let algorithm = match algorithm {
+                "sha1" => HashAlgorithm::Sha1,
+                "sha256" => HashAlgorithm::Sha256,
+                "sha384" => HashAlgorithm::Sha384,
+                "sha512" => HashAlgorithm::Sha512,
+                _ => return None,
+            };
+            let bytes = base64::engine::general_purpose::STANDARD.decode(digest).ok()?;
+            Some(DependencyHash { algorithm, value: bytes.iter().map(|b| format!("{:02x}", b)).collect() })
+        })
+        .collect()
+}
+
+/// Resolve declared dependencies against a lockfile and add the locked
+/// packages nothing declares as transitive dependencies
+fn apply_lockfile(declared: &mut Vec<Dependency>, locked: Vec<LockedPackage>) {
+    let mut resolved = vec![false; declared.len()];
+    let mut transitive = Vec::new();
+
+    for package in locked {
+        let declaration = declared.iter().enumerate().position(|(index, dependency)| {
+            !resolved[index]
+                && dependency.name == package.name
+                // Git and path declarations carry a location rather than a version requirement
+                && (!matches!(dependency.source, DependencySource::Registry)
+                    || requirement_admits(&dependency.version, &package.version))
+        });
+        match declaration {
+            Some(index) => {
+                resolved[index] = true;
+                let dependency = &mut declared[index];
+                if matches!(dependency.source, DependencySource::Registry) {
+                    dependency.version = package.version;
+                }
+                dependency.depends_on = package.depends_on;
+                dependency.hashes = package.hashes;
+            }
+            // Workspace members and path crates are the project's own code
+            None if matches!(package.source, DependencySource::Local) => {}
+            None => {
+                let dependency_type = if package.dev {
+                    DependencyType::Development
+                } else if package.optional {
+                    DependencyType::Optional
+                } else {
+                    DependencyType::Production
+                };
+                let mut dependency = dependency(&package.name, &package.version, dependency_type, package.source);
+                dependency.direct = false;
+                dependency.depends_on = package.depends_on;
+                dependency.hashes = package.hashes;
+                transitive.push(dependency);
+            }
+        }
+    }
+    declared.extend(transitive);
+}
+
+/// Whether a locked version can satisfy a declared requirement. Only the
+/// leading version that a caret requirement pins is compared: the major
+/// version, or the minor one for `0.x`.
+fn requirement_admits(requirement: &str, version: &str) -> bool {
+    let requirement = requirement.trim_start_matches(|c: char| matches!(c, '^' | '~' | '=' | '>' | '<' | 'v' | ' '));
+    if requirement.is_empty() || requirement == "*" || requirement == "latest" {
+        return true;
+    }
+    let wanted: Vec<&str> = requirement.split(|c: char| c == '.' || c == ',' || c == ' ').collect();
+    let locked: Vec<&str> = version.trim_start_matches('v').split('.').collect();
+    let pinned = if wanted[0] == "0" { 2 } else { 1 };
+    (0..pinned).all(|i| match (wanted.get(i), locked.get(i)) {
+        (Some(wanted), Some(locked)) => wanted == locked || matches!(*wanted, "x" | "*"),
+        (None, _) => true,
+        (Some(_), None) => false,
+    })
+}
+
+/// One dependency per name and version; a package declared directly anywhere stays direct
+fn merge_duplicates(dependencies: impl IntoIterator<Item = Dependency>) -> Vec<Dependency> {
+    let mut merged: Vec<Dependency> = Vec::new();
+    let mut index: HashMap<(String, String), usize> = HashMap::new();
+    for dependency in dependencies {
+        match index.get(&(dependency.name.clone(), dependency.version.clone())) {
+            Some(&existing) => {
+                let existing = &mut merged[existing];
+                existing.direct |= dependency.direct;
+                if existing.depends_on.is_empty() {
+                    existing.depends_on = dependency.depends_on;
+                }
+                if existing.hashes.is_empty() {
+                    existing.hashes = dependency.hashes;
+                }
+            }
+            None => {
+                index.insert((dependency.name.clone(), dependency.version.clone()), merged.len());
+                merged.push(dependency);
+            }
+        }
+    }
+    merged
+}
+
+fn dependency(name: &str, version: &str, dependency_type: DependencyType, source: DependencySource) -> Dependency {
+    Dependency {
+        name: name.to_string(),
+        version: version.to_string(),
+        dependency_type,
+        source,
+        vulnerabilities: Vec::new(),
+        license: None,
+        size: None,
+        direct: true,
+        depends_on: Vec::new(),
+        hashes: Vec::new(),
+    }
+}
+
+fn parse_cargo_toml(content: &str) -> Result<Vec<Dependency>> {
+    let manifest: toml::Value = toml::from_str(content)?;
+    let mut dependencies = Vec::new();
+
+    let mut tables: Vec<(&toml::Value, DependencyType)> = Vec::new();
+    let sections = [
+        ("dependencies", DependencyType::Production),
+        ("dev-dependencies", DependencyType::Development),
+        ("build-dependencies", DependencyType::Build),
+    ];
+    for (section, dependency_type) in &sections {
+        if let Some(table) = manifest.get(section) {
+            tables.push((table, dependency_type.clone()));
+        }
+        // [target.'cfg(..)'.dependencies]
+        if let Some(targets) = manifest.get("target").and_then(toml::Value::as_table) {
+            for target in targets.values() {
+                if let Some(table) = target.get(section) {
+                    tables.push((table, dependency_type.clone()));
+                }
+            }
+        }
+    }
+    if let Some(table) = manifest.get("workspace").and_then(|workspace| workspace.get("dependencies")) {
+        tables.push((table, DependencyType::Production));
+    }
+
+    for (table, dependency_type) in tables {
+        let Some(table) = table.as_table() else { continue };
+        for (key, spec) in table {
+            let (name, version, source, optional) = match spec {
+                toml::Value::String(version) => (key.as_str(), version.as_str(), DependencySource::Registry, false),
+                toml::Value::Table(spec) => {
+                    let name = spec.get("package").and_then(toml::Value::as_str).unwrap_or(key);
+                    let optional = spec.get("optional").and_then(toml::Value::as_bool).unwrap_or(false);
+                    let (version, source) = if let Some(git) = spec.get("git").and_then(toml::Value::as_str) {
+                        (git, DependencySource::Git)
+                    } else if let Some(path) = spec.get("path").and_then(toml::Value::as_str) {
+                        (path, DependencySource::Local)
+                    } else if spec.get("workspace").and_then(toml::Value::as_bool) == Some(true) {
+                        ("workspace", DependencySource::Local)
+                    } else {
+                        (spec.get("version").and_then(toml::Value::as_str).unwrap_or("*"), DependencySource::Registry)
+                    };
+                    (name, version, source, optional)
+                }
+                _ => continue,
+            };
+            let dependency_type = if optional { DependencyType::Optional } else { dependency_type.clone() };
+            dependencies.push(dependency(name, version, dependency_type, source));
+        }
+    }
+    Ok(dependencies)
+}
+
+fn parse_package_json(content: &str) -> Result<Vec<Dependency>> {
+    let manifest: serde_json::Value = serde_json::from_str(content)?;
+    let sections = [
+        ("dependencies", DependencyType::Production),
+        ("devDependencies", DependencyType::Development),
+        ("peerDependencies", DependencyType::Peer),
+        ("optionalDependencies", DependencyType::Optional),
+    ];
+
+    let mut dependencies = Vec::new();
+    for (section, dependency_type) in sections {
+        let Some(entries) = manifest.get(section).and_then(serde_json::Value::as_object) else { continue };
+        for (name, version) in entries {
+            let version = version.as_str().unwrap_or("*");
+            let source = if version.starts_with("git") || version.starts_with("github:") {
+                DependencySource::Git
+            } else if ["file:", "link:", "workspace:"].iter().any(|prefix| version.starts_with(prefix)) {
+                DependencySource::Local
+            } else if version.starts_with("http://") || version.starts_with("https://") {
+                DependencySource::URL
+            } else {
+                DependencySource::Registry
+            };
+            dependencies.push(dependency(name, version, dependency_type.clone(), source));
+        }
+    }
+    Ok(dependencies)
+}
+
+fn parse_requirements_txt(content: &str, dependency_type: DependencyType) -> Vec<Dependency> {
+    let mut dependencies = Vec::new();
+    for line in content.lines() {
+        let line = line.split(" #").next().unwrap_or_default().trim();
+        // Options such as -r other.txt, --index-url or -e .
+        if line.is_empty() || line.starts_with('#') || line.starts_with('-') {
+            continue;
+        }
+
+        if line.starts_with("git+") || line.contains("://") {
+            if let Some(name) = line.split("#egg=").nth(1) {
+                dependencies.push(dependency(name.trim(), line, dependency_type.clone(), DependencySource::Git));
+            }
+            continue;
+        }
+
+        // name[extras] <spec> ; markers
+        let requirement = line.split(';').next().unwrap_or_default().trim();
+        let name_end = requirement
+            .find(|c: char| !(c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.')))
+            .unwrap_or(requirement.len());
+        let name = &requirement[..name_end];
+        if name.is_empty() {
+            continue;
+        }
+        let rest = requirement[name_end..].trim_start();
+        let rest = match rest.strip_prefix('[') {
+            Some(after_extras) => after_extras.split_once(']').map_or("", |(_, spec)| spec.trim()),
+            None => rest,
+        };
+        let version = rest.strip_prefix("==").unwrap_or(rest).trim();
+        let version = if version.is_empty() { "*" } else { version };
+        dependencies.push(dependency(name, version, dependency_type.clone(), DependencySource::Registry));
+    }
+    dependencies
+}
+
+fn parse_go_mod(content: &str) -> Result<Vec<Dependency>> {
+    let mut dependencies = Vec::new();
+    let mut in_require_block = false;
+
+    for line in content.lines() {
+        let line = line.trim();
+        let requirement = if in_require_block {
+            if line.starts_with(')') {
+                in_require_block = false;
+                continue;
+            }
+            line
+        } else if line.starts_with("require (") || line == "require(" {
+            in_require_block = true;
+            continue;
+        } else if let Some(requirement) = line.strip_prefix("require ") {
+            requirement
+        } else {
+            continue;
+        };
+
+        let (requirement, comment) = match requirement.split_once("//") {
+            Some((requirement, comment)) => (requirement.trim(), comment.trim()),
+            None => (requirement.trim(), ""),
+        };
+        let mut parts = requirement.split_whitespace();
+        let (Some(module), Some(version)) = (parts.next(), parts.next()) else { continue };
+
+        let mut dependency = dependency(module, version, DependencyType::Production, DependencySource::Registry);
+        // Required only by other dependencies
+        dependency.direct = comment != "indirect";
+        dependencies.push(dependency);
+    }
+    Ok(dependencies)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reimport_keeps_ids_and_skips_ignored_and_binary_files() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join(".gitignore"), "target/\n").unwrap();
+        std::fs::create_dir_all(dir.path().join("src")).unwrap();
+        std::fs::create_dir_all(dir.path().join("target")).unwrap();
+        std::fs::write(dir.path().join("src/main.rs"), "fn main() {}\n").unwrap();
+        std::fs::write(dir.path().join("target/generated.rs"), "fn generated() {}\n").unwrap();
+        std::fs::write(dir.path().join("src/blob.rs"), b"\0\x01\x02").unwrap();
+        std::fs::write(
+            dir.path().join("Cargo.toml"),
+            "[package]\nname = \"demo\"\n\n[dependencies]\nserde = \"1.0\"\nlocal = { path = \"../local\" }\n\n[dev-dependencies]\ntempfile = \"3\"\n",
+        )
+        .unwrap();
+
+        let first = import_project(dir.path()).unwrap();
+        let second = import_project(dir.path()).unwrap();
+
+        let paths: Vec<_> = first.files.iter().map(|f| f.relative_path.to_string_lossy().into_owned()).collect();
+        assert_eq!(paths, vec!["Cargo.toml", "src/main.rs"]);
+        assert_eq!(first.id, second.id);
+        assert_eq!(
+            first.files.iter().map(|f| (f.id, f.hash.clone())).collect::<Vec<_>>(),
+            second.files.iter().map(|f| (f.id, f.hash.clone())).collect::<Vec<_>>()
+        );
+        assert_eq!(first.language, Language::Rust);
+        assert_eq!(first.configuration.build_system.as_deref(), Some("cargo"));
+
+        let local = first.dependencies.iter().find(|d| d.name == "local").unwrap();
+        assert!(matches!(local.source, DependencySource::Local));
+        let tempfile = first.dependencies.iter().find(|d| d.name == "tempfile").unwrap();
+        assert!(matches!(tempfile.dependency_type, DependencyType::Development));
+    }
+
+    #[test]
+    fn detects_extensionless_shell_script() {
+        let script = b"#!/usr/bin/env bash\nset -euo pipefail\necho \"deploying $1\"\n";
+        assert_eq!(detect_language(Path::new("scripts/deploy"), script), Language::Shell);
+
+        // No shebang, but unmistakably shell
+        let script = b"set -e\nfor f in *.log; do\n  gzip \"$f\"\ndone\n";
+        assert_eq!(detect_language(Path::new("rotate-logs"), script), Language::Shell);
+    }
+
+    #[test]
+    fn detects_rust_file_renamed_to_txt() {
+        let source = "#![allow(dead_code)]\nuse std::collections::HashMap;\n\nfn main() {\n    let mut counts = HashMap::new();\n    counts.insert(\"a\", 1);\n    println!(\"{:?}\", counts);\n}\n";
+        assert_eq!(detect_language(Path::new("snippets/main.txt"), source.as_bytes()), Language::Rust);
+        // A telling extension still wins over content
+        assert_eq!(detect_language(Path::new("src/main.py"), source.as_bytes()), Language::Python);
+        // Headers are C unless they look like C++
+        assert_eq!(detect_language(Path::new("include/point.h"), b"#include <stdint.h>\nint32_t norm(int32_t x);\n"), Language::C);
+        assert_eq!(
+            detect_language(Path::new("include/widget.h"), b"#include <vector>\nnamespace ui {\nclass Widget {\n  std::vector<int> sizes;\n};\n}\n"),
+            Language::CPlusPlus
+        );
+    }
+
+    #[test]
+    fn unknown_binary_and_prose_stay_unknown() {
+        let elf = b"\x7fELF\x02\x01\x01\0\0\0\0\0\0\0\0\0\x02\0\x3e\0\x01\0\0\0";
+        assert_eq!(detect_language(Path::new("bin/tool"), elf), Language::Unknown);
+        assert_eq!(detect_language(Path::new("NOTICE"), b"Copyright the project authors.\nAll rights reserved.\n"), Language::Unknown);
+    }
+
+    #[test]
+    fn import_detects_files_without_telling_extensions() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("bin")).unwrap();
+        std::fs::write(dir.path().join("bin/deploy"), "#!/bin/sh\necho deploying\n").unwrap();
+        std::fs::write(dir.path().join("bin/tool"), b"\x7fELF\x02\x01\x01\0\0\0").unwrap();
+        std::fs::write(dir.path().join("notes.txt"), "Remember to rotate the keys.\n").unwrap();
+
+        let project = import_project(dir.path()).unwrap();
+        let files: Vec<_> = project
+            .files
+            .iter()
+            .map(|f| (f.relative_path.to_string_lossy().into_owned(), f.language.clone()))
+            .collect();
+        assert_eq!(files, vec![("bin/deploy".to_string(), Language::Shell)]);
+    }
+
+    #[test]
+    fn cargo_lock_resolves_versions_graph_and_checksums() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("src")).unwrap();
+        std::fs::write(dir.path().join("src/main.rs"), "fn main() {}\n").unwrap();
+        std::fs::write(
+            dir.path().join("Cargo.toml"),
+            "[package]\nname = \"demo\"\n\n[dependencies]\nserde = \"1.0\"\nsyn = \"2\"\n",
+        )
+        .unwrap();
+        let checksum = "3c5113243e4a3a1c96587342d067f3e6b0f50790b6cf40d2868eb647a3eef0e0";
+        std::fs::write(
+            dir.path().join("Cargo.lock"),
+            format!(
+                r#"version = 3
+
+[[package]]
+name = "demo"
+version = "0.1.0"
+dependencies = ["serde", "syn 2.0.39"]
+
+[[package]]
+name = "serde"
+version = "1.0.193"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+checksum = "{checksum}"
+dependencies = ["serde_derive"]
+
+[[package]]
+name = "serde_derive"
+version = "1.0.193"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+
+[[package]]
+name = "syn"
+version = "1.0.109"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+
+[[package]]
+name = "syn"
+version = "2.0.39"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+"#
+            ),
+        )
+        .unwrap();
+
+        let project = import_project(dir.path()).unwrap();
+        let find = |name: &str, version: &str| {
+            project.dependencies.iter().find(|d| d.name == name && d.version == version).unwrap()
+        };
+
+        let serde = find("serde", "1.0.193");
+        assert!(serde.direct);
+        assert_eq!(serde.depends_on, vec!["serde_derive".to_string()]);
+        assert_eq!(serde.hashes.len(), 1);
+        assert_eq!(serde.hashes[0].algorithm, HashAlgorithm::Sha256);
+        assert_eq!(serde.hashes[0].value, checksum);
+
+        assert!(!find("serde_derive", "1.0.193").direct);
+        // The declared major version picks the locked one among several
+        assert!(find("syn", "2.0.39").direct);
+        assert!(!find("syn", "1.0.109").direct);
+        // The project itself is not a dependency
+        assert!(project.dependencies.iter().all(|d| d.name != "demo"));
+        assert_eq!(project.dependencies.len(), 4);
+    }
+
+    #[test]
+    fn package_lock_marks_transitive_and_dev_packages() {
+        let lock = r#"{
+            "name": "web",
+            "lockfileVersion": 3,
+            "packages": {
+                "": { "name": "web", "dependencies": { "express": "^4.18.0" } },
+                "node_modules/express": {
+                    "version": "4.18.2",
+                    "integrity": "sha512-3q2+7w==",
+                    "dependencies": { "body-parser": "1.20.1" }
+                },
+                "node_modules/body-parser": { "version": "1.20.1" },
+                "node_modules/jest": { "version": "29.7.0", "dev": true }
+            }
+        }"#;
+        let mut declared = parse_package_json(r#"{ "dependencies": { "express": "^4.18.0" } }"#).unwrap();
+        apply_lockfile(&mut declared, parse_package_lock(lock).unwrap());
+
+        assert_eq!(declared[0].name, "express");
+        assert_eq!(declared[0].version, "4.18.2");
+        assert!(declared[0].direct);
+        assert_eq!(declared[0].depends_on, vec!["body-parser".to_string()]);
+        assert_eq!(declared[0].hashes[0].algorithm, HashAlgorithm::Sha512);
+        assert_eq!(declared[0].hashes[0].value, "deadbeef");
+
+        let body_parser = declared.iter().find(|d| d.name == "body-parser").unwrap();
+        assert!(!body_parser.direct);
+        let jest = declared.iter().find(|d| d.name == "jest").unwrap();
+        assert!(matches!(jest.dependency_type, DependencyType::Development));
+    }
+
+    #[test]
+    fn parses_requirements_and_go_mod() {
+        let requirements = parse_requirements_txt(
+            "# pinned\nrequests==2.31.0\nuvicorn[standard]>=0.23 ; python_version >= \"3.8\"\n-r base.txt\ngit+https://github.com/org/lib.git#egg=lib\n",
+            DependencyType::Production,
+        );
+        let versions: Vec<_> = requirements.iter().map(|d| (d.name.as_str(), d.version.as_str())).collect();
+        assert_eq!(versions[..2], [("requests", "2.31.0"), ("uvicorn", ">=0.23")]);
+        assert!(matches!(requirements[2].source, DependencySource::Git));
+
+        let go = parse_go_mod(
+            "module example.com/app\n\nrequire (\n\tgithub.com/gin-gonic/gin v1.9.1\n\tgolang.org/x/sys v0.15.0 // indirect\n)\n",
+        )
+        .unwrap();
+        assert_eq!(go.len(), 2);
+        assert!(go[0].direct);
+        assert!(!go[1].direct);
+    }
+}
@@ -0,0 +1,1433 @@
+//! # Refactoring Previews
+//!
+//! Dry runs of [`RefactoringOpportunity`]s: [`preview`] works out the text
+//! changes an opportunity would make and renders them as a unified diff
+//! without writing anything. Changes stay within the opportunity's
+//! `affected_files`, or the file of its location when none are listed.
+//!
+//! Previews are available for:
+//! - `RenameFunction`: the function named at the opportunity's location is
+//!   renamed to its `new_name` wherever the name is used as a whole word
+//!   outside comments and string literals.
+//! - `ExtractMethod`: the whole lines covered by the location move into a new
+//!   function that is called in their place, for Rust and Python sources.
+//!   Variables the lines read from the enclosing function become parameters;
+//!   variables they assign for later use become return values. Lines that
+//!   return early or whose variables cannot be passed along are rejected
+//!   rather than previewed as broken code.
+
+use crate::analyzer::complexity_analyzer::{is_ident, mask_comments_and_strings, LineIndex};
+use crate::{
+    language_for_path, ChangeType, CodeLocation, ComplexityAnalyzer, FunctionComplexity, Language,
+    RefactoringOpportunity, RefactoringType, Result, SourceFile, TextChange,
+};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+
+/// Unchanged lines shown around each hunk
+const DIFF_CONTEXT: usize = 3;
+
+/// What applying a refactoring would change
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RefactoringPreview {
+    /// Edits to the original sources, ordered by file and position
+    pub changes: Vec<TextChange>,
+    /// The same edits as a unified diff with `a/` and `b/` path prefixes
+    pub diff: String,
+}
+
+/// Preview `opportunity` against its files on disk
+pub async fn preview(opportunity: &RefactoringOpportunity) -> Result<RefactoringPreview> {
+    let mut sources = BTreeMap::new();
+    for path in scope(opportunity) {
+        let content = tokio::fs::read_to_string(&path).await?;
+        sources.insert(path, content);
+    }
+    preview_sources(opportunity, &sources)
+}
+
+/// Preview `opportunity` against in-memory sources keyed by path
+pub fn preview_sources(opportunity: &RefactoringOpportunity, sources: &BTreeMap<PathBuf, String>) -> Result<RefactoringPreview> {
+    let scope = scope(opportunity);
+    let target = &opportunity.location.file_path;
+    if !scope.contains(target) {
+        return Err(format!("{} is not among the opportunity's affected files", target.display()).into());
+    }
+
+    let mut edits = match &opportunity.opportunity_type {
+        RefactoringType::RenameFunction => rename_function(opportunity, &scope, sources)?,
+        RefactoringType::ExtractMethod => extract_method(opportunity, source(sources, target)?)?,
+        other => return Err(format!("No preview is available for {:?} refactorings", other).into()),
+    };
+    edits.sort_by(|a, b| (&a.path, a.start, a.end).cmp(&(&b.path, b.start, b.end)));
+
+    let mut by_file: BTreeMap<&Path, Vec<&Edit>> = BTreeMap::new();
+    for edit in &edits {
+        by_file.entry(&edit.path).or_default().push(edit);
+    }
+    let mut changes = Vec::with_capacity(edits.len());
+    let mut diff = String::new();
+    for (path, file_edits) in by_file {
+        let content = source(sources, path)?;
+        let lines = LineIndex::new(content);
+        changes.extend(file_edits.iter().map(|edit| edit.text_change(content, &lines)));
+        diff.push_str(&file_diff(path, content, &lines, &file_edits));
+    }
+    Ok(RefactoringPreview { changes, diff })
+}
+
+/// Files the opportunity may change
+fn scope(opportunity: &RefactoringOpportunity) -> Vec<PathBuf> {
+    if opportunity.affected_files.is_empty() {
+        return vec![opportunity.location.file_path.clone()];
+    }
+    let mut files = opportunity.affected_files.clone();
+    files.dedup();
+    files
+}
+
+fn source<'a>(sources: &'a BTreeMap<PathBuf, String>, path: &Path) -> Result<&'a str> {
+    sources
+        .get(path)
+        .map(String::as_str)
+        .ok_or_else(|| format!("No source given for {}", path.display()).into())
+}
+
+/// Replacement of `start..end` in one file
+#[derive(Debug, Clone)]
+struct Edit {
+    path: PathBuf,
+    start: usize,
+    end: usize,
+    text: String,
+}
+
+impl Edit {
+    fn text_change(&self, content: &str, lines: &LineIndex) -> TextChange {
+        let change_type = if self.start == self.end {
+            ChangeType::Insert
+        } else if self.text.is_empty() {
+            ChangeType::Delete
+        } else {
+            ChangeType::Replace
+        };
+        TextChange {
+            location: CodeLocation {
+                file_path: self.path.clone(),
+                start_line: lines.line_of(self.start) as u32 + 1,
+                start_column: lines.column(content, self.start),
+                end_line: lines.line_of(self.end) as u32 + 1,
+                end_column: lines.column(content, self.end),
+                start_byte: self.start as u32,
+                end_byte: self.end as u32,
+            },
+            new_text: self.text.clone(),
+            change_type,
+        }
+    }
+}
+
+fn rename_function(
+    opportunity: &RefactoringOpportunity,
+    scope: &[PathBuf],
+    sources: &BTreeMap<PathBuf, String>,
+) -> Result<Vec<Edit>> {
+    let new_name = opportunity
+        .new_name
+        .as_deref()
+        .ok_or("Previewing a rename needs the opportunity's new_name")?;
+    if !is_identifier(new_name) {
+        return Err(format!("`{}` is not a valid function name", new_name).into());
+    }
+    let location = &opportunity.location;
+    let old_name = function_name_at(source(sources, &location.file_path)?, location)?;
+    if old_name == new_name {
+        return Err(format!("`{}` already has that name", old_name).into());
+    }
+
+    let mut edits = Vec::new();
+    for path in scope {
+        let code = mask_comments_and_strings(source(sources, path)?.as_bytes(), &language_for_path(path));
+        if !identifier_offsets(&code, new_name).is_empty() {
+            return Err(format!("`{}` is already used in {}", new_name, path.display()).into());
+        }
+        edits.extend(identifier_offsets(&code, &old_name).into_iter().map(|start| Edit {
+            path: path.clone(),
+            start,
+            end: start + old_name.len(),
+            text: new_name.to_string(),
+        }));
+    }
+    Ok(edits)
+}
+
+/// The function named at `location`: either the located text itself or the
+/// name following the first function keyword in it
+fn function_name_at(content: &str, location: &CodeLocation) -> Result<String> {
+    let text = content
+        .get(location.start_byte as usize..location.end_byte as usize)
+        .ok_or("The opportunity's location is outside its file")?
+        .trim();
+    if is_identifier(text) {
+        return Ok(text.to_string());
+    }
+    let tokens = identifiers(text.as_bytes());
+    tokens
+        .windows(2)
+        .find(|pair| matches!(pair[0].1, "fn" | "def" | "func" | "function"))
+        .map(|pair| pair[1].1.to_string())
+        .ok_or_else(|| format!("No function name found at {}:{}", location.file_path.display(), location.start_line).into())
+}
+
+/// A variable bound in the enclosing function
+#[derive(Debug, Clone)]
+struct Binding {
+    name: String,
+    /// Declared type, where the language has and the source states one
+    ty: Option<String>,
+    mutable: bool,
+}
+
+fn extract_method(opportunity: &RefactoringOpportunity, content: &str) -> Result<Vec<Edit>> {
+    let location = &opportunity.location;
+    let path = &location.file_path;
+    let language = language_for_path(path);
+    if !matches!(language, Language::Rust | Language::Python) {
+        return Err(format!("Extracting a method is not supported for {:?} sources", language).into());
+    }
+
+    let lines = LineIndex::new(content);
+    let line_start = |line: usize| lines.starts.get(line).copied().unwrap_or(content.len());
+    let line_count = content.split_inclusive('\n').count();
+    let (first, last) = (location.start_line as usize, location.end_line as usize);
+    if first == 0 || last < first || last > line_count {
+        return Err(format!("Lines {}-{} are outside {}", first, last, path.display()).into());
+    }
+    let (start, end) = (line_start(first - 1), line_start(last));
+
+    let python = language == Language::Python;
+    let file = SourceFile {
+        id: Uuid::new_v4(),
+        path: path.clone(),
+        relative_path: path.clone(),
+        language: language.clone(),
+        content: content.to_string(),
+        size_bytes: content.len() as u64,
+        line_count: line_count as u32,
+        hash: String::new(),
+        last_modified: Utc::now(),
+        analysis_results: None,
+    };
+    let function = ComplexityAnalyzer::new()
+        .functions(&file)
+        .into_iter()
+        .filter(|f| (f.start_line as usize) < first && (last < f.end_line as usize || (python && last == f.end_line as usize)))
+        .max_by_key(|f| f.start_line)
+        .ok_or_else(|| format!("Lines {}-{} are not inside a function body", first, last))?;
+
+    let code = mask_comments_and_strings(content.as_bytes(), &language);
+    let name = opportunity
+        .new_name
+        .clone()
+        .unwrap_or_else(|| format!("{}_extracted", function.name));
+    if !is_identifier(&name) {
+        return Err(format!("`{}` is not a valid function name", name).into());
+    }
+    if !identifier_offsets(&code, &name).is_empty() {
+        return Err(format!("`{}` is already used in {}", name, path.display()).into());
+    }
+
+    let selection = &code[start..end];
+    if !is_balanced(selection) {
+        return Err(format!("Lines {}-{} do not cover whole statements", first, last).into());
+    }
+    check_control_flow(selection, python)?;
+
+    let extraction = Extraction {
+        content,
+        code: &code,
+        function: &function,
+        selection: (start, end),
+        name,
+    };
+    let mut edits = if python { extraction.python()? } else { extraction.rust()? };
+    for edit in &mut edits {
+        edit.path = path.clone();
+    }
+
+    if !python {
+        validate_rust(&apply(content, &edits))
+            .map_err(|e| format!("Extracting lines {}-{} would not produce valid Rust: {}", first, last, e))?;
+    }
+    Ok(edits)
+}
+
+#[cfg(feature = "rust")]
+fn validate_rust(source: &str) -> std::result::Result<(), String> {
+    syn::parse_file(source).map(|_| ()).map_err(|e| e.to_string())
+}
+
+#[cfg(not(feature = "rust"))]
+fn validate_rust(_source: &str) -> std::result::Result<(), String> {
+    Ok(())
+}
+
+/// Lines that leave the enclosing function or loop early cannot be moved
+fn check_control_flow(selection: &[u8], python: bool) -> Result<()> {
+    let tokens = identifiers(selection);
+    let has_loop = tokens.iter().any(|(_, token)| matches!(*token, "loop" | "while" | "for"));
+    let early_exit = |token: &str| match token {
+        "return" | "await" => true,
+        "yield" | "global" | "nonlocal" => python,
+        "break" | "continue" => !has_loop,
+        _ => false,
+    };
+    if let Some((_, token)) = tokens.iter().find(|(_, token)| early_exit(token)) {
+        return Err(format!("The selected lines use `{}`, which cannot be moved into another function", token).into());
+    }
+    if !python && selection.contains(&b'?') {
+        return Err("The selected lines use `?`, which cannot be moved into another function".into());
+    }
+    Ok(())
+}
+
+struct Extraction<'a> {
+    content: &'a str,
+    /// `content` with comments and strings masked
+    code: &'a [u8],
+    function: &'a FunctionComplexity,
+    /// Byte range of the selected whole lines
+    selection: (usize, usize),
+    name: String,
+}
+
+impl Extraction<'_> {
+    fn rust(&self) -> Result<Vec<Edit>> {
+        let (start, end) = self.selection;
+        let header_start = self.function.start_byte as usize;
+        let body_open = find_byte(self.code, header_start, b'{').ok_or("The enclosing function has no body")?;
+        if start <= body_open {
+            return Err("The selected lines overlap the enclosing function's signature".into());
+        }
+        let (params, receiver) = rust_parameters(self.code, self.content, header_start, body_open);
+
+        let mut bound = params;
+        bound.extend(rust_lets(self.code, self.content, body_open, start));
+        let declared = rust_lets(self.code, self.content, start, end);
+        let read = reads(self.code, start, end, false);
+        let inputs = used_bindings(&bound, &read);
+
+        let uses_self = read.contains("self");
+        if uses_self && !receiver.as_deref().is_some_and(|receiver| receiver.starts_with('&')) {
+            return Err("The selected lines use `self`, which the enclosing function does not borrow".into());
+        }
+        let container = rust_container(self.code, header_start);
+        if uses_self && container.kind == ContainerKind::TraitImpl {
+            return Err("The selected lines use `self` in a trait impl, which cannot gain new methods".into());
+        }
+
+        for (offset, token) in identifiers(&self.code[start..end]) {
+            let field = self.code[..start + offset].iter().rev().find(|b| !b.is_ascii_whitespace()) == Some(&b'.');
+            let assigned = !field && is_assignment(&self.code[start + offset + token.len()..end]);
+            if assigned && inputs.iter().any(|input| input.name == token) && !declared.iter().any(|d| d.name == token) {
+                return Err(format!("The selected lines assign to `{}`, which would not reach the enclosing function", token).into());
+            }
+        }
+
+        let after = reads(self.code, end, self.function.end_byte as usize, false);
+        let outputs = used_bindings(&declared, &after);
+
+        let mut signature = Vec::new();
+        let mut arguments = Vec::new();
+        if uses_self {
+            signature.push(receiver.clone().unwrap_or_default());
+        }
+        for input in &inputs {
+            let ty = input.ty.as_deref().ok_or_else(|| unknown_type(&input.name))?;
+            if is_copy_type(ty) {
+                signature.push(format!("{}: {}", input.name, ty));
+                arguments.push(input.name.clone());
+            } else {
+                signature.push(format!("{}: &{}", input.name, ty));
+                arguments.push(format!("&{}", input.name));
+            }
+        }
+        let output_types = outputs
+            .iter()
+            .map(|output| output.ty.clone().ok_or_else(|| unknown_type(&output.name)))
+            .collect::<Result<Vec<_>>>()?;
+
+        let header_indent = line_indent(self.content, header_start);
+        let (call_indent, body) = reindent(&self.content[start..end]);
+        let unit = indent_unit(header_indent, call_indent);
+        let fn_indent = match container.kind {
+            ContainerKind::TraitImpl => line_indent(self.content, container.header),
+            _ => header_indent,
+        };
+        let inner_indent = format!("{}{}", fn_indent, unit);
+
+        let callee = if uses_self {
+            format!("self.{}", self.name)
+        } else if matches!(container.kind, ContainerKind::Impl | ContainerKind::Trait) {
+            format!("Self::{}", self.name)
+        } else {
+            self.name.clone()
+        };
+        let call = format!("{}({})", callee, arguments.join(", "));
+        let call = match outputs.as_slice() {
+            [] => format!("{}{};\n", call_indent, call),
+            [output] => format!("{}let {}{} = {};\n", call_indent, if output.mutable { "mut " } else { "" }, output.name, call),
+            _ => format!("{}let ({}) = {};\n", call_indent, outputs.iter().map(binding_pattern).collect::<Vec<_>>().join(", "), call),
+        };
+
+        let mut function = format!("\n{}fn {}({})", fn_indent, self.name, signature.join(", "));
+        match output_types.as_slice() {
+            [] => {}
+            [ty] => function.push_str(&format!(" -> {}", ty)),
+            types => function.push_str(&format!(" -> ({})", types.join(", "))),
+        }
+        function.push_str(" {\n");
+        push_indented(&mut function, &body, &inner_indent);
+        match outputs.as_slice() {
+            [] => {}
+            [output] => function.push_str(&format!("{}{}\n", inner_indent, output.name)),
+            _ => function.push_str(&format!(
+                "{}({})\n",
+                inner_indent,
+                outputs.iter().map(|output| output.name.as_str()).collect::<Vec<_>>().join(", ")
+            )),
+        }
+        function.push_str(&format!("{}}}\n", fn_indent));
+
+        let insert_after = match container.kind {
+            ContainerKind::TraitImpl => container.close.ok_or("The enclosing impl block is not closed")?,
+            _ => self.function.end_byte as usize - 1,
+        };
+        Ok(vec![
+            Edit { path: PathBuf::new(), start, end, text: call },
+            self.insertion_after(insert_after, function),
+        ])
+    }
+
+    fn python(&self) -> Result<Vec<Edit>> {
+        let (start, end) = self.selection;
+        let header_start = self.function.start_byte as usize;
+        let header_end = python_header_end(self.code, header_start).ok_or("The enclosing function has no body")?;
+
+        let mut bound = python_parameters(&self.content[header_start..header_end]);
+        bound.extend(python_assignments(self.code, header_end, start));
+        let declared = python_assignments(self.code, start, end);
+        let read = reads(self.code, start, end, true);
+        let inputs = used_bindings(&bound, &read);
+        let after = reads(self.code, end, (self.function.end_byte as usize).max(end), true);
+        let outputs = used_bindings(&declared, &after);
+
+        let (call_indent, body) = reindent(&self.content[start..end]);
+        match body.lines().find(|line| !line.trim().is_empty()) {
+            Some(line) if !line.starts_with(char::is_whitespace) => {}
+            _ => return Err("The selected lines start inside a nested block".into()),
+        }
+
+        let header_indent = line_indent(self.content, header_start);
+        let method = inputs.first().is_some_and(|input| input.name == "self") && !header_indent.is_empty();
+        let parameters: Vec<&str> = inputs.iter().map(|input| input.name.as_str()).collect();
+        let arguments: Vec<&str> = parameters.iter().copied().filter(|p| !(method && *p == "self")).collect();
+        let callee = if method { format!("self.{}", self.name) } else { self.name.clone() };
+        let returned = outputs.iter().map(|output| output.name.as_str()).collect::<Vec<_>>().join(", ");
+
+        let mut call = String::from(call_indent);
+        if !outputs.is_empty() {
+            call.push_str(&format!("{} = ", returned));
+        }
+        call.push_str(&format!("{}({})\n", callee, arguments.join(", ")));
+
+        let fn_indent = if method { header_indent } else { "" };
+        let inner_indent = format!("{}    ", fn_indent);
+        let mut function = if method { String::from("\n") } else { String::from("\n\n") };
+        function.push_str(&format!("{}def {}({}):\n", fn_indent, self.name, parameters.join(", ")));
+        push_indented(&mut function, &body, &inner_indent);
+        if !outputs.is_empty() {
+            function.push_str(&format!("{}return {}\n", inner_indent, returned));
+        }
+
+        let insert_after = if method {
+            self.function.end_byte as usize - 1
+        } else {
+            python_top_level_end(self.code, end)
+        };
+        Ok(vec![
+            Edit { path: PathBuf::new(), start, end, text: call },
+            self.insertion_after(insert_after, function),
+        ])
+    }
+
+    /// Insert `text` at the start of the line following the one holding `offset`
+    fn insertion_after(&self, offset: usize, mut text: String) -> Edit {
+        let at = match self.content[offset..].find('\n') {
+            Some(newline) => offset + newline + 1,
+            None => {
+                text.insert(0, '\n');
+                self.content.len()
+            }
+        };
+        Edit { path: PathBuf::new(), start: at, end: at, text }
+    }
+}
+
+fn unknown_type(name: &str) -> Box<dyn std::error::Error + Send + Sync> {
+    format!("Cannot determine the type of `{}`; give its binding an explicit type to extract these lines", name).into()
+}
+
+fn binding_pattern(binding: &Binding) -> String {
+    if binding.mutable {
+        format!("mut {}", binding.name)
+    } else {
+        binding.name.clone()
+    }
+}
+
+/// Bindings whose names are in `names`, the latest binding of each name winning
+fn used_bindings(bindings: &[Binding], names: &BTreeSet<String>) -> Vec<Binding> {
+    let mut used: BTreeMap<&str, &Binding> = BTreeMap::new();
+    for binding in bindings.iter().filter(|binding| names.contains(&binding.name)) {
+        used.insert(&binding.name, binding);
+    }
+    let mut ordered: Vec<Binding> = Vec::new();
+    for binding in bindings {
+        if let Some(latest) = used.remove(binding.name.as_str()) {
+            ordered.push(latest.clone());
+        }
+    }
+    ordered
+}
+
+/// Rust primitives and references, which are passed by value
+fn is_copy_type(ty: &str) -> bool {
+    ty.starts_with('&')
+        || matches!(
+            ty,
+            "i8" | "i16" | "i32" | "i64" | "i128" | "isize" | "u8" | "u16" | "u32" | "u64" | "u128" | "usize"
+                | "f32" | "f64" | "bool" | "char"
+        )
+}
+
+/// Parameters of the Rust function whose header spans `start..body_open`,
+/// and its `self` receiver if it has one
+fn rust_parameters(code: &[u8], content: &str, start: usize, body_open: usize) -> (Vec<Binding>, Option<String>) {
+    let mut depth = 0i32;
+    let Some(open) = (start..body_open).find(|&i| match code[i] {
+        b'<' => {
+            depth += 1;
+            false
+        }
+        b'>' if i > 0 && code[i - 1] != b'-' => {
+            depth -= 1;
+            false
+        }
+        b'(' => depth == 0,
+        _ => false,
+    }) else {
+        return (Vec::new(), None);
+    };
+    let Some(close) = matching(code, open) else {
+        return (Vec::new(), None);
+    };
+
+    let mut params = Vec::new();
+    let mut receiver = None;
+    for (from, to) in split_top_level(code, open + 1, close) {
+        let text = content[from..to].trim();
+        if text.is_empty() {
+            continue;
+        }
+        let Some(colon) = type_colon(text) else {
+            // `self`, `&self`, `&mut self`, `&'a self`
+            if text.ends_with("self") {
+                receiver = Some(text.to_string());
+            }
+            continue;
+        };
+        let pattern = identifiers(&text.as_bytes()[..colon]);
+        let names: Vec<&str> = pattern.iter().map(|(_, t)| *t).filter(|t| !matches!(*t, "mut" | "ref")).collect();
+        if let [name] = names.as_slice() {
+            if *name == "self" {
+                receiver = Some(text.to_string());
+                continue;
+            }
+            params.push(Binding {
+                name: name.to_string(),
+                ty: Some(text[colon + 1..].trim().to_string()),
+                mutable: pattern.iter().any(|(_, t)| *t == "mut"),
+            });
+        }
+    }
+    (params, receiver)
+}
+
+/// `let` and `for` bindings in `from..to`, with their types where stated
+fn rust_lets(code: &[u8], content: &str, from: usize, to: usize) -> Vec<Binding> {
+    let tokens = identifiers(&code[from..to]);
+    let mut bindings = Vec::new();
+    for (index, (_, token)) in tokens.iter().enumerate() {
+        match *token {
+            "let" => {
+                let mutable = tokens.get(index + 1).is_some_and(|(_, t)| *t == "mut");
+                let Some((name_offset, name)) = tokens.get(index + 1 + mutable as usize) else {
+                    continue;
+                };
+                let after = from + name_offset + name.len();
+                let next = next_non_space(code, after, to);
+                let ty = match next.map(|i| (i, code[i])) {
+                    Some((i, b':')) if code.get(i + 1) != Some(&b':') => {
+                        let type_end = (i + 1..to)
+                            .find(|&j| matches!(code[j], b'=' | b';') && depth_between(code, i + 1, j) == 0)
+                            .unwrap_or(to);
+                        Some(content[i + 1..type_end].trim().to_string())
+                    }
+                    Some((_, b'=' | b';')) => None,
+                    // A destructuring pattern
+                    _ => continue,
+                };
+                bindings.push(Binding { name: name.to_string(), ty, mutable });
+            }
+            "for" => {
+                let pattern_end = tokens[index + 1..].iter().position(|(_, t)| *t == "in");
+                for (_, name) in &tokens[index + 1..index + 1 + pattern_end.unwrap_or(0)] {
+                    if *name != "mut" {
+                        bindings.push(Binding { name: name.to_string(), ty: None, mutable: false });
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    bindings
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ContainerKind {
+    Module,
+    Impl,
+    TraitImpl,
+    Trait,
+    Function,
+}
+
+/// The block a Rust function is declared in
+struct Container {
+    kind: ContainerKind,
+    /// Start of the block's header
+    header: usize,
+    /// Closing brace, if the function is inside a block
+    close: Option<usize>,
+}
+
+fn rust_container(code: &[u8], function_start: usize) -> Container {
+    let mut open_braces = Vec::new();
+    for (i, byte) in code[..function_start].iter().enumerate() {
+        match byte {
+            b'{' => open_braces.push(i),
+            b'}' => {
+                open_braces.pop();
+            }
+            _ => {}
+        }
+    }
+    let Some(&open) = open_braces.last() else {
+        return Container { kind: ContainerKind::Module, header: 0, close: None };
+    };
+    let header = code[..open]
+        .iter()
+        .rposition(|&b| matches!(b, b'{' | b'}' | b';'))
+        .map_or(0, |i| i + 1);
+    let header = header + code[header..open].iter().take_while(|b| b.is_ascii_whitespace()).count();
+    let tokens: Vec<&str> = identifiers(&code[header..open]).into_iter().map(|(_, t)| t).collect();
+    let keyword = tokens.iter().find(|t| !matches!(**t, "pub" | "crate" | "unsafe" | "default" | "async" | "const"));
+    let kind = match keyword.copied() {
+        Some("impl") if tokens.contains(&"for") => ContainerKind::TraitImpl,
+        Some("impl") => ContainerKind::Impl,
+        Some("trait") => ContainerKind::Trait,
+        Some("mod") => ContainerKind::Module,
+        _ => ContainerKind::Function,
+    };
+    Container { kind, header, close: matching(code, open) }
+}
+
+/// Whether the text following an identifier assigns to it (`=`, `+=`, ...)
+fn is_assignment(rest: &[u8]) -> bool {
+    let rest = trim_start(rest);
+    let compound: [&[u8]; 10] = [b"<<=", b">>=", b"+=", b"-=", b"*=", b"/=", b"%=", b"&=", b"|=", b"^="];
+    compound.iter().any(|operator| rest.starts_with(operator))
+        || (rest.first() == Some(&b'=') && !matches!(rest.get(1), Some(b'=' | b'>')))
+}
+
+/// Byte just past the `:` ending a Python function header
+fn python_header_end(code: &[u8], start: usize) -> Option<usize> {
+    let mut depth = 0i32;
+    for (i, &byte) in code.iter().enumerate().skip(start) {
+        match byte {
+            b'(' | b'[' | b'{' => depth += 1,
+            b')' | b']' | b'}' => depth -= 1,
+            b':' if depth == 0 => return Some(i + 1),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Parameter names of a Python `def` header
+fn python_parameters(header: &str) -> Vec<Binding> {
+    let (Some(open), Some(close)) = (header.find('('), header.rfind(')')) else {
+        return Vec::new();
+    };
+    split_top_level(header.as_bytes(), open + 1, close)
+        .into_iter()
+        .filter_map(|(from, to)| {
+            let param = header[from..to].trim().trim_start_matches('*');
+            let name = param.split([':', '=']).next()?.trim();
+            is_identifier(name).then(|| Binding { name: name.to_string(), ty: None, mutable: true })
+        })
+        .collect()
+}
+
+/// Names assigned by statements starting in `from..to`: assignment targets,
+/// `for` targets and `with ... as` names
+fn python_assignments(code: &[u8], from: usize, to: usize) -> Vec<Binding> {
+    let text = &code[from..to];
+    let mut names = Vec::new();
+    let mut line_start = 0;
+    while line_start < text.len() {
+        let line_end = text[line_start..].iter().position(|&b| b == b'\n').map_or(text.len(), |i| line_start + i);
+        let line = trim_start(&text[line_start..line_end]);
+        let tokens = identifiers(line);
+        match tokens.first().map(|(_, t)| *t) {
+            Some("for") => {
+                let targets = tokens[1..].iter().take_while(|(_, t)| *t != "in");
+                names.extend(targets.map(|(_, t)| t.to_string()));
+            }
+            Some("with") => {
+                let aliases = tokens.windows(2).filter(|pair| pair[0].1 == "as");
+                names.extend(aliases.map(|pair| pair[1].1.to_string()));
+            }
+            _ => {
+                // Targets are the names before the first assignment operator,
+                // less any annotation
+                let mut depth = 0i32;
+                let operator = (0..line.len()).find(|&i| {
+                    match line[i] {
+                        b'(' | b'[' | b'{' => depth += 1,
+                        b')' | b']' | b'}' => depth -= 1,
+                        _ => {}
+                    }
+                    let after_operator = i > 0 && b"=!<>+-*/%&|^:@".contains(&line[i - 1]);
+                    depth == 0 && !after_operator && line[i] != b' ' && is_assignment(&line[i..])
+                });
+                if let Some(operator) = operator {
+                    let targets = line[..operator].split(|&b| b == b':').next().unwrap_or_default();
+                    if targets.iter().all(|&b| is_ident(b) || b" ,()[]*".contains(&b)) {
+                        names.extend(identifiers(targets).into_iter().map(|(_, t)| t.to_string()));
+                    }
+                }
+            }
+        }
+        line_start = line_end + 1;
+    }
+    names
+        .into_iter()
+        .map(|name| Binding { name, ty: None, mutable: true })
+        .collect()
+}
+
+/// End of the top-level statement containing `offset`: its last non-blank line
+fn python_top_level_end(code: &[u8], offset: usize) -> usize {
+    let mut last_code_line_end = offset.saturating_sub(1);
+    let mut position = offset;
+    while position < code.len() {
+        let end = code[position..].iter().position(|&b| b == b'\n').map_or(code.len(), |i| position + i);
+        let line = &code[position..end];
+        if let Some(first) = line.iter().position(|b| !b.is_ascii_whitespace()) {
+            if first == 0 {
+                break;
+            }
+            last_code_line_end = end.saturating_sub(1);
+        }
+        position = end + 1;
+    }
+    last_code_line_end
+}
+
+fn is_identifier(name: &str) -> bool {
+    let bytes = name.as_bytes();
+    !bytes.is_empty() && !bytes[0].is_ascii_digit() && bytes.iter().all(|&b| is_ident(b))
+}
+
+/// Identifier tokens of masked code with their offsets, skipping numbers
+/// and Rust lifetimes
+fn identifiers(code: &[u8]) -> Vec<(usize, &str)> {
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < code.len() {
+        if !is_ident(code[i]) {
+            i += 1;
+            continue;
+        }
+        let start = i;
+        while i < code.len() && is_ident(code[i]) {
+            i += 1;
+        }
+        let lifetime = start > 0 && code[start - 1] == b'\'';
+        if !code[start].is_ascii_digit() && !lifetime {
+            if let Ok(token) = std::str::from_utf8(&code[start..i]) {
+                tokens.push((start, token));
+            }
+        }
+    }
+    tokens
+}
+
+/// Offsets of whole-word occurrences of `name` in masked code
+fn identifier_offsets(code: &[u8], name: &str) -> Vec<usize> {
+    identifiers(code)
+        .into_iter()
+        .filter(|(_, token)| *token == name)
+        .map(|(offset, _)| offset)
+        .collect()
+}
+
+/// Names read as variables in `from..to`: identifiers that are not field or
+/// method names after `.`, path segments, macro names, struct field labels
+/// or (in Python) plain assignment targets and keyword arguments
+fn reads(code: &[u8], from: usize, to: usize, python: bool) -> BTreeSet<String> {
+    let text = &code[from..to];
+    identifiers(text)
+        .into_iter()
+        .filter(|(offset, token)| {
+            let before = text[..*offset].iter().rev().find(|b| !b.is_ascii_whitespace());
+            if before == Some(&b'.') || text[..*offset].ends_with(b"::") {
+                return false;
+            }
+            let rest = trim_start(&text[offset + token.len()..]);
+            if python {
+                !(rest.first() == Some(&b'=') && rest.get(1) != Some(&b'='))
+            } else {
+                let macro_name = rest.first() == Some(&b'!') && rest.get(1) != Some(&b'=');
+                !macro_name && rest.first() != Some(&b':')
+            }
+        })
+        .map(|(_, token)| token.to_string())
+        .collect()
+}
+
+fn is_balanced(code: &[u8]) -> bool {
+    let mut depth = 0i32;
+    for byte in code {
+        match byte {
+            b'(' | b'[' | b'{' => depth += 1,
+            b')' | b']' | b'}' => depth -= 1,
+            _ => {}
+        }
+        if depth < 0 {
+            return false;
+        }
+    }
+    depth == 0
+}
+
+/// Net bracket depth of `from..to`, counting generic angle brackets
+fn depth_between(code: &[u8], from: usize, to: usize) -> i32 {
+    (from..to).fold(0, |depth, i| match code[i] {
+        b'(' | b'[' | b'{' | b'<' => depth + 1,
+        b')' | b']' | b'}' => depth - 1,
+        b'>' if i == 0 || code[i - 1] != b'-' => depth - 1,
+        _ => depth,
+    })
+}
+
+/// The bracket closing the one at `open`
+fn matching(code: &[u8], open: usize) -> Option<usize> {
+    let mut depth = 0i32;
+    for (i, byte) in code.iter().enumerate().skip(open) {
+        match byte {
+            b'(' | b'[' | b'{' => depth += 1,
+            b')' | b']' | b'}' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(i);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Ranges between commas at bracket depth zero within `from..to`
+fn split_top_level(code: &[u8], from: usize, to: usize) -> Vec<(usize, usize)> {
+    let mut parts = Vec::new();
+    let mut depth = 0i32;
+    let mut part_start = from;
+    for i in from..to {
+        match code[i] {
+            b'(' | b'[' | b'{' | b'<' => depth += 1,
+            b')' | b']' | b'}' => depth -= 1,
+            b'>' if code[i - 1] != b'-' => depth -= 1,
+            b',' if depth == 0 => {
+                parts.push((part_start, i));
+                part_start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push((part_start, to));
+    parts
+}
+
+/// The `:` separating a parameter pattern from its type
+fn type_colon(param: &str) -> Option<usize> {
+    let bytes = param.as_bytes();
+    (0..bytes.len()).find(|&i| bytes[i] == b':' && bytes.get(i + 1) != Some(&b':') && (i == 0 || bytes[i - 1] != b':'))
+}
+
+fn find_byte(code: &[u8], from: usize, byte: u8) -> Option<usize> {
+    code.get(from..)?.iter().position(|&b| b == byte).map(|i| from + i)
+}
+
+fn next_non_space(code: &[u8], from: usize, to: usize) -> Option<usize> {
+    (from..to).find(|&i| !code[i].is_ascii_whitespace())
+}
+
+fn trim_start(bytes: &[u8]) -> &[u8] {
+    let start = bytes.iter().position(|b| !b.is_ascii_whitespace()).unwrap_or(bytes.len());
+    &bytes[start..]
+}
+
+/// Leading whitespace of the line containing `offset`
+fn line_indent(content: &str, offset: usize) -> &str {
+    let line_start = content[..offset].rfind('\n').map_or(0, |i| i + 1);
+    let line = &content[line_start..];
+    &line[..line.len() - line.trim_start_matches([' ', '\t']).len()]
+}
+
+/// Strip the indentation common to all non-blank lines of `text`, returning
+/// it with the dedented text
+fn reindent(text: &str) -> (&str, String) {
+    let common = text
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| &line[..line.len() - line.trim_start_matches([' ', '\t']).len()])
+        .reduce(|common, indent| {
+            let shared = common.bytes().zip(indent.bytes()).take_while(|(a, b)| a == b).count();
+            &common[..shared]
+        })
+        .unwrap_or("");
+    let dedented = text
+        .lines()
+        .map(|line| line.strip_prefix(common).unwrap_or_else(|| line.trim_start()))
+        .collect::<Vec<_>>()
+        .join("\n");
+    (common, dedented)
+}
+
+/// One level of indentation, as the file already uses it
+fn indent_unit(outer: &str, inner: &str) -> String {
+    if inner.starts_with('\t') || outer.starts_with('\t') {
+        "\t".to_string()
+    } else {
+        "    ".to_string()
+    }
+}
+
+fn push_indented(out: &mut String, text: &str, indent: &str) {
+    for line in text.lines() {
+        if !line.trim().is_empty() {
+            out.push_str(indent);
+            out.push_str(line);
+        }
+        out.push('\n');
+    }
+}
+
+/// `content` with sorted, non-overlapping `edits` applied
+fn apply(content: &str, edits: &[Edit]) -> String {
+    let mut sorted: Vec<&Edit> = edits.iter().collect();
+    sorted.sort_by_key(|edit| (edit.start, edit.end));
+    let mut output = String::with_capacity(content.len());
+    let mut at = 0;
+    for edit in sorted {
+        output.push_str(&content[at..edit.start]);
+        output.push_str(&edit.text);
+        at = edit.end;
+    }
+    output.push_str(&content[at..]);
+    output
+}
+
+/// Lines replaced by a run of edits: `removed` starts at zero-based `old_start`
+struct Block<'a> {
+    old_start: usize,
+    removed: Vec<&'a str>,
+    added: Vec<String>,
+}
+
+/// Unified diff of one file's sorted edits; empty if they change nothing
+fn file_diff(path: &Path, content: &str, lines: &LineIndex, edits: &[&Edit]) -> String {
+    let line_start = |line: usize| lines.starts.get(line).copied().unwrap_or(content.len());
+    // Exclusive end of the lines an edit touches
+    let touched_end = |edit: &Edit| {
+        if edit.end > edit.start && content.as_bytes()[edit.end - 1] == b'\n' {
+            lines.line_of(edit.end - 1) + 1
+        } else {
+            lines.line_of(edit.end) + 1
+        }
+    };
+
+    let mut blocks = Vec::new();
+    let mut pending = edits.iter().copied().peekable();
+    while let Some(edit) = pending.next() {
+        let first = lines.line_of(edit.start);
+        let mut last = touched_end(edit);
+        let mut group = vec![edit];
+        while let Some(next) = pending.next_if(|next| lines.line_of(next.start) < last) {
+            last = last.max(touched_end(next));
+            group.push(next);
+        }
+
+        let (from, to) = (line_start(first), line_start(last));
+        let mut replaced = String::new();
+        let mut at = from;
+        for edit in group {
+            replaced.push_str(&content[at..edit.start]);
+            replaced.push_str(&edit.text);
+            at = edit.end;
+        }
+        replaced.push_str(&content[at..to]);
+
+        let old: Vec<&str> = content[from..to].split_inclusive('\n').collect();
+        let new: Vec<&str> = replaced.split_inclusive('\n').collect();
+        let prefix = old.iter().zip(&new).take_while(|(a, b)| a == b).count();
+        let suffix = old[prefix..].iter().rev().zip(new[prefix..].iter().rev()).take_while(|(a, b)| a == b).count();
+        if old.len() == prefix + suffix && new.len() == prefix + suffix {
+            continue;
+        }
+        blocks.push(Block {
+            old_start: first + prefix,
+            removed: old[prefix..old.len() - suffix].to_vec(),
+            added: new[prefix..new.len() - suffix].iter().map(|line| line.to_string()).collect(),
+        });
+    }
+    if blocks.is_empty() {
+        return String::new();
+    }
+
+    let display = path.strip_prefix("/").unwrap_or(path).display();
+    let mut diff = format!("diff --git a/{0} b/{0}\n--- a/{0}\n+++ b/{0}\n", display);
+    let old_lines: Vec<&str> = content.split_inclusive('\n').collect();
+    let mut delta = 0isize;
+    let mut index = 0;
+    while index < blocks.len() {
+        // Blocks whose context would overlap share a hunk
+        let mut end = index + 1;
+        while end < blocks.len()
+            && blocks[end].old_start <= blocks[end - 1].old_start + blocks[end - 1].removed.len() + 2 * DIFF_CONTEXT
+        {
+            end += 1;
+        }
+        let hunk = &blocks[index..end];
+        let last = &hunk[hunk.len() - 1];
+        let old_from = hunk[0].old_start.saturating_sub(DIFF_CONTEXT);
+        let old_to = (last.old_start + last.removed.len() + DIFF_CONTEXT).min(old_lines.len());
+
+        let mut body = String::new();
+        let (mut old_count, mut new_count) = (0, 0);
+        let mut at = old_from;
+        let new_from = (old_from as isize + delta) as usize;
+        for block in hunk {
+            for line in &old_lines[at..block.old_start] {
+                push_diff_line(&mut body, ' ', line);
+            }
+            for line in &block.removed {
+                push_diff_line(&mut body, '-', line);
+            }
+            for line in &block.added {
+                push_diff_line(&mut body, '+', line);
+            }
+            old_count += block.old_start - at + block.removed.len();
+            new_count += block.old_start - at + block.added.len();
+            delta += block.added.len() as isize - block.removed.len() as isize;
+            at = block.old_start + block.removed.len();
+        }
+        for line in &old_lines[at..old_to] {
+            push_diff_line(&mut body, ' ', line);
+        }
+        old_count += old_to - at;
+        new_count += old_to - at;
+
+        diff.push_str(&format!("@@ -{} +{} @@\n", hunk_range(old_from, old_count), hunk_range(new_from, new_count)));
+        diff.push_str(&body);
+        index = end;
+    }
+    diff
+}
+
+fn push_diff_line(body: &mut String, marker: char, line: &str) {
+    body.push(marker);
+    body.push_str(line);
+    if !line.ends_with('\n') {
+        body.push_str("\n\\ No newline at end of file\n");
+    }
+}
+
+/// `start,count` of a hunk side from its zero-based first line
+fn hunk_range(start: usize, count: usize) -> String {
+    match count {
+        0 => format!("{},0", start),
+        1 => format!("{}", start + 1),
+        _ => format!("{},{}", start + 1, count),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RefactoringPriority;
+
+    fn opportunity(opportunity_type: RefactoringType, location: CodeLocation, affected_files: &[&str]) -> RefactoringOpportunity {
+        RefactoringOpportunity {
+            id: Uuid::new_v4(),
+            opportunity_type,
+            priority: RefactoringPriority::Medium,
+            title: String::new(),
+            description: String::new(),
+            location,
+            affected_files: affected_files.iter().map(PathBuf::from).collect(),
+            estimated_effort_hours: 1.0,
+            benefits: Vec::new(),
+            risks: Vec::new(),
+            suggested_approach: Vec::new(),
+            automation_available: true,
+            new_name: None,
+        }
+    }
+
+    fn lines(path: &str, start_line: u32, end_line: u32) -> CodeLocation {
+        CodeLocation {
+            file_path: PathBuf::from(path),
+            start_line,
+            start_column: 1,
+            end_line,
+            end_column: 1,
+            start_byte: 0,
+            end_byte: 0,
+        }
+    }
+
+    fn span(path: &str, content: &str, text: &str) -> CodeLocation {
+        let start = content.find(text).unwrap();
+        CodeLocation {
+            start_byte: start as u32,
+            end_byte: (start + text.len()) as u32,
+            ..lines(path, 1, 1)
+        }
+    }
+
+    fn sources(files: &[(&str, &str)]) -> BTreeMap<PathBuf, String> {
+        files.iter().map(|(path, content)| (PathBuf::from(path), content.to_string())).collect()
+    }
+
+    /// Apply a unified diff, failing unless every context and removed line
+    /// matches the original exactly where its hunk says
+    fn apply_diff(originals: &BTreeMap<PathBuf, String>, diff: &str) -> BTreeMap<PathBuf, String> {
+        let mut patched = originals.clone();
+        let mut diff_lines = diff.split_inclusive('\n').peekable();
+        while let Some(line) = diff_lines.next() {
+            let Some(path) = line.strip_prefix("+++ b/") else {
+                continue;
+            };
+            let path = PathBuf::from(path.trim_end());
+            let old: Vec<&str> = originals[&path].split_inclusive('\n').collect();
+            let mut new = String::new();
+            let mut at = 0;
+            while let Some(header) = diff_lines.next_if(|line| line.starts_with("@@ ")) {
+                let old_range = header.split_whitespace().nth(1).unwrap().trim_start_matches('-');
+                let mut range = old_range.split(',').map(|n| n.parse::<usize>().unwrap());
+                let start = range.next().unwrap();
+                let hunk_start = if range.next() == Some(0) { start } else { start - 1 };
+                assert!(hunk_start >= at, "overlapping hunks in {}", path.display());
+                old[at..hunk_start].iter().for_each(|line| new.push_str(line));
+                at = hunk_start;
+
+                let mut hunk: Vec<(char, String)> = Vec::new();
+                while let Some(line) = diff_lines.next_if(|line| !line.starts_with("@@ ") && !line.starts_with("diff ")) {
+                    if line.starts_with('\\') {
+                        hunk.last_mut().unwrap().1.pop();
+                    } else {
+                        hunk.push((line.chars().next().unwrap(), line[1..].to_string()));
+                    }
+                }
+                for (marker, text) in hunk {
+                    match marker {
+                        ' ' | '-' => {
+                            assert_eq!(old.get(at).copied(), Some(text.as_str()), "line {} of {}", at + 1, path.display());
+                            if marker == ' ' {
+                                new.push_str(&text);
+                            }
+                            at += 1;
+                        }
+                        '+' => new.push_str(&text),
+                        _ => panic!("unexpected diff line {:?}", text),
+                    }
+                }
+            }
+            old[at..].iter().for_each(|line| new.push_str(line));
+            patched.insert(path, new);
+        }
+        patched
+    }
+
+    fn applied(originals: &BTreeMap<PathBuf, String>, changes: &[TextChange]) -> BTreeMap<PathBuf, String> {
+        let mut patched = originals.clone();
+        for (path, content) in patched.iter_mut() {
+            let edits: Vec<Edit> = changes
+                .iter()
+                .filter(|change| &change.location.file_path == path)
+                .map(|change| Edit {
+                    path: path.clone(),
+                    start: change.location.start_byte as usize,
+                    end: change.location.end_byte as usize,
+                    text: change.new_text.clone(),
+                })
+                .collect();
+            *content = apply(content, &edits);
+        }
+        patched
+    }
+
+    const CONFIG_RS: &str = r#"/// Reads settings; see parse_config_file for the file variant
+pub fn parse_config(input: &str) -> Config {
+    let message = "parse_config failed";
+    Config::from_str(input).expect(message)
+}
+"#;
+
+    const MAIN_RS: &str = r#"use crate::config::parse_config;
+
+fn main() {
+    // parse_config reads the whole string
+    let config = parse_config(&std::env::args().nth(1).unwrap());
+    run(config);
+}
+"#;
+
+    #[test]
+    fn rename_function_preview_applies_cleanly() {
+        let sources = sources(&[("src/config.rs", CONFIG_RS), ("src/main.rs", MAIN_RS), ("src/other.rs", "fn f() { parse_config(\"\"); }\n")]);
+        let mut rename = opportunity(
+            RefactoringType::RenameFunction,
+            span("src/config.rs", CONFIG_RS, "pub fn parse_config(input: &str) -> Config {"),
+            &["src/config.rs", "src/main.rs"],
+        );
+        rename.new_name = Some("load_config".to_string());
+
+        let preview = preview_sources(&rename, &sources).unwrap();
+        assert_eq!(preview.changes.len(), 3);
+        assert!(preview.changes.iter().all(|change| matches!(change.change_type, ChangeType::Replace)));
+        assert!(!preview.diff.contains("other.rs"));
+
+        let patched = apply_diff(&sources, &preview.diff);
+        assert_eq!(patched, applied(&sources, &preview.changes));
+        assert_eq!(
+            patched[Path::new("src/config.rs")],
+            CONFIG_RS.replace("pub fn parse_config", "pub fn load_config")
+        );
+        assert_eq!(
+            patched[Path::new("src/main.rs")],
+            MAIN_RS
+                .replace("config::parse_config;", "config::load_config;")
+                .replace("= parse_config(", "= load_config(")
+        );
+        assert_eq!(patched[Path::new("src/other.rs")], sources[Path::new("src/other.rs")]);
+    }
+
+    #[test]
+    fn rename_rejects_a_name_already_in_use() {
+        let sources = sources(&[("src/main.rs", MAIN_RS)]);
+        let mut rename = opportunity(RefactoringType::RenameFunction, span("src/main.rs", MAIN_RS, "parse_config"), &[]);
+        rename.new_name = Some("run".to_string());
+
+        let error = preview_sources(&rename, &sources).unwrap_err();
+        assert!(error.to_string().contains("`run` is already used"), "{}", error);
+    }
+
+    const REPORT_RS: &str = "\
+impl Report {
+    pub fn render(&self, rows: &[Row], width: usize) -> String {
+        let mut out = String::new();
+        let title: String = self.title.to_uppercase();
+        let padding: usize = width.saturating_sub(title.len()) / 2;
+        let header: String = format!(\"{}{}\", \" \".repeat(padding), title);
+        out.push_str(&header);
+        for row in rows {
+            out.push_str(&row.to_string());
+        }
+        out
+    }
+}
+";
+
+    #[test]
+    fn extract_method_preview_applies_cleanly() {
+        let sources = sources(&[("src/report.rs", REPORT_RS)]);
+        let mut extract = opportunity(RefactoringType::ExtractMethod, lines("src/report.rs", 4, 6), &["src/report.rs"]);
+        extract.new_name = Some("centered_title".to_string());
+
+        let preview = preview_sources(&extract, &sources).unwrap();
+        let patched = apply_diff(&sources, &preview.diff);
+        assert_eq!(patched, applied(&sources, &preview.changes));
+        assert_eq!(
+            patched[Path::new("src/report.rs")],
+            "\
+impl Report {
+    pub fn render(&self, rows: &[Row], width: usize) -> String {
+        let mut out = String::new();
+        let header = self.centered_title(width);
+        out.push_str(&header);
+        for row in rows {
+            out.push_str(&row.to_string());
+        }
+        out
+    }
+
+    fn centered_title(&self, width: usize) -> String {
+        let title: String = self.title.to_uppercase();
+        let padding: usize = width.saturating_sub(title.len()) / 2;
+        let header: String = format!(\"{}{}\", \" \".repeat(padding), title);
+        header
+    }
+}
+"
+        );
+    }
+
+    const TRAIT_IMPL_RS: &str = "\
+impl Display for Point {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let scale: f64 = 2.0;
+        let x: f64 = self.x;
+        let scaled: f64 = x * scale;
+        write!(f, \"{}\", scaled)
+    }
+}
+";
+
+    #[test]
+    fn extract_method_from_trait_impl_adds_a_free_function() {
+        let sources = sources(&[("src/point.rs", TRAIT_IMPL_RS)]);
+        let extract = opportunity(RefactoringType::ExtractMethod, lines("src/point.rs", 5, 5), &[]);
+
+        let preview = preview_sources(&extract, &sources).unwrap();
+        let patched = apply_diff(&sources, &preview.diff);
+        assert_eq!(
+            patched[Path::new("src/point.rs")],
+            "\
+impl Display for Point {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let scale: f64 = 2.0;
+        let x: f64 = self.x;
+        let scaled = fmt_extracted(scale, x);
+        write!(f, \"{}\", scaled)
+    }
+}
+
+fn fmt_extracted(scale: f64, x: f64) -> f64 {
+    let scaled: f64 = x * scale;
+    scaled
+}
+"
+        );
+    }
+
+    const STATS_PY: &str = "\
+import math
+
+
+def summarize(values, precision=2):
+    total = sum(values)
+    count = len(values)
+    mean = total / count
+    spread = math.sqrt(sum((v - mean) ** 2 for v in values) / count)
+    return round(mean, precision), round(spread, precision)
+
+
+def main():
+    print(summarize([1, 2, 3]))
+";
+
+    #[test]
+    fn extract_method_from_python_returns_assigned_names() {
+        let sources = sources(&[("stats.py", STATS_PY)]);
+        let mut extract = opportunity(RefactoringType::ExtractMethod, lines("stats.py", 7, 8), &[]);
+        extract.new_name = Some("mean_and_spread".to_string());
+
+        let preview = preview_sources(&extract, &sources).unwrap();
+        let patched = apply_diff(&sources, &preview.diff);
+        assert_eq!(patched, applied(&sources, &preview.changes));
+        assert_eq!(
+            patched[Path::new("stats.py")],
+            "\
+import math
+
+
+def summarize(values, precision=2):
+    total = sum(values)
+    count = len(values)
+    mean, spread = mean_and_spread(values, total, count)
+    return round(mean, precision), round(spread, precision)
+
+
+def mean_and_spread(values, total, count):
+    mean = total / count
+    spread = math.sqrt(sum((v - mean) ** 2 for v in values) / count)
+    return mean, spread
+
+
+def main():
+    print(summarize([1, 2, 3]))
+"
+        );
+    }
+
+    #[test]
+    fn extract_method_rejects_early_return() {
+        let content = "fn check(x: i32) -> bool {\n    if x < 0 {\n        return false;\n    }\n    true\n}\n";
+        let sources = sources(&[("src/check.rs", content)]);
+        let extract = opportunity(RefactoringType::ExtractMethod, lines("src/check.rs", 2, 4), &[]);
+
+        let error = preview_sources(&extract, &sources).unwrap_err();
+        assert!(error.to_string().contains("`return`"), "{}", error);
+    }
+
+    #[test]
+    fn preview_stays_within_affected_files() {
+        let sources = sources(&[("src/report.rs", REPORT_RS)]);
+        let extract = opportunity(RefactoringType::ExtractMethod, lines("src/report.rs", 4, 6), &["src/other.rs"]);
+
+        let error = preview_sources(&extract, &sources).unwrap_err();
+        assert!(error.to_string().contains("not among the opportunity's affected files"), "{}", error);
+    }
+
+    #[test]
+    fn diff_marks_missing_trailing_newline() {
+        let content = "fn a() {}\nfn b() {}";
+        let edit = Edit { path: PathBuf::from("x.rs"), start: 13, end: 14, text: "c".to_string() };
+        let lines = LineIndex::new(content);
+
+        let diff = file_diff(Path::new("x.rs"), content, &lines, &[&edit]);
+        assert_eq!(
+            diff,
+            "diff --git a/x.rs b/x.rs\n--- a/x.rs\n+++ b/x.rs\n@@ -1,2 +1,2 @@\n fn a() {}\n-fn b() {}\n\\ No newline at end of file\n+fn c() {}\n\\ No newline at end of file\n"
+        );
+    }
+}
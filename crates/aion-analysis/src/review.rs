@@ -0,0 +1,369 @@
+use crate::{
+    AnalysisContext, AnalysisProject, CodeAnalyzer, FileAnalysisResult, ProjectAnalysisResult, Result,
+    SecuritySeverity, Severity, SourceFile,
+};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Lines added or modified in one file of a diff
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileDiff {
+    /// Path on the head side, relative to the repository root
+    pub path: PathBuf,
+    /// 1-based line numbers on the head side
+    pub changed_lines: BTreeSet<u32>,
+}
+
+impl FileDiff {
+    /// Whether any line in `start..=end` was added or modified
+    pub fn touches(&self, start: u32, end: u32) -> bool {
+        self.changed_lines.range(start..=end.max(start)).next().is_some()
+    }
+}
+
+/// Parse a unified diff (as printed by `git diff`) into the changed lines of
+/// each file; deleted files and pure deletions produce no entries
+pub fn parse_unified_diff(diff: &str) -> Vec<FileDiff> {
+    let mut files: Vec<FileDiff> = Vec::new();
+    let mut current: Option<FileDiff> = None;
+    let mut next_line = 0u32;
+
+    for line in diff.lines() {
+        if let Some(target) = line.strip_prefix("+++ ") {
+            files.extend(current.take().filter(|file| !file.changed_lines.is_empty()));
+            // "+++ /dev/null" marks a deleted file
+            current = target
+                .trim_end()
+                .strip_prefix("b/")
+                .map(|path| FileDiff { path: PathBuf::from(path), changed_lines: BTreeSet::new() });
+        } else if line.starts_with("diff --git ") {
+            files.extend(current.take().filter(|file| !file.changed_lines.is_empty()));
+        } else if let Some(header) = line.strip_prefix("@@ ") {
+            // @@ -old_start,old_len +new_start,new_len @@
+            next_line = header
+                .split_whitespace()
+                .find_map(|range| range.strip_prefix('+'))
+                .and_then(|range| range.split(',').next())
+                .and_then(|start| start.parse().ok())
+                .unwrap_or(0);
+        } else if let Some(file) = current.as_mut() {
+            match line.as_bytes().first() {
+                Some(b'+') => {
+                    file.changed_lines.insert(next_line);
+                    next_line += 1;
+                }
+                Some(b' ') => next_line += 1,
+                _ => {}
+            }
+        }
+    }
+    files.extend(current.filter(|file| !file.changed_lines.is_empty()));
+    files
+}
+
+/// Diff between two refs of the git repository at `repo`, e.g. `origin/main`
+/// and `HEAD`, taken from their merge base as a pull request would show it
+pub fn git_diff(repo: &Path, base: &str, head: &str) -> Result<String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(repo)
+        .args(["diff", "--no-color", "--no-ext-diff", "--unified=0"])
+        .arg(format!("{}...{}", base, head))
+        .output()?;
+    if !output.status.success() {
+        return Err(format!("git diff failed: {}", String::from_utf8_lossy(&output.stderr).trim()).into());
+    }
+    Ok(String::from_utf8(output.stdout)?)
+}
+
+/// Stable identity of a finding
+///
+/// Built from the rule, the file and the flagged line's trimmed text rather
+/// than its line number, so a finding keeps its fingerprint when unrelated
+/// edits move it up or down the file.
+pub fn finding_fingerprint(rule_id: &str, relative_path: &Path, line_text: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(rule_id.as_bytes());
+    hasher.update([0]);
+    hasher.update(relative_path.to_string_lossy().replace('\\', "/").as_bytes());
+    hasher.update([0]);
+    hasher.update(line_text.trim().as_bytes());
+    hasher.finalize()[..16].iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Fingerprints of findings that already exist and should not be reported again
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Baseline {
+    pub fingerprints: HashSet<String>,
+}
+
+impl Baseline {
+    /// Record every finding of a full project analysis
+    pub fn from_analysis(project: &AnalysisProject, result: &ProjectAnalysisResult) -> Self {
+        let mut fingerprints = HashSet::new();
+        for file in &project.files {
+            if let Some(file_result) = result.file_results.get(&file.relative_path) {
+                fingerprints.extend(collect_findings(file, file_result).into_iter().map(|f| f.fingerprint));
+            }
+        }
+        Self { fingerprints }
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        Ok(serde_json::from_str(&std::fs::read_to_string(path)?)?)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        std::fs::write(path, serde_json::to_vec_pretty(self)?)?;
+        Ok(())
+    }
+
+    pub fn contains(&self, fingerprint: &str) -> bool {
+        self.fingerprints.contains(fingerprint)
+    }
+}
+
+/// A finding placed on a line of the diff
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReviewFinding {
+    pub rule_id: String,
+    pub title: String,
+    pub message: String,
+    /// `error`, `warning`, `info` or `hint`
+    pub severity: String,
+    /// Head-side line the comment anchors to
+    pub line: u32,
+    pub end_line: u32,
+    pub fingerprint: String,
+}
+
+/// Findings on the changed lines of one file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileReview {
+    pub path: PathBuf,
+    pub findings: Vec<ReviewFinding>,
+}
+
+/// Outcome of reviewing a diff
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ReviewReport {
+    /// Files with at least one finding, ordered by path
+    pub files: Vec<FileReview>,
+    /// Findings dropped because they are only on unchanged lines
+    pub suppressed_unchanged: usize,
+    /// Findings on changed lines that the baseline already knows about
+    pub suppressed_baseline: usize,
+    /// Changed files that are not part of the project, e.g. binaries or ignored files
+    pub skipped_files: Vec<PathBuf>,
+}
+
+impl ReviewReport {
+    pub fn total_findings(&self) -> usize {
+        self.files.iter().map(|file| file.findings.len()).sum()
+    }
+
+    /// Whether any finding is an error, for failing a CI check
+    pub fn has_errors(&self) -> bool {
+        self.files.iter().flat_map(|file| &file.findings).any(|finding| finding.severity == "error")
+    }
+
+    /// Markdown summary suitable for a pull request comment
+    pub fn to_markdown(&self) -> String {
+        let mut out = String::from("## Code review\n\n");
+        if self.files.is_empty() {
+            out.push_str("No new findings on the changed lines.\n");
+        } else {
+            let _ = writeln!(
+                out,
+                "{} new finding(s) in {} file(s).\n",
+                self.total_findings(),
+                self.files.len()
+            );
+            for file in &self.files {
+                let path = file.path.to_string_lossy().replace('\\', "/");
+                let _ = writeln!(out, "### `{}`\n", path);
+                for finding in &file.findings {
+                    let lines = if finding.end_line > finding.line {
+                        format!("L{}-L{}", finding.line, finding.end_line)
+                    } else {
+                        format!("L{}", finding.line)
+                    };
+                    let _ = writeln!(
+                        out,
+                        "- **{}** [{}]({}#{}) `{}`: {}",
+                        finding.severity, lines, path, lines, finding.rule_id, finding.message
+                    );
+                }
+                out.push('\n');
+            }
+        }
+        if self.suppressed_baseline > 0 {
+            let _ = writeln!(out, "_{} pre-existing finding(s) on changed lines were not repeated._", self.suppressed_baseline);
+        }
+        out
+    }
+}
+
+/// Analyze the files touched by `diff` and keep only findings on added or
+/// modified lines
+///
+/// Files are analyzed whole so rules still see their full context; the diff
+/// only decides which findings are reported. Findings whose fingerprint is
+/// in `baseline` are treated as pre-existing and dropped as well.
+pub async fn review_diff<A>(
+    analyzer: &A,
+    project: &AnalysisProject,
+    diff: &str,
+    baseline: Option<&Baseline>,
+    ctx: &AnalysisContext,
+) -> Result<ReviewReport>
+where
+    A: CodeAnalyzer + Sync + ?Sized,
+{
+    let mut report = ReviewReport::default();
+    let mut reviews = BTreeMap::new();
+
+    for file_diff in parse_unified_diff(diff) {
+        let Some(file) = project.files.iter().find(|file| file.relative_path == file_diff.path) else {
+            report.skipped_files.push(file_diff.path);
+            continue;
+        };
+
+        let result = analyzer.analyze_file(file, ctx).await?;
+        let mut findings = Vec::new();
+        for finding in collect_findings(file, &result) {
+            if !file_diff.touches(finding.line, finding.end_line) {
+                report.suppressed_unchanged += 1;
+            } else if baseline.is_some_and(|baseline| baseline.contains(&finding.fingerprint)) {
+                report.suppressed_baseline += 1;
+            } else {
+                findings.push(finding);
+            }
+        }
+
+        if !findings.is_empty() {
+            findings.sort_by_key(|finding| (finding.line, finding.rule_id.clone()));
+            reviews.insert(file_diff.path.clone(), FileReview { path: file_diff.path, findings });
+        }
+    }
+
+    report.files = reviews.into_values().collect();
+    Ok(report)
+}
+
+/// Review the changes between `base` and `head` of the repository the
+/// project was imported from
+pub async fn review_refs<A>(
+    analyzer: &A,
+    project: &AnalysisProject,
+    base: &str,
+    head: &str,
+    baseline: Option<&Baseline>,
+    ctx: &AnalysisContext,
+) -> Result<ReviewReport>
+where
+    A: CodeAnalyzer + Sync + ?Sized,
+{
+    let diff = git_diff(&project.root_path, base, head)?;
+    review_diff(analyzer, project, &diff, baseline, ctx).await
+}
+
+/// Issues and security findings of a file in one reviewable shape
+fn collect_findings(file: &SourceFile, result: &FileAnalysisResult) -> Vec<ReviewFinding> {
+    let lines: Vec<&str> = file.content.lines().collect();
+    let line_text = |line: u32| lines.get((line as usize).wrapping_sub(1)).copied().unwrap_or_default();
+
+    let issues = result.issues.iter().map(|issue| {
+        let line = issue.location.start_line.max(1);
+        ReviewFinding {
+            fingerprint: finding_fingerprint(&issue.rule_id, &file.relative_path, line_text(line)),
+            rule_id: issue.rule_id.clone(),
+            title: issue.rule_name.clone(),
+            message: issue.message.clone(),
+            severity: match issue.severity {
+                Severity::Error => "error",
+                Severity::Warning => "warning",
+                Severity::Info => "info",
+                Severity::Hint => "hint",
+            }
+            .to_string(),
+            line,
+            end_line: issue.location.end_line.max(line),
+        }
+    });
+
+    let security = result.security_findings.iter().map(|finding| {
+        let line = finding.location.start_line.max(1);
+        let rule_id = finding.cwe_id.clone().unwrap_or_else(|| format!("{:?}", finding.vulnerability_type));
+        ReviewFinding {
+            fingerprint: finding_fingerprint(&rule_id, &file.relative_path, line_text(line)),
+            rule_id,
+            title: finding.title.clone(),
+            message: finding.description.clone(),
+            severity: match finding.severity {
+                SecuritySeverity::Critical | SecuritySeverity::High => "error",
+                SecuritySeverity::Medium => "warning",
+                SecuritySeverity::Low | SecuritySeverity::Info => "info",
+            }
+            .to_string(),
+            line,
+            end_line: finding.location.end_line.max(line),
+        }
+    });
+
+    issues.chain(security).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DIFF: &str = "\
+diff --git a/src/lib.rs b/src/lib.rs
+index 1111111..2222222 100644
+--- a/src/lib.rs
++++ b/src/lib.rs
+@@ -1,3 +1,4 @@
+ fn a() {}
+-fn b() {}
++fn b() { todo!() }
++fn c() {}
+ fn d() {}
+@@ -10,2 +11,0 @@
+-fn gone() {}
+-fn gone_too() {}
+diff --git a/old.rs b/old.rs
+deleted file mode 100644
+--- a/old.rs
++++ /dev/null
+@@ -1 +0,0 @@
+-fn old() {}
+";
+
+    #[test]
+    fn parses_changed_head_lines() {
+        let files = parse_unified_diff(DIFF);
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].path, PathBuf::from("src/lib.rs"));
+        assert_eq!(files[0].changed_lines.iter().copied().collect::<Vec<_>>(), vec![2, 3]);
+        assert!(files[0].touches(1, 2));
+        assert!(!files[0].touches(4, 10));
+    }
+
+    #[test]
+    fn fingerprint_ignores_line_position_and_indentation() {
+        let path = Path::new("src/lib.rs");
+        assert_eq!(
+            finding_fingerprint("todo", path, "    todo!()"),
+            finding_fingerprint("todo", path, "todo!()")
+        );
+        assert_ne!(
+            finding_fingerprint("todo", path, "todo!()"),
+            finding_fingerprint("todo", Path::new("src/main.rs"), "todo!()")
+        );
+    }
+}
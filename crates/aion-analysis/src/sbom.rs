@@ -0,0 +1,751 @@
+//! CycloneDX 1.5 SBOM export
+//!
+//! Builds a software bill of materials from the dependencies of an
+//! [`AnalysisProject`]: one component per dependency with its license, hashes
+//! and package URL, the dependency graph rooted at the project, and the known
+//! vulnerabilities attached to each dependency.
+
+use crate::{
+    AnalysisProject, Dependency, DependencySource, DependencyType, HashAlgorithm, Language, Result,
+    SecuritySeverity,
+};
+use chrono::{SecondsFormat, Utc};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use uuid::Uuid;
+
+pub const CYCLONEDX_SPEC_VERSION: &str = "1.5";
+
+/// SPDX identifiers emitted as `license.id`; anything else is emitted as a
+/// license name or, when it combines licenses, as an SPDX expression
+const SPDX_LICENSE_IDS: &[&str] = &[
+    "0BSD", "AGPL-3.0-only", "AGPL-3.0-or-later", "Apache-1.1", "Apache-2.0", "Artistic-2.0",
+    "BSD-2-Clause", "BSD-3-Clause", "BSL-1.0", "CC0-1.0", "CC-BY-4.0", "CDDL-1.0", "EPL-1.0",
+    "EPL-2.0", "GPL-2.0-only", "GPL-2.0-or-later", "GPL-3.0-only", "GPL-3.0-or-later", "ISC",
+    "LGPL-2.1-only", "LGPL-2.1-or-later", "LGPL-3.0-only", "LGPL-3.0-or-later", "MIT", "MIT-0",
+    "MPL-2.0", "OpenSSL", "PSF-2.0", "Python-2.0", "Unicode-3.0", "Unicode-DFS-2016", "Unlicense",
+    "Zlib",
+];
+
+/// Serialize `project` as a CycloneDX 1.5 JSON document
+///
+/// Dependencies that appear more than once with the same name and version are
+/// merged into one component. `depends_on` entries naming no known dependency
+/// are left out of the graph.
+pub fn export_sbom(project: &AnalysisProject) -> Result<String> {
+    let bom = build_bom(project);
+    Ok(serde_json::to_string_pretty(&bom)?)
+}
+
+fn build_bom(project: &AnalysisProject) -> Bom {
+    let root_ref = format!("project:{}", project.id);
+    let ecosystem = purl_type(&project.language);
+
+    // Unique components keyed by bom-ref, in first-seen order
+    let mut refs: Vec<String> = Vec::new();
+    let mut by_ref: HashMap<String, &Dependency> = HashMap::new();
+    for dependency in &project.dependencies {
+        let bom_ref = component_ref(dependency, ecosystem);
+        if !by_ref.contains_key(&bom_ref) {
+            refs.push(bom_ref.clone());
+            by_ref.insert(bom_ref, dependency);
+        }
+    }
+    let resolver = RefResolver::new(&refs, &by_ref);
+
+    let components = refs
+        .iter()
+        .map(|bom_ref| component(bom_ref, by_ref[bom_ref], ecosystem))
+        .collect();
+
+    let mut dependencies = vec![BomDependency {
+        bom_ref: root_ref.clone(),
+        depends_on: refs.iter().filter(|r| by_ref[*r].direct).cloned().collect(),
+    }];
+    for bom_ref in &refs {
+        let mut depends_on: Vec<String> = by_ref[bom_ref]
+            .depends_on
+            .iter()
+            .filter_map(|target| {
+                let resolved = resolver.resolve(target);
+                if resolved.is_none() {
+                    tracing::debug!("SBOM: {} depends on unknown dependency {}", bom_ref, target);
+                }
+                resolved
+            })
+            .filter(|target| target != bom_ref)
+            .collect();
+        depends_on.sort();
+        depends_on.dedup();
+        dependencies.push(BomDependency { bom_ref: bom_ref.clone(), depends_on });
+    }
+
+    Bom {
+        bom_format: "CycloneDX",
+        spec_version: CYCLONEDX_SPEC_VERSION,
+        serial_number: format!("urn:uuid:{}", Uuid::new_v4()),
+        version: 1,
+        metadata: Metadata {
+            timestamp: Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true),
+            tools: Tools {
+                components: vec![ToolComponent {
+                    component_type: "application",
+                    name: env!("CARGO_PKG_NAME"),
+                    version: env!("CARGO_PKG_VERSION"),
+                }],
+            },
+            component: RootComponent {
+                component_type: "application",
+                bom_ref: root_ref,
+                name: project.name.clone(),
+            },
+        },
+        components,
+        dependencies,
+        vulnerabilities: vulnerabilities(&refs, &by_ref),
+    }
+}
+
+fn component(bom_ref: &str, dependency: &Dependency, ecosystem: &str) -> Component {
+    let scope = match dependency.dependency_type {
+        DependencyType::Production | DependencyType::Peer => "required",
+        DependencyType::Optional => "optional",
+        // Not part of the shipped artifact
+        DependencyType::Development | DependencyType::Build => "excluded",
+    };
+
+    Component {
+        component_type: "library",
+        bom_ref: bom_ref.to_string(),
+        name: dependency.name.clone(),
+        version: dependency.version.clone(),
+        scope,
+        purl: purl(dependency, ecosystem),
+        licenses: dependency.license.as_deref().map(license).into_iter().collect(),
+        hashes: dependency
+            .hashes
+            .iter()
+            .map(|hash| Hash {
+                alg: hash_algorithm(hash.algorithm),
+                content: hash.value.to_lowercase(),
+            })
+            .collect(),
+        properties: vec![
+            Property {
+                name: "aion:dependency:direct",
+                value: dependency.direct.to_string(),
+            },
+            Property {
+                name: "aion:dependency:type",
+                value: format!("{:?}", dependency.dependency_type).to_lowercase(),
+            },
+        ],
+    }
+}
+
+/// Vulnerabilities across all components; an advisory affecting several
+/// components is listed once with every affected component
+fn vulnerabilities(refs: &[String], by_ref: &HashMap<String, &Dependency>) -> Vec<Vulnerability> {
+    let mut by_id: BTreeMap<String, Vulnerability> = BTreeMap::new();
+    let mut anonymous = Vec::new();
+
+    for bom_ref in refs {
+        let dependency = by_ref[bom_ref];
+        for advisory in &dependency.vulnerabilities {
+            let affects = Affects {
+                affected_ref: bom_ref.clone(),
+                versions: vec![AffectedVersion {
+                    version: dependency.version.clone(),
+                    status: "affected",
+                }],
+            };
+
+            if let Some(vulnerability) = advisory.cve_id.as_ref().and_then(|id| by_id.get_mut(id)) {
+                vulnerability.affects.push(affects);
+                continue;
+            }
+
+            let recommendation = (!advisory.fixed_versions.is_empty())
+                .then(|| format!("Upgrade {} to {}", dependency.name, advisory.fixed_versions.join(" or ")));
+            let vulnerability = Vulnerability {
+                id: advisory.cve_id.clone(),
+                source: advisory.cve_id.as_deref().and_then(advisory_source),
+                ratings: vec![Rating { severity: severity(&advisory.severity) }],
+                description: if advisory.description.is_empty() {
+                    advisory.title.clone()
+                } else {
+                    advisory.description.clone()
+                },
+                recommendation,
+                advisories: advisory
+                    .references
+                    .iter()
+                    .map(|url| Advisory { url: url.clone() })
+                    .collect(),
+                affects: vec![affects],
+            };
+            match advisory.cve_id.clone() {
+                Some(id) => {
+                    by_id.insert(id, vulnerability);
+                }
+                None => anonymous.push(vulnerability),
+            }
+        }
+    }
+
+    by_id.into_values().chain(anonymous).collect()
+}
+
+/// Resolves `name` and `name@version` references to component bom-refs
+struct RefResolver {
+    exact: HashMap<String, String>,
+    by_name: HashMap<String, String>,
+}
+
+impl RefResolver {
+    fn new(refs: &[String], by_ref: &HashMap<String, &Dependency>) -> Self {
+        let mut exact = HashMap::new();
+        let mut by_name = HashMap::new();
+        for bom_ref in refs {
+            let dependency = by_ref[bom_ref];
+            exact.insert(format!("{}@{}", dependency.name, dependency.version), bom_ref.clone());
+            // A bare name refers to the first version seen
+            by_name.entry(dependency.name.clone()).or_insert_with(|| bom_ref.clone());
+        }
+        Self { exact, by_name }
+    }
+
+    fn resolve(&self, target: &str) -> Option<String> {
+        self.exact
+            .get(target)
+            .or_else(|| self.by_name.get(target))
+            .cloned()
+    }
+}
+
+fn component_ref(dependency: &Dependency, ecosystem: &str) -> String {
+    purl(dependency, ecosystem).unwrap_or_else(|| format!("{}@{}", dependency.name, dependency.version))
+}
+
+fn purl_type(language: &Language) -> &'static str {
+    match language {
+        Language::Rust => "cargo",
+        Language::JavaScript | Language::TypeScript => "npm",
+        Language::Python => "pypi",
+        Language::Go => "golang",
+        Language::Java => "maven",
+        Language::CSharp => "nuget",
+        _ => "generic",
+    }
+}
+
+/// Package URL of a registry dependency; git and local sources have none
+fn purl(dependency: &Dependency, ecosystem: &str) -> Option<String> {
+    if !matches!(dependency.source, DependencySource::Registry) {
+        return None;
+    }
+    let name = match ecosystem {
+        // group:artifact -> group/artifact
+        "maven" => dependency.name.replacen(':', "/", 1),
+        // @scope/name -> %40scope/name
+        "npm" => dependency.name.replacen('@', "%40", 1),
+        "pypi" => dependency.name.to_lowercase().replace('_', "-"),
+        _ => dependency.name.clone(),
+    };
+    Some(format!("pkg:{}/{}@{}", ecosystem, name, percent_encode_version(&dependency.version)))
+}
+
+fn percent_encode_version(version: &str) -> String {
+    version
+        .chars()
+        .map(|c| match c {
+            'A'..='Z' | 'a'..='z' | '0'..='9' | '.' | '-' | '_' | '~' => c.to_string(),
+            other => {
+                let mut buf = [0u8; 4];
+                other
+                    .encode_utf8(&mut buf)
+                    .bytes()
+                    .map(|b| format!("%{:02X}", b))
+                    .collect()
+            }
+        })
+        .collect()
+}
+
+fn license(license: &str) -> LicenseChoice {
+    let license = license.trim();
+    if [" OR ", " AND ", " WITH ", "/"].iter().any(|op| license.contains(op)) {
+        // Cargo's legacy "MIT/Apache-2.0" means either license
+        return LicenseChoice::Expression {
+            expression: license.replace('/', " OR "),
+        };
+    }
+    match SPDX_LICENSE_IDS.iter().find(|id| id.eq_ignore_ascii_case(license)) {
+        Some(id) => LicenseChoice::License { license: License::Id { id: id.to_string() } },
+        None => LicenseChoice::License { license: License::Name { name: license.to_string() } },
+    }
+}
+
+fn hash_algorithm(algorithm: HashAlgorithm) -> &'static str {
+    match algorithm {
+        HashAlgorithm::Md5 => "MD5",
+        HashAlgorithm::Sha1 => "SHA-1",
+        HashAlgorithm::Sha256 => "SHA-256",
+        HashAlgorithm::Sha384 => "SHA-384",
+        HashAlgorithm::Sha512 => "SHA-512",
+        HashAlgorithm::Sha3_256 => "SHA3-256",
+        HashAlgorithm::Sha3_512 => "SHA3-512",
+        HashAlgorithm::Blake2b256 => "BLAKE2b-256",
+        HashAlgorithm::Blake3 => "BLAKE3",
+    }
+}
+
+fn severity(severity: &SecuritySeverity) -> &'static str {
+    match severity {
+        SecuritySeverity::Critical => "critical",
+        SecuritySeverity::High => "high",
+        SecuritySeverity::Medium => "medium",
+        SecuritySeverity::Low => "low",
+        SecuritySeverity::Info => "info",
+    }
+}
+
+/// Database an advisory id belongs to, derived from its prefix
+fn advisory_source(id: &str) -> Option<VulnerabilitySource> {
+    let (name, url) = if id.starts_with("CVE-") {
+        ("NVD", format!("https://nvd.nist.gov/vuln/detail/{}", id))
+    } else if id.starts_with("GHSA-") {
+        ("GitHub", format!("https://github.com/advisories/{}", id))
+    } else if id.starts_with("RUSTSEC-") {
+        ("RustSec", format!("https://rustsec.org/advisories/{}", id))
+    } else if id.starts_with("PYSEC-") {
+        ("PyPA", format!("https://osv.dev/vulnerability/{}", id))
+    } else {
+        return None;
+    };
+    Some(VulnerabilitySource { name, url })
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Bom {
+    bom_format: &'static str,
+    spec_version: &'static str,
+    serial_number: String,
+    version: u32,
+    metadata: Metadata,
+    components: Vec<Component>,
+    dependencies: Vec<BomDependency>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    vulnerabilities: Vec<Vulnerability>,
+}
+
+#[derive(Serialize)]
+struct Metadata {
+    timestamp: String,
+    tools: Tools,
+    component: RootComponent,
+}
+
+#[derive(Serialize)]
+struct Tools {
+    components: Vec<ToolComponent>,
+}
+
+#[derive(Serialize)]
+struct ToolComponent {
+    #[serde(rename = "type")]
+    component_type: &'static str,
+    name: &'static str,
+    version: &'static str,
+}
+
+#[derive(Serialize)]
+struct RootComponent {
+    #[serde(rename = "type")]
+    component_type: &'static str,
+    #[serde(rename = "bom-ref")]
+    bom_ref: String,
+    name: String,
+}
+
+#[derive(Serialize)]
+struct Component {
+    #[serde(rename = "type")]
+    component_type: &'static str,
+    #[serde(rename = "bom-ref")]
+    bom_ref: String,
+    name: String,
+    version: String,
+    scope: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    purl: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    licenses: Vec<LicenseChoice>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    hashes: Vec<Hash>,
+    properties: Vec<Property>,
+}
+
+#[derive(Serialize)]
+#[serde(untagged)]
+enum LicenseChoice {
+    License { license: License },
+    Expression { expression: String },
+}
+
+#[derive(Serialize)]
+#[serde(untagged)]
+enum License {
+    Id { id: String },
+    Name { name: String },
+}
+
+#[derive(Serialize)]
+struct Hash {
+    alg: &'static str,
+    content: String,
+}
+
+#[derive(Serialize)]
+struct Property {
+    name: &'static str,
+    value: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct BomDependency {
+    #[serde(rename = "ref")]
+    bom_ref: String,
+    depends_on: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct Vulnerability {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    source: Option<VulnerabilitySource>,
+    ratings: Vec<Rating>,
+    description: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    recommendation: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    advisories: Vec<Advisory>,
+    affects: Vec<Affects>,
+}
+
+#[derive(Serialize)]
+struct VulnerabilitySource {
+    name: &'static str,
+    url: String,
+}
+
+#[derive(Serialize)]
+struct Rating {
+    severity: &'static str,
+}
+
+#[derive(Serialize)]
+struct Advisory {
+    url: String,
+}
+
+#[derive(Serialize)]
+struct Affects {
+    #[serde(rename = "ref")]
+    affected_ref: String,
+    versions: Vec<AffectedVersion>,
+}
+
+#[derive(Serialize)]
+struct AffectedVersion {
+    version: String,
+    status: &'static str,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{apply_advisories, import_project, parse_osv_advisories};
+    use serde_json::{json, Value};
+
+    /// The parts of the CycloneDX 1.5 JSON schema the exporter emits, with the
+    /// upstream constraints on each
+    fn cyclonedx_schema() -> Value {
+        json!({
+            "$schema": "http://json-schema.org/draft-07/schema#",
+            "type": "object",
+            "required": ["bomFormat", "specVersion"],
+            "properties": {
+                "bomFormat": { "enum": ["CycloneDX"] },
+                "specVersion": { "type": "string" },
+                "serialNumber": {
+                    "type": "string",
+                    "pattern": "^urn:uuid:[0-9a-f]{8}-[0-9a-f]{4}-[1-5][0-9a-f]{3}-[89ab][0-9a-f]{3}-[0-9a-f]{12}$"
+                },
+                "version": { "type": "integer", "minimum": 1 },
+                "metadata": {
+                    "type": "object",
+                    "properties": {
+                        "timestamp": { "type": "string", "format": "date-time" },
+                        "tools": {
+                            "type": "object",
+                            "properties": { "components": { "type": "array", "items": { "$ref": "#/definitions/component" } } }
+                        },
+                        "component": { "$ref": "#/definitions/component" }
+                    }
+                },
+                "components": { "type": "array", "items": { "$ref": "#/definitions/component" }, "uniqueItems": true },
+                "dependencies": {
+                    "type": "array",
+                    "uniqueItems": true,
+                    "items": {
+                        "type": "object",
+                        "required": ["ref"],
+                        "additionalProperties": false,
+                        "properties": {
+                            "ref": { "type": "string", "minLength": 1 },
+                            "dependsOn": { "type": "array", "uniqueItems": true, "items": { "type": "string", "minLength": 1 } }
+                        }
+                    }
+                },
+                "vulnerabilities": { "type": "array", "items": { "$ref": "#/definitions/vulnerability" } }
+            },
+            "definitions": {
+                "component": {
+                    "type": "object",
+                    "required": ["type", "name"],
+                    "properties": {
+                        "type": {
+                            "enum": ["application", "framework", "library", "container", "platform", "operating-system",
+                                     "device", "device-driver", "firmware", "file", "machine-learning-model", "data"]
+                        },
+                        "bom-ref": { "type": "string", "minLength": 1 },
+                        "name": { "type": "string" },
+                        "version": { "type": "string" },
+                        "scope": { "enum": ["required", "optional", "excluded"] },
+                        "purl": { "type": "string" },
+                        "hashes": { "type": "array", "items": { "$ref": "#/definitions/hash" } },
+                        "licenses": { "$ref": "#/definitions/licenseChoice" },
+                        "properties": {
+                            "type": "array",
+                            "items": {
+                                "type": "object",
+                                "required": ["name"],
+                                "properties": { "name": { "type": "string" }, "value": { "type": "string" } }
+                            }
+                        }
+                    }
+                },
+                "hash": {
+                    "type": "object",
+                    "required": ["alg", "content"],
+                    "additionalProperties": false,
+                    "properties": {
+                        "alg": {
+                            "enum": ["MD5", "SHA-1", "SHA-256", "SHA-384", "SHA-512", "SHA3-256", "SHA3-384",
+                                     "SHA3-512", "BLAKE2b-256", "BLAKE2b-384", "BLAKE2b-512", "BLAKE3"]
+                        },
+                        "content": {
+                            "type": "string",
+                            "pattern": "^([a-fA-F0-9]{32}|[a-fA-F0-9]{40}|[a-fA-F0-9]{64}|[a-fA-F0-9]{96}|[a-fA-F0-9]{128})$"
+                        }
+                    }
+                },
+                "licenseChoice": {
+                    "oneOf": [
+                        {
+                            "type": "array",
+                            "items": {
+                                "type": "object",
+                                "required": ["license"],
+                                "additionalProperties": false,
+                                "properties": {
+                                    "license": {
+                                        "type": "object",
+                                        "oneOf": [{ "required": ["id"] }, { "required": ["name"] }],
+                                        "properties": { "id": { "type": "string" }, "name": { "type": "string" } }
+                                    }
+                                }
+                            }
+                        },
+                        {
+                            "type": "array",
+                            "minItems": 1,
+                            "maxItems": 1,
+                            "items": {
+                                "type": "object",
+                                "required": ["expression"],
+                                "additionalProperties": false,
+                                "properties": { "expression": { "type": "string" } }
+                            }
+                        }
+                    ]
+                },
+                "vulnerability": {
+                    "type": "object",
+                    "properties": {
+                        "id": { "type": "string" },
+                        "source": {
+                            "type": "object",
+                            "properties": { "name": { "type": "string" }, "url": { "type": "string" } }
+                        },
+                        "ratings": {
+                            "type": "array",
+                            "items": {
+                                "type": "object",
+                                "properties": {
+                                    "severity": { "enum": ["critical", "high", "medium", "low", "info", "none", "unknown"] }
+                                }
+                            }
+                        },
+                        "description": { "type": "string" },
+                        "recommendation": { "type": "string" },
+                        "advisories": {
+                            "type": "array",
+                            "items": { "type": "object", "required": ["url"], "properties": { "url": { "type": "string" } } }
+                        },
+                        "affects": {
+                            "type": "array",
+                            "uniqueItems": true,
+                            "items": {
+                                "type": "object",
+                                "required": ["ref"],
+                                "properties": {
+                                    "ref": { "type": "string" },
+                                    "versions": {
+                                        "type": "array",
+                                        "items": {
+                                            "type": "object",
+                                            "properties": {
+                                                "version": { "type": "string" },
+                                                "status": { "enum": ["affected", "unaffected", "unknown"] }
+                                            }
+                                        }
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        })
+    }
+
+    fn imported_project() -> AnalysisProject {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("src")).unwrap();
+        std::fs::write(dir.path().join("src/main.rs"), "fn main() {}\n").unwrap();
+        std::fs::write(
+            dir.path().join("Cargo.toml"),
+            "[package]\nname = \"demo\"\n\n[dependencies]\nserde = \"1.0\"\n",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.path().join("Cargo.lock"),
+            r#"version = 3
+
+[[package]]
+name = "demo"
+version = "0.1.0"
+dependencies = ["serde"]
+
+[[package]]
+name = "serde"
+version = "1.0.193"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+checksum = "3C5113243E4A3A1C96587342D067F3E6B0F50790B6CF40D2868EB647A3EEF0E0"
+dependencies = ["serde_derive"]
+
+[[package]]
+name = "serde_derive"
+version = "1.0.193"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+checksum = "43576ca501357b9b071ac53cdc7da8ef0cbd9493d8df094cd821777ea6e894d3"
+"#,
+        )
+        .unwrap();
+
+        let mut project = import_project(dir.path()).unwrap();
+        for dependency in &mut project.dependencies {
+            dependency.license = Some(if dependency.name == "serde" { "MIT/Apache-2.0" } else { "MIT" }.to_string());
+        }
+        let advisories = parse_osv_advisories(
+            r#"{
+                "id": "RUSTSEC-2099-0001",
+                "aliases": ["CVE-2099-0001"],
+                "summary": "Stack overflow on deeply nested input",
+                "affected": [{
+                    "package": { "ecosystem": "crates.io", "name": "serde_derive" },
+                    "ranges": [{ "type": "SEMVER", "events": [{ "introduced": "1.0.0" }, { "fixed": "1.0.194" }] }]
+                }],
+                "database_specific": { "severity": "HIGH" }
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(apply_advisories(&mut project, &advisories), 1);
+        project
+    }
+
+    #[test]
+    fn export_validates_against_cyclonedx_schema() {
+        let project = imported_project();
+        let bom: Value = serde_json::from_str(&export_sbom(&project).unwrap()).unwrap();
+
+        let schema = jsonschema::JSONSchema::compile(&cyclonedx_schema()).unwrap();
+        if let Err(errors) = schema.validate(&bom) {
+            let errors: Vec<String> = errors.map(|e| format!("{} at {}", e, e.instance_path)).collect();
+            panic!("SBOM does not match the CycloneDX schema:\n{}", errors.join("\n"));
+        }
+
+        // References the schema can't check: every ref names a component or the project
+        let root = bom["metadata"]["component"]["bom-ref"].as_str().unwrap();
+        let mut known: Vec<&str> = bom["components"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|component| component["bom-ref"].as_str().unwrap())
+            .collect();
+        known.push(root);
+        for dependency in bom["dependencies"].as_array().unwrap() {
+            assert!(known.contains(&dependency["ref"].as_str().unwrap()));
+            for target in dependency["dependsOn"].as_array().unwrap() {
+                assert!(known.contains(&target.as_str().unwrap()), "unknown dependency {}", target);
+            }
+        }
+        let affected = bom["vulnerabilities"][0]["affects"][0]["ref"].as_str().unwrap();
+        assert_eq!(affected, "pkg:cargo/serde_derive@1.0.193");
+        assert_eq!(bom["vulnerabilities"][0]["ratings"][0]["severity"], "high");
+    }
+
+    #[test]
+    fn graph_roots_direct_dependencies_at_the_project() {
+        let project = imported_project();
+        let bom: Value = serde_json::from_str(&export_sbom(&project).unwrap()).unwrap();
+        let depends_on = |bom_ref: &str| -> Vec<String> {
+            let entry = bom["dependencies"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .find(|dependency| dependency["ref"] == bom_ref)
+                .unwrap();
+            serde_json::from_value(entry["dependsOn"].clone()).unwrap()
+        };
+
+        let root = format!("project:{}", project.id);
+        assert_eq!(depends_on(&root), vec!["pkg:cargo/serde@1.0.193".to_string()]);
+        assert_eq!(depends_on("pkg:cargo/serde@1.0.193"), vec!["pkg:cargo/serde_derive@1.0.193".to_string()]);
+        assert!(depends_on("pkg:cargo/serde_derive@1.0.193").is_empty());
+
+        let serde = bom["components"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|component| component["name"] == "serde")
+            .unwrap();
+        assert_eq!(serde["hashes"][0]["alg"], "SHA-256");
+        assert_eq!(serde["licenses"][0]["expression"], "MIT OR Apache-2.0");
+    }
+}
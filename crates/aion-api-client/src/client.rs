@@ -99,6 +99,11 @@ impl AionClient {
         WebSocketClient::new(&self.base_url, &self.api_key).await
     }
 
+    /// WebSocket URL for `path`, authenticated with this client's API key
+    pub(crate) fn websocket_url(&self, path: &str) -> Result<Url> {
+        websocket_url(&self.base_url, path, &self.api_key)
+    }
+
     /// Internal method to make HTTP GET requests
     pub(crate) async fn get<T>(&self, path: &str) -> Result<T>
     where
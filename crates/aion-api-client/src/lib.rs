@@ -37,6 +37,8 @@ pub mod templates;
 pub mod qa;
 pub mod progress;
 pub mod websocket;
+pub mod reconnect;
+pub mod logs;
 pub mod error;
 
 pub use client::*;
@@ -46,6 +48,8 @@ pub use templates::*;
 pub use qa::*;
 pub use progress::*;
 pub use websocket::*;
+pub use reconnect::*;
+pub use logs::*;
 pub use error::*;
 
 #[cfg(test)]
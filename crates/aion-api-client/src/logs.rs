@@ -0,0 +1,365 @@
+use crate::{error::*, reconnect::ReconnectPolicy};
+use chrono::{DateTime, Utc};
+use futures_util::StreamExt;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+use tokio::net::TcpStream;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tokio_tungstenite::tungstenite::protocol::frame::coding::CloseCode;
+use tokio_tungstenite::{connect_async, tungstenite::Message, MaybeTlsStream, WebSocketStream};
+use url::Url;
+
+type Socket = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogLevel {
+    Trace,
+    Debug,
+    Info,
+    #[serde(alias = "warning")]
+    Warn,
+    Error,
+}
+
+impl FromStr for LogLevel {
+    type Err = AionError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "trace" => Ok(Self::Trace),
+            "debug" => Ok(Self::Debug),
+            "info" => Ok(Self::Info),
+            "warn" | "warning" => Ok(Self::Warn),
+            "error" => Ok(Self::Error),
+            other => Err(AionError::Validation(format!("Unknown log level: {}", other))),
+        }
+    }
+}
+
+impl fmt::Display for LogLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Trace => "trace",
+            Self::Debug => "debug",
+            Self::Info => "info",
+            Self::Warn => "warn",
+            Self::Error => "error",
+        };
+        f.write_str(name)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LogEntry {
+    pub timestamp: DateTime<Utc>,
+    pub level: LogLevel,
+    pub message: String,
+    /// Component, pod or deployment step that emitted the entry
+    #[serde(default, alias = "step", skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub fields: HashMap<String, serde_json::Value>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct LogStreamOptions {
+    /// Keep the stream open for new entries instead of ending after the backlog
+    pub follow: bool,
+    /// Only entries at or after this time
+    pub since: Option<DateTime<Utc>>,
+    /// Only entries at or above this level
+    pub min_level: Option<LogLevel>,
+    pub reconnect: ReconnectPolicy,
+}
+
+#[derive(Debug, Clone)]
+pub enum LogStreamEvent {
+    Entry(LogEntry),
+    /// The connection dropped; the next attempt starts after `retry_in`
+    Disconnected {
+        reason: String,
+        attempt: u32,
+        retry_in: Duration,
+    },
+    /// The connection was re-established and the stream resumed
+    Reconnected,
+}
+
+/// Live log stream of a project that survives dropped connections
+///
+/// After a reconnect the stream resumes from the last entry it delivered, so
+/// entries are neither repeated nor skipped as long as the server replays
+/// history for the `since` parameter.
+pub struct LogStream {
+    receiver: mpsc::Receiver<Result<LogStreamEvent>>,
+    task: JoinHandle<()>,
+}
+
+impl LogStream {
+    /// Connect to the log stream at `url`; the first connection attempt is not retried
+    pub(crate) async fn connect(url: Url, options: LogStreamOptions) -> Result<Self> {
+        let mut cursor = ResumeCursor::new(options.since);
+        let socket = open(&stream_url(&url, &options, cursor.since())).await?;
+
+        let (sender, receiver) = mpsc::channel(256);
+        let task = tokio::spawn(async move {
+            let mut socket = socket;
+            loop {
+                let reason = match pump(&mut socket, &sender, &mut cursor, options.min_level).await {
+                    Pumped::Ended | Pumped::ReceiverGone => return,
+                    Pumped::Dropped(reason) => reason,
+                };
+                match reconnect(&url, &options, &mut cursor, &sender, reason).await {
+                    Some(reconnected) => socket = reconnected,
+                    None => return,
+                }
+            }
+        });
+
+        Ok(Self { receiver, task })
+    }
+
+    /// Next entry or connection event; `None` once the stream has ended
+    pub async fn next(&mut self) -> Option<Result<LogStreamEvent>> {
+        self.receiver.recv().await
+    }
+}
+
+impl Drop for LogStream {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+enum Pumped {
+    /// The server closed the stream normally
+    Ended,
+    /// The connection was lost
+    Dropped(String),
+    /// Nobody is reading the stream anymore
+    ReceiverGone,
+}
+
+async fn pump(
+    socket: &mut Socket,
+    sender: &mpsc::Sender<Result<LogStreamEvent>>,
+    cursor: &mut ResumeCursor,
+    min_level: Option<LogLevel>,
+) -> Pumped {
+    while let Some(message) = socket.next().await {
+        match message {
+            Ok(Message::Text(text)) => {
+                let entry = match serde_json::from_str::<LogEntry>(&text) {
+                    Ok(entry) => entry,
+                    Err(e) => {
+                        tracing::warn!("Skipping malformed log record: {}", e);
+                        continue;
+                    }
+                };
+                if min_level.is_some_and(|min| entry.level < min) || !cursor.admit(&entry) {
+                    continue;
+                }
+                if sender.send(Ok(LogStreamEvent::Entry(entry))).await.is_err() {
+                    return Pumped::ReceiverGone;
+                }
+            }
+            Ok(Message::Close(frame)) => {
+                return match frame {
+                    Some(frame) if frame.code != CloseCode::Normal => {
+                        Pumped::Dropped(format!("closed by server: {} {}", frame.code, frame.reason))
+                    }
+                    _ => Pumped::Ended,
+                };
+            }
+            Ok(_) => {} // Pings are answered by tungstenite
+            Err(e) => return Pumped::Dropped(e.to_string()),
+        }
+    }
+    Pumped::Dropped("connection lost".to_string())
+}
+
+/// Re-open the stream with backoff; `None` when the policy gives up or nobody is listening
+async fn reconnect(
+    url: &Url,
+    options: &LogStreamOptions,
+    cursor: &mut ResumeCursor,
+    sender: &mpsc::Sender<Result<LogStreamEvent>>,
+    mut reason: String,
+) -> Option<Socket> {
+    let mut attempt = 0;
+    loop {
+        attempt += 1;
+        let Some(retry_in) = options.reconnect.delay(attempt) else {
+            let error = AionError::Connection(format!("log stream lost after {} reconnect attempts: {}", attempt - 1, reason));
+            let _ = sender.send(Err(error)).await;
+            return None;
+        };
+        let event = LogStreamEvent::Disconnected { reason: reason.clone(), attempt, retry_in };
+        sender.send(Ok(event)).await.ok()?;
+        tokio::time::sleep(retry_in).await;
+
+        cursor.rewind();
+        match open(&stream_url(url, options, cursor.since())).await {
+            Ok(socket) => {
+                sender.send(Ok(LogStreamEvent::Reconnected)).await.ok()?;
+                return Some(socket);
+            }
+            Err(e) => reason = e.to_string(),
+        }
+    }
+}
+
+async fn open(url: &Url) -> Result<Socket> {
+    let (socket, _) = connect_async(url.as_str()).await?;
+    Ok(socket)
+}
+
+fn stream_url(url: &Url, options: &LogStreamOptions, since: Option<DateTime<Utc>>) -> Url {
+    let mut url = url.clone();
+    {
+        let mut query = url.query_pairs_mut();
+        query.append_pair("follow", if options.follow { "true" } else { "false" });
+        if let Some(since) = since {
+            query.append_pair("since", &since.to_rfc3339());
+        }
+        if let Some(level) = options.min_level {
+            query.append_pair("level", &level.to_string());
+        }
+    }
+    url
+}
+
+/// Tracks delivered entries so a resumed stream picks up where it left off
+///
+/// The server replays from `since` inclusively, so entries sharing the last
+/// delivered timestamp are replayed too; as many of those as were already
+/// delivered are skipped.
+#[derive(Debug)]
+struct ResumeCursor {
+    since: Option<DateTime<Utc>>,
+    last: Option<DateTime<Utc>>,
+    delivered_at_last: usize,
+    to_skip: usize,
+}
+
+impl ResumeCursor {
+    fn new(since: Option<DateTime<Utc>>) -> Self {
+        Self {
+            since,
+            last: None,
+            delivered_at_last: 0,
+            to_skip: 0,
+        }
+    }
+
+    fn since(&self) -> Option<DateTime<Utc>> {
+        self.last.or(self.since)
+    }
+
+    /// Prepare for the replay of a new connection
+    fn rewind(&mut self) {
+        self.to_skip = self.delivered_at_last;
+    }
+
+    /// Whether `entry` is new and should be delivered
+    fn admit(&mut self, entry: &LogEntry) -> bool {
+        if self.since.is_some_and(|since| entry.timestamp < since) {
+            return false;
+        }
+        match self.last {
+            Some(last) if entry.timestamp < last => return false,
+            Some(last) if entry.timestamp == last => {
+                if self.to_skip > 0 {
+                    self.to_skip -= 1;
+                    return false;
+                }
+                self.delivered_at_last += 1;
+            }
+            _ => {
+                self.last = Some(entry.timestamp);
+                self.delivered_at_last = 1;
+                self.to_skip = 0;
+            }
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn entry(second: u32, message: &str) -> LogEntry {
+        LogEntry {
+            timestamp: Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, second).unwrap(),
+            level: LogLevel::Info,
+            message: message.to_string(),
+            source: None,
+            fields: HashMap::new(),
+        }
+    }
+
+    #[test]
+    fn test_resume_skips_replayed_entries_only() {
+        let mut cursor = ResumeCursor::new(None);
+        assert!(cursor.admit(&entry(1, "a")));
+        assert!(cursor.admit(&entry(2, "b")));
+        assert!(cursor.admit(&entry(2, "c")));
+
+        // The server replays everything from second 2 after a reconnect
+        cursor.rewind();
+        assert_eq!(cursor.since(), Some(entry(2, "").timestamp));
+        assert!(!cursor.admit(&entry(1, "a")));
+        assert!(!cursor.admit(&entry(2, "b")));
+        assert!(!cursor.admit(&entry(2, "c")));
+        assert!(cursor.admit(&entry(2, "d")));
+        assert!(cursor.admit(&entry(3, "e")));
+    }
+
+    #[test]
+    fn test_level_parsing_and_ordering() {
+        assert_eq!("WARNING".parse::<LogLevel>().unwrap(), LogLevel::Warn);
+        assert!("verbose".parse::<LogLevel>().is_err());
+        assert!(LogLevel::Error > LogLevel::Warn && LogLevel::Debug < LogLevel::Info);
+    }
+
+    #[test]
+    fn test_stream_url_carries_filters() {
+        let url = Url::parse("wss://api.example.com/ws/projects/p1/logs?api_key=k").unwrap();
+        let options = LogStreamOptions {
+            follow: true,
+            min_level: Some(LogLevel::Warn),
+            ..Default::default()
+        };
+        let since = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+
+        let url = stream_url(&url, &options, Some(since));
+        assert_eq!(
+            url.as_str(),
+            "wss://api.example.com/ws/projects/p1/logs?api_key=k&follow=true&since=2024-01-01T00%3A00%3A00%2B00%3A00&level=warn"
+        );
+    }
+
+    #[test]
+    fn test_parses_server_log_records() {
+        let record = serde_json::json!({
+            "sequence": 7,
+            "deployment_id": "5f0c6a9e-8d3b-4a55-9b51-2f6f2d4b1c10",
+            "timestamp": "2024-01-01T00:00:01Z",
+            "level": "warning",
+            "step": "deploy_worker",
+            "stream": "Stderr",
+            "message": "retrying upload"
+        });
+        let entry: LogEntry = serde_json::from_value(record).unwrap();
+        assert_eq!(entry.level, LogLevel::Warn);
+        assert_eq!(entry.source.as_deref(), Some("deploy_worker"));
+        assert_eq!(entry.message, "retrying upload");
+    }
+}
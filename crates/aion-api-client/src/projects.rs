@@ -1,4 +1,4 @@
-use crate::{types::*, error::Result, client::AionClient};
+use crate::{types::*, error::Result, client::AionClient, logs::{LogStream, LogStreamOptions}};
 use uuid::Uuid;
 
 #[derive(Debug, Clone)]
@@ -116,6 +116,12 @@ impl ProjectsApi {
         self.client.get(&path).await
     }
 
+    /// Open a log stream for the project's latest deployment, reconnecting per `options.reconnect`
+    pub async fn stream_logs(&self, project_id: Uuid, options: LogStreamOptions) -> Result<LogStream> {
+        let url = self.client.websocket_url(&format!("/ws/projects/{}/logs", project_id))?;
+        LogStream::connect(url, options).await
+    }
+
     /// Get project metrics
     pub async fn metrics(&self, project_id: Uuid) -> Result<serde_json::Value> {
         let path = format!("/api/v1/projects/{}/metrics", project_id);
@@ -0,0 +1,76 @@
+use std::time::Duration;
+
+/// Exponential backoff for re-establishing dropped connections
+#[derive(Debug, Clone)]
+pub struct ReconnectPolicy {
+    /// Delay before the first reconnect attempt
+    pub initial_delay: Duration,
+    /// Upper bound for the delay between attempts
+    pub max_delay: Duration,
+    /// Factor applied to the delay after every failed attempt
+    pub multiplier: f64,
+    /// Consecutive failed attempts before giving up; `None` retries forever
+    pub max_attempts: Option<u32>,
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> Self {
+        Self {
+            initial_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(30),
+            multiplier: 2.0,
+            max_attempts: None,
+        }
+    }
+}
+
+impl ReconnectPolicy {
+    /// Policy that never reconnects
+    pub fn disabled() -> Self {
+        Self {
+            max_attempts: Some(0),
+            ..Default::default()
+        }
+    }
+
+    /// Delay before reconnect attempt `attempt` (1-based), or `None` once the
+    /// policy has given up
+    pub fn delay(&self, attempt: u32) -> Option<Duration> {
+        if attempt == 0 || self.max_attempts.is_some_and(|max| attempt > max) {
+            return None;
+        }
+        let factor = self.multiplier.max(1.0).powi(attempt as i32 - 1);
+        let delay = self.initial_delay.as_secs_f64() * factor;
+        Some(Duration::from_secs_f64(delay.min(self.max_delay.as_secs_f64())))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_delay_grows_until_capped() {
+        let policy = ReconnectPolicy {
+            initial_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(5),
+            multiplier: 2.0,
+            max_attempts: None,
+        };
+
+        let delays: Vec<u64> = (1..=5).map(|n| policy.delay(n).unwrap().as_secs()).collect();
+        assert_eq!(delays, vec![1, 2, 4, 5, 5]);
+    }
+
+    #[test]
+    fn test_gives_up_after_max_attempts() {
+        let policy = ReconnectPolicy {
+            max_attempts: Some(2),
+            ..Default::default()
+        };
+
+        assert!(policy.delay(2).is_some());
+        assert!(policy.delay(3).is_none());
+        assert!(ReconnectPolicy::disabled().delay(1).is_none());
+    }
+}
@@ -125,25 +125,7 @@ impl WebSocketClient {
 
     /// Build WebSocket URL from base URL and API key
     fn build_ws_url(base_url: &Url, api_key: &str) -> Result<String> {
-        let mut ws_url = base_url.clone();
-
-        // Convert HTTP(S) scheme to WS(S)
-        let ws_scheme = match ws_url.scheme() {
-            "http" => "ws",
-            "https" => "wss",
-            scheme => return Err(AionError::Url(url::ParseError::InvalidIpv4Address)),
-        };
-
-        ws_url.set_scheme(ws_scheme)
-            .map_err(|_| AionError::Url(url::ParseError::InvalidIpv4Address))?;
-
-        // Set WebSocket path
-        ws_url.set_path("/ws/progress");
-
-        // Add API key as query parameter
-        ws_url.set_query(Some(&format!("api_key={}", api_key)));
-
-        Ok(ws_url.to_string())
+        Ok(websocket_url(base_url, "/ws/progress", api_key)?.to_string())
     }
 
     /// Subscribe to progress events for a specific session
@@ -194,6 +176,28 @@ impl WebSocketClient {
     }
 }
 
+/// WebSocket URL for `path` on the API host, authenticated with the API key
+pub(crate) fn websocket_url(base_url: &Url, path: &str, api_key: &str) -> Result<Url> {
+    let mut ws_url = base_url.clone();
+
+    // Convert HTTP(S) scheme to WS(S)
+    let ws_scheme = match ws_url.scheme() {
+        "http" => "ws",
+        "https" => "wss",
+        _ => return Err(AionError::Url(url::ParseError::InvalidIpv4Address)),
+    };
+
+    ws_url.set_scheme(ws_scheme)
+        .map_err(|_| AionError::Url(url::ParseError::InvalidIpv4Address))?;
+
+    ws_url.set_path(path);
+
+    // Add API key as query parameter
+    ws_url.set_query(Some(&format!("api_key={}", api_key)));
+
+    Ok(ws_url)
+}
+
 /// Stream of events filtered by session ID
 pub struct SessionEventStream {
     receiver: broadcast::Receiver<ProgressEvent>,
@@ -1,23 +1,88 @@
+use aion_monitoring::real_time_monitor::{AlertSeverity, AlertState};
+use aion_monitoring::{AlertEvent, RealTimeMonitor};
 use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::sync::atomic::{AtomicU64, AtomicU8, Ordering};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
-use tokio::sync::RwLock;
+use tokio::sync::{broadcast, RwLock};
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+/// Capacity of the channel carrying `CircuitBreakerEvent`s
+const EVENT_CHANNEL_CAPACITY: usize = 256;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub enum CircuitState {
     Closed = 0,   // Normal operation
     Open = 1,     // Circuit is open, failing fast
     HalfOpen = 2, // Testing if service recovered
 }
 
+/// A breaker changing state, emitted on every transition
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CircuitBreakerEvent {
+    /// Upstream service the breaker guards
+    pub service: String,
+    pub from: CircuitState,
+    pub to: CircuitState,
+    /// Consecutive failures counted at the time of the transition
+    pub failure_count: u64,
+    pub timestamp: DateTime<Utc>,
+}
+
+impl CircuitBreakerEvent {
+    /// The alert this transition raises: critical when the breaker trips,
+    /// a warning while probing, and resolved once it closes again
+    pub fn to_alert(&self) -> AlertEvent {
+        let (severity, state, message) = match self.to {
+            CircuitState::Open => (
+                AlertSeverity::Critical,
+                AlertState::Critical,
+                format!("Circuit breaker for {} opened after {} consecutive failures", self.service, self.failure_count),
+            ),
+            CircuitState::HalfOpen => (
+                AlertSeverity::Medium,
+                AlertState::Warning,
+                format!("Circuit breaker for {} is half-open; probing the upstream", self.service),
+            ),
+            CircuitState::Closed => (
+                AlertSeverity::Info,
+                AlertState::OK,
+                format!("Circuit breaker for {} closed; the upstream recovered", self.service),
+            ),
+        };
+
+        AlertEvent {
+            alert_id: format!("circuit-breaker:{}", self.service),
+            alert_name: "Circuit breaker state change".to_string(),
+            severity,
+            state,
+            message,
+            timestamp: self.timestamp,
+            metric_value: self.failure_count as f64,
+            labels: HashMap::from([
+                ("service".to_string(), self.service.clone()),
+                ("from".to_string(), format!("{:?}", self.from)),
+                ("to".to_string(), format!("{:?}", self.to)),
+            ]),
+        }
+    }
+}
+
+/// Returned by `CircuitBreaker::call` when the breaker rejects the call
+#[derive(Debug, thiserror::Error)]
+#[error("Circuit breaker is open")]
+pub struct CircuitOpenError;
+
 pub struct CircuitBreaker {
     state: AtomicU8,
     failure_count: AtomicU64,
     success_count: AtomicU64,
     last_failure_time: Arc<RwLock<Option<Instant>>>,
     config: CircuitBreakerConfig,
+    service: String,
+    events: Option<broadcast::Sender<CircuitBreakerEvent>>,
 }
 
 #[derive(Debug, Clone)]
@@ -47,9 +112,18 @@ impl CircuitBreaker {
             success_count: AtomicU64::new(0),
             last_failure_time: Arc::new(RwLock::new(None)),
             config,
+            service: String::new(),
+            events: None,
         }
     }
 
+    /// Emit a `CircuitBreakerEvent` for `service` on every state transition
+    pub fn with_events(mut self, service: impl Into<String>, events: broadcast::Sender<CircuitBreakerEvent>) -> Self {
+        self.service = service.into();
+        self.events = Some(events);
+        self
+    }
+
     pub async fn call<F, Fut, T>(&self, operation: F) -> Result<T>
     where
         F: FnOnce() -> Fut,
@@ -57,7 +131,7 @@ impl CircuitBreaker {
     {
         // Check if circuit should allow the call
         if !self.can_execute().await {
-            return Err(anyhow::anyhow!("Circuit breaker is open"));
+            return Err(CircuitOpenError.into());
         }
 
         // Execute the operation
@@ -82,7 +156,7 @@ impl CircuitBreaker {
                 // Check if we should transition to half-open
                 if let Some(last_failure) = *self.last_failure_time.read().await {
                     if last_failure.elapsed() >= self.config.reset_timeout {
-                        self.transition_to_half_open().await;
+                        self.transition_to_half_open();
                         true
                     } else {
                         false
@@ -102,7 +176,7 @@ impl CircuitBreaker {
             CircuitState::HalfOpen => {
                 let success_count = self.success_count.fetch_add(1, Ordering::Relaxed) + 1;
                 if success_count >= self.config.success_threshold {
-                    self.transition_to_closed().await;
+                    self.transition_to_closed();
                 }
             }
             CircuitState::Closed => {
@@ -122,11 +196,11 @@ impl CircuitBreaker {
             CircuitState::Closed => {
                 let failure_count = self.failure_count.fetch_add(1, Ordering::Relaxed) + 1;
                 if failure_count >= self.config.failure_threshold {
-                    self.transition_to_open().await;
+                    self.transition_to_open(CircuitState::Closed).await;
                 }
             }
             CircuitState::HalfOpen => {
-                self.transition_to_open().await;
+                self.transition_to_open(CircuitState::HalfOpen).await;
             }
             CircuitState::Open => {
                 // Already open, update failure time
@@ -136,24 +210,50 @@ impl CircuitBreaker {
         }
     }
 
-    async fn transition_to_closed(&self) {
-        self.state.store(CircuitState::Closed as u8, Ordering::Relaxed);
-        self.failure_count.store(0, Ordering::Relaxed);
-        self.success_count.store(0, Ordering::Relaxed);
-        tracing::info!("Circuit breaker transitioned to CLOSED");
+    /// Move from `from` to `to`, unless a concurrent call already moved the
+    /// breaker; only the call that wins emits the event
+    fn transition(&self, from: CircuitState, to: CircuitState) -> bool {
+        if self.state
+            .compare_exchange(from as u8, to as u8, Ordering::AcqRel, Ordering::Acquire)
+            .is_err()
+        {
+            return false;
+        }
+
+        if let Some(events) = &self.events {
+            // No subscribers is not an error
+            let _ = events.send(CircuitBreakerEvent {
+                service: self.service.clone(),
+                from,
+                to,
+                failure_count: self.failure_count.load(Ordering::Relaxed),
+                timestamp: Utc::now(),
+            });
+        }
+        true
     }
 
-    async fn transition_to_open(&self) {
-        self.state.store(CircuitState::Open as u8, Ordering::Relaxed);
-        let mut last_failure = self.last_failure_time.write().await;
-        *last_failure = Some(Instant::now());
-        tracing::warn!("Circuit breaker transitioned to OPEN");
+    fn transition_to_closed(&self) {
+        if self.transition(CircuitState::HalfOpen, CircuitState::Closed) {
+            self.failure_count.store(0, Ordering::Relaxed);
+            self.success_count.store(0, Ordering::Relaxed);
+            tracing::info!("Circuit breaker for {} transitioned to CLOSED", self.service);
+        }
     }
 
-    async fn transition_to_half_open(&self) {
-        self.state.store(CircuitState::HalfOpen as u8, Ordering::Relaxed);
-        self.success_count.store(0, Ordering::Relaxed);
-        tracing::info!("Circuit breaker transitioned to HALF-OPEN");
+    async fn transition_to_open(&self, from: CircuitState) {
+        if self.transition(from, CircuitState::Open) {
+            let mut last_failure = self.last_failure_time.write().await;
+            *last_failure = Some(Instant::now());
+            tracing::warn!("Circuit breaker for {} transitioned to OPEN", self.service);
+        }
+    }
+
+    fn transition_to_half_open(&self) {
+        if self.transition(CircuitState::Open, CircuitState::HalfOpen) {
+            self.success_count.store(0, Ordering::Relaxed);
+            tracing::info!("Circuit breaker for {} transitioned to HALF-OPEN", self.service);
+        }
     }
 
     pub fn get_state(&self) -> CircuitState {
@@ -189,15 +289,39 @@ pub struct CircuitBreakerMetrics {
 
 pub struct CircuitBreakerManager {
     breakers: Arc<RwLock<HashMap<String, Arc<CircuitBreaker>>>>,
+    events: broadcast::Sender<CircuitBreakerEvent>,
 }
 
 impl CircuitBreakerManager {
     pub fn new() -> Self {
+        let (events, _) = broadcast::channel(EVENT_CHANNEL_CAPACITY);
         Self {
             breakers: Arc::new(RwLock::new(HashMap::new())),
+            events,
         }
     }
 
+    /// Receive the state transitions of every breaker the manager creates
+    pub fn subscribe(&self) -> broadcast::Receiver<CircuitBreakerEvent> {
+        self.events.subscribe()
+    }
+
+    /// Raise an alert on `monitor` for every breaker state transition
+    pub fn forward_alerts(&self, monitor: Arc<RealTimeMonitor>) -> tokio::task::JoinHandle<()> {
+        let mut events = self.subscribe();
+        tokio::spawn(async move {
+            loop {
+                match events.recv().await {
+                    Ok(event) => monitor.raise_alert(event.to_alert()).await,
+                    Err(broadcast::error::RecvError::Lagged(missed)) => {
+                        tracing::warn!("Dropped {} circuit breaker events before alerting", missed);
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                }
+            }
+        })
+    }
+
     pub async fn get_or_create(&self, name: &str, config: Option<CircuitBreakerConfig>) -> Arc<CircuitBreaker> {
         let breakers = self.breakers.read().await;
         if let Some(breaker) = breakers.get(name) {
@@ -211,7 +335,7 @@ impl CircuitBreakerManager {
                 breaker.clone()
             } else {
                 let config = config.unwrap_or_default();
-                let breaker = Arc::new(CircuitBreaker::new(config));
+                let breaker = Arc::new(CircuitBreaker::new(config).with_events(name, self.events.clone()));
                 breakers.insert(name.to_string(), breaker.clone());
                 breaker
             }
@@ -228,4 +352,94 @@ impl CircuitBreakerManager {
 
         metrics
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(reset_timeout: Duration) -> CircuitBreakerConfig {
+        CircuitBreakerConfig {
+            failure_threshold: 3,
+            success_threshold: 2,
+            timeout: Duration::from_secs(1),
+            reset_timeout,
+        }
+    }
+
+    async fn fail(breaker: &CircuitBreaker) -> anyhow::Error {
+        breaker
+            .call(|| async { Err::<(), _>(anyhow::anyhow!("upstream refused connection")) })
+            .await
+            .unwrap_err()
+    }
+
+    fn drain(events: &mut broadcast::Receiver<CircuitBreakerEvent>) -> Vec<CircuitBreakerEvent> {
+        std::iter::from_fn(|| events.try_recv().ok()).collect()
+    }
+
+    #[tokio::test]
+    async fn test_reaching_failure_threshold_emits_one_open_event() {
+        let manager = CircuitBreakerManager::new();
+        let mut events = manager.subscribe();
+        let breaker = manager.get_or_create("billing", Some(config(Duration::from_secs(60)))).await;
+
+        for _ in 0..3 {
+            fail(&breaker).await;
+        }
+        // Further calls fail fast without counting as transitions
+        for _ in 0..3 {
+            assert!(fail(&breaker).await.is::<CircuitOpenError>());
+        }
+
+        let emitted = drain(&mut events);
+        assert_eq!(emitted.len(), 1);
+        assert_eq!(emitted[0].service, "billing");
+        assert_eq!(emitted[0].from, CircuitState::Closed);
+        assert_eq!(emitted[0].to, CircuitState::Open);
+        assert_eq!(emitted[0].failure_count, 3);
+        assert_eq!(breaker.get_state(), CircuitState::Open);
+    }
+
+    #[tokio::test]
+    async fn test_recovery_emits_half_open_then_closed() {
+        let manager = CircuitBreakerManager::new();
+        let mut events = manager.subscribe();
+        let breaker = manager.get_or_create("search", Some(config(Duration::ZERO))).await;
+
+        for _ in 0..3 {
+            fail(&breaker).await;
+        }
+        for _ in 0..2 {
+            breaker.call(|| async { Ok(()) }).await.unwrap();
+        }
+
+        let transitions: Vec<_> = drain(&mut events).iter().map(|event| (event.from, event.to)).collect();
+        assert_eq!(transitions, vec![
+            (CircuitState::Closed, CircuitState::Open),
+            (CircuitState::Open, CircuitState::HalfOpen),
+            (CircuitState::HalfOpen, CircuitState::Closed),
+        ]);
+        assert_eq!(breaker.get_metrics().failure_count, 0);
+    }
+
+    #[tokio::test]
+    async fn test_breaker_trip_surfaces_as_critical_alert() {
+        let manager = CircuitBreakerManager::new();
+        let monitor = Arc::new(RealTimeMonitor::new());
+        let mut alerts = monitor.subscribe_alerts();
+        let forwarder = manager.forward_alerts(monitor.clone());
+
+        let breaker = manager.get_or_create("billing", Some(config(Duration::from_secs(60)))).await;
+        for _ in 0..3 {
+            fail(&breaker).await;
+        }
+
+        let alert = tokio::time::timeout(Duration::from_secs(5), alerts.recv()).await.unwrap().unwrap();
+        assert_eq!(alert.alert_id, "circuit-breaker:billing");
+        assert!(matches!(alert.severity, AlertSeverity::Critical));
+        assert!(matches!(alert.state, AlertState::Critical));
+        assert_eq!(alert.metric_value, 3.0);
+        assert_eq!(monitor.get_alert_history().await.len(), 1);
+        forwarder.abort();
+    }
+}
@@ -1,4 +1,7 @@
-use crate::{LoadBalancer, Router, RateLimiter, HealthChecker};
+use crate::{
+    trace_context, CircuitBreakerEvent, CircuitBreakerManager, CircuitOpenError, HealthChecker,
+    LoadBalancer, LoadBalancingAlgorithm, RateLimiter, Router, TraceContext,
+};
 use aion_core::{PlatformService, ServiceHealth, HealthStatus};
 use anyhow::Result;
 use async_trait::async_trait;
@@ -14,6 +17,7 @@ use std::net::SocketAddr;
 use std::sync::Arc;
 use std::time::Duration;
 use tower::ServiceBuilder;
+use tracing::Instrument;
 use tower_http::{
     cors::CorsLayer,
     trace::TraceLayer,
@@ -31,7 +35,15 @@ pub struct GatewayConfig {
     pub enable_rate_limiting: bool,
     pub enable_circuit_breaker: bool,
     pub enable_load_balancing: bool,
+    #[serde(default)]
+    pub load_balancing_algorithm: LoadBalancingAlgorithm,
     pub health_check_interval_seconds: u64,
+    /// How long in-flight requests may finish on an upstream that failed its health check
+    #[serde(default = "default_drain_timeout_seconds")]
+    pub drain_timeout_seconds: u64,
+    /// Health checks a failed upstream must pass in a row before it gets requests again
+    #[serde(default = "default_healthy_threshold")]
+    pub healthy_threshold: u32,
     pub upstream_services: Vec<UpstreamService>,
 }
 
@@ -43,6 +55,21 @@ pub struct UpstreamService {
     pub weight: u32,
     pub max_connections: u32,
     pub timeout_seconds: u64,
+    /// Whether the gateway is unhealthy while this upstream is down, rather than degraded
+    #[serde(default = "default_critical")]
+    pub critical: bool,
+}
+
+fn default_critical() -> bool {
+    true
+}
+
+fn default_drain_timeout_seconds() -> u64 {
+    crate::DEFAULT_DRAIN_TIMEOUT.as_secs()
+}
+
+fn default_healthy_threshold() -> u32 {
+    crate::DEFAULT_HEALTHY_THRESHOLD
 }
 
 pub struct EnterpriseApiGateway {
@@ -51,6 +78,7 @@ pub struct EnterpriseApiGateway {
     router: Arc<Router>,
     rate_limiter: Arc<RateLimiter>,
     health_checker: Arc<HealthChecker>,
+    circuit_breakers: Arc<CircuitBreakerManager>,
     start_time: std::time::Instant,
 }
 
@@ -58,10 +86,20 @@ impl EnterpriseApiGateway {
     pub async fn new(config: GatewayConfig) -> Result<Self> {
         let config = Arc::new(config);
 
-        let load_balancer = Arc::new(LoadBalancer::new(config.upstream_services.clone()).await?);
+        let load_balancer = Arc::new(
+            LoadBalancer::new(config.upstream_services.clone())
+                .await?
+                .with_algorithm(config.load_balancing_algorithm.clone())
+                .with_draining(Duration::from_secs(config.drain_timeout_seconds), config.healthy_threshold),
+        );
         let router = Arc::new(Router::new().await?);
         let rate_limiter = Arc::new(RateLimiter::new().await?);
-        let health_checker = Arc::new(HealthChecker::new(config.clone()).await?);
+        let health_checker = Arc::new(
+            HealthChecker::new(config.clone())
+                .await?
+                .with_load_balancer(load_balancer.clone()),
+        );
+        let circuit_breakers = Arc::new(CircuitBreakerManager::new());
 
         Ok(Self {
             config,
@@ -69,10 +107,27 @@ impl EnterpriseApiGateway {
             router,
             rate_limiter,
             health_checker,
+            circuit_breakers,
             start_time: std::time::Instant::now(),
         })
     }
 
+    /// Replace the rate limiter, e.g. with one that has a key extractor and per-key limits
+    pub fn with_rate_limiter(mut self, rate_limiter: RateLimiter) -> Self {
+        self.rate_limiter = Arc::new(rate_limiter);
+        self
+    }
+
+    /// Receive a `CircuitBreakerEvent` whenever an upstream's breaker changes state
+    pub fn subscribe_circuit_breaker_events(&self) -> tokio::sync::broadcast::Receiver<CircuitBreakerEvent> {
+        self.circuit_breakers.subscribe()
+    }
+
+    /// Surface circuit breaker transitions as alerts on `monitor`
+    pub fn forward_alerts_to(&self, monitor: Arc<aion_monitoring::RealTimeMonitor>) -> tokio::task::JoinHandle<()> {
+        self.circuit_breakers.forward_alerts(monitor)
+    }
+
     pub fn create_app(&self) -> AxumRouter {
         let app = AxumRouter::new()
             .route("/*path", any(Self::proxy_handler))
@@ -108,11 +163,21 @@ impl EnterpriseApiGateway {
 
         tracing::debug!("Proxying request: {} {}", method, path);
 
-        // Rate limiting check
+        // Rate limiting check, by API scope where one applies and by IP otherwise
+        let mut rate_limit_status = None;
         if gateway.config.enable_rate_limiting {
-            if let Err(_) = gateway.rate_limiter.check_rate_limit(&request).await {
-                tracing::warn!("Rate limit exceeded for request: {} {}", method, path);
-                return Err(axum::http::StatusCode::TOO_MANY_REQUESTS);
+            match gateway.rate_limiter.check_scoped_rate_limit(&request) {
+                Some(status) if !status.is_allowed() => {
+                    tracing::warn!("Scoped rate limit exceeded for request: {} {}", method, path);
+                    return Ok(status.too_many_requests());
+                }
+                Some(status) => rate_limit_status = Some(status),
+                None => {
+                    if let Err(_) = gateway.rate_limiter.check_rate_limit(&request).await {
+                        tracing::warn!("Rate limit exceeded for request: {} {}", method, path);
+                        return Err(axum::http::StatusCode::TOO_MANY_REQUESTS);
+                    }
+                }
             }
         }
 
@@ -125,10 +190,11 @@ impl EnterpriseApiGateway {
             }
         };
 
-        // Load balance to upstream service
-        let upstream_url = if gateway.config.enable_load_balancing {
-            match gateway.load_balancer.get_upstream(&route_info.service_name).await {
-                Ok(url) => url,
+        // Load balance to upstream service. The connection stays counted
+        // against the instance until `upstream_connection` drops, however forwarding ends.
+        let (upstream_url, upstream_connection) = if gateway.config.enable_load_balancing {
+            match gateway.load_balancer.acquire_upstream(&route_info.service_name).await {
+                Ok(connection) => (connection.url().to_string(), Some(connection)),
                 Err(e) => {
                     tracing::error!("Failed to get upstream for service {}: {}", route_info.service_name, e);
                     return Err(axum::http::StatusCode::SERVICE_UNAVAILABLE);
@@ -136,16 +202,43 @@ impl EnterpriseApiGateway {
             }
         } else {
             // Use first available upstream
-            gateway.config.upstream_services
+            let url = gateway.config.upstream_services
                 .iter()
                 .find(|s| s.name == route_info.service_name)
                 .map(|s| s.base_url.clone())
-                .ok_or(axum::http::StatusCode::SERVICE_UNAVAILABLE)?
+                .ok_or(axum::http::StatusCode::SERVICE_UNAVAILABLE)?;
+            (url, None)
+        };
+
+        // Forward the request, failing fast while the upstream's breaker is open
+        let forward = async {
+            if gateway.config.enable_circuit_breaker {
+                let breaker = gateway.circuit_breakers.get_or_create(&route_info.service_name, None).await;
+                breaker.call(|| gateway.forward_request(request, &upstream_url)).await
+            } else {
+                gateway.forward_request(request, &upstream_url).await
+            }
+        };
+        // A draining upstream gets until its drain timeout to answer
+        let forwarded = match &upstream_connection {
+            Some(connection) => tokio::select! {
+                forwarded = forward => forwarded,
+                _ = connection.force_closed() => Err(anyhow::anyhow!("Upstream {} was drained before it answered", upstream_url)),
+            },
+            None => forward.await,
         };
 
-        // Forward the request
-        match gateway.forward_request(request, &upstream_url).await {
-            Ok(response) => Ok(response),
+        match forwarded {
+            Ok(mut response) => {
+                if let Some(status) = &rate_limit_status {
+                    status.apply_headers(response.headers_mut());
+                }
+                Ok(response)
+            }
+            Err(e) if e.is::<CircuitOpenError>() => {
+                tracing::warn!("Circuit breaker open for service {}", route_info.service_name);
+                Err(axum::http::StatusCode::SERVICE_UNAVAILABLE)
+            }
             Err(e) => {
                 tracing::error!("Failed to forward request: {}", e);
                 Err(axum::http::StatusCode::BAD_GATEWAY)
@@ -159,6 +252,16 @@ impl EnterpriseApiGateway {
         let method = request.method().clone();
         let headers = request.headers().clone();
 
+        // Continue the caller's trace, or start one here
+        let trace = TraceContext::continue_or_start(&headers);
+        let span = tracing::info_span!(
+            "upstream_request",
+            trace_id = %trace.trace_id,
+            span_id = %trace.span_id,
+            parent_span_id = trace.parent_span_id.as_deref(),
+            upstream = %upstream_url,
+        );
+
         // Build the upstream URL
         let upstream_uri = format!("{}{}", upstream_url.trim_end_matches('/'), uri.path_and_query().map(|p| p.as_str()).unwrap_or(""));
 
@@ -168,8 +271,14 @@ impl EnterpriseApiGateway {
             &upstream_uri
         );
 
-        // Copy headers
+        // Copy headers, replacing the caller's trace context with this hop's
         for (name, value) in headers.iter() {
+            if trace_context::is_trace_header(name.as_str()) {
+                continue;
+            }
+            req_builder = req_builder.header(name, value);
+        }
+        for (name, value) in trace.headers() {
             req_builder = req_builder.header(name, value);
         }
 
@@ -177,6 +286,7 @@ impl EnterpriseApiGateway {
         let response = req_builder
             .timeout(Duration::from_secs(self.config.request_timeout_seconds))
             .send()
+            .instrument(span)
             .await
             .map_err(|e| anyhow::anyhow!("Upstream request failed: {}", e))?;
 
@@ -278,6 +388,7 @@ impl Clone for EnterpriseApiGateway {
             router: self.router.clone(),
             rate_limiter: self.rate_limiter.clone(),
             health_checker: self.health_checker.clone(),
+            circuit_breakers: self.circuit_breakers.clone(),
             start_time: self.start_time,
         }
     }
@@ -361,7 +472,7 @@ pub struct GatewayMetrics {
     pub upstream_health: Vec<UpstreamHealth>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UpstreamHealth {
     pub service_name: String,
     pub url: String,
@@ -380,8 +491,57 @@ impl Default for GatewayConfig {
             enable_rate_limiting: true,
             enable_circuit_breaker: true,
             enable_load_balancing: true,
+            load_balancing_algorithm: LoadBalancingAlgorithm::default(),
             health_check_interval_seconds: 30,
+            drain_timeout_seconds: default_drain_timeout_seconds(),
+            healthy_threshold: default_healthy_threshold(),
             upstream_services: vec![],
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::method;
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    async fn forward_with_headers(headers: &[(&str, &str)]) -> wiremock::Request {
+        let upstream = MockServer::start().await;
+        Mock::given(method("GET")).respond_with(ResponseTemplate::new(200)).mount(&upstream).await;
+        let gateway = EnterpriseApiGateway::new(GatewayConfig::default()).await.unwrap();
+
+        let mut request = axum::http::Request::builder().uri("/projects?page=2");
+        for (name, value) in headers {
+            request = request.header(*name, *value);
+        }
+        let request = request.body(axum::body::Body::empty()).unwrap();
+        gateway.forward_request(request, &upstream.uri()).await.unwrap();
+
+        upstream.received_requests().await.unwrap().remove(0)
+    }
+
+    fn header<'a>(request: &'a wiremock::Request, name: &str) -> Option<&'a str> {
+        request.headers.get(name).and_then(|value| value.to_str().ok())
+    }
+
+    #[tokio::test]
+    async fn upstream_request_continues_the_inbound_trace() {
+        let inbound = "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01";
+        let forwarded = forward_with_headers(&[("traceparent", inbound), ("tracestate", "congo=t61rcWkgMzE")]).await;
+
+        let traceparent = header(&forwarded, "traceparent").unwrap();
+        let fields: Vec<&str> = traceparent.split('-').collect();
+        assert_eq!(fields[0], "00");
+        assert_eq!(fields[1], "4bf92f3577b34da6a3ce929d0e0e4736");
+        assert_ne!(fields[2], "00f067aa0ba902b7");
+        assert_eq!(fields[3], "01");
+        assert_eq!(header(&forwarded, "tracestate"), Some("congo=t61rcWkgMzE"));
+
+        // Without an inbound context the gateway starts a new trace
+        let forwarded = forward_with_headers(&[]).await;
+        let traceparent = header(&forwarded, "traceparent").unwrap();
+        assert_eq!(traceparent.len(), 55);
+        assert!(header(&forwarded, "tracestate").is_none());
+    }
+}
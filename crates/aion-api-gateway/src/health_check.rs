@@ -1,16 +1,39 @@
-use crate::gateway::{GatewayConfig, UpstreamHealth};
+use crate::gateway::{GatewayConfig, UpstreamHealth, UpstreamService};
+use crate::LoadBalancer;
+use aion_core::{ComponentHealth, ComponentHealthConfig, Criticality, HealthCheck, HealthRegistry, HealthStatus};
 use anyhow::Result;
+use async_trait::async_trait;
 use chrono::Utc;
 use reqwest::Client;
 use std::sync::Arc;
-use std::time::{Duration, Instant};
+use std::time::Duration;
 use tokio::sync::RwLock;
 
 pub struct HealthChecker {
     config: Arc<GatewayConfig>,
-    client: Client,
+    registry: Arc<HealthRegistry>,
     upstream_health: Arc<RwLock<Vec<UpstreamHealth>>>,
     monitoring_active: Arc<RwLock<bool>>,
+    /// Drained and restored as scheduled checks fail and pass
+    load_balancer: Option<Arc<LoadBalancer>>,
+}
+
+/// Probes an upstream's health check endpoint
+struct UpstreamCheck {
+    client: Client,
+    health_url: String,
+}
+
+#[async_trait]
+impl HealthCheck for UpstreamCheck {
+    async fn check(&self) -> Result<HealthStatus> {
+        let response = self.client.get(&self.health_url).send().await?;
+        if response.status().is_success() {
+            Ok(HealthStatus::Healthy)
+        } else {
+            Err(anyhow::anyhow!("HTTP {}", response.status()))
+        }
+    }
 }
 
 impl HealthChecker {
@@ -19,14 +42,41 @@ impl HealthChecker {
             .timeout(Duration::from_secs(10))
             .build()?;
 
+        // Results are reused until the next scheduled check
+        let registry = HealthRegistry::new()
+            .with_default_cache_ttl(Duration::from_secs(config.health_check_interval_seconds));
+
+        for service in &config.upstream_services {
+            let criticality = if service.critical { Criticality::Critical } else { Criticality::NonCritical };
+            let check = UpstreamCheck {
+                client: client.clone(),
+                health_url: format!("{}{}", service.base_url.trim_end_matches('/'), &service.health_check_path),
+            };
+            registry.register_with(
+                component_name(service),
+                ComponentHealthConfig {
+                    timeout: Some(Duration::from_secs(service.timeout_seconds)),
+                    ..ComponentHealthConfig::new(criticality)
+                },
+                check,
+            ).await;
+        }
+
         Ok(Self {
             config,
-            client,
+            registry: Arc::new(registry),
             upstream_health: Arc::new(RwLock::new(Vec::new())),
             monitoring_active: Arc::new(RwLock::new(false)),
+            load_balancer: None,
         })
     }
 
+    /// Feed scheduled check results to `load_balancer`, which drains upstreams that fail
+    pub fn with_load_balancer(mut self, load_balancer: Arc<LoadBalancer>) -> Self {
+        self.load_balancer = Some(load_balancer);
+        self
+    }
+
     pub async fn start_monitoring(&self) -> Result<()> {
         {
             let mut active = self.monitoring_active.write().await;
@@ -62,68 +112,81 @@ impl HealthChecker {
         Ok(())
     }
 
+    /// Registry of upstream checks, shared with anything reporting gateway health
+    pub fn registry(&self) -> Arc<HealthRegistry> {
+        self.registry.clone()
+    }
+
     async fn check_all_upstreams(&self) -> Result<()> {
-        let mut health_results = Vec::new();
+        let report = self.refresh().await;
 
-        for service in &self.config.upstream_services {
-            let health = self.check_upstream_health(service).await;
-            health_results.push(health);
+        // Only scheduled checks count towards draining and recovery; the
+        // health endpoint may reuse their cached results any number of times
+        if let Some(load_balancer) = &self.load_balancer {
+            for component in &report.components {
+                let passed = matches!(component.status, HealthStatus::Healthy | HealthStatus::Degraded);
+                if let Some(service) = self.service(&component.name) {
+                    load_balancer.record_health_check(&service.name, &service.base_url, passed).await;
+                }
+            }
         }
+        Ok(())
+    }
+
+    /// Run the upstream checks (or reuse fresh results) and record them
+    async fn refresh(&self) -> aion_core::HealthReport {
+        let report = self.registry.check_all().await;
 
-        // Update health status
         let mut upstream_health = self.upstream_health.write().await;
-        *upstream_health = health_results;
+        *upstream_health = report
+            .components
+            .iter()
+            .map(|component| self.to_upstream_health(component))
+            .collect();
 
-        Ok(())
+        report
     }
 
-    async fn check_upstream_health(&self, service: &crate::gateway::UpstreamService) -> UpstreamHealth {
-        let health_url = format!("{}{}", service.base_url.trim_end_matches('/'), &service.health_check_path);
-        let start_time = Instant::now();
-
-        match self.client.get(&health_url).send().await {
-            Ok(response) => {
-                let response_time = start_time.elapsed().as_millis() as u64;
-                let status = if response.status().is_success() {
-                    "healthy".to_string()
-                } else {
-                    format!("unhealthy (HTTP {})", response.status())
-                };
-
-                UpstreamHealth {
-                    service_name: service.name.clone(),
-                    url: service.base_url.clone(),
-                    status,
-                    response_time_ms: response_time,
-                    last_check: Utc::now(),
-                }
-            }
-            Err(e) => {
-                let response_time = start_time.elapsed().as_millis() as u64;
-                UpstreamHealth {
-                    service_name: service.name.clone(),
-                    url: service.base_url.clone(),
-                    status: format!("unhealthy ({})", e),
-                    response_time_ms: response_time,
-                    last_check: Utc::now(),
-                }
-            }
+    /// The upstream instance a registered component checks
+    fn service(&self, component_name: &str) -> Option<&UpstreamService> {
+        self.config
+            .upstream_services
+            .iter()
+            .find(|service| component_name(service) == component_name)
+    }
+
+    fn to_upstream_health(&self, component: &ComponentHealth) -> UpstreamHealth {
+        let status = match (component.status, &component.error) {
+            (HealthStatus::Healthy, _) => "healthy".to_string(),
+            (HealthStatus::Degraded, _) => "degraded".to_string(),
+            (_, Some(error)) => format!("unhealthy ({})", error),
+            (_, None) => "unhealthy".to_string(),
+        };
+        let service = self.service(&component.name);
+
+        UpstreamHealth {
+            service_name: service.map_or_else(|| component.name.clone(), |service| service.name.clone()),
+            url: service.map(|service| service.base_url.clone()).unwrap_or_default(),
+            status,
+            response_time_ms: component.response_time_ms,
+            last_check: component.checked_at,
         }
     }
 
     pub async fn check_gateway_health(&self) -> GatewayHealthStatus {
+        let report = self.refresh().await;
         let upstream_health = self.upstream_health.read().await;
         let healthy_count = upstream_health.iter().filter(|h| h.status == "healthy").count();
         let total_count = upstream_health.len();
 
         let overall_status = if total_count == 0 {
             "unknown".to_string()
-        } else if healthy_count == total_count {
-            "healthy".to_string()
-        } else if healthy_count > 0 {
-            "degraded".to_string()
         } else {
-            "unhealthy".to_string()
+            match report.status {
+                HealthStatus::Healthy => "healthy".to_string(),
+                HealthStatus::Degraded => "degraded".to_string(),
+                _ => "unhealthy".to_string(),
+            }
         };
 
         GatewayHealthStatus {
@@ -141,13 +204,20 @@ impl HealthChecker {
     }
 }
 
+/// Registry name of an upstream instance. Services may list several
+/// instances under one name, and each is checked on its own.
+fn component_name(service: &UpstreamService) -> String {
+    format!("{}@{}", service.name, service.base_url)
+}
+
 impl Clone for HealthChecker {
     fn clone(&self) -> Self {
         Self {
             config: self.config.clone(),
-            client: self.client.clone(),
+            registry: self.registry.clone(),
             upstream_health: self.upstream_health.clone(),
             monitoring_active: self.monitoring_active.clone(),
+            load_balancer: self.load_balancer.clone(),
         }
     }
 }
@@ -159,4 +229,54 @@ pub struct GatewayHealthStatus {
     pub healthy_upstreams: usize,
     pub total_upstreams: usize,
     pub last_check: chrono::DateTime<chrono::Utc>,
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::UpstreamState;
+    use wiremock::matchers::path;
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn instance(base_url: String) -> UpstreamService {
+        UpstreamService {
+            name: "ai-service".to_string(),
+            base_url,
+            health_check_path: "/health".to_string(),
+            weight: 1,
+            max_connections: 10,
+            timeout_seconds: 5,
+            critical: true,
+        }
+    }
+
+    async fn upstream(status: u16) -> MockServer {
+        let server = MockServer::start().await;
+        Mock::given(path("/health")).respond_with(ResponseTemplate::new(status)).mount(&server).await;
+        server
+    }
+
+    #[tokio::test]
+    async fn instances_of_one_service_are_checked_separately() {
+        let (healthy, failing) = (upstream(200).await, upstream(503).await);
+        let services = vec![instance(healthy.uri()), instance(failing.uri())];
+        let config = Arc::new(GatewayConfig { upstream_services: services.clone(), ..GatewayConfig::default() });
+        let load_balancer = Arc::new(LoadBalancer::new(services).await.unwrap());
+        let checker = HealthChecker::new(config).await.unwrap().with_load_balancer(load_balancer.clone());
+
+        checker.check_all_upstreams().await.unwrap();
+
+        let states = load_balancer.instance_states("ai-service").await;
+        assert_eq!(states[&healthy.uri()], UpstreamState::Healthy);
+        assert_eq!(states[&failing.uri()], UpstreamState::Draining);
+        assert_eq!(healthy.received_requests().await.unwrap().len(), 1);
+        assert_eq!(failing.received_requests().await.unwrap().len(), 1);
+
+        let upstreams = checker.get_upstream_health().await;
+        assert_eq!(upstreams.len(), 2);
+        let status_of = |url: &str| upstreams.iter().find(|health| health.url == url).map(|health| health.status.clone());
+        assert_eq!(status_of(&healthy.uri()).as_deref(), Some("healthy"));
+        assert!(status_of(&failing.uri()).unwrap().starts_with("unhealthy"));
+        assert!(upstreams.iter().all(|health| health.service_name == "ai-service"));
+    }
+}
@@ -5,6 +5,7 @@ pub mod middleware;
 pub mod rate_limiting;
 pub mod circuit_breaker;
 pub mod health_check;
+pub mod trace_context;
 
 pub use gateway::*;
 pub use load_balancer::*;
@@ -12,4 +13,5 @@ pub use routing::*;
 pub use middleware::*;
 pub use rate_limiting::*;
 pub use circuit_breaker::*;
-pub use health_check::*;
\ No newline at end of file
+pub use health_check::*;
+pub use trace_context::*;
\ No newline at end of file
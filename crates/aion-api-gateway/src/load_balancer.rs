@@ -1,30 +1,106 @@
 use crate::gateway::UpstreamService;
 use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
 use std::collections::HashMap;
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
-use tokio::sync::RwLock;
+use std::time::{Duration, Instant};
+use tokio::sync::{watch, Mutex, RwLock};
 
-#[derive(Debug, Clone)]
+/// How long in-flight requests may run on a draining instance before they are closed
+pub const DEFAULT_DRAIN_TIMEOUT: Duration = Duration::from_secs(30);
+/// Health checks an instance must pass in a row before it takes requests again
+pub const DEFAULT_HEALTHY_THRESHOLD: u32 = 2;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
 pub enum LoadBalancingAlgorithm {
+    #[default]
     RoundRobin,
     WeightedRoundRobin,
+    /// Fewest active connections, the heavier instance winning a tie
     LeastConnections,
     Random,
 }
 
+/// Routing state of an upstream instance
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum UpstreamState {
+    Healthy,
+    /// Failed a health check: takes no new requests while in-flight ones finish
+    Draining,
+    Unhealthy,
+}
+
 pub struct LoadBalancer {
     services: Arc<RwLock<HashMap<String, Vec<UpstreamInstance>>>>,
     algorithm: LoadBalancingAlgorithm,
+    drain_timeout: Duration,
+    healthy_threshold: u32,
     round_robin_counters: Arc<RwLock<HashMap<String, AtomicUsize>>>,
+    /// Held while an instance is chosen and its connection counted, so
+    /// concurrent requests see each other's connections
+    selection_lock: Mutex<()>,
 }
 
 #[derive(Debug, Clone)]
 pub struct UpstreamInstance {
     pub url: String,
     pub weight: u32,
-    pub healthy: bool,
-    pub active_connections: AtomicUsize,
+    pub state: UpstreamState,
+    /// Health checks passed in a row
+    pub consecutive_successes: u32,
+    pub active_connections: Arc<AtomicUsize>,
+    drain_started: Option<Instant>,
+    /// Bumped to close the connections open when a drain times out
+    force_close: Arc<watch::Sender<u64>>,
+}
+
+impl UpstreamInstance {
+    fn new(url: String, weight: u32) -> Self {
+        Self {
+            url,
+            weight,
+            state: UpstreamState::Healthy,
+            consecutive_successes: 0,
+            active_connections: Arc::new(AtomicUsize::new(0)),
+            drain_started: None,
+            force_close: Arc::new(watch::channel(0).0),
+        }
+    }
+}
+
+/// Upstream instance picked for one request. The instance's active
+/// connection count drops when this is dropped, so a request that fails,
+/// times out or is cancelled gives its connection back too.
+#[derive(Debug)]
+pub struct UpstreamConnection {
+    url: String,
+    active_connections: Arc<AtomicUsize>,
+    close_generation: u64,
+    force_close: watch::Receiver<u64>,
+}
+
+impl UpstreamConnection {
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Resolves when the instance's drain times out with this connection
+    /// still open; the request should then be abandoned
+    pub async fn force_closed(&self) {
+        let mut force_close = self.force_close.clone();
+        let generation = self.close_generation;
+        if force_close.wait_for(|current| *current != generation).await.is_err() {
+            std::future::pending::<()>().await;
+        }
+    }
+}
+
+impl Drop for UpstreamConnection {
+    fn drop(&mut self) {
+        self.active_connections.fetch_sub(1, Ordering::SeqCst);
+    }
 }
 
 impl LoadBalancer {
@@ -32,26 +108,140 @@ impl LoadBalancer {
         let mut services = HashMap::new();
         let mut counters = HashMap::new();
 
+        // Services listed more than once get one instance per entry
         for service in upstream_services {
-            let instance = UpstreamInstance {
-                url: service.base_url,
-                weight: service.weight,
-                healthy: true,
-                active_connections: AtomicUsize::new(0),
-            };
-
-            services.insert(service.name.clone(), vec![instance]);
-            counters.insert(service.name, AtomicUsize::new(0));
+            let instance = UpstreamInstance::new(service.base_url, service.weight);
+            services.entry(service.name.clone()).or_insert_with(Vec::new).push(instance);
+            counters.entry(service.name).or_insert_with(|| AtomicUsize::new(0));
         }
 
         Ok(Self {
             services: Arc::new(RwLock::new(services)),
             algorithm: LoadBalancingAlgorithm::RoundRobin,
+            drain_timeout: DEFAULT_DRAIN_TIMEOUT,
+            healthy_threshold: DEFAULT_HEALTHY_THRESHOLD,
             round_robin_counters: Arc::new(RwLock::new(counters)),
+            selection_lock: Mutex::new(()),
         })
     }
 
+    pub fn with_algorithm(mut self, algorithm: LoadBalancingAlgorithm) -> Self {
+        self.algorithm = algorithm;
+        self
+    }
+
+    pub fn with_draining(mut self, drain_timeout: Duration, healthy_threshold: u32) -> Self {
+        self.drain_timeout = drain_timeout;
+        self.healthy_threshold = healthy_threshold.max(1);
+        self
+    }
+
     pub async fn get_upstream(&self, service_name: &str) -> Result<String> {
+        Ok(self.select(service_name).await?.url)
+    }
+
+    /// Pick an instance of `service_name` and count a connection to it until
+    /// the returned `UpstreamConnection` is dropped
+    pub async fn acquire_upstream(&self, service_name: &str) -> Result<UpstreamConnection> {
+        let _selection = self.selection_lock.lock().await;
+        let instance = self.select(service_name).await?;
+        instance.active_connections.fetch_add(1, Ordering::SeqCst);
+
+        let force_close = instance.force_close.subscribe();
+        Ok(UpstreamConnection {
+            url: instance.url,
+            active_connections: instance.active_connections,
+            close_generation: *force_close.borrow(),
+            force_close,
+        })
+    }
+
+    /// Active connections of each instance of `service_name`, by URL
+    pub async fn active_connections(&self, service_name: &str) -> HashMap<String, usize> {
+        let services = self.services.read().await;
+        services
+            .get(service_name)
+            .map(|instances| {
+                instances
+                    .iter()
+                    .map(|instance| (instance.url.clone(), instance.active_connections.load(Ordering::SeqCst)))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Routing state of each instance of `service_name`, by URL
+    pub async fn instance_states(&self, service_name: &str) -> HashMap<String, UpstreamState> {
+        let services = self.services.read().await;
+        services
+            .get(service_name)
+            .map(|instances| instances.iter().map(|instance| (instance.url.clone(), instance.state)).collect())
+            .unwrap_or_default()
+    }
+
+    /// Apply a health check of the instance of `service_name` at `url`. A
+    /// failure starts draining a healthy instance; a draining or unhealthy
+    /// instance takes requests again after `healthy_threshold` passes in a row.
+    pub async fn record_health_check(&self, service_name: &str, url: &str, passed: bool) {
+        let mut services = self.services.write().await;
+        let Some(instance) = find_instance(&mut services, service_name, url) else {
+            return;
+        };
+
+        if !passed {
+            instance.consecutive_successes = 0;
+            if instance.state == UpstreamState::Healthy {
+                self.start_draining(service_name, instance);
+            }
+            return;
+        }
+
+        instance.consecutive_successes = instance.consecutive_successes.saturating_add(1);
+        if instance.state != UpstreamState::Healthy && instance.consecutive_successes >= self.healthy_threshold {
+            instance.state = UpstreamState::Healthy;
+            instance.drain_started = None;
+            tracing::info!("Instance healthy again after {} checks: {} -> {}", instance.consecutive_successes, service_name, url);
+        }
+    }
+
+    /// Stop routing to `instance` and close whatever is still open on it
+    /// once the drain timeout passes
+    fn start_draining(&self, service_name: &str, instance: &mut UpstreamInstance) {
+        let started = Instant::now();
+        instance.state = UpstreamState::Draining;
+        instance.drain_started = Some(started);
+        tracing::warn!(
+            "Draining instance with {} active connections: {} -> {}",
+            instance.active_connections.load(Ordering::SeqCst),
+            service_name,
+            instance.url
+        );
+
+        let services = self.services.clone();
+        let drain_timeout = self.drain_timeout;
+        let (service_name, url) = (service_name.to_string(), instance.url.clone());
+        tokio::spawn(async move {
+            tokio::time::sleep(drain_timeout).await;
+            let mut services = services.write().await;
+            let Some(instance) = find_instance(&mut services, &service_name, &url) else {
+                return;
+            };
+            // Recovered or drained again since
+            if instance.drain_started != Some(started) {
+                return;
+            }
+
+            instance.state = UpstreamState::Unhealthy;
+            instance.drain_started = None;
+            let remaining = instance.active_connections.load(Ordering::SeqCst);
+            if remaining > 0 {
+                tracing::warn!("Drain timed out, closing {} connections: {} -> {}", remaining, service_name, url);
+                instance.force_close.send_modify(|generation| *generation += 1);
+            }
+        });
+    }
+
+    async fn select(&self, service_name: &str) -> Result<UpstreamInstance> {
         let services = self.services.read().await;
         let instances = services
             .get(service_name)
@@ -59,7 +249,7 @@ impl LoadBalancer {
 
         let healthy_instances: Vec<&UpstreamInstance> = instances
             .iter()
-            .filter(|instance| instance.healthy)
+            .filter(|instance| instance.state == UpstreamState::Healthy)
             .collect();
 
         if healthy_instances.is_empty() {
@@ -81,7 +271,7 @@ impl LoadBalancer {
             }
         };
 
-        Ok(selected_instance.url.clone())
+        Ok(selected_instance.clone())
     }
 
     async fn round_robin_select(&self, service_name: &str, instances: &[&UpstreamInstance]) -> &UpstreamInstance {
@@ -113,7 +303,7 @@ impl LoadBalancer {
     fn least_connections_select(&self, instances: &[&UpstreamInstance]) -> &UpstreamInstance {
         instances
             .iter()
-            .min_by_key(|instance| instance.active_connections.load(Ordering::Relaxed))
+            .min_by_key(|instance| (instance.active_connections.load(Ordering::SeqCst), Reverse(instance.weight)))
             .copied()
             .unwrap_or(instances[0])
     }
@@ -123,29 +313,161 @@ impl LoadBalancer {
         instances[index]
     }
 
+    /// Drain the instance as a failed health check would
     pub async fn mark_instance_unhealthy(&self, service_name: &str, instance_url: &str) {
         let mut services = self.services.write().await;
-        if let Some(instances) = services.get_mut(service_name) {
-            for instance in instances.iter_mut() {
-                if instance.url == instance_url {
-                    instance.healthy = false;
-                    tracing::warn!("Marked instance unhealthy: {} -> {}", service_name, instance_url);
-                    break;
-                }
+        if let Some(instance) = find_instance(&mut services, service_name, instance_url) {
+            instance.consecutive_successes = 0;
+            if instance.state == UpstreamState::Healthy {
+                self.start_draining(service_name, instance);
             }
         }
     }
 
+    /// Route to the instance again right away, without waiting for health checks
     pub async fn mark_instance_healthy(&self, service_name: &str, instance_url: &str) {
         let mut services = self.services.write().await;
-        if let Some(instances) = services.get_mut(service_name) {
-            for instance in instances.iter_mut() {
-                if instance.url == instance_url {
-                    instance.healthy = true;
-                    tracing::info!("Marked instance healthy: {} -> {}", service_name, instance_url);
-                    break;
-                }
-            }
+        if let Some(instance) = find_instance(&mut services, service_name, instance_url) {
+            instance.state = UpstreamState::Healthy;
+            instance.drain_started = None;
+            tracing::info!("Marked instance healthy: {} -> {}", service_name, instance_url);
         }
     }
-}
\ No newline at end of file
+}
+
+fn find_instance<'a>(
+    services: &'a mut HashMap<String, Vec<UpstreamInstance>>,
+    service_name: &str,
+    url: &str,
+) -> Option<&'a mut UpstreamInstance> {
+    services.get_mut(service_name)?.iter_mut().find(|instance| instance.url == url)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::sync::Barrier;
+
+    fn upstream(url: &str, weight: u32) -> UpstreamService {
+        UpstreamService {
+            name: "ai-service".to_string(),
+            base_url: url.to_string(),
+            health_check_path: "/health".to_string(),
+            weight,
+            max_connections: 100,
+            timeout_seconds: 30,
+            critical: true,
+        }
+    }
+
+    async fn least_connections() -> Arc<LoadBalancer> {
+        let upstreams = vec![upstream("http://a", 1), upstream("http://b", 3), upstream("http://c", 2)];
+        Arc::new(
+            LoadBalancer::new(upstreams)
+                .await
+                .unwrap()
+                .with_algorithm(LoadBalancingAlgorithm::LeastConnections),
+        )
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn parallel_requests_spread_evenly_and_release_connections() {
+        let balancer = least_connections().await;
+        let acquired = Arc::new(Barrier::new(31));
+        let release = Arc::new(Barrier::new(31));
+
+        let mut requests = Vec::new();
+        for _ in 0..30 {
+            let (balancer, acquired, release) = (balancer.clone(), acquired.clone(), release.clone());
+            requests.push(tokio::spawn(async move {
+                let connection = balancer.acquire_upstream("ai-service").await.unwrap();
+                acquired.wait().await;
+                release.wait().await;
+                drop(connection);
+            }));
+        }
+
+        acquired.wait().await;
+        let counts = balancer.active_connections("ai-service").await;
+        assert_eq!(counts.values().copied().collect::<Vec<_>>(), vec![10, 10, 10]);
+        release.wait().await;
+        for request in requests {
+            request.await.unwrap();
+        }
+        assert!(balancer.active_connections("ai-service").await.values().all(|count| *count == 0));
+    }
+
+    #[tokio::test]
+    async fn failed_and_timed_out_requests_release_connections() {
+        let balancer = least_connections().await;
+
+        // Ties go to the heaviest instance
+        let connection = balancer.acquire_upstream("ai-service").await.unwrap();
+        assert_eq!(connection.url(), "http://b");
+
+        let failing = async move {
+            let _connection = connection;
+            Err::<(), _>(anyhow::anyhow!("upstream returned 502"))
+        };
+        assert!(failing.await.is_err());
+
+        let connection = balancer.acquire_upstream("ai-service").await.unwrap();
+        assert_eq!(connection.url(), "http://b");
+        let hanging = async move {
+            let _connection = connection;
+            std::future::pending::<()>().await
+        };
+        assert!(tokio::time::timeout(Duration::from_millis(20), hanging).await.is_err());
+
+        assert!(balancer.active_connections("ai-service").await.values().all(|count| *count == 0));
+    }
+
+    #[tokio::test]
+    async fn draining_instance_finishes_in_flight_request_without_taking_new_ones() {
+        let balancer = least_connections().await;
+        let in_flight = balancer.acquire_upstream("ai-service").await.unwrap();
+        assert_eq!(in_flight.url(), "http://b");
+
+        balancer.record_health_check("ai-service", "http://b", false).await;
+        assert_eq!(balancer.instance_states("ai-service").await["http://b"], UpstreamState::Draining);
+
+        let mut routed = Vec::new();
+        for _ in 0..6 {
+            routed.push(balancer.acquire_upstream("ai-service").await.unwrap());
+        }
+        assert!(routed.iter().all(|connection| connection.url() != "http://b"));
+
+        // The request that was already running is left alone and completes
+        assert!(tokio::time::timeout(Duration::from_millis(20), in_flight.force_closed()).await.is_err());
+        assert_eq!(balancer.active_connections("ai-service").await["http://b"], 1);
+        drop(in_flight);
+        assert_eq!(balancer.active_connections("ai-service").await["http://b"], 0);
+    }
+
+    #[tokio::test]
+    async fn drain_timeout_closes_connections_and_recovery_needs_consecutive_passes() {
+        let balancer = Arc::new(
+            LoadBalancer::new(vec![upstream("http://a", 1), upstream("http://b", 3)])
+                .await
+                .unwrap()
+                .with_algorithm(LoadBalancingAlgorithm::LeastConnections)
+                .with_draining(Duration::from_millis(30), 2),
+        );
+        let stuck = balancer.acquire_upstream("ai-service").await.unwrap();
+        assert_eq!(stuck.url(), "http://b");
+
+        balancer.record_health_check("ai-service", "http://b", false).await;
+        tokio::time::timeout(Duration::from_secs(1), stuck.force_closed()).await.unwrap();
+        assert_eq!(balancer.instance_states("ai-service").await["http://b"], UpstreamState::Unhealthy);
+        drop(stuck);
+
+        balancer.record_health_check("ai-service", "http://b", true).await;
+        balancer.record_health_check("ai-service", "http://b", false).await;
+        balancer.record_health_check("ai-service", "http://b", true).await;
+        assert_eq!(balancer.acquire_upstream("ai-service").await.unwrap().url(), "http://a");
+
+        balancer.record_health_check("ai-service", "http://b", true).await;
+        assert_eq!(balancer.instance_states("ai-service").await["http://b"], UpstreamState::Healthy);
+        assert_eq!(balancer.acquire_upstream("ai-service").await.unwrap().url(), "http://b");
+    }
+}
@@ -1,5 +1,12 @@
+use aion_licensing::{ApiKeyRestrictions, RateLimit};
 use anyhow::Result;
 use axum::extract::Request;
+use axum::http::{HeaderMap, HeaderValue, StatusCode};
+use axum::response::{IntoResponse, Response};
+use dashmap::DashMap;
+use governor::clock::{Clock, DefaultClock};
+use governor::middleware::StateInformationMiddleware;
+use governor::state::{InMemoryState, NotKeyed};
 use governor::{Quota, RateLimiter as GovernorRateLimiter};
 use std::collections::HashMap;
 use std::net::IpAddr;
@@ -8,9 +15,148 @@ use std::sync::Arc;
 use std::time::Duration;
 use tokio::sync::RwLock;
 
+/// Bucket a request is counted in: the caller's API key and the group of routes it calls
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct RateLimitKey {
+    pub api_key: String,
+    pub route_group: String,
+}
+
+/// Picks the bucket of a request; `None` leaves the request to the per-IP limit
+pub type KeyExtractor = Arc<dyn Fn(&Request) -> Option<RateLimitKey> + Send + Sync>;
+
+/// Buckets by the `X-API-Key` header and the first path segment, so
+/// `/search/users` and `/search/projects` share the `search` group
+pub fn api_key_by_path_prefix() -> KeyExtractor {
+    Arc::new(|request: &Request| {
+        let api_key = request.headers().get("X-API-Key")?.to_str().ok()?;
+        let route_group = request.uri().path().trim_start_matches('/').split('/').next().unwrap_or_default();
+        Some(RateLimitKey {
+            api_key: api_key.to_string(),
+            route_group: route_group.to_string(),
+        })
+    })
+}
+
+type WindowLimiter = GovernorRateLimiter<NotKeyed, InMemoryState, DefaultClock, StateInformationMiddleware>;
+
+/// One limiter per window (second, minute, hour, day) a `RateLimit` sets
+struct ScopedBucket {
+    windows: Vec<WindowLimiter>,
+}
+
+impl ScopedBucket {
+    fn new(limit: &RateLimit) -> Self {
+        let windows = [
+            (limit.requests_per_second, 1),
+            (limit.requests_per_minute, 60),
+            (limit.requests_per_hour, 3_600),
+            (limit.requests_per_day, 86_400),
+        ];
+        let mut burst_applied = false;
+
+        let windows = windows
+            .into_iter()
+            .filter_map(|(requests, seconds)| {
+                let requests = NonZeroU32::new(requests?)?;
+                let mut quota = Quota::with_period(Duration::from_secs(seconds) / requests.get())?.allow_burst(requests);
+                // The burst limit caps the shortest window
+                if let Some(burst) = limit.burst_limit.and_then(NonZeroU32::new).filter(|_| !burst_applied) {
+                    quota = quota.allow_burst(burst);
+                    burst_applied = true;
+                }
+                Some(GovernorRateLimiter::direct(quota).with_middleware::<StateInformationMiddleware>())
+            })
+            .collect();
+        Self { windows }
+    }
+
+    /// Take one request from every window. A request denied by a longer
+    /// window still counts against the shorter ones it passed.
+    fn check(&self) -> RateLimitStatus {
+        let clock = DefaultClock::default();
+        let mut tightest: Option<RateLimitStatus> = None;
+
+        for window in &self.windows {
+            match window.check() {
+                Ok(snapshot) => {
+                    let quota = snapshot.quota();
+                    let limit = quota.burst_size().get();
+                    let remaining = snapshot.remaining_burst_capacity();
+                    if tightest.as_ref().is_some_and(|status| status.remaining <= remaining) {
+                        continue;
+                    }
+                    tightest = Some(RateLimitStatus {
+                        limit,
+                        remaining,
+                        reset_after: quota.replenish_interval() * (limit - remaining),
+                        retry_after: None,
+                    });
+                }
+                Err(not_until) => {
+                    let wait = not_until.wait_time_from(clock.now());
+                    return RateLimitStatus {
+                        limit: not_until.quota().burst_size().get(),
+                        remaining: 0,
+                        reset_after: wait,
+                        retry_after: Some(wait),
+                    };
+                }
+            }
+        }
+
+        tightest.unwrap_or(RateLimitStatus {
+            limit: 0,
+            remaining: 0,
+            reset_after: Duration::ZERO,
+            retry_after: None,
+        })
+    }
+}
+
+/// Result of counting a request against its bucket
+#[derive(Debug, Clone)]
+pub struct RateLimitStatus {
+    pub limit: u32,
+    pub remaining: u32,
+    /// Time until the bucket is full again
+    pub reset_after: Duration,
+    /// Set when the request is over the limit
+    pub retry_after: Option<Duration>,
+}
+
+impl RateLimitStatus {
+    pub fn is_allowed(&self) -> bool {
+        self.retry_after.is_none()
+    }
+
+    /// Add `X-RateLimit-Limit`, `X-RateLimit-Remaining`, `X-RateLimit-Reset`
+    /// and, when over the limit, `Retry-After`; times are in whole seconds
+    pub fn apply_headers(&self, headers: &mut HeaderMap) {
+        let seconds = |duration: Duration| HeaderValue::from(duration.as_secs_f64().ceil() as u64);
+        headers.insert("X-RateLimit-Limit", HeaderValue::from(self.limit));
+        headers.insert("X-RateLimit-Remaining", HeaderValue::from(self.remaining));
+        headers.insert("X-RateLimit-Reset", seconds(self.reset_after));
+        if let Some(retry_after) = self.retry_after {
+            headers.insert("Retry-After", seconds(retry_after));
+        }
+    }
+
+    /// 429 response carrying the rate limit headers
+    pub fn too_many_requests(&self) -> Response {
+        let mut response = StatusCode::TOO_MANY_REQUESTS.into_response();
+        self.apply_headers(response.headers_mut());
+        response
+    }
+}
+
 pub struct RateLimiter {
     limiters: Arc<RwLock<HashMap<String, GovernorRateLimiter<String, dashmap::DashMap<String, governor::state::InMemoryState>, governor::clock::DefaultClock>>>>,
     default_quota: Quota,
+    key_extractor: Option<KeyExtractor>,
+    /// `ApiKeyRestrictions::rate_limits` of each API key, by route group
+    scope_limits: DashMap<String, HashMap<String, RateLimit>>,
+    buckets: DashMap<RateLimitKey, Arc<ScopedBucket>>,
 }
 
 impl RateLimiter {
@@ -20,9 +166,47 @@ impl RateLimiter {
         Ok(Self {
             limiters: Arc::new(RwLock::new(HashMap::new())),
             default_quota,
+            key_extractor: None,
+            scope_limits: DashMap::new(),
+            buckets: DashMap::new(),
         })
     }
 
+    /// Count requests in buckets chosen by `extractor` as well as per IP
+    pub fn with_key_extractor(mut self, extractor: KeyExtractor) -> Self {
+        self.key_extractor = Some(extractor);
+        self
+    }
+
+    /// Limit `api_key` per route group as `restrictions.rate_limits` says,
+    /// replacing its previous limits and starting its buckets afresh
+    pub fn configure_api_key(&self, api_key: &str, restrictions: &ApiKeyRestrictions) {
+        self.scope_limits.insert(api_key.to_string(), restrictions.rate_limits.clone());
+        self.buckets.retain(|key, _| key.api_key != api_key);
+        tracing::info!(
+            "Configured rate limits for {} route groups of an API key",
+            restrictions.rate_limits.len()
+        );
+    }
+
+    /// Count `request` in the bucket the key extractor picks. Returns `None`
+    /// when there is no extractor, it picks no bucket, or the API key has no
+    /// limit for the route group.
+    pub fn check_scoped_rate_limit(&self, request: &Request) -> Option<RateLimitStatus> {
+        let key = (self.key_extractor.as_ref()?)(request)?;
+        let bucket = match self.buckets.get(&key) {
+            Some(bucket) => bucket.clone(),
+            None => {
+                let limit = self.scope_limits.get(&key.api_key)?.get(&key.route_group)?.clone();
+                self.buckets.entry(key).or_insert_with(|| Arc::new(ScopedBucket::new(&limit))).clone()
+            }
+        };
+        if bucket.windows.is_empty() {
+            return None;
+        }
+        Some(bucket.check())
+    }
+
     pub async fn check_rate_limit(&self, request: &Request) -> Result<()> {
         let client_ip = self.extract_client_ip(request);
         let key = format!("global:{}", client_ip);
@@ -88,4 +272,62 @@ impl Default for RateLimitConfig {
             per_user: false,
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+
+    fn request(path: &str, api_key: &str) -> Request {
+        axum::http::Request::builder().uri(path).header("X-API-Key", api_key).body(Body::empty()).unwrap()
+    }
+
+    fn per_minute(requests: u32) -> RateLimit {
+        RateLimit {
+            requests_per_second: None,
+            requests_per_minute: Some(requests),
+            requests_per_hour: None,
+            requests_per_day: None,
+            burst_limit: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn exhausted_scope_does_not_limit_other_scopes() {
+        let limiter = RateLimiter::new().await.unwrap().with_key_extractor(api_key_by_path_prefix());
+        limiter.configure_api_key("key-1", &ApiKeyRestrictions {
+            allowed_ips: Vec::new(),
+            allowed_domains: Vec::new(),
+            rate_limits: HashMap::from([
+                ("search".to_string(), per_minute(2)),
+                ("generate".to_string(), per_minute(5)),
+            ]),
+            scopes: Vec::new(),
+        });
+
+        for remaining in [1, 0] {
+            let status = limiter.check_scoped_rate_limit(&request("/search/users", "key-1")).unwrap();
+            assert!(status.is_allowed());
+            assert_eq!((status.limit, status.remaining), (2, remaining));
+        }
+        let denied = limiter.check_scoped_rate_limit(&request("/search/projects", "key-1")).unwrap();
+        assert!(!denied.is_allowed());
+
+        let response = denied.too_many_requests();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        let retry_after: u64 = response.headers()["Retry-After"].to_str().unwrap().parse().unwrap();
+        assert!((1..=30).contains(&retry_after));
+        assert_eq!(response.headers()["X-RateLimit-Limit"], "2");
+        assert_eq!(response.headers()["X-RateLimit-Remaining"], "0");
+
+        // Same key, other route group: its own bucket
+        let generate = limiter.check_scoped_rate_limit(&request("/generate/code", "key-1")).unwrap();
+        assert!(generate.is_allowed());
+        assert_eq!(generate.remaining, 4);
+
+        // Unconfigured keys and groups fall back to the per-IP limit
+        assert!(limiter.check_scoped_rate_limit(&request("/search/users", "key-2")).is_none());
+        assert!(limiter.check_scoped_rate_limit(&request("/billing/invoices", "key-1")).is_none());
+    }
+}
@@ -0,0 +1,169 @@
+//! W3C Trace Context propagation
+//!
+//! Requests arriving with a `traceparent` header continue that trace: the
+//! gateway's hop to the upstream becomes a child span of the caller's, and
+//! `tracestate` is passed along unchanged. Requests without one start a new
+//! trace with the gateway as its root.
+
+use axum::http::HeaderMap;
+
+pub const TRACEPARENT_HEADER: &str = "traceparent";
+pub const TRACESTATE_HEADER: &str = "tracestate";
+
+const SAMPLED_FLAG: u8 = 0x01;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceContext {
+    /// 32 lowercase hex digits
+    pub trace_id: String,
+    /// 16 lowercase hex digits
+    pub span_id: String,
+    /// The caller's span when this one continues an inbound trace
+    pub parent_span_id: Option<String>,
+    pub flags: u8,
+    pub tracestate: Option<String>,
+}
+
+impl TraceContext {
+    /// Start a new, sampled trace
+    pub fn new_root() -> Self {
+        Self {
+            trace_id: random_hex_id(16),
+            span_id: random_hex_id(8),
+            parent_span_id: None,
+            flags: SAMPLED_FLAG,
+            tracestate: None,
+        }
+    }
+
+    /// Read the context of the caller's span from `headers`. Returns `None`
+    /// when there is no `traceparent` or it is malformed, in which case the
+    /// spec says to start a new trace.
+    pub fn extract(headers: &HeaderMap) -> Option<Self> {
+        let traceparent = headers.get(TRACEPARENT_HEADER)?.to_str().ok()?.trim();
+        let mut fields = traceparent.split('-');
+        let (version, trace_id, span_id, flags) = (fields.next()?, fields.next()?, fields.next()?, fields.next()?);
+
+        // Later versions may append fields; version 00 must not
+        if !is_hex(version, 2) || version == "ff" || (version == "00" && fields.next().is_some()) {
+            return None;
+        }
+        if !is_hex(trace_id, 32) || !is_hex(span_id, 16) || !is_hex(flags, 2) {
+            return None;
+        }
+        if trace_id.bytes().all(|b| b == b'0') || span_id.bytes().all(|b| b == b'0') {
+            return None;
+        }
+
+        let tracestate = headers
+            .get_all(TRACESTATE_HEADER)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+            .collect::<Vec<_>>()
+            .join(",");
+
+        Some(Self {
+            trace_id: trace_id.to_string(),
+            span_id: span_id.to_string(),
+            parent_span_id: None,
+            flags: u8::from_str_radix(flags, 16).ok()?,
+            tracestate: (!tracestate.is_empty()).then_some(tracestate),
+        })
+    }
+
+    /// Context of the caller's child span if `headers` carry one, else a new root
+    pub fn continue_or_start(headers: &HeaderMap) -> Self {
+        Self::extract(headers)
+            .map(|parent| parent.child())
+            .unwrap_or_else(Self::new_root)
+    }
+
+    /// New span in the same trace, with this span as its parent
+    pub fn child(&self) -> Self {
+        Self {
+            span_id: random_hex_id(8),
+            parent_span_id: Some(self.span_id.clone()),
+            ..self.clone()
+        }
+    }
+
+    pub fn traceparent(&self) -> String {
+        format!("00-{}-{}-{:02x}", self.trace_id, self.span_id, self.flags)
+    }
+
+    /// Header name and value pairs to send to the next hop
+    pub fn headers(&self) -> Vec<(&'static str, String)> {
+        let mut headers = vec![(TRACEPARENT_HEADER, self.traceparent())];
+        if let Some(tracestate) = &self.tracestate {
+            headers.push((TRACESTATE_HEADER, tracestate.clone()));
+        }
+        headers
+    }
+
+    pub fn is_sampled(&self) -> bool {
+        self.flags & SAMPLED_FLAG != 0
+    }
+}
+
+/// Whether the header is one `TraceContext` sets on outbound requests
+pub fn is_trace_header(name: &str) -> bool {
+    name.eq_ignore_ascii_case(TRACEPARENT_HEADER) || name.eq_ignore_ascii_case(TRACESTATE_HEADER)
+}
+
+fn is_hex(value: &str, len: usize) -> bool {
+    value.len() == len && value.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'))
+}
+
+/// Random non-zero id of `bytes` bytes as lowercase hex
+fn random_hex_id(bytes: usize) -> String {
+    loop {
+        let id: String = (0..bytes).map(|_| format!("{:02x}", fastrand::u8(..))).collect();
+        if id.bytes().any(|b| b != b'0') {
+            return id;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    #[test]
+    fn extracts_valid_context_and_rejects_malformed_ones() {
+        let mut headers = HeaderMap::new();
+        headers.insert(TRACEPARENT_HEADER, HeaderValue::from_static("00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01"));
+        headers.append(TRACESTATE_HEADER, HeaderValue::from_static("congo=t61rcWkgMzE"));
+        headers.append(TRACESTATE_HEADER, HeaderValue::from_static("rojo=00f067aa0ba902b7"));
+
+        let context = TraceContext::extract(&headers).unwrap();
+        assert_eq!(context.trace_id, "4bf92f3577b34da6a3ce929d0e0e4736");
+        assert_eq!(context.span_id, "00f067aa0ba902b7");
+        assert!(context.is_sampled());
+        assert_eq!(context.tracestate.as_deref(), Some("congo=t61rcWkgMzE,rojo=00f067aa0ba902b7"));
+
+        for malformed in [
+            "00-00000000000000000000000000000000-00f067aa0ba902b7-01",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-0000000000000000-01",
+            "00-4BF92F3577B34DA6A3CE929D0E0E4736-00f067aa0ba902b7-01",
+            "ff-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01-extra",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7",
+        ] {
+            headers.insert(TRACEPARENT_HEADER, HeaderValue::from_static(malformed));
+            assert!(TraceContext::extract(&headers).is_none(), "{}", malformed);
+        }
+
+        headers.insert(TRACEPARENT_HEADER, HeaderValue::from_static("00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01"));
+        let child = TraceContext::continue_or_start(&headers);
+        assert_eq!(child.parent_span_id.as_deref(), Some("00f067aa0ba902b7"));
+        assert_ne!(child.span_id, "00f067aa0ba902b7");
+
+        let root = TraceContext::continue_or_start(&HeaderMap::new());
+        assert!(is_hex(&root.trace_id, 32) && is_hex(&root.span_id, 16));
+        assert!(root.parent_span_id.is_none());
+        assert_eq!(root.traceparent().len(), 55);
+    }
+}
@@ -1,24 +1,147 @@
 use crate::models::JwtClaims;
-use anyhow::Result;
-use chrono::{Duration, Utc};
-use jsonwebtoken::{decode, encode, Algorithm, DecodingKey, EncodingKey, Header, Validation};
+use anyhow::{anyhow, bail, Result};
+use chrono::{DateTime, Duration, Utc};
+use jsonwebtoken::{decode, decode_header, encode, Algorithm, DecodingKey, EncodingKey, Header, Validation};
+use serde::{de::DeserializeOwned, Serialize};
+use std::sync::RwLock;
 use uuid::Uuid;
 
-pub struct JwtService {
+/// Key id of the key `JwtService::new` starts with
+pub const DEFAULT_JWT_KID: &str = "primary";
+
+/// Signing secret, named by the `kid` header of the tokens it signs
+pub struct JwtKey {
+    pub kid: String,
     encoding_key: EncodingKey,
     decoding_key: DecodingKey,
-    algorithm: Algorithm,
+    /// Tokens signed with the key stop verifying at this time
+    pub retires_at: Option<DateTime<Utc>>,
 }
 
-impl JwtService {
-    pub fn new(secret: &[u8]) -> Self {
+impl JwtKey {
+    pub fn new(kid: impl Into<String>, secret: &[u8]) -> Self {
         Self {
+            kid: kid.into(),
             encoding_key: EncodingKey::from_secret(secret),
             decoding_key: DecodingKey::from_secret(secret),
+            retires_at: None,
+        }
+    }
+
+    fn is_retired(&self, now: DateTime<Utc>) -> bool {
+        self.retires_at.is_some_and(|retires_at| retires_at <= now)
+    }
+}
+
+/// Keys that verify tokens, one of which signs new ones. Rotating in a new
+/// key keeps the old one verifying for a grace period, so tokens issued
+/// before the rotation stay valid until they expire.
+pub struct JwtKeyset {
+    keys: Vec<JwtKey>,
+    active_kid: String,
+    algorithm: Algorithm,
+}
+
+impl JwtKeyset {
+    pub fn new(active: JwtKey) -> Self {
+        Self {
+            active_kid: active.kid.clone(),
+            keys: vec![active],
             algorithm: Algorithm::HS256,
         }
     }
 
+    pub fn active_kid(&self) -> &str {
+        &self.active_kid
+    }
+
+    pub fn kids(&self) -> Vec<&str> {
+        self.keys.iter().map(|key| key.kid.as_str()).collect()
+    }
+
+    /// Make `key` the signing key. The previous signing key keeps verifying
+    /// for `grace`, which should be at least the lifetime of a token.
+    pub fn rotate(&mut self, key: JwtKey, grace: Duration) -> Result<()> {
+        if self.keys.iter().any(|existing| existing.kid == key.kid) {
+            bail!("JWT key {} already exists", key.kid);
+        }
+
+        let now = Utc::now();
+        self.keys.retain(|existing| !existing.is_retired(now));
+        if let Some(previous) = self.keys.iter_mut().find(|existing| existing.kid == self.active_kid) {
+            previous.retires_at = Some(now + grace);
+        }
+
+        self.active_kid = key.kid.clone();
+        self.keys.push(key);
+        Ok(())
+    }
+
+    /// Stop verifying tokens signed with `kid` right away. The signing key
+    /// cannot be retired; rotate first.
+    pub fn retire(&mut self, kid: &str) -> Result<()> {
+        if kid == self.active_kid {
+            bail!("JWT key {} is the signing key and cannot be retired", kid);
+        }
+        let before = self.keys.len();
+        self.keys.retain(|key| key.kid != kid);
+        if self.keys.len() == before {
+            bail!("JWT key {} not found", kid);
+        }
+        Ok(())
+    }
+
+    /// Sign `claims` with the active key, naming it in the `kid` header
+    pub fn sign<T: Serialize>(&self, claims: &T) -> Result<String> {
+        let key = self.key(&self.active_kid).ok_or_else(|| anyhow!("Signing key {} not found", self.active_kid))?;
+        let mut header = Header::new(self.algorithm);
+        header.kid = Some(key.kid.clone());
+        Ok(encode(&header, claims, &key.encoding_key)?)
+    }
+
+    /// Verify `token` with the key its `kid` names. Tokens without a `kid`
+    /// predate key rotation and are checked against the active key.
+    pub fn verify<T: DeserializeOwned>(&self, token: &str, validation: &Validation) -> Result<T> {
+        let kid = decode_header(token)?.kid.unwrap_or_else(|| self.active_kid.clone());
+        let key = self.key(&kid).ok_or_else(|| anyhow!("Token signed with unknown key {}", kid))?;
+        if key.is_retired(Utc::now()) {
+            bail!("Token signed with retired key {}", kid);
+        }
+        Ok(decode::<T>(token, &key.decoding_key, validation)?.claims)
+    }
+
+    fn key(&self, kid: &str) -> Option<&JwtKey> {
+        self.keys.iter().find(|key| key.kid == kid)
+    }
+}
+
+pub struct JwtService {
+    keyset: RwLock<JwtKeyset>,
+    algorithm: Algorithm,
+}
+
+impl JwtService {
+    pub fn new(secret: &[u8]) -> Self {
+        Self::with_keyset(JwtKeyset::new(JwtKey::new(DEFAULT_JWT_KID, secret)))
+    }
+
+    pub fn with_keyset(keyset: JwtKeyset) -> Self {
+        Self {
+            algorithm: keyset.algorithm,
+            keyset: RwLock::new(keyset),
+        }
+    }
+
+    /// Sign new tokens with `key`; see [`JwtKeyset::rotate`]
+    pub fn rotate_key(&self, key: JwtKey, grace: Duration) -> Result<()> {
+        self.keyset.write().map_err(|_| anyhow!("JWT keyset lock poisoned"))?.rotate(key, grace)
+    }
+
+    /// Reject tokens signed with `kid` from now on; see [`JwtKeyset::retire`]
+    pub fn retire_key(&self, kid: &str) -> Result<()> {
+        self.keyset.write().map_err(|_| anyhow!("JWT keyset lock poisoned"))?.retire(kid)
+    }
+
     pub fn create_access_token(&self, user_id: Uuid, tenant_id: Uuid, roles: Vec<String>, permissions: Vec<String>) -> Result<String> {
         let now = Utc::now();
         let claims = JwtClaims {
@@ -33,14 +156,62 @@ impl JwtService {
             session_id: Uuid::new_v4(),
         };
 
-        let header = Header::new(self.algorithm);
-        let token = encode(&header, &claims, &self.encoding_key)?;
-        Ok(token)
+        self.keyset.read().map_err(|_| anyhow!("JWT keyset lock poisoned"))?.sign(&claims)
     }
 
     pub fn validate_token(&self, token: &str) -> Result<JwtClaims> {
-        let validation = Validation::new(self.algorithm);
-        let token_data = decode::<JwtClaims>(token, &self.decoding_key, &validation)?;
-        Ok(token_data.claims)
+        let mut validation = Validation::new(self.algorithm);
+        validation.set_audience(&["aion-r"]);
+        validation.set_issuer(&["aion-r-auth"]);
+        self.keyset.read().map_err(|_| anyhow!("JWT keyset lock poisoned"))?.verify(token, &validation)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn token(service: &JwtService) -> String {
+        service
+            .create_access_token(Uuid::new_v4(), Uuid::new_v4(), vec!["admin".to_string()], Vec::new())
+            .unwrap()
     }
-}
\ No newline at end of file
+
+    fn kid(token: &str) -> String {
+        decode_header(token).unwrap().kid.unwrap()
+    }
+
+    #[test]
+    fn rotated_out_key_verifies_until_retired() {
+        let service = JwtService::with_keyset(JwtKeyset::new(JwtKey::new("a", b"secret-a")));
+        let signed_with_a = token(&service);
+        assert_eq!(kid(&signed_with_a), "a");
+
+        service.rotate_key(JwtKey::new("b", b"secret-b"), Duration::hours(8)).unwrap();
+        let signed_with_b = token(&service);
+        assert_eq!(kid(&signed_with_b), "b");
+        assert!(service.validate_token(&signed_with_a).is_ok());
+        assert!(service.validate_token(&signed_with_b).is_ok());
+
+        service.retire_key("a").unwrap();
+        assert!(service.validate_token(&signed_with_a).is_err());
+        assert!(service.validate_token(&signed_with_b).is_ok());
+        assert!(service.retire_key("b").is_err());
+    }
+
+    #[test]
+    fn expired_grace_and_unknown_keys_are_rejected() {
+        let service = JwtService::with_keyset(JwtKeyset::new(JwtKey::new("a", b"secret-a")));
+        let signed_with_a = token(&service);
+
+        service.rotate_key(JwtKey::new("b", b"secret-b"), Duration::zero()).unwrap();
+        assert!(service.validate_token(&signed_with_a).is_err());
+
+        let other = JwtService::with_keyset(JwtKeyset::new(JwtKey::new("c", b"secret-c")));
+        assert!(service.validate_token(&token(&other)).is_err());
+
+        // A kid naming a known key does not help a token signed with another secret
+        let forged = JwtService::with_keyset(JwtKeyset::new(JwtKey::new("b", b"guessed")));
+        assert!(service.validate_token(&token(&forged)).is_err());
+    }
+}
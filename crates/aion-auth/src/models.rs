@@ -30,6 +30,9 @@ pub enum AuthError {
     #[error("Token expired")]
     TokenExpired,
 
+    #[error("Refresh token reused; its token family has been revoked")]
+    RefreshTokenReused,
+
     #[error("MFA required")]
     MfaRequired,
 
@@ -415,6 +418,23 @@ pub struct Session {
     pub is_active: bool,
 }
 
+/// One refresh token of a family. A family starts when a session is created
+/// and gains a token on every refresh; each token may be used once.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RefreshTokenRecord {
+    /// SHA-256 of the token; the token itself is never stored
+    pub token_hash: String,
+    pub family_id: Uuid,
+    pub session_id: Uuid,
+    /// Hash of the token this one replaced; `None` for the first of a family
+    pub parent_hash: Option<String>,
+    pub issued_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    /// When the token was exchanged for its successor
+    pub used_at: Option<DateTime<Utc>>,
+    pub revoked: bool,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AuthenticationRequest {
     pub username: String,
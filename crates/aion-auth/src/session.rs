@@ -1,7 +1,9 @@
-use crate::models::Session;
+use crate::models::{AuthError, RefreshTokenRecord, Session};
 use anyhow::Result;
 use async_trait::async_trait;
-use chrono::{Duration, Utc};
+use chrono::{DateTime, Duration, Utc};
+use rand::RngCore;
+use sha2::{Digest, Sha256};
 use std::collections::HashMap;
 use std::sync::Arc;
 use tokio::sync::RwLock;
@@ -13,17 +15,25 @@ pub trait SessionStore: Send + Sync {
     async fn get_session(&self, session_id: Uuid) -> Result<Option<Session>>;
     async fn update_session(&self, session: Session) -> Result<()>;
     async fn delete_session(&self, session_id: Uuid) -> Result<()>;
+    async fn delete_user_sessions(&self, user_id: Uuid) -> Result<()>;
     async fn cleanup_expired_sessions(&self) -> Result<()>;
+    async fn save_refresh_token(&self, record: RefreshTokenRecord) -> Result<()>;
+    /// Atomically set `used_at` on the token if it is unset, returning the
+    /// record as it was before, or `None` if there is no such token
+    async fn mark_refresh_token_used(&self, token_hash: &str, used_at: DateTime<Utc>) -> Result<Option<RefreshTokenRecord>>;
+    async fn revoke_refresh_token_family(&self, family_id: Uuid) -> Result<()>;
 }
 
 pub struct InMemorySessionStore {
     sessions: Arc<RwLock<HashMap<Uuid, Session>>>,
+    refresh_tokens: Arc<RwLock<HashMap<String, RefreshTokenRecord>>>,
 }
 
 impl InMemorySessionStore {
     pub fn new() -> Self {
         Self {
             sessions: Arc::new(RwLock::new(HashMap::new())),
+            refresh_tokens: Arc::new(RwLock::new(HashMap::new())),
         }
     }
 }
@@ -53,21 +63,72 @@ impl SessionStore for InMemorySessionStore {
         Ok(())
     }
 
+    async fn delete_user_sessions(&self, user_id: Uuid) -> Result<()> {
+        let mut sessions = self.sessions.write().await;
+        sessions.retain(|_, session| session.user_id != user_id);
+        Ok(())
+    }
+
     async fn cleanup_expired_sessions(&self) -> Result<()> {
         let mut sessions = self.sessions.write().await;
         let now = Utc::now();
         sessions.retain(|_, session| session.expires_at > now);
+        drop(sessions);
+
+        let mut refresh_tokens = self.refresh_tokens.write().await;
+        refresh_tokens.retain(|_, record| record.expires_at > now);
+        Ok(())
+    }
+
+    async fn save_refresh_token(&self, record: RefreshTokenRecord) -> Result<()> {
+        let mut refresh_tokens = self.refresh_tokens.write().await;
+        refresh_tokens.insert(record.token_hash.clone(), record);
+        Ok(())
+    }
+
+    async fn mark_refresh_token_used(&self, token_hash: &str, used_at: DateTime<Utc>) -> Result<Option<RefreshTokenRecord>> {
+        let mut refresh_tokens = self.refresh_tokens.write().await;
+        Ok(refresh_tokens.get_mut(token_hash).map(|record| {
+            let before = record.clone();
+            record.used_at.get_or_insert(used_at);
+            before
+        }))
+    }
+
+    async fn revoke_refresh_token_family(&self, family_id: Uuid) -> Result<()> {
+        let mut refresh_tokens = self.refresh_tokens.write().await;
+        for record in refresh_tokens.values_mut().filter(|record| record.family_id == family_id) {
+            record.revoked = true;
+        }
         Ok(())
     }
 }
 
+/// A refresh token exchanged for its successor
+#[derive(Debug, Clone)]
+pub struct RefreshTokenRotation {
+    /// Replaces the presented token, which no longer works
+    pub refresh_token: String,
+    /// Session the token family belongs to, for minting the access token
+    pub session: Session,
+}
+
 pub struct SessionManager {
     store: Arc<dyn SessionStore>,
+    refresh_token_lifetime: Duration,
 }
 
 impl SessionManager {
     pub fn new(store: Arc<dyn SessionStore>) -> Self {
-        Self { store }
+        Self {
+            store,
+            refresh_token_lifetime: Duration::days(30),
+        }
+    }
+
+    pub fn with_refresh_token_lifetime(mut self, lifetime: Duration) -> Self {
+        self.refresh_token_lifetime = lifetime;
+        self
     }
 
     pub async fn create_session(&self, user_id: Uuid, tenant_id: Uuid) -> Result<Session> {
@@ -108,4 +169,142 @@ impl SessionManager {
     pub async fn invalidate_session(&self, session_id: Uuid) -> Result<()> {
         self.store.delete_session(session_id).await
     }
+
+    /// End every session of `user_id`; their refresh tokens stop working
+    pub async fn invalidate_user_sessions(&self, user_id: Uuid) -> Result<()> {
+        self.store.delete_user_sessions(user_id).await
+    }
+
+    /// Start a refresh token family for `session`
+    pub async fn issue_refresh_token(&self, session: &Session) -> Result<String> {
+        self.issue_family_token(Uuid::new_v4(), session.id, None).await
+    }
+
+    /// Exchange `refresh_token` for a new one. Every token works once:
+    /// presenting a used token means it was copied, so the whole family is
+    /// revoked and the session ended, locking out whoever holds the newer
+    /// tokens too. Errors carry an `AuthError`, all of which map to 401.
+    pub async fn rotate_refresh_token(&self, refresh_token: &str) -> Result<RefreshTokenRotation> {
+        let token_hash = hash_refresh_token(refresh_token);
+        let now = Utc::now();
+        let record = self
+            .store
+            .mark_refresh_token_used(&token_hash, now)
+            .await?
+            .ok_or(AuthError::InvalidToken)?;
+
+        if record.revoked {
+            return Err(AuthError::InvalidToken.into());
+        }
+        if record.used_at.is_some() {
+            tracing::warn!(
+                "Refresh token reuse in family {} of session {}; revoking the family",
+                record.family_id,
+                record.session_id
+            );
+            self.revoke_refresh_token_family(&record).await?;
+            return Err(AuthError::RefreshTokenReused.into());
+        }
+        if record.expires_at <= now {
+            return Err(AuthError::TokenExpired.into());
+        }
+
+        let Some(session) = self.validate_session(record.session_id).await? else {
+            self.store.revoke_refresh_token_family(record.family_id).await?;
+            return Err(AuthError::SessionExpired.into());
+        };
+        let refresh_token = self
+            .issue_family_token(record.family_id, record.session_id, Some(token_hash))
+            .await?;
+
+        Ok(RefreshTokenRotation { refresh_token, session })
+    }
+
+    async fn revoke_refresh_token_family(&self, record: &RefreshTokenRecord) -> Result<()> {
+        self.store.revoke_refresh_token_family(record.family_id).await?;
+        self.invalidate_session(record.session_id).await
+    }
+
+    async fn issue_family_token(&self, family_id: Uuid, session_id: Uuid, parent_hash: Option<String>) -> Result<String> {
+        let mut bytes = [0u8; 32];
+        rand::thread_rng().fill_bytes(&mut bytes);
+        let token: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+
+        let now = Utc::now();
+        self.store
+            .save_refresh_token(RefreshTokenRecord {
+                token_hash: hash_refresh_token(&token),
+                family_id,
+                session_id,
+                parent_hash,
+                issued_at: now,
+                expires_at: now + self.refresh_token_lifetime,
+                used_at: None,
+                revoked: false,
+            })
+            .await?;
+        Ok(token)
+    }
+}
+
+fn hash_refresh_token(token: &str) -> String {
+    Sha256::digest(token.as_bytes()).iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn auth_error(error: anyhow::Error) -> AuthError {
+        error.downcast::<AuthError>().expect("an AuthError")
+    }
+
+    #[tokio::test]
+    async fn replayed_refresh_token_revokes_the_family() {
+        let manager = SessionManager::new(Arc::new(InMemorySessionStore::new()));
+        let session = manager.create_session(Uuid::new_v4(), Uuid::new_v4()).await.unwrap();
+        let stolen = manager.issue_refresh_token(&session).await.unwrap();
+
+        // The legitimate client refreshes twice
+        let second = manager.rotate_refresh_token(&stolen).await.unwrap();
+        assert_eq!(second.session.id, session.id);
+        let third = manager.rotate_refresh_token(&second.refresh_token).await.unwrap();
+
+        // The attacker replays the stolen, already used token
+        let replay = manager.rotate_refresh_token(&stolen).await.unwrap_err();
+        assert!(matches!(auth_error(replay), AuthError::RefreshTokenReused));
+
+        // The whole family is dead, including the token the client holds
+        let current = manager.rotate_refresh_token(&third.refresh_token).await.unwrap_err();
+        assert!(matches!(auth_error(current), AuthError::InvalidToken));
+        assert!(manager.validate_session(session.id).await.unwrap().is_none());
+
+        // Other sessions are unaffected
+        let other = manager.create_session(Uuid::new_v4(), Uuid::new_v4()).await.unwrap();
+        let token = manager.issue_refresh_token(&other).await.unwrap();
+        assert!(manager.rotate_refresh_token(&token).await.is_ok());
+        let unknown = manager.rotate_refresh_token("not-a-token").await.unwrap_err();
+        assert!(matches!(auth_error(unknown), AuthError::InvalidToken));
+    }
+
+    #[tokio::test]
+    async fn invalidating_a_users_sessions_ends_their_token_families() {
+        let manager = SessionManager::new(Arc::new(InMemorySessionStore::new()));
+        let user_id = Uuid::new_v4();
+        let laptop = manager.create_session(user_id, Uuid::new_v4()).await.unwrap();
+        let phone = manager.create_session(user_id, Uuid::new_v4()).await.unwrap();
+        let other = manager.create_session(Uuid::new_v4(), Uuid::new_v4()).await.unwrap();
+        let tokens = [
+            manager.issue_refresh_token(&laptop).await.unwrap(),
+            manager.issue_refresh_token(&phone).await.unwrap(),
+        ];
+        let other_token = manager.issue_refresh_token(&other).await.unwrap();
+
+        manager.invalidate_user_sessions(user_id).await.unwrap();
+        for token in &tokens {
+            let error = manager.rotate_refresh_token(token).await.unwrap_err();
+            assert!(matches!(auth_error(error), AuthError::SessionExpired));
+        }
+        assert!(manager.rotate_refresh_token(&other_token).await.is_ok());
+    }
 }
\ No newline at end of file
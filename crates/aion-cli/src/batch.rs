@@ -0,0 +1,275 @@
+// Batch Processing of Requirement Files
+
+use anyhow::{anyhow, Result};
+use console::style;
+use futures::stream::{self, StreamExt};
+use indicatif::{ProgressBar, ProgressStyle};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use crate::output::OutputFormat;
+
+/// File extensions picked up when a directory is given as input
+const REQUIREMENTS_EXTENSIONS: [&str; 5] = ["md", "markdown", "txt", "rst", "adoc"];
+
+/// One requirements file of a batch
+#[derive(Debug, Clone)]
+pub struct BatchInput {
+    pub path: PathBuf,
+    /// Path relative to the batch root, used to name the output subdirectory
+    pub name: PathBuf,
+}
+
+/// Result of processing one file
+#[derive(Debug, Serialize)]
+pub struct BatchOutcome<T> {
+    pub input: PathBuf,
+    pub output_dir: PathBuf,
+    pub duration_ms: u128,
+    #[serde(flatten)]
+    pub result: BatchResult<T>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "status", rename_all = "lowercase")]
+pub enum BatchResult<T> {
+    Succeeded { result: T },
+    Failed { error: String },
+}
+
+/// Whether `source` names several files (a directory or a glob pattern)
+/// rather than a single requirements file
+pub fn is_batch_source(source: &Path) -> bool {
+    source.is_dir() || (!source.exists() && has_glob_chars(&source.to_string_lossy()))
+}
+
+/// Expand a directory (recursively, requirement documents only) or a glob
+/// pattern into the files to process, sorted by path
+pub fn collect_inputs(source: &Path) -> Result<Vec<BatchInput>> {
+    let (root, mut paths) = if source.is_dir() {
+        let mut paths = Vec::new();
+        collect_dir(source, &mut paths)?;
+        (source.to_path_buf(), paths)
+    } else {
+        let pattern = source.to_string_lossy();
+        let paths = glob::glob(&pattern)
+            .map_err(|e| anyhow!("Invalid pattern '{}': {}", pattern, e))?
+            .filter_map(|entry| entry.ok())
+            .filter(|path| path.is_file())
+            .collect();
+        (glob_root(&pattern), paths)
+    };
+    paths.sort();
+
+    if paths.is_empty() {
+        return Err(anyhow!("No requirements files found in {}", source.display()));
+    }
+
+    // Name outputs after the file without its extension, unless two files
+    // would end up in the same directory
+    let stems: Vec<PathBuf> = paths
+        .iter()
+        .map(|path| path.strip_prefix(&root).unwrap_or(path).with_extension(""))
+        .collect();
+    let mut counts: HashMap<&Path, usize> = HashMap::new();
+    for stem in &stems {
+        *counts.entry(stem.as_path()).or_default() += 1;
+    }
+
+    Ok(paths
+        .iter()
+        .zip(&stems)
+        .map(|(path, stem)| {
+            let name = if counts[stem.as_path()] > 1 {
+                path.strip_prefix(&root).unwrap_or(path).to_path_buf()
+            } else {
+                stem.clone()
+            };
+            BatchInput { path: path.clone(), name }
+        })
+        .collect())
+}
+
+fn collect_dir(dir: &Path, paths: &mut Vec<PathBuf>) -> Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_dir(&path, paths)?;
+        } else if path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| REQUIREMENTS_EXTENSIONS.contains(&ext.to_lowercase().as_str()))
+        {
+            paths.push(path);
+        }
+    }
+    Ok(())
+}
+
+fn has_glob_chars(pattern: &str) -> bool {
+    pattern.contains(['*', '?', '['])
+}
+
+/// Leading directories of a glob pattern that contain no wildcards
+fn glob_root(pattern: &str) -> PathBuf {
+    let mut root = PathBuf::new();
+    let components: Vec<_> = Path::new(pattern).components().collect();
+    for component in &components[..components.len().saturating_sub(1)] {
+        if has_glob_chars(&component.as_os_str().to_string_lossy()) {
+            break;
+        }
+        root.push(component);
+    }
+    root
+}
+
+/// Run `process` for every input with at most `concurrency` files in flight
+///
+/// A failing file does not stop the batch; its error is recorded in its
+/// outcome. `process` receives the input and the subdirectory of `output_root`
+/// reserved for it. Outcomes are returned in input order.
+pub async fn run_batch<T, F, Fut>(
+    inputs: Vec<BatchInput>,
+    output_root: &Path,
+    concurrency: usize,
+    process: F,
+) -> Result<Vec<BatchOutcome<T>>>
+where
+    F: Fn(BatchInput, PathBuf) -> Fut,
+    Fut: Future<Output = Result<T>>,
+{
+    if concurrency == 0 {
+        return Err(anyhow!("--concurrency must be at least 1"));
+    }
+
+    let pb = ProgressBar::new(inputs.len() as u64);
+    pb.set_style(
+        ProgressStyle::default_bar()
+            .template("{spinner:.blue} [{bar:30.cyan/blue}] {pos}/{len} {msg}")
+            .unwrap()
+    );
+
+    let pb_ref = &pb;
+    let process = &process;
+    let mut outcomes: Vec<(usize, BatchOutcome<T>)> = stream::iter(inputs.into_iter().enumerate())
+        .map(|(index, input)| async move {
+            let output_dir = output_root.join(&input.name);
+            let path = input.path.clone();
+            let started = Instant::now();
+            let result = match process(input, output_dir.clone()).await {
+                Ok(result) => BatchResult::Succeeded { result },
+                Err(e) => {
+                    pb_ref.println(format!("{} {}: {:#}", style("❌").red(), path.display(), e));
+                    BatchResult::Failed { error: format!("{:#}", e) }
+                }
+            };
+            pb_ref.inc(1);
+            let outcome = BatchOutcome {
+                input: path,
+                output_dir,
+                duration_ms: started.elapsed().as_millis(),
+                result,
+            };
+            (index, outcome)
+        })
+        .buffer_unordered(concurrency)
+        .collect()
+        .await;
+    pb.finish_and_clear();
+
+    outcomes.sort_by_key(|(index, _)| *index);
+    Ok(outcomes.into_iter().map(|(_, outcome)| outcome).collect())
+}
+
+/// Print the aggregated results; fails when any file failed so scripts can
+/// detect partial batches
+pub fn report<T: Serialize>(
+    outcomes: &[BatchOutcome<T>],
+    output_format: &OutputFormat,
+    describe: impl Fn(&T) -> String,
+) -> Result<()> {
+    let failed = outcomes
+        .iter()
+        .filter(|outcome| matches!(outcome.result, BatchResult::Failed { .. }))
+        .count();
+    let succeeded = outcomes.len() - failed;
+
+    match output_format {
+        OutputFormat::Table => {
+            use tabled::{Table, Tabled};
+
+            #[derive(Tabled)]
+            struct OutcomeRow {
+                #[tabled(rename = "File")]
+                file: String,
+                #[tabled(rename = "Status")]
+                status: String,
+                #[tabled(rename = "Output")]
+                output: String,
+                #[tabled(rename = "Time")]
+                time: String,
+                #[tabled(rename = "Details")]
+                details: String,
+            }
+
+            let rows: Vec<OutcomeRow> = outcomes
+                .iter()
+                .map(|outcome| {
+                    let (status, output, details) = match &outcome.result {
+                        BatchResult::Succeeded { result } => {
+                            ("✅ ok".to_string(), outcome.output_dir.display().to_string(), describe(result))
+                        }
+                        BatchResult::Failed { error } => ("❌ failed".to_string(), "-".to_string(), error.clone()),
+                    };
+                    OutcomeRow {
+                        file: outcome.input.display().to_string(),
+                        status,
+                        output,
+                        time: format!("{:.1}s", outcome.duration_ms as f64 / 1000.0),
+                        details: crate::utils::truncate_text(&details, 60),
+                    }
+                })
+                .collect();
+
+            println!("{}", Table::new(rows));
+            println!();
+            let summary = format!("{} succeeded, {} failed, {} total", succeeded, failed, outcomes.len());
+            if failed == 0 {
+                println!("{}", style(format!("✅ {}", summary)).bold().green());
+            } else {
+                println!("{}", style(format!("⚠️  {}", summary)).bold().yellow());
+            }
+        }
+        OutputFormat::Json | OutputFormat::Yaml => {
+            let report = serde_json::json!({
+                "total": outcomes.len(),
+                "succeeded": succeeded,
+                "failed": failed,
+                "results": outcomes,
+            });
+            if matches!(output_format, OutputFormat::Json) {
+                println!("{}", serde_json::to_string_pretty(&report)?);
+            } else {
+                println!("{}", serde_yaml::to_string(&report)?);
+            }
+        }
+        OutputFormat::Plain => {
+            for outcome in outcomes {
+                match &outcome.result {
+                    BatchResult::Succeeded { .. } => {
+                        println!("ok {} -> {}", outcome.input.display(), outcome.output_dir.display());
+                    }
+                    BatchResult::Failed { error } => println!("failed {}: {}", outcome.input.display(), error),
+                }
+            }
+        }
+    }
+
+    if failed > 0 {
+        return Err(anyhow!("{} of {} files failed", failed, outcomes.len()));
+    }
+    Ok(())
+}
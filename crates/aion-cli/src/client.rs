@@ -52,7 +52,7 @@ pub struct GenerateCodeRequest {
 }
 
 /// Code generation response
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct GenerateCodeResponse {
     pub id: String,
     pub status: String,
@@ -65,13 +65,13 @@ pub struct GenerateCodeResponse {
     pub estimated_time_saved_hours: f32,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct CodePreview {
     pub main_file: String,
     pub structure: Vec<FileInfo>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct FileInfo {
     pub path: String,
     pub language: String,
@@ -80,7 +80,7 @@ pub struct FileInfo {
 }
 
 /// Requirements analysis response
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct AnalyzeRequirementsResponse {
     pub id: String,
     pub confidence_score: f32,
@@ -278,6 +278,38 @@ impl AionClient {
         self.handle_response(response).await
     }
 
+    /// Optimize requirements
+    pub async fn optimize_requirements(&self, requirements: &str) -> Result<serde_json::Value> {
+        let body = serde_json::json!({
+            "requirements": requirements
+        });
+
+        let response = self.client
+            .post(&format!("{}/api/v1/requirements/optimize", self.base_url))
+            .bearer_auth(self.get_auth_token()?)
+            .json(&body)
+            .send()
+            .await?;
+
+        self.handle_response(response).await
+    }
+
+    /// Validate requirements
+    pub async fn validate_requirements(&self, requirements: &str) -> Result<serde_json::Value> {
+        let body = serde_json::json!({
+            "requirements": requirements
+        });
+
+        let response = self.client
+            .post(&format!("{}/api/v1/requirements/validate", self.base_url))
+            .bearer_auth(self.get_auth_token()?)
+            .json(&body)
+            .send()
+            .await?;
+
+        self.handle_response(response).await
+    }
+
     /// Analyze text
     pub async fn analyze_text(&self, text: &str, analysis_types: Vec<String>) -> Result<TextAnalysisResponse> {
         let body = serde_json::json!({
@@ -387,6 +419,12 @@ impl AionClient {
         self.handle_response(response).await
     }
 
+    /// SDK client authenticated with the current token, for the project and streaming APIs
+    pub fn api_client(&self) -> Result<aion_api_client::AionClient> {
+        let token = self.get_auth_token()?;
+        Ok(aion_api_client::AionClient::new(&self.base_url, &token)?)
+    }
+
     /// Get available models
     pub async fn get_models(&self) -> Result<serde_json::Value> {
         let response = self.client
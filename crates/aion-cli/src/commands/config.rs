@@ -0,0 +1,252 @@
+// Configuration Commands
+
+use anyhow::{anyhow, Result};
+use console::style;
+use std::process::Command;
+
+use crate::{
+    config::{CliConfig, ProfileSource},
+    output::OutputFormat,
+    utils,
+    ConfigCommands,
+};
+
+/// Config keys whose values are masked unless `--show-secrets` is given
+const SECRET_KEYS: [&str; 3] = ["token", "refresh_token", "hf_token"];
+
+pub async fn handle_config_command(
+    command: ConfigCommands,
+    config: &CliConfig,
+    output_format: &OutputFormat,
+) -> Result<()> {
+    match command {
+        ConfigCommands::Show { show_secrets } => show_config(config, show_secrets, output_format),
+        ConfigCommands::Edit => edit_config(config),
+        ConfigCommands::Profiles => list_profiles(config, output_format),
+        ConfigCommands::Use { profile } => {
+            let mut updated = config.clone();
+            updated.set_value("default_profile", &profile)?;
+            updated.save(config.path())?;
+            println!("{}", style(format!("✅ Default profile is now {}", profile)).green());
+            Ok(())
+        }
+        ConfigCommands::Set { key, value } => {
+            let mut updated = config.clone();
+            updated.set_value(&key, &value)?;
+            updated.save(config.path())?;
+            let shown = if is_secret_key(&key) { mask(&value) } else { value };
+            println!("{} = {}", style(config.qualify_key(&key)).cyan(), shown);
+            if let Some((var, _)) = config.env_overrides().get(&key) {
+                println!("{}", style(format!("⚠️  {} is set and overrides this value", var)).yellow());
+            }
+            Ok(())
+        }
+        ConfigCommands::Get { key, show_secrets } => {
+            let mut value = config.get_value(&key)?;
+            if !show_secrets {
+                mask_secrets(&key, &mut value);
+            }
+            match value {
+                toml::Value::String(s) => println!("{}", s),
+                other => println!("{}", other),
+            }
+            if let Some((var, _)) = config.env_overrides().get(&key) {
+                eprintln!("{}", style(format!("note: overridden by {}", var)).dim());
+            }
+            Ok(())
+        }
+        ConfigCommands::Reset { force } => {
+            if !force && !utils::confirm("Reset configuration to defaults? All profiles and tokens will be lost")? {
+                println!("Cancelled");
+                return Ok(());
+            }
+            CliConfig::default().save(config.path())?;
+            println!("{}", style("✅ Configuration reset to defaults").green());
+            Ok(())
+        }
+    }
+}
+
+fn show_config(config: &CliConfig, show_secrets: bool, output_format: &OutputFormat) -> Result<()> {
+    let mut file_values = toml::Value::try_from(config)?;
+    if !show_secrets {
+        mask_secrets("", &mut file_values);
+    }
+    let overrides: Vec<(String, String, String)> = config
+        .env_overrides()
+        .iter()
+        .map(|(key, (var, value))| {
+            let shown = if is_secret_key(key) && !show_secrets { mask(value) } else { value.clone() };
+            (key.clone(), var.clone(), shown)
+        })
+        .collect();
+    let profile_source = match config.profile_source() {
+        ProfileSource::Selected => "selected with --profile/AION_PROFILE",
+        ProfileSource::Default => "default_profile",
+    };
+
+    match output_format {
+        OutputFormat::Table | OutputFormat::Plain => {
+            println!("{}", style("⚙️  Configuration").bold());
+            println!("File: {}", style(config.path().display()).cyan());
+            println!("Active profile: {} ({})", style(config.active_profile()).green().bold(), profile_source);
+            println!();
+
+            println!("{}", style("Effective settings:").bold());
+            let api_url_source = match config.env_overrides().get("api_url") {
+                Some((var, _)) => format!("env {}", var),
+                None => format!("file profiles.{}.api_url", config.active_profile()),
+            };
+            println!("  api_url = {} ({})", config.api_url(), style(api_url_source).dim());
+            let token = match config.get_auth_token() {
+                Some(token) if show_secrets => token,
+                Some(token) => mask(&token),
+                None => "(none)".to_string(),
+            };
+            let token_source = match config.env_overrides().get("auth.token") {
+                Some((var, _)) => format!("env {}", var),
+                None => format!("file profiles.{}.auth.token", config.active_profile()),
+            };
+            println!("  auth.token = {} ({})", token, style(token_source).dim());
+            println!();
+
+            if !overrides.is_empty() {
+                println!("{}", style("Environment overrides:").bold());
+                for (key, var, value) in &overrides {
+                    println!("  {} = {} (from {})", key, value, var);
+                }
+                println!();
+            }
+
+            println!("{}", style("File contents:").bold());
+            println!("{}", toml::to_string_pretty(&file_values)?);
+        }
+        OutputFormat::Json | OutputFormat::Yaml => {
+            let report = serde_json::json!({
+                "path": config.path(),
+                "active_profile": config.active_profile(),
+                "profile_source": profile_source,
+                "env_overrides": overrides
+                    .iter()
+                    .map(|(key, var, value)| (key.clone(), serde_json::json!({ "variable": var, "value": value })))
+                    .collect::<serde_json::Map<_, _>>(),
+                "file": file_values,
+            });
+            if matches!(output_format, OutputFormat::Json) {
+                println!("{}", serde_json::to_string_pretty(&report)?);
+            } else {
+                println!("{}", serde_yaml::to_string(&report)?);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn list_profiles(config: &CliConfig, output_format: &OutputFormat) -> Result<()> {
+    match output_format {
+        OutputFormat::Json => {
+            let profiles: Vec<_> = config
+                .profiles
+                .iter()
+                .map(|(name, profile)| serde_json::json!({
+                    "name": name,
+                    "api_url": profile.api_url,
+                    "active": name == config.active_profile(),
+                    "default": *name == config.default_profile,
+                    "authenticated": profile.auth.token.is_some(),
+                }))
+                .collect();
+            println!("{}", serde_json::to_string_pretty(&profiles)?);
+        }
+        _ => {
+            for (name, profile) in &config.profiles {
+                let marker = if name == config.active_profile() { "*" } else { " " };
+                let default = if *name == config.default_profile { " (default)" } else { "" };
+                let auth = if profile.auth.token.is_some() { "authenticated" } else { "not logged in" };
+                println!("{} {}{}  {}  {}",
+                    marker,
+                    style(name).cyan(),
+                    default,
+                    profile.api_url,
+                    style(auth).dim()
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Edit the config file in `$VISUAL`/`$EDITOR`; the file is replaced only once
+/// the edited contents parse and validate
+fn edit_config(config: &CliConfig) -> Result<()> {
+    let editor = std::env::var("VISUAL")
+        .or_else(|_| std::env::var("EDITOR"))
+        .unwrap_or_else(|_| if cfg!(windows) { "notepad".to_string() } else { "vi".to_string() });
+    let mut editor_args = editor.split_whitespace();
+    let program = editor_args.next().ok_or_else(|| anyhow!("$EDITOR is empty"))?;
+    let editor_args: Vec<&str> = editor_args.collect();
+
+    let path = config.path();
+    let original = std::fs::read_to_string(path)?;
+    let draft = path.with_extension("toml.edit");
+    std::fs::write(&draft, &original)?;
+
+    loop {
+        let status = Command::new(program).args(&editor_args).arg(&draft).status()
+            .map_err(|e| anyhow!("Failed to launch editor '{}': {}", editor, e))?;
+        if !status.success() {
+            std::fs::remove_file(&draft)?;
+            return Err(anyhow!("Editor exited with {}; configuration left unchanged", status));
+        }
+
+        let edited = std::fs::read_to_string(&draft)?;
+        if edited == original {
+            std::fs::remove_file(&draft)?;
+            println!("No changes");
+            return Ok(());
+        }
+
+        match CliConfig::parse(&edited) {
+            Ok(_) => {
+                std::fs::rename(&draft, path)?;
+                println!("{}", style(format!("✅ Saved {}", path.display())).green());
+                return Ok(());
+            }
+            Err(e) => {
+                eprintln!("{}", style("❌ Invalid configuration:").red().bold());
+                eprintln!("{:#}", e);
+                if !utils::confirm("Re-open the editor to fix it?")? {
+                    std::fs::remove_file(&draft)?;
+                    return Err(anyhow!("Configuration not saved: {}", path.display()));
+                }
+            }
+        }
+    }
+}
+
+fn is_secret_key(key: &str) -> bool {
+    key.rsplit('.').next().is_some_and(|last| SECRET_KEYS.contains(&last))
+}
+
+/// Mask secret values in `value`, found at `key` in the config
+fn mask_secrets(key: &str, value: &mut toml::Value) {
+    match value {
+        toml::Value::String(s) if is_secret_key(key) => *s = mask(s),
+        toml::Value::Table(table) => {
+            for (child, value) in table.iter_mut() {
+                mask_secrets(child, value);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn mask(secret: &str) -> String {
+    let chars: Vec<char> = secret.chars().collect();
+    if chars.len() < 12 {
+        return "********".to_string();
+    }
+    let tail: String = chars[chars.len() - 4..].iter().collect();
+    format!("********{}", tail)
+}
@@ -1,13 +1,16 @@
 // Code Generation Commands
 
+use aion_ai_engine::ai_providers::AIProviderType;
+use aion_ai_engine::code_generation::estimate_cost;
 use anyhow::Result;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use tokio::fs;
 use indicatif::{ProgressBar, ProgressStyle};
 use console::style;
 
 use crate::{
-    client::{AionClient, GenerateCodeRequest},
+    batch,
+    client::{AionClient, GenerateCodeRequest, GenerateCodeResponse},
     output::OutputFormat,
     utils,
     GenerateCommands,
@@ -29,7 +32,24 @@ pub async fn handle_generate_command(
             output_dir,
             include_tests,
             include_docs,
+            concurrency,
+            provider,
+            max_cost,
         } => {
+            let provider: AIProviderType = provider.parse()?;
+            if let Some(source) = file.as_deref().filter(|f| requirements.is_none() && batch::is_batch_source(f)) {
+                let settings = GenerationSettings {
+                    language,
+                    framework,
+                    architecture,
+                    optimization,
+                    include_tests,
+                    include_docs,
+                    provider,
+                    max_cost,
+                };
+                return generate_batch(client, source, &settings, &output_dir, concurrency, output_format).await;
+            }
             generate_code(
                 client,
                 requirements,
@@ -41,6 +61,8 @@ pub async fn handle_generate_command(
                 output_dir,
                 include_tests,
                 include_docs,
+                provider,
+                max_cost,
                 output_format,
             ).await
         }
@@ -70,6 +92,8 @@ async fn generate_code(
     output_dir: PathBuf,
     include_tests: bool,
     include_docs: bool,
+    provider: AIProviderType,
+    max_cost: Option<f64>,
     output_format: &OutputFormat,
 ) -> Result<()> {
     // Get requirements text
@@ -102,7 +126,19 @@ async fn generate_code(
         println!("  Framework: {}", style(fw).yellow());
     }
     println!("  Optimization: {}", style(&optimization).yellow());
+
+    let estimate = estimate_cost(&requirements_text, &provider);
+    println!(
+        "  Estimated cost: {} ({:?}, ~{} prompt + ~{} completion tokens)",
+        style(format!("${:.4}", estimate.cost_usd)).yellow(),
+        estimate.provider,
+        estimate.prompt_tokens,
+        estimate.completion_tokens
+    );
     println!();
+    if let Some(max_cost) = max_cost {
+        estimate.check_limit(max_cost)?;
+    }
 
     // Create progress bar
     let pb = ProgressBar::new_spinner();
@@ -186,6 +222,71 @@ async fn generate_code(
     Ok(())
 }
 
+/// Options shared by every file of a batch generation
+struct GenerationSettings {
+    language: String,
+    framework: Option<String>,
+    architecture: String,
+    optimization: String,
+    include_tests: bool,
+    include_docs: bool,
+    provider: AIProviderType,
+    max_cost: Option<f64>,
+}
+
+/// Generate code for every file of a directory or pattern, extracting each
+/// result into its own subdirectory of `output_dir`
+async fn generate_batch(
+    client: &AionClient,
+    source: &Path,
+    settings: &GenerationSettings,
+    output_dir: &Path,
+    concurrency: usize,
+    output_format: &OutputFormat,
+) -> Result<()> {
+    let inputs = batch::collect_inputs(source)?;
+    if matches!(output_format, OutputFormat::Table) {
+        println!("{}", style(format!("🚀 Generating code for {} requirements files...", inputs.len())).bold().green());
+        println!("  Language: {}", style(&settings.language).yellow());
+        println!("  Architecture: {}", style(&settings.architecture).yellow());
+        println!("  Concurrency: {}", style(concurrency).yellow());
+        println!();
+    }
+
+    let outcomes = batch::run_batch(inputs, output_dir, concurrency, |input, dir| async move {
+        let requirements = fs::read_to_string(&input.path).await?;
+        if requirements.trim().is_empty() {
+            return Err(anyhow::anyhow!("Requirements cannot be empty"));
+        }
+        if let Some(max_cost) = settings.max_cost {
+            estimate_cost(&requirements, &settings.provider).check_limit(max_cost)?;
+        }
+        let request = GenerateCodeRequest {
+            requirements,
+            language: settings.language.clone(),
+            framework: settings.framework.clone(),
+            architecture: Some(settings.architecture.clone()),
+            optimization_level: Some(settings.optimization.clone()),
+            constraints: Some(serde_json::json!({
+                "include_tests": settings.include_tests,
+                "include_docs": settings.include_docs
+            })),
+            context: None,
+        };
+        let result: GenerateCodeResponse = client.generate_code(request).await?;
+        fetch_generation(client, &result.id, &dir).await?;
+        Ok(result)
+    })
+    .await?;
+
+    batch::report(&outcomes, output_format, |result| {
+        format!(
+            "{} files, {} lines (generation {})",
+            result.generated_files_count, result.total_lines_of_code, result.id
+        )
+    })
+}
+
 async fn list_generations(
     client: &AionClient,
     page: u32,
@@ -322,22 +423,9 @@ async fn download_generation(
     );
     pb.set_message("Downloading...");
 
-    let archive_data = client.download_generated_code(id).await?;
+    fetch_generation(client, id, &output_dir).await?;
     pb.finish_and_clear();
 
-    // Create output directory
-    fs::create_dir_all(&output_dir).await?;
-
-    // Extract archive
-    let archive_path = output_dir.join(format!("generation-{}.zip", id));
-    fs::write(&archive_path, &archive_data).await?;
-
-    println!("{}", style("📦 Extracting archive...").dim());
-    utils::extract_zip(&archive_path, &output_dir)?;
-
-    // Remove archive file
-    fs::remove_file(&archive_path).await?;
-
     match output_format {
         OutputFormat::Table => {
             println!("{}", style("✅ Download completed!").bold().green());
@@ -363,6 +451,23 @@ async fn download_generation(
     Ok(())
 }
 
+/// Download a generation and extract it into `output_dir`
+async fn fetch_generation(client: &AionClient, id: &str, output_dir: &Path) -> Result<()> {
+    let archive_data = client.download_generated_code(id).await?;
+
+    // Create output directory
+    fs::create_dir_all(output_dir).await?;
+
+    // Extract archive
+    let archive_path = output_dir.join(format!("generation-{}.zip", id));
+    fs::write(&archive_path, &archive_data).await?;
+    utils::extract_zip(&archive_path, output_dir)?;
+
+    // Remove archive file
+    fs::remove_file(&archive_path).await?;
+    Ok(())
+}
+
 async fn delete_generation(
     client: &AionClient,
     id: &str,
@@ -7,4 +7,5 @@ pub mod requirements;
 pub mod ai;
 pub mod project;
 pub mod config;
-pub mod status;
\ No newline at end of file
+pub mod status;
+pub mod models;
\ No newline at end of file
@@ -0,0 +1,364 @@
+// Local Model Management Commands
+
+use aion_ai_engine::{AIEngineError, CachedModel, DownloadPhase, DownloadProgress, ModelInfo, ModelManager, PullOptions};
+use anyhow::Result;
+use console::style;
+use indicatif::{ProgressBar, ProgressStyle};
+use std::time::Duration;
+
+use crate::{
+    config::CliConfig,
+    output::OutputFormat,
+    utils,
+    ModelsCommands,
+};
+
+pub async fn handle_models_command(
+    command: ModelsCommands,
+    config: &CliConfig,
+    output_format: &OutputFormat,
+) -> Result<()> {
+    let manager = ModelManager::new(
+        config.models.cache_dir.clone(),
+        (config.models.max_memory_gb * 1024 * 1024 * 1024) as usize,
+    ).await?;
+
+    match command {
+        ModelsCommands::List => list_models(&manager, output_format).await,
+        ModelsCommands::Pull { source, force } => {
+            let options = PullOptions {
+                force,
+                auth_token: config.huggingface_token(),
+                ..PullOptions::default()
+            };
+            pull_model(&manager, &source, &options, output_format).await
+        }
+        ModelsCommands::Rm { id, force } => remove_model(&manager, &id, force, output_format).await,
+        ModelsCommands::Info { id } => model_info(&manager, &id, output_format).await,
+    }
+}
+
+async fn list_models(manager: &ModelManager, output_format: &OutputFormat) -> Result<()> {
+    let models = manager.cached_models().await;
+
+    match output_format {
+        OutputFormat::Table => {
+            use tabled::{Table, Tabled};
+
+            #[derive(Tabled)]
+            struct ModelRow {
+                #[tabled(rename = "ID")]
+                id: String,
+                #[tabled(rename = "Name")]
+                name: String,
+                #[tabled(rename = "Size")]
+                size: String,
+                #[tabled(rename = "Last Used")]
+                last_used: String,
+                #[tabled(rename = "Loaded")]
+                loaded: String,
+            }
+
+            if models.is_empty() {
+                println!("{}", style("No cached models").dim());
+                println!("Cache directory: {}", manager.cache_dir().display());
+                return Ok(());
+            }
+
+            let total: u64 = models.iter().map(|m| m.size_on_disk).sum();
+            let rows: Vec<ModelRow> = models
+                .iter()
+                .map(|model| ModelRow {
+                    id: model.info.id.clone(),
+                    name: model.info.name.clone(),
+                    size: utils::format_bytes(model.size_on_disk),
+                    last_used: format_last_used(model),
+                    loaded: format_residency(&model.resident_in),
+                })
+                .collect();
+
+            println!("{}", Table::new(rows));
+            println!();
+            println!("{} models, {} in {}",
+                models.len(),
+                utils::format_bytes(total),
+                manager.cache_dir().display()
+            );
+        }
+        OutputFormat::Json => {
+            println!("{}", serde_json::to_string_pretty(&models)?);
+        }
+        OutputFormat::Yaml => {
+            println!("{}", serde_yaml::to_string(&models)?);
+        }
+        OutputFormat::Plain => {
+            for model in &models {
+                println!("{} {} {}",
+                    model.info.id,
+                    utils::format_bytes(model.size_on_disk),
+                    format_last_used(model)
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
+
+async fn pull_model(
+    manager: &ModelManager,
+    source: &str,
+    options: &PullOptions,
+    output_format: &OutputFormat,
+) -> Result<()> {
+    let cached = !options.force
+        && manager
+            .get_model_info(source)
+            .await
+            .and_then(|info| info.local_path)
+            .is_some_and(|path| path.exists());
+    if !cached {
+        println!("{}", style(format!("⬇️  Pulling {}...", source)).bold().blue());
+    }
+
+    let mut progress: Option<ProgressBar> = None;
+    let mut phase = None;
+    let result = manager.pull_model_with(source, options, |update| {
+        let pb = progress.get_or_insert_with(|| download_bar(update.total_bytes));
+        if phase != Some(update.phase) {
+            phase = Some(update.phase);
+            show_phase(pb, &update);
+        }
+        if pb.length().is_none() && update.phase != DownloadPhase::Verifying {
+            if let Some(total) = update.total_bytes {
+                pb.set_length(total);
+                pb.set_style(bar_style());
+            }
+        }
+        pb.set_position(update.downloaded_bytes);
+    }).await;
+    if let Some(pb) = progress {
+        pb.finish_and_clear();
+    }
+    let info = match result {
+        Ok(info) => info,
+        Err(AIEngineError::ModelAccessDenied { model, status, hint }) => {
+            return Err(anyhow::anyhow!(
+                "Access to model {} was denied (HTTP {}): {}\n\n\
+                 Create a read token at https://huggingface.co/settings/tokens and provide it with either:\n  \
+                 export HF_TOKEN=<token>\n  \
+                 ectus-r config set models.hf_token <token>",
+                model, status, hint
+            ));
+        }
+        Err(e @ AIEngineError::ChecksumMismatch { .. }) => {
+            return Err(anyhow::anyhow!("{}\nThe corrupt download was deleted; run the pull again", e));
+        }
+        Err(e) => return Err(e.into()),
+    };
+
+    match output_format {
+        OutputFormat::Table => {
+            if cached {
+                println!("{}", style("✅ Model already cached (use --force to download it again)").green());
+            } else {
+                println!("{}", style("✅ Model pulled successfully").bold().green());
+            }
+            println!("  ID: {}", style(&info.id).cyan());
+            println!("  Size: {}", utils::format_bytes(info.size_bytes));
+            if let Some(path) = &info.local_path {
+                println!("  Path: {}", path.display());
+            }
+        }
+        OutputFormat::Json => {
+            println!("{}", serde_json::to_string_pretty(&info)?);
+        }
+        OutputFormat::Yaml => {
+            println!("{}", serde_yaml::to_string(&info)?);
+        }
+        OutputFormat::Plain => {
+            println!("Pulled: {}", info.id);
+        }
+    }
+
+    Ok(())
+}
+
+async fn remove_model(
+    manager: &ModelManager,
+    id: &str,
+    force: bool,
+    output_format: &OutputFormat,
+) -> Result<()> {
+    let Some(model) = manager.cached_models().await.into_iter().find(|m| m.info.id == id) else {
+        return Err(anyhow::anyhow!("Model {} is not in the cache", id));
+    };
+
+    if !model.resident_in.is_empty() {
+        eprintln!("{}", style(format!(
+            "⚠️  Model {} is currently loaded by {}; removing it may break requests in flight",
+            id,
+            format_residency(&model.resident_in)
+        )).yellow());
+    }
+
+    let prompt = format!("Remove model {} and free {}?", id, utils::format_bytes(model.size_on_disk));
+    if !force && !utils::confirm(&prompt)? {
+        println!("Cancelled");
+        return Ok(());
+    }
+
+    let freed = manager.remove_cached_model(id).await?;
+
+    match output_format {
+        OutputFormat::Table => {
+            println!("{}", style(format!("✅ Removed {} ({} freed)", id, utils::format_bytes(freed))).green());
+        }
+        OutputFormat::Json => {
+            println!("{}", serde_json::json!({
+                "status": "removed",
+                "model_id": id,
+                "freed_bytes": freed
+            }));
+        }
+        OutputFormat::Yaml => {
+            println!("status: removed");
+            println!("model_id: {}", id);
+            println!("freed_bytes: {}", freed);
+        }
+        OutputFormat::Plain => {
+            println!("Removed: {}", id);
+        }
+    }
+
+    Ok(())
+}
+
+async fn model_info(manager: &ModelManager, id: &str, output_format: &OutputFormat) -> Result<()> {
+    let info = manager
+        .get_model_info(id)
+        .await
+        .ok_or_else(|| anyhow::anyhow!("Unknown model: {}", id))?;
+    let cached = manager.cached_models().await.into_iter().find(|m| m.info.id == id);
+
+    let details = serde_json::json!({
+        "id": info.id,
+        "name": info.name,
+        "version": info.version,
+        "description": info.description,
+        "format": format!("{:?}", info.format),
+        "tasks": info.tasks,
+        "context_length": info.metadata.get("context_length"),
+        "quantization": info.metadata.get("quantization"),
+        "tokenizer": info.metadata.get("tokenizer"),
+        "memory_requirements": info.memory_requirements,
+        "cached": cached.is_some(),
+        "size_on_disk": cached.as_ref().map(|m| m.size_on_disk),
+        "last_used": cached.as_ref().and_then(|m| m.last_used),
+        "loaded_by": cached.as_ref().map(|m| m.resident_in.clone()).unwrap_or_default(),
+        "remote_url": info.remote_url,
+    });
+
+    match output_format {
+        OutputFormat::Table => {
+            println!("{}", style(format!("🧠 {}", info.name)).bold());
+            println!();
+            println!("ID: {}", style(&info.id).cyan());
+            println!("Version: {}", info.version);
+            println!("Description: {}", info.description);
+            println!("Format: {:?}", info.format);
+            println!("Tasks: {}", info.tasks.join(", "));
+            println!("Context length: {}", metadata_value(&info, "context_length"));
+            println!("Quantization: {}", metadata_value(&info, "quantization"));
+            println!("Tokenizer: {}", metadata_value(&info, "tokenizer"));
+            println!("Memory required: {}", utils::format_bytes(info.memory_requirements));
+            match &cached {
+                Some(model) => {
+                    println!("Cached: {} ({})", style("yes").green(), utils::format_bytes(model.size_on_disk));
+                    println!("Last used: {}", format_last_used(model));
+                    println!("Loaded by: {}", format_residency(&model.resident_in));
+                }
+                None => println!("Cached: {}", style("no").dim()),
+            }
+        }
+        OutputFormat::Json => {
+            println!("{}", serde_json::to_string_pretty(&details)?);
+        }
+        OutputFormat::Yaml => {
+            println!("{}", serde_yaml::to_string(&details)?);
+        }
+        OutputFormat::Plain => {
+            println!("{} {} {}", info.id, info.version, metadata_value(&info, "context_length"));
+        }
+    }
+
+    Ok(())
+}
+
+fn download_bar(total_bytes: Option<u64>) -> ProgressBar {
+    match total_bytes {
+        Some(total) => {
+            let pb = ProgressBar::new(total);
+            pb.set_style(bar_style());
+            pb
+        }
+        None => {
+            let pb = ProgressBar::new_spinner();
+            pb.set_style(
+                ProgressStyle::default_spinner()
+                    .template("{spinner:.blue} {bytes} ({bytes_per_sec})")
+                    .unwrap()
+            );
+            pb
+        }
+    }
+}
+
+fn bar_style() -> ProgressStyle {
+    ProgressStyle::default_bar()
+        .template("{bar:40.cyan/blue} {bytes}/{total_bytes} ({bytes_per_sec}, {eta})")
+        .unwrap()
+}
+
+fn show_phase(pb: &ProgressBar, update: &DownloadProgress) {
+    match update.phase {
+        DownloadPhase::Downloading { resumed_from: 0 } => {}
+        DownloadPhase::Downloading { resumed_from } => {
+            pb.println(format!("↪️  Resuming after {} already downloaded", utils::format_bytes(resumed_from)));
+            // Speed and ETA should only count what this run downloads
+            pb.set_position(resumed_from);
+            pb.reset_eta();
+        }
+        DownloadPhase::Attached => {
+            pb.println("⏳ This model is already being pulled; following that download");
+        }
+        DownloadPhase::Verifying => {
+            pb.set_style(ProgressStyle::default_spinner().template("{spinner:.blue} {msg}").unwrap());
+            pb.set_message("Verifying checksum...");
+            pb.enable_steady_tick(Duration::from_millis(100));
+        }
+    }
+}
+
+fn metadata_value(info: &ModelInfo, key: &str) -> String {
+    match info.metadata.get(key) {
+        Some(serde_json::Value::String(value)) => value.clone(),
+        Some(value) => value.to_string(),
+        None => "unknown".to_string(),
+    }
+}
+
+fn format_last_used(model: &CachedModel) -> String {
+    model
+        .last_used
+        .map(|t| t.format("%Y-%m-%d %H:%M:%S").to_string())
+        .unwrap_or_else(|| "never".to_string())
+}
+
+fn format_residency(pids: &[u32]) -> String {
+    if pids.is_empty() {
+        return "-".to_string();
+    }
+    let pids: Vec<String> = pids.iter().map(|pid| format!("pid {}", pid)).collect();
+    pids.join(", ")
+}
@@ -0,0 +1,259 @@
+// Project Management Commands
+
+use aion_api_client::{
+    LogEntry, LogLevel, LogStreamEvent, LogStreamOptions, Project, ProjectRequest, ProjectsApi, ReconnectPolicy,
+};
+use aion_analysis::AnalysisProject;
+use anyhow::{anyhow, Context, Result};
+use console::style;
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+
+use crate::{
+    client::AionClient,
+    output::OutputFormat,
+    utils,
+    ProjectCommands,
+};
+
+pub async fn handle_project_command(
+    command: ProjectCommands,
+    client: &AionClient,
+    output_format: &OutputFormat,
+) -> Result<()> {
+    // Works offline on a local analysis, so no API client is needed
+    let command = match command {
+        ProjectCommands::Sbom { analysis, out, advisories } => {
+            return export_sbom(&analysis, out.as_deref(), &advisories, output_format).await
+        }
+        command => command,
+    };
+
+    let projects = client.api_client()?.projects();
+
+    match command {
+        ProjectCommands::List => {
+            let page = projects.list(None).await?;
+            print_projects(&page.data, output_format)
+        }
+        ProjectCommands::Create { name, description, template } => {
+            let metadata = template.map(|t| [("template".to_string(), serde_json::json!(t))].into_iter().collect());
+            let project = projects.create(ProjectRequest {
+                name,
+                description,
+                metadata,
+                ..Default::default()
+            }).await?;
+            print_project(&project, output_format)
+        }
+        ProjectCommands::Get { id } => {
+            let project = projects.get(resolve_project_id(&projects, &id).await?).await?;
+            print_project(&project, output_format)
+        }
+        ProjectCommands::Update { id, name, description } => {
+            let current = projects.get(resolve_project_id(&projects, &id).await?).await?;
+            let project = projects.update(current.id, ProjectRequest {
+                name: name.unwrap_or(current.name),
+                description: description.or(current.description),
+                tech_stack: current.tech_stack,
+                architecture: current.architecture,
+                requirements: None,
+                metadata: Some(current.metadata),
+            }).await?;
+            print_project(&project, output_format)
+        }
+        ProjectCommands::Delete { id, force } => {
+            let project_id = resolve_project_id(&projects, &id).await?;
+            if !force && !utils::confirm(&format!("Delete project {}?", id))? {
+                println!("Cancelled");
+                return Ok(());
+            }
+            projects.delete(project_id).await?;
+            println!("{}", style(format!("✅ Project {} deleted", id)).green());
+            Ok(())
+        }
+        ProjectCommands::Sbom { .. } => unreachable!("handled above"),
+        ProjectCommands::Deploy { id, environment } => {
+            Err(anyhow!(
+                "Deploying {} to {} is not available from the CLI yet; use the dashboard",
+                id,
+                environment
+            ))
+        }
+        ProjectCommands::Logs { id, follow, since, level } => {
+            // Without --follow the stream ends after the backlog, so don't retry forever
+            let reconnect = if follow {
+                ReconnectPolicy::default()
+            } else {
+                ReconnectPolicy { max_attempts: Some(3), ..Default::default() }
+            };
+            let options = LogStreamOptions {
+                follow,
+                since: since.as_deref().map(utils::parse_since).transpose()?,
+                min_level: level.as_deref().map(str::parse::<LogLevel>).transpose()?,
+                reconnect,
+            };
+            stream_logs(&projects, &id, options, output_format).await
+        }
+    }
+}
+
+/// Print log entries as they arrive until the stream ends or Ctrl-C is pressed
+async fn stream_logs(
+    projects: &ProjectsApi,
+    id: &str,
+    options: LogStreamOptions,
+    output_format: &OutputFormat,
+) -> Result<()> {
+    let project_id = resolve_project_id(projects, id).await?;
+    let mut stream = projects.stream_logs(project_id, options).await?;
+
+    let interrupted = tokio::signal::ctrl_c();
+    tokio::pin!(interrupted);
+
+    loop {
+        let event = tokio::select! {
+            _ = &mut interrupted => break,
+            event = stream.next() => event,
+        };
+        match event {
+            None => break,
+            Some(Ok(LogStreamEvent::Entry(entry))) => print_log_entry(&entry, output_format)?,
+            Some(Ok(LogStreamEvent::Disconnected { reason, attempt, retry_in })) => {
+                eprintln!("{}", style(format!(
+                    "⚠️  Log stream disconnected ({}); reconnecting in {:.1}s (attempt {})",
+                    reason,
+                    retry_in.as_secs_f64(),
+                    attempt
+                )).yellow());
+            }
+            Some(Ok(LogStreamEvent::Reconnected)) => {
+                eprintln!("{}", style("🔌 Log stream reconnected").dim());
+            }
+            Some(Err(e)) => return Err(e.into()),
+        }
+    }
+
+    Ok(())
+}
+
+fn print_log_entry(entry: &LogEntry, output_format: &OutputFormat) -> Result<()> {
+    match output_format {
+        // One record per line so the output can be piped into jq and friends
+        OutputFormat::Json => println!("{}", serde_json::to_string(entry)?),
+        OutputFormat::Yaml => print!("---\n{}", serde_yaml::to_string(entry)?),
+        OutputFormat::Table | OutputFormat::Plain => {
+            let level = format!("{:<5}", entry.level.to_string().to_uppercase());
+            let level = match entry.level {
+                LogLevel::Error => style(level).red().bold(),
+                LogLevel::Warn => style(level).yellow(),
+                LogLevel::Info => style(level).green(),
+                LogLevel::Debug => style(level).blue(),
+                LogLevel::Trace => style(level).dim(),
+            };
+            let source = entry
+                .source
+                .as_deref()
+                .map(|s| format!(" {}", style(format!("[{}]", s)).cyan()))
+                .unwrap_or_default();
+            println!("{} {}{} {}",
+                style(entry.timestamp.format("%Y-%m-%d %H:%M:%S%.3f")).dim(),
+                level,
+                source,
+                entry.message
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Write the CycloneDX SBOM of a serialized `AnalysisProject` to `out` or stdout
+async fn export_sbom(
+    analysis: &Path,
+    out: Option<&Path>,
+    advisories: &[PathBuf],
+    output_format: &OutputFormat,
+) -> Result<()> {
+    let content = tokio::fs::read_to_string(analysis)
+        .await
+        .with_context(|| format!("Cannot read {}", analysis.display()))?;
+    let mut project: AnalysisProject = serde_json::from_str(&content)
+        .with_context(|| format!("{} is not a project analysis", analysis.display()))?;
+
+    for path in advisories {
+        let content = tokio::fs::read_to_string(path)
+            .await
+            .with_context(|| format!("Cannot read {}", path.display()))?;
+        let advisories = aion_analysis::parse_osv_advisories(&content)
+            .map_err(|e| anyhow!("{} is not an OSV advisory file: {}", path.display(), e))?;
+        aion_analysis::apply_advisories(&mut project, &advisories);
+    }
+
+    let sbom = aion_analysis::export_sbom(&project).map_err(|e| anyhow!("Failed to build SBOM: {}", e))?;
+
+    match out {
+        Some(path) => {
+            tokio::fs::write(path, &sbom).await?;
+            if matches!(output_format, OutputFormat::Table) {
+                println!("{}", style(format!(
+                    "✅ SBOM covering {} dependencies written to {}",
+                    project.dependencies.len(),
+                    path.display()
+                )).green());
+            }
+        }
+        // The SBOM is already JSON, so it is printed as is for every output format
+        None => println!("{}", sbom),
+    }
+    Ok(())
+}
+
+/// Accept either a project ID or an exact project name
+async fn resolve_project_id(projects: &ProjectsApi, id: &str) -> Result<Uuid> {
+    if let Ok(project_id) = Uuid::parse_str(id) {
+        return Ok(project_id);
+    }
+    let matches = projects.search(id, None).await?;
+    matches
+        .data
+        .into_iter()
+        .find(|project| project.name == id)
+        .map(|project| project.id)
+        .ok_or_else(|| anyhow!("No project with ID or name '{}'", id))
+}
+
+fn print_project(project: &Project, output_format: &OutputFormat) -> Result<()> {
+    match output_format {
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(project)?),
+        OutputFormat::Yaml => println!("{}", serde_yaml::to_string(project)?),
+        OutputFormat::Table | OutputFormat::Plain => {
+            println!("{}", style(&project.name).bold());
+            println!("ID: {}", style(project.id).cyan());
+            if let Some(description) = &project.description {
+                println!("Description: {}", description);
+            }
+            println!("Status: {:?}", project.status);
+            if !project.tech_stack.is_empty() {
+                println!("Tech stack: {}", project.tech_stack.join(", "));
+            }
+            println!("Updated: {}", project.updated_at.format("%Y-%m-%d %H:%M:%S"));
+        }
+    }
+    Ok(())
+}
+
+fn print_projects(projects: &[Project], output_format: &OutputFormat) -> Result<()> {
+    match output_format {
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(projects)?),
+        OutputFormat::Yaml => println!("{}", serde_yaml::to_string(projects)?),
+        OutputFormat::Table | OutputFormat::Plain => {
+            if projects.is_empty() {
+                println!("{}", style("No projects found").dim());
+            }
+            for project in projects {
+                println!("{}  {}  {:?}", style(project.id).cyan(), project.name, project.status);
+            }
+        }
+    }
+    Ok(())
+}
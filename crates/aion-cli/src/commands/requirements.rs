@@ -0,0 +1,180 @@
+// Requirements Analysis Commands
+
+use anyhow::{anyhow, Result};
+use console::style;
+use indicatif::{ProgressBar, ProgressStyle};
+use std::path::{Path, PathBuf};
+use tokio::fs;
+
+use crate::{
+    batch,
+    client::{AionClient, AnalyzeRequirementsResponse},
+    output::OutputFormat,
+    utils,
+    RequirementsCommands,
+};
+
+pub async fn handle_requirements_command(
+    command: RequirementsCommands,
+    client: &AionClient,
+    output_format: &OutputFormat,
+) -> Result<()> {
+    match command {
+        RequirementsCommands::Analyze { requirements, file, detailed, output_dir, concurrency } => {
+            match file {
+                Some(source) if requirements.is_none() && batch::is_batch_source(&source) => {
+                    analyze_batch(client, &source, &output_dir, concurrency, output_format).await
+                }
+                file => {
+                    let text = read_requirements(requirements, file).await?;
+                    analyze_requirements(client, &text, detailed, output_format).await
+                }
+            }
+        }
+        RequirementsCommands::Optimize { requirements, file } => {
+            let text = read_requirements(requirements, file).await?;
+            let result = with_spinner("Optimizing requirements...", client.optimize_requirements(&text)).await?;
+            print_value("✨ Optimized Requirements", &result, output_format)
+        }
+        RequirementsCommands::Validate { requirements, file } => {
+            let text = read_requirements(requirements, file).await?;
+            let result = with_spinner("Validating requirements...", client.validate_requirements(&text)).await?;
+            print_value("🔍 Validation Results", &result, output_format)
+        }
+    }
+}
+
+/// Requirements text from `--requirements`, `--file` or stdin
+async fn read_requirements(requirements: Option<String>, file: Option<PathBuf>) -> Result<String> {
+    let text = match (requirements, file) {
+        (Some(text), None) => text,
+        (None, Some(path)) => fs::read_to_string(&path).await?,
+        (Some(_), Some(_)) => {
+            return Err(anyhow!("Cannot specify both --requirements and --file"));
+        }
+        (None, None) => {
+            println!("Enter your requirements (press Ctrl+D when finished):");
+            utils::read_multiline_input()?
+        }
+    };
+
+    if text.trim().is_empty() {
+        return Err(anyhow!("Requirements cannot be empty"));
+    }
+    Ok(text)
+}
+
+async fn with_spinner<T>(message: &'static str, task: impl std::future::Future<Output = Result<T>>) -> Result<T> {
+    let pb = ProgressBar::new_spinner();
+    pb.set_style(
+        ProgressStyle::default_spinner()
+            .template("{spinner:.blue} {msg}")
+            .unwrap()
+    );
+    pb.set_message(message);
+    pb.enable_steady_tick(std::time::Duration::from_millis(100));
+    let result = task.await;
+    pb.finish_and_clear();
+    result
+}
+
+async fn analyze_requirements(
+    client: &AionClient,
+    requirements: &str,
+    detailed: bool,
+    output_format: &OutputFormat,
+) -> Result<()> {
+    let result = with_spinner("Analyzing requirements...", client.analyze_requirements(requirements)).await?;
+
+    match output_format {
+        OutputFormat::Table => {
+            println!("{}", style("📋 Requirements Analysis").bold());
+            println!();
+            println!("  ID: {}", style(&result.id).cyan());
+            println!("  Confidence: {:.0}%", result.confidence_score * 100.0);
+            println!("  User stories: {}", style(result.user_stories_count).yellow());
+            println!("  Risks identified: {}", style(result.risks_identified).yellow());
+            println!("  Implementation phases: {}", style(result.implementation_phases).yellow());
+            println!("  Optimization suggestions: {}", style(result.optimization_suggestions).yellow());
+            println!();
+            println!("{}", style("Technical summary:").bold());
+            if detailed {
+                println!("{}", result.technical_summary);
+            } else {
+                println!("{}", utils::truncate_text(&result.technical_summary, 300));
+            }
+        }
+        OutputFormat::Json => {
+            println!("{}", serde_json::to_string_pretty(&result)?);
+        }
+        OutputFormat::Yaml => {
+            println!("{}", serde_yaml::to_string(&result)?);
+        }
+        OutputFormat::Plain => {
+            println!("Analysis ID: {}", result.id);
+            println!("Confidence: {:.2}", result.confidence_score);
+            println!("User stories: {}", result.user_stories_count);
+            println!("Risks: {}", result.risks_identified);
+        }
+    }
+
+    Ok(())
+}
+
+/// Analyze every file of a directory or pattern, writing `analysis.json` to
+/// a subdirectory of `output_dir` per file
+async fn analyze_batch(
+    client: &AionClient,
+    source: &Path,
+    output_dir: &Path,
+    concurrency: usize,
+    output_format: &OutputFormat,
+) -> Result<()> {
+    let inputs = batch::collect_inputs(source)?;
+    if matches!(output_format, OutputFormat::Table) {
+        println!("{}", style(format!("📚 Analyzing {} requirements files...", inputs.len())).bold().green());
+    }
+
+    let outcomes = batch::run_batch(inputs, output_dir, concurrency, |input, dir| async move {
+        let text = fs::read_to_string(&input.path).await?;
+        if text.trim().is_empty() {
+            return Err(anyhow!("Requirements cannot be empty"));
+        }
+        let result: AnalyzeRequirementsResponse = client.analyze_requirements(&text).await?;
+        fs::create_dir_all(&dir).await?;
+        fs::write(dir.join("analysis.json"), serde_json::to_string_pretty(&result)?).await?;
+        Ok(result)
+    })
+    .await?;
+
+    batch::report(&outcomes, output_format, |result| {
+        format!(
+            "{} stories, {} risks, {:.0}% confidence",
+            result.user_stories_count,
+            result.risks_identified,
+            result.confidence_score * 100.0
+        )
+    })
+}
+
+fn print_value(title: &str, value: &serde_json::Value, output_format: &OutputFormat) -> Result<()> {
+    match output_format {
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(value)?),
+        OutputFormat::Yaml => println!("{}", serde_yaml::to_string(value)?),
+        OutputFormat::Table | OutputFormat::Plain => {
+            println!("{}", style(title).bold());
+            match value.as_object() {
+                Some(fields) => {
+                    for (key, value) in fields {
+                        match value {
+                            serde_json::Value::String(s) => println!("  {}: {}", key, s),
+                            other => println!("  {}: {}", key, other),
+                        }
+                    }
+                }
+                None => println!("{}", value),
+            }
+        }
+    }
+    Ok(())
+}
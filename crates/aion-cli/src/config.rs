@@ -1,19 +1,66 @@
 // CLI Configuration Management
 
-use anyhow::Result;
+use anyhow::{anyhow, Context, Result};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::path::{Path, PathBuf};
 
+/// Name of the profile created for new and migrated configs
+pub const DEFAULT_PROFILE: &str = "default";
+
+/// Environment variables that override values of the active profile
+const ENV_OVERRIDES: [(&str, &str); 2] = [("api_url", "AION_API_URL"), ("auth.token", "AION_TOKEN")];
+
+/// Environment variables holding a Hugging Face token, in order of precedence
+const HF_TOKEN_VARS: [&str; 2] = ["HF_TOKEN", "HUGGING_FACE_HUB_TOKEN"];
+
 /// CLI configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CliConfig {
-    pub api_url: String,
-    pub auth: AuthConfig,
+    /// Profile used when none is selected with `--profile` or `AION_PROFILE`
+    #[serde(default = "default_profile_name")]
+    pub default_profile: String,
+    #[serde(default)]
+    pub profiles: BTreeMap<String, ProfileConfig>,
     pub output: OutputConfig,
     pub generation: GenerationConfig,
+    #[serde(default)]
+    pub models: ModelsConfig,
+    /// Pre-profile connection settings, moved into a profile when loaded
+    #[serde(default, skip_serializing)]
+    api_url: Option<String>,
+    #[serde(default, skip_serializing)]
+    auth: Option<AuthConfig>,
+    #[serde(skip)]
+    active_profile: String,
+    #[serde(skip)]
+    profile_source: ProfileSource,
+    /// Config keys overridden by the environment, with their values
+    #[serde(skip)]
+    env_overrides: BTreeMap<String, (String, String)>,
+    #[serde(skip)]
+    path: PathBuf,
 }
 
+/// Connection settings of one environment
 #[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProfileConfig {
+    pub api_url: String,
+    #[serde(default)]
+    pub auth: AuthConfig,
+}
+
+/// How the active profile was chosen
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ProfileSource {
+    /// `--profile` flag or `AION_PROFILE`
+    Selected,
+    /// `default_profile` from the config file
+    #[default]
+    Default,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct AuthConfig {
     pub token: Option<String>,
     pub refresh_token: Option<String>,
@@ -35,15 +82,43 @@ pub struct GenerationConfig {
     pub auto_download: bool,
 }
 
-impl Default for CliConfig {
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModelsConfig {
+    pub cache_dir: PathBuf,
+    pub max_memory_gb: u64,
+    /// Hugging Face access token for gated models
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hf_token: Option<String>,
+}
+
+impl Default for ModelsConfig {
+    fn default() -> Self {
+        let cache_dir = dirs::cache_dir()
+            .unwrap_or_else(|| PathBuf::from("."))
+            .join("aion")
+            .join("models");
+        Self {
+            cache_dir,
+            max_memory_gb: 8,
+            hf_token: None,
+        }
+    }
+}
+
+impl Default for ProfileConfig {
     fn default() -> Self {
         Self {
             api_url: "http://localhost:8080".to_string(),
-            auth: AuthConfig {
-                token: None,
-                refresh_token: None,
-                expires_at: None,
-            },
+            auth: AuthConfig::default(),
+        }
+    }
+}
+
+impl Default for CliConfig {
+    fn default() -> Self {
+        Self {
+            default_profile: default_profile_name(),
+            profiles: BTreeMap::from([(default_profile_name(), ProfileConfig::default())]),
             output: OutputConfig {
                 format: "table".to_string(),
                 no_color: false,
@@ -55,27 +130,115 @@ impl Default for CliConfig {
                 default_optimization: "balanced".to_string(),
                 auto_download: false,
             },
+            models: ModelsConfig::default(),
+            api_url: None,
+            auth: None,
+            active_profile: default_profile_name(),
+            profile_source: ProfileSource::Default,
+            env_overrides: BTreeMap::new(),
+            path: PathBuf::new(),
         }
     }
 }
 
+fn default_profile_name() -> String {
+    DEFAULT_PROFILE.to_string()
+}
+
 impl CliConfig {
-    /// Load configuration from file or create default
-    pub fn load(config_path: Option<&Path>) -> Result<Self> {
+    /// Load configuration from file or create default, activating `profile`
+    /// or the file's default profile
+    pub fn load(config_path: Option<&Path>, profile: Option<&str>) -> Result<Self> {
         let config_path = match config_path {
             Some(path) => path.to_path_buf(),
             None => Self::default_config_path()?,
         };
 
-        if config_path.exists() {
+        let mut config = if config_path.exists() {
             let content = std::fs::read_to_string(&config_path)?;
-            let config: CliConfig = toml::from_str(&content)?;
-            Ok(config)
+            let (config, migrated) = Self::from_toml(&content)
+                .with_context(|| format!("Invalid configuration in {}", config_path.display()))?;
+            if migrated {
+                config.save(&config_path)?;
+            }
+            config
         } else {
             // Create default config
             let config = Self::default();
             config.save(&config_path)?;
-            Ok(config)
+            config
+        };
+
+        config.path = config_path;
+        config.select_profile(profile)?;
+        config.apply_env_overrides();
+        Ok(config)
+    }
+
+    /// Parse and validate configuration file contents
+    pub fn parse(content: &str) -> Result<Self> {
+        Self::from_toml(content).map(|(config, _)| config)
+    }
+
+    /// Parse and validate, also reporting whether a pre-profile config was migrated
+    fn from_toml(content: &str) -> Result<(Self, bool)> {
+        let mut config: CliConfig = toml::from_str(content)?;
+        // Pre-profile configs carry api_url/auth at the top level
+        let migrated = config.migrate_legacy_settings();
+        if !config.profiles.contains_key(&config.default_profile) {
+            return Err(anyhow!(
+                "default_profile '{}' does not match any [profiles.*] table",
+                config.default_profile
+            ));
+        }
+        for (name, profile) in &config.profiles {
+            if !(profile.api_url.starts_with("http://") || profile.api_url.starts_with("https://")) {
+                return Err(anyhow!("profiles.{}.api_url must be an http(s) URL, got '{}'", name, profile.api_url));
+            }
+        }
+        Ok((config, migrated))
+    }
+
+    /// Move top-level api_url/auth into a profile; returns whether anything moved
+    fn migrate_legacy_settings(&mut self) -> bool {
+        let (api_url, auth) = (self.api_url.take(), self.auth.take());
+        if api_url.is_none() && auth.is_none() {
+            return false;
+        }
+        if self.profiles.is_empty() {
+            let default = ProfileConfig::default();
+            self.profiles.insert(
+                self.default_profile.clone(),
+                ProfileConfig {
+                    api_url: api_url.unwrap_or(default.api_url),
+                    auth: auth.unwrap_or_default(),
+                },
+            );
+        }
+        true
+    }
+
+    fn select_profile(&mut self, profile: Option<&str>) -> Result<()> {
+        let (name, source) = match profile {
+            Some(name) => (name.to_string(), ProfileSource::Selected),
+            None => (self.default_profile.clone(), ProfileSource::Default),
+        };
+        if !self.profiles.contains_key(&name) {
+            let available: Vec<&str> = self.profiles.keys().map(String::as_str).collect();
+            return Err(anyhow!("Unknown profile '{}' (available: {})", name, available.join(", ")));
+        }
+        self.active_profile = name;
+        self.profile_source = source;
+        Ok(())
+    }
+
+    fn apply_env_overrides(&mut self) {
+        for (key, var) in ENV_OVERRIDES {
+            if let Ok(value) = std::env::var(var) {
+                if !value.is_empty() {
+                    self.env_overrides.insert(key.to_string(), (var.to_string(), value));
+                }
+            }
         }
     }
 
@@ -95,35 +258,143 @@ impl CliConfig {
         Ok(home.join(".config").join("aion").join("config.toml"))
     }
 
+    /// File this configuration was loaded from
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn active_profile(&self) -> &str {
+        &self.active_profile
+    }
+
+    pub fn profile_source(&self) -> ProfileSource {
+        self.profile_source
+    }
+
+    /// Keys overridden by the environment, as key -> (variable, value)
+    pub fn env_overrides(&self) -> &BTreeMap<String, (String, String)> {
+        &self.env_overrides
+    }
+
+    /// Settings of the active profile as stored in the file
+    pub fn profile(&self) -> &ProfileConfig {
+        &self.profiles[&self.active_profile]
+    }
+
+    fn profile_mut(&mut self) -> &mut ProfileConfig {
+        self.profiles
+            .get_mut(&self.active_profile)
+            .expect("active profile exists")
+    }
+
+    /// API URL of the active profile, after environment overrides
+    pub fn api_url(&self) -> &str {
+        match self.env_overrides.get("api_url") {
+            Some((_, value)) => value,
+            None => &self.profile().api_url,
+        }
+    }
+
     /// Get authentication token
     pub fn get_auth_token(&self) -> Option<String> {
+        if let Some((_, token)) = self.env_overrides.get("auth.token") {
+            return Some(token.clone());
+        }
+        let auth = &self.profile().auth;
         // Check if token is expired
-        if let Some(expires_at) = self.auth.expires_at {
+        if let Some(expires_at) = auth.expires_at {
             let now = chrono::Utc::now().timestamp();
             if now >= expires_at {
                 return None; // Token expired
             }
         }
-        self.auth.token.clone()
+        auth.token.clone()
+    }
+
+    /// Hugging Face token from `HF_TOKEN`, `HUGGING_FACE_HUB_TOKEN` or `models.hf_token`
+    pub fn huggingface_token(&self) -> Option<String> {
+        HF_TOKEN_VARS
+            .iter()
+            .filter_map(|var| std::env::var(var).ok())
+            .find(|token| !token.is_empty())
+            .or_else(|| self.models.hf_token.clone().filter(|token| !token.is_empty()))
     }
 
     /// Set authentication tokens
     pub fn set_auth_tokens(&mut self, token: String, refresh_token: String, expires_in: u64) {
         let expires_at = chrono::Utc::now().timestamp() + expires_in as i64;
-        self.auth.token = Some(token);
-        self.auth.refresh_token = Some(refresh_token);
-        self.auth.expires_at = Some(expires_at);
+        let auth = &mut self.profile_mut().auth;
+        auth.token = Some(token);
+        auth.refresh_token = Some(refresh_token);
+        auth.expires_at = Some(expires_at);
     }
 
     /// Clear authentication
     pub fn clear_auth(&mut self) {
-        self.auth.token = None;
-        self.auth.refresh_token = None;
-        self.auth.expires_at = None;
+        self.profile_mut().auth = AuthConfig::default();
     }
 
     /// Check if authenticated
     pub fn is_authenticated(&self) -> bool {
         self.get_auth_token().is_some()
     }
-}
\ No newline at end of file
+
+    /// Resolve a user-facing key; `api_url` and `auth.*` refer to the active profile
+    pub fn qualify_key(&self, key: &str) -> String {
+        if key == "api_url" || key == "auth" || key.starts_with("auth.") {
+            format!("profiles.{}.{}", self.active_profile, key)
+        } else {
+            key.to_string()
+        }
+    }
+
+    /// Look up a dotted key in the file representation
+    pub fn get_value(&self, key: &str) -> Result<toml::Value> {
+        let mut value = toml::Value::try_from(self)?;
+        for part in self.qualify_key(key).split('.') {
+            value = value
+                .get(part)
+                .cloned()
+                .ok_or_else(|| anyhow!("Unknown configuration key: {}", key))?;
+        }
+        Ok(value)
+    }
+
+    /// Set a dotted key, validating the resulting configuration
+    ///
+    /// `raw` is read as a TOML literal when possible (numbers, booleans) and as
+    /// a string otherwise. Missing tables along the path are created, so
+    /// `profiles.staging.api_url` creates the `staging` profile.
+    pub fn set_value(&mut self, key: &str, raw: &str) -> Result<()> {
+        let qualified = self.qualify_key(key);
+        let new_value = toml::from_str::<toml::Table>(&format!("v = {}", raw))
+            .ok()
+            .and_then(|mut t| t.remove("v"))
+            .unwrap_or_else(|| toml::Value::String(raw.to_string()));
+
+        let mut root = toml::Value::try_from(&*self)?;
+        let mut parts = qualified.split('.').peekable();
+        let mut table = root.as_table_mut().expect("config serializes to a table");
+        while let Some(part) = parts.next() {
+            if parts.peek().is_none() {
+                table.insert(part.to_string(), new_value);
+                break;
+            }
+            table = table
+                .entry(part.to_string())
+                .or_insert_with(|| toml::Value::Table(toml::Table::new()))
+                .as_table_mut()
+                .ok_or_else(|| anyhow!("Configuration key {} is not a table", part))?;
+        }
+
+        let mut updated = Self::parse(&toml::to_string(&root)?)
+            .with_context(|| format!("Cannot set {} to {}", key, raw))?;
+        updated.path = std::mem::take(&mut self.path);
+        updated.env_overrides = std::mem::take(&mut self.env_overrides);
+        let active = self.active_profile.clone();
+        updated.select_profile(Some(&active)).or_else(|_| updated.select_profile(None))?;
+        updated.profile_source = self.profile_source;
+        *self = updated;
+        Ok(())
+    }
+}
@@ -5,6 +5,7 @@ use clap::{Parser, Subcommand};
 use std::path::PathBuf;
 
 mod auth;
+mod batch;
 mod client;
 mod commands;
 mod config;
@@ -29,6 +30,10 @@ struct Cli {
     #[arg(short, long, global = true, env = "AION_API_URL")]
     api_url: Option<String>,
 
+    /// Configuration profile (e.g. dev, staging, prod)
+    #[arg(long, global = true, env = "AION_PROFILE")]
+    profile: Option<String>,
+
     /// Output format (json, yaml, table, plain)
     #[arg(short, long, global = true, default_value = "table")]
     output: String,
@@ -91,6 +96,11 @@ enum Commands {
         #[command(subcommand)]
         command: StatusCommands,
     },
+    /// Local model cache management commands
+    Models {
+        #[command(subcommand)]
+        command: ModelsCommands,
+    },
 }
 
 #[derive(Subcommand)]
@@ -135,7 +145,7 @@ enum GenerateCommands {
         /// Requirements description
         #[arg(short, long)]
         requirements: Option<String>,
-        /// Requirements file path
+        /// Requirements file, or a directory or glob pattern to generate from every file in it
         #[arg(short, long)]
         file: Option<PathBuf>,
         /// Programming language
@@ -159,6 +169,15 @@ enum GenerateCommands {
         /// Include documentation
         #[arg(long, default_value = "true")]
         include_docs: bool,
+        /// Files processed in parallel when --file is a directory or pattern
+        #[arg(long, default_value = "4")]
+        concurrency: usize,
+        /// AI provider the cost estimate is priced for
+        #[arg(long, default_value = "openai")]
+        provider: String,
+        /// Abort before generating if the estimated cost in USD is higher
+        #[arg(long)]
+        max_cost: Option<f64>,
     },
     /// List previous generations
     List {
@@ -199,12 +218,18 @@ enum RequirementsCommands {
         /// Requirements text
         #[arg(short, long)]
         requirements: Option<String>,
-        /// Requirements file
+        /// Requirements file, or a directory or glob pattern to analyze every file in it
         #[arg(short, long)]
         file: Option<PathBuf>,
         /// Output detailed analysis
         #[arg(short, long)]
         detailed: bool,
+        /// Directory receiving one analysis per file in batch mode
+        #[arg(long, default_value = "./analysis")]
+        output_dir: PathBuf,
+        /// Files processed in parallel when --file is a directory or pattern
+        #[arg(long, default_value = "4")]
+        concurrency: usize,
     },
     /// Optimize requirements
     Optimize {
@@ -243,7 +268,7 @@ enum AICommands {
         #[command(subcommand)]
         command: AudioCommands,
     },
-    /// List available models
+    /// List models available on the platform
     Models,
 }
 
@@ -395,6 +420,31 @@ enum ProjectCommands {
         #[arg(short, long)]
         force: bool,
     },
+    /// Show project logs
+    Logs {
+        /// Project ID
+        id: String,
+        /// Keep streaming new entries until interrupted
+        #[arg(short, long)]
+        follow: bool,
+        /// Only entries newer than a duration (30s, 10m, 2h, 1d) or an RFC 3339 time
+        #[arg(long)]
+        since: Option<String>,
+        /// Minimum level (trace, debug, info, warn, error)
+        #[arg(short, long)]
+        level: Option<String>,
+    },
+    /// Export a CycloneDX SBOM from a saved project analysis
+    Sbom {
+        /// Analysis file (JSON) produced by the analyzer
+        analysis: PathBuf,
+        /// Write the SBOM to this file instead of stdout
+        #[arg(long)]
+        out: Option<PathBuf>,
+        /// OSV advisory file (JSON) to match against the dependencies; repeatable
+        #[arg(long = "advisories")]
+        advisories: Vec<PathBuf>,
+    },
     /// Deploy project
     Deploy {
         /// Project ID or name
@@ -408,8 +458,21 @@ enum ProjectCommands {
 #[derive(Subcommand)]
 enum ConfigCommands {
     /// Show current configuration
-    Show,
-    /// Set configuration value
+    Show {
+        /// Display tokens instead of masking them
+        #[arg(long)]
+        show_secrets: bool,
+    },
+    /// Open the configuration file in $EDITOR and validate it on save
+    Edit,
+    /// List configuration profiles
+    Profiles,
+    /// Make a profile the default
+    Use {
+        /// Profile name
+        profile: String,
+    },
+    /// Set configuration value (api_url and auth.* apply to the active profile)
     Set {
         /// Configuration key
         key: String,
@@ -420,6 +483,9 @@ enum ConfigCommands {
     Get {
         /// Configuration key
         key: String,
+        /// Display tokens instead of masking them
+        #[arg(long)]
+        show_secrets: bool,
     },
     /// Reset configuration to defaults
     Reset {
@@ -429,6 +495,33 @@ enum ConfigCommands {
     },
 }
 
+#[derive(Subcommand)]
+enum ModelsCommands {
+    /// List cached models
+    List,
+    /// Download a model into the cache
+    Pull {
+        /// Catalog model ID or download URL
+        source: String,
+        /// Download again even if the model is cached
+        #[arg(long)]
+        force: bool,
+    },
+    /// Remove a model from the cache
+    Rm {
+        /// Model ID
+        id: String,
+        /// Force removal without confirmation
+        #[arg(short, long)]
+        force: bool,
+    },
+    /// Show model details
+    Info {
+        /// Model ID
+        id: String,
+    },
+}
+
 #[derive(Subcommand)]
 enum StatusCommands {
     /// Show platform status
@@ -450,11 +543,11 @@ async fn main() -> anyhow::Result<()> {
     let cli = Cli::parse();
 
     // Initialize configuration
-    let config = CliConfig::load(cli.config.as_deref())?;
+    let config = CliConfig::load(cli.config.as_deref(), cli.profile.as_deref())?;
 
     // Create API client
     let client = AionClient::new(
-        cli.api_url.unwrap_or_else(|| config.api_url.clone()),
+        cli.api_url.unwrap_or_else(|| config.api_url().to_string()),
         config.clone(),
     )?;
 
@@ -487,6 +580,9 @@ async fn main() -> anyhow::Result<()> {
         Commands::Status { command } => {
             commands::status::handle_status_command(command, &client, &output_format).await?;
         }
+        Commands::Models { command } => {
+            commands::models::handle_models_command(command, &config, &output_format).await?;
+        }
     }
 
     Ok(())
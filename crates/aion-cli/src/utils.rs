@@ -29,6 +29,22 @@ pub fn truncate_text(text: &str, max_len: usize) -> String {
     }
 }
 
+/// Format a byte count for display
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KB", "MB", "GB", "TB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} {}", bytes, UNITS[0])
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
+}
+
 /// Format timestamp for display
 pub fn format_timestamp(timestamp: &str) -> String {
     match chrono::DateTime::parse_from_rfc3339(timestamp) {
@@ -37,6 +53,28 @@ pub fn format_timestamp(timestamp: &str) -> String {
     }
 }
 
+/// Parse a point in time given as an RFC 3339 timestamp or as a duration
+/// before now (`30s`, `10m`, `2h`, `1d`)
+pub fn parse_since(value: &str) -> Result<chrono::DateTime<chrono::Utc>> {
+    if let Ok(time) = chrono::DateTime::parse_from_rfc3339(value) {
+        return Ok(time.with_timezone(&chrono::Utc));
+    }
+
+    let split = value.find(|c: char| !c.is_ascii_digit()).unwrap_or(value.len());
+    let (amount, unit) = value.split_at(split);
+    let amount: i64 = amount
+        .parse()
+        .map_err(|_| anyhow::anyhow!("Invalid time '{}': expected e.g. 10m or 2024-01-01T00:00:00Z", value))?;
+    let duration = match unit {
+        "s" => chrono::Duration::seconds(amount),
+        "m" => chrono::Duration::minutes(amount),
+        "h" => chrono::Duration::hours(amount),
+        "d" => chrono::Duration::days(amount),
+        _ => return Err(anyhow::anyhow!("Invalid time unit in '{}': use s, m, h or d", value)),
+    };
+    Ok(chrono::Utc::now() - duration)
+}
+
 /// Extract ZIP archive
 pub fn extract_zip(archive_path: &Path, output_dir: &Path) -> Result<()> {
     let file = std::fs::File::open(archive_path)?;
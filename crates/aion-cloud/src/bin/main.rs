@@ -1,6 +1,6 @@
 use aion_cloud::{
     CloudProvider, CloudCredentials, DeploymentTemplate, TemplateType, ResourceDefinition,
-    VariableDefinition, CloudProviderInterface, providers::CloudProviderFactory,
+    VariableDefinition, CloudProviderInterface, RetryPolicy, providers::CloudProviderFactory,
     terraform::{DefaultTerraformGenerator, TerraformGenerator, TerraformFormatter}
 };
 use clap::{App, Arg, SubCommand};
@@ -217,6 +217,7 @@ async fn list_resources(provider: CloudProvider, region: Option<&str>) -> Result
         region: region.map(|r| r.to_string()),
         project_id: None,
         subscription_id: None,
+        retry_policy: RetryPolicy::default(),
     };
 
     cloud_provider.authenticate(&credentials).await?;
@@ -258,6 +259,7 @@ async fn deploy_template(template_path: &str, variables_path: Option<&str>) -> R
         region: Some("us-east-1".to_string()),
         project_id: None,
         subscription_id: None,
+        retry_policy: RetryPolicy::default(),
     };
 
     cloud_provider.authenticate(&credentials).await?;
@@ -4,6 +4,7 @@ pub mod kubernetes;
 pub mod monitoring;
 pub mod deployment;
 pub mod security;
+pub mod retry;
 
 pub use providers::*;
 pub use terraform::*;
@@ -11,6 +12,7 @@ pub use kubernetes::*;
 pub use monitoring::*;
 pub use deployment::*;
 pub use security::*;
+pub use retry::*;
 
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
@@ -33,6 +35,9 @@ pub struct CloudCredentials {
     pub region: Option<String>,
     pub project_id: Option<String>,
     pub subscription_id: Option<String>,
+    /// How throttled or unavailable API calls on this account are retried
+    #[serde(default)]
+    pub retry_policy: RetryPolicy,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
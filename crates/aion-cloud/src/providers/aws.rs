@@ -1,6 +1,8 @@
-use crate::{CloudCredentials, CloudResource, CloudProviderInterface, ResourceDefinition, ResourceStatus, DeploymentPlan, DeploymentTemplate, Result};
+use crate::{CloudApiError, CloudCredentials, CloudResource, CloudProviderInterface, ResourceDefinition, ResourceStatus, DeploymentPlan, DeploymentTemplate, Result};
 use aws_config::{BehaviorVersion, Region};
 use aws_sdk_ec2::{Client as EC2Client, types::Instance};
+use aws_sdk_ec2::config::http::HttpResponse;
+use aws_sdk_ec2::error::{DisplayErrorContext, ProvideErrorMetadata, SdkError};
 use aws_sdk_s3::{Client as S3Client};
 use aws_sdk_lambda::{Client as LambdaClient};
 use aws_sdk_cloudformation::{Client as CloudFormationClient};
@@ -203,7 +205,8 @@ impl AWSProvider {
             .min_count(1)
             .max_count(1)
             .send()
-            .await?;
+            .await
+            .map_err(api_error)?;
 
         let instance = resp.instances().first().ok_or("No instance created")?;
 
@@ -255,6 +258,24 @@ impl AWSProvider {
     }
 }
 
+/// Error codes AWS uses for throttling; some services send them with HTTP 400
+const THROTTLING_CODES: &[&str] = &["Throttling", "ThrottlingException", "RequestLimitExceeded", "TooManyRequestsException"];
+
+/// Surface the HTTP status of a failed SDK call, reporting throttling as 429,
+/// so the retry policy can recognize it
+fn api_error<E>(error: SdkError<E, HttpResponse>) -> Box<dyn std::error::Error + Send + Sync>
+where
+    E: std::error::Error + ProvideErrorMetadata + Send + Sync + 'static,
+{
+    let throttled = error.code().is_some_and(|code| THROTTLING_CODES.contains(&code));
+    let status = error.raw_response().map(|response| response.status().as_u16());
+    match (throttled, status) {
+        (true, _) => Box::new(CloudApiError { status: 429, message: DisplayErrorContext(&error).to_string() }),
+        (false, Some(status)) => Box::new(CloudApiError { status, message: DisplayErrorContext(&error).to_string() }),
+        (false, None) => Box::new(error),
+    }
+}
+
 #[async_trait::async_trait]
 impl CloudProviderInterface for AWSProvider {
     async fn authenticate(&self, credentials: &CloudCredentials) -> Result<()> {
@@ -331,7 +352,8 @@ impl CloudProviderInterface for AWSProvider {
             .stack_name(&stack_name)
             .template_body(template_body)
             .send()
-            .await?;
+            .await
+            .map_err(api_error)?;
 
         Ok(stack_name)
     }
@@ -6,17 +6,19 @@ pub use aws::AWSProvider;
 pub use gcp::GCPProvider;
 pub use azure::AzureProvider;
 
-use crate::{CloudProvider, CloudProviderInterface};
+use crate::{CloudProvider, CloudProviderInterface, RetryPolicy, RetryingProvider};
 use std::sync::Arc;
 
 pub struct CloudProviderFactory;
 
 impl CloudProviderFactory {
+    /// A provider with the default `RetryPolicy`; authenticating replaces it
+    /// with the policy of the credentials
     pub fn create_provider(provider: CloudProvider) -> Arc<dyn CloudProviderInterface + Send + Sync> {
         match provider {
-            CloudProvider::AWS => Arc::new(AWSProvider::new()),
-            CloudProvider::GCP => Arc::new(GCPProvider::new()),
-            CloudProvider::Azure => Arc::new(AzureProvider::new()),
+            CloudProvider::AWS => Arc::new(RetryingProvider::new(AWSProvider::new(), RetryPolicy::default())),
+            CloudProvider::GCP => Arc::new(RetryingProvider::new(GCPProvider::new(), RetryPolicy::default())),
+            CloudProvider::Azure => Arc::new(RetryingProvider::new(AzureProvider::new(), RetryPolicy::default())),
             _ => panic!("Unsupported provider: {:?}", provider),
         }
     }
@@ -0,0 +1,328 @@
+//! Retry with exponential backoff for cloud provider calls
+//!
+//! AWS and GCP throttle bursts of API calls with 429 and shed load with 503.
+//! Both mean the request was rejected before anything was created, so
+//! retrying `create_resource` cannot create a resource twice. Statuses that
+//! can follow a partial success (502, 504) are not retried by default.
+
+use crate::{
+    CloudCredentials, CloudProviderInterface, CloudResource, DeploymentPlan, DeploymentTemplate,
+    ResourceDefinition, ResourceStatus, Result,
+};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::RwLock;
+use std::time::Duration;
+
+/// An HTTP error from a provider API, carrying the status the retry policy
+/// inspects. Providers return it (possibly as the source of another error)
+/// for failures they want retried.
+#[derive(Debug, thiserror::Error)]
+#[error("cloud API returned HTTP {status}: {message}")]
+pub struct CloudApiError {
+    pub status: u16,
+    pub message: String,
+}
+
+impl CloudApiError {
+    /// The status of the first `CloudApiError` in an error's source chain
+    pub fn status_of(error: &(dyn std::error::Error + 'static)) -> Option<u16> {
+        let mut current = Some(error);
+        while let Some(error) = current {
+            if let Some(api_error) = error.downcast_ref::<CloudApiError>() {
+                return Some(api_error.status);
+            }
+            current = error.source();
+        }
+        None
+    }
+}
+
+/// How provider calls are retried; set per account on `CloudCredentials`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RetryPolicy {
+    /// Total attempts, including the first
+    pub max_attempts: u32,
+    /// Delay before the first retry; doubles on each further retry
+    pub base_delay_ms: u64,
+    /// Cap on any single delay
+    pub max_delay_ms: u64,
+    /// Fraction of each delay that is randomized, from 0.0 (none) to 1.0
+    /// (anywhere between zero and the full delay), so that many deployments
+    /// throttled together do not retry in lockstep
+    pub jitter: f64,
+    /// Whether a failure with this HTTP status is worth retrying
+    #[serde(skip, default = "default_retryable_status")]
+    pub retryable_status: fn(u16) -> bool,
+}
+
+fn default_retryable_status(status: u16) -> bool {
+    matches!(status, 429 | 503)
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 5,
+            base_delay_ms: 500,
+            max_delay_ms: 30_000,
+            jitter: 1.0,
+            retryable_status: default_retryable_status,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// A policy that makes a single attempt
+    pub fn none() -> Self {
+        Self { max_attempts: 1, ..Self::default() }
+    }
+
+    /// Delay before retry number `retry` (1 for the first retry)
+    pub fn delay(&self, retry: u32) -> Duration {
+        let exponential = self
+            .base_delay_ms
+            .saturating_mul(1u64 << (retry.saturating_sub(1)).min(32))
+            .min(self.max_delay_ms);
+        let jitter = self.jitter.clamp(0.0, 1.0);
+        let randomized = exponential as f64 * (1.0 - jitter * rand::thread_rng().gen::<f64>());
+        Duration::from_millis(randomized as u64)
+    }
+
+    pub fn is_retryable(&self, error: &(dyn std::error::Error + Send + Sync + 'static)) -> bool {
+        CloudApiError::status_of(error).is_some_and(self.retryable_status)
+    }
+
+    /// Run `call` until it succeeds, fails with a non-retryable error, or
+    /// runs out of attempts; the last error is returned
+    pub async fn run<T, F, Fut>(&self, operation: &str, mut call: F) -> Result<T>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        let mut attempt = 1;
+        loop {
+            match call().await {
+                Ok(value) => return Ok(value),
+                Err(e) if attempt < self.max_attempts && self.is_retryable(e.as_ref()) => {
+                    let delay = self.delay(attempt);
+                    tracing::warn!(
+                        "{} failed (attempt {}/{}): {}; retrying in {:?}",
+                        operation, attempt, self.max_attempts, e, delay
+                    );
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
+
+/// A provider whose mutating calls are retried under a `RetryPolicy`
+///
+/// `create_resource`, `update_resource` and `deploy_template` are retried;
+/// reads are left to the caller. Authenticating adopts the policy of the
+/// credentials used.
+pub struct RetryingProvider<P> {
+    inner: P,
+    policy: RwLock<RetryPolicy>,
+}
+
+impl<P> RetryingProvider<P> {
+    pub fn new(inner: P, policy: RetryPolicy) -> Self {
+        Self {
+            inner,
+            policy: RwLock::new(policy),
+        }
+    }
+
+    pub fn policy(&self) -> RetryPolicy {
+        self.policy.read().unwrap().clone()
+    }
+
+    pub fn set_policy(&self, policy: RetryPolicy) {
+        *self.policy.write().unwrap() = policy;
+    }
+}
+
+#[async_trait::async_trait]
+impl<P: CloudProviderInterface + Send + Sync> CloudProviderInterface for RetryingProvider<P> {
+    async fn authenticate(&self, credentials: &CloudCredentials) -> Result<()> {
+        self.set_policy(credentials.retry_policy.clone());
+        self.inner.authenticate(credentials).await
+    }
+
+    async fn list_resources(&self) -> Result<Vec<CloudResource>> {
+        self.inner.list_resources().await
+    }
+
+    async fn create_resource(&self, definition: &ResourceDefinition) -> Result<CloudResource> {
+        let operation = format!("Creating {} {}", definition.resource_type, definition.name);
+        self.policy()
+            .run(&operation, || self.inner.create_resource(definition))
+            .await
+    }
+
+    async fn update_resource(&self, id: &str, definition: &ResourceDefinition) -> Result<CloudResource> {
+        let operation = format!("Updating {} {}", definition.resource_type, id);
+        self.policy()
+            .run(&operation, || self.inner.update_resource(id, definition))
+            .await
+    }
+
+    async fn delete_resource(&self, id: &str) -> Result<()> {
+        self.inner.delete_resource(id).await
+    }
+
+    async fn get_resource_status(&self, id: &str) -> Result<ResourceStatus> {
+        self.inner.get_resource_status(id).await
+    }
+
+    async fn estimate_cost(&self, plan: &DeploymentPlan) -> Result<f64> {
+        self.inner.estimate_cost(plan).await
+    }
+
+    async fn deploy_template(&self, template: &DeploymentTemplate, variables: HashMap<String, serde_json::Value>) -> Result<String> {
+        let operation = format!("Deploying template {}", template.name);
+        self.policy()
+            .run(&operation, || self.inner.deploy_template(template, variables.clone()))
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CloudProvider;
+    use chrono::Utc;
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use uuid::Uuid;
+
+    /// Rejects the first `failures` creations with `status`, then creates
+    struct FlakyProvider {
+        failures: u32,
+        status: u16,
+        calls: AtomicU32,
+        created: AtomicU32,
+    }
+
+    impl FlakyProvider {
+        fn new(failures: u32, status: u16) -> Self {
+            Self {
+                failures,
+                status,
+                calls: AtomicU32::new(0),
+                created: AtomicU32::new(0),
+            }
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl CloudProviderInterface for FlakyProvider {
+        async fn authenticate(&self, _credentials: &CloudCredentials) -> Result<()> {
+            Ok(())
+        }
+
+        async fn list_resources(&self) -> Result<Vec<CloudResource>> {
+            Ok(Vec::new())
+        }
+
+        async fn create_resource(&self, definition: &ResourceDefinition) -> Result<CloudResource> {
+            if self.calls.fetch_add(1, Ordering::SeqCst) < self.failures {
+                return Err(Box::new(CloudApiError {
+                    status: self.status,
+                    message: "Rate exceeded".to_string(),
+                }));
+            }
+            self.created.fetch_add(1, Ordering::SeqCst);
+            Ok(CloudResource {
+                id: Uuid::new_v4(),
+                provider: CloudProvider::AWS,
+                resource_type: definition.resource_type.clone(),
+                name: definition.name.clone(),
+                region: "us-east-1".to_string(),
+                status: ResourceStatus::Creating,
+                tags: HashMap::new(),
+                metadata: serde_json::Value::Null,
+                created_at: Utc::now(),
+                updated_at: Utc::now(),
+            })
+        }
+
+        async fn update_resource(&self, _id: &str, definition: &ResourceDefinition) -> Result<CloudResource> {
+            self.create_resource(definition).await
+        }
+
+        async fn delete_resource(&self, _id: &str) -> Result<()> {
+            Ok(())
+        }
+
+        async fn get_resource_status(&self, _id: &str) -> Result<ResourceStatus> {
+            Ok(ResourceStatus::Running)
+        }
+
+        async fn estimate_cost(&self, _plan: &DeploymentPlan) -> Result<f64> {
+            Ok(0.0)
+        }
+
+        async fn deploy_template(&self, _template: &DeploymentTemplate, _variables: HashMap<String, serde_json::Value>) -> Result<String> {
+            Ok("stack".to_string())
+        }
+    }
+
+    fn fast_policy() -> RetryPolicy {
+        RetryPolicy {
+            base_delay_ms: 1,
+            max_delay_ms: 5,
+            ..RetryPolicy::default()
+        }
+    }
+
+    fn bucket() -> ResourceDefinition {
+        ResourceDefinition {
+            name: "assets".to_string(),
+            resource_type: "s3_bucket".to_string(),
+            properties: serde_json::json!({}),
+            depends_on: Vec::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_retries_throttling_then_creates_once() {
+        let provider = RetryingProvider::new(FlakyProvider::new(2, 503), fast_policy());
+
+        let resource = provider.create_resource(&bucket()).await.unwrap();
+        assert_eq!(resource.name, "assets");
+        assert_eq!(provider.inner.calls.load(Ordering::SeqCst), 3);
+        assert_eq!(provider.inner.created.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_non_retryable_and_exhausted_failures_are_returned() {
+        let provider = RetryingProvider::new(FlakyProvider::new(1, 400), fast_policy());
+        assert!(provider.create_resource(&bucket()).await.is_err());
+        assert_eq!(provider.inner.calls.load(Ordering::SeqCst), 1);
+
+        let provider = RetryingProvider::new(FlakyProvider::new(10, 429), fast_policy());
+        let error = provider.create_resource(&bucket()).await.unwrap_err();
+        assert_eq!(CloudApiError::status_of(error.as_ref()), Some(429));
+        assert_eq!(provider.inner.calls.load(Ordering::SeqCst), 5);
+        assert_eq!(provider.inner.created.load(Ordering::SeqCst), 0);
+    }
+
+    #[test]
+    fn test_delay_grows_exponentially_and_is_capped() {
+        let policy = RetryPolicy { jitter: 0.0, ..RetryPolicy::default() };
+        assert_eq!(policy.delay(1), Duration::from_millis(500));
+        assert_eq!(policy.delay(3), Duration::from_millis(2_000));
+        assert_eq!(policy.delay(10), Duration::from_secs(30));
+        assert_eq!(policy.delay(100), Duration::from_secs(30));
+
+        let jittered = RetryPolicy::default();
+        assert!((1..20).all(|retry| jittered.delay(retry) <= Duration::from_secs(30)));
+    }
+}
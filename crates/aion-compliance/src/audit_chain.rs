@@ -0,0 +1,272 @@
+// AION-R Compliance: Hash-Chained Audit Log
+// Every entry commits to the hash of its predecessor, so any edit, deletion or
+// reordering of past entries is detected by `verify`.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+/// Hash used as the predecessor of the first entry
+pub const GENESIS_HASH: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+/// Hash of one link in a chain, over the predecessor's hash and the link's fields.
+///
+/// Every field is prefixed with its length, so shifting bytes from one field
+/// to the next (actor "ab" + action "c" vs. actor "a" + action "bc") changes
+/// the hash.
+pub struct LinkHasher {
+    hasher: Sha256,
+}
+
+impl LinkHasher {
+    pub fn new(previous_hash: &str) -> Self {
+        let mut link = Self { hasher: Sha256::new() };
+        link.field(previous_hash);
+        link
+    }
+
+    pub fn field(&mut self, value: impl AsRef<[u8]>) -> &mut Self {
+        let value = value.as_ref();
+        self.hasher.update((value.len() as u64).to_be_bytes());
+        self.hasher.update(value);
+        self
+    }
+
+    /// A missing field hashes differently from an empty one
+    pub fn optional_field(&mut self, value: Option<impl AsRef<[u8]>>) -> &mut Self {
+        match value {
+            Some(value) => {
+                self.hasher.update([1u8]);
+                self.field(value)
+            }
+            None => {
+                self.hasher.update([0u8]);
+                self
+            }
+        }
+    }
+
+    pub fn finish(self) -> String {
+        self.hasher.finalize().iter().map(|b| format!("{:02x}", b)).collect()
+    }
+}
+
+/// Walk `links` from `GENESIS_HASH`. `check` gets each link's position, the
+/// link and its predecessor's hash, and returns the link's own hash or why the
+/// link is broken; the first broken link is returned with its position.
+pub fn verify_links<'a, T>(
+    links: &'a [T],
+    mut check: impl FnMut(usize, &'a T, &str) -> std::result::Result<&'a str, String>,
+) -> std::result::Result<(), (usize, String)> {
+    let mut previous_hash = GENESIS_HASH;
+    for (index, link) in links.iter().enumerate() {
+        previous_hash = check(index, link, previous_hash).map_err(|reason| (index, reason))?;
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditChainEntry {
+    pub sequence: u64,
+    pub timestamp: DateTime<Utc>,
+    pub actor: String,
+    pub action: String,
+    pub subject_id: Option<Uuid>,
+    pub details: serde_json::Value,
+    pub previous_hash: String,
+    pub hash: String,
+}
+
+impl AuditChainEntry {
+    fn compute_hash(
+        sequence: u64,
+        timestamp: &DateTime<Utc>,
+        actor: &str,
+        action: &str,
+        subject_id: Option<Uuid>,
+        details: &serde_json::Value,
+        previous_hash: &str,
+    ) -> String {
+        let mut link = LinkHasher::new(previous_hash);
+        link.field(sequence.to_be_bytes())
+            .field(timestamp.to_rfc3339())
+            .field(actor)
+            .field(action)
+            .optional_field(subject_id.map(|id| id.to_string()))
+            .field(details.to_string());
+        link.finish()
+    }
+
+    /// Recompute this entry's hash from its contents
+    pub fn recompute_hash(&self) -> String {
+        Self::compute_hash(
+            self.sequence,
+            &self.timestamp,
+            &self.actor,
+            &self.action,
+            self.subject_id,
+            &self.details,
+            &self.previous_hash,
+        )
+    }
+}
+
+/// Location and reason of the first broken link found while verifying a chain
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChainVerificationFailure {
+    pub sequence: u64,
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AuditChain {
+    entries: Vec<AuditChainEntry>,
+}
+
+impl AuditChain {
+    pub fn new() -> Self {
+        Self { entries: Vec::new() }
+    }
+
+    /// Append an entry linked to the current head of the chain
+    pub fn append(
+        &mut self,
+        actor: impl Into<String>,
+        action: impl Into<String>,
+        subject_id: Option<Uuid>,
+        details: serde_json::Value,
+    ) -> &AuditChainEntry {
+        let actor = actor.into();
+        let action = action.into();
+        let sequence = self.entries.len() as u64;
+        let timestamp = Utc::now();
+        let previous_hash = self.head_hash().to_string();
+        let hash = AuditChainEntry::compute_hash(
+            sequence,
+            &timestamp,
+            &actor,
+            &action,
+            subject_id,
+            &details,
+            &previous_hash,
+        );
+
+        self.entries.push(AuditChainEntry {
+            sequence,
+            timestamp,
+            actor,
+            action,
+            subject_id,
+            details,
+            previous_hash,
+            hash,
+        });
+
+        self.entries.last().expect("entry was just pushed")
+    }
+
+    pub fn head_hash(&self) -> &str {
+        self.entries.last().map(|e| e.hash.as_str()).unwrap_or(GENESIS_HASH)
+    }
+
+    pub fn entries(&self) -> &[AuditChainEntry] {
+        &self.entries
+    }
+
+    pub fn entries_for(&self, subject_id: Uuid) -> impl Iterator<Item = &AuditChainEntry> {
+        self.entries.iter().filter(move |e| e.subject_id == Some(subject_id))
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Walk the chain and report the first entry whose link or content hash is invalid
+    pub fn verify(&self) -> std::result::Result<(), ChainVerificationFailure> {
+        verify_links(&self.entries, |index, entry, previous_hash| {
+            if entry.sequence != index as u64 {
+                return Err(format!("expected sequence {}, found {}", index, entry.sequence));
+            }
+            if entry.previous_hash != previous_hash {
+                return Err("previous hash does not match predecessor".to_string());
+            }
+            if entry.recompute_hash() != entry.hash {
+                return Err("entry content does not match its hash".to_string());
+            }
+            Ok(entry.hash.as_str())
+        })
+        .map_err(|(index, reason)| ChainVerificationFailure {
+            sequence: self.entries[index].sequence,
+            reason,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn chain() -> AuditChain {
+        let mut chain = AuditChain::new();
+        let incident = Uuid::new_v4();
+        chain.append("alice", "incident.opened", Some(incident), json!({ "severity": "high" }));
+        chain.append("bob", "incident.assigned", Some(incident), json!({ "assignee": "bob" }));
+        chain.append("system", "retention.sweep", None, json!({}));
+        chain
+    }
+
+    #[test]
+    fn appended_entries_link_to_their_predecessor() {
+        let chain = chain();
+        assert!(chain.verify().is_ok());
+        assert_eq!(chain.entries()[0].previous_hash, GENESIS_HASH);
+        assert_eq!(chain.entries()[2].previous_hash, chain.entries()[1].hash);
+        assert_eq!(chain.head_hash(), chain.entries()[2].hash);
+
+        let incident = chain.entries()[0].subject_id.unwrap();
+        assert_eq!(chain.entries_for(incident).count(), 2);
+    }
+
+    #[test]
+    fn edited_entry_is_reported_at_its_sequence() {
+        let mut chain = chain();
+        chain.entries[1].details = json!({ "assignee": "mallory" });
+
+        let failure = chain.verify().unwrap_err();
+        assert_eq!(failure.sequence, 1);
+        assert_eq!(failure.reason, "entry content does not match its hash");
+    }
+
+    #[test]
+    fn deleted_and_reordered_entries_are_detected() {
+        let mut deleted = chain();
+        deleted.entries.remove(1);
+        assert_eq!(deleted.verify().unwrap_err().sequence, 2);
+
+        let mut reordered = chain();
+        reordered.entries.swap(1, 2);
+        assert_eq!(reordered.verify().unwrap_err().sequence, 2);
+    }
+
+    #[test]
+    fn shifting_bytes_between_fields_changes_the_hash() {
+        let timestamp = Utc::now();
+        let details = json!({});
+        let hash = |actor: &str, action: &str| {
+            AuditChainEntry::compute_hash(0, &timestamp, actor, action, None, &details, GENESIS_HASH)
+        };
+        assert_ne!(hash("ab", "c"), hash("a", "bc"));
+
+        let mut absent = LinkHasher::new(GENESIS_HASH);
+        absent.optional_field(None::<&str>);
+        let mut empty = LinkHasher::new(GENESIS_HASH);
+        empty.optional_field(Some(""));
+        assert_ne!(absent.finish(), empty.finish());
+    }
+}
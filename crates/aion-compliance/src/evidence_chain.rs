@@ -0,0 +1,197 @@
+// AION-R Compliance: Evidence Hash Chains
+// Each piece of evidence collected for a security control is hashed over its
+// content and the hash of the evidence before it, so inserting, deleting or
+// editing an entry breaks every hash from that point on. Removing the newest
+// entries leaves a valid shorter chain; compare `evidence_head_hash` with a
+// copy kept elsewhere to catch that.
+
+use crate::{verify_links, ComplianceProject, Evidence, LinkHasher, Result, SecurityControl, GENESIS_HASH};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use uuid::Uuid;
+
+/// Hash of `evidence` chained to `previous_hash`. The stored `hash` itself is not an input.
+pub fn evidence_hash(evidence: &Evidence, previous_hash: &str) -> String {
+    let mut link = LinkHasher::new(previous_hash);
+    link.field(evidence.id.as_bytes())
+        .field(format!("{:?}", evidence.evidence_type))
+        .field(&evidence.description)
+        .optional_field(evidence.file_path.as_deref())
+        .field(&evidence.collected_by)
+        .field(evidence.collected_at.to_rfc3339())
+        .optional_field(evidence.retention_date.map(|date| date.to_rfc3339()));
+    link.finish()
+}
+
+/// First entry of a control's evidence chain that fails verification
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EvidenceChainBreak {
+    pub control_id: String,
+    /// Position of the entry in the control's evidence
+    pub index: usize,
+    pub evidence_id: Uuid,
+    pub reason: String,
+}
+
+impl fmt::Display for EvidenceChainBreak {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "evidence chain of control {} breaks at entry {} ({}): {}",
+            self.control_id, self.index, self.evidence_id, self.reason
+        )
+    }
+}
+
+impl std::error::Error for EvidenceChainBreak {}
+
+impl SecurityControl {
+    /// Append `evidence`, setting its hash to extend the chain
+    pub fn add_evidence(&mut self, mut evidence: Evidence) -> &Evidence {
+        evidence.hash = Some(evidence_hash(&evidence, self.evidence_head_hash()));
+        self.evidence.push(evidence);
+        self.evidence.last().expect("evidence was just pushed")
+    }
+
+    /// Hash of the newest evidence, or `GENESIS_HASH` when there is none
+    pub fn evidence_head_hash(&self) -> &str {
+        self.evidence
+            .last()
+            .and_then(|evidence| evidence.hash.as_deref())
+            .unwrap_or(GENESIS_HASH)
+    }
+
+    /// Walk the evidence and report the first entry whose hash does not
+    /// match its content and predecessor
+    pub fn verify_evidence_chain(&self) -> std::result::Result<(), EvidenceChainBreak> {
+        verify_links(&self.evidence, |_, evidence, previous_hash| {
+            let hash = evidence.hash.as_deref().ok_or_else(|| "evidence has no hash".to_string())?;
+            if evidence_hash(evidence, previous_hash) != hash {
+                return Err("hash does not match the evidence and its predecessor".to_string());
+            }
+            Ok(hash)
+        })
+        .map_err(|(index, reason)| EvidenceChainBreak {
+            control_id: self.id.clone(),
+            index,
+            evidence_id: self.evidence[index].id,
+            reason,
+        })
+    }
+}
+
+impl ComplianceProject {
+    /// Verify the evidence chain of `control_id` on every system that has the control
+    pub fn verify_evidence_chain(&self, control_id: &str) -> Result<()> {
+        let mut controls = self
+            .systems
+            .iter()
+            .flat_map(|system| system.security_controls.iter())
+            .filter(|control| control.id == control_id)
+            .peekable();
+        if controls.peek().is_none() {
+            return Err(format!("Security control not found: {}", control_id).into());
+        }
+
+        for control in controls {
+            control.verify_evidence_chain()?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ControlEffectiveness, ControlType, EvidenceType, ImplementationStatus, TestingFrequency};
+    use chrono::Utc;
+    use std::collections::HashMap;
+
+    fn control() -> SecurityControl {
+        SecurityControl {
+            id: "access-review".to_string(),
+            name: "Quarterly access review".to_string(),
+            control_type: ControlType::Administrative,
+            framework_mapping: HashMap::new(),
+            implementation_status: ImplementationStatus::FullyImplemented,
+            effectiveness: ControlEffectiveness::Effective,
+            testing_frequency: TestingFrequency::Quarterly,
+            last_tested: None,
+            next_test_due: None,
+            responsible_party: "security".to_string(),
+            evidence: Vec::new(),
+        }
+    }
+
+    fn evidence(description: &str) -> Evidence {
+        Evidence {
+            id: Uuid::new_v4(),
+            evidence_type: EvidenceType::Document,
+            description: description.to_string(),
+            file_path: Some(format!("evidence/{}.pdf", description)),
+            collected_by: "auditor".to_string(),
+            collected_at: Utc::now(),
+            hash: None,
+            retention_date: None,
+        }
+    }
+
+    fn chained_control() -> SecurityControl {
+        let mut control = control();
+        for description in ["q1-review", "q2-review", "q3-review"] {
+            control.add_evidence(evidence(description));
+        }
+        control
+    }
+
+    #[test]
+    fn modified_middle_entry_is_pinpointed() {
+        let mut control = chained_control();
+        assert!(control.verify_evidence_chain().is_ok());
+
+        control.evidence[1].description = "q2-review (amended)".to_string();
+        let failure = control.verify_evidence_chain().unwrap_err();
+        assert_eq!(failure.index, 1);
+        assert_eq!(failure.evidence_id, control.evidence[1].id);
+        assert_eq!(failure.control_id, "access-review");
+    }
+
+    #[test]
+    fn insertion_and_deletion_break_the_chain() {
+        let mut deleted = chained_control();
+        deleted.evidence.remove(1);
+        assert_eq!(deleted.verify_evidence_chain().unwrap_err().index, 1);
+
+        let mut inserted = chained_control();
+        let mut forged = evidence("q2-extra");
+        forged.hash = Some(evidence_hash(&forged, inserted.evidence[1].hash.as_deref().unwrap()));
+        inserted.evidence.insert(2, forged);
+        // The forged entry links correctly, so the break shows at its successor
+        assert_eq!(inserted.verify_evidence_chain().unwrap_err().index, 3);
+    }
+
+    #[test]
+    fn shifting_bytes_between_fields_changes_the_hash() {
+        let mut original = evidence("ab");
+        original.file_path = None;
+        original.collected_by = "c".to_string();
+        let mut shifted = original.clone();
+        shifted.description = "a".to_string();
+        shifted.collected_by = "bc".to_string();
+        assert_ne!(evidence_hash(&original, GENESIS_HASH), evidence_hash(&shifted, GENESIS_HASH));
+
+        // No file and an empty file path are different evidence
+        let without_file = original.clone();
+        let mut empty_file = original;
+        empty_file.file_path = Some(String::new());
+        assert_ne!(evidence_hash(&without_file, GENESIS_HASH), evidence_hash(&empty_file, GENESIS_HASH));
+    }
+
+    #[test]
+    fn missing_hash_is_reported() {
+        let mut control = chained_control();
+        control.evidence[2].hash = None;
+        let failure = control.verify_evidence_chain().unwrap_err();
+        assert_eq!((failure.index, failure.reason.as_str()), (2, "evidence has no hash"));
+    }
+}
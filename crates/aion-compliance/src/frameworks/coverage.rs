@@ -0,0 +1,193 @@
+//! Cross-framework gap coverage
+//!
+//! A security control implemented for one framework often satisfies
+//! requirements of others, as recorded in `SecurityControl::framework_mapping`.
+//! Given the per-framework gap analyses of a project, this works out which
+//! gaps such controls already close and which remain net-new work.
+
+use crate::{ComplianceFramework, ComplianceGap, ImplementationStatus, SecurityControl};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CrossFrameworkCoverage {
+    pub project_id: Uuid,
+    /// Implemented controls that close gaps, with the requirements they close
+    pub reusable_controls: Vec<ReusableControl>,
+    /// One entry per analysed framework, in the order given
+    pub frameworks: Vec<FrameworkCoverage>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReusableControl {
+    pub control_id: String,
+    pub name: String,
+    pub closes: Vec<(ComplianceFramework, String)>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FrameworkCoverage {
+    pub framework: ComplianceFramework,
+    /// Gaps reported by the framework's own gap analysis
+    pub total_gaps: usize,
+    pub covered_gaps: Vec<CoveredGap>,
+    /// Gaps no implemented control closes
+    pub net_new_gaps: Vec<ComplianceGap>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CoveredGap {
+    pub gap: ComplianceGap,
+    /// Id of the `SecurityControl` that closes it
+    pub covered_by: String,
+}
+
+impl CrossFrameworkCoverage {
+    /// Match `gap_analyses` against `controls`. Only fully implemented
+    /// controls close gaps; a gap is closed when a control maps to the
+    /// gap's framework with the gap's control id.
+    pub fn analyze(
+        project_id: Uuid,
+        controls: &[SecurityControl],
+        gap_analyses: Vec<(ComplianceFramework, Vec<ComplianceGap>)>,
+    ) -> Self {
+        let implemented: Vec<&SecurityControl> = controls
+            .iter()
+            .filter(|control| matches!(control.implementation_status, ImplementationStatus::FullyImplemented))
+            .collect();
+
+        let mut reusable_controls: Vec<ReusableControl> = Vec::new();
+        let mut frameworks = Vec::new();
+
+        for (framework, gaps) in gap_analyses {
+            let total_gaps = gaps.len();
+            let mut covered_gaps = Vec::new();
+            let mut net_new_gaps = Vec::new();
+
+            for gap in gaps {
+                let covering = implemented.iter().find(|control| {
+                    control
+                        .framework_mapping
+                        .get(&gap.framework)
+                        .is_some_and(|requirement| requirement_matches(requirement, &gap.control_id))
+                });
+                let Some(control) = covering else {
+                    net_new_gaps.push(gap);
+                    continue;
+                };
+
+                let closed = (gap.framework.clone(), gap.control_id.clone());
+                match reusable_controls.iter_mut().find(|reusable| reusable.control_id == control.id) {
+                    Some(reusable) if !reusable.closes.contains(&closed) => reusable.closes.push(closed),
+                    Some(_) => {}
+                    None => reusable_controls.push(ReusableControl {
+                        control_id: control.id.clone(),
+                        name: control.name.clone(),
+                        closes: vec![closed],
+                    }),
+                }
+                covered_gaps.push(CoveredGap {
+                    gap,
+                    covered_by: control.id.clone(),
+                });
+            }
+
+            frameworks.push(FrameworkCoverage {
+                framework,
+                total_gaps,
+                covered_gaps,
+                net_new_gaps,
+            });
+        }
+
+        Self {
+            project_id,
+            reusable_controls,
+            frameworks,
+        }
+    }
+
+    pub fn net_new_gap_count(&self) -> usize {
+        self.frameworks.iter().map(|framework| framework.net_new_gaps.len()).sum()
+    }
+}
+
+/// Requirement ids are compared ignoring case and surrounding whitespace
+fn requirement_matches(mapped: &str, control_id: &str) -> bool {
+    mapped.trim().eq_ignore_ascii_case(control_id.trim())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ControlEffectiveness, ControlType, GapSeverity, GapStatus, RemediationEffort, RiskImpact, TestingFrequency};
+
+    fn gap(framework: ComplianceFramework, control_id: &str) -> ComplianceGap {
+        ComplianceGap {
+            id: Uuid::new_v4(),
+            severity: GapSeverity::High,
+            framework,
+            control_id: control_id.to_string(),
+            description: format!("{} is not implemented", control_id),
+            risk_impact: RiskImpact::Major,
+            remediation_effort: RemediationEffort::Medium,
+            due_date: None,
+            responsible_party: "security".to_string(),
+            status: GapStatus::Identified,
+        }
+    }
+
+    fn control(id: &str, status: ImplementationStatus, mapping: &[(ComplianceFramework, &str)]) -> SecurityControl {
+        SecurityControl {
+            id: id.to_string(),
+            name: format!("Control {}", id),
+            control_type: ControlType::Technical,
+            framework_mapping: mapping.iter().map(|(framework, requirement)| (framework.clone(), requirement.to_string())).collect(),
+            implementation_status: status,
+            effectiveness: ControlEffectiveness::Effective,
+            testing_frequency: TestingFrequency::Quarterly,
+            last_tested: None,
+            next_test_due: None,
+            responsible_party: "security".to_string(),
+            evidence: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn iso27001_control_closes_matching_nist_gap() {
+        let controls = vec![
+            control(
+                "iso-access-control",
+                ImplementationStatus::FullyImplemented,
+                &[(ComplianceFramework::ISO27001, "A.9"), (ComplianceFramework::NIST, "AC-1")],
+            ),
+            // Partially implemented controls do not close anything
+            control(
+                "iso-logging",
+                ImplementationStatus::PartiallyImplemented,
+                &[(ComplianceFramework::ISO27001, "A.12.4"), (ComplianceFramework::NIST, "AU-2")],
+            ),
+        ];
+        let gap_analyses = vec![
+            (ComplianceFramework::NIST, vec![
+                gap(ComplianceFramework::NIST, "ac-1"),
+                gap(ComplianceFramework::NIST, "AU-2"),
+            ]),
+            (ComplianceFramework::PCIDSS, vec![gap(ComplianceFramework::PCIDSS, "7.1")]),
+        ];
+
+        let coverage = CrossFrameworkCoverage::analyze(Uuid::new_v4(), &controls, gap_analyses);
+
+        let nist = &coverage.frameworks[0];
+        assert_eq!(nist.total_gaps, 2);
+        assert_eq!(nist.covered_gaps.len(), 1);
+        assert_eq!(nist.covered_gaps[0].covered_by, "iso-access-control");
+        assert_eq!(nist.net_new_gaps.len(), 1);
+        assert_eq!(nist.net_new_gaps[0].control_id, "AU-2");
+        assert_eq!(coverage.frameworks[1].net_new_gaps.len(), 1);
+        assert_eq!(coverage.net_new_gap_count(), 2);
+
+        assert_eq!(coverage.reusable_controls.len(), 1);
+        assert_eq!(coverage.reusable_controls[0].closes, vec![(ComplianceFramework::NIST, "ac-1".to_string())]);
+    }
+}
@@ -1,10 +1,10 @@
 use crate::{
     ComplianceFramework, Control, ComplianceGap, ControlType, MaturityLevel, AutomationLevel,
     CostImpact, TestingFrequency, TestingMethod, TestingProcedure, GapSeverity, RiskImpact,
-    RemediationEffort, GapStatus, Result
+    RemediationEffort, GapStatus, Result, ComplianceProject, DataFlow, Region
 };
 use crate::frameworks::FrameworkImplementation;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use uuid::Uuid;
 use chrono::Utc;
 
@@ -563,4 +563,198 @@ pub struct DPIAResult {
     pub requires_dpia: bool,
     pub high_risk_factors: Vec<String>,
     pub recommendations: Vec<String>,
-}
\ No newline at end of file
+}
+/// Region pairs between which personal data may flow without further
+/// safeguards (GDPR Article 45). Transfers within a region are always adequate.
+#[derive(Debug, Clone)]
+pub struct AdequacyMatrix {
+    adequate: HashSet<(Region, Region)>,
+}
+
+impl AdequacyMatrix {
+    /// A matrix in which only transfers within a region are adequate
+    pub fn empty() -> Self {
+        Self { adequate: HashSet::new() }
+    }
+
+    pub fn allow(mut self, from: Region, to: Region) -> Self {
+        self.adequate.insert((from, to));
+        self
+    }
+
+    pub fn is_adequate(&self, from: &Region, to: &Region) -> bool {
+        from == to || self.adequate.contains(&(from.clone(), to.clone()))
+    }
+}
+
+impl Default for AdequacyMatrix {
+    /// EU adequacy decisions for the UK and Canada, and the UK's for the EU
+    fn default() -> Self {
+        Self::empty()
+            .allow(Region::EU, Region::UK)
+            .allow(Region::EU, Region::Canada)
+            .allow(Region::UK, Region::EU)
+    }
+}
+
+/// Transfer mechanism named in a data flow's `safeguards`
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum TransferMechanism {
+    StandardContractualClauses,
+    BindingCorporateRules,
+    AdequacyDecision,
+}
+
+impl TransferMechanism {
+    fn parse(safeguard: &str) -> Option<Self> {
+        let safeguard = safeguard.trim().to_lowercase();
+        if safeguard == "scc" || safeguard == "sccs" || safeguard.contains("standard contractual clauses") {
+            Some(Self::StandardContractualClauses)
+        } else if safeguard == "bcr" || safeguard == "bcrs" || safeguard.contains("binding corporate rules") {
+            Some(Self::BindingCorporateRules)
+        } else if safeguard.contains("adequacy") {
+            Some(Self::AdequacyDecision)
+        } else {
+            None
+        }
+    }
+}
+
+/// Checks cross-border data flows for a lawful transfer mechanism: the
+/// regions are adequate under the `AdequacyMatrix`, or the flow's safeguards
+/// include standard contractual clauses or binding corporate rules. A claimed
+/// adequacy decision only counts when the matrix confirms it. Flows whose
+/// regions are unknown need SCCs or BCRs.
+pub struct TransferValidator {
+    matrix: AdequacyMatrix,
+}
+
+impl TransferValidator {
+    pub fn new(matrix: AdequacyMatrix) -> Self {
+        Self { matrix }
+    }
+
+    pub fn validate(&self, project: &ComplianceProject) -> Vec<ComplianceGap> {
+        project
+            .systems
+            .iter()
+            .flat_map(|system| system.data_flows.iter())
+            .flat_map(|flow| self.validate_flow(flow))
+            .collect()
+    }
+
+    pub fn validate_flow(&self, flow: &DataFlow) -> Vec<ComplianceGap> {
+        if !flow.cross_border {
+            return Vec::new();
+        }
+
+        let mut gaps = Vec::new();
+        let route = match (&flow.source_region, &flow.destination_region) {
+            (Some(from), Some(to)) => format!("{} ({:?}) -> {} ({:?})", flow.source_system, from, flow.destination_system, to),
+            _ => format!("{} -> {}", flow.source_system, flow.destination_system),
+        };
+        let adequate = match (&flow.source_region, &flow.destination_region) {
+            (Some(from), Some(to)) => self.matrix.is_adequate(from, to),
+            _ => false,
+        };
+        let mechanisms: Vec<TransferMechanism> =
+            flow.safeguards.iter().filter_map(|safeguard| TransferMechanism::parse(safeguard)).collect();
+        let has_contractual_safeguard = mechanisms.iter().any(|mechanism| {
+            matches!(mechanism, TransferMechanism::StandardContractualClauses | TransferMechanism::BindingCorporateRules)
+        });
+
+        if !adequate && !has_contractual_safeguard {
+            let description = if mechanisms.contains(&TransferMechanism::AdequacyDecision) {
+                format!("Cross-border transfer {} relies on an adequacy decision that does not cover these regions", route)
+            } else {
+                format!("Cross-border transfer {} has no transfer mechanism (SCCs, BCRs or adequacy decision)", route)
+            };
+            gaps.push(Self::transfer_gap("GDPR-46.1", description));
+        }
+        if flow.legal_basis.is_none() {
+            gaps.push(Self::transfer_gap(
+                "GDPR-6.1",
+                format!("Cross-border transfer {} has no documented legal basis", route),
+            ));
+        }
+
+        gaps
+    }
+
+    fn transfer_gap(control_id: &str, description: String) -> ComplianceGap {
+        ComplianceGap {
+            id: Uuid::new_v4(),
+            severity: GapSeverity::High,
+            framework: ComplianceFramework::GDPR,
+            control_id: control_id.to_string(),
+            description,
+            risk_impact: RiskImpact::Major,
+            remediation_effort: RemediationEffort::Medium,
+            due_date: Some(Utc::now() + chrono::Duration::days(60)),
+            responsible_party: "Privacy Team".to_string(),
+            status: GapStatus::Identified,
+        }
+    }
+}
+
+impl Default for TransferValidator {
+    fn default() -> Self {
+        Self::new(AdequacyMatrix::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{DataVolume, LegalBasis, TransferFrequency, TransferMethod};
+
+    fn flow(from: Region, to: Region, safeguards: &[&str]) -> DataFlow {
+        DataFlow {
+            id: Uuid::new_v4(),
+            source_system: "crm-eu".to_string(),
+            destination_system: "analytics-us".to_string(),
+            data_categories: Vec::new(),
+            transfer_method: TransferMethod::API,
+            encryption_in_transit: true,
+            encryption_at_rest: true,
+            cross_border: true,
+            source_region: Some(from),
+            destination_region: Some(to),
+            legal_basis: Some(LegalBasis::Contract),
+            safeguards: safeguards.iter().map(|s| s.to_string()).collect(),
+            frequency: TransferFrequency::Daily,
+            volume: DataVolume::Medium,
+        }
+    }
+
+    #[test]
+    fn eu_to_us_without_sccs_is_a_gap() {
+        let validator = TransferValidator::default();
+
+        let gaps = validator.validate_flow(&flow(Region::EU, Region::US, &["encryption"]));
+        assert_eq!(gaps.len(), 1);
+        assert_eq!(gaps[0].control_id, "GDPR-46.1");
+        assert!(matches!(gaps[0].severity, GapSeverity::High));
+
+        // Claiming an adequacy decision the matrix does not know is no better
+        let gaps = validator.validate_flow(&flow(Region::EU, Region::US, &["Adequacy decision"]));
+        assert_eq!(gaps.len(), 1);
+        assert!(gaps[0].description.contains("adequacy decision that does not cover"));
+    }
+
+    #[test]
+    fn eu_to_us_with_sccs_is_clean() {
+        let validator = TransferValidator::default();
+        assert!(validator.validate_flow(&flow(Region::EU, Region::US, &["SCCs"])).is_empty());
+        assert!(validator
+            .validate_flow(&flow(Region::EU, Region::US, &["Standard Contractual Clauses (2021)"]))
+            .is_empty());
+
+        // Adequate regions need no safeguards, and the matrix is configurable
+        assert!(validator.validate_flow(&flow(Region::EU, Region::UK, &[])).is_empty());
+        let strict = TransferValidator::new(AdequacyMatrix::empty());
+        assert_eq!(strict.validate_flow(&flow(Region::EU, Region::UK, &[])).len(), 1);
+        let with_us = TransferValidator::new(AdequacyMatrix::default().allow(Region::EU, Region::US));
+        assert!(with_us.validate_flow(&flow(Region::EU, Region::US, &[])).is_empty());
+    }
+}
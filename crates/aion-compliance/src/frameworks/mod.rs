@@ -1,3 +1,4 @@
+pub mod coverage;
 pub mod gdpr;
 pub mod hipaa;
 pub mod sox;
@@ -5,6 +6,7 @@ pub mod pci_dss;
 pub mod iso27001;
 pub mod nist;
 
+pub use coverage::*;
 pub use gdpr::*;
 pub use hipaa::*;
 pub use sox::*;
@@ -0,0 +1,724 @@
+// AION-R Compliance: Incident Response Workflow
+// State machine for security incidents with per-severity SLAs, stakeholder
+// notification, GDPR Article 33 breach-notification tracking and post-mortems.
+
+use crate::audit_chain::AuditChain;
+use crate::{ContactInfo, Result};
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use uuid::Uuid;
+
+/// GDPR Article 33: supervisory authority must be notified within 72 hours
+pub const GDPR_BREACH_NOTIFICATION_HOURS: i64 = 72;
+
+/// Remaining time below which a pending breach notification is flagged as approaching
+pub const BREACH_NOTIFICATION_WARNING_HOURS: i64 = 24;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum IncidentSeverity {
+    Low,
+    Medium,
+    High,
+    Critical,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum IncidentState {
+    Detected,
+    Triaged,
+    Contained,
+    Eradicated,
+    Recovered,
+    PostMortem,
+    Closed,
+}
+
+impl IncidentState {
+    /// Next state in the standard response lifecycle
+    pub fn next(&self) -> Option<IncidentState> {
+        match self {
+            IncidentState::Detected => Some(IncidentState::Triaged),
+            IncidentState::Triaged => Some(IncidentState::Contained),
+            IncidentState::Contained => Some(IncidentState::Eradicated),
+            IncidentState::Eradicated => Some(IncidentState::Recovered),
+            IncidentState::Recovered => Some(IncidentState::PostMortem),
+            IncidentState::PostMortem => Some(IncidentState::Closed),
+            IncidentState::Closed => None,
+        }
+    }
+
+    /// Actions that must be recorded before the incident can leave this state
+    pub fn required_actions(&self) -> Vec<ResponseAction> {
+        match self {
+            IncidentState::Detected => vec![ResponseAction::AssignOwner],
+            IncidentState::Triaged => vec![ResponseAction::AssessImpact],
+            IncidentState::Contained => vec![ResponseAction::IsolateSystems, ResponseAction::PreserveEvidence],
+            IncidentState::Eradicated => vec![ResponseAction::RemoveThreat],
+            IncidentState::Recovered => vec![ResponseAction::RestoreServices, ResponseAction::VerifyIntegrity],
+            IncidentState::PostMortem => vec![ResponseAction::RootCauseAnalysis],
+            IncidentState::Closed => Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ResponseAction {
+    AssignOwner,
+    AssessImpact,
+    IsolateSystems,
+    PreserveEvidence,
+    RemoveThreat,
+    PatchVulnerability,
+    RestoreServices,
+    VerifyIntegrity,
+    RootCauseAnalysis,
+    NotifySupervisoryAuthority,
+    NotifyDataSubjects,
+    Other(String),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IncidentActionRecord {
+    pub id: Uuid,
+    pub action: ResponseAction,
+    pub performed_by: String,
+    pub performed_at: DateTime<Utc>,
+    pub state: IncidentState,
+    pub notes: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StateTransition {
+    pub from: IncidentState,
+    pub to: IncidentState,
+    pub actor: String,
+    pub at: DateTime<Utc>,
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Incident {
+    pub id: Uuid,
+    pub title: String,
+    pub description: String,
+    pub severity: IncidentSeverity,
+    pub state: IncidentState,
+    pub detected_at: DateTime<Utc>,
+    pub state_entered_at: DateTime<Utc>,
+    pub affected_systems: Vec<Uuid>,
+    pub affected_data_categories: Vec<Uuid>,
+    pub personal_data_breach: bool,
+    pub owner: Option<String>,
+    pub actions: Vec<IncidentActionRecord>,
+    pub transitions: Vec<StateTransition>,
+    pub reopen_count: u32,
+    pub closed_at: Option<DateTime<Utc>>,
+}
+
+impl Incident {
+    pub fn new(title: impl Into<String>, description: impl Into<String>, severity: IncidentSeverity, personal_data_breach: bool) -> Self {
+        let now = Utc::now();
+        Self {
+            id: Uuid::new_v4(),
+            title: title.into(),
+            description: description.into(),
+            severity,
+            state: IncidentState::Detected,
+            detected_at: now,
+            state_entered_at: now,
+            affected_systems: Vec::new(),
+            affected_data_categories: Vec::new(),
+            personal_data_breach,
+            owner: None,
+            actions: Vec::new(),
+            transitions: Vec::new(),
+            reopen_count: 0,
+            closed_at: None,
+        }
+    }
+
+    pub fn has_action(&self, action: &ResponseAction) -> bool {
+        self.actions.iter().any(|a| &a.action == action)
+    }
+
+    /// Required actions of the current state that have not been recorded since entering it
+    pub fn missing_actions(&self) -> Vec<ResponseAction> {
+        self.state
+            .required_actions()
+            .into_iter()
+            .filter(|required| {
+                !self
+                    .actions
+                    .iter()
+                    .any(|a| &a.action == required && a.performed_at >= self.state_entered_at)
+            })
+            .collect()
+    }
+}
+
+/// Maximum elapsed time since detection for reaching each milestone state
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IncidentSla {
+    pub triage_within: Duration,
+    pub contain_within: Duration,
+    pub recover_within: Duration,
+    pub post_mortem_within: Duration,
+}
+
+impl IncidentSla {
+    pub fn for_severity(severity: IncidentSeverity) -> Self {
+        let (triage, contain, recover, post_mortem) = match severity {
+            IncidentSeverity::Critical => (Duration::minutes(15), Duration::hours(4), Duration::hours(24), Duration::days(5)),
+            IncidentSeverity::High => (Duration::hours(1), Duration::hours(12), Duration::hours(72), Duration::days(10)),
+            IncidentSeverity::Medium => (Duration::hours(4), Duration::hours(48), Duration::days(7), Duration::days(20)),
+            IncidentSeverity::Low => (Duration::hours(24), Duration::days(7), Duration::days(30), Duration::days(45)),
+        };
+
+        Self {
+            triage_within: triage,
+            contain_within: contain,
+            recover_within: recover,
+            post_mortem_within: post_mortem,
+        }
+    }
+
+    fn deadline_for(&self, state: IncidentState) -> Option<Duration> {
+        match state {
+            IncidentState::Triaged => Some(self.triage_within),
+            IncidentState::Contained => Some(self.contain_within),
+            IncidentState::Recovered => Some(self.recover_within),
+            IncidentState::PostMortem => Some(self.post_mortem_within),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SlaBreach {
+    pub milestone: IncidentState,
+    pub due_at: DateTime<Utc>,
+    pub reached_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BreachNotificationStatus {
+    pub deadline: DateTime<Utc>,
+    pub remaining: Duration,
+    pub notified: bool,
+    pub approaching: bool,
+    pub overdue: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IncidentNotification {
+    pub incident_id: Uuid,
+    pub recipients: Vec<String>,
+    pub subject: String,
+    pub body: String,
+}
+
+/// Delivery channel for incident notifications (email, chat, paging, ...)
+#[async_trait]
+pub trait IncidentNotifier: Send + Sync {
+    async fn notify(&self, notification: &IncidentNotification) -> Result<()>;
+}
+
+/// Notifier that only records notifications in the tracing log
+pub struct TracingNotifier;
+
+#[async_trait]
+impl IncidentNotifier for TracingNotifier {
+    async fn notify(&self, notification: &IncidentNotification) -> Result<()> {
+        tracing::info!(
+            incident_id = %notification.incident_id,
+            recipients = ?notification.recipients,
+            "{}",
+            notification.subject
+        );
+        Ok(())
+    }
+}
+
+/// Drives incidents through the response lifecycle, auditing every change
+pub struct IncidentResponseWorkflow {
+    contacts: ContactInfo,
+    notifier: Arc<dyn IncidentNotifier>,
+    audit: AuditChain,
+}
+
+impl IncidentResponseWorkflow {
+    pub fn new(contacts: ContactInfo, notifier: Arc<dyn IncidentNotifier>) -> Self {
+        Self {
+            contacts,
+            notifier,
+            audit: AuditChain::new(),
+        }
+    }
+
+    pub fn audit_log(&self) -> &AuditChain {
+        &self.audit
+    }
+
+    /// Register a newly detected incident
+    pub async fn open(&mut self, incident: &Incident, actor: &str) -> Result<()> {
+        self.audit.append(
+            actor,
+            "incident.detected",
+            Some(incident.id),
+            serde_json::json!({
+                "title": incident.title,
+                "severity": incident.severity,
+                "personal_data_breach": incident.personal_data_breach,
+            }),
+        );
+        self.notify_best_effort(incident, None).await;
+        Ok(())
+    }
+
+    pub fn record_action(&mut self, incident: &mut Incident, action: ResponseAction, actor: &str, notes: impl Into<String>) -> Result<()> {
+        if incident.state == IncidentState::Closed {
+            return Err(format!("Incident {} is closed; reopen it before recording actions", incident.id).into());
+        }
+
+        let record = IncidentActionRecord {
+            id: Uuid::new_v4(),
+            action,
+            performed_by: actor.to_string(),
+            performed_at: Utc::now(),
+            state: incident.state,
+            notes: notes.into(),
+        };
+
+        if record.action == ResponseAction::AssignOwner && incident.owner.is_none() {
+            incident.owner = Some(actor.to_string());
+        }
+
+        self.audit.append(
+            actor,
+            "incident.action",
+            Some(incident.id),
+            serde_json::json!({
+                "action": record.action,
+                "state": record.state,
+                "notes": record.notes,
+            }),
+        );
+        incident.actions.push(record);
+        Ok(())
+    }
+
+    /// Move the incident to its next lifecycle state once required actions are recorded
+    pub async fn advance(&mut self, incident: &mut Incident, actor: &str) -> Result<IncidentState> {
+        let next = incident
+            .state
+            .next()
+            .ok_or_else(|| format!("Incident {} is closed; use reopen to resume work", incident.id))?;
+
+        let missing = incident.missing_actions();
+        if !missing.is_empty() {
+            return Err(format!(
+                "Cannot move incident {} from {:?} to {:?}: missing required actions {:?}",
+                incident.id, incident.state, next, missing
+            )
+            .into());
+        }
+
+        self.apply_transition(incident, next, actor, None).await?;
+        Ok(next)
+    }
+
+    /// Reopen a closed incident, returning it to triage
+    pub async fn reopen(&mut self, incident: &mut Incident, actor: &str, reason: &str) -> Result<()> {
+        if incident.state != IncidentState::Closed {
+            return Err(format!("Incident {} is not closed", incident.id).into());
+        }
+        if reason.trim().is_empty() {
+            return Err("A reason is required to reopen an incident".into());
+        }
+
+        incident.reopen_count += 1;
+        incident.closed_at = None;
+        self.apply_transition(incident, IncidentState::Triaged, actor, Some(reason.to_string())).await
+    }
+
+    async fn apply_transition(&mut self, incident: &mut Incident, to: IncidentState, actor: &str, reason: Option<String>) -> Result<()> {
+        let now = Utc::now();
+        let transition = StateTransition {
+            from: incident.state,
+            to,
+            actor: actor.to_string(),
+            at: now,
+            reason,
+        };
+
+        self.audit.append(
+            actor,
+            "incident.transition",
+            Some(incident.id),
+            serde_json::json!({
+                "from": transition.from,
+                "to": transition.to,
+                "reason": transition.reason,
+            }),
+        );
+
+        incident.state = to;
+        incident.state_entered_at = now;
+        if to == IncidentState::Closed {
+            incident.closed_at = Some(now);
+        }
+        incident.transitions.push(transition.clone());
+
+        self.notify_best_effort(incident, Some(&transition)).await;
+        Ok(())
+    }
+
+    /// The transition is already recorded by the time stakeholders are told, so
+    /// a delivery failure is logged instead of failing a change that happened
+    async fn notify_best_effort(&self, incident: &Incident, transition: Option<&StateTransition>) {
+        if let Err(e) = self.notify_transition(incident, transition).await {
+            tracing::warn!(incident_id = %incident.id, state = ?incident.state, "Incident notification failed: {}", e);
+        }
+    }
+
+    async fn notify_transition(&self, incident: &Incident, transition: Option<&StateTransition>) -> Result<()> {
+        let recipients = self.recipients_for(incident);
+        if recipients.is_empty() {
+            return Ok(());
+        }
+
+        let subject = match transition {
+            Some(t) => format!("[{:?}] Incident '{}' moved {:?} -> {:?}", incident.severity, incident.title, t.from, t.to),
+            None => format!("[{:?}] Incident '{}' detected", incident.severity, incident.title),
+        };
+
+        let mut body = format!("Incident {}\nState: {:?}\n", incident.id, incident.state);
+        if let Some(reason) = transition.and_then(|t| t.reason.as_ref()) {
+            body.push_str(&format!("Reason: {}\n", reason));
+        }
+        if let Some(status) = self.breach_notification_status(incident, Utc::now()) {
+            if !status.notified {
+                body.push_str(&format!("GDPR breach notification due by {}\n", status.deadline.to_rfc3339()));
+            }
+        }
+
+        self.notifier
+            .notify(&IncidentNotification {
+                incident_id: incident.id,
+                recipients,
+                subject,
+                body,
+            })
+            .await
+    }
+
+    /// Contacts relevant to the incident in its current state
+    pub fn recipients_for(&self, incident: &Incident) -> Vec<String> {
+        let contacts = &self.contacts;
+        let mut recipients = vec![Some(contacts.incident_response_email.clone())];
+
+        match incident.state {
+            IncidentState::Detected | IncidentState::Triaged | IncidentState::Contained | IncidentState::Eradicated => {
+                recipients.push(contacts.security_officer_email.clone());
+            }
+            IncidentState::Recovered | IncidentState::PostMortem | IncidentState::Closed => {
+                recipients.push(contacts.security_officer_email.clone());
+                recipients.push(contacts.compliance_officer_email.clone());
+            }
+        }
+
+        if incident.personal_data_breach {
+            recipients.push(contacts.dpo_email.clone());
+            recipients.push(contacts.privacy_officer_email.clone());
+            recipients.push(contacts.legal_contact_email.clone());
+        }
+
+        if incident.severity == IncidentSeverity::Critical {
+            recipients.push(contacts.compliance_officer_email.clone());
+            recipients.push(contacts.legal_contact_email.clone());
+        }
+
+        let mut unique: Vec<String> = Vec::new();
+        for email in recipients.into_iter().flatten() {
+            if !unique.contains(&email) {
+                unique.push(email);
+            }
+        }
+        unique
+    }
+
+    /// GDPR 72-hour notification timer; `None` when no personal data is involved
+    pub fn breach_notification_status(&self, incident: &Incident, now: DateTime<Utc>) -> Option<BreachNotificationStatus> {
+        if !incident.personal_data_breach {
+            return None;
+        }
+
+        let deadline = incident.detected_at + Duration::hours(GDPR_BREACH_NOTIFICATION_HOURS);
+        let remaining = deadline - now;
+        let notified = incident.has_action(&ResponseAction::NotifySupervisoryAuthority);
+
+        Some(BreachNotificationStatus {
+            deadline,
+            remaining,
+            notified,
+            approaching: !notified && remaining > Duration::zero() && remaining <= Duration::hours(BREACH_NOTIFICATION_WARNING_HOURS),
+            overdue: !notified && remaining <= Duration::zero(),
+        })
+    }
+
+    /// SLA milestones that were reached late or are overdue at `now`
+    pub fn sla_breaches(&self, incident: &Incident, now: DateTime<Utc>) -> Vec<SlaBreach> {
+        let sla = IncidentSla::for_severity(incident.severity);
+        let milestones = [
+            IncidentState::Triaged,
+            IncidentState::Contained,
+            IncidentState::Recovered,
+            IncidentState::PostMortem,
+        ];
+
+        milestones
+            .iter()
+            .filter_map(|milestone| {
+                let due_at = incident.detected_at + sla.deadline_for(*milestone)?;
+                let reached_at = incident.transitions.iter().find(|t| t.to == *milestone).map(|t| t.at);
+                let breached = match reached_at {
+                    Some(at) => at > due_at,
+                    None => now > due_at,
+                };
+                breached.then(|| SlaBreach {
+                    milestone: *milestone,
+                    due_at,
+                    reached_at,
+                })
+            })
+            .collect()
+    }
+
+    /// Markdown post-mortem pre-filled with the recorded timeline
+    pub fn post_mortem_template(&self, incident: &Incident) -> String {
+        let mut timeline: Vec<(DateTime<Utc>, String)> = Vec::new();
+        timeline.push((incident.detected_at, "Incident detected".to_string()));
+        for t in &incident.transitions {
+            let mut line = format!("{:?} -> {:?} by {}", t.from, t.to, t.actor);
+            if let Some(reason) = &t.reason {
+                line.push_str(&format!(" (reason: {})", reason));
+            }
+            timeline.push((t.at, line));
+        }
+        for a in &incident.actions {
+            let mut line = format!("{:?} by {}", a.action, a.performed_by);
+            if !a.notes.is_empty() {
+                line.push_str(&format!(": {}", a.notes));
+            }
+            timeline.push((a.performed_at, line));
+        }
+        timeline.sort_by_key(|(at, _)| *at);
+
+        let mut doc = String::new();
+        doc.push_str(&format!("# Post-Mortem: {}\n\n", incident.title));
+        doc.push_str("## Summary\n\n");
+        doc.push_str(&format!("- Incident ID: {}\n", incident.id));
+        doc.push_str(&format!("- Severity: {:?}\n", incident.severity));
+        doc.push_str(&format!("- Detected: {}\n", incident.detected_at.to_rfc3339()));
+        doc.push_str(&format!("- Owner: {}\n", incident.owner.as_deref().unwrap_or("unassigned")));
+        doc.push_str(&format!("- Personal data breach: {}\n", if incident.personal_data_breach { "yes" } else { "no" }));
+        if incident.reopen_count > 0 {
+            doc.push_str(&format!("- Reopened: {} time(s)\n", incident.reopen_count));
+        }
+        doc.push_str(&format!("\n{}\n\n", incident.description));
+
+        doc.push_str("## Timeline\n\n");
+        for (at, entry) in &timeline {
+            doc.push_str(&format!("- {} — {}\n", at.to_rfc3339(), entry));
+        }
+
+        let breaches = self.sla_breaches(incident, Utc::now());
+        if !breaches.is_empty() {
+            doc.push_str("\n## SLA Breaches\n\n");
+            for breach in &breaches {
+                doc.push_str(&format!("- {:?} due {}\n", breach.milestone, breach.due_at.to_rfc3339()));
+            }
+        }
+
+        doc.push_str("\n## Root Cause\n\n_TODO_\n");
+        doc.push_str("\n## Impact\n\n_TODO_\n");
+        doc.push_str("\n## What Went Well\n\n_TODO_\n");
+        doc.push_str("\n## What Could Be Improved\n\n_TODO_\n");
+        doc.push_str("\n## Action Items\n\n| Action | Owner | Due |\n|---|---|---|\n");
+
+        doc
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    #[derive(Default)]
+    struct RecordingNotifier {
+        sent: Mutex<Vec<IncidentNotification>>,
+    }
+
+    #[async_trait]
+    impl IncidentNotifier for RecordingNotifier {
+        async fn notify(&self, notification: &IncidentNotification) -> Result<()> {
+            self.sent.lock().unwrap().push(notification.clone());
+            Ok(())
+        }
+    }
+
+    struct FailingNotifier;
+
+    #[async_trait]
+    impl IncidentNotifier for FailingNotifier {
+        async fn notify(&self, _notification: &IncidentNotification) -> Result<()> {
+            Err("smtp unavailable".into())
+        }
+    }
+
+    fn contacts() -> ContactInfo {
+        ContactInfo {
+            dpo_email: Some("dpo@example.com".to_string()),
+            privacy_officer_email: None,
+            security_officer_email: Some("security@example.com".to_string()),
+            compliance_officer_email: Some("compliance@example.com".to_string()),
+            legal_contact_email: None,
+            incident_response_email: "ir@example.com".to_string(),
+        }
+    }
+
+    fn workflow(notifier: Arc<dyn IncidentNotifier>) -> IncidentResponseWorkflow {
+        IncidentResponseWorkflow::new(contacts(), notifier)
+    }
+
+    fn complete_required_actions(workflow: &mut IncidentResponseWorkflow, incident: &mut Incident) {
+        for action in incident.state.required_actions() {
+            workflow.record_action(incident, action, "alice", "").unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn advance_requires_the_actions_of_the_current_state() {
+        let mut workflow = workflow(Arc::new(RecordingNotifier::default()));
+        let mut incident = Incident::new("Leaked key", "", IncidentSeverity::High, false);
+
+        let err = workflow.advance(&mut incident, "alice").await.unwrap_err();
+        assert!(err.to_string().contains("AssignOwner"));
+        assert_eq!(incident.state, IncidentState::Detected);
+        assert!(incident.transitions.is_empty());
+
+        complete_required_actions(&mut workflow, &mut incident);
+        assert_eq!(workflow.advance(&mut incident, "alice").await.unwrap(), IncidentState::Triaged);
+        assert_eq!(incident.owner.as_deref(), Some("alice"));
+
+        // Actions recorded in an earlier state do not count for the new one
+        assert_eq!(incident.missing_actions(), vec![ResponseAction::AssessImpact]);
+    }
+
+    #[tokio::test]
+    async fn closed_incidents_reject_advance_and_actions_until_reopened() {
+        let mut workflow = workflow(Arc::new(RecordingNotifier::default()));
+        let mut incident = Incident::new("Leaked key", "", IncidentSeverity::Low, false);
+
+        while incident.state != IncidentState::Closed {
+            complete_required_actions(&mut workflow, &mut incident);
+            workflow.advance(&mut incident, "alice").await.unwrap();
+        }
+        assert!(incident.closed_at.is_some());
+        assert!(workflow.advance(&mut incident, "alice").await.is_err());
+        assert!(workflow.record_action(&mut incident, ResponseAction::RemoveThreat, "alice", "").is_err());
+
+        assert!(workflow.reopen(&mut incident, "alice", " ").await.is_err());
+        workflow.reopen(&mut incident, "alice", "Attacker regained access").await.unwrap();
+        assert_eq!(incident.state, IncidentState::Triaged);
+        assert_eq!(incident.reopen_count, 1);
+        assert!(incident.closed_at.is_none());
+        assert_eq!(incident.missing_actions(), vec![ResponseAction::AssessImpact]);
+
+        let mut open = Incident::new("Phishing", "", IncidentSeverity::Low, false);
+        assert!(workflow.reopen(&mut open, "alice", "again").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn a_failed_notification_does_not_undo_the_transition() {
+        let mut workflow = workflow(Arc::new(FailingNotifier));
+        let mut incident = Incident::new("Leaked key", "", IncidentSeverity::High, false);
+        workflow.open(&incident, "alice").await.unwrap();
+        complete_required_actions(&mut workflow, &mut incident);
+
+        assert_eq!(workflow.advance(&mut incident, "alice").await.unwrap(), IncidentState::Triaged);
+        assert_eq!(incident.transitions.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn transitions_notify_the_contacts_for_the_new_state() {
+        let notifier = Arc::new(RecordingNotifier::default());
+        let mut workflow = workflow(notifier.clone());
+        let mut incident = Incident::new("Customer export", "", IncidentSeverity::High, true);
+        workflow.open(&incident, "alice").await.unwrap();
+        complete_required_actions(&mut workflow, &mut incident);
+        workflow.advance(&mut incident, "alice").await.unwrap();
+
+        let sent = notifier.sent.lock().unwrap();
+        assert_eq!(sent.len(), 2);
+        assert!(sent[0].subject.contains("detected"));
+        assert!(sent[1].subject.contains("Detected -> Triaged"));
+        assert!(sent[1].body.contains("GDPR breach notification due by"));
+        assert_eq!(
+            sent[1].recipients,
+            vec!["ir@example.com", "security@example.com", "dpo@example.com"]
+        );
+    }
+
+    #[test]
+    fn sla_breaches_cover_late_and_overdue_milestones() {
+        let workflow = workflow(Arc::new(RecordingNotifier::default()));
+        let mut incident = Incident::new("Outage", "", IncidentSeverity::Critical, false);
+        incident.detected_at = Utc::now() - Duration::hours(5);
+        incident.transitions.push(StateTransition {
+            from: IncidentState::Detected,
+            to: IncidentState::Triaged,
+            actor: "alice".to_string(),
+            at: incident.detected_at + Duration::minutes(10),
+            reason: None,
+        });
+
+        let breaches = workflow.sla_breaches(&incident, Utc::now());
+        let milestones: Vec<_> = breaches.iter().map(|b| b.milestone).collect();
+        // Triaged within 15 minutes; containment (4 hours) is overdue and unreached
+        assert_eq!(milestones, vec![IncidentState::Contained]);
+        assert!(breaches[0].reached_at.is_none());
+
+        incident.transitions[0].at = incident.detected_at + Duration::minutes(30);
+        let milestones: Vec<_> = workflow.sla_breaches(&incident, Utc::now()).iter().map(|b| b.milestone).collect();
+        assert_eq!(milestones, vec![IncidentState::Triaged, IncidentState::Contained]);
+    }
+
+    #[test]
+    fn gdpr_timer_tracks_the_72_hour_deadline() {
+        let mut workflow = workflow(Arc::new(RecordingNotifier::default()));
+        let mut incident = Incident::new("Customer export", "", IncidentSeverity::High, true);
+        let detected = incident.detected_at;
+
+        assert!(workflow.breach_notification_status(&Incident::new("x", "", IncidentSeverity::Low, false), detected).is_none());
+
+        let status = workflow.breach_notification_status(&incident, detected + Duration::hours(10)).unwrap();
+        assert_eq!(status.deadline, detected + Duration::hours(GDPR_BREACH_NOTIFICATION_HOURS));
+        assert!(!status.approaching && !status.overdue);
+
+        let status = workflow.breach_notification_status(&incident, detected + Duration::hours(60)).unwrap();
+        assert!(status.approaching && !status.overdue);
+
+        let status = workflow.breach_notification_status(&incident, detected + Duration::hours(73)).unwrap();
+        assert!(status.overdue && !status.approaching);
+
+        workflow
+            .record_action(&mut incident, ResponseAction::NotifySupervisoryAuthority, "dpo", "Filed with the ICO")
+            .unwrap();
+        let status = workflow.breach_notification_status(&incident, detected + Duration::hours(73)).unwrap();
+        assert!(status.notified && !status.overdue);
+    }
+}
@@ -1,5 +1,7 @@
 pub mod frameworks;
 pub mod audit;
+pub mod audit_chain;
+pub mod evidence_chain;
 pub mod data_protection;
 pub mod access_control;
 pub mod monitoring;
@@ -9,9 +11,12 @@ pub mod retention;
 pub mod consent;
 pub mod risk_assessment;
 pub mod incident_response;
+pub mod policy_engine;
 
 pub use frameworks::*;
 pub use audit::*;
+pub use audit_chain::*;
+pub use evidence_chain::*;
 pub use data_protection::*;
 pub use access_control::*;
 pub use monitoring::*;
@@ -21,13 +26,14 @@ pub use retention::*;
 pub use consent::*;
 pub use risk_assessment::*;
 pub use incident_response::*;
+pub use policy_engine::*;
 
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use uuid::Uuid;
 use chrono::{DateTime, Utc};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum ComplianceFramework {
     GDPR,
     HIPAA,
@@ -92,7 +98,7 @@ pub enum OrganizationSize {
     Enterprise,      // > 5000 employees
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum Region {
     EU,
     US,
@@ -401,6 +407,10 @@ pub struct DataFlow {
     pub encryption_in_transit: bool,
     pub encryption_at_rest: bool,
     pub cross_border: bool,
+    #[serde(default)]
+    pub source_region: Option<Region>,
+    #[serde(default)]
+    pub destination_region: Option<Region>,
     pub legal_basis: Option<LegalBasis>,
     pub safeguards: Vec<String>,
     pub frequency: TransferFrequency,
@@ -453,6 +463,8 @@ pub struct Policy {
     pub attachments: Vec<String>,
     pub training_required: bool,
     pub acknowledgment_required: bool,
+    #[serde(default)]
+    pub rule: Option<PolicyRule>,     // Executable policy-as-code expression
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -688,6 +700,35 @@ pub trait ComplianceManager {
     async fn generate_compliance_report(&self, project_id: Uuid, format: ReportFormat) -> Result<Vec<u8>>;
     async fn schedule_assessment(&self, project_id: Uuid, framework: ComplianceFramework, date: DateTime<Utc>) -> Result<Uuid>;
     async fn notify_stakeholders(&self, project_id: Uuid, notification_type: NotificationType) -> Result<()>;
+
+    /// Gap analysis across all of the project's frameworks, crediting gaps
+    /// already closed by security controls implemented for another framework
+    async fn cross_framework_coverage(&self, project_id: Uuid) -> Result<CrossFrameworkCoverage>
+    where
+        Self: Sync,
+    {
+        let project = self.get_project(project_id).await?;
+        let mut gap_analyses = Vec::new();
+        for framework in &project.frameworks {
+            let gaps = self.generate_gap_analysis(project_id, framework.clone()).await?;
+            gap_analyses.push((framework.clone(), gaps));
+        }
+        let controls: Vec<SecurityControl> = project
+            .systems
+            .iter()
+            .flat_map(|system| system.security_controls.iter().cloned())
+            .collect();
+        Ok(CrossFrameworkCoverage::analyze(project_id, &controls, gap_analyses))
+    }
+
+    /// GDPR gaps for cross-border data flows without a valid transfer mechanism
+    async fn validate_transfers(&self, project_id: Uuid) -> Result<Vec<ComplianceGap>>
+    where
+        Self: Sync,
+    {
+        let project = self.get_project(project_id).await?;
+        Ok(TransferValidator::default().validate(&project))
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
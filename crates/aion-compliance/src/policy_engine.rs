@@ -0,0 +1,643 @@
+// AION-R Compliance: Policy-as-Code Evaluation
+// Gives `Policy` rules executable semantics over the systems, data categories and
+// controls recorded in a `ComplianceProject`.
+
+use crate::{
+    AccessCondition, ComplianceFramework, ComplianceGap, ComplianceProject, GapSeverity, GapStatus,
+    ImplementationStatus, Policy, RemediationEffort, RiskImpact, SensitivityLevel,
+};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+/// Rule expression attached to a policy
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum PolicyRule {
+    /// Passes when every nested rule passes
+    AllOf(Vec<PolicyRule>),
+    /// Passes when at least one nested rule passes
+    AnyOf(Vec<PolicyRule>),
+    /// Passes when the nested rule fails
+    Not(Box<PolicyRule>),
+    /// Leaf predicate evaluated against the project state
+    Predicate(PolicyPredicate),
+}
+
+/// Library of reusable predicates
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum PolicyPredicate {
+    /// Every data flow stores data encrypted at rest
+    EncryptionAtRest,
+    /// Every data flow is encrypted in transit
+    EncryptionInTransit,
+    /// Access to high-sensitivity or special-category data requires MFA
+    AccessRequiresMfa,
+    /// Cross-border data flows declare a legal basis and at least one safeguard
+    CrossBorderSafeguards,
+    /// Every vendor of every system has a signed data processing agreement
+    VendorDpaSigned,
+    /// A security control with the given id is fully implemented on every system
+    ControlImplemented { control_id: String },
+    /// Predicate registered on the evaluator under this name
+    Custom(String),
+}
+
+impl PolicyPredicate {
+    pub fn name(&self) -> String {
+        match self {
+            PolicyPredicate::EncryptionAtRest => "encryption_at_rest".to_string(),
+            PolicyPredicate::EncryptionInTransit => "encryption_in_transit".to_string(),
+            PolicyPredicate::AccessRequiresMfa => "access_requires_mfa".to_string(),
+            PolicyPredicate::CrossBorderSafeguards => "cross_border_safeguards".to_string(),
+            PolicyPredicate::VendorDpaSigned => "vendor_dpa_signed".to_string(),
+            PolicyPredicate::ControlImplemented { control_id } => format!("control_implemented:{}", control_id),
+            PolicyPredicate::Custom(name) => format!("custom:{}", name),
+        }
+    }
+
+    fn severity(&self) -> GapSeverity {
+        match self {
+            PolicyPredicate::CrossBorderSafeguards => GapSeverity::Critical,
+            PolicyPredicate::EncryptionAtRest
+            | PolicyPredicate::EncryptionInTransit
+            | PolicyPredicate::AccessRequiresMfa => GapSeverity::High,
+            PolicyPredicate::VendorDpaSigned | PolicyPredicate::ControlImplemented { .. } => GapSeverity::Medium,
+            PolicyPredicate::Custom(_) => GapSeverity::Medium,
+        }
+    }
+}
+
+/// Kind of project resource a predicate was checked against
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum PolicyResourceKind {
+    Project,
+    System,
+    DataFlow,
+    DataCategory,
+    Vendor,
+    SecurityControl,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PolicyResource {
+    pub kind: PolicyResourceKind,
+    pub id: String,
+    pub name: String,
+}
+
+/// A single observation made while evaluating a predicate
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PolicyFact {
+    pub predicate: String,
+    pub resource: PolicyResource,
+    pub holds: bool,
+    pub detail: String,
+}
+
+/// Explanation of why a rule failed on a specific resource
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PolicyViolation {
+    pub predicate: String,
+    pub resource: PolicyResource,
+    pub explanation: String,
+    /// True when the violation comes from a `Not` rule whose inner rule held
+    pub negated: bool,
+    pub severity: GapSeverity,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RuleOutcome {
+    pub passed: bool,
+    pub violations: Vec<PolicyViolation>,
+    pub facts: Vec<PolicyFact>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PolicyEvaluation {
+    pub policy_id: Uuid,
+    pub policy_name: String,
+    pub passed: bool,
+    pub violations: Vec<PolicyViolation>,
+    pub facts: Vec<PolicyFact>,
+    pub evaluated_at: chrono::DateTime<Utc>,
+}
+
+pub type CustomPredicate = Box<dyn Fn(&ComplianceProject) -> Vec<PolicyFact> + Send + Sync>;
+
+/// Evaluates policy rules against a compliance project
+pub struct PolicyEvaluator {
+    custom_predicates: HashMap<String, CustomPredicate>,
+}
+
+impl PolicyEvaluator {
+    pub fn new() -> Self {
+        Self {
+            custom_predicates: HashMap::new(),
+        }
+    }
+
+    /// Register a project-specific predicate usable as `PolicyPredicate::Custom(name)`
+    pub fn register_predicate(&mut self, name: impl Into<String>, predicate: CustomPredicate) {
+        self.custom_predicates.insert(name.into(), predicate);
+    }
+
+    /// Evaluate a single policy; policies without a rule have nothing to check
+    pub fn evaluate_policy(&self, policy: &Policy, project: &ComplianceProject) -> Option<PolicyEvaluation> {
+        let rule = policy.rule.as_ref()?;
+        let outcome = self.evaluate_rule(rule, project);
+
+        Some(PolicyEvaluation {
+            policy_id: policy.id,
+            policy_name: policy.name.clone(),
+            passed: outcome.passed,
+            violations: outcome.violations,
+            facts: outcome.facts,
+            evaluated_at: Utc::now(),
+        })
+    }
+
+    /// Evaluate every policy of the project that carries a rule
+    pub fn evaluate_project(&self, project: &ComplianceProject) -> Vec<PolicyEvaluation> {
+        project
+            .policies
+            .iter()
+            .filter_map(|policy| self.evaluate_policy(policy, project))
+            .collect()
+    }
+
+    /// Evaluate all project policies and turn their violations into compliance gaps
+    pub fn identify_gaps(&self, project: &ComplianceProject) -> Vec<ComplianceGap> {
+        project
+            .policies
+            .iter()
+            .filter_map(|policy| {
+                self.evaluate_policy(policy, project)
+                    .map(|evaluation| Self::violations_to_gaps(policy, &evaluation))
+            })
+            .flatten()
+            .collect()
+    }
+
+    pub fn evaluate_rule(&self, rule: &PolicyRule, project: &ComplianceProject) -> RuleOutcome {
+        match rule {
+            PolicyRule::AllOf(rules) => {
+                let outcomes: Vec<RuleOutcome> = rules.iter().map(|r| self.evaluate_rule(r, project)).collect();
+                let passed = outcomes.iter().all(|o| o.passed);
+                Self::merge(passed, outcomes)
+            }
+            PolicyRule::AnyOf(rules) => {
+                let outcomes: Vec<RuleOutcome> = rules.iter().map(|r| self.evaluate_rule(r, project)).collect();
+                let passed = outcomes.iter().any(|o| o.passed);
+                if passed {
+                    // A satisfied alternative makes the other branches' failures irrelevant
+                    let facts = outcomes.into_iter().flat_map(|o| o.facts).collect();
+                    RuleOutcome { passed, violations: Vec::new(), facts }
+                } else {
+                    Self::merge(passed, outcomes)
+                }
+            }
+            PolicyRule::Not(inner) => {
+                let inner_outcome = self.evaluate_rule(inner, project);
+                let passed = !inner_outcome.passed;
+                let mut violations = if passed {
+                    Vec::new()
+                } else {
+                    inner_outcome
+                        .facts
+                        .iter()
+                        .filter(|fact| fact.holds)
+                        .map(|fact| PolicyViolation {
+                            predicate: fact.predicate.clone(),
+                            resource: fact.resource.clone(),
+                            explanation: format!("Predicate '{}' must not hold, but it does: {}", fact.predicate, fact.detail),
+                            negated: true,
+                            severity: Self::predicate_severity(inner, &fact.predicate),
+                        })
+                        .collect()
+                };
+                // The inner rule can pass without any fact holding, e.g. a
+                // predicate over data flows in a project that has none
+                if !passed && violations.is_empty() {
+                    violations.push(PolicyViolation {
+                        predicate: format!("not({})", Self::rule_label(inner)),
+                        resource: PolicyResource {
+                            kind: PolicyResourceKind::Project,
+                            id: project.id.to_string(),
+                            name: project.name.clone(),
+                        },
+                        explanation: format!(
+                            "Rule '{}' must not hold, but it does because nothing in the project contradicts it",
+                            Self::rule_label(inner)
+                        ),
+                        negated: true,
+                        severity: match inner.as_ref() {
+                            PolicyRule::Predicate(predicate) => predicate.severity(),
+                            _ => GapSeverity::Medium,
+                        },
+                    });
+                }
+
+                RuleOutcome {
+                    passed,
+                    violations,
+                    facts: inner_outcome.facts,
+                }
+            }
+            PolicyRule::Predicate(predicate) => self.evaluate_predicate(predicate, project),
+        }
+    }
+
+    /// Compact rendering of a rule for violation messages, e.g. `any_of(encryption_at_rest)`
+    fn rule_label(rule: &PolicyRule) -> String {
+        let list = |rules: &[PolicyRule]| rules.iter().map(Self::rule_label).collect::<Vec<_>>().join(", ");
+        match rule {
+            PolicyRule::AllOf(rules) => format!("all_of({})", list(rules)),
+            PolicyRule::AnyOf(rules) => format!("any_of({})", list(rules)),
+            PolicyRule::Not(inner) => format!("not({})", Self::rule_label(inner)),
+            PolicyRule::Predicate(predicate) => predicate.name(),
+        }
+    }
+
+    /// Severity of the predicate named `name` within `rule`
+    fn predicate_severity(rule: &PolicyRule, name: &str) -> GapSeverity {
+        let mut pending = vec![rule];
+        while let Some(rule) = pending.pop() {
+            match rule {
+                PolicyRule::AllOf(rules) | PolicyRule::AnyOf(rules) => pending.extend(rules),
+                PolicyRule::Not(inner) => pending.push(inner),
+                PolicyRule::Predicate(predicate) if predicate.name() == name => return predicate.severity(),
+                PolicyRule::Predicate(_) => {}
+            }
+        }
+        GapSeverity::Medium
+    }
+
+    fn merge(passed: bool, outcomes: Vec<RuleOutcome>) -> RuleOutcome {
+        let mut violations = Vec::new();
+        let mut facts = Vec::new();
+        for outcome in outcomes {
+            violations.extend(outcome.violations);
+            facts.extend(outcome.facts);
+        }
+        if passed {
+            violations.clear();
+        }
+        RuleOutcome { passed, violations, facts }
+    }
+
+    fn evaluate_predicate(&self, predicate: &PolicyPredicate, project: &ComplianceProject) -> RuleOutcome {
+        let facts = match predicate {
+            PolicyPredicate::EncryptionAtRest => Self::check_data_flows(project, predicate, |flow| {
+                (flow.encryption_at_rest, "data is not encrypted at rest".to_string())
+            }),
+            PolicyPredicate::EncryptionInTransit => Self::check_data_flows(project, predicate, |flow| {
+                (flow.encryption_in_transit, format!("{:?} transfer is not encrypted in transit", flow.transfer_method))
+            }),
+            PolicyPredicate::CrossBorderSafeguards => {
+                let mut facts = Vec::new();
+                for system in &project.systems {
+                    for flow in system.data_flows.iter().filter(|f| f.cross_border) {
+                        let holds = !flow.safeguards.is_empty() && flow.legal_basis.is_some();
+                        let detail = if holds {
+                            format!("cross-border transfer covered by {}", flow.safeguards.join(", "))
+                        } else if flow.safeguards.is_empty() {
+                            "cross-border transfer has no safeguards (e.g. SCCs, adequacy decision)".to_string()
+                        } else {
+                            "cross-border transfer has no documented legal basis".to_string()
+                        };
+                        facts.push(PolicyFact {
+                            predicate: predicate.name(),
+                            resource: PolicyResource {
+                                kind: PolicyResourceKind::DataFlow,
+                                id: flow.id.to_string(),
+                                name: format!("{} -> {}", flow.source_system, flow.destination_system),
+                            },
+                            holds,
+                            detail,
+                        });
+                    }
+                }
+                facts
+            }
+            PolicyPredicate::AccessRequiresMfa => {
+                let mut facts = Vec::new();
+                for category in &project.data_categories {
+                    let sensitive = category.special_category
+                        || matches!(category.sensitivity_level, SensitivityLevel::High | SensitivityLevel::Critical);
+                    if !sensitive {
+                        continue;
+                    }
+                    // Nothing recorded means nothing enforces MFA, not that it holds
+                    if category.access_controls.is_empty() {
+                        facts.push(PolicyFact {
+                            predicate: predicate.name(),
+                            resource: PolicyResource {
+                                kind: PolicyResourceKind::DataCategory,
+                                id: category.id.to_string(),
+                                name: category.name.clone(),
+                            },
+                            holds: false,
+                            detail: "no access control requirements are recorded, so MFA is not enforced".to_string(),
+                        });
+                    }
+                    for requirement in &category.access_controls {
+                        let holds = requirement
+                            .conditions
+                            .iter()
+                            .any(|c| matches!(c, AccessCondition::MultiFactorAuth));
+                        facts.push(PolicyFact {
+                            predicate: predicate.name(),
+                            resource: PolicyResource {
+                                kind: PolicyResourceKind::DataCategory,
+                                id: category.id.to_string(),
+                                name: category.name.clone(),
+                            },
+                            holds,
+                            detail: if holds {
+                                format!("role '{}' requires MFA", requirement.role)
+                            } else {
+                                format!("role '{}' can access without MFA", requirement.role)
+                            },
+                        });
+                    }
+                }
+                facts
+            }
+            PolicyPredicate::VendorDpaSigned => {
+                let mut facts = Vec::new();
+                for system in &project.systems {
+                    for vendor in &system.vendors {
+                        facts.push(PolicyFact {
+                            predicate: predicate.name(),
+                            resource: PolicyResource {
+                                kind: PolicyResourceKind::Vendor,
+                                id: vendor.name.clone(),
+                                name: format!("{} ({})", vendor.name, system.name),
+                            },
+                            holds: vendor.dpa_signed,
+                            detail: if vendor.dpa_signed {
+                                "data processing agreement signed".to_string()
+                            } else {
+                                "no data processing agreement signed".to_string()
+                            },
+                        });
+                    }
+                }
+                facts
+            }
+            PolicyPredicate::ControlImplemented { control_id } => project
+                .systems
+                .iter()
+                .map(|system| {
+                    let status = system
+                        .security_controls
+                        .iter()
+                        .find(|c| &c.id == control_id)
+                        .map(|c| &c.implementation_status);
+                    let holds = matches!(status, Some(ImplementationStatus::FullyImplemented));
+                    PolicyFact {
+                        predicate: predicate.name(),
+                        resource: PolicyResource {
+                            kind: PolicyResourceKind::System,
+                            id: system.id.to_string(),
+                            name: system.name.clone(),
+                        },
+                        holds,
+                        detail: match status {
+                            Some(status) => format!("control {} is {:?}", control_id, status),
+                            None => format!("control {} is missing", control_id),
+                        },
+                    }
+                })
+                .collect(),
+            PolicyPredicate::Custom(name) => match self.custom_predicates.get(name) {
+                Some(check) => check(project),
+                None => vec![PolicyFact {
+                    predicate: predicate.name(),
+                    resource: PolicyResource {
+                        kind: PolicyResourceKind::Project,
+                        id: project.id.to_string(),
+                        name: project.name.clone(),
+                    },
+                    holds: false,
+                    detail: format!("custom predicate '{}' is not registered", name),
+                }],
+            },
+        };
+
+        let violations: Vec<PolicyViolation> = facts
+            .iter()
+            .filter(|fact| !fact.holds)
+            .map(|fact| PolicyViolation {
+                predicate: fact.predicate.clone(),
+                resource: fact.resource.clone(),
+                explanation: format!("Predicate '{}' failed on {:?} '{}': {}", fact.predicate, fact.resource.kind, fact.resource.name, fact.detail),
+                negated: false,
+                severity: predicate.severity(),
+            })
+            .collect();
+
+        RuleOutcome {
+            passed: violations.is_empty(),
+            violations,
+            facts,
+        }
+    }
+
+    fn check_data_flows<F>(project: &ComplianceProject, predicate: &PolicyPredicate, check: F) -> Vec<PolicyFact>
+    where
+        F: Fn(&crate::DataFlow) -> (bool, String),
+    {
+        let mut facts = Vec::new();
+        for system in &project.systems {
+            for flow in &system.data_flows {
+                let (holds, failure_detail) = check(flow);
+                facts.push(PolicyFact {
+                    predicate: predicate.name(),
+                    resource: PolicyResource {
+                        kind: PolicyResourceKind::DataFlow,
+                        id: flow.id.to_string(),
+                        name: format!("{} -> {}", flow.source_system, flow.destination_system),
+                    },
+                    holds,
+                    detail: if holds { "requirement satisfied".to_string() } else { failure_detail },
+                });
+            }
+        }
+        facts
+    }
+
+    /// Convert the violations of a failed evaluation into compliance gaps, one per
+    /// violation and framework the policy applies to
+    pub fn violations_to_gaps(policy: &Policy, evaluation: &PolicyEvaluation) -> Vec<ComplianceGap> {
+        if evaluation.passed {
+            return Vec::new();
+        }
+
+        let frameworks = if policy.frameworks.is_empty() {
+            vec![ComplianceFramework::Custom(policy.name.clone())]
+        } else {
+            policy.frameworks.clone()
+        };
+
+        let mut gaps = Vec::new();
+        for framework in &frameworks {
+            for violation in &evaluation.violations {
+                let (risk_impact, effort, due_days) = match violation.severity {
+                    GapSeverity::Critical => (RiskImpact::Catastrophic, RemediationEffort::High, 30),
+                    GapSeverity::High => (RiskImpact::Major, RemediationEffort::Medium, 60),
+                    GapSeverity::Medium => (RiskImpact::Moderate, RemediationEffort::Medium, 90),
+                    GapSeverity::Low => (RiskImpact::Minor, RemediationEffort::Low, 180),
+                };
+
+                gaps.push(ComplianceGap {
+                    id: Uuid::new_v4(),
+                    severity: violation.severity.clone(),
+                    framework: framework.clone(),
+                    control_id: format!("POLICY-{}:{}", policy.id, violation.predicate),
+                    description: format!("Policy '{}' violated. {}", policy.name, violation.explanation),
+                    risk_impact,
+                    remediation_effort: effort,
+                    due_date: Some(Utc::now() + chrono::Duration::days(due_days)),
+                    responsible_party: policy.approved_by.clone(),
+                    status: GapStatus::Identified,
+                });
+            }
+        }
+
+        gaps
+    }
+}
+
+impl Default for PolicyEvaluator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        AccessControlRequirement, ComplianceStatus, ContactInfo, DataCategory, DataClassification, Industry,
+        OrganizationInfo, OrganizationSize, Region, RetentionPeriod,
+    };
+
+    fn project(data_categories: Vec<DataCategory>) -> ComplianceProject {
+        ComplianceProject {
+            id: Uuid::new_v4(),
+            name: "clinic".to_string(),
+            description: String::new(),
+            frameworks: Vec::new(),
+            organization: OrganizationInfo {
+                name: "clinic".to_string(),
+                industry: Industry::Healthcare,
+                size: OrganizationSize::Small,
+                regions: vec![Region::EU],
+                contact_info: ContactInfo {
+                    dpo_email: None,
+                    privacy_officer_email: None,
+                    security_officer_email: None,
+                    compliance_officer_email: None,
+                    legal_contact_email: None,
+                    incident_response_email: "incident@clinic.example".to_string(),
+                },
+                regulatory_requirements: Vec::new(),
+            },
+            data_categories,
+            systems: Vec::new(),
+            policies: Vec::new(),
+            controls: Vec::new(),
+            assessments: Vec::new(),
+            audits: Vec::new(),
+            incidents: Vec::new(),
+            created_at: Utc::now(),
+            last_updated: Utc::now(),
+            compliance_status: ComplianceStatus {
+                overall_score: 0.0,
+                framework_scores: HashMap::new(),
+                critical_gaps: Vec::new(),
+                improvement_recommendations: Vec::new(),
+                next_assessment_due: Utc::now(),
+                certification_status: Vec::new(),
+            },
+        }
+    }
+
+    fn health_records(access_controls: Vec<AccessControlRequirement>) -> DataCategory {
+        DataCategory {
+            id: Uuid::new_v4(),
+            name: "health records".to_string(),
+            classification: DataClassification::Restricted,
+            sensitivity_level: SensitivityLevel::Critical,
+            personal_data: true,
+            special_category: true,
+            retention_period: RetentionPeriod { duration_years: 10, trigger_event: None, legal_hold: false, auto_deletion: false },
+            processing_purposes: Vec::new(),
+            legal_basis: Vec::new(),
+            data_subjects: Vec::new(),
+            geographic_restrictions: Vec::new(),
+            encryption_required: true,
+            access_controls,
+        }
+    }
+
+    fn requirement(conditions: Vec<AccessCondition>) -> AccessControlRequirement {
+        AccessControlRequirement {
+            role: "clinician".to_string(),
+            permissions: Vec::new(),
+            conditions,
+            approval_required: false,
+            audit_required: true,
+        }
+    }
+
+    #[test]
+    fn sensitive_data_without_access_requirements_fails_mfa() {
+        let evaluator = PolicyEvaluator::new();
+        let rule = PolicyRule::Predicate(PolicyPredicate::AccessRequiresMfa);
+
+        let outcome = evaluator.evaluate_rule(&rule, &project(vec![health_records(Vec::new())]));
+        assert!(!outcome.passed);
+        assert_eq!(outcome.violations.len(), 1);
+        assert!(outcome.violations[0].explanation.contains("no access control requirements"));
+
+        let with_mfa = health_records(vec![requirement(vec![AccessCondition::MultiFactorAuth])]);
+        assert!(evaluator.evaluate_rule(&rule, &project(vec![with_mfa])).passed);
+    }
+
+    #[test]
+    fn negated_violations_keep_the_predicate_severity() {
+        let evaluator = PolicyEvaluator::new();
+        let rule = PolicyRule::Not(Box::new(PolicyRule::AnyOf(vec![PolicyRule::Predicate(
+            PolicyPredicate::AccessRequiresMfa,
+        )])));
+        let with_mfa = health_records(vec![requirement(vec![AccessCondition::MultiFactorAuth])]);
+
+        let outcome = evaluator.evaluate_rule(&rule, &project(vec![with_mfa]));
+        assert!(!outcome.passed);
+        assert_eq!(outcome.violations.len(), 1);
+        assert!(outcome.violations[0].negated);
+        assert!(matches!(outcome.violations[0].severity, GapSeverity::High));
+    }
+
+    #[test]
+    fn negating_a_vacuous_pass_reports_the_negated_rule() {
+        let evaluator = PolicyEvaluator::new();
+        // No systems, so there are no data flows for the inner predicate to inspect
+        let project = project(Vec::new());
+        let rule = PolicyRule::Not(Box::new(PolicyRule::Predicate(PolicyPredicate::EncryptionInTransit)));
+
+        let outcome = evaluator.evaluate_rule(&rule, &project);
+        assert!(!outcome.passed);
+        assert!(outcome.facts.is_empty());
+        assert_eq!(outcome.violations.len(), 1);
+        let violation = &outcome.violations[0];
+        assert_eq!(violation.predicate, "not(encryption_in_transit)");
+        assert!(violation.negated);
+        assert!(matches!(violation.resource.kind, PolicyResourceKind::Project));
+        assert_eq!(violation.resource.id, project.id.to_string());
+        assert!(matches!(violation.severity, GapSeverity::High));
+    }
+}
@@ -0,0 +1,288 @@
+use crate::EventEnvelope;
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::{Mutex, RwLock};
+
+const EVENTS_FILE: &str = "events.jsonl";
+const CHECKPOINTS_FILE: &str = "checkpoints.json";
+const DEAD_LETTERS_FILE: &str = "dead_letters.jsonl";
+
+/// Where a replay starts
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReplayFrom {
+    Beginning,
+    /// First sequence number to return
+    Sequence(u64),
+    /// Events published at or after this time
+    Time(DateTime<Utc>),
+}
+
+/// Outcome of appending an event
+#[derive(Debug, Clone)]
+pub enum Appended {
+    /// Stored with a new sequence number
+    New(EventEnvelope),
+    /// An event with the same dedup key was already stored; this is that event
+    Duplicate(EventEnvelope),
+}
+
+/// An event a durable subscriber gave up on after exhausting its retries
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeadLetter {
+    pub subscriber: String,
+    pub envelope: EventEnvelope,
+    /// Error of the last attempt
+    pub error: String,
+    pub failed_at: DateTime<Utc>,
+}
+
+/// Durable, ordered log of published events
+///
+/// Sequence numbers start at 1 and increase by one per stored event. Stores
+/// also keep each durable subscriber's checkpoint, the last sequence it
+/// handled, so delivery resumes where it stopped after a restart, and the
+/// events a subscriber could not handle, so they can be inspected and
+/// re-driven instead of being lost.
+#[async_trait]
+pub trait EventStore: Send + Sync {
+    async fn append(&self, envelope: EventEnvelope) -> Result<Appended>;
+    async fn read(&self, from: ReplayFrom, limit: usize) -> Result<Vec<EventEnvelope>>;
+    async fn load_checkpoint(&self, subscriber: &str) -> Result<Option<u64>>;
+    async fn save_checkpoint(&self, subscriber: &str, sequence: u64) -> Result<()>;
+    async fn dead_letter(&self, dead_letter: DeadLetter) -> Result<()>;
+    async fn dead_letters(&self, subscriber: &str) -> Result<Vec<DeadLetter>>;
+}
+
+#[derive(Debug, Default)]
+struct StoreState {
+    events: Vec<EventEnvelope>,
+    /// Dedup key -> index in `events`
+    dedup: HashMap<String, usize>,
+    checkpoints: HashMap<String, u64>,
+    dead_letters: Vec<DeadLetter>,
+}
+
+impl StoreState {
+    fn duplicate_of(&self, envelope: &EventEnvelope) -> Option<EventEnvelope> {
+        let key = envelope.dedup_key.as_ref()?;
+        self.dedup.get(key).map(|&index| self.events[index].clone())
+    }
+
+    fn next_sequence(&self) -> u64 {
+        self.events.last().map_or(1, |envelope| envelope.sequence + 1)
+    }
+
+    fn push(&mut self, envelope: EventEnvelope) {
+        if let Some(key) = &envelope.dedup_key {
+            self.dedup.insert(key.clone(), self.events.len());
+        }
+        self.events.push(envelope);
+    }
+
+    fn read(&self, from: ReplayFrom, limit: usize) -> Vec<EventEnvelope> {
+        match from {
+            ReplayFrom::Beginning => self.events.iter().take(limit).cloned().collect(),
+            ReplayFrom::Sequence(sequence) => {
+                let start = self.events.partition_point(|envelope| envelope.sequence < sequence);
+                self.events[start..].iter().take(limit).cloned().collect()
+            }
+            ReplayFrom::Time(time) => self
+                .events
+                .iter()
+                .filter(|envelope| envelope.timestamp >= time)
+                .take(limit)
+                .cloned()
+                .collect(),
+        }
+    }
+
+    fn dead_letters(&self, subscriber: &str) -> Vec<DeadLetter> {
+        self.dead_letters
+            .iter()
+            .filter(|dead_letter| dead_letter.subscriber == subscriber)
+            .cloned()
+            .collect()
+    }
+}
+
+/// Event store kept in memory, for tests and single-process deployments
+#[derive(Debug, Default)]
+pub struct InMemoryEventStore {
+    state: RwLock<StoreState>,
+}
+
+impl InMemoryEventStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl EventStore for InMemoryEventStore {
+    async fn append(&self, mut envelope: EventEnvelope) -> Result<Appended> {
+        let mut state = self.state.write().await;
+        if let Some(existing) = state.duplicate_of(&envelope) {
+            return Ok(Appended::Duplicate(existing));
+        }
+        envelope.sequence = state.next_sequence();
+        state.push(envelope.clone());
+        Ok(Appended::New(envelope))
+    }
+
+    async fn read(&self, from: ReplayFrom, limit: usize) -> Result<Vec<EventEnvelope>> {
+        Ok(self.state.read().await.read(from, limit))
+    }
+
+    async fn load_checkpoint(&self, subscriber: &str) -> Result<Option<u64>> {
+        Ok(self.state.read().await.checkpoints.get(subscriber).copied())
+    }
+
+    async fn save_checkpoint(&self, subscriber: &str, sequence: u64) -> Result<()> {
+        self.state.write().await.checkpoints.insert(subscriber.to_string(), sequence);
+        Ok(())
+    }
+
+    async fn dead_letter(&self, dead_letter: DeadLetter) -> Result<()> {
+        self.state.write().await.dead_letters.push(dead_letter);
+        Ok(())
+    }
+
+    async fn dead_letters(&self, subscriber: &str) -> Result<Vec<DeadLetter>> {
+        Ok(self.state.read().await.dead_letters(subscriber))
+    }
+}
+
+/// Event store backed by an append-only JSON Lines file
+///
+/// Every event is synced to disk before it is delivered. The whole log is
+/// also kept in memory to serve replays.
+pub struct FileEventStore {
+    dir: PathBuf,
+    state: RwLock<StoreState>,
+    log: Mutex<tokio::fs::File>,
+}
+
+impl FileEventStore {
+    /// Open the store in `dir`, creating it if needed
+    pub async fn open(dir: impl Into<PathBuf>) -> Result<Self> {
+        let dir = dir.into();
+        tokio::fs::create_dir_all(&dir)
+            .await
+            .with_context(|| format!("Cannot create event store directory {}", dir.display()))?;
+
+        let mut state = StoreState::default();
+        let events_path = dir.join(EVENTS_FILE);
+        if let Ok(content) = tokio::fs::read_to_string(&events_path).await {
+            for (line_number, line) in content.lines().enumerate() {
+                if line.trim().is_empty() {
+                    continue;
+                }
+                match serde_json::from_str::<EventEnvelope>(line) {
+                    Ok(envelope) => state.push(envelope),
+                    // A crash mid-write can leave a truncated last line
+                    Err(e) => tracing::warn!(
+                        "Skipping unreadable event at {}:{}: {}",
+                        events_path.display(),
+                        line_number + 1,
+                        e
+                    ),
+                }
+            }
+        }
+
+        if let Ok(content) = tokio::fs::read_to_string(dir.join(DEAD_LETTERS_FILE)).await {
+            for line in content.lines().filter(|line| !line.trim().is_empty()) {
+                match serde_json::from_str::<DeadLetter>(line) {
+                    Ok(dead_letter) => state.dead_letters.push(dead_letter),
+                    Err(e) => tracing::warn!("Skipping unreadable dead letter in {}: {}", dir.display(), e),
+                }
+            }
+        }
+
+        if let Ok(content) = tokio::fs::read_to_string(dir.join(CHECKPOINTS_FILE)).await {
+            state.checkpoints = serde_json::from_str(&content)
+                .with_context(|| format!("Corrupt checkpoints in {}", dir.display()))?;
+        }
+
+        let log = tokio::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&events_path)
+            .await
+            .with_context(|| format!("Cannot open {}", events_path.display()))?;
+
+        tracing::info!("Opened event store {} with {} events", dir.display(), state.events.len());
+        Ok(Self {
+            dir,
+            state: RwLock::new(state),
+            log: Mutex::new(log),
+        })
+    }
+}
+
+#[async_trait]
+impl EventStore for FileEventStore {
+    async fn append(&self, mut envelope: EventEnvelope) -> Result<Appended> {
+        let mut state = self.state.write().await;
+        if let Some(existing) = state.duplicate_of(&envelope) {
+            return Ok(Appended::Duplicate(existing));
+        }
+        envelope.sequence = state.next_sequence();
+
+        let mut line = serde_json::to_vec(&envelope)?;
+        line.push(b'\n');
+        let mut log = self.log.lock().await;
+        log.write_all(&line).await?;
+        log.sync_data().await?;
+
+        state.push(envelope.clone());
+        Ok(Appended::New(envelope))
+    }
+
+    async fn read(&self, from: ReplayFrom, limit: usize) -> Result<Vec<EventEnvelope>> {
+        Ok(self.state.read().await.read(from, limit))
+    }
+
+    async fn load_checkpoint(&self, subscriber: &str) -> Result<Option<u64>> {
+        Ok(self.state.read().await.checkpoints.get(subscriber).copied())
+    }
+
+    async fn save_checkpoint(&self, subscriber: &str, sequence: u64) -> Result<()> {
+        let mut state = self.state.write().await;
+        state.checkpoints.insert(subscriber.to_string(), sequence);
+
+        // Write then rename so a crash never leaves a half-written file
+        let path = self.dir.join(CHECKPOINTS_FILE);
+        let temp_path = self.dir.join(format!("{}.tmp", CHECKPOINTS_FILE));
+        tokio::fs::write(&temp_path, serde_json::to_vec(&state.checkpoints)?).await?;
+        tokio::fs::rename(&temp_path, &path).await?;
+        Ok(())
+    }
+    async fn dead_letter(&self, dead_letter: DeadLetter) -> Result<()> {
+        let mut state = self.state.write().await;
+
+        let mut line = serde_json::to_vec(&dead_letter)?;
+        line.push(b'\n');
+        let path = self.dir.join(DEAD_LETTERS_FILE);
+        let mut file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .await
+            .with_context(|| format!("Cannot open {}", path.display()))?;
+        file.write_all(&line).await?;
+        file.sync_data().await?;
+
+        state.dead_letters.push(dead_letter);
+        Ok(())
+    }
+
+    async fn dead_letters(&self, subscriber: &str) -> Result<Vec<DeadLetter>> {
+        Ok(self.state.read().await.dead_letters(subscriber))
+    }
+}
@@ -1,10 +1,19 @@
-use crate::PlatformConfig;
-use anyhow::Result;
+use crate::{Appended, DeadLetter, EventStore, PlatformConfig, ReplayFrom};
+use anyhow::{anyhow, bail, Context, Result};
 use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use futures::future::BoxFuture;
+use futures::FutureExt;
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::future::Future;
+use std::panic::AssertUnwindSafe;
 use std::sync::Arc;
-use tokio::sync::{broadcast, RwLock};
+use std::time::Duration;
+use tokio::sync::{broadcast, mpsc, RwLock};
+use tokio::task::JoinHandle;
+use uuid::Uuid;
 
 #[async_trait]
 pub trait EventHandler: Send + Sync {
@@ -95,4 +104,794 @@ impl EventBus {
     pub fn subscribe(&self) -> broadcast::Receiver<PlatformEvent> {
         self.event_sender.subscribe()
     }
-}
\ No newline at end of file
+}
+
+/// A typed event payload published on a [`TypedEventBus`]
+pub trait Event: Serialize + DeserializeOwned + Clone + Send + Sync + 'static {
+    /// Name the event is published, stored and subscribed under
+    const NAME: &'static str;
+    /// Payload schema version, bumped on incompatible changes
+    const VERSION: u32 = 1;
+}
+
+/// An event as published, stored and replayed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EventEnvelope {
+    pub id: Uuid,
+    pub name: String,
+    pub version: u32,
+    /// Position in the event store; 0 when the bus has no store
+    pub sequence: u64,
+    pub timestamp: DateTime<Utc>,
+    /// Events sharing a key are stored and delivered once
+    pub dedup_key: Option<String>,
+    pub payload: serde_json::Value,
+}
+
+impl EventEnvelope {
+    pub fn new(name: impl Into<String>, version: u32, payload: serde_json::Value) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            name: name.into(),
+            version,
+            sequence: 0,
+            timestamp: Utc::now(),
+            dedup_key: None,
+            payload,
+        }
+    }
+
+    pub fn with_dedup_key(mut self, dedup_key: impl Into<String>) -> Self {
+        self.dedup_key = Some(dedup_key.into());
+        self
+    }
+
+    /// Deserialize the payload as `T`
+    pub fn decode<T: Event>(&self) -> Result<TypedEvent<T>> {
+        if self.name != T::NAME {
+            bail!("Event '{}' is not a '{}' event", self.name, T::NAME);
+        }
+        Ok(TypedEvent {
+            id: self.id,
+            sequence: self.sequence,
+            timestamp: self.timestamp,
+            payload: serde_json::from_value(self.payload.clone())?,
+        })
+    }
+}
+
+/// An event delivered to a typed subscriber
+#[derive(Debug, Clone)]
+pub struct TypedEvent<T> {
+    pub id: Uuid,
+    pub sequence: u64,
+    pub timestamp: DateTime<Utc>,
+    pub payload: T,
+}
+
+type PayloadValidator = Arc<dyn Fn(&serde_json::Value) -> Result<()> + Send + Sync>;
+
+/// Payload schema registered for an event name
+#[derive(Clone)]
+pub struct EventSchema {
+    pub name: String,
+    pub version: u32,
+    /// Rust type of the payload, or "dynamic" for schemas defined at runtime
+    pub type_name: &'static str,
+    validator: PayloadValidator,
+}
+
+impl EventSchema {
+    pub fn of<T: Event>() -> Self {
+        Self {
+            name: T::NAME.to_string(),
+            version: T::VERSION,
+            type_name: std::any::type_name::<T>(),
+            validator: Arc::new(|payload| {
+                serde_json::from_value::<T>(payload.clone())?;
+                Ok(())
+            }),
+        }
+    }
+
+    /// Schema of an event without a Rust type, e.g. one defined by a plugin
+    pub fn dynamic(
+        name: impl Into<String>,
+        version: u32,
+        validator: impl Fn(&serde_json::Value) -> Result<()> + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            version,
+            type_name: "dynamic",
+            validator: Arc::new(validator),
+        }
+    }
+
+    pub fn validate(&self, payload: &serde_json::Value) -> Result<()> {
+        (self.validator)(payload).map_err(|e| anyhow!("Invalid '{}' v{} payload: {}", self.name, self.version, e))
+    }
+}
+
+impl std::fmt::Debug for EventSchema {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EventSchema")
+            .field("name", &self.name)
+            .field("version", &self.version)
+            .field("type_name", &self.type_name)
+            .finish()
+    }
+}
+
+/// Maps event names to their payload schemas
+#[derive(Debug, Default)]
+pub struct EventRegistry {
+    schemas: HashMap<String, EventSchema>,
+}
+
+impl EventRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a schema; registering the same schema again is a no-op, a
+    /// different one under the same name is an error
+    pub fn register(&mut self, schema: EventSchema) -> Result<()> {
+        if let Some(existing) = self.schemas.get(&schema.name) {
+            if existing.type_name != schema.type_name || existing.version != schema.version {
+                bail!(
+                    "Event '{}' is already registered as {} v{}",
+                    schema.name,
+                    existing.type_name,
+                    existing.version
+                );
+            }
+            return Ok(());
+        }
+        self.schemas.insert(schema.name.clone(), schema);
+        Ok(())
+    }
+
+    pub fn register_type<T: Event>(&mut self) -> Result<()> {
+        self.register(EventSchema::of::<T>())
+    }
+
+    pub fn get(&self, name: &str) -> Option<&EventSchema> {
+        self.schemas.get(name)
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.schemas.keys().map(String::as_str)
+    }
+
+    /// Check an envelope against the schema registered for its name
+    pub fn validate(&self, envelope: &EventEnvelope) -> Result<()> {
+        let schema = self
+            .schemas
+            .get(&envelope.name)
+            .ok_or_else(|| anyhow!("Event '{}' is not registered", envelope.name))?;
+        if schema.version != envelope.version {
+            bail!(
+                "Event '{}' has version {}, but v{} is registered",
+                envelope.name,
+                envelope.version,
+                schema.version
+            );
+        }
+        schema.validate(&envelope.payload)
+    }
+}
+
+type EnvelopeHandler = Arc<dyn Fn(EventEnvelope) -> BoxFuture<'static, Result<()>> + Send + Sync>;
+
+/// Subscribe to every event name
+pub const ALL_EVENTS: &str = "*";
+
+struct SubscriberEntry {
+    id: Uuid,
+    name: String,
+    event_name: String,
+    durable: bool,
+    sender: mpsc::UnboundedSender<EventEnvelope>,
+}
+
+impl SubscriberEntry {
+    fn accepts(&self, envelope: &EventEnvelope) -> bool {
+        self.event_name == ALL_EVENTS || self.event_name == envelope.name
+    }
+}
+
+/// Handle of a subscriber; pass it to [`TypedEventBus::unsubscribe`] to stop it
+#[derive(Debug)]
+pub struct Subscription {
+    id: Uuid,
+    name: String,
+    task: JoinHandle<()>,
+}
+
+impl Subscription {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+/// Retries of durable deliveries whose handler fails or panics
+///
+/// After the last attempt the event is recorded as a [`DeadLetter`] in the
+/// store and the subscriber moves on. If the dead letter cannot be written
+/// the subscriber stops without advancing its checkpoint, so the event is
+/// delivered again after a restart rather than lost.
+#[derive(Debug, Clone)]
+pub struct DeliveryPolicy {
+    pub max_attempts: u32,
+    /// Delay before the first retry, doubled for each further one
+    pub initial_backoff: Duration,
+}
+
+impl Default for DeliveryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 5,
+            initial_backoff: Duration::from_millis(100),
+        }
+    }
+}
+
+/// Publish/subscribe of typed events with an optional durable store
+///
+/// Each subscriber runs in its own task and handles its events in order, so
+/// a slow or panicking handler never affects the publisher or other
+/// subscribers. With a store, events are persisted before delivery, publishes
+/// are deduplicated by key, and durable subscribers get at-least-once
+/// delivery: they resume from their checkpoint after a restart and may see
+/// an event again if they stopped while handling it.
+pub struct TypedEventBus {
+    registry: RwLock<EventRegistry>,
+    subscribers: RwLock<Vec<SubscriberEntry>>,
+    store: Option<Arc<dyn EventStore>>,
+    delivery_policy: DeliveryPolicy,
+}
+
+impl TypedEventBus {
+    pub fn new() -> Self {
+        Self {
+            registry: RwLock::new(EventRegistry::new()),
+            subscribers: RwLock::new(Vec::new()),
+            store: None,
+            delivery_policy: DeliveryPolicy::default(),
+        }
+    }
+
+    pub fn with_store(mut self, store: Arc<dyn EventStore>) -> Self {
+        self.store = Some(store);
+        self
+    }
+
+    pub fn with_delivery_policy(mut self, delivery_policy: DeliveryPolicy) -> Self {
+        self.delivery_policy = delivery_policy;
+        self
+    }
+
+    pub fn store(&self) -> Option<&Arc<dyn EventStore>> {
+        self.store.as_ref()
+    }
+
+    /// Register a schema for events published as raw envelopes
+    pub async fn register_schema(&self, schema: EventSchema) -> Result<()> {
+        self.registry.write().await.register(schema)
+    }
+
+    pub async fn schema(&self, name: &str) -> Option<EventSchema> {
+        self.registry.read().await.get(name).cloned()
+    }
+
+    pub async fn publish<T: Event>(&self, event: T) -> Result<EventEnvelope> {
+        self.publish_typed(event, None).await
+    }
+
+    /// Publish unless an event with the same key was already stored
+    ///
+    /// Lets publishers retry safely; deduplication needs an event store.
+    pub async fn publish_with_dedup_key<T: Event>(&self, event: T, dedup_key: impl Into<String>) -> Result<EventEnvelope> {
+        self.publish_typed(event, Some(dedup_key.into())).await
+    }
+
+    async fn publish_typed<T: Event>(&self, event: T, dedup_key: Option<String>) -> Result<EventEnvelope> {
+        self.registry.write().await.register_type::<T>()?;
+        let mut envelope = EventEnvelope::new(T::NAME, T::VERSION, serde_json::to_value(&event)?);
+        envelope.dedup_key = dedup_key;
+        self.dispatch(envelope).await
+    }
+
+    /// Publish an untyped event after validating it against its registered schema
+    pub async fn publish_envelope(&self, envelope: EventEnvelope) -> Result<EventEnvelope> {
+        self.registry.read().await.validate(&envelope)?;
+        self.dispatch(envelope).await
+    }
+
+    async fn dispatch(&self, envelope: EventEnvelope) -> Result<EventEnvelope> {
+        let envelope = match &self.store {
+            Some(store) => match store.append(envelope).await? {
+                Appended::New(envelope) => envelope,
+                Appended::Duplicate(existing) => {
+                    tracing::debug!("Dropping duplicate '{}' event {}", existing.name, existing.id);
+                    return Ok(existing);
+                }
+            },
+            None => envelope,
+        };
+
+        let subscribers = self.subscribers.read().await;
+        for subscriber in subscribers.iter().filter(|subscriber| subscriber.accepts(&envelope)) {
+            // A closed channel means the subscriber is shutting down
+            let _ = subscriber.sender.send(envelope.clone());
+        }
+        Ok(envelope)
+    }
+
+    /// Handle events of type `T` published from now on
+    pub async fn subscribe<T, F, Fut>(&self, name: impl Into<String>, handler: F) -> Result<Subscription>
+    where
+        T: Event,
+        F: Fn(TypedEvent<T>) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<()>> + Send + 'static,
+    {
+        self.registry.write().await.register_type::<T>()?;
+        self.add_subscriber(name.into(), T::NAME.to_string(), false, typed_handler(handler)).await
+    }
+
+    /// Handle every event of type `T`, resuming after the last one handled
+    ///
+    /// The subscriber's progress is checkpointed under `name` in the event
+    /// store, so `name` must be stable across restarts.
+    pub async fn subscribe_durable<T, F, Fut>(&self, name: impl Into<String>, handler: F) -> Result<Subscription>
+    where
+        T: Event,
+        F: Fn(TypedEvent<T>) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<()>> + Send + 'static,
+    {
+        self.registry.write().await.register_type::<T>()?;
+        self.add_subscriber(name.into(), T::NAME.to_string(), true, typed_handler(handler)).await
+    }
+
+    /// Handle raw events named `event_name`, or all events with [`ALL_EVENTS`]
+    pub async fn subscribe_envelopes<F, Fut>(
+        &self,
+        name: impl Into<String>,
+        event_name: impl Into<String>,
+        durable: bool,
+        handler: F,
+    ) -> Result<Subscription>
+    where
+        F: Fn(EventEnvelope) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<()>> + Send + 'static,
+    {
+        let handler: EnvelopeHandler = Arc::new(move |envelope| handler(envelope).boxed());
+        self.add_subscriber(name.into(), event_name.into(), durable, handler).await
+    }
+
+    async fn add_subscriber(
+        &self,
+        name: String,
+        event_name: String,
+        durable: bool,
+        handler: EnvelopeHandler,
+    ) -> Result<Subscription> {
+        let store = match (durable, &self.store) {
+            (false, _) => None,
+            (true, Some(store)) => Some(store.clone()),
+            (true, None) => bail!("Durable subscriber '{}' needs an event store", name),
+        };
+
+        let mut subscribers = self.subscribers.write().await;
+        if durable && subscribers.iter().any(|subscriber| subscriber.durable && subscriber.name == name) {
+            bail!("Durable subscriber '{}' is already running", name);
+        }
+
+        // Registered before the replay starts so no event falls in between
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let id = Uuid::new_v4();
+        subscribers.push(SubscriberEntry {
+            id,
+            name: name.clone(),
+            event_name: event_name.clone(),
+            durable,
+            sender,
+        });
+
+        let worker = SubscriberWorker {
+            name: name.clone(),
+            event_name,
+            handler,
+            store,
+            policy: self.delivery_policy.clone(),
+        };
+        let task = tokio::spawn(worker.run(receiver));
+
+        tracing::info!("Registered event subscriber: {}", name);
+        Ok(Subscription { id, name, task })
+    }
+
+    /// Stop a subscriber after it has handled the events already sent to it
+    pub async fn unsubscribe(&self, subscription: Subscription) {
+        self.subscribers.write().await.retain(|subscriber| subscriber.id != subscription.id);
+        if let Err(e) = subscription.task.await {
+            tracing::error!("Event subscriber {} ended abnormally: {}", subscription.name, e);
+        }
+    }
+
+    /// Feed stored events of type `T` from `from` on to `handler`, e.g. to
+    /// rebuild a projection; returns how many were replayed
+    pub async fn replay<T, F, Fut>(&self, from: ReplayFrom, mut handler: F) -> Result<u64>
+    where
+        T: Event,
+        F: FnMut(TypedEvent<T>) -> Fut,
+        Fut: Future<Output = Result<()>>,
+    {
+        self.replay_envelopes(from, |envelope| {
+            let event = if envelope.name == T::NAME { Some(envelope.decode::<T>()) } else { None };
+            let handled = event.map(|event| event.map(&mut handler));
+            async move {
+                match handled {
+                    Some(Ok(future)) => future.await.map(|_| true),
+                    Some(Err(e)) => Err(e),
+                    None => Ok(false),
+                }
+            }
+        })
+        .await
+    }
+
+    /// Feed every stored event from `from` on to `handler`; it returns whether
+    /// it used the event, and the count of used events is returned
+    pub async fn replay_envelopes<F, Fut>(&self, from: ReplayFrom, mut handler: F) -> Result<u64>
+    where
+        F: FnMut(EventEnvelope) -> Fut,
+        Fut: Future<Output = Result<bool>>,
+    {
+        const BATCH_SIZE: usize = 512;
+
+        let store = self.store.as_ref().ok_or_else(|| anyhow!("Replaying events needs an event store"))?;
+        let mut from = from;
+        let mut replayed = 0;
+        loop {
+            let batch = store.read(from, BATCH_SIZE).await?;
+            let Some(last) = batch.last().map(|envelope| envelope.sequence) else { break };
+            for envelope in batch {
+                if handler(envelope).await? {
+                    replayed += 1;
+                }
+            }
+            from = ReplayFrom::Sequence(last + 1);
+        }
+        Ok(replayed)
+    }
+}
+
+impl Default for TypedEventBus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn typed_handler<T, F, Fut>(handler: F) -> EnvelopeHandler
+where
+    T: Event,
+    F: Fn(TypedEvent<T>) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = Result<()>> + Send + 'static,
+{
+    let handler = Arc::new(handler);
+    Arc::new(move |envelope: EventEnvelope| {
+        let handler = handler.clone();
+        async move { handler(envelope.decode::<T>()?).await }.boxed()
+    })
+}
+
+/// Delivers one subscriber's events in order
+struct SubscriberWorker {
+    name: String,
+    event_name: String,
+    handler: EnvelopeHandler,
+    /// Set for durable subscribers
+    store: Option<Arc<dyn EventStore>>,
+    policy: DeliveryPolicy,
+}
+
+impl SubscriberWorker {
+    async fn run(self, mut receiver: mpsc::UnboundedReceiver<EventEnvelope>) {
+        let mut last_handled = 0;
+
+        if let Some(store) = &self.store {
+            match self.catch_up(store.as_ref()).await {
+                Ok(sequence) => last_handled = sequence,
+                Err(e) => {
+                    tracing::error!("Event subscriber {} could not catch up: {}", self.name, e);
+                    return;
+                }
+            }
+        }
+
+        while let Some(envelope) = receiver.recv().await {
+            if self.store.is_some() && envelope.sequence <= last_handled {
+                continue;
+            }
+            if let Err(e) = self.handle(&envelope).await {
+                tracing::error!("Event subscriber {} stopped at event {}: {}", self.name, envelope.sequence, e);
+                return;
+            }
+            if let Some(store) = &self.store {
+                last_handled = envelope.sequence;
+                if let Err(e) = store.save_checkpoint(&self.name, last_handled).await {
+                    tracing::error!("Event subscriber {} could not save its checkpoint: {}", self.name, e);
+                }
+            }
+        }
+    }
+
+    /// Deliver stored events after the checkpoint; returns the last sequence handled
+    async fn catch_up(&self, store: &dyn EventStore) -> Result<u64> {
+        const BATCH_SIZE: usize = 512;
+
+        let mut last_handled = store.load_checkpoint(&self.name).await?.unwrap_or(0);
+        loop {
+            let batch = store.read(ReplayFrom::Sequence(last_handled + 1), BATCH_SIZE).await?;
+            if batch.is_empty() {
+                return Ok(last_handled);
+            }
+            for envelope in batch {
+                if self.event_name == ALL_EVENTS || envelope.name == self.event_name {
+                    if let Err(e) = self.handle(&envelope).await {
+                        store.save_checkpoint(&self.name, last_handled).await?;
+                        return Err(e);
+                    }
+                }
+                last_handled = envelope.sequence;
+            }
+            store.save_checkpoint(&self.name, last_handled).await?;
+        }
+    }
+
+    /// Deliver an event; fails only when a durable subscriber must not move
+    /// past it because its dead letter could not be stored
+    async fn handle(&self, envelope: &EventEnvelope) -> Result<()> {
+        let Err(error) = self.deliver(envelope).await else { return Ok(()) };
+        let Some(store) = &self.store else { return Ok(()) };
+
+        store
+            .dead_letter(DeadLetter {
+                subscriber: self.name.clone(),
+                envelope: envelope.clone(),
+                error,
+                failed_at: Utc::now(),
+            })
+            .await
+            .with_context(|| format!("Cannot dead-letter '{}' event {}", envelope.name, envelope.id))
+    }
+
+    /// Run the handler, isolating panics; durable subscribers retry failures.
+    /// Returns the last error once the attempts are exhausted.
+    async fn deliver(&self, envelope: &EventEnvelope) -> std::result::Result<(), String> {
+        let max_attempts = if self.store.is_some() { self.policy.max_attempts.max(1) } else { 1 };
+        let mut backoff = self.policy.initial_backoff;
+
+        for attempt in 1..=max_attempts {
+            let handler = self.handler.clone();
+            let event = envelope.clone();
+            let outcome = AssertUnwindSafe(async move { handler(event).await }).catch_unwind().await;
+            let error = match outcome {
+                Ok(Ok(())) => return Ok(()),
+                Ok(Err(e)) => e.to_string(),
+                Err(panic) => format!("handler panicked: {}", panic_message(panic.as_ref())),
+            };
+
+            if attempt < max_attempts {
+                tracing::warn!(
+                    "Event subscriber {} failed on '{}' event {} (attempt {}/{}): {}",
+                    self.name, envelope.name, envelope.id, attempt, max_attempts, error
+                );
+                tokio::time::sleep(backoff).await;
+                backoff *= 2;
+            } else {
+                tracing::error!(
+                    "Event subscriber {} gave up on '{}' event {}: {}",
+                    self.name, envelope.name, envelope.id, error
+                );
+                return Err(error);
+            }
+        }
+        Ok(())
+    }
+}
+
+fn panic_message(panic: &(dyn std::any::Any + Send)) -> &str {
+    panic
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| panic.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("non-string panic payload")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::InMemoryEventStore;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Mutex;
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    struct Pinged {
+        n: u32,
+    }
+
+    impl Event for Pinged {
+        const NAME: &'static str = "test.pinged";
+    }
+
+    fn quick_retries() -> DeliveryPolicy {
+        DeliveryPolicy {
+            max_attempts: 2,
+            initial_backoff: Duration::from_millis(1),
+        }
+    }
+
+    /// Store whose dead letters cannot be written
+    struct NoDeadLetters(InMemoryEventStore);
+
+    #[async_trait]
+    impl EventStore for NoDeadLetters {
+        async fn append(&self, envelope: EventEnvelope) -> Result<Appended> {
+            self.0.append(envelope).await
+        }
+
+        async fn read(&self, from: ReplayFrom, limit: usize) -> Result<Vec<EventEnvelope>> {
+            self.0.read(from, limit).await
+        }
+
+        async fn load_checkpoint(&self, subscriber: &str) -> Result<Option<u64>> {
+            self.0.load_checkpoint(subscriber).await
+        }
+
+        async fn save_checkpoint(&self, subscriber: &str, sequence: u64) -> Result<()> {
+            self.0.save_checkpoint(subscriber, sequence).await
+        }
+
+        async fn dead_letter(&self, _dead_letter: DeadLetter) -> Result<()> {
+            bail!("dead letter storage is unavailable")
+        }
+
+        async fn dead_letters(&self, _subscriber: &str) -> Result<Vec<DeadLetter>> {
+            Ok(Vec::new())
+        }
+    }
+
+    #[tokio::test]
+    async fn panicking_handler_does_not_affect_other_subscribers() {
+        let bus = TypedEventBus::new();
+        let handled = Arc::new(AtomicUsize::new(0));
+
+        let panicking = bus
+            .subscribe("panicking", |event: TypedEvent<Pinged>| async move {
+                assert!(event.payload.n > 100, "handler bug");
+                Ok(())
+            })
+            .await
+            .unwrap();
+        let counter = handled.clone();
+        let counting = bus
+            .subscribe("counting", move |_event: TypedEvent<Pinged>| {
+                let counter = counter.clone();
+                async move {
+                    counter.fetch_add(1, Ordering::SeqCst);
+                    Ok(())
+                }
+            })
+            .await
+            .unwrap();
+
+        for n in 0..3 {
+            bus.publish(Pinged { n }).await.unwrap();
+        }
+        bus.unsubscribe(panicking).await;
+        bus.unsubscribe(counting).await;
+
+        assert_eq!(handled.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn duplicate_publishes_are_stored_and_delivered_once() {
+        let store = Arc::new(InMemoryEventStore::new());
+        let bus = TypedEventBus::new().with_store(store.clone());
+        let handled = Arc::new(AtomicUsize::new(0));
+
+        let counter = handled.clone();
+        let subscription = bus
+            .subscribe_durable("projection", move |_event: TypedEvent<Pinged>| {
+                let counter = counter.clone();
+                async move {
+                    counter.fetch_add(1, Ordering::SeqCst);
+                    Ok(())
+                }
+            })
+            .await
+            .unwrap();
+
+        let first = bus.publish_with_dedup_key(Pinged { n: 1 }, "order-1").await.unwrap();
+        let second = bus.publish_with_dedup_key(Pinged { n: 2 }, "order-1").await.unwrap();
+        bus.unsubscribe(subscription).await;
+
+        assert_eq!(first.id, second.id);
+        assert_eq!(store.read(ReplayFrom::Beginning, 10).await.unwrap().len(), 1);
+        assert_eq!(handled.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn durable_subscriber_resumes_after_its_checkpoint() {
+        let store = Arc::new(InMemoryEventStore::new());
+        let bus = TypedEventBus::new().with_store(store.clone());
+        for n in 1..=3 {
+            bus.publish(Pinged { n }).await.unwrap();
+        }
+        store.save_checkpoint("projection", 2).await.unwrap();
+
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let sink = seen.clone();
+        let subscription = bus
+            .subscribe_durable("projection", move |event: TypedEvent<Pinged>| {
+                sink.lock().unwrap().push(event.payload.n);
+                async { Ok(()) }
+            })
+            .await
+            .unwrap();
+        bus.publish(Pinged { n: 4 }).await.unwrap();
+        bus.unsubscribe(subscription).await;
+
+        assert_eq!(*seen.lock().unwrap(), vec![3, 4]);
+        assert_eq!(store.load_checkpoint("projection").await.unwrap(), Some(4));
+    }
+
+    #[tokio::test]
+    async fn exhausted_retries_are_dead_lettered_before_the_checkpoint_moves() {
+        let store = Arc::new(InMemoryEventStore::new());
+        let bus = TypedEventBus::new().with_store(store.clone()).with_delivery_policy(quick_retries());
+        let attempts = Arc::new(AtomicUsize::new(0));
+
+        let counter = attempts.clone();
+        let subscription = bus
+            .subscribe_durable("failing", move |_event: TypedEvent<Pinged>| {
+                counter.fetch_add(1, Ordering::SeqCst);
+                async { Err(anyhow!("downstream unavailable")) }
+            })
+            .await
+            .unwrap();
+        let published = bus.publish(Pinged { n: 1 }).await.unwrap();
+        bus.unsubscribe(subscription).await;
+
+        assert_eq!(attempts.load(Ordering::SeqCst), 2);
+        let dead_letters = store.dead_letters("failing").await.unwrap();
+        assert_eq!(dead_letters.len(), 1);
+        assert_eq!(dead_letters[0].envelope.id, published.id);
+        assert!(dead_letters[0].error.contains("downstream unavailable"));
+        assert_eq!(store.load_checkpoint("failing").await.unwrap(), Some(1));
+    }
+
+    #[tokio::test]
+    async fn checkpoint_stays_put_when_the_dead_letter_cannot_be_stored() {
+        let store = Arc::new(NoDeadLetters(InMemoryEventStore::new()));
+        let bus = TypedEventBus::new().with_store(store.clone()).with_delivery_policy(quick_retries());
+        bus.publish(Pinged { n: 1 }).await.unwrap();
+        bus.publish(Pinged { n: 2 }).await.unwrap();
+
+        let subscription = bus
+            .subscribe_durable("failing", |event: TypedEvent<Pinged>| async move {
+                if event.payload.n == 2 {
+                    bail!("cannot handle {}", event.payload.n);
+                }
+                Ok(())
+            })
+            .await
+            .unwrap();
+        bus.unsubscribe(subscription).await;
+
+        assert_eq!(store.load_checkpoint("failing").await.unwrap(), Some(1));
+    }
+}
@@ -1,9 +1,13 @@
 use crate::{PlatformConfig, PlatformService, ServiceHealth, HealthStatus};
 use anyhow::Result;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::future::Future;
 use std::sync::Arc;
-use tokio::sync::RwLock;
-use chrono::Utc;
+use std::time::{Duration, Instant};
+use tokio::sync::{Mutex, RwLock};
+use chrono::{DateTime, Utc};
 
 pub struct HealthChecker {
     config: Arc<PlatformConfig>,
@@ -121,4 +125,258 @@ impl Clone for HealthChecker {
             monitoring_active: self.monitoring_active.clone(),
         }
     }
-}
\ No newline at end of file
+}
+
+/// How a failing component affects overall health
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Criticality {
+    /// The system cannot serve requests without it
+    Critical,
+    /// The system keeps working, degraded, without it
+    NonCritical,
+}
+
+/// A component's health check
+///
+/// Returning an error counts as [`HealthStatus::Unhealthy`]. Implemented for
+/// async closures, so `|| async { Ok(HealthStatus::Healthy) }` is a check.
+#[async_trait]
+pub trait HealthCheck: Send + Sync {
+    async fn check(&self) -> Result<HealthStatus>;
+}
+
+#[async_trait]
+impl<F, Fut> HealthCheck for F
+where
+    F: Fn() -> Fut + Send + Sync,
+    Fut: Future<Output = Result<HealthStatus>> + Send,
+{
+    async fn check(&self) -> Result<HealthStatus> {
+        self().await
+    }
+}
+
+/// Checks a [`PlatformService`] through its own `health_check`
+pub struct PlatformServiceCheck(pub Arc<dyn PlatformService>);
+
+#[async_trait]
+impl HealthCheck for PlatformServiceCheck {
+    async fn check(&self) -> Result<HealthStatus> {
+        Ok(self.0.health_check().await?.status)
+    }
+}
+
+/// Registration settings of a component
+#[derive(Debug, Clone, Copy)]
+pub struct ComponentHealthConfig {
+    pub criticality: Criticality,
+    /// Overrides the registry's check timeout
+    pub timeout: Option<Duration>,
+    /// Overrides the registry's cache TTL
+    pub cache_ttl: Option<Duration>,
+}
+
+impl ComponentHealthConfig {
+    pub fn new(criticality: Criticality) -> Self {
+        Self {
+            criticality,
+            timeout: None,
+            cache_ttl: None,
+        }
+    }
+}
+
+/// Result of one component's check
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ComponentHealth {
+    pub name: String,
+    pub criticality: Criticality,
+    pub status: HealthStatus,
+    pub error: Option<String>,
+    pub response_time_ms: u64,
+    pub checked_at: DateTime<Utc>,
+    /// Whether the result was served from the cache
+    pub cached: bool,
+}
+
+impl ComponentHealth {
+    pub fn is_failing(&self) -> bool {
+        matches!(self.status, HealthStatus::Unhealthy | HealthStatus::Critical | HealthStatus::Unknown)
+    }
+}
+
+/// Aggregated health of every registered component
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HealthReport {
+    pub status: HealthStatus,
+    pub components: Vec<ComponentHealth>,
+    pub checked_at: DateTime<Utc>,
+}
+
+impl HealthReport {
+    /// Ready unless a critical component is failing
+    pub fn is_ready(&self) -> bool {
+        self.status != HealthStatus::Unhealthy
+    }
+
+    pub fn component(&self, name: &str) -> Option<&ComponentHealth> {
+        self.components.iter().find(|component| component.name == name)
+    }
+}
+
+struct RegisteredComponent {
+    name: String,
+    config: ComponentHealthConfig,
+    check: Arc<dyn HealthCheck>,
+    /// Held while checking, so concurrent reports share one check
+    last_result: Mutex<Option<(Instant, ComponentHealth)>>,
+}
+
+/// Components with criticality-aware health aggregation
+///
+/// Checks run concurrently, each bounded by its timeout, and results are
+/// cached for a TTL so frequent probes don't hammer dependencies. A failing
+/// critical component makes the report unhealthy; a failing non-critical one,
+/// or any degraded component, makes it degraded.
+pub struct HealthRegistry {
+    components: RwLock<Vec<Arc<RegisteredComponent>>>,
+    default_timeout: Duration,
+    default_cache_ttl: Duration,
+}
+
+impl HealthRegistry {
+    pub fn new() -> Self {
+        Self {
+            components: RwLock::new(Vec::new()),
+            default_timeout: Duration::from_secs(5),
+            default_cache_ttl: Duration::from_secs(10),
+        }
+    }
+
+    pub fn with_default_timeout(mut self, timeout: Duration) -> Self {
+        self.default_timeout = timeout;
+        self
+    }
+
+    /// How long a result is reused; zero checks on every report
+    pub fn with_default_cache_ttl(mut self, cache_ttl: Duration) -> Self {
+        self.default_cache_ttl = cache_ttl;
+        self
+    }
+
+    /// Register a component, replacing any component with the same name
+    pub async fn register(
+        &self,
+        name: impl Into<String>,
+        criticality: Criticality,
+        check: impl HealthCheck + 'static,
+    ) {
+        self.register_with(name, ComponentHealthConfig::new(criticality), check).await;
+    }
+
+    pub async fn register_with(
+        &self,
+        name: impl Into<String>,
+        config: ComponentHealthConfig,
+        check: impl HealthCheck + 'static,
+    ) {
+        let component = Arc::new(RegisteredComponent {
+            name: name.into(),
+            config,
+            check: Arc::new(check),
+            last_result: Mutex::new(None),
+        });
+
+        let mut components = self.components.write().await;
+        components.retain(|existing| existing.name != component.name);
+        components.push(component);
+    }
+
+    pub async fn unregister(&self, name: &str) -> bool {
+        let mut components = self.components.write().await;
+        let before = components.len();
+        components.retain(|component| component.name != name);
+        components.len() != before
+    }
+
+    /// Check every component and aggregate the results
+    pub async fn check_all(&self) -> HealthReport {
+        let components = self.components.read().await.clone();
+        let results = futures::future::join_all(
+            components.iter().map(|component| self.check_component(component)),
+        ).await;
+
+        HealthReport {
+            status: aggregate_status(&results),
+            components: results,
+            checked_at: Utc::now(),
+        }
+    }
+
+    /// Check one component, or `None` if it isn't registered
+    pub async fn check(&self, name: &str) -> Option<ComponentHealth> {
+        let component = self
+            .components
+            .read()
+            .await
+            .iter()
+            .find(|component| component.name == name)
+            .cloned()?;
+        Some(self.check_component(&component).await)
+    }
+
+    async fn check_component(&self, component: &RegisteredComponent) -> ComponentHealth {
+        let cache_ttl = component.config.cache_ttl.unwrap_or(self.default_cache_ttl);
+        let mut last_result = component.last_result.lock().await;
+
+        if let Some((checked, health)) = last_result.as_ref() {
+            if checked.elapsed() < cache_ttl {
+                return ComponentHealth { cached: true, ..health.clone() };
+            }
+        }
+
+        let timeout = component.config.timeout.unwrap_or(self.default_timeout);
+        let started = Instant::now();
+        let (status, error) = match tokio::time::timeout(timeout, component.check.check()).await {
+            Ok(Ok(status)) => (status, None),
+            Ok(Err(e)) => (HealthStatus::Unhealthy, Some(e.to_string())),
+            Err(_) => (
+                HealthStatus::Unhealthy,
+                Some(format!("health check timed out after {}ms", timeout.as_millis())),
+            ),
+        };
+
+        let health = ComponentHealth {
+            name: component.name.clone(),
+            criticality: component.config.criticality,
+            status,
+            error,
+            response_time_ms: started.elapsed().as_millis() as u64,
+            checked_at: Utc::now(),
+            cached: false,
+        };
+        *last_result = Some((Instant::now(), health.clone()));
+        health
+    }
+}
+
+impl Default for HealthRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn aggregate_status(components: &[ComponentHealth]) -> HealthStatus {
+    let mut status = HealthStatus::Healthy;
+    for component in components {
+        if component.is_failing() {
+            match component.criticality {
+                Criticality::Critical => return HealthStatus::Unhealthy,
+                Criticality::NonCritical => status = HealthStatus::Degraded,
+            }
+        } else if component.status == HealthStatus::Degraded {
+            status = HealthStatus::Degraded;
+        }
+    }
+    status
+}
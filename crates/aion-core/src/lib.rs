@@ -2,12 +2,16 @@ pub mod platform;
 pub mod enterprise;
 pub mod metrics;
 pub mod events;
+pub mod event_store;
 pub mod cache;
 pub mod health;
+pub mod logging;
 
 pub use platform::*;
 pub use enterprise::*;
 pub use metrics::*;
 pub use events::*;
+pub use event_store::*;
 pub use cache::*;
-pub use health::*;
\ No newline at end of file
+pub use health::*;
+pub use logging::*;
\ No newline at end of file
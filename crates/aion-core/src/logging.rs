@@ -0,0 +1,16 @@
+use std::future::Future;
+
+tokio::task_local! {
+    static CORRELATION_ID: String;
+}
+
+/// Run `future` with `correlation_id` attached, so anything it logs can be
+/// tied back to the request that caused it
+pub async fn with_correlation_id<F: Future>(correlation_id: impl Into<String>, future: F) -> F::Output {
+    CORRELATION_ID.scope(correlation_id.into(), future).await
+}
+
+/// Correlation id of the request the current task is serving, if any
+pub fn current_correlation_id() -> Option<String> {
+    CORRELATION_ID.try_with(|correlation_id| correlation_id.clone()).ok()
+}
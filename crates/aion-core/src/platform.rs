@@ -103,7 +103,7 @@ pub struct ServiceHealth {
     pub dependencies: Vec<DependencyHealth>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum HealthStatus {
     Healthy,
     Degraded,
@@ -0,0 +1,34 @@
+//! Apply pending database migrations, or preview them with `--dry-run`
+
+use aion_database::MigrationManager;
+use anyhow::Result;
+use clap::Parser;
+
+#[derive(Parser)]
+#[command(name = "aion-migrate", about = "Apply AION-R database migrations")]
+struct Cli {
+    /// PostgreSQL connection URL
+    #[arg(long, env = "DATABASE_URL")]
+    database_url: String,
+    /// Print the pending migrations and their SQL without applying them
+    #[arg(long)]
+    dry_run: bool,
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let cli = Cli::parse();
+    let pool = sqlx::postgres::PgPoolOptions::new()
+        .max_connections(1)
+        .connect(&cli.database_url)
+        .await?;
+    let manager = MigrationManager::new(pool);
+
+    if cli.dry_run {
+        // Still verifies checksums, so drift shows up in a dry run too
+        print!("{}", manager.plan().await?);
+        return Ok(());
+    }
+
+    manager.migrate().await
+}
@@ -1,9 +1,11 @@
 pub mod connection;
 pub mod pool;
+pub mod query_tracker;
 pub mod migrations;
 pub mod schema;
 
 pub use connection::*;
 pub use pool::*;
+pub use query_tracker::*;
 pub use migrations::*;
 pub use schema::*;
\ No newline at end of file
@@ -3,7 +3,9 @@
 //! Database migration system for AION-R Enterprise Platform.
 
 use anyhow::{Context, Result};
+use sha2::{Digest, Sha256};
 use sqlx::{Pool, Postgres, Row};
+use std::fmt;
 use tracing::{info, warn};
 
 /// Migration struct containing SQL and metadata
@@ -15,6 +17,108 @@ pub struct Migration {
     pub down_sql: &'static str,
 }
 
+impl Migration {
+    /// SHA-256 of `up_sql`, recorded when the migration is applied
+    pub fn checksum(&self) -> String {
+        Sha256::digest(self.up_sql.as_bytes()).iter().map(|b| format!("{:02x}", b)).collect()
+    }
+}
+
+/// Row of `schema_migrations`
+#[derive(Debug, Clone)]
+pub struct AppliedMigration {
+    pub version: u32,
+    pub name: String,
+    /// Missing for migrations applied before checksums were recorded
+    pub checksum: Option<String>,
+}
+
+/// An applied migration whose SQL has changed since it ran
+#[derive(Debug, Clone, thiserror::Error)]
+#[error("Migration {version} ({name}) was modified after it was applied: checksum {recorded} on record, {current} now")]
+pub struct ChecksumDrift {
+    pub version: u32,
+    pub name: String,
+    pub recorded: String,
+    pub current: String,
+}
+
+/// Migration that would be applied, with the SQL it would run
+#[derive(Debug, Clone)]
+pub struct PlannedMigration {
+    pub version: u32,
+    pub name: String,
+    pub checksum: String,
+    pub sql: String,
+}
+
+/// Pending migrations, in the order they would be applied
+#[derive(Debug, Clone)]
+pub struct MigrationPlan {
+    pub current_version: u32,
+    pub pending: Vec<PlannedMigration>,
+}
+
+impl MigrationPlan {
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+}
+
+impl fmt::Display for MigrationPlan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.pending.is_empty() {
+            return writeln!(f, "Schema is at version {}; no pending migrations", self.current_version);
+        }
+        writeln!(f, "Schema is at version {}; {} pending migrations", self.current_version, self.pending.len())?;
+        for migration in &self.pending {
+            writeln!(f, "\n-- Migration {}: {} (checksum {})", migration.version, migration.name, migration.checksum)?;
+            writeln!(f, "{}", migration.sql.trim())?;
+        }
+        Ok(())
+    }
+}
+
+/// Check `applied` against the checksums of `migrations` and list the ones
+/// still to apply. Fails with `ChecksumDrift` if an applied migration has
+/// changed; migrations applied before checksums were recorded are not checked.
+pub fn plan(migrations: &[Migration], applied: &[AppliedMigration]) -> Result<MigrationPlan> {
+    for record in applied {
+        let Some(migration) = migrations.iter().find(|m| m.version == record.version) else {
+            warn!("Applied migration {} ({}) is not known to this build", record.version, record.name);
+            continue;
+        };
+        let current = migration.checksum();
+        match &record.checksum {
+            Some(recorded) if *recorded != current => {
+                return Err(ChecksumDrift {
+                    version: migration.version,
+                    name: migration.name.clone(),
+                    recorded: recorded.clone(),
+                    current,
+                }
+                .into());
+            }
+            Some(_) => {}
+            None => warn!("Migration {} ({}) has no recorded checksum", record.version, record.name),
+        }
+    }
+
+    let current_version = applied.iter().map(|record| record.version).max().unwrap_or(0);
+    let pending = migrations
+        .iter()
+        .filter(|m| m.version > current_version)
+        .map(|m| PlannedMigration {
+            version: m.version,
+            name: m.name.clone(),
+            checksum: m.checksum(),
+            sql: m.up_sql.to_string(),
+        })
+        .collect();
+
+    Ok(MigrationPlan { current_version, pending })
+}
+
 /// Migration manager for handling database schema changes
 pub struct MigrationManager {
     pool: Pool<Postgres>,
@@ -35,6 +139,7 @@ impl MigrationManager {
             CREATE TABLE IF NOT EXISTS schema_migrations (
                 version INTEGER PRIMARY KEY,
                 name VARCHAR(255) NOT NULL,
+                checksum VARCHAR(64),
                 applied_at TIMESTAMP WITH TIME ZONE DEFAULT NOW()
             )
             "#,
@@ -43,6 +148,12 @@ impl MigrationManager {
         .await
         .context("Failed to create schema_migrations table")?;
 
+        // Tables created before checksums were recorded
+        sqlx::query("ALTER TABLE schema_migrations ADD COLUMN IF NOT EXISTS checksum VARCHAR(64)")
+            .execute(&self.pool)
+            .await
+            .context("Failed to add checksum column to schema_migrations")?;
+
         info!("Migration system initialized");
         Ok(())
     }
@@ -57,17 +168,38 @@ impl MigrationManager {
         Ok(result.get::<i32, _>("version") as u32)
     }
 
-    /// Run all pending migrations
-    pub async fn migrate(&self) -> Result<()> {
+    /// Migrations recorded in `schema_migrations`
+    pub async fn applied_migrations(&self) -> Result<Vec<AppliedMigration>> {
+        let rows = sqlx::query("SELECT version, name, checksum FROM schema_migrations ORDER BY version")
+            .fetch_all(&self.pool)
+            .await
+            .context("Failed to fetch applied migrations")?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| AppliedMigration {
+                version: row.get::<i32, _>("version") as u32,
+                name: row.get("name"),
+                checksum: row.get("checksum"),
+            })
+            .collect())
+    }
+
+    /// Verify applied migrations and list the pending ones without applying anything
+    pub async fn plan(&self) -> Result<MigrationPlan> {
         self.init().await?;
+        plan(&self.migrations, &self.applied_migrations().await?)
+    }
 
-        let current_version = self.get_current_version().await?;
-        info!("Current schema version: {}", current_version);
+    /// Run all pending migrations, refusing to if an applied one has changed
+    pub async fn migrate(&self) -> Result<()> {
+        let plan = self.plan().await?;
+        info!("Current schema version: {}", plan.current_version);
 
         let pending_migrations: Vec<_> = self
             .migrations
             .iter()
-            .filter(|m| m.version > current_version)
+            .filter(|m| m.version > plan.current_version)
             .collect();
 
         if pending_migrations.is_empty() {
@@ -102,10 +234,11 @@ impl MigrationManager {
 
         // Record migration as applied
         sqlx::query(
-            "INSERT INTO schema_migrations (version, name) VALUES ($1, $2)"
+            "INSERT INTO schema_migrations (version, name, checksum) VALUES ($1, $2, $3)"
         )
         .bind(migration.version as i32)
         .bind(&migration.name)
+        .bind(migration.checksum())
         .execute(&mut *tx)
         .await
         .context("Failed to record migration")?;
@@ -915,4 +1048,46 @@ DROP MATERIALIZED VIEW IF EXISTS daily_usage_summary;
 -- Drop performance tables
 DROP TABLE IF EXISTS model_performance CASCADE;
 DROP TABLE IF EXISTS system_metrics CASCADE;
-"#;
\ No newline at end of file
+"#;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn applied(migrations: &[Migration], through: u32) -> Vec<AppliedMigration> {
+        migrations
+            .iter()
+            .filter(|m| m.version <= through)
+            .map(|m| AppliedMigration {
+                version: m.version,
+                name: m.name.clone(),
+                checksum: Some(m.checksum()),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn plan_lists_pending_sql_and_detects_drift() {
+        let migrations = get_all_migrations();
+        let applied = applied(&migrations, 2);
+
+        let migration_plan = plan(&migrations, &applied).unwrap();
+        assert_eq!(migration_plan.current_version, 2);
+        let versions: Vec<u32> = migration_plan.pending.iter().map(|m| m.version).collect();
+        assert_eq!(versions, vec![3, 4]);
+        assert_eq!(migration_plan.pending[0].sql, MIGRATION_003_UP);
+        assert!(migration_plan.to_string().contains("-- Migration 4: add_performance_tables"));
+
+        // Someone edits migration 2 after it ran
+        let mut edited = migrations.clone();
+        edited[1].up_sql = "CREATE INDEX idx_users_email ON users(email);";
+        let drift = plan(&edited, &applied).unwrap_err().downcast::<ChecksumDrift>().unwrap();
+        assert_eq!(drift.version, 2);
+        assert_eq!(drift.recorded, migrations[1].checksum());
+
+        // Rows from before checksums were recorded are not checked
+        let mut legacy = applied.clone();
+        legacy[1].checksum = None;
+        assert!(plan(&edited, &legacy).is_ok());
+    }
+}
@@ -1 +1,194 @@
-// Database pool management
\ No newline at end of file
+//! # Connection Pool
+//!
+//! PostgreSQL pool that keeps `min_idle` connections open ahead of demand.
+//! sqlx opens them as soon as the pool is created and tops them up again when
+//! a burst of requests has taken them, never going past `max_size`.
+
+use crate::{QueryTracker, DEFAULT_SLOW_QUERY_THRESHOLD, DEFAULT_STATEMENT_TIMEOUT};
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use sqlx::pool::PoolConnection;
+use sqlx::postgres::{PgConnectOptions, PgPool, PgPoolOptions};
+use sqlx::Postgres;
+use std::future::Future;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Pool sizing and timing
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PoolConfig {
+    /// Connections open at once, idle or in use
+    pub max_size: u32,
+    /// Connections kept open ahead of demand, as far as `max_size` allows
+    pub min_idle: u32,
+    pub acquire_timeout: Duration,
+    /// Queries run through `ConnectionPool::track_query` taking longer are logged
+    pub slow_query_threshold: Duration,
+    /// Set as `statement_timeout` on every connection, so PostgreSQL cancels
+    /// statements running longer; `None` leaves the server's setting in place
+    pub statement_timeout: Option<Duration>,
+}
+
+impl Default for PoolConfig {
+    fn default() -> Self {
+        Self {
+            max_size: 50,
+            min_idle: 5,
+            acquire_timeout: Duration::from_secs(30),
+            slow_query_threshold: DEFAULT_SLOW_QUERY_THRESHOLD,
+            statement_timeout: Some(DEFAULT_STATEMENT_TIMEOUT),
+        }
+    }
+}
+
+impl PoolConfig {
+    fn connect_options(&self, database_url: &str) -> Result<PgConnectOptions> {
+        let options = PgConnectOptions::from_str(database_url)?;
+        Ok(match self.statement_timeout {
+            Some(timeout) => options.options([("statement_timeout", format!("{}ms", timeout.as_millis()))]),
+            None => options,
+        })
+    }
+
+    fn pool_options(&self) -> PgPoolOptions {
+        PgPoolOptions::new()
+            .max_connections(self.max_size)
+            .min_connections(self.min_idle.min(self.max_size))
+            .acquire_timeout(self.acquire_timeout)
+    }
+}
+
+/// Point-in-time pool statistics
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DatabaseMetrics {
+    /// Connections checked out
+    pub active: usize,
+    pub idle: usize,
+    /// Connections open, idle or in use
+    pub size: usize,
+    pub max_size: usize,
+    /// Queries slower than `slow_query_threshold`, timed out ones included
+    pub slow_queries: u64,
+    pub timed_out_queries: u64,
+}
+
+#[derive(Clone)]
+pub struct ConnectionPool {
+    pool: PgPool,
+    config: PoolConfig,
+    query_tracker: Arc<QueryTracker>,
+}
+
+impl ConnectionPool {
+    /// Connect to `database_url`, opening the `min_idle` connections before returning
+    pub async fn connect(database_url: &str, config: PoolConfig) -> Result<Self> {
+        let pool = config.pool_options().connect_with(config.connect_options(database_url)?).await?;
+        Ok(Self::from_pool(pool, config))
+    }
+
+    /// Create the pool without waiting for a connection; the `min_idle`
+    /// connections are opened in the background. Must be called within a
+    /// Tokio runtime.
+    pub fn connect_lazy(database_url: &str, config: PoolConfig) -> Result<Self> {
+        let pool = config.pool_options().connect_lazy_with(config.connect_options(database_url)?);
+        Ok(Self::from_pool(pool, config))
+    }
+
+    fn from_pool(pool: PgPool, config: PoolConfig) -> Self {
+        let query_tracker = Arc::new(QueryTracker::new(config.slow_query_threshold));
+        Self { pool, config, query_tracker }
+    }
+
+    pub fn pool(&self) -> &PgPool {
+        &self.pool
+    }
+
+    /// Run `query` under the pool's slow query threshold
+    pub async fn track_query<T, F>(&self, name: &str, query: F) -> Result<T>
+    where
+        F: Future<Output = Result<T>>,
+    {
+        self.query_tracker.track(name, query).await
+    }
+
+    /// Check out a connection, waiting up to `acquire_timeout` while
+    /// `max_size` connections are in use
+    pub async fn acquire(&self) -> Result<PoolConnection<Postgres>> {
+        self.pool.acquire().await.map_err(|e| match e {
+            sqlx::Error::PoolTimedOut => anyhow!(
+                "Timed out after {:?} waiting for a database connection",
+                self.config.acquire_timeout
+            ),
+            e => e.into(),
+        })
+    }
+
+    pub fn metrics(&self) -> DatabaseMetrics {
+        let size = self.pool.size() as usize;
+        let idle = self.pool.num_idle();
+        DatabaseMetrics {
+            active: size.saturating_sub(idle),
+            idle,
+            size,
+            max_size: self.config.max_size as usize,
+            slow_queries: self.query_tracker.slow_query_count(),
+            timed_out_queries: self.query_tracker.timed_out_query_count(),
+        }
+    }
+
+    pub async fn close(&self) {
+        self.pool.close().await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn config_maps_onto_pool_options() {
+        let options = PoolConfig {
+            max_size: 8,
+            min_idle: 3,
+            acquire_timeout: Duration::from_millis(250),
+            ..PoolConfig::default()
+        }
+        .pool_options();
+
+        assert_eq!(options.get_max_connections(), 8);
+        assert_eq!(options.get_min_connections(), 3);
+        assert_eq!(options.get_acquire_timeout(), Duration::from_millis(250));
+
+        // min_idle never exceeds max_size
+        let options = PoolConfig { max_size: 2, min_idle: 5, ..PoolConfig::default() }.pool_options();
+        assert_eq!(options.get_min_connections(), 2);
+    }
+
+    #[test]
+    fn statement_timeout_is_set_on_connections() {
+        let config = PoolConfig {
+            statement_timeout: Some(Duration::from_secs(5)),
+            ..PoolConfig::default()
+        };
+        let options = config.connect_options("postgres://aion@localhost/aion").unwrap();
+        assert_eq!(options.get_options(), Some("-c statement_timeout=5000ms"));
+
+        let config = PoolConfig { statement_timeout: None, ..config };
+        assert_eq!(config.connect_options("postgres://aion@localhost/aion").unwrap().get_options(), None);
+    }
+
+    #[tokio::test]
+    async fn metrics_come_from_the_pool() {
+        let pool = ConnectionPool::connect_lazy(
+            "postgres://aion@127.0.0.1:1/aion",
+            PoolConfig { max_size: 4, min_idle: 0, ..PoolConfig::default() },
+        )
+        .unwrap();
+
+        let metrics = pool.metrics();
+        assert_eq!((metrics.active, metrics.idle, metrics.size, metrics.max_size), (0, 0, 0, 4));
+        assert!(ConnectionPool::connect_lazy("not a url", PoolConfig::default()).is_err());
+        pool.close().await;
+    }
+}
@@ -0,0 +1,190 @@
+//! # Query Tracking
+//!
+//! Times database queries and warns about the ones slower than
+//! `slow_query_threshold`. Runaway queries are stopped by PostgreSQL itself:
+//! `ConnectionPool` sets `statement_timeout` on every connection it opens, and
+//! the tracker counts the queries the server cancelled for it.
+
+use anyhow::Result;
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+use tracing::warn;
+
+pub const DEFAULT_SLOW_QUERY_THRESHOLD: Duration = Duration::from_millis(500);
+pub const DEFAULT_STATEMENT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// SQLSTATE PostgreSQL reports for a statement cancelled by `statement_timeout`
+const QUERY_CANCELED: &str = "57014";
+
+pub struct QueryTracker {
+    slow_query_threshold: Duration,
+    queries: AtomicU64,
+    slow_queries: AtomicU64,
+    timed_out_queries: AtomicU64,
+}
+
+impl QueryTracker {
+    pub fn new(slow_query_threshold: Duration) -> Self {
+        Self {
+            slow_query_threshold,
+            queries: AtomicU64::new(0),
+            slow_queries: AtomicU64::new(0),
+            timed_out_queries: AtomicU64::new(0),
+        }
+    }
+
+    /// Run `query`, named `name` in logs. Queries the server cancelled for
+    /// exceeding its statement timeout count as timed out and slow.
+    pub async fn track<T, F>(&self, name: &str, query: F) -> Result<T>
+    where
+        F: Future<Output = Result<T>>,
+    {
+        self.queries.fetch_add(1, Ordering::Relaxed);
+        let started = Instant::now();
+
+        let result = query.await;
+        let timed_out = result.as_ref().err().is_some_and(is_statement_timeout);
+        if timed_out {
+            self.timed_out_queries.fetch_add(1, Ordering::Relaxed);
+        }
+
+        let elapsed = started.elapsed();
+        if timed_out || elapsed >= self.slow_query_threshold {
+            self.slow_queries.fetch_add(1, Ordering::Relaxed);
+            warn!(
+                query = name,
+                elapsed_ms = elapsed.as_millis() as u64,
+                threshold_ms = self.slow_query_threshold.as_millis() as u64,
+                correlation_id = aion_core::current_correlation_id().as_deref().unwrap_or("none"),
+                failed = result.is_err(),
+                timed_out,
+                "Slow query"
+            );
+        }
+        result
+    }
+
+    pub fn query_count(&self) -> u64 {
+        self.queries.load(Ordering::Relaxed)
+    }
+
+    pub fn slow_query_count(&self) -> u64 {
+        self.slow_queries.load(Ordering::Relaxed)
+    }
+
+    pub fn timed_out_query_count(&self) -> u64 {
+        self.timed_out_queries.load(Ordering::Relaxed)
+    }
+}
+
+impl Default for QueryTracker {
+    fn default() -> Self {
+        Self::new(DEFAULT_SLOW_QUERY_THRESHOLD)
+    }
+}
+
+fn is_statement_timeout(error: &anyhow::Error) -> bool {
+    error
+        .downcast_ref::<sqlx::Error>()
+        .and_then(|error| error.as_database_error())
+        .and_then(|error| error.code())
+        .is_some_and(|code| code == QUERY_CANCELED)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io;
+    use std::sync::{Arc, Mutex};
+
+    /// Collects formatted log output
+    #[derive(Clone, Default)]
+    struct CapturedLogs(Arc<Mutex<Vec<u8>>>);
+
+    impl io::Write for CapturedLogs {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    /// What PostgreSQL returns for a statement cancelled by `statement_timeout`
+    #[derive(Debug)]
+    struct StatementTimeout;
+
+    impl std::fmt::Display for StatementTimeout {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            f.write_str("canceling statement due to statement timeout")
+        }
+    }
+
+    impl std::error::Error for StatementTimeout {}
+
+    impl sqlx::error::DatabaseError for StatementTimeout {
+        fn message(&self) -> &str {
+            "canceling statement due to statement timeout"
+        }
+
+        fn code(&self) -> Option<std::borrow::Cow<'_, str>> {
+            Some(QUERY_CANCELED.into())
+        }
+
+        fn as_error(&self) -> &(dyn std::error::Error + Send + Sync + 'static) {
+            self
+        }
+
+        fn as_error_mut(&mut self) -> &mut (dyn std::error::Error + Send + Sync + 'static) {
+            self
+        }
+
+        fn into_error(self: Box<Self>) -> Box<dyn std::error::Error + Send + Sync + 'static> {
+            self
+        }
+
+        fn kind(&self) -> sqlx::error::ErrorKind {
+            sqlx::error::ErrorKind::Other
+        }
+    }
+
+    async fn delayed_query(delay: Duration) -> Result<u32> {
+        tokio::time::sleep(delay).await;
+        Ok(7)
+    }
+
+    async fn cancelled_query() -> Result<u32> {
+        Err(sqlx::Error::Database(Box::new(StatementTimeout)).into())
+    }
+
+    #[tokio::test]
+    async fn slow_and_server_cancelled_queries_are_logged_and_counted() {
+        let logs = CapturedLogs::default();
+        let writer = logs.clone();
+        let subscriber = tracing_subscriber::fmt().json().with_writer(move || writer.clone()).finish();
+        let _guard = tracing::subscriber::set_default(subscriber);
+
+        let tracker = QueryTracker::new(Duration::from_millis(20));
+        assert_eq!(tracker.track("fast", delayed_query(Duration::ZERO)).await.unwrap(), 7);
+        assert_eq!(tracker.slow_query_count(), 0);
+
+        let slow = aion_core::with_correlation_id("req-42", tracker.track("load_projects", delayed_query(Duration::from_millis(40))));
+        assert_eq!(slow.await.unwrap(), 7);
+        assert_eq!((tracker.slow_query_count(), tracker.timed_out_query_count()), (1, 0));
+
+        let output = String::from_utf8(logs.0.lock().unwrap().clone()).unwrap();
+        let warning = output.lines().find(|line| line.contains("Slow query")).expect("slow query warning");
+        assert!(warning.contains("\"query\":\"load_projects\""));
+        assert!(warning.contains("\"correlation_id\":\"req-42\""));
+        assert!(warning.contains("WARN"));
+
+        // A quick failure is only slow when the server cancelled it for running too long
+        assert!(tracker.track("bad", async { Err::<u32, _>(anyhow::anyhow!("syntax error")) }).await.is_err());
+        let cancelled = tracker.track("hung", cancelled_query()).await.unwrap_err();
+        assert!(is_statement_timeout(&cancelled));
+        assert_eq!((tracker.slow_query_count(), tracker.timed_out_query_count(), tracker.query_count()), (2, 1, 4));
+    }
+}
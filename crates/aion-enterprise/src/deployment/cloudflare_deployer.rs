@@ -2,6 +2,7 @@
 // Magic Loop implementation for zero-cost SaaS deployment
 
 use crate::infrastructure::cloudflare_impl::*;
+use super::logs::{DeploymentLogStore, LogStream};
 use anyhow::{Result, Context};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
@@ -20,6 +21,7 @@ pub struct CloudflareDeployer {
     infrastructure_generator: Arc<CloudflareInfrastructureGenerator>,
     deployment_tracker: Arc<RwLock<HashMap<Uuid, CloudflareDeploymentStatus>>>,
     godaddy_client: Option<GoDaddyClient>,
+    log_store: Option<DeploymentLogStore>,
 }
 
 /// Cloudflare deployment configuration
@@ -123,6 +125,7 @@ impl CloudflareDeployer {
             infrastructure_generator: infra_generator,
             deployment_tracker: Arc::new(RwLock::new(HashMap::new())),
             godaddy_client: None,
+            log_store: None,
         }
     }
 
@@ -132,6 +135,12 @@ impl CloudflareDeployer {
         self
     }
 
+    /// Capture structured step logs, including tool output, in a log store
+    pub fn with_log_store(mut self, log_store: DeploymentLogStore) -> Self {
+        self.log_store = Some(log_store);
+        self
+    }
+
     /// Execute the Magic Loop: From prompt to production URL
     pub async fn magic_loop_deployment(
         &self,
@@ -140,6 +149,22 @@ impl CloudflareDeployer {
         options: MagicLoopOptions,
     ) -> Result<MagicLoopResult> {
         let deployment_id = Uuid::new_v4();
+        let result = self.run_magic_loop(deployment_id, prompt, user_id, options).await;
+
+        // End live log streams whether the deployment succeeded or not
+        if let Some(log_store) = &self.log_store {
+            log_store.finish(deployment_id).await?;
+        }
+        result
+    }
+
+    async fn run_magic_loop(
+        &self,
+        deployment_id: Uuid,
+        prompt: &str,
+        user_id: Uuid,
+        options: MagicLoopOptions,
+    ) -> Result<MagicLoopResult> {
         tracing::info!("Starting Magic Loop deployment {} for prompt: {}", deployment_id, prompt);
 
         // Initialize deployment tracking
@@ -204,7 +229,7 @@ impl CloudflareDeployer {
         self.update_stage(&mut status, DeploymentStage::DeployingWorker, 70.0).await?;
         self.log_deployment(&mut status, LogLevel::Info, "Deploying worker to Cloudflare").await?;
 
-        let worker_url = self.deploy_worker(&worker_config, &worker_code, &app_spec).await
+        let worker_url = self.deploy_worker(deployment_id, &worker_config, &worker_code, &app_spec).await
             .context("Failed to deploy worker")?;
 
         status.worker_url = Some(worker_url.clone());
@@ -346,6 +371,7 @@ impl CloudflareDeployer {
     /// Deploy worker using wrangler CLI
     async fn deploy_worker(
         &self,
+        deployment_id: Uuid,
         config: &CloudflareWorkerConfig,
         code: &GeneratedWorkerCode,
         app_spec: &ApplicationSpec,
@@ -367,21 +393,28 @@ impl CloudflareDeployer {
         std::env::set_var("CLOUDFLARE_ACCOUNT_ID", &self.config.account_id);
 
         // Run wrangler deploy
-        let output = Command::new("wrangler")
-            .args(&["deploy", "--compatibility-date", "2024-01-01"])
-            .current_dir(&temp_dir)
-            .stdout(Stdio::piped())
-            .stderr(Stdio::piped())
-            .output()
-            .context("Failed to execute wrangler deploy")?;
-
-        if !output.status.success() {
-            let error = String::from_utf8_lossy(&output.stderr);
-            return Err(anyhow::anyhow!("Wrangler deploy failed: {}", error));
-        }
+        let stdout = match &self.log_store {
+            Some(log_store) => {
+                self.run_logged_wrangler_deploy(log_store, deployment_id, &temp_dir).await?
+            }
+            None => {
+                let output = Command::new("wrangler")
+                    .args(&["deploy", "--compatibility-date", "2024-01-01"])
+                    .current_dir(&temp_dir)
+                    .stdout(Stdio::piped())
+                    .stderr(Stdio::piped())
+                    .output()
+                    .context("Failed to execute wrangler deploy")?;
+
+                if !output.status.success() {
+                    let error = String::from_utf8_lossy(&output.stderr);
+                    return Err(anyhow::anyhow!("Wrangler deploy failed: {}", error));
+                }
+                String::from_utf8_lossy(&output.stdout).into_owned()
+            }
+        };
 
         // Extract URL from wrangler output
-        let stdout = String::from_utf8_lossy(&output.stdout);
         let url = self.extract_worker_url_from_output(&stdout)
             .unwrap_or_else(|| format!("https://{}.{}.workers.dev", config.worker_name, self.config.account_id));
 
@@ -391,6 +424,38 @@ impl CloudflareDeployer {
         Ok(url)
     }
 
+    /// Run wrangler deploy, streaming its output into the log store as it is produced
+    async fn run_logged_wrangler_deploy(
+        &self,
+        log_store: &DeploymentLogStore,
+        deployment_id: Uuid,
+        dir: &Path,
+    ) -> Result<String> {
+        let mut child = tokio::process::Command::new("wrangler")
+            .args(["deploy", "--compatibility-date", "2024-01-01"])
+            .current_dir(dir)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .context("Failed to execute wrangler deploy")?;
+
+        let writer = log_store.writer(deployment_id, DeploymentStage::DeployingWorker.stage_name());
+        let stdout = child.stdout.take().context("wrangler stdout not captured")?;
+        let stderr = child.stderr.take().context("wrangler stderr not captured")?;
+
+        // Both pipes are drained concurrently so neither can fill up and stall the process
+        let (stdout, stderr) = tokio::try_join!(
+            writer.capture(LogStream::Stdout, stdout),
+            writer.capture(LogStream::Stderr, stderr),
+        )?;
+        let status = child.wait().await.context("Failed to wait for wrangler deploy")?;
+
+        if !status.success() {
+            return Err(anyhow::anyhow!("Wrangler deploy failed: {}", stderr));
+        }
+        Ok(stdout)
+    }
+
     /// Extract worker URL from wrangler output
     fn extract_worker_url_from_output(&self, output: &str) -> Option<String> {
         let re = Regex::new(r"https://[a-zA-Z0-9\-]+\..*\.workers\.dev").ok()?;
@@ -604,6 +669,18 @@ impl CloudflareDeployer {
         status.logs.push(log.clone());
         tracing::info!("[{}] {}: {}", status.deployment_id, log.stage_name(), message);
 
+        if let Some(log_store) = &self.log_store {
+            let level = match log.level {
+                LogLevel::Info | LogLevel::Success => super::logs::LogLevel::Info,
+                LogLevel::Warning => super::logs::LogLevel::Warning,
+                LogLevel::Error => super::logs::LogLevel::Error,
+            };
+            log_store
+                .writer(status.deployment_id, log.stage_name())
+                .log(level, LogStream::System, message)
+                .await?;
+        }
+
         self.update_deployment_status(status).await
     }
 
@@ -0,0 +1,655 @@
+// AION-R Enterprise: Deployment Log Store
+// Structured ingestion, retention, cursor-paginated queries and live following of deployment logs
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex, RwLock};
+use tokio::io::{AsyncBufReadExt, AsyncRead, BufReader};
+use tokio::sync::{broadcast, mpsc};
+use tokio::task::JoinHandle;
+use uuid::Uuid;
+
+/// Log severity, ordered from least to most severe
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogLevel {
+    Debug,
+    Info,
+    #[serde(alias = "warn")]
+    Warning,
+    Error,
+}
+
+/// Where a log line came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum LogStream {
+    Stdout,
+    Stderr,
+    /// Messages emitted by the deployer itself
+    System,
+}
+
+/// A structured deployment log entry
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeploymentLogEntry {
+    /// Store-wide, strictly increasing sequence number
+    pub sequence: u64,
+    pub deployment_id: Uuid,
+    pub timestamp: DateTime<Utc>,
+    pub level: LogLevel,
+    pub step: String,
+    pub stream: LogStream,
+    pub message: String,
+}
+
+/// Query over a deployment's logs; also the query string of the web API's logs endpoints
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LogsQuery {
+    /// Minimum level to include
+    pub level: Option<LogLevel>,
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+    /// Case-insensitive substring match on the message
+    pub text: Option<String>,
+    pub step: Option<String>,
+    pub stream: Option<LogStream>,
+    /// Continue after the page that returned this cursor
+    pub cursor: Option<String>,
+    /// Page size, capped by `LogStoreConfig::max_page_size`
+    #[serde(alias = "lines")]
+    pub limit: Option<usize>,
+    /// Keep streaming new entries until the deployment finishes
+    pub follow: Option<bool>,
+}
+
+impl LogsQuery {
+    fn matches(&self, entry: &DeploymentLogEntry, text: Option<&str>) -> bool {
+        self.level.map_or(true, |level| entry.level >= level)
+            && self.since.map_or(true, |since| entry.timestamp >= since)
+            && self.until.map_or(true, |until| entry.timestamp < until)
+            && self.step.as_deref().map_or(true, |step| entry.step == step)
+            && self.stream.map_or(true, |stream| entry.stream == stream)
+            && text.map_or(true, |text| entry.message.to_lowercase().contains(text))
+    }
+
+    fn lowercase_text(&self) -> Option<String> {
+        self.text.as_ref().map(|text| text.to_lowercase())
+    }
+}
+
+/// One page of logs
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LogPage {
+    pub entries: Vec<DeploymentLogEntry>,
+    /// Pass back in `LogsQuery::cursor` to fetch the next page
+    pub next_cursor: Option<String>,
+    pub has_more: bool,
+    /// Entries evicted from this deployment because it exceeded the per-deployment cap
+    pub truncated_entries: u64,
+    /// Whether the deployment is still producing logs
+    pub live: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct LogStoreConfig {
+    /// How long logs of finished deployments are kept
+    pub retention: Duration,
+    /// Oldest entries are evicted beyond this many per deployment
+    pub max_entries_per_deployment: usize,
+    /// Pending entries between producers and the store; producers wait when it is full
+    pub ingest_buffer: usize,
+    /// Entries buffered for live followers before they must catch up from the store
+    pub live_buffer: usize,
+    pub default_page_size: usize,
+    pub max_page_size: usize,
+}
+
+impl Default for LogStoreConfig {
+    fn default() -> Self {
+        Self {
+            retention: Duration::days(7),
+            max_entries_per_deployment: 200_000,
+            ingest_buffer: 4_096,
+            live_buffer: 1_024,
+            default_page_size: 200,
+            max_page_size: 1_000,
+        }
+    }
+}
+
+enum IngestMessage {
+    Entry {
+        deployment_id: Uuid,
+        timestamp: DateTime<Utc>,
+        level: LogLevel,
+        step: String,
+        stream: LogStream,
+        message: String,
+    },
+    Finish(Uuid),
+}
+
+#[derive(Debug, Clone)]
+enum LiveEvent {
+    Entry(Arc<DeploymentLogEntry>),
+    Finished(Uuid),
+}
+
+#[derive(Default)]
+struct DeploymentLogs {
+    /// Ordered by sequence; timestamps are non-decreasing
+    entries: VecDeque<Arc<DeploymentLogEntry>>,
+    truncated: u64,
+    finished_at: Option<DateTime<Utc>>,
+}
+
+struct StoreState {
+    next_sequence: u64,
+    deployments: HashMap<Uuid, DeploymentLogs>,
+}
+
+/// In-memory deployment log store.
+///
+/// Producers write through a bounded channel, so a step emitting logs faster
+/// than they can be stored is slowed down instead of growing memory. Stored
+/// logs are capped per deployment and kept for `retention` after the
+/// deployment finishes.
+///
+/// Nothing is stored until [`start`](Self::start) spawns the ingestion task;
+/// until then writers wait once the ingest buffer is full.
+#[derive(Clone)]
+pub struct DeploymentLogStore {
+    config: Arc<LogStoreConfig>,
+    state: Arc<RwLock<StoreState>>,
+    ingest: mpsc::Sender<IngestMessage>,
+    /// Taken by `start`
+    pending: Arc<Mutex<Option<mpsc::Receiver<IngestMessage>>>>,
+    live: broadcast::Sender<LiveEvent>,
+}
+
+impl DeploymentLogStore {
+    pub fn new(config: LogStoreConfig) -> Self {
+        let (ingest, receiver) = mpsc::channel(config.ingest_buffer.max(1));
+        let (live, _) = broadcast::channel(config.live_buffer.max(1));
+
+        Self {
+            config: Arc::new(config),
+            state: Arc::new(RwLock::new(StoreState {
+                next_sequence: 1,
+                deployments: HashMap::new(),
+            })),
+            ingest,
+            pending: Arc::new(Mutex::new(Some(receiver))),
+            live,
+        }
+    }
+
+    /// Spawn the ingestion task; must be called within a Tokio runtime and only once
+    pub fn start(&self) -> Result<JoinHandle<()>> {
+        let receiver = self
+            .pending
+            .lock()
+            .unwrap()
+            .take()
+            .context("Log store ingestion is already running")?;
+        Ok(tokio::spawn(self.clone().run_ingestion(receiver)))
+    }
+
+    /// Writer for one deployment step
+    pub fn writer(&self, deployment_id: Uuid, step: impl Into<String>) -> StepLogWriter {
+        StepLogWriter {
+            deployment_id,
+            step: step.into(),
+            sender: self.ingest.clone(),
+        }
+    }
+
+    /// Mark a deployment as finished; live followers end once they have seen all its logs
+    pub async fn finish(&self, deployment_id: Uuid) -> Result<()> {
+        self.ingest
+            .send(IngestMessage::Finish(deployment_id))
+            .await
+            .context("Log store is shut down")
+    }
+
+    /// Query a deployment's logs, oldest first
+    pub fn query(&self, deployment_id: Uuid, filter: &LogsQuery) -> Result<LogPage> {
+        let after = filter.cursor.as_deref().map(decode_cursor).transpose()?.unwrap_or(0);
+        let limit = filter
+            .limit
+            .unwrap_or(self.config.default_page_size)
+            .clamp(1, self.config.max_page_size);
+        let text = filter.lowercase_text();
+
+        let state = self.state.read().unwrap();
+        let Some(logs) = state.deployments.get(&deployment_id) else {
+            return Ok(LogPage {
+                entries: Vec::new(),
+                next_cursor: None,
+                has_more: false,
+                truncated_entries: 0,
+                live: false,
+            });
+        };
+
+        // Sequence and timestamp are both monotonic, so the cursor and time range bound the scan
+        let mut start = logs.entries.partition_point(|e| e.sequence <= after);
+        if let Some(since) = filter.since {
+            start = start.max(logs.entries.partition_point(|e| e.timestamp < since));
+        }
+        let end = filter
+            .until
+            .map(|until| logs.entries.partition_point(|e| e.timestamp < until))
+            .unwrap_or(logs.entries.len());
+
+        let mut entries = Vec::with_capacity(limit.min(end.saturating_sub(start)));
+        let mut has_more = false;
+        for entry in logs.entries.range(start..end.max(start)) {
+            if !filter.matches(entry, text.as_deref()) {
+                continue;
+            }
+            if entries.len() == limit {
+                has_more = true;
+                break;
+            }
+            entries.push(DeploymentLogEntry::clone(entry));
+        }
+
+        Ok(LogPage {
+            next_cursor: entries.last().map(|e| encode_cursor(e.sequence)),
+            entries,
+            has_more,
+            truncated_entries: logs.truncated,
+            live: logs.finished_at.is_none(),
+        })
+    }
+
+    /// Follow a deployment's logs: entries after `filter.cursor` first, then live
+    /// entries until the deployment finishes
+    pub fn follow(&self, deployment_id: Uuid, filter: LogsQuery) -> Result<LogFollower> {
+        let last_sequence = filter.cursor.as_deref().map(decode_cursor).transpose()?.unwrap_or(0);
+        // Subscribe before reading the backlog so nothing slips between the two
+        let receiver = self.live.subscribe();
+
+        Ok(LogFollower {
+            store: self.clone(),
+            deployment_id,
+            text: filter.lowercase_text(),
+            filter,
+            receiver,
+            backlog: VecDeque::new(),
+            last_sequence,
+            caught_up: false,
+            finished: false,
+        })
+    }
+
+    /// Drop logs of deployments that finished longer than `retention` ago
+    pub fn purge_expired(&self) -> usize {
+        let cutoff = Utc::now() - self.config.retention;
+        let mut state = self.state.write().unwrap();
+        let before = state.deployments.len();
+        state
+            .deployments
+            .retain(|_, logs| logs.finished_at.map_or(true, |finished| finished > cutoff));
+        before - state.deployments.len()
+    }
+
+    async fn run_ingestion(self, mut receiver: mpsc::Receiver<IngestMessage>) {
+        let mut ingested: u64 = 0;
+        while let Some(message) = receiver.recv().await {
+            let event = self.ingest_message(message);
+            // No followers is fine; lagging followers recover from the store
+            let _ = self.live.send(event);
+
+            ingested += 1;
+            if ingested % 10_000 == 0 {
+                self.purge_expired();
+            }
+        }
+    }
+
+    fn ingest_message(&self, message: IngestMessage) -> LiveEvent {
+        let mut state = self.state.write().unwrap();
+        match message {
+            IngestMessage::Entry { deployment_id, timestamp, level, step, stream, message } => {
+                let sequence = state.next_sequence;
+                state.next_sequence += 1;
+
+                let logs = state.deployments.entry(deployment_id).or_default();
+                // Keep timestamps monotonic so time ranges can be binary searched
+                let timestamp = logs
+                    .entries
+                    .back()
+                    .map_or(timestamp, |last| timestamp.max(last.timestamp));
+                let entry = Arc::new(DeploymentLogEntry {
+                    sequence,
+                    deployment_id,
+                    timestamp,
+                    level,
+                    step,
+                    stream,
+                    message,
+                });
+
+                logs.entries.push_back(entry.clone());
+                while logs.entries.len() > self.config.max_entries_per_deployment {
+                    logs.entries.pop_front();
+                    logs.truncated += 1;
+                }
+                LiveEvent::Entry(entry)
+            }
+            IngestMessage::Finish(deployment_id) => {
+                state.deployments.entry(deployment_id).or_default().finished_at = Some(Utc::now());
+                LiveEvent::Finished(deployment_id)
+            }
+        }
+    }
+
+    fn is_finished(&self, deployment_id: Uuid) -> bool {
+        self.state
+            .read()
+            .unwrap()
+            .deployments
+            .get(&deployment_id)
+            .map_or(false, |logs| logs.finished_at.is_some())
+    }
+}
+
+impl Default for DeploymentLogStore {
+    fn default() -> Self {
+        Self::new(LogStoreConfig::default())
+    }
+}
+
+/// Writes the logs of one deployment step
+#[derive(Clone)]
+pub struct StepLogWriter {
+    deployment_id: Uuid,
+    step: String,
+    sender: mpsc::Sender<IngestMessage>,
+}
+
+impl StepLogWriter {
+    /// Write one entry, waiting while the store's ingest buffer is full
+    pub async fn log(&self, level: LogLevel, stream: LogStream, message: impl Into<String>) -> Result<()> {
+        self.sender
+            .send(IngestMessage::Entry {
+                deployment_id: self.deployment_id,
+                timestamp: Utc::now(),
+                level,
+                step: self.step.clone(),
+                stream,
+                message: message.into(),
+            })
+            .await
+            .context("Log store is shut down")
+    }
+
+    /// Ingest a process stream line by line; returns the captured text for callers
+    /// that parse the output
+    pub async fn capture<R: AsyncRead + Unpin>(&self, stream: LogStream, reader: R) -> Result<String> {
+        let level = match stream {
+            LogStream::Stderr => LogLevel::Warning,
+            _ => LogLevel::Info,
+        };
+        let mut lines = BufReader::new(reader).lines();
+        let mut output = String::new();
+
+        while let Some(line) = lines.next_line().await? {
+            output.push_str(&line);
+            output.push('\n');
+            self.log(level, stream, line).await?;
+        }
+        Ok(output)
+    }
+}
+
+/// Live view of a deployment's logs, e.g. for a WebSocket session
+pub struct LogFollower {
+    store: DeploymentLogStore,
+    deployment_id: Uuid,
+    filter: LogsQuery,
+    text: Option<String>,
+    receiver: broadcast::Receiver<LiveEvent>,
+    backlog: VecDeque<DeploymentLogEntry>,
+    last_sequence: u64,
+    caught_up: bool,
+    finished: bool,
+}
+
+impl LogFollower {
+    /// Next matching entry; `None` once the deployment finished and all its logs were returned
+    pub async fn next(&mut self) -> Result<Option<DeploymentLogEntry>> {
+        loop {
+            if let Some(entry) = self.backlog.pop_front() {
+                self.last_sequence = entry.sequence;
+                return Ok(Some(entry));
+            }
+            if !self.caught_up {
+                self.catch_up()?;
+                continue;
+            }
+            if self.finished {
+                return Ok(None);
+            }
+
+            match self.receiver.recv().await {
+                Ok(LiveEvent::Entry(entry)) => {
+                    if entry.deployment_id == self.deployment_id
+                        && entry.sequence > self.last_sequence
+                        && self.filter.matches(&entry, self.text.as_deref())
+                    {
+                        self.last_sequence = entry.sequence;
+                        return Ok(Some(DeploymentLogEntry::clone(&entry)));
+                    }
+                }
+                Ok(LiveEvent::Finished(deployment_id)) if deployment_id == self.deployment_id => {
+                    // Entries precede the finish marker, but re-read in case we lagged
+                    self.finished = true;
+                    self.caught_up = false;
+                }
+                Ok(LiveEvent::Finished(_)) => {}
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    tracing::debug!("Log follower for {} lagged by {} events", self.deployment_id, skipped);
+                    self.caught_up = false;
+                }
+                Err(broadcast::error::RecvError::Closed) => return Ok(None),
+            }
+        }
+    }
+
+    /// Next matching stored entry without waiting for live ones; `None` once caught up
+    pub fn next_stored(&mut self) -> Result<Option<DeploymentLogEntry>> {
+        loop {
+            if let Some(entry) = self.backlog.pop_front() {
+                self.last_sequence = entry.sequence;
+                return Ok(Some(entry));
+            }
+            if self.caught_up {
+                return Ok(None);
+            }
+            self.catch_up()?;
+        }
+    }
+
+    /// Load stored entries after the last returned one
+    fn catch_up(&mut self) -> Result<()> {
+        let mut filter = self.filter.clone();
+        filter.cursor = Some(encode_cursor(self.last_sequence));
+        filter.limit = Some(self.store.config.max_page_size);
+
+        let page = self.store.query(self.deployment_id, &filter)?;
+        self.backlog.extend(page.entries);
+        if !page.has_more {
+            self.caught_up = true;
+            self.finished = self.finished || self.store.is_finished(self.deployment_id);
+        }
+        Ok(())
+    }
+}
+
+fn encode_cursor(sequence: u64) -> String {
+    format!("{:x}", sequence)
+}
+
+fn decode_cursor(cursor: &str) -> Result<u64> {
+    u64::from_str_radix(cursor, 16).with_context(|| format!("Invalid log cursor: {}", cursor))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn started(config: LogStoreConfig) -> DeploymentLogStore {
+        let store = DeploymentLogStore::new(config);
+        store.start().unwrap();
+        store
+    }
+
+    async fn flush(store: &DeploymentLogStore, deployment_id: Uuid) {
+        store.finish(deployment_id).await.unwrap();
+        while !store.is_finished(deployment_id) {
+            tokio::task::yield_now().await;
+        }
+    }
+
+    #[tokio::test]
+    async fn test_filters_and_paginates_with_cursor() {
+        let store = started(LogStoreConfig::default());
+        let deployment_id = Uuid::new_v4();
+        let build = store.writer(deployment_id, "build");
+        let deploy = store.writer(deployment_id, "deploy");
+
+        for i in 0..25 {
+            build.log(LogLevel::Info, LogStream::Stdout, format!("compiling crate {}", i)).await.unwrap();
+        }
+        build.log(LogLevel::Error, LogStream::Stderr, "linker failed").await.unwrap();
+        deploy.log(LogLevel::Info, LogStream::System, "uploading bundle").await.unwrap();
+        flush(&store, deployment_id).await;
+
+        let mut filter = LogsQuery { step: Some("build".to_string()), limit: Some(10), ..Default::default() };
+        let mut seen = Vec::new();
+        loop {
+            let page = store.query(deployment_id, &filter).unwrap();
+            seen.extend(page.entries.iter().map(|e| e.message.clone()));
+            if !page.has_more {
+                break;
+            }
+            filter.cursor = page.next_cursor;
+        }
+        assert_eq!(seen.len(), 26);
+        assert_eq!(seen[0], "compiling crate 0");
+
+        let errors = store.query(deployment_id, &LogsQuery { level: Some(LogLevel::Error), ..Default::default() }).unwrap();
+        assert_eq!(errors.entries.len(), 1);
+        assert_eq!(errors.entries[0].stream, LogStream::Stderr);
+
+        let text = store.query(deployment_id, &LogsQuery { text: Some("BUNDLE".to_string()), ..Default::default() }).unwrap();
+        assert_eq!(text.entries.len(), 1);
+        assert_eq!(text.entries[0].step, "deploy");
+        assert!(!text.live);
+    }
+
+    #[tokio::test]
+    async fn test_caps_entries_per_deployment() {
+        let store = started(LogStoreConfig {
+            max_entries_per_deployment: 5,
+            ingest_buffer: 2,
+            ..Default::default()
+        });
+        let deployment_id = Uuid::new_v4();
+        let writer = store.writer(deployment_id, "build");
+
+        for i in 0..20 {
+            writer.log(LogLevel::Info, LogStream::Stdout, format!("line {}", i)).await.unwrap();
+        }
+        flush(&store, deployment_id).await;
+
+        let page = store.query(deployment_id, &LogsQuery::default()).unwrap();
+        assert_eq!(page.entries.len(), 5);
+        assert_eq!(page.truncated_entries, 15);
+        assert_eq!(page.entries[0].message, "line 15");
+    }
+
+    #[tokio::test]
+    async fn test_follower_streams_backlog_then_live_until_finished() {
+        let store = started(LogStoreConfig::default());
+        let deployment_id = Uuid::new_v4();
+        let writer = store.writer(deployment_id, "deploy");
+
+        writer.log(LogLevel::Info, LogStream::System, "before follow").await.unwrap();
+        let mut follower = store.follow(deployment_id, LogsQuery::default()).unwrap();
+
+        let producer = {
+            let store = store.clone();
+            tokio::spawn(async move {
+                for i in 0..50 {
+                    writer.log(LogLevel::Info, LogStream::Stdout, format!("live {}", i)).await.unwrap();
+                }
+                store.finish(deployment_id).await.unwrap();
+            })
+        };
+
+        let mut messages = Vec::new();
+        while let Some(entry) = follower.next().await.unwrap() {
+            messages.push(entry.message);
+        }
+        producer.await.unwrap();
+
+        assert_eq!(messages.len(), 51);
+        assert_eq!(messages[0], "before follow");
+        assert_eq!(messages[50], "live 49");
+    }
+
+    #[tokio::test]
+    async fn test_next_stored_ends_after_backlog() {
+        let store = started(LogStoreConfig { max_page_size: 2, ..Default::default() });
+        let deployment_id = Uuid::new_v4();
+        let writer = store.writer(deployment_id, "build");
+        for i in 0..5 {
+            writer.log(LogLevel::Info, LogStream::Stdout, format!("line {}", i)).await.unwrap();
+        }
+        writer.log(LogLevel::Error, LogStream::Stderr, "failed").await.unwrap();
+        while store.query(deployment_id, &LogsQuery { limit: Some(10), ..Default::default() }).unwrap().entries.len() < 6 {
+            tokio::task::yield_now().await;
+        }
+
+        // The deployment is still running, but a non-following reader stops at the end of the backlog
+        let mut follower = store.follow(deployment_id, LogsQuery::default()).unwrap();
+        let mut messages = Vec::new();
+        while let Some(entry) = follower.next_stored().unwrap() {
+            messages.push(entry.message);
+        }
+        assert_eq!(messages.len(), 6);
+        assert_eq!(messages[5], "failed");
+    }
+
+    #[tokio::test]
+    async fn test_entries_are_stored_once_started() {
+        let store = DeploymentLogStore::default();
+        let deployment_id = Uuid::new_v4();
+        store.writer(deployment_id, "build").log(LogLevel::Info, LogStream::Stdout, "queued").await.unwrap();
+        assert!(store.query(deployment_id, &LogsQuery::default()).unwrap().entries.is_empty());
+
+        store.start().unwrap();
+        assert!(store.start().is_err());
+        flush(&store, deployment_id).await;
+        assert_eq!(store.query(deployment_id, &LogsQuery::default()).unwrap().entries.len(), 1);
+    }
+
+    #[test]
+    fn test_query_string_accepts_lines_as_limit() {
+        let query: LogsQuery = serde_json::from_value(serde_json::json!({
+            "lines": 50,
+            "level": "warn",
+            "follow": true
+        }))
+        .unwrap();
+        assert_eq!(query.limit, Some(50));
+        assert_eq!(query.level, Some(LogLevel::Warning));
+        assert_eq!(query.follow, Some(true));
+    }
+}
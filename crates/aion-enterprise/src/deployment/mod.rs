@@ -1 +1,3 @@
 pub mod cloudflare_deployer;
+pub mod logs;
+pub mod orchestrator;
@@ -5,25 +5,28 @@ use crate::infrastructure::terraform::*;
 use anyhow::{Result, Context};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use sha2::{Digest, Sha256};
 use std::path::{Path, PathBuf};
 use uuid::Uuid;
 use chrono::{DateTime, Utc};
-use async_trait::async_trait;
 use tokio::sync::{RwLock, Mutex};
 use std::sync::Arc;
 
 /// Main deployment orchestrator that coordinates the entire pipeline
 #[derive(Clone)]
 pub struct DeploymentOrchestrator {
-    terraform_manager: Arc<TerraformManager>,
+    terraform_manager: Arc<Mutex<TerraformManager>>,
     cloud_deployer: Arc<CloudDeployer>,
     validation_engine: Arc<ValidationEngine>,
     monitoring_integrator: Arc<MonitoringIntegrator>,
     rollback_manager: Arc<RollbackManager>,
     notification_service: Arc<NotificationService>,
-    deployment_history: Arc<RwLock<Vec<DeploymentRecord>>>,
+    artifact_store: Arc<ArtifactStore>,
+    deployment_history: Arc<RwLock<HashMap<Uuid, Vec<DeploymentRecord>>>>,
     active_deployments: Arc<RwLock<HashMap<Uuid, DeploymentStatus>>>,
     deployment_queue: Arc<Mutex<Vec<DeploymentRequest>>>,
+    /// Held for the whole of a revert so concurrent reverts see each other's result
+    revert_lock: Arc<Mutex<()>>,
 }
 
 /// Complete deployment request from prompt to production
@@ -43,9 +46,60 @@ pub struct DeploymentRequest {
     pub auto_scaling: bool,
     pub cost_limits: Option<CostLimits>,
     pub compliance_checks: Vec<ComplianceFramework>,
+    /// Content-addressed artifacts deployed by this request
+    #[serde(default)]
+    pub artifacts: Vec<ArtifactReference>,
+    /// User or service that requested the deployment
+    #[serde(default)]
+    pub requested_by: String,
+    /// Set when this request reverts to a prior version
+    #[serde(default)]
+    pub revert_to_version: Option<u32>,
     pub created_at: DateTime<Utc>,
 }
 
+/// Build artifact identified by the digest of its content
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ArtifactReference {
+    pub name: String,
+    /// `sha256:<hex>` digest
+    pub digest: String,
+}
+
+/// A successful deployment, recorded with everything needed to redeploy it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeploymentRecord {
+    pub deployment_id: Uuid,
+    pub version: u32,
+    pub request: DeploymentRequest,
+    pub production_url: String,
+    pub deployed_by: String,
+    pub deployed_at: DateTime<Utc>,
+    pub change_summary: String,
+}
+
+/// History entry returned to clients
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeploymentVersion {
+    pub version: u32,
+    pub deployed_at: DateTime<Utc>,
+    pub deployed_by: String,
+    pub change_summary: String,
+    pub strategy: DeploymentStrategy,
+    pub artifacts: Vec<ArtifactReference>,
+    pub reverted_to: Option<u32>,
+}
+
+/// Outcome of `revert_deployment`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RevertResult {
+    /// Version now live; a new version when a redeploy happened
+    pub live_version: u32,
+    /// The deployment already ran the target version's configuration and artifacts
+    pub already_current: bool,
+    pub result: Option<DeploymentResult>,
+}
+
 /// Deployment strategies
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum DeploymentStrategy {
@@ -125,32 +179,43 @@ impl DeploymentOrchestrator {
     /// Create new deployment orchestrator
     pub fn new() -> Self {
         Self {
-            terraform_manager: Arc::new(TerraformManager::new()),
+            terraform_manager: Arc::new(Mutex::new(TerraformManager::new())),
             cloud_deployer: Arc::new(CloudDeployer::new()),
             validation_engine: Arc::new(ValidationEngine::new()),
             monitoring_integrator: Arc::new(MonitoringIntegrator::new()),
             rollback_manager: Arc::new(RollbackManager::new()),
             notification_service: Arc::new(NotificationService::new()),
-            deployment_history: Arc::new(RwLock::new(Vec::new())),
+            artifact_store: Arc::new(ArtifactStore::new(std::env::temp_dir().join("aion-artifacts"))),
+            deployment_history: Arc::new(RwLock::new(HashMap::new())),
             active_deployments: Arc::new(RwLock::new(HashMap::new())),
             deployment_queue: Arc::new(Mutex::new(Vec::new())),
+            revert_lock: Arc::new(Mutex::new(())),
         }
     }
 
+    /// Use a specific content-addressed artifact store
+    pub fn with_artifact_store(mut self, store: ArtifactStore) -> Self {
+        self.artifact_store = Arc::new(store);
+        self
+    }
+
     /// Execute complete deployment pipeline from code to production
     pub async fn deploy_to_production(
         &self,
-        request: DeploymentRequest,
+        mut request: DeploymentRequest,
     ) -> Result<DeploymentResult> {
         tracing::info!("Starting deployment pipeline for: {}", request.project_name);
 
         // Initialize deployment status
         let mut status = self.initialize_deployment_status(request.id).await?;
         self.update_status(&mut status, DeploymentState::Initializing).await?;
+        self.record_artifacts(&mut request).await?;
 
         // Step 1: Generate Infrastructure as Code
         self.update_status(&mut status, DeploymentState::GeneratingInfrastructure).await?;
         let infrastructure = self.terraform_manager
+            .lock()
+            .await
             .generate_infrastructure(request.infrastructure_spec.clone())
             .await
             .context("Failed to generate infrastructure")?;
@@ -216,7 +281,7 @@ impl DeploymentOrchestrator {
         self.update_status(&mut status, DeploymentState::Completed).await?;
 
         // Record deployment
-        self.record_deployment(&request, &deployment).await?;
+        self.record_deployment(&request, &production_url).await?;
 
         // Send notifications
         self.notification_service
@@ -230,12 +295,119 @@ impl DeploymentOrchestrator {
             infrastructure_resources: provisioning_result.resources,
             monitoring_dashboard_url: deployment.monitoring_url.clone(),
             total_cost_estimate: provisioning_result.cost_estimate,
-            deployment_time: status.updated_at - status.started_at,
+            deployment_time: (status.updated_at - status.started_at).to_std().unwrap_or_default(),
             health_check_results: health_results,
             test_results,
         })
     }
 
+    /// List recorded versions of a deployment, newest first
+    pub async fn get_deployment_history(&self, deployment_id: Uuid) -> Vec<DeploymentVersion> {
+        let history = self.deployment_history.read().await;
+        history
+            .get(&deployment_id)
+            .map(|records| {
+                records
+                    .iter()
+                    .rev()
+                    .map(|record| DeploymentVersion {
+                        version: record.version,
+                        deployed_at: record.deployed_at,
+                        deployed_by: record.deployed_by.clone(),
+                        change_summary: record.change_summary.clone(),
+                        strategy: record.request.deployment_strategy.clone(),
+                        artifacts: record.request.artifacts.clone(),
+                        reverted_to: record.request.revert_to_version,
+                    })
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Redeploy a prior known-good version through the normal pipeline.
+    ///
+    /// Idempotent: if the live version already runs the target's configuration
+    /// and artifacts nothing is redeployed. Artifacts are verified before any
+    /// step starts so a garbage-collected version fails without side effects.
+    pub async fn revert_deployment(
+        &self,
+        deployment_id: Uuid,
+        to_version: u32,
+        requested_by: &str,
+    ) -> Result<RevertResult> {
+        let _revert = self.revert_lock.lock().await;
+        let (target, live) = {
+            let history = self.deployment_history.read().await;
+            let records = history
+                .get(&deployment_id)
+                .ok_or_else(|| anyhow::anyhow!("No deployment history for {}", deployment_id))?;
+            let target = records
+                .iter()
+                .find(|r| r.version == to_version)
+                .cloned()
+                .ok_or_else(|| anyhow::anyhow!("Deployment {} has no version {}", deployment_id, to_version))?;
+            let live = records.last().cloned().expect("history is never empty");
+            (target, live)
+        };
+
+        if same_release(&live.request, &target.request) {
+            tracing::info!(
+                "Deployment {} already runs the content of version {} (live version {})",
+                deployment_id,
+                to_version,
+                live.version
+            );
+            return Ok(RevertResult {
+                live_version: live.version,
+                already_current: true,
+                result: None,
+            });
+        }
+
+        let mut missing = Vec::new();
+        for artifact in &target.request.artifacts {
+            if !self.artifact_store.contains(&artifact.digest).await? {
+                missing.push(format!("{} ({})", artifact.name, artifact.digest));
+            }
+        }
+        if !missing.is_empty() {
+            return Err(anyhow::anyhow!(
+                "Cannot revert deployment {} to version {}: artifacts were garbage-collected: {}",
+                deployment_id,
+                to_version,
+                missing.join(", ")
+            ));
+        }
+
+        tracing::info!(
+            "Reverting deployment {} from version {} to version {}",
+            deployment_id,
+            live.version,
+            to_version
+        );
+
+        let mut request = target.request;
+        request.requested_by = requested_by.to_string();
+        request.revert_to_version = Some(to_version);
+        request.created_at = Utc::now();
+
+        let result = self.deploy_to_production(request).await?;
+        let live_version = self
+            .deployment_history
+            .read()
+            .await
+            .get(&deployment_id)
+            .and_then(|records| records.last())
+            .map(|record| record.version)
+            .unwrap_or(to_version);
+
+        Ok(RevertResult {
+            live_version,
+            already_current: false,
+            result: Some(result),
+        })
+    }
+
     /// Deploy using specific strategy
     async fn deploy_with_strategy(
         &self,
@@ -398,7 +570,58 @@ impl DeploymentOrchestrator {
         Ok(())
     }
 
-    async fn record_deployment(&self, request: &DeploymentRequest, deployment: &Deployment) -> Result<()> {
+    async fn record_deployment(&self, request: &DeploymentRequest, production_url: &str) -> Result<()> {
+        let mut history = self.deployment_history.write().await;
+        let records = history.entry(request.id).or_default();
+        let previous = records.last();
+
+        let version = previous.map(|r| r.version + 1).unwrap_or(1);
+        let change_summary = match (request.revert_to_version, previous) {
+            (Some(version), _) => format!("Revert to version {}", version),
+            (None, Some(previous)) => summarize_changes(&previous.request, request),
+            (None, None) => "Initial deployment".to_string(),
+        };
+
+        records.push(DeploymentRecord {
+            deployment_id: request.id,
+            version,
+            request: request.clone(),
+            production_url: production_url.to_string(),
+            deployed_by: request.requested_by.clone(),
+            deployed_at: Utc::now(),
+            change_summary,
+        });
+        Ok(())
+    }
+
+    /// Put the deployed source into the artifact store and reference it from
+    /// `request`, so the version can be redeployed after the source changes.
+    /// Artifacts the caller already referenced must be in the store.
+    async fn record_artifacts(&self, request: &mut DeploymentRequest) -> Result<()> {
+        for artifact in &request.artifacts {
+            if !self.artifact_store.contains(&artifact.digest).await? {
+                return Err(anyhow::anyhow!("Artifact {} ({}) is not in the artifact store", artifact.name, artifact.digest));
+            }
+        }
+        // A revert redeploys exactly the artifacts recorded for the target version
+        if request.revert_to_version.is_some() || !request.source_code_path.exists() {
+            return Ok(());
+        }
+
+        let root = request.source_code_path.clone();
+        for path in source_files(&root)? {
+            let name = match path.strip_prefix(&root) {
+                Ok(relative) if !relative.as_os_str().is_empty() => relative.to_string_lossy().into_owned(),
+                _ => path.file_name().unwrap_or_default().to_string_lossy().into_owned(),
+            };
+            let content = tokio::fs::read(&path)
+                .await
+                .with_context(|| format!("Failed to read artifact {}", path.display()))?;
+            let artifact = self.artifact_store.put(&name, &content).await?;
+            if !request.artifacts.contains(&artifact) {
+                request.artifacts.push(artifact);
+            }
+        }
         Ok(())
     }
 
@@ -424,7 +647,7 @@ pub struct DeploymentResult {
     pub infrastructure_resources: Vec<CloudResource>,
     pub monitoring_dashboard_url: String,
     pub total_cost_estimate: f64,
-    pub deployment_time: chrono::Duration,
+    pub deployment_time: std::time::Duration,
     pub health_check_results: HealthCheckResults,
     pub test_results: TestResults,
 }
@@ -440,8 +663,127 @@ pub struct MonitoringIntegrator;
 pub struct RollbackManager;
 #[derive(Debug, Clone)]
 pub struct NotificationService;
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct DeploymentRecord;
+
+/// Content-addressed artifact store laid out as `<root>/<algorithm>/<hex>`
+#[derive(Debug, Clone)]
+pub struct ArtifactStore {
+    root: PathBuf,
+}
+
+impl ArtifactStore {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    /// Store `content` under its SHA-256 digest and return the reference to record
+    pub async fn put(&self, name: &str, content: &[u8]) -> Result<ArtifactReference> {
+        let digest = format!("sha256:{:x}", Sha256::digest(content));
+        let path = self.path_for(&digest)?;
+
+        if !tokio::fs::try_exists(&path).await? {
+            if let Some(dir) = path.parent() {
+                tokio::fs::create_dir_all(dir).await?;
+            }
+            // Write aside and rename so a partial artifact is never visible under its digest
+            let partial = path.with_extension(format!("{}.partial", Uuid::new_v4()));
+            tokio::fs::write(&partial, content).await?;
+            tokio::fs::rename(&partial, &path).await?;
+        }
+
+        Ok(ArtifactReference {
+            name: name.to_string(),
+            digest,
+        })
+    }
+
+    /// Whether an artifact with `digest` (e.g. `sha256:ab12...`) is still stored
+    pub async fn contains(&self, digest: &str) -> Result<bool> {
+        let path = self.path_for(digest)?;
+        Ok(tokio::fs::try_exists(path).await?)
+    }
+
+    fn path_for(&self, digest: &str) -> Result<PathBuf> {
+        let (algorithm, hex) = digest
+            .split_once(':')
+            .ok_or_else(|| anyhow::anyhow!("Invalid artifact digest: {}", digest))?;
+        let valid = matches!(algorithm, "sha256" | "sha512")
+            && !hex.is_empty()
+            && hex.chars().all(|c| c.is_ascii_hexdigit());
+        if !valid {
+            return Err(anyhow::anyhow!("Invalid artifact digest: {}", digest));
+        }
+        Ok(self.root.join(algorithm).join(hex.to_ascii_lowercase()))
+    }
+}
+
+/// Files under `root`, or `root` itself when it is a file, in a stable order
+fn source_files(root: &Path) -> Result<Vec<PathBuf>> {
+    if root.is_file() {
+        return Ok(vec![root.to_path_buf()]);
+    }
+
+    let mut files = Vec::new();
+    let mut dirs = vec![root.to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        for entry in std::fs::read_dir(&dir).with_context(|| format!("Failed to read {}", dir.display()))? {
+            let path = entry?.path();
+            if path.is_dir() {
+                dirs.push(path);
+            } else {
+                files.push(path);
+            }
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Same configuration and artifacts, ignoring request bookkeeping
+fn same_release(a: &DeploymentRequest, b: &DeploymentRequest) -> bool {
+    release_fields(a) == release_fields(b)
+}
+
+fn release_fields(request: &DeploymentRequest) -> serde_json::Map<String, serde_json::Value> {
+    let mut fields = match serde_json::to_value(request) {
+        Ok(serde_json::Value::Object(fields)) => fields,
+        _ => serde_json::Map::new(),
+    };
+    for key in ["id", "requested_by", "revert_to_version", "created_at"] {
+        fields.remove(key);
+    }
+    fields
+}
+
+/// Human-readable summary of what changed between two deployments
+fn summarize_changes(previous: &DeploymentRequest, current: &DeploymentRequest) -> String {
+    let before = release_fields(previous);
+    let after = release_fields(current);
+    let mut changes = Vec::new();
+
+    let changed_artifacts = current
+        .artifacts
+        .iter()
+        .filter(|a| !previous.artifacts.contains(a))
+        .count();
+    if changed_artifacts > 0 {
+        changes.push(format!("{} artifact(s) updated", changed_artifacts));
+    }
+
+    let changed_fields: Vec<&str> = after
+        .iter()
+        .filter(|(key, value)| key.as_str() != "artifacts" && before.get(key.as_str()) != Some(value))
+        .map(|(key, _)| key.as_str())
+        .collect();
+    if !changed_fields.is_empty() {
+        changes.push(format!("changed {}", changed_fields.join(", ")));
+    }
+
+    if changes.is_empty() {
+        "Redeploy without changes".to_string()
+    } else {
+        changes.join("; ")
+    }
+}
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ValidationRequirements;
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -543,4 +885,122 @@ impl NotificationService {
     fn new() -> Self { Self }
     async fn send_status_update(&self, status: &DeploymentStatus) -> Result<()> { Ok(()) }
     async fn notify_deployment_complete(&self, deployment: &Deployment, channels: &[NotificationChannel]) -> Result<()> { Ok(()) }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::infrastructure::terraform as tf;
+
+    fn scratch_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("aion-orchestrator-{}-{}", name, Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn request(id: Uuid, source_code_path: &Path) -> DeploymentRequest {
+        DeploymentRequest {
+            id,
+            project_name: "storefront".to_string(),
+            source_code_path: source_code_path.to_path_buf(),
+            infrastructure_spec: InfrastructureSpec {
+                name: "storefront".to_string(),
+                description: "Storefront API".to_string(),
+                provider: CloudProvider::Docker,
+                region: "local".to_string(),
+                environment: tf::Environment,
+                application_type: ApplicationType::Ecommerce,
+                architecture_pattern: ArchitecturePattern::Monolithic,
+                scaling_requirements: tf::ScalingRequirements,
+                security_requirements: tf::SecurityRequirements,
+                compliance_frameworks: vec![],
+                budget_constraints: None,
+                performance_targets: tf::PerformanceTargets,
+                availability_requirements: tf::AvailabilityRequirements,
+                disaster_recovery: tf::DisasterRecoveryConfig,
+                monitoring_config: tf::MonitoringConfig,
+                networking_config: tf::NetworkingConfig,
+                storage_config: tf::StorageConfig,
+                compute_config: tf::ComputeConfig,
+                database_config: None,
+                caching_config: None,
+                messaging_config: None,
+                cdn_config: None,
+                tags: HashMap::new(),
+            },
+            deployment_strategy: DeploymentStrategy::Recreate,
+            environment: DeploymentEnvironment::Production,
+            validation_requirements: ValidationRequirements,
+            monitoring_config: MonitoringConfig,
+            rollback_policy: RollbackPolicy { auto_rollback: false },
+            notification_channels: vec![],
+            approval_required: false,
+            auto_scaling: false,
+            cost_limits: None,
+            compliance_checks: vec![],
+            artifacts: vec![],
+            requested_by: "alice".to_string(),
+            revert_to_version: None,
+            created_at: Utc::now(),
+        }
+    }
+
+    /// Deploy two versions of the same source, returning the orchestrator,
+    /// its artifact store root and the deployment id
+    async fn two_versions() -> (DeploymentOrchestrator, PathBuf, Uuid) {
+        let source = scratch_dir("source");
+        let store = scratch_dir("store");
+        let orchestrator = DeploymentOrchestrator::new().with_artifact_store(ArtifactStore::new(&store));
+        let deployment_id = Uuid::new_v4();
+
+        std::fs::write(source.join("app.js"), "console.log('v1')").unwrap();
+        orchestrator.deploy_to_production(request(deployment_id, &source)).await.unwrap();
+        std::fs::write(source.join("app.js"), "console.log('v2')").unwrap();
+        orchestrator.deploy_to_production(request(deployment_id, &source)).await.unwrap();
+
+        (orchestrator, store, deployment_id)
+    }
+
+    #[tokio::test]
+    async fn deploys_record_artifacts_and_repeated_revert_is_a_no_op() {
+        let (orchestrator, _store, deployment_id) = two_versions().await;
+
+        let history = orchestrator.get_deployment_history(deployment_id).await;
+        assert_eq!(history.iter().map(|v| v.version).collect::<Vec<_>>(), vec![2, 1]);
+        assert_eq!(history[0].artifacts.len(), 1);
+        assert_ne!(history[0].artifacts, history[1].artifacts);
+        assert_eq!(history[0].change_summary, "1 artifact(s) updated");
+        assert_eq!(history[1].deployed_by, "alice");
+
+        let reverted = orchestrator.revert_deployment(deployment_id, 1, "bob").await.unwrap();
+        assert!(!reverted.already_current);
+        assert_eq!(reverted.live_version, 3);
+
+        let history = orchestrator.get_deployment_history(deployment_id).await;
+        assert_eq!(history[0].reverted_to, Some(1));
+        assert_eq!(history[0].deployed_by, "bob");
+        assert_eq!(history[0].artifacts, history[2].artifacts);
+
+        // Reverting again finds version 1 already live and deploys nothing
+        let repeated = orchestrator.revert_deployment(deployment_id, 1, "bob").await.unwrap();
+        assert!(repeated.already_current);
+        assert!(repeated.result.is_none());
+        assert_eq!(repeated.live_version, 3);
+        assert_eq!(orchestrator.get_deployment_history(deployment_id).await.len(), 3);
+    }
+
+    #[tokio::test]
+    async fn revert_fails_before_deploying_when_artifacts_were_collected() {
+        let (orchestrator, store, deployment_id) = two_versions().await;
+        let history = orchestrator.get_deployment_history(deployment_id).await;
+        let collected = &history[1].artifacts[0];
+        let (algorithm, hex) = collected.digest.split_once(':').unwrap();
+        std::fs::remove_file(store.join(algorithm).join(hex)).unwrap();
+
+        let err = orchestrator.revert_deployment(deployment_id, 1, "bob").await.unwrap_err();
+        assert!(err.to_string().contains("garbage-collected"));
+        assert!(err.to_string().contains(&collected.digest));
+        assert_eq!(orchestrator.get_deployment_history(deployment_id).await.len(), 2);
+
+        assert!(orchestrator.revert_deployment(deployment_id, 7, "bob").await.is_err());
+    }
+}
@@ -0,0 +1,267 @@
+//! EU VAT under the One Stop Shop (OSS) scheme
+//!
+//! Consumers are charged the VAT of the member state they live in, declared
+//! through the seller's single OSS return. Business customers in another
+//! member state who give a VAT number account for the VAT themselves (reverse
+//! charge), so their invoices carry a zero-rated line naming their country.
+//! Customers in the seller's own country are charged domestic VAT, and
+//! customers outside the EU are out of scope.
+//!
+//! Rates come from `TaxRule`s of type `VAT`, one per destination country,
+//! with `rate` as a percentage (19 for 19%).
+
+use crate::{Address, LineItem, LineItemType, Result, TaxClassification, TaxInformation, TaxLineItem, TaxRule, TaxType};
+use chrono::{DateTime, Utc};
+use rust_decimal::{Decimal, RoundingStrategy};
+use uuid::Uuid;
+
+/// EU member states by ISO 3166-1 alpha-2 code
+pub const EU_MEMBER_STATES: &[&str] = &[
+    "AT", "BE", "BG", "CY", "CZ", "DE", "DK", "EE", "ES", "FI", "FR", "GR", "HR", "HU", "IE", "IT", "LT", "LU",
+    "LV", "MT", "NL", "PL", "PT", "RO", "SE", "SI", "SK",
+];
+
+pub struct OssVatCalculator {
+    /// Member state the seller is established in
+    seller_country: String,
+    rules: Vec<TaxRule>,
+    reverse_charge_enabled: bool,
+}
+
+impl OssVatCalculator {
+    pub fn new(seller_country: &str, rules: Vec<TaxRule>) -> Self {
+        Self {
+            seller_country: country_code(seller_country),
+            rules,
+            reverse_charge_enabled: true,
+        }
+    }
+
+    /// Charge VAT to cross-border business customers as well
+    pub fn with_reverse_charge(mut self, enabled: bool) -> Self {
+        self.reverse_charge_enabled = enabled;
+        self
+    }
+
+    /// VAT owed on `line_items` by a customer, as of `at`. The customer's tax
+    /// address decides the destination when set, the billing address otherwise.
+    pub fn calculate(
+        &self,
+        tax_info: &TaxInformation,
+        billing_address: &Address,
+        line_items: &[LineItem],
+        at: DateTime<Utc>,
+    ) -> Result<Vec<TaxLineItem>> {
+        let destination = country_code(&tax_info.tax_address.as_ref().unwrap_or(billing_address).country);
+        if destination.is_empty() {
+            return Err("Customer address has no country to determine VAT".into());
+        }
+        if !EU_MEMBER_STATES.contains(&destination.as_str()) {
+            return Ok(Vec::new());
+        }
+
+        let taxable_amount: Decimal = line_items
+            .iter()
+            .filter(|item| !matches!(item.item_type, LineItemType::Tax | LineItemType::Discount))
+            .map(|item| item.total_amount - item.discount_amount)
+            .sum();
+        let rule = self.rule_for(&destination, at);
+
+        if self.reverse_charge_applies(tax_info, &destination) {
+            return Ok(vec![TaxLineItem {
+                tax_rule_id: rule.map_or(Uuid::nil(), |rule| rule.id),
+                tax_name: "VAT reverse charge".to_string(),
+                tax_rate: Decimal::ZERO,
+                taxable_amount,
+                tax_amount: Decimal::ZERO,
+                jurisdiction: destination,
+            }]);
+        }
+
+        let rule = rule.ok_or_else(|| format!("No VAT rate configured for {}", destination))?;
+        let tax_amount = (taxable_amount * rule.rate / Decimal::ONE_HUNDRED)
+            .round_dp_with_strategy(2, RoundingStrategy::MidpointAwayFromZero);
+        Ok(vec![TaxLineItem {
+            tax_rule_id: rule.id,
+            tax_name: rule.name.clone(),
+            tax_rate: rule.rate,
+            taxable_amount,
+            tax_amount,
+            jurisdiction: destination,
+        }])
+    }
+
+    /// Business customers in another member state with a VAT number issued there
+    fn reverse_charge_applies(&self, tax_info: &TaxInformation, destination: &str) -> bool {
+        if !self.reverse_charge_enabled || destination == self.seller_country {
+            return false;
+        }
+        if matches!(tax_info.tax_classification, TaxClassification::Individual) {
+            return false;
+        }
+        tax_info
+            .vat_number
+            .as_deref()
+            .is_some_and(|vat_number| vat_number_matches(vat_number, destination))
+    }
+
+    /// The VAT rule for `country` in force at `at`, the most recent if several are
+    fn rule_for(&self, country: &str, at: DateTime<Utc>) -> Option<&TaxRule> {
+        self.rules
+            .iter()
+            .filter(|rule| matches!(rule.tax_type, TaxType::VAT))
+            .filter(|rule| country_code(&rule.jurisdiction.country) == country)
+            .filter(|rule| rule.effective_date <= at && !rule.expiry_date.is_some_and(|expiry| expiry <= at))
+            .max_by_key(|rule| rule.effective_date)
+    }
+}
+
+/// Uppercase ISO code, with Greece's VAT prefix `EL` mapped to `GR`
+fn country_code(country: &str) -> String {
+    match country.trim().to_ascii_uppercase().as_str() {
+        "EL" => "GR".to_string(),
+        code => code.to_string(),
+    }
+}
+
+/// Whether `vat_number` is shaped like one issued by `country`: its prefix
+/// followed by 2 to 13 letters and digits. Spaces, dots and dashes are ignored.
+fn vat_number_matches(vat_number: &str, country: &str) -> bool {
+    let normalized: String = vat_number
+        .chars()
+        .filter(|c| !matches!(c, ' ' | '.' | '-'))
+        .map(|c| c.to_ascii_uppercase())
+        .collect();
+    if normalized.len() < 4 || !normalized.is_char_boundary(2) {
+        return false;
+    }
+    let (prefix, number) = normalized.split_at(2);
+    country_code(prefix) == country && number.len() <= 13 && number.chars().all(|c| c.is_ascii_alphanumeric())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{TaxApplicability, TaxJurisdiction};
+    use chrono::Duration;
+    use std::collections::HashMap;
+
+    fn vat_rule(country: &str, rate: i64) -> TaxRule {
+        TaxRule {
+            id: Uuid::new_v4(),
+            name: format!("VAT {}", country),
+            tax_type: TaxType::VAT,
+            rate: Decimal::from(rate),
+            jurisdiction: TaxJurisdiction {
+                country: country.to_string(),
+                state_province: None,
+                city: None,
+                postal_codes: Vec::new(),
+            },
+            applicability: TaxApplicability {
+                product_categories: Vec::new(),
+                customer_types: Vec::new(),
+                transaction_types: Vec::new(),
+                amount_thresholds: None,
+            },
+            effective_date: Utc::now() - Duration::days(365),
+            expiry_date: None,
+        }
+    }
+
+    fn calculator() -> OssVatCalculator {
+        OssVatCalculator::new("DE", vec![vat_rule("DE", 19), vat_rule("IE", 23)])
+    }
+
+    fn address(country: &str) -> Address {
+        Address {
+            line1: "1 Main Street".to_string(),
+            line2: None,
+            city: "City".to_string(),
+            state_province: None,
+            postal_code: "10115".to_string(),
+            country: country.to_string(),
+            latitude: None,
+            longitude: None,
+        }
+    }
+
+    fn tax_info(classification: TaxClassification, vat_number: Option<&str>) -> TaxInformation {
+        TaxInformation {
+            tax_id: None,
+            tax_exempt: false,
+            tax_exemption_certificate: None,
+            vat_number: vat_number.map(str::to_string),
+            tax_classification: classification,
+            tax_address: None,
+            reverse_charge_applicable: vat_number.is_some(),
+        }
+    }
+
+    fn line_items() -> Vec<LineItem> {
+        let item = |total: i64, discount: i64| LineItem {
+            id: Uuid::new_v4(),
+            description: "Subscription - pro".to_string(),
+            quantity: Decimal::ONE,
+            unit_amount: Decimal::from(total),
+            total_amount: Decimal::from(total),
+            tax_amount: Decimal::ZERO,
+            discount_amount: Decimal::from(discount),
+            item_type: LineItemType::Subscription,
+            period_start: None,
+            period_end: None,
+            metadata: HashMap::new(),
+        };
+        vec![item(100, 10), item(15, 0)]
+    }
+
+    #[test]
+    fn german_consumer_pays_german_vat() {
+        let lines = calculator()
+            .calculate(&tax_info(TaxClassification::Individual, None), &address("de"), &line_items(), Utc::now())
+            .unwrap();
+
+        assert_eq!(lines.len(), 1);
+        assert_eq!(lines[0].jurisdiction, "DE");
+        assert_eq!(lines[0].tax_rate, Decimal::from(19));
+        assert_eq!(lines[0].taxable_amount, Decimal::from(105));
+        assert_eq!(lines[0].tax_amount, Decimal::new(1995, 2));
+    }
+
+    #[test]
+    fn irish_business_with_vat_number_is_reverse_charged() {
+        let business = tax_info(TaxClassification::Business, Some("IE 6388047V"));
+        let lines = calculator().calculate(&business, &address("IE"), &line_items(), Utc::now()).unwrap();
+
+        assert_eq!(lines.len(), 1);
+        assert_eq!(lines[0].jurisdiction, "IE");
+        assert_eq!(lines[0].tax_amount, Decimal::ZERO);
+        assert_eq!(lines[0].taxable_amount, Decimal::from(105));
+        assert_eq!(lines[0].tax_name, "VAT reverse charge");
+
+        // Without a VAT number, or with one from another country, Irish VAT is charged
+        for tax_info in [
+            tax_info(TaxClassification::Business, None),
+            tax_info(TaxClassification::Business, Some("DE123456789")),
+        ] {
+            let lines = calculator().calculate(&tax_info, &address("IE"), &line_items(), Utc::now()).unwrap();
+            assert_eq!(lines[0].tax_amount, Decimal::new(2415, 2));
+        }
+
+        // Domestic business customers are charged domestic VAT
+        let german = tax_info(TaxClassification::Business, Some("DE123456789"));
+        let lines = calculator().calculate(&german, &address("DE"), &line_items(), Utc::now()).unwrap();
+        assert_eq!(lines[0].tax_amount, Decimal::new(1995, 2));
+    }
+
+    #[test]
+    fn missing_destination_rate_is_an_error() {
+        let consumer = tax_info(TaxClassification::Individual, None);
+        let error = calculator().calculate(&consumer, &address("FR"), &line_items(), Utc::now()).unwrap_err();
+        assert_eq!(error.to_string(), "No VAT rate configured for FR");
+
+        // Customers outside the EU are not charged EU VAT
+        let lines = calculator().calculate(&consumer, &address("US"), &line_items(), Utc::now()).unwrap();
+        assert!(lines.is_empty());
+    }
+}
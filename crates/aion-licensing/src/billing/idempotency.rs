@@ -0,0 +1,427 @@
+//! Idempotent payment processing
+//!
+//! A `PaymentRequest` carrying an `idempotency_key` is charged at most once
+//! per key within the TTL: a retry gets the original `PaymentResult` back, and
+//! a retry that arrives while the first attempt is still running waits for
+//! it. Reusing a key for a different customer, amount or currency is an error.
+//!
+//! The key is also what the charge sends to the provider as its own
+//! idempotency key, so the provider deduplicates too. That makes it safe to
+//! run a charge again when its outcome is unknown: an attempt holds the key
+//! only for a short lease, and once that lapses (the process crashed, the
+//! request future was dropped, the provider call errored) a retry charges
+//! again under the same provider key. Only a definitive decline releases the
+//! key; the next attempt then gets a fresh provider key, since providers
+//! remember declines as well.
+
+use crate::{PaymentRequest, PaymentResult, PaymentStatus, Result};
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+
+/// How long a key is remembered after it is first used
+pub const DEFAULT_IDEMPOTENCY_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// How long an attempt holds a key before a retry may take it over
+pub const DEFAULT_IDEMPOTENCY_LEASE: Duration = Duration::from_secs(2 * 60);
+
+/// Error a charge returns when the provider definitively declined the
+/// payment, as opposed to failing in a way that leaves the outcome unknown
+#[derive(Debug, thiserror::Error)]
+#[error("Payment declined: {reason}")]
+pub struct PaymentDeclined {
+    pub reason: String,
+}
+
+/// Outcome of claiming an idempotency key
+#[derive(Debug, Clone)]
+pub enum IdempotencyClaim {
+    /// The caller must charge with `provider_key` and then `complete` or
+    /// `release` the key
+    Acquired { provider_key: String },
+    /// Another attempt holds the key and its lease has not lapsed
+    InProgress,
+    /// A payment already completed under the key
+    Completed(PaymentResult),
+}
+
+/// Storage for idempotency keys and the payments made under them
+#[async_trait]
+pub trait IdempotencyStore: Send + Sync {
+    /// Atomically claim `key` for a request with `fingerprint`, remembering
+    /// it for `ttl` and holding it for `lease`. Fails if the key was used for
+    /// a different request that was not declined.
+    async fn claim(&self, key: &str, fingerprint: &str, ttl: Duration, lease: Duration) -> Result<IdempotencyClaim>;
+    /// Record the result of the payment made under a claimed key
+    async fn complete(&self, key: &str, result: &PaymentResult) -> Result<()>;
+    /// Free a claimed key whose payment was declined, so it can be charged
+    /// again under a new provider key
+    async fn release(&self, key: &str) -> Result<()>;
+}
+
+struct IdempotencyEntry {
+    fingerprint: String,
+    expires_at: Instant,
+    /// Attempts that ended in a decline; part of the provider key
+    declines: u32,
+    /// Set while an attempt holds the key
+    lease_until: Option<Instant>,
+    result: Option<PaymentResult>,
+}
+
+impl IdempotencyEntry {
+    fn provider_key(&self, key: &str) -> String {
+        match self.declines {
+            0 => key.to_string(),
+            declines => format!("{}:retry-{}", key, declines),
+        }
+    }
+}
+
+/// Keys kept in memory for the life of the process
+#[derive(Default)]
+pub struct InMemoryIdempotencyStore {
+    entries: Mutex<HashMap<String, IdempotencyEntry>>,
+}
+
+impl InMemoryIdempotencyStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl IdempotencyStore for InMemoryIdempotencyStore {
+    async fn claim(&self, key: &str, fingerprint: &str, ttl: Duration, lease: Duration) -> Result<IdempotencyClaim> {
+        let mut entries = self.entries.lock().await;
+        let now = Instant::now();
+        entries.retain(|_, entry| entry.expires_at > now);
+
+        let Some(entry) = entries.get_mut(key) else {
+            let entry = IdempotencyEntry {
+                fingerprint: fingerprint.to_string(),
+                expires_at: now + ttl,
+                declines: 0,
+                lease_until: Some(now + lease),
+                result: None,
+            };
+            let provider_key = entry.provider_key(key);
+            entries.insert(key.to_string(), entry);
+            return Ok(IdempotencyClaim::Acquired { provider_key });
+        };
+
+        // After a decline the client may retry with another payment method
+        let declined = entry.result.is_none() && entry.lease_until.is_none();
+        if entry.fingerprint != fingerprint && !declined {
+            return Err(format!("Idempotency key {} was already used for a different payment", key).into());
+        }
+        if let Some(result) = &entry.result {
+            return Ok(IdempotencyClaim::Completed(result.clone()));
+        }
+        if entry.lease_until.is_some_and(|lease_until| lease_until > now) {
+            return Ok(IdempotencyClaim::InProgress);
+        }
+
+        // Declined, or the last attempt's lease lapsed with the outcome
+        // unknown; the latter is retried under the same provider key
+        entry.fingerprint = fingerprint.to_string();
+        entry.lease_until = Some(now + lease);
+        Ok(IdempotencyClaim::Acquired { provider_key: entry.provider_key(key) })
+    }
+
+    async fn complete(&self, key: &str, result: &PaymentResult) -> Result<()> {
+        let mut entries = self.entries.lock().await;
+        let entry = entries
+            .get_mut(key)
+            .ok_or_else(|| format!("Idempotency key {} is not claimed", key))?;
+        entry.result = Some(result.clone());
+        entry.lease_until = None;
+        Ok(())
+    }
+
+    async fn release(&self, key: &str) -> Result<()> {
+        if let Some(entry) = self.entries.lock().await.get_mut(key) {
+            entry.declines += 1;
+            entry.lease_until = None;
+        }
+        Ok(())
+    }
+}
+
+/// Runs payments through an `IdempotencyStore`
+pub struct PaymentIdempotency {
+    store: Arc<dyn IdempotencyStore>,
+    ttl: Duration,
+    lease: Duration,
+    /// How long a retry waits for an attempt in progress before giving up
+    max_wait: Duration,
+    poll_interval: Duration,
+}
+
+impl PaymentIdempotency {
+    pub fn new(store: Arc<dyn IdempotencyStore>) -> Self {
+        Self {
+            store,
+            ttl: DEFAULT_IDEMPOTENCY_TTL,
+            lease: DEFAULT_IDEMPOTENCY_LEASE,
+            max_wait: Duration::from_secs(30),
+            poll_interval: Duration::from_millis(50),
+        }
+    }
+
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    /// How long an attempt may run before a retry charges again under the
+    /// same provider key; should exceed the provider's request timeout
+    pub fn with_lease(mut self, lease: Duration) -> Self {
+        self.lease = lease;
+        self
+    }
+
+    /// Run `charge` for `request` unless a payment under its idempotency key
+    /// exists, in which case that payment's result is returned. `charge` gets
+    /// the key to send to the provider as its idempotency key. Requests
+    /// without a key are always charged, under a key of their own.
+    pub async fn process<F, Fut>(&self, request: &PaymentRequest, charge: F) -> Result<PaymentResult>
+    where
+        F: FnOnce(String) -> Fut,
+        Fut: Future<Output = Result<PaymentResult>>,
+    {
+        let Some(key) = request.idempotency_key.as_deref() else {
+            return charge(uuid::Uuid::new_v4().to_string()).await;
+        };
+        let fingerprint = fingerprint(request);
+        let deadline = Instant::now() + self.max_wait;
+
+        let provider_key = loop {
+            match self.store.claim(key, &fingerprint, self.ttl, self.lease).await? {
+                IdempotencyClaim::Acquired { provider_key } => break provider_key,
+                IdempotencyClaim::Completed(result) => {
+                    tracing::info!("Returning payment {} for idempotency key {}", result.payment_id, key);
+                    return Ok(result);
+                }
+                IdempotencyClaim::InProgress if Instant::now() >= deadline => {
+                    return Err(format!("Payment with idempotency key {} is still in progress", key).into());
+                }
+                IdempotencyClaim::InProgress => tokio::time::sleep(self.poll_interval).await,
+            }
+        };
+
+        match charge(provider_key).await {
+            Ok(result) if is_declined(&result.status) => {
+                self.store.release(key).await?;
+                Ok(result)
+            }
+            Ok(result) => {
+                self.store.complete(key, &result).await?;
+                Ok(result)
+            }
+            Err(error) if error.downcast_ref::<PaymentDeclined>().is_some() => {
+                self.store.release(key).await?;
+                Err(error)
+            }
+            Err(error) => {
+                // The provider may have charged; keep the key until the lease
+                // lapses so a retry goes through the provider's deduplication
+                tracing::warn!("Payment with idempotency key {} ended with an unknown outcome: {}", key, error);
+                Err(error)
+            }
+        }
+    }
+}
+
+impl Default for PaymentIdempotency {
+    fn default() -> Self {
+        Self::new(Arc::new(InMemoryIdempotencyStore::new()))
+    }
+}
+
+/// Statuses after which the payment can be attempted again
+fn is_declined(status: &PaymentStatus) -> bool {
+    matches!(status, PaymentStatus::Failed | PaymentStatus::Canceled | PaymentStatus::RequiresPaymentMethod)
+}
+
+/// What must not change between retries of a payment
+fn fingerprint(request: &PaymentRequest) -> String {
+    format!(
+        "{}:{}:{:?}:{}",
+        request.customer_id, request.amount, request.currency, request.payment_method_id
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Currency, PaymentProviderType, PaymentRiskAssessment, ProviderResponse, RiskAction, RiskLevel};
+    use chrono::Utc;
+    use rust_decimal::Decimal;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use uuid::Uuid;
+
+    fn request(idempotency_key: Option<&str>) -> PaymentRequest {
+        PaymentRequest {
+            customer_id: Uuid::new_v4(),
+            amount: Decimal::new(4900, 2),
+            currency: Currency::USD,
+            payment_method_id: Uuid::new_v4(),
+            description: "Pro plan".to_string(),
+            metadata: HashMap::new(),
+            capture: true,
+            statement_descriptor: None,
+            idempotency_key: idempotency_key.map(str::to_string),
+        }
+    }
+
+    fn result(request: &PaymentRequest, status: PaymentStatus, provider_key: String) -> PaymentResult {
+        PaymentResult {
+            payment_id: Uuid::new_v4(),
+            status,
+            amount_captured: request.amount,
+            amount_refunded: Decimal::ZERO,
+            fees: Vec::new(),
+            risk_assessment: PaymentRiskAssessment {
+                risk_score: 0.0,
+                risk_level: RiskLevel::Low,
+                factors: Vec::new(),
+                recommended_action: RiskAction::Approve,
+            },
+            provider_response: ProviderResponse {
+                provider: PaymentProviderType::Stripe,
+                transaction_id: provider_key,
+                response_code: "succeeded".to_string(),
+                response_message: String::new(),
+                raw_response: serde_json::Value::Null,
+            },
+            created_at: Utc::now(),
+        }
+    }
+
+    /// Charges by counting, taking a while like a real provider would; the
+    /// provider key is reported as the transaction id
+    async fn charge(request: &PaymentRequest, charges: &AtomicUsize, provider_key: String) -> Result<PaymentResult> {
+        charges.fetch_add(1, Ordering::SeqCst);
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        Ok(result(request, PaymentStatus::Succeeded, provider_key))
+    }
+
+    fn short_lease() -> PaymentIdempotency {
+        PaymentIdempotency {
+            max_wait: Duration::from_millis(20),
+            poll_interval: Duration::from_millis(5),
+            ..PaymentIdempotency::default()
+        }
+        .with_lease(Duration::from_millis(100))
+    }
+
+    #[tokio::test]
+    async fn concurrent_retries_charge_once() {
+        let idempotency = PaymentIdempotency::default();
+        let charges = AtomicUsize::new(0);
+        let request = request(Some("order-42"));
+
+        let (first, second) = tokio::join!(
+            idempotency.process(&request, |key| charge(&request, &charges, key)),
+            idempotency.process(&request, |key| charge(&request, &charges, key)),
+        );
+        let (first, second) = (first.unwrap(), second.unwrap());
+        assert_eq!(charges.load(Ordering::SeqCst), 1);
+        assert_eq!(first.payment_id, second.payment_id);
+        // The client's key is what the provider sees
+        assert_eq!(first.provider_response.transaction_id, "order-42");
+
+        // A later retry gets the same payment too
+        let third = idempotency.process(&request, |key| charge(&request, &charges, key)).await.unwrap();
+        assert_eq!(third.payment_id, first.payment_id);
+        assert_eq!(charges.load(Ordering::SeqCst), 1);
+    }
+
+    async fn decline(_provider_key: String) -> Result<PaymentResult> {
+        Err(PaymentDeclined { reason: "insufficient funds".to_string() }.into())
+    }
+
+    #[tokio::test]
+    async fn key_reuse_and_declines() {
+        let idempotency = PaymentIdempotency::default();
+        let charges = AtomicUsize::new(0);
+        let keyed = request(Some("order-43"));
+
+        assert!(idempotency.process(&keyed, decline).await.is_err());
+        // The decline released the key; the retry needs a new provider key
+        // because the provider remembers the decline
+        let paid = idempotency.process(&keyed, |key| charge(&keyed, &charges, key)).await.unwrap();
+        assert_eq!(charges.load(Ordering::SeqCst), 1);
+        assert_eq!(paid.provider_response.transaction_id, "order-43:retry-1");
+
+        let mut other = keyed.clone();
+        other.amount = Decimal::new(9900, 2);
+        assert!(idempotency.process(&other, |key| charge(&other, &charges, key)).await.is_err());
+
+        // Requests without a key are never deduplicated
+        let unkeyed = request(None);
+        idempotency.process(&unkeyed, |key| charge(&unkeyed, &charges, key)).await.unwrap();
+        idempotency.process(&unkeyed, |key| charge(&unkeyed, &charges, key)).await.unwrap();
+        assert_eq!(charges.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn declined_status_releases_the_key() {
+        let idempotency = PaymentIdempotency::default();
+        let keyed = request(Some("order-44"));
+
+        let declined = idempotency
+            .process(&keyed, |key| {
+                let declined = result(&keyed, PaymentStatus::Failed, key);
+                async move { Ok(declined) }
+            })
+            .await
+            .unwrap();
+        assert!(matches!(declined.status, PaymentStatus::Failed));
+
+        // A different card may be tried under the same key
+        let mut other_card = keyed.clone();
+        other_card.payment_method_id = Uuid::new_v4();
+        let charges = AtomicUsize::new(0);
+        idempotency.process(&other_card, |key| charge(&other_card, &charges, key)).await.unwrap();
+        assert_eq!(charges.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn unknown_outcome_holds_the_key_until_the_lease_lapses() {
+        let idempotency = short_lease();
+        let charges = AtomicUsize::new(0);
+        let keyed = request(Some("order-45"));
+
+        let timed_out = idempotency.process(&keyed, |_key| async { Err("connection reset".into()) }).await;
+        assert!(timed_out.is_err());
+        // The provider may have charged, so a prompt retry must not charge again
+        assert!(idempotency.process(&keyed, |key| charge(&keyed, &charges, key)).await.is_err());
+        assert_eq!(charges.load(Ordering::SeqCst), 0);
+
+        tokio::time::sleep(Duration::from_millis(120)).await;
+        let paid = idempotency.process(&keyed, |key| charge(&keyed, &charges, key)).await.unwrap();
+        // Same provider key, so the provider would return the first charge
+        assert_eq!(paid.provider_response.transaction_id, "order-45");
+        assert_eq!(charges.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn dropped_attempt_does_not_hold_the_key_for_the_ttl() {
+        let idempotency = short_lease();
+        let charges = AtomicUsize::new(0);
+        let keyed = request(Some("order-46"));
+
+        let abandoned = idempotency.process(&keyed, |_key| std::future::pending());
+        assert!(tokio::time::timeout(Duration::from_millis(20), abandoned).await.is_err());
+
+        tokio::time::sleep(Duration::from_millis(120)).await;
+        let paid = idempotency.process(&keyed, |key| charge(&keyed, &charges, key)).await.unwrap();
+        assert_eq!(paid.provider_response.transaction_id, "order-46");
+        assert_eq!(charges.load(Ordering::SeqCst), 1);
+    }
+}
@@ -1,10 +1,14 @@
 pub mod engine;
+pub mod eu_vat;
+pub mod idempotency;
 pub mod invoice_generator;
 pub mod payment_processor;
 pub mod subscription_manager;
 pub mod usage_calculator;
 
 pub use engine::*;
+pub use eu_vat::*;
+pub use idempotency::*;
 pub use invoice_generator::*;
 pub use payment_processor::*;
 pub use subscription_manager::*;
@@ -13,9 +17,10 @@ pub use usage_calculator::*;
 use crate::{
     Customer, Subscription, Invoice, PaymentRequest, PaymentResult, BillingManager,
     SubscriptionChanges, CancellationRequest, InvoiceRequest, BillingPeriod, UsageCharge,
-    LineItem, TaxLineItem, Result
+    LineItem, TaxLineItem, TaxConfiguration, TaxRule, EuVatHandling, Result
 };
 use std::collections::HashMap;
+use std::sync::Arc;
 use uuid::Uuid;
 use async_trait::async_trait;
 use rust_decimal::Decimal;
@@ -30,6 +35,7 @@ pub struct ComprehensiveBillingEngine {
     discount_engine: DiscountEngine,
     compliance_checker: ComplianceChecker,
     audit_logger: AuditLogger,
+    payment_idempotency: PaymentIdempotency,
 }
 
 impl ComprehensiveBillingEngine {
@@ -43,9 +49,21 @@ impl ComprehensiveBillingEngine {
             discount_engine: DiscountEngine::new(),
             compliance_checker: ComplianceChecker::new(),
             audit_logger: AuditLogger::new(),
+            payment_idempotency: PaymentIdempotency::default(),
         }
     }
 
+    pub fn with_tax_calculator(mut self, tax_calculator: TaxCalculator) -> Self {
+        self.tax_calculator = tax_calculator;
+        self
+    }
+
+    /// Keep payment idempotency keys in `store` rather than in memory
+    pub fn with_idempotency_store(mut self, store: Arc<dyn IdempotencyStore>) -> Self {
+        self.payment_idempotency = PaymentIdempotency::new(store);
+        self
+    }
+
     async fn calculate_subscription_charges(
         &self,
         subscription: &Subscription,
@@ -139,7 +157,8 @@ impl ComprehensiveBillingEngine {
         customer_id: Uuid,
         line_items: &[LineItem],
     ) -> Result<Vec<TaxLineItem>> {
-        self.tax_calculator.calculate_taxes(customer_id, line_items).await
+        let customer = self.get_customer(customer_id).await?;
+        self.tax_calculator.calculate_taxes(&customer, line_items).await
     }
 
     async fn validate_compliance(
@@ -278,7 +297,14 @@ impl BillingManager for ComprehensiveBillingEngine {
     }
 
     async fn process_payment(&self, payment_request: PaymentRequest) -> Result<PaymentResult> {
-        self.payment_processor.process_payment(payment_request).await
+        self.payment_idempotency
+            .process(&payment_request, |provider_key| {
+                // The processor sends the request's key on to the provider
+                let mut request = payment_request.clone();
+                request.idempotency_key = Some(provider_key);
+                self.payment_processor.process_payment(request)
+            })
+            .await
     }
 
     async fn generate_invoice(&self, invoice_request: InvoiceRequest) -> Result<Invoice> {
@@ -294,7 +320,7 @@ impl BillingManager for ComprehensiveBillingEngine {
     }
 
     async fn calculate_taxes(&self, customer_id: Uuid, charges: &[LineItem]) -> Result<Vec<TaxLineItem>> {
-        self.tax_calculator.calculate_taxes(customer_id, charges).await
+        self.calculate_taxes_for_line_items(customer_id, charges).await
     }
 }
 
@@ -433,18 +459,30 @@ pub enum BillingEventType {
 }
 
 pub struct TaxCalculator {
-    // Tax calculation implementation
+    /// Set when EU VAT is handled through the One Stop Shop
+    oss: Option<OssVatCalculator>,
 }
 
 impl TaxCalculator {
     pub fn new() -> Self {
-        Self {}
+        Self { oss: None }
     }
 
-    pub async fn calculate_taxes(&self, customer_id: Uuid, line_items: &[LineItem]) -> Result<Vec<TaxLineItem>> {
-        // Implementation would calculate taxes based on customer location and line items
-        tracing::info!("Calculating taxes for customer: {}", customer_id);
-        Ok(Vec::new())
+    /// Calculator for `configuration`, with VAT rates from `vat_rules` for a
+    /// seller established in `seller_country`
+    pub fn from_configuration(configuration: &TaxConfiguration, seller_country: &str, vat_rules: Vec<TaxRule>) -> Self {
+        let oss = (configuration.enabled && matches!(configuration.eu_vat_handling, EuVatHandling::Oss)).then(|| {
+            OssVatCalculator::new(seller_country, vat_rules).with_reverse_charge(configuration.reverse_charge_enabled)
+        });
+        Self { oss }
+    }
+
+    pub async fn calculate_taxes(&self, customer: &Customer, line_items: &[LineItem]) -> Result<Vec<TaxLineItem>> {
+        tracing::info!("Calculating taxes for customer: {}", customer.id);
+        match &self.oss {
+            Some(oss) => oss.calculate(&customer.tax_info, &customer.billing_info.billing_address, line_items, Utc::now()),
+            None => Ok(Vec::new()),
+        }
     }
 }
 
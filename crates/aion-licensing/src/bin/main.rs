@@ -132,6 +132,25 @@ async fn main() -> Result<()> {
                                 .required(true)
                         )
                 )
+                .subcommand(
+                    SubCommand::with_name("deactivate")
+                        .about("Deactivate a license on one machine, or on all machines")
+                        .arg(
+                            Arg::with_name("key")
+                                .long("key")
+                                .value_name("LICENSE_KEY")
+                                .help("License key to deactivate")
+                                .takes_value(true)
+                                .required(true)
+                        )
+                        .arg(
+                            Arg::with_name("machine-id")
+                                .long("machine-id")
+                                .value_name("MACHINE_ID")
+                                .help("Machine fingerprint to free; all machines if omitted")
+                                .takes_value(true)
+                        )
+                )
                 .subcommand(
                     SubCommand::with_name("revoke")
                         .about("Revoke a license")
@@ -592,6 +611,17 @@ async fn handle_license_commands(
             license_manager.activate_license(license_key, activation_data).await?;
             println!("License activated successfully!");
         },
+        ("deactivate", Some(deactivate_matches)) => {
+            let license_key = deactivate_matches.value_of("key").unwrap();
+
+            if let Some(machine_id) = deactivate_matches.value_of("machine-id") {
+                license_manager.deactivate_installation(license_key, machine_id).await?;
+                println!("License deactivated on machine {}", machine_id);
+            } else {
+                license_manager.deactivate_license(license_key).await?;
+                println!("License deactivated on all machines");
+            }
+        },
         ("revoke", Some(revoke_matches)) => {
             let license_key = revoke_matches.value_of("key").unwrap();
             let reason_str = revoke_matches.value_of("reason").unwrap();
@@ -764,6 +794,7 @@ async fn handle_billing_commands(
                 metadata: HashMap::new(),
                 capture: true,
                 statement_descriptor: None,
+                idempotency_key: None,
             };
 
             let payment_result = billing_engine.process_payment(payment_request).await?;
@@ -1221,6 +1252,7 @@ fn create_license(
         expires_at,
         last_verified: None,
         status: LicenseStatus::PendingActivation,
+        signature: None,
     })
 }
 
@@ -1242,6 +1274,7 @@ fn get_default_features_for_tier(tier: &LicenseTier) -> Vec<aion_licensing::Feat
                     max_bandwidth_gb: Some(10),
                     rate_limits: HashMap::new(),
                     custom_limits: HashMap::new(),
+                    limit_period: None,
                 }),
                 expires_at: None,
                 usage_tracking: true,
@@ -1261,6 +1294,7 @@ fn get_default_features_for_tier(tier: &LicenseTier) -> Vec<aion_licensing::Feat
                     max_bandwidth_gb: Some(100),
                     rate_limits: HashMap::new(),
                     custom_limits: HashMap::new(),
+                    limit_period: None,
                 }),
                 expires_at: None,
                 usage_tracking: true,
@@ -1288,6 +1322,7 @@ fn get_default_features_for_tier(tier: &LicenseTier) -> Vec<aion_licensing::Feat
                     max_bandwidth_gb: Some(1000),
                     rate_limits: HashMap::new(),
                     custom_limits: HashMap::new(),
+                    limit_period: None,
                 }),
                 expires_at: None,
                 usage_tracking: true,
@@ -1323,6 +1358,7 @@ fn get_default_features_for_tier(tier: &LicenseTier) -> Vec<aion_licensing::Feat
                     max_bandwidth_gb: Some(10000),
                     rate_limits: HashMap::new(),
                     custom_limits: HashMap::new(),
+                    limit_period: None,
                 }),
                 expires_at: None,
                 usage_tracking: true,
@@ -234,6 +234,10 @@ pub struct License {
     pub expires_at: Option<DateTime<Utc>>,
     pub last_verified: Option<DateTime<Utc>>,
     pub status: LicenseStatus,
+    /// Base64 Ed25519 signature over the canonical serialization of every
+    /// other field, set when the license is issued by a signing manager
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -280,6 +284,9 @@ pub struct FeatureLimitations {
     pub max_bandwidth_gb: Option<u32>,
     pub rate_limits: HashMap<String, RateLimit>,
     pub custom_limits: HashMap<String, serde_json::Value>,
+    /// Limits apply per window of this size rather than over the license's lifetime
+    #[serde(default)]
+    pub limit_period: Option<Granularity>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -957,10 +964,13 @@ pub trait LicensingManager {
     async fn validate_license(&self, license_key: &str) -> Result<LicenseValidationResult>;
     async fn activate_license(&self, license_key: &str, activation_data: ActivationData) -> Result<()>;
     async fn deactivate_license(&self, license_key: &str) -> Result<()>;
+    async fn deactivate_installation(&self, license_key: &str, machine_fingerprint: &str) -> Result<()>;
     async fn renew_license(&self, license_key: &str, renewal_period: chrono::Duration) -> Result<()>;
     async fn revoke_license(&self, license_key: &str, reason: RevocationReason) -> Result<()>;
     async fn transfer_license(&self, license_key: &str, new_customer_id: Uuid) -> Result<()>;
     async fn get_license_usage(&self, license_key: &str) -> Result<UsageStatistics>;
+    /// Usage rolled up per metric and `window`, oldest window first
+    async fn aggregate_usage(&self, license_key: &str, window: Granularity) -> Result<Vec<UsageBucket>>;
     async fn enforce_license_limits(&self, license_key: &str, resource: &str, amount: u64) -> Result<bool>;
 }
 
@@ -1012,6 +1022,29 @@ pub struct UsageDataPoint {
     pub metadata: HashMap<String, String>,
 }
 
+/// Window size usage is aggregated over; windows are aligned to UTC hours,
+/// days and calendar months
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Granularity {
+    Hour,
+    Day,
+    Month,
+}
+
+/// Usage of one metric within one window
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UsageBucket {
+    pub metric: String,
+    pub window_start: DateTime<Utc>,
+    pub window_end: DateTime<Utc>,
+    /// Sum of the values recorded in the window
+    pub total: u64,
+    /// Largest single value recorded in the window
+    pub peak: u64,
+    pub data_points: u32,
+}
+
 #[async_trait::async_trait]
 pub trait BillingManager {
     async fn create_customer(&self, customer: Customer) -> Result<Uuid>;
@@ -1466,6 +1499,9 @@ pub struct PaymentRequest {
     pub metadata: HashMap<String, String>,
     pub capture: bool,
     pub statement_descriptor: Option<String>,
+    /// Retries with the same key return the original payment instead of charging again
+    #[serde(default)]
+    pub idempotency_key: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -1,16 +1,22 @@
 use crate::{
     License, LicenseValidationResult, ActivationData, RevocationReason, UsageStatistics,
-    LicensingManager, LicenseStatus, Feature, UsageDataPoint, Result
+    LicensingManager, LicenseStatus, Feature, UsageDataPoint, Granularity, UsageBucket, Result
 };
 use std::collections::HashMap;
 use uuid::Uuid;
-use chrono::{DateTime, Utc, Duration};
+use chrono::{DateTime, Datelike, Months, Timelike, Utc, Duration};
 use async_trait::async_trait;
+use tokio::sync::RwLock;
 use ring::{digest, hmac};
+use ring::rand::SystemRandom;
+use ring::signature::{Ed25519KeyPair, KeyPair, UnparsedPublicKey, ED25519};
 use base64;
+use base64::Engine;
+use base64::engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD};
 
 pub struct ComprehensiveLicenseManager {
     encryption_key: Vec<u8>,
+    signing_key: Option<Ed25519KeyPair>,
     database: LicenseDatabase,
     usage_tracker: UsageTracker,
     validation_cache: ValidationCache,
@@ -22,6 +28,7 @@ impl ComprehensiveLicenseManager {
     pub fn new(encryption_key: Vec<u8>) -> Self {
         Self {
             encryption_key,
+            signing_key: None,
             database: LicenseDatabase::new(),
             usage_tracker: UsageTracker::new(),
             validation_cache: ValidationCache::new(),
@@ -30,6 +37,38 @@ impl ComprehensiveLicenseManager {
         }
     }
 
+    /// Sign issued licenses with an Ed25519 key in PKCS#8 form, so that they
+    /// can be verified offline with `validate_license_offline`
+    pub fn with_signing_key(mut self, pkcs8: &[u8]) -> Result<Self> {
+        let key_pair = Ed25519KeyPair::from_pkcs8(pkcs8)
+            .map_err(|e| format!("Invalid license signing key: {}", e))?;
+        self.signing_key = Some(key_pair);
+        Ok(self)
+    }
+
+    /// Generate a new Ed25519 signing key in PKCS#8 form
+    pub fn generate_signing_key() -> Result<Vec<u8>> {
+        let pkcs8 = Ed25519KeyPair::generate_pkcs8(&SystemRandom::new())
+            .map_err(|_| "Failed to generate license signing key")?;
+        Ok(pkcs8.as_ref().to_vec())
+    }
+
+    /// The public half of the signing key, distributed to air-gapped installs
+    pub fn public_key(&self) -> Option<&[u8]> {
+        self.signing_key.as_ref().map(|key_pair| key_pair.public_key().as_ref())
+    }
+
+    /// Sign the license in its current state. Any later change to its fields
+    /// invalidates the signature, so re-sign after activating or renewing a
+    /// license that is handed out for offline use.
+    pub fn sign_license(&self, license: &mut License) -> Result<()> {
+        let key_pair = self.signing_key.as_ref().ok_or("No license signing key configured")?;
+        license.signature = None;
+        let signature = key_pair.sign(&canonical_license_bytes(license)?);
+        license.signature = Some(STANDARD.encode(signature.as_ref()));
+        Ok(())
+    }
+
     fn generate_license_key(&self, license: &License) -> String {
         // Generate a secure license key using customer ID, product ID, and timestamp
         let data = format!("{}-{}-{}", license.customer_id, license.product_id, license.created_at.timestamp());
@@ -60,31 +99,15 @@ impl ComprehensiveLicenseManager {
         expected_key == license_key
     }
 
-    async fn check_activation_limits(&self, license: &License, activation_data: &ActivationData) -> Result<bool> {
-        if let Some(max_installations) = license.limitations.max_installations {
-            let current_activations = self.database.count_active_installations(&license.license_key).await?;
-            if current_activations >= max_installations {
-                return Ok(false);
-            }
-        }
-
+    async fn check_activation_limits(&self, license: &License, activation_data: &ActivationData) -> Result<()> {
         // Check IP restrictions
         if !license.limitations.ip_restrictions.is_empty() {
             if !license.limitations.ip_restrictions.contains(&activation_data.ip_address) {
-                return Ok(false);
+                return Err(format!("Activation from {} is not permitted by this license", activation_data.ip_address).into());
             }
         }
 
-        // Check hardware fingerprinting
-        if license.limitations.hardware_fingerprinting {
-            let existing_fingerprints = self.database.get_hardware_fingerprints(&license.license_key).await?;
-            if !existing_fingerprints.contains(&activation_data.machine_fingerprint) &&
-               existing_fingerprints.len() >= license.limitations.max_installations.unwrap_or(1) as usize {
-                return Ok(false);
-            }
-        }
-
-        Ok(true)
+        Ok(())
     }
 
     async fn validate_license_constraints(&self, license: &License) -> Result<Vec<String>> {
@@ -144,7 +167,7 @@ impl ComprehensiveLicenseManager {
                     // Check various limits based on feature type
                     if feature_id == "api_calls" {
                         if let Some(max_api_calls) = limitations.max_api_calls {
-                            let current_usage = self.usage_tracker.get_current_usage(&license.license_key, feature_id).await?;
+                            let current_usage = self.usage_against_limit(&license.license_key, feature_id, limitations.limit_period).await?;
                             if current_usage + requested_amount > max_api_calls {
                                 return Ok(false);
                             }
@@ -153,7 +176,7 @@ impl ComprehensiveLicenseManager {
 
                     if feature_id == "users" {
                         if let Some(max_users) = limitations.max_users {
-                            let current_users = self.usage_tracker.get_current_usage(&license.license_key, "users").await?;
+                            let current_users = self.usage_against_limit(&license.license_key, "users", limitations.limit_period).await?;
                             if current_users + requested_amount > max_users as u64 {
                                 return Ok(false);
                             }
@@ -162,7 +185,7 @@ impl ComprehensiveLicenseManager {
 
                     if feature_id == "storage" {
                         if let Some(max_storage) = limitations.max_storage_gb {
-                            let current_storage = self.usage_tracker.get_current_usage(&license.license_key, "storage_gb").await?;
+                            let current_storage = self.usage_against_limit(&license.license_key, "storage_gb", limitations.limit_period).await?;
                             if current_storage + requested_amount > max_storage as u64 {
                                 return Ok(false);
                             }
@@ -184,6 +207,15 @@ impl ComprehensiveLicenseManager {
         Ok(false)
     }
 
+    /// Usage a limit is checked against: the current window's total for
+    /// periodic limits, the lifetime total otherwise
+    async fn usage_against_limit(&self, license_key: &str, metric: &str, period: Option<Granularity>) -> Result<u64> {
+        match period {
+            Some(window) => self.usage_tracker.get_window_usage(license_key, metric, window, Utc::now()).await,
+            None => self.usage_tracker.get_current_usage(license_key, metric).await,
+        }
+    }
+
     async fn record_license_event(&self, license_key: &str, event: LicenseEvent) -> Result<()> {
         // Record licensing events for audit and analytics
         self.database.record_license_event(license_key, event).await
@@ -225,6 +257,11 @@ impl LicensingManager for ComprehensiveLicenseManager {
         license.license_key = self.generate_license_key(&license);
         license.status = LicenseStatus::PendingActivation;
 
+        // Sign for offline verification
+        if self.signing_key.is_some() {
+            self.sign_license(&mut license)?;
+        }
+
         // Store in database
         self.database.store_license(&license).await?;
 
@@ -351,28 +388,47 @@ impl LicensingManager for ComprehensiveLicenseManager {
         let mut license = self.database.get_license_by_key(license_key).await?
             .ok_or("License not found")?;
 
-        // Check if license can be activated
-        if !matches!(license.status, LicenseStatus::PendingActivation | LicenseStatus::Inactive) {
+        // Check if license can be activated; an active license can be
+        // activated on further machines up to its installation limit
+        if !matches!(license.status, LicenseStatus::PendingActivation | LicenseStatus::Inactive | LicenseStatus::Active) {
             return Err("License cannot be activated in current state".into());
         }
 
         // Check activation limits
-        if !self.check_activation_limits(&license, &activation_data).await? {
-            return Err("Activation limits exceeded".into());
-        }
+        self.check_activation_limits(&license, &activation_data).await?;
 
         // Validate geographic restrictions
         if !self.validate_geographic_restrictions(&license, &activation_data.ip_address).await? {
             return Err("License not valid in this geographic region".into());
         }
 
-        // Record activation
-        self.database.record_activation(license_key, &activation_data).await?;
+        // Take a machine slot. With hardware fingerprinting the license binds
+        // to the first `max_installations` machines; a bound machine may
+        // always reactivate.
+        let fingerprinting = license.limitations.hardware_fingerprinting;
+        let max_machines = match license.limitations.max_installations {
+            None if fingerprinting => Some(1),
+            max_installations => max_installations,
+        };
+        if !self.database.record_activation(license_key, &activation_data, max_machines).await? {
+            let max_machines = max_machines.unwrap_or_default();
+            return Err(if fingerprinting {
+                format!(
+                    "License is already bound to {} of {} machines; deactivate one of them before activating machine {}",
+                    max_machines, max_machines, activation_data.machine_fingerprint
+                )
+            } else {
+                format!("Maximum installations ({}) reached for this license", max_machines)
+            }
+            .into());
+        }
 
         // Update license status
-        license.status = LicenseStatus::Active;
-        license.activated_at = Some(Utc::now());
-        self.database.update_license(&license).await?;
+        if !matches!(license.status, LicenseStatus::Active) {
+            license.status = LicenseStatus::Active;
+            license.activated_at = Some(Utc::now());
+            self.database.update_license(&license).await?;
+        }
 
         // Record activation event
         self.record_license_event(license_key, LicenseEvent {
@@ -394,6 +450,9 @@ impl LicensingManager for ComprehensiveLicenseManager {
         let mut license = self.database.get_license_by_key(license_key).await?
             .ok_or("License not found")?;
 
+        // Free every machine slot
+        self.database.deactivate_all_installations(license_key).await?;
+
         license.status = LicenseStatus::Inactive;
         self.database.update_license(&license).await?;
 
@@ -410,6 +469,34 @@ impl LicensingManager for ComprehensiveLicenseManager {
         Ok(())
     }
 
+    async fn deactivate_installation(&self, license_key: &str, machine_fingerprint: &str) -> Result<()> {
+        let mut license = self.database.get_license_by_key(license_key).await?
+            .ok_or("License not found")?;
+
+        // Free the machine's slot
+        if !self.database.deactivate_installation(license_key, machine_fingerprint).await? {
+            return Err(format!("Machine {} is not activated on this license", machine_fingerprint).into());
+        }
+
+        // The license is inactive once no machine remains
+        if self.database.count_active_installations(license_key).await? == 0 {
+            license.status = LicenseStatus::Inactive;
+            self.database.update_license(&license).await?;
+            self.security_monitor.stop_monitoring(license_key).await?;
+        }
+
+        // Record deactivation event
+        self.record_license_event(license_key, LicenseEvent {
+            event_type: LicenseEventType::Deactivated,
+            timestamp: Utc::now(),
+            metadata: HashMap::from([
+                ("machine_fingerprint".to_string(), machine_fingerprint.to_string()),
+            ]),
+        }).await?;
+
+        Ok(())
+    }
+
     async fn renew_license(&self, license_key: &str, renewal_period: Duration) -> Result<()> {
         let mut license = self.database.get_license_by_key(license_key).await?
             .ok_or("License not found")?;
@@ -520,6 +607,10 @@ impl LicensingManager for ComprehensiveLicenseManager {
         self.usage_tracker.get_usage_statistics(license_key).await
     }
 
+    async fn aggregate_usage(&self, license_key: &str, window: Granularity) -> Result<Vec<UsageBucket>> {
+        self.usage_tracker.aggregate_usage(license_key, window).await
+    }
+
     async fn enforce_license_limits(&self, license_key: &str, resource: &str, amount: u64) -> Result<bool> {
         let license = self.database.get_license_by_key(license_key).await?
             .ok_or("License not found")?;
@@ -539,6 +630,133 @@ impl LicensingManager for ComprehensiveLicenseManager {
     }
 }
 
+/// The key an air-gapped install validates with: the signed license itself,
+/// base64url encoded
+pub fn offline_license_key(license: &License) -> Result<String> {
+    if license.signature.is_none() {
+        return Err("License has not been signed".into());
+    }
+    Ok(URL_SAFE_NO_PAD.encode(serde_json::to_vec(license)?))
+}
+
+/// Validate an offline license key against the issuer's Ed25519 public key,
+/// without contacting the licensing server
+///
+/// A key whose signature does not match its contents is rejected. The
+/// validity window is checked against the local clock; a lapsed heartbeat is
+/// reported as a warning until the license's offline duration runs out.
+pub fn validate_license_offline(license_key: &str, public_key: &[u8]) -> Result<LicenseValidationResult> {
+    let mut license = match URL_SAFE_NO_PAD
+        .decode(license_key.trim())
+        .ok()
+        .and_then(|payload| serde_json::from_slice::<License>(&payload).ok())
+    {
+        Some(license) => license,
+        None => return Ok(rejected(None, "Malformed offline license key".to_string())),
+    };
+
+    let signature = license
+        .signature
+        .take()
+        .and_then(|signature| STANDARD.decode(signature).ok());
+    let verified = match signature {
+        Some(signature) => UnparsedPublicKey::new(&ED25519, public_key)
+            .verify(&canonical_license_bytes(&license)?, &signature)
+            .is_ok(),
+        None => false,
+    };
+    if !verified {
+        return Ok(rejected(None, "Invalid license signature".to_string()));
+    }
+
+    if !matches!(license.status, LicenseStatus::Active | LicenseStatus::PendingActivation) {
+        let error = format!("License status: {:?}", license.status);
+        return Ok(rejected(Some(license), error));
+    }
+
+    let now = Utc::now();
+    let mut warnings = Vec::new();
+
+    if license.validity.starts_at > now {
+        let error = format!("License is not valid before {}", license.validity.starts_at.format("%Y-%m-%d"));
+        return Ok(rejected(Some(license), error));
+    }
+
+    if let Some(expires_at) = license.expires_at {
+        let grace_end = expires_at + Duration::days(license.validity.grace_period_days as i64);
+        if now > grace_end {
+            return Ok(rejected(Some(license), "License has expired".to_string()));
+        } else if now > expires_at {
+            warnings.push(format!("License expired on {}; grace period ends on {}",
+                expires_at.format("%Y-%m-%d"), grace_end.format("%Y-%m-%d")));
+        } else if expires_at <= now + Duration::days(30) {
+            warnings.push(format!("License expires on {}", expires_at.format("%Y-%m-%d")));
+        }
+    }
+
+    if license.validity.heartbeat_required {
+        let last_heartbeat = license.last_verified.or(license.activated_at).unwrap_or(license.created_at);
+        let heartbeat_due = last_heartbeat + Duration::hours(license.validity.heartbeat_interval_hours as i64);
+        if now > heartbeat_due {
+            if license.validity.offline_allowed
+                && now > last_heartbeat + Duration::hours(license.validity.offline_duration_hours as i64)
+            {
+                return Ok(rejected(Some(license), "Maximum offline duration exceeded".to_string()));
+            }
+            warnings.push(format!("License heartbeat was due on {}; connect to the licensing server to renew it",
+                heartbeat_due.format("%Y-%m-%d %H:%M UTC")));
+        }
+    }
+
+    Ok(LicenseValidationResult {
+        valid: true,
+        features: license.features.clone(),
+        limitations: license.limitations.clone(),
+        expires_at: license.expires_at,
+        license: Some(license),
+        warnings,
+        errors: Vec::new(),
+    })
+}
+
+/// The bytes a license signature covers: the license without its signature,
+/// serialized as JSON with object keys sorted
+fn canonical_license_bytes(license: &License) -> Result<Vec<u8>> {
+    fn canonicalize(value: serde_json::Value) -> serde_json::Value {
+        match value {
+            serde_json::Value::Object(map) => {
+                let sorted: std::collections::BTreeMap<_, _> = map
+                    .into_iter()
+                    .map(|(key, value)| (key, canonicalize(value)))
+                    .collect();
+                serde_json::Value::Object(sorted.into_iter().collect())
+            }
+            serde_json::Value::Array(values) => {
+                serde_json::Value::Array(values.into_iter().map(canonicalize).collect())
+            }
+            value => value,
+        }
+    }
+
+    let mut value = serde_json::to_value(license)?;
+    if let serde_json::Value::Object(map) = &mut value {
+        map.remove("signature");
+    }
+    Ok(serde_json::to_vec(&canonicalize(value))?)
+}
+
+fn rejected(license: Option<License>, error: String) -> LicenseValidationResult {
+    LicenseValidationResult {
+        valid: false,
+        features: Vec::new(),
+        limitations: license.as_ref().map(|l| l.limitations.clone()).unwrap_or_default(),
+        expires_at: license.as_ref().and_then(|l| l.expires_at),
+        license,
+        warnings: Vec::new(),
+        errors: vec![error],
+    }
+}
+
 // Supporting structures and implementations
 
 #[derive(Debug, Clone)]
@@ -562,36 +780,46 @@ pub enum LicenseEventType {
     SecurityViolation,
 }
 
-pub struct LicenseDatabase;
+pub struct LicenseDatabase {
+    licenses: RwLock<HashMap<String, License>>,
+    /// Fingerprints of the machines each license is activated on, in
+    /// activation order
+    installations: RwLock<HashMap<String, Vec<String>>>,
+}
 
 impl LicenseDatabase {
     pub fn new() -> Self {
-        Self
+        Self {
+            licenses: RwLock::new(HashMap::new()),
+            installations: RwLock::new(HashMap::new()),
+        }
     }
 
     pub async fn store_license(&self, license: &License) -> Result<()> {
         tracing::info!("Storing license: {}", license.id);
+        self.licenses.write().await.insert(license.license_key.clone(), license.clone());
         Ok(())
     }
 
     pub async fn get_license_by_key(&self, license_key: &str) -> Result<Option<License>> {
         tracing::info!("Getting license by key: {}", license_key);
-        Err("Database operation not implemented".into())
+        Ok(self.licenses.read().await.get(license_key).cloned())
     }
 
     pub async fn update_license(&self, license: &License) -> Result<()> {
         tracing::info!("Updating license: {}", license.id);
+        self.licenses.write().await.insert(license.license_key.clone(), license.clone());
         Ok(())
     }
 
     pub async fn count_active_installations(&self, license_key: &str) -> Result<u32> {
         tracing::info!("Counting active installations for: {}", license_key);
-        Ok(0)
+        Ok(self.installations.read().await.get(license_key).map_or(0, |machines| machines.len() as u32))
     }
 
     pub async fn get_hardware_fingerprints(&self, license_key: &str) -> Result<Vec<String>> {
         tracing::info!("Getting hardware fingerprints for: {}", license_key);
-        Ok(Vec::new())
+        Ok(self.installations.read().await.get(license_key).cloned().unwrap_or_default())
     }
 
     pub async fn record_license_event(&self, license_key: &str, event: LicenseEvent) -> Result<()> {
@@ -604,9 +832,34 @@ impl LicenseDatabase {
         Ok(())
     }
 
-    pub async fn record_activation(&self, license_key: &str, activation_data: &ActivationData) -> Result<()> {
+    /// Record the activation unless the license already holds `max_machines`
+    /// other machines, returning false then. The limit is checked and the
+    /// machine recorded under one lock, so concurrent activations cannot
+    /// overshoot it.
+    pub async fn record_activation(&self, license_key: &str, activation_data: &ActivationData, max_machines: Option<u32>) -> Result<bool> {
         tracing::info!("Recording activation for {}: {}", license_key, activation_data.machine_fingerprint);
-        Ok(())
+        let mut installations = self.installations.write().await;
+        let machines = installations.entry(license_key.to_string()).or_default();
+        if machines.contains(&activation_data.machine_fingerprint) {
+            return Ok(true);
+        }
+        if max_machines.is_some_and(|max_machines| machines.len() >= max_machines as usize) {
+            return Ok(false);
+        }
+        machines.push(activation_data.machine_fingerprint.clone());
+        Ok(true)
+    }
+
+    /// Free the slot held by one machine; false if it was not activated
+    pub async fn deactivate_installation(&self, license_key: &str, machine_fingerprint: &str) -> Result<bool> {
+        tracing::info!("Deactivating installation for {}: {}", license_key, machine_fingerprint);
+        let mut installations = self.installations.write().await;
+        let Some(machines) = installations.get_mut(license_key) else {
+            return Ok(false);
+        };
+        let before = machines.len();
+        machines.retain(|machine| machine != machine_fingerprint);
+        Ok(machines.len() < before)
     }
 
     pub async fn count_transfers_this_year(&self, license_key: &str) -> Result<u32> {
@@ -621,6 +874,7 @@ impl LicenseDatabase {
 
     pub async fn deactivate_all_installations(&self, license_key: &str) -> Result<()> {
         tracing::info!("Deactivating all installations for: {}", license_key);
+        self.installations.write().await.remove(license_key);
         Ok(())
     }
 
@@ -630,16 +884,43 @@ impl LicenseDatabase {
     }
 }
 
-pub struct UsageTracker;
+/// Usage data points recorded per license
+pub struct UsageTracker {
+    points: RwLock<HashMap<String, Vec<UsageDataPoint>>>,
+}
 
 impl UsageTracker {
     pub fn new() -> Self {
-        Self
+        Self {
+            points: RwLock::new(HashMap::new()),
+        }
     }
 
+    /// Total recorded for `metric` over the license's lifetime
     pub async fn get_current_usage(&self, license_key: &str, metric: &str) -> Result<u64> {
         tracing::info!("Getting current usage for {}: {}", license_key, metric);
-        Ok(0)
+        Ok(self.total_in(license_key, metric, None).await)
+    }
+
+    /// Total recorded for `metric` in the `window` containing `at`
+    pub async fn get_window_usage(&self, license_key: &str, metric: &str, window: Granularity, at: DateTime<Utc>) -> Result<u64> {
+        tracing::info!("Getting {:?} usage for {}: {}", window, license_key, metric);
+        let start = window.window_start(at);
+        Ok(self.total_in(license_key, metric, Some((start, window.window_end(start)))).await)
+    }
+
+    async fn total_in(&self, license_key: &str, metric: &str, range: Option<(DateTime<Utc>, DateTime<Utc>)>) -> u64 {
+        let points = self.points.read().await;
+        points
+            .get(license_key)
+            .into_iter()
+            .flatten()
+            .filter(|point| point.metric == metric)
+            .filter(|point| match range {
+                Some((start, end)) => point.timestamp >= start && point.timestamp < end,
+                None => true,
+            })
+            .fold(0u64, |total, point| total.saturating_add(point.value))
     }
 
     pub async fn check_rate_limit(&self, license_key: &str, feature_id: &str, rate_limit: &crate::RateLimit) -> Result<bool> {
@@ -654,20 +935,97 @@ impl UsageTracker {
 
     pub async fn record_usage(&self, license_key: &str, resource: &str, amount: u64) -> Result<()> {
         tracing::info!("Recording usage for {} resource {}: {}", license_key, resource, amount);
+        self.record_data_point(license_key, UsageDataPoint {
+            timestamp: Utc::now(),
+            metric: resource.to_string(),
+            value: amount,
+            metadata: HashMap::new(),
+        }).await
+    }
+
+    /// Record a point with its own timestamp, e.g. usage reported late by an
+    /// offline installation
+    pub async fn record_data_point(&self, license_key: &str, point: UsageDataPoint) -> Result<()> {
+        self.points.write().await.entry(license_key.to_string()).or_default().push(point);
         Ok(())
     }
 
     pub async fn get_usage_statistics(&self, license_key: &str) -> Result<UsageStatistics> {
         tracing::info!("Getting usage statistics for: {}", license_key);
+        let mut usage_history = self.points.read().await.get(license_key).cloned().unwrap_or_default();
+        usage_history.sort_by_key(|point| point.timestamp);
+
+        let mut current_usage: HashMap<String, u64> = HashMap::new();
+        let mut peak_usage: HashMap<String, u64> = HashMap::new();
+        for point in &usage_history {
+            let total = current_usage.entry(point.metric.clone()).or_default();
+            *total = total.saturating_add(point.value);
+            let peak = peak_usage.entry(point.metric.clone()).or_default();
+            *peak = (*peak).max(point.value);
+        }
+
         Ok(UsageStatistics {
             license_key: license_key.to_string(),
-            current_usage: HashMap::new(),
-            usage_history: Vec::new(),
-            peak_usage: HashMap::new(),
+            current_usage,
+            usage_history,
+            peak_usage,
             limits: HashMap::new(),
             overage_charges: rust_decimal::Decimal::ZERO,
         })
     }
+
+    /// Recorded usage summed and peaked per metric and window
+    pub async fn aggregate_usage(&self, license_key: &str, window: Granularity) -> Result<Vec<UsageBucket>> {
+        tracing::info!("Aggregating {:?} usage for: {}", window, license_key);
+        let points = self.points.read().await;
+        Ok(aggregate_data_points(points.get(license_key).map(Vec::as_slice).unwrap_or_default(), window))
+    }
+}
+
+/// Roll `points` up into one bucket per metric and window, ordered by window
+/// start and then metric. Windows without points are omitted.
+pub fn aggregate_data_points(points: &[UsageDataPoint], window: Granularity) -> Vec<UsageBucket> {
+    let mut buckets: HashMap<(DateTime<Utc>, &str), UsageBucket> = HashMap::new();
+    for point in points {
+        let window_start = window.window_start(point.timestamp);
+        let bucket = buckets.entry((window_start, point.metric.as_str())).or_insert_with(|| UsageBucket {
+            metric: point.metric.clone(),
+            window_start,
+            window_end: window.window_end(window_start),
+            total: 0,
+            peak: 0,
+            data_points: 0,
+        });
+        bucket.total = bucket.total.saturating_add(point.value);
+        bucket.peak = bucket.peak.max(point.value);
+        bucket.data_points += 1;
+    }
+
+    let mut buckets: Vec<UsageBucket> = buckets.into_values().collect();
+    buckets.sort_by(|a, b| a.window_start.cmp(&b.window_start).then_with(|| a.metric.cmp(&b.metric)));
+    buckets
+}
+
+impl Granularity {
+    /// Start of the window containing `timestamp`
+    pub fn window_start(&self, timestamp: DateTime<Utc>) -> DateTime<Utc> {
+        let date = timestamp.date_naive();
+        let start = match self {
+            Granularity::Hour => date.and_hms_opt(timestamp.hour(), 0, 0),
+            Granularity::Day => date.and_hms_opt(0, 0, 0),
+            Granularity::Month => date.with_day(1).and_then(|first| first.and_hms_opt(0, 0, 0)),
+        };
+        start.expect("valid start of window").and_utc()
+    }
+
+    /// End (exclusive) of the window starting at `start`
+    pub fn window_end(&self, start: DateTime<Utc>) -> DateTime<Utc> {
+        match self {
+            Granularity::Hour => start + Duration::hours(1),
+            Granularity::Day => start + Duration::days(1),
+            Granularity::Month => start.checked_add_months(Months::new(1)).expect("valid end of month window"),
+        }
+    }
 }
 
 pub struct ValidationCache;
@@ -755,4 +1113,339 @@ impl Default for crate::LicenseLimitations {
             },
         }
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{LicenseCompliance, LicenseLimitations, LicenseMetadata, LicenseTier, LicenseType, LicenseValidity};
+
+    fn signing_manager() -> ComprehensiveLicenseManager {
+        let pkcs8 = ComprehensiveLicenseManager::generate_signing_key().unwrap();
+        ComprehensiveLicenseManager::new(b"test-encryption-key".to_vec())
+            .with_signing_key(&pkcs8)
+            .unwrap()
+    }
+
+    fn license(last_verified: DateTime<Utc>) -> License {
+        let now = Utc::now();
+        License {
+            id: Uuid::new_v4(),
+            license_key: "ABCD-EFGH-IJKL-MNOP-QRST".to_string(),
+            customer_id: Uuid::new_v4(),
+            product_id: Uuid::new_v4(),
+            subscription_id: None,
+            license_type: LicenseType::Subscription,
+            tier: LicenseTier::Starter,
+            features: Vec::new(),
+            limitations: LicenseLimitations {
+                max_installations: Some(3),
+                ..Default::default()
+            },
+            validity: LicenseValidity {
+                starts_at: now - Duration::days(10),
+                expires_at: Some(now + Duration::days(355)),
+                auto_renewal: true,
+                grace_period_days: 7,
+                heartbeat_required: true,
+                heartbeat_interval_hours: 24,
+                offline_allowed: true,
+                offline_duration_hours: 72,
+            },
+            metadata: LicenseMetadata {
+                purchase_order: None,
+                contract_reference: None,
+                sales_person: None,
+                partner_id: None,
+                reseller_id: None,
+                custom_fields: HashMap::from([
+                    ("site".to_string(), "plant-7".to_string()),
+                    ("network".to_string(), "isolated".to_string()),
+                ]),
+                tags: Vec::new(),
+                notes: None,
+            },
+            compliance_info: LicenseCompliance {
+                audit_required: false,
+                last_audit_date: None,
+                next_audit_date: None,
+                compliance_officer: None,
+                regulatory_requirements: Vec::new(),
+                export_restrictions: Vec::new(),
+                privacy_requirements: Vec::new(),
+            },
+            created_at: now - Duration::days(10),
+            activated_at: Some(now - Duration::days(10)),
+            expires_at: Some(now + Duration::days(355)),
+            last_verified: Some(last_verified),
+            status: LicenseStatus::Active,
+            signature: None,
+        }
+    }
+
+    fn signed_key(manager: &ComprehensiveLicenseManager, mut license: License) -> String {
+        manager.sign_license(&mut license).unwrap();
+        offline_license_key(&license).unwrap()
+    }
+
+    /// Decode an offline key, edit its JSON, and encode it again
+    fn tamper(license_key: &str, edit: impl FnOnce(&mut serde_json::Value)) -> String {
+        let mut payload: serde_json::Value =
+            serde_json::from_slice(&URL_SAFE_NO_PAD.decode(license_key).unwrap()).unwrap();
+        edit(&mut payload);
+        URL_SAFE_NO_PAD.encode(serde_json::to_vec(&payload).unwrap())
+    }
+
+    #[test]
+    fn test_signed_license_validates_offline() {
+        let manager = signing_manager();
+        let license_key = signed_key(&manager, license(Utc::now()));
+
+        let result = validate_license_offline(&license_key, manager.public_key().unwrap()).unwrap();
+        assert!(result.valid, "{:?}", result.errors);
+        assert!(result.warnings.is_empty());
+        let license = result.license.unwrap();
+        assert_eq!(license.limitations.max_installations, Some(3));
+        assert_eq!(license.metadata.custom_fields["site"], "plant-7");
+    }
+
+    #[test]
+    fn test_tampered_payload_fails_verification() {
+        let manager = signing_manager();
+        let license_key = signed_key(&manager, license(Utc::now()));
+        let public_key = manager.public_key().unwrap();
+
+        let upgraded = tamper(&license_key, |payload| payload["tier"] = "Enterprise".into());
+        let extended = tamper(&license_key, |payload| {
+            payload["expires_at"] = (Utc::now() + Duration::days(3650)).to_rfc3339().into();
+        });
+        let unlimited = tamper(&license_key, |payload| {
+            payload["limitations"]["max_installations"] = serde_json::Value::Null;
+        });
+        for tampered in [upgraded, extended, unlimited] {
+            let result = validate_license_offline(&tampered, public_key).unwrap();
+            assert!(!result.valid);
+            assert!(result.license.is_none());
+            assert_eq!(result.errors, vec!["Invalid license signature".to_string()]);
+        }
+    }
+
+    #[test]
+    fn test_tampered_or_foreign_signature_fails_verification() {
+        let manager = signing_manager();
+        let license_key = signed_key(&manager, license(Utc::now()));
+
+        let forged = tamper(&license_key, |payload| {
+            let mut signature = STANDARD.decode(payload["signature"].as_str().unwrap()).unwrap();
+            signature[0] ^= 0x01;
+            payload["signature"] = STANDARD.encode(signature).into();
+        });
+        let unsigned = tamper(&license_key, |payload| {
+            payload.as_object_mut().unwrap().remove("signature");
+        });
+        for tampered in [forged, unsigned] {
+            let result = validate_license_offline(&tampered, manager.public_key().unwrap()).unwrap();
+            assert!(!result.valid);
+        }
+
+        let other_issuer = signing_manager();
+        let result = validate_license_offline(&license_key, other_issuer.public_key().unwrap()).unwrap();
+        assert!(!result.valid);
+
+        let result = validate_license_offline("not-a-license", manager.public_key().unwrap()).unwrap();
+        assert_eq!(result.errors, vec!["Malformed offline license key".to_string()]);
+    }
+
+    #[test]
+    fn test_lapsed_heartbeat_warns_until_offline_duration_runs_out() {
+        let manager = signing_manager();
+        let public_key = manager.public_key().unwrap();
+
+        let lapsed = signed_key(&manager, license(Utc::now() - Duration::hours(30)));
+        let result = validate_license_offline(&lapsed, public_key).unwrap();
+        assert!(result.valid, "{:?}", result.errors);
+        assert_eq!(result.warnings.len(), 1);
+        assert!(result.warnings[0].contains("heartbeat"));
+
+        let offline_too_long = signed_key(&manager, license(Utc::now() - Duration::hours(80)));
+        let result = validate_license_offline(&offline_too_long, public_key).unwrap();
+        assert!(!result.valid);
+        assert_eq!(result.errors, vec!["Maximum offline duration exceeded".to_string()]);
+    }
+
+    fn activation(machine_fingerprint: &str) -> ActivationData {
+        ActivationData {
+            machine_fingerprint: machine_fingerprint.to_string(),
+            ip_address: "10.0.0.12".to_string(),
+            user_agent: None,
+            activation_name: None,
+            metadata: HashMap::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_fingerprint_binding_limits_activations_to_bound_machines() {
+        let manager = ComprehensiveLicenseManager::new(b"test-encryption-key".to_vec());
+        let mut license = license(Utc::now());
+        license.status = LicenseStatus::PendingActivation;
+        license.limitations.hardware_fingerprinting = true;
+        license.limitations.max_installations = Some(2);
+        let license_key = license.license_key.clone();
+        manager.database.store_license(&license).await.unwrap();
+
+        manager.activate_license(&license_key, activation("machine-a")).await.unwrap();
+        manager.activate_license(&license_key, activation("machine-b")).await.unwrap();
+        // A bound machine may reactivate at the limit
+        manager.activate_license(&license_key, activation("machine-a")).await.unwrap();
+
+        let error = manager.activate_license(&license_key, activation("machine-c")).await.unwrap_err();
+        assert!(error.to_string().contains("already bound to 2 of 2 machines"), "{}", error);
+
+        manager.deactivate_installation(&license_key, "machine-a").await.unwrap();
+        manager.activate_license(&license_key, activation("machine-c")).await.unwrap();
+        assert_eq!(
+            manager.database.get_hardware_fingerprints(&license_key).await.unwrap(),
+            vec!["machine-b".to_string(), "machine-c".to_string()]
+        );
+
+        // The freed machine is no longer bound
+        let error = manager.activate_license(&license_key, activation("machine-a")).await.unwrap_err();
+        assert!(error.to_string().contains("machine-a"));
+        assert!(manager.deactivate_installation(&license_key, "machine-a").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_deactivating_every_machine_makes_license_inactive() {
+        let manager = ComprehensiveLicenseManager::new(b"test-encryption-key".to_vec());
+        let mut license = license(Utc::now());
+        license.status = LicenseStatus::PendingActivation;
+        license.limitations.hardware_fingerprinting = true;
+        let license_key = license.license_key.clone();
+        manager.database.store_license(&license).await.unwrap();
+
+        manager.activate_license(&license_key, activation("machine-a")).await.unwrap();
+        manager.deactivate_installation(&license_key, "machine-a").await.unwrap();
+
+        let stored = manager.database.get_license_by_key(&license_key).await.unwrap().unwrap();
+        assert!(matches!(stored.status, LicenseStatus::Inactive));
+
+        manager.activate_license(&license_key, activation("machine-b")).await.unwrap();
+        manager.deactivate_license(&license_key).await.unwrap();
+        assert_eq!(manager.database.count_active_installations(&license_key).await.unwrap(), 0);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_concurrent_activations_cannot_exceed_the_limit() {
+        let database = std::sync::Arc::new(LicenseDatabase::new());
+        let attempts: Vec<_> = (0..16)
+            .map(|machine| {
+                let database = database.clone();
+                tokio::spawn(async move {
+                    database
+                        .record_activation("ABCD", &activation(&format!("machine-{}", machine)), Some(3))
+                        .await
+                        .unwrap()
+                })
+            })
+            .collect();
+
+        let mut recorded = 0;
+        for attempt in attempts {
+            recorded += attempt.await.unwrap() as usize;
+        }
+        assert_eq!(recorded, 3);
+        assert_eq!(database.count_active_installations("ABCD").await.unwrap(), 3);
+    }
+
+    #[test]
+    fn test_unsigned_license_has_no_offline_key() {
+        assert!(offline_license_key(&license(Utc::now())).is_err());
+        let manager = ComprehensiveLicenseManager::new(b"test-encryption-key".to_vec());
+        assert!(manager.public_key().is_none());
+        assert!(manager.sign_license(&mut license(Utc::now())).is_err());
+    }
+
+    fn point(timestamp: &str, metric: &str, value: u64) -> UsageDataPoint {
+        UsageDataPoint {
+            timestamp: DateTime::parse_from_rfc3339(timestamp).unwrap().with_timezone(&Utc),
+            metric: metric.to_string(),
+            value,
+            metadata: HashMap::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_usage_aggregates_into_daily_buckets() {
+        let manager = ComprehensiveLicenseManager::new(b"test-encryption-key".to_vec());
+        let license_key = "ABCD-EFGH-IJKL-MNOP-QRST";
+        for point in [
+            point("2024-03-01T09:00:00Z", "api_calls", 40),
+            point("2024-03-01T12:00:00Z", "storage_gb", 3),
+            point("2024-03-01T23:59:59Z", "api_calls", 25),
+            point("2024-03-02T00:00:00Z", "api_calls", 10),
+        ] {
+            manager.usage_tracker.record_data_point(license_key, point).await.unwrap();
+        }
+
+        let daily = manager.aggregate_usage(license_key, Granularity::Day).await.unwrap();
+        let summary: Vec<(String, &str, u64, u64, u32)> = daily
+            .iter()
+            .map(|bucket| (bucket.window_start.to_rfc3339(), bucket.metric.as_str(), bucket.total, bucket.peak, bucket.data_points))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("2024-03-01T00:00:00+00:00".to_string(), "api_calls", 65, 40, 2),
+                ("2024-03-01T00:00:00+00:00".to_string(), "storage_gb", 3, 3, 1),
+                ("2024-03-02T00:00:00+00:00".to_string(), "api_calls", 10, 10, 1),
+            ]
+        );
+        assert_eq!(daily[0].window_end, daily[2].window_start);
+
+        let monthly = manager.aggregate_usage(license_key, Granularity::Month).await.unwrap();
+        assert_eq!((monthly[0].total, monthly[0].peak), (75, 40));
+        assert_eq!(monthly[0].window_end.to_rfc3339(), "2024-04-01T00:00:00+00:00");
+        assert!(manager.aggregate_usage("unknown", Granularity::Hour).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_periodic_limits_only_count_the_current_window() {
+        let manager = ComprehensiveLicenseManager::new(b"test-encryption-key".to_vec());
+        let mut license = license(Utc::now());
+        license.features = vec![Feature {
+            id: "api_calls".to_string(),
+            name: "API calls".to_string(),
+            enabled: true,
+            limitations: Some(crate::FeatureLimitations {
+                max_users: None,
+                max_api_calls: Some(100),
+                max_storage_gb: None,
+                max_projects: None,
+                max_deployments: None,
+                max_bandwidth_gb: None,
+                rate_limits: HashMap::new(),
+                custom_limits: HashMap::new(),
+                limit_period: Some(Granularity::Day),
+            }),
+            expires_at: None,
+            usage_tracking: true,
+        }];
+        let license_key = license.license_key.clone();
+        manager.database.store_license(&license).await.unwrap();
+        manager.usage_tracker.record_data_point(&license_key, UsageDataPoint {
+            timestamp: Utc::now() - Duration::days(1),
+            metric: "api_calls".to_string(),
+            value: 90,
+            metadata: HashMap::new(),
+        }).await.unwrap();
+
+        // Yesterday's calls don't count against today's limit
+        assert!(manager.enforce_license_limits(&license_key, "api_calls", 50).await.unwrap());
+        assert!(!manager.enforce_license_limits(&license_key, "api_calls", 60).await.unwrap());
+
+        // The same limit without a period counts every call ever made
+        license.features[0].limitations.as_mut().unwrap().limit_period = None;
+        manager.database.update_license(&license).await.unwrap();
+        assert!(!manager.enforce_license_limits(&license_key, "api_calls", 1).await.unwrap());
+        assert_eq!(manager.get_license_usage(&license_key).await.unwrap().current_usage["api_calls"], 140);
+    }
+}
@@ -0,0 +1,458 @@
+//! Dunning: recovering a subscription after a failed payment
+//!
+//! A failed payment opens a dunning case and moves the subscription to
+//! `PastDue`. The `retry_schedule` steps are scheduled relative to the
+//! failure and run by [`DunningProcessor::process`] once due. A successful
+//! retry closes the case and reactivates the subscription; once the last step
+//! has run without one, the subscription becomes `Unpaid` and the
+//! `final_action` is applied.
+//!
+//! Every side effect of a step is recorded once it succeeds, so the processor
+//! can be re-run (on a timer, after a crash) without charging or emailing the
+//! customer twice. Effects are also handed a stable idempotency key for
+//! providers that deduplicate on their side.
+
+use crate::{DunningAction, DunningFinalAction, DunningSettings, DunningStep, Result, Subscription, SubscriptionStatus};
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use uuid::Uuid;
+
+/// Carries out the side effects of dunning steps
+#[async_trait]
+pub trait DunningExecutor: Send + Sync {
+    /// Charge the outstanding amount again; true if the payment succeeded
+    async fn retry_payment(&self, subscription: &Subscription, idempotency_key: &str) -> Result<bool>;
+    async fn send_email(&self, subscription: &Subscription, template: &str, idempotency_key: &str) -> Result<()>;
+    async fn suspend_access(&self, subscription: &Subscription, idempotency_key: &str) -> Result<()>;
+    /// Actions without a dedicated method: SMS, tickets, feature reduction and custom ones
+    async fn perform(&self, subscription: &Subscription, action: &DunningAction, idempotency_key: &str) -> Result<()>;
+    async fn apply_final_action(&self, subscription: &Subscription, action: &DunningFinalAction, idempotency_key: &str) -> Result<()>;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DunningCaseStatus {
+    /// Steps are still scheduled
+    Open,
+    /// A payment succeeded; remaining steps were skipped
+    Recovered,
+    /// Every step ran without a payment; the final action was applied
+    Escalated,
+}
+
+/// One scheduled step of a dunning case and the effects it has completed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScheduledDunningStep {
+    pub step: DunningStep,
+    pub due_at: DateTime<Utc>,
+    pub payment_retried: bool,
+    pub email_sent: bool,
+    pub access_suspended: bool,
+    pub action_performed: bool,
+    pub completed_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DunningCase {
+    pub subscription_id: Uuid,
+    pub failed_at: DateTime<Utc>,
+    /// Ordered by due date
+    pub steps: Vec<ScheduledDunningStep>,
+    pub status: DunningCaseStatus,
+    pub final_action_applied: bool,
+    pub closed_at: Option<DateTime<Utc>>,
+}
+
+impl DunningCase {
+    /// When the next step is due, if any is left
+    pub fn next_due(&self) -> Option<DateTime<Utc>> {
+        if self.status != DunningCaseStatus::Open {
+            return None;
+        }
+        self.steps.iter().find(|step| step.completed_at.is_none()).map(|step| step.due_at)
+    }
+
+    fn key(&self, effect: &str) -> String {
+        format!("dunning:{}:{}:{}", self.subscription_id, self.failed_at.timestamp(), effect)
+    }
+}
+
+/// Runs dunning cases for subscriptions under one set of `DunningSettings`
+pub struct DunningProcessor {
+    settings: DunningSettings,
+    executor: Arc<dyn DunningExecutor>,
+    /// Held across a whole run so concurrent runs cannot repeat an effect
+    cases: Mutex<HashMap<Uuid, DunningCase>>,
+}
+
+impl DunningProcessor {
+    pub fn new(settings: DunningSettings, executor: Arc<dyn DunningExecutor>) -> Self {
+        Self {
+            settings,
+            executor,
+            cases: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Open a dunning case for a failed payment and mark the subscription
+    /// `PastDue`. Further failures while the case is open keep its schedule.
+    pub async fn payment_failed(&self, subscription: &mut Subscription, failed_at: DateTime<Utc>) -> Result<()> {
+        subscription.status = SubscriptionStatus::PastDue;
+        subscription.updated_at = failed_at;
+        if !self.settings.enabled {
+            return Ok(());
+        }
+
+        let mut cases = self.cases.lock().await;
+        if cases.get(&subscription.id).is_some_and(|case| case.status == DunningCaseStatus::Open) {
+            return Ok(());
+        }
+
+        let mut steps: Vec<ScheduledDunningStep> = self
+            .settings
+            .retry_schedule
+            .iter()
+            .map(|step| ScheduledDunningStep {
+                step: step.clone(),
+                due_at: failed_at + Duration::days(step.days_after_failure as i64),
+                payment_retried: false,
+                email_sent: false,
+                access_suspended: false,
+                action_performed: false,
+                completed_at: None,
+            })
+            .collect();
+        steps.sort_by_key(|step| step.due_at);
+
+        tracing::info!("Opening dunning case for subscription {} with {} steps", subscription.id, steps.len());
+        cases.insert(subscription.id, DunningCase {
+            subscription_id: subscription.id,
+            failed_at,
+            steps,
+            status: DunningCaseStatus::Open,
+            final_action_applied: false,
+            closed_at: None,
+        });
+        Ok(())
+    }
+
+    /// Close the open case after the customer paid some other way
+    pub async fn payment_recovered(&self, subscription: &mut Subscription, at: DateTime<Utc>) -> Result<()> {
+        let mut cases = self.cases.lock().await;
+        if let Some(case) = cases.get_mut(&subscription.id).filter(|case| case.status == DunningCaseStatus::Open) {
+            recover(case, subscription, at);
+        }
+        Ok(())
+    }
+
+    /// Run every step of the subscription's case that is due at `now`.
+    /// Returns the case, or `None` if the subscription has none.
+    pub async fn process(&self, subscription: &mut Subscription, now: DateTime<Utc>) -> Result<Option<DunningCase>> {
+        let mut cases = self.cases.lock().await;
+        let Some(case) = cases.get_mut(&subscription.id) else {
+            return Ok(None);
+        };
+
+        for index in 0..case.steps.len() {
+            if case.status != DunningCaseStatus::Open {
+                break;
+            }
+            if case.steps[index].completed_at.is_some() {
+                continue;
+            }
+            if case.steps[index].due_at > now {
+                break;
+            }
+            self.run_step(case, index, subscription, now).await?;
+        }
+
+        let all_steps_run = case.steps.iter().all(|step| step.completed_at.is_some());
+        if case.status == DunningCaseStatus::Open && all_steps_run {
+            self.escalate(case, subscription, now).await?;
+        }
+        Ok(Some(case.clone()))
+    }
+
+    pub async fn case(&self, subscription_id: Uuid) -> Option<DunningCase> {
+        self.cases.lock().await.get(&subscription_id).cloned()
+    }
+
+    async fn run_step(&self, case: &mut DunningCase, index: usize, subscription: &mut Subscription, now: DateTime<Utc>) -> Result<()> {
+        let step = case.steps[index].step.clone();
+        let step_key = case.key(&index.to_string());
+        let key = |effect: &str| format!("{}:{}", step_key, effect);
+
+        if matches!(step.action, DunningAction::RetryPayment) && !case.steps[index].payment_retried {
+            let paid = self.executor.retry_payment(subscription, &key("retry")).await?;
+            case.steps[index].payment_retried = true;
+            if paid {
+                case.steps[index].completed_at = Some(now);
+                recover(case, subscription, now);
+                return Ok(());
+            }
+        }
+
+        if !step.email_template.is_empty() && !case.steps[index].email_sent {
+            let template = self.settings.email_templates.get(&step.email_template).unwrap_or(&step.email_template);
+            self.executor.send_email(subscription, template, &key("email")).await?;
+            case.steps[index].email_sent = true;
+        }
+
+        let suspend = step.suspend_access || matches!(step.action, DunningAction::SuspendAccess);
+        if suspend && !case.steps[index].access_suspended {
+            self.executor.suspend_access(subscription, &key("suspend")).await?;
+            case.steps[index].access_suspended = true;
+        }
+
+        let other_action = !matches!(
+            step.action,
+            DunningAction::RetryPayment | DunningAction::SendEmail | DunningAction::SuspendAccess
+        );
+        if other_action && !case.steps[index].action_performed {
+            self.executor.perform(subscription, &step.action, &key("action")).await?;
+            case.steps[index].action_performed = true;
+        }
+
+        case.steps[index].completed_at = Some(now);
+        Ok(())
+    }
+
+    async fn escalate(&self, case: &mut DunningCase, subscription: &mut Subscription, now: DateTime<Utc>) -> Result<()> {
+        subscription.status = SubscriptionStatus::Unpaid;
+        subscription.updated_at = now;
+
+        if !case.final_action_applied {
+            tracing::warn!(
+                "Dunning exhausted for subscription {}; applying {:?}",
+                subscription.id,
+                self.settings.final_action
+            );
+            self.executor
+                .apply_final_action(subscription, &self.settings.final_action, &case.key("final"))
+                .await?;
+            case.final_action_applied = true;
+        }
+
+        if matches!(self.settings.final_action, DunningFinalAction::CancelSubscription) {
+            subscription.status = SubscriptionStatus::Canceled;
+            subscription.canceled_at = Some(now);
+        }
+        case.status = DunningCaseStatus::Escalated;
+        case.closed_at = Some(now);
+        Ok(())
+    }
+}
+
+fn recover(case: &mut DunningCase, subscription: &mut Subscription, at: DateTime<Utc>) {
+    tracing::info!("Subscription {} recovered from dunning", subscription.id);
+    subscription.status = SubscriptionStatus::Active;
+    subscription.updated_at = at;
+    case.status = DunningCaseStatus::Recovered;
+    case.closed_at = Some(at);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BillingCycle;
+    use std::collections::VecDeque;
+    use std::sync::Mutex as StdMutex;
+
+    /// Records every effect; retries succeed or fail in the given order
+    struct RecordingExecutor {
+        calls: StdMutex<Vec<String>>,
+        retry_outcomes: StdMutex<VecDeque<bool>>,
+    }
+
+    impl RecordingExecutor {
+        fn with_retries(outcomes: &[bool]) -> Arc<Self> {
+            Arc::new(Self {
+                calls: StdMutex::new(Vec::new()),
+                retry_outcomes: StdMutex::new(outcomes.iter().copied().collect()),
+            })
+        }
+
+        fn calls(&self) -> Vec<String> {
+            self.calls.lock().unwrap().clone()
+        }
+
+        fn record(&self, call: String) {
+            self.calls.lock().unwrap().push(call);
+        }
+    }
+
+    #[async_trait]
+    impl DunningExecutor for RecordingExecutor {
+        async fn retry_payment(&self, _subscription: &Subscription, _idempotency_key: &str) -> Result<bool> {
+            self.record("retry".to_string());
+            Ok(self.retry_outcomes.lock().unwrap().pop_front().unwrap_or(false))
+        }
+
+        async fn send_email(&self, _subscription: &Subscription, template: &str, _idempotency_key: &str) -> Result<()> {
+            self.record(format!("email {}", template));
+            Ok(())
+        }
+
+        async fn suspend_access(&self, _subscription: &Subscription, _idempotency_key: &str) -> Result<()> {
+            self.record("suspend".to_string());
+            Ok(())
+        }
+
+        async fn perform(&self, _subscription: &Subscription, action: &DunningAction, _idempotency_key: &str) -> Result<()> {
+            self.record(format!("perform {:?}", action));
+            Ok(())
+        }
+
+        async fn apply_final_action(&self, _subscription: &Subscription, action: &DunningFinalAction, _idempotency_key: &str) -> Result<()> {
+            self.record(format!("final {:?}", action));
+            Ok(())
+        }
+    }
+
+    fn step(days_after_failure: u32, action: DunningAction, email_template: &str) -> DunningStep {
+        DunningStep {
+            days_after_failure,
+            action,
+            email_template: email_template.to_string(),
+            suspend_access: false,
+        }
+    }
+
+    fn settings() -> DunningSettings {
+        DunningSettings {
+            enabled: true,
+            retry_schedule: vec![
+                step(1, DunningAction::SendEmail, "payment_failed_1"),
+                step(7, DunningAction::RetryPayment, "payment_retry"),
+                step(14, DunningAction::SuspendAccess, "account_suspended"),
+            ],
+            final_action: DunningFinalAction::CancelSubscription,
+            email_templates: HashMap::new(),
+            webhook_notifications: false,
+        }
+    }
+
+    fn subscription() -> Subscription {
+        let now = Utc::now();
+        Subscription {
+            id: Uuid::new_v4(),
+            customer_id: Uuid::new_v4(),
+            plan_id: Uuid::new_v4(),
+            status: SubscriptionStatus::Active,
+            billing_cycle: BillingCycle::Monthly,
+            current_period_start: now,
+            current_period_end: now + Duration::days(30),
+            trial_start: None,
+            trial_end: None,
+            cancel_at_period_end: false,
+            canceled_at: None,
+            items: Vec::new(),
+            addons: Vec::new(),
+            discounts: Vec::new(),
+            metadata: HashMap::new(),
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    #[tokio::test]
+    async fn retry_recovers_subscription_mid_sequence() {
+        let executor = RecordingExecutor::with_retries(&[true]);
+        let processor = DunningProcessor::new(settings(), executor.clone());
+        let mut subscription = subscription();
+        let failed_at = Utc::now();
+
+        processor.payment_failed(&mut subscription, failed_at).await.unwrap();
+        assert!(matches!(subscription.status, SubscriptionStatus::PastDue));
+        // Nothing is due yet
+        processor.process(&mut subscription, failed_at).await.unwrap();
+        assert!(executor.calls().is_empty());
+
+        // Re-running a day's processing repeats nothing
+        for _ in 0..2 {
+            processor.process(&mut subscription, failed_at + Duration::days(1)).await.unwrap();
+        }
+        assert_eq!(executor.calls(), vec!["email payment_failed_1"]);
+
+        let case = processor.process(&mut subscription, failed_at + Duration::days(7)).await.unwrap().unwrap();
+        assert_eq!(case.status, DunningCaseStatus::Recovered);
+        assert!(matches!(subscription.status, SubscriptionStatus::Active));
+        assert_eq!(case.next_due(), None);
+
+        // The suspension step is skipped once the payment went through
+        processor.process(&mut subscription, failed_at + Duration::days(30)).await.unwrap();
+        assert_eq!(executor.calls(), vec!["email payment_failed_1", "retry"]);
+        assert!(matches!(subscription.status, SubscriptionStatus::Active));
+    }
+
+    #[tokio::test]
+    async fn exhausted_schedule_escalates_to_cancellation() {
+        let executor = RecordingExecutor::with_retries(&[false]);
+        let processor = DunningProcessor::new(settings(), executor.clone());
+        let mut subscription = subscription();
+        let failed_at = Utc::now();
+
+        processor.payment_failed(&mut subscription, failed_at).await.unwrap();
+        for day in [1, 7, 7, 10] {
+            processor.process(&mut subscription, failed_at + Duration::days(day)).await.unwrap();
+            assert!(matches!(subscription.status, SubscriptionStatus::PastDue));
+        }
+        assert_eq!(
+            processor.case(subscription.id).await.unwrap().next_due(),
+            Some(failed_at + Duration::days(14))
+        );
+
+        // A second failure report while the case is open keeps the schedule
+        processor.payment_failed(&mut subscription, failed_at + Duration::days(8)).await.unwrap();
+
+        let case = processor.process(&mut subscription, failed_at + Duration::days(14)).await.unwrap().unwrap();
+        assert_eq!(case.status, DunningCaseStatus::Escalated);
+        assert!(case.final_action_applied);
+        assert!(matches!(subscription.status, SubscriptionStatus::Canceled));
+        assert!(subscription.canceled_at.is_some());
+
+        processor.process(&mut subscription, failed_at + Duration::days(15)).await.unwrap();
+        assert_eq!(
+            executor.calls(),
+            vec![
+                "email payment_failed_1",
+                "retry",
+                "email payment_retry",
+                "email account_suspended",
+                "suspend",
+                "final CancelSubscription",
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn processing_late_runs_every_due_step_in_order() {
+        let executor = RecordingExecutor::with_retries(&[false]);
+        let mut settings = settings();
+        settings.final_action = DunningFinalAction::SuspendIndefinitely;
+        settings.retry_schedule.push(step(3, DunningAction::CreateTicket, ""));
+        let processor = DunningProcessor::new(settings, executor.clone());
+        let mut subscription = subscription();
+        let failed_at = Utc::now();
+
+        processor.payment_failed(&mut subscription, failed_at).await.unwrap();
+        processor.process(&mut subscription, failed_at + Duration::days(20)).await.unwrap();
+
+        assert!(matches!(subscription.status, SubscriptionStatus::Unpaid));
+        assert_eq!(
+            executor.calls(),
+            vec![
+                "email payment_failed_1",
+                "perform CreateTicket",
+                "retry",
+                "email payment_retry",
+                "email account_suspended",
+                "suspend",
+                "final SuspendIndefinitely",
+            ]
+        );
+    }
+}
@@ -0,0 +1,7 @@
+//! Subscription lifecycle
+
+pub mod dunning;
+pub mod proration;
+
+pub use dunning::*;
+pub use proration::*;
@@ -0,0 +1,235 @@
+//! Proration for plan changes in the middle of a billing period
+//!
+//! The customer is credited for the part of the current period left on the
+//! old plan and charged for the same part on the new plan. The remaining part
+//! is measured in whole days or hours, as `ProrationType` says, with a
+//! started unit counting as remaining.
+
+use crate::{LineItem, LineItemType, PlanChange, ProrationSettings, ProrationType, Result, Subscription, SubscriptionItem};
+use chrono::{DateTime, Utc};
+use rust_decimal::{Decimal, RoundingStrategy};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+pub struct ProrationCalculator {
+    settings: ProrationSettings,
+}
+
+impl ProrationCalculator {
+    pub fn new(settings: ProrationSettings) -> Self {
+        Self { settings }
+    }
+
+    /// Proration line items for the next invoice when `subscription` moves to
+    /// a plan billed as `new_plan_items` per period. Returns no items when
+    /// proration is off, the change takes effect at or after the end of the
+    /// period, or the net adjustment is below `minimum_proration_amount`.
+    pub fn calculate_proration(
+        &self,
+        subscription: &Subscription,
+        change: &PlanChange,
+        new_plan_items: &[SubscriptionItem],
+    ) -> Result<Vec<LineItem>> {
+        let period_start = subscription.current_period_start;
+        let period_end = subscription.current_period_end;
+        if period_end <= period_start {
+            return Err("Invalid billing period".into());
+        }
+        if change.effective_date < period_start {
+            return Err("Plan change cannot take effect before the current billing period".into());
+        }
+
+        let unit_seconds = match self.settings.proration_type {
+            ProrationType::Daily => 86_400,
+            ProrationType::Hourly => 3_600,
+            ProrationType::None => return Ok(Vec::new()),
+        };
+        if !self.settings.enabled || !change.prorate || change.effective_date >= period_end {
+            return Ok(Vec::new());
+        }
+
+        let total_units = units(period_start, period_end, unit_seconds);
+        let remaining_units = units(change.effective_date, period_end, unit_seconds).min(total_units);
+        let prorate = |amount: Decimal| round(amount * Decimal::from(remaining_units) / Decimal::from(total_units));
+
+        let credit = if self.settings.credit_unused_time {
+            -prorate(period_amount(&subscription.items))
+        } else {
+            Decimal::ZERO
+        };
+        let charge = prorate(period_amount(new_plan_items));
+        if (charge + credit).abs() < self.settings.minimum_proration_amount {
+            return Ok(Vec::new());
+        }
+
+        let mut line_items = Vec::new();
+        if !credit.is_zero() {
+            line_items.push(proration_item(
+                format!("Unused time on plan {}", subscription.plan_id),
+                credit,
+                subscription.plan_id,
+                change.effective_date,
+                period_end,
+            ));
+        }
+        if !charge.is_zero() {
+            line_items.push(proration_item(
+                format!("Remaining time on plan {}", change.new_plan_id),
+                charge,
+                change.new_plan_id,
+                change.effective_date,
+                period_end,
+            ));
+        }
+        Ok(line_items)
+    }
+}
+
+/// Whole units between `from` and `to`, a started unit counting as one
+fn units(from: DateTime<Utc>, to: DateTime<Utc>, unit_seconds: u64) -> u64 {
+    let seconds = (to - from).num_seconds().max(0) as u64;
+    seconds.div_ceil(unit_seconds)
+}
+
+fn period_amount(items: &[SubscriptionItem]) -> Decimal {
+    items.iter().map(|item| item.unit_amount * Decimal::from(item.quantity)).sum()
+}
+
+fn round(amount: Decimal) -> Decimal {
+    amount.round_dp_with_strategy(2, RoundingStrategy::MidpointAwayFromZero)
+}
+
+fn proration_item(description: String, amount: Decimal, plan_id: Uuid, start: DateTime<Utc>, end: DateTime<Utc>) -> LineItem {
+    let mut metadata = HashMap::new();
+    metadata.insert("proration".to_string(), "true".to_string());
+    metadata.insert("plan_id".to_string(), plan_id.to_string());
+    LineItem {
+        id: Uuid::new_v4(),
+        description,
+        quantity: Decimal::ONE,
+        unit_amount: amount,
+        total_amount: amount,
+        tax_amount: Decimal::ZERO,
+        discount_amount: Decimal::ZERO,
+        item_type: LineItemType::Subscription,
+        period_start: Some(start),
+        period_end: Some(end),
+        metadata,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BillingCycle, SubscriptionStatus};
+    use chrono::{Duration, TimeZone};
+
+    fn settings(proration_type: ProrationType) -> ProrationSettings {
+        ProrationSettings {
+            enabled: true,
+            proration_type,
+            minimum_proration_amount: Decimal::new(100, 2),
+            credit_unused_time: true,
+            immediate_charge: false,
+        }
+    }
+
+    fn items(unit_amount: i64, quantity: u32) -> Vec<SubscriptionItem> {
+        vec![SubscriptionItem {
+            id: Uuid::new_v4(),
+            price_id: Uuid::new_v4(),
+            quantity,
+            unit_amount: Decimal::from(unit_amount),
+            metadata: HashMap::new(),
+        }]
+    }
+
+    /// A 30 day period starting on 1 June
+    fn subscription(items: Vec<SubscriptionItem>) -> Subscription {
+        let start = Utc.with_ymd_and_hms(2024, 6, 1, 0, 0, 0).unwrap();
+        Subscription {
+            id: Uuid::new_v4(),
+            customer_id: Uuid::new_v4(),
+            plan_id: Uuid::new_v4(),
+            status: SubscriptionStatus::Active,
+            billing_cycle: BillingCycle::Monthly,
+            current_period_start: start,
+            current_period_end: start + Duration::days(30),
+            trial_start: None,
+            trial_end: None,
+            cancel_at_period_end: false,
+            canceled_at: None,
+            items,
+            addons: Vec::new(),
+            discounts: Vec::new(),
+            metadata: HashMap::new(),
+            created_at: start,
+            updated_at: start,
+        }
+    }
+
+    fn change(subscription: &Subscription, after: Duration) -> PlanChange {
+        PlanChange {
+            new_plan_id: Uuid::new_v4(),
+            effective_date: subscription.current_period_start + after,
+            prorate: true,
+        }
+    }
+
+    fn amounts(line_items: &[LineItem]) -> Vec<Decimal> {
+        line_items.iter().map(|item| item.total_amount).collect()
+    }
+
+    #[test]
+    fn upgrade_credits_old_plan_and_charges_new_plan() {
+        let calculator = ProrationCalculator::new(settings(ProrationType::Daily));
+        let subscription = subscription(items(10, 3));
+        let change = change(&subscription, Duration::days(10));
+
+        let line_items = calculator.calculate_proration(&subscription, &change, &items(60, 1)).unwrap();
+        assert_eq!(amounts(&line_items), vec![Decimal::from(-20), Decimal::from(40)]);
+        assert_eq!(line_items[1].metadata["plan_id"], change.new_plan_id.to_string());
+        assert_eq!(line_items[0].period_start, Some(change.effective_date));
+        assert_eq!(line_items[0].period_end, Some(subscription.current_period_end));
+
+        // Part of a day left counts as a whole day with daily proration
+        let change = PlanChange { effective_date: change.effective_date + Duration::hours(12), ..change };
+        let line_items = calculator.calculate_proration(&subscription, &change, &items(60, 1)).unwrap();
+        assert_eq!(amounts(&line_items), vec![Decimal::from(-20), Decimal::from(40)]);
+    }
+
+    #[test]
+    fn downgrade_results_in_net_credit() {
+        let subscription = subscription(items(60, 1));
+        let change = change(&subscription, Duration::days(10) + Duration::hours(12));
+
+        let daily = ProrationCalculator::new(settings(ProrationType::Daily));
+        let line_items = daily.calculate_proration(&subscription, &change, &items(30, 1)).unwrap();
+        assert_eq!(amounts(&line_items), vec![Decimal::from(-40), Decimal::from(20)]);
+
+        // 468 of 720 hours remain
+        let hourly = ProrationCalculator::new(settings(ProrationType::Hourly));
+        let line_items = hourly.calculate_proration(&subscription, &change, &items(30, 1)).unwrap();
+        assert_eq!(amounts(&line_items), vec![Decimal::from(-39), Decimal::new(1950, 2)]);
+    }
+
+    #[test]
+    fn tiny_or_disabled_adjustments_are_suppressed() {
+        let calculator = ProrationCalculator::new(settings(ProrationType::Daily));
+        let subscription = subscription(items(30, 1));
+
+        // 31 instead of 30 a month, one day before renewal: 0.03 net
+        let change = change(&subscription, Duration::days(29));
+        assert!(calculator.calculate_proration(&subscription, &change, &items(31, 1)).unwrap().is_empty());
+
+        let change = PlanChange { prorate: false, ..change };
+        assert!(calculator.calculate_proration(&subscription, &change, &items(300, 1)).unwrap().is_empty());
+
+        let none = ProrationCalculator::new(settings(ProrationType::None));
+        let change = PlanChange { prorate: true, ..change };
+        assert!(none.calculate_proration(&subscription, &change, &items(300, 1)).unwrap().is_empty());
+
+        let early = PlanChange { effective_date: subscription.current_period_start - Duration::days(1), ..change };
+        assert!(calculator.calculate_proration(&subscription, &early, &items(300, 1)).is_err());
+    }
+}
@@ -68,12 +68,20 @@ pub fn create_api_router(marketplace: Arc<Marketplace>) -> Router {
         .route("/packages/:id/download", get(download_package))
         .route("/packages/:id/install", post(install_package))
         .route("/packages/:id/versions", get(get_package_versions))
+        .route("/packages/:id/versions/:version/yank", post(yank_version))
+        .route("/packages/:id/versions/:version/deprecate", post(deprecate_version))
         .route("/packages/:id/reviews", get(get_package_reviews).post(submit_review))
         .route("/packages/search", get(search_packages))
         .route("/packages/featured", get(get_featured_packages))
         .route("/packages/popular", get(get_popular_packages))
         .route("/packages/recent", get(get_recent_packages))
 
+        // Organization routes
+        .route("/organizations", post(create_organization))
+        .route("/organizations/:id/members/:user_id", put(set_organization_member).delete(remove_organization_member))
+        .route("/organizations/:id/catalog/:package_id", post(add_catalog_package).delete(remove_catalog_package))
+        .route("/organizations/:id/catalog/:package_id/install", post(install_catalog_package))
+
         // User routes
         .route("/users/:id", get(get_user).put(update_user))
         .route("/users/:id/packages", get(get_user_packages))
@@ -113,7 +121,10 @@ pub fn create_api_router(marketplace: Arc<Marketplace>) -> Router {
 async fn list_packages(
     State(state): State<ApiState>,
     Query(params): Query<ListPackagesParams>,
+    headers: HeaderMap,
 ) -> impl IntoResponse {
+    let viewer_id = extract_user_id_from_headers(&headers);
+
     let filters = SearchFilters {
         package_type: params.package_type,
         tags: params.tags.unwrap_or_default(),
@@ -121,6 +132,7 @@ async fn list_packages(
         min_rating: params.min_rating,
         verified_only: params.verified_only.unwrap_or(false),
         free_only: params.free_only.unwrap_or(false),
+        include_yanked: params.include_yanked.unwrap_or(false),
         ..Default::default()
     };
 
@@ -131,7 +143,7 @@ async fn list_packages(
         sort_order: params.sort_order.unwrap_or(SortOrder::Descending),
     };
 
-    match state.marketplace.search_packages("", Some(filters), Some(pagination)).await {
+    match state.marketplace.search_packages("", Some(filters), Some(pagination), viewer_id).await {
         Ok(results) => (StatusCode::OK, Json(ApiResponse::success(results))),
         Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, Json(ApiResponse::error(e.to_string()))),
     }
@@ -141,7 +153,9 @@ async fn list_packages(
 async fn search_packages(
     State(state): State<ApiState>,
     Query(params): Query<SearchPackagesParams>,
+    headers: HeaderMap,
 ) -> impl IntoResponse {
+    let viewer_id = extract_user_id_from_headers(&headers);
     let query = params.q.unwrap_or_default();
 
     let filters = SearchFilters {
@@ -151,6 +165,8 @@ async fn search_packages(
         min_rating: params.min_rating,
         verified_only: params.verified_only.unwrap_or(false),
         free_only: params.free_only.unwrap_or(false),
+        include_yanked: params.include_yanked.unwrap_or(false),
+        organization_id: params.organization_id,
         ..Default::default()
     };
 
@@ -161,8 +177,11 @@ async fn search_packages(
         sort_order: params.sort_order.unwrap_or(SortOrder::Descending),
     };
 
-    match state.marketplace.search_packages(&query, Some(filters), Some(pagination)).await {
+    match state.marketplace.search_packages(&query, Some(filters), Some(pagination), viewer_id).await {
         Ok(results) => (StatusCode::OK, Json(ApiResponse::success(results))),
+        Err(MarketplaceError::OrganizationNotFound(_)) => {
+            (StatusCode::NOT_FOUND, Json(ApiResponse::error("Organization not found".to_string())))
+        },
         Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, Json(ApiResponse::error(e.to_string()))),
     }
 }
@@ -171,8 +190,11 @@ async fn search_packages(
 async fn get_package(
     State(state): State<ApiState>,
     Path(package_id): Path<Uuid>,
+    headers: HeaderMap,
 ) -> impl IntoResponse {
-    match state.marketplace.get_package(package_id).await {
+    let viewer_id = extract_user_id_from_headers(&headers);
+
+    match state.marketplace.get_package_for_viewer(package_id, viewer_id).await {
         Ok(package) => (StatusCode::OK, Json(ApiResponse::success(package))),
         Err(MarketplaceError::PackageNotFound(_)) => {
             (StatusCode::NOT_FOUND, Json(ApiResponse::error("Package not found".to_string())))
@@ -239,11 +261,24 @@ async fn download_package(
 ) -> impl IntoResponse {
     let downloader_id = extract_user_id_from_headers(&headers);
 
-    match state.marketplace.download_package(package_id, params.version.as_deref(), downloader_id).await {
+    let result = match (params.locked.unwrap_or(false), params.version.as_deref()) {
+        (true, Some(version)) => {
+            state.marketplace.download_locked_version(package_id, version, downloader_id).await
+        },
+        (true, None) => {
+            return (StatusCode::BAD_REQUEST, Json(ApiResponse::error("Locked downloads require a version".to_string())));
+        },
+        (false, version) => state.marketplace.download_package(package_id, version, downloader_id).await,
+    };
+
+    match result {
         Ok(download) => (StatusCode::OK, Json(ApiResponse::success(download))),
         Err(MarketplaceError::PackageNotFound(_)) => {
             (StatusCode::NOT_FOUND, Json(ApiResponse::error("Package not found".to_string())))
         },
+        Err(e @ MarketplaceError::VersionYanked(_)) => {
+            (StatusCode::GONE, Json(ApiResponse::error(e.to_string())))
+        },
         Err(MarketplaceError::AccessDenied) => {
             (StatusCode::FORBIDDEN, Json(ApiResponse::error("Access denied".to_string())))
         },
@@ -264,7 +299,7 @@ async fn install_package(
     };
 
     // Get package to extract name
-    let package = match state.marketplace.get_package(package_id).await {
+    let package = match state.marketplace.get_package_for_viewer(package_id, Some(installer_id)).await {
         Ok(p) => p,
         Err(_) => return (StatusCode::NOT_FOUND, Json(ApiResponse::error("Package not found".to_string()))),
     };
@@ -275,6 +310,179 @@ async fn install_package(
     }
 }
 
+/// Yank a package version
+async fn yank_version(
+    State(state): State<ApiState>,
+    Path((package_id, version)): Path<(Uuid, String)>,
+    headers: HeaderMap,
+    Json(request): Json<YankVersionRequest>,
+) -> impl IntoResponse {
+    let actor_id = match extract_user_id_from_headers(&headers) {
+        Some(id) => id,
+        None => return (StatusCode::UNAUTHORIZED, Json(ApiResponse::error("Unauthorized".to_string()))),
+    };
+
+    match state.marketplace.yank_version(package_id, &version, &request.reason, actor_id).await {
+        Ok(advisory) => (StatusCode::OK, Json(ApiResponse::success(advisory))),
+        Err(e) => version_advisory_error(e),
+    }
+}
+
+/// Deprecate a package version
+async fn deprecate_version(
+    State(state): State<ApiState>,
+    Path((package_id, version)): Path<(Uuid, String)>,
+    headers: HeaderMap,
+    Json(request): Json<DeprecateVersionRequest>,
+) -> impl IntoResponse {
+    let actor_id = match extract_user_id_from_headers(&headers) {
+        Some(id) => id,
+        None => return (StatusCode::UNAUTHORIZED, Json(ApiResponse::error("Unauthorized".to_string()))),
+    };
+
+    match state.marketplace.deprecate_version(
+        package_id,
+        &version,
+        &request.message,
+        request.replacement_version.as_deref(),
+        actor_id,
+    ).await {
+        Ok(advisory) => (StatusCode::OK, Json(ApiResponse::success(advisory))),
+        Err(e) => version_advisory_error(e),
+    }
+}
+
+fn version_advisory_error(error: MarketplaceError) -> (StatusCode, Json<ApiResponse<VersionAdvisory>>) {
+    let status = match error {
+        MarketplaceError::PackageNotFound(_) | MarketplaceError::VersionNotFound(_) => StatusCode::NOT_FOUND,
+        MarketplaceError::InsufficientPermissions => StatusCode::FORBIDDEN,
+        MarketplaceError::InvalidVersion(_) | MarketplaceError::InvalidRequest(_) => StatusCode::BAD_REQUEST,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    };
+    (status, Json(ApiResponse::error(error.to_string())))
+}
+
+/// Create an organization
+async fn create_organization(
+    State(state): State<ApiState>,
+    headers: HeaderMap,
+    Json(request): Json<CreateOrganizationRequest>,
+) -> impl IntoResponse {
+    let owner_id = match extract_user_id_from_headers(&headers) {
+        Some(id) => id,
+        None => return (StatusCode::UNAUTHORIZED, Json(ApiResponse::error("Unauthorized".to_string()))),
+    };
+
+    match state.marketplace.create_organization(&request.name, request.display_name, owner_id).await {
+        Ok(organization) => (StatusCode::CREATED, Json(ApiResponse::success(organization))),
+        Err(e) => (StatusCode::BAD_REQUEST, Json(ApiResponse::error(e.to_string()))),
+    }
+}
+
+/// Add or update an organization member
+async fn set_organization_member(
+    State(state): State<ApiState>,
+    Path((organization_id, user_id)): Path<(Uuid, Uuid)>,
+    headers: HeaderMap,
+    Json(request): Json<SetMemberRequest>,
+) -> impl IntoResponse {
+    let actor_id = match extract_user_id_from_headers(&headers) {
+        Some(id) => id,
+        None => return (StatusCode::UNAUTHORIZED, Json(ApiResponse::error("Unauthorized".to_string()))),
+    };
+
+    match state.marketplace.set_organization_member(organization_id, user_id, request.role, actor_id).await {
+        Ok(()) => (StatusCode::OK, Json(ApiResponse::success(()))),
+        Err(e) => organization_error(e),
+    }
+}
+
+/// Remove an organization member
+async fn remove_organization_member(
+    State(state): State<ApiState>,
+    Path((organization_id, user_id)): Path<(Uuid, Uuid)>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    let actor_id = match extract_user_id_from_headers(&headers) {
+        Some(id) => id,
+        None => return (StatusCode::UNAUTHORIZED, Json(ApiResponse::error("Unauthorized".to_string()))),
+    };
+
+    match state.marketplace.remove_organization_member(organization_id, user_id, actor_id).await {
+        Ok(()) => (StatusCode::OK, Json(ApiResponse::success(()))),
+        Err(e) => organization_error(e),
+    }
+}
+
+/// Allowlist a package into an organization's catalog
+async fn add_catalog_package(
+    State(state): State<ApiState>,
+    Path((organization_id, package_id)): Path<(Uuid, Uuid)>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    let actor_id = match extract_user_id_from_headers(&headers) {
+        Some(id) => id,
+        None => return (StatusCode::UNAUTHORIZED, Json(ApiResponse::error("Unauthorized".to_string()))),
+    };
+
+    match state.marketplace.add_to_catalog(organization_id, package_id, actor_id).await {
+        Ok(entry) => (StatusCode::CREATED, Json(ApiResponse::success(entry))),
+        Err(e) => organization_error(e),
+    }
+}
+
+/// Remove a package from an organization's catalog
+async fn remove_catalog_package(
+    State(state): State<ApiState>,
+    Path((organization_id, package_id)): Path<(Uuid, Uuid)>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    let actor_id = match extract_user_id_from_headers(&headers) {
+        Some(id) => id,
+        None => return (StatusCode::UNAUTHORIZED, Json(ApiResponse::error("Unauthorized".to_string()))),
+    };
+
+    match state.marketplace.remove_from_catalog(organization_id, package_id, actor_id).await {
+        Ok(()) => (StatusCode::OK, Json(ApiResponse::success(()))),
+        Err(e) => organization_error(e),
+    }
+}
+
+/// Install a package through an organization's catalog
+async fn install_catalog_package(
+    State(state): State<ApiState>,
+    Path((organization_id, package_id)): Path<(Uuid, Uuid)>,
+    Query(params): Query<InstallParams>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    let installer_id = match extract_user_id_from_headers(&headers) {
+        Some(id) => id,
+        None => return (StatusCode::UNAUTHORIZED, Json(ApiResponse::error("Unauthorized".to_string()))),
+    };
+
+    let package = match state.marketplace.get_package_for_viewer(package_id, Some(installer_id)).await {
+        Ok(p) => p,
+        Err(_) => return (StatusCode::NOT_FOUND, Json(ApiResponse::error("Package not found".to_string()))),
+    };
+
+    match state.marketplace.install_from_catalog(organization_id, &package.name, params.version.as_deref(), installer_id).await {
+        Ok(result) => (StatusCode::OK, Json(ApiResponse::success(result))),
+        Err(e) => organization_error(e),
+    }
+}
+
+fn organization_error<T>(error: MarketplaceError) -> (StatusCode, Json<ApiResponse<T>>) {
+    let status = match error {
+        MarketplaceError::OrganizationNotFound(_)
+        | MarketplaceError::PackageNotFound(_)
+        | MarketplaceError::UserNotFound(_) => StatusCode::NOT_FOUND,
+        MarketplaceError::InsufficientPermissions => StatusCode::FORBIDDEN,
+        MarketplaceError::InvalidRequest(_) => StatusCode::BAD_REQUEST,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    };
+    (status, Json(ApiResponse::error(error.to_string())))
+}
+
 /// Submit a package review
 async fn submit_review(
     State(state): State<ApiState>,
@@ -427,6 +635,7 @@ struct ListPackagesParams {
     pub min_rating: Option<f32>,
     pub verified_only: Option<bool>,
     pub free_only: Option<bool>,
+    pub include_yanked: Option<bool>,
     pub page: Option<u32>,
     pub per_page: Option<u32>,
     pub sort_by: Option<SortField>,
@@ -443,6 +652,8 @@ struct SearchPackagesParams {
     pub min_rating: Option<f32>,
     pub verified_only: Option<bool>,
     pub free_only: Option<bool>,
+    pub include_yanked: Option<bool>,
+    pub organization_id: Option<Uuid>,
     pub page: Option<u32>,
     pub per_page: Option<u32>,
     pub sort_by: Option<SortField>,
@@ -453,6 +664,8 @@ struct SearchPackagesParams {
 #[derive(Debug, Deserialize)]
 struct DownloadParams {
     pub version: Option<String>,
+    /// Exact version comes from a lockfile; serve it even if yanked
+    pub locked: Option<bool>,
 }
 
 /// Query parameters for installing packages
@@ -461,6 +674,32 @@ struct InstallParams {
     pub version: Option<String>,
 }
 
+/// Request body for creating an organization
+#[derive(Debug, Deserialize)]
+struct CreateOrganizationRequest {
+    pub name: String,
+    pub display_name: Option<String>,
+}
+
+/// Request body for setting an organization member's role
+#[derive(Debug, Deserialize)]
+struct SetMemberRequest {
+    pub role: OrganizationRole,
+}
+
+/// Request body for yanking a version
+#[derive(Debug, Deserialize)]
+struct YankVersionRequest {
+    pub reason: String,
+}
+
+/// Request body for deprecating a version
+#[derive(Debug, Deserialize)]
+struct DeprecateVersionRequest {
+    pub message: String,
+    pub replacement_version: Option<String>,
+}
+
 /// Payment processing request
 #[derive(Debug, Deserialize)]
 struct ProcessPaymentRequest {
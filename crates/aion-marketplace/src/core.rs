@@ -8,6 +8,7 @@ use crate::{
     notifications::*,
     search::*,
     validation::*,
+    resolver::*,
     errors::*,
     config::*,
     PackageType,
@@ -94,6 +95,9 @@ impl Marketplace {
         // Validate publisher permissions
         let publisher = self.get_user(publisher_id).await?;
         self.security.validate_publish_permission(&publisher, &package_data).await?;
+        if let Some(organization_id) = package_data.organization_id {
+            self.organization_role(organization_id, publisher_id).await?;
+        }
 
         // Validate package content
         let validation_result = self.validator.validate_package(&package_files, &package_data).await?;
@@ -133,6 +137,8 @@ impl Marketplace {
             created_at: chrono::Utc::now(),
             updated_at: chrono::Utc::now(),
             metadata: package_data.metadata.unwrap_or_default(),
+            pricing: package_data.pricing.clone(),
+            organization_id: package_data.organization_id,
         };
 
         // Store package files
@@ -156,6 +162,9 @@ impl Marketplace {
             published_at: chrono::Utc::now(),
             yanked: false,
             yank_reason: None,
+            deprecated: false,
+            deprecation_message: None,
+            replacement_version: None,
         };
 
         // Store in database
@@ -186,11 +195,16 @@ impl Marketplace {
     }
 
     /// Search for packages
+    ///
+    /// Unlisted packages never appear, and private ones only for members of
+    /// the owning organization. With `filters.organization_id` set, results
+    /// are limited to that organization's catalog.
     pub async fn search_packages(
         &self,
         query: &str,
         filters: Option<SearchFilters>,
         pagination: Option<PaginationParams>,
+        viewer_id: Option<Uuid>,
     ) -> Result<SearchResults<PackageSummary>> {
         tracing::debug!("Searching packages: query='{}' filters={:?}", query, filters);
 
@@ -201,11 +215,42 @@ impl Marketplace {
         };
 
         let results = self.search.search_packages(&search_params).await?;
+        let include_yanked = search_params.filters.include_yanked;
+
+        let catalog = match search_params.filters.organization_id {
+            Some(organization_id) => {
+                let viewer_id = viewer_id.ok_or(MarketplaceError::OrganizationNotFound(organization_id))?;
+                self.organization_role(organization_id, viewer_id).await?;
+                let ids = self.database.get_catalog_package_ids(organization_id).await?;
+                Some(ids.into_iter().collect::<std::collections::HashSet<_>>())
+            },
+            None => None,
+        };
 
         // Convert to summaries
         let mut summaries = Vec::new();
+        let mut hidden = 0u64;
         for package_id in &results.items {
-            if let Ok(package) = self.get_package(*package_id).await {
+            if let Ok(mut package) = self.get_package(*package_id).await {
+                let listed = listed_in_search(&package, catalog.as_ref())
+                    && self.can_view_package(&package, viewer_id).await?;
+                if !listed {
+                    hidden += 1;
+                    continue;
+                }
+
+                // Advertise the newest installable version; packages with
+                // nothing but yanked versions are hidden unless asked for.
+                if !include_yanked {
+                    match self.database.get_latest_package_version(package.id).await {
+                        Ok(latest) => package.current_version = latest.version,
+                        Err(_) => {
+                            hidden += 1;
+                            continue;
+                        },
+                    }
+                }
+
                 if let Ok(author) = self.get_user(package.publisher_id).await {
                     summaries.push(PackageSummary {
                         id: package.id,
@@ -232,7 +277,7 @@ impl Marketplace {
 
         Ok(SearchResults {
             items: summaries,
-            total_count: results.total_count,
+            total_count: results.total_count.saturating_sub(hidden),
             page: results.page,
             per_page: results.per_page,
             total_pages: results.total_pages,
@@ -240,24 +285,41 @@ impl Marketplace {
     }
 
     /// Download a package
+    ///
+    /// Yanked versions are refused; use [`Marketplace::download_locked_version`]
+    /// to fetch one that an existing lockfile already pins.
     pub async fn download_package(
         &self,
         package_id: Uuid,
         version: Option<&str>,
         downloader_id: Option<Uuid>,
     ) -> Result<PackageDownload> {
-        tracing::info!("Downloading package: {} version {:?}", package_id, version);
+        self.download_version(package_id, version, downloader_id, false).await
+    }
 
-        let package = self.get_package(package_id).await?;
+    /// Download an exact version that an existing dependent has locked.
+    ///
+    /// Unlike [`Marketplace::download_package`] this still serves yanked
+    /// versions (with a warning) so that existing builds keep working.
+    pub async fn download_locked_version(
+        &self,
+        package_id: Uuid,
+        version: &str,
+        downloader_id: Option<Uuid>,
+    ) -> Result<PackageDownload> {
+        self.download_version(package_id, Some(version), downloader_id, true).await
+    }
 
-        // Check visibility permissions
-        if package.visibility == PackageVisibility::Private {
-            if let Some(downloader_id) = downloader_id {
-                self.security.validate_package_access(&package, downloader_id).await?;
-            } else {
-                return Err(MarketplaceError::AccessDenied);
-            }
-        }
+    async fn download_version(
+        &self,
+        package_id: Uuid,
+        version: Option<&str>,
+        downloader_id: Option<Uuid>,
+        allow_yanked: bool,
+    ) -> Result<PackageDownload> {
+        tracing::info!("Downloading package: {} version {:?}", package_id, version);
+
+        let package = self.get_package_for_viewer(package_id, downloader_id).await?;
 
         // Get specific version or latest
         let version_record = if let Some(version_str) = version {
@@ -269,9 +331,10 @@ impl Marketplace {
         };
 
         // Check if version is yanked
-        if version_record.yanked {
+        if version_record.yanked && !allow_yanked {
             return Err(MarketplaceError::VersionYanked(version_record.yank_reason));
         }
+        let warnings = version_warnings(&package, &version_record);
 
         // Generate download URL with expiration
         let download_url = self.storage.generate_download_url(&version_record.file_url).await?;
@@ -287,6 +350,7 @@ impl Marketplace {
             file_size: version_record.file_size,
             user_agent: None, // Could be filled from request headers
             ip_address: None, // Could be filled from request
+            warnings,
         };
 
         // Update download count (async)
@@ -320,13 +384,13 @@ impl Marketplace {
 
         // Download package
         let download = self.download_package(package.id, version, Some(installer_id)).await?;
+        let version_record = self.database.get_package_version(package.id, &download.version).await?;
 
         // Download actual files
-        let package_files = self.storage.download_package_files(&download.download_url).await?;
+        let package_files = self.storage.download_package_files(&version_record.file_url).await?;
 
         // Verify file integrity
         let calculated_hash = self.calculate_file_hash(&package_files);
-        let version_record = self.database.get_package_version(package.id, &download.version).await?;
 
         if calculated_hash != version_record.file_hash {
             return Err(MarketplaceError::FileIntegrityCheckFailed);
@@ -335,6 +399,10 @@ impl Marketplace {
         // Extract and prepare for installation
         let extracted_path = self.extract_package_files(&package_files).await?;
 
+        for warning in &download.warnings {
+            tracing::warn!("{}", warning);
+        }
+
         // Create installation record
         let installation = PackageInstallation {
             id: Uuid::new_v4(),
@@ -360,9 +428,146 @@ impl Marketplace {
             installation,
             extracted_path,
             package_files,
+            warnings: download.warnings,
         })
     }
 
+    /// Install the highest published version of a plugin matching
+    /// `requirement`, together with its transitive dependencies.
+    ///
+    /// All versions are resolved before anything is installed; if the
+    /// requirements cannot be met together, a `DependencyConflict` naming
+    /// every incompatible constraint is returned. Results are in install
+    /// order, dependencies first and the plugin itself last.
+    pub async fn install_plugin_range(
+        &self,
+        plugin_name: &str,
+        requirement: &semver::VersionReq,
+        installer_id: Uuid,
+    ) -> Result<Vec<InstallationResult>> {
+        tracing::info!("Installing plugin: {} {} for user {}", plugin_name, requirement, installer_id);
+
+        let package = self.database.get_package_by_name(plugin_name).await?;
+        let visible = self.can_view_package(&package, Some(installer_id)).await?;
+        require_visible_plugin(&package, visible)?;
+
+        let index = self.dependency_index(plugin_name).await?;
+        let resolution = resolve_dependencies(&index, plugin_name, requirement)?;
+
+        let mut installations = Vec::with_capacity(resolution.packages.len());
+        for resolved in &resolution.packages {
+            let version = resolved.version.to_string();
+            installations.push(self.install_package(&resolved.name, Some(&version), installer_id).await?);
+        }
+        Ok(installations)
+    }
+
+    /// Yank a version so it is no longer served to new installs.
+    ///
+    /// The artifact stays in storage and remains reachable through
+    /// [`Marketplace::download_locked_version`], so dependents that already
+    /// pinned it keep building. Dependents and installers are notified.
+    pub async fn yank_version(
+        &self,
+        package_id: Uuid,
+        version: &str,
+        reason: &str,
+        actor_id: Uuid,
+    ) -> Result<VersionAdvisory> {
+        tracing::info!("Yanking {} version {} by {}", package_id, version, actor_id);
+
+        let package = self.get_package_for_viewer(package_id, Some(actor_id)).await?;
+        self.ensure_can_manage_versions(&package, actor_id).await?;
+
+        let version = semver::Version::parse(version)
+            .map_err(|e| MarketplaceError::InvalidVersion(e.to_string()))?;
+        let versions = self.database.get_package_versions(package_id).await?;
+        let record = versions.iter().find(|v| v.version == version)
+            .ok_or_else(|| MarketplaceError::VersionNotFound(version.clone()))?;
+        if record.yanked {
+            return Err(MarketplaceError::InvalidRequest(format!("{}@{} is already yanked", package.name, version)));
+        }
+
+        let replacement = suggest_replacement(&versions, &version);
+        self.database.set_version_yanked(package_id, &version, reason, replacement.as_ref()).await?;
+
+        // Keep the advertised version installable
+        if package.current_version == version {
+            let fallback = versions.iter()
+                .filter(|v| !v.yanked && v.version != version)
+                .map(|v| &v.version)
+                .max();
+            if let Some(fallback) = fallback {
+                self.database.update_package_current_version(package_id, fallback).await?;
+            }
+        }
+        self.package_cache.write().await.remove(&package_id);
+
+        let advisory = VersionAdvisory {
+            kind: AdvisoryKind::Yanked,
+            package_id,
+            package_name: package.name.clone(),
+            version,
+            reason: Some(reason.to_string()),
+            replacement_version: replacement,
+            issued_at: chrono::Utc::now(),
+        };
+        self.notify_version_consumers(&advisory).await;
+
+        Ok(advisory)
+    }
+
+    /// Deprecate a version: it stays installable but every download warns.
+    pub async fn deprecate_version(
+        &self,
+        package_id: Uuid,
+        version: &str,
+        message: &str,
+        replacement: Option<&str>,
+        actor_id: Uuid,
+    ) -> Result<VersionAdvisory> {
+        tracing::info!("Deprecating {} version {} by {}", package_id, version, actor_id);
+
+        let package = self.get_package_for_viewer(package_id, Some(actor_id)).await?;
+        self.ensure_can_manage_versions(&package, actor_id).await?;
+
+        let version = semver::Version::parse(version)
+            .map_err(|e| MarketplaceError::InvalidVersion(e.to_string()))?;
+        let versions = self.database.get_package_versions(package_id).await?;
+        let record = versions.iter().find(|v| v.version == version)
+            .ok_or_else(|| MarketplaceError::VersionNotFound(version.clone()))?;
+        if record.yanked {
+            return Err(MarketplaceError::InvalidRequest(format!("{}@{} is yanked", package.name, version)));
+        }
+
+        let replacement = match replacement {
+            Some(replacement) => {
+                let replacement = semver::Version::parse(replacement)
+                    .map_err(|e| MarketplaceError::InvalidVersion(e.to_string()))?;
+                if !versions.iter().any(|v| v.version == replacement && !v.yanked) {
+                    return Err(MarketplaceError::VersionNotFound(replacement));
+                }
+                Some(replacement)
+            },
+            None => suggest_replacement(&versions, &version),
+        };
+
+        self.database.set_version_deprecated(package_id, &version, message, replacement.as_ref()).await?;
+
+        let advisory = VersionAdvisory {
+            kind: AdvisoryKind::Deprecated,
+            package_id,
+            package_name: package.name.clone(),
+            version,
+            reason: Some(message.to_string()),
+            replacement_version: replacement,
+            issued_at: chrono::Utc::now(),
+        };
+        self.notify_version_consumers(&advisory).await;
+
+        Ok(advisory)
+    }
+
     /// Get package details
     pub async fn get_package(&self, package_id: Uuid) -> Result<Package> {
         // Check cache first
@@ -379,6 +584,132 @@ impl Marketplace {
         Ok(package)
     }
 
+    /// Get package details as seen by `viewer_id`.
+    ///
+    /// Private packages the viewer may not see are reported as not found so
+    /// their existence does not leak.
+    pub async fn get_package_for_viewer(&self, package_id: Uuid, viewer_id: Option<Uuid>) -> Result<Package> {
+        let package = self.get_package(package_id).await?;
+        if !self.can_view_package(&package, viewer_id).await? {
+            return Err(MarketplaceError::PackageNotFound(package_id));
+        }
+        Ok(package)
+    }
+
+    /// Create an organization owned by `owner_id`
+    pub async fn create_organization(
+        &self,
+        name: &str,
+        display_name: Option<String>,
+        owner_id: Uuid,
+    ) -> Result<Organization> {
+        self.get_user(owner_id).await?;
+
+        let organization = Organization {
+            id: Uuid::new_v4(),
+            name: name.to_string(),
+            display_name,
+            owner_id,
+            created_at: chrono::Utc::now(),
+        };
+        self.database.create_organization(&organization).await?;
+
+        tracing::info!("Organization created: {} ({})", organization.name, organization.id);
+        Ok(organization)
+    }
+
+    /// Add a user to an organization or change their role
+    pub async fn set_organization_member(
+        &self,
+        organization_id: Uuid,
+        user_id: Uuid,
+        role: OrganizationRole,
+        actor_id: Uuid,
+    ) -> Result<()> {
+        let actor_role = self.organization_role(organization_id, actor_id).await?;
+        if !actor_role.can_manage() {
+            return Err(MarketplaceError::InsufficientPermissions);
+        }
+
+        self.get_user(user_id).await?;
+        let organization = self.database.get_organization(organization_id).await?;
+        let current_role = self.database.get_organization_role(organization_id, user_id).await?;
+        let owners = self.database.count_organization_owners(organization_id).await?;
+        check_owner_change(&organization, actor_role, user_id, current_role, Some(role), owners)?;
+
+        self.database.upsert_organization_member(organization_id, user_id, role).await
+    }
+
+    /// Remove a user from an organization
+    pub async fn remove_organization_member(&self, organization_id: Uuid, user_id: Uuid, actor_id: Uuid) -> Result<()> {
+        let actor_role = self.organization_role(organization_id, actor_id).await?;
+        if !actor_role.can_manage() && actor_id != user_id {
+            return Err(MarketplaceError::InsufficientPermissions);
+        }
+
+        let organization = self.database.get_organization(organization_id).await?;
+        if organization.owner_id == user_id {
+            return Err(MarketplaceError::InvalidRequest("The organization owner cannot be removed".to_string()));
+        }
+        let current_role = self.database.get_organization_role(organization_id, user_id).await?;
+        let owners = self.database.count_organization_owners(organization_id).await?;
+        check_owner_change(&organization, actor_role, user_id, current_role, None, owners)?;
+
+        self.database.remove_organization_member(organization_id, user_id).await
+    }
+
+    /// Allowlist a public package into an organization's catalog
+    pub async fn add_to_catalog(&self, organization_id: Uuid, package_id: Uuid, actor_id: Uuid) -> Result<CatalogEntry> {
+        let actor_role = self.organization_role(organization_id, actor_id).await?;
+        if !actor_role.can_manage() {
+            return Err(MarketplaceError::InsufficientPermissions);
+        }
+
+        let package = self.get_package_for_viewer(package_id, Some(actor_id)).await?;
+        if package.visibility == PackageVisibility::Private {
+            return Err(MarketplaceError::InvalidRequest(
+                "Only public or unlisted packages can be added to a catalog".to_string(),
+            ));
+        }
+
+        let entry = CatalogEntry {
+            organization_id,
+            package_id,
+            added_by: actor_id,
+            added_at: chrono::Utc::now(),
+        };
+        self.database.add_catalog_entry(&entry).await?;
+        Ok(entry)
+    }
+
+    /// Remove an allowlisted package from an organization's catalog
+    pub async fn remove_from_catalog(&self, organization_id: Uuid, package_id: Uuid, actor_id: Uuid) -> Result<()> {
+        let actor_role = self.organization_role(organization_id, actor_id).await?;
+        if !actor_role.can_manage() {
+            return Err(MarketplaceError::InsufficientPermissions);
+        }
+        self.database.remove_catalog_entry(organization_id, package_id).await
+    }
+
+    /// Install a package through an organization's curated catalog.
+    ///
+    /// Packages outside the catalog are reported as not found.
+    pub async fn install_from_catalog(
+        &self,
+        organization_id: Uuid,
+        package_name: &str,
+        version: Option<&str>,
+        installer_id: Uuid,
+    ) -> Result<InstallationResult> {
+        self.organization_role(organization_id, installer_id).await?;
+
+        let package = self.database.get_package_by_name(package_name).await?;
+        let catalog = self.database.get_catalog_package_ids(organization_id).await?;
+        require_in_catalog(&catalog, &package)?;
+
+        self.install_package(package_name, version, installer_id).await
+    }
+
     /// Get user details
     pub async fn get_user(&self, user_id: Uuid) -> Result<User> {
         // Check cache first
@@ -411,7 +742,7 @@ impl Marketplace {
         tracing::info!("Submitting review for package {} by user {}", package_id, reviewer_id);
 
         // Validate package exists
-        let package = self.get_package(package_id).await?;
+        let package = self.get_package_for_viewer(package_id, Some(reviewer_id)).await?;
 
         // Check if user has already reviewed this package
         if self.database.has_user_reviewed_package(reviewer_id, package_id).await? {
@@ -460,7 +791,7 @@ impl Marketplace {
         buyer_id: Uuid,
         payment_method: PaymentMethod,
     ) -> Result<PaymentResult> {
-        let package = self.get_package(package_id).await?;
+        let package = self.get_package_for_viewer(package_id, Some(buyer_id)).await?;
 
         // Check if package requires payment
         if let Some(pricing) = &package.pricing {
@@ -519,6 +850,116 @@ impl Marketplace {
         Ok(())
     }
 
+    /// Whether `viewer_id` may see a package. Only private packages are
+    /// restricted: to their publisher, members of the owning organization
+    /// and holders of an explicit access grant.
+    async fn can_view_package(&self, package: &Package, viewer_id: Option<Uuid>) -> Result<bool> {
+        if let Some(visible) = visibility_without_grants(package, viewer_id) {
+            return Ok(visible);
+        }
+        let Some(viewer_id) = viewer_id else {
+            return Ok(false);
+        };
+        if let Some(organization_id) = package.organization_id {
+            if self.database.get_organization_role(organization_id, viewer_id).await?.is_some() {
+                return Ok(true);
+            }
+        }
+        if !self.database.has_package_access(viewer_id, package.id).await? {
+            return Ok(false);
+        }
+        Ok(self.security.validate_package_access(package, viewer_id).await.is_ok())
+    }
+
+    /// Role of `user_id` in an organization. Non-members get
+    /// `OrganizationNotFound` so membership cannot be probed.
+    async fn organization_role(&self, organization_id: Uuid, user_id: Uuid) -> Result<OrganizationRole> {
+        self.database.get_organization_role(organization_id, user_id).await?
+            .ok_or(MarketplaceError::OrganizationNotFound(organization_id))
+    }
+
+    /// Only the publisher or marketplace staff may yank or deprecate
+    async fn ensure_can_manage_versions(&self, package: &Package, actor_id: Uuid) -> Result<()> {
+        if package.publisher_id == actor_id {
+            return Ok(());
+        }
+        let actor = self.get_user(actor_id).await?;
+        match actor.role {
+            UserRole::Admin | UserRole::Moderator => Ok(()),
+            _ => Err(MarketplaceError::InsufficientPermissions),
+        }
+    }
+
+    /// Notify publishers of dependent packages and users who installed the
+    /// affected version. Failures are logged; the yank/deprecation stands.
+    async fn notify_version_consumers(&self, advisory: &VersionAdvisory) {
+        let mut recipient_ids = std::collections::HashSet::new();
+
+        match self.database.get_dependent_versions(&advisory.package_name).await {
+            Ok(dependents) => {
+                for dependent in dependents {
+                    if dependent.yanked || dependent.package_id == advisory.package_id {
+                        continue;
+                    }
+                    let requirement = dependent.dependencies.get(&advisory.package_name)
+                        .or_else(|| dependent.peer_dependencies.get(&advisory.package_name));
+                    let affected = requirement
+                        .and_then(|req| semver::VersionReq::parse(req).ok())
+                        .map_or(true, |req| req.matches(&advisory.version));
+                    if !affected {
+                        continue;
+                    }
+                    if let Ok(package) = self.get_package(dependent.package_id).await {
+                        recipient_ids.insert(package.publisher_id);
+                    }
+                }
+            },
+            Err(e) => tracing::error!("Failed to look up dependents of {}: {}", advisory.package_name, e),
+        }
+
+        match self.database.get_version_installers(advisory.package_id, &advisory.version).await {
+            Ok(installers) => recipient_ids.extend(installers),
+            Err(e) => tracing::error!("Failed to look up installers of {}: {}", advisory.package_name, e),
+        }
+
+        let mut recipients = Vec::new();
+        for user_id in recipient_ids {
+            match self.get_user(user_id).await {
+                Ok(user) => recipients.push(user),
+                Err(e) => tracing::warn!("Skipping advisory recipient {}: {}", user_id, e),
+            }
+        }
+
+        if let Err(e) = self.notifications.notify_version_advisory(advisory, &recipients).await {
+            tracing::error!("Failed to send version advisory: {}", e);
+        }
+    }
+
+    /// Published versions of `package_name` and of every package its
+    /// versions transitively depend on, keyed by name. Unknown dependencies
+    /// map to no versions so the resolver can report them.
+    async fn dependency_index(&self, package_name: &str) -> Result<std::collections::HashMap<String, Vec<PackageVersion>>> {
+        let mut index = std::collections::HashMap::new();
+        let mut queue = vec![package_name.to_string()];
+
+        while let Some(name) = queue.pop() {
+            if index.contains_key(&name) {
+                continue;
+            }
+            let versions = match self.database.get_package_by_name(&name).await {
+                Ok(package) => self.database.get_package_versions(package.id).await?,
+                Err(MarketplaceError::PackageNotFound(_)) => Vec::new(),
+                Err(e) => return Err(e),
+            };
+            for version in versions.iter().filter(|v| !v.yanked) {
+                queue.extend(version.dependencies.keys().cloned());
+                queue.extend(version.peer_dependencies.keys().cloned());
+            }
+            index.insert(name, versions);
+        }
+        Ok(index)
+    }
+
     /// Calculate file hash for integrity verification
     fn calculate_file_hash(&self, data: &[u8]) -> String {
         use sha2::{Sha256, Digest};
@@ -540,4 +981,242 @@ impl Marketplace {
 
         Ok(extract_path)
     }
-}
\ No newline at end of file
+}
+
+/// Pick the version consumers should move to: the newest healthy version
+/// above `version`, or failing that the newest healthy one below it.
+fn suggest_replacement(versions: &[PackageVersion], version: &semver::Version) -> Option<semver::Version> {
+    let healthy = || versions.iter()
+        .filter(|v| !v.yanked && !v.deprecated && v.version != *version && v.version.pre.is_empty())
+        .map(|v| &v.version);
+
+    healthy().filter(|v| *v > version).max()
+        .or_else(|| healthy().max())
+        .cloned()
+}
+
+/// Warnings to surface when serving a yanked or deprecated version
+fn version_warnings(package: &Package, version: &PackageVersion) -> Vec<String> {
+    let mut warnings = Vec::new();
+    let suggestion = version.replacement_version.as_ref()
+        .map(|v| format!(" (consider upgrading to {})", v))
+        .unwrap_or_default();
+
+    if version.yanked {
+        warnings.push(format!(
+            "{}@{} has been yanked: {}{}",
+            package.name,
+            version.version,
+            version.yank_reason.as_deref().unwrap_or("no reason given"),
+            suggestion,
+        ));
+    }
+    if version.deprecated {
+        warnings.push(format!(
+            "{}@{} is deprecated: {}{}",
+            package.name,
+            version.version,
+            version.deprecation_message.as_deref().unwrap_or("no reason given"),
+            suggestion,
+        ));
+    }
+    warnings
+}
+
+/// Visibility decision that needs no database lookup, or `None` when a private
+/// package has to be checked against memberships and grants
+fn visibility_without_grants(package: &Package, viewer_id: Option<Uuid>) -> Option<bool> {
+    if package.visibility != PackageVisibility::Private {
+        return Some(true);
+    }
+    match viewer_id {
+        None => Some(false),
+        Some(viewer_id) if package.publisher_id == viewer_id => Some(true),
+        Some(_) => None,
+    }
+}
+
+/// Whether a package belongs in search results, given the organization catalog filter
+fn listed_in_search(package: &Package, catalog: Option<&std::collections::HashSet<Uuid>>) -> bool {
+    package.visibility != PackageVisibility::Unlisted
+        && catalog.map_or(true, |ids| ids.contains(&package.id))
+}
+
+/// Packages outside an organization's catalog are reported as missing
+fn require_in_catalog(catalog: &[Uuid], package: &Package) -> Result<()> {
+    if !catalog.contains(&package.id) {
+        return Err(MarketplaceError::PackageNotFound(package.id));
+    }
+    Ok(())
+}
+
+/// A package the installer cannot see is reported as missing before its type
+/// is checked, so the error does not reveal that a private package exists
+fn require_visible_plugin(package: &Package, visible: bool) -> Result<()> {
+    if !visible {
+        return Err(MarketplaceError::PackageNotFound(package.id));
+    }
+    if package.package_type != PackageType::Plugin {
+        return Err(MarketplaceError::InvalidRequest(format!("{} is not a plugin", package.name)));
+    }
+    Ok(())
+}
+
+/// Rules for changes that touch an owner: only an owner may make them, and the
+/// last owner can be neither demoted nor removed
+fn check_owner_change(
+    organization: &Organization,
+    actor_role: OrganizationRole,
+    user_id: Uuid,
+    current_role: Option<OrganizationRole>,
+    new_role: Option<OrganizationRole>,
+    owner_count: usize,
+) -> Result<()> {
+    let was_owner = current_role == Some(OrganizationRole::Owner);
+    let touches_owner = user_id == organization.owner_id
+        || was_owner
+        || new_role == Some(OrganizationRole::Owner);
+    if touches_owner && actor_role != OrganizationRole::Owner {
+        return Err(MarketplaceError::InsufficientPermissions);
+    }
+    if was_owner && new_role != Some(OrganizationRole::Owner) && owner_count <= 1 {
+        return Err(MarketplaceError::InvalidRequest("An organization must keep at least one owner".to_string()));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::PackageLicense;
+    use std::collections::HashSet;
+
+    fn package(visibility: PackageVisibility, package_type: PackageType) -> Package {
+        Package {
+            id: Uuid::new_v4(),
+            name: "acme-lint".to_string(),
+            description: String::new(),
+            package_type,
+            visibility,
+            license: PackageLicense::OpenSource("MIT".to_string()),
+            publisher_id: Uuid::new_v4(),
+            current_version: semver::Version::new(1, 0, 0),
+            total_downloads: 0,
+            rating: 0.0,
+            review_count: 0,
+            tags: Vec::new(),
+            categories: Vec::new(),
+            readme: None,
+            documentation_url: None,
+            repository_url: None,
+            homepage_url: None,
+            created_at: chrono::Utc::now(),
+            updated_at: chrono::Utc::now(),
+            metadata: serde_json::Value::Null,
+            pricing: None,
+            organization_id: None,
+        }
+    }
+
+    fn organization() -> Organization {
+        Organization {
+            id: Uuid::new_v4(),
+            name: "acme".to_string(),
+            display_name: None,
+            owner_id: Uuid::new_v4(),
+            created_at: chrono::Utc::now(),
+        }
+    }
+
+    #[test]
+    fn private_packages_are_hidden_from_anonymous_and_unrelated_viewers() {
+        let private = package(PackageVisibility::Private, PackageType::Library);
+
+        assert_eq!(visibility_without_grants(&private, None), Some(false));
+        assert_eq!(visibility_without_grants(&private, Some(private.publisher_id)), Some(true));
+        // Anyone else needs a membership or grant lookup
+        assert_eq!(visibility_without_grants(&private, Some(Uuid::new_v4())), None);
+    }
+
+    #[test]
+    fn unlisted_packages_are_reachable_but_not_searchable() {
+        let unlisted = package(PackageVisibility::Unlisted, PackageType::Library);
+        let public = package(PackageVisibility::Public, PackageType::Library);
+
+        assert_eq!(visibility_without_grants(&unlisted, None), Some(true));
+        assert!(!listed_in_search(&unlisted, None));
+        assert!(listed_in_search(&public, None));
+    }
+
+    #[test]
+    fn invisible_plugins_are_not_found_before_the_type_check() {
+        let private_library = package(PackageVisibility::Private, PackageType::Library);
+
+        let err = require_visible_plugin(&private_library, false).unwrap_err();
+        assert!(matches!(err, MarketplaceError::PackageNotFound(id) if id == private_library.id));
+
+        let err = require_visible_plugin(&private_library, true).unwrap_err();
+        assert!(matches!(err, MarketplaceError::InvalidRequest(_)));
+
+        let plugin = package(PackageVisibility::Public, PackageType::Plugin);
+        assert!(require_visible_plugin(&plugin, true).is_ok());
+    }
+
+    #[test]
+    fn catalog_allowlist_limits_search_and_install() {
+        let allowed = package(PackageVisibility::Public, PackageType::Library);
+        let other = package(PackageVisibility::Public, PackageType::Library);
+        let catalog: HashSet<Uuid> = [allowed.id].into_iter().collect();
+
+        assert!(listed_in_search(&allowed, Some(&catalog)));
+        assert!(!listed_in_search(&other, Some(&catalog)));
+
+        assert!(require_in_catalog(&[allowed.id], &allowed).is_ok());
+        let err = require_in_catalog(&[allowed.id], &other).unwrap_err();
+        assert!(matches!(err, MarketplaceError::PackageNotFound(id) if id == other.id));
+    }
+
+    #[test]
+    fn only_owners_may_change_owner_rows() {
+        let org = organization();
+
+        let err = check_owner_change(
+            &org, OrganizationRole::Admin, org.owner_id,
+            Some(OrganizationRole::Owner), Some(OrganizationRole::Member), 2,
+        ).unwrap_err();
+        assert!(matches!(err, MarketplaceError::InsufficientPermissions));
+
+        let err = check_owner_change(
+            &org, OrganizationRole::Admin, Uuid::new_v4(),
+            Some(OrganizationRole::Member), Some(OrganizationRole::Owner), 1,
+        ).unwrap_err();
+        assert!(matches!(err, MarketplaceError::InsufficientPermissions));
+
+        assert!(check_owner_change(
+            &org, OrganizationRole::Admin, Uuid::new_v4(),
+            Some(OrganizationRole::Member), Some(OrganizationRole::Admin), 1,
+        ).is_ok());
+    }
+
+    #[test]
+    fn the_last_owner_cannot_be_demoted_or_removed() {
+        let org = organization();
+
+        let err = check_owner_change(
+            &org, OrganizationRole::Owner, org.owner_id,
+            Some(OrganizationRole::Owner), Some(OrganizationRole::Admin), 1,
+        ).unwrap_err();
+        assert!(matches!(err, MarketplaceError::InvalidRequest(_)));
+
+        let err = check_owner_change(
+            &org, OrganizationRole::Owner, Uuid::new_v4(),
+            Some(OrganizationRole::Owner), None, 1,
+        ).unwrap_err();
+        assert!(matches!(err, MarketplaceError::InvalidRequest(_)));
+
+        assert!(check_owner_change(
+            &org, OrganizationRole::Owner, org.owner_id,
+            Some(OrganizationRole::Owner), Some(OrganizationRole::Admin), 2,
+        ).is_ok());
+    }
+}
@@ -29,6 +29,7 @@ impl DatabaseManager {
 
         // Create tables
         self.create_users_table().await?;
+        self.create_organizations_tables().await?;
         self.create_packages_table().await?;
         self.create_package_versions_table().await?;
         self.create_package_reviews_table().await?;
@@ -36,6 +37,7 @@ impl DatabaseManager {
         self.create_package_installations_table().await?;
         self.create_user_package_access_table().await?;
         self.create_payment_records_table().await?;
+        self.create_organization_catalog_table().await?;
 
         // Create indexes
         self.create_indexes().await?;
@@ -73,10 +75,10 @@ impl DatabaseManager {
                 id, name, description, package_type, visibility, publisher_id,
                 current_version, total_downloads, rating, review_count,
                 tags, categories, readme, documentation_url, repository_url,
-                homepage_url, created_at, updated_at, metadata
+                homepage_url, created_at, updated_at, metadata, organization_id
             ) VALUES (
                 $1, $2, $3, $4, $5, $6, $7, $8, $9, $10,
-                $11, $12, $13, $14, $15, $16, $17, $18, $19
+                $11, $12, $13, $14, $15, $16, $17, $18, $19, $20
             )
             "#,
             package.id,
@@ -97,7 +99,8 @@ impl DatabaseManager {
             package.homepage_url,
             package.created_at,
             package.updated_at,
-            package.metadata
+            package.metadata,
+            package.organization_id
         ).execute(tx).await?;
 
         Ok(())
@@ -115,9 +118,11 @@ impl DatabaseManager {
             INSERT INTO package_versions (
                 id, package_id, version, file_url, file_size, file_hash,
                 changelog, dependencies, dev_dependencies, peer_dependencies,
-                compatibility, published_at, yanked, yank_reason
+                compatibility, published_at, yanked, yank_reason,
+                deprecated, deprecation_message, replacement_version
             ) VALUES (
-                $1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14
+                $1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14,
+                $15, $16, $17
             )
             "#,
             version.id,
@@ -133,7 +138,10 @@ impl DatabaseManager {
             compatibility_json,
             version.published_at,
             version.yanked,
-            version.yank_reason
+            version.yank_reason,
+            version.deprecated,
+            version.deprecation_message,
+            version.replacement_version.as_ref().map(|v| v.to_string())
         ).execute(tx).await?;
 
         Ok(())
@@ -147,7 +155,7 @@ impl DatabaseManager {
                 id, name, description, package_type, visibility, publisher_id,
                 current_version, total_downloads, rating, review_count,
                 tags, categories, readme, documentation_url, repository_url,
-                homepage_url, created_at, updated_at, metadata
+                homepage_url, created_at, updated_at, metadata, organization_id
             FROM packages WHERE id = $1
             "#,
             package_id
@@ -176,6 +184,7 @@ impl DatabaseManager {
             updated_at: row.updated_at,
             metadata: row.metadata,
             pricing: None, // TODO: Implement pricing table
+            organization_id: row.organization_id,
         })
     }
 
@@ -187,7 +196,7 @@ impl DatabaseManager {
                 id, name, description, package_type, visibility, publisher_id,
                 current_version, total_downloads, rating, review_count,
                 tags, categories, readme, documentation_url, repository_url,
-                homepage_url, created_at, updated_at, metadata
+                homepage_url, created_at, updated_at, metadata, organization_id
             FROM packages WHERE name = $1
             "#,
             name
@@ -216,6 +225,7 @@ impl DatabaseManager {
             updated_at: row.updated_at,
             metadata: row.metadata,
             pricing: None,
+            organization_id: row.organization_id,
         })
     }
 
@@ -227,7 +237,7 @@ impl DatabaseManager {
                 id, name, description, package_type, visibility, publisher_id,
                 current_version, total_downloads, rating, review_count,
                 tags, categories, readme, documentation_url, repository_url,
-                homepage_url, created_at, updated_at, metadata
+                homepage_url, created_at, updated_at, metadata, organization_id
             FROM packages
             ORDER BY created_at DESC
             "#
@@ -257,6 +267,7 @@ impl DatabaseManager {
                 updated_at: row.updated_at,
                 metadata: row.metadata,
                 pricing: None,
+                organization_id: row.organization_id,
             });
         }
 
@@ -270,7 +281,8 @@ impl DatabaseManager {
             SELECT
                 id, package_id, version, file_url, file_size, file_hash,
                 changelog, dependencies, dev_dependencies, peer_dependencies,
-                compatibility, published_at, yanked, yank_reason
+                compatibility, published_at, yanked, yank_reason,
+                deprecated, deprecation_message, replacement_version
             FROM package_versions
             WHERE package_id = $1 AND version = $2
             "#,
@@ -294,6 +306,9 @@ impl DatabaseManager {
             published_at: row.published_at,
             yanked: row.yanked,
             yank_reason: row.yank_reason,
+            deprecated: row.deprecated,
+            deprecation_message: row.deprecation_message,
+            replacement_version: row.replacement_version.and_then(|v| semver::Version::parse(&v).ok()),
         })
     }
 
@@ -304,7 +319,8 @@ impl DatabaseManager {
             SELECT
                 id, package_id, version, file_url, file_size, file_hash,
                 changelog, dependencies, dev_dependencies, peer_dependencies,
-                compatibility, published_at, yanked, yank_reason
+                compatibility, published_at, yanked, yank_reason,
+                deprecated, deprecation_message, replacement_version
             FROM package_versions
             WHERE package_id = $1 AND NOT yanked
             ORDER BY published_at DESC
@@ -329,9 +345,129 @@ impl DatabaseManager {
             published_at: row.published_at,
             yanked: row.yanked,
             yank_reason: row.yank_reason,
+            deprecated: row.deprecated,
+            deprecation_message: row.deprecation_message,
+            replacement_version: row.replacement_version.and_then(|v| semver::Version::parse(&v).ok()),
         })
     }
 
+    /// Get all versions of a package, newest first
+    pub async fn get_package_versions(&self, package_id: Uuid) -> Result<Vec<PackageVersion>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT
+                id, package_id, version, file_url, file_size, file_hash,
+                changelog, dependencies, dev_dependencies, peer_dependencies,
+                compatibility, published_at, yanked, yank_reason,
+                deprecated, deprecation_message, replacement_version
+            FROM package_versions
+            WHERE package_id = $1
+            ORDER BY published_at DESC
+            "#
+        )
+        .bind(package_id)
+        .fetch_all(&self.pool).await?;
+
+        rows.iter().map(|row| self.version_from_row(row)).collect()
+    }
+
+    /// Get versions of other packages that declare a dependency on `package_name`
+    pub async fn get_dependent_versions(&self, package_name: &str) -> Result<Vec<PackageVersion>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT
+                id, package_id, version, file_url, file_size, file_hash,
+                changelog, dependencies, dev_dependencies, peer_dependencies,
+                compatibility, published_at, yanked, yank_reason,
+                deprecated, deprecation_message, replacement_version
+            FROM package_versions
+            WHERE jsonb_exists(dependencies, $1) OR jsonb_exists(peer_dependencies, $1)
+            "#
+        )
+        .bind(package_name)
+        .fetch_all(&self.pool).await?;
+
+        rows.iter().map(|row| self.version_from_row(row)).collect()
+    }
+
+    /// Get users with an active installation of a package version
+    pub async fn get_version_installers(&self, package_id: Uuid, version: &semver::Version) -> Result<Vec<Uuid>> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT DISTINCT installer_id
+            FROM package_installations
+            WHERE package_id = $1 AND version = $2 AND status <> 'uninstalled'
+            "#,
+            package_id,
+            version.to_string()
+        ).fetch_all(&self.pool).await?;
+
+        Ok(rows.into_iter().map(|row| row.installer_id).collect())
+    }
+
+    /// Mark a version as yanked; the artifact itself is kept
+    pub async fn set_version_yanked(
+        &self,
+        package_id: Uuid,
+        version: &semver::Version,
+        reason: &str,
+        replacement: Option<&semver::Version>,
+    ) -> Result<()> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE package_versions
+            SET yanked = TRUE, yank_reason = $3, replacement_version = COALESCE($4, replacement_version)
+            WHERE package_id = $1 AND version = $2
+            "#,
+            package_id,
+            version.to_string(),
+            reason,
+            replacement.map(|v| v.to_string())
+        ).execute(&self.pool).await?;
+
+        if result.rows_affected() == 0 {
+            return Err(MarketplaceError::VersionNotFound(version.clone()));
+        }
+        Ok(())
+    }
+
+    /// Mark a version as deprecated
+    pub async fn set_version_deprecated(
+        &self,
+        package_id: Uuid,
+        version: &semver::Version,
+        message: &str,
+        replacement: Option<&semver::Version>,
+    ) -> Result<()> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE package_versions
+            SET deprecated = TRUE, deprecation_message = $3, replacement_version = COALESCE($4, replacement_version)
+            WHERE package_id = $1 AND version = $2
+            "#,
+            package_id,
+            version.to_string(),
+            message,
+            replacement.map(|v| v.to_string())
+        ).execute(&self.pool).await?;
+
+        if result.rows_affected() == 0 {
+            return Err(MarketplaceError::VersionNotFound(version.clone()));
+        }
+        Ok(())
+    }
+
+    /// Point a package at a different current version
+    pub async fn update_package_current_version(&self, package_id: Uuid, version: &semver::Version) -> Result<()> {
+        sqlx::query!(
+            "UPDATE packages SET current_version = $1, updated_at = NOW() WHERE id = $2",
+            version.to_string(),
+            package_id
+        ).execute(&self.pool).await?;
+
+        Ok(())
+    }
+
     /// Get user by ID
     pub async fn get_user(&self, user_id: Uuid) -> Result<User> {
         let row = sqlx::query!(
@@ -555,8 +691,176 @@ impl DatabaseManager {
         Ok(())
     }
 
+    /// Create an organization and make its creator the owner
+    pub async fn create_organization(&self, organization: &Organization) -> Result<()> {
+        let mut tx = self.begin_transaction().await?;
+
+        sqlx::query!(
+            r#"
+            INSERT INTO organizations (id, name, display_name, owner_id, created_at)
+            VALUES ($1, $2, $3, $4, $5)
+            "#,
+            organization.id,
+            organization.name,
+            organization.display_name,
+            organization.owner_id,
+            organization.created_at
+        ).execute(&mut *tx).await?;
+
+        sqlx::query!(
+            r#"
+            INSERT INTO organization_members (organization_id, user_id, role, joined_at)
+            VALUES ($1, $2, 'owner', NOW())
+            "#,
+            organization.id,
+            organization.owner_id
+        ).execute(&mut *tx).await?;
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Get organization by ID
+    pub async fn get_organization(&self, organization_id: Uuid) -> Result<Organization> {
+        let row = sqlx::query!(
+            "SELECT id, name, display_name, owner_id, created_at FROM organizations WHERE id = $1",
+            organization_id
+        ).fetch_one(&self.pool).await
+        .map_err(|_| MarketplaceError::OrganizationNotFound(organization_id))?;
+
+        Ok(Organization {
+            id: row.id,
+            name: row.name,
+            display_name: row.display_name,
+            owner_id: row.owner_id,
+            created_at: row.created_at,
+        })
+    }
+
+    /// Add a member to an organization, or change their role
+    pub async fn upsert_organization_member(&self, organization_id: Uuid, user_id: Uuid, role: OrganizationRole) -> Result<()> {
+        sqlx::query!(
+            r#"
+            INSERT INTO organization_members (organization_id, user_id, role, joined_at)
+            VALUES ($1, $2, $3, NOW())
+            ON CONFLICT (organization_id, user_id) DO UPDATE SET role = EXCLUDED.role
+            "#,
+            organization_id,
+            user_id,
+            self.organization_role_str(role)
+        ).execute(&self.pool).await?;
+
+        Ok(())
+    }
+
+    /// Remove a member from an organization
+    pub async fn remove_organization_member(&self, organization_id: Uuid, user_id: Uuid) -> Result<()> {
+        sqlx::query!(
+            "DELETE FROM organization_members WHERE organization_id = $1 AND user_id = $2",
+            organization_id,
+            user_id
+        ).execute(&self.pool).await?;
+
+        Ok(())
+    }
+
+    /// Get a user's role in an organization, if they are a member
+    pub async fn get_organization_role(&self, organization_id: Uuid, user_id: Uuid) -> Result<Option<OrganizationRole>> {
+        let row = sqlx::query!(
+            "SELECT role FROM organization_members WHERE organization_id = $1 AND user_id = $2",
+            organization_id,
+            user_id
+        ).fetch_optional(&self.pool).await?;
+
+        Ok(row.map(|row| self.parse_organization_role(&row.role)))
+    }
+
+    /// Number of members holding the owner role
+    pub async fn count_organization_owners(&self, organization_id: Uuid) -> Result<usize> {
+        let count = sqlx::query!(
+            "SELECT COUNT(*) as count FROM organization_members WHERE organization_id = $1 AND role = $2",
+            organization_id,
+            self.organization_role_str(OrganizationRole::Owner)
+        ).fetch_one(&self.pool).await?;
+
+        Ok(count.count.unwrap_or(0) as usize)
+    }
+
+    /// Check whether a user holds an explicit access grant for a package
+    pub async fn has_package_access(&self, user_id: Uuid, package_id: Uuid) -> Result<bool> {
+        let count = sqlx::query!(
+            r#"
+            SELECT COUNT(*) as count FROM user_package_access
+            WHERE user_id = $1 AND package_id = $2 AND (expires_at IS NULL OR expires_at > NOW())
+            "#,
+            user_id,
+            package_id
+        ).fetch_one(&self.pool).await?;
+
+        Ok(count.count.unwrap_or(0) > 0)
+    }
+
+    /// Allowlist a package into an organization's catalog
+    pub async fn add_catalog_entry(&self, entry: &CatalogEntry) -> Result<()> {
+        sqlx::query!(
+            r#"
+            INSERT INTO organization_catalog (organization_id, package_id, added_by, added_at)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT (organization_id, package_id) DO NOTHING
+            "#,
+            entry.organization_id,
+            entry.package_id,
+            entry.added_by,
+            entry.added_at
+        ).execute(&self.pool).await?;
+
+        Ok(())
+    }
+
+    /// Remove a package from an organization's catalog
+    pub async fn remove_catalog_entry(&self, organization_id: Uuid, package_id: Uuid) -> Result<()> {
+        sqlx::query!(
+            "DELETE FROM organization_catalog WHERE organization_id = $1 AND package_id = $2",
+            organization_id,
+            package_id
+        ).execute(&self.pool).await?;
+
+        Ok(())
+    }
+
+    /// IDs of every package in an organization's catalog: its own packages
+    /// plus the allowlisted public ones
+    pub async fn get_catalog_package_ids(&self, organization_id: Uuid) -> Result<Vec<Uuid>> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT id AS "package_id!" FROM packages WHERE organization_id = $1
+            UNION
+            SELECT package_id AS "package_id!" FROM organization_catalog WHERE organization_id = $1
+            "#,
+            organization_id
+        ).fetch_all(&self.pool).await?;
+
+        Ok(rows.into_iter().map(|row| row.package_id).collect())
+    }
+
     // Private helper methods
 
+    fn organization_role_str(&self, role: OrganizationRole) -> &'static str {
+        match role {
+            OrganizationRole::Owner => "owner",
+            OrganizationRole::Admin => "admin",
+            OrganizationRole::Member => "member",
+        }
+    }
+
+    fn parse_organization_role(&self, role_str: &str) -> OrganizationRole {
+        match role_str {
+            "owner" => OrganizationRole::Owner,
+            "admin" => OrganizationRole::Admin,
+            _ => OrganizationRole::Member,
+        }
+    }
+
     fn parse_package_type(&self, type_str: &str) -> crate::PackageType {
         match type_str {
             "template" => crate::PackageType::Template,
@@ -578,6 +882,33 @@ impl DatabaseManager {
         }
     }
 
+    fn version_from_row(&self, row: &sqlx::postgres::PgRow) -> Result<PackageVersion> {
+        let version: String = row.try_get("version")?;
+        let replacement: Option<String> = row.try_get("replacement_version")?;
+
+        Ok(PackageVersion {
+            id: row.try_get("id")?,
+            package_id: row.try_get("package_id")?,
+            version: semver::Version::parse(&version)
+                .map_err(|e| MarketplaceError::InvalidVersion(e.to_string()))?,
+            file_url: row.try_get("file_url")?,
+            file_size: row.try_get("file_size")?,
+            file_hash: row.try_get("file_hash")?,
+            changelog: row.try_get("changelog")?,
+            dependencies: serde_json::from_value(row.try_get("dependencies")?).unwrap_or_default(),
+            dev_dependencies: serde_json::from_value(row.try_get("dev_dependencies")?).unwrap_or_default(),
+            peer_dependencies: serde_json::from_value(row.try_get("peer_dependencies")?).unwrap_or_default(),
+            compatibility: row.try_get::<Option<serde_json::Value>, _>("compatibility")?
+                .and_then(|v| serde_json::from_value(v).ok()),
+            published_at: row.try_get("published_at")?,
+            yanked: row.try_get::<Option<bool>, _>("yanked")?.unwrap_or(false),
+            yank_reason: row.try_get("yank_reason")?,
+            deprecated: row.try_get::<Option<bool>, _>("deprecated")?.unwrap_or(false),
+            deprecation_message: row.try_get("deprecation_message")?,
+            replacement_version: replacement.and_then(|v| semver::Version::parse(&v).ok()),
+        })
+    }
+
     fn parse_user_role(&self, role_str: &str) -> UserRole {
         match role_str {
             "user" => UserRole::User,
@@ -645,7 +976,57 @@ impl DatabaseManager {
                 homepage_url TEXT,
                 created_at TIMESTAMPTZ DEFAULT NOW(),
                 updated_at TIMESTAMPTZ DEFAULT NOW(),
-                metadata JSONB DEFAULT '{}'
+                metadata JSONB DEFAULT '{}',
+                organization_id UUID REFERENCES organizations(id)
+            )
+            "#
+        ).execute(&self.pool).await?;
+
+        // Columns added after the initial schema
+        sqlx::query!(
+            "ALTER TABLE packages ADD COLUMN IF NOT EXISTS organization_id UUID REFERENCES organizations(id)"
+        ).execute(&self.pool).await?;
+
+        Ok(())
+    }
+
+    async fn create_organizations_tables(&self) -> Result<()> {
+        sqlx::query!(
+            r#"
+            CREATE TABLE IF NOT EXISTS organizations (
+                id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+                name VARCHAR(100) UNIQUE NOT NULL,
+                display_name VARCHAR(100),
+                owner_id UUID NOT NULL REFERENCES users(id),
+                created_at TIMESTAMPTZ DEFAULT NOW()
+            )
+            "#
+        ).execute(&self.pool).await?;
+
+        sqlx::query!(
+            r#"
+            CREATE TABLE IF NOT EXISTS organization_members (
+                organization_id UUID NOT NULL REFERENCES organizations(id) ON DELETE CASCADE,
+                user_id UUID NOT NULL REFERENCES users(id),
+                role VARCHAR(20) NOT NULL DEFAULT 'member',
+                joined_at TIMESTAMPTZ DEFAULT NOW(),
+                PRIMARY KEY (organization_id, user_id)
+            )
+            "#
+        ).execute(&self.pool).await?;
+
+        Ok(())
+    }
+
+    async fn create_organization_catalog_table(&self) -> Result<()> {
+        sqlx::query!(
+            r#"
+            CREATE TABLE IF NOT EXISTS organization_catalog (
+                organization_id UUID NOT NULL REFERENCES organizations(id) ON DELETE CASCADE,
+                package_id UUID NOT NULL REFERENCES packages(id) ON DELETE CASCADE,
+                added_by UUID NOT NULL REFERENCES users(id),
+                added_at TIMESTAMPTZ DEFAULT NOW(),
+                PRIMARY KEY (organization_id, package_id)
             )
             "#
         ).execute(&self.pool).await?;
@@ -671,11 +1052,24 @@ impl DatabaseManager {
                 published_at TIMESTAMPTZ DEFAULT NOW(),
                 yanked BOOLEAN DEFAULT FALSE,
                 yank_reason TEXT,
+                deprecated BOOLEAN DEFAULT FALSE,
+                deprecation_message TEXT,
+                replacement_version VARCHAR(50),
                 UNIQUE(package_id, version)
             )
             "#
         ).execute(&self.pool).await?;
 
+        // Columns added after the initial schema
+        sqlx::query!(
+            r#"
+            ALTER TABLE package_versions
+                ADD COLUMN IF NOT EXISTS deprecated BOOLEAN DEFAULT FALSE,
+                ADD COLUMN IF NOT EXISTS deprecation_message TEXT,
+                ADD COLUMN IF NOT EXISTS replacement_version VARCHAR(50)
+            "#
+        ).execute(&self.pool).await?;
+
         Ok(())
     }
 
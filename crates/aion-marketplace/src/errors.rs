@@ -15,9 +15,15 @@ pub enum MarketplaceError {
     #[error("User not found: {0}")]
     UserNotFound(Uuid),
 
+    #[error("Organization not found: {0}")]
+    OrganizationNotFound(Uuid),
+
     #[error("Version not found: {0}")]
     VersionNotFound(semver::Version),
 
+    #[error("Dependency conflict on {0}: {1:?}")]
+    DependencyConflict(String, Vec<String>),
+
     #[error("Package validation failed: {0:?}")]
     ValidationFailed(Vec<String>),
 
@@ -66,6 +66,7 @@ pub mod payments;
 pub mod notifications;
 pub mod search;
 pub mod validation;
+pub mod resolver;
 pub mod errors;
 pub mod config;
 
@@ -85,6 +86,7 @@ pub use payments::*;
 pub use notifications::*;
 pub use search::*;
 pub use validation::*;
+pub use resolver::*;
 pub use errors::*;
 pub use config::*;
 
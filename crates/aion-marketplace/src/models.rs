@@ -48,6 +48,9 @@ pub struct Package {
     pub metadata: serde_json::Value,
     /// Pricing information for commercial packages
     pub pricing: Option<PackagePricing>,
+    /// Owning organization; private packages are scoped to its members
+    #[serde(default)]
+    pub organization_id: Option<Uuid>,
 }
 
 /// Package version information
@@ -59,7 +62,7 @@ pub struct PackageVersion {
     pub package_id: Uuid,
     /// Semantic version
     pub version: semver::Version,
-    /// File storage URL
+    /// Storage path of the package archive
     pub file_url: String,
     /// File size in bytes
     pub file_size: i64,
@@ -81,6 +84,15 @@ pub struct PackageVersion {
     pub yanked: bool,
     /// Reason for yanking
     pub yank_reason: Option<String>,
+    /// Whether version is deprecated (still installable, but warns)
+    #[serde(default)]
+    pub deprecated: bool,
+    /// Deprecation notice shown to installers
+    #[serde(default)]
+    pub deprecation_message: Option<String>,
+    /// Suggested version to move to after a yank or deprecation
+    #[serde(default)]
+    pub replacement_version: Option<semver::Version>,
 }
 
 /// Package compatibility information
@@ -226,6 +238,9 @@ pub struct PackageDownload {
     pub user_agent: Option<String>,
     /// IP address
     pub ip_address: Option<String>,
+    /// Warnings about the served version (deprecated, or yanked but locked)
+    #[serde(default)]
+    pub warnings: Vec<String>,
 }
 
 /// Package installation record
@@ -357,6 +372,12 @@ pub struct SearchFilters {
     /// Date range filters
     pub created_after: Option<chrono::DateTime<chrono::Utc>>,
     pub updated_after: Option<chrono::DateTime<chrono::Utc>>,
+    /// Also list packages whose versions are all yanked (for debugging)
+    #[serde(default)]
+    pub include_yanked: bool,
+    /// Restrict results to an organization's catalog
+    #[serde(default)]
+    pub organization_id: Option<Uuid>,
 }
 
 /// Pagination parameters
@@ -465,6 +486,9 @@ pub struct CreatePackageRequest {
     pub metadata: Option<serde_json::Value>,
     /// Pricing (for commercial packages)
     pub pricing: Option<PackagePricing>,
+    /// Organization to publish under
+    #[serde(default)]
+    pub organization_id: Option<Uuid>,
 }
 
 /// Review creation request
@@ -535,6 +559,100 @@ pub struct InstallationResult {
     pub extracted_path: std::path::PathBuf,
     /// Package file data
     pub package_files: Vec<u8>,
+    /// Warnings about the installed version
+    pub warnings: Vec<String>,
+}
+
+/// Organization owning private packages and a curated catalog
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Organization {
+    /// Organization ID
+    pub id: Uuid,
+    /// Unique organization slug
+    pub name: String,
+    /// Display name
+    pub display_name: Option<String>,
+    /// Creating user
+    pub owner_id: Uuid,
+    /// Creation timestamp
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// Role of a user within an organization
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum OrganizationRole {
+    /// Full control, including membership
+    Owner,
+    /// Can manage members and the catalog
+    Admin,
+    /// Can see and install the organization's packages
+    Member,
+}
+
+impl OrganizationRole {
+    /// Whether this role may change membership and the catalog
+    pub fn can_manage(&self) -> bool {
+        matches!(self, OrganizationRole::Owner | OrganizationRole::Admin)
+    }
+}
+
+/// Public package allowlisted into an organization's catalog
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CatalogEntry {
+    /// Organization ID
+    pub organization_id: Uuid,
+    /// Allowlisted package ID
+    pub package_id: Uuid,
+    /// User who added the package
+    pub added_by: Uuid,
+    /// Timestamp of addition
+    pub added_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// Kind of version advisory
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum AdvisoryKind {
+    /// Version no longer served to new installs
+    Yanked,
+    /// Version still installable but discouraged
+    Deprecated,
+}
+
+/// Notice sent to consumers when a version is yanked or deprecated
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VersionAdvisory {
+    /// Advisory kind
+    pub kind: AdvisoryKind,
+    /// Affected package ID
+    pub package_id: Uuid,
+    /// Affected package name
+    pub package_name: String,
+    /// Affected version
+    pub version: semver::Version,
+    /// Reason given by the publisher
+    pub reason: Option<String>,
+    /// Suggested version to move to
+    pub replacement_version: Option<semver::Version>,
+    /// Advisory timestamp
+    pub issued_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl VersionAdvisory {
+    /// Human-readable one-line summary
+    pub fn message(&self) -> String {
+        let action = match self.kind {
+            AdvisoryKind::Yanked => "has been yanked",
+            AdvisoryKind::Deprecated => "is deprecated",
+        };
+        let mut message = format!("{}@{} {}", self.package_name, self.version, action);
+        if let Some(reason) = &self.reason {
+            message.push_str(&format!(": {}", reason));
+        }
+        if let Some(replacement) = &self.replacement_version {
+            message.push_str(&format!(" (consider upgrading to {})", replacement));
+        }
+        message
+    }
 }
 
 /// Search parameters
@@ -2,11 +2,12 @@ use crate::{config::NotificationConfig, models::*, errors::*};
 
 pub struct NotificationManager {
     config: NotificationConfig,
+    client: reqwest::Client,
 }
 
 impl NotificationManager {
     pub async fn new(config: NotificationConfig) -> Result<Self> {
-        Ok(Self { config })
+        Ok(Self { config, client: reqwest::Client::new() })
     }
 
     pub async fn notify_package_published(&self, _package: &Package, _publisher: &User) -> Result<()> {
@@ -16,4 +17,39 @@ impl NotificationManager {
     pub async fn notify_review_submitted(&self, _package: &Package, _review: &PackageReview, _reviewer: &User, _owner: &User) -> Result<()> {
         Ok(())
     }
-}
\ No newline at end of file
+
+    /// Tell dependents and installers that a version was yanked or deprecated.
+    ///
+    /// Delivery is best-effort: a failing webhook is logged and does not
+    /// abort the remaining deliveries.
+    pub async fn notify_version_advisory(&self, advisory: &VersionAdvisory, recipients: &[User]) -> Result<()> {
+        let message = advisory.message();
+        tracing::info!("Version advisory for {} recipient(s): {}", recipients.len(), message);
+
+        if self.config.webhook_enabled {
+            let payload = serde_json::json!({
+                "event": "package.version_advisory",
+                "message": message,
+                "advisory": advisory,
+                "recipients": recipients.iter().map(|user| user.id).collect::<Vec<_>>(),
+            });
+
+            for url in &self.config.webhook_urls {
+                let delivered = self.client.post(url).json(&payload).send().await
+                    .and_then(|response| response.error_for_status());
+                if let Err(e) = delivered {
+                    tracing::warn!("Failed to deliver version advisory to {}: {}", url, e);
+                }
+            }
+        }
+
+        if self.config.email_enabled {
+            // No SMTP transport is wired up yet; record who should be emailed.
+            for user in recipients {
+                tracing::debug!("Version advisory email pending for {}: {}", user.email, message);
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,340 @@
+//! Semantic-version dependency resolution for range installs.
+//!
+//! Given a package name and a [`semver::VersionReq`], picks the highest
+//! published, non-yanked version of the package and of every package it
+//! transitively depends on such that all requirements hold at once. When
+//! the newest candidate leads to a conflict further down, older candidates
+//! are tried before giving up.
+//!
+//! Runtime dependencies are pulled into the resolution. Peer dependencies
+//! only constrain a package that something else already pulls in.
+
+use crate::{errors::*, models::PackageVersion};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+/// Upper bound on candidate versions tried before reporting the first conflict
+const MAX_RESOLUTION_STEPS: usize = 10_000;
+
+/// A package version picked by the resolver
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResolvedPackage {
+    pub name: String,
+    pub version: semver::Version,
+}
+
+/// Result of resolving a range install
+#[derive(Debug, Clone)]
+pub struct Resolution {
+    /// Resolved packages, dependencies before their dependents; the
+    /// requested package comes last
+    pub packages: Vec<ResolvedPackage>,
+}
+
+impl Resolution {
+    /// The version picked for `name`, if it is part of the resolution
+    pub fn version_of(&self, name: &str) -> Option<&semver::Version> {
+        self.packages.iter().find(|p| p.name == name).map(|p| &p.version)
+    }
+}
+
+/// Resolve `name` at the highest version matching `requirement`, along with
+/// its transitive dependencies.
+///
+/// `index` holds the published versions of every package that may be
+/// reached, keyed by name. A package missing from the index is treated as
+/// having no versions.
+pub fn resolve_dependencies(
+    index: &HashMap<String, Vec<PackageVersion>>,
+    name: &str,
+    requirement: &semver::VersionReq,
+) -> Result<Resolution> {
+    let mut solver = Solver {
+        index,
+        selected: HashMap::new(),
+        constraints: vec![Constraint {
+            package: name.to_string(),
+            requirement: requirement.clone(),
+            required_by: None,
+        }],
+        steps: 0,
+    };
+    solver.solve(vec![name.to_string()])?;
+    Ok(Resolution { packages: solver.install_order(name) })
+}
+
+/// A requirement on a package, remembered so conflicts can name its source
+#[derive(Debug, Clone)]
+struct Constraint {
+    package: String,
+    requirement: semver::VersionReq,
+    /// Package version declaring the requirement; `None` for the install request
+    required_by: Option<(String, semver::Version)>,
+}
+
+impl fmt::Display for Constraint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.required_by {
+            Some((name, version)) => write!(f, "{}@{} requires {} {}", name, version, self.package, self.requirement),
+            None => write!(f, "install requested {} {}", self.package, self.requirement),
+        }
+    }
+}
+
+struct Solver<'a> {
+    index: &'a HashMap<String, Vec<PackageVersion>>,
+    selected: HashMap<String, &'a PackageVersion>,
+    constraints: Vec<Constraint>,
+    steps: usize,
+}
+
+impl<'a> Solver<'a> {
+    /// Select a version for every package in `pending`, backtracking on
+    /// conflicts. Leaves `selected` and `constraints` untouched on failure.
+    fn solve(&mut self, mut pending: Vec<String>) -> Result<()> {
+        let name = loop {
+            match pending.pop() {
+                Some(name) if self.selected.contains_key(&name) => continue,
+                Some(name) => break name,
+                None => return Ok(()),
+            }
+        };
+
+        let mut candidates: Vec<&'a PackageVersion> = self.index.get(&name)
+            .into_iter()
+            .flatten()
+            .filter(|v| !v.yanked && self.constraints_on(&name).all(|c| c.requirement.matches(&v.version)))
+            .collect();
+        candidates.sort_by(|a, b| b.version.cmp(&a.version));
+        if candidates.is_empty() {
+            return Err(self.conflict(&name));
+        }
+
+        let mut first_error = None;
+        for candidate in candidates {
+            if self.steps >= MAX_RESOLUTION_STEPS {
+                break;
+            }
+            self.steps += 1;
+
+            let mark = self.constraints.len();
+            self.selected.insert(name.clone(), candidate);
+            let outcome = self.require(&name, candidate, pending.clone())
+                .and_then(|next| self.solve(next));
+            match outcome {
+                Ok(()) => return Ok(()),
+                Err(error) => {
+                    first_error.get_or_insert(error);
+                }
+            }
+            self.constraints.truncate(mark);
+            self.selected.remove(&name);
+        }
+        Err(first_error.unwrap_or_else(|| self.conflict(&name)))
+    }
+
+    /// Record the requirements of a selected version, checking them against
+    /// packages already selected. Returns `pending` extended with runtime
+    /// dependencies that still need a version.
+    fn require(&mut self, name: &str, version: &PackageVersion, mut pending: Vec<String>) -> Result<Vec<String>> {
+        let runtime = version.dependencies.iter().map(|dep| (dep, true));
+        let peer = version.peer_dependencies.iter().map(|dep| (dep, false));
+
+        for ((dependency, requirement), pulls_in) in runtime.chain(peer) {
+            let requirement = semver::VersionReq::parse(requirement).map_err(|e| MarketplaceError::InvalidVersion(
+                format!("{}@{} dependency {} {}: {}", name, version.version, dependency, requirement, e),
+            ))?;
+            let satisfied = self.selected.get(dependency)
+                .map(|selected| requirement.matches(&selected.version));
+            self.constraints.push(Constraint {
+                package: dependency.clone(),
+                requirement,
+                required_by: Some((name.to_string(), version.version.clone())),
+            });
+
+            match satisfied {
+                Some(false) => return Err(self.conflict(dependency)),
+                None if pulls_in => pending.push(dependency.clone()),
+                _ => {},
+            }
+        }
+        Ok(pending)
+    }
+
+    fn constraints_on<'s>(&'s self, name: &'s str) -> impl Iterator<Item = &'s Constraint> + 's {
+        self.constraints.iter().filter(move |c| c.package == name)
+    }
+
+    fn conflict(&self, name: &str) -> MarketplaceError {
+        MarketplaceError::DependencyConflict(
+            name.to_string(),
+            self.constraints_on(name).map(|c| c.to_string()).collect(),
+        )
+    }
+
+    /// Selected packages reachable from `root`, dependencies first
+    fn install_order(&self, root: &str) -> Vec<ResolvedPackage> {
+        fn visit(solver: &Solver<'_>, name: &str, seen: &mut HashSet<String>, order: &mut Vec<ResolvedPackage>) {
+            let Some(version) = solver.selected.get(name) else { return };
+            if !seen.insert(name.to_string()) {
+                return;
+            }
+            let mut dependencies: Vec<&String> = version.dependencies.keys()
+                .chain(version.peer_dependencies.keys())
+                .collect();
+            dependencies.sort();
+            for dependency in dependencies {
+                visit(solver, dependency, seen, order);
+            }
+            order.push(ResolvedPackage { name: name.to_string(), version: version.version.clone() });
+        }
+
+        let mut order = Vec::new();
+        visit(self, root, &mut HashSet::new(), &mut order);
+        order
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    fn version(number: &str, dependencies: &[(&str, &str)]) -> PackageVersion {
+        PackageVersion {
+            id: Uuid::new_v4(),
+            package_id: Uuid::nil(),
+            version: semver::Version::parse(number).unwrap(),
+            file_url: String::new(),
+            file_size: 0,
+            file_hash: String::new(),
+            changelog: None,
+            dependencies: dependencies.iter().map(|(n, r)| (n.to_string(), r.to_string())).collect(),
+            dev_dependencies: HashMap::new(),
+            peer_dependencies: HashMap::new(),
+            compatibility: None,
+            published_at: chrono::Utc::now(),
+            yanked: false,
+            yank_reason: None,
+            deprecated: false,
+            deprecation_message: None,
+            replacement_version: None,
+        }
+    }
+
+    fn index(packages: Vec<(&str, Vec<PackageVersion>)>) -> HashMap<String, Vec<PackageVersion>> {
+        packages.into_iter().map(|(name, versions)| (name.to_string(), versions)).collect()
+    }
+
+    fn req(requirement: &str) -> semver::VersionReq {
+        semver::VersionReq::parse(requirement).unwrap()
+    }
+
+    fn versions(numbers: &[&str]) -> Vec<PackageVersion> {
+        numbers.iter().map(|n| version(n, &[])).collect()
+    }
+
+    #[test]
+    fn caret_range_picks_highest_compatible_version() {
+        let index = index(vec![("ai-code-reviewer", versions(&["1.1.0", "1.2.0", "1.4.3", "2.0.0"]))]);
+
+        let resolution = resolve_dependencies(&index, "ai-code-reviewer", &req("^1.2")).unwrap();
+        assert_eq!(resolution.version_of("ai-code-reviewer"), Some(&semver::Version::new(1, 4, 3)));
+    }
+
+    #[test]
+    fn tilde_range_stays_within_minor_version() {
+        let index = index(vec![("ai-code-reviewer", versions(&["1.2.0", "1.2.7", "1.3.0"]))]);
+
+        let resolution = resolve_dependencies(&index, "ai-code-reviewer", &req("~1.2")).unwrap();
+        assert_eq!(resolution.version_of("ai-code-reviewer"), Some(&semver::Version::new(1, 2, 7)));
+    }
+
+    #[test]
+    fn skips_yanked_versions() {
+        let mut published = versions(&["1.2.0", "1.3.0"]);
+        published[1].yanked = true;
+        let index = index(vec![("ai-code-reviewer", published)]);
+
+        let resolution = resolve_dependencies(&index, "ai-code-reviewer", &req("^1")).unwrap();
+        assert_eq!(resolution.version_of("ai-code-reviewer"), Some(&semver::Version::new(1, 2, 0)));
+    }
+
+    #[test]
+    fn resolves_transitive_dependencies_in_install_order() {
+        let index = index(vec![
+            ("reviewer", vec![version("1.0.0", &[("linter", "^2.1"), ("formatter", "~0.3")])]),
+            ("linter", vec![version("2.1.0", &[("ast", "^1")]), version("2.5.1", &[("ast", "^1.1")])]),
+            ("formatter", vec![version("0.3.2", &[("ast", "^1")]), version("0.4.0", &[])]),
+            ("ast", versions(&["1.0.0", "1.1.4", "2.0.0"])),
+        ]);
+
+        let resolution = resolve_dependencies(&index, "reviewer", &req("^1")).unwrap();
+        let order: Vec<String> = resolution.packages.iter().map(|p| format!("{}@{}", p.name, p.version)).collect();
+        assert_eq!(order, vec!["ast@1.1.4", "formatter@0.3.2", "linter@2.5.1", "reviewer@1.0.0"]);
+    }
+
+    #[test]
+    fn backtracks_to_an_older_version_to_avoid_a_conflict() {
+        let index = index(vec![
+            ("app", vec![version("1.0.0", &[("left", "^1"), ("right", "^1")])]),
+            ("left", vec![version("1.0.0", &[("shared", "^1")]), version("1.1.0", &[("shared", "^2")])]),
+            ("right", vec![version("1.0.0", &[("shared", "^1")])]),
+            ("shared", versions(&["1.3.0", "2.0.0"])),
+        ]);
+
+        let resolution = resolve_dependencies(&index, "app", &req("*")).unwrap();
+        assert_eq!(resolution.version_of("left"), Some(&semver::Version::new(1, 0, 0)));
+        assert_eq!(resolution.version_of("shared"), Some(&semver::Version::new(1, 3, 0)));
+    }
+
+    #[test]
+    fn diamond_conflict_lists_incompatible_constraints() {
+        let index = index(vec![
+            ("app", vec![version("1.0.0", &[("left", "^1"), ("right", "^1")])]),
+            ("left", vec![version("1.0.0", &[("shared", "^1")])]),
+            ("right", vec![version("1.0.0", &[("shared", "^2")])]),
+            ("shared", versions(&["1.3.0", "2.0.0"])),
+        ]);
+
+        match resolve_dependencies(&index, "app", &req("^1")) {
+            Err(MarketplaceError::DependencyConflict(package, mut constraints)) => {
+                constraints.sort();
+                assert_eq!(package, "shared");
+                assert_eq!(constraints, vec![
+                    "left@1.0.0 requires shared ^1",
+                    "right@1.0.0 requires shared ^2",
+                ]);
+            },
+            other => panic!("expected a dependency conflict, got {:?}", other.map(|r| r.packages)),
+        }
+    }
+
+    #[test]
+    fn reports_range_without_matching_version() {
+        let index = index(vec![("ai-code-reviewer", versions(&["1.2.0"]))]);
+
+        match resolve_dependencies(&index, "ai-code-reviewer", &req("^2")) {
+            Err(MarketplaceError::DependencyConflict(package, constraints)) => {
+                assert_eq!(package, "ai-code-reviewer");
+                assert_eq!(constraints, vec!["install requested ai-code-reviewer ^2"]);
+            },
+            other => panic!("expected a dependency conflict, got {:?}", other.map(|r| r.packages)),
+        }
+    }
+
+    #[test]
+    fn peer_dependencies_constrain_without_pulling_in() {
+        let mut host = version("1.0.0", &[("runtime", "^1")]);
+        host.peer_dependencies.insert("theme".to_string(), "^3".to_string());
+        let index = index(vec![
+            ("host", vec![host]),
+            ("runtime", vec![version("1.0.0", &[]), version("1.1.0", &[("theme", "^2")])]),
+            ("theme", versions(&["2.0.0", "3.0.0"])),
+        ]);
+
+        let resolution = resolve_dependencies(&index, "host", &req("*")).unwrap();
+        assert_eq!(resolution.version_of("runtime"), Some(&semver::Version::new(1, 0, 0)));
+        assert_eq!(resolution.version_of("theme"), None);
+    }
+}
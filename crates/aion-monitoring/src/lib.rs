@@ -7,11 +7,17 @@ use anyhow::Result;
 
 // Core monitoring modules
 pub mod real_time_monitor;
+pub mod metric_query;
+pub mod metrics_registry;
+pub mod prometheus_exporter;
 pub mod websocket_service;
 pub mod test_integration;
 
 // Re-export the main types
 pub use real_time_monitor::{RealTimeMonitor, MetricUpdate, DataPoint, DashboardUpdate, AlertEvent};
+pub use metric_query::{MetricQuery, QueryAggregation, QueryParseError, QueryValue};
+pub use metrics_registry::{MetricsRegistry, MetricsRegistryConfig, DEFAULT_INFERENCE_LATENCY_BUCKETS};
+pub use prometheus_exporter::PrometheusExporter;
 pub use websocket_service::{WebSocketService, WSMessage, ClientType};
 pub use test_integration::*;
 
@@ -19,19 +25,32 @@ pub use test_integration::*;
 pub struct AionMonitoring {
     pub real_time_monitor: std::sync::Arc<RealTimeMonitor>,
     pub websocket_service: std::sync::Arc<WebSocketService>,
+    pub metrics_registry: std::sync::Arc<MetricsRegistry>,
+    pub prometheus_exporter: std::sync::Arc<PrometheusExporter>,
 }
 
 impl AionMonitoring {
     /// Create a new AION monitoring system
     pub fn new() -> Self {
+        Self::with_metrics_registry(MetricsRegistry::new())
+    }
+
+    /// Create a monitoring system exporting the series of `metrics_registry`
+    pub fn with_metrics_registry(metrics_registry: MetricsRegistry) -> Self {
         let real_time_monitor = std::sync::Arc::new(RealTimeMonitor::new());
         let websocket_service = std::sync::Arc::new(WebSocketService::new(
             std::sync::Arc::clone(&real_time_monitor)
         ));
+        let metrics_registry = std::sync::Arc::new(metrics_registry);
+        let prometheus_exporter = std::sync::Arc::new(PrometheusExporter::new(
+            std::sync::Arc::clone(&metrics_registry)
+        ));
 
         Self {
             real_time_monitor,
             websocket_service,
+            metrics_registry,
+            prometheus_exporter,
         }
     }
 
@@ -44,6 +63,16 @@ impl AionMonitoring {
         Ok(())
     }
 
+    /// Record a completed model inference in the Prometheus series
+    pub fn record_inference(&self, model_id: &str, duration: std::time::Duration, tokens: u64) {
+        self.metrics_registry.record_inference(model_id, duration, tokens);
+    }
+
+    /// Render the Prometheus series in the text exposition format
+    pub fn get_prometheus_metrics(&self) -> Result<String> {
+        self.prometheus_exporter.render()
+    }
+
     /// Record a metric
     pub async fn record_metric(&self, metric: MetricUpdate) -> Result<()> {
         self.real_time_monitor.record_metric(metric).await
@@ -0,0 +1,317 @@
+//! Metric Query Expressions
+//!
+//! Parses and evaluates the aggregation queries dashboards subscribe to, such as
+//! `p95(aion.api.latency_ms{endpoint="/api/v1/generate"})`. A query applies one
+//! aggregation to the points of one metric, optionally filtered by labels:
+//!
+//! ```text
+//! query    := aggregation "(" metric [ "{" matcher { "," matcher } "}" ] ")"
+//! matcher  := label ( "=" | "!=" ) "\"" value "\""
+//! ```
+//!
+//! Supported aggregations are `avg`, `min`, `max`, `sum`, `count`, `last`,
+//! `rate` (sum per second of the window) and percentiles `p1` to `p99.9`.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::real_time_monitor::DataPoint;
+
+/// Aggregation applied to the points of a query's window
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum QueryAggregation {
+    Avg,
+    Min,
+    Max,
+    Sum,
+    Count,
+    Last,
+    /// Sum of values per second of the window
+    Rate,
+    /// Percentile in (0, 100)
+    Percentile(f64),
+}
+
+/// Label filter of a query
+#[derive(Debug, Clone, PartialEq)]
+pub struct LabelMatcher {
+    pub label: String,
+    pub value: String,
+    pub negated: bool,
+}
+
+impl LabelMatcher {
+    fn matches(&self, labels: &HashMap<String, String>) -> bool {
+        let equal = labels.get(&self.label).is_some_and(|value| *value == self.value);
+        equal != self.negated
+    }
+}
+
+/// A parsed metric query
+#[derive(Debug, Clone, PartialEq)]
+pub struct MetricQuery {
+    pub aggregation: QueryAggregation,
+    pub metric_name: String,
+    pub matchers: Vec<LabelMatcher>,
+}
+
+/// Result of evaluating a query over one window
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct QueryValue {
+    /// `None` when no point matched, except for `count` and `rate` which are zero then
+    pub value: Option<f64>,
+    /// Number of points the value was computed from
+    pub sample_count: usize,
+}
+
+/// Why a query could not be parsed
+#[derive(Debug, Clone, PartialEq)]
+pub struct QueryParseError {
+    pub message: String,
+    /// Character offset in the query where the problem was found
+    pub position: usize,
+}
+
+impl fmt::Display for QueryParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at position {}", self.message, self.position)
+    }
+}
+
+impl std::error::Error for QueryParseError {}
+
+impl MetricQuery {
+    pub fn parse(query: &str) -> Result<Self, QueryParseError> {
+        let mut parser = Parser { chars: query.chars().collect(), pos: 0 };
+        let parsed = parser.query()?;
+        parser.skip_whitespace();
+        if parser.pos < parser.chars.len() {
+            return Err(parser.error(format!("unexpected '{}' after the query", parser.chars[parser.pos])));
+        }
+        Ok(parsed)
+    }
+
+    /// Whether a point belongs to this query's series
+    pub fn matches(&self, point: &DataPoint) -> bool {
+        self.matchers.iter().all(|matcher| matcher.matches(&point.labels))
+    }
+
+    /// Aggregate the matching `points` of a window of length `window`
+    pub fn evaluate<'a>(&self, points: impl IntoIterator<Item = &'a DataPoint>, window: Duration) -> QueryValue {
+        let values: Vec<f64> = points
+            .into_iter()
+            .filter(|point| self.matches(point))
+            .map(|point| point.value)
+            .collect();
+
+        let sample_count = values.len();
+        QueryValue {
+            value: self.aggregate(values, window),
+            sample_count,
+        }
+    }
+
+    fn aggregate(&self, mut values: Vec<f64>, window: Duration) -> Option<f64> {
+        match self.aggregation {
+            QueryAggregation::Count => return Some(values.len() as f64),
+            QueryAggregation::Rate => {
+                let seconds = window.as_secs_f64();
+                return Some(if seconds > 0.0 { values.iter().sum::<f64>() / seconds } else { 0.0 });
+            }
+            _ if values.is_empty() => return None,
+            _ => {}
+        }
+
+        Some(match self.aggregation {
+            QueryAggregation::Avg => values.iter().sum::<f64>() / values.len() as f64,
+            QueryAggregation::Min => values.iter().cloned().fold(f64::INFINITY, f64::min),
+            QueryAggregation::Max => values.iter().cloned().fold(f64::NEG_INFINITY, f64::max),
+            QueryAggregation::Sum => values.iter().sum(),
+            QueryAggregation::Last => *values.last().expect("values is not empty"),
+            QueryAggregation::Percentile(p) => {
+                values.sort_by(|a, b| a.total_cmp(b));
+                let index = (p / 100.0 * (values.len() - 1) as f64) as usize;
+                values[index.min(values.len() - 1)]
+            }
+            QueryAggregation::Count | QueryAggregation::Rate => unreachable!("handled above"),
+        })
+    }
+}
+
+impl fmt::Display for MetricQuery {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.aggregation {
+            QueryAggregation::Avg => f.write_str("avg")?,
+            QueryAggregation::Min => f.write_str("min")?,
+            QueryAggregation::Max => f.write_str("max")?,
+            QueryAggregation::Sum => f.write_str("sum")?,
+            QueryAggregation::Count => f.write_str("count")?,
+            QueryAggregation::Last => f.write_str("last")?,
+            QueryAggregation::Rate => f.write_str("rate")?,
+            QueryAggregation::Percentile(p) => write!(f, "p{}", p)?,
+        }
+        write!(f, "({}", self.metric_name)?;
+        if !self.matchers.is_empty() {
+            let matchers: Vec<String> = self
+                .matchers
+                .iter()
+                .map(|m| format!("{}{}\"{}\"", m.label, if m.negated { "!=" } else { "=" }, m.value.replace('"', "\\\"")))
+                .collect();
+            write!(f, "{{{}}}", matchers.join(","))?;
+        }
+        f.write_str(")")
+    }
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl Parser {
+    fn query(&mut self) -> Result<MetricQuery, QueryParseError> {
+        self.skip_whitespace();
+        let start = self.pos;
+        let name = self.identifier("an aggregation such as avg or p95")?;
+        let aggregation = parse_aggregation(&name).ok_or_else(|| QueryParseError {
+            message: format!("unknown aggregation '{}' (expected avg, min, max, sum, count, last, rate or pNN)", name),
+            position: start,
+        })?;
+
+        self.expect('(')?;
+        self.skip_whitespace();
+        let metric_name = self.identifier("a metric name")?;
+        self.skip_whitespace();
+
+        let mut matchers = Vec::new();
+        if self.peek() == Some('{') {
+            self.pos += 1;
+            loop {
+                self.skip_whitespace();
+                if self.peek() == Some('}') {
+                    self.pos += 1;
+                    break;
+                }
+                matchers.push(self.matcher()?);
+                self.skip_whitespace();
+                match self.peek() {
+                    Some(',') => self.pos += 1,
+                    Some('}') => {
+                        self.pos += 1;
+                        break;
+                    }
+                    _ => return Err(self.error("expected ',' or '}' after a label matcher")),
+                }
+            }
+        }
+
+        self.expect(')')?;
+        Ok(MetricQuery { aggregation, metric_name, matchers })
+    }
+
+    fn matcher(&mut self) -> Result<LabelMatcher, QueryParseError> {
+        let label = self.identifier("a label name")?;
+        self.skip_whitespace();
+        let negated = match (self.peek(), self.chars.get(self.pos + 1)) {
+            (Some('!'), Some('=')) => {
+                self.pos += 2;
+                true
+            }
+            (Some('='), _) => {
+                self.pos += 1;
+                false
+            }
+            _ => return Err(self.error(format!("expected '=' or '!=' after label '{}'", label))),
+        };
+        self.skip_whitespace();
+        let value = self.string()?;
+        Ok(LabelMatcher { label, value, negated })
+    }
+
+    fn identifier(&mut self, expected: &str) -> Result<String, QueryParseError> {
+        let start = self.pos;
+        while let Some(c) = self.peek() {
+            let allowed = c.is_ascii_alphanumeric() || c == '_' || (self.pos > start && matches!(c, '.' | ':' | '-'));
+            if !allowed {
+                break;
+            }
+            self.pos += 1;
+        }
+        if self.pos == start {
+            return Err(self.error(format!("expected {}", expected)));
+        }
+        Ok(self.chars[start..self.pos].iter().collect())
+    }
+
+    fn string(&mut self) -> Result<String, QueryParseError> {
+        if self.peek() != Some('"') {
+            return Err(self.error("expected a double-quoted label value"));
+        }
+        let start = self.pos;
+        self.pos += 1;
+        let mut value = String::new();
+        while let Some(c) = self.peek() {
+            self.pos += 1;
+            match c {
+                '"' => return Ok(value),
+                '\\' => match self.peek() {
+                    Some(escaped) => {
+                        value.push(escaped);
+                        self.pos += 1;
+                    }
+                    None => break,
+                },
+                c => value.push(c),
+            }
+        }
+        Err(QueryParseError { message: "unterminated label value".to_string(), position: start })
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), QueryParseError> {
+        self.skip_whitespace();
+        if self.peek() == Some(expected) {
+            self.pos += 1;
+            Ok(())
+        } else {
+            Err(self.error(format!("expected '{}'", expected)))
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.pos += 1;
+        }
+    }
+
+    fn error(&self, message: impl Into<String>) -> QueryParseError {
+        QueryParseError { message: message.into(), position: self.pos }
+    }
+}
+
+fn parse_aggregation(name: &str) -> Option<QueryAggregation> {
+    Some(match name.to_ascii_lowercase().as_str() {
+        "avg" | "mean" => QueryAggregation::Avg,
+        "min" => QueryAggregation::Min,
+        "max" => QueryAggregation::Max,
+        "sum" => QueryAggregation::Sum,
+        "count" => QueryAggregation::Count,
+        "last" => QueryAggregation::Last,
+        "rate" => QueryAggregation::Rate,
+        other => {
+            // p95, p99.9 or p99_9
+            let digits = other.strip_prefix('p')?.replace('_', ".");
+            let percentile: f64 = digits.parse().ok()?;
+            if !(percentile > 0.0 && percentile < 100.0) {
+                return None;
+            }
+            QueryAggregation::Percentile(percentile)
+        }
+    })
+}
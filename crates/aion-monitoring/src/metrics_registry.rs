@@ -0,0 +1,120 @@
+//! Prometheus Metrics Registry
+//!
+//! Holds the Prometheus series the platform exports: per-model inference
+//! latency histograms and token counters fed by the AI engine. The registry
+//! is rendered in the Prometheus text exposition format by
+//! [`PrometheusExporter`](crate::prometheus_exporter::PrometheusExporter).
+
+use std::fmt;
+use std::time::Duration;
+
+use anyhow::Result;
+use prometheus::{Encoder, HistogramOpts, HistogramVec, IntCounterVec, Opts, Registry, TextEncoder};
+use serde::{Deserialize, Serialize};
+
+/// Inference latency buckets in seconds, from 10ms to 60s
+pub const DEFAULT_INFERENCE_LATENCY_BUCKETS: &[f64] = &[
+    0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0,
+];
+
+/// Configuration of the exported series
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MetricsRegistryConfig {
+    /// Upper bounds, in seconds, of the inference latency histogram buckets;
+    /// must be strictly increasing
+    pub inference_latency_buckets: Vec<f64>,
+}
+
+impl Default for MetricsRegistryConfig {
+    fn default() -> Self {
+        Self {
+            inference_latency_buckets: DEFAULT_INFERENCE_LATENCY_BUCKETS.to_vec(),
+        }
+    }
+}
+
+/// Prometheus registry with the platform's series
+pub struct MetricsRegistry {
+    registry: Registry,
+    inference_duration: HistogramVec,
+    inference_tokens: IntCounterVec,
+}
+
+impl MetricsRegistry {
+    /// Create a registry with the default buckets
+    pub fn new() -> Self {
+        Self::with_config(MetricsRegistryConfig::default())
+            .expect("default metrics registry configuration is valid")
+    }
+
+    /// Create a registry; fails if the buckets are not strictly increasing
+    pub fn with_config(config: MetricsRegistryConfig) -> Result<Self> {
+        let registry = Registry::new();
+
+        let inference_duration = HistogramVec::new(
+            HistogramOpts::new(
+                "aion_inference_duration_seconds",
+                "Latency of successful model inferences",
+            )
+            .buckets(config.inference_latency_buckets),
+            &["model"],
+        )?;
+        let inference_tokens = IntCounterVec::new(
+            Opts::new("aion_inference_tokens_total", "Tokens processed by model inferences"),
+            &["model"],
+        )?;
+
+        registry.register(Box::new(inference_duration.clone()))?;
+        registry.register(Box::new(inference_tokens.clone()))?;
+
+        Ok(Self {
+            registry,
+            inference_duration,
+            inference_tokens,
+        })
+    }
+
+    /// Record a completed inference of `model_id`
+    pub fn record_inference(&self, model_id: &str, duration: Duration, tokens: u64) {
+        self.inference_duration
+            .with_label_values(&[model_id])
+            .observe(duration.as_secs_f64());
+        self.inference_tokens.with_label_values(&[model_id]).inc_by(tokens);
+    }
+
+    /// Total seconds and count of recorded inferences of `model_id`
+    pub fn inference_latency(&self, model_id: &str) -> (f64, u64) {
+        let histogram = self.inference_duration.with_label_values(&[model_id]);
+        (histogram.get_sample_sum(), histogram.get_sample_count())
+    }
+
+    /// Total tokens recorded for `model_id`
+    pub fn inference_tokens(&self, model_id: &str) -> u64 {
+        self.inference_tokens.with_label_values(&[model_id]).get()
+    }
+
+    /// The underlying registry, for registering further collectors
+    pub fn registry(&self) -> &Registry {
+        &self.registry
+    }
+
+    /// Render every series in the Prometheus text exposition format
+    pub fn render(&self) -> Result<String> {
+        let mut buffer = Vec::new();
+        TextEncoder::new().encode(&self.registry.gather(), &mut buffer)?;
+        Ok(String::from_utf8(buffer)?)
+    }
+}
+
+impl Default for MetricsRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for MetricsRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MetricsRegistry").finish_non_exhaustive()
+    }
+}
@@ -1,14 +1,24 @@
 use anyhow::Result;
+use std::sync::Arc;
 
-pub struct PrometheusExporter {}
+use crate::metrics_registry::MetricsRegistry;
+
+pub struct PrometheusExporter {
+    registry: Arc<MetricsRegistry>,
+}
 
 impl PrometheusExporter {
-    pub fn new() -> Self {
-        Self {}
+    pub fn new(registry: Arc<MetricsRegistry>) -> Self {
+        Self { registry }
     }
 
     pub async fn start(&self) -> Result<()> {
         tracing::info!("Prometheus exporter started");
         Ok(())
     }
-}
\ No newline at end of file
+
+    /// The current scrape payload
+    pub fn render(&self) -> Result<String> {
+        self.registry.render()
+    }
+}
@@ -12,6 +12,10 @@ use serde::{Deserialize, Serialize};
 use tokio::sync::{RwLock, mpsc, broadcast};
 use tokio::time::interval;
 use std::process::Command;
+use crate::metric_query::{MetricQuery, QueryValue};
+
+/// Alert events kept for `get_alert_history`
+const MAX_ALERT_HISTORY: usize = 1000;
 
 /// Real-time monitoring system with actual implementation
 pub struct RealTimeMonitor {
@@ -255,6 +259,30 @@ impl RealTimeMonitor {
         Ok(())
     }
 
+    /// Publish an alert raised outside metric evaluation, such as a circuit
+    /// breaker tripping in the gateway
+    pub async fn raise_alert(&self, event: AlertEvent) {
+        {
+            let mut history = self.alert_manager.alert_history.write().await;
+            history.push_back(event.clone());
+            while history.len() > MAX_ALERT_HISTORY {
+                history.pop_front();
+            }
+        }
+
+        let _ = self.event_bus.alert_sender.send(event);
+    }
+
+    /// Subscribe to alert events as they are raised
+    pub fn subscribe_alerts(&self) -> broadcast::Receiver<AlertEvent> {
+        self.event_bus.alert_sender.subscribe()
+    }
+
+    /// Alerts raised through `raise_alert`, oldest first
+    pub async fn get_alert_history(&self) -> Vec<AlertEvent> {
+        self.alert_manager.alert_history.read().await.iter().cloned().collect()
+    }
+
     /// Create a dashboard stream
     pub async fn create_dashboard_stream(&self, dashboard_id: String, metrics: Vec<String>) -> broadcast::Receiver<DashboardUpdate> {
         let (tx, rx) = broadcast::channel(1000);
@@ -297,6 +325,20 @@ impl RealTimeMonitor {
         Ok(result)
     }
 
+    /// Evaluate a metric query over the points recorded in the last `window`
+    pub async fn evaluate_query(&self, query: &MetricQuery, window: Duration) -> Result<QueryValue> {
+        let store = self.metrics_store.read().await;
+        let cutoff_time = Utc::now() - chrono::Duration::from_std(window)?;
+
+        let points = store.time_series
+            .get(&query.metric_name)
+            .into_iter()
+            .flat_map(|series| series.data_points.iter())
+            .filter(|point| point.timestamp >= cutoff_time);
+
+        Ok(query.evaluate(points, window))
+    }
+
     /// Get aggregated metrics
     pub async fn get_aggregations(&self, metric_names: &[String]) -> Result<HashMap<String, AggregatedMetrics>> {
         let store = self.metrics_store.read().await;
@@ -1,5 +1,6 @@
 //! Integration tests for the real-time monitoring system
 
+use crate::metric_query::MetricQuery;
 use crate::real_time_monitor::{RealTimeMonitor, MetricUpdate};
 use std::sync::Arc;
 
@@ -65,6 +66,36 @@ pub async fn test_system_metrics_collection() -> anyhow::Result<()> {
     Ok(())
 }
 
+/// Test evaluating a labelled percentile query over the metrics store
+pub async fn test_metric_query_evaluation() -> anyhow::Result<()> {
+    let monitor = Arc::new(RealTimeMonitor::new());
+
+    for (endpoint, latencies) in [("/api/v1/generate", 1..=100), ("/api/v1/health", 500..=510)] {
+        for latency in latencies {
+            let mut labels = std::collections::HashMap::new();
+            labels.insert("endpoint".to_string(), endpoint.to_string());
+            monitor.record_metric(MetricUpdate {
+                metric_name: "aion.api.latency_ms".to_string(),
+                value: latency as f64,
+                labels,
+                timestamp: chrono::Utc::now(),
+                source: "integration_test".to_string(),
+            }).await?;
+        }
+    }
+
+    let query = MetricQuery::parse(r#"p95(aion.api.latency_ms{endpoint="/api/v1/generate"})"#)?;
+    let result = monitor.evaluate_query(&query, std::time::Duration::from_secs(300)).await?;
+    assert_eq!(result.sample_count, 100, "Only points of the filtered endpoint should be used");
+    assert_eq!(result.value, Some(95.0), "p95 of 1..=100 should be 95");
+
+    assert!(MetricQuery::parse("p95(aion.api.latency_ms{endpoint=/api})").is_err(), "Unquoted label values should be rejected");
+    assert!(MetricQuery::parse("median(aion.api.latency_ms)").is_err(), "Unknown aggregations should be rejected");
+
+    println!("✅ Metric query evaluation test passed");
+    Ok(())
+}
+
 /// Run all integration tests
 pub async fn run_integration_tests() -> anyhow::Result<()> {
     println!("🧪 Running real-time monitoring integration tests...");
@@ -75,6 +106,9 @@ pub async fn run_integration_tests() -> anyhow::Result<()> {
     // Run system metrics test
     test_system_metrics_collection().await?;
 
+    // Run metric query test
+    test_metric_query_evaluation().await?;
+
     println!("✅ All integration tests passed!");
     Ok(())
 }
\ No newline at end of file
@@ -14,10 +14,20 @@ use axum::{
 };
 use futures::{sink::SinkExt, stream::StreamExt};
 use serde::{Deserialize, Serialize};
-use tokio::sync::{RwLock, broadcast};
+use tokio::sync::{RwLock, broadcast, mpsc};
+use tokio::task::AbortHandle;
 use uuid::Uuid;
+use crate::metric_query::MetricQuery;
 use crate::real_time_monitor::{RealTimeMonitor, DashboardUpdate, MetricUpdate, AlertEvent};
 
+/// Evaluation interval of a query subscription when the client sets none
+const DEFAULT_QUERY_INTERVAL_SECONDS: u64 = 5;
+/// Window of a query subscription when the client sets none
+const DEFAULT_QUERY_WINDOW_SECONDS: u64 = 300;
+/// Longest evaluation interval and window; the store keeps one hour of points
+const MAX_QUERY_SECONDS: u64 = 3600;
+const MAX_QUERY_SUBSCRIPTIONS_PER_CLIENT: usize = 50;
+
 /// WebSocket service for real-time monitoring
 pub struct WebSocketService {
     monitor: Arc<RealTimeMonitor>,
@@ -30,9 +40,17 @@ struct ClientConnection {
     id: String,
     client_type: ClientType,
     subscriptions: Vec<String>,
+    query_subscriptions: HashMap<String, QuerySubscription>,
     last_ping: chrono::DateTime<chrono::Utc>,
 }
 
+/// A metric query evaluated periodically for one client
+#[derive(Debug, Clone)]
+struct QuerySubscription {
+    query: MetricQuery,
+    task: AbortHandle,
+}
+
 /// Type of client connection
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum ClientType {
@@ -51,6 +69,14 @@ pub enum WSMessage {
     Unsubscribe { metrics: Vec<String> },
     GetMetrics { metrics: Vec<String>, time_range_seconds: Option<u64> },
     CreateAlert { alert: AlertConfig },
+    /// Evaluate `query` every `interval_seconds` over the last `window_seconds`
+    SubscribeQuery {
+        subscription_id: Option<String>,
+        query: String,
+        interval_seconds: Option<u64>,
+        window_seconds: Option<u64>,
+    },
+    UnsubscribeQuery { subscription_id: String },
     Ping,
 
     // Server -> Client
@@ -61,6 +87,24 @@ pub enum WSMessage {
     Pong,
     Error { message: String },
     Connected { client_id: String },
+    QuerySubscribed {
+        subscription_id: String,
+        query: String,
+        interval_seconds: u64,
+        window_seconds: u64,
+    },
+    /// Sent when a subscription's value changes
+    QueryResult {
+        subscription_id: String,
+        value: Option<f64>,
+        sample_count: usize,
+        evaluated_at: chrono::DateTime<chrono::Utc>,
+    },
+    QueryRejected {
+        subscription_id: Option<String>,
+        query: String,
+        error: String,
+    },
 }
 
 /// Alert configuration from client
@@ -113,6 +157,7 @@ impl WebSocketService {
                 id: client_id.clone(),
                 client_type: client_type.clone(),
                 subscriptions: Vec::new(),
+                query_subscriptions: HashMap::new(),
                 last_ping: chrono::Utc::now(),
             });
         }
@@ -148,6 +193,9 @@ impl WebSocketService {
             rx
         };
 
+        // Replies and query results for this client, written by the sender task
+        let (outgoing_tx, mut outgoing_rx) = mpsc::channel::<WSMessage>(256);
+
         // Handle incoming messages
         let connections_for_receiver = Arc::clone(&self.connections);
         let monitor_for_receiver = Arc::clone(&self.monitor);
@@ -157,13 +205,21 @@ impl WebSocketService {
             while let Some(msg) = receiver.next().await {
                 match msg {
                     Ok(Message::Text(text)) => {
-                        if let Ok(ws_msg) = serde_json::from_str::<WSMessage>(&text) {
-                            Self::handle_client_message(
-                                &connections_for_receiver,
-                                &monitor_for_receiver,
-                                &client_id_for_receiver,
-                                ws_msg,
-                            ).await;
+                        match serde_json::from_str::<WSMessage>(&text) {
+                            Ok(ws_msg) => {
+                                Self::handle_client_message(
+                                    &connections_for_receiver,
+                                    &monitor_for_receiver,
+                                    &outgoing_tx,
+                                    &client_id_for_receiver,
+                                    ws_msg,
+                                ).await;
+                            }
+                            Err(e) => {
+                                let _ = outgoing_tx.send(WSMessage::Error {
+                                    message: format!("Invalid message: {}", e),
+                                }).await;
+                            }
                         }
                     }
                     Ok(Message::Close(_)) => {
@@ -178,9 +234,13 @@ impl WebSocketService {
                 }
             }
 
-            // Remove connection
+            // Remove connection and stop its query subscriptions
             let mut connections = connections_for_receiver.write().await;
-            connections.remove(&client_id_for_receiver);
+            if let Some(connection) = connections.remove(&client_id_for_receiver) {
+                for subscription in connection.query_subscriptions.values() {
+                    subscription.task.abort();
+                }
+            }
         });
 
         // Handle outgoing messages
@@ -218,6 +278,16 @@ impl WebSocketService {
                         }
                     }
 
+                    // Replies and query results; closed once the client is gone
+                    outgoing = outgoing_rx.recv() => {
+                        let Some(msg) = outgoing else { break };
+                        if let Ok(msg_str) = serde_json::to_string(&msg) {
+                            if sender.send(Message::Text(msg_str)).await.is_err() {
+                                break;
+                            }
+                        }
+                    }
+
                     // Periodic ping
                     _ = tokio::time::sleep(tokio::time::Duration::from_secs(30)) => {
                         let ping_msg = WSMessage::Ping;
@@ -240,6 +310,7 @@ impl WebSocketService {
     async fn handle_client_message(
         connections: &Arc<RwLock<HashMap<String, ClientConnection>>>,
         monitor: &Arc<RealTimeMonitor>,
+        outgoing: &mpsc::Sender<WSMessage>,
         client_id: &str,
         message: WSMessage,
     ) {
@@ -274,6 +345,43 @@ impl WebSocketService {
                 // Implementation would parse the alert and register it
             }
 
+            WSMessage::SubscribeQuery { subscription_id, query, interval_seconds, window_seconds } => {
+                let subscribed = Self::subscribe_query(
+                    connections,
+                    monitor,
+                    outgoing,
+                    client_id,
+                    subscription_id.clone(),
+                    &query,
+                    interval_seconds.unwrap_or(DEFAULT_QUERY_INTERVAL_SECONDS),
+                    window_seconds.unwrap_or(DEFAULT_QUERY_WINDOW_SECONDS),
+                ).await;
+                if let Err(error) = subscribed {
+                    tracing::debug!("Rejected query from client {}: {}", client_id, error);
+                    let _ = outgoing.send(WSMessage::QueryRejected { subscription_id, query, error }).await;
+                }
+            }
+
+            WSMessage::UnsubscribeQuery { subscription_id } => {
+                let removed = {
+                    let mut connections_guard = connections.write().await;
+                    connections_guard
+                        .get_mut(client_id)
+                        .and_then(|connection| connection.query_subscriptions.remove(&subscription_id))
+                };
+                match removed {
+                    Some(subscription) => {
+                        subscription.task.abort();
+                        tracing::debug!("Client {} unsubscribed from query {}", client_id, subscription.query);
+                    }
+                    None => {
+                        let _ = outgoing.send(WSMessage::Error {
+                            message: format!("No query subscription '{}'", subscription_id),
+                        }).await;
+                    }
+                }
+            }
+
             WSMessage::Ping => {
                 let mut connections_guard = connections.write().await;
                 if let Some(connection) = connections_guard.get_mut(client_id) {
@@ -287,6 +395,107 @@ impl WebSocketService {
         }
     }
 
+    /// Validate a query subscription and start evaluating it
+    #[allow(clippy::too_many_arguments)]
+    async fn subscribe_query(
+        connections: &Arc<RwLock<HashMap<String, ClientConnection>>>,
+        monitor: &Arc<RealTimeMonitor>,
+        outgoing: &mpsc::Sender<WSMessage>,
+        client_id: &str,
+        subscription_id: Option<String>,
+        query: &str,
+        interval_seconds: u64,
+        window_seconds: u64,
+    ) -> std::result::Result<(), String> {
+        let query = MetricQuery::parse(query).map_err(|e| format!("Invalid query: {}", e))?;
+        if !(1..=MAX_QUERY_SECONDS).contains(&interval_seconds) {
+            return Err(format!("interval_seconds must be between 1 and {}", MAX_QUERY_SECONDS));
+        }
+        if !(1..=MAX_QUERY_SECONDS).contains(&window_seconds) {
+            return Err(format!("window_seconds must be between 1 and {}", MAX_QUERY_SECONDS));
+        }
+
+        let mut connections_guard = connections.write().await;
+        let connection = connections_guard
+            .get_mut(client_id)
+            .ok_or_else(|| "Connection is closed".to_string())?;
+        let subscription_id = subscription_id.unwrap_or_else(|| Uuid::new_v4().to_string());
+        if connection.query_subscriptions.contains_key(&subscription_id) {
+            return Err(format!("Subscription '{}' already exists", subscription_id));
+        }
+        if connection.query_subscriptions.len() >= MAX_QUERY_SUBSCRIPTIONS_PER_CLIENT {
+            return Err(format!(
+                "At most {} query subscriptions are allowed per connection",
+                MAX_QUERY_SUBSCRIPTIONS_PER_CLIENT
+            ));
+        }
+
+        let task = tokio::spawn(Self::run_query_subscription(
+            Arc::clone(monitor),
+            outgoing.clone(),
+            subscription_id.clone(),
+            query.clone(),
+            std::time::Duration::from_secs(interval_seconds),
+            std::time::Duration::from_secs(window_seconds),
+        ));
+        tracing::debug!("Client {} subscribed to query {} as {}", client_id, query, subscription_id);
+        connection.query_subscriptions.insert(subscription_id, QuerySubscription {
+            query,
+            task: task.abort_handle(),
+        });
+        Ok(())
+    }
+
+    /// Confirm a query subscription, then push its value whenever it changes
+    async fn run_query_subscription(
+        monitor: Arc<RealTimeMonitor>,
+        outgoing: mpsc::Sender<WSMessage>,
+        subscription_id: String,
+        query: MetricQuery,
+        interval: std::time::Duration,
+        window: std::time::Duration,
+    ) {
+        // Sent from here so that it always precedes the first result
+        let subscribed = WSMessage::QuerySubscribed {
+            subscription_id: subscription_id.clone(),
+            query: query.to_string(),
+            interval_seconds: interval.as_secs(),
+            window_seconds: window.as_secs(),
+        };
+        if outgoing.send(subscribed).await.is_err() {
+            return;
+        }
+
+        let mut ticker = tokio::time::interval(interval);
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        let mut last_sent = None;
+
+        loop {
+            ticker.tick().await;
+            let result = match monitor.evaluate_query(&query, window).await {
+                Ok(result) => result,
+                Err(e) => {
+                    tracing::warn!("Failed to evaluate query {}: {}", query, e);
+                    continue;
+                }
+            };
+            if last_sent == Some(result) {
+                continue;
+            }
+            last_sent = Some(result);
+
+            let update = WSMessage::QueryResult {
+                subscription_id: subscription_id.clone(),
+                value: result.value,
+                sample_count: result.sample_count,
+                evaluated_at: chrono::Utc::now(),
+            };
+            if outgoing.send(update).await.is_err() {
+                break;
+            }
+        }
+    }
+
     /// Get connected clients count
     pub async fn get_connected_clients(&self) -> usize {
         let connections = self.connections.read().await;
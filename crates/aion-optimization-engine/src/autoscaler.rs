@@ -0,0 +1,451 @@
+//! Autoscaling Recommender
+//!
+//! Horizontal scaling recommendations for the `Infrastructure` category.
+//! Scale-ups are driven by the larger of current demand and the predictive
+//! analyzer's forecast over the scale-up lead time, so new replicas are ready
+//! when a predicted spike arrives. Scale-downs require utilization to stay low
+//! for a sustained window. Separate cooldowns after any scaling action keep the
+//! recommender from flapping.
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use chrono::{DateTime, Utc, Duration};
+use uuid::Uuid;
+use tracing::debug;
+
+use crate::{
+    MetricEvidence, MetricForecast, OptimizationCategory, OptimizationParameter, OptimizationPriority,
+    OptimizationRecommendation, ParameterType, PredictiveAnalyzer, TimeSeriesPoint,
+};
+
+/// Metric the recommender scales on: average CPU utilization per replica, in percent
+const CPU_METRIC: &str = "cpu_usage";
+
+/// Metadata key carrying the replica count a sample was measured at
+const REPLICAS_METADATA_KEY: &str = "replicas";
+
+/// Scaling bounds, targets and timing
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScalingPolicy {
+    pub min_replicas: u32,
+    pub max_replicas: u32,
+    /// Per-replica CPU utilization to size for
+    pub target_cpu_percentage: f64,
+    /// Scale down only while per-replica utilization stays below this
+    pub scale_down_cpu_percentage: f64,
+    /// How long utilization must stay low before scaling down
+    pub scale_down_window: Duration,
+    /// Largest number of replicas removed in one step
+    pub max_scale_down_step: u32,
+    /// Time for a new replica to become ready
+    pub scale_up_lead_time: Duration,
+    /// How often recommendations are evaluated
+    pub evaluation_interval: Duration,
+    /// Minimum time after any scaling action before scaling up again
+    pub scale_up_cooldown: Duration,
+    /// Minimum time after any scaling action before scaling down
+    pub scale_down_cooldown: Duration,
+}
+
+impl Default for ScalingPolicy {
+    fn default() -> Self {
+        Self {
+            min_replicas: 2,
+            max_replicas: 20,
+            target_cpu_percentage: 60.0,
+            scale_down_cpu_percentage: 35.0,
+            scale_down_window: Duration::minutes(30),
+            max_scale_down_step: 2,
+            scale_up_lead_time: Duration::minutes(5),
+            evaluation_interval: Duration::minutes(5),
+            scale_up_cooldown: Duration::minutes(3),
+            scale_down_cooldown: Duration::minutes(15),
+        }
+    }
+}
+
+/// Current state of the scaled workload
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorkloadState {
+    pub name: String,
+    pub current_replicas: u32,
+    /// Average per-replica CPU utilization right now, in percent
+    pub current_cpu_percentage: f64,
+    pub last_scaled_at: Option<DateTime<Utc>>,
+}
+
+/// Recommends replica counts from load history and forecasts
+#[derive(Debug, Clone)]
+pub struct AutoscalingRecommender {
+    policy: ScalingPolicy,
+}
+
+impl AutoscalingRecommender {
+    pub fn new(policy: ScalingPolicy) -> Self {
+        Self { policy }
+    }
+
+    pub fn policy(&self) -> &ScalingPolicy {
+        &self.policy
+    }
+
+    /// Recommend a scaling action for `workload`, if one is warranted now
+    pub async fn recommend(
+        &self,
+        analyzer: &PredictiveAnalyzer,
+        workload: &WorkloadState,
+    ) -> Result<Option<OptimizationRecommendation>> {
+        let now = Utc::now();
+        let history = analyzer.metric_history(CPU_METRIC, now - self.policy.scale_down_window).await;
+
+        let mut forecasts = Vec::new();
+        for horizon in self.forecast_horizons() {
+            if let Some(forecast) = analyzer.forecast_metric(CPU_METRIC, horizon).await? {
+                forecasts.push(forecast);
+            }
+        }
+
+        Ok(self.evaluate(workload, &history, &forecasts, now))
+    }
+
+    /// Forecast horizons covering the lead time plus one evaluation interval:
+    /// a spike inside that window must be acted on now to be absorbed in time
+    pub fn forecast_horizons(&self) -> Vec<u32> {
+        let step = self.policy.evaluation_interval.num_minutes().max(1) as u32;
+        let reach = (self.policy.scale_up_lead_time + self.policy.evaluation_interval).num_minutes().max(1) as u32;
+        (1..=reach.div_ceil(step)).map(|i| (i * step).min(reach)).collect()
+    }
+
+    /// Decide on a scaling action from history (per-replica CPU samples over the
+    /// scale-down window) and forecasts made at the current replica count
+    pub fn evaluate(
+        &self,
+        workload: &WorkloadState,
+        history: &[TimeSeriesPoint],
+        forecasts: &[MetricForecast],
+        now: DateTime<Utc>,
+    ) -> Option<OptimizationRecommendation> {
+        let policy = &self.policy;
+        let replicas = workload.current_replicas.max(1);
+        let since_last_scale = workload.last_scaled_at.map(|at| now - at);
+
+        // Demand in units of "percent of one replica" is independent of the replica count
+        let current_demand = workload.current_cpu_percentage * replicas as f64;
+        let peak_forecast = forecasts
+            .iter()
+            .max_by(|a, b| a.predicted_value.total_cmp(&b.predicted_value));
+        let forecast_demand = peak_forecast.map(|f| f.predicted_value * replicas as f64);
+
+        let needed_now = self.replicas_for(current_demand);
+        let needed_forecast = forecast_demand.map(|d| self.replicas_for(d)).unwrap_or(0);
+        let scale_up_target = needed_now.max(needed_forecast).clamp(policy.min_replicas, policy.max_replicas);
+
+        if workload.current_replicas < policy.min_replicas || workload.current_replicas > policy.max_replicas {
+            let target = workload.current_replicas.clamp(policy.min_replicas, policy.max_replicas);
+            let rationale = format!(
+                "{} runs {} replicas, outside the allowed range of {} to {}.",
+                workload.name, workload.current_replicas, policy.min_replicas, policy.max_replicas
+            );
+            return Some(self.recommendation(workload, target, rationale, peak_forecast, OptimizationPriority::High, 0.95));
+        }
+
+        if scale_up_target > workload.current_replicas {
+            if since_last_scale.is_some_and(|elapsed| elapsed < policy.scale_up_cooldown) {
+                debug!("Scale-up of {} suppressed by cooldown", workload.name);
+                return None;
+            }
+
+            let (rationale, priority, confidence) = match peak_forecast {
+                Some(forecast) if needed_forecast > needed_now => (
+                    format!(
+                        "CPU is forecast to reach {:.1}% per replica within {} minutes (currently {:.1}%). Replicas take {} minutes to become ready, so scaling now absorbs the spike.",
+                        forecast.predicted_value,
+                        forecast.horizon_minutes,
+                        workload.current_cpu_percentage,
+                        policy.scale_up_lead_time.num_minutes()
+                    ),
+                    OptimizationPriority::High,
+                    forecast_confidence(forecast),
+                ),
+                _ => (
+                    format!(
+                        "CPU is at {:.1}% per replica, above the {:.0}% target.",
+                        workload.current_cpu_percentage, policy.target_cpu_percentage
+                    ),
+                    if workload.current_cpu_percentage >= 90.0 {
+                        OptimizationPriority::Critical
+                    } else {
+                        OptimizationPriority::High
+                    },
+                    0.9,
+                ),
+            };
+            return Some(self.recommendation(workload, scale_up_target, rationale, peak_forecast, priority, confidence));
+        }
+
+        // Scale down only after utilization stayed low for the whole window
+        let window_start = now - policy.scale_down_window;
+        let window_covered = history.first().is_some_and(|p| p.timestamp <= window_start + policy.evaluation_interval);
+        let sustained_low = window_covered
+            && workload.current_cpu_percentage < policy.scale_down_cpu_percentage
+            && history.iter().all(|p| p.value < policy.scale_down_cpu_percentage);
+        if !sustained_low {
+            return None;
+        }
+        if since_last_scale.is_some_and(|elapsed| elapsed < policy.scale_down_cooldown) {
+            debug!("Scale-down of {} suppressed by cooldown", workload.name);
+            return None;
+        }
+
+        // Size for the busiest moment of the window or forecast, not just the current value
+        let recent_peak_demand = history
+            .iter()
+            .map(|p| p.value * sample_replicas(p, replicas) as f64)
+            .fold(current_demand, f64::max);
+        let needed = self
+            .replicas_for(recent_peak_demand.max(forecast_demand.unwrap_or(0.0)))
+            .clamp(policy.min_replicas, policy.max_replicas);
+        let target = needed.max(workload.current_replicas.saturating_sub(policy.max_scale_down_step));
+        if target >= workload.current_replicas {
+            return None;
+        }
+
+        let rationale = format!(
+            "CPU stayed below {:.0}% per replica for {} minutes (peak {:.1}% of one replica in total); {} replicas keep utilization under the {:.0}% target.",
+            policy.scale_down_cpu_percentage,
+            policy.scale_down_window.num_minutes(),
+            recent_peak_demand,
+            target,
+            policy.target_cpu_percentage
+        );
+        let confidence = peak_forecast.map(forecast_confidence).unwrap_or(0.8);
+        Some(self.recommendation(workload, target, rationale, peak_forecast, OptimizationPriority::Low, confidence))
+    }
+
+    fn replicas_for(&self, demand: f64) -> u32 {
+        (demand / self.policy.target_cpu_percentage.max(1.0)).ceil().max(0.0) as u32
+    }
+
+    fn recommendation(
+        &self,
+        workload: &WorkloadState,
+        target_replicas: u32,
+        rationale: String,
+        forecast: Option<&MetricForecast>,
+        priority: OptimizationPriority,
+        confidence: f64,
+    ) -> OptimizationRecommendation {
+        let current = workload.current_replicas.max(1) as f64;
+        let scaling_up = target_replicas > workload.current_replicas;
+        let projected_cpu = |per_replica: f64| per_replica * current / target_replicas.max(1) as f64;
+
+        let mut metric_evidence = vec![MetricEvidence {
+            metric: CPU_METRIC.to_string(),
+            current_value: workload.current_cpu_percentage,
+            predicted_value: forecast.map(|f| f.predicted_value),
+            predicted_lower: forecast.map(|f| f.lower_bound),
+            predicted_upper: forecast.map(|f| f.upper_bound),
+            horizon_minutes: forecast.map(|f| f.horizon_minutes),
+            unit: "percent".to_string(),
+        }];
+        metric_evidence.push(MetricEvidence {
+            metric: "cpu_usage_after_scaling".to_string(),
+            current_value: projected_cpu(workload.current_cpu_percentage),
+            predicted_value: forecast.map(|f| projected_cpu(f.predicted_value)),
+            predicted_lower: forecast.map(|f| projected_cpu(f.lower_bound)),
+            predicted_upper: forecast.map(|f| projected_cpu(f.upper_bound)),
+            horizon_minutes: forecast.map(|f| f.horizon_minutes),
+            unit: "percent".to_string(),
+        });
+
+        let mut parameters = HashMap::new();
+        parameters.insert("replicas".to_string(), OptimizationParameter {
+            name: "replicas".to_string(),
+            current_value: workload.current_replicas.to_string(),
+            recommended_value: target_replicas.to_string(),
+            parameter_type: ParameterType::Integer,
+        });
+
+        // Up: relief in per-replica utilization; down: share of capacity released
+        let expected_impact = if scaling_up {
+            1.0 - current / target_replicas.max(1) as f64
+        } else {
+            (current - target_replicas as f64) / current
+        };
+
+        OptimizationRecommendation {
+            id: Uuid::new_v4(),
+            category: OptimizationCategory::Infrastructure,
+            description: format!(
+                "Scale {} {} from {} to {} replicas. {}",
+                workload.name,
+                if scaling_up { "up" } else { "down" },
+                workload.current_replicas,
+                target_replicas,
+                rationale
+            ),
+            priority,
+            expected_impact: expected_impact.clamp(0.0, 1.0),
+            confidence: confidence.clamp(0.0, 1.0),
+            parameters,
+            metric_evidence,
+            attributions: Vec::new(),
+            counterfactual: None,
+            created_at: Utc::now(),
+            estimated_implementation_time: self.policy.scale_up_lead_time.num_seconds().max(0) as u64,
+        }
+    }
+}
+
+impl Default for AutoscalingRecommender {
+    fn default() -> Self {
+        Self::new(ScalingPolicy::default())
+    }
+}
+
+/// Replica count a sample was measured at, falling back to the current count
+fn sample_replicas(point: &TimeSeriesPoint, current: u32) -> u32 {
+    point
+        .metadata
+        .get(REPLICAS_METADATA_KEY)
+        .and_then(|r| r.parse().ok())
+        .unwrap_or(current)
+}
+
+/// Narrow prediction intervals relative to the forecast mean inspire more confidence
+fn forecast_confidence(forecast: &MetricForecast) -> f64 {
+    let width = forecast.upper_bound - forecast.lower_bound;
+    let relative = width / (2.0 * forecast.predicted_value.max(1.0));
+    (1.0 - relative).clamp(0.1, 0.95)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ForecastMethod;
+
+    fn workload(replicas: u32, cpu: f64, last_scaled_minutes_ago: Option<i64>, now: DateTime<Utc>) -> WorkloadState {
+        WorkloadState {
+            name: "api".to_string(),
+            current_replicas: replicas,
+            current_cpu_percentage: cpu,
+            last_scaled_at: last_scaled_minutes_ago.map(|m| now - Duration::minutes(m)),
+        }
+    }
+
+    fn history(values: &[f64], now: DateTime<Utc>) -> Vec<TimeSeriesPoint> {
+        let step = 30 / values.len() as i64;
+        values
+            .iter()
+            .enumerate()
+            .map(|(i, value)| TimeSeriesPoint {
+                timestamp: now - Duration::minutes(30 - i as i64 * step),
+                value: *value,
+                metadata: HashMap::new(),
+            })
+            .collect()
+    }
+
+    fn forecast(horizon_minutes: u32, value: f64) -> MetricForecast {
+        MetricForecast {
+            metric_name: CPU_METRIC.to_string(),
+            horizon_minutes,
+            predicted_value: value,
+            lower_bound: value - 5.0,
+            upper_bound: value + 5.0,
+            method: ForecastMethod::DailySeasonal,
+            samples_used: 60,
+        }
+    }
+
+    fn recommended_replicas(recommendation: &OptimizationRecommendation) -> u32 {
+        recommendation.parameters["replicas"].recommended_value.parse().unwrap()
+    }
+
+    #[test]
+    fn test_scales_up_ahead_of_predicted_spike() {
+        let now = Utc::now();
+        let recommender = AutoscalingRecommender::default();
+        let recommendation = recommender
+            .evaluate(&workload(4, 40.0, None, now), &history(&[40.0; 10], now), &[forecast(5, 45.0), forecast(10, 90.0)], now)
+            .expect("spike should trigger a scale-up");
+
+        // 90% across 4 replicas is 360% of one replica; at a 60% target that needs 6
+        assert_eq!(recommended_replicas(&recommendation), 6);
+        assert_eq!(recommendation.priority, OptimizationPriority::High);
+        let evidence = &recommendation.metric_evidence[0];
+        assert_eq!(evidence.current_value, 40.0);
+        assert_eq!(evidence.predicted_value, Some(90.0));
+        assert_eq!(evidence.horizon_minutes, Some(10));
+    }
+
+    #[test]
+    fn test_forecast_horizons_cover_lead_time() {
+        let recommender = AutoscalingRecommender::new(ScalingPolicy {
+            scale_up_lead_time: Duration::minutes(12),
+            evaluation_interval: Duration::minutes(5),
+            ..Default::default()
+        });
+        assert_eq!(recommender.forecast_horizons(), vec![5, 10, 15, 17]);
+    }
+
+    #[test]
+    fn test_scales_down_only_after_sustained_low_usage() {
+        let now = Utc::now();
+        let recommender = AutoscalingRecommender::default();
+
+        let recommendation = recommender
+            .evaluate(&workload(8, 15.0, None, now), &history(&[15.0; 10], now), &[forecast(10, 16.0)], now)
+            .expect("sustained low usage should scale down");
+        assert_eq!(recommended_replicas(&recommendation), 6, "limited by max_scale_down_step");
+        assert_eq!(recommendation.priority, OptimizationPriority::Low);
+
+        let mut brief_dip = history(&[50.0; 10], now);
+        brief_dip.last_mut().unwrap().value = 15.0;
+        assert!(recommender.evaluate(&workload(8, 15.0, None, now), &brief_dip, &[], now).is_none());
+
+        let short_history = &history(&[15.0; 10], now)[5..];
+        assert!(recommender.evaluate(&workload(8, 15.0, None, now), short_history, &[], now).is_none());
+    }
+
+    #[test]
+    fn test_cooldown_prevents_flapping() {
+        let now = Utc::now();
+        let recommender = AutoscalingRecommender::default();
+
+        assert!(recommender
+            .evaluate(&workload(8, 15.0, Some(5), now), &history(&[15.0; 10], now), &[], now)
+            .is_none());
+        assert!(recommender
+            .evaluate(&workload(4, 80.0, Some(1), now), &history(&[80.0; 10], now), &[], now)
+            .is_none());
+        assert!(recommender
+            .evaluate(&workload(4, 80.0, Some(4), now), &history(&[80.0; 10], now), &[], now)
+            .is_some());
+    }
+
+    #[test]
+    fn test_respects_replica_bounds() {
+        let now = Utc::now();
+        let recommender = AutoscalingRecommender::new(ScalingPolicy {
+            min_replicas: 2,
+            max_replicas: 5,
+            ..Default::default()
+        });
+
+        let recommendation = recommender
+            .evaluate(&workload(4, 95.0, None, now), &history(&[95.0; 10], now), &[forecast(10, 200.0)], now)
+            .unwrap();
+        assert_eq!(recommended_replicas(&recommendation), 5);
+
+        let recommendation = recommender
+            .evaluate(&workload(3, 1.0, None, now), &history(&[1.0; 10], now), &[], now)
+            .unwrap();
+        assert_eq!(recommended_replicas(&recommendation), 2);
+
+        assert!(recommender
+            .evaluate(&workload(2, 1.0, None, now), &history(&[1.0; 10], now), &[], now)
+            .is_none());
+    }
+}
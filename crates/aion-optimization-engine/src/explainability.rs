@@ -0,0 +1,178 @@
+//! Recommendation Explainability
+//!
+//! Model-agnostic explanations for ML-backed recommendations. Attributions are
+//! exact interventional Shapley values: every coalition of input signals is
+//! evaluated with the remaining signals set to a baseline (the training mean),
+//! so contributions add up to the difference between the prediction for the
+//! current state and the prediction for a typical state. Counterfactuals vary a
+//! single signal, holding the others fixed, until the model no longer supports
+//! the recommendation.
+
+use anyhow::{bail, Result};
+use statrs::distribution::{ContinuousCDF, Normal};
+
+/// Exact Shapley values need 2^n predictions; beyond this, use fewer signals
+pub const MAX_EXACT_FEATURES: usize = 12;
+
+/// Shapley contribution of each feature of `instance` to `predict`, relative to `baseline`
+///
+/// `predict` receives a batch of feature rows and returns one prediction per row.
+pub fn shapley_values<F>(predict: F, instance: &[f64], baseline: &[f64]) -> Result<Vec<f64>>
+where
+    F: Fn(&[Vec<f64>]) -> Result<Vec<f64>>,
+{
+    let n = instance.len();
+    if baseline.len() != n {
+        bail!("Baseline has {} features, instance has {}", baseline.len(), n);
+    }
+    if n > MAX_EXACT_FEATURES {
+        bail!("Exact attribution supports at most {} features, got {}", MAX_EXACT_FEATURES, n);
+    }
+    if n == 0 {
+        return Ok(Vec::new());
+    }
+
+    // Row `mask` takes feature i from the instance when bit i is set, else from the baseline
+    let coalitions: Vec<Vec<f64>> = (0..1usize << n)
+        .map(|mask| {
+            (0..n)
+                .map(|i| if mask & (1 << i) != 0 { instance[i] } else { baseline[i] })
+                .collect()
+        })
+        .collect();
+    let values = predict(&coalitions)?;
+    if values.len() != coalitions.len() {
+        bail!("Model returned {} predictions for {} rows", values.len(), coalitions.len());
+    }
+
+    // Weight of a coalition of size s that excludes the feature: s! (n - s - 1)! / n!
+    let weights: Vec<f64> = (0..n)
+        .map(|s| factorial(s) * factorial(n - s - 1) / factorial(n))
+        .collect();
+
+    let mut contributions = vec![0.0; n];
+    for (mask, value) in values.iter().enumerate() {
+        let size = mask.count_ones() as usize;
+        for (i, contribution) in contributions.iter_mut().enumerate() {
+            if mask & (1 << i) == 0 {
+                *contribution += weights[size] * (values[mask | (1 << i)] - value);
+            }
+        }
+    }
+
+    Ok(contributions)
+}
+
+/// Value of `feature` nearest to its current value, within `range`, for which
+/// the prediction is accepted by `accept`
+///
+/// The range is scanned in `steps` increments on each side of the current value.
+pub fn find_counterfactual<F, A>(
+    predict: F,
+    instance: &[f64],
+    feature: usize,
+    range: (f64, f64),
+    steps: usize,
+    accept: A,
+) -> Result<Option<f64>>
+where
+    F: Fn(&[Vec<f64>]) -> Result<Vec<f64>>,
+    A: Fn(f64) -> bool,
+{
+    let Some(&current) = instance.get(feature) else {
+        bail!("Feature index {} out of range for {} features", feature, instance.len());
+    };
+    let (low, high) = range;
+    let steps = steps.max(1);
+
+    let mut candidates = Vec::with_capacity(steps * 2);
+    for step in 1..=steps {
+        if low < current {
+            candidates.push(current - (current - low) * step as f64 / steps as f64);
+        }
+        if high > current {
+            candidates.push(current + (high - current) * step as f64 / steps as f64);
+        }
+    }
+    if candidates.is_empty() {
+        return Ok(None);
+    }
+
+    let rows: Vec<Vec<f64>> = candidates
+        .iter()
+        .map(|&candidate| {
+            let mut row = instance.to_vec();
+            row[feature] = candidate;
+            row
+        })
+        .collect();
+    let predictions = predict(&rows)?;
+
+    Ok(candidates
+        .into_iter()
+        .zip(predictions)
+        .filter(|(_, prediction)| accept(*prediction))
+        .map(|(candidate, _)| candidate)
+        .min_by(|a, b| (a - current).abs().total_cmp(&(b - current).abs())))
+}
+
+/// Probability that the true value is below `threshold`, assuming normally
+/// distributed prediction error with the model's held-out RMSE
+pub fn probability_below(predicted: f64, threshold: f64, rmse: f64) -> f64 {
+    Normal::new(predicted, rmse.max(f64::EPSILON))
+        .map(|error| error.cdf(threshold))
+        .unwrap_or(0.5)
+}
+
+fn factorial(n: usize) -> f64 {
+    (1..=n).map(|k| k as f64).product()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn linear(rows: &[Vec<f64>]) -> Result<Vec<f64>> {
+        Ok(rows.iter().map(|r| 2.0 * r[0] - 3.0 * r[1] + 0.5 * r[2]).collect())
+    }
+
+    #[test]
+    fn test_shapley_values_of_linear_model() {
+        let contributions = shapley_values(linear, &[4.0, 1.0, 10.0], &[1.0, 1.0, 2.0]).unwrap();
+        let expected = [6.0, 0.0, 4.0];
+        for (actual, expected) in contributions.iter().zip(expected) {
+            assert!((actual - expected).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn test_shapley_values_split_interactions_and_sum_to_prediction_delta() {
+        let product = |rows: &[Vec<f64>]| -> Result<Vec<f64>> { Ok(rows.iter().map(|r| r[0] * r[1] + r[2]).collect()) };
+        let contributions = shapley_values(product, &[2.0, 3.0, 1.0], &[0.0, 0.0, 0.0]).unwrap();
+
+        assert!((contributions[0] - 3.0).abs() < 1e-9);
+        assert!((contributions[1] - 3.0).abs() < 1e-9);
+        assert!((contributions.iter().sum::<f64>() - 7.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_shapley_values_reject_mismatched_baseline() {
+        assert!(shapley_values(linear, &[1.0, 2.0, 3.0], &[0.0]).is_err());
+    }
+
+    #[test]
+    fn test_counterfactual_finds_nearest_accepted_value() {
+        let found = find_counterfactual(linear, &[10.0, 0.0, 0.0], 0, (0.0, 20.0), 20, |p| p <= 8.0).unwrap();
+        assert_eq!(found, Some(4.0));
+
+        let unreachable = find_counterfactual(linear, &[10.0, 0.0, 0.0], 0, (5.0, 20.0), 20, |p| p <= 8.0).unwrap();
+        assert_eq!(unreachable, None);
+    }
+
+    #[test]
+    fn test_probability_below_tracks_uncertainty() {
+        assert!(probability_below(0.5, 0.8, 0.05) > 0.99);
+        assert!((probability_below(0.8, 0.8, 0.1) - 0.5).abs() < 1e-9);
+        assert!(probability_below(0.75, 0.8, 0.2) < probability_below(0.75, 0.8, 0.02));
+    }
+}
@@ -10,6 +10,8 @@ pub mod telemetry;
 pub mod recommendation_engine;
 pub mod models;
 pub mod metrics_collector;
+pub mod autoscaler;
+pub mod explainability;
 
 pub use ml_optimizer::*;
 pub use predictive_analyzer::*;
@@ -18,6 +20,8 @@ pub use telemetry::*;
 pub use recommendation_engine::*;
 pub use models::*;
 pub use metrics_collector::*;
+pub use autoscaler::*;
+pub use explainability::*;
 
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
@@ -82,6 +86,7 @@ pub struct OptimizationEngine {
     telemetry: TelemetryCollector,
     recommendation_engine: RecommendationEngine,
     metrics_collector: MetricsCollector,
+    autoscaling_recommender: AutoscalingRecommender,
 }
 
 impl OptimizationEngine {
@@ -102,6 +107,7 @@ impl OptimizationEngine {
             telemetry,
             recommendation_engine,
             metrics_collector,
+            autoscaling_recommender: AutoscalingRecommender::default(),
         })
     }
 
@@ -158,6 +164,16 @@ impl OptimizationEngine {
         self.predictive_analyzer.predict_performance(horizon_minutes).await
     }
 
+    /// Predict performance under hypothetical parameter changes without applying them
+    pub async fn simulate_changes(&self, changes: Vec<OptimizationParameter>, horizon_minutes: u32) -> Result<PerformancePrediction> {
+        self.predictive_analyzer.simulate(changes, horizon_minutes).await
+    }
+
+    /// Get a horizontal scaling recommendation for a workload, if one is warranted
+    pub async fn get_scaling_recommendation(&self, workload: &WorkloadState) -> Result<Option<OptimizationRecommendation>> {
+        self.autoscaling_recommender.recommend(&self.predictive_analyzer, workload).await
+    }
+
     /// Apply optimization recommendations
     pub async fn apply_optimizations(&mut self, recommendations: &[OptimizationRecommendation]) -> Result<OptimizationResult> {
         self.auto_tuner.apply_optimizations(recommendations).await
@@ -197,10 +213,61 @@ pub struct OptimizationRecommendation {
     pub expected_impact: f64,
     pub confidence: f64,
     pub parameters: HashMap<String, OptimizationParameter>,
+    /// Observed and predicted metrics backing the recommendation
+    #[serde(default)]
+    pub metric_evidence: Vec<MetricEvidence>,
+    /// Input signals that drove the recommendation, strongest first
+    #[serde(default)]
+    pub attributions: Vec<FeatureAttribution>,
+    /// Single-signal change under which the recommendation would not be made
+    #[serde(default)]
+    pub counterfactual: Option<Counterfactual>,
     pub created_at: DateTime<Utc>,
     pub estimated_implementation_time: u64, // seconds
 }
 
+/// Metric value supporting a recommendation
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MetricEvidence {
+    pub metric: String,
+    pub current_value: f64,
+    pub predicted_value: Option<f64>,
+    /// 95% prediction interval around `predicted_value`
+    pub predicted_lower: Option<f64>,
+    pub predicted_upper: Option<f64>,
+    pub horizon_minutes: Option<u32>,
+    pub unit: String,
+}
+
+/// Influence of one input signal on a recommendation
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FeatureAttribution {
+    pub feature: String,
+    pub value: f64,
+    /// Reference value the contribution is measured against
+    pub baseline: f64,
+    /// Shapley contribution towards the recommendation; negative values argue against it
+    pub contribution: f64,
+    pub direction: AttributionDirection,
+}
+
+/// Whether a signal argues for or against a recommendation
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AttributionDirection {
+    Supports,
+    Opposes,
+}
+
+/// Counterfactual explanation for a recommendation
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Counterfactual {
+    pub feature: String,
+    pub current_value: f64,
+    /// Value at which the recommendation would no longer be made
+    pub threshold_value: f64,
+    pub description: String,
+}
+
 /// Optimization categories
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum OptimizationCategory {
@@ -261,6 +328,42 @@ pub struct PerformancePrediction {
     pub confidence_interval: ConfidenceInterval,
     pub prediction_time: DateTime<Utc>,
     pub horizon_minutes: u32,
+    /// Present when the prediction assumes hypothetical parameter changes
+    #[serde(default)]
+    pub simulation: Option<SimulationReport>,
+}
+
+/// Effect of hypothetical parameter changes relative to the no-change baseline
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SimulationReport {
+    pub changes: Vec<SimulatedChange>,
+    pub metrics: Vec<SimulatedMetric>,
+    /// At least one change lies outside the values the model was fitted on
+    pub extrapolated: bool,
+}
+
+/// A simulated parameter change and the data backing it
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SimulatedChange {
+    pub parameter: String,
+    pub current_value: f64,
+    pub simulated_value: f64,
+    /// Range of the parameter in historical samples, if it was ever recorded
+    pub observed_range: Option<(f64, f64)>,
+    pub samples: usize,
+    /// The simulated value is outside `observed_range`, or the parameter was never observed
+    pub extrapolated: bool,
+}
+
+/// Simulated prediction for one metric
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SimulatedMetric {
+    pub metric: String,
+    pub baseline: f64,
+    pub predicted: f64,
+    /// `predicted - baseline`
+    pub delta: f64,
+    pub confidence_interval: ConfidenceInterval,
 }
 
 /// Resource usage prediction
@@ -273,7 +376,7 @@ pub struct ResourceUsagePrediction {
 }
 
 /// Confidence interval for predictions
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ConfidenceInterval {
     pub lower_bound: f64,
     pub upper_bound: f64,
@@ -19,7 +19,26 @@ use smartcore::ensemble::random_forest_regressor::RandomForestRegressor;
 use smartcore::linalg::basic::matrix::DenseMatrix;
 use smartcore::api::{Predictor, SupervisedEstimator};
 
-use crate::{OptimizationConfig, OptimizationRecommendation, OptimizationCategory, OptimizationPriority};
+use crate::{
+    AttributionDirection, Counterfactual, FeatureAttribution, OptimizationConfig, OptimizationRecommendation,
+    OptimizationCategory, OptimizationPriority, find_counterfactual, probability_below, shapley_values,
+};
+
+/// Input signals of the performance predictor, in feature-vector order
+pub const PERFORMANCE_FEATURES: &[&str] = &[
+    "cpu_usage",
+    "cpu_trend",
+    "memory_usage",
+    "disk_io",
+    "network_io",
+    "active_connections",
+    "request_rate",
+    "response_time",
+    "error_rate",
+];
+
+/// Grid resolution when searching for counterfactual values
+const COUNTERFACTUAL_STEPS: usize = 40;
 
 /// ML-based optimizer for system performance
 #[derive(Debug)]
@@ -35,6 +54,7 @@ pub struct MLOptimizer {
 #[derive(Debug)]
 pub struct OptimizationModels {
     pub performance_predictor: Option<RandomForestRegressor<f64>>,
+    pub performance_profile: Option<FeatureProfile>,
     pub resource_optimizer: Option<LinearRegression<f64>>,
     pub latency_predictor: Option<RandomForestRegressor<f64>>,
     pub throughput_optimizer: Option<RandomForestRegressor<f64>>,
@@ -45,6 +65,7 @@ impl Default for OptimizationModels {
     fn default() -> Self {
         Self {
             performance_predictor: None,
+            performance_profile: None,
             resource_optimizer: None,
             latency_predictor: None,
             throughput_optimizer: None,
@@ -53,6 +74,18 @@ impl Default for OptimizationModels {
     }
 }
 
+/// Training-set statistics of a model's input features
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FeatureProfile {
+    pub feature_names: Vec<String>,
+    /// Per-feature mean, the reference point for attributions
+    pub baseline: Vec<f64>,
+    pub min: Vec<f64>,
+    pub max: Vec<f64>,
+    /// Root-mean-square error on held-out samples
+    pub validation_rmse: f64,
+}
+
 /// Training dataset for ML models
 #[derive(Debug, Default)]
 pub struct TrainingDataset {
@@ -220,12 +253,19 @@ impl MLOptimizer {
 
     /// Generate optimization recommendations using ML models
     pub async fn generate_recommendations(&self) -> Result<Vec<OptimizationRecommendation>> {
+        // Snapshot the latest samples before taking the models lock; training takes them in the opposite order
+        let latest: Vec<PerformanceDataPoint> = {
+            let training_data = self.training_data.read().await;
+            let points = &training_data.performance_data;
+            points[points.len().saturating_sub(2)..].to_vec()
+        };
+
         let models = self.models.read().await;
         let mut recommendations = Vec::new();
 
         // Performance optimization recommendations
-        if let Some(ref predictor) = models.performance_predictor {
-            recommendations.extend(self.generate_performance_recommendations(predictor).await?);
+        if let (Some(predictor), Some(profile)) = (&models.performance_predictor, &models.performance_profile) {
+            recommendations.extend(self.generate_performance_recommendations(predictor, profile, &latest).await?);
         }
 
         // Resource optimization recommendations
@@ -274,7 +314,9 @@ impl MLOptimizer {
         let mut models = self.models.write().await;
 
         // Train performance predictor
-        models.performance_predictor = Some(self.train_performance_predictor(&training_data).await?);
+        let (performance_predictor, performance_profile) = self.train_performance_predictor(&training_data).await?;
+        models.performance_predictor = Some(performance_predictor);
+        models.performance_profile = Some(performance_profile);
 
         // Train resource optimizer
         models.resource_optimizer = Some(self.train_resource_optimizer(&training_data).await?);
@@ -412,17 +454,50 @@ impl MLOptimizer {
         Ok(())
     }
 
-    async fn train_performance_predictor(&self, _data: &TrainingDataset) -> Result<RandomForestRegressor<f64>> {
-        // Mock implementation - would use actual training data
-        let x = DenseMatrix::from_2d_array(&[
-            &[1.0, 2.0, 3.0],
-            &[4.0, 5.0, 6.0],
-            &[7.0, 8.0, 9.0],
-        ]);
-        let y = vec![1.0, 2.0, 3.0];
+    async fn train_performance_predictor(&self, data: &TrainingDataset) -> Result<(RandomForestRegressor<f64>, FeatureProfile)> {
+        let points = &data.performance_data;
+        if points.len() < 5 {
+            anyhow::bail!("Performance predictor needs at least 5 samples, got {}", points.len());
+        }
 
-        let model = RandomForestRegressor::fit(&x, &y, Default::default())?;
-        Ok(model)
+        let rows: Vec<Vec<f64>> = points
+            .iter()
+            .enumerate()
+            .map(|(i, point)| performance_features(point, i.checked_sub(1).map(|prev| &points[prev])))
+            .collect();
+        let targets: Vec<f64> = points.iter().map(|p| p.performance_score).collect();
+
+        // Hold out every fifth sample to measure predictive error, then fit on everything
+        let (train, holdout): (Vec<usize>, Vec<usize>) = (0..rows.len()).partition(|i| i % 5 != 4);
+        let select = |indices: &[usize]| -> (Vec<Vec<f64>>, Vec<f64>) {
+            (indices.iter().map(|&i| rows[i].clone()).collect(), indices.iter().map(|&i| targets[i]).collect())
+        };
+        let (train_x, train_y) = select(&train);
+        let (holdout_x, holdout_y) = select(&holdout);
+        let validation_model = RandomForestRegressor::fit(&DenseMatrix::from_2d_vec(&train_x), &train_y, Default::default())?;
+        let holdout_predictions = predict_rows(&validation_model, &holdout_x)?;
+        let validation_rmse = (holdout_predictions
+            .iter()
+            .zip(&holdout_y)
+            .map(|(predicted, actual)| (predicted - actual).powi(2))
+            .sum::<f64>()
+            / holdout_y.len() as f64)
+            .sqrt();
+
+        let model = RandomForestRegressor::fit(&DenseMatrix::from_2d_vec(&rows), &targets, Default::default())?;
+
+        let column = |i: usize| rows.iter().map(move |row| row[i]);
+        let features = 0..PERFORMANCE_FEATURES.len();
+        let profile = FeatureProfile {
+            feature_names: PERFORMANCE_FEATURES.iter().map(|f| f.to_string()).collect(),
+            baseline: features.clone().map(|i| column(i).sum::<f64>() / rows.len() as f64).collect(),
+            min: features.clone().map(|i| column(i).fold(f64::INFINITY, f64::min)).collect(),
+            max: features.map(|i| column(i).fold(f64::NEG_INFINITY, f64::max)).collect(),
+            validation_rmse,
+        };
+
+        info!("Performance predictor trained on {} samples (held-out RMSE {:.4})", rows.len(), validation_rmse);
+        Ok((model, profile))
     }
 
     async fn train_resource_optimizer(&self, _data: &TrainingDataset) -> Result<LinearRegression<f64>> {
@@ -463,17 +538,99 @@ impl MLOptimizer {
         Ok(dataset)
     }
 
-    async fn generate_performance_recommendations(&self, _predictor: &RandomForestRegressor<f64>) -> Result<Vec<OptimizationRecommendation>> {
-        // Generate performance-focused recommendations
+    async fn generate_performance_recommendations(
+        &self,
+        predictor: &RandomForestRegressor<f64>,
+        profile: &FeatureProfile,
+        latest: &[PerformanceDataPoint],
+    ) -> Result<Vec<OptimizationRecommendation>> {
+        let Some(current) = latest.last() else {
+            return Ok(vec![]);
+        };
+        let previous = latest.len().checked_sub(2).map(|i| &latest[i]);
+        let instance = performance_features(current, previous);
+        let predict = |rows: &[Vec<f64>]| predict_rows(predictor, rows);
+
+        // Recommend only when the predicted score falls below the optimization trigger
+        let threshold = self.config.performance_threshold;
+        let predicted_score = predict(&[instance.clone()])?[0];
+        if predicted_score >= threshold {
+            return Ok(vec![]);
+        }
+
+        // Signals lowering the predicted score support the recommendation
+        let contributions = shapley_values(predict, &instance, &profile.baseline)?;
+        let mut attributions: Vec<(usize, FeatureAttribution)> = contributions
+            .iter()
+            .enumerate()
+            .map(|(i, contribution)| {
+                (i, FeatureAttribution {
+                    feature: profile.feature_names[i].clone(),
+                    value: instance[i],
+                    baseline: profile.baseline[i],
+                    contribution: -contribution,
+                    direction: if *contribution <= 0.0 {
+                        AttributionDirection::Supports
+                    } else {
+                        AttributionDirection::Opposes
+                    },
+                })
+            })
+            .collect();
+        attributions.sort_by(|(_, a), (_, b)| b.contribution.abs().total_cmp(&a.contribution.abs()));
+
+        let mut counterfactual = None;
+        for (i, attribution) in attributions.iter().filter(|(_, a)| a.direction == AttributionDirection::Supports) {
+            let range = (profile.min[*i], profile.max[*i]);
+            if let Some(value) = find_counterfactual(predict, &instance, *i, range, COUNTERFACTUAL_STEPS, |score| score >= threshold)? {
+                let label = attribution.feature.replace('_', " ");
+                let bound = if value < instance[*i] { "below" } else { "above" };
+                counterfactual = Some(Counterfactual {
+                    feature: attribution.feature.clone(),
+                    current_value: instance[*i],
+                    threshold_value: value,
+                    description: format!(
+                        "This would not be recommended if {} were {} {:.2} (currently {:.2})",
+                        label, bound, value, instance[*i]
+                    ),
+                });
+                break;
+            }
+        }
+
+        let drivers: Vec<String> = attributions
+            .iter()
+            .filter(|(_, a)| a.direction == AttributionDirection::Supports)
+            .take(3)
+            .map(|(_, a)| format!("{} ({:.2})", a.feature.replace('_', " "), a.value))
+            .collect();
+        let description = if drivers.is_empty() {
+            format!(
+                "Increase connection pool size: predicted performance score {:.2} is below the {:.2} threshold",
+                predicted_score, threshold
+            )
+        } else {
+            format!(
+                "Increase connection pool size: predicted performance score {:.2} is below the {:.2} threshold, driven mostly by {}",
+                predicted_score,
+                threshold,
+                drivers.join(", ")
+            )
+        };
+
         Ok(vec![
             OptimizationRecommendation {
                 id: Uuid::new_v4(),
                 category: OptimizationCategory::Application,
-                description: "Increase connection pool size based on predicted load".to_string(),
+                description,
                 priority: OptimizationPriority::High,
-                expected_impact: 0.15,
-                confidence: 0.89,
+                expected_impact: (threshold - predicted_score).clamp(0.0, 1.0),
+                // Probability the score really is below the threshold, given held-out model error
+                confidence: probability_below(predicted_score, threshold, profile.validation_rmse),
                 parameters: HashMap::new(),
+                metric_evidence: Vec::new(),
+                attributions: attributions.into_iter().map(|(_, a)| a).collect(),
+                counterfactual,
                 created_at: Utc::now(),
                 estimated_implementation_time: 300,
             }
@@ -561,4 +718,23 @@ pub struct LatencyOptimization {
     pub database_optimizations: Vec<String>,
     pub cache_optimizations: Vec<String>,
     pub network_optimizations: Vec<String>,
-}
\ No newline at end of file
+}
+
+/// Feature vector of the performance predictor for a sample, see `PERFORMANCE_FEATURES`
+fn performance_features(point: &PerformanceDataPoint, previous: Option<&PerformanceDataPoint>) -> Vec<f64> {
+    vec![
+        point.cpu_usage,
+        previous.map(|p| point.cpu_usage - p.cpu_usage).unwrap_or(0.0),
+        point.memory_usage,
+        point.disk_io,
+        point.network_io,
+        point.active_connections,
+        point.request_rate,
+        point.response_time,
+        point.error_rate,
+    ]
+}
+
+fn predict_rows(predictor: &RandomForestRegressor<f64>, rows: &[Vec<f64>]) -> Result<Vec<f64>> {
+    Ok(predictor.predict(&DenseMatrix::from_2d_vec(&rows.to_vec()))?)
+}
@@ -17,7 +17,24 @@ use ndarray::{Array1, Array2};
 use statrs::statistics::{Statistics, OrderStatistics};
 use statrs::distribution::{Normal, ContinuousCDF};
 
-use crate::{OptimizationConfig, PerformancePrediction, ResourceUsagePrediction, ConfidenceInterval};
+use crate::{
+    OptimizationConfig, OptimizationParameter, ParameterType, PerformancePrediction, ResourceUsagePrediction,
+    ConfidenceInterval, SimulatedChange, SimulatedMetric, SimulationReport,
+};
+
+/// Metrics covered by a simulation, in `PerformancePrediction` field order
+const SIMULATED_METRICS: [&str; 7] = [
+    "response_time",
+    "throughput",
+    "error_rate",
+    "cpu_usage",
+    "memory_usage",
+    "disk_io",
+    "network_io",
+];
+
+/// Tagged samples needed before a parameter's effect on a metric is estimated
+const MIN_SENSITIVITY_SAMPLES: usize = 5;
 
 /// Predictive analyzer for performance forecasting
 #[derive(Debug)]
@@ -46,6 +63,23 @@ pub struct TimeSeriesData {
     pub max_history_size: usize,
 }
 
+impl TimeSeriesData {
+    /// Series for a metric name as accepted by `add_data_point`
+    pub fn series(&self, metric_name: &str) -> Option<&VecDeque<TimeSeriesPoint>> {
+        match metric_name {
+            "response_time" => Some(&self.response_times),
+            "throughput" => Some(&self.throughput),
+            "error_rate" => Some(&self.error_rates),
+            "cpu_usage" => Some(&self.cpu_usage),
+            "memory_usage" => Some(&self.memory_usage),
+            "disk_io" => Some(&self.disk_io),
+            "network_io" => Some(&self.network_io),
+            "active_connections" => Some(&self.active_connections),
+            _ => None,
+        }
+    }
+}
+
 /// Single point in time series
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TimeSeriesPoint {
@@ -297,6 +331,28 @@ pub enum ChangePointType {
     Seasonal,
 }
 
+/// Forecast of a single metric at a horizon
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MetricForecast {
+    pub metric_name: String,
+    pub horizon_minutes: u32,
+    pub predicted_value: f64,
+    /// 95% prediction interval
+    pub lower_bound: f64,
+    pub upper_bound: f64,
+    pub method: ForecastMethod,
+    pub samples_used: usize,
+}
+
+/// How a forecast was produced
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ForecastMethod {
+    /// Same time one day earlier, shifted by the change in level since then
+    DailySeasonal,
+    /// Least-squares trend over recent samples
+    LinearTrend,
+}
+
 /// Predictive analyzer status
 #[derive(Debug, Serialize, Deserialize)]
 pub struct PredictiveAnalyzerStatus {
@@ -428,9 +484,188 @@ impl PredictiveAnalyzer {
             confidence_interval,
             prediction_time: Utc::now(),
             horizon_minutes,
+            simulation: None,
+        })
+    }
+
+    /// Predict performance `horizon_minutes` ahead as if `changes` were applied,
+    /// without applying them.
+    ///
+    /// A metric's sensitivity to a parameter is fitted by least squares on the
+    /// samples that record the parameter's value in their metadata (e.g.
+    /// `{"connection_pool_size": "20"}`); effects of several changes are added.
+    /// Changes to values outside the range seen in those samples, or to
+    /// parameters never recorded, are flagged as extrapolated.
+    pub async fn simulate(&self, changes: Vec<OptimizationParameter>, horizon_minutes: u32) -> Result<PerformancePrediction> {
+        let mut simulated_changes = Vec::with_capacity(changes.len());
+        for change in &changes {
+            simulated_changes.push(SimulatedChange {
+                parameter: change.name.clone(),
+                current_value: parse_parameter_value(change, &change.current_value)?,
+                simulated_value: parse_parameter_value(change, &change.recommended_value)?,
+                observed_range: None,
+                samples: 0,
+                extrapolated: true,
+            });
+        }
+
+        let baseline = self.predict_performance(horizon_minutes).await?;
+        let baseline_values = [
+            baseline.predicted_response_time,
+            baseline.predicted_throughput,
+            baseline.predicted_error_rate,
+            baseline.predicted_resource_usage.cpu_percentage,
+            baseline.predicted_resource_usage.memory_percentage,
+            baseline.predicted_resource_usage.disk_io_rate,
+            baseline.predicted_resource_usage.network_io_rate,
+        ];
+
+        let time_series_data = self.time_series_data.read().await;
+        let empty = VecDeque::new();
+        let mut metrics = Vec::with_capacity(SIMULATED_METRICS.len());
+        for (metric, baseline_value) in SIMULATED_METRICS.iter().zip(baseline_values) {
+            let series = time_series_data.series(metric).unwrap_or(&empty);
+
+            let mut delta = 0.0;
+            let mut delta_variance = 0.0;
+            for change in &mut simulated_changes {
+                let Some(fit) = fit_parameter_sensitivity(series, &change.parameter) else {
+                    continue;
+                };
+                let shift = change.simulated_value - change.current_value;
+                delta += fit.slope * shift;
+                delta_variance += (fit.slope_std_error * shift).powi(2);
+                change.observed_range = Some(match change.observed_range {
+                    Some((min, max)) => (min.min(fit.min), max.max(fit.max)),
+                    None => (fit.min, fit.max),
+                });
+                change.samples = change.samples.max(fit.samples);
+            }
+
+            // Widen the baseline interval by the uncertainty of the estimated effect
+            let interval = self.calculate_confidence_interval(baseline_value, series, 0.95).await?;
+            let baseline_margin = (interval.upper_bound - interval.lower_bound) / 2.0;
+            let margin = (baseline_margin.powi(2) + 1.96f64.powi(2) * delta_variance).sqrt();
+            let predicted = (baseline_value + delta).max(0.0);
+
+            metrics.push(SimulatedMetric {
+                metric: metric.to_string(),
+                baseline: baseline_value,
+                predicted,
+                delta: predicted - baseline_value,
+                confidence_interval: ConfidenceInterval {
+                    lower_bound: (predicted - margin).max(0.0),
+                    upper_bound: predicted + margin,
+                    confidence_level: 0.95,
+                },
+            });
+        }
+        drop(time_series_data);
+
+        for change in &mut simulated_changes {
+            change.extrapolated = match change.observed_range {
+                Some((min, max)) => change.simulated_value < min || change.simulated_value > max,
+                None => true,
+            };
+            if change.extrapolated {
+                warn!(
+                    "Simulated {} = {} is outside the range the model was fitted on",
+                    change.parameter, change.simulated_value
+                );
+            }
+        }
+        let extrapolated = simulated_changes.iter().any(|c| c.extrapolated);
+
+        let predicted = |metric: usize| metrics[metric].predicted;
+        Ok(PerformancePrediction {
+            predicted_response_time: predicted(0),
+            predicted_throughput: predicted(1),
+            predicted_error_rate: predicted(2),
+            predicted_resource_usage: ResourceUsagePrediction {
+                cpu_percentage: predicted(3),
+                memory_percentage: predicted(4),
+                disk_io_rate: predicted(5),
+                network_io_rate: predicted(6),
+            },
+            confidence_interval: metrics[0].confidence_interval.clone(),
+            prediction_time: Utc::now(),
+            horizon_minutes,
+            simulation: Some(SimulationReport {
+                changes: simulated_changes,
+                metrics,
+                extrapolated,
+            }),
         })
     }
 
+    /// Forecast a metric `horizon_minutes` ahead.
+    ///
+    /// Uses the daily pattern when at least a day of history is available, so
+    /// recurring peaks are anticipated before the metric starts rising, and a
+    /// recent linear trend otherwise. Returns `None` with fewer than 10 samples.
+    pub async fn forecast_metric(&self, metric_name: &str, horizon_minutes: u32) -> Result<Option<MetricForecast>> {
+        let time_series_data = self.time_series_data.read().await;
+        let Some(series) = time_series_data.series(metric_name) else {
+            return Ok(None);
+        };
+        let points: Vec<&TimeSeriesPoint> = series.iter().collect();
+        if points.len() < 10 {
+            return Ok(None);
+        }
+
+        let now = points[points.len() - 1].timestamp;
+        let target = now + Duration::minutes(horizon_minutes as i64);
+        let day = Duration::days(1);
+        let recent: Vec<f64> = points.iter().rev().take(10).map(|p| p.value).collect();
+        let recent_level = recent.iter().sum::<f64>() / recent.len() as f64;
+
+        let forecast = if points[0].timestamp <= target - day {
+            // Level around the same time yesterday, to carry today's offset forward
+            let past_level = mean_near(&points, now - day, 5);
+            let seasonal_value = mean_near(&points, target - day, 1);
+            let predicted = seasonal_value + (recent_level - past_level);
+
+            // Spread of day-over-day changes measures how repeatable the pattern is
+            let changes: Vec<f64> = points
+                .iter()
+                .rev()
+                .take(60)
+                .map(|p| p.value - mean_near(&points, p.timestamp - day, 1))
+                .collect();
+            (predicted, std_dev(&changes), ForecastMethod::DailySeasonal, changes.len())
+        } else {
+            let window: Vec<&TimeSeriesPoint> = points.iter().rev().take(60).rev().copied().collect();
+            let origin = window[0].timestamp;
+            let xs: Vec<f64> = window.iter().map(|p| (p.timestamp - origin).num_seconds() as f64 / 60.0).collect();
+            let ys: Vec<f64> = window.iter().map(|p| p.value).collect();
+            let (slope, intercept) = least_squares(&xs, &ys);
+            let residuals: Vec<f64> = xs.iter().zip(&ys).map(|(x, y)| y - (intercept + slope * x)).collect();
+            let x_target = (target - origin).num_seconds() as f64 / 60.0;
+            (intercept + slope * x_target, std_dev(&residuals), ForecastMethod::LinearTrend, window.len())
+        };
+
+        let (predicted, spread, method, samples_used) = forecast;
+        let predicted = predicted.max(0.0);
+        Ok(Some(MetricForecast {
+            metric_name: metric_name.to_string(),
+            horizon_minutes,
+            predicted_value: predicted,
+            lower_bound: (predicted - 1.96 * spread).max(0.0),
+            upper_bound: predicted + 1.96 * spread,
+            method,
+            samples_used,
+        }))
+    }
+
+    /// Recorded samples of a metric since `since`, oldest first
+    pub async fn metric_history(&self, metric_name: &str, since: DateTime<Utc>) -> Vec<TimeSeriesPoint> {
+        let time_series_data = self.time_series_data.read().await;
+        time_series_data
+            .series(metric_name)
+            .map(|series| series.iter().filter(|p| p.timestamp >= since).cloned().collect())
+            .unwrap_or_default()
+    }
+
     /// Add new data point for analysis
     pub async fn add_data_point(&self, metric_name: &str, value: f64, metadata: Option<HashMap<String, String>>) -> Result<()> {
         let mut time_series_data = self.time_series_data.write().await;
@@ -910,4 +1145,147 @@ impl Clone for PredictiveAnalyzer {
             last_prediction: Arc::clone(&self.last_prediction),
         }
     }
-}
\ No newline at end of file
+}
+
+/// Mean of the `count` samples closest to `at`
+fn mean_near(points: &[&TimeSeriesPoint], at: DateTime<Utc>, count: usize) -> f64 {
+    let index = points.partition_point(|p| p.timestamp < at);
+    let start = index.saturating_sub(count / 2);
+    let end = (start + count).min(points.len());
+    let start = end.saturating_sub(count);
+    let window = &points[start..end];
+    window.iter().map(|p| p.value).sum::<f64>() / window.len().max(1) as f64
+}
+
+fn least_squares(xs: &[f64], ys: &[f64]) -> (f64, f64) {
+    let n = xs.len() as f64;
+    let mean_x = xs.iter().sum::<f64>() / n;
+    let mean_y = ys.iter().sum::<f64>() / n;
+    let covariance: f64 = xs.iter().zip(ys).map(|(x, y)| (x - mean_x) * (y - mean_y)).sum();
+    let variance: f64 = xs.iter().map(|x| (x - mean_x).powi(2)).sum();
+    let slope = if variance > 0.0 { covariance / variance } else { 0.0 };
+    (slope, mean_y - slope * mean_x)
+}
+
+fn std_dev(values: &[f64]) -> f64 {
+    if values.len() < 2 {
+        return 0.0;
+    }
+    let mean = values.iter().sum::<f64>() / values.len() as f64;
+    let variance = values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / (values.len() - 1) as f64;
+    variance.sqrt()
+}
+
+/// Linear effect of a parameter on a metric
+struct ParameterSensitivity {
+    slope: f64,
+    slope_std_error: f64,
+    min: f64,
+    max: f64,
+    samples: usize,
+}
+
+/// Fit a metric against the parameter values recorded in its samples' metadata
+fn fit_parameter_sensitivity(series: &VecDeque<TimeSeriesPoint>, parameter: &str) -> Option<ParameterSensitivity> {
+    let (xs, ys): (Vec<f64>, Vec<f64>) = series
+        .iter()
+        .filter_map(|p| Some((p.metadata.get(parameter)?.trim().parse::<f64>().ok()?, p.value)))
+        .unzip();
+    if xs.len() < MIN_SENSITIVITY_SAMPLES {
+        return None;
+    }
+
+    let (slope, intercept) = least_squares(&xs, &ys);
+    let mean_x = xs.iter().sum::<f64>() / xs.len() as f64;
+    let sxx: f64 = xs.iter().map(|x| (x - mean_x).powi(2)).sum();
+    let residual_variance = xs
+        .iter()
+        .zip(&ys)
+        .map(|(x, y)| (y - (intercept + slope * x)).powi(2))
+        .sum::<f64>()
+        / (xs.len() - 2) as f64;
+
+    Some(ParameterSensitivity {
+        slope,
+        slope_std_error: if sxx > 0.0 { (residual_variance / sxx).sqrt() } else { 0.0 },
+        min: xs.iter().copied().fold(f64::INFINITY, f64::min),
+        max: xs.iter().copied().fold(f64::NEG_INFINITY, f64::max),
+        samples: xs.len(),
+    })
+}
+
+fn parse_parameter_value(parameter: &OptimizationParameter, value: &str) -> Result<f64> {
+    match parameter.parameter_type {
+        ParameterType::String => anyhow::bail!("Parameter {} is not numeric and cannot be simulated", parameter.name),
+        ParameterType::Boolean => match value.trim() {
+            "true" => Ok(1.0),
+            "false" => Ok(0.0),
+            other => anyhow::bail!("Parameter {} has invalid boolean value {:?}", parameter.name, other),
+        },
+        _ => value
+            .trim()
+            .parse()
+            .map_err(|_| anyhow::anyhow!("Parameter {} has non-numeric value {:?}", parameter.name, value)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pool_size_change(from: u32, to: u32) -> OptimizationParameter {
+        OptimizationParameter {
+            name: "connection_pool_size".to_string(),
+            current_value: from.to_string(),
+            recommended_value: to.to_string(),
+            parameter_type: ParameterType::Integer,
+        }
+    }
+
+    async fn analyzer_with_pool_history() -> PredictiveAnalyzer {
+        let analyzer = PredictiveAnalyzer::new(&OptimizationConfig::default()).await.unwrap();
+        // CPU drops by one point per extra pooled connection, pool sizes 10..=30
+        for i in 0..40 {
+            let pool_size = 10 + (i % 5) * 5;
+            let metadata = HashMap::from([("connection_pool_size".to_string(), pool_size.to_string())]);
+            analyzer.add_data_point("cpu_usage", 80.0 - pool_size as f64, Some(metadata)).await.unwrap();
+        }
+        analyzer
+    }
+
+    fn cpu(prediction: &PerformancePrediction) -> &SimulatedMetric {
+        let simulation = prediction.simulation.as_ref().unwrap();
+        simulation.metrics.iter().find(|m| m.metric == "cpu_usage").unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_simulate_reports_delta_against_baseline() {
+        let analyzer = analyzer_with_pool_history().await;
+        let prediction = analyzer.simulate(vec![pool_size_change(20, 25)], 30).await.unwrap();
+
+        let cpu = cpu(&prediction);
+        assert!((cpu.baseline - 60.0).abs() < 1e-6);
+        assert!((cpu.delta + 5.0).abs() < 1e-6);
+        assert!((prediction.predicted_resource_usage.cpu_percentage - 55.0).abs() < 1e-6);
+        assert!(cpu.confidence_interval.lower_bound <= cpu.predicted && cpu.predicted <= cpu.confidence_interval.upper_bound);
+
+        let simulation = prediction.simulation.as_ref().unwrap();
+        assert!(!simulation.extrapolated);
+        assert_eq!(simulation.changes[0].observed_range, Some((10.0, 30.0)));
+    }
+
+    #[tokio::test]
+    async fn test_simulate_flags_extrapolation() {
+        let analyzer = analyzer_with_pool_history().await;
+
+        let beyond_range = analyzer.simulate(vec![pool_size_change(20, 60)], 30).await.unwrap();
+        assert!(beyond_range.simulation.as_ref().unwrap().extrapolated);
+
+        let mut unknown = pool_size_change(20, 25);
+        unknown.name = "worker_threads".to_string();
+        let unobserved = analyzer.simulate(vec![unknown], 30).await.unwrap();
+        let simulation = unobserved.simulation.as_ref().unwrap();
+        assert!(simulation.extrapolated);
+        assert_eq!(cpu(&unobserved).delta, 0.0);
+    }
+}
@@ -1773,6 +1773,9 @@ impl RecommendationEngine {
                 expected_impact: 0.12,
                 confidence: 0.88,
                 parameters: HashMap::new(),
+                metric_evidence: Vec::new(),
+                attributions: Vec::new(),
+                counterfactual: None,
                 created_at: Utc::now(),
                 estimated_implementation_time: 300,
             }
@@ -0,0 +1,49 @@
+//! # Plugin System Errors
+
+use uuid::Uuid;
+
+/// Plugin system error
+#[derive(Debug, thiserror::Error)]
+pub enum PluginError {
+    #[error("Plugin not found: {0}")]
+    PluginNotFound(Uuid),
+
+    #[error("Plugin {plugin_id} exceeded its {resource} quota ({used} of {limit}); disabled until {retry_after}")]
+    QuotaExceeded {
+        plugin_id: Uuid,
+        resource: String,
+        used: u64,
+        limit: u64,
+        retry_after: chrono::DateTime<chrono::Utc>,
+    },
+
+    #[error("Security violation: {0}")]
+    SecurityViolation(String),
+
+    #[error("Plugin ABI version {found} is not supported (supported: {supported})")]
+    UnsupportedAbi { found: u32, supported: String },
+
+    #[error("Configuration error: {0}")]
+    ConfigurationError(String),
+
+    #[error("Execution limit exceeded: {0}")]
+    LimitExceeded(String),
+
+    #[error("Runtime error: {0}")]
+    RuntimeError(String),
+
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("File watcher error: {0}")]
+    Watcher(#[from] notify::Error),
+
+    #[error("Serialization error: {0}")]
+    Serialization(#[from] serde_json::Error),
+
+    #[error(transparent)]
+    Other(#[from] anyhow::Error),
+}
+
+/// Result type for plugin system operations
+pub type Result<T, E = PluginError> = std::result::Result<T, E>;
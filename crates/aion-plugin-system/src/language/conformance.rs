@@ -0,0 +1,352 @@
+//! # Language Plugin Conformance
+//!
+//! Checks every language plugin must pass before it is published: a valid
+//! self-description, a hello-world program that compiles, formatting that is
+//! idempotent, and syntax errors that the analyzer reports.
+//!
+//! Compilation needs the language's toolchain, which the plugin cannot run
+//! from its sandbox. The plugin author supplies the compile command through
+//! [`ConformanceOptions`]; without one the compile check is skipped and the
+//! plugin's own analyzer must accept the program instead.
+
+use super::{validate_info, AnalyzeRequest, FormatRequest, GenerateRequest, LanguagePlugin, SourceFile};
+use serde::{Deserialize, Serialize};
+use std::path::{Component, Path};
+use std::process::Command;
+
+/// Task given to the generator for the hello-world checks
+pub const HELLO_WORLD_TASK: &str = "A complete program that prints \"Hello, world!\" to standard output";
+
+/// Appended to valid source to make it syntactically invalid; an unclosed
+/// string and bracket are errors in every mainstream language
+const SYNTAX_ERROR_SUFFIX: &str = "\n(\"unterminated\n";
+
+/// How to run the checks
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ConformanceOptions {
+    /// Command compiling the generated program, run in a scratch directory
+    /// holding the generated files. `{dir}` is replaced with that directory
+    /// and `{file}` with the first generated file.
+    /// e.g. `["kotlinc", "{file}", "-d", "{dir}/out"]`
+    pub compile_command: Option<Vec<String>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "status", content = "detail", rename_all = "snake_case")]
+pub enum CheckStatus {
+    Passed,
+    Failed(String),
+    Skipped(String),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConformanceCheck {
+    pub name: String,
+    pub status: CheckStatus,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConformanceReport {
+    pub language: String,
+    pub checks: Vec<ConformanceCheck>,
+}
+
+impl ConformanceReport {
+    /// No check failed
+    pub fn passed(&self) -> bool {
+        self.failures().next().is_none()
+    }
+
+    pub fn failures(&self) -> impl Iterator<Item = &ConformanceCheck> {
+        self.checks.iter().filter(|check| matches!(check.status, CheckStatus::Failed(_)))
+    }
+
+    fn record(&mut self, name: &str, status: CheckStatus) {
+        self.checks.push(ConformanceCheck {
+            name: name.to_string(),
+            status,
+        });
+    }
+}
+
+/// Run the conformance suite against a plugin
+pub fn run_conformance(plugin: &dyn LanguagePlugin, options: &ConformanceOptions) -> ConformanceReport {
+    let info = plugin.info();
+    let mut report = ConformanceReport {
+        language: info.name.clone(),
+        checks: Vec::new(),
+    };
+
+    report.record("language_info", match validate_info(info) {
+        Ok(()) => CheckStatus::Passed,
+        Err(e) => CheckStatus::Failed(e.to_string()),
+    });
+
+    let request = GenerateRequest {
+        task: HELLO_WORLD_TASK.to_string(),
+        ..GenerateRequest::default()
+    };
+    let files = match plugin.generate(&request) {
+        Ok(response) => response.files,
+        Err(e) => {
+            report.record("generates_hello_world", CheckStatus::Failed(e.to_string()));
+            return skip_rest(report, "no hello-world program was generated");
+        }
+    };
+    let generated = check_generated_files(&files, &info.file_extensions);
+    let usable = generated == CheckStatus::Passed;
+    report.record("generates_hello_world", generated);
+    if !usable {
+        return skip_rest(report, "no hello-world program was generated");
+    }
+    let main = &files[0];
+
+    report.record("analyzes_hello_world_cleanly", match plugin.analyze(&AnalyzeRequest {
+        path: main.path.clone(),
+        source: main.content.clone(),
+    }) {
+        Ok(analysis) if analysis.has_errors() => CheckStatus::Failed(format!(
+            "analyzer reports errors in the generated program: {:?}",
+            analysis.diagnostics
+        )),
+        Ok(_) => CheckStatus::Passed,
+        Err(e) => CheckStatus::Failed(e.to_string()),
+    });
+
+    report.record("hello_world_compiles", match &options.compile_command {
+        Some(command) => compile(command, &files),
+        None => CheckStatus::Skipped("no compile command configured".to_string()),
+    });
+
+    report.record("formats_idempotently", check_formatting(plugin, main));
+
+    let broken = format!("{}{}", main.content, SYNTAX_ERROR_SUFFIX);
+    let line_count = broken.lines().count() as u32;
+    report.record("reports_syntax_errors", match plugin.analyze(&AnalyzeRequest {
+        path: main.path.clone(),
+        source: broken,
+    }) {
+        Ok(analysis) => {
+            let errors: Vec<_> = analysis
+                .diagnostics
+                .iter()
+                .filter(|d| d.severity == super::DiagnosticSeverity::Error)
+                .collect();
+            if errors.is_empty() {
+                CheckStatus::Failed("no error reported for an unterminated string".to_string())
+            } else if let Some(bad) = errors.iter().find(|d| d.line == 0 || d.line > line_count || d.column == 0) {
+                CheckStatus::Failed(format!("error reported outside the source: line {}, column {}", bad.line, bad.column))
+            } else {
+                CheckStatus::Passed
+            }
+        }
+        Err(e) => CheckStatus::Failed(e.to_string()),
+    });
+
+    report
+}
+
+fn skip_rest(mut report: ConformanceReport, reason: &str) -> ConformanceReport {
+    for name in ["analyzes_hello_world_cleanly", "hello_world_compiles", "formats_idempotently", "reports_syntax_errors"] {
+        report.record(name, CheckStatus::Skipped(reason.to_string()));
+    }
+    report
+}
+
+fn check_generated_files(files: &[SourceFile], extensions: &[String]) -> CheckStatus {
+    if files.is_empty() {
+        return CheckStatus::Failed("generator returned no files".to_string());
+    }
+    for file in files {
+        let path = Path::new(&file.path);
+        if file.path.is_empty() || !path.components().all(|c| matches!(c, Component::Normal(_))) {
+            return CheckStatus::Failed(format!("{} is not a relative path inside the project", file.path));
+        }
+    }
+    let main = Path::new(&files[0].path);
+    let extension = main.extension().and_then(|ext| ext.to_str()).unwrap_or("");
+    if !extensions.iter().any(|ext| ext.eq_ignore_ascii_case(extension)) {
+        return CheckStatus::Failed(format!("{} does not have one of the declared extensions", files[0].path));
+    }
+    if files[0].content.trim().is_empty() {
+        return CheckStatus::Failed(format!("{} is empty", files[0].path));
+    }
+    CheckStatus::Passed
+}
+
+fn check_formatting(plugin: &dyn LanguagePlugin, file: &SourceFile) -> CheckStatus {
+    let format = |source: &str| {
+        plugin.format(&FormatRequest {
+            path: file.path.clone(),
+            source: source.to_string(),
+        })
+    };
+    let once = match format(&file.content) {
+        Ok(response) => response.source,
+        Err(e) => return CheckStatus::Failed(e.to_string()),
+    };
+    match format(&once) {
+        Ok(twice) if twice.source == once => CheckStatus::Passed,
+        Ok(twice) => {
+            let location = match once.lines().zip(twice.source.lines()).position(|(a, b)| a != b) {
+                Some(index) => format!("first at line {}", index + 1),
+                None => "at the end".to_string(),
+            };
+            CheckStatus::Failed(format!("formatting formatted output changes it, {}", location))
+        }
+        Err(e) => CheckStatus::Failed(e.to_string()),
+    }
+}
+
+fn compile(command: &[String], files: &[SourceFile]) -> CheckStatus {
+    let Some((program, args)) = command.split_first() else {
+        return CheckStatus::Failed("compile command is empty".to_string());
+    };
+    let dir = match tempfile::tempdir() {
+        Ok(dir) => dir,
+        Err(e) => return CheckStatus::Failed(format!("cannot create scratch directory: {}", e)),
+    };
+    for file in files {
+        let path = dir.path().join(&file.path);
+        let written = path
+            .parent()
+            .map_or(Ok(()), std::fs::create_dir_all)
+            .and_then(|_| std::fs::write(&path, &file.content));
+        if let Err(e) = written {
+            return CheckStatus::Failed(format!("cannot write {}: {}", file.path, e));
+        }
+    }
+
+    let dir_arg = dir.path().to_string_lossy();
+    let file_arg = dir.path().join(&files[0].path).to_string_lossy().into_owned();
+    let substitute = |arg: &String| arg.replace("{dir}", &dir_arg).replace("{file}", &file_arg);
+    match Command::new(substitute(program))
+        .args(args.iter().map(substitute))
+        .current_dir(dir.path())
+        .output()
+    {
+        Ok(output) if output.status.success() => CheckStatus::Passed,
+        Ok(output) => CheckStatus::Failed(format!(
+            "{} exited with {}: {}",
+            program,
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            CheckStatus::Skipped(format!("compiler {} is not installed", program))
+        }
+        Err(e) => CheckStatus::Failed(format!("cannot run {}: {}", program, e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::errors::Result;
+    use crate::language::{
+        AnalyzeResponse, Diagnostic, DiagnosticSeverity, FormatResponse, GenerateResponse, LanguageInfo,
+        LANGUAGE_ABI_VERSION,
+    };
+
+    /// Language whose only syntax rule is that quotes and brackets balance
+    struct ToyLanguage {
+        info: LanguageInfo,
+        /// Formatter appends a newline every time, so is not idempotent
+        sloppy_formatter: bool,
+    }
+
+    impl ToyLanguage {
+        fn new(sloppy_formatter: bool) -> Self {
+            Self {
+                info: LanguageInfo {
+                    name: "toy".to_string(),
+                    version: "0.1.0".to_string(),
+                    abi_version: LANGUAGE_ABI_VERSION,
+                    file_extensions: vec!["toy".to_string()],
+                    description: None,
+                },
+                sloppy_formatter,
+            }
+        }
+    }
+
+    impl LanguagePlugin for ToyLanguage {
+        fn info(&self) -> &LanguageInfo {
+            &self.info
+        }
+
+        fn generate(&self, _request: &GenerateRequest) -> Result<GenerateResponse> {
+            Ok(GenerateResponse {
+                files: vec![SourceFile {
+                    path: "src/main.toy".to_string(),
+                    content: "print(\"Hello, world!\")\n".to_string(),
+                }],
+            })
+        }
+
+        fn format(&self, request: &FormatRequest) -> Result<FormatResponse> {
+            let source = if self.sloppy_formatter {
+                format!("{}\n", request.source)
+            } else {
+                format!("{}\n", request.source.trim_end())
+            };
+            Ok(FormatResponse { source })
+        }
+
+        fn analyze(&self, request: &AnalyzeRequest) -> Result<AnalyzeResponse> {
+            let mut depth = 0i32;
+            let mut in_string = false;
+            for c in request.source.chars() {
+                match c {
+                    '"' => in_string = !in_string,
+                    '(' if !in_string => depth += 1,
+                    ')' if !in_string => depth -= 1,
+                    _ => {}
+                }
+            }
+            let mut response = AnalyzeResponse::default();
+            if in_string || depth != 0 {
+                response.diagnostics.push(Diagnostic {
+                    severity: DiagnosticSeverity::Error,
+                    message: "unbalanced quotes or brackets".to_string(),
+                    line: request.source.lines().count() as u32,
+                    column: 1,
+                });
+            }
+            Ok(response)
+        }
+    }
+
+    #[test]
+    fn conforming_plugin_passes() {
+        let report = run_conformance(&ToyLanguage::new(false), &ConformanceOptions::default());
+        assert!(report.passed(), "{:?}", report);
+        let compile = report.checks.iter().find(|check| check.name == "hello_world_compiles").unwrap();
+        assert!(matches!(compile.status, CheckStatus::Skipped(_)));
+    }
+
+    #[test]
+    fn non_idempotent_formatter_fails() {
+        let report = run_conformance(&ToyLanguage::new(true), &ConformanceOptions::default());
+        let failures: Vec<&str> = report.failures().map(|check| check.name.as_str()).collect();
+        assert_eq!(failures, vec!["formats_idempotently"]);
+    }
+
+    #[test]
+    fn missing_compiler_is_skipped_and_failing_compiler_fails() {
+        let options = ConformanceOptions {
+            compile_command: Some(vec!["aion-no-such-compiler".to_string(), "{file}".to_string()]),
+        };
+        let report = run_conformance(&ToyLanguage::new(false), &options);
+        assert!(report.passed());
+
+        let options = ConformanceOptions {
+            compile_command: Some(vec!["false".to_string()]),
+        };
+        if cfg!(unix) {
+            let report = run_conformance(&ToyLanguage::new(false), &options);
+            let failures: Vec<&str> = report.failures().map(|check| check.name.as_str()).collect();
+            assert_eq!(failures, vec!["hello_world_compiles"]);
+        }
+    }
+}
@@ -0,0 +1,250 @@
+//! # Language Plugins
+//!
+//! Support for a target language (code generation, formatting and syntax
+//! analysis) shipped as a sandboxed WASM module.
+//!
+//! ## ABI, version 1
+//!
+//! A language plugin is a core WASM module. It may import
+//! `wasi_snapshot_preview1`, but only sees the filesystem its permissions
+//! grant (none by default), and must export:
+//!
+//! | Export | Signature | Purpose |
+//! |---|---|---|
+//! | `memory` | memory | Linear memory for request and response buffers |
+//! | `aion_abi_version` | `() -> i32` | ABI version the plugin implements; must be `1` |
+//! | `aion_alloc` | `(len: i32) -> i32` | Allocate `len` bytes, return the pointer |
+//! | `aion_dealloc` | `(ptr: i32, len: i32)` | Free a buffer from `aion_alloc` or a hook |
+//! | `aion_language_info` | `() -> i64` | [`LanguageInfo`] |
+//! | `aion_generate` | `(ptr: i32, len: i32) -> i64` | [`GenerateRequest`] to [`GenerateResponse`] |
+//! | `aion_format` | `(ptr: i32, len: i32) -> i64` | [`FormatRequest`] to [`FormatResponse`] |
+//! | `aion_analyze` | `(ptr: i32, len: i32) -> i64` | [`AnalyzeRequest`] to [`AnalyzeResponse`] |
+//!
+//! Requests and responses are UTF-8 JSON. The host allocates each request
+//! with `aion_alloc`, writes it, and passes pointer and length to the hook.
+//! A hook returns its response buffer packed as `(ptr << 32) | len`; the
+//! host frees both buffers with `aion_dealloc` once it has read them. Every
+//! response is wrapped in an envelope, `{"ok": <response>}` on success or
+//! `{"error": "<message>"}` on failure.
+//!
+//! Each call runs in a fresh instance with its own memory, under the
+//! plugin's timeout and memory limits, so hooks must not rely on state kept
+//! between calls.
+//!
+//! The ABI only changes by adding a new version; hosts keep loading every
+//! version in [`SUPPORTED_ABI_VERSIONS`]. A plugin must pass
+//! [`conformance::run_conformance`] before it is published.
+
+pub mod conformance;
+#[cfg(feature = "wasm-plugins")]
+pub mod wasm;
+
+#[cfg(feature = "wasm-plugins")]
+pub use wasm::WasmLanguagePlugin;
+
+use crate::errors::{PluginError, Result};
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::ops::RangeInclusive;
+use std::sync::Arc;
+
+/// Current language plugin ABI version
+pub const LANGUAGE_ABI_VERSION: u32 = 1;
+
+/// ABI versions this host can load
+pub const SUPPORTED_ABI_VERSIONS: RangeInclusive<u32> = 1..=LANGUAGE_ABI_VERSION;
+
+/// Describes the language a plugin supports
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LanguageInfo {
+    /// Language name, e.g. `kotlin`
+    pub name: String,
+    /// Plugin version
+    pub version: String,
+    pub abi_version: u32,
+    /// Extensions of the language's source files, without the dot
+    pub file_extensions: Vec<String>,
+    #[serde(default)]
+    pub description: Option<String>,
+}
+
+/// Generate source files for a task
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GenerateRequest {
+    /// What to generate, in natural language
+    pub task: String,
+    /// Module, package or namespace to generate into
+    #[serde(default)]
+    pub module_name: Option<String>,
+    /// Language-specific options
+    #[serde(default)]
+    pub options: HashMap<String, serde_json::Value>,
+}
+
+/// A source file produced by a plugin
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SourceFile {
+    /// Path relative to the project root
+    pub path: String,
+    pub content: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GenerateResponse {
+    pub files: Vec<SourceFile>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FormatRequest {
+    pub path: String,
+    pub source: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FormatResponse {
+    pub source: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AnalyzeRequest {
+    pub path: String,
+    pub source: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AnalyzeResponse {
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl AnalyzeResponse {
+    pub fn has_errors(&self) -> bool {
+        self.diagnostics.iter().any(|d| d.severity == DiagnosticSeverity::Error)
+    }
+}
+
+/// A problem found by a plugin's analyzer
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Diagnostic {
+    pub severity: DiagnosticSeverity,
+    pub message: String,
+    /// 1-based line
+    pub line: u32,
+    /// 1-based column
+    pub column: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DiagnosticSeverity {
+    Error,
+    Warning,
+    Info,
+}
+
+/// Response envelope every hook returns
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Envelope<T> {
+    Ok(T),
+    Error(String),
+}
+
+impl<T> Envelope<T> {
+    pub fn into_result(self) -> Result<T> {
+        match self {
+            Envelope::Ok(value) => Ok(value),
+            Envelope::Error(message) => Err(PluginError::RuntimeError(message)),
+        }
+    }
+}
+
+/// Generator, formatter and analyzer hooks for one language, whatever
+/// runtime implements them
+pub trait LanguagePlugin: Send + Sync {
+    fn info(&self) -> &LanguageInfo;
+
+    fn generate(&self, request: &GenerateRequest) -> Result<GenerateResponse>;
+
+    fn format(&self, request: &FormatRequest) -> Result<FormatResponse>;
+
+    fn analyze(&self, request: &AnalyzeRequest) -> Result<AnalyzeResponse>;
+}
+
+pub fn check_abi_version(version: u32) -> Result<()> {
+    if SUPPORTED_ABI_VERSIONS.contains(&version) {
+        Ok(())
+    } else {
+        Err(PluginError::UnsupportedAbi {
+            found: version,
+            supported: format!("{}-{}", SUPPORTED_ABI_VERSIONS.start(), SUPPORTED_ABI_VERSIONS.end()),
+        })
+    }
+}
+
+/// Check that a plugin's self-description is loadable by this host
+pub fn validate_info(info: &LanguageInfo) -> Result<()> {
+    check_abi_version(info.abi_version)?;
+    if info.name.trim().is_empty() {
+        return Err(PluginError::ConfigurationError("Language plugin has no language name".to_string()));
+    }
+    if info.file_extensions.is_empty() || info.file_extensions.iter().any(|ext| ext.is_empty() || ext.starts_with('.')) {
+        return Err(PluginError::ConfigurationError(format!(
+            "Language plugin {} must declare file extensions without a leading dot",
+            info.name
+        )));
+    }
+    Ok(())
+}
+
+/// Language plugins by language name
+#[derive(Default)]
+pub struct LanguageRegistry {
+    plugins: DashMap<String, Arc<dyn LanguagePlugin>>,
+}
+
+impl LanguageRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a plugin; fails if its language already has one
+    pub fn register(&self, plugin: Arc<dyn LanguagePlugin>) -> Result<()> {
+        validate_info(plugin.info())?;
+        let name = plugin.info().name.to_lowercase();
+        match self.plugins.entry(name) {
+            dashmap::mapref::entry::Entry::Occupied(entry) => Err(PluginError::ConfigurationError(format!(
+                "Language {} already has a plugin",
+                entry.key()
+            ))),
+            dashmap::mapref::entry::Entry::Vacant(entry) => {
+                tracing::info!("Registered language plugin: {} {}", plugin.info().name, plugin.info().version);
+                entry.insert(plugin);
+                Ok(())
+            }
+        }
+    }
+
+    pub fn unregister(&self, language: &str) -> bool {
+        self.plugins.remove(&language.to_lowercase()).is_some()
+    }
+
+    pub fn get(&self, language: &str) -> Option<Arc<dyn LanguagePlugin>> {
+        self.plugins.get(&language.to_lowercase()).map(|entry| entry.value().clone())
+    }
+
+    /// Plugin for a source file extension, with or without the leading dot
+    pub fn for_extension(&self, extension: &str) -> Option<Arc<dyn LanguagePlugin>> {
+        let extension = extension.trim_start_matches('.');
+        self.plugins
+            .iter()
+            .find(|entry| entry.value().info().file_extensions.iter().any(|ext| ext.eq_ignore_ascii_case(extension)))
+            .map(|entry| entry.value().clone())
+    }
+
+    pub fn languages(&self) -> Vec<LanguageInfo> {
+        let mut languages: Vec<LanguageInfo> = self.plugins.iter().map(|entry| entry.value().info().clone()).collect();
+        languages.sort_by(|a, b| a.name.cmp(&b.name));
+        languages
+    }
+}
@@ -0,0 +1,241 @@
+//! # WASM Language Plugin Host
+//!
+//! Runs a language plugin module under the ABI described in the parent
+//! module. The module is compiled once; every hook call gets a new store and
+//! instance, stopped by the same epoch deadline and memory limiter as a
+//! general plugin (see [`crate::runtime::wasm`]).
+
+use super::{
+    check_abi_version, validate_info, AnalyzeRequest, AnalyzeResponse, Envelope, FormatRequest, FormatResponse, GenerateRequest,
+    GenerateResponse, LanguageInfo, LanguagePlugin,
+};
+use crate::errors::{PluginError, Result};
+use crate::runtime::wasm::{failure, EpochEngine};
+use crate::runtime::{WasiSandbox, WasiState};
+use crate::{ExecutionLimits, PluginPermissions};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::path::Path;
+use wasmtime::{Instance, Linker, Module, Store};
+
+/// Largest response a plugin may return
+const MAX_RESPONSE_BYTES: usize = 16 * 1024 * 1024;
+
+/// A language plugin loaded from a WASM module
+pub struct WasmLanguagePlugin {
+    runtime: PluginModule,
+    info: LanguageInfo,
+}
+
+/// Compiled module and the settings each call's instance is created with
+struct PluginModule {
+    engine: EpochEngine,
+    module: Module,
+    linker: Linker<WasiState>,
+    sandbox: WasiSandbox,
+    limits: ExecutionLimits,
+}
+
+impl WasmLanguagePlugin {
+    /// Load a plugin module from disk
+    pub fn load(path: impl AsRef<Path>, permissions: &PluginPermissions, limits: ExecutionLimits) -> Result<Self> {
+        let bytes = std::fs::read(path.as_ref())?;
+        Self::from_bytes(&bytes, permissions, limits)
+    }
+
+    /// Load a plugin from module bytes (binary or text format)
+    pub fn from_bytes(bytes: &[u8], permissions: &PluginPermissions, limits: ExecutionLimits) -> Result<Self> {
+        let engine = EpochEngine::new()?;
+        let module = Module::new(engine.engine(), bytes)?;
+
+        let mut linker = Linker::new(engine.engine());
+        WasiState::add_to_linker(&mut linker)?;
+
+        let runtime = PluginModule {
+            engine,
+            module,
+            linker,
+            sandbox: WasiSandbox::from_permissions(permissions)?,
+            limits,
+        };
+
+        // Check the ABI version before trusting any other export
+        let (mut store, instance) = runtime.instantiate()?;
+        let abi_version = instance
+            .get_typed_func::<(), i32>(&mut store, "aion_abi_version")
+            .map_err(|_| PluginError::ConfigurationError("Language plugin does not export aion_abi_version".to_string()))?
+            .call(&mut store, ())?;
+        let abi_version = u32::try_from(abi_version).unwrap_or(0);
+        check_abi_version(abi_version)?;
+
+        let info: LanguageInfo = call_hook(&mut store, &instance, "aion_language_info", None)?;
+        if info.abi_version != abi_version {
+            return Err(PluginError::ConfigurationError(format!(
+                "Language plugin reports ABI version {} but exports version {}",
+                info.abi_version, abi_version
+            )));
+        }
+        validate_info(&info)?;
+        Ok(Self { runtime, info })
+    }
+}
+
+impl PluginModule {
+    fn instantiate(&self) -> Result<(Store<WasiState>, Instance)> {
+        let mut store = self.engine.store(&self.sandbox, &self.limits)?;
+        let instance = self
+            .linker
+            .instantiate(&mut store, &self.module)
+            .map_err(|error| failure(error, &store, &self.limits))?;
+        Ok((store, instance))
+    }
+
+    /// Call a hook in a fresh instance
+    fn call<Req: Serialize, Resp: DeserializeOwned>(&self, hook: &str, request: &Req) -> Result<Resp> {
+        let (mut store, instance) = self.instantiate()?;
+        let request = serde_json::to_vec(request)?;
+        call_hook(&mut store, &instance, hook, Some(&request)).map_err(|error| match error {
+            // Traps, including the timeout and memory limit, surface as wasmtime errors
+            PluginError::Other(error) => failure(error, &store, &self.limits),
+            error => error,
+        })
+    }
+}
+
+/// Call a hook and decode its enveloped response
+fn call_hook<Resp: DeserializeOwned>(
+    store: &mut Store<WasiState>,
+    instance: &Instance,
+    hook: &str,
+    request: Option<&[u8]>,
+) -> Result<Resp> {
+    let memory = instance
+        .get_memory(&mut *store, "memory")
+        .ok_or_else(|| PluginError::ConfigurationError("Language plugin does not export memory".to_string()))?;
+    let alloc = instance.get_typed_func::<i32, i32>(&mut *store, "aion_alloc")?;
+    let dealloc = instance.get_typed_func::<(i32, i32), ()>(&mut *store, "aion_dealloc")?;
+
+    let packed = match request {
+        Some(request) => {
+            let len = i32::try_from(request.len())
+                .map_err(|_| PluginError::RuntimeError(format!("{} request is too large", hook)))?;
+            let ptr = alloc.call(&mut *store, len)?;
+            memory.write(&mut *store, ptr as u32 as usize, request).map_err(|e| {
+                PluginError::RuntimeError(format!("aion_alloc returned an invalid buffer for {}: {}", hook, e))
+            })?;
+            let packed = instance.get_typed_func::<(i32, i32), i64>(&mut *store, hook)?.call(&mut *store, (ptr, len))?;
+            dealloc.call(&mut *store, (ptr, len))?;
+            packed
+        }
+        None => instance.get_typed_func::<(), i64>(&mut *store, hook)?.call(&mut *store, ())?,
+    };
+
+    let (ptr, len) = ((packed as u64 >> 32) as usize, (packed as u64 & 0xffff_ffff) as usize);
+    if len > MAX_RESPONSE_BYTES {
+        return Err(PluginError::RuntimeError(format!("{} response of {} bytes is too large", hook, len)));
+    }
+    let response = memory
+        .data(&*store)
+        .get(ptr..ptr + len)
+        .ok_or_else(|| PluginError::RuntimeError(format!("{} returned a response outside its memory", hook)))?
+        .to_vec();
+    dealloc.call(&mut *store, (ptr as i32, len as i32))?;
+
+    serde_json::from_slice::<Envelope<Resp>>(&response)?.into_result()
+}
+
+impl LanguagePlugin for WasmLanguagePlugin {
+    fn info(&self) -> &LanguageInfo {
+        &self.info
+    }
+
+    fn generate(&self, request: &GenerateRequest) -> Result<GenerateResponse> {
+        self.runtime.call("aion_generate", request)
+    }
+
+    fn format(&self, request: &FormatRequest) -> Result<FormatResponse> {
+        self.runtime.call("aion_format", request)
+    }
+
+    fn analyze(&self, request: &AnalyzeRequest) -> Result<AnalyzeResponse> {
+        self.runtime.call("aion_analyze", request)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Plugin with a bump allocator and canned hooks: `aion_generate`
+    /// answers with no files, `aion_format` traps and `aion_analyze` never
+    /// returns
+    fn plugin_wat(abi_version: i32, info: &str) -> String {
+        let info_len = info.len();
+        let info = info.replace('"', "\\\"");
+        format!(
+            r#"(module
+              (memory (export "memory") 1)
+              (global $next (mut i32) (i32.const 4096))
+              (data (i32.const 0) "{info}")
+              (data (i32.const 2048) "{{\"ok\":{{\"files\":[]}}}}")
+              (func (export "aion_abi_version") (result i32) (i32.const {abi_version}))
+              (func (export "aion_alloc") (param $len i32) (result i32)
+                (local $ptr i32)
+                (local.set $ptr (global.get $next))
+                (global.set $next (i32.add (global.get $next) (local.get $len)))
+                (local.get $ptr))
+              (func (export "aion_dealloc") (param i32 i32))
+              (func (export "aion_language_info") (result i64) (i64.const {info_len}))
+              (func (export "aion_generate") (param i32 i32) (result i64)
+                (i64.or (i64.shl (i64.const 2048) (i64.const 32)) (i64.const 19)))
+              (func (export "aion_format") (param i32 i32) (result i64) (unreachable))
+              (func (export "aion_analyze") (param i32 i32) (result i64) (loop $spin (br $spin)) (i64.const 0)))"#,
+            info = info,
+            info_len = info_len,
+            abi_version = abi_version,
+        )
+    }
+
+    const INFO: &str = r#"{"ok":{"name":"toy","version":"0.1.0","abi_version":1,"file_extensions":["toy"]}}"#;
+
+    fn limits() -> ExecutionLimits {
+        ExecutionLimits {
+            timeout_seconds: 1,
+            ..ExecutionLimits::default()
+        }
+    }
+
+    #[test]
+    fn loads_plugin_and_calls_hooks() {
+        let plugin = WasmLanguagePlugin::from_bytes(plugin_wat(1, INFO).as_bytes(), &PluginPermissions::default(), limits()).unwrap();
+        assert_eq!(plugin.info().name, "toy");
+        assert_eq!(plugin.info().file_extensions, vec!["toy".to_string()]);
+
+        let generated = plugin.generate(&GenerateRequest::default()).unwrap();
+        assert!(generated.files.is_empty());
+
+        // A trapping hook and a runaway hook both fail the call, not the host
+        let request = FormatRequest {
+            path: "main.toy".to_string(),
+            source: String::new(),
+        };
+        assert!(matches!(plugin.format(&request), Err(PluginError::RuntimeError(_))));
+        let request = AnalyzeRequest {
+            path: "main.toy".to_string(),
+            source: String::new(),
+        };
+        let started = std::time::Instant::now();
+        match plugin.analyze(&request) {
+            Err(PluginError::LimitExceeded(message)) => assert!(message.contains("timeout of 1 seconds"), "{}", message),
+            other => panic!("expected the timeout to stop the hook, got {:?}", other.map(|_| ())),
+        }
+        assert!(started.elapsed() < std::time::Duration::from_secs(5));
+        assert!(plugin.generate(&GenerateRequest::default()).is_ok());
+    }
+
+    #[test]
+    fn rejects_unsupported_abi_versions() {
+        let result = WasmLanguagePlugin::from_bytes(plugin_wat(2, INFO).as_bytes(), &PluginPermissions::default(), limits());
+        assert!(matches!(result, Err(PluginError::UnsupportedAbi { found: 2, .. })));
+    }
+}
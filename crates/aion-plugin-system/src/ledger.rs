@@ -0,0 +1,350 @@
+//! # Plugin Resource Ledger
+//!
+//! Cumulative resource accounting per plugin. Single executions are bounded by
+//! `ExecutionLimits`; the ledger sums usage across executions within a fixed
+//! window and disables a plugin that exceeds its quota until the window ends.
+
+use crate::errors::{PluginError, Result};
+use crate::PluginExecutionResult;
+use chrono::{DateTime, Duration, Utc};
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// Resources consumed by one or more executions
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ResourceUsage {
+    /// Wall-clock execution time in milliseconds
+    pub execution_time_ms: u64,
+    /// Memory held over time, in byte-seconds
+    pub memory_byte_seconds: u64,
+    /// Files written
+    pub fs_operations: u64,
+}
+
+impl ResourceUsage {
+    /// Usage reported by an execution result.
+    ///
+    /// Only what the runtimes measure is charged: CPU time isn't reported
+    /// separately from wall time, and plugins have no network access to count.
+    /// Memory is charged at its peak for the whole execution.
+    pub fn from_execution(result: &PluginExecutionResult) -> Self {
+        Self {
+            execution_time_ms: result.duration_ms,
+            memory_byte_seconds: (result.memory_usage.peak_bytes as u64).saturating_mul(result.duration_ms) / 1000,
+            fs_operations: result.generated_files.len() as u64,
+        }
+    }
+
+    fn add(&mut self, other: &ResourceUsage) {
+        self.execution_time_ms = self.execution_time_ms.saturating_add(other.execution_time_ms);
+        self.memory_byte_seconds = self.memory_byte_seconds.saturating_add(other.memory_byte_seconds);
+        self.fs_operations = self.fs_operations.saturating_add(other.fs_operations);
+    }
+}
+
+/// Cumulative limits per plugin and window; `None` means unlimited
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResourceQuota {
+    /// Accounting window length in seconds
+    pub window_seconds: u64,
+    pub max_execution_time_ms: Option<u64>,
+    pub max_memory_byte_seconds: Option<u64>,
+    pub max_fs_operations: Option<u64>,
+}
+
+impl Default for ResourceQuota {
+    fn default() -> Self {
+        Self {
+            window_seconds: 3600,
+            max_execution_time_ms: Some(10 * 60 * 1000), // 10 minutes per hour
+            max_memory_byte_seconds: Some(64 * 1024 * 1024 * 3600), // 64 MB held for the whole hour
+            max_fs_operations: Some(100_000),
+        }
+    }
+}
+
+impl ResourceQuota {
+    /// First exceeded resource as (name, used, limit)
+    fn exceeded(&self, usage: &ResourceUsage) -> Option<(&'static str, u64, u64)> {
+        [
+            ("execution_time_ms", usage.execution_time_ms, self.max_execution_time_ms),
+            ("memory_byte_seconds", usage.memory_byte_seconds, self.max_memory_byte_seconds),
+            ("fs_operations", usage.fs_operations, self.max_fs_operations),
+        ]
+        .into_iter()
+        .find_map(|(name, used, limit)| limit.filter(|limit| used > *limit).map(|limit| (name, used, limit)))
+    }
+}
+
+/// Snapshot of a plugin's usage in the current window
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UsageLedger {
+    pub plugin_id: Uuid,
+    pub window_start: DateTime<Utc>,
+    pub window_end: DateTime<Utc>,
+    pub usage: ResourceUsage,
+    pub executions: u64,
+    pub quota: ResourceQuota,
+    /// Set while the plugin is disabled for exceeding its quota
+    pub disabled_until: Option<DateTime<Utc>>,
+    /// Resource that caused the plugin to be disabled
+    pub exceeded_resource: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+struct LedgerEntry {
+    window_start: DateTime<Utc>,
+    usage: ResourceUsage,
+    executions: u64,
+    exceeded: Option<(String, u64, u64)>,
+}
+
+/// Per-plugin resource ledger with quota enforcement
+#[derive(Debug)]
+pub struct ResourceLedger {
+    default_quota: ResourceQuota,
+    quotas: DashMap<Uuid, ResourceQuota>,
+    entries: DashMap<Uuid, LedgerEntry>,
+}
+
+impl ResourceLedger {
+    pub fn new(default_quota: ResourceQuota) -> Self {
+        Self {
+            default_quota,
+            quotas: DashMap::new(),
+            entries: DashMap::new(),
+        }
+    }
+
+    /// Override the quota for one plugin
+    pub fn set_quota(&self, plugin_id: Uuid, quota: ResourceQuota) {
+        self.quotas.insert(plugin_id, quota);
+    }
+
+    pub fn quota_for(&self, plugin_id: &Uuid) -> ResourceQuota {
+        self.quotas
+            .get(plugin_id)
+            .map(|q| q.clone())
+            .unwrap_or_else(|| self.default_quota.clone())
+    }
+
+    /// Fail with `QuotaExceeded` while the plugin is disabled
+    pub fn check(&self, plugin_id: &Uuid) -> Result<()> {
+        let quota = self.quota_for(plugin_id);
+        let now = Utc::now();
+        let Some(mut entry) = self.entries.get_mut(plugin_id) else {
+            return Ok(());
+        };
+        Self::roll_window(&mut entry, &quota, now);
+
+        match &entry.exceeded {
+            Some((resource, used, limit)) => Err(PluginError::QuotaExceeded {
+                plugin_id: *plugin_id,
+                resource: resource.clone(),
+                used: *used,
+                limit: *limit,
+                retry_after: Self::window_end(entry.window_start, &quota),
+            }),
+            None => Ok(()),
+        }
+    }
+
+    /// Charge an execution's usage; returns `QuotaExceeded` if this pushed the plugin over its quota
+    pub fn record(&self, plugin_id: &Uuid, usage: &ResourceUsage) -> Result<()> {
+        let quota = self.quota_for(plugin_id);
+        let now = Utc::now();
+        let mut entry = self.entries.entry(*plugin_id).or_insert_with(|| LedgerEntry {
+            window_start: Self::window_start(now, &quota),
+            usage: ResourceUsage::default(),
+            executions: 0,
+            exceeded: None,
+        });
+        Self::roll_window(&mut entry, &quota, now);
+
+        entry.usage.add(usage);
+        entry.executions += 1;
+
+        if entry.exceeded.is_none() {
+            if let Some((resource, used, limit)) = quota.exceeded(&entry.usage) {
+                let retry_after = Self::window_end(entry.window_start, &quota);
+                tracing::warn!(
+                    "Plugin {} exceeded its {} quota ({} > {}); disabled until {}",
+                    plugin_id,
+                    resource,
+                    used,
+                    limit,
+                    retry_after
+                );
+                entry.exceeded = Some((resource.to_string(), used, limit));
+                return Err(PluginError::QuotaExceeded {
+                    plugin_id: *plugin_id,
+                    resource: resource.to_string(),
+                    used,
+                    limit,
+                    retry_after,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Current window's ledger for a plugin
+    pub fn usage(&self, plugin_id: &Uuid) -> UsageLedger {
+        let quota = self.quota_for(plugin_id);
+        let now = Utc::now();
+        let (window_start, usage, executions, exceeded) = match self.entries.get_mut(plugin_id) {
+            Some(mut entry) => {
+                Self::roll_window(&mut entry, &quota, now);
+                (entry.window_start, entry.usage.clone(), entry.executions, entry.exceeded.clone())
+            }
+            None => (Self::window_start(now, &quota), ResourceUsage::default(), 0, None),
+        };
+        let window_end = Self::window_end(window_start, &quota);
+
+        UsageLedger {
+            plugin_id: *plugin_id,
+            window_start,
+            window_end,
+            usage,
+            executions,
+            disabled_until: exceeded.as_ref().map(|_| window_end),
+            exceeded_resource: exceeded.map(|(resource, _, _)| resource),
+            quota,
+        }
+    }
+
+    /// Forget a plugin's usage, e.g. after it is uninstalled
+    pub fn remove(&self, plugin_id: &Uuid) {
+        self.entries.remove(plugin_id);
+        self.quotas.remove(plugin_id);
+    }
+
+    /// Windows are aligned to multiples of their length since the epoch
+    fn window_start(now: DateTime<Utc>, quota: &ResourceQuota) -> DateTime<Utc> {
+        let window = quota.window_seconds.max(1) as i64;
+        let start = now.timestamp().div_euclid(window) * window;
+        DateTime::from_timestamp(start, 0).unwrap_or(now)
+    }
+
+    fn window_end(window_start: DateTime<Utc>, quota: &ResourceQuota) -> DateTime<Utc> {
+        window_start + Duration::seconds(quota.window_seconds.max(1) as i64)
+    }
+
+    /// Reset totals and re-enable the plugin once the window has passed
+    fn roll_window(entry: &mut LedgerEntry, quota: &ResourceQuota, now: DateTime<Utc>) {
+        if now >= Self::window_end(entry.window_start, quota) {
+            entry.window_start = Self::window_start(now, quota);
+            entry.usage = ResourceUsage::default();
+            entry.executions = 0;
+            entry.exceeded = None;
+        }
+    }
+}
+
+impl Default for ResourceLedger {
+    fn default() -> Self {
+        Self::new(ResourceQuota::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{GeneratedFile, MemoryUsage};
+
+    fn execution(duration_ms: u64, peak_bytes: usize, files: usize) -> PluginExecutionResult {
+        PluginExecutionResult {
+            execution_id: Uuid::new_v4(),
+            plugin_id: Uuid::new_v4(),
+            success: true,
+            result: None,
+            error: None,
+            duration_ms,
+            memory_usage: MemoryUsage {
+                peak_bytes,
+                final_bytes: 0,
+                allocations: 0,
+            },
+            generated_files: (0..files)
+                .map(|i| GeneratedFile {
+                    path: format!("out/{}.txt", i),
+                    content: Some(String::new()),
+                    is_binary: false,
+                    permissions: None,
+                    size_bytes: 0,
+                })
+                .collect(),
+            logs: Vec::new(),
+        }
+    }
+
+    fn quota() -> ResourceQuota {
+        ResourceQuota {
+            window_seconds: 3600,
+            max_execution_time_ms: Some(1_000),
+            max_memory_byte_seconds: None,
+            max_fs_operations: Some(3),
+        }
+    }
+
+    #[test]
+    fn charges_measured_usage() {
+        let usage = ResourceUsage::from_execution(&execution(1_500, 2 * 1024 * 1024, 2));
+        assert_eq!(
+            usage,
+            ResourceUsage {
+                execution_time_ms: 1_500,
+                memory_byte_seconds: 3 * 1024 * 1024,
+                fs_operations: 2,
+            }
+        );
+    }
+
+    #[test]
+    fn exceeding_a_quota_disables_the_plugin_for_the_window() {
+        let ledger = ResourceLedger::new(quota());
+        let plugin_id = Uuid::new_v4();
+
+        ledger.record(&plugin_id, &ResourceUsage::from_execution(&execution(600, 0, 1))).unwrap();
+        ledger.check(&plugin_id).unwrap();
+
+        let error = ledger
+            .record(&plugin_id, &ResourceUsage::from_execution(&execution(600, 0, 1)))
+            .unwrap_err();
+        assert!(matches!(
+            error,
+            PluginError::QuotaExceeded { ref resource, used: 1_200, limit: 1_000, .. } if resource == "execution_time_ms"
+        ));
+        assert!(matches!(ledger.check(&plugin_id), Err(PluginError::QuotaExceeded { .. })));
+
+        let ledger_view = ledger.usage(&plugin_id);
+        assert_eq!(ledger_view.executions, 2);
+        assert_eq!(ledger_view.usage.fs_operations, 2);
+        assert_eq!(ledger_view.exceeded_resource.as_deref(), Some("execution_time_ms"));
+        assert_eq!(ledger_view.disabled_until, Some(ledger_view.window_end));
+
+        // Other plugins are unaffected
+        ledger.check(&Uuid::new_v4()).unwrap();
+    }
+
+    #[test]
+    fn per_plugin_quota_overrides_the_default() {
+        let ledger = ResourceLedger::new(quota());
+        let plugin_id = Uuid::new_v4();
+        ledger.set_quota(
+            plugin_id,
+            ResourceQuota {
+                max_execution_time_ms: None,
+                ..quota()
+            },
+        );
+
+        ledger.record(&plugin_id, &ResourceUsage::from_execution(&execution(5_000, 0, 0))).unwrap();
+        ledger.check(&plugin_id).unwrap();
+
+        ledger.remove(&plugin_id);
+        assert_eq!(ledger.usage(&plugin_id).executions, 0);
+        assert_eq!(ledger.quota_for(&plugin_id).max_execution_time_ms, Some(1_000));
+    }
+}
@@ -17,7 +17,8 @@
 //! - **Code Generators**: Custom project templates and scaffolding
 //! - **Quality Analyzers**: Custom testing and analysis tools
 //! - **Build Tools**: Custom build pipelines and optimization
-//! - **Language Processors**: Support for new programming languages
+//! - **Language Processors**: Support for new programming languages, as
+//!   sandboxed WASM modules implementing the [`language`] ABI
 //! - **Deployment Providers**: Custom deployment targets and strategies
 //! - **Monitoring Tools**: Custom metrics and observability
 //! - **Integration Connectors**: Third-party service integrations
@@ -53,6 +54,9 @@ pub mod security;
 pub mod events;
 pub mod config;
 pub mod errors;
+pub mod ledger;
+pub mod language;
+pub mod reload;
 
 pub use manager::*;
 pub use plugin::*;
@@ -63,6 +67,9 @@ pub use security::*;
 pub use events::*;
 pub use config::*;
 pub use errors::*;
+pub use ledger::*;
+pub use reload::{ExecutionGuard, ExecutionTracker, PluginConfig, PluginConfigs, ReloadStrategy};
+pub use language::{LanguageInfo, LanguagePlugin, LanguageRegistry, LANGUAGE_ABI_VERSION};
 
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
@@ -97,6 +104,9 @@ pub struct PluginContext {
     pub limits: ExecutionLimits,
     /// User permissions
     pub permissions: PluginPermissions,
+    /// Configuration registered for the plugin
+    #[serde(default)]
+    pub config: PluginConfig,
 }
 
 /// Plugin execution limits
@@ -7,6 +7,9 @@ use crate::{
     events::*,
     config::*,
     errors::*,
+    ledger::*,
+    language::LanguageRegistry,
+    reload::{ExecutionTracker, PluginConfig, PluginConfigs, ReloadStrategy},
     PluginContext,
     PluginExecutionResult,
     ExecutionLimits,
@@ -36,6 +39,14 @@ pub struct PluginManager {
     config: Arc<PluginSystemConfig>,
     /// Plugin watchers for hot-reload
     watchers: Arc<RwLock<DashMap<PathBuf, notify::RecommendedWatcher>>>,
+    /// Cumulative resource usage and quotas
+    ledger: Arc<ResourceLedger>,
+    /// Language support plugins
+    languages: Arc<LanguageRegistry>,
+    /// Registered plugin configurations, kept across reloads
+    configs: Arc<PluginConfigs>,
+    /// In-flight executions, drained or cancelled on reload
+    executions: Arc<ExecutionTracker>,
 }
 
 impl PluginManager {
@@ -64,6 +75,10 @@ impl PluginManager {
             marketplace,
             config,
             watchers: Arc::new(RwLock::new(DashMap::new())),
+            ledger: Arc::new(ResourceLedger::default()),
+            languages: Arc::new(LanguageRegistry::new()),
+            configs: Arc::new(PluginConfigs::new()),
+            executions: Arc::new(ExecutionTracker::new()),
         };
 
         // Load plugins from configured directories
@@ -77,6 +92,12 @@ impl PluginManager {
         let path = path.as_ref();
         tracing::info!("Loading plugin from: {}", path.display());
 
+        let loaded_plugin = self.prepare_plugin(path).await?;
+        self.register_plugin(path, loaded_plugin).await
+    }
+
+    /// Load, validate and instantiate the plugin at `path` without registering it
+    async fn prepare_plugin(&self, path: &Path) -> Result<LoadedPlugin> {
         // Security check
         self.security.validate_plugin_path(path).await?;
 
@@ -89,16 +110,18 @@ impl PluginManager {
         // Create runtime
         let runtime = self.runtime_factory.create_runtime(&plugin_info).await?;
 
-        // Create loaded plugin
-        let loaded_plugin = LoadedPlugin {
+        Ok(LoadedPlugin {
             id: plugin_info.metadata.id,
             info: plugin_info,
             runtime,
             loaded_at: chrono::Utc::now(),
             execution_count: std::sync::atomic::AtomicU64::new(0),
             last_executed: Arc::new(RwLock::new(None)),
-        };
+        })
+    }
 
+    /// Make a prepared plugin available for execution
+    async fn register_plugin(&self, path: &Path, loaded_plugin: LoadedPlugin) -> Result<Uuid> {
         let plugin_id = loaded_plugin.id;
         self.plugins.insert(plugin_id, Arc::new(loaded_plugin));
 
@@ -127,9 +150,11 @@ impl PluginManager {
 
             // Remove hot-reload watcher
             let mut watchers = self.watchers.write().await;
-            if let Some(path) = self.find_plugin_path(plugin_id) {
-                watchers.remove(&path);
-            }
+            watchers.remove(&loaded_plugin.info.source_path);
+            drop(watchers);
+
+            self.configs.remove(plugin_id);
+            self.executions.remove(plugin_id);
 
             // Emit plugin unloaded event
             self.event_bus.emit(PluginEvent::Unloaded {
@@ -172,10 +197,17 @@ impl PluginManager {
             execution_id
         );
 
-        // Get plugin
+        // Get plugin; the map entry is not held across the execution so a reload can replace it
         let plugin = self.plugins.get(plugin_id)
+            .map(|plugin| Arc::clone(&plugin))
             .ok_or(PluginError::PluginNotFound(*plugin_id))?;
 
+        // Reject plugins disabled for exceeding their quota
+        self.ledger.check(plugin_id)?;
+
+        // Reject executions while the plugin is reloading
+        let execution = self.executions.start(plugin_id)?;
+
         // Create execution context
         let context = PluginContext {
             execution_id,
@@ -185,6 +217,7 @@ impl PluginManager {
             environment: self.create_environment().await,
             limits: limits.unwrap_or_else(|| self.config.default_limits.clone()),
             permissions: self.get_plugin_permissions(plugin_id).await?,
+            config: self.configs.get(plugin_id),
         };
 
         // Security validation
@@ -198,8 +231,17 @@ impl PluginManager {
             timestamp: chrono::Utc::now(),
         }).await;
 
-        // Execute plugin
-        let result = match plugin.runtime.execute(function, &context).await {
+        // Execute plugin, unless a reload cancels it first
+        let outcome = tokio::select! {
+            outcome = plugin.runtime.execute(function, &context) => outcome,
+            _ = execution.cancelled() => Err(PluginError::RuntimeError(format!(
+                "Execution {} was cancelled because plugin {} is being reloaded",
+                execution_id, plugin_id
+            ))),
+        };
+        drop(execution);
+
+        let result = match outcome {
             Ok(result) => {
                 plugin.execution_count.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
                 *plugin.last_executed.write().await = Some(chrono::Utc::now());
@@ -235,6 +277,11 @@ impl PluginManager {
             }
         };
 
+        // Charge usage; a plugin that crosses its quota is disabled for later executions
+        if let Err(e) = self.ledger.record(plugin_id, &ResourceUsage::from_execution(&result)) {
+            tracing::warn!("{}", e);
+        }
+
         // Emit execution completed event
         self.event_bus.emit(PluginEvent::ExecutionCompleted {
             plugin_id: *plugin_id,
@@ -284,6 +331,67 @@ impl PluginManager {
         })
     }
 
+    /// Get plugin resource usage for the current quota window
+    pub fn plugin_usage(&self, plugin_id: &Uuid) -> Result<UsageLedger> {
+        if !self.plugins.contains_key(plugin_id) {
+            return Err(PluginError::PluginNotFound(*plugin_id));
+        }
+
+        Ok(self.ledger.usage(plugin_id))
+    }
+
+    /// Override the resource quota for a plugin
+    pub fn set_resource_quota(&self, plugin_id: Uuid, quota: ResourceQuota) {
+        self.ledger.set_quota(plugin_id, quota);
+    }
+
+    /// Register the configuration passed to a plugin's executions; it is
+    /// re-applied when the plugin is reloaded
+    pub fn register_plugin_config(&self, plugin_id: &Uuid, config: PluginConfig) -> Result<()> {
+        if !self.plugins.contains_key(plugin_id) {
+            return Err(PluginError::PluginNotFound(*plugin_id));
+        }
+
+        self.configs.set(*plugin_id, config);
+        Ok(())
+    }
+
+    /// Configuration registered for a plugin, or the default one
+    pub fn plugin_config(&self, plugin_id: &Uuid) -> Result<PluginConfig> {
+        if !self.plugins.contains_key(plugin_id) {
+            return Err(PluginError::PluginNotFound(*plugin_id));
+        }
+
+        Ok(self.configs.get(plugin_id))
+    }
+
+    /// Language support plugins
+    pub fn languages(&self) -> &Arc<LanguageRegistry> {
+        &self.languages
+    }
+
+    /// Load a WASM language plugin and register its language
+    #[cfg(feature = "wasm-plugins")]
+    pub async fn load_language_plugin<P: AsRef<Path>>(&self, path: P) -> Result<crate::language::LanguageInfo> {
+        let path = path.as_ref();
+        tracing::info!("Loading language plugin from: {}", path.display());
+
+        self.security.validate_plugin_path(path).await?;
+
+        // Language plugins get no filesystem access; generated files are returned as data
+        let path = path.to_path_buf();
+        let limits = self.config.default_limits.clone();
+        let plugin = tokio::task::spawn_blocking(move || {
+            crate::language::WasmLanguagePlugin::load(path, &PluginPermissions::default(), limits)
+        })
+        .await
+        .map_err(|e| PluginError::RuntimeError(e.to_string()))??;
+
+        let info = plugin.info().clone();
+        self.languages.register(Arc::new(plugin))?;
+        Ok(info)
+    }
+
     /// Install plugin from marketplace
     pub async fn install_plugin(&self, plugin_name: &str, version: Option<&str>) -> Result<Uuid> {
         tracing::info!("Installing plugin from marketplace: {} {:?}", plugin_name, version);
@@ -323,6 +431,8 @@ impl PluginManager {
             }
         }
 
+        self.ledger.remove(plugin_id);
+
         // Mark as uninstalled in marketplace
         self.marketplace.mark_plugin_uninstalled(plugin_id).await?;
 
@@ -330,19 +440,42 @@ impl PluginManager {
         Ok(())
     }
 
-    /// Reload a plugin (hot-reload)
-    pub async fn reload_plugin(&self, plugin_id: &Uuid) -> Result<()> {
-        tracing::info!("Reloading plugin: {}", plugin_id);
+    /// Reload a plugin (hot-reload) from its artifact.
+    ///
+    /// The new artifact is loaded, validated and instantiated first; if any of
+    /// that fails the current instance keeps running untouched. Otherwise new
+    /// executions are rejected while the plugin is swapped; running ones
+    /// finish or are cancelled according to `strategy`. The registered
+    /// configuration is re-applied to the new instance. Returns the plugin's
+    /// ID after the reload, which changes if the artifact declares a new one.
+    pub async fn reload_plugin(&self, plugin_id: &Uuid, strategy: ReloadStrategy) -> Result<Uuid> {
+        tracing::info!("Reloading plugin: {} ({:?})", plugin_id, strategy);
 
         // Find plugin path
         let plugin_path = self.find_plugin_path(plugin_id)
             .ok_or(PluginError::PluginNotFound(*plugin_id))?;
+        let config = self.configs.contains(plugin_id).then(|| self.configs.get(plugin_id));
 
-        // Unload current plugin
-        self.unload_plugin(plugin_id).await?;
+        let replacement = match self.prepare_plugin(&plugin_path).await {
+            Ok(replacement) => replacement,
+            Err(e) => {
+                tracing::error!("Plugin {} failed to reload, keeping the running version: {}", plugin_id, e);
+                return Err(e);
+            }
+        };
 
-        // Load plugin again
-        let new_plugin_id = self.load_plugin(&plugin_path).await?;
+        if self.executions.quiesce(plugin_id, strategy).await {
+            tracing::warn!("Cancelled in-flight executions of plugin {} for reload", plugin_id);
+        }
+
+        // The old instance is already out of the registry if its shutdown fails; the replacement takes over regardless
+        if let Err(e) = self.unload_plugin(plugin_id).await {
+            tracing::warn!("Previous instance of plugin {} did not shut down cleanly: {}", plugin_id, e);
+            // Reopen for executions, which the quiesce above closed
+            self.executions.remove(plugin_id);
+        }
+
+        let new_plugin_id = self.register_plugin(&plugin_path, replacement).await?;
 
         if new_plugin_id != *plugin_id {
             tracing::warn!(
@@ -352,8 +485,12 @@ impl PluginManager {
             );
         }
 
-        tracing::info!("Plugin reloaded successfully: {}", plugin_id);
-        Ok(())
+        if let Some(config) = config {
+            self.configs.set(new_plugin_id, config);
+        }
+
+        tracing::info!("Plugin reloaded successfully: {}", new_plugin_id);
+        Ok(new_plugin_id)
     }
 
     /// Subscribe to plugin events
@@ -446,7 +583,7 @@ impl PluginManager {
                 if let Some(manager) = manager.upgrade() {
                     tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
 
-                    if let Err(e) = manager.reload_plugin(&plugin_id).await {
+                    if let Err(e) = manager.reload_plugin(&plugin_id, ReloadStrategy::default()).await {
                         tracing::error!("Hot-reload failed for plugin {}: {}", plugin_id, e);
                     }
                 }
@@ -503,6 +640,61 @@ impl Clone for PluginManager {
             marketplace: Arc::clone(&self.marketplace),
             config: Arc::clone(&self.config),
             watchers: Arc::clone(&self.watchers),
+            ledger: Arc::clone(&self.ledger),
+            languages: Arc::clone(&self.languages),
+            configs: Arc::clone(&self.configs),
+            executions: Arc::clone(&self.executions),
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `ok` answers `{"ok":{"done":true}}`
+    const PLUGIN_WAT: &str = r#"
+        (module
+          (memory (export "memory") 1)
+          (global $next (mut i32) (i32.const 4096))
+          (data (i32.const 2048) "{\"ok\":{\"done\":true}}")
+          (func (export "aion_alloc") (param $len i32) (result i32)
+            (local $ptr i32)
+            (local.set $ptr (global.get $next))
+            (global.set $next (i32.add (global.get $next) (local.get $len)))
+            (local.get $ptr))
+          (func (export "aion_dealloc") (param i32 i32))
+          (func (export "ok") (param i32 i32) (result i64)
+            (i64.or (i64.shl (i64.const 2048) (i64.const 32)) (i64.const 20))))
+    "#;
+
+    async fn manager() -> PluginManager {
+        let mut config = PluginSystemConfig::default();
+        config.plugin_directories = Vec::new();
+        config.hot_reload.enabled = false;
+        PluginManager::new_with_config(config).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn failed_reload_keeps_the_running_plugin() {
+        let dir = std::env::temp_dir().join(format!("aion-plugin-reload-{}", Uuid::new_v4()));
+        tokio::fs::create_dir_all(&dir).await.unwrap();
+        let path = dir.join("plugin.wasm");
+        tokio::fs::write(&path, PLUGIN_WAT).await.unwrap();
+
+        let manager = manager().await;
+        let plugin_id = manager.load_plugin(&path).await.unwrap();
+        manager.register_plugin_config(&plugin_id, PluginConfig::default()).unwrap();
+
+        // A broken build lands on disk
+        tokio::fs::write(&path, b"not a wasm module").await.unwrap();
+        assert!(manager.reload_plugin(&plugin_id, ReloadStrategy::Cancel).await.is_err());
+
+        assert!(manager.get_plugin_info(&plugin_id).await.is_ok());
+        assert!(manager.plugin_config(&plugin_id).is_ok());
+        let result = manager.execute_plugin(&plugin_id, "ok", serde_json::json!({})).await.unwrap();
+        assert!(result.success, "{:?}", result.error);
+
+        tokio::fs::remove_dir_all(&dir).await.ok();
+    }
+}
@@ -0,0 +1,261 @@
+//! # Plugin Hot Reload
+//!
+//! State that has to survive a plugin being unloaded and loaded again: the
+//! configuration registered for it, and the executions still running in the
+//! old instance when a reload starts. [`ReloadStrategy`] decides whether
+//! those executions are allowed to finish or are cancelled.
+
+use crate::errors::{PluginError, Result};
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Notify;
+use uuid::Uuid;
+
+/// Configuration registered for a plugin; passed to every execution and
+/// re-applied when the plugin is reloaded
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PluginConfig {
+    pub enabled: bool,
+    pub options: HashMap<String, serde_json::Value>,
+    pub custom_settings: Option<HashMap<String, serde_json::Value>>,
+}
+
+impl Default for PluginConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            options: HashMap::new(),
+            custom_settings: None,
+        }
+    }
+}
+
+/// What happens to a plugin's in-flight executions when it is reloaded
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReloadStrategy {
+    /// Let executions finish, cancelling those still running after `timeout_seconds`
+    Drain { timeout_seconds: u64 },
+    /// Cancel executions immediately
+    Cancel,
+}
+
+impl Default for ReloadStrategy {
+    fn default() -> Self {
+        ReloadStrategy::Drain { timeout_seconds: 30 }
+    }
+}
+
+/// In-flight executions of one plugin instance
+#[derive(Debug, Default)]
+struct PluginExecutions {
+    active: AtomicUsize,
+    /// Set once a reload starts; no new executions are admitted
+    closed: AtomicBool,
+    cancelled: AtomicBool,
+    changed: Notify,
+}
+
+impl PluginExecutions {
+    fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+        self.changed.notify_waiters();
+    }
+
+    async fn idle(&self) {
+        loop {
+            // Created before the check so a notification in between is not lost
+            let changed = self.changed.notified();
+            if self.active.load(Ordering::SeqCst) == 0 {
+                return;
+            }
+            changed.await;
+        }
+    }
+}
+
+/// Tracks in-flight executions per plugin so a reload can drain or cancel them
+#[derive(Debug, Default)]
+pub struct ExecutionTracker {
+    plugins: DashMap<Uuid, Arc<PluginExecutions>>,
+}
+
+impl ExecutionTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register an execution; fails while the plugin is being reloaded
+    pub fn start(&self, plugin_id: &Uuid) -> Result<ExecutionGuard> {
+        let executions = Arc::clone(&self.plugins.entry(*plugin_id).or_default());
+        executions.active.fetch_add(1, Ordering::SeqCst);
+        if executions.closed.load(Ordering::SeqCst) {
+            drop(ExecutionGuard { executions });
+            return Err(PluginError::RuntimeError(format!(
+                "Plugin {} is being reloaded; retry the execution once it is loaded again",
+                plugin_id
+            )));
+        }
+        Ok(ExecutionGuard { executions })
+    }
+
+    /// Number of executions of the plugin still running
+    pub fn active(&self, plugin_id: &Uuid) -> usize {
+        self.plugins
+            .get(plugin_id)
+            .map(|executions| executions.active.load(Ordering::SeqCst))
+            .unwrap_or(0)
+    }
+
+    /// Stop admitting executions of the plugin, then wait for or cancel the
+    /// running ones as `strategy` says. Returns once none are left, with
+    /// whether any had to be cancelled.
+    pub async fn quiesce(&self, plugin_id: &Uuid, strategy: ReloadStrategy) -> bool {
+        let executions = Arc::clone(&self.plugins.entry(*plugin_id).or_default());
+        executions.closed.store(true, Ordering::SeqCst);
+
+        let cancelled = match strategy {
+            ReloadStrategy::Drain { timeout_seconds } => {
+                tokio::time::timeout(Duration::from_secs(timeout_seconds), executions.idle())
+                    .await
+                    .is_err()
+            }
+            ReloadStrategy::Cancel => executions.active.load(Ordering::SeqCst) > 0,
+        };
+        if cancelled {
+            executions.cancel();
+        }
+        executions.idle().await;
+        cancelled
+    }
+
+    /// Forget the plugin, e.g. once it is unloaded
+    pub fn remove(&self, plugin_id: &Uuid) {
+        self.plugins.remove(plugin_id);
+    }
+}
+
+/// Marks an execution as in flight until dropped
+#[derive(Debug)]
+pub struct ExecutionGuard {
+    executions: Arc<PluginExecutions>,
+}
+
+impl ExecutionGuard {
+    /// Resolves when a reload cancels this execution
+    pub async fn cancelled(&self) {
+        loop {
+            let changed = self.executions.changed.notified();
+            if self.executions.cancelled.load(Ordering::SeqCst) {
+                return;
+            }
+            changed.await;
+        }
+    }
+}
+
+impl Drop for ExecutionGuard {
+    fn drop(&mut self) {
+        if self.executions.active.fetch_sub(1, Ordering::SeqCst) == 1 {
+            self.executions.changed.notify_waiters();
+        }
+    }
+}
+
+/// Registered plugin configurations
+#[derive(Debug, Default)]
+pub struct PluginConfigs {
+    configs: DashMap<Uuid, PluginConfig>,
+}
+
+impl PluginConfigs {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set(&self, plugin_id: Uuid, config: PluginConfig) {
+        self.configs.insert(plugin_id, config);
+    }
+
+    /// The registered configuration, or the default one
+    pub fn get(&self, plugin_id: &Uuid) -> PluginConfig {
+        self.configs.get(plugin_id).map(|config| config.clone()).unwrap_or_default()
+    }
+
+    pub fn contains(&self, plugin_id: &Uuid) -> bool {
+        self.configs.contains_key(plugin_id)
+    }
+
+    pub fn remove(&self, plugin_id: &Uuid) -> Option<PluginConfig> {
+        self.configs.remove(plugin_id).map(|(_, config)| config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn drain_waits_for_running_executions() {
+        let tracker = ExecutionTracker::new();
+        let plugin_id = Uuid::new_v4();
+        let guard = tracker.start(&plugin_id).unwrap();
+
+        let finish = tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            drop(guard);
+        });
+        let cancelled = tracker.quiesce(&plugin_id, ReloadStrategy::Drain { timeout_seconds: 5 }).await;
+        assert!(!cancelled);
+        assert_eq!(tracker.active(&plugin_id), 0);
+        finish.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn cancel_stops_running_executions() {
+        let tracker = ExecutionTracker::new();
+        let plugin_id = Uuid::new_v4();
+        let guard = tracker.start(&plugin_id).unwrap();
+
+        // An execution that would never finish on its own
+        let execution = tokio::spawn(async move {
+            tokio::select! {
+                _ = std::future::pending::<()>() => false,
+                _ = guard.cancelled() => true,
+            }
+        });
+        assert!(tracker.quiesce(&plugin_id, ReloadStrategy::Cancel).await);
+        assert!(execution.await.unwrap());
+        assert_eq!(tracker.active(&plugin_id), 0);
+    }
+
+    #[tokio::test]
+    async fn drain_cancels_executions_past_the_timeout() {
+        let tracker = ExecutionTracker::new();
+        let plugin_id = Uuid::new_v4();
+        let guard = tracker.start(&plugin_id).unwrap();
+
+        let execution = async move {
+            guard.cancelled().await;
+        };
+        let (cancelled, ()) =
+            tokio::join!(tracker.quiesce(&plugin_id, ReloadStrategy::Drain { timeout_seconds: 0 }), execution);
+        assert!(cancelled);
+    }
+
+    #[tokio::test]
+    async fn rejects_executions_during_reload() {
+        let tracker = ExecutionTracker::new();
+        let plugin_id = Uuid::new_v4();
+        tracker.quiesce(&plugin_id, ReloadStrategy::Cancel).await;
+        tracker.start(&plugin_id).unwrap_err();
+
+        // A reloaded plugin starts with a fresh entry
+        tracker.remove(&plugin_id);
+        assert!(tracker.start(&plugin_id).is_ok());
+    }
+}
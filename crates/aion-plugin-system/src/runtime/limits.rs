@@ -0,0 +1,75 @@
+//! # WASM Resource Limits
+//!
+//! Store limiter enforcing `ExecutionLimits::max_memory_bytes`. Unlike
+//! `wasmtime::StoreLimits`, which only makes `memory.grow` return -1, growing
+//! past the limit traps, so a runaway plugin is stopped at the allocation
+//! that crosses it. The limiter also keeps the high-water mark reported in
+//! [`MemoryUsage`].
+
+use crate::MemoryUsage;
+
+/// Memory limiter and accounting for one plugin store
+#[derive(Debug, Clone)]
+pub struct PluginLimiter {
+    memory_limit: usize,
+    current_bytes: usize,
+    peak_bytes: usize,
+    allocations: usize,
+    exceeded: Option<usize>,
+}
+
+impl PluginLimiter {
+    /// Limit the plugin's linear memories to `memory_limit` bytes in total
+    pub fn new(memory_limit: usize) -> Self {
+        Self {
+            memory_limit,
+            current_bytes: 0,
+            peak_bytes: 0,
+            allocations: 0,
+            exceeded: None,
+        }
+    }
+
+    pub fn memory_limit(&self) -> usize {
+        self.memory_limit
+    }
+
+    /// Size the plugin tried to grow its memory to when it hit the limit
+    pub fn exceeded(&self) -> Option<usize> {
+        self.exceeded
+    }
+
+    /// Memory statistics so far: peak and current linear memory size, and
+    /// the number of successful allocations and growths
+    pub fn usage(&self) -> MemoryUsage {
+        MemoryUsage {
+            peak_bytes: self.peak_bytes,
+            final_bytes: self.current_bytes,
+            allocations: self.allocations,
+        }
+    }
+}
+
+impl wasmtime::ResourceLimiter for PluginLimiter {
+    fn memory_growing(&mut self, current: usize, desired: usize, _maximum: Option<usize>) -> anyhow::Result<bool> {
+        let total = self.current_bytes.saturating_sub(current).saturating_add(desired);
+        if total > self.memory_limit {
+            self.exceeded = Some(total);
+            anyhow::bail!(
+                "plugin memory would grow to {} bytes, over its limit of {} bytes",
+                total,
+                self.memory_limit
+            );
+        }
+
+        self.current_bytes = total;
+        self.peak_bytes = self.peak_bytes.max(total);
+        self.allocations += 1;
+        Ok(true)
+    }
+
+    fn table_growing(&mut self, _current: u32, _desired: u32, _maximum: Option<u32>) -> anyhow::Result<bool> {
+        // Tables are bounded by the module's declared maximum
+        Ok(true)
+    }
+}
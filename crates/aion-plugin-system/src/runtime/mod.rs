@@ -0,0 +1,13 @@
+//! # Plugin Runtimes
+
+#[cfg(feature = "wasm-plugins")]
+pub mod limits;
+pub mod sandbox;
+#[cfg(feature = "wasm-plugins")]
+pub mod wasm;
+
+#[cfg(feature = "wasm-plugins")]
+pub use limits::*;
+pub use sandbox::*;
+#[cfg(feature = "wasm-plugins")]
+pub use wasm::WasmPluginRuntime;
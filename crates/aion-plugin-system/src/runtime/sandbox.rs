@@ -0,0 +1,319 @@
+//! # WASI Filesystem Sandbox
+//!
+//! Maps a plugin's filesystem grants to WASI preopened directories. A WASM
+//! plugin has no ambient filesystem access: it can only reach files through a
+//! preopen, and every lookup beneath a preopen is resolved by `cap-std`, which
+//! rejects absolute paths, `..` past the preopen root and symlinks that lead
+//! outside it. Escaping the mapped directory is therefore not a matter of path
+//! validation in this crate but impossible by construction.
+
+use crate::errors::{PluginError, Result};
+use crate::security::{FilesystemAccess, PluginPermissions};
+use std::path::{Component, Path, PathBuf};
+
+/// A host directory preopened for a plugin
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Preopen {
+    /// Canonical host directory
+    pub host_root: PathBuf,
+    /// Path the plugin sees
+    pub guest_path: String,
+    pub access: FilesystemAccess,
+}
+
+/// Filesystem view of a WASM plugin, derived from its granted capabilities
+#[derive(Debug, Clone, Default)]
+pub struct WasiSandbox {
+    preopens: Vec<Preopen>,
+}
+
+impl WasiSandbox {
+    /// Build the sandbox from a plugin's filesystem grants
+    pub fn from_permissions(permissions: &PluginPermissions) -> Result<Self> {
+        let mut preopens: Vec<Preopen> = Vec::with_capacity(permissions.filesystem.len());
+
+        for grant in &permissions.filesystem {
+            validate_guest_path(&grant.guest_path)?;
+            if preopens.iter().any(|p| p.guest_path == grant.guest_path) {
+                return Err(PluginError::SecurityViolation(format!(
+                    "Guest path {} is granted more than once",
+                    grant.guest_path
+                )));
+            }
+
+            // Canonicalize so a symlinked grant is pinned to its target at load time
+            let host_root = grant.host_path.canonicalize().map_err(|e| {
+                PluginError::SecurityViolation(format!(
+                    "Filesystem grant {} is not accessible: {}",
+                    grant.host_path.display(),
+                    e
+                ))
+            })?;
+            if !host_root.is_dir() {
+                return Err(PluginError::SecurityViolation(format!(
+                    "Filesystem grant {} is not a directory",
+                    grant.host_path.display()
+                )));
+            }
+
+            preopens.push(Preopen {
+                host_root,
+                guest_path: grant.guest_path.clone(),
+                access: grant.access,
+            });
+        }
+
+        Ok(Self { preopens })
+    }
+
+    pub fn preopens(&self) -> &[Preopen] {
+        &self.preopens
+    }
+
+    /// Build a WASI context exposing only the granted directories
+    #[cfg(feature = "wasm-plugins")]
+    pub fn build_context(&self) -> Result<WasiState> {
+        use wasmtime_wasi::preview2::{DirPerms, FilePerms, Table, WasiCtxBuilder};
+        use wasmtime_wasi::preview2::preview1::WasiPreview1Adapter;
+        use wasmtime_wasi::sync::{ambient_authority, Dir};
+
+        let mut builder = WasiCtxBuilder::new();
+        for preopen in &self.preopens {
+            let dir = Dir::open_ambient_dir(&preopen.host_root, ambient_authority())?;
+            let (dir_perms, file_perms) = match preopen.access {
+                FilesystemAccess::ReadOnly => (DirPerms::READ, FilePerms::READ),
+                FilesystemAccess::ReadWrite => (DirPerms::all(), FilePerms::all()),
+            };
+            builder.preopened_dir(dir, dir_perms, file_perms, &preopen.guest_path);
+        }
+
+        Ok(WasiState {
+            table: Table::new(),
+            wasi: builder.build(),
+            adapter: WasiPreview1Adapter::new(),
+            limits: super::PluginLimiter::new(crate::MAX_PLUGIN_MEMORY),
+        })
+    }
+}
+
+/// Store data for a sandboxed WASM plugin
+#[cfg(feature = "wasm-plugins")]
+pub struct WasiState {
+    table: wasmtime_wasi::preview2::Table,
+    wasi: wasmtime_wasi::preview2::WasiCtx,
+    adapter: wasmtime_wasi::preview2::preview1::WasiPreview1Adapter,
+    limits: super::PluginLimiter,
+}
+
+#[cfg(feature = "wasm-plugins")]
+impl WasiState {
+    /// Register the `wasi_snapshot_preview1` imports plugins link against
+    pub fn add_to_linker(linker: &mut wasmtime::Linker<WasiState>) -> Result<()> {
+        wasmtime_wasi::preview2::preview1::add_to_linker_sync(linker)?;
+        Ok(())
+    }
+
+    /// Cap the plugin's linear memory at `bytes`
+    pub fn set_memory_limit(&mut self, bytes: usize) {
+        self.limits = super::PluginLimiter::new(bytes.min(crate::MAX_PLUGIN_MEMORY));
+    }
+
+    /// Memory limit and usage of the plugin
+    pub fn limits(&self) -> &super::PluginLimiter {
+        &self.limits
+    }
+
+    /// Limits to install with `Store::limiter`
+    pub fn limits_mut(&mut self) -> &mut super::PluginLimiter {
+        &mut self.limits
+    }
+}
+
+#[cfg(feature = "wasm-plugins")]
+impl wasmtime_wasi::preview2::WasiView for WasiState {
+    fn table(&self) -> &wasmtime_wasi::preview2::Table {
+        &self.table
+    }
+
+    fn table_mut(&mut self) -> &mut wasmtime_wasi::preview2::Table {
+        &mut self.table
+    }
+
+    fn ctx(&self) -> &wasmtime_wasi::preview2::WasiCtx {
+        &self.wasi
+    }
+
+    fn ctx_mut(&mut self) -> &mut wasmtime_wasi::preview2::WasiCtx {
+        &mut self.wasi
+    }
+}
+
+#[cfg(feature = "wasm-plugins")]
+impl wasmtime_wasi::preview2::preview1::WasiPreview1View for WasiState {
+    fn adapter(&self) -> &wasmtime_wasi::preview2::preview1::WasiPreview1Adapter {
+        &self.adapter
+    }
+
+    fn adapter_mut(&mut self) -> &mut wasmtime_wasi::preview2::preview1::WasiPreview1Adapter {
+        &mut self.adapter
+    }
+}
+
+/// Guest paths must be absolute and normalized, e.g. `/project`
+fn validate_guest_path(guest_path: &str) -> Result<()> {
+    let path = Path::new(guest_path);
+    let normalized = path.is_absolute()
+        && path.components().skip(1).all(|c| matches!(c, Component::Normal(_)))
+        && path.components().count() > 1;
+
+    if normalized {
+        Ok(())
+    } else {
+        Err(PluginError::SecurityViolation(format!(
+            "Invalid guest path for filesystem grant: {}",
+            guest_path
+        )))
+    }
+}
+
+#[cfg(all(test, feature = "wasm-plugins"))]
+mod tests {
+    use super::*;
+    use crate::security::FilesystemGrant;
+    use wasmtime::{Engine, Instance, Linker, Module, Store};
+
+    const ERRNO_SUCCESS: i32 = 0;
+    const RIGHT_FD_READ: i64 = 1 << 1;
+    const RIGHT_FD_WRITE: i64 = 1 << 6;
+    const OFLAGS_CREAT: i32 = 1;
+    const PATH_OFFSET: usize = 1024;
+
+    // Opens a path relative to the first preopen (fd 3) and returns the WASI errno
+    const PLUGIN_WAT: &str = r#"
+        (module
+          (import "wasi_snapshot_preview1" "path_open"
+            (func $path_open (param i32 i32 i32 i32 i32 i64 i64 i32 i32) (result i32)))
+          (memory (export "memory") 1)
+          (func (export "open") (param $ptr i32) (param $len i32) (param $oflags i32) (param $rights i64) (result i32)
+            (call $path_open
+              (i32.const 3) (i32.const 1)
+              (local.get $ptr) (local.get $len)
+              (local.get $oflags) (local.get $rights) (i64.const 0)
+              (i32.const 0) (i32.const 0))))
+    "#;
+
+    struct Fixture {
+        _root: tempfile::TempDir,
+        project: PathBuf,
+        outside: PathBuf,
+    }
+
+    // <root>/secret and <root>/work/project/src/main.rs
+    fn fixture() -> Fixture {
+        let root = tempfile::tempdir().unwrap();
+        let project = root.path().join("work").join("project");
+        std::fs::create_dir_all(project.join("src")).unwrap();
+        std::fs::write(project.join("src").join("main.rs"), "fn main() {}").unwrap();
+        let outside = root.path().join("secret");
+        std::fs::write(&outside, "top secret").unwrap();
+
+        Fixture {
+            project,
+            outside,
+            _root: root,
+        }
+    }
+
+    fn open(grant: FilesystemGrant, path: &str, oflags: i32, rights: i64) -> i32 {
+        let permissions = PluginPermissions {
+            filesystem: vec![grant],
+            ..Default::default()
+        };
+        let sandbox = WasiSandbox::from_permissions(&permissions).unwrap();
+
+        let engine = Engine::default();
+        let mut linker = Linker::new(&engine);
+        WasiState::add_to_linker(&mut linker).unwrap();
+        let mut store = Store::new(&engine, sandbox.build_context().unwrap());
+        let module = Module::new(&engine, PLUGIN_WAT).unwrap();
+        let instance: Instance = linker.instantiate(&mut store, &module).unwrap();
+
+        let memory = instance.get_memory(&mut store, "memory").unwrap();
+        memory.write(&mut store, PATH_OFFSET, path.as_bytes()).unwrap();
+        let open = instance
+            .get_typed_func::<(i32, i32, i32, i64), i32>(&mut store, "open")
+            .unwrap();
+        open.call(&mut store, (PATH_OFFSET as i32, path.len() as i32, oflags, rights))
+            .unwrap()
+    }
+
+    #[test]
+    fn reads_granted_project_file() {
+        let fixture = fixture();
+        let grant = FilesystemGrant::read_only(&fixture.project, "/project");
+        assert_eq!(open(grant, "src/main.rs", 0, RIGHT_FD_READ), ERRNO_SUCCESS);
+    }
+
+    #[test]
+    fn rejects_absolute_host_paths() {
+        let fixture = fixture();
+        let grant = FilesystemGrant::read_write(&fixture.project, "/project");
+        assert_ne!(open(grant, "/etc/passwd", 0, RIGHT_FD_READ), ERRNO_SUCCESS);
+    }
+
+    #[test]
+    fn rejects_traversal_out_of_preopen() {
+        let fixture = fixture();
+        let grant = FilesystemGrant::read_write(&fixture.project, "/project");
+        assert_ne!(open(grant, "../../secret", 0, RIGHT_FD_READ), ERRNO_SUCCESS);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn rejects_symlink_escape() {
+        let fixture = fixture();
+        std::os::unix::fs::symlink(&fixture.outside, fixture.project.join("escape")).unwrap();
+        let grant = FilesystemGrant::read_write(&fixture.project, "/project");
+        assert_ne!(open(grant, "escape", 0, RIGHT_FD_READ), ERRNO_SUCCESS);
+    }
+
+    #[test]
+    fn read_only_grant_refuses_writes() {
+        let fixture = fixture();
+        let grant = FilesystemGrant::read_only(&fixture.project, "/project");
+        assert_ne!(
+            open(grant, "generated.rs", OFLAGS_CREAT, RIGHT_FD_WRITE),
+            ERRNO_SUCCESS
+        );
+        assert!(!fixture.project.join("generated.rs").exists());
+    }
+
+    #[test]
+    fn read_write_grant_allows_writes() {
+        let fixture = fixture();
+        let grant = FilesystemGrant::read_write(&fixture.project, "/project");
+        assert_eq!(
+            open(grant, "generated.rs", OFLAGS_CREAT, RIGHT_FD_WRITE),
+            ERRNO_SUCCESS
+        );
+        assert!(fixture.project.join("generated.rs").exists());
+    }
+
+    #[test]
+    fn rejects_invalid_grants() {
+        let fixture = fixture();
+        for grant in [
+            FilesystemGrant::read_only(&fixture.project, "project"),
+            FilesystemGrant::read_only(&fixture.project, "/project/../etc"),
+            FilesystemGrant::read_only(&fixture.project, "/"),
+            FilesystemGrant::read_only(fixture.project.join("missing"), "/project"),
+            FilesystemGrant::read_only(&fixture.outside, "/project"),
+        ] {
+            let permissions = PluginPermissions {
+                filesystem: vec![grant],
+                ..Default::default()
+            };
+            assert!(WasiSandbox::from_permissions(&permissions).is_err());
+        }
+    }
+}
@@ -0,0 +1,338 @@
+//! # WASM Plugin Runtime
+//!
+//! Executes functions of a general WASM plugin with its `ExecutionLimits`
+//! enforced by the engine rather than trusted to the plugin:
+//!
+//! - `timeout_seconds` sets an epoch deadline. A background thread advances
+//!   the engine epoch every [`EPOCH_TICK`], and a plugin still running when
+//!   its deadline passes traps at the next loop header or function entry.
+//! - `max_memory_bytes` is enforced by [`PluginLimiter`](super::PluginLimiter), which traps the
+//!   allocation that would cross it.
+//!
+//! Either way the call is aborted, its store is dropped and the execution
+//! reports `success: false` with the limit that stopped it; the host and
+//! other executions are unaffected. Language plugins run under the same
+//! limits through [`EpochEngine`].
+//!
+//! A plugin function follows the language plugin buffer convention: it takes
+//! the JSON input as `(ptr: i32, len: i32)`, written through `aion_alloc`,
+//! and returns a `{"ok": ...}` / `{"error": ...}` envelope packed as
+//! `(ptr << 32) | len`.
+
+use super::{WasiSandbox, WasiState};
+use crate::errors::{PluginError, Result};
+use crate::language::Envelope;
+use crate::{ExecutionLimits, MemoryUsage, PluginContext, PluginExecutionResult, PluginPermissions, MAX_PLUGIN_EXECUTION_TIMEOUT};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+use wasmtime::{Config, Engine, Instance, Linker, Module, Store, Trap};
+
+/// Interval between epoch increments; the resolution of timeouts
+pub const EPOCH_TICK: Duration = Duration::from_millis(10);
+
+/// Largest response a plugin function may return
+const MAX_RESPONSE_BYTES: usize = 16 * 1024 * 1024;
+
+/// A general plugin loaded from a WASM module
+pub struct WasmPluginRuntime {
+    engine: EpochEngine,
+    module: Module,
+    linker: Linker<WasiState>,
+    sandbox: WasiSandbox,
+}
+
+impl WasmPluginRuntime {
+    /// Load a plugin module from disk
+    pub fn load(path: impl AsRef<Path>, permissions: &PluginPermissions) -> Result<Self> {
+        let bytes = std::fs::read(path.as_ref())?;
+        Self::from_bytes(&bytes, permissions)
+    }
+
+    /// Load a plugin from module bytes (binary or text format)
+    pub fn from_bytes(bytes: &[u8], permissions: &PluginPermissions) -> Result<Self> {
+        let engine = EpochEngine::new()?;
+        let module = Module::new(engine.engine(), bytes)?;
+
+        let mut linker = Linker::new(engine.engine());
+        WasiState::add_to_linker(&mut linker)?;
+
+        Ok(Self {
+            engine,
+            module,
+            linker,
+            sandbox: WasiSandbox::from_permissions(permissions)?,
+        })
+    }
+
+    /// Run `function` in a fresh instance with `context.input` as its input.
+    ///
+    /// Blocks until the function returns or a limit stops it; call it from a
+    /// blocking task in async code.
+    pub fn execute(&self, function: &str, context: &PluginContext) -> PluginExecutionResult {
+        let started = Instant::now();
+        let mut store = None;
+        let outcome = self.run(function, context, &mut store);
+        let memory_usage = store.as_ref().map(|store| store.data().limits().usage()).unwrap_or(MemoryUsage {
+            peak_bytes: 0,
+            final_bytes: 0,
+            allocations: 0,
+        });
+
+        if let Err(error) = &outcome {
+            tracing::warn!("Plugin {} function {} failed: {}", context.plugin_id, function, error);
+        }
+        let (result, error) = match outcome {
+            Ok(output) => (Some(output), None),
+            Err(error) => (None, Some(error.to_string())),
+        };
+
+        PluginExecutionResult {
+            execution_id: context.execution_id,
+            plugin_id: context.plugin_id,
+            success: error.is_none(),
+            result,
+            error,
+            duration_ms: started.elapsed().as_millis() as u64,
+            memory_usage,
+            generated_files: Vec::new(),
+            logs: Vec::new(),
+        }
+    }
+
+    /// The store is handed back through `slot` so usage can be read after a failure
+    fn run(&self, function: &str, context: &PluginContext, slot: &mut Option<Store<WasiState>>) -> Result<serde_json::Value> {
+        let limits = &context.limits;
+        let store = slot.insert(self.engine.store(&self.sandbox, limits)?);
+        let instance = self
+            .linker
+            .instantiate(&mut *store, &self.module)
+            .map_err(|error| failure(error, store, limits))?;
+        let input = serde_json::to_vec(&context.input)?;
+        let response = call(store, &instance, function, &input).map_err(|error| failure(error, store, limits))?;
+
+        serde_json::from_slice::<Envelope<serde_json::Value>>(&response)?.into_result()
+    }
+}
+
+/// Engine that interrupts plugins by epoch, with the thread advancing it
+pub(crate) struct EpochEngine {
+    engine: Engine,
+    _ticker: EpochTicker,
+}
+
+impl EpochEngine {
+    pub(crate) fn new() -> Result<Self> {
+        let mut config = Config::new();
+        config.epoch_interruption(true);
+        let engine = Engine::new(&config)?;
+        Ok(Self {
+            _ticker: EpochTicker::start(engine.clone()),
+            engine,
+        })
+    }
+
+    pub(crate) fn engine(&self) -> &Engine {
+        &self.engine
+    }
+
+    /// Store for one execution, sandboxed by `sandbox` and stopped once it
+    /// exceeds the timeout or memory limit in `limits`
+    pub(crate) fn store(&self, sandbox: &WasiSandbox, limits: &ExecutionLimits) -> Result<Store<WasiState>> {
+        let mut state = sandbox.build_context()?;
+        state.set_memory_limit(limits.max_memory_bytes);
+
+        let mut store = Store::new(&self.engine, state);
+        store.limiter(|state| state.limits_mut());
+        store.epoch_deadline_trap();
+        store.set_epoch_deadline(deadline_ticks(limits));
+        Ok(store)
+    }
+}
+
+/// Epoch ticks allowed for one execution
+fn deadline_ticks(limits: &ExecutionLimits) -> u64 {
+    let timeout = Duration::from_secs(limits.timeout_seconds.clamp(1, MAX_PLUGIN_EXECUTION_TIMEOUT));
+    (timeout.as_millis() / EPOCH_TICK.as_millis()).max(1) as u64
+}
+
+/// Call a plugin function and copy out its response
+fn call(store: &mut Store<WasiState>, instance: &Instance, function: &str, input: &[u8]) -> anyhow::Result<Vec<u8>> {
+    let memory = instance
+        .get_memory(&mut *store, "memory")
+        .ok_or_else(|| anyhow::anyhow!("plugin does not export memory"))?;
+    let alloc = instance.get_typed_func::<i32, i32>(&mut *store, "aion_alloc")?;
+    let dealloc = instance.get_typed_func::<(i32, i32), ()>(&mut *store, "aion_dealloc")?;
+    let entry = instance.get_typed_func::<(i32, i32), i64>(&mut *store, function)?;
+
+    let len = i32::try_from(input.len()).map_err(|_| anyhow::anyhow!("{} input is too large", function))?;
+    let ptr = alloc.call(&mut *store, len)?;
+    memory.write(&mut *store, ptr as u32 as usize, input)?;
+    let packed = entry.call(&mut *store, (ptr, len))?;
+    dealloc.call(&mut *store, (ptr, len))?;
+
+    let (ptr, len) = ((packed as u64 >> 32) as usize, (packed as u64 & 0xffff_ffff) as usize);
+    if len > MAX_RESPONSE_BYTES {
+        anyhow::bail!("{} response of {} bytes is too large", function, len);
+    }
+    let response = memory
+        .data(&*store)
+        .get(ptr..ptr + len)
+        .ok_or_else(|| anyhow::anyhow!("{} returned a response outside its memory", function))?
+        .to_vec();
+    dealloc.call(&mut *store, (ptr as i32, len as i32))?;
+    Ok(response)
+}
+
+/// Name the limit that stopped a failed call, if any
+pub(crate) fn failure(error: anyhow::Error, store: &Store<WasiState>, limits: &ExecutionLimits) -> PluginError {
+    let limiter = store.data().limits();
+    if let Some(requested) = limiter.exceeded() {
+        return PluginError::LimitExceeded(format!(
+            "plugin tried to use {} bytes of memory, over its limit of {} bytes",
+            requested,
+            limiter.memory_limit()
+        ));
+    }
+    if matches!(error.downcast_ref::<Trap>(), Some(Trap::Interrupt)) {
+        return PluginError::LimitExceeded(format!(
+            "plugin was still running after its timeout of {} seconds",
+            limits.timeout_seconds.clamp(1, MAX_PLUGIN_EXECUTION_TIMEOUT)
+        ));
+    }
+    PluginError::RuntimeError(error.to_string())
+}
+
+/// Advances an engine's epoch every `EPOCH_TICK` until dropped
+struct EpochTicker {
+    stop: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl EpochTicker {
+    fn start(engine: Engine) -> Self {
+        let stop = Arc::new(AtomicBool::new(false));
+        let handle = std::thread::Builder::new()
+            .name("aion-plugin-epoch".to_string())
+            .spawn({
+                let stop = Arc::clone(&stop);
+                move || {
+                    while !stop.load(Ordering::Relaxed) {
+                        std::thread::sleep(EPOCH_TICK);
+                        engine.increment_epoch();
+                    }
+                }
+            })
+            .ok();
+        if handle.is_none() {
+            tracing::error!("Failed to start the plugin epoch thread; WASM plugin timeouts will not fire");
+        }
+        Self { stop, handle }
+    }
+}
+
+impl Drop for EpochTicker {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use uuid::Uuid;
+
+    const PAGE: usize = 64 * 1024;
+
+    /// `ok` answers `{"ok":{"done":true}}`, `grow` allocates a page at a
+    /// time forever and `spin` never returns
+    const PLUGIN_WAT: &str = r#"
+        (module
+          (memory (export "memory") 1)
+          (global $next (mut i32) (i32.const 4096))
+          (data (i32.const 2048) "{\"ok\":{\"done\":true}}")
+          (func (export "aion_alloc") (param $len i32) (result i32)
+            (local $ptr i32)
+            (local.set $ptr (global.get $next))
+            (global.set $next (i32.add (global.get $next) (local.get $len)))
+            (local.get $ptr))
+          (func (export "aion_dealloc") (param i32 i32))
+          (func (export "ok") (param i32 i32) (result i64)
+            (i64.or (i64.shl (i64.const 2048) (i64.const 32)) (i64.const 20)))
+          (func (export "grow") (param i32 i32) (result i64)
+            (loop $more
+              (drop (memory.grow (i32.const 1)))
+              (br $more))
+            (i64.const 0))
+          (func (export "spin") (param i32 i32) (result i64)
+            (loop $spin (br $spin))
+            (i64.const 0)))
+    "#;
+
+    fn context(limits: ExecutionLimits) -> PluginContext {
+        PluginContext {
+            execution_id: Uuid::new_v4(),
+            plugin_id: Uuid::new_v4(),
+            timestamp: chrono::Utc::now(),
+            input: serde_json::json!({ "task": "test" }),
+            environment: HashMap::new(),
+            limits,
+            permissions: PluginPermissions::default(),
+            config: Default::default(),
+        }
+    }
+
+    fn runtime() -> WasmPluginRuntime {
+        WasmPluginRuntime::from_bytes(PLUGIN_WAT.as_bytes(), &PluginPermissions::default()).unwrap()
+    }
+
+    #[test]
+    fn executes_plugin_function() {
+        let result = runtime().execute("ok", &context(ExecutionLimits::default()));
+        assert!(result.success, "{:?}", result.error);
+        assert_eq!(result.result, Some(serde_json::json!({ "done": true })));
+        assert_eq!(result.memory_usage.peak_bytes, PAGE);
+    }
+
+    #[test]
+    fn terminates_plugin_that_exceeds_memory_limit() {
+        let runtime = runtime();
+        let limits = ExecutionLimits {
+            max_memory_bytes: 4 * PAGE,
+            ..ExecutionLimits::default()
+        };
+
+        let result = runtime.execute("grow", &context(limits.clone()));
+        assert!(!result.success);
+        let error = result.error.unwrap();
+        assert!(error.contains("over its limit of 262144 bytes"), "{}", error);
+        assert_eq!(result.memory_usage.peak_bytes, 4 * PAGE);
+        assert!(result.result.is_none());
+
+        // The host and the runtime are unaffected
+        assert!(runtime.execute("ok", &context(limits)).success);
+    }
+
+    #[test]
+    fn terminates_plugin_that_exceeds_timeout() {
+        let runtime = runtime();
+        let limits = ExecutionLimits {
+            timeout_seconds: 1,
+            ..ExecutionLimits::default()
+        };
+
+        let result = runtime.execute("spin", &context(limits.clone()));
+        assert!(!result.success);
+        let error = result.error.unwrap();
+        assert!(error.contains("timeout of 1 seconds"), "{}", error);
+        assert!(result.duration_ms >= 1000 && result.duration_ms < 5000, "{}", result.duration_ms);
+
+        assert!(runtime.execute("ok", &context(limits)).success);
+    }
+}
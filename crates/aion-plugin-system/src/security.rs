@@ -0,0 +1,55 @@
+//! # Plugin Security
+//!
+//! Capabilities granted to a plugin. Runtimes derive their sandbox from these
+//! grants; anything not granted is unavailable to the plugin.
+
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+/// Capabilities granted to a plugin execution
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PluginPermissions {
+    /// Host directories mapped into the plugin's filesystem
+    pub filesystem: Vec<FilesystemGrant>,
+    /// Outbound network access
+    pub network_access: bool,
+    /// Read access to execution environment variables
+    pub environment_access: bool,
+}
+
+/// A host directory exposed to a plugin under a guest path
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FilesystemGrant {
+    /// Directory on the host, e.g. the project path
+    pub host_path: PathBuf,
+    /// Absolute path the plugin sees, e.g. `/project`
+    pub guest_path: String,
+    /// Access level
+    pub access: FilesystemAccess,
+}
+
+/// Access level for a filesystem grant
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FilesystemAccess {
+    ReadOnly,
+    ReadWrite,
+}
+
+impl FilesystemGrant {
+    pub fn read_only(host_path: impl Into<PathBuf>, guest_path: impl Into<String>) -> Self {
+        Self {
+            host_path: host_path.into(),
+            guest_path: guest_path.into(),
+            access: FilesystemAccess::ReadOnly,
+        }
+    }
+
+    pub fn read_write(host_path: impl Into<PathBuf>, guest_path: impl Into<String>) -> Self {
+        Self {
+            host_path: host_path.into(),
+            guest_path: guest_path.into(),
+            access: FilesystemAccess::ReadWrite,
+        }
+    }
+}
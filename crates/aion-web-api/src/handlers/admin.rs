@@ -3,12 +3,13 @@
 use axum::{
     extract::{Query, State},
     http::StatusCode,
-    response::Json,
+    response::{IntoResponse, Json, Response},
 };
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
-use uuid::Uuid;
 use crate::{AppState, models::*};
+use crate::listing::{ListQuery, PagedResponse};
+use crate::services::auth::USER_LIST;
 
 /// Admin statistics response
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -105,41 +106,24 @@ pub async fn get_admin_stats(
 }
 
 /// List all users (admin only)
+///
+/// Accepts the shared list parameters (see [`crate::listing`]) over the fields
+/// in [`USER_LIST`].
 pub async fn list_users(
-    Query(params): Query<AdminQuery>,
-    State(_state): State<AppState>
-) -> Result<Json<Vec<User>>, StatusCode> {
+    Query(params): Query<Vec<(String, String)>>,
+    State(state): State<AppState>
+) -> Result<Json<PagedResponse<User>>, Response> {
     println!("👑 Admin: Listing all users");
 
-    // Generate sample user data
-    let users = vec![
-        User {
-            id: Uuid::new_v4(),
-            email: "john.doe@example.com".to_string(),
-            name: "John Doe".to_string(),
-            role: "user".to_string(),
-            created_at: chrono::Utc::now() - chrono::Duration::days(30),
-            last_login: chrono::Utc::now() - chrono::Duration::hours(2),
-        },
-        User {
-            id: Uuid::new_v4(),
-            email: "admin@ectus.ai".to_string(),
-            name: "System Administrator".to_string(),
-            role: "admin".to_string(),
-            created_at: chrono::Utc::now() - chrono::Duration::days(365),
-            last_login: chrono::Utc::now() - chrono::Duration::minutes(15),
-        },
-        User {
-            id: Uuid::new_v4(),
-            email: "jane.smith@corp.com".to_string(),
-            name: "Jane Smith".to_string(),
-            role: "user".to_string(),
-            created_at: chrono::Utc::now() - chrono::Duration::days(15),
-            last_login: chrono::Utc::now() - chrono::Duration::days(1),
-        },
-    ];
+    let query = ListQuery::parse(&USER_LIST, &params).map_err(IntoResponse::into_response)?;
 
-    Ok(Json(users))
+    match state.auth_service.list_users(&query).await {
+        Ok(page) => Ok(Json(page)),
+        Err(e) => {
+            eprintln!("❌ Failed to list users: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR.into_response())
+        }
+    }
 }
 
 /// Get detailed system information
@@ -1,37 +1,106 @@
 //! AI-related request handlers
 
-use axum::{extract::State, http::StatusCode, response::Json};
+use axum::{extract::State, http::StatusCode, response::{IntoResponse, Json, Response}};
 use serde_json::Value;
-use crate::{AppState, models::*};
+use crate::{AppState, middleware::TenantContext, models::*};
+use crate::handlers::uploads::MAX_TEXT_UPLOAD_BYTES;
+use crate::services::ai::sampling_parameters;
+use crate::services::provider_pool::{ProviderError, ProviderMetrics};
+use crate::services::single_flight::SharedError;
 
 /// Generate code from natural language prompt
 pub async fn generate_code(
     State(state): State<AppState>,
-    Json(request): Json<GenerateRequest>
-) -> Result<Json<GenerateResponse>, StatusCode> {
+    tenant: Option<TenantContext>,
+    Json(mut request): Json<GenerateRequest>
+) -> Result<Json<GenerateResponse>, Response> {
     println!("🧠 Processing code generation request for: {}", request.prompt);
 
+    if let Err(e) = sampling_parameters(&request).validate() {
+        eprintln!("❌ Rejected generation request: {}", e);
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({
+                "success": false,
+                "error": e.to_string()
+            })),
+        )
+            .into_response());
+    }
+
+    if let Some(upload_id) = request.upload_id.take() {
+        let reference = read_upload_text(&state, upload_id).await.map_err(IntoResponse::into_response)?;
+        request.prompt = format!("{}\n\nReference input:\n{}", request.prompt, reference);
+    }
+
     match state.ai_service.generate_code(request).await {
-        Ok(response) => Ok(Json(response)),
+        Ok(response) => {
+            if let Some(tenant) = tenant {
+                state.webhook_service.emit(&tenant.org_id, "generation.completed", serde_json::json!({
+                    "id": response.id,
+                    "status": response.status,
+                    "files": response.generated_files.len(),
+                    "confidence_score": response.confidence_score
+                }));
+            }
+            Ok(Json(response))
+        }
+        Err(e) if is_provider_unavailable(&e) => {
+            eprintln!("⚡ Code generation rejected, no provider available: {}", e);
+            Err(StatusCode::SERVICE_UNAVAILABLE.into_response())
+        }
         Err(e) => {
             eprintln!("❌ Code generation failed: {}", e);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
+            Err(StatusCode::INTERNAL_SERVER_ERROR.into_response())
         }
     }
 }
 
+/// Whether generation failed because every provider rejected the call,
+/// including when the failure was shared from a deduplicated request
+fn is_provider_unavailable(error: &anyhow::Error) -> bool {
+    let error = error.downcast_ref::<SharedError>().map(SharedError::inner).unwrap_or(error);
+    error.downcast_ref::<ProviderError>().is_some()
+}
+
+/// Content of a text upload passed by id
+async fn read_upload_text(state: &AppState, upload_id: uuid::Uuid) -> Result<String, StatusCode> {
+    state.upload_service
+        .read_text(upload_id, MAX_TEXT_UPLOAD_BYTES)
+        .await
+        .map_err(|e| {
+            eprintln!("❌ Cannot use upload {}: {}", upload_id, e);
+            e.into_response().status()
+        })
+}
+
+/// Circuit breaker and bulkhead state of the AI providers
+pub async fn get_provider_metrics(
+    State(state): State<AppState>,
+) -> Json<Vec<ProviderMetrics>> {
+    Json(state.ai_service.provider_metrics())
+}
+
 /// Analyze existing code
 pub async fn analyze_code(
     State(state): State<AppState>,
     Json(code): Json<Value>
 ) -> Result<Json<Value>, StatusCode> {
-    let code_str = code.get("code")
-        .and_then(|v| v.as_str())
-        .ok_or(StatusCode::BAD_REQUEST)?;
+    // Large inputs are uploaded first and referenced by id
+    let code_str = match code.get("upload_id").and_then(|v| v.as_str()) {
+        Some(upload_id) => {
+            let upload_id = uuid::Uuid::parse_str(upload_id).map_err(|_| StatusCode::BAD_REQUEST)?;
+            read_upload_text(&state, upload_id).await?
+        }
+        None => code.get("code")
+            .and_then(|v| v.as_str())
+            .ok_or(StatusCode::BAD_REQUEST)?
+            .to_string(),
+    };
 
     println!("🔍 Processing code analysis request");
 
-    match state.ai_service.analyze_code(code_str).await {
+    match state.ai_service.analyze_code(&code_str).await {
         Ok(analysis) => Ok(Json(analysis)),
         Err(e) => {
             eprintln!("❌ Code analysis failed: {}", e);
@@ -93,6 +162,7 @@ pub async fn refactor_code(
 /// Run autonomous quality assurance
 pub async fn run_autonomous_qa(
     State(state): State<AppState>,
+    tenant: Option<TenantContext>,
     Json(request): Json<Value>
 ) -> Result<Json<Value>, StatusCode> {
     let code = request.get("code")
@@ -102,7 +172,12 @@ pub async fn run_autonomous_qa(
     println!("🧪 Running autonomous QA");
 
     match state.ai_service.run_autonomous_qa(code).await {
-        Ok(qa_result) => Ok(Json(qa_result)),
+        Ok(qa_result) => {
+            if let Some(tenant) = tenant {
+                state.webhook_service.emit(&tenant.org_id, "qa.completed", qa_result.clone());
+            }
+            Ok(Json(qa_result))
+        }
         Err(e) => {
             eprintln!("❌ Autonomous QA failed: {}", e);
             Err(StatusCode::INTERNAL_SERVER_ERROR)
@@ -3,13 +3,16 @@
 //! Tracks user behavior, conversion funnels, and product metrics
 
 use axum::{
-    extract::{Json, State},
+    extract::{Json, Path, Query, State},
     http::StatusCode,
     response::IntoResponse,
 };
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 use uuid::Uuid;
+use crate::{AppState, middleware::TenantContext};
+use crate::services::analytics::{DateRange, Granularity};
+use crate::services::experiments::{minimum_sample_size, CreateExperimentRequest};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum EventType {
@@ -40,9 +43,41 @@ pub enum EventType {
     AdClicked { campaign: String, source: String },
 }
 
+impl EventType {
+    /// Stable event name used in exports and funnel definitions
+    pub fn name(&self) -> &'static str {
+        match self {
+            EventType::PageView { .. } => "page_view",
+            EventType::SignupStarted => "signup_started",
+            EventType::SignupCompleted { .. } => "signup_completed",
+            EventType::TrialStarted => "trial_started",
+            EventType::DemoPlayed { .. } => "demo_played",
+            EventType::CodeGenerated { .. } => "code_generated",
+            EventType::ProjectCreated { .. } => "project_created",
+            EventType::CheckoutStarted { .. } => "checkout_started",
+            EventType::PaymentCompleted { .. } => "payment_completed",
+            EventType::SubscriptionCancelled { .. } => "subscription_cancelled",
+            EventType::FeatureUsed { .. } => "feature_used",
+            EventType::EmailOpened { .. } => "email_opened",
+            EventType::EmailClicked { .. } => "email_clicked",
+            EventType::AdClicked { .. } => "ad_clicked",
+        }
+    }
+
+    /// Numeric measure carried by the event, if any
+    pub fn value(&self) -> Option<f64> {
+        match self {
+            EventType::PaymentCompleted { amount, .. } => Some(*amount),
+            EventType::CodeGenerated { lines, .. } => Some(*lines as f64),
+            _ => None,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AnalyticsEvent {
     pub event_id: String,
+    pub org_id: Option<String>,
     pub user_id: Option<String>,
     pub session_id: String,
     pub event_type: EventType,
@@ -114,11 +149,17 @@ pub struct FunnelStep {
 }
 
 /// Track an analytics event
+///
+/// Anonymous events (e.g. from the marketing site) are accepted but not attributed
+/// to any organization, so they never appear in tenant-scoped reports.
 pub async fn track_event(
+    State(state): State<AppState>,
+    tenant: Option<TenantContext>,
     Json(request): Json<TrackEventRequest>,
 ) -> impl IntoResponse {
     let event = AnalyticsEvent {
         event_id: Uuid::new_v4().to_string(),
+        org_id: tenant.map(|t| t.org_id),
         user_id: request.user_id,
         session_id: request.session_id,
         event_type: request.event_type,
@@ -130,13 +171,14 @@ pub async fn track_event(
 
     tracing::info!("Analytics event tracked: {:?}", event.event_type);
 
-    // In production, send to analytics backend (e.g., Mixpanel, Amplitude, PostHog)
-    // store_event_in_database(&event).await;
+    // In production, also forward to analytics backend (e.g., Mixpanel, Amplitude, PostHog)
     // send_to_analytics_platform(&event).await;
+    let event_id = event.event_id.clone();
+    state.analytics_service.record(event);
 
     (StatusCode::OK, Json(serde_json::json!({
         "success": true,
-        "event_id": event.event_id
+        "event_id": event_id
     })))
 }
 
@@ -220,79 +262,77 @@ pub async fn get_analytics_metrics() -> impl IntoResponse {
     })))
 }
 
+/// Query parameters for funnel analysis
+#[derive(Debug, Deserialize)]
+pub struct FunnelQuery {
+    /// Comma-separated, ordered event names
+    pub steps: Option<String>,
+    pub window_hours: Option<i64>,
+    pub from: Option<chrono::DateTime<chrono::Utc>>,
+    pub to: Option<chrono::DateTime<chrono::Utc>>,
+    pub granularity: Option<Granularity>,
+}
+
+/// Default funnel mirroring the signup-to-payment journey
+const DEFAULT_FUNNEL_STEPS: &str = "page_view,signup_started,signup_completed,trial_started,payment_completed";
+
 /// Get conversion funnel analysis
-pub async fn get_conversion_funnel() -> impl IntoResponse {
-    let funnel = FunnelMetrics {
-        landing_page_views: 45000,
-        signup_started: 15000,
-        signup_completed: 12000,
-        trial_started: 9360,
-        payment_completed: 3931,
-        conversion_rates: vec![
-            FunnelStep {
-                from: "Landing Page".to_string(),
-                to: "Signup Started".to_string(),
-                rate: 0.33,
-                drop_off: 30000,
-            },
-            FunnelStep {
-                from: "Signup Started".to_string(),
-                to: "Signup Completed".to_string(),
-                rate: 0.80,
-                drop_off: 3000,
-            },
-            FunnelStep {
-                from: "Signup Completed".to_string(),
-                to: "Trial Started".to_string(),
-                rate: 0.78,
-                drop_off: 2640,
-            },
-            FunnelStep {
-                from: "Trial Started".to_string(),
-                to: "Payment Completed".to_string(),
-                rate: 0.42,
-                drop_off: 5429,
-            },
-        ],
-    };
+pub async fn get_conversion_funnel(
+    State(state): State<AppState>,
+    tenant: TenantContext,
+    Query(query): Query<FunnelQuery>,
+) -> impl IntoResponse {
+    let steps: Vec<String> = query
+        .steps
+        .as_deref()
+        .unwrap_or(DEFAULT_FUNNEL_STEPS)
+        .split(',')
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+        .collect();
+    let window = chrono::Duration::hours(query.window_hours.unwrap_or(24 * 7).max(1));
+    let range = DateRange { from: query.from, to: query.to };
+    let granularity = query.granularity.unwrap_or(Granularity::Day);
+
+    match state.analytics_service.compute_funnel(&tenant.org_id, &steps, window, &range, granularity) {
+        Ok(funnel) => (StatusCode::OK, Json(serde_json::json!({
+            "success": true,
+            "funnel": funnel
+        }))),
+        Err(e) => (StatusCode::BAD_REQUEST, Json(serde_json::json!({
+            "success": false,
+            "error": e.to_string()
+        }))),
+    }
+}
 
-    (StatusCode::OK, Json(serde_json::json!({
-        "success": true,
-        "funnel": funnel
-    })))
+/// Query parameters for cohort analysis
+#[derive(Debug, Deserialize)]
+pub struct CohortQuery {
+    pub signup_event: Option<String>,
+    /// Event counted as activity; any event when omitted
+    pub activity_event: Option<String>,
+    pub from: Option<chrono::DateTime<chrono::Utc>>,
+    pub to: Option<chrono::DateTime<chrono::Utc>>,
+    pub granularity: Option<Granularity>,
+    pub periods: Option<usize>,
 }
 
 /// Get user cohort analysis
-pub async fn get_cohort_analysis() -> impl IntoResponse {
-    #[derive(Serialize)]
-    struct CohortData {
-        month: String,
-        new_users: u64,
-        retention_rates: Vec<f64>,
-    }
-
-    let cohorts = vec![
-        CohortData {
-            month: "2025-01".to_string(),
-            new_users: 1250,
-            retention_rates: vec![1.0, 0.85, 0.78, 0.72, 0.68, 0.65],
-        },
-        CohortData {
-            month: "2025-02".to_string(),
-            new_users: 1450,
-            retention_rates: vec![1.0, 0.88, 0.80, 0.75, 0.71],
-        },
-        CohortData {
-            month: "2025-03".to_string(),
-            new_users: 1680,
-            retention_rates: vec![1.0, 0.90, 0.82, 0.78],
-        },
-        CohortData {
-            month: "2025-04".to_string(),
-            new_users: 1920,
-            retention_rates: vec![1.0, 0.91, 0.84],
-        },
-    ];
+pub async fn get_cohort_analysis(
+    State(state): State<AppState>,
+    tenant: TenantContext,
+    Query(query): Query<CohortQuery>,
+) -> impl IntoResponse {
+    let range = DateRange { from: query.from, to: query.to };
+    let cohorts = state.analytics_service.compute_cohorts(
+        &tenant.org_id,
+        query.signup_event.as_deref().unwrap_or("signup_completed"),
+        query.activity_event.as_deref(),
+        &range,
+        query.granularity.unwrap_or(Granularity::Month),
+        query.periods.unwrap_or(12).min(365),
+    );
 
     (StatusCode::OK, Json(serde_json::json!({
         "success": true,
@@ -300,68 +340,96 @@ pub async fn get_cohort_analysis() -> impl IntoResponse {
     })))
 }
 
-/// Get A/B test results
-pub async fn get_ab_test_results() -> impl IntoResponse {
-    #[derive(Serialize)]
-    struct ABTestResult {
-        test_name: String,
-        variant_a: ABTestVariant,
-        variant_b: ABTestVariant,
-        winner: Option<String>,
-        confidence: f64,
-    }
-
-    #[derive(Serialize)]
-    struct ABTestVariant {
-        name: String,
-        users: u64,
-        conversions: u64,
-        conversion_rate: f64,
+/// Get A/B test results for every experiment of the caller's organization
+pub async fn get_ab_test_results(
+    State(state): State<AppState>,
+    tenant: TenantContext,
+) -> impl IntoResponse {
+    let mut tests = Vec::new();
+    for experiment in state.experiment_service.list_experiments(&tenant.org_id) {
+        match state.experiment_service.analyze(&tenant.org_id, &experiment.id, &state.analytics_service) {
+            Ok(results) => tests.push(results),
+            Err(e) => tracing::warn!("Failed to analyze experiment {}: {}", experiment.id, e),
+        }
     }
 
-    let tests = vec![
-        ABTestResult {
-            test_name: "Pricing Page CTA".to_string(),
-            variant_a: ABTestVariant {
-                name: "Start Free Trial".to_string(),
-                users: 5000,
-                conversions: 650,
-                conversion_rate: 0.13,
-            },
-            variant_b: ABTestVariant {
-                name: "Get Started Free".to_string(),
-                users: 5000,
-                conversions: 780,
-                conversion_rate: 0.156,
-            },
-            winner: Some("variant_b".to_string()),
-            confidence: 0.95,
-        },
-        ABTestResult {
-            test_name: "Demo Playground Position".to_string(),
-            variant_a: ABTestVariant {
-                name: "Above Fold".to_string(),
-                users: 6000,
-                conversions: 1200,
-                conversion_rate: 0.20,
-            },
-            variant_b: ABTestVariant {
-                name: "After Features".to_string(),
-                users: 6000,
-                conversions: 960,
-                conversion_rate: 0.16,
-            },
-            winner: Some("variant_a".to_string()),
-            confidence: 0.98,
-        },
-    ];
-
     (StatusCode::OK, Json(serde_json::json!({
         "success": true,
         "tests": tests
     })))
 }
 
+/// Create an A/B test
+pub async fn create_ab_test(
+    State(state): State<AppState>,
+    tenant: TenantContext,
+    Json(request): Json<CreateExperimentRequest>,
+) -> impl IntoResponse {
+    match state.experiment_service.create_experiment(&tenant.org_id, request) {
+        Ok(experiment) => {
+            let minimum_sample_size = minimum_sample_size(
+                experiment.baseline_rate,
+                experiment.minimum_detectable_effect,
+                experiment.confidence_level,
+                experiment.statistical_power,
+            );
+            (StatusCode::CREATED, Json(serde_json::json!({
+                "success": true,
+                "experiment": experiment,
+                "minimum_sample_size_per_variant": minimum_sample_size
+            })))
+        }
+        Err(e) => (StatusCode::BAD_REQUEST, Json(serde_json::json!({
+            "success": false,
+            "error": e.to_string()
+        }))),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AssignmentQuery {
+    pub user_id: String,
+}
+
+/// Get the variant a user should see; stable across calls
+pub async fn get_ab_test_assignment(
+    State(state): State<AppState>,
+    tenant: TenantContext,
+    Path(experiment_id): Path<String>,
+    Query(query): Query<AssignmentQuery>,
+) -> impl IntoResponse {
+    match state.experiment_service.assign(&tenant.org_id, &experiment_id, &query.user_id) {
+        Ok(assignment) => (StatusCode::OK, Json(serde_json::json!({
+            "success": true,
+            "experiment_id": experiment_id,
+            "user_id": query.user_id,
+            "assignment": assignment
+        }))),
+        Err(e) => (StatusCode::NOT_FOUND, Json(serde_json::json!({
+            "success": false,
+            "error": e.to_string()
+        }))),
+    }
+}
+
+/// Get statistical results for a single A/B test
+pub async fn get_ab_test_result(
+    State(state): State<AppState>,
+    tenant: TenantContext,
+    Path(experiment_id): Path<String>,
+) -> impl IntoResponse {
+    match state.experiment_service.analyze(&tenant.org_id, &experiment_id, &state.analytics_service) {
+        Ok(results) => (StatusCode::OK, Json(serde_json::json!({
+            "success": true,
+            "results": results
+        }))),
+        Err(e) => (StatusCode::NOT_FOUND, Json(serde_json::json!({
+            "success": false,
+            "error": e.to_string()
+        }))),
+    }
+}
+
 /// Track feature flag activation
 pub async fn track_feature_flag(
     Json(request): Json<serde_json::Value>,
@@ -39,9 +39,10 @@ pub async fn refresh_token(
     println!("🔄 Refreshing token");
 
     match state.auth_service.refresh_token(refresh_token).await {
-        Ok(new_token) => Ok(Json(serde_json::json!({
-            "access_token": new_token,
-            "expires_in": 24 * 3600,
+        Ok(refreshed) => Ok(Json(serde_json::json!({
+            "access_token": refreshed.access_token,
+            "refresh_token": refreshed.refresh_token,
+            "expires_in": refreshed.expires_in,
             "timestamp": chrono::Utc::now()
         }))),
         Err(e) => {
@@ -1,22 +1,51 @@
 //! Deployment management handlers
 
 use axum::{
-    extract::{Path, Query, State},
+    extract::{
+        ws::{Message, WebSocket, WebSocketUpgrade},
+        Path, Query, State,
+    },
     http::StatusCode,
-    response::Json,
+    response::{IntoResponse, Json, Response},
 };
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use std::collections::HashMap;
 use uuid::Uuid;
-use crate::{AppState, models::*};
+use crate::{AppState, middleware::TenantContext, models::*};
+use crate::listing::{FieldKind, FieldValue, ListField, ListQuery, ListSpec, Listable, PagedResponse};
+use crate::versioning::{with_etag, IfMatch, PreconditionFailed, VersionConflict};
+use crate::services::deployment::{DeploymentPlanPreview, PlanApplyError};
+use aion_enterprise::deployment::logs::{LogFollower, LogPage};
+use crate::services::deployment_planner::ResourceDefinition;
+use aion_enterprise::deployment::orchestrator::{DeploymentVersion, RevertResult};
 
-/// Query parameters for listing deployments
-#[derive(Deserialize)]
-pub struct DeploymentQuery {
-    pub offset: Option<usize>,
-    pub limit: Option<usize>,
-    pub environment: Option<String>,
-    pub status: Option<String>,
+/// Fields `GET /api/v1/deployments` can be sorted and filtered by
+pub static DEPLOYMENT_LIST: ListSpec = ListSpec {
+    fields: &[
+        ListField { name: "name", column: "name", kind: FieldKind::Text, sortable: true, filterable: true },
+        ListField { name: "status", column: "status", kind: FieldKind::Text, sortable: true, filterable: true },
+        ListField { name: "environment", column: "environment", kind: FieldKind::Text, sortable: true, filterable: true },
+        ListField { name: "health_score", column: "health_score", kind: FieldKind::Number, sortable: true, filterable: false },
+        ListField { name: "created_at", column: "created_at", kind: FieldKind::Timestamp, sortable: true, filterable: false },
+        ListField { name: "updated_at", column: "updated_at", kind: FieldKind::Timestamp, sortable: true, filterable: false },
+    ],
+    default_sort: "created_at:desc",
+    tiebreaker: "id",
+};
+
+impl Listable for Deployment {
+    fn field_value(&self, field: &str) -> Option<FieldValue> {
+        match field {
+            "name" => Some(FieldValue::Text(self.name.clone())),
+            "status" => Some(FieldValue::Text(self.status.clone())),
+            "environment" => Some(FieldValue::Text(self.environment.clone())),
+            "health_score" => Some(FieldValue::Number(self.health_score)),
+            "created_at" => Some(FieldValue::Timestamp(self.created_at)),
+            "updated_at" => Some(FieldValue::Timestamp(self.updated_at)),
+            _ => None,
+        }
+    }
 }
 
 /// Request for creating a deployment
@@ -25,36 +54,38 @@ pub struct CreateDeploymentRequest {
     pub project_id: Uuid,
     pub environment: String,
     pub config: Option<Value>,
+    /// Apply exactly this plan from `POST /deployments/plan`
+    pub plan_id: Option<Uuid>,
+}
+
+/// Request for previewing a deployment
+#[derive(Deserialize)]
+pub struct PlanDeploymentRequest {
+    pub project_id: Uuid,
+    pub environment: String,
+    /// Desired resources for the environment
+    pub resources: Vec<ResourceDefinition>,
+    #[serde(default)]
+    pub variables: HashMap<String, Value>,
 }
 
 /// List all deployments with filtering
+///
+/// Accepts the shared list parameters (see [`crate::listing`]) over the fields
+/// in [`DEPLOYMENT_LIST`].
 pub async fn list_deployments(
-    Query(params): Query<DeploymentQuery>,
+    Query(params): Query<Vec<(String, String)>>,
     State(state): State<AppState>
-) -> Result<Json<Vec<Deployment>>, StatusCode> {
-    let offset = params.offset.unwrap_or(0);
-    let limit = params.limit.unwrap_or(20).min(100); // Cap at 100
-
-    println!("📋 Listing deployments (offset: {}, limit: {})", offset, limit);
-
-    match state.deployment_service.list_deployments(offset, limit).await {
-        Ok(deployments) => {
-            // Apply filtering
-            let mut filtered_deployments = deployments;
-
-            if let Some(environment) = params.environment {
-                filtered_deployments.retain(|d| d.environment == environment);
-            }
+) -> Result<Json<PagedResponse<Deployment>>, Response> {
+    let query = ListQuery::parse(&DEPLOYMENT_LIST, &params).map_err(IntoResponse::into_response)?;
 
-            if let Some(status) = params.status {
-                filtered_deployments.retain(|d| d.status == status);
-            }
+    println!("📋 Listing deployments (offset: {}, per_page: {})", query.offset(), query.per_page);
 
-            Ok(Json(filtered_deployments))
-        },
+    match state.deployment_service.list_deployments(&query).await {
+        Ok(page) => Ok(Json(page)),
         Err(e) => {
             eprintln!("❌ Failed to list deployments: {}", e);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
+            Err(StatusCode::INTERNAL_SERVER_ERROR.into_response())
         }
     }
 }
@@ -62,14 +93,52 @@ pub async fn list_deployments(
 /// Create a new deployment
 pub async fn create_deployment(
     State(state): State<AppState>,
+    tenant: Option<TenantContext>,
     Json(request): Json<CreateDeploymentRequest>
 ) -> Result<Json<Deployment>, StatusCode> {
     println!("🚀 Creating new deployment for project: {}", request.project_id);
 
-    match state.deployment_service.create_deployment(request.project_id, request.environment).await {
-        Ok(deployment) => Ok(Json(deployment)),
+    let result = match request.plan_id {
+        Some(plan_id) => state.deployment_service.apply_plan(plan_id, request.project_id, request.environment).await,
+        None => state.deployment_service.create_deployment(request.project_id, request.environment).await,
+    };
+
+    match result {
+        Ok(deployment) => {
+            if let Some(tenant) = tenant {
+                state.webhook_service.emit(&tenant.org_id, "deployment.created", serde_json::json!(deployment));
+            }
+            Ok(Json(deployment))
+        }
         Err(e) => {
             eprintln!("❌ Failed to create deployment: {}", e);
+            Err(match e.downcast_ref::<PlanApplyError>() {
+                Some(PlanApplyError::NotFound(_)) => StatusCode::NOT_FOUND,
+                Some(PlanApplyError::Expired(_)) => StatusCode::GONE,
+                Some(PlanApplyError::TargetMismatch { .. }) => StatusCode::UNPROCESSABLE_ENTITY,
+                Some(PlanApplyError::StateChanged(_)) => StatusCode::CONFLICT,
+                None => StatusCode::INTERNAL_SERVER_ERROR,
+            })
+        }
+    }
+}
+
+/// Preview what a deployment would change without executing it
+pub async fn plan_deployment(
+    State(state): State<AppState>,
+    Json(request): Json<PlanDeploymentRequest>
+) -> Result<Json<DeploymentPlanPreview>, StatusCode> {
+    println!("📝 Planning deployment for project: {}", request.project_id);
+
+    match state.deployment_service.plan_deployment(
+        request.project_id,
+        request.environment,
+        request.resources,
+        request.variables,
+    ).await {
+        Ok(plan) => Ok(Json(plan)),
+        Err(e) => {
+            eprintln!("❌ Failed to plan deployment: {}", e);
             Err(StatusCode::INTERNAL_SERVER_ERROR)
         }
     }
@@ -79,11 +148,11 @@ pub async fn create_deployment(
 pub async fn get_deployment(
     Path(deployment_id): Path<Uuid>,
     State(state): State<AppState>
-) -> Result<Json<Deployment>, StatusCode> {
+) -> Result<Response, StatusCode> {
     println!("📄 Fetching deployment: {}", deployment_id);
 
     match state.deployment_service.get_deployment(deployment_id).await {
-        Ok(Some(deployment)) => Ok(Json(deployment)),
+        Ok(Some(deployment)) => Ok(with_etag(deployment.version, deployment)),
         Ok(None) => Err(StatusCode::NOT_FOUND),
         Err(e) => {
             eprintln!("❌ Failed to get deployment: {}", e);
@@ -93,23 +162,38 @@ pub async fn get_deployment(
 }
 
 /// Update deployment status
+///
+/// Requires `If-Match` with the deployment's current ETag; see [`crate::versioning`].
 pub async fn update_deployment(
     Path(deployment_id): Path<Uuid>,
     State(state): State<AppState>,
+    tenant: Option<TenantContext>,
+    if_match: IfMatch,
     Json(update): Json<Value>
-) -> Result<Json<Deployment>, StatusCode> {
+) -> Result<Response, Response> {
     let status = update.get("status")
         .and_then(|v| v.as_str())
-        .ok_or(StatusCode::BAD_REQUEST)?;
+        .ok_or_else(|| StatusCode::BAD_REQUEST.into_response())?;
 
     println!("🔄 Updating deployment {} to status: {}", deployment_id, status);
 
-    match state.deployment_service.update_deployment(deployment_id, status.to_string()).await {
-        Ok(deployment) => Ok(Json(deployment)),
-        Err(e) => {
-            eprintln!("❌ Failed to update deployment: {}", e);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
+    match state.deployment_service.update_deployment(deployment_id, status.to_string(), &if_match).await {
+        Ok(deployment) => {
+            if let Some(tenant) = tenant {
+                state.webhook_service.emit(&tenant.org_id, "deployment.status_changed", serde_json::json!(deployment));
+            }
+            Ok(with_etag(deployment.version, deployment))
         }
+        Err(e) => match e.downcast_ref::<VersionConflict>() {
+            Some(conflict) => {
+                let current = state.deployment_service.get_deployment(deployment_id).await.ok().flatten();
+                Err(PreconditionFailed { current_version: conflict.current_version, current }.into_response())
+            }
+            None => {
+                eprintln!("❌ Failed to update deployment: {}", e);
+                Err(StatusCode::INTERNAL_SERVER_ERROR.into_response())
+            }
+        },
     }
 }
 
@@ -129,32 +213,112 @@ pub async fn delete_deployment(
     }
 }
 
-/// Get deployment logs
+/// Request for reverting a deployment
 #[derive(Deserialize)]
-pub struct LogsQuery {
-    pub lines: Option<usize>,
-    pub follow: Option<bool>,
+pub struct RevertDeploymentRequest {
+    pub to_version: u32,
+}
+
+/// List the recorded versions of a deployment, newest first
+pub async fn get_deployment_history(
+    Path(deployment_id): Path<Uuid>,
+    State(state): State<AppState>
+) -> Result<Json<Vec<DeploymentVersion>>, StatusCode> {
+    println!("📜 Fetching version history for deployment: {}", deployment_id);
+
+    match state.deployment_service.get_deployment_history(deployment_id).await {
+        Ok(history) if history.is_empty() => Err(StatusCode::NOT_FOUND),
+        Ok(history) => Ok(Json(history)),
+        Err(e) => {
+            eprintln!("❌ Failed to get deployment history: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+/// Redeploy a prior version of a deployment
+///
+/// Reverting to the version that is already live returns it without redeploying.
+pub async fn revert_deployment(
+    Path(deployment_id): Path<Uuid>,
+    State(state): State<AppState>,
+    tenant: Option<TenantContext>,
+    Json(request): Json<RevertDeploymentRequest>
+) -> Result<Json<RevertResult>, (StatusCode, Json<Value>)> {
+    println!("⏪ Reverting deployment {} to version {}", deployment_id, request.to_version);
+
+    let requested_by = tenant.as_ref().map(|tenant| tenant.user_id.as_str()).unwrap_or("api");
+    match state.deployment_service.revert_deployment(deployment_id, request.to_version, requested_by).await {
+        Ok(result) => Ok(Json(result)),
+        Err(e) => {
+            eprintln!("❌ Failed to revert deployment: {}", e);
+            Err((StatusCode::UNPROCESSABLE_ENTITY, Json(serde_json::json!({ "error": e.to_string() }))))
+        }
+    }
 }
 
+pub use aion_enterprise::deployment::logs::LogsQuery;
+
+/// Get one page of deployment logs; pass `next_cursor` back as `cursor` for the next page
 pub async fn get_deployment_logs(
     Path(deployment_id): Path<Uuid>,
     Query(params): Query<LogsQuery>,
     State(state): State<AppState>
-) -> Result<Json<Value>, StatusCode> {
-    let lines = params.lines.unwrap_or(100).min(1000); // Cap at 1000 lines
-
-    println!("📋 Fetching {} lines of logs for deployment: {}", lines, deployment_id);
-
-    match state.deployment_service.get_deployment_logs(deployment_id, Some(lines)).await {
-        Ok(logs) => Ok(Json(serde_json::json!({
-            "deployment_id": deployment_id,
-            "lines": logs.len(),
-            "logs": logs,
-            "timestamp": chrono::Utc::now()
-        }))),
+) -> Result<Json<LogPage>, StatusCode> {
+    println!("📋 Fetching logs for deployment: {}", deployment_id);
+
+    match state.deployment_service.get_deployment_logs(deployment_id, &params).await {
+        Ok(page) => Ok(Json(page)),
         Err(e) => {
             eprintln!("❌ Failed to get deployment logs: {}", e);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
+            Err(StatusCode::BAD_REQUEST)
         }
     }
-}
\ No newline at end of file
+}
+
+/// Stream deployment logs over a WebSocket: stored entries after `cursor` first,
+/// then, with `follow=true`, live entries until the deployment finishes
+pub async fn stream_deployment_logs(
+    ws: WebSocketUpgrade,
+    Path(deployment_id): Path<Uuid>,
+    Query(params): Query<LogsQuery>,
+    State(state): State<AppState>
+) -> Result<Response, StatusCode> {
+    println!("📡 Streaming logs for deployment: {}", deployment_id);
+    upgrade_log_stream(ws, &state, deployment_id, params)
+}
+
+/// Upgrade to a WebSocket that sends each matching log entry of `deployment_id` as a JSON text message
+pub(crate) fn upgrade_log_stream(
+    ws: WebSocketUpgrade,
+    state: &AppState,
+    deployment_id: Uuid,
+    params: LogsQuery,
+) -> Result<Response, StatusCode> {
+    let follow = params.follow.unwrap_or(false);
+    let follower = state.deployment_service.follow_deployment_logs(deployment_id, params).map_err(|e| {
+        eprintln!("❌ Failed to follow deployment logs: {}", e);
+        StatusCode::BAD_REQUEST
+    })?;
+    Ok(ws.on_upgrade(move |socket| send_log_entries(socket, follower, follow)))
+}
+
+async fn send_log_entries(mut socket: WebSocket, mut follower: LogFollower, follow: bool) {
+    loop {
+        let next = if follow { follower.next().await } else { follower.next_stored() };
+        let entry = match next {
+            Ok(Some(entry)) => entry,
+            Ok(None) => break,
+            Err(e) => {
+                eprintln!("❌ Deployment log stream failed: {}", e);
+                break;
+            }
+        };
+        let Ok(text) = serde_json::to_string(&entry) else { continue };
+        if socket.send(Message::Text(text)).await.is_err() {
+            // Client went away
+            return;
+        }
+    }
+    let _ = socket.send(Message::Close(None)).await;
+}
@@ -11,6 +11,9 @@ pub mod dashboard;
 pub mod optimization;
 pub mod payments;
 pub mod analytics;
+pub mod analytics_export;
+pub mod uploads;
+pub mod webhooks;
 
 // Re-export handler functions
 pub use system::*;
@@ -22,4 +25,7 @@ pub use admin::*;
 pub use dashboard::*;
 pub use optimization::*;
 pub use payments::*;
-pub use analytics::*;
\ No newline at end of file
+pub use analytics::*;
+pub use analytics_export::*;
+pub use uploads::*;
+pub use webhooks::*;
\ No newline at end of file
@@ -4,15 +4,19 @@
 use axum::{
     extract::{Path, Query, State},
     http::StatusCode,
-    response::Json,
+    response::{IntoResponse, Json, Response},
 };
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use chrono::{DateTime, Utc};
 
 use crate::AppState;
+use crate::versioning::{with_etag, IfMatch, PreconditionFailed};
 use aion_optimization_engine::{OptimizationConfig, OptimizationMetrics, RecommendationRequest, OptimizationRecommendation};
 
+/// Key of the optimization configuration in the version store
+const CONFIG_VERSION_KEY: &str = "optimization_config";
+
 /// Optimization status response
 #[derive(Debug, Serialize)]
 pub struct OptimizationStatusResponse {
@@ -97,25 +101,37 @@ pub async fn get_optimization_status(
 }
 
 /// Get optimization configuration
+///
+/// The response carries the configuration's version as an `ETag` for use in `If-Match`.
 pub async fn get_optimization_config(
     State(state): State<AppState>,
-) -> Result<Json<OptimizationConfig>, StatusCode> {
+) -> Result<Response, StatusCode> {
     let engine = state.optimization_engine.read().await;
 
     match engine.get_config().await {
-        Ok(config) => Ok(Json(config)),
+        Ok(config) => Ok(with_etag(state.versions.current(CONFIG_VERSION_KEY), config)),
         Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
     }
 }
 
 /// Update optimization configuration
+///
+/// Requires `If-Match` with the configuration's current ETag; a stale one gets
+/// `412 Precondition Failed` with the current configuration (see [`crate::versioning`]).
 pub async fn update_optimization_config(
     State(state): State<AppState>,
+    if_match: IfMatch,
     Json(request): Json<OptimizationConfigRequest>,
-) -> Result<Json<OptimizationConfig>, StatusCode> {
+) -> Result<Response, Response> {
     let mut engine = state.optimization_engine.write().await;
 
-    let mut config = engine.get_config().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let mut config = engine.get_config().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR.into_response())?;
+    let version = state
+        .versions
+        .bump_if_match(CONFIG_VERSION_KEY, &if_match)
+        .map_err(|conflict| {
+            PreconditionFailed { current_version: conflict.current_version, current: config.clone() }.into_response()
+        })?;
 
     // Update configuration fields if provided
     if let Some(ml_enabled) = request.ml_enabled {
@@ -139,8 +155,8 @@ pub async fn update_optimization_config(
 
     // Apply the updated configuration
     match engine.update_config(config.clone()).await {
-        Ok(_) => Ok(Json(config)),
-        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+        Ok(_) => Ok(with_etag(version, config)),
+        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR.into_response()),
     }
 }
 
@@ -3,14 +3,23 @@
 //! Handles Stripe integration for Ectus-R subscriptions
 
 use axum::{
+    body::Bytes,
     extract::{Json, State},
-    http::StatusCode,
+    http::{HeaderMap, StatusCode},
     response::IntoResponse,
 };
+use chrono::Utc;
+use hmac::{Hmac, Mac};
 use serde::{Deserialize, Serialize};
+use sha2::Sha256;
 use std::sync::Arc;
 use uuid::Uuid;
 
+use crate::AppState;
+
+/// How far a `Stripe-Signature` timestamp may be from now, in seconds
+pub const STRIPE_SIGNATURE_TOLERANCE_SECS: i64 = 300;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum SubscriptionPlan {
     Free,
@@ -125,6 +134,7 @@ pub struct CheckoutResponse {
 
 #[derive(Debug, Deserialize)]
 pub struct WebhookEvent {
+    #[serde(alias = "type")]
     pub event_type: String,
     pub data: serde_json::Value,
 }
@@ -196,10 +206,92 @@ pub async fn create_checkout_session(
     })))
 }
 
-/// Handle Stripe webhooks
+#[derive(Debug, thiserror::Error, PartialEq, Eq)]
+pub enum StripeSignatureError {
+    #[error("Stripe-Signature header is missing or malformed")]
+    Malformed,
+    #[error("Stripe-Signature timestamp is outside the tolerance window")]
+    Expired,
+    #[error("No Stripe-Signature v1 signature matches the payload")]
+    Mismatch,
+}
+
+/// Check a `Stripe-Signature` header (`t=<unix seconds>,v1=<hex HMAC-SHA256>,...`)
+/// against the raw request body. Any of several `v1` signatures may match,
+/// as Stripe sends one per active secret while a secret is being rolled.
+pub fn verify_stripe_signature(payload: &[u8], header: &str, secret: &str) -> Result<(), StripeSignatureError> {
+    verify_stripe_signature_at(payload, header, secret, Utc::now().timestamp())
+}
+
+fn verify_stripe_signature_at(payload: &[u8], header: &str, secret: &str, now: i64) -> Result<(), StripeSignatureError> {
+    let mut timestamp = None;
+    let mut signatures = Vec::new();
+    for (key, value) in header.split(',').filter_map(|part| part.trim().split_once('=')) {
+        match key {
+            "t" => timestamp = value.parse::<i64>().ok(),
+            "v1" => signatures.extend(decode_hex(value)),
+            _ => {}
+        }
+    }
+    let timestamp = timestamp.ok_or(StripeSignatureError::Malformed)?;
+    if signatures.is_empty() || secret.is_empty() {
+        return Err(StripeSignatureError::Malformed);
+    }
+    if (now - timestamp).abs() > STRIPE_SIGNATURE_TOLERANCE_SECS {
+        return Err(StripeSignatureError::Expired);
+    }
+
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(payload);
+    // `verify_slice` compares in constant time
+    let matches = signatures.iter().any(|signature| mac.clone().verify_slice(signature).is_ok());
+    if matches {
+        Ok(())
+    } else {
+        Err(StripeSignatureError::Mismatch)
+    }
+}
+
+fn decode_hex(value: &str) -> Option<Vec<u8>> {
+    value
+        .as_bytes()
+        .chunks(2)
+        .map(|pair| std::str::from_utf8(pair).ok().filter(|digits| digits.len() == 2))
+        .map(|digits| digits.and_then(|digits| u8::from_str_radix(digits, 16).ok()))
+        .collect()
+}
+
+/// Handle Stripe webhooks. The signature is checked against the raw body
+/// before anything is parsed or processed.
 pub async fn handle_stripe_webhook(
-    Json(event): Json<WebhookEvent>,
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    body: Bytes,
 ) -> impl IntoResponse {
+    let signature = headers
+        .get("Stripe-Signature")
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default();
+    if let Err(error) = verify_stripe_signature(&body, signature, &state.config.stripe_webhook_secret) {
+        tracing::warn!("Rejected Stripe webhook: {}", error);
+        return (StatusCode::BAD_REQUEST, Json(serde_json::json!({
+            "received": false,
+            "error": error.to_string()
+        })));
+    }
+
+    let event: WebhookEvent = match serde_json::from_slice(&body) {
+        Ok(event) => event,
+        Err(error) => {
+            return (StatusCode::BAD_REQUEST, Json(serde_json::json!({
+                "received": false,
+                "error": format!("Invalid webhook payload: {}", error)
+            })));
+        }
+    };
+
     tracing::info!("Received Stripe webhook: {}", event.event_type);
 
     match event.event_type.as_str() {
@@ -337,6 +429,59 @@ pub async fn get_customer_portal(
 mod tests {
     use super::*;
 
+    const SECRET: &str = "whsec_test";
+    const PAYLOAD: &[u8] = br#"{"type":"invoice.payment_failed","data":{"object":{"id":"in_1"}}}"#;
+
+    fn sign(payload: &[u8], timestamp: i64) -> String {
+        let mut mac = Hmac::<Sha256>::new_from_slice(SECRET.as_bytes()).unwrap();
+        mac.update(format!("{}.", timestamp).as_bytes());
+        mac.update(payload);
+        let digest: String = mac.finalize().into_bytes().iter().map(|byte| format!("{:02x}", byte)).collect();
+        format!("t={},v1={}", timestamp, digest)
+    }
+
+    #[test]
+    fn test_valid_stripe_signature() {
+        let now = 1_700_000_000;
+        let header = sign(PAYLOAD, now - 30);
+        assert_eq!(verify_stripe_signature_at(PAYLOAD, &header, SECRET, now), Ok(()));
+
+        // A signature from a rolled secret next to the current one
+        let current = sign(PAYLOAD, now);
+        let v1 = current.split(',').nth(1).unwrap();
+        let header = format!("t={},v1={},{},v0=ignored", now, "ab".repeat(32), v1);
+        assert_eq!(verify_stripe_signature_at(PAYLOAD, &header, SECRET, now), Ok(()));
+    }
+
+    #[test]
+    fn test_tampered_stripe_payload() {
+        let now = 1_700_000_000;
+        let header = sign(PAYLOAD, now);
+        let tampered = br#"{"type":"invoice.payment_succeeded","data":{"object":{"id":"in_1"}}}"#;
+        assert_eq!(
+            verify_stripe_signature_at(tampered, &header, SECRET, now),
+            Err(StripeSignatureError::Mismatch)
+        );
+        assert_eq!(
+            verify_stripe_signature_at(PAYLOAD, &header, "whsec_other", now),
+            Err(StripeSignatureError::Mismatch)
+        );
+        assert_eq!(
+            verify_stripe_signature_at(PAYLOAD, "v1=zz", SECRET, now),
+            Err(StripeSignatureError::Malformed)
+        );
+    }
+
+    #[test]
+    fn test_expired_stripe_signature() {
+        let now = 1_700_000_000;
+        let header = sign(PAYLOAD, now - STRIPE_SIGNATURE_TOLERANCE_SECS - 1);
+        assert_eq!(
+            verify_stripe_signature_at(PAYLOAD, &header, SECRET, now),
+            Err(StripeSignatureError::Expired)
+        );
+    }
+
     #[test]
     fn test_pricing_plans() {
         let free = PricingInfo::free();
@@ -9,6 +9,7 @@
 //! - `GET /api/v1/projects/:id` - Get a specific project
 //! - `PUT /api/v1/projects/:id` - Update a project
 //! - `DELETE /api/v1/projects/:id` - Delete a project
+//! - `GET /ws/projects/:id/logs` - Stream logs of the project's latest deployment
 //!
 //! # Caching Strategy
 //!
@@ -21,8 +22,8 @@
 //! # List all projects
 //! curl http://localhost:8080/api/v1/projects
 //!
-//! # Filter by status
-//! curl http://localhost:8080/api/v1/projects?status=active
+//! # Active projects, most recently created first
+//! curl 'http://localhost:8080/api/v1/projects?filter[status]=active&sort=created_at:desc'
 //!
 //! # Create new project
 //! curl -X POST http://localhost:8080/api/v1/projects \
@@ -31,29 +32,48 @@
 //! ```
 
 use axum::{
-    extract::{Path, Query, State},
+    extract::{ws::WebSocketUpgrade, Path, Query, State},
     http::StatusCode,
-    response::Json,
+    response::{IntoResponse, Json, Response},
 };
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 use crate::{AppState, models::*};
+use crate::handlers::deployments::{upgrade_log_stream, LogsQuery};
+use crate::versioning::{with_etag, IfMatch, PreconditionFailed, INITIAL_VERSION};
+use crate::listing::{FieldKind, FieldValue, ListError, ListField, ListQuery, ListSpec, Listable, PagedResponse};
 use std::time::Duration;
 
-/// Query parameters for listing projects
-///
-/// # Fields
-///
-/// - `offset` - Number of projects to skip (default: 0)
-/// - `limit` - Maximum number of projects to return (default: 20, max: 100)
-/// - `language` - Filter by programming language (e.g., "Rust", "TypeScript")
-/// - `status` - Filter by project status (e.g., "active", "inactive", "deploying")
-#[derive(Deserialize)]
-pub struct ProjectQuery {
-    pub offset: Option<usize>,
-    pub limit: Option<usize>,
-    pub language: Option<String>,
-    pub status: Option<String>,
+/// Fields `GET /api/v1/projects` can be sorted and filtered by
+pub static PROJECT_LIST: ListSpec = ListSpec {
+    fields: &[
+        ListField { name: "name", column: "name", kind: FieldKind::Text, sortable: true, filterable: true },
+        ListField { name: "language", column: "language", kind: FieldKind::Text, sortable: true, filterable: true },
+        ListField { name: "framework", column: "framework", kind: FieldKind::Text, sortable: true, filterable: true },
+        ListField { name: "status", column: "status", kind: FieldKind::Text, sortable: true, filterable: true },
+        ListField { name: "environment", column: "environment", kind: FieldKind::Text, sortable: true, filterable: true },
+        ListField { name: "visibility", column: "visibility", kind: FieldKind::Text, sortable: false, filterable: true },
+        ListField { name: "created_at", column: "created_at", kind: FieldKind::Timestamp, sortable: true, filterable: false },
+        ListField { name: "updated_at", column: "updated_at", kind: FieldKind::Timestamp, sortable: true, filterable: false },
+    ],
+    default_sort: "updated_at:desc",
+    tiebreaker: "id",
+};
+
+impl Listable for Project {
+    fn field_value(&self, field: &str) -> Option<FieldValue> {
+        match field {
+            "name" => Some(FieldValue::Text(self.name.clone())),
+            "language" => Some(FieldValue::Text(self.language.clone())),
+            "framework" => Some(FieldValue::Text(self.framework.clone())),
+            "status" => Some(FieldValue::Text(self.status.clone())),
+            "environment" => Some(FieldValue::Text(self.environment.clone())),
+            "visibility" => Some(FieldValue::Text(self.visibility.clone())),
+            "created_at" => Some(FieldValue::Timestamp(self.created_at)),
+            "updated_at" => Some(FieldValue::Timestamp(self.updated_at)),
+            _ => None,
+        }
+    }
 }
 
 /// Request for creating a project
@@ -75,48 +95,16 @@ pub struct UpdateProjectRequest {
     pub repository_url: Option<String>,
 }
 
-/// Response wrapper for projects list
-///
-/// Wraps the projects array in a JSON object for consistent API responses.
-///
-/// # Example Response
-///
-/// ```json
-/// {
-///   "projects": [
-///     {
-///       "id": "550e8400-e29b-41d4-a716-446655440000",
-///       "name": "E-commerce Platform",
-///       "description": "Full-featured e-commerce platform",
-///       "status": "active",
-///       "language": "Rust",
-///       "framework": "Axum",
-///       "environment": "production",
-///       "tags": ["backend", "api", "database"]
-///     }
-///   ]
-/// }
-/// ```
-#[derive(Serialize, Deserialize, Clone)]
-pub struct ProjectsResponse {
-    pub projects: Vec<Project>,
-}
-
 /// List all projects with optional filtering
 ///
-/// Returns a paginated list of projects with optional filters for language and status.
-/// Results are cached for 30 seconds to optimize performance.
-///
-/// # Query Parameters
-///
-/// - `offset` - Skip this many projects (pagination)
-/// - `limit` - Return at most this many projects (max 100)
-/// - `language` - Filter by programming language
-/// - `status` - Filter by project status
+/// Returns a page of projects; see [`crate::listing`] for the `page`, `per_page`,
+/// `cursor`, `sort` and `filter[...]` parameters and [`PROJECT_LIST`] for the
+/// accepted fields. Results are cached for 30 seconds to optimize performance.
 ///
 /// # Response
 ///
-/// Returns `200 OK` with a ProjectsResponse containing the filtered projects.
+/// Returns `200 OK` with a `PagedResponse` of projects, or `400 Bad Request`
+/// for an unknown sort or filter field.
 ///
 /// # Performance
 ///
@@ -124,20 +112,13 @@ pub struct ProjectsResponse {
 /// - Cache miss: ~10-20ms response time
 /// - Cache TTL: 30 seconds
 pub async fn list_projects(
-    Query(params): Query<ProjectQuery>,
+    Query(params): Query<Vec<(String, String)>>,
     State(state): State<AppState>
-) -> Result<Json<ProjectsResponse>, StatusCode> {
-    let offset = params.offset.unwrap_or(0);
-    let limit = params.limit.unwrap_or(20).min(100);
+) -> Result<Json<PagedResponse<Project>>, ListError> {
+    let query = ListQuery::parse(&PROJECT_LIST, &params)?;
 
     // Create cache key based on query params
-    let cache_key = format!(
-        "projects:offset:{}:limit:{}:lang:{}:status:{}",
-        offset,
-        limit,
-        params.language.as_deref().unwrap_or("all"),
-        params.status.as_deref().unwrap_or("all")
-    );
+    let cache_key = format!("projects:{}", query.cache_key());
 
     // Try to get from cache first
     if let Some(cached) = get_from_cache(&state, &cache_key).await {
@@ -145,7 +126,7 @@ pub async fn list_projects(
         return Ok(Json(cached));
     }
 
-    println!("📋 Listing projects from source (offset: {}, limit: {})", offset, limit);
+    println!("📋 Listing projects from source (offset: {}, per_page: {})", query.offset(), query.per_page);
 
     // Generate sample projects
     let mut projects = Vec::new();
@@ -181,49 +162,39 @@ pub async fn list_projects(
     ];
 
     for (i, (name, description)) in project_names.iter().enumerate() {
-        if i >= offset && projects.len() < limit {
-            let created_at = chrono::Utc::now() - chrono::Duration::days((i * 7) as i64);
-            let updated_at = chrono::Utc::now() - chrono::Duration::hours((i * 6) as i64);
-            let repo_name = name.to_lowercase().replace(' ', "-");
-
-            let project = Project {
-                id: Uuid::new_v4(),
-                name: name.to_string(),
-                description: description.to_string(),
-                language: languages[i % languages.len()].to_string(),
-                framework: frameworks[i % frameworks.len()].to_string(),
-                status: statuses[i % statuses.len()].to_string(),
-                created_at,
-                updated_at,
-                repository_url: Some(format!("https://github.com/ectus-r/{}", repo_name)),
-                deployment_count: fastrand::u32(1..10),
-                last_deployment: updated_at.to_rfc3339(),
-                created_at_iso: created_at.to_rfc3339(),
-                repository: format!("ectus-r/{}", repo_name),
-                environment: environments[i % environments.len()].to_string(),
-                team: vec!["team-member-1".to_string(), "team-member-2".to_string()],
-                deployment_url: if i % 2 == 0 {
-                    Some(format!("https://{}.ectus.app", repo_name))
-                } else {
-                    None
-                },
-                visibility: visibilities[i % visibilities.len()].to_string(),
-                tags: tags_list[i % tags_list.len()].iter().map(|s| s.to_string()).collect(),
-            };
-            projects.push(project);
-        }
+        let created_at = chrono::Utc::now() - chrono::Duration::days((i * 7) as i64);
+        let updated_at = chrono::Utc::now() - chrono::Duration::hours((i * 6) as i64);
+        let repo_name = name.to_lowercase().replace(' ', "-");
+
+        let project = Project {
+            id: Uuid::new_v4(),
+            name: name.to_string(),
+            description: description.to_string(),
+            language: languages[i % languages.len()].to_string(),
+            framework: frameworks[i % frameworks.len()].to_string(),
+            status: statuses[i % statuses.len()].to_string(),
+            created_at,
+            updated_at,
+            repository_url: Some(format!("https://github.com/ectus-r/{}", repo_name)),
+            deployment_count: fastrand::u32(1..10),
+            last_deployment: updated_at.to_rfc3339(),
+            created_at_iso: created_at.to_rfc3339(),
+            repository: format!("ectus-r/{}", repo_name),
+            environment: environments[i % environments.len()].to_string(),
+            team: vec!["team-member-1".to_string(), "team-member-2".to_string()],
+            deployment_url: if i % 2 == 0 {
+                Some(format!("https://{}.ectus.app", repo_name))
+            } else {
+                None
+            },
+            visibility: visibilities[i % visibilities.len()].to_string(),
+            tags: tags_list[i % tags_list.len()].iter().map(|s| s.to_string()).collect(),
+            version: INITIAL_VERSION,
+        };
+        projects.push(project);
     }
 
-    // Apply filters
-    if let Some(language) = params.language {
-        projects.retain(|p| p.language.to_lowercase() == language.to_lowercase());
-    }
-
-    if let Some(status) = params.status {
-        projects.retain(|p| p.status == status);
-    }
-
-    let response = ProjectsResponse { projects };
+    let response = query.apply(projects);
 
     // Cache the response for 30 seconds
     cache_response(&state, &cache_key, &response, 30).await;
@@ -277,6 +248,7 @@ pub async fn create_project(
         deployment_url: None,
         visibility: "private".to_string(),
         tags: vec!["new".to_string()],
+        version: INITIAL_VERSION,
     };
 
     // Invalidate cache since we created a new project
@@ -286,48 +258,41 @@ pub async fn create_project(
 }
 
 /// Get specific project by ID
+///
+/// The response carries the project's version as an `ETag` for use in `If-Match`.
 pub async fn get_project(
     Path(project_id): Path<Uuid>,
-    State(_state): State<AppState>
-) -> Result<Json<Project>, StatusCode> {
+    State(state): State<AppState>
+) -> Result<Response, StatusCode> {
     println!("📄 Fetching project: {}", project_id);
 
-    let created_at = chrono::Utc::now() - chrono::Duration::days(30);
-    let updated_at = chrono::Utc::now() - chrono::Duration::hours(2);
-
-    // Generate sample project data
-    let project = Project {
-        id: project_id,
-        name: "Example Project".to_string(),
-        description: "A sample project for demonstration".to_string(),
-        language: "Rust".to_string(),
-        framework: "Axum".to_string(),
-        status: "active".to_string(),
-        created_at,
-        updated_at,
-        repository_url: Some("https://github.com/ectus-r/example-project".to_string()),
-        deployment_count: 5,
-        last_deployment: updated_at.to_rfc3339(),
-        created_at_iso: created_at.to_rfc3339(),
-        repository: "ectus-r/example-project".to_string(),
-        environment: "production".to_string(),
-        team: vec!["alice".to_string(), "bob".to_string()],
-        deployment_url: Some("https://example-project.ectus.app".to_string()),
-        visibility: "public".to_string(),
-        tags: vec!["rust".to_string(), "backend".to_string(), "api".to_string()],
-    };
-
-    Ok(Json(project))
+    let version = state.versions.current(&version_key(project_id));
+    Ok(with_etag(version, example_project(project_id, version)))
 }
 
 /// Update a project
+///
+/// Requires `If-Match` with the project's current ETag; a stale one gets
+/// `412 Precondition Failed` with the current project (see [`crate::versioning`]).
 pub async fn update_project(
     Path(project_id): Path<Uuid>,
     State(state): State<AppState>,
+    if_match: IfMatch,
     Json(update): Json<UpdateProjectRequest>
-) -> Result<Json<Project>, StatusCode> {
+) -> Result<Response, Response> {
     println!("🔄 Updating project: {}", project_id);
 
+    let version = state
+        .versions
+        .bump_if_match(&version_key(project_id), &if_match)
+        .map_err(|conflict| {
+            PreconditionFailed {
+                current_version: conflict.current_version,
+                current: example_project(project_id, conflict.current_version),
+            }
+            .into_response()
+        })?;
+
     let created_at = chrono::Utc::now() - chrono::Duration::days(30);
     let updated_at = chrono::Utc::now();
 
@@ -351,32 +316,66 @@ pub async fn update_project(
         deployment_url: Some("https://updated-project.ectus.app".to_string()),
         visibility: "private".to_string(),
         tags: vec!["updated".to_string()],
+        version,
     };
 
     // Invalidate cache since we updated a project
     invalidate_projects_cache(&state).await;
 
-    Ok(Json(project))
+    Ok(with_etag(version, project))
 }
 
 /// Delete a project
 pub async fn delete_project(
     Path(project_id): Path<Uuid>,
-    State(_state): State<AppState>
+    State(state): State<AppState>
 ) -> Result<StatusCode, StatusCode> {
     println!("🗑️ Deleting project: {}", project_id);
 
     // In a real implementation, this would delete from database
     // Also invalidate cache
-    invalidate_projects_cache(&_state).await;
+    state.versions.remove(&version_key(project_id));
+    invalidate_projects_cache(&state).await;
 
     Ok(StatusCode::NO_CONTENT)
 }
 
+fn version_key(project_id: Uuid) -> String {
+    format!("project:{}", project_id)
+}
+
+/// Sample project data returned until projects are persisted
+fn example_project(project_id: Uuid, version: u64) -> Project {
+    let created_at = chrono::Utc::now() - chrono::Duration::days(30);
+    let updated_at = chrono::Utc::now() - chrono::Duration::hours(2);
+
+    Project {
+        id: project_id,
+        name: "Example Project".to_string(),
+        description: "A sample project for demonstration".to_string(),
+        language: "Rust".to_string(),
+        framework: "Axum".to_string(),
+        status: "active".to_string(),
+        created_at,
+        updated_at,
+        repository_url: Some("https://github.com/ectus-r/example-project".to_string()),
+        deployment_count: 5,
+        last_deployment: updated_at.to_rfc3339(),
+        created_at_iso: created_at.to_rfc3339(),
+        repository: "ectus-r/example-project".to_string(),
+        environment: "production".to_string(),
+        team: vec!["alice".to_string(), "bob".to_string()],
+        deployment_url: Some("https://example-project.ectus.app".to_string()),
+        visibility: "public".to_string(),
+        tags: vec!["rust".to_string(), "backend".to_string(), "api".to_string()],
+        version,
+    }
+}
+
 // Cache helper functions
 
 /// Get cached response from Redis
-async fn get_from_cache(_state: &AppState, _key: &str) -> Option<ProjectsResponse> {
+async fn get_from_cache(_state: &AppState, _key: &str) -> Option<PagedResponse<Project>> {
     // In production, this would connect to Redis
     // For now, return None to always fetch fresh data
     // Implementation would use redis crate:
@@ -387,7 +386,7 @@ async fn get_from_cache(_state: &AppState, _key: &str) -> Option<ProjectsRespons
 }
 
 /// Cache response in Redis
-async fn cache_response(_state: &AppState, _key: &str, _response: &ProjectsResponse, _ttl_seconds: u64) {
+async fn cache_response(_state: &AppState, _key: &str, _response: &PagedResponse<Project>, _ttl_seconds: u64) {
     // In production, this would store in Redis with TTL
     // Implementation would use redis crate:
     // if let Ok(mut conn) = state.redis_pool.get().await {
@@ -407,4 +406,22 @@ async fn invalidate_projects_cache(_state: &AppState) {
     //         let _ = conn.del(&key).await;
     //     }
     // }
-}
\ No newline at end of file
+}
+
+/// Stream the logs of a project's latest deployment over a WebSocket
+///
+/// Accepts the same query as `GET /api/v1/deployments/:id/logs`.
+pub async fn stream_project_logs(
+    ws: WebSocketUpgrade,
+    Path(project_id): Path<Uuid>,
+    Query(params): Query<LogsQuery>,
+    State(state): State<AppState>,
+) -> Result<Response, StatusCode> {
+    let deployment_id = state
+        .deployment_service
+        .latest_deployment(project_id)
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    println!("📡 Streaming logs for project {} (deployment {})", project_id, deployment_id);
+    upgrade_log_stream(ws, &state, deployment_id, params)
+}
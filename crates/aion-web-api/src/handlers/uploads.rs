@@ -0,0 +1,134 @@
+//! Streaming file uploads
+//!
+//! - `POST /api/v1/uploads` (multipart, one `file` part)
+//! - `GET /api/v1/uploads/:id`
+//! - `DELETE /api/v1/uploads/:id`
+//!
+//! The `file` part is written to temporary storage chunk by chunk as it
+//! arrives. The returned `upload_id` can be passed as `upload_id` to the code
+//! generation and analysis endpoints instead of inlining the content.
+
+use axum::{
+    extract::{Multipart, Path, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use uuid::Uuid;
+use crate::{
+    AppState,
+    services::uploads::{UploadError, UploadRecord},
+};
+
+/// Allowance for multipart boundaries and part headers when checking Content-Length
+const MULTIPART_OVERHEAD_BYTES: u64 = 64 * 1024;
+
+/// Largest upload read back as text for generation and analysis
+pub const MAX_TEXT_UPLOAD_BYTES: u64 = 10 * 1024 * 1024;
+
+impl IntoResponse for UploadError {
+    fn into_response(self) -> Response {
+        let status = match &self {
+            UploadError::TooLarge { .. } => StatusCode::PAYLOAD_TOO_LARGE,
+            UploadError::UnsupportedContentType(_) => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            UploadError::Busy => StatusCode::SERVICE_UNAVAILABLE,
+            UploadError::NotFound(_) => StatusCode::NOT_FOUND,
+            UploadError::NotText(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            UploadError::Io(e) => {
+                eprintln!("❌ Upload storage failed: {}", e);
+                StatusCode::INTERNAL_SERVER_ERROR
+            }
+        };
+        upload_error(status, self.to_string())
+    }
+}
+
+/// Receive a file into temporary storage
+pub async fn upload_file(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    mut multipart: Multipart,
+) -> Response {
+    let uploads = &state.upload_service;
+
+    // Reject obviously oversized requests before reading any of the body
+    let content_length = headers
+        .get(header::CONTENT_LENGTH)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<u64>().ok());
+    if content_length.is_some_and(|length| length > uploads.max_upload_bytes() + MULTIPART_OVERHEAD_BYTES) {
+        return UploadError::TooLarge { limit: uploads.max_upload_bytes() }.into_response();
+    }
+
+    loop {
+        let mut field = match multipart.next_field().await {
+            Ok(Some(field)) => field,
+            Ok(None) => return upload_error(StatusCode::BAD_REQUEST, "Missing multipart part named 'file'".to_string()),
+            Err(e) => return upload_error(e.status(), format!("Invalid multipart body: {}", e.body_text())),
+        };
+        if field.name() != Some("file") {
+            continue;
+        }
+
+        let Some(content_type) = field.content_type().map(str::to_string) else {
+            return upload_error(
+                StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                "The 'file' part needs a Content-Type".to_string(),
+            );
+        };
+        let filename = field.file_name().map(str::to_string);
+
+        // Dropping `upload` on any early return removes the partial file
+        let mut upload = match uploads.begin(filename, &content_type).await {
+            Ok(upload) => upload,
+            Err(e) => return e.into_response(),
+        };
+        loop {
+            match field.chunk().await {
+                Ok(Some(chunk)) => {
+                    if let Err(e) = upload.write_chunk(&chunk).await {
+                        return e.into_response();
+                    }
+                }
+                Ok(None) => break,
+                Err(e) => {
+                    eprintln!("⚠️  Upload {} aborted after {} bytes: {}", upload.upload_id(), upload.bytes_written(), e);
+                    return upload_error(e.status(), format!("Upload interrupted: {}", e.body_text()));
+                }
+            }
+        }
+
+        return match uploads.finish(upload).await {
+            Ok(record) => (StatusCode::CREATED, Json(record)).into_response(),
+            Err(e) => e.into_response(),
+        };
+    }
+}
+
+/// Metadata of a completed upload
+pub async fn get_upload(
+    State(state): State<AppState>,
+    Path(upload_id): Path<Uuid>,
+) -> Result<Json<UploadRecord>, UploadError> {
+    state.upload_service.get(upload_id).map(Json)
+}
+
+/// Delete an upload before it expires
+pub async fn delete_upload(
+    State(state): State<AppState>,
+    Path(upload_id): Path<Uuid>,
+) -> Result<StatusCode, UploadError> {
+    state.upload_service.delete(upload_id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+fn upload_error(status: StatusCode, message: String) -> Response {
+    (
+        status,
+        Json(serde_json::json!({
+            "success": false,
+            "error": message
+        })),
+    )
+        .into_response()
+}
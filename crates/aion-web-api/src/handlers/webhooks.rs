@@ -0,0 +1,123 @@
+//! Webhook endpoint management and delivery history
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::{IntoResponse, Json, Response},
+};
+use uuid::Uuid;
+use crate::{AppState, middleware::TenantContext};
+use crate::services::webhooks::{CreateWebhookRequest, EVENT_TYPES};
+
+/// Register a webhook endpoint for the caller's organization
+///
+/// The response includes the signing secret; it is not shown again.
+pub async fn create_webhook(
+    State(state): State<AppState>,
+    tenant: TenantContext,
+    Json(request): Json<CreateWebhookRequest>,
+) -> Response {
+    match state.webhook_service.register(&tenant.org_id, request).await {
+        Ok(created) => (StatusCode::CREATED, Json(serde_json::json!({
+            "success": true,
+            "webhook": created
+        }))).into_response(),
+        Err(e) => error(StatusCode::BAD_REQUEST, e.to_string()),
+    }
+}
+
+/// List the organization's webhook endpoints and the events available
+pub async fn list_webhooks(
+    State(state): State<AppState>,
+    tenant: TenantContext,
+) -> Json<serde_json::Value> {
+    Json(serde_json::json!({
+        "success": true,
+        "webhooks": state.webhook_service.list_endpoints(&tenant.org_id),
+        "available_events": EVENT_TYPES
+    }))
+}
+
+pub async fn get_webhook(
+    State(state): State<AppState>,
+    tenant: TenantContext,
+    Path(webhook_id): Path<Uuid>,
+) -> Response {
+    match state.webhook_service.get_endpoint(&tenant.org_id, webhook_id) {
+        Some(webhook) => Json(serde_json::json!({
+            "success": true,
+            "webhook": webhook
+        })).into_response(),
+        None => not_found(webhook_id),
+    }
+}
+
+pub async fn delete_webhook(
+    State(state): State<AppState>,
+    tenant: TenantContext,
+    Path(webhook_id): Path<Uuid>,
+) -> Response {
+    if state.webhook_service.remove_endpoint(&tenant.org_id, webhook_id) {
+        StatusCode::NO_CONTENT.into_response()
+    } else {
+        not_found(webhook_id)
+    }
+}
+
+/// Recent deliveries to an endpoint, newest first, with every attempt's outcome
+pub async fn list_webhook_deliveries(
+    State(state): State<AppState>,
+    tenant: TenantContext,
+    Path(webhook_id): Path<Uuid>,
+) -> Response {
+    match state.webhook_service.deliveries(&tenant.org_id, webhook_id) {
+        Some(deliveries) => Json(serde_json::json!({
+            "success": true,
+            "deliveries": deliveries
+        })).into_response(),
+        None => not_found(webhook_id),
+    }
+}
+
+pub async fn get_webhook_delivery(
+    State(state): State<AppState>,
+    tenant: TenantContext,
+    Path((webhook_id, delivery_id)): Path<(Uuid, Uuid)>,
+) -> Response {
+    match state.webhook_service.delivery(&tenant.org_id, webhook_id, delivery_id) {
+        Some(delivery) => Json(serde_json::json!({
+            "success": true,
+            "delivery": delivery
+        })).into_response(),
+        None => error(StatusCode::NOT_FOUND, format!("Delivery {} not found", delivery_id)),
+    }
+}
+
+/// Retry a dead-lettered delivery, keeping its delivery id
+pub async fn redeliver_webhook_delivery(
+    State(state): State<AppState>,
+    tenant: TenantContext,
+    Path((webhook_id, delivery_id)): Path<(Uuid, Uuid)>,
+) -> Response {
+    if state.webhook_service.delivery(&tenant.org_id, webhook_id, delivery_id).is_none() {
+        return error(StatusCode::NOT_FOUND, format!("Delivery {} not found", delivery_id));
+    }
+    match state.webhook_service.redeliver(&tenant.org_id, webhook_id, delivery_id) {
+        Ok(delivery) => (StatusCode::ACCEPTED, Json(serde_json::json!({
+            "success": true,
+            "delivery": delivery
+        }))).into_response(),
+        Err(e) => error(StatusCode::CONFLICT, e.to_string()),
+    }
+}
+
+fn not_found(webhook_id: Uuid) -> Response {
+    error(StatusCode::NOT_FOUND, format!("Webhook {} not found", webhook_id))
+}
+
+fn error(status: StatusCode, message: String) -> Response {
+    (status, Json(serde_json::json!({
+        "success": false,
+        "error": message
+    }))).into_response()
+}
@@ -0,0 +1,533 @@
+//! Shared pagination, filtering and sorting for list endpoints
+//!
+//! Every list endpoint accepts the same query parameters:
+//!
+//! - `page` (1-based) and `per_page` (1-100, default 20), or `cursor` with the
+//!   `next_cursor` of a previous page
+//! - `sort=field:dir[,field:dir...]` where `dir` is `asc` (default) or `desc`
+//! - `filter[field]=value` for exact matches; text compares case-insensitively
+//!
+//! The fields each endpoint accepts are declared once in a [`ListSpec`].
+//! Unknown sort or filter fields are rejected with `400 Bad Request` instead
+//! of being ignored, so a typo never silently returns the wrong data. Other
+//! query parameters are left to the handler.
+//!
+//! Results are wrapped in [`PagedResponse`]. Database-backed lists build their
+//! query with [`ListQuery::select`] and [`ListQuery::count`]: filter values are
+//! always bound as parameters and only the column names declared in the spec
+//! are ever written into the SQL.
+
+use axum::{
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sqlx::{Postgres, QueryBuilder};
+use std::cmp::Ordering;
+
+pub const DEFAULT_PAGE_SIZE: u64 = 20;
+pub const MAX_PAGE_SIZE: u64 = 100;
+
+/// Prefix of the opaque cursor, so cursors can change format later
+const CURSOR_PREFIX: &str = "o";
+
+/// Type of a listable field, used to parse filter values
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FieldKind {
+    Text,
+    Number,
+    Boolean,
+    Timestamp,
+}
+
+/// A field clients may sort or filter a list by
+#[derive(Debug)]
+pub struct ListField {
+    /// Name used in `sort` and `filter[...]`
+    pub name: &'static str,
+    /// Column in the backing table, for database-backed lists
+    pub column: &'static str,
+    pub kind: FieldKind,
+    pub sortable: bool,
+    pub filterable: bool,
+}
+
+/// The fields a list endpoint supports and its default order
+#[derive(Debug)]
+pub struct ListSpec {
+    pub fields: &'static [ListField],
+    /// Sort applied when the request has none, as `field:dir`
+    pub default_sort: &'static str,
+    /// Unique column appended to every ORDER BY so pages never overlap
+    pub tiebreaker: &'static str,
+}
+
+impl ListSpec {
+    fn field(&self, name: &str) -> Option<&'static ListField> {
+        self.fields.iter().find(|field| field.name == name)
+    }
+
+    fn names(&self, include: impl Fn(&ListField) -> bool) -> String {
+        let names: Vec<&str> = self.fields.iter().filter(|field| include(field)).map(|field| field.name).collect();
+        names.join(", ")
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortDirection {
+    Asc,
+    Desc,
+}
+
+impl SortDirection {
+    fn sql(self) -> &'static str {
+        match self {
+            SortDirection::Asc => "ASC",
+            SortDirection::Desc => "DESC",
+        }
+    }
+}
+
+/// A field value, either parsed from a filter or read from a listed item
+#[derive(Debug, Clone, PartialEq)]
+pub enum FieldValue {
+    Text(String),
+    Number(f64),
+    Boolean(bool),
+    Timestamp(DateTime<Utc>),
+}
+
+impl FieldValue {
+    fn parse(kind: FieldKind, raw: &str) -> Option<Self> {
+        match kind {
+            FieldKind::Text => Some(FieldValue::Text(raw.to_string())),
+            FieldKind::Number => raw.parse().ok().filter(|n: &f64| n.is_finite()).map(FieldValue::Number),
+            FieldKind::Boolean => raw.parse().ok().map(FieldValue::Boolean),
+            FieldKind::Timestamp => DateTime::parse_from_rfc3339(raw)
+                .ok()
+                .map(|t| FieldValue::Timestamp(t.with_timezone(&Utc))),
+        }
+    }
+
+    fn matches(&self, other: &FieldValue) -> bool {
+        match (self, other) {
+            (FieldValue::Text(a), FieldValue::Text(b)) => a.to_lowercase() == b.to_lowercase(),
+            _ => self == other,
+        }
+    }
+
+    fn compare(&self, other: &FieldValue) -> Ordering {
+        match (self, other) {
+            (FieldValue::Text(a), FieldValue::Text(b)) => a.cmp(b),
+            (FieldValue::Number(a), FieldValue::Number(b)) => a.partial_cmp(b).unwrap_or(Ordering::Equal),
+            (FieldValue::Boolean(a), FieldValue::Boolean(b)) => a.cmp(b),
+            (FieldValue::Timestamp(a), FieldValue::Timestamp(b)) => a.cmp(b),
+            _ => Ordering::Equal,
+        }
+    }
+
+    fn push_bind(&self, builder: &mut QueryBuilder<'_, Postgres>) {
+        match self {
+            FieldValue::Text(value) => builder.push_bind(value.clone()),
+            FieldValue::Number(value) => builder.push_bind(*value),
+            FieldValue::Boolean(value) => builder.push_bind(*value),
+            FieldValue::Timestamp(value) => builder.push_bind(*value),
+        };
+    }
+}
+
+/// Items of an in-memory list, exposing the fields named in its [`ListSpec`]
+pub trait Listable {
+    fn field_value(&self, field: &str) -> Option<FieldValue>;
+}
+
+/// Invalid list parameters; responds with `400 Bad Request`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ListError(pub String);
+
+impl std::fmt::Display for ListError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for ListError {}
+
+impl IntoResponse for ListError {
+    fn into_response(self) -> Response {
+        (
+            StatusCode::BAD_REQUEST,
+            Json(json!({
+                "success": false,
+                "error": self.0
+            })),
+        )
+            .into_response()
+    }
+}
+
+/// Validated list parameters of one request
+#[derive(Debug, Clone)]
+pub struct ListQuery {
+    spec: &'static ListSpec,
+    pub page: u64,
+    pub per_page: u64,
+    offset: u64,
+    pub sort: Vec<(&'static ListField, SortDirection)>,
+    pub filters: Vec<(&'static ListField, FieldValue)>,
+}
+
+impl ListQuery {
+    /// Validate raw query parameters, as extracted with
+    /// `Query<Vec<(String, String)>>`, against `spec`
+    pub fn parse(spec: &'static ListSpec, params: &[(String, String)]) -> Result<Self, ListError> {
+        let mut page = None;
+        let mut per_page = None;
+        let mut cursor = None;
+        let mut sort = None;
+        let mut filters: Vec<(&'static ListField, FieldValue)> = Vec::new();
+
+        for (key, value) in params {
+            match key.as_str() {
+                "page" => page = Some(parse_positive("page", value)?),
+                "per_page" => per_page = Some(parse_positive("per_page", value)?),
+                "cursor" => cursor = Some(decode_cursor(value)?),
+                "sort" => sort = Some(value.as_str()),
+                _ => {
+                    let Some(name) = key.strip_prefix("filter[").and_then(|rest| rest.strip_suffix(']')) else {
+                        continue;
+                    };
+                    let field = spec.field(name).filter(|field| field.filterable).ok_or_else(|| {
+                        ListError(format!(
+                            "Cannot filter by '{}'; filterable fields are: {}",
+                            name,
+                            spec.names(|field| field.filterable)
+                        ))
+                    })?;
+                    if filters.iter().any(|(existing, _)| existing.name == field.name) {
+                        return Err(ListError(format!("Filter '{}' is given more than once", name)));
+                    }
+                    let parsed = FieldValue::parse(field.kind, value).ok_or_else(|| {
+                        ListError(format!("Invalid value '{}' for filter '{}': expected {}", value, name, kind_name(field.kind)))
+                    })?;
+                    filters.push((field, parsed));
+                }
+            }
+        }
+
+        let per_page = per_page.unwrap_or(DEFAULT_PAGE_SIZE);
+        if per_page > MAX_PAGE_SIZE {
+            return Err(ListError(format!("per_page must be at most {}", MAX_PAGE_SIZE)));
+        }
+        let (page, offset) = match (page, cursor) {
+            (Some(_), Some(_)) => return Err(ListError("Use either page or cursor, not both".to_string())),
+            (_, Some(offset)) => (offset / per_page + 1, offset),
+            (page, None) => {
+                let page = page.unwrap_or(1);
+                let offset = (page - 1)
+                    .checked_mul(per_page)
+                    .ok_or_else(|| ListError("page is out of range".to_string()))?;
+                (page, offset)
+            }
+        };
+
+        Ok(Self {
+            spec,
+            page,
+            per_page,
+            offset,
+            sort: parse_sort(spec, sort.unwrap_or(spec.default_sort))?,
+            filters,
+        })
+    }
+
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// Canonical form of the parameters, for cache keys
+    pub fn cache_key(&self) -> String {
+        let sort: Vec<String> = self
+            .sort
+            .iter()
+            .map(|(field, direction)| format!("{}:{}", field.name, if *direction == SortDirection::Asc { "asc" } else { "desc" }))
+            .collect();
+        let filters: Vec<String> = self.filters.iter().map(|(field, value)| format!("{}={:?}", field.name, value)).collect();
+        format!("offset:{}:per_page:{}:sort:{}:filter:{}", self.offset, self.per_page, sort.join(","), filters.join(","))
+    }
+
+    /// Filter, sort and page an in-memory collection
+    pub fn apply<T: Listable>(&self, items: Vec<T>) -> PagedResponse<T> {
+        let mut items: Vec<T> = items
+            .into_iter()
+            .filter(|item| {
+                self.filters
+                    .iter()
+                    .all(|(field, wanted)| item.field_value(field.name).is_some_and(|value| value.matches(wanted)))
+            })
+            .collect();
+        // Stable sort, so equal keys keep the source order like the SQL tiebreaker
+        items.sort_by(|a, b| {
+            self.sort
+                .iter()
+                .map(|(field, direction)| {
+                    let ordering = match (a.field_value(field.name), b.field_value(field.name)) {
+                        (Some(a), Some(b)) => a.compare(&b),
+                        (a, b) => a.is_some().cmp(&b.is_some()),
+                    };
+                    match direction {
+                        SortDirection::Asc => ordering,
+                        SortDirection::Desc => ordering.reverse(),
+                    }
+                })
+                .find(|ordering| *ordering != Ordering::Equal)
+                .unwrap_or(Ordering::Equal)
+        });
+
+        let total = items.len() as u64;
+        let page = items
+            .into_iter()
+            .skip(self.offset.min(usize::MAX as u64) as usize)
+            .take(self.per_page as usize)
+            .collect();
+        self.respond(page, total)
+    }
+
+    /// `SELECT` for the requested page: `select` must end with the `FROM`
+    /// clause, and WHERE, ORDER BY, LIMIT and OFFSET are appended
+    pub fn select<'a>(&self, select: &str) -> QueryBuilder<'a, Postgres> {
+        let mut builder = QueryBuilder::new(select);
+        self.push_filters(&mut builder);
+
+        builder.push(" ORDER BY ");
+        for (field, direction) in &self.sort {
+            builder.push(field.column).push(" ").push(direction.sql()).push(", ");
+        }
+        builder.push(self.spec.tiebreaker);
+
+        builder.push(" LIMIT ").push_bind(self.per_page as i64);
+        builder.push(" OFFSET ").push_bind(self.offset.min(i64::MAX as u64) as i64);
+        builder
+    }
+
+    /// `SELECT COUNT(*)` over the filtered rows; `from` is the `FROM` clause
+    pub fn count<'a>(&self, from: &str) -> QueryBuilder<'a, Postgres> {
+        let mut builder = QueryBuilder::new("SELECT COUNT(*) ");
+        builder.push(from);
+        self.push_filters(&mut builder);
+        builder
+    }
+
+    fn push_filters(&self, builder: &mut QueryBuilder<'_, Postgres>) {
+        for (i, (field, value)) in self.filters.iter().enumerate() {
+            builder.push(if i == 0 { " WHERE " } else { " AND " });
+            if let FieldValue::Text(_) = value {
+                builder.push("lower(").push(field.column).push(") = lower(");
+                value.push_bind(builder);
+                builder.push(")");
+            } else {
+                builder.push(field.column).push(" = ");
+                value.push_bind(builder);
+            }
+        }
+    }
+
+    /// Wrap one page of results, `total` being the count of all matches
+    pub fn respond<T>(&self, data: Vec<T>, total: u64) -> PagedResponse<T> {
+        let next_offset = self.offset + data.len() as u64;
+        PagedResponse {
+            success: true,
+            pagination: Pagination {
+                page: self.page,
+                per_page: self.per_page,
+                total_items: total,
+                total_pages: total.div_ceil(self.per_page),
+                next_cursor: (!data.is_empty() && next_offset < total).then(|| encode_cursor(next_offset)),
+            },
+            data,
+        }
+    }
+}
+
+/// Envelope of every list response
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PagedResponse<T> {
+    pub success: bool,
+    pub data: Vec<T>,
+    pub pagination: Pagination,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Pagination {
+    pub page: u64,
+    pub per_page: u64,
+    pub total_items: u64,
+    pub total_pages: u64,
+    /// Pass as `cursor` to fetch the following page; absent on the last page
+    pub next_cursor: Option<String>,
+}
+
+fn parse_positive(name: &str, value: &str) -> Result<u64, ListError> {
+    value
+        .parse::<u64>()
+        .ok()
+        .filter(|n| *n > 0)
+        .ok_or_else(|| ListError(format!("{} must be a positive integer", name)))
+}
+
+fn parse_sort(spec: &ListSpec, raw: &str) -> Result<Vec<(&'static ListField, SortDirection)>, ListError> {
+    let mut sort: Vec<(&'static ListField, SortDirection)> = Vec::new();
+    for term in raw.split(',').map(str::trim).filter(|term| !term.is_empty()) {
+        let (name, direction) = match term.split_once(':') {
+            Some((name, "asc")) => (name, SortDirection::Asc),
+            Some((name, "desc")) => (name, SortDirection::Desc),
+            Some((_, other)) => {
+                return Err(ListError(format!("Invalid sort direction '{}'; use asc or desc", other)));
+            }
+            None => (term, SortDirection::Asc),
+        };
+        let field = spec.field(name).filter(|field| field.sortable).ok_or_else(|| {
+            ListError(format!(
+                "Cannot sort by '{}'; sortable fields are: {}",
+                name,
+                spec.names(|field| field.sortable)
+            ))
+        })?;
+        if sort.iter().any(|(existing, _)| existing.name == field.name) {
+            return Err(ListError(format!("Sort field '{}' is given more than once", name)));
+        }
+        sort.push((field, direction));
+    }
+    Ok(sort)
+}
+
+fn kind_name(kind: FieldKind) -> &'static str {
+    match kind {
+        FieldKind::Text => "text",
+        FieldKind::Number => "a number",
+        FieldKind::Boolean => "true or false",
+        FieldKind::Timestamp => "an RFC 3339 timestamp",
+    }
+}
+
+fn encode_cursor(offset: u64) -> String {
+    format!("{}{:x}", CURSOR_PREFIX, offset)
+}
+
+fn decode_cursor(cursor: &str) -> Result<u64, ListError> {
+    cursor
+        .strip_prefix(CURSOR_PREFIX)
+        .and_then(|hex| u64::from_str_radix(hex, 16).ok())
+        .ok_or_else(|| ListError("Invalid cursor".to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static SPEC: ListSpec = ListSpec {
+        fields: &[
+            ListField { name: "name", column: "name", kind: FieldKind::Text, sortable: true, filterable: true },
+            ListField { name: "size", column: "size_bytes", kind: FieldKind::Number, sortable: true, filterable: false },
+            ListField { name: "active", column: "is_active", kind: FieldKind::Boolean, sortable: false, filterable: true },
+        ],
+        default_sort: "name:asc",
+        tiebreaker: "id",
+    };
+
+    struct Item {
+        name: &'static str,
+        size: f64,
+        active: bool,
+    }
+
+    impl Listable for Item {
+        fn field_value(&self, field: &str) -> Option<FieldValue> {
+            match field {
+                "name" => Some(FieldValue::Text(self.name.to_string())),
+                "size" => Some(FieldValue::Number(self.size)),
+                "active" => Some(FieldValue::Boolean(self.active)),
+                _ => None,
+            }
+        }
+    }
+
+    fn params(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+    }
+
+    fn items() -> Vec<Item> {
+        vec![
+            Item { name: "b", size: 3.0, active: true },
+            Item { name: "a", size: 1.0, active: true },
+            Item { name: "c", size: 2.0, active: false },
+            Item { name: "d", size: 5.0, active: true },
+        ]
+    }
+
+    #[test]
+    fn test_rejects_unknown_fields_and_bad_values() {
+        for bad in [
+            vec![("sort", "password:asc")],
+            vec![("sort", "active")],
+            vec![("sort", "name:sideways")],
+            vec![("filter[size]", "3")],
+            vec![("filter[owner]", "x")],
+            vec![("filter[active]", "maybe")],
+            vec![("per_page", "0")],
+            vec![("per_page", "101")],
+            vec![("page", "2"), ("cursor", "o14")],
+            vec![("cursor", "14")],
+        ] {
+            assert!(ListQuery::parse(&SPEC, &params(&bad)).is_err(), "{:?} should be rejected", bad);
+        }
+        // Parameters the layer does not own are left to the handler
+        assert!(ListQuery::parse(&SPEC, &params(&[("q", "search")])).is_ok());
+    }
+
+    #[test]
+    fn test_in_memory_filter_sort_and_page() {
+        let query = ListQuery::parse(
+            &SPEC,
+            &params(&[("filter[active]", "true"), ("sort", "size:desc"), ("per_page", "2")]),
+        )
+        .unwrap();
+        let page = query.apply(items());
+        let names: Vec<&str> = page.data.iter().map(|item| item.name).collect();
+        assert_eq!(names, vec!["d", "b"]);
+        assert_eq!(page.pagination.total_items, 3);
+        assert_eq!(page.pagination.total_pages, 2);
+
+        // The cursor continues exactly where the first page stopped
+        let cursor = page.pagination.next_cursor.unwrap();
+        let next = ListQuery::parse(
+            &SPEC,
+            &params(&[("filter[active]", "true"), ("sort", "size:desc"), ("per_page", "2"), ("cursor", &cursor)]),
+        )
+        .unwrap()
+        .apply(items());
+        assert_eq!(next.data.iter().map(|item| item.name).collect::<Vec<_>>(), vec!["a"]);
+        assert_eq!(next.pagination.page, 2);
+        assert!(next.pagination.next_cursor.is_none());
+    }
+
+    #[test]
+    fn test_sql_binds_values_and_whitelists_columns() {
+        let query = ListQuery::parse(
+            &SPEC,
+            &params(&[("filter[name]", "x' OR '1'='1"), ("sort", "size:desc"), ("page", "3"), ("per_page", "10")]),
+        )
+        .unwrap();
+        assert_eq!(query.offset(), 20);
+
+        let select = query.select("SELECT id, name FROM items");
+        assert_eq!(
+            select.sql(),
+            "SELECT id, name FROM items WHERE lower(name) = lower($1) ORDER BY size_bytes DESC, id LIMIT $2 OFFSET $3"
+        );
+        assert_eq!(query.count("FROM items").sql(), "SELECT COUNT(*) FROM items WHERE lower(name) = lower($1)");
+    }
+}
@@ -19,13 +19,16 @@ use tower_http::{
     compression::CompressionLayer,
 };
 use uuid::Uuid;
+use aion_core::{Criticality, HealthRegistry, HealthReport, HealthStatus};
 
 mod handlers;
+mod listing;
 mod models;
 mod middleware;
 mod services;
 mod openapi;
 mod secrets_manager;
+mod versioning;
 
 use handlers::*;
 use models::*;
@@ -43,6 +46,13 @@ pub struct AppState {
     pub ai_service: Arc<AIService>,
     pub deployment_service: Arc<DeploymentService>,
     pub auth_service: Arc<AuthService>,
+    pub analytics_service: Arc<AnalyticsService>,
+    pub experiment_service: Arc<ExperimentService>,
+    pub health: Arc<HealthRegistry>,
+    pub upload_service: Arc<UploadService>,
+    pub webhook_service: Arc<WebhookService>,
+    /// Versions of mutable resources without a service of their own
+    pub versions: Arc<versioning::VersionStore>,
     // pub optimization_engine: Arc<RwLock<OptimizationEngine>>,
     pub config: AppConfig,
 }
@@ -58,6 +68,11 @@ pub struct AppConfig {
     pub cors_origins: Vec<String>,
     pub rate_limit: u32,
     pub max_request_size: usize,
+    /// Cap for streamed uploads, which bypass `max_request_size`
+    pub max_upload_size: u64,
+    pub upload_dir: std::path::PathBuf,
+    /// Signing secret of the Stripe webhook endpoint (`whsec_...`)
+    pub stripe_webhook_secret: String,
 }
 
 impl Default for AppConfig {
@@ -75,6 +90,9 @@ impl Default for AppConfig {
             ],
             rate_limit: 1000,
             max_request_size: 50 * 1024 * 1024, // 50MB
+            max_upload_size: 1024 * 1024 * 1024, // 1GB
+            upload_dir: std::env::temp_dir().join("ectus-uploads"),
+            stripe_webhook_secret: String::new(),
         }
     }
 }
@@ -118,6 +136,15 @@ async fn main() -> anyhow::Result<()> {
     let ai_service = Arc::new(AIService::new().await?);
     let deployment_service = Arc::new(DeploymentService::new().await?);
     let auth_service = Arc::new(AuthService::new(&secrets_config.jwt_secret)?);
+    let analytics_service = Arc::new(AnalyticsService::new().await?);
+    let experiment_service = Arc::new(ExperimentService::new().await?);
+    let upload_service = Arc::new(UploadService::new(services::uploads::UploadConfig {
+        upload_dir: config.upload_dir.clone(),
+        max_upload_bytes: config.max_upload_size,
+        ..Default::default()
+    }).await?);
+    upload_service.start_cleanup(Duration::from_secs(15 * 60));
+    let webhook_service = Arc::new(WebhookService::new().await?);
 
     println!("✅ Secrets manager initialized with {} backend",
         match secrets_manager.backend {
@@ -125,6 +152,7 @@ async fn main() -> anyhow::Result<()> {
             SecretBackend::Vault { .. } => "HashiCorp Vault",
             SecretBackend::AwsSecretsManager { .. } => "AWS Secrets Manager",
             SecretBackend::AzureKeyVault { .. } => "Azure Key Vault",
+            SecretBackend::GcpSecretManager { .. } => "GCP Secret Manager",
         }
     );
 
@@ -138,11 +166,24 @@ async fn main() -> anyhow::Result<()> {
     // Start optimization engine
     // optimization_engine.write().await.start().await?;
 
+    let health = create_health_registry(
+        &monitoring_service,
+        &ai_service,
+        &deployment_service,
+        &analytics_service,
+    ).await;
+
     let app_state = AppState {
         monitoring_service,
         ai_service,
         deployment_service,
         auth_service,
+        analytics_service,
+        experiment_service,
+        health,
+        upload_service,
+        webhook_service,
+        versions: Arc::new(versioning::VersionStore::new()),
         // optimization_engine,
         config: config.clone(),
     };
@@ -171,12 +212,15 @@ fn create_router(state: AppState) -> Router {
     Router::new()
         // Health check endpoint
         .route("/health", get(health_check))
+        .route("/ready", get(readiness_check))
 
         // API v1 routes
         .nest("/api/v1", create_api_v1_router())
 
         // WebSocket endpoint for real-time updates
         .route("/ws", get(websocket_handler))
+        .route("/ws/deployments/:id/logs", get(stream_deployment_logs))
+        .route("/ws/projects/:id/logs", get(stream_project_logs))
 
         // OpenAPI documentation endpoints
         .route("/api/openapi.json", get(serve_openapi_json))
@@ -199,6 +243,7 @@ fn create_router(state: AppState) -> Router {
                     TraceLayer::new_for_http()
                         .make_span_with(DefaultMakeSpan::default().include_headers(true)),
                 )
+                .layer(axum::middleware::from_fn(middleware::correlation_id_middleware))
         )
         .with_state(state)
 }
@@ -226,14 +271,18 @@ fn create_api_v1_router() -> Router<AppState> {
         .route("/ai/fix", post(fix_code))
         .route("/ai/refactor", post(refactor_code))
         .route("/ai/qa", post(run_autonomous_qa))
+        .route("/ai/providers", get(get_provider_metrics))
 
         // Deployment management
         .route("/deployments", get(list_deployments))
         .route("/deployments", post(create_deployment))
+        .route("/deployments/plan", post(plan_deployment))
         .route("/deployments/:id", get(get_deployment))
         .route("/deployments/:id", put(update_deployment))
         .route("/deployments/:id", delete(delete_deployment))
         .route("/deployments/:id/logs", get(get_deployment_logs))
+        .route("/deployments/:id/history", get(get_deployment_history))
+        .route("/deployments/:id/revert", post(revert_deployment))
 
         // Project management
         .route("/projects", get(list_projects))
@@ -242,6 +291,20 @@ fn create_api_v1_router() -> Router<AppState> {
         .route("/projects/:id", put(update_project))
         .route("/projects/:id", delete(delete_project))
 
+        // Streaming uploads for inputs too large for a JSON body
+        .route("/uploads", post(upload_file).layer(DefaultBodyLimit::disable()))
+        .route("/uploads/:id", get(get_upload))
+        .route("/uploads/:id", delete(delete_upload))
+
+        // Outbound webhooks
+        .route("/webhooks", get(list_webhooks))
+        .route("/webhooks", post(create_webhook))
+        .route("/webhooks/:id", get(get_webhook))
+        .route("/webhooks/:id", delete(delete_webhook))
+        .route("/webhooks/:id/deliveries", get(list_webhook_deliveries))
+        .route("/webhooks/:id/deliveries/:delivery_id", get(get_webhook_delivery))
+        .route("/webhooks/:id/deliveries/:delivery_id/redeliver", post(redeliver_webhook_delivery))
+
         // Authentication
         .route("/auth/login", post(login))
         .route("/auth/refresh", post(refresh_token))
@@ -276,8 +339,12 @@ fn create_api_v1_router() -> Router<AppState> {
         .route("/analytics/funnel", get(get_conversion_funnel))
         .route("/analytics/cohorts", get(get_cohort_analysis))
         .route("/analytics/ab-tests", get(get_ab_test_results))
+        .route("/analytics/ab-tests", post(create_ab_test))
+        .route("/analytics/ab-tests/:id/results", get(get_ab_test_result))
+        .route("/analytics/ab-tests/:id/assignment", get(get_ab_test_assignment))
         .route("/analytics/realtime", get(get_realtime_analytics))
         .route("/analytics/feature-flag", post(track_feature_flag))
+        .route("/analytics/export", get(export_analytics))
 }
 
 /// Load application configuration
@@ -305,6 +372,18 @@ async fn load_config() -> anyhow::Result<AppConfig> {
         config.admin_token = admin_token;
     }
 
+    if let Ok(max_upload_size) = std::env::var("ECTUS_MAX_UPLOAD_SIZE") {
+        config.max_upload_size = max_upload_size.parse()?;
+    }
+
+    if let Ok(upload_dir) = std::env::var("ECTUS_UPLOAD_DIR") {
+        config.upload_dir = upload_dir.into();
+    }
+
+    if let Ok(stripe_webhook_secret) = std::env::var("STRIPE_WEBHOOK_SECRET") {
+        config.stripe_webhook_secret = stripe_webhook_secret;
+    }
+
     Ok(config)
 }
 
@@ -334,6 +413,10 @@ async fn root_handler() -> Html<&'static str> {
         <span class="method">GET</span> /health - Health check
     </div>
 
+    <div class="endpoint">
+        <span class="method">GET</span> /ready - Readiness probe
+    </div>
+
     <div class="endpoint">
         <span class="method">GET</span> /api/v1/status - System status
     </div>
@@ -346,14 +429,38 @@ async fn root_handler() -> Html<&'static str> {
         <span class="method">POST</span> /api/v1/ai/generate - Generate code
     </div>
 
+    <div class="endpoint">
+        <span class="method">GET</span> /api/v1/ai/providers - AI provider circuit breaker and bulkhead state
+    </div>
+
     <div class="endpoint">
         <span class="method">GET</span> /api/v1/deployments - List deployments
     </div>
 
+    <div class="endpoint">
+        <span class="method">POST</span> /api/v1/deployments/plan - Preview a deployment
+    </div>
+
+    <div class="endpoint">
+        <span class="method">GET</span> /api/v1/deployments/:id/history - Deployed versions of a deployment
+    </div>
+
+    <div class="endpoint">
+        <span class="method">POST</span> /api/v1/uploads - Stream a large file (multipart) for generation or analysis
+    </div>
+
     <div class="endpoint">
         <span class="method">GET</span> /ws - WebSocket connection for real-time updates
     </div>
 
+    <div class="endpoint">
+        <span class="method">GET</span> /ws/deployments/:id/logs - Live deployment logs
+    </div>
+
+    <div class="endpoint">
+        <span class="method">GET</span> /ws/projects/:id/logs - Live logs of a project's latest deployment
+    </div>
+
     <p><strong>Documentation:</strong> <a href="/docs">/docs</a></p>
     <p><strong>Dashboard:</strong> <a href="https://dashboard.ectus.ai">https://dashboard.ectus.ai</a></p>
 </body>
@@ -381,6 +488,58 @@ async fn health_check() -> Json<serde_json::Value> {
     }))
 }
 
+/// Readiness probe: 503 while a critical component is failing
+async fn readiness_check(State(state): State<AppState>) -> (StatusCode, Json<HealthReport>) {
+    let report = state.health.check_all().await;
+    let status = if report.is_ready() { StatusCode::OK } else { StatusCode::SERVICE_UNAVAILABLE };
+    (status, Json(report))
+}
+
+/// Register the components the readiness probe checks
+pub async fn create_health_registry(
+    monitoring_service: &Arc<MonitoringService>,
+    ai_service: &Arc<AIService>,
+    deployment_service: &Arc<DeploymentService>,
+    analytics_service: &Arc<AnalyticsService>,
+) -> Arc<HealthRegistry> {
+    let registry = HealthRegistry::new();
+
+    let ai = ai_service.clone();
+    registry.register("ai_engine", Criticality::Critical, move || {
+        let ai = ai.clone();
+        async move { Ok::<_, anyhow::Error>(service_health(&ai.get_health_status().await?.status)) }
+    }).await;
+
+    let monitoring = monitoring_service.clone();
+    registry.register("monitoring", Criticality::NonCritical, move || {
+        let monitoring = monitoring.clone();
+        async move { Ok::<_, anyhow::Error>(service_health(&monitoring.get_system_health().await?.status)) }
+    }).await;
+
+    let deployments = deployment_service.clone();
+    registry.register("deployments", Criticality::NonCritical, move || {
+        let deployments = deployments.clone();
+        async move { Ok::<_, anyhow::Error>(service_health(&deployments.get_health_status().await?.status)) }
+    }).await;
+
+    let analytics = analytics_service.clone();
+    registry.register("analytics", Criticality::NonCritical, move || {
+        let analytics = analytics.clone();
+        async move { Ok::<_, anyhow::Error>(service_health(&analytics.get_health_status().await?.status)) }
+    }).await;
+
+    Arc::new(registry)
+}
+
+/// Map the status strings our services report onto a health status
+fn service_health(status: &str) -> HealthStatus {
+    match status {
+        "operational" | "healthy" => HealthStatus::Healthy,
+        "degraded" | "warning" => HealthStatus::Degraded,
+        _ => HealthStatus::Unhealthy,
+    }
+}
+
 /// Serve OpenAPI JSON specification
 async fn serve_openapi_json() -> (StatusCode, Json<serde_json::Value>) {
     match openapi::export_json() {
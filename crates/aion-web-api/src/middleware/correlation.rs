@@ -0,0 +1,70 @@
+//! Request correlation
+//!
+//! Runs every request under a correlation id, taken from the caller's
+//! `X-Request-ID` header or generated, so logs written while serving it
+//! (slow query warnings included) can be tied back to the request.
+
+use axum::{
+    extract::Request,
+    http::HeaderValue,
+    middleware::Next,
+    response::Response,
+};
+use uuid::Uuid;
+
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// Longest caller-supplied id accepted; longer ones are replaced
+const MAX_REQUEST_ID_LEN: usize = 128;
+
+pub async fn correlation_id_middleware(request: Request, next: Next) -> Response {
+    let correlation_id = request
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .filter(|id| !id.is_empty() && id.len() <= MAX_REQUEST_ID_LEN)
+        .map(str::to_string)
+        .unwrap_or_else(|| Uuid::new_v4().to_string());
+
+    let mut response = aion_core::with_correlation_id(correlation_id.clone(), next.run(request)).await;
+    if let Ok(value) = HeaderValue::from_str(&correlation_id) {
+        response.headers_mut().insert(REQUEST_ID_HEADER, value);
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Body, routing::get, Router};
+    use tower::ServiceExt;
+
+    fn app() -> Router {
+        Router::new()
+            .route("/", get(|| async { aion_core::current_correlation_id().unwrap_or_default() }))
+            .layer(axum::middleware::from_fn(correlation_id_middleware))
+    }
+
+    async fn body_text(response: Response) -> String {
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        String::from_utf8(bytes.to_vec()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_handlers_run_under_the_callers_request_id() {
+        let request = Request::builder().uri("/").header(REQUEST_ID_HEADER, "req-42").body(Body::empty()).unwrap();
+        let response = app().oneshot(request).await.unwrap();
+
+        assert_eq!(response.headers()[REQUEST_ID_HEADER], "req-42");
+        assert_eq!(body_text(response).await, "req-42");
+    }
+
+    #[tokio::test]
+    async fn test_requests_without_an_id_get_one() {
+        let response = app().oneshot(Request::builder().uri("/").body(Body::empty()).unwrap()).await.unwrap();
+
+        let header = response.headers()[REQUEST_ID_HEADER].to_str().unwrap().to_string();
+        assert!(Uuid::parse_str(&header).is_ok());
+        assert_eq!(body_text(response).await, header);
+    }
+}
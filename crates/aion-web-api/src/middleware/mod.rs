@@ -3,7 +3,12 @@
 pub mod auth;
 pub mod rate_limit;
 pub mod cors;
+pub mod tenancy;
+pub mod correlation;
+pub mod error_handling;
 
 pub use auth::*;
 pub use rate_limit::*;
-pub use cors::*;
\ No newline at end of file
+pub use cors::*;
+pub use tenancy::*;
+pub use correlation::*;
\ No newline at end of file
@@ -0,0 +1,59 @@
+//! Tenancy guard
+//!
+//! Extracts the caller's organization from a validated bearer token so handlers
+//! can scope every query to data the caller is allowed to see.
+
+use axum::{
+    async_trait,
+    extract::FromRequestParts,
+    http::{header, request::Parts, StatusCode},
+    Json,
+};
+use crate::AppState;
+
+/// Authenticated caller and the organization all queries must be scoped to
+#[derive(Debug, Clone)]
+pub struct TenantContext {
+    pub user_id: String,
+    pub org_id: String,
+    pub role: String,
+}
+
+#[async_trait]
+impl FromRequestParts<AppState> for TenantContext {
+    type Rejection = (StatusCode, Json<serde_json::Value>);
+
+    async fn from_request_parts(parts: &mut Parts, state: &AppState) -> Result<Self, Self::Rejection> {
+        let token = parts
+            .headers
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .ok_or_else(|| unauthorized("Missing bearer token"))?;
+
+        let claims = state
+            .auth_service
+            .validate_token(token)
+            .await
+            .map_err(|_| unauthorized("Invalid or expired token"))?;
+
+        // Users without an organization act within their personal workspace
+        let org_id = claims.org_id.clone().unwrap_or_else(|| claims.sub.clone());
+
+        Ok(TenantContext {
+            user_id: claims.sub,
+            org_id,
+            role: claims.role,
+        })
+    }
+}
+
+fn unauthorized(message: &str) -> (StatusCode, Json<serde_json::Value>) {
+    (
+        StatusCode::UNAUTHORIZED,
+        Json(serde_json::json!({
+            "success": false,
+            "error": message
+        })),
+    )
+}
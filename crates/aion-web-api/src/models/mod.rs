@@ -175,6 +175,8 @@ pub struct Deployment {
     pub updated_at: chrono::DateTime<chrono::Utc>,
     pub url: Option<String>,
     pub health_score: f64,
+    /// Increases on every update; also sent as the `ETag` header
+    pub version: u64,
 }
 
 /// Project information
@@ -202,6 +204,8 @@ pub struct Project {
     pub deployment_url: Option<String>,
     pub visibility: String,
     pub tags: Vec<String>,
+    /// Increases on every update; also sent as the `ETag` header
+    pub version: u64,
 }
 
 /// AI generation request
@@ -212,6 +216,32 @@ pub struct GenerateRequest {
     pub framework: Option<String>,
     pub features: Vec<String>,
     pub deployment_target: Option<String>,
+    /// Sampling temperature; 0.0 selects greedy decoding
+    pub temperature: Option<f32>,
+    /// Seed for reproducible sampling
+    pub seed: Option<u64>,
+    /// Keep only the k most likely tokens
+    #[serde(default)]
+    pub top_k: Option<usize>,
+    /// Nucleus sampling mass, in (0, 1]
+    #[serde(default)]
+    pub top_p: Option<f32>,
+    /// Drop tokens less likely than this fraction of the most likely one, in [0, 1]
+    #[serde(default)]
+    pub min_p: Option<f32>,
+    /// Locally typical sampling mass, in (0, 1]
+    #[serde(default)]
+    pub typical_p: Option<f32>,
+    /// Perplexity-targeted sampling; replaces top_k, typical_p, top_p and min_p
+    #[serde(default)]
+    pub mirostat: Option<aion_ai_engine::generation::MirostatConfig>,
+    /// Text file from `POST /uploads` included as reference input
+    #[serde(default)]
+    pub upload_id: Option<Uuid>,
+    /// Most sensitive data the caller may receive; can only lower the
+    /// service's configured clearance
+    #[serde(default)]
+    pub clearance: Option<aion_compliance::DataClassification>,
 }
 
 /// AI generation response
@@ -223,6 +253,26 @@ pub struct GenerateResponse {
     pub deployment_instructions: Option<String>,
     pub estimated_time: u64,
     pub confidence_score: f64,
+    /// What the output filter redacted or blocked, when it is enabled
+    #[serde(default)]
+    pub output_filter: Option<OutputFilterSummary>,
+}
+
+/// Output filtering applied to a generation, for auditing
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct OutputFilterSummary {
+    /// Clearance the output was filtered for
+    pub clearance: aion_compliance::DataClassification,
+    pub blocked: bool,
+    /// Files the filter changed or blocked
+    pub files: Vec<FileFilterReport>,
+}
+
+/// Filter report for one generated file
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct FileFilterReport {
+    pub path: String,
+    pub report: aion_ai_engine::output_filter::FilterReport,
 }
 
 /// Generated file
@@ -250,6 +300,14 @@ pub struct LoginResponse {
     pub user: User,
 }
 
+/// Token refresh response; `refresh_token` replaces the one presented
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct RefreshResponse {
+    pub access_token: String,
+    pub refresh_token: String,
+    pub expires_in: u64,
+}
+
 /// User information
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct User {
@@ -1,13 +1,16 @@
 //! Secrets Management Module
 //!
 //! Implements secure secrets management following audit recommendation #2
-//! Supports multiple backends: environment variables, HashiCorp Vault, AWS Secrets Manager
+//! Supports multiple backends: environment variables, HashiCorp Vault, AWS Secrets Manager,
+//! Azure Key Vault and GCP Secret Manager
 
 use std::collections::HashMap;
 use std::sync::Arc;
 use tokio::sync::RwLock;
 use serde::{Deserialize, Serialize};
 use anyhow::{Context, Result};
+use async_trait::async_trait;
+use base64::Engine;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum SecretBackend {
@@ -26,6 +29,9 @@ pub enum SecretBackend {
         tenant_id: String,
         client_id: String,
     },
+    GcpSecretManager {
+        project: String,
+    },
 }
 
 #[derive(Debug, Clone)]
@@ -33,6 +39,106 @@ pub struct SecretsManager {
     backend: SecretBackend,
     cache: Arc<RwLock<HashMap<String, Secret>>>,
     cache_ttl_seconds: u64,
+    gcp_client: Arc<dyn GcpSecretClient>,
+    /// Read secrets the GCP backend fails to fetch from environment variables
+    env_fallback: bool,
+}
+
+/// Access to GCP Secret Manager
+#[async_trait]
+pub trait GcpSecretClient: Send + Sync + std::fmt::Debug {
+    /// Latest version of secret `name` in `project`
+    async fn access_secret(&self, project: &str, name: &str) -> Result<Secret>;
+}
+
+/// GCP Secret Manager REST API client. Authenticates with `GCP_ACCESS_TOKEN`
+/// when set, otherwise with the service account of the GCE/GKE metadata server.
+#[derive(Debug, Clone)]
+pub struct GcpRestClient {
+    client: reqwest::Client,
+    base_url: String,
+}
+
+impl GcpRestClient {
+    pub fn new() -> Self {
+        Self::with_base_url("https://secretmanager.googleapis.com")
+    }
+
+    pub fn with_base_url(base_url: impl Into<String>) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            base_url: base_url.into(),
+        }
+    }
+
+    async fn access_token(&self) -> Result<String> {
+        if let Ok(token) = std::env::var("GCP_ACCESS_TOKEN") {
+            return Ok(token);
+        }
+
+        let response: serde_json::Value = self
+            .client
+            .get("http://metadata.google.internal/computeMetadata/v1/instance/service-accounts/default/token")
+            .header("Metadata-Flavor", "Google")
+            .send()
+            .await
+            .context("Failed to reach the GCP metadata server")?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        response["access_token"]
+            .as_str()
+            .map(str::to_string)
+            .context("Invalid GCP metadata token response")
+    }
+}
+
+impl Default for GcpRestClient {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl GcpSecretClient for GcpRestClient {
+    async fn access_secret(&self, project: &str, name: &str) -> Result<Secret> {
+        let response = self
+            .client
+            .get(format!(
+                "{}/v1/projects/{}/secrets/{}/versions/latest:access",
+                self.base_url.trim_end_matches('/'),
+                project,
+                name
+            ))
+            .bearer_auth(self.access_token().await?)
+            .send()
+            .await
+            .context("Failed to fetch from GCP Secret Manager")?;
+
+        if !response.status().is_success() {
+            anyhow::bail!("GCP Secret Manager returned status: {}", response.status());
+        }
+
+        let data: serde_json::Value = response.json().await?;
+        let payload = data["payload"]["data"]
+            .as_str()
+            .context("Invalid GCP Secret Manager response format")?;
+        let value = String::from_utf8(base64::engine::general_purpose::STANDARD.decode(payload)?)
+            .context("GCP secret is not valid UTF-8")?;
+
+        // "projects/<project>/secrets/<name>/versions/<version>"
+        let version = data["name"]
+            .as_str()
+            .and_then(|name| name.rsplit('/').next())
+            .map(str::to_string);
+
+        Ok(Secret {
+            value,
+            version,
+            retrieved_at: std::time::SystemTime::now(),
+        })
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -50,6 +156,8 @@ impl SecretsManager {
             backend,
             cache: Arc::new(RwLock::new(HashMap::new())),
             cache_ttl_seconds: 300, // 5 minutes default
+            gcp_client: Arc::new(GcpRestClient::new()),
+            env_fallback: false,
         }
     }
 
@@ -85,10 +193,21 @@ impl SecretsManager {
 
                 SecretBackend::AzureKeyVault { vault_url, tenant_id, client_id }
             }
+            "gcp" => {
+                let project = std::env::var("GCP_PROJECT")
+                    .or_else(|_| std::env::var("GOOGLE_CLOUD_PROJECT"))
+                    .context("GCP_PROJECT required for GCP backend")?;
+
+                SecretBackend::GcpSecretManager { project }
+            }
             _ => SecretBackend::Environment,
         };
 
-        Ok(Self::new(backend))
+        let env_fallback = std::env::var("SECRETS_ENV_FALLBACK")
+            .map(|value| value == "true" || value == "1")
+            .unwrap_or(false);
+
+        Ok(Self::new(backend).with_env_fallback(env_fallback))
     }
 
     /// Set cache TTL in seconds
@@ -97,6 +216,20 @@ impl SecretsManager {
         self
     }
 
+    /// Use `client` to reach GCP Secret Manager
+    pub fn with_gcp_client(mut self, client: Arc<dyn GcpSecretClient>) -> Self {
+        self.gcp_client = client;
+        self
+    }
+
+    /// Fall back to environment variables when GCP Secret Manager cannot
+    /// provide a secret. Off by default, so an outage is not silently
+    /// papered over with stale local values.
+    pub fn with_env_fallback(mut self, enabled: bool) -> Self {
+        self.env_fallback = enabled;
+        self
+    }
+
     /// Get a secret by key
     pub async fn get_secret(&self, key: &str) -> Result<String> {
         // Check cache first
@@ -181,6 +314,19 @@ impl SecretsManager {
             SecretBackend::AzureKeyVault { vault_url, tenant_id, client_id } => {
                 self.fetch_from_azure(key, vault_url, tenant_id, client_id).await
             }
+            SecretBackend::GcpSecretManager { project } => self.fetch_from_gcp(key, project).await,
+        }
+    }
+
+    /// Fetch from GCP Secret Manager
+    async fn fetch_from_gcp(&self, key: &str, project: &str) -> Result<Secret> {
+        match self.gcp_client.access_secret(project, key).await {
+            Ok(secret) => Ok(secret),
+            Err(e) if self.env_fallback => {
+                tracing::warn!("GCP Secret Manager failed for '{}', using environment: {}", key, e);
+                self.fetch_from_env(key).await
+            }
+            Err(e) => Err(e.context(format!("Failed to fetch secret '{}' from GCP project '{}'", key, project))),
         }
     }
 
@@ -392,4 +538,58 @@ mod tests {
         std::env::remove_var("SECRET_2");
         std::env::remove_var("SECRET_3");
     }
+
+    /// Counts calls, failing them when `fail` is set
+    #[derive(Debug, Default)]
+    struct MockGcpClient {
+        calls: std::sync::atomic::AtomicUsize,
+        fail: bool,
+    }
+
+    #[async_trait]
+    impl GcpSecretClient for MockGcpClient {
+        async fn access_secret(&self, project: &str, name: &str) -> Result<Secret> {
+            let call = self.calls.fetch_add(1, std::sync::atomic::Ordering::SeqCst) + 1;
+            if self.fail {
+                anyhow::bail!("GCP Secret Manager returned status: 503 Service Unavailable");
+            }
+            Ok(Secret {
+                value: format!("{}/{}", project, name),
+                version: Some(call.to_string()),
+                retrieved_at: std::time::SystemTime::now(),
+            })
+        }
+    }
+
+    fn gcp_manager(client: Arc<MockGcpClient>) -> SecretsManager {
+        SecretsManager::new(SecretBackend::GcpSecretManager { project: "ectus-prod".to_string() })
+            .with_gcp_client(client)
+    }
+
+    #[tokio::test]
+    async fn test_gcp_backend_serves_cache_hits_without_calls() {
+        let client = Arc::new(MockGcpClient::default());
+        let manager = gcp_manager(client.clone());
+
+        assert_eq!(manager.get_secret("JWT_SECRET").await.unwrap(), "ectus-prod/JWT_SECRET");
+        assert_eq!(manager.get_secret("JWT_SECRET").await.unwrap(), "ectus-prod/JWT_SECRET");
+        assert_eq!(client.calls.load(std::sync::atomic::Ordering::SeqCst), 1);
+
+        manager.refresh_secret("JWT_SECRET").await.unwrap();
+        manager.get_secret("REDIS_URL").await.unwrap();
+        assert_eq!(client.calls.load(std::sync::atomic::Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_gcp_backend_falls_back_to_env_only_when_configured() {
+        std::env::set_var("GCP_FALLBACK_SECRET", "local_value");
+        let client = Arc::new(MockGcpClient { fail: true, ..Default::default() });
+
+        assert!(gcp_manager(client.clone()).get_secret("GCP_FALLBACK_SECRET").await.is_err());
+
+        let manager = gcp_manager(client).with_env_fallback(true);
+        assert_eq!(manager.get_secret("GCP_FALLBACK_SECRET").await.unwrap(), "local_value");
+
+        std::env::remove_var("GCP_FALLBACK_SECRET");
+    }
 }
@@ -1,9 +1,25 @@
 //! AI service for code generation and analysis
+//!
+//! Code generation goes through a failover chain: the local AI engine first,
+//! then any remote LLM providers configured through API keys. Each provider
+//! sits behind its own bulkhead and circuit breaker (see `provider_pool`), so
+//! an outage at one provider cannot tie up requests meant for the others.
+//!
+//! Identical deterministic generation requests that arrive while one is
+//! already running share that run instead of starting their own. Only the
+//! local engine honors seeds, so such requests never fail over to a remote
+//! provider.
+//!
+//! When `AI_OUTPUT_FILTER` is set, generated files are screened before they
+//! are returned: personal data classified above the caller's clearance is
+//! redacted, and any `AI_OUTPUT_BLOCKLIST` term withholds the output.
 
 use anyhow::Result;
 use uuid::Uuid;
 use std::sync::Arc;
 use crate::models::*;
+use crate::services::provider_pool::{ProviderMetrics, ProviderPolicy, ProviderPool};
+use crate::services::single_flight::SingleFlight;
 use aion_ai_engine::{
     AIEngineConfig, initialize_ai_engine,
     code_generation::{CodeGenerator, GenerationRequest, GeneratedCode},
@@ -12,11 +28,258 @@ use aion_ai_engine::{
     refactoring_engine::RefactoringEngine,
     autonomous_qa::AutonomousQA,
     documentation_generator::DocumentationGenerator,
-    inference::{InferenceEngine, InferenceRequest},
+    inference::{InferenceEngine, InferenceParameters, InferenceRequest},
     performance::PerformanceMonitor,
+    llm_providers::{
+        CloudflareAIClient, GitHubModelsClient, GroqClient, HuggingFaceClient, LLMClient, LLMRequest, OpenAIClient,
+    },
+    nlp::EntityKind,
+    output_filter::{BlocklistRule, FilterAction, OutputFilter, OutputFilterPolicy},
 };
+use aion_compliance::DataClassification;
+
+/// A backend able to generate code
+#[derive(Clone)]
+enum CodeProvider {
+    Engine(Arc<CodeGenerator>),
+    Llm(Arc<dyn LLMClient>),
+}
+
+impl CodeProvider {
+    async fn generate(self, request: GenerationRequest) -> Result<GeneratedCode> {
+        match self {
+            CodeProvider::Engine(generator) => generator.generate_code(request).await,
+            CodeProvider::Llm(client) => {
+                let started = std::time::Instant::now();
+                let language = request.language.clone().unwrap_or_else(|| "rust".to_string());
+                let response = client.generate(&LLMRequest {
+                    prompt: format!(
+                        "Write {} code for the following request.\n\n{}\n\nRequirements:\n{}",
+                        language,
+                        request.prompt,
+                        request.requirements.join("\n")
+                    ),
+                    system_prompt: Some("You are an expert software engineer. Reply with the complete source file only.".to_string()),
+                    max_tokens: Some(4096),
+                    temperature: request.sampling.temperature,
+                    model: None,
+                }).await?;
+
+                Ok(GeneratedCode {
+                    generation_id: Uuid::new_v4(),
+                    files: vec![aion_ai_engine::code_generation::CodeFile {
+                        path: format!("generated.{}", file_extension(&language)),
+                        content: response.content.clone(),
+                        language: language.clone(),
+                        file_type: aion_ai_engine::code_generation::FileType::Source,
+                    }],
+                    metadata: aion_ai_engine::code_generation::GenerationMetadata {
+                        prompt: request.prompt.clone(),
+                        language,
+                        framework: request.framework.clone(),
+                        generation_time_ms: started.elapsed().as_millis() as _,
+                        // Single-shot remote output has not been through the engine's validation
+                        confidence_score: 0.6,
+                        complexity_score: 0.0,
+                        lines_of_code: response.content.lines().count(),
+                        estimated_runtime_performance: aion_ai_engine::code_generation::PerformanceMetrics::default(),
+                    },
+                    deployment_instructions: None,
+                })
+            }
+        }
+    }
+}
+
+/// Temperature for code generation when the request does not set one
+const DEFAULT_CODE_TEMPERATURE: f32 = 0.2;
+
+impl CodeProvider {
+    /// Whether the provider honors seeds and greedy decoding. Remote LLM
+    /// APIs only take a temperature, so their output is never reproducible.
+    fn is_reproducible(&self) -> bool {
+        matches!(self, CodeProvider::Engine(_))
+    }
+}
+
+/// Inference parameters carrying a generation request's sampling settings
+pub fn sampling_parameters(request: &GenerateRequest) -> InferenceParameters {
+    let defaults = InferenceParameters::default();
+    InferenceParameters {
+        temperature: Some(request.temperature.unwrap_or(DEFAULT_CODE_TEMPERATURE)),
+        top_p: request.top_p.or(defaults.top_p),
+        top_k: request.top_k,
+        min_p: request.min_p,
+        typical_p: request.typical_p,
+        mirostat: request.mirostat,
+        seed: request.seed,
+        greedy: request.temperature == Some(0.0),
+        ..defaults
+    }
+}
+
+/// Sensitivity of each kind of entity the output filter recognizes
+fn entity_classification(kind: EntityKind) -> DataClassification {
+    match kind {
+        EntityKind::CreditCard => DataClassification::Restricted,
+        EntityKind::Person | EntityKind::Email | EntityKind::PhoneNumber | EntityKind::IpAddress => {
+            DataClassification::Confidential
+        }
+        EntityKind::Url | EntityKind::Date | EntityKind::Money => DataClassification::Public,
+    }
+}
+
+fn classification_rank(classification: &DataClassification) -> u8 {
+    match classification {
+        DataClassification::Public => 0,
+        DataClassification::Internal => 1,
+        DataClassification::Confidential => 2,
+        DataClassification::Restricted => 3,
+        DataClassification::TopSecret => 4,
+    }
+}
+
+fn parse_classification(value: &str) -> Option<DataClassification> {
+    match value.trim().to_lowercase().replace(['-', '_'], "").as_str() {
+        "public" => Some(DataClassification::Public),
+        "internal" => Some(DataClassification::Internal),
+        "confidential" => Some(DataClassification::Confidential),
+        "restricted" => Some(DataClassification::Restricted),
+        "topsecret" => Some(DataClassification::TopSecret),
+        _ => None,
+    }
+}
+
+/// Output policy redacting every entity classified above `clearance`
+pub fn output_policy(clearance: &DataClassification) -> OutputFilterPolicy {
+    [
+        EntityKind::Person,
+        EntityKind::Email,
+        EntityKind::Url,
+        EntityKind::IpAddress,
+        EntityKind::PhoneNumber,
+        EntityKind::CreditCard,
+        EntityKind::Date,
+        EntityKind::Money,
+    ]
+    .into_iter()
+    .filter(|kind| classification_rank(&entity_classification(*kind)) > classification_rank(clearance))
+    .fold(OutputFilterPolicy::new(), |policy, kind| policy.with_entity(kind, FilterAction::Redact))
+}
+
+/// Output filter settings read from the environment
+struct OutputFilterSettings {
+    clearance: DataClassification,
+    blocklist: Vec<String>,
+}
+
+impl OutputFilterSettings {
+    /// `None` unless `AI_OUTPUT_FILTER` is set
+    fn from_env() -> Option<Self> {
+        std::env::var("AI_OUTPUT_FILTER").ok().filter(|v| !matches!(v.as_str(), "" | "0" | "false"))?;
+        let clearance = match std::env::var("AI_OUTPUT_CLEARANCE") {
+            Ok(value) => parse_classification(&value).unwrap_or_else(|| {
+                println!("⚠️ Unknown AI_OUTPUT_CLEARANCE {:?}, using Internal", value);
+                DataClassification::Internal
+            }),
+            Err(_) => DataClassification::Internal,
+        };
+        let blocklist = std::env::var("AI_OUTPUT_BLOCKLIST")
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|term| !term.is_empty())
+            .map(str::to_string)
+            .collect();
+        Some(Self { clearance, blocklist })
+    }
+
+    /// Filter for a caller, whose requested clearance may only narrow the
+    /// configured one
+    fn filter_for(&self, requested: Option<&DataClassification>) -> (OutputFilter, DataClassification) {
+        let clearance = match requested {
+            Some(requested) if classification_rank(requested) < classification_rank(&self.clearance) => requested.clone(),
+            _ => self.clearance.clone(),
+        };
+        let policy = self
+            .blocklist
+            .iter()
+            .fold(output_policy(&clearance), |policy, term| policy.with_rule(BlocklistRule::term(term, FilterAction::Block)));
+        (OutputFilter::new(policy), clearance)
+    }
+}
+
+/// AI engine request for a web API generation request, sampled with `sampling`
+fn generation_request(request: &GenerateRequest, sampling: InferenceParameters) -> GenerationRequest {
+    GenerationRequest {
+        prompt: request.prompt.clone(),
+        language: request.language.clone(),
+        framework: request.framework.clone(),
+        requirements: request.requirements.clone().unwrap_or_default(),
+        constraints: request.constraints.clone().unwrap_or_default(),
+        optimization_level: aion_ai_engine::code_generation::OptimizationLevel::Balanced,
+        include_tests: true,
+        include_docs: true,
+        include_ci: false,
+        sampling,
+    }
+}
+
+/// Normalized form of a deterministic generation request
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct GenerationKey {
+    prompt: String,
+    language: Option<String>,
+    framework: Option<String>,
+    features: Vec<String>,
+    deployment_target: Option<String>,
+    /// Reference input from `POST /uploads`
+    upload_id: Option<Uuid>,
+    seed: Option<u64>,
+    temperature_bits: Option<u32>,
+    top_k: Option<usize>,
+    /// top_p, min_p and typical_p
+    filter_bits: [Option<u32>; 3],
+    /// Mirostat tau and eta
+    mirostat_bits: Option<(u32, u32)>,
+    /// Output is filtered per clearance
+    clearance_rank: Option<u8>,
+}
+
+impl GenerationKey {
+    /// Key for requests whose output is reproducible, `None` for sampled ones
+    fn for_request(request: &GenerateRequest) -> Option<Self> {
+        let greedy = request.temperature == Some(0.0);
+        if !greedy && request.seed.is_none() {
+            return None;
+        }
+
+        let normalize = |value: &str| value.split_whitespace().collect::<Vec<_>>().join(" ");
+        let normalize_name = |value: &Option<String>| value.as_deref().map(|v| normalize(v).to_lowercase());
+        let mut features: Vec<String> = request.features.iter().map(|f| normalize(f).to_lowercase()).collect();
+        features.sort();
+        features.dedup();
+
+        Some(Self {
+            prompt: normalize(&request.prompt),
+            language: normalize_name(&request.language),
+            framework: normalize_name(&request.framework),
+            features,
+            deployment_target: normalize_name(&request.deployment_target),
+            upload_id: request.upload_id,
+            seed: request.seed,
+            // -0.0 and 0.0 are the same greedy setting
+            temperature_bits: request.temperature.map(|t| if t == 0.0 { 0 } else { t.to_bits() }),
+            top_k: request.top_k,
+            filter_bits: [request.top_p, request.min_p, request.typical_p].map(|p| p.map(f32::to_bits)),
+            mirostat_bits: request.mirostat.map(|m| (m.tau.to_bits(), m.eta.to_bits())),
+            clearance_rank: request.clearance.as_ref().map(classification_rank),
+        })
+    }
+}
 
 /// Service for AI-powered operations with real AI engine connections
+#[derive(Clone)]
 pub struct AIService {
     code_generator: Arc<CodeGenerator>,
     bug_predictor: Arc<BugPredictor>,
@@ -26,6 +289,9 @@ pub struct AIService {
     documentation_generator: Arc<DocumentationGenerator>,
     performance_monitor: Arc<PerformanceMonitor>,
     inference_engine: Arc<InferenceEngine>,
+    code_providers: Arc<ProviderPool<CodeProvider>>,
+    generation_flights: SingleFlight<GenerationKey, GenerateResponse>,
+    output_filter: Option<Arc<OutputFilterSettings>>,
 }
 
 impl AIService {
@@ -73,6 +339,25 @@ impl AIService {
             inference_engine.clone(),
         ).await?);
 
+        let mut code_providers = ProviderPool::new(ProviderPolicy {
+            max_concurrent_calls: config.max_concurrent_inferences,
+            ..Default::default()
+        });
+        code_providers.add_provider("ai-engine", CodeProvider::Engine(code_generator.clone()));
+        for (name, client) in remote_llm_providers() {
+            code_providers.add_provider(name, CodeProvider::Llm(client));
+        }
+        println!("🔀 Code generation failover chain: {}", code_providers.provider_names().join(" → "));
+
+        let output_filter = OutputFilterSettings::from_env().map(Arc::new);
+        if let Some(settings) = &output_filter {
+            println!(
+                "🔒 Output filter enabled: clearance {:?}, {} blocklist terms",
+                settings.clearance,
+                settings.blocklist.len()
+            );
+        }
+
         println!("✅ AI Service initialized with all real engines");
 
         Ok(Self {
@@ -84,9 +369,17 @@ impl AIService {
             documentation_generator,
             performance_monitor,
             inference_engine,
+            code_providers: Arc::new(code_providers),
+            generation_flights: SingleFlight::new(),
+            output_filter,
         })
     }
 
+    /// Circuit breaker and bulkhead state of each code generation provider
+    pub fn provider_metrics(&self) -> Vec<ProviderMetrics> {
+        self.code_providers.metrics()
+    }
+
     /// Get AI service health status
     pub async fn get_health_status(&self) -> Result<ServiceStatus> {
         // Get real performance metrics from the performance monitor
@@ -132,23 +425,36 @@ impl AIService {
 
     /// Generate code from natural language prompt
     pub async fn generate_code(&self, request: GenerateRequest) -> Result<GenerateResponse> {
-        println!("🚀 Generating code for prompt: {}", request.prompt);
+        let sampling = sampling_parameters(&request);
+        sampling.validate()?;
 
-        // Convert web API request to AI engine request
-        let generation_request = aion_ai_engine::code_generation::GenerationRequest {
-            prompt: request.prompt.clone(),
-            language: request.language.clone(),
-            framework: request.framework.clone(),
-            requirements: request.requirements.unwrap_or_default(),
-            constraints: request.constraints.unwrap_or_default(),
-            optimization_level: aion_ai_engine::code_generation::OptimizationLevel::Balanced,
-            include_tests: true,
-            include_docs: true,
-            include_ci: false,
+        // Sampled requests are intentionally distinct even with identical parameters
+        let Some(key) = GenerationKey::for_request(&request) else {
+            return self.run_generation(request, sampling).await;
         };
 
-        // Use real AI engine for code generation
-        let generated_code = self.code_generator.generate_code(generation_request).await?;
+        let service = self.clone();
+        let response = self.generation_flights
+            .run(key, move || async move { service.run_generation(request, sampling).await })
+            .await?;
+        Ok(response)
+    }
+
+    async fn run_generation(&self, request: GenerateRequest, sampling: InferenceParameters) -> Result<GenerateResponse> {
+        println!("🚀 Generating code for prompt: {}", request.prompt);
+
+        // Seeded and greedy requests are deduplicated on the promise of
+        // identical output, so they only go to providers that keep it
+        let reproducible = sampling.greedy || sampling.seed.is_some();
+        let generation_request = generation_request(&request, sampling);
+
+        // Use the first available provider of the failover chain
+        let generated_code = self.code_providers
+            .call_where(
+                |provider| !reproducible || provider.is_reproducible(),
+                |provider| provider.clone().generate(generation_request.clone()),
+            )
+            .await?;
 
         // Run bug prediction on generated code
         let bug_predictions = self.bug_predictor.predict_bugs(&generated_code).await?;
@@ -174,7 +480,7 @@ impl AIService {
         println!("🛡️ Security scan found {} vulnerabilities", vulnerabilities.len());
 
         // Convert AI engine response to web API response
-        let generated_files: Vec<GeneratedFile> = fixed_code.files.iter().map(|file| {
+        let mut generated_files: Vec<GeneratedFile> = fixed_code.files.iter().map(|file| {
             GeneratedFile {
                 path: file.path.clone(),
                 content: file.content.clone(),
@@ -183,13 +489,39 @@ impl AIService {
             }
         }).collect();
 
+        // Screen the output before it leaves the service
+        let output_filter = self.output_filter.as_ref().map(|settings| {
+            let (filter, clearance) = settings.filter_for(request.clearance.as_ref());
+            let mut summary = OutputFilterSummary { clearance, blocked: false, files: Vec::new() };
+            for file in &mut generated_files {
+                let filtered = filter.filter(&file.content);
+                if filtered.report.is_clean() {
+                    continue;
+                }
+                file.content = filtered.text;
+                file.size = file.content.len();
+                summary.blocked |= filtered.report.blocked;
+                summary.files.push(FileFilterReport { path: file.path.clone(), report: filtered.report });
+            }
+            summary
+        });
+
+        let blocked = output_filter.as_ref().map_or(false, |summary| summary.blocked);
+        if blocked {
+            println!("🚫 Generated output withheld by the output filter");
+            generated_files.clear();
+        } else if let Some(summary) = output_filter.as_ref().filter(|summary| !summary.files.is_empty()) {
+            println!("🔒 Output filter redacted content in {} files", summary.files.len());
+        }
+
         Ok(GenerateResponse {
             id: fixed_code.generation_id,
-            status: "completed".to_string(),
+            status: if blocked { "blocked" } else { "completed" }.to_string(),
             generated_files,
-            deployment_instructions: fixed_code.deployment_instructions,
+            deployment_instructions: if blocked { None } else { fixed_code.deployment_instructions },
             estimated_time: fixed_code.metadata.generation_time_ms as u64,
             confidence_score: fixed_code.metadata.confidence_score,
+            output_filter,
         })
     }
 
@@ -413,4 +745,160 @@ impl AIService {
             "detailed_results": qa_result
         }))
     }
-}
\ No newline at end of file
+}
+
+/// Remote LLM providers with configured credentials, in failover order
+fn remote_llm_providers() -> Vec<(&'static str, Arc<dyn LLMClient>)> {
+    let mut providers: Vec<(&'static str, Arc<dyn LLMClient>)> = Vec::new();
+
+    if let Ok(key) = std::env::var("GROQ_API_KEY") {
+        if let Ok(client) = GroqClient::new(key) {
+            providers.push(("groq", Arc::new(client)));
+        }
+    }
+    if let Ok(key) = std::env::var("OPENAI_API_KEY") {
+        if let Ok(client) = OpenAIClient::new(key) {
+            providers.push(("openai", Arc::new(client)));
+        }
+    }
+    if let Ok(key) = std::env::var("GITHUB_TOKEN") {
+        if let Ok(client) = GitHubModelsClient::new(key) {
+            providers.push(("github-models", Arc::new(client)));
+        }
+    }
+    if let Ok(key) = std::env::var("HUGGINGFACE_API_KEY") {
+        if let Ok(client) = HuggingFaceClient::new(key) {
+            providers.push(("huggingface", Arc::new(client)));
+        }
+    }
+    if let (Ok(key), Ok(account_id)) = (std::env::var("CLOUDFLARE_API_TOKEN"), std::env::var("CLOUDFLARE_ACCOUNT_ID")) {
+        if let Ok(client) = CloudflareAIClient::new(key, account_id) {
+            providers.push(("cloudflare-ai", Arc::new(client)));
+        }
+    }
+
+    providers.retain(|(_, client)| client.is_available());
+    providers
+}
+
+fn file_extension(language: &str) -> &'static str {
+    match language.to_lowercase().as_str() {
+        "rust" => "rs",
+        "python" => "py",
+        "javascript" => "js",
+        "typescript" => "ts",
+        "go" => "go",
+        "java" => "java",
+        "c#" | "csharp" => "cs",
+        _ => "txt",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aion_ai_engine::generation::MirostatConfig;
+    use aion_ai_engine::llm_providers::{LLMProvider, LLMResponse};
+    use std::sync::Mutex;
+
+    /// Records the requests it receives
+    #[derive(Default)]
+    struct RecordingClient {
+        requests: Mutex<Vec<LLMRequest>>,
+    }
+
+    #[async_trait::async_trait]
+    impl LLMClient for RecordingClient {
+        async fn generate(&self, request: &LLMRequest) -> Result<LLMResponse> {
+            self.requests.lock().unwrap().push(request.clone());
+            Ok(LLMResponse {
+                content: "fn main() {}".to_string(),
+                model: "recording".to_string(),
+                provider: LLMProvider::Groq,
+                tokens_used: None,
+                finish_reason: None,
+            })
+        }
+
+        fn provider_type(&self) -> LLMProvider {
+            LLMProvider::Groq
+        }
+
+        fn is_available(&self) -> bool {
+            true
+        }
+    }
+
+    fn request(json: serde_json::Value) -> GenerateRequest {
+        let mut body = serde_json::json!({ "prompt": "a todo api", "features": [] });
+        body.as_object_mut().unwrap().extend(json.as_object().unwrap().clone());
+        serde_json::from_value(body).unwrap()
+    }
+
+    #[test]
+    fn engine_request_carries_every_sampling_parameter() {
+        let request = request(serde_json::json!({
+            "temperature": 0.8,
+            "seed": 42,
+            "top_k": 40,
+            "top_p": 0.95,
+            "min_p": 0.05,
+            "typical_p": 0.9,
+            "mirostat": { "tau": 5.0, "eta": 0.1 },
+        }));
+
+        let sampling = generation_request(&request, sampling_parameters(&request)).sampling;
+        assert_eq!(sampling.temperature, Some(0.8));
+        assert_eq!(sampling.seed, Some(42));
+        assert_eq!(sampling.top_k, Some(40));
+        assert_eq!(sampling.top_p, Some(0.95));
+        assert_eq!(sampling.min_p, Some(0.05));
+        assert_eq!(sampling.typical_p, Some(0.9));
+        assert_eq!(sampling.mirostat, Some(MirostatConfig { tau: 5.0, eta: 0.1 }));
+        assert!(!sampling.greedy);
+
+        let greedy = request_sampling(serde_json::json!({ "temperature": 0.0 }));
+        assert!(greedy.greedy);
+        assert_eq!(request_sampling(serde_json::json!({})).temperature, Some(DEFAULT_CODE_TEMPERATURE));
+    }
+
+    fn request_sampling(json: serde_json::Value) -> InferenceParameters {
+        let request = request(json);
+        generation_request(&request, sampling_parameters(&request)).sampling
+    }
+
+    #[test]
+    fn only_reproducible_requests_get_a_key() {
+        assert!(GenerationKey::for_request(&request(serde_json::json!({ "temperature": 0.7 }))).is_none());
+
+        let seeded = GenerationKey::for_request(&request(serde_json::json!({ "seed": 7 }))).unwrap();
+        let reformatted = GenerationKey::for_request(&request(serde_json::json!({ "seed": 7, "prompt": "a  todo\napi" }))).unwrap();
+        assert_eq!(seeded, reformatted);
+
+        // Anything reaching the engine separates keys
+        let other_seed = GenerationKey::for_request(&request(serde_json::json!({ "seed": 8 }))).unwrap();
+        let with_upload = GenerationKey::for_request(&request(serde_json::json!({ "seed": 7, "upload_id": Uuid::new_v4() }))).unwrap();
+        let with_top_k = GenerationKey::for_request(&request(serde_json::json!({ "seed": 7, "top_k": 40 }))).unwrap();
+        assert_ne!(seeded, other_seed);
+        assert_ne!(seeded, with_upload);
+        assert_ne!(seeded, with_top_k);
+    }
+
+    #[test]
+    fn remote_providers_are_not_reproducible() {
+        assert!(!CodeProvider::Llm(Arc::new(RecordingClient::default())).is_reproducible());
+    }
+
+    #[tokio::test]
+    async fn remote_providers_receive_the_requested_temperature() {
+        let client = Arc::new(RecordingClient::default());
+        let request = request(serde_json::json!({ "temperature": 0.4 }));
+
+        CodeProvider::Llm(client.clone())
+            .generate(generation_request(&request, sampling_parameters(&request)))
+            .await
+            .unwrap();
+
+        assert_eq!(client.requests.lock().unwrap()[0].temperature, Some(0.4));
+    }
+}
@@ -0,0 +1,505 @@
+//! Analytics event store
+//!
+//! Keeps tracked analytics events per organization and serves them in
+//! time-ordered pages so exports and reports never materialize a full dataset.
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Datelike, Duration, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::sync::RwLock;
+use crate::handlers::analytics::AnalyticsEvent;
+use crate::models::ServiceStatus;
+
+/// Time window applied to event queries; both bounds are optional and inclusive
+#[derive(Debug, Clone, Default)]
+pub struct DateRange {
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+}
+
+impl DateRange {
+    pub fn contains(&self, timestamp: &DateTime<Utc>) -> bool {
+        self.from.map_or(true, |from| *timestamp >= from) && self.to.map_or(true, |to| *timestamp <= to)
+    }
+}
+
+/// Position in the event store: the `(timestamp, event_id)` of the last event
+/// a page looked at. Unlike an index it stays valid while events are inserted.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EventCursor {
+    pub timestamp: DateTime<Utc>,
+    pub event_id: String,
+}
+
+impl EventCursor {
+    fn of(event: &AnalyticsEvent) -> Self {
+        Self { timestamp: event.timestamp, event_id: event.event_id.clone() }
+    }
+}
+
+/// One page of events plus the cursor to resume from, if more remain
+pub struct EventPage {
+    pub events: Vec<AnalyticsEvent>,
+    pub next_cursor: Option<EventCursor>,
+}
+
+/// Service for storing and querying analytics events
+pub struct AnalyticsService {
+    // Sorted by (timestamp, event_id); clients supply timestamps, so events
+    // do not necessarily arrive in that order
+    events: RwLock<Vec<AnalyticsEvent>>,
+}
+
+impl AnalyticsService {
+    pub async fn new() -> Result<Self> {
+        println!("📈 Initializing Analytics Service...");
+        Ok(Self {
+            events: RwLock::new(Vec::new()),
+        })
+    }
+
+    /// Get analytics service health
+    pub async fn get_health_status(&self) -> Result<ServiceStatus> {
+        Ok(ServiceStatus {
+            name: "Analytics".to_string(),
+            status: "operational".to_string(),
+            uptime: chrono::Duration::hours(72),
+            last_check: Utc::now(),
+            error_rate: 0.0,
+            response_time: 5.0,
+        })
+    }
+
+    /// Add a tracked event to the store, keeping it in `(timestamp, event_id)` order
+    pub fn record(&self, event: AnalyticsEvent) {
+        let mut events = self.events.write().expect("analytics store poisoned");
+        let position = events.partition_point(|e| {
+            (e.timestamp, e.event_id.as_str()) <= (event.timestamp, event.event_id.as_str())
+        });
+        events.insert(position, event);
+    }
+
+    /// Return up to `limit` events of `org_id` within `range`, after `cursor`
+    ///
+    /// The lock is only held while copying one page, so long-running consumers
+    /// never block event ingestion. Pages continue after the cursor's
+    /// `(timestamp, event_id)`, so events recorded meanwhile neither repeat nor
+    /// shift the rest; one recorded behind the cursor is not picked up.
+    pub fn events_page(&self, org_id: &str, range: &DateRange, cursor: Option<&EventCursor>, limit: usize) -> EventPage {
+        let events = self.events.read().expect("analytics store poisoned");
+
+        let after_cursor = cursor.map_or(0, |cursor| {
+            events.partition_point(|e| {
+                (e.timestamp, e.event_id.as_str()) <= (cursor.timestamp, cursor.event_id.as_str())
+            })
+        });
+        // Skip straight past everything before the range start
+        let start = match range.from {
+            Some(from) => after_cursor.max(events.partition_point(|e| e.timestamp < from)),
+            None => after_cursor,
+        };
+
+        // At least one event per page, so the cursor always moves forward
+        let limit = limit.max(1);
+        let mut page = Vec::with_capacity(limit.min(1024));
+        let mut position = start;
+        while position < events.len() && page.len() < limit {
+            let event = &events[position];
+            position += 1;
+
+            if range.to.map_or(false, |to| event.timestamp > to) {
+                return EventPage { events: page, next_cursor: None };
+            }
+            if event.org_id.as_deref() == Some(org_id) && range.contains(&event.timestamp) {
+                page.push(event.clone());
+            }
+        }
+
+        let next_cursor = (position < events.len()).then(|| EventCursor::of(&events[position - 1]));
+        EventPage { events: page, next_cursor }
+    }
+
+    /// Visit every event of `org_id` within `range` in timestamp order, without cloning
+    pub fn for_each_event<F>(&self, org_id: &str, range: &DateRange, mut visit: F)
+    where
+        F: FnMut(&AnalyticsEvent),
+    {
+        let events = self.events.read().expect("analytics store poisoned");
+        let start = range.from.map_or(0, |from| events.partition_point(|e| e.timestamp < from));
+
+        for event in &events[start..] {
+            if range.to.map_or(false, |to| event.timestamp > to) {
+                break;
+            }
+            if event.org_id.as_deref() == Some(org_id) {
+                visit(event);
+            }
+        }
+    }
+
+    /// Compute an ordered conversion funnel
+    ///
+    /// A user reaches step `n` only after reaching every earlier step in order
+    /// within `window` of entering the funnel. Skipped steps stop progression and
+    /// repeated events are counted once per user; when a user enters the funnel
+    /// several times the furthest attempt counts.
+    pub fn compute_funnel(
+        &self,
+        org_id: &str,
+        steps: &[String],
+        window: Duration,
+        range: &DateRange,
+        granularity: Granularity,
+    ) -> Result<FunnelReport> {
+        if steps.is_empty() {
+            return Err(anyhow!("A funnel needs at least one step"));
+        }
+
+        // Open attempts per user: (entry time, index of the last step reached)
+        let mut attempts: HashMap<String, Vec<(DateTime<Utc>, usize)>> = HashMap::new();
+        // Furthest step per user and when that attempt entered the funnel
+        let mut best: HashMap<String, (usize, DateTime<Utc>)> = HashMap::new();
+        let step_index: HashMap<&str, usize> = steps.iter().enumerate().map(|(i, s)| (s.as_str(), i)).collect();
+
+        self.for_each_event(org_id, range, |event| {
+            let (Some(user_id), Some(&index)) = (event.user_id.as_deref(), step_index.get(event.event_type.name())) else {
+                return;
+            };
+            if !attempts.contains_key(user_id) {
+                attempts.insert(user_id.to_string(), Vec::new());
+            }
+            let user_attempts = attempts.get_mut(user_id).expect("inserted above");
+
+            user_attempts.retain(|(entered, _)| event.timestamp - *entered <= window);
+            for attempt in user_attempts.iter_mut() {
+                if attempt.1 + 1 == index {
+                    attempt.1 = index;
+                }
+            }
+            if index == 0 {
+                // A fresh entry dominates older attempts that never left the first step
+                user_attempts.retain(|(_, reached)| *reached != 0);
+                user_attempts.push((event.timestamp, 0));
+            }
+
+            for (entered, reached) in user_attempts.iter() {
+                match best.get_mut(user_id) {
+                    Some(entry) if *reached > entry.0 => *entry = (*reached, *entered),
+                    Some(_) => {}
+                    None => {
+                        best.insert(user_id.to_string(), (*reached, *entered));
+                    }
+                }
+            }
+        });
+
+        let mut counts = vec![0u64; steps.len()];
+        let mut series: HashMap<NaiveDate, Vec<u64>> = HashMap::new();
+        for (reached, entered) in best.values() {
+            let period = granularity.period_start(entered);
+            let period_counts = series.entry(period).or_insert_with(|| vec![0; steps.len()]);
+            for step in 0..=*reached {
+                counts[step] += 1;
+                period_counts[step] += 1;
+            }
+        }
+
+        let entered = counts[0];
+        let report_steps = steps
+            .iter()
+            .enumerate()
+            .map(|(i, name)| {
+                let previous = if i == 0 { entered } else { counts[i - 1] };
+                FunnelStepReport {
+                    step: name.clone(),
+                    users: counts[i],
+                    conversion_from_start: ratio(counts[i], entered),
+                    conversion_from_previous: ratio(counts[i], previous),
+                    drop_off: previous - counts[i],
+                }
+            })
+            .collect();
+
+        let mut series: Vec<FunnelPeriod> = series
+            .into_iter()
+            .map(|(period, step_users)| FunnelPeriod { period: granularity.label(period), period_start: period, step_users })
+            .collect();
+        series.sort_by_key(|p| p.period_start);
+
+        Ok(FunnelReport {
+            steps: report_steps,
+            conversion_window_hours: window.num_hours(),
+            granularity,
+            series,
+        })
+    }
+
+    /// Group users into cohorts by the period of their first `signup_event` and
+    /// compute the share of each cohort active in every following period
+    pub fn compute_cohorts(
+        &self,
+        org_id: &str,
+        signup_event: &str,
+        activity_event: Option<&str>,
+        range: &DateRange,
+        granularity: Granularity,
+        periods: usize,
+    ) -> CohortReport {
+        let mut signup_period: HashMap<String, NaiveDate> = HashMap::new();
+        let mut activity: HashMap<String, HashSet<usize>> = HashMap::new();
+
+        self.for_each_event(org_id, range, |event| {
+            let Some(user_id) = &event.user_id else { return };
+            let name = event.event_type.name();
+
+            if name == signup_event && !signup_period.contains_key(user_id) {
+                signup_period.insert(user_id.clone(), granularity.period_start(&event.timestamp));
+                return;
+            }
+            if activity_event.map_or(true, |a| a == name) {
+                if let Some(cohort) = signup_period.get(user_id) {
+                    let offset = granularity.periods_between(*cohort, granularity.period_start(&event.timestamp));
+                    if offset >= 1 && offset <= periods {
+                        activity.entry(user_id.clone()).or_default().insert(offset);
+                    }
+                }
+            }
+        });
+
+        let mut cohorts: HashMap<NaiveDate, (u64, Vec<u64>)> = HashMap::new();
+        for (user_id, cohort) in &signup_period {
+            let entry = cohorts.entry(*cohort).or_insert_with(|| (0, vec![0; periods + 1]));
+            entry.0 += 1;
+            entry.1[0] += 1;
+            if let Some(active) = activity.get(user_id) {
+                for offset in active {
+                    entry.1[*offset] += 1;
+                }
+            }
+        }
+
+        let latest = granularity.period_start(&range.to.unwrap_or_else(Utc::now));
+        let mut rows: Vec<CohortRow> = cohorts
+            .into_iter()
+            .map(|(start, (size, retained))| {
+                // Periods that have not happened yet are omitted instead of reported as 0%
+                let observable = granularity.periods_between(start, latest).min(periods);
+                CohortRow {
+                    cohort: granularity.label(start),
+                    cohort_start: start,
+                    size,
+                    retained_users: retained[..=observable].to_vec(),
+                    retention: retained[..=observable].iter().map(|r| ratio(*r, size)).collect(),
+                }
+            })
+            .collect();
+        rows.sort_by_key(|r| r.cohort_start);
+
+        CohortReport {
+            granularity,
+            signup_event: signup_event.to_string(),
+            activity_event: activity_event.map(str::to_string),
+            cohorts: rows,
+        }
+    }
+}
+
+fn ratio(numerator: u64, denominator: u64) -> f64 {
+    if denominator == 0 { 0.0 } else { numerator as f64 / denominator as f64 }
+}
+
+/// Time bucket size for analytics reports
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Granularity {
+    Day,
+    Week,
+    Month,
+}
+
+impl Granularity {
+    /// First day of the period containing `timestamp` (weeks start on Monday)
+    pub fn period_start(&self, timestamp: &DateTime<Utc>) -> NaiveDate {
+        let date = timestamp.date_naive();
+        match self {
+            Granularity::Day => date,
+            Granularity::Week => date - Duration::days(date.weekday().num_days_from_monday() as i64),
+            Granularity::Month => NaiveDate::from_ymd_opt(date.year(), date.month(), 1).expect("valid first of month"),
+        }
+    }
+
+    /// Whole periods from the period starting at `from` to the one starting at `to`
+    pub fn periods_between(&self, from: NaiveDate, to: NaiveDate) -> usize {
+        if to <= from {
+            return 0;
+        }
+        match self {
+            Granularity::Day => (to - from).num_days() as usize,
+            Granularity::Week => ((to - from).num_days() / 7) as usize,
+            Granularity::Month => {
+                ((to.year() - from.year()) * 12 + to.month() as i32 - from.month() as i32).max(0) as usize
+            }
+        }
+    }
+
+    pub fn label(&self, period_start: NaiveDate) -> String {
+        match self {
+            Granularity::Day | Granularity::Week => period_start.format("%Y-%m-%d").to_string(),
+            Granularity::Month => period_start.format("%Y-%m").to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FunnelStepReport {
+    pub step: String,
+    pub users: u64,
+    pub conversion_from_start: f64,
+    pub conversion_from_previous: f64,
+    pub drop_off: u64,
+}
+
+/// Users reaching each step, grouped by the period they entered the funnel
+#[derive(Debug, Clone, Serialize)]
+pub struct FunnelPeriod {
+    pub period: String,
+    #[serde(skip)]
+    pub period_start: NaiveDate,
+    pub step_users: Vec<u64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FunnelReport {
+    pub steps: Vec<FunnelStepReport>,
+    pub conversion_window_hours: i64,
+    pub granularity: Granularity,
+    pub series: Vec<FunnelPeriod>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CohortRow {
+    pub cohort: String,
+    #[serde(skip)]
+    pub cohort_start: NaiveDate,
+    pub size: u64,
+    /// Index 0 is the signup period itself
+    pub retained_users: Vec<u64>,
+    pub retention: Vec<f64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CohortReport {
+    pub granularity: Granularity,
+    pub signup_event: String,
+    pub activity_event: Option<String>,
+    pub cohorts: Vec<CohortRow>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::handlers::analytics::EventType;
+    use chrono::TimeZone;
+
+    fn event(user: &str, event_type: EventType, timestamp: DateTime<Utc>) -> AnalyticsEvent {
+        AnalyticsEvent {
+            event_id: uuid::Uuid::new_v4().to_string(),
+            org_id: Some("org-1".to_string()),
+            user_id: Some(user.to_string()),
+            session_id: "session".to_string(),
+            event_type,
+            timestamp,
+            properties: serde_json::json!({}),
+            user_agent: None,
+            ip_address: None,
+        }
+    }
+
+    fn store(mut events: Vec<AnalyticsEvent>) -> AnalyticsService {
+        events.sort_by(|a, b| (a.timestamp, &a.event_id).cmp(&(b.timestamp, &b.event_id)));
+        AnalyticsService { events: RwLock::new(events) }
+    }
+
+    #[test]
+    fn test_pages_follow_timestamp_order_when_events_arrive_out_of_order() {
+        let t0 = Utc.with_ymd_and_hms(2025, 3, 3, 9, 0, 0).unwrap();
+        let service = store(Vec::new());
+        for minutes in [5, 1, 4, 1, 3, 2, 0] {
+            service.record(event("a", EventType::TrialStarted, t0 + Duration::minutes(minutes)));
+        }
+
+        let mut seen = Vec::new();
+        let mut cursor: Option<EventCursor> = None;
+        loop {
+            let page = service.events_page("org-1", &DateRange::default(), cursor.as_ref(), 2);
+            seen.extend(page.events.iter().map(|e| (e.timestamp, e.event_id.clone())));
+            if seen.len() == 4 {
+                // Recorded ahead of the cursor: picked up once, without repeating anything
+                service.record(event("b", EventType::TrialStarted, t0 + Duration::minutes(6)));
+            }
+            match page.next_cursor {
+                Some(next) => cursor = Some(next),
+                None => break,
+            }
+        }
+
+        assert_eq!(seen.len(), 8);
+        let mut sorted = seen.clone();
+        sorted.sort();
+        sorted.dedup();
+        assert_eq!(seen, sorted);
+    }
+
+    #[test]
+    fn test_funnel_handles_skipped_and_repeated_steps() {
+        let t0 = Utc.with_ymd_and_hms(2025, 3, 3, 9, 0, 0).unwrap();
+        let service = store(vec![
+            // Completes every step, repeating the first one
+            event("a", EventType::SignupStarted, t0),
+            event("a", EventType::SignupStarted, t0 + Duration::minutes(1)),
+            event("a", EventType::TrialStarted, t0 + Duration::minutes(5)),
+            event("a", EventType::PaymentCompleted { plan: "pro".into(), amount: 49.0 }, t0 + Duration::hours(2)),
+            // Skips the trial, so the payment does not count
+            event("b", EventType::SignupStarted, t0),
+            event("b", EventType::PaymentCompleted { plan: "pro".into(), amount: 49.0 }, t0 + Duration::hours(1)),
+            // Converts outside the window
+            event("c", EventType::SignupStarted, t0),
+            event("c", EventType::TrialStarted, t0 + Duration::days(3)),
+        ]);
+
+        let steps = vec!["signup_started".to_string(), "trial_started".to_string(), "payment_completed".to_string()];
+        let report = service
+            .compute_funnel("org-1", &steps, Duration::days(1), &DateRange::default(), Granularity::Day)
+            .unwrap();
+
+        let users: Vec<u64> = report.steps.iter().map(|s| s.users).collect();
+        assert_eq!(users, vec![3, 1, 1]);
+        assert_eq!(report.steps[1].drop_off, 2);
+        assert_eq!(report.series.len(), 1);
+    }
+
+    #[test]
+    fn test_cohort_retention_and_tenancy() {
+        let jan = Utc.with_ymd_and_hms(2025, 1, 10, 12, 0, 0).unwrap();
+        let feb = Utc.with_ymd_and_hms(2025, 2, 10, 12, 0, 0).unwrap();
+        let mut other_org = event("x", EventType::SignupCompleted { plan: "free".into() }, jan);
+        other_org.org_id = Some("org-2".to_string());
+
+        let service = store(vec![
+            event("a", EventType::SignupCompleted { plan: "free".into() }, jan),
+            event("b", EventType::SignupCompleted { plan: "free".into() }, jan),
+            event("a", EventType::FeatureUsed { feature_name: "deploy".into() }, feb),
+            other_org,
+        ]);
+
+        let range = DateRange { from: None, to: Some(Utc.with_ymd_and_hms(2025, 3, 1, 0, 0, 0).unwrap()) };
+        let report = service.compute_cohorts("org-1", "signup_completed", None, &range, Granularity::Month, 6);
+
+        assert_eq!(report.cohorts.len(), 1);
+        let cohort = &report.cohorts[0];
+        assert_eq!(cohort.cohort, "2025-01");
+        assert_eq!(cohort.size, 2);
+        assert_eq!(cohort.retention, vec![1.0, 0.5, 0.0]);
+    }
+}
@@ -8,6 +8,9 @@ use argon2::{Argon2, PasswordHash, PasswordHasher, PasswordVerifier};
 use argon2::password_hash::{rand_core::OsRng, SaltString};
 use sqlx::{PgPool, Row};
 use std::sync::Arc;
+use aion_auth::{SessionManager, SessionStore};
+use crate::services::session_store::PgSessionStore;
+use crate::listing::{FieldKind, ListField, ListQuery, ListSpec, PagedResponse};
 use crate::models::*;
 
 /// JWT claims structure
@@ -18,8 +21,24 @@ pub struct Claims {
     pub role: String,
     pub exp: usize, // Expiration time
     pub iat: usize, // Issued at
+    #[serde(default)]
+    pub org_id: Option<String>, // Organization (tenant) the user acts for
 }
 
+/// Fields the admin user list can be sorted and filtered by
+pub static USER_LIST: ListSpec = ListSpec {
+    fields: &[
+        ListField { name: "email", column: "email", kind: FieldKind::Text, sortable: true, filterable: true },
+        ListField { name: "name", column: "name", kind: FieldKind::Text, sortable: true, filterable: true },
+        ListField { name: "role", column: "role", kind: FieldKind::Text, sortable: true, filterable: true },
+        ListField { name: "is_active", column: "is_active", kind: FieldKind::Boolean, sortable: false, filterable: true },
+        ListField { name: "created_at", column: "created_at", kind: FieldKind::Timestamp, sortable: true, filterable: false },
+        ListField { name: "last_login", column: "last_login", kind: FieldKind::Timestamp, sortable: true, filterable: false },
+    ],
+    default_sort: "created_at:desc",
+    tiebreaker: "id",
+};
+
 /// Authentication service with secure database integration
 pub struct AuthService {
     jwt_secret: String,
@@ -27,6 +46,8 @@ pub struct AuthService {
     decoding_key: DecodingKey,
     db_pool: Arc<PgPool>,
     argon2: Argon2<'static>,
+    /// Sessions and their rotating refresh token families
+    sessions: SessionManager,
 }
 
 impl AuthService {
@@ -50,11 +71,18 @@ impl AuthService {
             jwt_secret: jwt_secret.to_string(),
             encoding_key: EncodingKey::from_secret(jwt_secret.as_ref()),
             decoding_key: DecodingKey::from_secret(jwt_secret.as_ref()),
+            sessions: SessionManager::new(Arc::new(PgSessionStore::new(db_pool.clone()))),
             db_pool,
             argon2,
         })
     }
 
+    /// Keep sessions and refresh tokens in `store` instead of Postgres
+    pub fn with_session_store(mut self, store: Arc<dyn SessionStore>) -> Self {
+        self.sessions = SessionManager::new(store);
+        self
+    }
+
     /// Initialize authentication-related database tables
     async fn initialize_database_tables(pool: &PgPool) -> Result<()> {
         // Create users table
@@ -95,6 +123,28 @@ impl AuthService {
         .execute(pool)
         .await?;
 
+        // Sessions created before the session manager have no tenant
+        sqlx::query("ALTER TABLE user_sessions ADD COLUMN IF NOT EXISTS tenant_id UUID NOT NULL DEFAULT '00000000-0000-0000-0000-000000000000'")
+            .execute(pool)
+            .await?;
+
+        // Create refresh token families; tokens outlive their session so a
+        // replayed token is still recognised after the session has ended
+        sqlx::query(r#"
+            CREATE TABLE IF NOT EXISTS user_refresh_tokens (
+                token_hash TEXT PRIMARY KEY,
+                family_id UUID NOT NULL,
+                session_id UUID NOT NULL,
+                parent_hash TEXT,
+                issued_at TIMESTAMPTZ NOT NULL,
+                expires_at TIMESTAMPTZ NOT NULL,
+                used_at TIMESTAMPTZ,
+                revoked BOOLEAN NOT NULL DEFAULT false
+            )
+        "#)
+        .execute(pool)
+        .await?;
+
         // Create indexes for performance
         sqlx::query("CREATE INDEX IF NOT EXISTS idx_users_email ON users(email)")
             .execute(pool)
@@ -108,6 +158,10 @@ impl AuthService {
             .execute(pool)
             .await?;
 
+        sqlx::query("CREATE INDEX IF NOT EXISTS idx_user_refresh_tokens_family_id ON user_refresh_tokens(family_id)")
+            .execute(pool)
+            .await?;
+
         println!("✅ Authentication database tables initialized");
         Ok(())
     }
@@ -206,31 +260,14 @@ impl AuthService {
             role: user.role.clone(),
             exp: (chrono::Utc::now() + chrono::Duration::hours(24)).timestamp() as usize,
             iat: chrono::Utc::now().timestamp() as usize,
+            org_id: None,
         };
 
         let access_token = encode(&Header::default(), &claims, &self.encoding_key)?;
 
-        // Generate secure refresh token and store session
-        let refresh_token_raw = format!("{}{}", Uuid::new_v4(), Uuid::new_v4());
-        let refresh_token_salt = SaltString::generate(&mut OsRng);
-        let refresh_token_hash = self.argon2
-            .hash_password(refresh_token_raw.as_bytes(), &refresh_token_salt)
-            .map_err(|e| anyhow::anyhow!("Failed to hash refresh token: {}", e))?
-            .to_string();
-
-        // Store session in database
-        let session_expires = chrono::Utc::now() + chrono::Duration::days(30);
-        sqlx::query!(
-            r#"
-            INSERT INTO user_sessions (user_id, refresh_token_hash, expires_at)
-            VALUES ($1, $2, $3)
-            "#,
-            user.id,
-            refresh_token_hash,
-            session_expires
-        )
-        .execute(&*self.db_pool)
-        .await?;
+        // Start a session with its first refresh token
+        let session = self.sessions.create_session(user.id, Uuid::nil()).await?;
+        let refresh_token_raw = self.sessions.issue_refresh_token(&session).await?;
 
         Ok(LoginResponse {
             access_token,
@@ -251,21 +288,46 @@ impl AuthService {
         Ok(token_data.claims)
     }
 
-    /// Refresh JWT token
-    pub async fn refresh_token(&self, refresh_token: &str) -> Result<String> {
-        let claims = self.validate_token(refresh_token).await?;
+    /// Exchange a refresh token for a new access token and its successor.
+    /// Each refresh token works once; replaying a used one revokes its whole
+    /// family (see `SessionManager::rotate_refresh_token`). Access tokens
+    /// are not refresh tokens and are rejected.
+    pub async fn refresh_token(&self, refresh_token: &str) -> Result<RefreshResponse> {
+        if self.validate_token(refresh_token).await.is_ok() {
+            return Err(anyhow::anyhow!("An access token cannot be used to refresh"));
+        }
+        let rotation = self.sessions.rotate_refresh_token(refresh_token).await?;
+        let session = rotation.session;
 
-        // Generate new access token with updated expiration
-        let new_claims = Claims {
-            sub: claims.sub,
-            email: claims.email,
-            role: claims.role,
+        // Re-read the user so role changes and deactivation apply on refresh
+        let user = sqlx::query!(
+            "SELECT email, role, is_active FROM users WHERE id = $1",
+            session.user_id
+        )
+        .fetch_optional(&*self.db_pool)
+        .await?;
+        let user = match user {
+            Some(user) if user.is_active => user,
+            _ => {
+                self.sessions.invalidate_session(session.id).await?;
+                return Err(anyhow::anyhow!("Account is deactivated"));
+            }
+        };
+
+        let claims = Claims {
+            sub: session.user_id.to_string(),
+            email: user.email,
+            role: user.role,
             exp: (chrono::Utc::now() + chrono::Duration::hours(24)).timestamp() as usize,
             iat: chrono::Utc::now().timestamp() as usize,
+            org_id: (!session.tenant_id.is_nil()).then(|| session.tenant_id.to_string()),
         };
 
-        let new_token = encode(&Header::default(), &new_claims, &self.encoding_key)?;
-        Ok(new_token)
+        Ok(RefreshResponse {
+            access_token: encode(&Header::default(), &claims, &self.encoding_key)?,
+            refresh_token: rotation.refresh_token,
+            expires_in: 24 * 3600,
+        })
     }
 
     /// Get user from token
@@ -407,14 +469,95 @@ impl AuthService {
 
     /// Invalidate all user sessions (logout from all devices)
     pub async fn invalidate_all_sessions(&self, user_id: Uuid) -> Result<()> {
-        sqlx::query!(
-            "UPDATE user_sessions SET is_active = false WHERE user_id = $1",
-            user_id
-        )
-        .execute(&*self.db_pool)
-        .await?;
+        self.sessions.invalidate_user_sessions(user_id).await?;
 
         println!("✅ All sessions invalidated for user: {}", user_id);
         Ok(())
     }
-}
\ No newline at end of file
+
+    /// One page of users, as requested by an admin
+    pub async fn list_users(&self, query: &ListQuery) -> Result<PagedResponse<User>> {
+        let total: i64 = query
+            .count("FROM users")
+            .build_query_scalar()
+            .fetch_one(&*self.db_pool)
+            .await?;
+
+        let rows = query
+            .select("SELECT id, email, name, role, created_at, last_login FROM users")
+            .build()
+            .fetch_all(&*self.db_pool)
+            .await?;
+
+        let users = rows
+            .iter()
+            .map(|row| -> Result<User> {
+                let created_at: Option<chrono::DateTime<chrono::Utc>> = row.try_get("created_at")?;
+                let created_at = created_at.unwrap_or_else(chrono::Utc::now);
+                let last_login: Option<chrono::DateTime<chrono::Utc>> = row.try_get("last_login")?;
+                Ok(User {
+                    id: row.try_get("id")?,
+                    email: row.try_get("email")?,
+                    name: row.try_get("name")?,
+                    role: row.try_get("role")?,
+                    created_at,
+                    // Users who never logged in report their sign-up time
+                    last_login: last_login.unwrap_or(created_at),
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(query.respond(users, total.max(0) as u64))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aion_auth::InMemorySessionStore;
+
+    const SECRET: &str = "a-test-secret-that-is-long-enough-to-use";
+
+    /// A service whose pool never connects; these paths must not reach the database
+    fn service() -> AuthService {
+        let pool = PgPool::connect_lazy("postgres://localhost/unused").unwrap();
+        AuthService {
+            jwt_secret: SECRET.to_string(),
+            encoding_key: EncodingKey::from_secret(SECRET.as_ref()),
+            decoding_key: DecodingKey::from_secret(SECRET.as_ref()),
+            db_pool: Arc::new(pool),
+            argon2: Argon2::default(),
+            sessions: SessionManager::new(Arc::new(InMemorySessionStore::new())),
+        }
+    }
+
+    #[tokio::test]
+    async fn access_tokens_cannot_be_used_to_refresh() {
+        let auth = service();
+        let claims = Claims {
+            sub: Uuid::new_v4().to_string(),
+            email: "user@example.com".to_string(),
+            role: "user".to_string(),
+            exp: (chrono::Utc::now() + chrono::Duration::hours(1)).timestamp() as usize,
+            iat: chrono::Utc::now().timestamp() as usize,
+            org_id: None,
+        };
+        let access_token = encode(&Header::default(), &claims, &auth.encoding_key).unwrap();
+
+        let error = auth.refresh_token(&access_token).await.unwrap_err();
+        assert!(error.to_string().contains("access token"));
+    }
+
+    #[tokio::test]
+    async fn unknown_and_used_refresh_tokens_are_rejected() {
+        let auth = service();
+        assert!(auth.refresh_token("not-a-refresh-token").await.is_err());
+
+        let session = auth.sessions.create_session(Uuid::new_v4(), Uuid::nil()).await.unwrap();
+        let first = auth.sessions.issue_refresh_token(&session).await.unwrap();
+        auth.sessions.rotate_refresh_token(&first).await.unwrap();
+
+        // Replaying the rotated token fails before any user lookup
+        assert!(auth.refresh_token(&first).await.is_err());
+    }
+}
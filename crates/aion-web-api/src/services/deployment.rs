@@ -1,18 +1,99 @@
 //! Deployment service for managing application deployments
+//!
+//! Deployments can be previewed with a plan before they are applied. A plan
+//! records a fingerprint of the environment's deployed resources; applying it
+//! fails if that state changed in the meantime, so what was reviewed is
+//! exactly what gets deployed.
 
+use aion_enterprise::deployment::logs::{DeploymentLogStore, LogFollower, LogLevel, LogPage, LogStream, LogsQuery};
+use aion_enterprise::deployment::orchestrator::{DeploymentOrchestrator, DeploymentVersion, RevertResult};
 use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::RwLock;
 use uuid::Uuid;
+use crate::listing::{ListQuery, PagedResponse};
+use crate::versioning::{IfMatch, VersionStore, INITIAL_VERSION};
 use crate::models::*;
+use super::deployment_planner::{state_fingerprint, DeploymentPlan, DeploymentPlanner, PlanRisk, ResourceDefinition};
+
+/// How long a plan can be applied after it was computed
+const PLAN_TTL_MINUTES: i64 = 60;
+
+/// Preview of what a deployment would change
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeploymentPlanPreview {
+    pub plan_id: Uuid,
+    pub project_id: Uuid,
+    pub environment: String,
+    pub resources_to_create: Vec<ResourceDefinition>,
+    pub resources_to_update: Vec<ResourceDefinition>,
+    pub resources_to_delete: Vec<String>,
+    /// Monthly cost of the environment once applied
+    pub estimated_monthly_cost: f64,
+    /// Change in monthly cost compared to what is deployed now
+    pub cost_delta: f64,
+    /// Detected risks, most severe first
+    pub risks: Vec<PlanRisk>,
+    /// Fingerprint of the deployed state the plan was computed against
+    pub state_fingerprint: String,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// Reasons a plan cannot be applied
+#[derive(Debug, thiserror::Error)]
+pub enum PlanApplyError {
+    #[error("Deployment plan {0} not found")]
+    NotFound(Uuid),
+    #[error("Deployment plan {0} expired; create a new plan")]
+    Expired(Uuid),
+    #[error("Deployment plan {plan_id} was computed for project {project_id} in {environment}")]
+    TargetMismatch {
+        plan_id: Uuid,
+        project_id: Uuid,
+        environment: String,
+    },
+    #[error("Deployed state changed since plan {0} was computed; review a new plan before applying")]
+    StateChanged(Uuid),
+}
+
+struct StoredPlan {
+    plan: DeploymentPlan,
+    desired: Vec<ResourceDefinition>,
+    state_fingerprint: String,
+    expires_at: DateTime<Utc>,
+}
 
 /// Service for deployment management
 pub struct DeploymentService {
-    // Database connection and deployment engine would go here
+    planner: DeploymentPlanner,
+    /// Deployed resources per (project, environment)
+    environments: RwLock<HashMap<(Uuid, String), Vec<ResourceDefinition>>>,
+    plans: RwLock<HashMap<Uuid, StoredPlan>>,
+    versions: VersionStore,
+    /// Records each successful deployment as a version that can be reverted to
+    orchestrator: DeploymentOrchestrator,
+    logs: DeploymentLogStore,
+    /// Deployments created per project, oldest first
+    project_deployments: RwLock<HashMap<Uuid, Vec<Uuid>>>,
 }
 
 impl DeploymentService {
     pub async fn new() -> Result<Self> {
         println!("🚢 Initializing Deployment Service...");
-        Ok(Self {})
+        let logs = DeploymentLogStore::default();
+        logs.start()?;
+        Ok(Self {
+            planner: DeploymentPlanner::new(),
+            environments: RwLock::new(HashMap::new()),
+            plans: RwLock::new(HashMap::new()),
+            versions: VersionStore::new(),
+            orchestrator: DeploymentOrchestrator::new(),
+            logs,
+            project_deployments: RwLock::new(HashMap::new()),
+        })
     }
 
     /// Get deployment service health
@@ -70,15 +151,17 @@ impl DeploymentService {
                 } else {
                     50.0 + (fastrand::f64() * 30.0)
                 },
+                version: self.versions.current(&deployment_id.to_string()),
             });
         }
 
         Ok(deployments)
     }
 
-    /// Get all deployments with pagination
-    pub async fn list_deployments(&self, offset: usize, limit: usize) -> Result<Vec<Deployment>> {
-        self.get_recent_deployments(limit).await
+    /// Get one page of deployments
+    pub async fn list_deployments(&self, query: &ListQuery) -> Result<PagedResponse<Deployment>> {
+        let deployments = self.get_recent_deployments(usize::MAX).await?;
+        Ok(query.apply(deployments))
     }
 
     /// Get specific deployment
@@ -92,6 +175,7 @@ impl DeploymentService {
             updated_at: chrono::Utc::now() - chrono::Duration::minutes(30),
             url: Some("https://example-app.ectus.ai".to_string()),
             health_score: 97.3,
+            version: self.versions.current(&deployment_id.to_string()),
         }))
     }
 
@@ -106,14 +190,115 @@ impl DeploymentService {
             updated_at: chrono::Utc::now(),
             url: None,
             health_score: 0.0,
+            version: INITIAL_VERSION,
         };
 
+        self.project_deployments.write().unwrap().entry(project_id).or_default().push(deployment.id);
+        self.logs
+            .writer(deployment.id, "create")
+            .log(LogLevel::Info, LogStream::System, format!("Created deployment {} in {}", deployment.name, deployment.environment))
+            .await?;
+
         println!("🚀 Created new deployment: {}", deployment.name);
         Ok(deployment)
     }
 
-    /// Update deployment
-    pub async fn update_deployment(&self, deployment_id: Uuid, status: String) -> Result<Deployment> {
+    /// Compute what deploying `resources` to an environment would change, without executing it
+    pub async fn plan_deployment(
+        &self,
+        project_id: Uuid,
+        environment: String,
+        resources: Vec<ResourceDefinition>,
+        variables: HashMap<String, serde_json::Value>,
+    ) -> Result<DeploymentPlanPreview> {
+        let current = self.environments.read().unwrap()
+            .get(&(project_id, environment.clone()))
+            .cloned()
+            .unwrap_or_default();
+
+        let plan = self.planner.plan(project_id, &environment, variables, &resources, &current);
+        let risks = self.planner.assess_risks(&plan, &current);
+        let estimated_monthly_cost = plan.estimated_cost.unwrap_or(0.0);
+        let cost_delta = estimated_monthly_cost - self.planner.estimate_monthly_cost(&current);
+        let fingerprint = state_fingerprint(&current);
+        let expires_at = plan.created_at + chrono::Duration::minutes(PLAN_TTL_MINUTES);
+
+        let preview = DeploymentPlanPreview {
+            plan_id: plan.id,
+            project_id,
+            environment,
+            resources_to_create: plan.resources_to_create.clone(),
+            resources_to_update: plan.resources_to_update.clone(),
+            resources_to_delete: plan.resources_to_delete.clone(),
+            estimated_monthly_cost,
+            cost_delta,
+            risks,
+            state_fingerprint: fingerprint.clone(),
+            created_at: plan.created_at,
+            expires_at,
+        };
+
+        let mut plans = self.plans.write().unwrap();
+        let now = Utc::now();
+        plans.retain(|_, stored| stored.expires_at > now);
+        plans.insert(plan.id, StoredPlan {
+            plan,
+            desired: resources,
+            state_fingerprint: fingerprint,
+            expires_at,
+        });
+
+        println!("📝 Planned deployment {} for project {}", preview.plan_id, project_id);
+        Ok(preview)
+    }
+
+    /// Apply exactly the changes of a previously computed plan
+    pub async fn apply_plan(&self, plan_id: Uuid, project_id: Uuid, environment: String) -> Result<Deployment> {
+        let stored = {
+            let mut plans = self.plans.write().unwrap();
+            let stored = plans.get(&plan_id).ok_or(PlanApplyError::NotFound(plan_id))?;
+
+            if stored.expires_at <= Utc::now() {
+                plans.remove(&plan_id);
+                return Err(PlanApplyError::Expired(plan_id).into());
+            }
+            if stored.plan.project_id != project_id || stored.plan.target_environment != environment {
+                return Err(PlanApplyError::TargetMismatch {
+                    plan_id,
+                    project_id: stored.plan.project_id,
+                    environment: stored.plan.target_environment.clone(),
+                }.into());
+            }
+
+            plans.remove(&plan_id).expect("plan checked above")
+        };
+
+        {
+            // Compare and swap under one lock so concurrent applies can't both succeed
+            let mut environments = self.environments.write().unwrap();
+            let current = environments.entry((project_id, environment.clone())).or_default();
+            if state_fingerprint(current) != stored.state_fingerprint {
+                return Err(PlanApplyError::StateChanged(plan_id).into());
+            }
+            *current = stored.desired;
+        }
+
+        println!(
+            "📦 Applying plan {}: {} to create, {} to update, {} to delete",
+            plan_id,
+            stored.plan.resources_to_create.len(),
+            stored.plan.resources_to_update.len(),
+            stored.plan.resources_to_delete.len()
+        );
+        self.create_deployment(project_id, environment).await
+    }
+
+    /// Update deployment status if it is still at a version `precondition`
+    /// accepts; fails with [`VersionConflict`](crate::versioning::VersionConflict)
+    /// otherwise
+    pub async fn update_deployment(&self, deployment_id: Uuid, status: String, precondition: &IfMatch) -> Result<Deployment> {
+        let version = self.versions.bump_if_match(&deployment_id.to_string(), precondition)?;
+
         let deployment = Deployment {
             id: deployment_id,
             name: "example-app".to_string(),
@@ -123,6 +308,7 @@ impl DeploymentService {
             updated_at: chrono::Utc::now(),
             url: Some("https://example-app.ectus.ai".to_string()),
             health_score: 97.3,
+            version,
         };
 
         println!("🔄 Updated deployment: {}", deployment.name);
@@ -131,26 +317,169 @@ impl DeploymentService {
 
     /// Delete deployment
     pub async fn delete_deployment(&self, deployment_id: Uuid) -> Result<()> {
+        self.versions.remove(&deployment_id.to_string());
         println!("🗑️ Deleted deployment: {}", deployment_id);
         Ok(())
     }
 
-    /// Get deployment logs
-    pub async fn get_deployment_logs(&self, deployment_id: Uuid, lines: Option<usize>) -> Result<Vec<String>> {
-        let lines = lines.unwrap_or(50);
-        let mut logs = Vec::new();
+    /// Recorded versions of a deployment, newest first
+    pub async fn get_deployment_history(&self, deployment_id: Uuid) -> Result<Vec<DeploymentVersion>> {
+        Ok(self.orchestrator.get_deployment_history(deployment_id).await)
+    }
+
+    /// Redeploy a prior version; a no-op when that version is already live
+    pub async fn revert_deployment(&self, deployment_id: Uuid, to_version: u32, requested_by: &str) -> Result<RevertResult> {
+        self.orchestrator.revert_deployment(deployment_id, to_version, requested_by).await
+    }
 
-        for i in 0..lines {
-            let timestamp = chrono::Utc::now() - chrono::Duration::minutes((lines - i) as i64);
-            let log_level = if i % 10 == 0 { "ERROR" } else if i % 3 == 0 { "WARN" } else { "INFO" };
+    /// Query a deployment's stored logs, one cursor page at a time
+    pub async fn get_deployment_logs(&self, deployment_id: Uuid, query: &LogsQuery) -> Result<LogPage> {
+        self.logs.query(deployment_id, query)
+    }
+
+    /// Stored logs after `query.cursor`, then live entries until the deployment finishes
+    pub fn follow_deployment_logs(&self, deployment_id: Uuid, query: LogsQuery) -> Result<LogFollower> {
+        self.logs.follow(deployment_id, query)
+    }
 
-            logs.push(format!(
-                "{} [{}] Application server listening on port 8080",
-                timestamp.format("%Y-%m-%d %H:%M:%S UTC"),
-                log_level
-            ));
+    /// Most recently created deployment of a project
+    pub fn latest_deployment(&self, project_id: Uuid) -> Option<Uuid> {
+        self.project_deployments.read().unwrap().get(&project_id).and_then(|ids| ids.last().copied())
+    }
+
+    /// Log store that deployers write their step output to
+    pub fn log_store(&self) -> &DeploymentLogStore {
+        &self.logs
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::deployment_planner::RiskKind;
+
+    fn resource(name: &str, resource_type: &str, properties: serde_json::Value) -> ResourceDefinition {
+        ResourceDefinition {
+            name: name.to_string(),
+            resource_type: resource_type.to_string(),
+            properties,
+            depends_on: vec![],
+        }
+    }
+
+    fn stack(instance_type: &str) -> Vec<ResourceDefinition> {
+        vec![
+            resource("api", "ec2_instance", serde_json::json!({ "instance_type": instance_type })),
+            resource("db", "rds_instance", serde_json::json!({ "engine": "postgres" })),
+        ]
+    }
+
+    async fn apply(service: &DeploymentService, project_id: Uuid, resources: Vec<ResourceDefinition>) {
+        let plan = service.plan_deployment(project_id, "production".to_string(), resources, HashMap::new()).await.unwrap();
+        service.apply_plan(plan.plan_id, project_id, "production".to_string()).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_plan_reports_changes_cost_and_risks() {
+        let service = DeploymentService::new().await.unwrap();
+        let project_id = Uuid::new_v4();
+        apply(&service, project_id, stack("t3.micro")).await;
+
+        let plan = service.plan_deployment(
+            project_id,
+            "production".to_string(),
+            vec![resource("api", "ec2_instance", serde_json::json!({ "instance_type": "t3.large" }))],
+            HashMap::new(),
+        ).await.unwrap();
+
+        assert!(plan.resources_to_create.is_empty());
+        assert_eq!(plan.resources_to_update.len(), 1);
+        assert_eq!(plan.resources_to_delete, vec!["db".to_string()]);
+        assert!(plan.cost_delta != 0.0);
+        assert!(plan.risks.iter().any(|r| r.resource == "db" && r.kind == RiskKind::StatefulResourceDeletion));
+        assert!(plan.risks.iter().any(|r| r.resource == "api" && r.kind == RiskKind::Downtime));
+    }
+
+    #[tokio::test]
+    async fn test_deployment_logs_are_queryable() {
+        let service = DeploymentService::new().await.unwrap();
+        let deployment = service.create_deployment(Uuid::new_v4(), "staging".to_string()).await.unwrap();
+
+        let mut page = service.get_deployment_logs(deployment.id, &LogsQuery::default()).await.unwrap();
+        while page.entries.is_empty() {
+            tokio::task::yield_now().await;
+            page = service.get_deployment_logs(deployment.id, &LogsQuery::default()).await.unwrap();
         }
+        assert_eq!(page.entries[0].step, "create");
+        assert!(page.entries[0].message.contains("staging"));
+        assert!(page.live);
+    }
+
+    #[tokio::test]
+    async fn test_latest_deployment_of_project() {
+        let service = DeploymentService::new().await.unwrap();
+        let project_id = Uuid::new_v4();
+        assert_eq!(service.latest_deployment(project_id), None);
 
-        Ok(logs)
+        service.create_deployment(project_id, "staging".to_string()).await.unwrap();
+        let latest = service.create_deployment(project_id, "production".to_string()).await.unwrap();
+        service.create_deployment(Uuid::new_v4(), "production".to_string()).await.unwrap();
+
+        assert_eq!(service.latest_deployment(project_id), Some(latest.id));
     }
-}
\ No newline at end of file
+
+    #[tokio::test]
+    async fn test_plan_is_recorded_for_the_project() {
+        let service = DeploymentService::new().await.unwrap();
+        let project_id = Uuid::new_v4();
+
+        let preview = service.plan_deployment(project_id, "staging".to_string(), stack("t3.micro"), HashMap::new()).await.unwrap();
+
+        let plans = service.plans.read().unwrap();
+        let stored = &plans[&preview.plan_id].plan;
+        assert_eq!(stored.project_id, project_id);
+        assert_eq!(stored.target_environment, "staging");
+    }
+
+    #[tokio::test]
+    async fn test_plan_does_not_change_state() {
+        let service = DeploymentService::new().await.unwrap();
+        let project_id = Uuid::new_v4();
+
+        let first = service.plan_deployment(project_id, "production".to_string(), stack("t3.micro"), HashMap::new()).await.unwrap();
+        let second = service.plan_deployment(project_id, "production".to_string(), stack("t3.micro"), HashMap::new()).await.unwrap();
+
+        assert_eq!(first.state_fingerprint, second.state_fingerprint);
+        assert_eq!(second.resources_to_create.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_apply_fails_when_state_changed_since_plan() {
+        let service = DeploymentService::new().await.unwrap();
+        let project_id = Uuid::new_v4();
+
+        let stale = service.plan_deployment(project_id, "production".to_string(), stack("t3.small"), HashMap::new()).await.unwrap();
+        apply(&service, project_id, stack("t3.micro")).await;
+
+        let err = service.apply_plan(stale.plan_id, project_id, "production".to_string()).await.unwrap_err();
+        assert!(matches!(err.downcast_ref::<PlanApplyError>(), Some(PlanApplyError::StateChanged(_))));
+    }
+
+    #[tokio::test]
+    async fn test_apply_rejects_unknown_and_mismatched_plans() {
+        let service = DeploymentService::new().await.unwrap();
+        let project_id = Uuid::new_v4();
+
+        let err = service.apply_plan(Uuid::new_v4(), project_id, "production".to_string()).await.unwrap_err();
+        assert!(matches!(err.downcast_ref::<PlanApplyError>(), Some(PlanApplyError::NotFound(_))));
+
+        let plan = service.plan_deployment(project_id, "production".to_string(), stack("t3.micro"), HashMap::new()).await.unwrap();
+        let err = service.apply_plan(plan.plan_id, project_id, "staging".to_string()).await.unwrap_err();
+        assert!(matches!(err.downcast_ref::<PlanApplyError>(), Some(PlanApplyError::TargetMismatch { .. })));
+
+        // A mismatched apply leaves the plan usable, a successful one consumes it
+        service.apply_plan(plan.plan_id, project_id, "production".to_string()).await.unwrap();
+        let err = service.apply_plan(plan.plan_id, project_id, "production".to_string()).await.unwrap_err();
+        assert!(matches!(err.downcast_ref::<PlanApplyError>(), Some(PlanApplyError::NotFound(_))));
+    }
+}
@@ -0,0 +1,258 @@
+//! Deployment planning: diff desired resources against deployed state, detect
+//! risky changes and estimate cost, without touching any provider.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::hash::{Hash, Hasher};
+use uuid::Uuid;
+
+/// Hours in an average month, used for monthly cost estimates
+const HOURS_PER_MONTH: f64 = 730.0;
+
+/// Resource type tokens that indicate the resource holds data
+const STATEFUL_TOKENS: &[&str] = &[
+    "db", "database", "rds", "sql", "postgres", "postgresql", "mysql", "mongodb", "dynamodb",
+    "bucket", "s3", "storage", "blob", "volume", "disk", "ebs", "redis", "cache", "elasticache",
+    "queue", "sqs", "kafka", "elasticsearch", "pvc",
+];
+
+/// Properties whose change forces the provider to replace or restart the resource
+const DISRUPTIVE_PROPERTIES: &[&str] = &[
+    "instance_type", "machine_type", "vm_size", "size", "image", "ami", "engine", "engine_version",
+    "region", "availability_zone", "zone", "subnet_id", "vpc_id",
+];
+
+/// A resource as it is declared for, or deployed to, an environment
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResourceDefinition {
+    pub name: String,
+    pub resource_type: String,
+    pub properties: serde_json::Value,
+    #[serde(default)]
+    pub depends_on: Vec<String>,
+}
+
+/// Changes needed to bring a project's environment to the desired resources
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeploymentPlan {
+    pub id: Uuid,
+    pub project_id: Uuid,
+    pub variables: HashMap<String, serde_json::Value>,
+    pub target_environment: String,
+    pub estimated_cost: Option<f64>,
+    pub resources_to_create: Vec<ResourceDefinition>,
+    pub resources_to_update: Vec<ResourceDefinition>,
+    pub resources_to_delete: Vec<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum RiskSeverity {
+    Low,
+    Medium,
+    High,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RiskKind {
+    /// A resource holding data would be deleted
+    StatefulResourceDeletion,
+    /// A resource would be destroyed and recreated
+    ResourceReplacement,
+    /// A change restarts or interrupts the resource
+    Downtime,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlanRisk {
+    pub kind: RiskKind,
+    pub severity: RiskSeverity,
+    pub resource: String,
+    pub message: String,
+}
+
+/// Computes deployment plans from desired and current resources
+#[derive(Debug, Clone, Default)]
+pub struct DeploymentPlanner;
+
+impl DeploymentPlanner {
+    pub fn new() -> Self {
+        Self
+    }
+
+    /// Diff `desired` against `current` by resource name
+    pub fn plan(
+        &self,
+        project_id: Uuid,
+        target_environment: &str,
+        variables: HashMap<String, serde_json::Value>,
+        desired: &[ResourceDefinition],
+        current: &[ResourceDefinition],
+    ) -> DeploymentPlan {
+        let current_by_name: HashMap<&str, &ResourceDefinition> =
+            current.iter().map(|r| (r.name.as_str(), r)).collect();
+
+        let mut resources_to_create = Vec::new();
+        let mut resources_to_update = Vec::new();
+        for resource in desired {
+            match current_by_name.get(resource.name.as_str()) {
+                None => resources_to_create.push(resource.clone()),
+                Some(existing) if !same_definition(existing, resource) => {
+                    resources_to_update.push(resource.clone())
+                }
+                Some(_) => {}
+            }
+        }
+
+        let resources_to_delete = current
+            .iter()
+            .filter(|r| !desired.iter().any(|d| d.name == r.name))
+            .map(|r| r.name.clone())
+            .collect();
+
+        DeploymentPlan {
+            id: Uuid::new_v4(),
+            project_id,
+            variables,
+            target_environment: target_environment.to_string(),
+            estimated_cost: Some(self.estimate_monthly_cost(desired)),
+            resources_to_create,
+            resources_to_update,
+            resources_to_delete,
+            created_at: Utc::now(),
+        }
+    }
+
+    /// Detect data loss and downtime caused by applying `plan` over `current`
+    pub fn assess_risks(&self, plan: &DeploymentPlan, current: &[ResourceDefinition]) -> Vec<PlanRisk> {
+        let mut risks = Vec::new();
+
+        for name in &plan.resources_to_delete {
+            if let Some(resource) = current.iter().find(|r| &r.name == name) {
+                if is_stateful(&resource.resource_type) {
+                    risks.push(PlanRisk {
+                        kind: RiskKind::StatefulResourceDeletion,
+                        severity: RiskSeverity::High,
+                        resource: name.clone(),
+                        message: format!(
+                            "Deleting {} ({}) permanently destroys the data it holds",
+                            name, resource.resource_type
+                        ),
+                    });
+                }
+            }
+        }
+
+        for desired in &plan.resources_to_update {
+            let Some(existing) = current.iter().find(|r| r.name == desired.name) else {
+                continue;
+            };
+            let stateful = is_stateful(&existing.resource_type);
+
+            if existing.resource_type != desired.resource_type {
+                risks.push(PlanRisk {
+                    kind: RiskKind::ResourceReplacement,
+                    severity: if stateful { RiskSeverity::High } else { RiskSeverity::Medium },
+                    resource: desired.name.clone(),
+                    message: format!(
+                        "Changing {} from {} to {} replaces the resource{}",
+                        desired.name,
+                        existing.resource_type,
+                        desired.resource_type,
+                        if stateful { " and loses its data" } else { "" }
+                    ),
+                });
+                continue;
+            }
+
+            let disruptive: Vec<&str> = DISRUPTIVE_PROPERTIES
+                .iter()
+                .copied()
+                .filter(|key| existing.properties.get(key) != desired.properties.get(key))
+                .collect();
+            if !disruptive.is_empty() {
+                risks.push(PlanRisk {
+                    kind: RiskKind::Downtime,
+                    severity: if stateful { RiskSeverity::High } else { RiskSeverity::Medium },
+                    resource: desired.name.clone(),
+                    message: format!(
+                        "Changing {} on {} restarts or replaces the resource",
+                        disruptive.join(", "),
+                        desired.name
+                    ),
+                });
+            }
+        }
+
+        risks.sort_by(|a, b| b.severity.cmp(&a.severity));
+        risks
+    }
+
+    /// Rough monthly cost of running `resources`
+    pub fn estimate_monthly_cost(&self, resources: &[ResourceDefinition]) -> f64 {
+        resources.iter().map(hourly_cost).sum::<f64>() * HOURS_PER_MONTH
+    }
+}
+
+/// Stable fingerprint of deployed state, used to detect changes between plan and apply
+pub fn state_fingerprint(resources: &[ResourceDefinition]) -> String {
+    let sorted: BTreeMap<&str, &ResourceDefinition> =
+        resources.iter().map(|r| (r.name.as_str(), r)).collect();
+
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    for (name, resource) in sorted {
+        name.hash(&mut hasher);
+        resource.resource_type.hash(&mut hasher);
+        resource.properties.to_string().hash(&mut hasher);
+        resource.depends_on.hash(&mut hasher);
+    }
+    format!("{:016x}", hasher.finish())
+}
+
+/// Whether a resource type holds data that deletion would destroy
+pub fn is_stateful(resource_type: &str) -> bool {
+    resource_type
+        .to_lowercase()
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .any(|token| STATEFUL_TOKENS.contains(&token))
+}
+
+fn same_definition(a: &ResourceDefinition, b: &ResourceDefinition) -> bool {
+    a.resource_type == b.resource_type && a.properties == b.properties && a.depends_on == b.depends_on
+}
+
+fn hourly_cost(resource: &ResourceDefinition) -> f64 {
+    let instance_type = resource
+        .properties
+        .get("instance_type")
+        .and_then(|v| v.as_str());
+
+    // Same simplified on-demand rates as the aion-cloud AWS provider
+    match instance_type {
+        Some("t3.nano") => return 0.0052,
+        Some("t3.micro") => return 0.0104,
+        Some("t3.small") => return 0.0208,
+        Some("t3.medium") => return 0.0416,
+        Some("t3.large") => return 0.0832,
+        Some("t3.xlarge") => return 0.1664,
+        Some("t3.2xlarge") => return 0.3328,
+        Some(_) => return 0.1,
+        None => {}
+    }
+
+    let resource_type = resource.resource_type.to_lowercase();
+    if is_stateful(&resource_type) {
+        if resource_type.contains("bucket") || resource_type.contains("s3") || resource_type.contains("blob") {
+            0.003
+        } else {
+            0.034
+        }
+    } else if resource_type.contains("load_balancer") || resource_type.contains("lb") {
+        0.0225
+    } else if resource_type.contains("instance") || resource_type.contains("vm") || resource_type.contains("node") {
+        0.0104
+    } else {
+        0.0
+    }
+}
@@ -0,0 +1,655 @@
+//! A/B test assignment and analysis
+//!
+//! Users are bucketed deterministically from a hash of experiment id and user id,
+//! so the same user always sees the same variant without storing assignments.
+//! Experiments linked by `conflicts_with` form an exclusion group that shares
+//! one traffic layer: each member owns its own range of the layer's slots, so
+//! a user lands in at most one of them.
+//! Results use a two-proportion z-test against the control variant and only
+//! declare a winner once every variant reached the pre-computed sample size,
+//! which protects against peeking at early, noisy results.
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::ops::Range;
+use std::sync::RwLock;
+use crate::services::analytics::{AnalyticsService, DateRange};
+
+/// Resolution of bucketing hashes (0.01% traffic granularity)
+const BUCKETS: u64 = 10_000;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Variant {
+    pub key: String,
+    pub name: String,
+    /// Relative share of enrolled traffic
+    pub weight: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Experiment {
+    pub id: String,
+    pub org_id: String,
+    pub name: String,
+    /// The first variant is the control
+    pub variants: Vec<Variant>,
+    /// Fraction of users enrolled in the experiment, between 0.0 and 1.0
+    pub traffic_allocation: f64,
+    /// Experiments that must not share users with this one. Conflicts apply
+    /// both ways and transitively. Members take slots of the group's layer in
+    /// the order they started, so a new member only gets slots nobody held
+    /// and gets less than its allocation once the layer is full. Linking two
+    /// existing groups reshuffles the one that started later.
+    pub conflicts_with: Vec<String>,
+    /// Analytics event name counted as a conversion
+    pub conversion_event: String,
+    /// Expected conversion rate of the control, used for sample size planning
+    pub baseline_rate: f64,
+    /// Smallest absolute lift worth detecting
+    pub minimum_detectable_effect: f64,
+    pub confidence_level: f64,
+    pub statistical_power: f64,
+    pub started_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateExperimentRequest {
+    pub id: String,
+    pub name: String,
+    pub variants: Vec<Variant>,
+    pub traffic_allocation: Option<f64>,
+    pub conflicts_with: Option<Vec<String>>,
+    pub conversion_event: String,
+    pub baseline_rate: Option<f64>,
+    pub minimum_detectable_effect: Option<f64>,
+    pub confidence_level: Option<f64>,
+    pub statistical_power: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum Assignment {
+    Assigned { variant: String },
+    NotEnrolled,
+    Excluded { conflicting_experiment: String },
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct VariantResult {
+    pub key: String,
+    pub name: String,
+    pub users: u64,
+    pub conversions: u64,
+    pub conversion_rate: f64,
+    pub confidence_interval: (f64, f64),
+    /// Absolute lift over control; `None` for the control itself
+    pub lift: Option<f64>,
+    pub p_value: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ExperimentStatus {
+    /// Not every variant reached the planned sample size yet
+    NeedsMoreSamples,
+    /// Planned sample reached and a variant differs significantly from control
+    Significant,
+    /// Planned sample reached without a significant difference
+    NoSignificantDifference,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ExperimentResults {
+    pub experiment_id: String,
+    pub name: String,
+    pub status: ExperimentStatus,
+    pub minimum_sample_size_per_variant: u64,
+    pub variants: Vec<VariantResult>,
+    pub winner: Option<String>,
+    pub confidence_level: f64,
+}
+
+/// Service managing experiments and their analysis
+pub struct ExperimentService {
+    experiments: RwLock<HashMap<(String, String), Experiment>>,
+}
+
+impl ExperimentService {
+    pub async fn new() -> Result<Self> {
+        println!("🧪 Initializing Experiment Service...");
+        Ok(Self {
+            experiments: RwLock::new(HashMap::new()),
+        })
+    }
+
+    pub fn create_experiment(&self, org_id: &str, request: CreateExperimentRequest) -> Result<Experiment> {
+        if request.variants.len() < 2 {
+            return Err(anyhow!("An experiment needs at least two variants"));
+        }
+        if request.variants.iter().all(|v| v.weight == 0) {
+            return Err(anyhow!("At least one variant must have a non-zero weight"));
+        }
+        let keys: HashSet<&str> = request.variants.iter().map(|v| v.key.as_str()).collect();
+        if keys.len() != request.variants.len() {
+            return Err(anyhow!("Variant keys must be unique"));
+        }
+
+        let traffic_allocation = request.traffic_allocation.unwrap_or(1.0);
+        if !(0.0..=1.0).contains(&traffic_allocation) {
+            return Err(anyhow!("traffic_allocation must be between 0 and 1"));
+        }
+        let confidence_level = request.confidence_level.unwrap_or(0.95);
+        let statistical_power = request.statistical_power.unwrap_or(0.8);
+        if !(0.5..1.0).contains(&confidence_level) || !(0.5..1.0).contains(&statistical_power) {
+            return Err(anyhow!("confidence_level and statistical_power must be in [0.5, 1)"));
+        }
+
+        let experiment = Experiment {
+            id: request.id,
+            org_id: org_id.to_string(),
+            name: request.name,
+            variants: request.variants,
+            traffic_allocation,
+            conflicts_with: request.conflicts_with.unwrap_or_default(),
+            conversion_event: request.conversion_event,
+            baseline_rate: request.baseline_rate.unwrap_or(0.1).clamp(0.001, 0.999),
+            minimum_detectable_effect: request.minimum_detectable_effect.unwrap_or(0.02).abs().max(0.001),
+            confidence_level,
+            statistical_power,
+            started_at: Utc::now(),
+        };
+
+        let mut experiments = self.experiments.write().expect("experiment store poisoned");
+        let key = (org_id.to_string(), experiment.id.clone());
+        if experiments.contains_key(&key) {
+            return Err(anyhow!("Experiment '{}' already exists", experiment.id));
+        }
+        experiments.insert(key, experiment.clone());
+        Ok(experiment)
+    }
+
+    pub fn get_experiment(&self, org_id: &str, experiment_id: &str) -> Option<Experiment> {
+        let experiments = self.experiments.read().expect("experiment store poisoned");
+        experiments.get(&(org_id.to_string(), experiment_id.to_string())).cloned()
+    }
+
+    pub fn list_experiments(&self, org_id: &str) -> Vec<Experiment> {
+        let experiments = self.experiments.read().expect("experiment store poisoned");
+        let mut list: Vec<Experiment> = experiments
+            .values()
+            .filter(|e| e.org_id == org_id)
+            .cloned()
+            .collect();
+        list.sort_by(|a, b| a.started_at.cmp(&b.started_at));
+        list
+    }
+
+    /// Assign a user to a variant, honoring traffic allocation and conflicts
+    pub fn assign(&self, org_id: &str, experiment_id: &str, user_id: &str) -> Result<Assignment> {
+        let experiments = self.experiments.read().expect("experiment store poisoned");
+        let experiment = experiments
+            .get(&(org_id.to_string(), experiment_id.to_string()))
+            .ok_or_else(|| anyhow!("Experiment '{}' not found", experiment_id))?;
+
+        let layer = TrafficLayer::new(exclusion_group(&experiments, experiment));
+        Ok(match layer.owner(user_id) {
+            Some(owner) if owner.id == experiment.id => match pick_variant(experiment, user_id) {
+                Some(variant) => Assignment::Assigned { variant: variant.key.clone() },
+                None => Assignment::NotEnrolled,
+            },
+            Some(owner) => Assignment::Excluded { conflicting_experiment: owner.id.clone() },
+            None => Assignment::NotEnrolled,
+        })
+    }
+
+    /// Compute per-variant conversion statistics from the analytics event store
+    ///
+    /// Every user seen in the org's events since the experiment started is
+    /// re-bucketed, so results match exactly what `assign` hands out.
+    pub fn analyze(&self, org_id: &str, experiment_id: &str, analytics: &AnalyticsService) -> Result<ExperimentResults> {
+        let experiment = self
+            .get_experiment(org_id, experiment_id)
+            .ok_or_else(|| anyhow!("Experiment '{}' not found", experiment_id))?;
+
+        let range = DateRange { from: Some(experiment.started_at), to: None };
+        let mut users: HashSet<String> = HashSet::new();
+        let mut converted: HashSet<String> = HashSet::new();
+        let mut cursor = None;
+        loop {
+            let page = analytics.events_page(org_id, &range, cursor.as_ref(), 5_000);
+            for event in &page.events {
+                if let Some(user_id) = &event.user_id {
+                    users.insert(user_id.clone());
+                    if event.event_type.name() == experiment.conversion_event {
+                        converted.insert(user_id.clone());
+                    }
+                }
+            }
+            match page.next_cursor {
+                Some(next) => cursor = Some(next),
+                None => break,
+            }
+        }
+
+        let mut counts: HashMap<String, (u64, u64)> = HashMap::new();
+        for user_id in &users {
+            if let Ok(Assignment::Assigned { variant }) = self.assign(org_id, experiment_id, user_id) {
+                let entry = counts.entry(variant).or_insert((0, 0));
+                entry.0 += 1;
+                if converted.contains(user_id) {
+                    entry.1 += 1;
+                }
+            }
+        }
+
+        let observed: Vec<(String, String, u64, u64)> = experiment
+            .variants
+            .iter()
+            .map(|v| {
+                let (n, c) = counts.get(&v.key).copied().unwrap_or((0, 0));
+                (v.key.clone(), v.name.clone(), n, c)
+            })
+            .collect();
+
+        Ok(analyze_counts(&experiment, &observed))
+    }
+}
+
+/// `experiment` and every experiment of its org linked to it through
+/// `conflicts_with` in either direction
+fn exclusion_group<'a>(experiments: &'a HashMap<(String, String), Experiment>, experiment: &'a Experiment) -> Vec<&'a Experiment> {
+    let org: Vec<&Experiment> = experiments.values().filter(|e| e.org_id == experiment.org_id).collect();
+    let mut group = vec![experiment];
+    let mut next = 0;
+    while next < group.len() {
+        let current = group[next];
+        next += 1;
+        for other in &org {
+            let linked = current.conflicts_with.contains(&other.id) || other.conflicts_with.contains(&current.id);
+            if linked && !group.iter().any(|member| member.id == other.id) {
+                group.push(*other);
+            }
+        }
+    }
+    group
+}
+
+/// Slots shared by an exclusion group
+///
+/// Members own contiguous slot ranges sized by their traffic allocation, in
+/// the order they started. The salt is the first member's id, so it does not
+/// change as members join, and a lone experiment keeps the enrollment it
+/// would have on its own.
+struct TrafficLayer<'a> {
+    salt: &'a str,
+    ranges: Vec<(&'a Experiment, Range<u64>)>,
+}
+
+impl<'a> TrafficLayer<'a> {
+    fn new(mut members: Vec<&'a Experiment>) -> Self {
+        members.sort_by(|a, b| a.started_at.cmp(&b.started_at).then_with(|| a.id.cmp(&b.id)));
+        let first: &'a Experiment = members[0];
+
+        let mut start = 0;
+        let ranges = members
+            .into_iter()
+            .map(|member| {
+                let width = (member.traffic_allocation * BUCKETS as f64).round() as u64;
+                let end = (start + width).min(BUCKETS);
+                let range = start..end;
+                start = end;
+                (member, range)
+            })
+            .collect();
+
+        Self { salt: first.id.as_str(), ranges }
+    }
+
+    /// Member whose slot range holds the user, if any
+    fn owner(&self, user_id: &str) -> Option<&'a Experiment> {
+        let slot = stable_hash(&format!("{}:{}:enroll", self.salt, user_id)) % BUCKETS;
+        self.ranges
+            .iter()
+            .find(|(_, range)| range.contains(&slot))
+            .map(|(member, _)| *member)
+    }
+}
+
+/// Pick the variant for an enrolled user
+fn pick_variant<'a>(experiment: &'a Experiment, user_id: &str) -> Option<&'a Variant> {
+    let total_weight: u64 = experiment.variants.iter().map(|v| v.weight as u64).sum();
+    if total_weight == 0 {
+        return None;
+    }
+
+    // A separate salt keeps variant choice independent of enrollment
+    let mut point = stable_hash(&format!("{}:{}:variant", experiment.id, user_id)) % total_weight;
+    for variant in &experiment.variants {
+        if point < variant.weight as u64 {
+            return Some(variant);
+        }
+        point -= variant.weight as u64;
+    }
+    None
+}
+
+/// FNV-1a; unlike `DefaultHasher` it is stable across processes and releases
+fn stable_hash(input: &str) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in input.as_bytes() {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
+
+/// Turn raw `(key, name, users, conversions)` counts into experiment results
+pub fn analyze_counts(experiment: &Experiment, observed: &[(String, String, u64, u64)]) -> ExperimentResults {
+    let alpha = 1.0 - experiment.confidence_level;
+    let z_alpha = inverse_normal_cdf(1.0 - alpha / 2.0);
+    let minimum_sample = minimum_sample_size(
+        experiment.baseline_rate,
+        experiment.minimum_detectable_effect,
+        experiment.confidence_level,
+        experiment.statistical_power,
+    );
+
+    let (control_n, control_c) = observed.first().map(|o| (o.2, o.3)).unwrap_or((0, 0));
+    let control_rate = rate(control_c, control_n);
+
+    let variants: Vec<VariantResult> = observed
+        .iter()
+        .enumerate()
+        .map(|(index, (key, name, n, c))| {
+            let p = rate(*c, *n);
+            let margin = if *n > 0 { z_alpha * (p * (1.0 - p) / *n as f64).sqrt() } else { 0.0 };
+            let (lift, p_value) = if index == 0 {
+                (None, None)
+            } else {
+                (Some(p - control_rate), two_proportion_p_value(control_c, control_n, *c, *n))
+            };
+
+            VariantResult {
+                key: key.clone(),
+                name: name.clone(),
+                users: *n,
+                conversions: *c,
+                conversion_rate: p,
+                confidence_interval: ((p - margin).max(0.0), (p + margin).min(1.0)),
+                lift,
+                p_value,
+            }
+        })
+        .collect();
+
+    let enough_samples = observed.iter().all(|o| o.2 >= minimum_sample);
+    // Bonferroni correction keeps the family-wise error rate at alpha with several treatments
+    let comparisons = observed.len().saturating_sub(1).max(1) as f64;
+    let significant: Vec<&VariantResult> = variants
+        .iter()
+        .skip(1)
+        .filter(|v| v.p_value.map_or(false, |p| p < alpha / comparisons))
+        .collect();
+
+    let (status, winner) = if !enough_samples {
+        (ExperimentStatus::NeedsMoreSamples, None)
+    } else if significant.is_empty() {
+        (ExperimentStatus::NoSignificantDifference, None)
+    } else {
+        let best = significant
+            .iter()
+            .max_by(|a, b| a.conversion_rate.partial_cmp(&b.conversion_rate).unwrap_or(std::cmp::Ordering::Equal))
+            .expect("non-empty");
+        let winner = if best.conversion_rate > control_rate {
+            best.key.clone()
+        } else {
+            variants[0].key.clone()
+        };
+        (ExperimentStatus::Significant, Some(winner))
+    };
+
+    ExperimentResults {
+        experiment_id: experiment.id.clone(),
+        name: experiment.name.clone(),
+        status,
+        minimum_sample_size_per_variant: minimum_sample,
+        variants,
+        winner,
+        confidence_level: experiment.confidence_level,
+    }
+}
+
+fn rate(conversions: u64, users: u64) -> f64 {
+    if users == 0 { 0.0 } else { conversions as f64 / users as f64 }
+}
+
+/// Two-sided p-value of a pooled two-proportion z-test
+fn two_proportion_p_value(c1: u64, n1: u64, c2: u64, n2: u64) -> Option<f64> {
+    if n1 == 0 || n2 == 0 {
+        return None;
+    }
+    let p1 = rate(c1, n1);
+    let p2 = rate(c2, n2);
+    let pooled = (c1 + c2) as f64 / (n1 + n2) as f64;
+    let se = (pooled * (1.0 - pooled) * (1.0 / n1 as f64 + 1.0 / n2 as f64)).sqrt();
+    if se == 0.0 {
+        return Some(1.0);
+    }
+    let z = (p2 - p1) / se;
+    Some(2.0 * (1.0 - normal_cdf(z.abs())))
+}
+
+/// Users needed per variant to detect `mde` over `baseline` with the given error rates
+pub fn minimum_sample_size(baseline: f64, mde: f64, confidence_level: f64, power: f64) -> u64 {
+    let p1 = baseline;
+    let p2 = (baseline + mde).min(0.999);
+    let p_bar = (p1 + p2) / 2.0;
+    let z_alpha = inverse_normal_cdf(1.0 - (1.0 - confidence_level) / 2.0);
+    let z_beta = inverse_normal_cdf(power);
+    let numerator = z_alpha * (2.0 * p_bar * (1.0 - p_bar)).sqrt() + z_beta * (p1 * (1.0 - p1) + p2 * (1.0 - p2)).sqrt();
+    ((numerator * numerator) / ((p2 - p1) * (p2 - p1))).ceil() as u64
+}
+
+fn normal_cdf(x: f64) -> f64 {
+    0.5 * (1.0 + erf(x / std::f64::consts::SQRT_2))
+}
+
+/// Abramowitz & Stegun 7.1.26 (max error 1.5e-7)
+fn erf(x: f64) -> f64 {
+    let sign = if x < 0.0 { -1.0 } else { 1.0 };
+    let x = x.abs();
+    let t = 1.0 / (1.0 + 0.3275911 * x);
+    let y = 1.0
+        - (((((1.061405429 * t - 1.453152027) * t) + 1.421413741) * t - 0.284496736) * t + 0.254829592)
+            * t
+            * (-x * x).exp();
+    sign * y
+}
+
+/// Acklam's rational approximation of the standard normal quantile
+fn inverse_normal_cdf(p: f64) -> f64 {
+    const A: [f64; 6] = [-3.969683028665376e1, 2.209460984245205e2, -2.759285104469687e2, 1.383577518672690e2, -3.066479806614716e1, 2.506628277459239];
+    const B: [f64; 5] = [-5.447609879822406e1, 1.615858368580409e2, -1.556989798598866e2, 6.680131188771972e1, -1.328068155288572e1];
+    const C: [f64; 6] = [-7.784894002430293e-3, -3.223964580411365e-1, -2.400758277161838, -2.549732539343734, 4.374664141464968, 2.938163982698783];
+    const D: [f64; 4] = [7.784695709041462e-3, 3.224671290700398e-1, 2.445134137142996, 3.754408661907416];
+    const P_LOW: f64 = 0.02425;
+
+    let p = p.clamp(1e-12, 1.0 - 1e-12);
+    if p < P_LOW {
+        let q = (-2.0 * p.ln()).sqrt();
+        (((((C[0] * q + C[1]) * q + C[2]) * q + C[3]) * q + C[4]) * q + C[5]) / ((((D[0] * q + D[1]) * q + D[2]) * q + D[3]) * q + 1.0)
+    } else if p <= 1.0 - P_LOW {
+        let q = p - 0.5;
+        let r = q * q;
+        (((((A[0] * r + A[1]) * r + A[2]) * r + A[3]) * r + A[4]) * r + A[5]) * q
+            / (((((B[0] * r + B[1]) * r + B[2]) * r + B[3]) * r + B[4]) * r + 1.0)
+    } else {
+        let q = (-2.0 * (1.0 - p).ln()).sqrt();
+        -(((((C[0] * q + C[1]) * q + C[2]) * q + C[3]) * q + C[4]) * q + C[5]) / ((((D[0] * q + D[1]) * q + D[2]) * q + D[3]) * q + 1.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn experiment(traffic: f64) -> Experiment {
+        Experiment {
+            id: "pricing-cta".to_string(),
+            org_id: "org".to_string(),
+            name: "Pricing CTA".to_string(),
+            variants: vec![
+                Variant { key: "control".to_string(), name: "Start Free Trial".to_string(), weight: 50 },
+                Variant { key: "treatment".to_string(), name: "Get Started Free".to_string(), weight: 50 },
+            ],
+            traffic_allocation: traffic,
+            conflicts_with: vec![],
+            conversion_event: "trial_started".to_string(),
+            baseline_rate: 0.13,
+            minimum_detectable_effect: 0.02,
+            confidence_level: 0.95,
+            statistical_power: 0.8,
+            started_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_assignment_is_deterministic_and_balanced() {
+        let exp = experiment(1.0);
+        let first = pick_variant(&exp, "user-42").map(|v| v.key.clone());
+        assert_eq!(first, pick_variant(&exp, "user-42").map(|v| v.key.clone()));
+
+        let treated = (0..10_000)
+            .filter(|i| pick_variant(&exp, &format!("user-{}", i)).map_or(false, |v| v.key == "treatment"))
+            .count();
+        assert!((4_500..5_500).contains(&treated), "unbalanced split: {}", treated);
+    }
+
+    #[test]
+    fn test_traffic_allocation() {
+        let exp = experiment(0.2);
+        let layer = TrafficLayer::new(vec![&exp]);
+        let enrolled = (0..10_000)
+            .filter(|i| layer.owner(&format!("user-{}", i)).is_some())
+            .count();
+        assert!((1_700..2_300).contains(&enrolled), "unexpected enrollment: {}", enrolled);
+    }
+
+    fn create(service: &ExperimentService, id: &str, traffic: f64, conflicts_with: &[&str]) {
+        let exp = experiment(traffic);
+        service
+            .create_experiment("org", CreateExperimentRequest {
+                id: id.to_string(),
+                name: exp.name,
+                variants: exp.variants,
+                traffic_allocation: Some(traffic),
+                conflicts_with: Some(conflicts_with.iter().map(|c| c.to_string()).collect()),
+                conversion_event: exp.conversion_event,
+                baseline_rate: None,
+                minimum_detectable_effect: None,
+                confidence_level: None,
+                statistical_power: None,
+            })
+            .unwrap();
+    }
+
+    #[test]
+    fn test_mutually_exclusive_experiments_split_users() {
+        let service = ExperimentService { experiments: RwLock::new(HashMap::new()) };
+        // Each lists the other; "banner" is only linked from "checkout"
+        create(&service, "pricing", 0.3, &["checkout"]);
+        create(&service, "checkout", 0.3, &["pricing", "banner"]);
+        create(&service, "banner", 0.3, &[]);
+
+        let mut enrolled: HashMap<&str, usize> = HashMap::new();
+        for i in 0..3_000 {
+            let user = format!("user-{}", i);
+            let assignments: Vec<(&str, Assignment)> = ["pricing", "checkout", "banner"]
+                .iter()
+                .map(|id| (*id, service.assign("org", id, &user).unwrap()))
+                .collect();
+            let owners: Vec<&str> = assignments
+                .iter()
+                .filter(|(_, a)| matches!(a, Assignment::Assigned { .. }))
+                .map(|(id, _)| *id)
+                .collect();
+            // The layer is 90% allocated, so at most one takes the user
+            assert!(owners.len() <= 1, "{} is in {:?}", user, owners);
+            if owners.is_empty() {
+                continue;
+            }
+            for (_, assignment) in &assignments {
+                if let Assignment::Excluded { conflicting_experiment } = assignment {
+                    assert_eq!(conflicting_experiment, owners[0]);
+                }
+            }
+            assert_eq!(service.assign("org", owners[0], &user).unwrap(), assignments.iter().find(|(id, _)| *id == owners[0]).unwrap().1);
+            *enrolled.entry(owners[0]).or_default() += 1;
+        }
+        for id in ["pricing", "checkout", "banner"] {
+            assert!((800..1_000).contains(&enrolled[id]), "unbalanced exclusion: {:?}", enrolled);
+        }
+
+        // Experiments outside the group are unaffected
+        create(&service, "onboarding", 1.0, &[]);
+        assert!(matches!(service.assign("org", "onboarding", "user-1").unwrap(), Assignment::Assigned { .. }));
+    }
+
+    #[test]
+    fn test_joining_a_group_only_takes_unassigned_slots() {
+        let service = ExperimentService { experiments: RwLock::new(HashMap::new()) };
+        create(&service, "pricing", 0.4, &[]);
+        create(&service, "checkout", 0.4, &["pricing"]);
+
+        let users: Vec<String> = (0..3_000).map(|i| format!("user-{}", i)).collect();
+        let before: Vec<(Assignment, Assignment)> = users
+            .iter()
+            .map(|user| (service.assign("org", "pricing", user).unwrap(), service.assign("org", "checkout", user).unwrap()))
+            .collect();
+
+        create(&service, "banner", 0.4, &["checkout"]);
+        let mut banner = 0;
+        for (user, (pricing, checkout)) in users.iter().zip(before) {
+            let was_enrolled = matches!(pricing, Assignment::Assigned { .. }) || matches!(checkout, Assignment::Assigned { .. });
+            let joined = service.assign("org", "banner", user).unwrap();
+            if was_enrolled {
+                assert_eq!(service.assign("org", "pricing", user).unwrap(), pricing);
+                assert_eq!(service.assign("org", "checkout", user).unwrap(), checkout);
+                assert!(!matches!(joined, Assignment::Assigned { .. }), "{} was taken from an existing member", user);
+            } else if matches!(joined, Assignment::Assigned { .. }) {
+                banner += 1;
+            }
+        }
+        // Only the last 20% of the layer was still free
+        assert!((450..750).contains(&banner), "unexpected enrollment: {}", banner);
+    }
+
+    #[test]
+    fn test_statistics() {
+        assert!((inverse_normal_cdf(0.975) - 1.959964).abs() < 1e-4);
+        assert!((normal_cdf(1.959964) - 0.975).abs() < 1e-4);
+
+        let n = minimum_sample_size(0.13, 0.02, 0.95, 0.8);
+        assert!((4_500..5_200).contains(&n), "unexpected sample size: {}", n);
+    }
+
+    #[test]
+    fn test_peeking_guard() {
+        let exp = experiment(1.0);
+        let early = analyze_counts(&exp, &[
+            ("control".to_string(), "A".to_string(), 200, 20),
+            ("treatment".to_string(), "B".to_string(), 200, 40),
+        ]);
+        assert_eq!(early.status, ExperimentStatus::NeedsMoreSamples);
+        assert!(early.winner.is_none());
+
+        let done = analyze_counts(&exp, &[
+            ("control".to_string(), "A".to_string(), 6_000, 780),
+            ("treatment".to_string(), "B".to_string(), 6_000, 960),
+        ]);
+        assert_eq!(done.status, ExperimentStatus::Significant);
+        assert_eq!(done.winner.as_deref(), Some("treatment"));
+    }
+}
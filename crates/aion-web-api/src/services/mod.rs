@@ -3,12 +3,25 @@
 pub mod monitoring;
 pub mod ai;
 pub mod deployment;
+pub mod deployment_planner;
 pub mod auth;
 pub mod email_marketing;
+pub mod analytics;
+pub mod experiments;
+pub mod provider_pool;
+pub mod session_store;
+pub mod single_flight;
+pub mod uploads;
+pub mod webhooks;
 
 // Re-export services
 pub use monitoring::MonitoringService;
 pub use ai::AIService;
 pub use deployment::DeploymentService;
 pub use auth::AuthService;
-pub use email_marketing::EmailMarketingService;
\ No newline at end of file
+pub use session_store::PgSessionStore;
+pub use email_marketing::EmailMarketingService;
+pub use analytics::AnalyticsService;
+pub use experiments::ExperimentService;
+pub use uploads::UploadService;
+pub use webhooks::WebhookService;
\ No newline at end of file
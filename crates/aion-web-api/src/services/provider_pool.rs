@@ -0,0 +1,367 @@
+//! Isolation for AI provider calls
+//!
+//! Every provider gets its own bulkhead (a fixed number of concurrent call
+//! slots) and circuit breaker. A provider that hangs can only tie up its own
+//! slots, and once they are taken further calls are rejected immediately
+//! instead of queueing. A provider that keeps failing trips its breaker and is
+//! skipped until the recovery timeout passes. Rejected or failed calls move on
+//! to the next provider in the failover chain.
+
+use crate::middleware::error_handling::{CircuitBreaker, CircuitState};
+use serde::{Deserialize, Serialize};
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use thiserror::Error;
+use tokio::sync::Semaphore;
+use tracing::warn;
+
+/// Limits applied to each provider
+#[derive(Debug, Clone)]
+pub struct ProviderPolicy {
+    /// Concurrent calls allowed per provider
+    pub max_concurrent_calls: usize,
+    /// Calls running longer than this count as failures
+    pub call_timeout: Duration,
+    /// Consecutive failures that open the breaker
+    pub failure_threshold: u32,
+    /// How long an open breaker rejects calls before letting probes through
+    pub recovery_timeout: Duration,
+    /// Successful probes needed to close the breaker again
+    pub success_threshold: u32,
+}
+
+impl Default for ProviderPolicy {
+    fn default() -> Self {
+        Self {
+            max_concurrent_calls: 8,
+            call_timeout: Duration::from_secs(60),
+            failure_threshold: 5,
+            recovery_timeout: Duration::from_secs(30),
+            success_threshold: 2,
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum ProviderError {
+    #[error("circuit breaker for provider {provider} is open")]
+    CircuitOpen { provider: String },
+    #[error("provider {provider} is saturated ({capacity} calls in flight)")]
+    BulkheadFull { provider: String, capacity: usize },
+    #[error("provider {provider} did not respond within {timeout:?}")]
+    Timeout { provider: String, timeout: Duration },
+    #[error("provider {provider} failed: {source}")]
+    Failed {
+        provider: String,
+        #[source]
+        source: anyhow::Error,
+    },
+    #[error("no AI provider available: {}", describe_attempts(.0))]
+    Exhausted(Vec<ProviderError>),
+}
+
+/// Breaker and bulkhead state of one provider
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProviderMetrics {
+    pub provider: String,
+    /// `closed`, `open` or `half_open`
+    pub circuit_state: String,
+    pub in_flight: usize,
+    pub capacity: usize,
+    /// Share of bulkhead slots in use, 0.0 to 1.0
+    pub saturation: f64,
+    pub successes: u64,
+    pub failures: u64,
+    pub timeouts: u64,
+    pub rejected_by_circuit: u64,
+    pub rejected_by_bulkhead: u64,
+}
+
+#[derive(Debug, Default)]
+struct ProviderCounters {
+    successes: AtomicU64,
+    failures: AtomicU64,
+    timeouts: AtomicU64,
+    rejected_by_circuit: AtomicU64,
+    rejected_by_bulkhead: AtomicU64,
+}
+
+struct GuardedProvider<P> {
+    name: String,
+    provider: P,
+    slots: Arc<Semaphore>,
+    breaker: Mutex<CircuitBreaker>,
+    counters: ProviderCounters,
+}
+
+/// Ordered failover chain of providers, each behind its own bulkhead and breaker
+pub struct ProviderPool<P> {
+    policy: ProviderPolicy,
+    providers: Vec<GuardedProvider<P>>,
+}
+
+impl<P> ProviderPool<P> {
+    pub fn new(policy: ProviderPolicy) -> Self {
+        Self {
+            policy,
+            providers: Vec::new(),
+        }
+    }
+
+    /// Append a provider to the failover chain
+    pub fn add_provider(&mut self, name: impl Into<String>, provider: P) {
+        self.providers.push(GuardedProvider {
+            name: name.into(),
+            provider,
+            slots: Arc::new(Semaphore::new(self.policy.max_concurrent_calls)),
+            breaker: Mutex::new(CircuitBreaker {
+                failure_threshold: self.policy.failure_threshold,
+                recovery_timeout: self.policy.recovery_timeout,
+                success_threshold: self.policy.success_threshold,
+                ..Default::default()
+            }),
+            counters: ProviderCounters::default(),
+        });
+    }
+
+    pub fn provider_names(&self) -> Vec<String> {
+        self.providers.iter().map(|p| p.name.clone()).collect()
+    }
+
+    /// Run `call` against the first provider that accepts it, in chain order
+    pub async fn call<T, F, Fut>(&self, call: F) -> Result<T, ProviderError>
+    where
+        F: FnMut(&P) -> Fut,
+        Fut: Future<Output = anyhow::Result<T>>,
+    {
+        self.call_where(|_| true, call).await
+    }
+
+    /// Like [`call`](Self::call), skipping providers `eligible` rejects
+    pub async fn call_where<T, E, F, Fut>(&self, eligible: E, mut call: F) -> Result<T, ProviderError>
+    where
+        E: Fn(&P) -> bool,
+        F: FnMut(&P) -> Fut,
+        Fut: Future<Output = anyhow::Result<T>>,
+    {
+        let mut attempts = Vec::new();
+        for guarded in self.providers.iter().filter(|guarded| eligible(&guarded.provider)) {
+            match self.call_provider(guarded, &mut call).await {
+                Ok(value) => return Ok(value),
+                Err(e) => {
+                    warn!("AI provider {} unavailable, trying next: {}", guarded.name, e);
+                    attempts.push(e);
+                }
+            }
+        }
+        Err(ProviderError::Exhausted(attempts))
+    }
+
+    pub fn metrics(&self) -> Vec<ProviderMetrics> {
+        self.providers
+            .iter()
+            .map(|guarded| {
+                let capacity = self.policy.max_concurrent_calls;
+                let in_flight = capacity - guarded.slots.available_permits();
+                let circuit_state = match guarded.breaker.lock().unwrap().state {
+                    CircuitState::Closed => "closed",
+                    CircuitState::Open => "open",
+                    CircuitState::HalfOpen => "half_open",
+                };
+                let counters = &guarded.counters;
+                ProviderMetrics {
+                    provider: guarded.name.clone(),
+                    circuit_state: circuit_state.to_string(),
+                    in_flight,
+                    capacity,
+                    saturation: in_flight as f64 / capacity.max(1) as f64,
+                    successes: counters.successes.load(Ordering::Relaxed),
+                    failures: counters.failures.load(Ordering::Relaxed),
+                    timeouts: counters.timeouts.load(Ordering::Relaxed),
+                    rejected_by_circuit: counters.rejected_by_circuit.load(Ordering::Relaxed),
+                    rejected_by_bulkhead: counters.rejected_by_bulkhead.load(Ordering::Relaxed),
+                }
+            })
+            .collect()
+    }
+
+    async fn call_provider<T, F, Fut>(&self, guarded: &GuardedProvider<P>, call: &mut F) -> Result<T, ProviderError>
+    where
+        F: FnMut(&P) -> Fut,
+        Fut: Future<Output = anyhow::Result<T>>,
+    {
+        let provider = guarded.name.clone();
+        let counters = &guarded.counters;
+
+        if !guarded.breaker.lock().unwrap().should_allow_request() {
+            counters.rejected_by_circuit.fetch_add(1, Ordering::Relaxed);
+            return Err(ProviderError::CircuitOpen { provider });
+        }
+
+        // Fail fast rather than queue behind a slow provider
+        let Ok(_slot) = guarded.slots.clone().try_acquire_owned() else {
+            counters.rejected_by_bulkhead.fetch_add(1, Ordering::Relaxed);
+            return Err(ProviderError::BulkheadFull {
+                provider,
+                capacity: self.policy.max_concurrent_calls,
+            });
+        };
+
+        let result = tokio::time::timeout(self.policy.call_timeout, call(&guarded.provider)).await;
+        let mut breaker = guarded.breaker.lock().unwrap();
+        match result {
+            Ok(Ok(value)) => {
+                breaker.record_success();
+                counters.successes.fetch_add(1, Ordering::Relaxed);
+                Ok(value)
+            }
+            Ok(Err(source)) => {
+                breaker.record_failure();
+                counters.failures.fetch_add(1, Ordering::Relaxed);
+                Err(ProviderError::Failed { provider, source })
+            }
+            Err(_) => {
+                breaker.record_failure();
+                counters.timeouts.fetch_add(1, Ordering::Relaxed);
+                Err(ProviderError::Timeout {
+                    provider,
+                    timeout: self.policy.call_timeout,
+                })
+            }
+        }
+    }
+}
+
+fn describe_attempts(attempts: &[ProviderError]) -> String {
+    if attempts.is_empty() {
+        return "no providers configured".to_string();
+    }
+    attempts.iter().map(|e| e.to_string()).collect::<Vec<_>>().join("; ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Clone, Copy)]
+    enum Behavior {
+        Hang,
+        Fail,
+        Succeed(&'static str),
+    }
+
+    async fn run(behavior: Behavior) -> anyhow::Result<&'static str> {
+        match behavior {
+            Behavior::Hang => std::future::pending().await,
+            Behavior::Fail => Err(anyhow::anyhow!("upstream returned 500")),
+            Behavior::Succeed(name) => Ok(name),
+        }
+    }
+
+    fn pool(policy: ProviderPolicy, providers: &[(&str, Behavior)]) -> Arc<ProviderPool<Behavior>> {
+        let mut pool = ProviderPool::new(policy);
+        for (name, behavior) in providers {
+            pool.add_provider(*name, *behavior);
+        }
+        Arc::new(pool)
+    }
+
+    fn metrics_for(pool: &ProviderPool<Behavior>, provider: &str) -> ProviderMetrics {
+        pool.metrics().into_iter().find(|m| m.provider == provider).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_hung_provider_does_not_starve_healthy_provider() {
+        let policy = ProviderPolicy {
+            max_concurrent_calls: 2,
+            call_timeout: Duration::from_secs(300),
+            ..Default::default()
+        };
+        let pool = pool(policy, &[("hung", Behavior::Hang), ("healthy", Behavior::Succeed("healthy"))]);
+
+        // Occupy every slot of the hung provider
+        let stuck: Vec<_> = (0..2)
+            .map(|_| {
+                let pool = pool.clone();
+                tokio::spawn(async move { pool.call(|b| run(*b)).await })
+            })
+            .collect();
+        while metrics_for(&pool, "hung").in_flight < 2 {
+            tokio::task::yield_now().await;
+        }
+
+        let served = tokio::time::timeout(Duration::from_secs(5), async {
+            for _ in 0..20 {
+                assert_eq!(pool.call(|b| run(*b)).await.unwrap(), "healthy");
+            }
+        })
+        .await;
+        assert!(served.is_ok(), "requests to the healthy provider were blocked");
+
+        let hung = metrics_for(&pool, "hung");
+        assert_eq!(hung.saturation, 1.0);
+        assert_eq!(hung.rejected_by_bulkhead, 20);
+        assert_eq!(metrics_for(&pool, "healthy").successes, 20);
+
+        for task in stuck {
+            task.abort();
+        }
+    }
+
+    #[tokio::test]
+    async fn test_failing_provider_trips_only_its_own_breaker() {
+        let policy = ProviderPolicy {
+            failure_threshold: 3,
+            recovery_timeout: Duration::from_secs(300),
+            ..Default::default()
+        };
+        let pool = pool(policy, &[("flaky", Behavior::Fail), ("healthy", Behavior::Succeed("healthy"))]);
+
+        for _ in 0..5 {
+            assert_eq!(pool.call(|b| run(*b)).await.unwrap(), "healthy");
+        }
+
+        let flaky = metrics_for(&pool, "flaky");
+        assert_eq!(flaky.circuit_state, "open");
+        assert_eq!(flaky.failures, 3);
+        assert_eq!(flaky.rejected_by_circuit, 2);
+
+        let healthy = metrics_for(&pool, "healthy");
+        assert_eq!(healthy.circuit_state, "closed");
+        assert_eq!(healthy.successes, 5);
+    }
+
+    #[tokio::test]
+    async fn test_ineligible_providers_are_skipped() {
+        let pool = pool(ProviderPolicy::default(), &[
+            ("first", Behavior::Succeed("first")),
+            ("second", Behavior::Succeed("second")),
+        ]);
+
+        let served = pool.call_where(|b| matches!(b, Behavior::Succeed("second")), |b| run(*b)).await;
+        assert_eq!(served.unwrap(), "second");
+        assert_eq!(metrics_for(&pool, "first").successes, 0);
+
+        assert!(matches!(pool.call_where(|_| false, |b| run(*b)).await, Err(ProviderError::Exhausted(attempts)) if attempts.is_empty()));
+    }
+
+    #[tokio::test]
+    async fn test_open_breaker_fails_fast_without_fallback() {
+        let policy = ProviderPolicy {
+            failure_threshold: 1,
+            recovery_timeout: Duration::from_secs(300),
+            ..Default::default()
+        };
+        let pool = pool(policy, &[("only", Behavior::Fail)]);
+
+        assert!(pool.call(|b| run(*b)).await.is_err());
+        match pool.call(|b| run(*b)).await {
+            Err(ProviderError::Exhausted(attempts)) => {
+                assert!(matches!(attempts.as_slice(), [ProviderError::CircuitOpen { .. }]));
+            }
+            other => panic!("expected exhausted chain, got {:?}", other.map(|_| ())),
+        }
+    }
+}
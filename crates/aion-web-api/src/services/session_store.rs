@@ -0,0 +1,188 @@
+//! Postgres storage for login sessions and refresh token families
+//!
+//! Sessions live in `user_sessions` and refresh tokens in
+//! `user_refresh_tokens`, both created by `AuthService`. Keeping them in the
+//! database lets a refresh token outlive a restart and work on any instance.
+
+use aion_auth::{RefreshTokenRecord, Session, SessionStore};
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::postgres::PgRow;
+use sqlx::{PgPool, Row};
+use std::sync::Arc;
+use uuid::Uuid;
+
+const SESSION_COLUMNS: &str = "id, user_id, tenant_id, refresh_token_hash, host(ip_address) AS ip_address, \
+     user_agent, created_at, expires_at, last_used, is_active";
+
+const REFRESH_TOKEN_COLUMNS: &str = "token_hash, family_id, session_id, parent_hash, issued_at, expires_at, used_at, revoked";
+
+/// `SessionStore` over the `user_sessions` and `user_refresh_tokens` tables
+pub struct PgSessionStore {
+    db_pool: Arc<PgPool>,
+}
+
+impl PgSessionStore {
+    pub fn new(db_pool: Arc<PgPool>) -> Self {
+        Self { db_pool }
+    }
+
+    async fn save_session(&self, session: &Session) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO user_sessions
+                (id, user_id, tenant_id, refresh_token_hash, ip_address, user_agent, created_at, expires_at, last_used, is_active)
+            VALUES ($1, $2, $3, $4, $5::inet, $6, $7, $8, $9, $10)
+            ON CONFLICT (id) DO UPDATE SET
+                expires_at = EXCLUDED.expires_at,
+                last_used = EXCLUDED.last_used,
+                is_active = EXCLUDED.is_active
+            "#,
+        )
+        .bind(session.id)
+        .bind(session.user_id)
+        .bind(session.tenant_id)
+        .bind(&session.token_hash)
+        .bind(&session.ip_address)
+        .bind(&session.user_agent)
+        .bind(session.created_at)
+        .bind(session.expires_at)
+        .bind(session.last_activity)
+        .bind(session.is_active)
+        .execute(&*self.db_pool)
+        .await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl SessionStore for PgSessionStore {
+    async fn create_session(&self, session: Session) -> Result<()> {
+        self.save_session(&session).await
+    }
+
+    async fn get_session(&self, session_id: Uuid) -> Result<Option<Session>> {
+        let row = sqlx::query(&format!("SELECT {} FROM user_sessions WHERE id = $1", SESSION_COLUMNS))
+            .bind(session_id)
+            .fetch_optional(&*self.db_pool)
+            .await?;
+        row.as_ref().map(session_from_row).transpose()
+    }
+
+    async fn update_session(&self, session: Session) -> Result<()> {
+        self.save_session(&session).await
+    }
+
+    async fn delete_session(&self, session_id: Uuid) -> Result<()> {
+        sqlx::query("DELETE FROM user_sessions WHERE id = $1")
+            .bind(session_id)
+            .execute(&*self.db_pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn delete_user_sessions(&self, user_id: Uuid) -> Result<()> {
+        sqlx::query("DELETE FROM user_sessions WHERE user_id = $1")
+            .bind(user_id)
+            .execute(&*self.db_pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn cleanup_expired_sessions(&self) -> Result<()> {
+        sqlx::query("DELETE FROM user_sessions WHERE expires_at <= NOW()")
+            .execute(&*self.db_pool)
+            .await?;
+        sqlx::query("DELETE FROM user_refresh_tokens WHERE expires_at <= NOW()")
+            .execute(&*self.db_pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn save_refresh_token(&self, record: RefreshTokenRecord) -> Result<()> {
+        sqlx::query(&format!(
+            "INSERT INTO user_refresh_tokens ({}) VALUES ($1, $2, $3, $4, $5, $6, $7, $8)",
+            REFRESH_TOKEN_COLUMNS
+        ))
+        .bind(&record.token_hash)
+        .bind(record.family_id)
+        .bind(record.session_id)
+        .bind(&record.parent_hash)
+        .bind(record.issued_at)
+        .bind(record.expires_at)
+        .bind(record.used_at)
+        .bind(record.revoked)
+        .execute(&*self.db_pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn mark_refresh_token_used(&self, token_hash: &str, used_at: DateTime<Utc>) -> Result<Option<RefreshTokenRecord>> {
+        // The CTE reads the row as it was before the update; the row lock makes
+        // a concurrent exchange of the same token wait and then see it used
+        let row = sqlx::query(&format!(
+            r#"
+            WITH previous AS (
+                SELECT {columns} FROM user_refresh_tokens WHERE token_hash = $1 FOR UPDATE
+            )
+            UPDATE user_refresh_tokens AS token
+            SET used_at = COALESCE(token.used_at, $2)
+            FROM previous
+            WHERE token.token_hash = previous.token_hash
+            RETURNING {returning}
+            "#,
+            columns = REFRESH_TOKEN_COLUMNS,
+            returning = REFRESH_TOKEN_COLUMNS
+                .split(", ")
+                .map(|column| format!("previous.{}", column))
+                .collect::<Vec<_>>()
+                .join(", "),
+        ))
+        .bind(token_hash)
+        .bind(used_at)
+        .fetch_optional(&*self.db_pool)
+        .await?;
+        row.as_ref().map(refresh_token_from_row).transpose()
+    }
+
+    async fn revoke_refresh_token_family(&self, family_id: Uuid) -> Result<()> {
+        sqlx::query("UPDATE user_refresh_tokens SET revoked = true WHERE family_id = $1")
+            .bind(family_id)
+            .execute(&*self.db_pool)
+            .await?;
+        Ok(())
+    }
+}
+
+fn session_from_row(row: &PgRow) -> Result<Session> {
+    let created_at: Option<DateTime<Utc>> = row.try_get("created_at")?;
+    let created_at = created_at.unwrap_or_else(Utc::now);
+    let last_used: Option<DateTime<Utc>> = row.try_get("last_used")?;
+    let is_active: Option<bool> = row.try_get("is_active")?;
+    Ok(Session {
+        id: row.try_get("id")?,
+        user_id: row.try_get("user_id")?,
+        tenant_id: row.try_get("tenant_id")?,
+        token_hash: row.try_get("refresh_token_hash")?,
+        ip_address: row.try_get("ip_address")?,
+        user_agent: row.try_get("user_agent")?,
+        created_at,
+        expires_at: row.try_get("expires_at")?,
+        last_activity: last_used.unwrap_or(created_at),
+        is_active: is_active.unwrap_or(true),
+    })
+}
+
+fn refresh_token_from_row(row: &PgRow) -> Result<RefreshTokenRecord> {
+    Ok(RefreshTokenRecord {
+        token_hash: row.try_get("token_hash")?,
+        family_id: row.try_get("family_id")?,
+        session_id: row.try_get("session_id")?,
+        parent_hash: row.try_get("parent_hash")?,
+        issued_at: row.try_get("issued_at")?,
+        expires_at: row.try_get("expires_at")?,
+        used_at: row.try_get("used_at")?,
+        revoked: row.try_get("revoked")?,
+    })
+}
@@ -0,0 +1,226 @@
+//! Single-flight execution of identical concurrent work
+//!
+//! The first caller for a key starts the work on its own task; callers that
+//! arrive while it runs wait for the same result instead of starting another
+//! run. Because the work is spawned, a caller that gives up does not cancel it
+//! for the others. Completed results are not kept: once the work finishes, the
+//! next call for the key starts afresh.
+
+use futures::future::{BoxFuture, FutureExt, Shared};
+use std::collections::HashMap;
+use std::future::Future;
+use std::hash::Hash;
+use std::sync::{Arc, Mutex};
+use thiserror::Error;
+
+/// Error of a shared run, delivered to every waiter
+#[derive(Debug, Clone, Error)]
+#[error("{0:#}")]
+pub struct SharedError(Arc<anyhow::Error>);
+
+impl SharedError {
+    /// The original error, for downcasting
+    pub fn inner(&self) -> &anyhow::Error {
+        &self.0
+    }
+}
+
+type Flight<V> = Shared<BoxFuture<'static, Result<V, SharedError>>>;
+
+/// Deduplicates concurrent work by key
+pub struct SingleFlight<K, V> {
+    in_flight: Arc<Mutex<HashMap<K, Flight<V>>>>,
+}
+
+impl<K, V> Clone for SingleFlight<K, V> {
+    fn clone(&self) -> Self {
+        Self {
+            in_flight: Arc::clone(&self.in_flight),
+        }
+    }
+}
+
+impl<K, V> Default for SingleFlight<K, V> {
+    fn default() -> Self {
+        Self {
+            in_flight: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+}
+
+impl<K, V> SingleFlight<K, V>
+where
+    K: Hash + Eq + Clone + Send + 'static,
+    V: Clone + Send + Sync + 'static,
+{
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Run `work` for `key`, or join the run already in flight for it
+    pub async fn run<F, Fut>(&self, key: K, work: F) -> Result<V, SharedError>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = anyhow::Result<V>> + Send + 'static,
+    {
+        let flight = {
+            let mut in_flight = self.in_flight.lock().unwrap();
+            match in_flight.get(&key) {
+                Some(flight) => flight.clone(),
+                None => {
+                    let flight = self.start(key.clone(), work());
+                    in_flight.insert(key, flight.clone());
+                    flight
+                }
+            }
+        };
+        flight.await
+    }
+
+    /// Number of keys with work in flight
+    pub fn in_flight(&self) -> usize {
+        self.in_flight.lock().unwrap().len()
+    }
+
+    fn start<Fut>(&self, key: K, work: Fut) -> Flight<V>
+    where
+        Fut: Future<Output = anyhow::Result<V>> + Send + 'static,
+    {
+        let in_flight = Arc::clone(&self.in_flight);
+        let task = tokio::spawn(async move {
+            let result = work.await;
+            // Runs even when every caller has gone away, so the key never goes stale
+            in_flight.lock().unwrap().remove(&key);
+            result
+        });
+
+        async move {
+            match task.await {
+                Ok(Ok(value)) => Ok(value),
+                Ok(Err(e)) => Err(SharedError(Arc::new(e))),
+                Err(e) => Err(SharedError(Arc::new(anyhow::anyhow!("Shared task failed: {}", e)))),
+            }
+        }
+        .boxed()
+        .shared()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+    use tokio::sync::watch;
+
+    /// Work that counts its runs and finishes once the gate opens
+    fn gated_work(
+        runs: &Arc<AtomicUsize>,
+        gate: &watch::Receiver<bool>,
+        result: anyhow::Result<u32>,
+    ) -> impl Future<Output = anyhow::Result<u32>> + Send + 'static {
+        let runs = runs.clone();
+        let mut gate = gate.clone();
+        async move {
+            runs.fetch_add(1, Ordering::SeqCst);
+            gate.wait_for(|open| *open).await.unwrap();
+            result
+        }
+    }
+
+    async fn wait_until_joined(flights: &SingleFlight<&'static str, u32>, runs: &AtomicUsize) {
+        while flights.in_flight() == 0 || runs.load(Ordering::SeqCst) == 0 {
+            tokio::task::yield_now().await;
+        }
+    }
+
+    #[tokio::test]
+    async fn test_identical_requests_share_one_run() {
+        let flights = SingleFlight::new();
+        let runs = Arc::new(AtomicUsize::new(0));
+        let (open, gate) = watch::channel(false);
+
+        let waiters: Vec<_> = (0..8)
+            .map(|_| {
+                let flights = flights.clone();
+                let work = gated_work(&runs, &gate, Ok(42));
+                tokio::spawn(async move { flights.run("prompt", || work).await })
+            })
+            .collect();
+        wait_until_joined(&flights, &runs).await;
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        open.send(true).unwrap();
+
+        for waiter in waiters {
+            assert_eq!(waiter.await.unwrap().unwrap(), 42);
+        }
+        assert_eq!(runs.load(Ordering::SeqCst), 1);
+        assert_eq!(flights.in_flight(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_different_keys_run_separately() {
+        let flights = SingleFlight::new();
+        let runs = Arc::new(AtomicUsize::new(0));
+        let (_open, gate) = watch::channel(true);
+
+        let a = flights.run("a", || gated_work(&runs, &gate, Ok(1)));
+        let b = flights.run("b", || gated_work(&runs, &gate, Ok(2)));
+        let (a, b) = tokio::join!(a, b);
+
+        assert_eq!((a.unwrap(), b.unwrap()), (1, 2));
+        assert_eq!(runs.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_caller_does_not_cancel_shared_work() {
+        let flights = SingleFlight::new();
+        let runs = Arc::new(AtomicUsize::new(0));
+        let (open, gate) = watch::channel(false);
+
+        let leader = {
+            let flights = flights.clone();
+            let work = gated_work(&runs, &gate, Ok(7));
+            tokio::spawn(async move { flights.run("prompt", || work).await })
+        };
+        wait_until_joined(&flights, &runs).await;
+
+        let follower = {
+            let flights = flights.clone();
+            let work = gated_work(&runs, &gate, Ok(0));
+            tokio::spawn(async move { flights.run("prompt", || work).await })
+        };
+        tokio::task::yield_now().await;
+
+        leader.abort();
+        assert!(leader.await.unwrap_err().is_cancelled());
+        open.send(true).unwrap();
+
+        assert_eq!(follower.await.unwrap().unwrap(), 7);
+        assert_eq!(runs.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_error_is_fanned_out_to_all_waiters() {
+        let flights = SingleFlight::new();
+        let runs = Arc::new(AtomicUsize::new(0));
+        let (open, gate) = watch::channel(false);
+
+        let waiters: Vec<_> = (0..3)
+            .map(|_| {
+                let flights = flights.clone();
+                let work = gated_work(&runs, &gate, Err(anyhow::anyhow!("backend exploded")));
+                tokio::spawn(async move { flights.run("prompt", || work).await })
+            })
+            .collect();
+        wait_until_joined(&flights, &runs).await;
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        open.send(true).unwrap();
+
+        for waiter in waiters {
+            let error = waiter.await.unwrap().unwrap_err();
+            assert_eq!(error.to_string(), "backend exploded");
+        }
+        assert_eq!(runs.load(Ordering::SeqCst), 1);
+    }
+}
@@ -0,0 +1,370 @@
+//! Temporary storage for large uploads
+//!
+//! Uploads are streamed chunk by chunk into `<upload_dir>/<id>.part` and only
+//! renamed to `<upload_dir>/<id>` once complete, so memory stays bounded no
+//! matter how large the file is. A [`PendingUpload`] that is dropped before
+//! [`PendingUpload::finish`] (size cap exceeded, client disconnected, ...)
+//! deletes its partial file. Completed uploads expire after a TTL.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use uuid::Uuid;
+
+const PARTIAL_EXTENSION: &str = "part";
+
+/// Content types accepted when none are configured; entries ending in `/*`
+/// match a whole family
+pub const DEFAULT_ALLOWED_CONTENT_TYPES: &[&str] = &[
+    "application/zip",
+    "application/x-zip-compressed",
+    "application/gzip",
+    "application/x-gzip",
+    "application/x-tar",
+    "application/json",
+    "application/x-yaml",
+    "application/octet-stream",
+    "text/*",
+    "audio/*",
+];
+
+#[derive(Debug, thiserror::Error)]
+pub enum UploadError {
+    #[error("Upload exceeds the maximum size of {limit} bytes")]
+    TooLarge { limit: u64 },
+    #[error("Content type '{0}' is not accepted for uploads")]
+    UnsupportedContentType(String),
+    #[error("Too many uploads in progress, retry later")]
+    Busy,
+    #[error("Upload {0} not found")]
+    NotFound(Uuid),
+    #[error("Upload {0} is not UTF-8 text")]
+    NotText(Uuid),
+    #[error("Upload storage error: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+#[derive(Debug, Clone)]
+pub struct UploadConfig {
+    pub upload_dir: PathBuf,
+    pub max_upload_bytes: u64,
+    pub max_concurrent_uploads: usize,
+    pub allowed_content_types: Vec<String>,
+    /// How long completed uploads are kept
+    pub ttl: Duration,
+}
+
+impl Default for UploadConfig {
+    fn default() -> Self {
+        Self {
+            upload_dir: std::env::temp_dir().join("ectus-uploads"),
+            max_upload_bytes: 1024 * 1024 * 1024, // 1GB
+            max_concurrent_uploads: 16,
+            allowed_content_types: DEFAULT_ALLOWED_CONTENT_TYPES.iter().map(|t| t.to_string()).collect(),
+            ttl: Duration::from_secs(24 * 60 * 60),
+        }
+    }
+}
+
+/// A completed upload
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UploadRecord {
+    pub upload_id: Uuid,
+    pub filename: Option<String>,
+    pub content_type: String,
+    pub size_bytes: u64,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
+pub struct UploadService {
+    config: UploadConfig,
+    uploads: RwLock<HashMap<Uuid, UploadRecord>>,
+    permits: Arc<Semaphore>,
+}
+
+impl UploadService {
+    /// Create the service, removing partial files left by a previous run
+    pub async fn new(config: UploadConfig) -> anyhow::Result<Self> {
+        tokio::fs::create_dir_all(&config.upload_dir).await?;
+
+        let mut entries = tokio::fs::read_dir(&config.upload_dir).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            // Only touch our own files in case the directory is shared
+            let file_name = entry.file_name().to_string_lossy().into_owned();
+            let stem = file_name.strip_suffix(&format!(".{}", PARTIAL_EXTENSION)).unwrap_or(&file_name);
+            if Uuid::parse_str(stem).is_err() {
+                continue;
+            }
+            // Completed uploads are not indexed across restarts either
+            if let Err(e) = tokio::fs::remove_file(entry.path()).await {
+                tracing::warn!("Could not remove stale upload {}: {}", entry.path().display(), e);
+            }
+        }
+
+        Ok(Self {
+            permits: Arc::new(Semaphore::new(config.max_concurrent_uploads)),
+            config,
+            uploads: RwLock::new(HashMap::new()),
+        })
+    }
+
+    pub fn max_upload_bytes(&self) -> u64 {
+        self.config.max_upload_bytes
+    }
+
+    /// Start receiving a file
+    pub async fn begin(&self, filename: Option<String>, content_type: &str) -> Result<PendingUpload, UploadError> {
+        let content_type = normalize_content_type(content_type);
+        if !self.is_allowed(&content_type) {
+            return Err(UploadError::UnsupportedContentType(content_type));
+        }
+        let permit = self.permits.clone().try_acquire_owned().map_err(|_| UploadError::Busy)?;
+
+        let upload_id = Uuid::new_v4();
+        let partial_path = self.partial_path(upload_id);
+        let file = tokio::fs::File::create(&partial_path).await?;
+
+        Ok(PendingUpload {
+            upload_id,
+            filename: filename.map(|name| sanitize_filename(&name)),
+            content_type,
+            partial_path: Some(partial_path),
+            final_path: self.upload_path(upload_id),
+            file,
+            written: 0,
+            limit: self.config.max_upload_bytes,
+            _permit: permit,
+        })
+    }
+
+    /// Make a finished upload available under its id
+    pub async fn finish(&self, upload: PendingUpload) -> Result<UploadRecord, UploadError> {
+        let record = upload.finish(self.config.ttl).await?;
+        self.uploads.write().unwrap().insert(record.upload_id, record.clone());
+        tracing::info!("Stored upload {} ({} bytes)", record.upload_id, record.size_bytes);
+        Ok(record)
+    }
+
+    pub fn get(&self, upload_id: Uuid) -> Result<UploadRecord, UploadError> {
+        let record = self.uploads.read().unwrap().get(&upload_id).cloned();
+        record
+            .filter(|record| record.expires_at > Utc::now())
+            .ok_or(UploadError::NotFound(upload_id))
+    }
+
+    /// Path of a completed upload's content
+    pub fn path(&self, upload_id: Uuid) -> Result<PathBuf, UploadError> {
+        self.get(upload_id).map(|_| self.upload_path(upload_id))
+    }
+
+    /// Read a completed upload of at most `limit` bytes as text, e.g. source
+    /// code for analysis
+    pub async fn read_text(&self, upload_id: Uuid, limit: u64) -> Result<String, UploadError> {
+        if self.get(upload_id)?.size_bytes > limit {
+            return Err(UploadError::TooLarge { limit });
+        }
+        let bytes = tokio::fs::read(self.path(upload_id)?).await?;
+        String::from_utf8(bytes).map_err(|_| UploadError::NotText(upload_id))
+    }
+
+    pub async fn delete(&self, upload_id: Uuid) -> Result<(), UploadError> {
+        let removed = self.uploads.write().unwrap().remove(&upload_id);
+        removed.ok_or(UploadError::NotFound(upload_id))?;
+        tokio::fs::remove_file(self.upload_path(upload_id)).await?;
+        Ok(())
+    }
+
+    /// Delete expired uploads; returns how many were removed
+    pub async fn remove_expired(&self) -> usize {
+        let now = Utc::now();
+        let expired: Vec<Uuid> = {
+            let mut uploads = self.uploads.write().unwrap();
+            let expired: Vec<Uuid> = uploads
+                .values()
+                .filter(|record| record.expires_at <= now)
+                .map(|record| record.upload_id)
+                .collect();
+            for upload_id in &expired {
+                uploads.remove(upload_id);
+            }
+            expired
+        };
+
+        for upload_id in &expired {
+            if let Err(e) = tokio::fs::remove_file(self.upload_path(*upload_id)).await {
+                tracing::warn!("Could not remove expired upload {}: {}", upload_id, e);
+            }
+        }
+        expired.len()
+    }
+
+    /// Periodically delete expired uploads
+    pub fn start_cleanup(self: &Arc<Self>, interval: Duration) {
+        let service = Arc::downgrade(self);
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                let Some(service) = service.upgrade() else { break };
+                let removed = service.remove_expired().await;
+                if removed > 0 {
+                    tracing::info!("Removed {} expired uploads", removed);
+                }
+            }
+        });
+    }
+
+    fn is_allowed(&self, content_type: &str) -> bool {
+        self.config.allowed_content_types.iter().any(|allowed| match allowed.strip_suffix("/*") {
+            Some(family) => content_type.split('/').next() == Some(family),
+            None => allowed == content_type,
+        })
+    }
+
+    fn partial_path(&self, upload_id: Uuid) -> PathBuf {
+        self.config.upload_dir.join(format!("{}.{}", upload_id, PARTIAL_EXTENSION))
+    }
+
+    fn upload_path(&self, upload_id: Uuid) -> PathBuf {
+        self.config.upload_dir.join(upload_id.to_string())
+    }
+}
+
+/// An upload being received; its partial file is removed unless it finishes
+pub struct PendingUpload {
+    upload_id: Uuid,
+    filename: Option<String>,
+    content_type: String,
+    /// Taken once the file has been moved to `final_path`
+    partial_path: Option<PathBuf>,
+    final_path: PathBuf,
+    file: tokio::fs::File,
+    written: u64,
+    limit: u64,
+    _permit: OwnedSemaphorePermit,
+}
+
+impl PendingUpload {
+    pub fn upload_id(&self) -> Uuid {
+        self.upload_id
+    }
+
+    pub fn bytes_written(&self) -> u64 {
+        self.written
+    }
+
+    /// Append a chunk, failing once the upload would exceed its size cap
+    pub async fn write_chunk(&mut self, chunk: &[u8]) -> Result<(), UploadError> {
+        if self.written + chunk.len() as u64 > self.limit {
+            return Err(UploadError::TooLarge { limit: self.limit });
+        }
+        self.file.write_all(chunk).await?;
+        self.written += chunk.len() as u64;
+        Ok(())
+    }
+
+    async fn finish(mut self, ttl: Duration) -> Result<UploadRecord, UploadError> {
+        self.file.flush().await?;
+        self.file.sync_all().await?;
+
+        let partial_path = self.partial_path.as_ref().expect("upload not finished yet");
+        tokio::fs::rename(partial_path, &self.final_path).await?;
+        self.partial_path = None;
+
+        let created_at = Utc::now();
+        Ok(UploadRecord {
+            upload_id: self.upload_id,
+            filename: self.filename.take(),
+            content_type: std::mem::take(&mut self.content_type),
+            size_bytes: self.written,
+            created_at,
+            expires_at: created_at + chrono::Duration::from_std(ttl).unwrap_or_else(|_| chrono::Duration::days(1)),
+        })
+    }
+}
+
+impl Drop for PendingUpload {
+    fn drop(&mut self) {
+        if let Some(partial_path) = self.partial_path.take() {
+            tracing::debug!("Discarding partial upload {}", self.upload_id);
+            // Unlinking is fine while the handle is still open
+            if let Err(e) = std::fs::remove_file(&partial_path) {
+                tracing::warn!("Could not remove partial upload {}: {}", partial_path.display(), e);
+            }
+        }
+    }
+}
+
+/// Lowercase and strip parameters such as `; charset=utf-8`
+fn normalize_content_type(content_type: &str) -> String {
+    content_type.split(';').next().unwrap_or_default().trim().to_ascii_lowercase()
+}
+
+/// Keep only the final path component of a client-supplied file name
+fn sanitize_filename(filename: &str) -> String {
+    Path::new(&filename.replace('\\', "/"))
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn service(max_upload_bytes: u64) -> UploadService {
+        UploadService::new(UploadConfig {
+            upload_dir: std::env::temp_dir().join(format!("ectus-uploads-test-{}", Uuid::new_v4())),
+            max_upload_bytes,
+            max_concurrent_uploads: 2,
+            ..Default::default()
+        })
+        .await
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_upload_is_stored_in_chunks() {
+        let service = service(1024).await;
+        let mut upload = service.begin(Some("../../src/main.rs".to_string()), "text/plain; charset=utf-8").await.unwrap();
+        upload.write_chunk(b"fn main() {").await.unwrap();
+        upload.write_chunk(b"}").await.unwrap();
+        let record = service.finish(upload).await.unwrap();
+
+        assert_eq!(record.size_bytes, 12);
+        assert_eq!(record.content_type, "text/plain");
+        assert_eq!(record.filename.as_deref(), Some("main.rs"));
+        assert_eq!(service.read_text(record.upload_id, 1024).await.unwrap(), "fn main() {}");
+    }
+
+    #[tokio::test]
+    async fn test_oversized_upload_is_rejected_and_removed() {
+        let service = service(8).await;
+        let mut upload = service.begin(None, "application/zip").await.unwrap();
+        let partial_path = service.partial_path(upload.upload_id());
+        upload.write_chunk(b"12345").await.unwrap();
+
+        assert!(matches!(upload.write_chunk(b"6789").await, Err(UploadError::TooLarge { limit: 8 })));
+        drop(upload);
+        assert!(!partial_path.exists(), "partial file should be removed");
+    }
+
+    #[tokio::test]
+    async fn test_content_type_and_concurrency_limits() {
+        let service = service(8).await;
+        assert!(matches!(
+            service.begin(None, "application/x-msdownload").await,
+            Err(UploadError::UnsupportedContentType(_))
+        ));
+
+        let _first = service.begin(None, "audio/mpeg").await.unwrap();
+        let _second = service.begin(None, "audio/wav").await.unwrap();
+        assert!(matches!(service.begin(None, "audio/ogg").await, Err(UploadError::Busy)));
+    }
+}
@@ -0,0 +1,916 @@
+//! Outbound webhooks
+//!
+//! Organizations register endpoints for the events they care about. Each event
+//! is POSTed as JSON with these headers:
+//!
+//! - `X-Ectus-Event`: the event type, e.g. `deployment.status_changed`
+//! - `X-Ectus-Delivery`: id of the delivery, identical on every retry so
+//!   consumers can drop duplicates
+//! - `X-Ectus-Signature`: `t=<unix seconds>,v1=<hex HMAC-SHA256>` computed with
+//!   the endpoint's secret over `<t>.<raw body>`
+//!
+//! Delivery is at least once: anything but a 2xx response is retried with
+//! exponential backoff until `max_attempts`, after which the delivery is kept
+//! as dead-lettered and can be redelivered by hand. Each endpoint has its own
+//! queue and worker, so a slow or failing consumer never delays another.
+//!
+//! The delivery history shown to users and written to logs has sensitive
+//! payload fields redacted; only the consumer receives the full payload.
+//! Response bodies are never read or stored, only status codes.
+//! Endpoints and deliveries are kept in memory for the life of the process.
+//!
+//! Endpoints must be https URLs whose host resolves only to public
+//! addresses. The check runs at registration and again on every connection,
+//! so a host cannot be re-pointed at loopback, link-local or private
+//! addresses (e.g. cloud metadata services) after it was registered.
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::Sha256;
+use std::collections::{HashMap, VecDeque};
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Arc, Mutex, RwLock, Weak};
+use std::time::{Duration, Instant};
+use tokio::sync::{mpsc, Notify};
+use uuid::Uuid;
+
+/// Events that can be subscribed to
+pub const EVENT_TYPES: [&str; 4] = [
+    "generation.completed",
+    "deployment.created",
+    "deployment.status_changed",
+    "qa.completed",
+];
+
+/// Payload keys whose values never appear in delivery history or logs
+const SENSITIVE_KEYS: [&str; 8] = [
+    "password",
+    "secret",
+    "token",
+    "api_key",
+    "apikey",
+    "authorization",
+    "credential",
+    "private_key",
+];
+
+const REDACTED: &str = "[REDACTED]";
+
+#[derive(Debug, Clone)]
+pub struct WebhookConfig {
+    pub max_attempts: u32,
+    /// Wait before the first retry; doubled for each further one
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+    pub request_timeout: Duration,
+    /// Deliveries kept per endpoint; the oldest finished ones are dropped first
+    pub history_limit: usize,
+    /// Deliveries waiting per endpoint; events beyond it are dead-lettered
+    pub queue_capacity: usize,
+    /// Accept http URLs and non-public addresses; only for tests against local consumers
+    pub allow_insecure_targets: bool,
+}
+
+impl Default for WebhookConfig {
+    fn default() -> Self {
+        Self {
+            max_attempts: 8,
+            initial_backoff: Duration::from_secs(10),
+            max_backoff: Duration::from_secs(60 * 60),
+            request_timeout: Duration::from_secs(10),
+            history_limit: 200,
+            queue_capacity: 1000,
+            allow_insecure_targets: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebhookEndpoint {
+    pub id: Uuid,
+    pub org_id: String,
+    pub url: String,
+    /// Event types delivered; `deployment.*` matches a whole family and `*` everything
+    pub events: Vec<String>,
+    pub description: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct CreateWebhookRequest {
+    pub url: String,
+    pub events: Vec<String>,
+    /// Signing secret; generated when omitted
+    pub secret: Option<String>,
+    pub description: Option<String>,
+}
+
+/// A newly registered endpoint; the only time its secret is returned
+#[derive(Debug, Clone, Serialize)]
+pub struct CreatedWebhook {
+    #[serde(flatten)]
+    pub endpoint: WebhookEndpoint,
+    pub secret: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeliveryStatus {
+    Pending,
+    /// Failed at least once; another attempt is scheduled
+    Retrying,
+    Delivered,
+    /// Gave up after `max_attempts`
+    DeadLettered,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeliveryAttempt {
+    pub attempt: u32,
+    pub attempted_at: DateTime<Utc>,
+    pub duration_ms: u64,
+    pub status_code: Option<u16>,
+    /// Network error or timeout, when no response arrived
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebhookDelivery {
+    /// Sent as `X-Ectus-Delivery`
+    pub id: Uuid,
+    pub endpoint_id: Uuid,
+    pub event_id: Uuid,
+    pub event_type: String,
+    pub status: DeliveryStatus,
+    pub created_at: DateTime<Utc>,
+    pub next_attempt_at: Option<DateTime<Utc>>,
+    pub attempts: Vec<DeliveryAttempt>,
+    /// The event as sent, with sensitive fields redacted
+    pub payload: Value,
+    /// Exact body sent, needed for retries and signatures
+    #[serde(skip)]
+    body: Arc<String>,
+}
+
+/// Registered endpoint with its queue and delivery history
+struct EndpointState {
+    endpoint: WebhookEndpoint,
+    secret: String,
+    queue: mpsc::Sender<Uuid>,
+    deliveries: Mutex<VecDeque<WebhookDelivery>>,
+    /// Stops the endpoint's worker when the endpoint is removed
+    shutdown: Arc<Notify>,
+}
+
+pub struct WebhookService {
+    config: WebhookConfig,
+    client: reqwest::Client,
+    endpoints: RwLock<HashMap<Uuid, Arc<EndpointState>>>,
+}
+
+impl WebhookService {
+    pub async fn new() -> Result<Self> {
+        Self::with_config(WebhookConfig::default())
+    }
+
+    pub fn with_config(config: WebhookConfig) -> Result<Self> {
+        println!("🪝 Initializing Webhook Service...");
+        let mut client = reqwest::Client::builder()
+            .timeout(config.request_timeout)
+            // A redirect would re-send the signed body to a URL nobody registered
+            .redirect(reqwest::redirect::Policy::none());
+        if !config.allow_insecure_targets {
+            client = client.dns_resolver(Arc::new(PublicResolver));
+        }
+        let client = client.build()?;
+        Ok(Self {
+            config,
+            client,
+            endpoints: RwLock::new(HashMap::new()),
+        })
+    }
+
+    /// Register an endpoint and start its delivery worker
+    pub async fn register(&self, org_id: &str, request: CreateWebhookRequest) -> Result<CreatedWebhook> {
+        let url = reqwest::Url::parse(&request.url).map_err(|e| anyhow!("Invalid webhook URL: {}", e))?;
+        self.check_target(&url).await?;
+        if request.events.is_empty() {
+            return Err(anyhow!("Subscribe to at least one event; use \"*\" for all"));
+        }
+        for pattern in &request.events {
+            if !EVENT_TYPES.iter().any(|event| event_matches(pattern, event)) {
+                return Err(anyhow!(
+                    "Unknown event '{}'; available events are: {}",
+                    pattern,
+                    EVENT_TYPES.join(", ")
+                ));
+            }
+        }
+        let secret = match request.secret {
+            Some(secret) if secret.len() < 16 => return Err(anyhow!("Webhook secret must be at least 16 characters")),
+            Some(secret) => secret,
+            None => format!("whsec_{}{}", Uuid::new_v4().simple(), Uuid::new_v4().simple()),
+        };
+
+        let endpoint = WebhookEndpoint {
+            id: Uuid::new_v4(),
+            org_id: org_id.to_string(),
+            url: url.to_string(),
+            events: request.events,
+            description: request.description,
+            created_at: Utc::now(),
+        };
+        let (queue, jobs) = mpsc::channel(self.config.queue_capacity.max(1));
+        let state = Arc::new(EndpointState {
+            endpoint: endpoint.clone(),
+            secret: secret.clone(),
+            queue,
+            deliveries: Mutex::new(VecDeque::new()),
+            shutdown: Arc::new(Notify::new()),
+        });
+        tokio::spawn(run_worker(
+            Arc::downgrade(&state),
+            jobs,
+            state.shutdown.clone(),
+            self.client.clone(),
+            self.config.clone(),
+        ));
+        self.endpoints.write().unwrap().insert(endpoint.id, state);
+
+        println!("🪝 Registered webhook {} for {}", endpoint.id, endpoint.url);
+        Ok(CreatedWebhook { endpoint, secret })
+    }
+
+    /// Reject URLs that are not https or that reach a non-public address
+    async fn check_target(&self, url: &reqwest::Url) -> Result<()> {
+        let insecure = self.config.allow_insecure_targets;
+        if !(url.scheme() == "https" || insecure && url.scheme() == "http") {
+            return Err(anyhow!("Webhook URL must use https"));
+        }
+        let host = url.host_str().ok_or_else(|| anyhow!("Webhook URL must have a host"))?;
+        if !insecure {
+            // IPv6 literals keep their brackets in the URL
+            resolve_public(host.trim_start_matches('[').trim_end_matches(']')).await?;
+        }
+        Ok(())
+    }
+
+    pub fn list_endpoints(&self, org_id: &str) -> Vec<WebhookEndpoint> {
+        let mut endpoints: Vec<WebhookEndpoint> = self
+            .endpoints
+            .read()
+            .unwrap()
+            .values()
+            .filter(|state| state.endpoint.org_id == org_id)
+            .map(|state| state.endpoint.clone())
+            .collect();
+        endpoints.sort_by_key(|endpoint| endpoint.created_at);
+        endpoints
+    }
+
+    pub fn get_endpoint(&self, org_id: &str, endpoint_id: Uuid) -> Option<WebhookEndpoint> {
+        self.endpoint_state(org_id, endpoint_id).map(|state| state.endpoint.clone())
+    }
+
+    /// Remove an endpoint and stop its worker; retries already scheduled
+    /// for it are dropped
+    pub fn remove_endpoint(&self, org_id: &str, endpoint_id: Uuid) -> bool {
+        let mut endpoints = self.endpoints.write().unwrap();
+        match endpoints.get(&endpoint_id) {
+            Some(state) if state.endpoint.org_id == org_id => {
+                state.shutdown.notify_one();
+                endpoints.remove(&endpoint_id);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Queue `data` for every endpoint of the organization subscribed to
+    /// `event_type`; returns the number of deliveries created
+    pub fn emit(&self, org_id: &str, event_type: &str, data: Value) -> usize {
+        let event_id = Uuid::new_v4();
+        let created_at = Utc::now();
+        let envelope = serde_json::json!({
+            "id": event_id,
+            "type": event_type,
+            "created_at": created_at,
+            "data": data,
+        });
+        let body = Arc::new(envelope.to_string());
+        let payload = redact(&envelope);
+
+        let endpoints = self.endpoints.read().unwrap();
+        let mut queued = 0;
+        for state in endpoints.values() {
+            if state.endpoint.org_id != org_id || !state.endpoint.events.iter().any(|pattern| event_matches(pattern, event_type)) {
+                continue;
+            }
+            let delivery = WebhookDelivery {
+                id: Uuid::new_v4(),
+                endpoint_id: state.endpoint.id,
+                event_id,
+                event_type: event_type.to_string(),
+                status: DeliveryStatus::Pending,
+                created_at,
+                next_attempt_at: Some(created_at),
+                attempts: Vec::new(),
+                payload: payload.clone(),
+                body: body.clone(),
+            };
+            let delivery_id = delivery.id;
+            state.record(delivery, self.config.history_limit);
+            match state.queue.try_send(delivery_id) {
+                Ok(()) => queued += 1,
+                Err(mpsc::error::TrySendError::Full(_)) => {
+                    // Kept for a manual redelivery once the consumer catches up
+                    state.update(delivery_id, |delivery| {
+                        delivery.status = DeliveryStatus::DeadLettered;
+                        delivery.next_attempt_at = None;
+                    });
+                    tracing::warn!(delivery_id = %delivery_id, url = %state.endpoint.url, "Webhook queue full; delivery dead-lettered");
+                }
+                Err(mpsc::error::TrySendError::Closed(_)) => {}
+            }
+        }
+        if queued > 0 {
+            tracing::info!(event_type, event_id = %event_id, endpoints = queued, "Webhook event queued");
+        }
+        queued
+    }
+
+    /// Delivery history of an endpoint, newest first
+    pub fn deliveries(&self, org_id: &str, endpoint_id: Uuid) -> Option<Vec<WebhookDelivery>> {
+        let state = self.endpoint_state(org_id, endpoint_id)?;
+        let deliveries = state.deliveries.lock().unwrap();
+        Some(deliveries.iter().rev().cloned().collect())
+    }
+
+    pub fn delivery(&self, org_id: &str, endpoint_id: Uuid, delivery_id: Uuid) -> Option<WebhookDelivery> {
+        let state = self.endpoint_state(org_id, endpoint_id)?;
+        let deliveries = state.deliveries.lock().unwrap();
+        deliveries.iter().find(|delivery| delivery.id == delivery_id).cloned()
+    }
+
+    /// Send a dead-lettered delivery again, with a fresh set of attempts
+    pub fn redeliver(&self, org_id: &str, endpoint_id: Uuid, delivery_id: Uuid) -> Result<WebhookDelivery> {
+        let state = self
+            .endpoint_state(org_id, endpoint_id)
+            .ok_or_else(|| anyhow!("Webhook {} not found", endpoint_id))?;
+        let delivery = state.update(delivery_id, |delivery| {
+            if delivery.status != DeliveryStatus::DeadLettered {
+                return Err(anyhow!("Only dead-lettered deliveries can be redelivered; this one is {:?}", delivery.status));
+            }
+            delivery.status = DeliveryStatus::Pending;
+            delivery.next_attempt_at = Some(Utc::now());
+            Ok(delivery.clone())
+        })
+        .ok_or_else(|| anyhow!("Delivery {} not found", delivery_id))??;
+        if let Err(e) = state.queue.try_send(delivery_id) {
+            state.update(delivery_id, |delivery| {
+                delivery.status = DeliveryStatus::DeadLettered;
+                delivery.next_attempt_at = None;
+            });
+            return Err(match e {
+                mpsc::error::TrySendError::Full(_) => anyhow!("Webhook {} has too many pending deliveries; try again later", endpoint_id),
+                mpsc::error::TrySendError::Closed(_) => anyhow!("Webhook {} is shutting down", endpoint_id),
+            });
+        }
+        Ok(delivery)
+    }
+
+    fn endpoint_state(&self, org_id: &str, endpoint_id: Uuid) -> Option<Arc<EndpointState>> {
+        self.endpoints
+            .read()
+            .unwrap()
+            .get(&endpoint_id)
+            .filter(|state| state.endpoint.org_id == org_id)
+            .cloned()
+    }
+}
+
+impl EndpointState {
+    fn record(&self, delivery: WebhookDelivery, limit: usize) {
+        let mut deliveries = self.deliveries.lock().unwrap();
+        deliveries.push_back(delivery);
+        while deliveries.len() > limit.max(1) {
+            // Drop the oldest finished delivery; in-flight ones must stay
+            let Some(index) = deliveries
+                .iter()
+                .position(|d| matches!(d.status, DeliveryStatus::Delivered | DeliveryStatus::DeadLettered))
+            else {
+                break;
+            };
+            deliveries.remove(index);
+        }
+    }
+
+    fn update<T>(&self, delivery_id: Uuid, f: impl FnOnce(&mut WebhookDelivery) -> T) -> Option<T> {
+        let mut deliveries = self.deliveries.lock().unwrap();
+        deliveries.iter_mut().find(|delivery| delivery.id == delivery_id).map(f)
+    }
+}
+
+/// Deliver queued deliveries of one endpoint, one at a time
+///
+/// The worker only holds a weak reference to the endpoint, so it never keeps
+/// the endpoint (and with it the queue's sender) alive. It stops on
+/// `shutdown` or once the endpoint is gone.
+async fn run_worker(
+    state: Weak<EndpointState>,
+    mut jobs: mpsc::Receiver<Uuid>,
+    shutdown: Arc<Notify>,
+    client: reqwest::Client,
+    config: WebhookConfig,
+) {
+    loop {
+        let delivery_id = tokio::select! {
+            biased;
+            _ = shutdown.notified() => break,
+            job = jobs.recv() => match job {
+                Some(delivery_id) => delivery_id,
+                None => break,
+            },
+        };
+        let Some(state) = state.upgrade() else {
+            break;
+        };
+        let Some((body, event_type, attempt)) =
+            state.update(delivery_id, |d| (d.body.clone(), d.event_type.clone(), d.attempts.len() as u32 + 1))
+        else {
+            continue;
+        };
+
+        let result = send(&client, &state, delivery_id, &event_type, &body).await;
+        let delivered = result.status_code.is_some_and(|code| (200..300).contains(&code));
+        let retry_in = (!delivered && attempt < config.max_attempts).then(|| backoff(&config, attempt));
+
+        state.update(delivery_id, |delivery| {
+            delivery.attempts.push(DeliveryAttempt { attempt, ..result.clone() });
+            delivery.status = match (delivered, retry_in) {
+                (true, _) => DeliveryStatus::Delivered,
+                (false, Some(_)) => DeliveryStatus::Retrying,
+                (false, None) => DeliveryStatus::DeadLettered,
+            };
+            delivery.next_attempt_at = retry_in.map(|delay| Utc::now() + chrono::Duration::from_std(delay).unwrap_or_else(|_| chrono::Duration::zero()));
+        });
+
+        match retry_in {
+            _ if delivered => {
+                tracing::debug!(delivery_id = %delivery_id, attempt, "Webhook delivered");
+            }
+            Some(delay) => {
+                tracing::warn!(
+                    delivery_id = %delivery_id,
+                    url = %state.endpoint.url,
+                    attempt,
+                    status = ?result.status_code,
+                    error = ?result.error,
+                    "Webhook delivery failed; retrying in {:?}",
+                    delay
+                );
+                // Retry from a timer so later deliveries are not held up meanwhile
+                let state = Arc::downgrade(&state);
+                tokio::spawn(async move {
+                    tokio::time::sleep(delay).await;
+                    let Some(queue) = state.upgrade().map(|state| state.queue.clone()) else {
+                        return;
+                    };
+                    let _ = queue.send(delivery_id).await;
+                });
+            }
+            None => {
+                tracing::error!(
+                    delivery_id = %delivery_id,
+                    url = %state.endpoint.url,
+                    attempts = attempt,
+                    "Webhook delivery dead-lettered"
+                );
+            }
+        }
+    }
+}
+
+async fn send(
+    client: &reqwest::Client,
+    state: &EndpointState,
+    delivery_id: Uuid,
+    event_type: &str,
+    body: &str,
+) -> DeliveryAttempt {
+    let attempted_at = Utc::now();
+    let timestamp = attempted_at.timestamp();
+    let started = Instant::now();
+
+    let response = client
+        .post(&state.endpoint.url)
+        .header(reqwest::header::CONTENT_TYPE, "application/json")
+        .header("X-Ectus-Event", event_type)
+        .header("X-Ectus-Delivery", delivery_id.to_string())
+        .header("X-Ectus-Signature", signature_header(&state.secret, timestamp, body))
+        .body(body.to_string())
+        .send()
+        .await;
+
+    // The body is never read: consumers could echo back anything, and only
+    // the status decides the outcome
+    let (status_code, error) = match response {
+        Ok(response) => (Some(response.status().as_u16()), None),
+        Err(e) if e.is_timeout() => (None, Some("Timed out waiting for a response".to_string())),
+        Err(e) => (None, Some(e.to_string())),
+    };
+    DeliveryAttempt {
+        attempt: 0,
+        attempted_at,
+        duration_ms: started.elapsed().as_millis() as u64,
+        status_code,
+        error,
+    }
+}
+
+/// DNS resolver of the delivery client that only returns public addresses,
+/// so every connection is checked, not just the registration
+struct PublicResolver;
+
+impl reqwest::dns::Resolve for PublicResolver {
+    fn resolve(&self, name: reqwest::dns::Name) -> reqwest::dns::Resolving {
+        let host = name.as_str().to_string();
+        Box::pin(async move {
+            let addrs = resolve_public(&host).await.map_err(|e| -> Box<dyn std::error::Error + Send + Sync> { e.into() })?;
+            let addrs: reqwest::dns::Addrs = Box::new(addrs.into_iter());
+            Ok(addrs)
+        })
+    }
+}
+
+/// Resolve `host`, failing unless every address it resolves to is public
+async fn resolve_public(host: &str) -> Result<Vec<SocketAddr>> {
+    let addrs: Vec<SocketAddr> = tokio::net::lookup_host((host, 0))
+        .await
+        .map_err(|e| anyhow!("Cannot resolve webhook host {}: {}", host, e))?
+        .collect();
+    if addrs.is_empty() {
+        return Err(anyhow!("Webhook host {} has no addresses", host));
+    }
+    if let Some(addr) = addrs.iter().find(|addr| !is_public(addr.ip())) {
+        return Err(anyhow!("Webhook host {} resolves to non-public address {}", host, addr.ip()));
+    }
+    Ok(addrs)
+}
+
+/// Whether `ip` is routable on the internet, i.e. not loopback, link-local,
+/// private, shared, multicast or otherwise reserved
+fn is_public(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, ..] = ip.octets();
+            !(ip.is_loopback()
+                || ip.is_private()
+                || ip.is_link_local()
+                || ip.is_unspecified()
+                || ip.is_broadcast()
+                || ip.is_multicast()
+                || ip.is_documentation()
+                // Carrier-grade NAT, 100.64.0.0/10
+                || a == 100 && (64..128).contains(&b)
+                || a == 0
+                || a >= 240)
+        }
+        IpAddr::V6(ip) => {
+            if let Some(mapped) = ip.to_ipv4_mapped() {
+                return is_public(IpAddr::V4(mapped));
+            }
+            let first = ip.segments()[0];
+            !(ip.is_loopback()
+                || ip.is_unspecified()
+                || ip.is_multicast()
+                // Unique local, fc00::/7
+                || first & 0xfe00 == 0xfc00
+                // Link-local, fe80::/10
+                || first & 0xffc0 == 0xfe80
+                // NAT64, 64:ff9b::/96, reaches any IPv4 address through a translator
+                || ip.segments()[..6] == [0x64, 0xff9b, 0, 0, 0, 0]
+                // Documentation, 2001:db8::/32
+                || ip.segments()[..2] == [0x2001, 0xdb8])
+        }
+    }
+}
+
+/// `X-Ectus-Signature` value for `body` sent at `timestamp`
+pub fn signature_header(secret: &str, timestamp: i64, body: &str) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(body.as_bytes());
+    let digest: String = mac.finalize().into_bytes().iter().map(|byte| format!("{:02x}", byte)).collect();
+    format!("t={},v1={}", timestamp, digest)
+}
+
+fn backoff(config: &WebhookConfig, attempt: u32) -> Duration {
+    let factor = 2u32.saturating_pow(attempt.saturating_sub(1));
+    config.initial_backoff.saturating_mul(factor).min(config.max_backoff)
+}
+
+/// Whether a subscription pattern covers `event_type`
+fn event_matches(pattern: &str, event_type: &str) -> bool {
+    match pattern.strip_suffix('*') {
+        Some("") => true,
+        Some(prefix) if prefix.ends_with('.') => event_type.starts_with(prefix),
+        _ => pattern == event_type,
+    }
+}
+
+/// Copy of `value` with the values of sensitive keys replaced
+pub fn redact(value: &Value) -> Value {
+    match value {
+        Value::Object(map) => Value::Object(
+            map.iter()
+                .map(|(key, value)| {
+                    let key_lower = key.to_lowercase();
+                    if SENSITIVE_KEYS.iter().any(|sensitive| key_lower.contains(sensitive)) {
+                        (key.clone(), Value::String(REDACTED.to_string()))
+                    } else {
+                        (key.clone(), redact(value))
+                    }
+                })
+                .collect(),
+        ),
+        Value::Array(items) => Value::Array(items.iter().map(redact).collect()),
+        other => other.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    /// A consumer answering with `statuses` in turn (the last one repeats),
+    /// recording each request's headers
+    async fn consumer(statuses: Vec<u16>) -> (String, Arc<Mutex<Vec<String>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let seen = requests.clone();
+        tokio::spawn(async move {
+            for i in 0.. {
+                let Ok((mut socket, _)) = listener.accept().await else { break };
+                let mut buf = vec![0u8; 64 * 1024];
+                let mut read = 0;
+                // Requests are small; read until the body announced by Content-Length arrived
+                loop {
+                    let n = socket.read(&mut buf[read..]).await.unwrap();
+                    read += n;
+                    let text = String::from_utf8_lossy(&buf[..read]).to_string();
+                    if let Some(head_end) = text.find("\r\n\r\n") {
+                        let length = text
+                            .lines()
+                            .find_map(|line| line.to_lowercase().strip_prefix("content-length: ").map(|v| v.trim().parse::<usize>().unwrap()))
+                            .unwrap_or(0);
+                        if read >= head_end + 4 + length || n == 0 {
+                            seen.lock().unwrap().push(text);
+                            break;
+                        }
+                    }
+                    if n == 0 {
+                        break;
+                    }
+                }
+                let status = statuses[i.min(statuses.len() - 1)];
+                let response = format!("HTTP/1.1 {} X\r\ncontent-length: 2\r\nconnection: close\r\n\r\nok", status);
+                let _ = socket.write_all(response.as_bytes()).await;
+            }
+        });
+        (url, requests)
+    }
+
+    fn service(max_attempts: u32) -> WebhookService {
+        WebhookService::with_config(WebhookConfig {
+            max_attempts,
+            initial_backoff: Duration::from_millis(10),
+            max_backoff: Duration::from_millis(40),
+            request_timeout: Duration::from_secs(2),
+            history_limit: 10,
+            queue_capacity: 16,
+            // The test consumers listen on plain http on loopback
+            allow_insecure_targets: true,
+        })
+        .unwrap()
+    }
+
+    fn request(url: &str, events: &[&str]) -> CreateWebhookRequest {
+        CreateWebhookRequest {
+            url: url.to_string(),
+            events: events.iter().map(|e| e.to_string()).collect(),
+            secret: Some("a-test-secret-of-32-characters!!".to_string()),
+            description: None,
+        }
+    }
+
+    async fn wait_for(service: &WebhookService, endpoint: Uuid, status: DeliveryStatus) -> WebhookDelivery {
+        for _ in 0..200 {
+            if let Some(delivery) = service.deliveries("org", endpoint).unwrap().into_iter().find(|d| d.status == status) {
+                return delivery;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        panic!("no delivery reached {:?}", status);
+    }
+
+    #[tokio::test]
+    async fn test_retries_until_delivered_with_stable_id_and_signature() {
+        let (url, requests) = consumer(vec![500, 503, 200]).await;
+        let service = service(5);
+        let endpoint = service.register("org", request(&url, &["deployment.*"])).await.unwrap().endpoint;
+
+        assert_eq!(service.emit("org", "deployment.created", serde_json::json!({"name": "api", "api_token": "t0p"})), 1);
+        // Not subscribed, and other organizations' events are never delivered
+        assert_eq!(service.emit("org", "qa.completed", serde_json::json!({})), 0);
+        assert_eq!(service.emit("other-org", "deployment.created", serde_json::json!({})), 0);
+
+        let delivery = wait_for(&service, endpoint.id, DeliveryStatus::Delivered).await;
+        assert_eq!(delivery.attempts.len(), 3);
+        assert_eq!(delivery.attempts[0].status_code, Some(500));
+        // History never shows sensitive values
+        assert_eq!(delivery.payload["data"]["api_token"], REDACTED);
+
+        let requests = requests.lock().unwrap();
+        assert_eq!(requests.len(), 3);
+        for raw in requests.iter() {
+            let raw_lower = raw.to_lowercase();
+            assert!(raw_lower.contains(&format!("x-ectus-delivery: {}", delivery.id)));
+            // The consumer gets the full payload, signed
+            let body = &raw[raw.find("\r\n\r\n").unwrap() + 4..];
+            assert!(body.contains("t0p"));
+            let header = raw_lower.lines().find_map(|line| line.strip_prefix("x-ectus-signature: ")).unwrap();
+            let timestamp: i64 = header[2..header.find(',').unwrap()].parse().unwrap();
+            assert_eq!(header, signature_header("a-test-secret-of-32-characters!!", timestamp, body));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_dead_letters_after_max_attempts_and_redelivers() {
+        let (url, requests) = consumer(vec![500, 500, 204]).await;
+        let service = service(2);
+        let endpoint = service.register("org", request(&url, &["*"])).await.unwrap().endpoint;
+
+        service.emit("org", "qa.completed", serde_json::json!({"score": 0.9}));
+        let dead = wait_for(&service, endpoint.id, DeliveryStatus::DeadLettered).await;
+        assert_eq!(dead.attempts.len(), 2);
+        assert!(dead.next_attempt_at.is_none());
+
+        service.redeliver("org", endpoint.id, dead.id).unwrap();
+        let delivered = wait_for(&service, endpoint.id, DeliveryStatus::Delivered).await;
+        assert_eq!(delivered.id, dead.id);
+        assert_eq!(delivered.attempts.last().unwrap().status_code, Some(204));
+        assert_eq!(requests.lock().unwrap().len(), 3);
+        assert!(service.redeliver("org", endpoint.id, dead.id).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_slow_endpoint_does_not_delay_others() {
+        // Accepts connections but never answers
+        let stalled = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let stalled_url = format!("http://{}/hook", stalled.local_addr().unwrap());
+        tokio::spawn(async move {
+            let mut sockets = Vec::new();
+            while let Ok((socket, _)) = stalled.accept().await {
+                sockets.push(socket);
+            }
+        });
+        let (url, _) = consumer(vec![200]).await;
+        let service = service(1);
+        service.register("org", request(&stalled_url, &["*"])).await.unwrap();
+        let fast = service.register("org", request(&url, &["*"])).await.unwrap().endpoint;
+
+        for _ in 0..3 {
+            service.emit("org", "generation.completed", serde_json::json!({}));
+        }
+        let started = Instant::now();
+        for _ in 0..200 {
+            let delivered = service.deliveries("org", fast.id).unwrap().iter().filter(|d| d.status == DeliveryStatus::Delivered).count();
+            if delivered == 3 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert!(started.elapsed() < Duration::from_secs(2), "fast endpoint waited on the stalled one");
+    }
+
+    #[tokio::test]
+    async fn test_registration_validation() {
+        let service = service(1);
+        assert!(service.register("org", request("ftp://example.com", &["*"])).await.is_err());
+        assert!(service.register("org", request("https://example.com", &[])).await.is_err());
+        assert!(service.register("org", request("https://example.com", &["deployment.deleted"])).await.is_err());
+        let mut short_secret = request("https://example.com", &["*"]);
+        short_secret.secret = Some("short".to_string());
+        assert!(service.register("org", short_secret).await.is_err());
+
+        assert!(event_matches("deployment.*", "deployment.created"));
+        assert!(!event_matches("deployment.*", "qa.completed"));
+        assert!(!event_matches("deploy*", "deployment.created"));
+    }
+
+    #[tokio::test]
+    async fn test_rejects_insecure_and_internal_targets() {
+        let service = WebhookService::with_config(WebhookConfig::default()).unwrap();
+        for url in [
+            "http://example.com/hook",
+            "https://127.0.0.1/hook",
+            "https://localhost/hook",
+            "https://10.1.2.3/hook",
+            "https://192.168.0.10/hook",
+            "https://169.254.169.254/latest/meta-data",
+            "https://[::1]/hook",
+            "https://[fd00::1]/hook",
+            "https://[::ffff:127.0.0.1]/hook",
+        ] {
+            let error = service.register("org", request(url, &["*"])).await.unwrap_err();
+            assert!(error.to_string().contains("https") || error.to_string().contains("non-public"), "{}: {}", url, error);
+        }
+        assert!(service.list_endpoints("org").is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_delivery_client_refuses_internal_addresses() {
+        // The same check the resolver runs on every connection
+        assert!(resolve_public("localhost").await.is_err());
+        assert!(resolve_public("127.0.0.1").await.is_err());
+
+        let service = WebhookService::with_config(WebhookConfig::default()).unwrap();
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://localhost:{}/hook", listener.local_addr().unwrap().port());
+        assert!(service.client.post(&url).send().await.is_err());
+        let connected = tokio::time::timeout(Duration::from_millis(100), listener.accept()).await;
+        assert!(connected.is_err(), "the client connected to loopback");
+    }
+
+    #[test]
+    fn test_public_addresses() {
+        for ip in ["93.184.216.34", "2606:2800:220:1:248:1893:25c8:1946"] {
+            assert!(is_public(ip.parse().unwrap()), "{}", ip);
+        }
+        for ip in ["0.0.0.0", "100.64.0.1", "172.16.5.4", "224.0.0.1", "fe80::1", "::", "64:ff9b::a9fe:a9fe", "2001:db8::1"] {
+            assert!(!is_public(ip.parse().unwrap()), "{}", ip);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_removing_an_endpoint_stops_its_worker() {
+        let service = service(3);
+        let endpoint = service.register("org", request("http://127.0.0.1:9/hook", &["*"])).await.unwrap().endpoint;
+        let queue = service.endpoints.read().unwrap()[&endpoint.id].queue.clone();
+        assert!(!queue.is_closed());
+
+        assert!(service.remove_endpoint("org", endpoint.id));
+        // The receiver is dropped once the worker returns
+        tokio::time::timeout(Duration::from_secs(1), queue.closed())
+            .await
+            .expect("worker kept running after its endpoint was removed");
+    }
+
+    #[tokio::test]
+    async fn test_full_queue_dead_letters_instead_of_growing() {
+        // Accepts connections but never answers, so the worker stays busy
+        let stalled = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/hook", stalled.local_addr().unwrap());
+        tokio::spawn(async move {
+            let mut sockets = Vec::new();
+            while let Ok((socket, _)) = stalled.accept().await {
+                sockets.push(socket);
+            }
+        });
+        let service = service(1);
+        let endpoint = service.register("org", request(&url, &["*"])).await.unwrap().endpoint;
+
+        let queued: usize = (0..40).map(|_| service.emit("org", "qa.completed", serde_json::json!({}))).sum();
+        // One in flight at most, plus the queue capacity
+        assert!(queued <= 17, "queued {}", queued);
+        let dead = service
+            .deliveries("org", endpoint.id)
+            .unwrap()
+            .iter()
+            .filter(|d| d.status == DeliveryStatus::DeadLettered && d.attempts.is_empty())
+            .count();
+        assert!(dead > 0);
+    }
+
+    #[test]
+    fn test_redaction() {
+        let value = serde_json::json!({
+            "user": {"email": "a@b.c", "Password": "x", "tokens": ["1"]},
+            "items": [{"client_secret": "s", "ok": 1}]
+        });
+        let redacted = redact(&value);
+        assert_eq!(redacted["user"]["email"], "a@b.c");
+        assert_eq!(redacted["user"]["Password"], REDACTED);
+        assert_eq!(redacted["user"]["tokens"], REDACTED);
+        assert_eq!(redacted["items"][0]["client_secret"], REDACTED);
+        assert_eq!(redacted["items"][0]["ok"], 1);
+    }
+}
@@ -0,0 +1,209 @@
+//! Optimistic concurrency control for mutable resources
+//!
+//! Every mutable resource carries a version that starts at 1 and increases by
+//! one on each successful update. `GET` returns it as a strong `ETag` (`"3"`)
+//! and in the body's `version` field. `PUT` must send `If-Match` with the
+//! version it was based on:
+//!
+//! - no `If-Match` header: `428 Precondition Required`
+//! - a version other than the current one: `412 Precondition Failed`, with the
+//!   current version and resource in the body so the client can merge and retry
+//!
+//! Checking and incrementing happen under one lock in [`VersionStore`], so two
+//! concurrent updates from the same version can never both succeed. Tables in
+//! Postgres get the same guarantee from a single statement:
+//! `UPDATE ... SET version = version + 1 WHERE id = $1 AND version = $2`.
+
+use axum::{
+    async_trait,
+    extract::FromRequestParts,
+    http::{header, request::Parts, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::Serialize;
+use serde_json::json;
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+/// Version of a resource that was never updated
+pub const INITIAL_VERSION: u64 = 1;
+
+/// Strong entity tag for a version
+pub fn etag(version: u64) -> String {
+    format!("\"{}\"", version)
+}
+
+/// `body` as JSON with its version in the `ETag` header
+pub fn with_etag<T: Serialize>(version: u64, body: T) -> Response {
+    ([(header::ETAG, etag(version))], Json(body)).into_response()
+}
+
+/// Current versions of mutable resources, keyed by resource kind and id
+#[derive(Debug, Default)]
+pub struct VersionStore {
+    versions: Mutex<HashMap<String, u64>>,
+}
+
+impl VersionStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn current(&self, key: &str) -> u64 {
+        self.versions.lock().unwrap().get(key).copied().unwrap_or(INITIAL_VERSION)
+    }
+
+    /// Increment the version if `precondition` matches it, returning the new
+    /// version. If the update that follows fails, the version has still moved
+    /// on; clients then refetch, which is always safe.
+    pub fn bump_if_match(&self, key: &str, precondition: &IfMatch) -> Result<u64, VersionConflict> {
+        let mut versions = self.versions.lock().unwrap();
+        let version = versions.entry(key.to_string()).or_insert(INITIAL_VERSION);
+        if !precondition.matches(*version) {
+            return Err(VersionConflict { current_version: *version });
+        }
+        *version += 1;
+        Ok(*version)
+    }
+
+    pub fn remove(&self, key: &str) {
+        self.versions.lock().unwrap().remove(key);
+    }
+}
+
+/// The resource changed since the client read it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+#[error("Resource was modified; current version is {current_version}")]
+pub struct VersionConflict {
+    pub current_version: u64,
+}
+
+/// Parsed `If-Match` header, required on every update
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IfMatch {
+    /// `*`: any current version
+    Any,
+    Versions(Vec<u64>),
+}
+
+impl IfMatch {
+    pub fn parse(value: &str) -> Option<Self> {
+        let value = value.trim();
+        if value == "*" {
+            return Some(IfMatch::Any);
+        }
+        // Weak tags never match under If-Match's strong comparison, and we
+        // never hand them out, so treat them as malformed
+        value
+            .split(',')
+            .map(|tag| tag.trim().strip_prefix('"')?.strip_suffix('"')?.parse().ok())
+            .collect::<Option<Vec<u64>>>()
+            .filter(|versions| !versions.is_empty())
+            .map(IfMatch::Versions)
+    }
+
+    pub fn matches(&self, version: u64) -> bool {
+        match self {
+            IfMatch::Any => true,
+            IfMatch::Versions(versions) => versions.contains(&version),
+        }
+    }
+}
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for IfMatch {
+    type Rejection = (StatusCode, Json<serde_json::Value>);
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let Some(value) = parts.headers.get(header::IF_MATCH) else {
+            return Err(rejection(
+                StatusCode::PRECONDITION_REQUIRED,
+                "Send If-Match with the ETag from your last GET of this resource",
+            ));
+        };
+        value
+            .to_str()
+            .ok()
+            .and_then(IfMatch::parse)
+            .ok_or_else(|| rejection(StatusCode::BAD_REQUEST, "If-Match must be a quoted ETag such as \"3\", or *"))
+    }
+}
+
+fn rejection(status: StatusCode, message: &str) -> (StatusCode, Json<serde_json::Value>) {
+    (
+        status,
+        Json(json!({
+            "success": false,
+            "error": message
+        })),
+    )
+}
+
+/// `412 Precondition Failed` carrying the resource as it is now
+pub struct PreconditionFailed<T> {
+    pub current_version: u64,
+    pub current: T,
+}
+
+impl<T: Serialize> IntoResponse for PreconditionFailed<T> {
+    fn into_response(self) -> Response {
+        (
+            StatusCode::PRECONDITION_FAILED,
+            [(header::ETAG, etag(self.current_version))],
+            Json(json!({
+                "success": false,
+                "error": "The resource was modified since you fetched it; merge your changes into the current version and retry",
+                "current_version": self.current_version,
+                "current": self.current
+            })),
+        )
+            .into_response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_if_match_parsing() {
+        assert_eq!(IfMatch::parse("*"), Some(IfMatch::Any));
+        assert_eq!(IfMatch::parse("\"3\""), Some(IfMatch::Versions(vec![3])));
+        assert_eq!(IfMatch::parse("\"3\", \"4\""), Some(IfMatch::Versions(vec![3, 4])));
+        assert_eq!(IfMatch::parse("3"), None);
+        assert_eq!(IfMatch::parse("W/\"3\""), None);
+        assert_eq!(IfMatch::parse(""), None);
+    }
+
+    #[test]
+    fn test_only_one_update_per_version_succeeds() {
+        let store = VersionStore::new();
+        let based_on = IfMatch::Versions(vec![store.current("project:1")]);
+
+        assert_eq!(store.bump_if_match("project:1", &based_on), Ok(2));
+        // A second writer that read the same version is turned away
+        assert_eq!(
+            store.bump_if_match("project:1", &based_on),
+            Err(VersionConflict { current_version: 2 })
+        );
+        assert_eq!(store.bump_if_match("project:1", &IfMatch::Versions(vec![2])), Ok(3));
+        assert_eq!(store.current("project:2"), INITIAL_VERSION);
+    }
+
+    #[test]
+    fn test_concurrent_updates_do_not_both_win() {
+        let store = std::sync::Arc::new(VersionStore::new());
+        let winners: usize = (0..8)
+            .map(|_| {
+                let store = store.clone();
+                std::thread::spawn(move || store.bump_if_match("config", &IfMatch::Versions(vec![INITIAL_VERSION])).is_ok())
+            })
+            .collect::<Vec<_>>()
+            .into_iter()
+            .map(|handle| handle.join().unwrap() as usize)
+            .sum();
+        assert_eq!(winners, 1);
+        assert_eq!(store.current("config"), INITIAL_VERSION + 1);
+    }
+}
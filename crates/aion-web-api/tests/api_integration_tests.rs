@@ -48,11 +48,47 @@ async fn create_test_app_state() -> AppState {
             .expect("Failed to create auth service")
     );
 
+    let analytics_service = Arc::new(
+        services::AnalyticsService::new().await
+            .expect("Failed to create analytics service")
+    );
+
+    let experiment_service = Arc::new(
+        services::ExperimentService::new().await
+            .expect("Failed to create experiment service")
+    );
+
+    let upload_service = Arc::new(
+        services::UploadService::new(services::uploads::UploadConfig {
+            upload_dir: std::env::temp_dir().join(format!("ectus-uploads-{}", Uuid::new_v4())),
+            ..Default::default()
+        }).await
+            .expect("Failed to create upload service")
+    );
+
+    let webhook_service = Arc::new(
+        services::WebhookService::new().await
+            .expect("Failed to create webhook service")
+    );
+
+    let health = create_health_registry(
+        &monitoring_service,
+        &ai_service,
+        &deployment_service,
+        &analytics_service,
+    ).await;
+
     AppState {
         monitoring_service,
         ai_service,
         deployment_service,
         auth_service,
+        analytics_service,
+        experiment_service,
+        health,
+        upload_service,
+        webhook_service,
+        versions: Arc::new(versioning::VersionStore::new()),
         config,
     }
 }
@@ -0,0 +1,5 @@
+//! HTTP API of the documentation portal
+
+pub mod versioning;
+
+pub use versioning::*;
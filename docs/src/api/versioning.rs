@@ -0,0 +1,475 @@
+//! Version-aware documentation routing
+//!
+//! `/v2/guide/intro` is split into a version selector and a page path. The
+//! selector may be a version or an alias from `alias_mapping`. Pages missing
+//! from the requested version fall back to the default version with a banner,
+//! unless the page was removed in the requested version: such pages are only
+//! served under the versions that still contain them.
+
+use crate::{DocumentationVersion, Result, VersionStatus, VersioningConfiguration};
+use axum::extract::{Path, State};
+use axum::http::{header, HeaderMap, HeaderValue, StatusCode};
+use axum::response::{Html, IntoResponse, Response};
+use axum::routing::get;
+use axum::Router;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+#[async_trait::async_trait]
+pub trait VersionedContentStore: Send + Sync {
+    /// Rendered content of `page` in `version`, or `None` when the version has no such page
+    async fn load(&self, version: &str, page: &str) -> Result<Option<String>>;
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum VersionBanner {
+    /// The requested version has no such page; the default version's page is shown
+    Fallback { requested: String, served: String },
+    Deprecated { version: String, deprecation_date: Option<DateTime<Utc>>, current: String },
+    Archived { version: String, current: String },
+}
+
+impl VersionBanner {
+    pub fn message(&self) -> String {
+        match self {
+            VersionBanner::Fallback { requested, served } => {
+                format!("This page does not exist in {}. You are viewing the {} version.", requested, served)
+            }
+            VersionBanner::Deprecated { version, deprecation_date, current } => match deprecation_date {
+                Some(date) => format!(
+                    "Version {} is deprecated since {}. Please upgrade to {}.",
+                    version,
+                    date.format("%Y-%m-%d"),
+                    current
+                ),
+                None => format!("Version {} is deprecated. Please upgrade to {}.", version, current),
+            },
+            VersionBanner::Archived { version, current } => {
+                format!("Version {} is archived and no longer maintained. The current version is {}.", version, current)
+            }
+        }
+    }
+
+    pub fn to_html(&self) -> String {
+        let class = match self {
+            VersionBanner::Fallback { .. } => "version-banner version-banner--fallback",
+            VersionBanner::Deprecated { .. } | VersionBanner::Archived { .. } => "version-banner version-banner--warning",
+        };
+        format!("<div class=\"{}\" role=\"note\">{}</div>", class, escape_html(&self.message()))
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VersionedPage {
+    pub page: String,
+    /// Version selector from the URL, before alias resolution
+    pub requested_version: String,
+    pub served_version: String,
+    pub content: String,
+    pub banners: Vec<VersionBanner>,
+    pub canonical_url: String,
+    /// Versions that contain this page, for the version switcher
+    pub available_versions: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum VersionResolution {
+    Found(VersionedPage),
+    /// The page existed in earlier versions but was removed in the requested one
+    Removed { page: String, requested_version: String, available_in: Vec<String> },
+    NotFound { page: String },
+    UnknownVersion(String),
+}
+
+pub struct VersionRouter {
+    config: VersioningConfiguration,
+    base_url: String,
+    /// Configured versions, oldest first
+    ordered_versions: Vec<String>,
+    /// Versions containing each page, oldest first, so a request does not
+    /// probe every version of the store
+    availability: RwLock<HashMap<String, Vec<String>>>,
+}
+
+impl VersionRouter {
+    pub fn new(config: VersioningConfiguration, base_url: &str) -> Self {
+        let mut ordered_versions: Vec<String> = config.versions.iter().map(|v| v.version.clone()).collect();
+        let mut router = Self {
+            config,
+            base_url: base_url.trim_end_matches('/').to_string(),
+            ordered_versions: Vec::new(),
+            availability: RwLock::new(HashMap::new()),
+        };
+        router.sort_versions(&mut ordered_versions);
+        router.ordered_versions = ordered_versions;
+        router
+    }
+
+    /// Forget which versions contain which pages, after the store's content changed
+    pub async fn clear_cache(&self) {
+        self.availability.write().await.clear();
+    }
+
+    /// Versions of the store containing `page`, oldest first. A router is
+    /// meant to serve a single store, whose answers it caches.
+    async fn available_versions(&self, page: &str, store: &dyn VersionedContentStore) -> Result<Vec<String>> {
+        if let Some(versions) = self.availability.read().await.get(page) {
+            return Ok(versions.clone());
+        }
+
+        let mut versions = Vec::new();
+        for version in &self.ordered_versions {
+            if store.load(version, page).await?.is_some() {
+                versions.push(version.clone());
+            }
+        }
+        self.availability.write().await.insert(page.to_string(), versions.clone());
+        Ok(versions)
+    }
+
+    /// Resolve a version selector or alias to a configured version
+    pub fn resolve_version(&self, selector: &str) -> Option<&DocumentationVersion> {
+        let target = self.config.alias_mapping.get(selector).map(String::as_str).unwrap_or(selector);
+        self.config.versions.iter().find(|v| v.version == target)
+    }
+
+    /// The version marked `Current`, or the default version
+    pub fn current_version(&self) -> &str {
+        self.config
+            .versions
+            .iter()
+            .find(|v| matches!(v.status, VersionStatus::Current))
+            .map(|v| v.version.as_str())
+            .unwrap_or(&self.config.default_version)
+    }
+
+    /// Split `/v2/guide/intro` into `("v2", "guide/intro")`; paths without a
+    /// version selector address the default version
+    pub fn split_path<'a>(&'a self, path: &'a str) -> (&'a str, &'a str) {
+        let trimmed = path.trim_start_matches('/');
+        let (first, rest) = trimmed.split_once('/').unwrap_or((trimmed, ""));
+        if self.config.enabled && (self.resolve_version(first).is_some() || self.looks_like_version(first)) {
+            (first, rest.trim_end_matches('/'))
+        } else {
+            (self.config.default_version.as_str(), trimmed.trim_end_matches('/'))
+        }
+    }
+
+    fn looks_like_version(&self, segment: &str) -> bool {
+        let digits = segment.strip_prefix('v').unwrap_or(segment);
+        !digits.is_empty() && digits.chars().next().map(|c| c.is_ascii_digit()).unwrap_or(false)
+            && digits.chars().all(|c| c.is_ascii_digit() || c == '.')
+    }
+
+    pub async fn resolve(&self, path: &str, store: &dyn VersionedContentStore) -> Result<VersionResolution> {
+        let (selector, page) = self.split_path(path);
+        let Some(requested) = self.resolve_version(selector) else {
+            return Ok(VersionResolution::UnknownVersion(selector.to_string()));
+        };
+
+        let available_versions = self.available_versions(page, store).await?;
+
+        // The requested version first, then the default version as fallback
+        let mut banners = Vec::new();
+        let served = if available_versions.contains(&requested.version) {
+            store.load(&requested.version, page).await?.map(|content| (requested, content))
+        } else if !self.config.enabled || requested.version == self.config.default_version {
+            None
+        } else {
+            // A page present in an older version but missing here was removed; don't resurrect it
+            let removed = available_versions
+                .iter()
+                .any(|v| self.compare_versions(v, &requested.version) == Ordering::Less);
+            let default = self.resolve_version(&self.config.default_version);
+            match default {
+                Some(default) if !removed && available_versions.contains(&default.version) => {
+                    store.load(&default.version, page).await?.map(|content| {
+                        banners.push(VersionBanner::Fallback {
+                            requested: requested.version.clone(),
+                            served: default.version.clone(),
+                        });
+                        (default, content)
+                    })
+                }
+                _ => None,
+            }
+        };
+
+        let Some((served, content)) = served else {
+            return Ok(if available_versions.is_empty() {
+                VersionResolution::NotFound { page: page.to_string() }
+            } else {
+                VersionResolution::Removed {
+                    page: page.to_string(),
+                    requested_version: selector.to_string(),
+                    available_in: available_versions,
+                }
+            });
+        };
+
+        let current = self.current_version().to_string();
+        match served.status {
+            VersionStatus::Deprecated => banners.push(VersionBanner::Deprecated {
+                version: served.version.clone(),
+                deprecation_date: served.deprecation_date,
+                current: current.clone(),
+            }),
+            VersionStatus::Archived => banners.push(VersionBanner::Archived {
+                version: served.version.clone(),
+                current: current.clone(),
+            }),
+            VersionStatus::Current | VersionStatus::Supported => {}
+        }
+
+        // Canonical is the current version when it has the page, otherwise the newest version that does
+        let canonical_version = if available_versions.contains(&current) {
+            current
+        } else {
+            available_versions.last().cloned().unwrap_or_else(|| served.version.clone())
+        };
+
+        Ok(VersionResolution::Found(VersionedPage {
+            page: page.to_string(),
+            requested_version: selector.to_string(),
+            served_version: served.version.clone(),
+            content,
+            banners,
+            canonical_url: self.page_url(&canonical_version, page),
+            available_versions,
+        }))
+    }
+
+    pub fn page_url(&self, version: &str, page: &str) -> String {
+        if page.is_empty() {
+            format!("{}/{}/", self.base_url, version)
+        } else {
+            format!("{}/{}/{}", self.base_url, version, page)
+        }
+    }
+
+    /// Oldest first
+    fn sort_versions(&self, versions: &mut [String]) {
+        versions.sort_by(|a, b| self.compare_versions(a, b));
+    }
+
+    /// Numeric comparison of `v1.2.3`-style versions, then release date, then configuration order
+    fn compare_versions(&self, a: &str, b: &str) -> Ordering {
+        fn numeric(version: &str) -> Option<Vec<u64>> {
+            version.trim_start_matches('v').split('.').map(|part| part.parse().ok()).collect()
+        }
+        if let (Some(a), Some(b)) = (numeric(a), numeric(b)) {
+            return a.cmp(&b);
+        }
+
+        let find = |version: &str| self.config.versions.iter().position(|v| v.version == version);
+        let (ia, ib) = (find(a), find(b));
+        let release = |index: Option<usize>| index.and_then(|i| self.config.versions[i].release_date);
+        match (release(ia), release(ib)) {
+            (Some(ra), Some(rb)) => ra.cmp(&rb),
+            // Configuration lists newest versions first
+            _ => ib.cmp(&ia),
+        }
+    }
+}
+
+#[derive(Clone)]
+struct VersionedDocsState {
+    router: Arc<VersionRouter>,
+    store: Arc<dyn VersionedContentStore>,
+}
+
+/// Catch-all routes serving versioned pages
+pub fn versioned_docs_router(router: Arc<VersionRouter>, store: Arc<dyn VersionedContentStore>) -> Router {
+    Router::new()
+        .route("/", get(serve_root))
+        .route("/*path", get(serve_page))
+        .with_state(VersionedDocsState { router, store })
+}
+
+async fn serve_root(state: State<VersionedDocsState>) -> Response {
+    serve_page(state, Path(String::new())).await
+}
+
+async fn serve_page(State(state): State<VersionedDocsState>, Path(path): Path<String>) -> Response {
+    match state.router.resolve(&path, state.store.as_ref()).await {
+        Ok(VersionResolution::Found(page)) => {
+            let mut headers = HeaderMap::new();
+            if let Ok(link) = HeaderValue::from_str(&format!("<{}>; rel=\"canonical\"", page.canonical_url)) {
+                headers.insert(header::LINK, link);
+            }
+            if let Ok(version) = HeaderValue::from_str(&page.served_version) {
+                headers.insert("X-Docs-Version", version);
+            }
+
+            let banners: String = page.banners.iter().map(VersionBanner::to_html).collect();
+            let html = format!(
+                "<link rel=\"canonical\" href=\"{}\">\n{}{}",
+                escape_html(&page.canonical_url),
+                banners,
+                page.content
+            );
+            (StatusCode::OK, headers, Html(html)).into_response()
+        }
+        Ok(VersionResolution::Removed { page, requested_version, available_in }) => {
+            let links: String = available_in
+                .iter()
+                .map(|v| format!("<li><a href=\"{}\">{}</a></li>", escape_html(&state.router.page_url(v, &page)), escape_html(v)))
+                .collect();
+            let html = format!(
+                "<p>This page is not part of {}. It is available in:</p><ul>{}</ul>",
+                escape_html(&requested_version),
+                links
+            );
+            (StatusCode::NOT_FOUND, Html(html)).into_response()
+        }
+        Ok(VersionResolution::NotFound { .. }) => (StatusCode::NOT_FOUND, Html("<p>Page not found</p>".to_string())).into_response(),
+        Ok(VersionResolution::UnknownVersion(version)) => (
+            StatusCode::NOT_FOUND,
+            Html(format!("<p>Unknown documentation version {}</p>", escape_html(&version))),
+        )
+            .into_response(),
+        Err(e) => {
+            tracing::error!("Failed to resolve docs page {}: {}", path, e);
+            (StatusCode::INTERNAL_SERVER_ERROR, Html("<p>Failed to load page</p>".to_string())).into_response()
+        }
+    }
+}
+
+fn escape_html(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering as AtomicOrdering};
+
+    struct MemoryStore {
+        pages: HashMap<(String, String), String>,
+        loads: AtomicUsize,
+    }
+
+    impl MemoryStore {
+        fn new(pages: &[(&str, &str)]) -> Self {
+            Self {
+                pages: pages
+                    .iter()
+                    .map(|(version, page)| ((version.to_string(), page.to_string()), format!("{} {}", page, version)))
+                    .collect(),
+                loads: AtomicUsize::new(0),
+            }
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl VersionedContentStore for MemoryStore {
+        async fn load(&self, version: &str, page: &str) -> Result<Option<String>> {
+            self.loads.fetch_add(1, AtomicOrdering::SeqCst);
+            Ok(self.pages.get(&(version.to_string(), page.to_string())).cloned())
+        }
+    }
+
+    fn version(version: &str, status: VersionStatus) -> DocumentationVersion {
+        DocumentationVersion {
+            version: version.to_string(),
+            label: version.to_string(),
+            status,
+            release_date: None,
+            deprecation_date: None,
+            changelog_url: None,
+        }
+    }
+
+    fn router() -> VersionRouter {
+        let config = VersioningConfiguration {
+            enabled: true,
+            strategy: crate::VersioningStrategy::Manual,
+            default_version: "v2.3".to_string(),
+            versions: vec![
+                version("v3.0", VersionStatus::Supported),
+                version("v2.3", VersionStatus::Current),
+                version("v1.0", VersionStatus::Deprecated),
+            ],
+            alias_mapping: HashMap::from([("latest".to_string(), "v2.3".to_string())]),
+        };
+        VersionRouter::new(config, "https://docs.example.com/")
+    }
+
+    fn found(resolution: VersionResolution) -> VersionedPage {
+        match resolution {
+            VersionResolution::Found(page) => page,
+            other => panic!("expected a page, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn missing_pages_fall_back_to_the_default_version() {
+        let store = MemoryStore::new(&[("v2.3", "guide/intro"), ("v3.0", "guide/new")]);
+        let router = router();
+
+        let page = found(router.resolve("/v1.0/guide/intro", &store).await.unwrap());
+        assert_eq!(page.served_version, "v2.3");
+        assert_eq!(page.content, "guide/intro v2.3");
+        assert!(matches!(page.banners[0], VersionBanner::Fallback { .. }));
+        // The served version is current, so no deprecation warning
+        assert_eq!(page.banners.len(), 1);
+
+        let page = found(router.resolve("/latest/guide/intro", &store).await.unwrap());
+        assert_eq!(page.requested_version, "latest");
+        assert!(page.banners.is_empty());
+        assert_eq!(page.canonical_url, "https://docs.example.com/v2.3/guide/intro");
+
+        // Pages outside the default version have no fallback; the newest version is canonical
+        let page = found(router.resolve("/v3.0/guide/new", &store).await.unwrap());
+        assert_eq!(page.canonical_url, "https://docs.example.com/v3.0/guide/new");
+        assert!(matches!(
+            router.resolve("/v1.0/guide/new", &store).await.unwrap(),
+            VersionResolution::Removed { .. }
+        ));
+    }
+
+    #[tokio::test]
+    async fn removed_pages_are_only_served_where_they_exist() {
+        let store = MemoryStore::new(&[("v1.0", "legacy"), ("v2.3", "legacy")]);
+        let router = router();
+
+        match router.resolve("/v3.0/legacy", &store).await.unwrap() {
+            VersionResolution::Removed { available_in, .. } => assert_eq!(available_in, ["v1.0", "v2.3"]),
+            other => panic!("expected a removed page, got {:?}", other),
+        }
+
+        let page = found(router.resolve("/v1.0/legacy", &store).await.unwrap());
+        assert_eq!(page.served_version, "v1.0");
+        assert!(matches!(page.banners[0], VersionBanner::Deprecated { .. }));
+
+        assert!(matches!(router.resolve("/v9/legacy", &store).await.unwrap(), VersionResolution::UnknownVersion(_)));
+        assert!(matches!(router.resolve("/v2.3/missing", &store).await.unwrap(), VersionResolution::NotFound { .. }));
+    }
+
+    #[tokio::test]
+    async fn page_availability_is_probed_once() {
+        let store = MemoryStore::new(&[("v2.3", "guide/intro")]);
+        let router = router();
+
+        found(router.resolve("/v2.3/guide/intro", &store).await.unwrap());
+        // One probe per version, then the page itself
+        assert_eq!(store.loads.load(AtomicOrdering::SeqCst), 4);
+
+        found(router.resolve("/v1.0/guide/intro", &store).await.unwrap());
+        found(router.resolve("/v2.3/guide/intro", &store).await.unwrap());
+        assert_eq!(store.loads.load(AtomicOrdering::SeqCst), 6);
+
+        router.clear_cache().await;
+        found(router.resolve("/v2.3/guide/intro", &store).await.unwrap());
+        assert_eq!(store.loads.load(AtomicOrdering::SeqCst), 10);
+    }
+}
@@ -0,0 +1,94 @@
+//! Client artifact generators
+//!
+//! Implementations of `ApiDocumentationGenerator` delegate
+//! `generate_postman_collection`, `generate_curl_examples` and `generate_sdk`
+//! to the functions below; all of them work for OpenAPI and GraphQL
+//! specifications alike.
+
+pub mod curl;
+pub mod postman;
+pub mod samples;
+pub mod typescript;
+
+pub use curl::*;
+pub use postman::*;
+pub use samples::*;
+pub use typescript::*;
+
+use crate::{ApiSpecification, Result};
+use std::collections::HashMap;
+
+pub fn generate_postman_collection(spec: &ApiSpecification) -> Result<String> {
+    PostmanCollectionGenerator::generate(spec)
+}
+
+pub fn generate_curl_examples(spec: &ApiSpecification) -> Result<HashMap<String, String>> {
+    Ok(CurlExampleGenerator::generate(spec))
+}
+
+/// SDK for `language` as a zip archive of its source files
+pub fn generate_sdk(spec: &ApiSpecification, language: &str) -> Result<Vec<u8>> {
+    match language.to_ascii_lowercase().as_str() {
+        "typescript" | "ts" => TypeScriptSdkGenerator::generate(spec).to_zip(),
+        other => Err(format!("Unsupported SDK language: {}", other).into()),
+    }
+}
+
+/// An OpenAPI spec exercising path, query and body examples and both
+/// bearer and query API key auth
+#[cfg(test)]
+fn petstore() -> ApiSpecification {
+    const PETSTORE: &str = r##"
+openapi: 3.0.3
+info: { title: Pets, version: "1.0" }
+servers:
+  - url: "https://{region}.example.com/v1"
+    variables:
+      region: { default: eu }
+components:
+  securitySchemes:
+    bearerAuth: { type: http, scheme: bearer }
+    apiKey: { type: apiKey, in: query, name: api_key }
+  schemas:
+    Pet:
+      type: object
+      required: [name]
+      properties:
+        name: { type: string }
+        tag: { type: string }
+security:
+  - bearerAuth: []
+paths:
+  /pets/{petId}:
+    get:
+      operationId: getPet
+      summary: Get a pet
+      tags: [pets]
+      parameters:
+        - { name: petId, in: path, required: true, schema: { type: integer }, example: 42 }
+        - { name: fields, in: query, schema: { type: string } }
+        - { name: q, in: query, required: true, schema: { type: string }, example: "it's here" }
+      responses: { "200": { description: ok } }
+  /pets:
+    post:
+      operationId: createPet
+      summary: Create a pet
+      tags: [pets]
+      security:
+        - apiKey: []
+      requestBody:
+        required: true
+        content:
+          application/json:
+            schema: { $ref: "#/components/schemas/Pet" }
+      responses: { "201": { description: created } }
+"##;
+    crate::parsers::OpenApiParser::parse(PETSTORE, std::path::PathBuf::new()).unwrap().specification
+}
+
+/// A GraphQL spec with one query taking a required argument
+#[cfg(test)]
+fn graphql_spec(authentication: Option<crate::AuthenticationScheme>) -> ApiSpecification {
+    let schema = crate::parsers::GraphQLParser::schema_from_sdl("type Query { pet(id: ID!): Pet }\ntype Pet { id: ID! name: String }").unwrap();
+    crate::parsers::GraphQLParser::to_specification("Pets", &schema, "https://api.example.com/graphql", authentication, std::path::PathBuf::new())
+}
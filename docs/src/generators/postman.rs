@@ -0,0 +1,252 @@
+//! Postman v2.1 collection generator
+
+use super::samples::{auth_placement, base_url, build_samples, AuthPlacement, PathSegment, RequestSample, RequestSampleBody};
+use crate::{ApiSpecification, Result};
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+
+pub const POSTMAN_SCHEMA_URL: &str = "https://schema.getpostman.com/json/collection/v2.1.0/collection.json";
+
+/// Collection variables every generated request refers to
+pub const BASE_URL_VARIABLE: &str = "baseUrl";
+pub const AUTH_TOKEN_VARIABLE: &str = "authToken";
+pub const USERNAME_VARIABLE: &str = "username";
+pub const PASSWORD_VARIABLE: &str = "password";
+
+pub struct PostmanCollectionGenerator;
+
+impl PostmanCollectionGenerator {
+    pub fn generate(spec: &ApiSpecification) -> Result<String> {
+        Ok(serde_json::to_string_pretty(&Self::collection(spec))?)
+    }
+
+    pub fn collection(spec: &ApiSpecification) -> Value {
+        let samples = build_samples(spec);
+
+        // Requests are grouped into one folder per tag, untagged ones stay at the top level
+        let mut folders: BTreeMap<String, Vec<Value>> = BTreeMap::new();
+        let mut items = Vec::new();
+        for sample in &samples {
+            let item = Self::request_item(sample);
+            match &sample.folder {
+                Some(folder) => folders.entry(folder.clone()).or_default().push(item),
+                None => items.push(item),
+            }
+        }
+        for (name, children) in folders {
+            items.push(json!({ "name": name, "item": children }));
+        }
+
+        let mut variables = vec![
+            json!({ "key": BASE_URL_VARIABLE, "value": base_url(spec), "type": "string" }),
+            json!({ "key": AUTH_TOKEN_VARIABLE, "value": "", "type": "string" }),
+        ];
+        if samples.iter().any(|s| s.auth.as_ref().map(auth_placement) == Some(AuthPlacement::Basic)) {
+            variables.push(json!({ "key": USERNAME_VARIABLE, "value": "", "type": "string" }));
+            variables.push(json!({ "key": PASSWORD_VARIABLE, "value": "", "type": "string" }));
+        }
+
+        let mut collection = json!({
+            "info": {
+                "_postman_id": spec.id.to_string(),
+                "name": spec.name,
+                "description": format!("Generated from {} version {}", spec.name, spec.version),
+                "schema": POSTMAN_SCHEMA_URL,
+            },
+            "item": items,
+            "variable": variables,
+        });
+        if let Some(auth) = spec.authentication.first().and_then(|scheme| Self::auth(&auth_placement(scheme))) {
+            collection["auth"] = auth;
+        }
+        collection
+    }
+
+    /// Postman environment holding the same variables, for teams that keep secrets out of collections
+    pub fn environment(spec: &ApiSpecification) -> Value {
+        json!({
+            "id": spec.id.to_string(),
+            "name": format!("{} environment", spec.name),
+            "values": [
+                { "key": BASE_URL_VARIABLE, "value": base_url(spec), "type": "default", "enabled": true },
+                { "key": AUTH_TOKEN_VARIABLE, "value": "", "type": "secret", "enabled": true },
+                { "key": USERNAME_VARIABLE, "value": "", "type": "default", "enabled": true },
+                { "key": PASSWORD_VARIABLE, "value": "", "type": "secret", "enabled": true },
+            ],
+            "_postman_variable_scope": "environment",
+        })
+    }
+
+    fn request_item(sample: &RequestSample) -> Value {
+        let path: Vec<String> = sample
+            .path
+            .iter()
+            .map(|segment| match segment {
+                PathSegment::Literal(literal) => literal.clone(),
+                PathSegment::Parameter { name, .. } => format!(":{}", name),
+            })
+            .collect();
+        let path_variables: Vec<Value> = sample
+            .path
+            .iter()
+            .filter_map(|segment| match segment {
+                PathSegment::Parameter { name, example } => Some(json!({ "key": name, "value": example })),
+                PathSegment::Literal(_) => None,
+            })
+            .collect();
+        let query: Vec<Value> = sample.query.iter().map(|(key, value)| json!({ "key": key, "value": value })).collect();
+
+        // Postman encodes the structured url itself; `raw` is only what the UI shows
+        let mut raw = format!("{{{{{}}}}}", BASE_URL_VARIABLE);
+        for segment in &path {
+            raw.push('/');
+            raw.push_str(segment);
+        }
+        if !sample.query.is_empty() {
+            let pairs: Vec<String> = sample.query.iter().map(|(k, v)| format!("{}={}", k, v)).collect();
+            raw.push('?');
+            raw.push_str(&pairs.join("&"));
+        }
+
+        let mut headers: Vec<Value> = sample
+            .headers
+            .iter()
+            .map(|(key, value)| json!({ "key": key, "value": value, "type": "text" }))
+            .collect();
+        let placement = sample.auth.as_ref().map(auth_placement);
+        let mut cookies: Vec<String> = sample.cookies.iter().map(|(k, v)| format!("{}={}", k, v)).collect();
+        if let Some(AuthPlacement::Cookie(name)) = &placement {
+            cookies.push(format!("{}={{{{{}}}}}", name, AUTH_TOKEN_VARIABLE));
+        }
+        if !cookies.is_empty() {
+            headers.push(json!({ "key": "Cookie", "value": cookies.join("; "), "type": "text" }));
+        }
+
+        let mut request = json!({
+            "method": sample.method,
+            "header": headers,
+            "url": {
+                "raw": raw,
+                "host": [format!("{{{{{}}}}}", BASE_URL_VARIABLE)],
+                "path": path,
+                "query": query,
+                "variable": path_variables,
+            },
+        });
+        if let Some(description) = &sample.description {
+            request["description"] = json!(description);
+        }
+        if let Some(body) = &sample.body {
+            request["body"] = Self::body(body);
+            if !matches!(body, RequestSampleBody::Json(_) | RequestSampleBody::GraphQL { .. }) {
+                if let Some(headers) = request["header"].as_array_mut() {
+                    headers.push(json!({ "key": "Content-Type", "value": body.content_type(), "type": "text" }));
+                }
+            }
+        }
+        if let Some(auth) = placement.as_ref().and_then(Self::auth) {
+            request["auth"] = auth;
+        }
+
+        json!({ "name": sample.name, "request": request })
+    }
+
+    fn body(body: &RequestSampleBody) -> Value {
+        match body {
+            RequestSampleBody::Json(_) => json!({
+                "mode": "raw",
+                "raw": body.to_wire(),
+                "options": { "raw": { "language": "json" } },
+            }),
+            RequestSampleBody::GraphQL { query, variables } => json!({
+                "mode": "graphql",
+                "graphql": {
+                    "query": query,
+                    "variables": serde_json::to_string_pretty(variables).unwrap_or_default(),
+                },
+            }),
+            RequestSampleBody::Raw { content, .. } => json!({ "mode": "raw", "raw": content }),
+        }
+    }
+
+    fn auth(placement: &AuthPlacement) -> Option<Value> {
+        let token = format!("{{{{{}}}}}", AUTH_TOKEN_VARIABLE);
+        Some(match placement {
+            AuthPlacement::Bearer => json!({
+                "type": "bearer",
+                "bearer": [{ "key": "token", "value": token, "type": "string" }],
+            }),
+            AuthPlacement::Basic => json!({
+                "type": "basic",
+                "basic": [
+                    { "key": "username", "value": format!("{{{{{}}}}}", USERNAME_VARIABLE), "type": "string" },
+                    { "key": "password", "value": format!("{{{{{}}}}}", PASSWORD_VARIABLE), "type": "string" },
+                ],
+            }),
+            AuthPlacement::Header(name) | AuthPlacement::Query(name) => json!({
+                "type": "apikey",
+                "apikey": [
+                    { "key": "key", "value": name, "type": "string" },
+                    { "key": "value", "value": token, "type": "string" },
+                    { "key": "in", "value": if matches!(placement, AuthPlacement::Query(_)) { "query" } else { "header" }, "type": "string" },
+                ],
+            }),
+            // Postman's apikey auth has no cookie placement; the request carries a Cookie header instead
+            AuthPlacement::Cookie(_) => return None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generators::{graphql_spec, petstore};
+
+    fn request<'a>(collection: &'a Value, folder: &str, name: &str) -> &'a Value {
+        let folder = collection["item"].as_array().unwrap().iter().find(|item| item["name"] == folder).unwrap();
+        let item = folder["item"].as_array().unwrap().iter().find(|item| item["name"] == name).unwrap();
+        &item["request"]
+    }
+
+    #[test]
+    fn openapi_requests_use_collection_variables() {
+        let collection = PostmanCollectionGenerator::collection(&petstore());
+        assert_eq!(collection["info"]["schema"], POSTMAN_SCHEMA_URL);
+        assert_eq!(
+            collection["variable"],
+            json!([
+                { "key": "baseUrl", "value": "https://eu.example.com/v1", "type": "string" },
+                { "key": "authToken", "value": "", "type": "string" },
+            ])
+        );
+
+        let get_pet = request(&collection, "pets", "Get a pet");
+        assert_eq!(get_pet["method"], "GET");
+        assert_eq!(get_pet["url"]["raw"], "{{baseUrl}}/pets/:petId?q=it's here");
+        assert_eq!(get_pet["url"]["path"], json!(["pets", ":petId"]));
+        assert_eq!(get_pet["url"]["variable"], json!([{ "key": "petId", "value": "42" }]));
+        assert_eq!(get_pet["auth"]["type"], "bearer");
+        assert_eq!(get_pet["auth"]["bearer"][0]["value"], "{{authToken}}");
+
+        let create_pet = request(&collection, "pets", "Create a pet");
+        assert_eq!(create_pet["auth"]["apikey"][0]["value"], "api_key");
+        assert_eq!(create_pet["auth"]["apikey"][2]["value"], "query");
+        assert_eq!(create_pet["body"]["mode"], "raw");
+        let body: Value = serde_json::from_str(create_pet["body"]["raw"].as_str().unwrap()).unwrap();
+        assert_eq!(body, json!({ "name": "string", "tag": "string" }));
+    }
+
+    #[test]
+    fn graphql_requests_use_graphql_bodies() {
+        let collection = PostmanCollectionGenerator::collection(&graphql_spec(None));
+        let pet = request(&collection, "Query", "query pet");
+
+        assert_eq!(pet["method"], "POST");
+        assert_eq!(pet["url"]["raw"], "{{baseUrl}}/graphql");
+        assert_eq!(pet["body"]["mode"], "graphql");
+        assert!(pet["body"]["graphql"]["query"].as_str().unwrap().starts_with("query Pet($id: ID!)"));
+        let variables: Value = serde_json::from_str(pet["body"]["graphql"]["variables"].as_str().unwrap()).unwrap();
+        assert_eq!(variables, json!({ "id": "1" }));
+        assert!(pet.get("auth").is_none());
+    }
+}
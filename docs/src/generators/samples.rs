@@ -0,0 +1,301 @@
+//! Request samples shared by the Postman and curl generators
+//!
+//! A `RequestSample` is one fully resolved example call for an endpoint: the
+//! path with example path parameters substituted, example query and header
+//! parameters, an example body and the authentication scheme it needs.
+
+use crate::{
+    ApiEndpoint, ApiSpecType, ApiSpecification, AuthType, AuthenticationScheme, ParameterLocation, SchemaDefinition,
+    SchemaReference, SchemaType,
+};
+use serde_json::Value;
+
+/// Nesting limit when synthesizing examples from schemas
+const MAX_EXAMPLE_DEPTH: usize = 4;
+
+#[derive(Debug, Clone)]
+pub struct RequestSample {
+    /// Unique key for the sample: operation id, or `METHOD path`
+    pub key: String,
+    pub name: String,
+    pub folder: Option<String>,
+    pub method: String,
+    /// Path segments; path parameters are kept as `(name, example)` pairs
+    pub path: Vec<PathSegment>,
+    pub query: Vec<(String, String)>,
+    pub headers: Vec<(String, String)>,
+    pub cookies: Vec<(String, String)>,
+    pub body: Option<RequestSampleBody>,
+    pub auth: Option<AuthenticationScheme>,
+    pub description: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub enum PathSegment {
+    Literal(String),
+    Parameter { name: String, example: String },
+}
+
+#[derive(Debug, Clone)]
+pub enum RequestSampleBody {
+    Json(Value),
+    GraphQL { query: String, variables: Value },
+    Raw { content_type: String, content: String },
+}
+
+impl RequestSampleBody {
+    pub fn content_type(&self) -> &str {
+        match self {
+            RequestSampleBody::Json(_) | RequestSampleBody::GraphQL { .. } => "application/json",
+            RequestSampleBody::Raw { content_type, .. } => content_type,
+        }
+    }
+
+    /// Body exactly as it goes on the wire
+    pub fn to_wire(&self) -> String {
+        match self {
+            RequestSampleBody::Json(value) => serde_json::to_string_pretty(value).unwrap_or_default(),
+            RequestSampleBody::GraphQL { query, variables } => {
+                serde_json::to_string(&serde_json::json!({ "query": query, "variables": variables })).unwrap_or_default()
+            }
+            RequestSampleBody::Raw { content, .. } => content.clone(),
+        }
+    }
+}
+
+/// Base URL of the first server with its variables set to their defaults
+pub fn base_url(spec: &ApiSpecification) -> String {
+    let Some(server) = spec.servers.first() else { return "http://localhost".to_string() };
+    let mut url = server.url.clone();
+    for (name, variable) in &server.variables {
+        url = url.replace(&format!("{{{}}}", name), &variable.default);
+    }
+    url.trim_end_matches('/').to_string()
+}
+
+pub fn build_samples(spec: &ApiSpecification) -> Vec<RequestSample> {
+    spec.endpoints.iter().map(|endpoint| build_sample(spec, endpoint)).collect()
+}
+
+pub fn build_sample(spec: &ApiSpecification, endpoint: &ApiEndpoint) -> RequestSample {
+    let method = format!("{:?}", endpoint.method);
+    let key = endpoint
+        .operation_id
+        .clone()
+        .unwrap_or_else(|| format!("{} {}", method, endpoint.path));
+
+    let mut path_examples = Vec::new();
+    let mut query = Vec::new();
+    let mut headers = Vec::new();
+    let mut cookies = Vec::new();
+    for parameter in &endpoint.parameters {
+        let example = parameter
+            .example
+            .clone()
+            .or_else(|| Some(example_for_reference(spec, &parameter.schema, 0)).filter(|v| !v.is_null()));
+        let Some(example) = example else { continue };
+        // Optional query parameters only appear when the spec gives an explicit example
+        if !parameter.required && parameter.example.is_none() && !matches!(parameter.location, ParameterLocation::Path) {
+            continue;
+        }
+        let value = scalar_string(&example);
+        match parameter.location {
+            ParameterLocation::Path => path_examples.push((parameter.name.clone(), value)),
+            ParameterLocation::Query => query.push((parameter.name.clone(), value)),
+            ParameterLocation::Header => headers.push((parameter.name.clone(), value)),
+            ParameterLocation::Cookie => cookies.push((parameter.name.clone(), value)),
+        }
+    }
+
+    let path = endpoint
+        .path
+        .split('/')
+        .filter(|segment| !segment.is_empty())
+        .map(|segment| match segment.strip_prefix('{').and_then(|s| s.strip_suffix('}')) {
+            Some(name) => PathSegment::Parameter {
+                name: name.to_string(),
+                example: path_examples
+                    .iter()
+                    .find(|(n, _)| n == name)
+                    .map(|(_, v)| v.clone())
+                    .unwrap_or_else(|| "1".to_string()),
+            },
+            None => PathSegment::Literal(segment.to_string()),
+        })
+        .collect();
+
+    RequestSample {
+        key,
+        name: if endpoint.summary.is_empty() { format!("{} {}", method, endpoint.path) } else { endpoint.summary.clone() },
+        folder: endpoint.tags.first().cloned(),
+        method,
+        path,
+        query,
+        headers,
+        cookies,
+        body: sample_body(spec, endpoint),
+        auth: endpoint_auth(spec, endpoint),
+        description: endpoint.description.clone(),
+    }
+}
+
+fn sample_body(spec: &ApiSpecification, endpoint: &ApiEndpoint) -> Option<RequestSampleBody> {
+    let body = endpoint.request_body.as_ref()?;
+
+    // Prefer JSON, then whatever the spec lists first in a stable order
+    let mut content_types: Vec<&String> = body.content.keys().collect();
+    content_types.sort_by(|a, b| (!a.contains("json"), a).cmp(&(!b.contains("json"), b)));
+    let content_type = content_types.first()?.to_string();
+    let media = &body.content[&content_type];
+
+    let example = media
+        .example
+        .clone()
+        .or_else(|| {
+            let mut named: Vec<_> = media.examples.iter().collect();
+            named.sort_by(|(a, _), (b, _)| a.cmp(b));
+            named.first().map(|(_, example)| example.value.clone())
+        })
+        .or_else(|| endpoint.examples.first().map(|example| example.value.clone()))
+        .or_else(|| media.schema.as_ref().map(|schema| example_for_reference(spec, schema, 0)))?;
+
+    if matches!(spec.spec_type, ApiSpecType::GraphQL) {
+        if let Some(query) = example.get("query").and_then(|q| q.as_str()) {
+            return Some(RequestSampleBody::GraphQL {
+                query: query.to_string(),
+                variables: example.get("variables").cloned().unwrap_or_else(|| serde_json::json!({})),
+            });
+        }
+    }
+
+    if content_type.contains("json") {
+        Some(RequestSampleBody::Json(example))
+    } else {
+        Some(RequestSampleBody::Raw {
+            content: match &example {
+                Value::String(s) => s.clone(),
+                other => other.to_string(),
+            },
+            content_type,
+        })
+    }
+}
+
+/// The endpoint's first security requirement, or the spec's default scheme
+pub fn endpoint_auth(spec: &ApiSpecification, endpoint: &ApiEndpoint) -> Option<AuthenticationScheme> {
+    match endpoint.security.first() {
+        Some(requirement) => spec
+            .authentication
+            .iter()
+            .find(|scheme| scheme.name == requirement.scheme_name)
+            .cloned(),
+        None => spec.authentication.first().cloned(),
+    }
+}
+
+/// How a scheme places its credential on the request
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AuthPlacement {
+    Bearer,
+    Basic,
+    Header(String),
+    Query(String),
+    Cookie(String),
+}
+
+pub fn auth_placement(scheme: &AuthenticationScheme) -> AuthPlacement {
+    match scheme.scheme_type {
+        AuthType::ApiKey => match scheme.location.as_deref() {
+            Some("query") => AuthPlacement::Query(scheme.name.clone()),
+            Some("cookie") => AuthPlacement::Cookie(scheme.name.clone()),
+            _ => AuthPlacement::Header(scheme.name.clone()),
+        },
+        AuthType::Http if scheme.scheme.as_deref().map(|s| s.eq_ignore_ascii_case("basic")).unwrap_or(false) => {
+            AuthPlacement::Basic
+        }
+        AuthType::Http | AuthType::OAuth2 | AuthType::OpenIdConnect => AuthPlacement::Bearer,
+    }
+}
+
+pub fn example_for_reference(spec: &ApiSpecification, reference: &SchemaReference, depth: usize) -> Value {
+    match reference {
+        SchemaReference::Inline(definition) => example_for_schema(spec, definition, depth),
+        SchemaReference::Reference(name) => {
+            let name = name.rsplit('/').next().unwrap_or(name);
+            match spec.schemas.iter().find(|schema| schema.name == name) {
+                Some(definition) if depth < MAX_EXAMPLE_DEPTH => example_for_schema(spec, definition, depth + 1),
+                _ => Value::Null,
+            }
+        }
+        SchemaReference::DynamicReference(_) => Value::Null,
+    }
+}
+
+pub fn example_for_schema(spec: &ApiSpecification, schema: &SchemaDefinition, depth: usize) -> Value {
+    if let Some(example) = &schema.example {
+        return example.clone();
+    }
+    if let Some(first) = schema.enum_values.as_ref().and_then(|values| values.first()) {
+        return first.clone();
+    }
+
+    match schema.schema_type {
+        SchemaType::String => serde_json::json!(match schema.format.as_deref() {
+            Some("date-time") | Some("DateTime") => "2024-01-01T00:00:00Z",
+            Some("date") | Some("Date") => "2024-01-01",
+            Some("uuid") | Some("UUID") => "00000000-0000-0000-0000-000000000000",
+            Some("email") => "user@example.com",
+            Some("uri") | Some("url") => "https://example.com",
+            Some("id") => "1",
+            _ => "string",
+        }),
+        SchemaType::Integer => serde_json::json!(1),
+        SchemaType::Number => serde_json::json!(1.5),
+        SchemaType::Boolean => serde_json::json!(true),
+        SchemaType::Null => Value::Null,
+        SchemaType::Array if !schema.prefix_items.is_empty() && depth < MAX_EXAMPLE_DEPTH => Value::Array(
+            schema.prefix_items.iter().map(|item| example_for_reference(spec, item, depth + 1)).collect(),
+        ),
+        SchemaType::Array => match &schema.items {
+            Some(items) if depth < MAX_EXAMPLE_DEPTH => {
+                let item = example_for_reference(spec, items, depth + 1);
+                if item.is_null() { serde_json::json!([]) } else { serde_json::json!([item]) }
+            }
+            _ => serde_json::json!([]),
+        },
+        SchemaType::Object => {
+            let mut object = serde_json::Map::new();
+            if depth < MAX_EXAMPLE_DEPTH {
+                let mut names: Vec<&String> = schema.properties.keys().collect();
+                names.sort();
+                for name in names {
+                    let value = example_for_reference(spec, &schema.properties[name], depth + 1);
+                    if !value.is_null() || schema.required.contains(name) {
+                        object.insert(name.clone(), value);
+                    }
+                }
+            }
+            Value::Object(object)
+        }
+    }
+}
+
+fn scalar_string(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        Value::Array(items) => items.iter().map(scalar_string).collect::<Vec<_>>().join(","),
+        other => other.to_string(),
+    }
+}
+
+/// Percent-encode everything outside the RFC 3986 unreserved set
+pub fn percent_encode(value: &str) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => encoded.push(byte as char),
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
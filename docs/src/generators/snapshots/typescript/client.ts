@@ -0,0 +1,85 @@
+// Generated by aion-docs from Pet Store 1.0.0. Do not edit.
+
+import type { NewPet, Pet, Pets } from "./types";
+
+export * from "./types";
+
+export interface ClientOptions {
+  baseUrl?: string;
+  token?: string;
+  fetch?: typeof fetch;
+}
+
+export class ApiError extends Error {
+  constructor(readonly status: number, readonly body: unknown) {
+    super(`Request failed with status ${status}`);
+  }
+}
+
+export class PetStoreClient {
+  private readonly baseUrl: string;
+
+  constructor(private readonly options: ClientOptions = {}) {
+    this.baseUrl = (options.baseUrl ?? "https://petstore.example.com/v1").replace(/\/+$/, "");
+  }
+
+  /** List pets */
+  async listPets(xRequestId: string, limit?: number, tags?: string[]): Promise<Pets> {
+    const query = new URLSearchParams();
+    if (limit !== undefined) query.set("limit", String(limit));
+    if (tags !== undefined) for (const value of tags) query.append("tags", String(value));
+    const headers: Record<string, string> = {};
+    headers["X-Request-Id"] = String(xRequestId);
+    if (this.options.token !== undefined) headers["Authorization"] = `Bearer ${this.options.token}`;
+    return this.request<Pets>("GET", "/pets", query, headers);
+  }
+
+  /** Create a pet */
+  async createPet(body: NewPet): Promise<Pet> {
+    const query = new URLSearchParams();
+    const headers: Record<string, string> = {};
+    headers["Content-Type"] = "application/json";
+    if (this.options.token !== undefined) headers["Authorization"] = `Bearer ${this.options.token}`;
+    return this.request<Pet>("POST", "/pets", query, headers, body);
+  }
+
+  /** Get a pet */
+  async getPet(petId: string, includeHistory?: boolean): Promise<Pet> {
+    const query = new URLSearchParams();
+    if (includeHistory !== undefined) query.set("include-history", String(includeHistory));
+    const headers: Record<string, string> = {};
+    if (this.options.token !== undefined) headers["Authorization"] = `Bearer ${this.options.token}`;
+    return this.request<Pet>("GET", `/pets/${encodeURIComponent(String(petId))}`, query, headers);
+  }
+
+  /** @deprecated */
+  async deletePetsByPetId(petId: string): Promise<void> {
+    const query = new URLSearchParams();
+    const headers: Record<string, string> = {};
+    if (this.options.token !== undefined) headers["X-API-Key"] = this.options.token;
+    return this.request<void>("DELETE", `/pets/${encodeURIComponent(String(petId))}`, query, headers);
+  }
+
+  private async request<T>(
+    method: string,
+    path: string,
+    query: URLSearchParams,
+    headers: Record<string, string>,
+    body?: unknown,
+  ): Promise<T> {
+    const search = query.toString();
+    const url = `${this.baseUrl}${path}${search ? `?${search}` : ""}`;
+    const json = headers["Content-Type"]?.includes("json") ?? false;
+    const response = await (this.options.fetch ?? fetch)(url, {
+      method,
+      headers,
+      body: body === undefined ? undefined : json ? JSON.stringify(body) : String(body),
+    });
+    const text = await response.text();
+    const payload = text && response.headers.get("Content-Type")?.includes("json") ? JSON.parse(text) : text;
+    if (!response.ok) {
+      throw new ApiError(response.status, payload);
+    }
+    return (text ? payload : undefined) as T;
+  }
+}
@@ -0,0 +1,18 @@
+// Generated by aion-docs from Pet Store 1.0.0. Do not edit.
+
+/** A pet in the store */
+export interface Pet {
+  id: number;
+  name: string;
+  status?: "available" | "sold";
+  /** Free-form label */
+  tag?: string | null;
+}
+
+/** Fields accepted when creating a pet */
+export interface NewPet {
+  name: string;
+  tag?: string;
+}
+
+export type Pets = Pet[];
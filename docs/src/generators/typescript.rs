@@ -0,0 +1,746 @@
+//! TypeScript SDK generator
+//!
+//! Emits two files: `types.ts`, with an interface (or type alias) per
+//! `SchemaDefinition`, and `client.ts`, a fetch-based client class with one
+//! typed async method per endpoint. Path, query and header parameters become
+//! method arguments, optional ones last; cookie parameters are left to the
+//! runtime's cookie jar. Credentials are passed to the client constructor and
+//! placed on each request the way the endpoint's security scheme requires.
+
+use super::samples::{auth_placement, base_url, endpoint_auth, AuthPlacement};
+use crate::{
+    ApiEndpoint, ApiSpecification, Parameter, ParameterLocation, RequestBody, Result, SchemaDefinition, SchemaReference,
+    SchemaType,
+};
+use serde_json::Value;
+use std::collections::{BTreeSet, HashSet};
+use std::io::Write;
+
+pub const CLIENT_FILE: &str = "client.ts";
+pub const TYPES_FILE: &str = "types.ts";
+
+/// Identifiers TypeScript does not allow as parameter names, plus the
+/// generated methods' own locals
+const RESERVED_IDENTIFIERS: &[&str] = &[
+    "break", "case", "catch", "class", "const", "continue", "debugger", "default", "delete", "do", "else", "enum",
+    "export", "extends", "false", "finally", "for", "function", "if", "import", "in", "instanceof", "new", "null",
+    "return", "super", "switch", "this", "throw", "true", "try", "typeof", "var", "void", "while", "with", "let",
+    "static", "yield", "await", "body", "headers", "query",
+];
+
+#[derive(Debug, Clone)]
+pub struct TypeScriptSdk {
+    /// Contents of `client.ts`
+    pub client: String,
+    /// Contents of `types.ts`
+    pub types: String,
+}
+
+impl TypeScriptSdk {
+    /// Both files as a zip archive
+    pub fn to_zip(&self) -> Result<Vec<u8>> {
+        let mut archive = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+        for (name, contents) in [(CLIENT_FILE, &self.client), (TYPES_FILE, &self.types)] {
+            archive.start_file(name, zip::write::FileOptions::default())?;
+            archive.write_all(contents.as_bytes())?;
+        }
+        Ok(archive.finish()?.into_inner())
+    }
+}
+
+pub struct TypeScriptSdkGenerator;
+
+impl TypeScriptSdkGenerator {
+    pub fn generate(spec: &ApiSpecification) -> TypeScriptSdk {
+        TypeScriptSdk { client: Self::client(spec), types: Self::types(spec) }
+    }
+
+    pub fn types(spec: &ApiSpecification) -> String {
+        let mut lines = vec![header(spec)];
+        for schema in &spec.schemas {
+            lines.push(String::new());
+            lines.extend(doc_comment(schema.description.as_deref(), false, ""));
+
+            let name = pascal_case(&schema.name);
+            let is_interface = matches!(schema.schema_type, SchemaType::Object)
+                && !schema.properties.is_empty()
+                && schema.types.is_empty()
+                && schema.enum_values.is_none();
+            if is_interface {
+                lines.push(format!("export interface {} {{", name));
+                let mut properties: Vec<&String> = schema.properties.keys().collect();
+                properties.sort();
+                for property in properties {
+                    let reference = &schema.properties[property];
+                    if let SchemaReference::Inline(definition) = reference {
+                        lines.extend(doc_comment(definition.description.as_deref(), false, "  "));
+                    }
+                    lines.push(format!(
+                        "  {}{}: {};",
+                        property_key(property),
+                        if schema.required.contains(property) { "" } else { "?" },
+                        reference_type(spec, reference, &mut BTreeSet::new())
+                    ));
+                }
+                lines.push("}".to_string());
+            } else {
+                lines.push(format!("export type {} = {};", name, schema_type(spec, schema, &mut BTreeSet::new())));
+            }
+        }
+        lines.push(String::new());
+        lines.join("\n")
+    }
+
+    pub fn client(spec: &ApiSpecification) -> String {
+        let placements: Vec<AuthPlacement> = spec
+            .endpoints
+            .iter()
+            .filter_map(|endpoint| endpoint_auth(spec, endpoint))
+            .map(|scheme| auth_placement(&scheme))
+            .collect();
+
+        let mut used_types = BTreeSet::new();
+        let mut method_names = HashSet::new();
+        let mut methods = Vec::new();
+        for endpoint in &spec.endpoints {
+            let name = unique_name(&mut method_names, method_name(endpoint));
+            methods.push(String::new());
+            methods.extend(Self::method(spec, endpoint, &name, &mut used_types));
+        }
+
+        let mut lines = vec![header(spec), String::new()];
+        if !used_types.is_empty() {
+            let names: Vec<String> = used_types.into_iter().collect();
+            lines.push(format!("import type {{ {} }} from \"./types\";", names.join(", ")));
+            lines.push(String::new());
+        }
+        lines.push("export * from \"./types\";".to_string());
+        lines.push(String::new());
+
+        lines.push("export interface ClientOptions {".to_string());
+        lines.push("  baseUrl?: string;".to_string());
+        if placements.iter().any(|placement| *placement != AuthPlacement::Basic) {
+            lines.push("  token?: string;".to_string());
+        }
+        if placements.contains(&AuthPlacement::Basic) {
+            lines.push("  username?: string;".to_string());
+            lines.push("  password?: string;".to_string());
+        }
+        lines.push("  fetch?: typeof fetch;".to_string());
+        lines.push("}".to_string());
+        lines.push(String::new());
+
+        lines.extend(
+            [
+                "export class ApiError extends Error {",
+                "  constructor(readonly status: number, readonly body: unknown) {",
+                "    super(`Request failed with status ${status}`);",
+                "  }",
+                "}",
+            ]
+            .map(str::to_string),
+        );
+        lines.push(String::new());
+
+        lines.push(format!("export class {} {{", client_name(spec)));
+        lines.push("  private readonly baseUrl: string;".to_string());
+        lines.push(String::new());
+        lines.push("  constructor(private readonly options: ClientOptions = {}) {".to_string());
+        lines.push(format!(
+            "    this.baseUrl = (options.baseUrl ?? {}).replace(/\\/+$/, \"\");",
+            string_literal(&base_url(spec))
+        ));
+        lines.push("  }".to_string());
+        lines.extend(methods);
+        lines.push(String::new());
+        lines.extend(
+            [
+                "  private async request<T>(",
+                "    method: string,",
+                "    path: string,",
+                "    query: URLSearchParams,",
+                "    headers: Record<string, string>,",
+                "    body?: unknown,",
+                "  ): Promise<T> {",
+                "    const search = query.toString();",
+                "    const url = `${this.baseUrl}${path}${search ? `?${search}` : \"\"}`;",
+                "    const json = headers[\"Content-Type\"]?.includes(\"json\") ?? false;",
+                "    const response = await (this.options.fetch ?? fetch)(url, {",
+                "      method,",
+                "      headers,",
+                "      body: body === undefined ? undefined : json ? JSON.stringify(body) : String(body),",
+                "    });",
+                "    const text = await response.text();",
+                "    const payload = text && response.headers.get(\"Content-Type\")?.includes(\"json\") ? JSON.parse(text) : text;",
+                "    if (!response.ok) {",
+                "      throw new ApiError(response.status, payload);",
+                "    }",
+                "    return (text ? payload : undefined) as T;",
+                "  }",
+                "}",
+                "",
+            ]
+            .map(str::to_string),
+        );
+        lines.join("\n")
+    }
+
+    fn method(spec: &ApiSpecification, endpoint: &ApiEndpoint, name: &str, used_types: &mut BTreeSet<String>) -> Vec<String> {
+        let parameters: Vec<_> = endpoint
+            .parameters
+            .iter()
+            .filter(|parameter| !matches!(parameter.location, ParameterLocation::Cookie))
+            .map(|parameter| {
+                let required = parameter.required || matches!(parameter.location, ParameterLocation::Path);
+                (parameter, identifier(&parameter.name), required)
+            })
+            .collect();
+        let body = endpoint.request_body.as_ref().map(|body| (body, body_media(body)));
+
+        // Optional arguments have to follow the required ones
+        let mut required_arguments = Vec::new();
+        let mut optional_arguments = Vec::new();
+        for (parameter, argument, required) in &parameters {
+            let argument = format!(
+                "{}{}: {}",
+                argument,
+                if *required { "" } else { "?" },
+                reference_type(spec, &parameter.schema, used_types)
+            );
+            if *required { required_arguments.push(argument) } else { optional_arguments.push(argument) }
+        }
+        if let Some((body, media)) = &body {
+            let body_type = match media.and_then(|(_, schema)| schema) {
+                Some(schema) => reference_type(spec, schema, used_types),
+                None => "unknown".to_string(),
+            };
+            if body.required {
+                required_arguments.push(format!("body: {}", body_type));
+            } else {
+                optional_arguments.push(format!("body?: {}", body_type));
+            }
+        }
+        required_arguments.extend(optional_arguments);
+
+        let return_type = return_type(spec, endpoint, used_types);
+
+        let mut doc = String::new();
+        if !endpoint.summary.is_empty() {
+            doc.push_str(&endpoint.summary);
+        } else if let Some(description) = &endpoint.description {
+            doc.push_str(description);
+        }
+        let mut lines = doc_comment(Some(&doc).filter(|doc| !doc.is_empty()).map(String::as_str), endpoint.deprecated, "  ");
+        lines.push(format!("  async {}({}): Promise<{}> {{", name, required_arguments.join(", "), return_type));
+
+        lines.push("    const query = new URLSearchParams();".to_string());
+        for (parameter, argument, required) in &parameters {
+            if !matches!(parameter.location, ParameterLocation::Query) {
+                continue;
+            }
+            let key = string_literal(&parameter.name);
+            let set = if is_array(spec, &parameter.schema) {
+                format!("for (const value of {}) query.append({}, String(value));", argument, key)
+            } else {
+                format!("query.set({}, String({}));", key, argument)
+            };
+            lines.push(guarded(argument, *required, set));
+        }
+
+        lines.push("    const headers: Record<string, string> = {};".to_string());
+        for (parameter, argument, required) in &parameters {
+            if matches!(parameter.location, ParameterLocation::Header) {
+                let set = format!("headers[{}] = String({});", string_literal(&parameter.name), argument);
+                lines.push(guarded(argument, *required, set));
+            }
+        }
+        if let Some((_, Some((content_type, _)))) = &body {
+            lines.push(format!("    headers[\"Content-Type\"] = {};", string_literal(content_type)));
+        }
+        if let Some(scheme) = endpoint_auth(spec, endpoint) {
+            lines.push(auth_line(&auth_placement(&scheme)));
+        }
+
+        let mut call = format!(
+            "    return this.request<{}>({}, {}, query, headers",
+            return_type,
+            string_literal(&format!("{:?}", endpoint.method)),
+            path_expression(&endpoint.path, &parameters)
+        );
+        if body.is_some() {
+            call.push_str(", body");
+        }
+        call.push_str(");");
+        lines.push(call);
+        lines.push("  }".to_string());
+        lines
+    }
+}
+
+/// The media type a request body is sent as, preferring JSON, with its schema
+fn body_media(body: &RequestBody) -> Option<(&str, Option<&SchemaReference>)> {
+    let mut content_types: Vec<&String> = body.content.keys().collect();
+    content_types.sort_by(|a, b| (!a.contains("json"), a).cmp(&(!b.contains("json"), b)));
+    let content_type = content_types.first()?;
+    Some((content_type.as_str(), body.content[*content_type].schema.as_ref()))
+}
+
+/// The type of the first 2xx response; `void` when it has no body
+fn return_type(spec: &ApiSpecification, endpoint: &ApiEndpoint, used_types: &mut BTreeSet<String>) -> String {
+    let mut statuses: Vec<&String> = endpoint.responses.keys().filter(|status| status.starts_with('2')).collect();
+    statuses.sort();
+    let Some(status) = statuses.first() else { return "unknown".to_string() };
+
+    let response = &endpoint.responses[*status];
+    let mut content_types: Vec<&String> = response.content.keys().collect();
+    content_types.sort_by(|a, b| (!a.contains("json"), a).cmp(&(!b.contains("json"), b)));
+    match content_types.first().map(|content_type| &response.content[*content_type]) {
+        Some(media) => match &media.schema {
+            Some(schema) => reference_type(spec, schema, used_types),
+            None => "unknown".to_string(),
+        },
+        None => "void".to_string(),
+    }
+}
+
+fn auth_line(placement: &AuthPlacement) -> String {
+    match placement {
+        AuthPlacement::Bearer => {
+            "    if (this.options.token !== undefined) headers[\"Authorization\"] = `Bearer ${this.options.token}`;"
+                .to_string()
+        }
+        AuthPlacement::Basic => "    if (this.options.username !== undefined) headers[\"Authorization\"] = `Basic ${btoa(`${this.options.username}:${this.options.password ?? \"\"}`)}`;".to_string(),
+        AuthPlacement::Header(name) => format!(
+            "    if (this.options.token !== undefined) headers[{}] = this.options.token;",
+            string_literal(name)
+        ),
+        AuthPlacement::Query(name) => format!(
+            "    if (this.options.token !== undefined) query.set({}, this.options.token);",
+            string_literal(name)
+        ),
+        AuthPlacement::Cookie(name) => format!(
+            "    if (this.options.token !== undefined) headers[\"Cookie\"] = `{}=${{this.options.token}}`;",
+            name
+        ),
+    }
+}
+
+fn guarded(argument: &str, required: bool, statement: String) -> String {
+    if required {
+        format!("    {}", statement)
+    } else {
+        format!("    if ({} !== undefined) {}", argument, statement)
+    }
+}
+
+/// The request path, with path parameters spliced in as encoded template substitutions
+fn path_expression(path: &str, parameters: &[(&Parameter, String, bool)]) -> String {
+    if !path.contains('{') {
+        return string_literal(path);
+    }
+    let mut template = String::from("`");
+    let mut rest = path;
+    while let Some(start) = rest.find('{') {
+        let Some(end) = rest[start..].find('}').map(|end| start + end) else { break };
+        template.push_str(&rest[..start]);
+        let name = &rest[start + 1..end];
+        let argument = parameters
+            .iter()
+            .find(|(parameter, _, _)| parameter.name == name)
+            .map(|(_, argument, _)| argument.clone())
+            .unwrap_or_else(|| identifier(name));
+        template.push_str(&format!("${{encodeURIComponent(String({}))}}", argument));
+        rest = &rest[end + 1..];
+    }
+    template.push_str(rest);
+    template.push('`');
+    template
+}
+
+fn reference_type(spec: &ApiSpecification, reference: &SchemaReference, used_types: &mut BTreeSet<String>) -> String {
+    match reference {
+        SchemaReference::Inline(definition) => schema_type(spec, definition, used_types),
+        SchemaReference::Reference(target) => {
+            let name = target.rsplit('/').next().unwrap_or(target);
+            if spec.schemas.iter().any(|schema| schema.name == name) {
+                let name = pascal_case(name);
+                used_types.insert(name.clone());
+                name
+            } else {
+                "unknown".to_string()
+            }
+        }
+        SchemaReference::DynamicReference(_) => "unknown".to_string(),
+    }
+}
+
+fn schema_type(spec: &ApiSpecification, schema: &SchemaDefinition, used_types: &mut BTreeSet<String>) -> String {
+    if let Some(values) = schema.enum_values.as_ref().filter(|values| !values.is_empty()) {
+        let literals: Vec<String> = values
+            .iter()
+            .map(|value| match value {
+                Value::Array(_) | Value::Object(_) => "unknown".to_string(),
+                literal => literal.to_string(),
+            })
+            .collect();
+        return literals.join(" | ");
+    }
+
+    let types = if schema.types.is_empty() { vec![schema.schema_type] } else { schema.types.clone() };
+    let mut alternatives: Vec<String> = Vec::new();
+    for schema_type in types {
+        let alternative = match schema_type {
+            SchemaType::String => "string".to_string(),
+            SchemaType::Integer | SchemaType::Number => "number".to_string(),
+            SchemaType::Boolean => "boolean".to_string(),
+            SchemaType::Null => "null".to_string(),
+            SchemaType::Array if !schema.prefix_items.is_empty() => {
+                let items: Vec<String> =
+                    schema.prefix_items.iter().map(|item| reference_type(spec, item, used_types)).collect();
+                format!("[{}]", items.join(", "))
+            }
+            SchemaType::Array => match &schema.items {
+                Some(items) => {
+                    let item = reference_type(spec, items, used_types);
+                    if item.contains(' ') { format!("({})[]", item) } else { format!("{}[]", item) }
+                }
+                None => "unknown[]".to_string(),
+            },
+            SchemaType::Object if !schema.properties.is_empty() => {
+                let mut names: Vec<&String> = schema.properties.keys().collect();
+                names.sort();
+                let properties: Vec<String> = names
+                    .into_iter()
+                    .map(|name| {
+                        format!(
+                            "{}{}: {}",
+                            property_key(name),
+                            if schema.required.contains(name) { "" } else { "?" },
+                            reference_type(spec, &schema.properties[name], used_types)
+                        )
+                    })
+                    .collect();
+                format!("{{ {} }}", properties.join("; "))
+            }
+            SchemaType::Object => match &schema.additional_properties {
+                Some(values) => format!("Record<string, {}>", reference_type(spec, values, used_types)),
+                None => "Record<string, unknown>".to_string(),
+            },
+        };
+        if !alternatives.contains(&alternative) {
+            alternatives.push(alternative);
+        }
+    }
+    alternatives.join(" | ")
+}
+
+fn is_array(spec: &ApiSpecification, reference: &SchemaReference) -> bool {
+    match reference {
+        SchemaReference::Inline(definition) => matches!(definition.schema_type, SchemaType::Array),
+        SchemaReference::Reference(target) => {
+            let name = target.rsplit('/').next().unwrap_or(target);
+            spec.schemas.iter().any(|schema| schema.name == name && matches!(schema.schema_type, SchemaType::Array))
+        }
+        SchemaReference::DynamicReference(_) => false,
+    }
+}
+
+fn header(spec: &ApiSpecification) -> String {
+    format!("// Generated by aion-docs from {} {}. Do not edit.", spec.name, spec.version)
+}
+
+fn doc_comment(text: Option<&str>, deprecated: bool, indent: &str) -> Vec<String> {
+    let mut lines: Vec<String> = text
+        .map(|text| text.replace("*/", "*\\/").lines().map(str::trim_end).map(str::to_string).collect())
+        .unwrap_or_default();
+    if deprecated {
+        lines.push("@deprecated".to_string());
+    }
+    match lines.len() {
+        0 => Vec::new(),
+        1 => vec![format!("{}/** {} */", indent, lines[0])],
+        _ => {
+            let mut comment = vec![format!("{}/**", indent)];
+            comment.extend(lines.iter().map(|line| format!("{} * {}", indent, line).trim_end().to_string()));
+            comment.push(format!("{} */", indent));
+            comment
+        }
+    }
+}
+
+fn client_name(spec: &ApiSpecification) -> String {
+    let name = pascal_case(&spec.name);
+    if name.is_empty() {
+        "ApiClient".to_string()
+    } else if name.ends_with("Client") {
+        name
+    } else {
+        format!("{}Client", name)
+    }
+}
+
+/// The operation id, or the method and the path's literal segments
+fn method_name(endpoint: &ApiEndpoint) -> String {
+    match &endpoint.operation_id {
+        Some(operation_id) => camel_case(operation_id),
+        None => {
+            let mut words = vec![format!("{:?}", endpoint.method).to_lowercase()];
+            for segment in endpoint.path.split('/').filter(|segment| !segment.is_empty()) {
+                match segment.strip_prefix('{').and_then(|s| s.strip_suffix('}')) {
+                    Some(parameter) => words.push(format!("by {}", parameter)),
+                    None => words.push(segment.to_string()),
+                }
+            }
+            camel_case(&words.join(" "))
+        }
+    }
+}
+
+fn unique_name(used: &mut HashSet<String>, name: String) -> String {
+    let mut candidate = name.clone();
+    let mut suffix = 2;
+    while !used.insert(candidate.clone()) {
+        candidate = format!("{}{}", name, suffix);
+        suffix += 1;
+    }
+    candidate
+}
+
+/// A parameter name as a TypeScript identifier
+fn identifier(name: &str) -> String {
+    let identifier = camel_case(name);
+    if identifier.is_empty() {
+        "value".to_string()
+    } else if RESERVED_IDENTIFIERS.contains(&identifier.as_str()) {
+        format!("{}_", identifier)
+    } else {
+        identifier
+    }
+}
+
+fn words(value: &str) -> impl Iterator<Item = &str> {
+    value.split(|c: char| !c.is_ascii_alphanumeric()).filter(|word| !word.is_empty())
+}
+
+fn upper_first(word: &str) -> String {
+    let mut chars = word.chars();
+    chars.next().map(|first| first.to_ascii_uppercase().to_string() + chars.as_str()).unwrap_or_default()
+}
+
+fn pascal_case(value: &str) -> String {
+    let name: String = words(value).map(upper_first).collect();
+    if name.starts_with(|c: char| c.is_ascii_digit()) { format!("_{}", name) } else { name }
+}
+
+fn camel_case(value: &str) -> String {
+    let pascal = pascal_case(value);
+    let mut chars = pascal.chars();
+    chars.next().map(|first| first.to_ascii_lowercase().to_string() + chars.as_str()).unwrap_or_default()
+}
+
+fn property_key(name: &str) -> String {
+    let valid = name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_' || c == '$')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '$');
+    if valid { name.to_string() } else { string_literal(name) }
+}
+
+fn string_literal(value: &str) -> String {
+    Value::String(value.to_string()).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        ApiSpecType, AuthType, AuthenticationScheme, HttpMethod, MediaType, Response, SecurityRequirement,
+        ServerConfiguration,
+    };
+    use std::collections::HashMap;
+    use std::path::PathBuf;
+
+    fn schema(schema_type: SchemaType) -> SchemaDefinition {
+        SchemaDefinition {
+            name: String::new(),
+            schema_type,
+            format: None,
+            description: None,
+            example: None,
+            properties: HashMap::new(),
+            required: Vec::new(),
+            additional_properties: None,
+            items: None,
+            prefix_items: Vec::new(),
+            types: Vec::new(),
+            enum_values: None,
+            discriminator: None,
+            xml: None,
+            external_docs: None,
+        }
+    }
+
+    fn inline(definition: SchemaDefinition) -> SchemaReference {
+        SchemaReference::Inline(Box::new(definition))
+    }
+
+    fn reference(name: &str) -> SchemaReference {
+        SchemaReference::Reference(format!("#/components/schemas/{}", name))
+    }
+
+    fn object(name: &str, description: &str, properties: &[(&str, SchemaReference)], required: &[&str]) -> SchemaDefinition {
+        SchemaDefinition {
+            name: name.to_string(),
+            description: Some(description.to_string()),
+            properties: properties.iter().cloned().map(|(name, schema)| (name.to_string(), schema)).collect(),
+            required: required.iter().map(|name| name.to_string()).collect(),
+            ..schema(SchemaType::Object)
+        }
+    }
+
+    fn parameter(name: &str, location: ParameterLocation, required: bool, schema: SchemaReference) -> Parameter {
+        Parameter {
+            name: name.to_string(),
+            location,
+            description: None,
+            required,
+            deprecated: false,
+            schema,
+            example: None,
+        }
+    }
+
+    fn json(schema: Option<SchemaReference>) -> HashMap<String, MediaType> {
+        let media = MediaType { schema, example: None, examples: HashMap::new(), encoding: HashMap::new() };
+        HashMap::from([("application/json".to_string(), media)])
+    }
+
+    fn response(status: &str, content: HashMap<String, MediaType>) -> HashMap<String, Response> {
+        let response = Response { description: String::new(), headers: HashMap::new(), content, links: HashMap::new() };
+        HashMap::from([(status.to_string(), response)])
+    }
+
+    fn endpoint(method: HttpMethod, path: &str, operation_id: Option<&str>, summary: &str) -> ApiEndpoint {
+        ApiEndpoint {
+            path: path.to_string(),
+            method,
+            operation_id: operation_id.map(str::to_string),
+            summary: summary.to_string(),
+            description: None,
+            parameters: Vec::new(),
+            request_body: None,
+            responses: HashMap::new(),
+            security: Vec::new(),
+            tags: Vec::new(),
+            deprecated: false,
+            examples: Vec::new(),
+        }
+    }
+
+    fn auth_scheme(name: &str, scheme_type: AuthType, location: Option<&str>, scheme: Option<&str>) -> AuthenticationScheme {
+        AuthenticationScheme {
+            scheme_type,
+            description: None,
+            name: name.to_string(),
+            location: location.map(str::to_string),
+            scheme: scheme.map(str::to_string),
+            bearer_format: None,
+            flows: None,
+            open_id_connect_url: None,
+        }
+    }
+
+    fn pet_store() -> ApiSpecification {
+        let nullable_string = SchemaDefinition {
+            description: Some("Free-form label".to_string()),
+            types: vec![SchemaType::String, SchemaType::Null],
+            ..schema(SchemaType::String)
+        };
+        let status = SchemaDefinition {
+            enum_values: Some(vec![serde_json::json!("available"), serde_json::json!("sold")]),
+            ..schema(SchemaType::String)
+        };
+        let pet = object(
+            "Pet",
+            "A pet in the store",
+            &[
+                ("id", inline(schema(SchemaType::Integer))),
+                ("name", inline(schema(SchemaType::String))),
+                ("tag", inline(nullable_string)),
+                ("status", inline(status)),
+            ],
+            &["id", "name"],
+        );
+        let new_pet = object(
+            "NewPet",
+            "Fields accepted when creating a pet",
+            &[("name", inline(schema(SchemaType::String))), ("tag", inline(schema(SchemaType::String)))],
+            &["name"],
+        );
+        let pets = SchemaDefinition {
+            name: "Pets".to_string(),
+            items: Some(Box::new(reference("Pet"))),
+            ..schema(SchemaType::Array)
+        };
+        let tags = SchemaDefinition { items: Some(Box::new(inline(schema(SchemaType::String)))), ..schema(SchemaType::Array) };
+
+        let mut list_pets = endpoint(HttpMethod::GET, "/pets", Some("listPets"), "List pets");
+        list_pets.parameters = vec![
+            parameter("limit", ParameterLocation::Query, false, inline(schema(SchemaType::Integer))),
+            parameter("tags", ParameterLocation::Query, false, inline(tags)),
+            parameter("X-Request-Id", ParameterLocation::Header, true, inline(schema(SchemaType::String))),
+        ];
+        list_pets.responses = response("200", json(Some(reference("Pets"))));
+
+        let mut create_pet = endpoint(HttpMethod::POST, "/pets", Some("create_pet"), "Create a pet");
+        create_pet.request_body = Some(RequestBody { description: None, content: json(Some(reference("NewPet"))), required: true });
+        create_pet.responses = response("201", json(Some(reference("Pet"))));
+
+        let mut get_pet = endpoint(HttpMethod::GET, "/pets/{petId}", Some("getPet"), "Get a pet");
+        get_pet.parameters = vec![
+            parameter("petId", ParameterLocation::Path, true, inline(schema(SchemaType::String))),
+            parameter("include-history", ParameterLocation::Query, false, inline(schema(SchemaType::Boolean))),
+        ];
+        get_pet.responses = response("200", json(Some(reference("Pet"))));
+
+        let mut delete_pet = endpoint(HttpMethod::DELETE, "/pets/{petId}", None, "");
+        delete_pet.parameters =
+            vec![parameter("petId", ParameterLocation::Path, true, inline(schema(SchemaType::String)))];
+        delete_pet.responses = response("204", HashMap::new());
+        delete_pet.security = vec![SecurityRequirement { scheme_name: "X-API-Key".to_string(), scopes: Vec::new() }];
+        delete_pet.deprecated = true;
+
+        ApiSpecification {
+            id: uuid::Uuid::nil(),
+            name: "Pet Store".to_string(),
+            version: "1.0.0".to_string(),
+            spec_type: ApiSpecType::OpenAPI3,
+            source_path: PathBuf::from("pets.yaml"),
+            endpoints: vec![list_pets, create_pet, get_pet, delete_pet],
+            schemas: vec![pet, new_pet, pets],
+            authentication: vec![
+                auth_scheme("bearerAuth", AuthType::Http, None, Some("bearer")),
+                auth_scheme("X-API-Key", AuthType::ApiKey, Some("header"), None),
+            ],
+            servers: vec![ServerConfiguration {
+                url: "https://petstore.example.com/v1".to_string(),
+                description: None,
+                variables: HashMap::new(),
+            }],
+            tags: Vec::new(),
+            external_docs: None,
+            auto_generated: false,
+            last_updated: chrono::Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_client_snapshot() {
+        let client = TypeScriptSdkGenerator::client(&pet_store());
+        assert_eq!(client, include_str!("snapshots/typescript/client.ts"));
+    }
+
+    #[test]
+    fn test_types_snapshot() {
+        let types = TypeScriptSdkGenerator::types(&pet_store());
+        assert_eq!(types, include_str!("snapshots/typescript/types.ts"));
+    }
+}
@@ -20,6 +20,7 @@ pub use export::*;
 pub use integrations::*;
 pub use themes::*;
 
+use aion_ai_engine::semantic_search::Embedder;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::path::PathBuf;
@@ -74,6 +75,7 @@ pub struct ApiSpecification {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum ApiSpecType {
     OpenAPI3,
+    OpenAPI31,
     OpenAPI2,
     AsyncAPI,
     GraphQL,
@@ -189,13 +191,20 @@ pub struct SchemaDefinition {
     pub required: Vec<String>,
     pub additional_properties: Option<Box<SchemaReference>>,
     pub items: Option<Box<SchemaReference>>,
+    /// Schemas of the leading array positions (JSON Schema `prefixItems`)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub prefix_items: Vec<SchemaReference>,
+    /// Every type a value may have when the schema allows more than one,
+    /// such as `["string", "null"]`; empty when `schema_type` is the only one
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub types: Vec<SchemaType>,
     pub enum_values: Option<Vec<serde_json::Value>>,
     pub discriminator: Option<Discriminator>,
     pub xml: Option<XmlObject>,
     pub external_docs: Option<ExternalDocumentation>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum SchemaType {
     String,
     Number,
@@ -209,6 +218,9 @@ pub enum SchemaType {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum SchemaReference {
     Reference(String),
+    /// JSON Schema `$dynamicRef`, resolved against the dynamic scope at
+    /// validation time rather than to a fixed schema
+    DynamicReference(String),
     Inline(Box<SchemaDefinition>),
 }
 
@@ -581,6 +593,9 @@ pub struct SearchConfiguration {
     pub provider: SearchProvider,
     pub indexing: IndexingConfiguration,
     pub ui: SearchUIConfiguration,
+    /// Fuse keyword results with the vector index; keyword search alone when unset
+    #[serde(default)]
+    pub hybrid: Option<HybridSearchConfiguration>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -1044,6 +1059,28 @@ pub trait DocumentationManager {
     async fn preview_project(&self, id: Uuid) -> Result<String>;
     async fn sync_content(&self, project_id: Uuid, source_id: Uuid) -> Result<()>;
     async fn search_content(&self, project_id: Uuid, query: &str) -> Result<Vec<SearchResult>>;
+    /// Keyword search, fused with the closest pages in `index` when
+    /// `config.hybrid` is set; see [`search::fuse_scores`]
+    async fn search_content_hybrid(
+        &self,
+        project_id: Uuid,
+        query: &str,
+        config: &SearchConfiguration,
+        index: &VectorIndex,
+        embedder: &dyn Embedder,
+    ) -> Result<Vec<SearchResult>> {
+        let keyword = self.search_content(project_id, query).await?;
+        let Some(hybrid) = &config.hybrid else {
+            return Ok(keyword);
+        };
+        let query_vector = embedder
+            .embed(&[query.to_string()])
+            .await?
+            .pop()
+            .ok_or("Embedder returned no vector for the query")?;
+        let vector = index.search(&query_vector, hybrid.vector_candidates).await?;
+        Ok(fuse_scores(keyword, vector, hybrid.vector_weight))
+    }
     async fn generate_sitemap(&self, project_id: Uuid) -> Result<String>;
     async fn export_project(&self, project_id: Uuid, format: ExportFormat) -> Result<Vec<u8>>;
 }
@@ -1072,6 +1109,7 @@ pub enum ExportFormat {
 
 #[async_trait::async_trait]
 pub trait ApiDocumentationGenerator {
+    /// Parse an OpenAPI 3.0 or 3.1 document; see [`parsers::parse_openapi_spec`]
     async fn parse_openapi_spec(&self, spec_path: &str) -> Result<ApiSpecification>;
     async fn generate_documentation(&self, spec: &ApiSpecification) -> Result<String>;
     async fn generate_sdk(&self, spec: &ApiSpecification, language: &str) -> Result<Vec<u8>>;
@@ -0,0 +1,1161 @@
+//! GraphQL schema parser
+//!
+//! Loads a GraphQL schema either from an SDL document or by running the standard
+//! introspection query against a live endpoint, and maps it into the shared
+//! `ApiSpecification` model:
+//!
+//! - object, input, interface and union types become `SchemaDefinition`s
+//!   (interfaces and unions carry a `__typename` discriminator)
+//! - enums become string schemas with `enum_values`, custom scalars become
+//!   string schemas whose `format` is the scalar name
+//! - every query, mutation and subscription field becomes an `ApiEndpoint`
+//!   with a runnable example operation and example variables
+
+use crate::{
+    ApiEndpoint, ApiSpecType, ApiSpecification, AuthType, AuthenticationScheme, Discriminator, Example,
+    HttpMethod, MediaType, RequestBody, Response, Result, SchemaDefinition, SchemaReference, SchemaType,
+    SecurityRequirement, ServerConfiguration,
+};
+use chrono::Utc;
+use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use uuid::Uuid;
+
+/// Maximum nesting of generated example selection sets
+const EXAMPLE_SELECTION_DEPTH: usize = 2;
+
+const BUILTIN_SCALARS: [&str; 5] = ["String", "Int", "Float", "Boolean", "ID"];
+
+#[derive(Debug, thiserror::Error)]
+pub enum GraphQLParseError {
+    #[error("Introspection is disabled on {endpoint}. Upload the schema as an SDL file instead (e.g. exported with `rover graph introspect` or from your server's schema printer).")]
+    IntrospectionDisabled { endpoint: String },
+    #[error("Introspection request to {endpoint} was rejected with HTTP {status}: check the configured authentication")]
+    Unauthorized { endpoint: String, status: u16 },
+    #[error("Introspection request failed: {0}")]
+    Request(String),
+    #[error("Invalid GraphQL SDL: {0}")]
+    InvalidSdl(String),
+    #[error("Invalid introspection response: {0}")]
+    InvalidIntrospection(String),
+}
+
+/// Type reference with GraphQL wrapping preserved
+#[derive(Debug, Clone, PartialEq)]
+pub enum GraphQLTypeRef {
+    Named(String),
+    List(Box<GraphQLTypeRef>),
+    NonNull(Box<GraphQLTypeRef>),
+}
+
+impl GraphQLTypeRef {
+    pub fn named_type(&self) -> &str {
+        match self {
+            GraphQLTypeRef::Named(name) => name,
+            GraphQLTypeRef::List(inner) | GraphQLTypeRef::NonNull(inner) => inner.named_type(),
+        }
+    }
+
+    pub fn is_non_null(&self) -> bool {
+        matches!(self, GraphQLTypeRef::NonNull(_))
+    }
+
+    /// SDL notation, e.g. `[String!]!`
+    pub fn to_sdl(&self) -> String {
+        match self {
+            GraphQLTypeRef::Named(name) => name.clone(),
+            GraphQLTypeRef::List(inner) => format!("[{}]", inner.to_sdl()),
+            GraphQLTypeRef::NonNull(inner) => format!("{}!", inner.to_sdl()),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct GraphQLInputValue {
+    pub name: String,
+    pub description: Option<String>,
+    pub type_ref: GraphQLTypeRef,
+    pub default_value: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct GraphQLField {
+    pub name: String,
+    pub description: Option<String>,
+    pub arguments: Vec<GraphQLInputValue>,
+    pub type_ref: GraphQLTypeRef,
+    pub deprecation_reason: Option<String>,
+    pub deprecated: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GraphQLTypeKind {
+    Object,
+    InputObject,
+    Interface,
+    Union,
+    Enum,
+    Scalar,
+}
+
+#[derive(Debug, Clone)]
+pub struct GraphQLType {
+    pub name: String,
+    pub kind: GraphQLTypeKind,
+    pub description: Option<String>,
+    pub fields: Vec<GraphQLField>,
+    pub input_fields: Vec<GraphQLInputValue>,
+    pub interfaces: Vec<String>,
+    /// Union members or interface implementors
+    pub possible_types: Vec<String>,
+    pub enum_values: Vec<String>,
+}
+
+impl GraphQLType {
+    fn new(name: String, kind: GraphQLTypeKind, description: Option<String>) -> Self {
+        Self {
+            name,
+            kind,
+            description,
+            fields: Vec::new(),
+            input_fields: Vec::new(),
+            interfaces: Vec::new(),
+            possible_types: Vec::new(),
+            enum_values: Vec::new(),
+        }
+    }
+}
+
+/// Source-independent view of a GraphQL schema
+#[derive(Debug, Clone)]
+pub struct GraphQLSchema {
+    pub query_type: Option<String>,
+    pub mutation_type: Option<String>,
+    pub subscription_type: Option<String>,
+    pub types: HashMap<String, GraphQLType>,
+}
+
+/// Where and how to run introspection
+#[derive(Debug, Clone)]
+pub struct GraphQLIntrospectionOptions {
+    pub endpoint: String,
+    pub authentication: Option<AuthenticationScheme>,
+    /// Token, API key or pre-encoded basic credentials for `authentication`
+    pub credential: Option<String>,
+    pub headers: HashMap<String, String>,
+}
+
+pub const INTROSPECTION_QUERY: &str = r#"
+query IntrospectionQuery {
+  __schema {
+    queryType { name }
+    mutationType { name }
+    subscriptionType { name }
+    types {
+      kind
+      name
+      description
+      fields(includeDeprecated: true) {
+        name
+        description
+        args { name description type { ...TypeRef } defaultValue }
+        type { ...TypeRef }
+        isDeprecated
+        deprecationReason
+      }
+      inputFields { name description type { ...TypeRef } defaultValue }
+      interfaces { name }
+      enumValues(includeDeprecated: true) { name }
+      possibleTypes { name }
+    }
+  }
+}
+
+fragment TypeRef on __Type {
+  kind
+  name
+  ofType { kind name ofType { kind name ofType { kind name ofType { kind name ofType { kind name } } } } }
+}
+"#;
+
+pub struct GraphQLParser;
+
+impl GraphQLParser {
+    /// Parse an SDL document into an `ApiSpecification`
+    pub fn parse_sdl(name: &str, sdl: &str, endpoint: &str) -> Result<ApiSpecification> {
+        let schema = Self::schema_from_sdl(sdl)?;
+        Ok(Self::to_specification(name, &schema, endpoint, None, PathBuf::new()))
+    }
+
+    pub async fn parse_sdl_file(name: &str, path: &str, endpoint: &str) -> Result<ApiSpecification> {
+        let sdl = tokio::fs::read_to_string(path).await?;
+        let schema = Self::schema_from_sdl(&sdl)?;
+        Ok(Self::to_specification(name, &schema, endpoint, None, PathBuf::from(path)))
+    }
+
+    /// Run introspection against a live endpoint
+    pub async fn introspect(name: &str, options: &GraphQLIntrospectionOptions) -> Result<ApiSpecification> {
+        let client = reqwest::Client::new();
+        let mut request = client
+            .post(&options.endpoint)
+            .json(&serde_json::json!({ "query": INTROSPECTION_QUERY, "operationName": "IntrospectionQuery" }));
+
+        for (header, value) in &options.headers {
+            request = request.header(header, value);
+        }
+        if let (Some(scheme), Some(credential)) = (&options.authentication, &options.credential) {
+            request = Self::apply_auth(request, scheme, credential);
+        }
+
+        let response = request
+            .send()
+            .await
+            .map_err(|e| GraphQLParseError::Request(e.to_string()))?;
+        let status = response.status();
+        if status == reqwest::StatusCode::UNAUTHORIZED || status == reqwest::StatusCode::FORBIDDEN {
+            return Err(GraphQLParseError::Unauthorized { endpoint: options.endpoint.clone(), status: status.as_u16() }.into());
+        }
+
+        let body: serde_json::Value = response
+            .json()
+            .await
+            .map_err(|e| GraphQLParseError::InvalidIntrospection(e.to_string()))?;
+
+        let schema = Self::schema_from_introspection(&body).map_err(|e| match e {
+            GraphQLParseError::InvalidIntrospection(_) if Self::introspection_disabled(&body) => {
+                GraphQLParseError::IntrospectionDisabled { endpoint: options.endpoint.clone() }
+            }
+            other => other,
+        })?;
+
+        Ok(Self::to_specification(name, &schema, &options.endpoint, options.authentication.clone(), PathBuf::new()))
+    }
+
+    fn apply_auth(request: reqwest::RequestBuilder, scheme: &AuthenticationScheme, credential: &str) -> reqwest::RequestBuilder {
+        match scheme.scheme_type {
+            AuthType::ApiKey => match scheme.location.as_deref() {
+                Some("query") => request.query(&[(scheme.name.as_str(), credential)]),
+                Some("cookie") => request.header("Cookie", format!("{}={}", scheme.name, credential)),
+                _ => request.header(scheme.name.as_str(), credential),
+            },
+            AuthType::Http => match scheme.scheme.as_deref().map(str::to_ascii_lowercase).as_deref() {
+                Some("basic") => request.header("Authorization", format!("Basic {}", credential)),
+                _ => request.bearer_auth(credential),
+            },
+            AuthType::OAuth2 | AuthType::OpenIdConnect => request.bearer_auth(credential),
+        }
+    }
+
+    /// Servers report disabled introspection as a GraphQL error rather than an HTTP status
+    fn introspection_disabled(body: &serde_json::Value) -> bool {
+        body.get("errors")
+            .and_then(|e| e.as_array())
+            .map(|errors| {
+                errors.iter().any(|error| {
+                    let message = error.get("message").and_then(|m| m.as_str()).unwrap_or("").to_ascii_lowercase();
+                    message.contains("introspection") || message.contains("__schema")
+                })
+            })
+            .unwrap_or(false)
+    }
+
+    pub fn schema_from_sdl(sdl: &str) -> std::result::Result<GraphQLSchema, GraphQLParseError> {
+        use graphql_parser::schema::{Definition, TypeExtension};
+
+        let document = graphql_parser::parse_schema::<String>(sdl)
+            .map_err(|e| GraphQLParseError::InvalidSdl(e.to_string()))?;
+
+        let mut schema = GraphQLSchema {
+            query_type: None,
+            mutation_type: None,
+            subscription_type: None,
+            types: HashMap::new(),
+        };
+        let mut explicit_roots = false;
+
+        for definition in &document.definitions {
+            match definition {
+                Definition::SchemaDefinition(def) => {
+                    explicit_roots = true;
+                    schema.query_type = def.query.clone();
+                    schema.mutation_type = def.mutation.clone();
+                    schema.subscription_type = def.subscription.clone();
+                }
+                Definition::TypeDefinition(def) => {
+                    let ty = Self::convert_sdl_type(def);
+                    schema.types.insert(ty.name.clone(), ty);
+                }
+                Definition::TypeExtension(ext) => {
+                    // Extensions only add members; apply them to the base definition
+                    match ext {
+                        TypeExtension::Object(object) => {
+                            if let Some(ty) = schema.types.get_mut(&object.name) {
+                                ty.fields.extend(object.fields.iter().map(Self::convert_sdl_field));
+                                ty.interfaces.extend(object.implements_interfaces.iter().cloned());
+                            }
+                        }
+                        TypeExtension::InputObject(input) => {
+                            if let Some(ty) = schema.types.get_mut(&input.name) {
+                                ty.input_fields.extend(input.fields.iter().map(Self::convert_sdl_input));
+                            }
+                        }
+                        TypeExtension::Enum(enumeration) => {
+                            if let Some(ty) = schema.types.get_mut(&enumeration.name) {
+                                ty.enum_values.extend(enumeration.values.iter().map(|v| v.name.clone()));
+                            }
+                        }
+                        TypeExtension::Union(union) => {
+                            if let Some(ty) = schema.types.get_mut(&union.name) {
+                                ty.possible_types.extend(union.types.iter().cloned());
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+                Definition::DirectiveDefinition(_) => {}
+            }
+        }
+
+        if !explicit_roots {
+            for (root, slot) in [
+                ("Query", &mut schema.query_type),
+                ("Mutation", &mut schema.mutation_type),
+                ("Subscription", &mut schema.subscription_type),
+            ] {
+                if schema.types.contains_key(root) {
+                    *slot = Some(root.to_string());
+                }
+            }
+        }
+
+        Self::link_implementors(&mut schema);
+        Ok(schema)
+    }
+
+    fn convert_sdl_type(def: &graphql_parser::schema::TypeDefinition<'_, String>) -> GraphQLType {
+        use graphql_parser::schema::TypeDefinition;
+
+        match def {
+            TypeDefinition::Object(object) => {
+                let mut ty = GraphQLType::new(object.name.clone(), GraphQLTypeKind::Object, object.description.clone());
+                ty.fields = object.fields.iter().map(Self::convert_sdl_field).collect();
+                ty.interfaces = object.implements_interfaces.clone();
+                ty
+            }
+            TypeDefinition::Interface(interface) => {
+                let mut ty = GraphQLType::new(interface.name.clone(), GraphQLTypeKind::Interface, interface.description.clone());
+                ty.fields = interface.fields.iter().map(Self::convert_sdl_field).collect();
+                ty
+            }
+            TypeDefinition::Union(union) => {
+                let mut ty = GraphQLType::new(union.name.clone(), GraphQLTypeKind::Union, union.description.clone());
+                ty.possible_types = union.types.clone();
+                ty
+            }
+            TypeDefinition::Enum(enumeration) => {
+                let mut ty = GraphQLType::new(enumeration.name.clone(), GraphQLTypeKind::Enum, enumeration.description.clone());
+                ty.enum_values = enumeration.values.iter().map(|v| v.name.clone()).collect();
+                ty
+            }
+            TypeDefinition::InputObject(input) => {
+                let mut ty = GraphQLType::new(input.name.clone(), GraphQLTypeKind::InputObject, input.description.clone());
+                ty.input_fields = input.fields.iter().map(Self::convert_sdl_input).collect();
+                ty
+            }
+            TypeDefinition::Scalar(scalar) => {
+                GraphQLType::new(scalar.name.clone(), GraphQLTypeKind::Scalar, scalar.description.clone())
+            }
+        }
+    }
+
+    fn convert_sdl_field(field: &graphql_parser::schema::Field<'_, String>) -> GraphQLField {
+        let deprecation = field.directives.iter().find(|d| d.name == "deprecated");
+        GraphQLField {
+            name: field.name.clone(),
+            description: field.description.clone(),
+            arguments: field.arguments.iter().map(Self::convert_sdl_input).collect(),
+            type_ref: Self::convert_sdl_type_ref(&field.field_type),
+            deprecated: deprecation.is_some(),
+            deprecation_reason: deprecation.and_then(|d| {
+                d.arguments
+                    .iter()
+                    .find(|(name, _)| name == "reason")
+                    .map(|(_, value)| value.to_string().trim_matches('"').to_string())
+            }),
+        }
+    }
+
+    fn convert_sdl_input(input: &graphql_parser::schema::InputValue<'_, String>) -> GraphQLInputValue {
+        GraphQLInputValue {
+            name: input.name.clone(),
+            description: input.description.clone(),
+            type_ref: Self::convert_sdl_type_ref(&input.value_type),
+            default_value: input.default_value.as_ref().map(|v| v.to_string()),
+        }
+    }
+
+    fn convert_sdl_type_ref(ty: &graphql_parser::schema::Type<'_, String>) -> GraphQLTypeRef {
+        use graphql_parser::schema::Type;
+        match ty {
+            Type::NamedType(name) => GraphQLTypeRef::Named(name.clone()),
+            Type::ListType(inner) => GraphQLTypeRef::List(Box::new(Self::convert_sdl_type_ref(inner))),
+            Type::NonNullType(inner) => GraphQLTypeRef::NonNull(Box::new(Self::convert_sdl_type_ref(inner))),
+        }
+    }
+
+    /// Fill interface `possible_types` from the objects that implement them
+    fn link_implementors(schema: &mut GraphQLSchema) {
+        let mut implementors: HashMap<String, Vec<String>> = HashMap::new();
+        for ty in schema.types.values() {
+            for interface in &ty.interfaces {
+                implementors.entry(interface.clone()).or_default().push(ty.name.clone());
+            }
+        }
+        for (interface, mut objects) in implementors {
+            if let Some(ty) = schema.types.get_mut(&interface) {
+                if ty.kind == GraphQLTypeKind::Interface {
+                    objects.sort();
+                    for object in objects {
+                        if !ty.possible_types.contains(&object) {
+                            ty.possible_types.push(object);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn schema_from_introspection(body: &serde_json::Value) -> std::result::Result<GraphQLSchema, GraphQLParseError> {
+        let raw = body
+            .get("data")
+            .and_then(|d| d.get("__schema"))
+            .ok_or_else(|| GraphQLParseError::InvalidIntrospection("missing data.__schema".to_string()))?;
+        let introspected: IntrospectionSchema = serde_json::from_value(raw.clone())
+            .map_err(|e| GraphQLParseError::InvalidIntrospection(e.to_string()))?;
+
+        let mut schema = GraphQLSchema {
+            query_type: introspected.query_type.map(|t| t.name),
+            mutation_type: introspected.mutation_type.map(|t| t.name),
+            subscription_type: introspected.subscription_type.map(|t| t.name),
+            types: HashMap::new(),
+        };
+
+        for raw_type in introspected.types {
+            let Some(name) = raw_type.name else { continue };
+            if name.starts_with("__") {
+                continue;
+            }
+            let kind = match raw_type.kind.as_str() {
+                "OBJECT" => GraphQLTypeKind::Object,
+                "INPUT_OBJECT" => GraphQLTypeKind::InputObject,
+                "INTERFACE" => GraphQLTypeKind::Interface,
+                "UNION" => GraphQLTypeKind::Union,
+                "ENUM" => GraphQLTypeKind::Enum,
+                "SCALAR" => GraphQLTypeKind::Scalar,
+                other => return Err(GraphQLParseError::InvalidIntrospection(format!("unknown type kind {}", other))),
+            };
+
+            let mut ty = GraphQLType::new(name.clone(), kind, raw_type.description);
+            ty.fields = raw_type
+                .fields
+                .unwrap_or_default()
+                .into_iter()
+                .map(|f| {
+                    Ok(GraphQLField {
+                        name: f.name,
+                        description: f.description,
+                        arguments: f.args.into_iter().map(IntrospectionInputValue::convert).collect::<std::result::Result<_, _>>()?,
+                        type_ref: f.type_ref.convert()?,
+                        deprecated: f.is_deprecated,
+                        deprecation_reason: f.deprecation_reason,
+                    })
+                })
+                .collect::<std::result::Result<_, GraphQLParseError>>()?;
+            ty.input_fields = raw_type
+                .input_fields
+                .unwrap_or_default()
+                .into_iter()
+                .map(IntrospectionInputValue::convert)
+                .collect::<std::result::Result<_, _>>()?;
+            ty.interfaces = raw_type.interfaces.unwrap_or_default().into_iter().map(|t| t.name).collect();
+            ty.possible_types = raw_type.possible_types.unwrap_or_default().into_iter().map(|t| t.name).collect();
+            ty.enum_values = raw_type.enum_values.unwrap_or_default().into_iter().map(|v| v.name).collect();
+
+            schema.types.insert(name, ty);
+        }
+
+        Ok(schema)
+    }
+
+    /// Map a parsed schema into the crate's documentation model
+    pub fn to_specification(
+        name: &str,
+        schema: &GraphQLSchema,
+        endpoint: &str,
+        authentication: Option<AuthenticationScheme>,
+        source_path: PathBuf,
+    ) -> ApiSpecification {
+        let root_types: HashSet<&str> = [&schema.query_type, &schema.mutation_type, &schema.subscription_type]
+            .iter()
+            .filter_map(|t| t.as_deref())
+            .collect();
+
+        let mut type_names: Vec<&String> = schema.types.keys().collect();
+        type_names.sort();
+        let schemas: Vec<SchemaDefinition> = type_names
+            .into_iter()
+            .filter(|n| !root_types.contains(n.as_str()) && !BUILTIN_SCALARS.contains(&n.as_str()))
+            .map(|n| Self::schema_definition(&schema.types[n]))
+            .collect();
+
+        let mut endpoints = Vec::new();
+        for (operation, root) in [
+            ("query", &schema.query_type),
+            ("mutation", &schema.mutation_type),
+            ("subscription", &schema.subscription_type),
+        ] {
+            let Some(root_type) = root.as_ref().and_then(|r| schema.types.get(r)) else { continue };
+            for field in &root_type.fields {
+                endpoints.push(Self::operation_endpoint(schema, operation, field, endpoint, authentication.as_ref()));
+            }
+        }
+
+        let path = url_path(endpoint);
+        let mut tags: Vec<String> = endpoints.iter().flat_map(|e| e.tags.clone()).collect();
+        tags.dedup();
+
+        ApiSpecification {
+            id: Uuid::new_v4(),
+            name: name.to_string(),
+            version: "graphql".to_string(),
+            spec_type: ApiSpecType::GraphQL,
+            source_path,
+            endpoints,
+            schemas,
+            authentication: authentication.into_iter().collect(),
+            servers: vec![ServerConfiguration {
+                url: endpoint.trim_end_matches(&path).to_string(),
+                description: Some("GraphQL server".to_string()),
+                variables: HashMap::new(),
+            }],
+            tags,
+            external_docs: None,
+            auto_generated: true,
+            last_updated: Utc::now(),
+        }
+    }
+
+    fn schema_definition(ty: &GraphQLType) -> SchemaDefinition {
+        let mut definition = SchemaDefinition {
+            name: ty.name.clone(),
+            schema_type: SchemaType::Object,
+            format: None,
+            description: ty.description.clone(),
+            example: None,
+            properties: HashMap::new(),
+            required: Vec::new(),
+            additional_properties: None,
+            items: None,
+            prefix_items: Vec::new(),
+            types: Vec::new(),
+            enum_values: None,
+            discriminator: None,
+            xml: None,
+            external_docs: None,
+        };
+
+        match ty.kind {
+            GraphQLTypeKind::Object | GraphQLTypeKind::Interface => {
+                for field in &ty.fields {
+                    definition.properties.insert(field.name.clone(), type_ref_schema(&field.type_ref));
+                    if field.type_ref.is_non_null() {
+                        definition.required.push(field.name.clone());
+                    }
+                }
+                if ty.kind == GraphQLTypeKind::Interface {
+                    definition.discriminator = Some(typename_discriminator(&ty.possible_types));
+                }
+            }
+            GraphQLTypeKind::InputObject => {
+                for input in &ty.input_fields {
+                    definition.properties.insert(input.name.clone(), type_ref_schema(&input.type_ref));
+                    if input.type_ref.is_non_null() && input.default_value.is_none() {
+                        definition.required.push(input.name.clone());
+                    }
+                }
+            }
+            GraphQLTypeKind::Union => {
+                definition.discriminator = Some(typename_discriminator(&ty.possible_types));
+                let members = ty.possible_types.join(" | ");
+                definition.description = Some(match &ty.description {
+                    Some(description) => format!("{}\n\nOne of: {}", description, members),
+                    None => format!("One of: {}", members),
+                });
+            }
+            GraphQLTypeKind::Enum => {
+                definition.schema_type = SchemaType::String;
+                definition.enum_values = Some(ty.enum_values.iter().map(|v| serde_json::json!(v)).collect());
+            }
+            GraphQLTypeKind::Scalar => {
+                definition.schema_type = SchemaType::String;
+                definition.format = Some(ty.name.clone());
+            }
+        }
+
+        definition.required.sort();
+        definition
+    }
+
+    fn operation_endpoint(
+        schema: &GraphQLSchema,
+        operation: &str,
+        field: &GraphQLField,
+        endpoint: &str,
+        authentication: Option<&AuthenticationScheme>,
+    ) -> ApiEndpoint {
+        let (query, variables) = example_operation(schema, operation, field);
+        let body_example = serde_json::json!({ "query": query, "variables": variables });
+
+        let mut variable_schema = SchemaDefinition {
+            name: format!("{}Variables", upper_first(&field.name)),
+            schema_type: SchemaType::Object,
+            format: None,
+            description: None,
+            example: Some(variables.clone()),
+            properties: HashMap::new(),
+            required: Vec::new(),
+            additional_properties: None,
+            items: None,
+            prefix_items: Vec::new(),
+            types: Vec::new(),
+            enum_values: None,
+            discriminator: None,
+            xml: None,
+            external_docs: None,
+        };
+        for argument in &field.arguments {
+            variable_schema.properties.insert(argument.name.clone(), type_ref_schema(&argument.type_ref));
+            if argument.type_ref.is_non_null() && argument.default_value.is_none() {
+                variable_schema.required.push(argument.name.clone());
+            }
+        }
+
+        let mut content = HashMap::new();
+        content.insert(
+            "application/json".to_string(),
+            MediaType {
+                schema: Some(SchemaReference::Inline(Box::new(variable_schema))),
+                example: Some(body_example.clone()),
+                examples: HashMap::new(),
+                encoding: HashMap::new(),
+            },
+        );
+
+        let mut response_content = HashMap::new();
+        response_content.insert(
+            "application/json".to_string(),
+            MediaType {
+                schema: Some(type_ref_schema(&field.type_ref)),
+                example: None,
+                examples: HashMap::new(),
+                encoding: HashMap::new(),
+            },
+        );
+        let mut responses = HashMap::new();
+        responses.insert(
+            "200".to_string(),
+            Response {
+                description: format!("`data.{}` of type `{}`", field.name, field.type_ref.to_sdl()),
+                headers: HashMap::new(),
+                content: response_content,
+                links: HashMap::new(),
+            },
+        );
+
+        let mut description = field.description.clone().unwrap_or_default();
+        if let Some(reason) = &field.deprecation_reason {
+            description.push_str(&format!("\n\nDeprecated: {}", reason));
+        }
+
+        ApiEndpoint {
+            path: url_path(endpoint),
+            method: HttpMethod::POST,
+            operation_id: Some(field.name.clone()),
+            summary: format!("{} {}", operation, field.name),
+            description: if description.is_empty() { None } else { Some(description) },
+            parameters: Vec::new(),
+            request_body: Some(RequestBody {
+                description: Some(format!("GraphQL {} document and variables", operation)),
+                content,
+                required: true,
+            }),
+            responses,
+            security: authentication
+                .map(|a| vec![SecurityRequirement { scheme_name: a.name.clone(), scopes: Vec::new() }])
+                .unwrap_or_default(),
+            tags: vec![upper_first(operation)],
+            deprecated: field.deprecated,
+            examples: vec![Example {
+                name: field.name.clone(),
+                summary: Some(format!("Example {}", operation)),
+                description: None,
+                value: body_example,
+                external_value: None,
+            }],
+        }
+    }
+}
+
+/// Build `operation Name($arg: Type) { field(arg: $arg) { ... } }` plus example variables
+pub fn example_operation(schema: &GraphQLSchema, operation: &str, field: &GraphQLField) -> (String, serde_json::Value) {
+    let mut variables = serde_json::Map::new();
+    let mut declarations = Vec::new();
+    let mut arguments = Vec::new();
+    for argument in &field.arguments {
+        declarations.push(format!("${}: {}", argument.name, argument.type_ref.to_sdl()));
+        arguments.push(format!("{}: ${}", argument.name, argument.name));
+        variables.insert(argument.name.clone(), example_value(schema, &argument.type_ref, 0));
+    }
+
+    let mut document = format!("{} {}", operation, upper_first(&field.name));
+    if !declarations.is_empty() {
+        document.push_str(&format!("({})", declarations.join(", ")));
+    }
+    document.push_str(" {\n  ");
+    document.push_str(&field.name);
+    if !arguments.is_empty() {
+        document.push_str(&format!("({})", arguments.join(", ")));
+    }
+    document.push_str(&selection_set(schema, field.type_ref.named_type(), 1));
+    document.push_str("\n}");
+
+    (document, serde_json::Value::Object(variables))
+}
+
+fn selection_set(schema: &GraphQLSchema, type_name: &str, depth: usize) -> String {
+    let Some(ty) = schema.types.get(type_name) else { return String::new() };
+    let indent = "  ".repeat(depth + 1);
+    let closing = "  ".repeat(depth);
+
+    let mut lines: Vec<String> = Vec::new();
+    match ty.kind {
+        GraphQLTypeKind::Object | GraphQLTypeKind::Interface => {
+            if ty.kind == GraphQLTypeKind::Interface {
+                lines.push("__typename".to_string());
+            }
+            for field in &ty.fields {
+                // Fields with required arguments cannot be selected without values
+                if field.arguments.iter().any(|a| a.type_ref.is_non_null() && a.default_value.is_none()) {
+                    continue;
+                }
+                let target = field.type_ref.named_type();
+                if is_leaf(schema, target) {
+                    lines.push(field.name.clone());
+                } else if depth < EXAMPLE_SELECTION_DEPTH {
+                    let nested = selection_set(schema, target, depth + 1);
+                    if !nested.is_empty() {
+                        lines.push(format!("{}{}", field.name, nested));
+                    }
+                }
+            }
+        }
+        GraphQLTypeKind::Union => {
+            lines.push("__typename".to_string());
+            for member in &ty.possible_types {
+                let fragment = selection_set(schema, member, depth + 1);
+                if !fragment.is_empty() {
+                    lines.push(format!("... on {}{}", member, fragment));
+                }
+            }
+        }
+        _ => return String::new(),
+    }
+
+    if lines.is_empty() {
+        return String::new();
+    }
+    format!(" {{\n{}{}\n{}}}", indent, lines.join(&format!("\n{}", indent)), closing)
+}
+
+fn is_leaf(schema: &GraphQLSchema, type_name: &str) -> bool {
+    match schema.types.get(type_name) {
+        Some(ty) => matches!(ty.kind, GraphQLTypeKind::Scalar | GraphQLTypeKind::Enum),
+        None => BUILTIN_SCALARS.contains(&type_name),
+    }
+}
+
+/// Example JSON value for a variable of the given type
+pub fn example_value(schema: &GraphQLSchema, type_ref: &GraphQLTypeRef, depth: usize) -> serde_json::Value {
+    match type_ref {
+        GraphQLTypeRef::NonNull(inner) => example_value(schema, inner, depth),
+        GraphQLTypeRef::List(inner) => serde_json::json!([example_value(schema, inner, depth)]),
+        GraphQLTypeRef::Named(name) => match name.as_str() {
+            "String" => serde_json::json!("example"),
+            "ID" => serde_json::json!("1"),
+            "Int" => serde_json::json!(1),
+            "Float" => serde_json::json!(1.5),
+            "Boolean" => serde_json::json!(true),
+            _ => match schema.types.get(name) {
+                Some(ty) if ty.kind == GraphQLTypeKind::Enum => {
+                    ty.enum_values.first().map(|v| serde_json::json!(v)).unwrap_or(serde_json::Value::Null)
+                }
+                Some(ty) if ty.kind == GraphQLTypeKind::InputObject && depth < EXAMPLE_SELECTION_DEPTH + 1 => {
+                    let mut object = serde_json::Map::new();
+                    for input in &ty.input_fields {
+                        // Optional nested inputs are left out to keep examples short
+                        if input.type_ref.is_non_null() || depth == 0 {
+                            object.insert(input.name.clone(), example_value(schema, &input.type_ref, depth + 1));
+                        }
+                    }
+                    serde_json::Value::Object(object)
+                }
+                Some(ty) if ty.kind == GraphQLTypeKind::Scalar => scalar_example(&ty.name),
+                _ => serde_json::Value::Null,
+            },
+        },
+    }
+}
+
+fn scalar_example(name: &str) -> serde_json::Value {
+    match name.to_ascii_lowercase().as_str() {
+        "datetime" | "timestamp" => serde_json::json!("2024-01-01T00:00:00Z"),
+        "date" => serde_json::json!("2024-01-01"),
+        "uuid" => serde_json::json!("00000000-0000-0000-0000-000000000000"),
+        "json" | "jsonobject" => serde_json::json!({}),
+        "url" | "uri" => serde_json::json!("https://example.com"),
+        "email" | "emailaddress" => serde_json::json!("user@example.com"),
+        _ => serde_json::json!("value"),
+    }
+}
+
+fn type_ref_schema(type_ref: &GraphQLTypeRef) -> SchemaReference {
+    match type_ref {
+        GraphQLTypeRef::NonNull(inner) => type_ref_schema(inner),
+        GraphQLTypeRef::List(inner) => SchemaReference::Inline(Box::new(SchemaDefinition {
+            name: type_ref.to_sdl(),
+            schema_type: SchemaType::Array,
+            format: None,
+            description: None,
+            example: None,
+            properties: HashMap::new(),
+            required: Vec::new(),
+            additional_properties: None,
+            items: Some(Box::new(type_ref_schema(inner))),
+            prefix_items: Vec::new(),
+            types: Vec::new(),
+            enum_values: None,
+            discriminator: None,
+            xml: None,
+            external_docs: None,
+        })),
+        GraphQLTypeRef::Named(name) => {
+            let builtin = match name.as_str() {
+                "String" | "ID" => Some(SchemaType::String),
+                "Int" => Some(SchemaType::Integer),
+                "Float" => Some(SchemaType::Number),
+                "Boolean" => Some(SchemaType::Boolean),
+                _ => None,
+            };
+            match builtin {
+                Some(schema_type) => SchemaReference::Inline(Box::new(SchemaDefinition {
+                    name: name.clone(),
+                    schema_type,
+                    format: if name == "ID" { Some("id".to_string()) } else { None },
+                    description: None,
+                    example: None,
+                    properties: HashMap::new(),
+                    required: Vec::new(),
+                    additional_properties: None,
+                    items: None,
+                    prefix_items: Vec::new(),
+                    types: Vec::new(),
+                    enum_values: None,
+                    discriminator: None,
+                    xml: None,
+                    external_docs: None,
+                })),
+                None => SchemaReference::Reference(name.clone()),
+            }
+        }
+    }
+}
+
+fn typename_discriminator(possible_types: &[String]) -> Discriminator {
+    Discriminator {
+        property_name: "__typename".to_string(),
+        mapping: possible_types.iter().map(|t| (t.clone(), t.clone())).collect(),
+    }
+}
+
+fn upper_first(value: &str) -> String {
+    let mut chars = value.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().collect::<String>() + chars.as_str(),
+        None => String::new(),
+    }
+}
+
+/// Path component of an endpoint URL (`/graphql` when it has none)
+fn url_path(endpoint: &str) -> String {
+    let without_scheme = endpoint.split_once("://").map(|(_, rest)| rest).unwrap_or(endpoint);
+    match without_scheme.find('/') {
+        Some(index) => without_scheme[index..].to_string(),
+        None => "/graphql".to_string(),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct IntrospectionSchema {
+    query_type: Option<IntrospectionName>,
+    mutation_type: Option<IntrospectionName>,
+    subscription_type: Option<IntrospectionName>,
+    types: Vec<IntrospectionType>,
+}
+
+#[derive(Debug, Deserialize)]
+struct IntrospectionName {
+    name: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct IntrospectionType {
+    kind: String,
+    name: Option<String>,
+    description: Option<String>,
+    fields: Option<Vec<IntrospectionField>>,
+    input_fields: Option<Vec<IntrospectionInputValue>>,
+    interfaces: Option<Vec<IntrospectionName>>,
+    enum_values: Option<Vec<IntrospectionName>>,
+    possible_types: Option<Vec<IntrospectionName>>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct IntrospectionField {
+    name: String,
+    description: Option<String>,
+    #[serde(default)]
+    args: Vec<IntrospectionInputValue>,
+    #[serde(rename = "type")]
+    type_ref: IntrospectionTypeRef,
+    #[serde(default)]
+    is_deprecated: bool,
+    deprecation_reason: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct IntrospectionInputValue {
+    name: String,
+    description: Option<String>,
+    #[serde(rename = "type")]
+    type_ref: IntrospectionTypeRef,
+    default_value: Option<String>,
+}
+
+impl IntrospectionInputValue {
+    fn convert(self) -> std::result::Result<GraphQLInputValue, GraphQLParseError> {
+        Ok(GraphQLInputValue {
+            name: self.name,
+            description: self.description,
+            type_ref: self.type_ref.convert()?,
+            default_value: self.default_value,
+        })
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct IntrospectionTypeRef {
+    kind: String,
+    name: Option<String>,
+    of_type: Option<Box<IntrospectionTypeRef>>,
+}
+
+impl IntrospectionTypeRef {
+    fn convert(&self) -> std::result::Result<GraphQLTypeRef, GraphQLParseError> {
+        let inner = || {
+            self.of_type
+                .as_ref()
+                .ok_or_else(|| GraphQLParseError::InvalidIntrospection(format!("{} type without ofType", self.kind)))
+                .and_then(|t| t.convert())
+        };
+        match self.kind.as_str() {
+            "NON_NULL" => Ok(GraphQLTypeRef::NonNull(Box::new(inner()?))),
+            "LIST" => Ok(GraphQLTypeRef::List(Box::new(inner()?))),
+            _ => self
+                .name
+                .clone()
+                .map(GraphQLTypeRef::Named)
+                .ok_or_else(|| GraphQLParseError::InvalidIntrospection("named type without name".to_string())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SDL: &str = r#"
+        scalar DateTime
+
+        enum Role { ADMIN MEMBER }
+
+        interface Node { id: ID! }
+
+        type User implements Node {
+          id: ID!
+          name: String
+          role: Role!
+          joinedAt: DateTime
+          posts(first: Int!): [Post!]!
+        }
+
+        type Post implements Node {
+          id: ID!
+          title: String!
+        }
+
+        union SearchResult = User | Post
+
+        input NewUser {
+          name: String!
+          role: Role = MEMBER
+        }
+
+        type Query {
+          user(id: ID!): User
+          search(term: String!): [SearchResult!]!
+        }
+
+        extend type Query {
+          node(id: ID!): Node
+        }
+
+        type Mutation {
+          createUser(input: NewUser!): User!
+        }
+
+        type Subscription {
+          userJoined: User!
+        }
+    "#;
+
+    fn definition<'a>(spec: &'a ApiSpecification, name: &str) -> &'a SchemaDefinition {
+        spec.schemas.iter().find(|s| s.name == name).unwrap()
+    }
+
+    fn endpoint<'a>(spec: &'a ApiSpecification, operation_id: &str) -> &'a ApiEndpoint {
+        spec.endpoints.iter().find(|e| e.operation_id.as_deref() == Some(operation_id)).unwrap()
+    }
+
+    #[test]
+    fn sdl_maps_types_and_operations() {
+        let spec = GraphQLParser::parse_sdl("Users", SDL, "https://api.example.com/graphql").unwrap();
+
+        // Root types become endpoints, not schemas
+        assert!(spec.schemas.iter().all(|s| !["Query", "Mutation", "Subscription"].contains(&s.name.as_str())));
+        assert_eq!(spec.endpoints.len(), 5);
+        assert_eq!(spec.servers[0].url, "https://api.example.com");
+        assert_eq!(endpoint(&spec, "node").path, "/graphql");
+        assert_eq!(endpoint(&spec, "createUser").tags, ["Mutation"]);
+        assert_eq!(endpoint(&spec, "userJoined").summary, "subscription userJoined");
+
+        let user = definition(&spec, "User");
+        assert_eq!(user.required, ["id", "posts", "role"]);
+        assert!(matches!(&user.properties["role"], SchemaReference::Reference(name) if name == "Role"));
+
+        let node = definition(&spec, "Node").discriminator.as_ref().unwrap();
+        assert_eq!(node.property_name, "__typename");
+        let mut implementors: Vec<&String> = node.mapping.keys().collect();
+        implementors.sort();
+        assert_eq!(implementors, ["Post", "User"]);
+
+        let search_result = definition(&spec, "SearchResult");
+        assert_eq!(search_result.description.as_deref(), Some("One of: User | Post"));
+
+        let role = definition(&spec, "Role");
+        assert!(matches!(role.schema_type, SchemaType::String));
+        assert_eq!(role.enum_values.as_ref().unwrap(), &vec![serde_json::json!("ADMIN"), serde_json::json!("MEMBER")]);
+        assert_eq!(definition(&spec, "DateTime").format.as_deref(), Some("DateTime"));
+
+        // Inputs with a default are optional
+        assert_eq!(definition(&spec, "NewUser").required, ["name"]);
+    }
+
+    #[test]
+    fn example_operations_declare_variables_and_select_fields() {
+        let schema = GraphQLParser::schema_from_sdl(SDL).unwrap();
+        let query = &schema.types["Query"];
+
+        let user = query.fields.iter().find(|f| f.name == "user").unwrap();
+        let (document, variables) = example_operation(&schema, "query", user);
+        assert!(document.starts_with("query User($id: ID!) {\n  user(id: $id) {"));
+        // `posts` needs an argument and is left out of the selection
+        assert!(document.contains("joinedAt") && !document.contains("posts"));
+        assert_eq!(variables, serde_json::json!({ "id": "1" }));
+
+        let search = query.fields.iter().find(|f| f.name == "search").unwrap();
+        let (document, _) = example_operation(&schema, "query", search);
+        assert!(document.contains("__typename"));
+        assert!(document.contains("... on Post {"));
+
+        let create = schema.types["Mutation"].fields.iter().find(|f| f.name == "createUser").unwrap();
+        let (_, variables) = example_operation(&schema, "mutation", create);
+        assert_eq!(variables, serde_json::json!({ "input": { "name": "example", "role": "ADMIN" } }));
+    }
+
+    #[test]
+    fn introspection_results_unwrap_type_references() {
+        let body = serde_json::json!({
+            "data": { "__schema": {
+                "queryType": { "name": "Query" },
+                "mutationType": null,
+                "subscriptionType": null,
+                "types": [
+                    {
+                        "kind": "OBJECT", "name": "Query", "description": null,
+                        "fields": [{
+                            "name": "tags", "description": "All tags", "args": [],
+                            "type": { "kind": "NON_NULL", "name": null, "ofType": {
+                                "kind": "LIST", "name": null, "ofType": { "kind": "SCALAR", "name": "String", "ofType": null }
+                            }},
+                            "isDeprecated": true, "deprecationReason": "Use labels"
+                        }],
+                        "inputFields": null, "interfaces": [], "enumValues": null, "possibleTypes": null
+                    },
+                    { "kind": "OBJECT", "name": "__Schema", "description": null, "fields": [] }
+                ]
+            }}
+        });
+
+        let schema = GraphQLParser::schema_from_introspection(&body).unwrap();
+        assert_eq!(schema.query_type.as_deref(), Some("Query"));
+        assert!(!schema.types.contains_key("__Schema"));
+
+        let tags = &schema.types["Query"].fields[0];
+        assert_eq!(tags.type_ref.to_sdl(), "[String]!");
+        assert!(tags.deprecated);
+
+        let spec = GraphQLParser::to_specification("Tags", &schema, "https://api.example.com/gql", None, PathBuf::new());
+        let endpoint = endpoint(&spec, "tags");
+        assert!(endpoint.deprecated);
+        assert_eq!(endpoint.description.as_deref(), Some("All tags\n\nDeprecated: Use labels"));
+    }
+
+    #[test]
+    fn disabled_introspection_and_bad_input_are_reported() {
+        let disabled = serde_json::json!({ "errors": [{ "message": "GraphQL introspection is not allowed" }] });
+        assert!(GraphQLParser::introspection_disabled(&disabled));
+        assert!(matches!(
+            GraphQLParser::schema_from_introspection(&disabled),
+            Err(GraphQLParseError::InvalidIntrospection(_))
+        ));
+        assert!(!GraphQLParser::introspection_disabled(&serde_json::json!({ "errors": [{ "message": "timeout" }] })));
+
+        assert!(matches!(GraphQLParser::schema_from_sdl("type Query {"), Err(GraphQLParseError::InvalidSdl(_))));
+    }
+}
@@ -0,0 +1,10 @@
+//! API specification parsers
+//!
+//! Each parser turns a source specification into the crate's `ApiSpecification`
+//! model so every renderer and generator can work on it unchanged.
+
+pub mod graphql;
+pub mod openapi;
+
+pub use graphql::*;
+pub use openapi::*;
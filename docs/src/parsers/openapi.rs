@@ -0,0 +1,859 @@
+//! OpenAPI document parser
+//!
+//! Parses OpenAPI 3.0 and 3.1 documents, in JSON or YAML, into the shared
+//! `ApiSpecification` model. OpenAPI 3.1 schemas are JSON Schema 2020-12; the
+//! keywords 3.0 does not have are mapped as follows:
+//!
+//! - type arrays such as `["string", "null"]` fill `SchemaDefinition::types`,
+//!   with `schema_type` set to the first non-null type (3.0 `nullable: true`
+//!   is mapped the same way)
+//! - `prefixItems` fills `SchemaDefinition::prefix_items`
+//! - `$dynamicRef` becomes `SchemaReference::DynamicReference`
+//! - `examples` supplies the example when `example` is absent
+//!
+//! A 3.1-only construct in a document that declares 3.0 is still mapped, and
+//! reported as a warning-level `ValidationError`.
+
+use crate::{
+    ApiEndpoint, ApiSpecType, ApiSpecification, AuthType, AuthenticationScheme, Discriminator, Encoding,
+    Example, ExternalDocumentation, Header, HttpMethod, Link, MediaType, OAuthFlow, OAuthFlows, Parameter,
+    ParameterLocation, RequestBody, Response, Result, SchemaDefinition, SchemaReference, SchemaType,
+    SecurityRequirement, ServerConfiguration, ServerVariable, ValidationError, ValidationSeverity, XmlObject,
+};
+use chrono::Utc;
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use uuid::Uuid;
+
+const OPERATION_METHODS: [(&str, HttpMethod); 8] = [
+    ("get", HttpMethod::GET),
+    ("put", HttpMethod::PUT),
+    ("post", HttpMethod::POST),
+    ("delete", HttpMethod::DELETE),
+    ("options", HttpMethod::OPTIONS),
+    ("head", HttpMethod::HEAD),
+    ("patch", HttpMethod::PATCH),
+    ("trace", HttpMethod::TRACE),
+];
+
+/// Local `$ref` chains longer than this are treated as cycles
+const MAX_REF_DEPTH: usize = 16;
+
+#[derive(Debug, thiserror::Error)]
+pub enum OpenApiParseError {
+    #[error("Invalid OpenAPI document: {0}")]
+    InvalidDocument(String),
+    #[error("Missing `openapi` version field; is this an OpenAPI document?")]
+    MissingVersion,
+    #[error("Swagger 2.0 documents are not supported; convert the document to OpenAPI 3 first")]
+    Swagger2,
+    #[error("Unsupported OpenAPI version {0}; 3.0 and 3.1 are supported")]
+    UnsupportedVersion(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OpenApiVersion {
+    V3_0,
+    V3_1,
+}
+
+impl OpenApiVersion {
+    fn from_declared(declared: &str) -> std::result::Result<Self, OpenApiParseError> {
+        if declared.starts_with("3.0") {
+            Ok(OpenApiVersion::V3_0)
+        } else if declared.starts_with("3.1") {
+            Ok(OpenApiVersion::V3_1)
+        } else if declared.starts_with('2') {
+            Err(OpenApiParseError::Swagger2)
+        } else {
+            Err(OpenApiParseError::UnsupportedVersion(declared.to_string()))
+        }
+    }
+
+    pub fn spec_type(self) -> ApiSpecType {
+        match self {
+            OpenApiVersion::V3_0 => ApiSpecType::OpenAPI3,
+            OpenApiVersion::V3_1 => ApiSpecType::OpenAPI31,
+        }
+    }
+}
+
+/// A parsed document and the problems found while mapping it
+#[derive(Debug, Clone)]
+pub struct ParsedOpenApi {
+    pub version: OpenApiVersion,
+    pub specification: ApiSpecification,
+    pub warnings: Vec<ValidationError>,
+}
+
+pub struct OpenApiParser;
+
+impl OpenApiParser {
+    /// Parse a JSON or YAML OpenAPI document
+    pub fn parse(content: &str, source_path: PathBuf) -> Result<ParsedOpenApi> {
+        let document = parse_document(content)?;
+
+        let declared = match (document.get("openapi"), document.get("swagger")) {
+            (Some(Value::String(version)), _) => version.clone(),
+            // An unquoted `openapi: 3.1` is read from YAML as a number
+            (Some(Value::Number(version)), _) => version.to_string(),
+            (None, Some(_)) => return Err(OpenApiParseError::Swagger2.into()),
+            _ => return Err(OpenApiParseError::MissingVersion.into()),
+        };
+        let version = OpenApiVersion::from_declared(&declared)?;
+
+        let mut mapper = Mapper { document: &document, version, warnings: Vec::new() };
+        let specification = mapper.specification(source_path);
+        Ok(ParsedOpenApi { version, specification, warnings: mapper.warnings })
+    }
+
+    pub async fn parse_file(path: &str) -> Result<ParsedOpenApi> {
+        let content = tokio::fs::read_to_string(path).await?;
+        Self::parse(&content, PathBuf::from(path))
+    }
+}
+
+/// Parse the document at `spec_path`, logging mapping warnings;
+/// `ApiDocumentationGenerator::parse_openapi_spec` implementations delegate here
+pub async fn parse_openapi_spec(spec_path: &str) -> Result<ApiSpecification> {
+    let parsed = OpenApiParser::parse_file(spec_path).await?;
+    for warning in &parsed.warnings {
+        tracing::warn!("{}: {} at {}", spec_path, warning.message, warning.path);
+    }
+    Ok(parsed.specification)
+}
+
+fn parse_document(content: &str) -> Result<Value> {
+    if content.trim_start().starts_with('{') {
+        return serde_json::from_str(content).map_err(|e| OpenApiParseError::InvalidDocument(e.to_string()).into());
+    }
+    let yaml: serde_yaml::Value =
+        serde_yaml::from_str(content).map_err(|e| OpenApiParseError::InvalidDocument(e.to_string()))?;
+    Ok(yaml_to_json(yaml))
+}
+
+/// YAML allows non-string mapping keys (response codes are often written
+/// unquoted); JSON Schema does not, so keys are stringified
+fn yaml_to_json(value: serde_yaml::Value) -> Value {
+    match value {
+        serde_yaml::Value::Null => Value::Null,
+        serde_yaml::Value::Bool(b) => Value::Bool(b),
+        serde_yaml::Value::Number(n) => {
+            if let Some(i) = n.as_i64() {
+                Value::from(i)
+            } else if let Some(u) = n.as_u64() {
+                Value::from(u)
+            } else {
+                n.as_f64().map(Value::from).unwrap_or(Value::Null)
+            }
+        }
+        serde_yaml::Value::String(s) => Value::String(s),
+        serde_yaml::Value::Sequence(items) => Value::Array(items.into_iter().map(yaml_to_json).collect()),
+        serde_yaml::Value::Mapping(mapping) => Value::Object(
+            mapping
+                .into_iter()
+                .map(|(key, value)| {
+                    let key = match key {
+                        serde_yaml::Value::String(s) => s,
+                        other => match yaml_to_json(other) {
+                            Value::String(s) => s,
+                            other => other.to_string(),
+                        },
+                    };
+                    (key, yaml_to_json(value))
+                })
+                .collect(),
+        ),
+        serde_yaml::Value::Tagged(tagged) => yaml_to_json(tagged.value),
+    }
+}
+
+/// JSON pointer escaping of one path segment
+fn pointer_segment(segment: &str) -> String {
+    segment.replace('~', "~0").replace('/', "~1")
+}
+
+fn str_field(value: &Value, key: &str) -> Option<String> {
+    value.get(key).and_then(Value::as_str).map(str::to_string)
+}
+
+fn bool_field(value: &Value, key: &str) -> bool {
+    value.get(key).and_then(Value::as_bool).unwrap_or(false)
+}
+
+fn object_entries<'a>(value: &'a Value, key: &str) -> impl Iterator<Item = (&'a String, &'a Value)> {
+    value.get(key).and_then(Value::as_object).into_iter().flat_map(Map::iter)
+}
+
+fn schema_type(name: &str) -> Option<SchemaType> {
+    match name {
+        "string" => Some(SchemaType::String),
+        "number" => Some(SchemaType::Number),
+        "integer" => Some(SchemaType::Integer),
+        "boolean" => Some(SchemaType::Boolean),
+        "array" => Some(SchemaType::Array),
+        "object" => Some(SchemaType::Object),
+        "null" => Some(SchemaType::Null),
+        _ => None,
+    }
+}
+
+struct Mapper<'a> {
+    document: &'a Value,
+    version: OpenApiVersion,
+    warnings: Vec<ValidationError>,
+}
+
+impl<'a> Mapper<'a> {
+    fn specification(&mut self, source_path: PathBuf) -> ApiSpecification {
+        let document = self.document;
+        let info = document.get("info").cloned().unwrap_or(Value::Null);
+
+        let authentication: Vec<AuthenticationScheme> = document
+            .pointer("/components/securitySchemes")
+            .and_then(Value::as_object)
+            .into_iter()
+            .flat_map(Map::iter)
+            .map(|(key, scheme)| self.security_scheme(key, self.resolve(scheme)))
+            .collect();
+        let scheme_names: HashMap<String, String> = document
+            .pointer("/components/securitySchemes")
+            .and_then(Value::as_object)
+            .into_iter()
+            .flat_map(Map::iter)
+            .zip(&authentication)
+            .map(|((key, _), scheme)| (key.clone(), scheme.name.clone()))
+            .collect();
+        let default_security = document.get("security").map(|security| security_requirements(security, &scheme_names));
+
+        let mut schema_names: Vec<&String> = document
+            .pointer("/components/schemas")
+            .and_then(Value::as_object)
+            .map(|schemas| schemas.keys().collect())
+            .unwrap_or_default();
+        schema_names.sort();
+        let schemas = schema_names
+            .into_iter()
+            .map(|name| {
+                let path = format!("#/components/schemas/{}", pointer_segment(name));
+                self.definition(name, &document["components"]["schemas"][name], &path)
+            })
+            .collect();
+
+        let mut endpoints = Vec::new();
+        for (path, item) in object_entries(document, "paths") {
+            let item = self.resolve(item);
+            let item_path = format!("#/paths/{}", pointer_segment(path));
+            for (method_name, method) in OPERATION_METHODS {
+                if let Some(operation) = item.get(method_name) {
+                    let operation_path = format!("{}/{}", item_path, method_name);
+                    endpoints.push(self.endpoint(
+                        path,
+                        method,
+                        item,
+                        operation,
+                        &operation_path,
+                        default_security.as_deref(),
+                        &scheme_names,
+                    ));
+                }
+            }
+        }
+
+        let mut tags: Vec<String> = document
+            .get("tags")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+            .filter_map(|tag| str_field(tag, "name"))
+            .collect();
+        for tag in endpoints.iter().flat_map(|endpoint| &endpoint.tags) {
+            if !tags.contains(tag) {
+                tags.push(tag.clone());
+            }
+        }
+
+        ApiSpecification {
+            id: Uuid::new_v4(),
+            name: str_field(&info, "title").unwrap_or_else(|| "API".to_string()),
+            version: str_field(&info, "version").unwrap_or_default(),
+            spec_type: self.version.spec_type(),
+            source_path,
+            endpoints,
+            schemas,
+            authentication,
+            servers: document
+                .get("servers")
+                .and_then(Value::as_array)
+                .into_iter()
+                .flatten()
+                .map(server)
+                .collect(),
+            tags,
+            external_docs: document.get("externalDocs").map(external_docs),
+            auto_generated: false,
+            last_updated: Utc::now(),
+        }
+    }
+
+    /// Follow local `$ref`s (`#/components/...`) to the referenced object
+    fn resolve(&self, mut value: &'a Value) -> &'a Value {
+        for _ in 0..MAX_REF_DEPTH {
+            let Some(reference) = value.get("$ref").and_then(Value::as_str) else { break };
+            match reference.strip_prefix('#').and_then(|pointer| self.document.pointer(pointer)) {
+                Some(target) => value = target,
+                None => break,
+            }
+        }
+        value
+    }
+
+    fn warn_3_1_only(&mut self, construct: &str, path: &str) {
+        if self.version == OpenApiVersion::V3_0 {
+            self.warnings.push(ValidationError {
+                message: format!("{} is only valid in OpenAPI 3.1, but the document declares 3.0", construct),
+                path: path.to_string(),
+                severity: ValidationSeverity::Warning,
+                suggestion: Some("Declare `openapi: 3.1.0`, or use the OpenAPI 3.0 equivalent".to_string()),
+            });
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn endpoint(
+        &mut self,
+        path: &str,
+        method: HttpMethod,
+        item: &'a Value,
+        operation: &'a Value,
+        operation_path: &str,
+        default_security: Option<&[SecurityRequirement]>,
+        scheme_names: &HashMap<String, String>,
+    ) -> ApiEndpoint {
+        // Operation parameters override path-level ones with the same name and location
+        let mut parameters: Vec<Parameter> = Vec::new();
+        for (source, key) in [(operation, "parameters"), (item, "parameters")] {
+            for (index, parameter) in source.get(key).and_then(Value::as_array).into_iter().flatten().enumerate() {
+                let parameter = self.resolve(parameter);
+                let parameter_path = format!("{}/parameters/{}", operation_path, index);
+                let parameter = self.parameter(parameter, &parameter_path);
+                let overridden = parameters.iter().any(|existing| {
+                    existing.name == parameter.name
+                        && std::mem::discriminant(&existing.location) == std::mem::discriminant(&parameter.location)
+                });
+                if !overridden {
+                    parameters.push(parameter);
+                }
+            }
+        }
+
+        let request_body = operation.get("requestBody").map(|body| {
+            let body = self.resolve(body);
+            RequestBody {
+                description: str_field(body, "description"),
+                content: self.content(body, &format!("{}/requestBody", operation_path)),
+                required: bool_field(body, "required"),
+            }
+        });
+
+        let mut responses = HashMap::new();
+        for (status, response) in object_entries(operation, "responses") {
+            let response = self.resolve(response);
+            let response_path = format!("{}/responses/{}", operation_path, pointer_segment(status));
+            responses.insert(status.clone(), self.response(response, &response_path));
+        }
+
+        let security = match operation.get("security") {
+            Some(security) => security_requirements(security, scheme_names),
+            None => default_security.map(<[SecurityRequirement]>::to_vec).unwrap_or_default(),
+        };
+
+        let mut examples = Vec::new();
+        if let Some(media) = request_body.as_ref().and_then(|body| body.content.values().next()) {
+            examples.extend(media.examples.values().cloned());
+        }
+
+        ApiEndpoint {
+            path: path.to_string(),
+            method,
+            operation_id: str_field(operation, "operationId"),
+            summary: str_field(operation, "summary").unwrap_or_default(),
+            description: str_field(operation, "description"),
+            parameters,
+            request_body,
+            responses,
+            security,
+            tags: operation
+                .get("tags")
+                .and_then(Value::as_array)
+                .into_iter()
+                .flatten()
+                .filter_map(|tag| tag.as_str().map(str::to_string))
+                .collect(),
+            deprecated: bool_field(operation, "deprecated"),
+            examples,
+        }
+    }
+
+    fn parameter(&mut self, parameter: &'a Value, path: &str) -> Parameter {
+        let location = match parameter.get("in").and_then(Value::as_str) {
+            Some("header") => ParameterLocation::Header,
+            Some("path") => ParameterLocation::Path,
+            Some("cookie") => ParameterLocation::Cookie,
+            _ => ParameterLocation::Query,
+        };
+        let name = str_field(parameter, "name").unwrap_or_default();
+
+        Parameter {
+            required: bool_field(parameter, "required") || matches!(location, ParameterLocation::Path),
+            schema: self.parameter_schema(&name, parameter, path),
+            example: parameter.get("example").cloned(),
+            description: str_field(parameter, "description"),
+            deprecated: bool_field(parameter, "deprecated"),
+            location,
+            name,
+        }
+    }
+
+    /// A parameter or header's `schema`, or the schema of its single `content` entry
+    fn parameter_schema(&mut self, name: &str, value: &'a Value, path: &str) -> SchemaReference {
+        if let Some(schema) = value.get("schema") {
+            return self.reference(name, schema, &format!("{}/schema", path));
+        }
+        if let Some((media_type, media)) = object_entries(value, "content").next() {
+            if let Some(schema) = media.get("schema") {
+                let schema_path = format!("{}/content/{}/schema", path, pointer_segment(media_type));
+                return self.reference(name, schema, &schema_path);
+            }
+        }
+        self.reference(name, &Value::Object(Map::new()), path)
+    }
+
+    fn response(&mut self, response: &'a Value, path: &str) -> Response {
+        let mut headers = HashMap::new();
+        for (name, header) in object_entries(response, "headers") {
+            let header_path = format!("{}/headers/{}", path, pointer_segment(name));
+            headers.insert(name.clone(), self.header(name, self.resolve(header), &header_path));
+        }
+
+        let links = object_entries(response, "links")
+            .map(|(name, link)| {
+                let link = self.resolve(link);
+                (
+                    name.clone(),
+                    Link {
+                        operation_ref: str_field(link, "operationRef"),
+                        operation_id: str_field(link, "operationId"),
+                        parameters: link
+                            .get("parameters")
+                            .and_then(Value::as_object)
+                            .map(|parameters| parameters.iter().map(|(k, v)| (k.clone(), v.clone())).collect())
+                            .unwrap_or_default(),
+                        request_body: link.get("requestBody").cloned(),
+                        description: str_field(link, "description"),
+                    },
+                )
+            })
+            .collect();
+
+        Response {
+            description: str_field(response, "description").unwrap_or_default(),
+            headers,
+            content: self.content(response, path),
+            links,
+        }
+    }
+
+    fn header(&mut self, name: &str, header: &'a Value, path: &str) -> Header {
+        Header {
+            description: str_field(header, "description"),
+            required: bool_field(header, "required"),
+            deprecated: bool_field(header, "deprecated"),
+            schema: self.parameter_schema(name, header, path),
+        }
+    }
+
+    fn content(&mut self, container: &'a Value, path: &str) -> HashMap<String, MediaType> {
+        let mut content = HashMap::new();
+        for (media_type, media) in object_entries(container, "content") {
+            let media_path = format!("{}/content/{}", path, pointer_segment(media_type));
+
+            let mut encoding = HashMap::new();
+            for (property, property_encoding) in object_entries(media, "encoding") {
+                let mut headers = HashMap::new();
+                for (name, header) in object_entries(property_encoding, "headers") {
+                    let header_path = format!("{}/encoding/{}/headers/{}", media_path, pointer_segment(property), pointer_segment(name));
+                    headers.insert(name.clone(), self.header(name, self.resolve(header), &header_path));
+                }
+                encoding.insert(
+                    property.clone(),
+                    Encoding {
+                        content_type: str_field(property_encoding, "contentType"),
+                        headers,
+                        style: str_field(property_encoding, "style"),
+                        explode: property_encoding.get("explode").and_then(Value::as_bool),
+                        allow_reserved: property_encoding.get("allowReserved").and_then(Value::as_bool),
+                    },
+                );
+            }
+
+            content.insert(
+                media_type.clone(),
+                MediaType {
+                    schema: media
+                        .get("schema")
+                        .map(|schema| self.reference(media_type, schema, &format!("{}/schema", media_path))),
+                    example: media.get("example").cloned(),
+                    examples: object_entries(media, "examples")
+                        .map(|(name, example)| (name.clone(), self.example(name, self.resolve(example))))
+                        .collect(),
+                    encoding,
+                },
+            );
+        }
+        content
+    }
+
+    fn example(&self, name: &str, example: &Value) -> Example {
+        Example {
+            name: name.to_string(),
+            summary: str_field(example, "summary"),
+            description: str_field(example, "description"),
+            value: example.get("value").cloned().unwrap_or(Value::Null),
+            external_value: str_field(example, "externalValue"),
+        }
+    }
+
+    fn security_scheme(&self, key: &str, scheme: &Value) -> AuthenticationScheme {
+        let scheme_type = match scheme.get("type").and_then(Value::as_str) {
+            Some("apiKey") => AuthType::ApiKey,
+            Some("oauth2") => AuthType::OAuth2,
+            Some("openIdConnect") => AuthType::OpenIdConnect,
+            _ => AuthType::Http,
+        };
+
+        AuthenticationScheme {
+            // API keys are placed on requests under their parameter name
+            name: match scheme_type {
+                AuthType::ApiKey => str_field(scheme, "name").unwrap_or_else(|| key.to_string()),
+                _ => key.to_string(),
+            },
+            location: str_field(scheme, "in"),
+            scheme: str_field(scheme, "scheme"),
+            bearer_format: str_field(scheme, "bearerFormat"),
+            flows: scheme.get("flows").map(|flows| OAuthFlows {
+                implicit: flows.get("implicit").map(oauth_flow),
+                password: flows.get("password").map(oauth_flow),
+                client_credentials: flows.get("clientCredentials").map(oauth_flow),
+                authorization_code: flows.get("authorizationCode").map(oauth_flow),
+            }),
+            open_id_connect_url: str_field(scheme, "openIdConnectUrl"),
+            description: str_field(scheme, "description"),
+            scheme_type,
+        }
+    }
+
+    fn reference(&mut self, name: &str, schema: &'a Value, path: &str) -> SchemaReference {
+        if let Some(reference) = schema.get("$ref").and_then(Value::as_str) {
+            return SchemaReference::Reference(reference.to_string());
+        }
+        if let Some(reference) = schema.get("$dynamicRef").and_then(Value::as_str) {
+            self.warn_3_1_only("`$dynamicRef`", &format!("{}/$dynamicRef", path));
+            return SchemaReference::DynamicReference(reference.to_string());
+        }
+        SchemaReference::Inline(Box::new(self.definition(name, schema, path)))
+    }
+
+    fn definition(&mut self, name: &str, schema: &'a Value, path: &str) -> SchemaDefinition {
+        let mut types: Vec<SchemaType> = match schema.get("type") {
+            Some(Value::String(type_name)) => schema_type(type_name).into_iter().collect(),
+            Some(Value::Array(names)) => {
+                self.warn_3_1_only("A type array", &format!("{}/type", path));
+                names.iter().filter_map(Value::as_str).filter_map(schema_type).collect()
+            }
+            _ => Vec::new(),
+        };
+        let primary = types
+            .iter()
+            .copied()
+            .find(|schema_type| *schema_type != SchemaType::Null)
+            .or_else(|| types.first().copied())
+            .unwrap_or_else(|| inferred_type(schema));
+        if bool_field(schema, "nullable") {
+            if types.is_empty() {
+                types.push(primary);
+            }
+            if !types.contains(&SchemaType::Null) {
+                types.push(SchemaType::Null);
+            }
+        }
+        if types.len() < 2 {
+            types.clear();
+        }
+
+        let mut properties = HashMap::new();
+        for (property, property_schema) in object_entries(schema, "properties") {
+            let property_path = format!("{}/properties/{}", path, pointer_segment(property));
+            properties.insert(property.clone(), self.reference(property, property_schema, &property_path));
+        }
+
+        let additional_properties = match schema.get("additionalProperties") {
+            Some(additional @ Value::Object(_)) => Some(Box::new(self.reference(
+                name,
+                additional,
+                &format!("{}/additionalProperties", path),
+            ))),
+            _ => None,
+        };
+
+        // In 3.1, `items: false` alongside `prefixItems` closes the tuple
+        let items = match schema.get("items") {
+            Some(items @ Value::Object(_)) => Some(Box::new(self.reference(name, items, &format!("{}/items", path)))),
+            _ => None,
+        };
+
+        let mut prefix_items = Vec::new();
+        if let Some(prefix) = schema.get("prefixItems").and_then(Value::as_array) {
+            self.warn_3_1_only("`prefixItems`", &format!("{}/prefixItems", path));
+            for (index, item) in prefix.iter().enumerate() {
+                prefix_items.push(self.reference(name, item, &format!("{}/prefixItems/{}", path, index)));
+            }
+        }
+
+        let enum_values = match (schema.get("enum"), schema.get("const")) {
+            (Some(Value::Array(values)), _) => Some(values.clone()),
+            (None, Some(value)) => Some(vec![value.clone()]),
+            _ => None,
+        };
+
+        let example = schema.get("example").cloned().or_else(|| {
+            schema
+                .get("examples")
+                .and_then(Value::as_array)
+                .and_then(|examples| examples.first())
+                .cloned()
+        });
+
+        SchemaDefinition {
+            name: name.to_string(),
+            schema_type: primary,
+            format: str_field(schema, "format"),
+            description: str_field(schema, "description"),
+            example,
+            properties,
+            required: schema
+                .get("required")
+                .and_then(Value::as_array)
+                .into_iter()
+                .flatten()
+                .filter_map(|field| field.as_str().map(str::to_string))
+                .collect(),
+            additional_properties,
+            items,
+            prefix_items,
+            types,
+            enum_values,
+            discriminator: schema.get("discriminator").map(|discriminator| Discriminator {
+                property_name: str_field(discriminator, "propertyName").unwrap_or_default(),
+                mapping: discriminator
+                    .get("mapping")
+                    .and_then(Value::as_object)
+                    .map(|mapping| {
+                        mapping
+                            .iter()
+                            .filter_map(|(key, target)| target.as_str().map(|target| (key.clone(), target.to_string())))
+                            .collect()
+                    })
+                    .unwrap_or_default(),
+            }),
+            xml: schema.get("xml").map(|xml| XmlObject {
+                name: str_field(xml, "name"),
+                namespace: str_field(xml, "namespace"),
+                prefix: str_field(xml, "prefix"),
+                attribute: xml.get("attribute").and_then(Value::as_bool),
+                wrapped: xml.get("wrapped").and_then(Value::as_bool),
+            }),
+            external_docs: schema.get("externalDocs").map(external_docs),
+        }
+    }
+}
+
+/// The type of a schema without `type`, from the keywords it uses
+fn inferred_type(schema: &Value) -> SchemaType {
+    if schema.get("items").is_some() || schema.get("prefixItems").is_some() {
+        return SchemaType::Array;
+    }
+    let first_value = schema
+        .get("enum")
+        .and_then(Value::as_array)
+        .and_then(|values| values.first())
+        .or_else(|| schema.get("const"));
+    match first_value {
+        Some(Value::String(_)) => SchemaType::String,
+        Some(Value::Bool(_)) => SchemaType::Boolean,
+        Some(Value::Number(n)) if n.is_f64() => SchemaType::Number,
+        Some(Value::Number(_)) => SchemaType::Integer,
+        _ => SchemaType::Object,
+    }
+}
+
+fn security_requirements(security: &Value, scheme_names: &HashMap<String, String>) -> Vec<SecurityRequirement> {
+    security
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_object)
+        .flat_map(Map::iter)
+        .map(|(key, scopes)| SecurityRequirement {
+            scheme_name: scheme_names.get(key).cloned().unwrap_or_else(|| key.clone()),
+            scopes: scopes
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter_map(|scope| scope.as_str().map(str::to_string))
+                .collect(),
+        })
+        .collect()
+}
+
+fn oauth_flow(flow: &Value) -> OAuthFlow {
+    OAuthFlow {
+        authorization_url: str_field(flow, "authorizationUrl"),
+        token_url: str_field(flow, "tokenUrl"),
+        refresh_url: str_field(flow, "refreshUrl"),
+        scopes: flow
+            .get("scopes")
+            .and_then(Value::as_object)
+            .map(|scopes| {
+                scopes
+                    .iter()
+                    .map(|(scope, description)| (scope.clone(), description.as_str().unwrap_or_default().to_string()))
+                    .collect()
+            })
+            .unwrap_or_default(),
+    }
+}
+
+fn server(server: &Value) -> ServerConfiguration {
+    ServerConfiguration {
+        url: str_field(server, "url").unwrap_or_else(|| "/".to_string()),
+        description: str_field(server, "description"),
+        variables: object_entries(server, "variables")
+            .map(|(name, variable)| {
+                (
+                    name.clone(),
+                    ServerVariable {
+                        enum_values: variable.get("enum").and_then(Value::as_array).map(|values| {
+                            values.iter().filter_map(|v| v.as_str().map(str::to_string)).collect()
+                        }),
+                        default: str_field(variable, "default").unwrap_or_default(),
+                        description: str_field(variable, "description"),
+                    },
+                )
+            })
+            .collect(),
+    }
+}
+
+fn external_docs(docs: &Value) -> ExternalDocumentation {
+    ExternalDocumentation {
+        description: str_field(docs, "description"),
+        url: str_field(docs, "url").unwrap_or_default(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NULLABLE_3_1: &str = include_str!("fixtures/openapi_3_1_nullable.yaml");
+
+    fn schema<'s>(spec: &'s ApiSpecification, name: &str) -> &'s SchemaDefinition {
+        spec.schemas.iter().find(|schema| schema.name == name).unwrap()
+    }
+
+    fn inline<'s>(reference: &'s SchemaReference) -> &'s SchemaDefinition {
+        match reference {
+            SchemaReference::Inline(definition) => definition,
+            other => panic!("expected an inline schema, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_type_array_parses_with_null_type() {
+        let parsed = OpenApiParser::parse(NULLABLE_3_1, PathBuf::from("pets.yaml")).unwrap();
+        assert_eq!(parsed.version, OpenApiVersion::V3_1);
+        assert!(matches!(parsed.specification.spec_type, ApiSpecType::OpenAPI31));
+        assert!(parsed.warnings.is_empty(), "{:?}", parsed.warnings);
+
+        let pet = schema(&parsed.specification, "Pet");
+        let nickname = inline(&pet.properties["nickname"]);
+        assert_eq!(nickname.schema_type, SchemaType::String);
+        assert_eq!(nickname.types, vec![SchemaType::String, SchemaType::Null]);
+        assert!(inline(&pet.properties["name"]).types.is_empty());
+    }
+
+    #[test]
+    fn test_json_schema_2020_12_keywords_are_mapped() {
+        let parsed = OpenApiParser::parse(NULLABLE_3_1, PathBuf::from("pets.yaml")).unwrap();
+        let spec = &parsed.specification;
+
+        let location = inline(&schema(spec, "Pet").properties["location"]);
+        assert_eq!(location.schema_type, SchemaType::Array);
+        assert_eq!(location.prefix_items.len(), 2);
+        assert_eq!(inline(&location.prefix_items[0]).schema_type, SchemaType::Number);
+        assert!(location.items.is_none());
+
+        let children = inline(&schema(spec, "Tree").properties["children"]);
+        assert!(matches!(
+            children.items.as_deref(),
+            Some(SchemaReference::DynamicReference(anchor)) if anchor == "#node"
+        ));
+
+        let endpoint = &spec.endpoints[0];
+        assert_eq!(endpoint.path, "/pets/{petId}");
+        assert_eq!(endpoint.parameters[0].name, "petId");
+        assert!(endpoint.parameters[0].required);
+        assert!(matches!(
+            endpoint.responses["200"].content["application/json"].schema,
+            Some(SchemaReference::Reference(ref target)) if target == "#/components/schemas/Pet"
+        ));
+    }
+
+    #[test]
+    fn test_3_1_constructs_under_3_0_are_warnings() {
+        let declared_3_0 = NULLABLE_3_1.replace("openapi: 3.1.0", "openapi: 3.0.3");
+        let parsed = OpenApiParser::parse(&declared_3_0, PathBuf::from("pets.yaml")).unwrap();
+        assert_eq!(parsed.version, OpenApiVersion::V3_0);
+
+        let paths: Vec<&str> = parsed.warnings.iter().map(|warning| warning.path.as_str()).collect();
+        assert!(paths.contains(&"#/components/schemas/Pet/properties/nickname/type"), "{:?}", paths);
+        assert!(paths.contains(&"#/components/schemas/Pet/properties/location/prefixItems"));
+        assert!(paths.contains(&"#/components/schemas/Tree/properties/children/items/$dynamicRef"));
+        assert!(parsed.warnings.iter().all(|warning| matches!(warning.severity, ValidationSeverity::Warning)));
+
+        // The constructs are still mapped
+        let nickname = inline(&schema(&parsed.specification, "Pet").properties["nickname"]);
+        assert!(nickname.types.contains(&SchemaType::Null));
+    }
+
+    #[test]
+    fn test_3_0_nullable_maps_to_null_type() {
+        let document = r#"{
+            "openapi": "3.0.3",
+            "info": { "title": "Pets", "version": "1.0.0" },
+            "paths": {},
+            "components": { "schemas": { "Tag": { "type": "string", "nullable": true } } }
+        }"#;
+        let parsed = OpenApiParser::parse(document, PathBuf::new()).unwrap();
+        assert!(parsed.warnings.is_empty());
+        assert_eq!(schema(&parsed.specification, "Tag").types, vec![SchemaType::String, SchemaType::Null]);
+    }
+
+    #[test]
+    fn test_swagger_2_is_rejected() {
+        let error = OpenApiParser::parse("swagger: '2.0'\ninfo: {title: Pets, version: '1'}", PathBuf::new()).unwrap_err();
+        assert!(error.to_string().contains("Swagger 2.0"));
+    }
+}
@@ -0,0 +1,643 @@
+//! Rendered page cache with stale-while-revalidate
+//!
+//! Pages are served straight from the cache while fresh. Once a page's TTL has
+//! elapsed, or an `InvalidationRule` marked it stale, the cached copy is still
+//! served immediately and a single background render refreshes it. A failed
+//! render never replaces a cached page, so a broken edit keeps serving the last
+//! good version until it is fixed.
+//!
+//! Every invalidation bumps the page's generation. A render only stores its
+//! result if the generation it started under is still current, so a
+//! revalidation that was already running when the page was invalidated cannot
+//! overwrite the invalidated entry with content rendered from the old source.
+//!
+//! `ContentUpdate` invalidation is scoped to the changed page and the pages that
+//! cross-reference it; the other triggers use the rule's `InvalidationScope`.
+
+use crate::{CachingConfiguration, CachingStrategy, InvalidationRule, InvalidationScope, InvalidationTrigger, Result};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use tokio::sync::{Mutex, RwLock};
+
+/// Output of a single page render
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RenderedPage {
+    pub html: String,
+    /// Pages this page links to through cross-references
+    pub references: Vec<String>,
+    pub section: Option<String>,
+    pub tags: Vec<String>,
+    pub version: Option<String>,
+}
+
+#[async_trait::async_trait]
+pub trait PageRenderer: Send + Sync {
+    async fn render(&self, page: &str) -> Result<RenderedPage>;
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CachedPage {
+    pub page: String,
+    pub rendered: RenderedPage,
+    pub rendered_at: DateTime<Utc>,
+    /// Set by invalidation; the page is served but re-rendered on next access
+    pub invalidated: bool,
+}
+
+#[async_trait::async_trait]
+pub trait CacheBackend: Send + Sync {
+    async fn get(&self, page: &str) -> Result<Option<CachedPage>>;
+    async fn put(&self, entry: &CachedPage) -> Result<()>;
+    async fn remove(&self, page: &str) -> Result<()>;
+}
+
+#[derive(Default)]
+pub struct MemoryCacheBackend {
+    entries: RwLock<HashMap<String, CachedPage>>,
+}
+
+#[async_trait::async_trait]
+impl CacheBackend for MemoryCacheBackend {
+    async fn get(&self, page: &str) -> Result<Option<CachedPage>> {
+        Ok(self.entries.read().await.get(page).cloned())
+    }
+
+    async fn put(&self, entry: &CachedPage) -> Result<()> {
+        self.entries.write().await.insert(entry.page.clone(), entry.clone());
+        Ok(())
+    }
+
+    async fn remove(&self, page: &str) -> Result<()> {
+        self.entries.write().await.remove(page);
+        Ok(())
+    }
+}
+
+/// One JSON file per page under `root`
+pub struct FileCacheBackend {
+    root: PathBuf,
+}
+
+impl FileCacheBackend {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    fn entry_path(&self, page: &str) -> PathBuf {
+        // Page paths contain separators; hex-encode them into a flat, collision-free file name
+        let name: String = page.bytes().map(|b| format!("{:02x}", b)).collect();
+        self.root.join(format!("{}.json", name))
+    }
+}
+
+#[async_trait::async_trait]
+impl CacheBackend for FileCacheBackend {
+    async fn get(&self, page: &str) -> Result<Option<CachedPage>> {
+        match tokio::fs::read(self.entry_path(page)).await {
+            Ok(bytes) => Ok(serde_json::from_slice(&bytes).ok()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn put(&self, entry: &CachedPage) -> Result<()> {
+        tokio::fs::create_dir_all(&self.root).await?;
+        // Write to a temporary file first so readers never see a half-written entry
+        let path = self.entry_path(&entry.page);
+        let tmp = path.with_extension("json.tmp");
+        tokio::fs::write(&tmp, serde_json::to_vec(entry)?).await?;
+        tokio::fs::rename(&tmp, &path).await?;
+        Ok(())
+    }
+
+    async fn remove(&self, page: &str) -> Result<()> {
+        match tokio::fs::remove_file(self.entry_path(page)).await {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+}
+
+pub struct RedisCacheBackend {
+    client: redis::Client,
+    prefix: String,
+}
+
+impl RedisCacheBackend {
+    pub fn new(url: &str, prefix: &str) -> Result<Self> {
+        Ok(Self { client: redis::Client::open(url)?, prefix: prefix.to_string() })
+    }
+
+    fn key(&self, page: &str) -> String {
+        format!("{}:page:{}", self.prefix, page)
+    }
+}
+
+#[async_trait::async_trait]
+impl CacheBackend for RedisCacheBackend {
+    async fn get(&self, page: &str) -> Result<Option<CachedPage>> {
+        let mut conn = self.client.get_multiplexed_async_connection().await?;
+        let raw: Option<String> = redis::cmd("GET").arg(self.key(page)).query_async(&mut conn).await?;
+        Ok(raw.and_then(|raw| serde_json::from_str(&raw).ok()))
+    }
+
+    async fn put(&self, entry: &CachedPage) -> Result<()> {
+        let mut conn = self.client.get_multiplexed_async_connection().await?;
+        redis::cmd("SET")
+            .arg(self.key(&entry.page))
+            .arg(serde_json::to_string(entry)?)
+            .query_async::<_, ()>(&mut conn)
+            .await?;
+        Ok(())
+    }
+
+    async fn remove(&self, page: &str) -> Result<()> {
+        let mut conn = self.client.get_multiplexed_async_connection().await?;
+        redis::cmd("DEL").arg(self.key(page)).query_async::<_, ()>(&mut conn).await?;
+        Ok(())
+    }
+}
+
+/// Where a cache backend keeps its data
+#[derive(Debug, Clone, Default)]
+pub struct CacheBackendOptions {
+    pub redis_url: Option<String>,
+    pub redis_prefix: Option<String>,
+    pub file_root: Option<PathBuf>,
+}
+
+pub fn backend_for(strategy: &CachingStrategy, options: &CacheBackendOptions) -> Result<Arc<dyn CacheBackend>> {
+    Ok(match strategy {
+        CachingStrategy::Redis => {
+            let url = options.redis_url.as_deref().ok_or("Redis caching requires a redis_url")?;
+            Arc::new(RedisCacheBackend::new(url, options.redis_prefix.as_deref().unwrap_or("aion-docs"))?)
+        }
+        CachingStrategy::File => {
+            let root = options.file_root.clone().ok_or("File caching requires a file_root")?;
+            Arc::new(FileCacheBackend::new(root))
+        }
+        // CDN caching happens in front of the portal via `cache_headers`; the origin still keeps a memory copy
+        CachingStrategy::Memory | CachingStrategy::CDN | CachingStrategy::Hybrid => Arc::new(MemoryCacheBackend::default()),
+    })
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub stale_hits: u64,
+    pub misses: u64,
+    pub revalidations: u64,
+    pub render_errors: u64,
+    /// Share of requests served from cache, fresh or stale
+    pub hit_rate: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CacheOutcome {
+    Fresh,
+    Stale,
+    Miss,
+}
+
+#[derive(Debug, Clone)]
+pub struct CacheResponse {
+    pub page: RenderedPage,
+    pub outcome: CacheOutcome,
+    pub headers: HashMap<String, String>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct PageIndex {
+    /// page -> pages that cross-reference it
+    referrers: HashMap<String, HashSet<String>>,
+    /// page -> pages it references, kept to unlink stale edges on re-render
+    references: HashMap<String, Vec<String>>,
+    section: HashMap<String, String>,
+    tags: HashMap<String, Vec<String>>,
+    version: HashMap<String, String>,
+}
+
+impl PageIndex {
+    fn record(&mut self, page: &str, rendered: &RenderedPage) {
+        if let Some(previous) = self.references.remove(page) {
+            for target in previous {
+                if let Some(referrers) = self.referrers.get_mut(&target) {
+                    referrers.remove(page);
+                }
+            }
+        }
+        for target in &rendered.references {
+            self.referrers.entry(target.clone()).or_default().insert(page.to_string());
+        }
+        self.references.insert(page.to_string(), rendered.references.clone());
+
+        match &rendered.section {
+            Some(section) => self.section.insert(page.to_string(), section.clone()),
+            None => self.section.remove(page),
+        };
+        self.tags.insert(page.to_string(), rendered.tags.clone());
+        match &rendered.version {
+            Some(version) => self.version.insert(page.to_string(), version.clone()),
+            None => self.version.remove(page),
+        };
+    }
+
+    fn pages(&self) -> impl Iterator<Item = &String> {
+        self.references.keys()
+    }
+
+    fn matches(&self, page: &str, scope: &InvalidationScope) -> bool {
+        match scope {
+            InvalidationScope::All => true,
+            InvalidationScope::Page(pattern) => match pattern.strip_suffix('*') {
+                Some(prefix) => page.starts_with(prefix),
+                None => page == pattern,
+            },
+            InvalidationScope::Section(section) => {
+                self.section.get(page) == Some(section) || page.starts_with(&format!("{}/", section.trim_end_matches('/')))
+            }
+            InvalidationScope::Tag(tag) => self.tags.get(page).map(|tags| tags.contains(tag)).unwrap_or(false),
+            InvalidationScope::Version(version) => self.version.get(page) == Some(version),
+        }
+    }
+}
+
+pub struct RenderCache {
+    config: CachingConfiguration,
+    backend: Arc<dyn CacheBackend>,
+    renderer: Arc<dyn PageRenderer>,
+    index: RwLock<PageIndex>,
+    revalidating: Mutex<HashSet<String>>,
+    /// page -> invalidation generation; held while storing so a store and an invalidation never interleave
+    generations: Mutex<HashMap<String, u64>>,
+    hits: AtomicU64,
+    stale_hits: AtomicU64,
+    misses: AtomicU64,
+    revalidations: AtomicU64,
+    render_errors: AtomicU64,
+}
+
+impl RenderCache {
+    pub fn new(config: CachingConfiguration, backend: Arc<dyn CacheBackend>, renderer: Arc<dyn PageRenderer>) -> Arc<Self> {
+        Arc::new(Self {
+            config,
+            backend,
+            renderer,
+            index: RwLock::new(PageIndex::default()),
+            revalidating: Mutex::new(HashSet::new()),
+            generations: Mutex::new(HashMap::new()),
+            hits: AtomicU64::new(0),
+            stale_hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+            revalidations: AtomicU64::new(0),
+            render_errors: AtomicU64::new(0),
+        })
+    }
+
+    pub fn from_config(
+        config: CachingConfiguration,
+        options: &CacheBackendOptions,
+        renderer: Arc<dyn PageRenderer>,
+    ) -> Result<Arc<Self>> {
+        let backend = backend_for(&config.strategy, options)?;
+        Ok(Self::new(config, backend, renderer))
+    }
+
+    /// Serve a page, rendering it on a miss and revalidating in the background when stale
+    pub async fn get(self: &Arc<Self>, page: &str) -> Result<CacheResponse> {
+        if !self.config.enabled {
+            let rendered = self.renderer.render(page).await?;
+            return Ok(self.response(rendered, CacheOutcome::Miss));
+        }
+
+        let cached = match self.backend.get(page).await {
+            Ok(cached) => cached,
+            Err(e) => {
+                // A backend outage degrades to rendering, it doesn't take the docs down
+                tracing::warn!("Docs cache read failed for {}: {}", page, e);
+                None
+            }
+        };
+
+        match cached {
+            Some(entry) if !self.is_stale(&entry) => {
+                self.hits.fetch_add(1, Ordering::Relaxed);
+                self.index.write().await.record(page, &entry.rendered);
+                Ok(self.response(entry.rendered, CacheOutcome::Fresh))
+            }
+            Some(entry) => {
+                self.stale_hits.fetch_add(1, Ordering::Relaxed);
+                self.index.write().await.record(page, &entry.rendered);
+                self.spawn_revalidation(page.to_string());
+                Ok(self.response(entry.rendered, CacheOutcome::Stale))
+            }
+            None => {
+                self.misses.fetch_add(1, Ordering::Relaxed);
+                let rendered = self.render_and_store(page).await?;
+                Ok(self.response(rendered, CacheOutcome::Miss))
+            }
+        }
+    }
+
+    fn is_stale(&self, entry: &CachedPage) -> bool {
+        entry.invalidated || Utc::now() - entry.rendered_at >= Duration::seconds(self.config.ttl_seconds as i64)
+    }
+
+    fn response(&self, page: RenderedPage, outcome: CacheOutcome) -> CacheResponse {
+        let mut headers = self.config.cache_headers.clone();
+        headers.entry("Cache-Control".to_string()).or_insert_with(|| {
+            format!("public, max-age={}, stale-while-revalidate={}", self.config.ttl_seconds, self.config.ttl_seconds)
+        });
+        headers.insert(
+            "X-Cache".to_string(),
+            match outcome {
+                CacheOutcome::Fresh => "HIT",
+                CacheOutcome::Stale => "STALE",
+                CacheOutcome::Miss => "MISS",
+            }
+            .to_string(),
+        );
+        CacheResponse { page, outcome, headers }
+    }
+
+    /// Render and cache; errors are returned to the caller and never stored. The
+    /// result is still returned but not stored when the page was invalidated while
+    /// it was rendering.
+    async fn render_and_store(&self, page: &str) -> Result<RenderedPage> {
+        let generation = self.generations.lock().await.get(page).copied().unwrap_or(0);
+        let rendered = match self.renderer.render(page).await {
+            Ok(rendered) => rendered,
+            Err(e) => {
+                self.render_errors.fetch_add(1, Ordering::Relaxed);
+                return Err(e);
+            }
+        };
+
+        let entry = CachedPage {
+            page: page.to_string(),
+            rendered: rendered.clone(),
+            rendered_at: Utc::now(),
+            invalidated: false,
+        };
+        let generations = self.generations.lock().await;
+        if generations.get(page).copied().unwrap_or(0) != generation {
+            tracing::debug!("Dropping render of {}: invalidated while rendering", page);
+            return Ok(rendered);
+        }
+        if let Err(e) = self.backend.put(&entry).await {
+            tracing::warn!("Docs cache write failed for {}: {}", page, e);
+        }
+        drop(generations);
+        self.index.write().await.record(page, &rendered);
+        Ok(rendered)
+    }
+
+    fn spawn_revalidation(self: &Arc<Self>, page: String) {
+        let cache = Arc::clone(self);
+        tokio::spawn(async move {
+            // Only one revalidation per page at a time; concurrent requests keep getting the stale copy
+            if !cache.revalidating.lock().await.insert(page.clone()) {
+                return;
+            }
+            cache.revalidations.fetch_add(1, Ordering::Relaxed);
+            if let Err(e) = cache.render_and_store(&page).await {
+                tracing::warn!("Revalidating {} failed, keeping the cached version: {}", page, e);
+            }
+            cache.revalidating.lock().await.remove(&page);
+        });
+    }
+
+    /// Apply every rule registered for `trigger`. `changed_page` is the page whose
+    /// content changed and is required for `ContentUpdate`.
+    pub async fn invalidate(self: &Arc<Self>, trigger: InvalidationTrigger, changed_page: Option<&str>) -> Result<usize> {
+        let rules: Vec<InvalidationRule> = self
+            .config
+            .invalidation_rules
+            .iter()
+            .filter(|rule| std::mem::discriminant(&rule.trigger) == std::mem::discriminant(&trigger))
+            .cloned()
+            .collect();
+
+        let mut marked = 0;
+        for rule in rules {
+            let pages = self.pages_for(&trigger, &rule.scope, changed_page).await;
+            match rule.delay_seconds.filter(|delay| *delay > 0) {
+                Some(delay) => {
+                    let cache = Arc::clone(self);
+                    marked += pages.len();
+                    tokio::spawn(async move {
+                        tokio::time::sleep(std::time::Duration::from_secs(delay)).await;
+                        if let Err(e) = cache.mark_stale(&pages).await {
+                            tracing::warn!("Delayed docs cache invalidation failed: {}", e);
+                        }
+                    });
+                }
+                None => marked += self.mark_stale(&pages).await?,
+            }
+        }
+        Ok(marked)
+    }
+
+    async fn pages_for(&self, trigger: &InvalidationTrigger, scope: &InvalidationScope, changed_page: Option<&str>) -> Vec<String> {
+        let index = self.index.read().await;
+        match (trigger, changed_page) {
+            (InvalidationTrigger::ContentUpdate, Some(changed)) => {
+                // The rule's scope decides whether this change is relevant at all; the affected
+                // pages are always the changed page plus its referrers
+                if !index.matches(changed, scope) {
+                    return Vec::new();
+                }
+                let mut pages = vec![changed.to_string()];
+                if let Some(referrers) = index.referrers.get(changed) {
+                    let mut referrers: Vec<String> = referrers.iter().cloned().collect();
+                    referrers.sort();
+                    pages.extend(referrers);
+                }
+                pages
+            }
+            (InvalidationTrigger::ContentUpdate, None) => Vec::new(),
+            _ => {
+                let mut pages: Vec<String> = index.pages().filter(|page| index.matches(page, scope)).cloned().collect();
+                pages.sort();
+                pages
+            }
+        }
+    }
+
+    async fn mark_stale(&self, pages: &[String]) -> Result<usize> {
+        let mut marked = 0;
+        let mut generations = self.generations.lock().await;
+        for page in pages {
+            *generations.entry(page.clone()).or_default() += 1;
+            if let Some(mut entry) = self.backend.get(page).await? {
+                entry.invalidated = true;
+                self.backend.put(&entry).await?;
+                marked += 1;
+            }
+        }
+        Ok(marked)
+    }
+
+    /// Drop a page entirely, e.g. when it was deleted from the source
+    pub async fn evict(&self, page: &str) -> Result<()> {
+        let mut generations = self.generations.lock().await;
+        *generations.entry(page.to_string()).or_default() += 1;
+        self.backend.remove(page).await
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        let hits = self.hits.load(Ordering::Relaxed);
+        let stale_hits = self.stale_hits.load(Ordering::Relaxed);
+        let misses = self.misses.load(Ordering::Relaxed);
+        let total = hits + stale_hits + misses;
+        CacheStats {
+            hits,
+            stale_hits,
+            misses,
+            revalidations: self.revalidations.load(Ordering::Relaxed),
+            render_errors: self.render_errors.load(Ordering::Relaxed),
+            hit_rate: if total == 0 { 0.0 } else { (hits + stale_hits) as f64 / total as f64 },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicBool;
+    use tokio::sync::Notify;
+
+    /// Renders the current `source`; while `hold` is set each render parks after reading it
+    #[derive(Default)]
+    struct SourceRenderer {
+        source: std::sync::Mutex<String>,
+        hold: AtomicBool,
+        started: Notify,
+        release: Notify,
+    }
+
+    impl SourceRenderer {
+        fn set(&self, source: &str) {
+            *self.source.lock().unwrap() = source.to_string();
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl PageRenderer for SourceRenderer {
+        async fn render(&self, _page: &str) -> Result<RenderedPage> {
+            let source = self.source.lock().unwrap().clone();
+            if self.hold.load(Ordering::SeqCst) {
+                self.started.notify_one();
+                self.release.notified().await;
+            }
+            if source == "broken" {
+                return Err("render failed".into());
+            }
+            Ok(RenderedPage { html: source, references: Vec::new(), section: None, tags: Vec::new(), version: None })
+        }
+    }
+
+    fn cache(renderer: Arc<SourceRenderer>) -> Arc<RenderCache> {
+        let config = CachingConfiguration {
+            enabled: true,
+            strategy: CachingStrategy::Memory,
+            ttl_seconds: 3600,
+            cache_headers: HashMap::new(),
+            invalidation_rules: vec![InvalidationRule {
+                trigger: InvalidationTrigger::ManualTrigger,
+                scope: InvalidationScope::All,
+                delay_seconds: None,
+            }],
+        };
+        RenderCache::new(config, Arc::new(MemoryCacheBackend::default()), renderer)
+    }
+
+    /// Wait until `count` revalidations have started and none is still running
+    async fn settle(cache: &RenderCache, count: u64) {
+        for _ in 0..200 {
+            if cache.revalidations.load(Ordering::SeqCst) >= count && cache.revalidating.lock().await.is_empty() {
+                return;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+        }
+        panic!("revalidation did not finish");
+    }
+
+    #[tokio::test]
+    async fn test_stale_page_is_served_while_revalidating() {
+        let renderer = Arc::new(SourceRenderer::default());
+        let cache = cache(Arc::clone(&renderer));
+        renderer.set("v1");
+
+        let first = cache.get("guide/intro").await.unwrap();
+        assert_eq!(first.outcome, CacheOutcome::Miss);
+        assert_eq!(cache.get("guide/intro").await.unwrap().outcome, CacheOutcome::Fresh);
+
+        renderer.set("v2");
+        assert_eq!(cache.invalidate(InvalidationTrigger::ManualTrigger, None).await.unwrap(), 1);
+
+        let stale = cache.get("guide/intro").await.unwrap();
+        assert_eq!(stale.outcome, CacheOutcome::Stale);
+        assert_eq!(stale.page.html, "v1");
+        assert_eq!(stale.headers.get("X-Cache").map(String::as_str), Some("STALE"));
+
+        settle(&cache, 1).await;
+        let fresh = cache.get("guide/intro").await.unwrap();
+        assert_eq!(fresh.outcome, CacheOutcome::Fresh);
+        assert_eq!(fresh.page.html, "v2");
+    }
+
+    #[tokio::test]
+    async fn test_failed_revalidation_keeps_cached_page() {
+        let renderer = Arc::new(SourceRenderer::default());
+        let cache = cache(Arc::clone(&renderer));
+        renderer.set("v1");
+        cache.get("guide/intro").await.unwrap();
+
+        renderer.set("broken");
+        cache.invalidate(InvalidationTrigger::ManualTrigger, None).await.unwrap();
+        assert_eq!(cache.get("guide/intro").await.unwrap().page.html, "v1");
+        settle(&cache, 1).await;
+
+        let response = cache.get("guide/intro").await.unwrap();
+        assert_eq!(response.outcome, CacheOutcome::Stale);
+        assert_eq!(response.page.html, "v1");
+        assert!(cache.stats().render_errors >= 1);
+    }
+
+    #[tokio::test]
+    async fn test_invalidation_during_revalidation_drops_the_result() {
+        let renderer = Arc::new(SourceRenderer::default());
+        let cache = cache(Arc::clone(&renderer));
+        renderer.set("v1");
+        cache.get("guide/intro").await.unwrap();
+
+        // The revalidation reads v2 and parks before storing it
+        renderer.set("v2");
+        cache.invalidate(InvalidationTrigger::ManualTrigger, None).await.unwrap();
+        renderer.hold.store(true, Ordering::SeqCst);
+        assert_eq!(cache.get("guide/intro").await.unwrap().page.html, "v1");
+        renderer.started.notified().await;
+
+        // The source changes again while it is in flight
+        renderer.set("v3");
+        cache.invalidate(InvalidationTrigger::ManualTrigger, None).await.unwrap();
+        renderer.hold.store(false, Ordering::SeqCst);
+        renderer.release.notify_one();
+        settle(&cache, 1).await;
+
+        // The v2 render finished after the second invalidation, so the entry is still stale
+        let response = cache.get("guide/intro").await.unwrap();
+        assert_eq!(response.outcome, CacheOutcome::Stale);
+        assert_eq!(response.page.html, "v1");
+
+        settle(&cache, 2).await;
+        let fresh = cache.get("guide/intro").await.unwrap();
+        assert_eq!(fresh.outcome, CacheOutcome::Fresh);
+        assert_eq!(fresh.page.html, "v3");
+    }
+}
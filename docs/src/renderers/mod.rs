@@ -0,0 +1,5 @@
+//! Documentation renderers
+
+pub mod cache;
+
+pub use cache::*;
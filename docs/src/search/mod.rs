@@ -0,0 +1,5 @@
+//! Documentation search
+
+pub mod vector;
+
+pub use vector::*;
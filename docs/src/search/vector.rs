@@ -0,0 +1,214 @@
+//! In-memory vector index for semantic documentation search
+//!
+//! Each `SearchResult` is stored with the embedding of its content in a
+//! [`BruteForceVectorStore`] and queries are ranked by cosine similarity, so
+//! a page about "signing in" can match a query for "login" without sharing a
+//! word with it. The index is saved to and loaded from a single file.
+//!
+//! [`fuse_scores`] combines vector hits with keyword hits; see
+//! [`DocumentationManager::search_content_hybrid`](crate::DocumentationManager::search_content_hybrid).
+
+use crate::{Result, SearchResult};
+use aion_ai_engine::vector_store::{BruteForceVectorStore, VectorRecord, VectorStore};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+
+/// How keyword and vector scores are combined for one query
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HybridSearchConfiguration {
+    /// Share of the fused score taken from vector similarity, from 0.0
+    /// (keyword only) to 1.0 (vector only)
+    pub vector_weight: f64,
+    /// Vector hits considered per query
+    pub vector_candidates: usize,
+}
+
+impl Default for HybridSearchConfiguration {
+    fn default() -> Self {
+        Self { vector_weight: 0.5, vector_candidates: 50 }
+    }
+}
+
+/// Embeddings of documentation pages, one per `SearchResult::url`. The page
+/// is kept as JSON in the record's content and the URL is the record id.
+#[derive(Default)]
+pub struct VectorIndex {
+    store: BruteForceVectorStore,
+}
+
+impl VectorIndex {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub async fn len(&self) -> Result<usize> {
+        Ok(self.store.len().await?)
+    }
+
+    pub async fn is_empty(&self) -> Result<bool> {
+        Ok(self.store.is_empty().await?)
+    }
+
+    /// Index `result` under `embedding`, replacing any entry with the same URL.
+    /// Every embedding must have the same number of dimensions.
+    pub async fn add(&self, result: SearchResult, embedding: Vec<f32>) -> Result<()> {
+        if !has_direction(&embedding) {
+            return Err(format!("Embedding for {} has no direction (zero or non-finite)", result.url).into());
+        }
+        let record = VectorRecord::new(result.url.clone(), embedding).with_content(serde_json::to_string(&result)?);
+        Ok(self.store.upsert(vec![record]).await?)
+    }
+
+    /// Remove the entry for `url`; returns whether there was one
+    pub async fn remove(&self, url: &str) -> Result<bool> {
+        Ok(self.store.delete(&[url.to_string()]).await? > 0)
+    }
+
+    /// The `top_k` entries most similar to `query_vector`, best first, with
+    /// `score` set to their cosine similarity
+    pub async fn search(&self, query_vector: &[f32], top_k: usize) -> Result<Vec<SearchResult>> {
+        if top_k == 0 || !has_direction(query_vector) {
+            return Ok(Vec::new());
+        }
+        self.store
+            .query(query_vector, top_k, None)
+            .await?
+            .into_iter()
+            .map(|hit| -> Result<SearchResult> {
+                let result: SearchResult = serde_json::from_str(&hit.content)?;
+                Ok(SearchResult { score: hit.score as f64, ..result })
+            })
+            .collect()
+    }
+
+    /// Write the index to `path`
+    pub async fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        Ok(self.store.save(path).await?)
+    }
+
+    /// Read an index written by [`save`](Self::save)
+    pub async fn load(path: impl AsRef<Path>) -> Result<Self> {
+        Ok(Self { store: BruteForceVectorStore::load(path).await? })
+    }
+}
+
+/// Merge keyword and vector hits for the same query into one ranking.
+///
+/// Keyword scores are scaled so the best keyword hit scores 1.0, vector
+/// scores are cosine similarities clamped to `[0, 1]`, and each page scores
+/// `(1 - vector_weight) * keyword + vector_weight * vector`, with 0 for a
+/// list it is missing from. Pages are matched by URL; the keyword hit's
+/// excerpt and highlights are kept when a page is in both lists.
+pub fn fuse_scores(keyword: Vec<SearchResult>, vector: Vec<SearchResult>, vector_weight: f64) -> Vec<SearchResult> {
+    let vector_weight = vector_weight.clamp(0.0, 1.0);
+    let max_keyword = keyword.iter().map(|result| result.score).fold(0.0, f64::max);
+
+    let mut fused: Vec<SearchResult> = Vec::new();
+    let mut positions: HashMap<String, usize> = HashMap::new();
+    for result in keyword {
+        let relevance = if max_keyword > 0.0 { result.score / max_keyword } else { 0.0 };
+        positions.insert(result.url.clone(), fused.len());
+        fused.push(SearchResult { score: (1.0 - vector_weight) * relevance, ..result });
+    }
+    for result in vector {
+        let weighted = vector_weight * result.score.clamp(0.0, 1.0);
+        match positions.get(&result.url) {
+            Some(&position) => fused[position].score += weighted,
+            None => {
+                positions.insert(result.url.clone(), fused.len());
+                fused.push(SearchResult { score: weighted, ..result });
+            }
+        }
+    }
+
+    // Stable, so ties keep keyword order
+    fused.sort_by(|a, b| b.score.total_cmp(&a.score));
+    fused
+}
+
+fn has_direction(vector: &[f32]) -> bool {
+    vector.iter().all(|value| value.is_finite()) && vector.iter().any(|value| *value != 0.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    fn page(url: &str, title: &str, score: f64) -> SearchResult {
+        SearchResult {
+            title: title.to_string(),
+            url: url.to_string(),
+            excerpt: String::new(),
+            score,
+            content_type: "guide".to_string(),
+            last_updated: Utc::now(),
+            highlights: Vec::new(),
+        }
+    }
+
+    /// Embedding axes: [authentication, billing, newsletters]
+    async fn index() -> VectorIndex {
+        let index = VectorIndex::new();
+        index.add(page("/guides/auth", "Logging in with SSO", 0.0), vec![0.95, 0.05, 0.1]).await.unwrap();
+        index.add(page("/guides/billing", "Invoices and payment methods", 0.0), vec![0.05, 0.9, 0.1]).await.unwrap();
+        index.add(page("/blog/newsletter", "Sign up for the newsletter", 0.0), vec![0.05, 0.1, 0.95]).await.unwrap();
+        index
+    }
+
+    #[tokio::test]
+    async fn semantic_match_outranks_keyword_only_match() {
+        // "how do I sign in": only the newsletter page contains "sign", but
+        // the query embeds close to the authentication page
+        let keyword = vec![page("/blog/newsletter", "Sign up for the newsletter", 3.2)];
+        let vector = index().await.search(&[0.9, 0.1, 0.2], 3).await.unwrap();
+        assert_eq!(vector[0].url, "/guides/auth");
+        assert_eq!(vector[0].title, "Logging in with SSO");
+
+        let fused = fuse_scores(keyword.clone(), vector.clone(), 0.6);
+        assert_eq!(fused[0].url, "/guides/auth");
+        assert_eq!(fused[1].url, "/blog/newsletter");
+        assert_eq!(fused.len(), 3);
+
+        // With vectors weighted out, keyword ranking wins again
+        let keyword_only = fuse_scores(keyword, vector, 0.0);
+        assert_eq!(keyword_only[0].url, "/blog/newsletter");
+    }
+
+    #[tokio::test]
+    async fn add_replaces_pages_and_rejects_mismatched_dimensions() {
+        let index = index().await;
+        index.add(page("/guides/auth", "Signing in", 0.0), vec![0.0, 0.0, 1.0]).await.unwrap();
+        assert_eq!(index.len().await.unwrap(), 3);
+        let hits = index.search(&[0.0, 0.0, 1.0], 1).await.unwrap();
+        assert_eq!(hits[0].title, "Signing in");
+        assert!((hits[0].score - 1.0).abs() < 1e-6);
+
+        assert!(index.add(page("/other", "Other", 0.0), vec![1.0, 0.0]).await.is_err());
+        assert!(index.search(&[1.0, 0.0], 1).await.is_err());
+        assert!(index.add(page("/zero", "Zero", 0.0), vec![0.0; 3]).await.is_err());
+        assert!(index.remove("/guides/auth").await.unwrap());
+        assert_eq!(index.len().await.unwrap(), 2);
+    }
+
+    #[tokio::test]
+    async fn index_survives_save_and_load() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("search").join("vectors.bin");
+        let index = index().await;
+        index.save(&path).await.unwrap();
+
+        let loaded = VectorIndex::load(&path).await.unwrap();
+        assert_eq!(loaded.len().await.unwrap(), index.len().await.unwrap());
+        let urls = |hits: Vec<SearchResult>| -> Vec<String> { hits.into_iter().map(|hit| hit.url).collect() };
+        assert_eq!(
+            urls(loaded.search(&[0.1, 0.9, 0.1], 3).await.unwrap()),
+            urls(index.search(&[0.1, 0.9, 0.1], 3).await.unwrap())
+        );
+    }
+}